@@ -67,6 +67,12 @@ impl TaskManager {
     }
 
     pub async fn restore(&self) -> Result<(), TaskError> {
+        self.restore_with_reason("Interrupted by restart").await
+    }
+
+    /// Like [`restore`](Self::restore), recording `reason` on tasks that were
+    /// still pending or running.
+    pub async fn restore_with_reason(&self, reason: &str) -> Result<(), TaskError> {
         let Some(state_file) = &self.state_file else {
             return Ok(());
         };
@@ -82,7 +88,7 @@ impl TaskManager {
         for task in &mut task_infos {
             if task.status == TaskStatus::Running || task.status == TaskStatus::Pending {
                 task.status = TaskStatus::Failed;
-                task.error = Some("Interrupted by restart".to_string());
+                task.error = Some(reason.to_string());
                 task.updated_at = now;
             }
         }
@@ -241,6 +247,11 @@ impl TaskManager {
         let _ = self.persist_state().await;
     }
 
+    /// Write the current task list to the state file now.
+    pub async fn flush(&self) -> Result<(), TaskError> {
+        self.persist_state().await
+    }
+
     async fn persist_state(&self) -> Result<(), TaskError> {
         let Some(state_file) = &self.state_file else {
             return Ok(());
@@ -4,6 +4,23 @@ fn main() {
     let nvidia_config = Config {
         provider: LLMProvider::Nvidia,
         model: "test".to_string(),
+        sandbox: Default::default(),
+        backup: Default::default(),
+        plugins: Default::default(),
+        response: Default::default(),
+        verification: Default::default(),
+        analytics: Default::default(),
+        audit: Default::default(),
+        annealing: Default::default(),
+        watchdog: Default::default(),
+        update: Default::default(),
+        context: Default::default(),
+        pricing: Default::default(),
+        concurrency: Default::default(),
+        prefetch: Default::default(),
+        retention: Default::default(),
+        ocr: Default::default(),
+        thermal: Default::default(),
     };
 
     let local_config = Config {
@@ -11,6 +28,23 @@ fn main() {
             base_url: "http://localhost:8080".to_string(),
         },
         model: "test".to_string(),
+        sandbox: Default::default(),
+        backup: Default::default(),
+        plugins: Default::default(),
+        response: Default::default(),
+        verification: Default::default(),
+        analytics: Default::default(),
+        audit: Default::default(),
+        annealing: Default::default(),
+        watchdog: Default::default(),
+        update: Default::default(),
+        context: Default::default(),
+        pricing: Default::default(),
+        concurrency: Default::default(),
+        prefetch: Default::default(),
+        retention: Default::default(),
+        ocr: Default::default(),
+        thermal: Default::default(),
     };
 
     println!("Nvidia YAML:");
@@ -0,0 +1,656 @@
+//! Per-run tool action logs shown by the `actions` command.
+//!
+//! Every foreground and side run appends its tool calls to
+//! `run-<id>.jsonl` under `./data/actions`, so the action feed survives a
+//! restart and can be followed from a second terminal with
+//! `hypr-claw actions --follow`, or every run as it happens with
+//! `hypr-claw observe`. Only the most recent runs are kept.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const DEFAULT_KEEP_RUNS: usize = 100;
+
+/// One line of a run's log.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ActionEvent {
+    Start {
+        run_id: u64,
+        request: String,
+        at: i64,
+        /// Process writing the run, to tell a run in progress from one
+        /// whose instance died.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pid: Option<u32>,
+    },
+    Action {
+        index: u64,
+        status: String,
+        tool: String,
+        detail: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        elapsed_ms: Option<u64>,
+        at: i64,
+    },
+    /// Tools offered to the model on one iteration and the one it picked.
+    Decision {
+        iteration: u64,
+        offered: Vec<String>,
+        /// Absent when the model answered instead of calling a tool.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chosen: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rationale: Option<String>,
+        at: i64,
+    },
+    /// The answer that ended a successful run.
+    Reply { text: String, at: i64 },
+    /// What the run changed, written just before it ends.
+    Changes {
+        manifest: crate::side_effects::Manifest,
+        at: i64,
+    },
+    End {
+        outcome: String,
+        duration_ms: u64,
+        at: i64,
+    },
+}
+
+pub struct ActionLog {
+    dir: PathBuf,
+    keep_runs: usize,
+    /// Run currently being recorded for each session.
+    active: Mutex<HashMap<String, u64>>,
+    /// Highest run id handed out by [`ActionLog::claim_run_id`].
+    claimed: Mutex<u64>,
+}
+
+impl ActionLog {
+    pub fn new(dir: impl AsRef<Path>, keep_runs: usize) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            keep_runs,
+            active: Mutex::new(HashMap::new()),
+            claimed: Mutex::new(0),
+        }
+    }
+
+    /// Id for a new run: `wanted`, unless a logged or already claimed run
+    /// has it, in which case the next free one. Side runs and the foreground
+    /// run claim from here so they never share a log.
+    pub fn claim_run_id(&self, wanted: u64) -> u64 {
+        let logged = self
+            .runs()
+            .ok()
+            .and_then(|runs| runs.last().copied())
+            .unwrap_or(0);
+        let Ok(mut claimed) = self.claimed.lock() else {
+            return wanted.max(logged + 1);
+        };
+        *claimed = wanted.max(logged + 1).max(*claimed + 1);
+        *claimed
+    }
+
+    pub fn run_path(&self, run_id: u64) -> PathBuf {
+        self.dir.join(format!("run-{run_id:06}.jsonl"))
+    }
+
+    /// Start recording `session_key`'s actions under `run_id`, dropping the
+    /// oldest logs of finished runs beyond the keep limit.
+    pub fn begin_run(&self, session_key: &str, run_id: u64, request: &str) -> io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        File::create(self.run_path(run_id))?;
+        if let Ok(mut active) = self.active.lock() {
+            active.insert(session_key.to_string(), run_id);
+        }
+        crate::crash::begin_run(session_key, run_id, request);
+        self.append(
+            run_id,
+            &ActionEvent::Start {
+                run_id,
+                request: request.to_string(),
+                at: chrono::Utc::now().timestamp(),
+                pid: Some(std::process::id()),
+            },
+        )?;
+        self.prune()
+    }
+
+    /// Append a tool action to the session's current run, if one is open.
+    pub fn record(
+        &self,
+        session_key: &str,
+        index: u64,
+        status: &str,
+        tool: &str,
+        detail: &str,
+        elapsed_ms: Option<u64>,
+    ) -> io::Result<()> {
+        let Some(run_id) = self.active_run(session_key) else {
+            return Ok(());
+        };
+        self.append(
+            run_id,
+            &ActionEvent::Action {
+                index,
+                status: status.to_string(),
+                tool: tool.to_string(),
+                detail: detail.to_string(),
+                elapsed_ms,
+                at: chrono::Utc::now().timestamp(),
+            },
+        )
+    }
+
+    /// Append the model's tool choice to the session's current run, if one
+    /// is open.
+    pub fn record_decision(&self, decision: &hypr_claw_runtime::ToolDecision) -> io::Result<()> {
+        let Some(run_id) = self.active_run(&decision.session_key) else {
+            return Ok(());
+        };
+        self.append(
+            run_id,
+            &ActionEvent::Decision {
+                iteration: decision.iteration as u64,
+                offered: decision.offered.clone(),
+                chosen: decision.chosen.clone(),
+                rationale: decision.rationale.clone(),
+                at: chrono::Utc::now().timestamp(),
+            },
+        )
+    }
+
+    /// Append the run's final answer to the session's current run, if one
+    /// is open.
+    pub fn record_reply(&self, session_key: &str, text: &str) -> io::Result<()> {
+        let Some(run_id) = self.active_run(session_key) else {
+            return Ok(());
+        };
+        self.append(
+            run_id,
+            &ActionEvent::Reply {
+                text: text.to_string(),
+                at: chrono::Utc::now().timestamp(),
+            },
+        )
+    }
+
+    /// Append what the session's current run changed, if one is open.
+    pub fn record_changes(
+        &self,
+        session_key: &str,
+        manifest: &crate::side_effects::Manifest,
+    ) -> io::Result<()> {
+        let Some(run_id) = self.active_run(session_key) else {
+            return Ok(());
+        };
+        self.append(
+            run_id,
+            &ActionEvent::Changes {
+                manifest: manifest.clone(),
+                at: chrono::Utc::now().timestamp(),
+            },
+        )
+    }
+
+    pub fn finish_run(&self, session_key: &str, outcome: &str, duration_ms: u64) -> io::Result<()> {
+        let run_id = self
+            .active
+            .lock()
+            .ok()
+            .and_then(|mut active| active.remove(session_key));
+        crate::crash::end_run(session_key);
+        let Some(run_id) = run_id else {
+            return Ok(());
+        };
+        self.append(
+            run_id,
+            &ActionEvent::End {
+                outcome: outcome.to_string(),
+                duration_ms,
+                at: chrono::Utc::now().timestamp(),
+            },
+        )
+    }
+
+    fn active_run(&self, session_key: &str) -> Option<u64> {
+        self.active
+            .lock()
+            .ok()
+            .and_then(|active| active.get(session_key).copied())
+    }
+
+    fn append(&self, run_id: u64, event: &ActionEvent) -> io::Result<()> {
+        let line = serde_json::to_string(event).map_err(io::Error::other)?;
+        crate::crash::record_event(&line);
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(self.run_path(run_id))?;
+        writeln!(file, "{line}")
+    }
+
+    /// Logged run ids, oldest first.
+    pub fn runs(&self) -> io::Result<Vec<u64>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut runs: Vec<u64> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                name.strip_prefix("run-")?
+                    .strip_suffix(".jsonl")?
+                    .parse()
+                    .ok()
+            })
+            .collect();
+        runs.sort_unstable();
+        Ok(runs)
+    }
+
+    /// Drops the oldest logs beyond the keep limit, except runs still open.
+    fn prune(&self) -> io::Result<()> {
+        let runs = self.runs()?;
+        let open: Vec<u64> = self
+            .active
+            .lock()
+            .map(|active| active.values().copied().collect())
+            .unwrap_or_default();
+        for run_id in &runs[..runs.len().saturating_sub(self.keep_runs)] {
+            if !open.contains(run_id) {
+                std::fs::remove_file(self.run_path(*run_id))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn load(&self, run_id: u64) -> io::Result<Vec<ActionEvent>> {
+        let mut reader = RunReader::open(&self.run_path(run_id))?;
+        reader.read_new()
+    }
+}
+
+/// Incremental reader for one run's log, used to follow it while it grows.
+pub struct RunReader {
+    file: File,
+    offset: u64,
+}
+
+impl RunReader {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            file: File::open(path)?,
+            offset: 0,
+        })
+    }
+
+    /// Events appended since the last call. A half-written last line is left
+    /// for the next call.
+    pub fn read_new(&mut self) -> io::Result<Vec<ActionEvent>> {
+        self.file.seek(SeekFrom::Start(self.offset))?;
+        let mut reader = BufReader::new(&self.file);
+        let mut events = Vec::new();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 || !line.ends_with('\n') {
+                break;
+            }
+            self.offset += read as u64;
+            if let Ok(event) = serde_json::from_str(line.trim_end()) {
+                events.push(event);
+            }
+        }
+        Ok(events)
+    }
+}
+
+/// Follows every open run of a log at once: the latest run and those still
+/// in progress when it starts, then each run begun later, until they end.
+pub struct RunFollower {
+    /// Newest run picked up so far.
+    seen: Option<u64>,
+    runs: BTreeMap<u64, FollowedRun>,
+}
+
+struct FollowedRun {
+    reader: RunReader,
+    pid: Option<u32>,
+}
+
+impl RunFollower {
+    pub fn new(log: &ActionLog) -> io::Result<Self> {
+        let runs = log.runs()?;
+        let mut follower = Self {
+            seen: runs.last().copied(),
+            runs: BTreeMap::new(),
+        };
+        for (i, run_id) in runs.iter().enumerate() {
+            let events = log.load(*run_id).unwrap_or_default();
+            let in_progress = !events.iter().any(|e| matches!(e, ActionEvent::End { .. }))
+                && start_pid(&events).is_some_and(writer_alive);
+            if in_progress || i + 1 == runs.len() {
+                follower.follow(log, *run_id);
+            }
+        }
+        Ok(follower)
+    }
+
+    /// Runs being followed.
+    pub fn following(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Events appended since the last poll, with the run each belongs to.
+    /// A run is dropped after its End, or once its writer is gone.
+    pub fn poll(&mut self, log: &ActionLog) -> io::Result<Vec<(u64, ActionEvent)>> {
+        let runs = log.runs()?;
+        for run_id in &runs {
+            if self.seen.is_none_or(|seen| *run_id > seen) {
+                self.follow(log, *run_id);
+                self.seen = Some(*run_id);
+            }
+        }
+        let newest = self.seen;
+        let mut polled = Vec::new();
+        let mut done = Vec::new();
+        for (run_id, run) in self.runs.iter_mut() {
+            let events = run.reader.read_new()?;
+            run.pid = run.pid.or(start_pid(&events));
+            let ended = events.iter().any(|e| matches!(e, ActionEvent::End { .. }));
+            let abandoned = events.is_empty()
+                && match run.pid {
+                    Some(pid) => !writer_alive(pid),
+                    // Logged before writers were recorded: gone once a newer
+                    // run started.
+                    None => newest.is_some_and(|newest| newest > *run_id),
+                };
+            if ended || abandoned {
+                done.push(*run_id);
+            }
+            polled.extend(events.into_iter().map(|event| (*run_id, event)));
+        }
+        for run_id in done {
+            self.runs.remove(&run_id);
+        }
+        Ok(polled)
+    }
+
+    fn follow(&mut self, log: &ActionLog, run_id: u64) {
+        // A log pruned in the meantime is simply not followed.
+        if let Ok(reader) = RunReader::open(&log.run_path(run_id)) {
+            self.runs.insert(run_id, FollowedRun { reader, pid: None });
+        }
+    }
+}
+
+fn start_pid(events: &[ActionEvent]) -> Option<u32> {
+    events.iter().find_map(|event| match event {
+        ActionEvent::Start { pid, .. } => *pid,
+        _ => None,
+    })
+}
+
+fn writer_alive(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+/// Display line for an event.
+pub fn render_event(event: &ActionEvent) -> String {
+    match event {
+        ActionEvent::Start {
+            run_id,
+            request,
+            at,
+            ..
+        } => format!("run {run_id} started {}: {request}", format_time(*at)),
+        ActionEvent::Action {
+            index,
+            status,
+            tool,
+            detail,
+            elapsed_ms,
+            ..
+        } => format!(
+            "{:>3}. {:<6} {:<30} {:>7} {}",
+            index,
+            status,
+            tool,
+            elapsed_ms.map(|ms| format!("{ms}ms")).unwrap_or_default(),
+            detail
+        ),
+        ActionEvent::Decision {
+            iteration,
+            offered,
+            chosen,
+            rationale,
+            ..
+        } => {
+            let choice = match chosen {
+                Some(tool) if offered.contains(tool) => format!("chose {tool}"),
+                Some(tool) => format!("chose {tool} (not offered!)"),
+                None => "answered".to_string(),
+            };
+            let mut line = format!(
+                "     step {iteration}: {choice} of {} offered",
+                offered.len()
+            );
+            if let Some(rationale) = rationale {
+                line.push_str(&format!(" — {rationale}"));
+            }
+            line
+        }
+        ActionEvent::Reply { text, .. } => format!("reply: {}", text.trim()),
+        ActionEvent::Changes { manifest, .. } => {
+            std::iter::once(format!("changes: {}", manifest.summary()))
+                .chain(manifest.render())
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        ActionEvent::End {
+            outcome,
+            duration_ms,
+            at,
+        } => format!(
+            "run finished {} after {duration_ms}ms: {outcome}",
+            format_time(*at)
+        ),
+    }
+}
+
+fn format_time(at: i64) -> String {
+    chrono::DateTime::from_timestamp(at, 0)
+        .map(|at| {
+            at.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|| at.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_log(name: &str, keep_runs: usize) -> ActionLog {
+        let dir = std::env::temp_dir().join(format!("hypr-claw-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        ActionLog::new(dir, keep_runs)
+    }
+
+    #[test]
+    fn runs_are_recorded_per_session_and_pruned() {
+        let log = temp_log("actions", 2);
+        log.begin_run("user:agent", 1, "open firefox").unwrap();
+        log.record(
+            "user:agent",
+            1,
+            "tool",
+            "desktop.launch_app",
+            "input={}",
+            None,
+        )
+        .unwrap();
+        log.record("other", 1, "tool", "fs.read", "ignored", None)
+            .unwrap();
+        log.record(
+            "user:agent",
+            1,
+            "ok",
+            "desktop.launch_app",
+            "done",
+            Some(40),
+        )
+        .unwrap();
+        log.finish_run("user:agent", "STOP_NONE", 55).unwrap();
+        // Nothing is open any more, so this is dropped.
+        log.record("user:agent", 2, "ok", "fs.read", "late", None)
+            .unwrap();
+
+        let events = log.load(1).unwrap();
+        assert_eq!(events.len(), 4);
+        assert!(
+            matches!(&events[0], ActionEvent::Start { request, .. } if request == "open firefox")
+        );
+        assert!(matches!(
+            events[2],
+            ActionEvent::Action {
+                elapsed_ms: Some(40),
+                ..
+            }
+        ));
+        assert!(render_event(&events[2]).contains("desktop.launch_app"));
+        assert!(render_event(&events[3]).ends_with("after 55ms: STOP_NONE"));
+
+        log.begin_run("user:agent", 2, "b").unwrap();
+        log.begin_run("user:agent", 3, "c").unwrap();
+        assert_eq!(log.runs().unwrap(), vec![2, 3]);
+    }
+
+    #[test]
+    fn decisions_are_logged_with_the_offered_tools() {
+        let log = temp_log("actions-decisions", 5);
+        let mut decision = hypr_claw_runtime::ToolDecision {
+            session_key: "s".to_string(),
+            iteration: 1,
+            offered: vec!["fs.read".to_string(), "fs.list".to_string()],
+            chosen: Some("fs.list".to_string()),
+            rationale: Some("see what is there first".to_string()),
+        };
+        // Not recorded: no run is open yet.
+        log.record_decision(&decision).unwrap();
+        log.begin_run("s", 1, "tidy up").unwrap();
+        log.record_decision(&decision).unwrap();
+        decision.iteration = 2;
+        decision.chosen = Some("fs.delete".to_string());
+        decision.rationale = None;
+        log.record_decision(&decision).unwrap();
+
+        let events = log.load(1).unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(
+            render_event(&events[1]).trim_start(),
+            "step 1: chose fs.list of 2 offered — see what is there first"
+        );
+        assert!(render_event(&events[2]).ends_with("chose fs.delete (not offered!) of 2 offered"));
+    }
+
+    #[test]
+    fn observers_move_from_run_to_run_and_see_the_reply() {
+        let log = temp_log("actions-observe", 5);
+        let mut follower = RunFollower::new(&log).unwrap();
+        assert!(follower.poll(&log).unwrap().is_empty());
+        log.begin_run("s", 3, "what time is it").unwrap();
+        log.record_reply("s", "It is 14:05.\n").unwrap();
+        let mut manifest = crate::side_effects::Manifest::default();
+        manifest.record(
+            "desktop.open_url",
+            &serde_json::json!({"url": "https://time.is"}),
+            &serde_json::json!({"opened": "https://time.is"}),
+        );
+        log.record_changes("s", &manifest).unwrap();
+        log.finish_run("s", "STOP_NONE", 12).unwrap();
+        // Nothing is open any more, so this is dropped.
+        log.record_reply("s", "late").unwrap();
+        log.begin_run("s", 4, "and the date").unwrap();
+
+        let polled = follower.poll(&log).unwrap();
+        let runs: Vec<u64> = polled.iter().map(|(run_id, _)| *run_id).collect();
+        assert_eq!(runs, vec![3, 3, 3, 3, 4]);
+        assert_eq!(follower.following(), 1);
+        let events = log.load(3).unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(render_event(&events[1]), "reply: It is 14:05.");
+        assert_eq!(
+            render_event(&events[2]),
+            "changes: urls opened: 1\n  URLs opened   : https://time.is"
+        );
+    }
+
+    #[test]
+    fn observers_follow_parallel_runs_until_each_ends() {
+        let log = temp_log("actions-parallel", 10);
+        let foreground = log.claim_run_id(1);
+        log.begin_run("main", foreground, "long task").unwrap();
+        let mut follower = RunFollower::new(&log).unwrap();
+        assert_eq!(follower.poll(&log).unwrap().len(), 1);
+
+        // A side run starting must not drop the quiet foreground run.
+        let side = log.claim_run_id(0);
+        assert_eq!(side, foreground + 1);
+        log.begin_run("thread", side, "quick one").unwrap();
+        assert_eq!(follower.poll(&log).unwrap().len(), 1);
+        assert!(follower.poll(&log).unwrap().is_empty());
+        assert_eq!(follower.following(), 2);
+
+        log.record("main", 1, "ok", "fs.list", "", None).unwrap();
+        log.finish_run("thread", "STOP_NONE", 5).unwrap();
+        let polled = follower.poll(&log).unwrap();
+        assert_eq!(polled.len(), 2);
+        assert_eq!(follower.following(), 1);
+        assert!(polled.iter().any(|(run_id, _)| *run_id == foreground));
+
+        // The foreground asks for the next id it knows of; it is taken.
+        assert_eq!(log.claim_run_id(foreground + 1), side + 1);
+
+        // A run whose writer died is dropped; one never started by this
+        // process is not picked up as in progress.
+        let dead = side + 2;
+        std::fs::write(
+            log.run_path(dead),
+            "{\"kind\":\"start\",\"run_id\":9,\"request\":\"x\",\"at\":0,\"pid\":4294967295}\n",
+        )
+        .unwrap();
+        assert_eq!(follower.poll(&log).unwrap().len(), 1);
+        assert!(follower.poll(&log).unwrap().is_empty());
+        assert_eq!(follower.following(), 1);
+    }
+
+    #[test]
+    fn reader_follows_appended_lines() {
+        let log = temp_log("actions-follow", 5);
+        log.begin_run("s", 7, "watch").unwrap();
+        let mut reader = RunReader::open(&log.run_path(7)).unwrap();
+        assert_eq!(reader.read_new().unwrap().len(), 1);
+        assert!(reader.read_new().unwrap().is_empty());
+
+        log.record("s", 1, "tool", "fs.list", "", None).unwrap();
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(log.run_path(7))
+            .unwrap();
+        write!(file, "{{\"kind\":\"end\"").unwrap();
+        let new = reader.read_new().unwrap();
+        assert_eq!(new.len(), 1);
+        assert!(matches!(new[0], ActionEvent::Action { .. }));
+    }
+}
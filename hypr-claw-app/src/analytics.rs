@@ -0,0 +1,430 @@
+//! Local usage analytics shown by the `stats` command.
+//!
+//! Nothing here is sent anywhere: counts live in one JSON file under
+//! `./data/analytics` and are only read back by the dashboard. Recording is
+//! off until the user opts in with `stats on`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Distinct error messages kept per tool; the rest are counted as "other".
+const MAX_ERRORS_PER_TOOL: usize = 8;
+const ERROR_KEY_CHARS: usize = 80;
+
+/// Commands counted under their own name; any other input is a prompt.
+const KNOWN_COMMANDS: &[&str] = &[
+    "help",
+    "status",
+    "scan",
+    "capabilities",
+    "clear",
+    "interrupt",
+    "models",
+    "tasks",
+    "queue",
+    "reminder",
+    "contacts",
+    "knowledge",
+    "profile",
+    "view",
+    "citations",
+    "preset",
+    "stats",
+    "thread",
+    "threads",
+    "history",
+    "explain",
+    "audit",
+    "actions",
+    "config",
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ToolUsage {
+    pub calls: u64,
+    pub failures: u64,
+    /// Failure count per (shortened) error message.
+    #[serde(default)]
+    pub errors: BTreeMap<String, u64>,
+}
+
+impl ToolUsage {
+    pub fn failure_rate(&self) -> f64 {
+        if self.calls == 0 {
+            return 0.0;
+        }
+        self.failures as f64 / self.calls as f64
+    }
+
+    /// The error seen most often, ties broken by name.
+    pub fn top_error(&self) -> Option<(&str, u64)> {
+        self.errors
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+            .map(|(error, count)| (error.as_str(), *count))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UsageStats {
+    #[serde(default)]
+    pub since: Option<i64>,
+    #[serde(default)]
+    pub commands: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub tools: BTreeMap<String, ToolUsage>,
+    #[serde(default)]
+    pub runs: u64,
+    #[serde(default)]
+    pub failed_runs: u64,
+    /// Failed runs per stop code, e.g. `STOP_TOOL_FAILURE`.
+    #[serde(default)]
+    pub stop_codes: BTreeMap<String, u64>,
+    /// Removed commands still typed, by registry name.
+    #[serde(default)]
+    pub removed_commands: BTreeMap<String, u64>,
+}
+
+/// Name a REPL input is counted under: the command and its subcommand
+/// (`queue add`, `capabilities install`), or `prompt` for agent requests.
+/// Arguments are never recorded.
+pub fn command_name(input: &str) -> String {
+    let mut words = input.split_whitespace();
+    let Some(first) = words.next().map(|w| w.trim_start_matches('/')) else {
+        return "prompt".to_string();
+    };
+    if first == "exit" || first == "quit" {
+        return "exit".to_string();
+    }
+    if !KNOWN_COMMANDS.contains(&first) {
+        return "prompt".to_string();
+    }
+    match words.next() {
+        Some(sub) if sub.len() <= 12 && sub.chars().all(|c| c.is_ascii_lowercase()) => {
+            format!("{first} {sub}")
+        }
+        _ => first.to_string(),
+    }
+}
+
+fn error_key(error: &str) -> String {
+    let flat = error.split_whitespace().collect::<Vec<_>>().join(" ");
+    let flat = flat.split(" | fallback_tools:").next().unwrap_or_default();
+    flat.chars().take(ERROR_KEY_CHARS).collect()
+}
+
+impl UsageStats {
+    pub fn record_command(&mut self, input: &str, now: i64) {
+        self.since.get_or_insert(now);
+        *self.commands.entry(command_name(input)).or_insert(0) += 1;
+    }
+
+    pub fn record_tool(&mut self, tool: &str, error: Option<&str>, now: i64) {
+        self.since.get_or_insert(now);
+        let usage = self.tools.entry(tool.to_string()).or_default();
+        usage.calls += 1;
+        let Some(error) = error else {
+            return;
+        };
+        usage.failures += 1;
+        let key = error_key(error);
+        if usage.errors.contains_key(&key) || usage.errors.len() < MAX_ERRORS_PER_TOOL {
+            *usage.errors.entry(key).or_insert(0) += 1;
+        } else {
+            *usage.errors.entry("other".to_string()).or_insert(0) += 1;
+        }
+    }
+
+    pub fn record_run(&mut self, stop_code: Option<&str>, now: i64) {
+        self.since.get_or_insert(now);
+        self.runs += 1;
+        if let Some(code) = stop_code {
+            self.failed_runs += 1;
+            *self.stop_codes.entry(code.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    pub fn record_removed_command(&mut self, name: &str, now: i64) {
+        self.since.get_or_insert(now);
+        *self.removed_commands.entry(name.to_string()).or_insert(0) += 1;
+    }
+
+    /// Most used commands, busiest first.
+    pub fn top_commands(&self, limit: usize) -> Vec<(&str, u64)> {
+        let mut commands: Vec<(&str, u64)> = self
+            .commands
+            .iter()
+            .map(|(name, count)| (name.as_str(), *count))
+            .collect();
+        commands.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        commands.truncate(limit);
+        commands
+    }
+
+    /// Tools that failed at least once, most failures first.
+    pub fn hot_spots(&self, limit: usize) -> Vec<(&str, &ToolUsage)> {
+        let mut failing: Vec<(&str, &ToolUsage)> = self
+            .tools
+            .iter()
+            .filter(|(_, usage)| usage.failures > 0)
+            .map(|(name, usage)| (name.as_str(), usage))
+            .collect();
+        failing.sort_by(|a, b| b.1.failures.cmp(&a.1.failures).then_with(|| a.0.cmp(b.0)));
+        failing.truncate(limit);
+        failing
+    }
+
+    /// Available tools that were never called, candidates for trimming.
+    pub fn unused_tools(&self, available: &HashSet<String>) -> Vec<String> {
+        let mut unused: Vec<String> = available
+            .iter()
+            .filter(|tool| !self.tools.contains_key(*tool))
+            .cloned()
+            .collect();
+        unused.sort();
+        unused
+    }
+
+    /// Dashboard lines for `stats`.
+    pub fn render(&self, available: &HashSet<String>) -> Vec<String> {
+        let mut lines = Vec::new();
+        let prompts = self.commands.get("prompt").copied().unwrap_or(0);
+        lines.push(format!(
+            "Runs: {} ({} failed)  Prompts: {}  Tool calls: {}",
+            self.runs,
+            self.failed_runs,
+            prompts,
+            self.tools.values().map(|t| t.calls).sum::<u64>()
+        ));
+
+        let commands: Vec<String> = self
+            .top_commands(8)
+            .into_iter()
+            .filter(|(name, _)| *name != "prompt")
+            .map(|(name, count)| format!("{name} {count}"))
+            .collect();
+        if !commands.is_empty() {
+            lines.push(format!("Commands: {}", commands.join(", ")));
+        }
+
+        let mut tools: Vec<(&String, &ToolUsage)> = self.tools.iter().collect();
+        tools.sort_by(|a, b| b.1.calls.cmp(&a.1.calls).then_with(|| a.0.cmp(b.0)));
+        if !tools.is_empty() {
+            lines.push("Top tools:".to_string());
+            for (name, usage) in tools.into_iter().take(8) {
+                lines.push(format!(
+                    "  {:<32} {:>5} calls {:>4} failed",
+                    name, usage.calls, usage.failures
+                ));
+            }
+        }
+
+        let hot_spots = self.hot_spots(5);
+        if !hot_spots.is_empty() {
+            lines.push("Failure hot-spots:".to_string());
+            for (name, usage) in hot_spots {
+                let top = usage
+                    .top_error()
+                    .map(|(error, count)| format!(" - {error} (x{count})"))
+                    .unwrap_or_default();
+                lines.push(format!(
+                    "  {:<32} {:>3.0}% of {}{}",
+                    name,
+                    usage.failure_rate() * 100.0,
+                    usage.calls,
+                    top
+                ));
+            }
+        }
+
+        if !self.stop_codes.is_empty() {
+            let codes: Vec<String> = self
+                .stop_codes
+                .iter()
+                .map(|(code, count)| format!("{code} {count}"))
+                .collect();
+            lines.push(format!("Run failures: {}", codes.join(", ")));
+        }
+
+        if !self.removed_commands.is_empty() {
+            let removed: Vec<String> = self
+                .removed_commands
+                .iter()
+                .map(|(name, count)| format!("{name} {count}"))
+                .collect();
+            lines.push(format!("Removed commands typed: {}", removed.join(", ")));
+        }
+
+        let unused = self.unused_tools(available);
+        if !unused.is_empty() && !self.tools.is_empty() {
+            lines.push(format!(
+                "Never used ({}): {}",
+                unused.len(),
+                unused.join(", ")
+            ));
+        }
+        lines
+    }
+}
+
+/// Opt-in recorder shared by the REPL and the tool dispatcher. Every
+/// recording call is a no-op while disabled.
+pub struct UsageTracker {
+    path: PathBuf,
+    enabled: AtomicBool,
+    stats: Mutex<UsageStats>,
+}
+
+impl UsageTracker {
+    pub fn open(path: impl AsRef<Path>, enabled: bool) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let stats = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            enabled: AtomicBool::new(enabled),
+            stats: Mutex::new(stats),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn snapshot(&self) -> UsageStats {
+        self.stats
+            .lock()
+            .map(|stats| stats.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn clear(&self) -> io::Result<()> {
+        self.update(|stats| *stats = UsageStats::default())
+    }
+
+    pub fn record_command(&self, input: &str) {
+        if self.enabled() {
+            let now = chrono::Utc::now().timestamp();
+            let _ = self.update(|stats| stats.record_command(input, now));
+        }
+    }
+
+    pub fn record_tool(&self, tool: &str, error: Option<&str>) {
+        if self.enabled() {
+            let now = chrono::Utc::now().timestamp();
+            let _ = self.update(|stats| stats.record_tool(tool, error, now));
+        }
+    }
+
+    pub fn record_removed_command(&self, name: &str) {
+        if self.enabled() {
+            let now = chrono::Utc::now().timestamp();
+            let _ = self.update(|stats| stats.record_removed_command(name, now));
+        }
+    }
+
+    /// `stop_code` is `None` for a run that finished.
+    pub fn record_run(&self, stop_code: Option<&str>) {
+        if self.enabled() {
+            let now = chrono::Utc::now().timestamp();
+            let _ = self.update(|stats| stats.record_run(stop_code, now));
+        }
+    }
+
+    fn update(&self, change: impl FnOnce(&mut UsageStats)) -> io::Result<()> {
+        let mut stats = self
+            .stats
+            .lock()
+            .map_err(|_| io::Error::other("usage stats lock poisoned"))?;
+        change(&mut stats);
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let payload = serde_json::to_string_pretty(&*stats)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        std::fs::write(&self.path, payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_names_drop_arguments_and_prompts() {
+        assert_eq!(command_name("queue add open firefox"), "queue add");
+        assert_eq!(
+            command_name("/capabilities install grim"),
+            "capabilities install"
+        );
+        assert_eq!(command_name("reminder cancel r-12"), "reminder cancel");
+        assert_eq!(command_name("status"), "status");
+        assert_eq!(command_name("contacts add Bob email=b@x.y"), "contacts add");
+        assert_eq!(command_name("open my notes in code"), "prompt");
+        assert_eq!(command_name("/exit"), "exit");
+    }
+
+    #[test]
+    fn hot_spots_rank_tools_by_failures() {
+        let mut stats = UsageStats::default();
+        stats.record_tool("fs.read", None, 1);
+        stats.record_tool("desktop.ocr_screen", Some("tesseract not found"), 1);
+        stats.record_tool(
+            "desktop.ocr_screen",
+            Some("tesseract not found | fallback_tools: desktop.capture_screen"),
+            2,
+        );
+        stats.record_tool("proc.kill", Some("no such process"), 3);
+        stats.record_run(Some("STOP_TOOL_FAILURE"), 3);
+        stats.record_run(None, 4);
+
+        let hot = stats.hot_spots(5);
+        assert_eq!(hot[0].0, "desktop.ocr_screen");
+        assert_eq!(hot[0].1.top_error(), Some(("tesseract not found", 2)));
+        assert_eq!(hot[1].0, "proc.kill");
+        assert_eq!(stats.since, Some(1));
+        assert_eq!((stats.runs, stats.failed_runs), (2, 1));
+
+        let available: HashSet<String> = ["fs.read", "fs.write"].map(String::from).into();
+        assert_eq!(stats.unused_tools(&available), vec!["fs.write"]);
+        assert!(stats
+            .render(&available)
+            .iter()
+            .any(|line| line.starts_with("Failure hot-spots")));
+    }
+
+    #[test]
+    fn tracker_records_nothing_until_enabled() {
+        let dir = std::env::temp_dir().join(format!("test_analytics_{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let path = dir.join("analytics").join("me.json");
+        let tracker = UsageTracker::open(&path, false);
+        tracker.record_command("status");
+        assert_eq!(tracker.snapshot(), UsageStats::default());
+        assert!(!path.exists());
+
+        tracker.set_enabled(true);
+        tracker.record_command("status");
+        tracker.record_tool("fs.read", None);
+        tracker.record_removed_command("soul");
+        let reopened = UsageTracker::open(&path, true).snapshot();
+        assert_eq!(reopened.commands.get("status"), Some(&1));
+        assert_eq!(reopened.tools["fs.read"].calls, 1);
+        assert_eq!(reopened.removed_commands.get("soul"), Some(&1));
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -1,4 +1,4 @@
-use crate::config::{Config, LLMProvider};
+use crate::config::{Config, ConfigIssue, ConfigIssueKind, LLMProvider};
 use anyhow::{Context, Result};
 use std::io::{self, Write};
 
@@ -35,25 +35,28 @@ pub fn run_bootstrap() -> Result<Config> {
 }
 
 fn bootstrap_nvidia() -> Result<Config> {
-    println!("\nEnter NVIDIA API key:");
-    let api_key = rpassword::read_password().context("Failed to read API key")?;
-
-    if api_key.trim().is_empty() {
-        anyhow::bail!("API key cannot be empty");
-    }
-
-    // Store encrypted credential
-    let master_key = get_or_create_master_key()?;
-    let cred_store = hypr_claw::infra::credential_store::CredentialStore::new(
-        "./data/credentials",
-        &master_key,
-    )?;
-
-    cred_store.store_secret(NVIDIA_API_KEY_NAME, api_key.trim())?;
+    read_and_store_api_key("NVIDIA", NVIDIA_API_KEY_NAME)?;
 
     let config = Config {
         provider: LLMProvider::Nvidia,
         model: "z-ai/glm4.7".to_string(),
+        sandbox: Default::default(),
+        backup: Default::default(),
+        plugins: Default::default(),
+        response: Default::default(),
+        verification: Default::default(),
+        analytics: Default::default(),
+        audit: Default::default(),
+        annealing: Default::default(),
+        watchdog: Default::default(),
+        update: Default::default(),
+        context: Default::default(),
+        pricing: Default::default(),
+        concurrency: Default::default(),
+        prefetch: Default::default(),
+        retention: Default::default(),
+        ocr: Default::default(),
+        thermal: Default::default(),
     };
 
     config.save()?;
@@ -63,25 +66,28 @@ fn bootstrap_nvidia() -> Result<Config> {
 }
 
 fn bootstrap_google() -> Result<Config> {
-    println!("\nEnter Google API key:");
-    let api_key = rpassword::read_password().context("Failed to read API key")?;
-
-    if api_key.trim().is_empty() {
-        anyhow::bail!("API key cannot be empty");
-    }
-
-    // Store encrypted credential
-    let master_key = get_or_create_master_key()?;
-    let cred_store = hypr_claw::infra::credential_store::CredentialStore::new(
-        "./data/credentials",
-        &master_key,
-    )?;
-
-    cred_store.store_secret(GOOGLE_API_KEY_NAME, api_key.trim())?;
+    read_and_store_api_key("Google", GOOGLE_API_KEY_NAME)?;
 
     let config = Config {
         provider: LLMProvider::Google,
         model: "gemini-2.5-flash".to_string(),
+        sandbox: Default::default(),
+        backup: Default::default(),
+        plugins: Default::default(),
+        response: Default::default(),
+        verification: Default::default(),
+        analytics: Default::default(),
+        audit: Default::default(),
+        annealing: Default::default(),
+        watchdog: Default::default(),
+        update: Default::default(),
+        context: Default::default(),
+        pricing: Default::default(),
+        concurrency: Default::default(),
+        prefetch: Default::default(),
+        retention: Default::default(),
+        ocr: Default::default(),
+        thermal: Default::default(),
     };
 
     config.save()?;
@@ -105,6 +111,23 @@ fn bootstrap_local() -> Result<Config> {
     let config = Config {
         provider: LLMProvider::Local { base_url },
         model: "default".to_string(),
+        sandbox: Default::default(),
+        backup: Default::default(),
+        plugins: Default::default(),
+        response: Default::default(),
+        verification: Default::default(),
+        analytics: Default::default(),
+        audit: Default::default(),
+        annealing: Default::default(),
+        watchdog: Default::default(),
+        update: Default::default(),
+        context: Default::default(),
+        pricing: Default::default(),
+        concurrency: Default::default(),
+        prefetch: Default::default(),
+        retention: Default::default(),
+        ocr: Default::default(),
+        thermal: Default::default(),
     };
 
     config.save()?;
@@ -113,10 +136,55 @@ fn bootstrap_local() -> Result<Config> {
     Ok(config)
 }
 
+fn read_and_store_api_key(label: &str, name: &str) -> Result<()> {
+    println!("\nEnter {label} API key:");
+    let api_key = rpassword::read_password().context("Failed to read API key")?;
+
+    if api_key.trim().is_empty() {
+        anyhow::bail!("API key cannot be empty");
+    }
+
+    // Store encrypted credential
+    let master_key = get_or_create_master_key()?;
+    let cred_store = hypr_claw::infra::credential_store::CredentialStore::new(
+        crate::data_dir::path("./data/credentials"),
+        &master_key,
+    )?;
+
+    cred_store.store_secret(name, api_key.trim())?;
+    Ok(())
+}
+
+/// Ask for the provider's API key again and store it, without touching the
+/// rest of the configuration.
+pub fn enter_api_key(provider: &LLMProvider) -> Result<()> {
+    match provider {
+        LLMProvider::Nvidia => read_and_store_api_key("NVIDIA", NVIDIA_API_KEY_NAME),
+        LLMProvider::Google => read_and_store_api_key("Google", GOOGLE_API_KEY_NAME),
+        _ => anyhow::bail!("This provider does not use an API key"),
+    }
+}
+
+/// Issue for a provider whose API key is missing from the credential store.
+pub fn api_key_issue(provider: &LLMProvider) -> Option<ConfigIssue> {
+    let stored = match provider {
+        LLMProvider::Nvidia => get_nvidia_api_key(),
+        LLMProvider::Google => get_google_api_key(),
+        _ => return None,
+    };
+    let error = stored.err()?;
+    Some(ConfigIssue::new(
+        ConfigIssueKind::MissingApiKey,
+        "credentials",
+        format!("API key unavailable: {error}"),
+        "enter the key again",
+    ))
+}
+
 pub fn get_nvidia_api_key() -> Result<String> {
     let master_key = get_or_create_master_key()?;
     let cred_store = hypr_claw::infra::credential_store::CredentialStore::new(
-        "./data/credentials",
+        crate::data_dir::path("./data/credentials"),
         &master_key,
     )?;
 
@@ -128,7 +196,7 @@ pub fn get_nvidia_api_key() -> Result<String> {
 pub fn get_google_api_key() -> Result<String> {
     let master_key = get_or_create_master_key()?;
     let cred_store = hypr_claw::infra::credential_store::CredentialStore::new(
-        "./data/credentials",
+        crate::data_dir::path("./data/credentials"),
         &master_key,
     )?;
 
@@ -140,7 +208,7 @@ pub fn get_google_api_key() -> Result<String> {
 pub fn delete_nvidia_api_key() -> Result<()> {
     let master_key = get_or_create_master_key()?;
     let cred_store = hypr_claw::infra::credential_store::CredentialStore::new(
-        "./data/credentials",
+        crate::data_dir::path("./data/credentials"),
         &master_key,
     )?;
 
@@ -151,7 +219,7 @@ pub fn delete_nvidia_api_key() -> Result<()> {
 pub fn delete_google_api_key() -> Result<()> {
     let master_key = get_or_create_master_key()?;
     let cred_store = hypr_claw::infra::credential_store::CredentialStore::new(
-        "./data/credentials",
+        crate::data_dir::path("./data/credentials"),
         &master_key,
     )?;
 
@@ -159,10 +227,10 @@ pub fn delete_google_api_key() -> Result<()> {
     Ok(())
 }
 
-fn get_or_create_master_key() -> Result<[u8; 32]> {
-    let key_path = "./data/.master_key";
+pub fn get_or_create_master_key() -> Result<[u8; 32]> {
+    let key_path = crate::data_dir::path("./data/.master_key");
 
-    if std::path::Path::new(key_path).exists() {
+    if key_path.exists() {
         let key_bytes = std::fs::read(key_path)?;
         if key_bytes.len() != 32 {
             anyhow::bail!("Invalid master key length");
@@ -188,12 +256,29 @@ fn bootstrap_antigravity() -> Result<Config> {
     io::stdin().read_line(&mut _input)?;
 
     // Check if accounts already exist
-    let accounts_path = "./data/antigravity-accounts.json";
-    if std::path::Path::new(accounts_path).exists() {
+    let accounts_path = crate::data_dir::path("./data/antigravity-accounts.json");
+    if accounts_path.exists() {
         println!("✅ Antigravity accounts found");
         let config = Config {
             provider: LLMProvider::Antigravity,
             model: "antigravity-claude-opus-4-6-thinking-medium".to_string(),
+            sandbox: Default::default(),
+            backup: Default::default(),
+            plugins: Default::default(),
+            response: Default::default(),
+            verification: Default::default(),
+            analytics: Default::default(),
+            audit: Default::default(),
+            annealing: Default::default(),
+            watchdog: Default::default(),
+            update: Default::default(),
+            context: Default::default(),
+            pricing: Default::default(),
+            concurrency: Default::default(),
+            prefetch: Default::default(),
+            retention: Default::default(),
+            ocr: Default::default(),
+            thermal: Default::default(),
         };
         config.save()?;
         return Ok(config);
@@ -202,7 +287,7 @@ fn bootstrap_antigravity() -> Result<Config> {
     println!("\n⚠️  No accounts configured yet.");
     println!("Run this command to authenticate:");
     println!("  cargo run --example basic_usage -p hypr-claw-antigravity");
-    println!("\nOr manually add accounts to: {}", accounts_path);
+    println!("\nOr manually add accounts to: {}", accounts_path.display());
 
     anyhow::bail!("Antigravity authentication required. Please run the OAuth flow first.");
 }
@@ -216,12 +301,29 @@ fn bootstrap_gemini_cli() -> Result<Config> {
     io::stdin().read_line(&mut _input)?;
 
     // Check if accounts already exist
-    let accounts_path = "./data/antigravity-accounts.json";
-    if std::path::Path::new(accounts_path).exists() {
+    let accounts_path = crate::data_dir::path("./data/antigravity-accounts.json");
+    if accounts_path.exists() {
         println!("✅ Accounts found (shared with Antigravity)");
         let config = Config {
             provider: LLMProvider::GeminiCli,
             model: "gemini-3-flash-preview-high".to_string(),
+            sandbox: Default::default(),
+            backup: Default::default(),
+            plugins: Default::default(),
+            response: Default::default(),
+            verification: Default::default(),
+            analytics: Default::default(),
+            audit: Default::default(),
+            annealing: Default::default(),
+            watchdog: Default::default(),
+            update: Default::default(),
+            context: Default::default(),
+            pricing: Default::default(),
+            concurrency: Default::default(),
+            prefetch: Default::default(),
+            retention: Default::default(),
+            ocr: Default::default(),
+            thermal: Default::default(),
         };
         config.save()?;
         return Ok(config);
@@ -230,7 +332,7 @@ fn bootstrap_gemini_cli() -> Result<Config> {
     println!("\n⚠️  No accounts configured yet.");
     println!("Run this command to authenticate:");
     println!("  cargo run --example basic_usage -p hypr-claw-antigravity");
-    println!("\nOr manually add accounts to: {}", accounts_path);
+    println!("\nOr manually add accounts to: {}", accounts_path.display());
 
     anyhow::bail!("OAuth authentication required. Please run the OAuth flow first.");
 }
@@ -257,6 +359,23 @@ fn bootstrap_codex() -> Result<Config> {
     let config = Config {
         provider: LLMProvider::Codex,
         model,
+        sandbox: Default::default(),
+        backup: Default::default(),
+        plugins: Default::default(),
+        response: Default::default(),
+        verification: Default::default(),
+        analytics: Default::default(),
+        audit: Default::default(),
+        annealing: Default::default(),
+        watchdog: Default::default(),
+        update: Default::default(),
+        context: Default::default(),
+        pricing: Default::default(),
+        concurrency: Default::default(),
+        prefetch: Default::default(),
+        retention: Default::default(),
+        ocr: Default::default(),
+        thermal: Default::default(),
     };
 
     config.save()?;
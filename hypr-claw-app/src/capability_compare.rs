@@ -0,0 +1,222 @@
+//! `capabilities export` / `capabilities compare <file>`: carry a machine's
+//! capability registry to another install and see what it lacks.
+//!
+//! An export is the capability registry plus the tools usable with it and
+//! the machine's host name. Comparing lists, per section, what the other
+//! machine has that this one is missing (backends to install, tools that
+//! stay disabled) and what only this one has.
+
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+/// Set-valued sections compared, with the registry pointer they come from.
+const SECTIONS: &[(&str, &str)] = &[
+    ("wallpaper backends", "/capabilities/wallpaper_backends"),
+    ("screenshot backends", "/capabilities/screenshot_backends"),
+    ("input backends", "/capabilities/input_backends"),
+    ("ocr languages", "/capabilities/ocr_languages"),
+    ("gpus", "/platform/gpus"),
+    ("tools", "/tools"),
+    ("launchers", "/desktop_apps/launcher_commands"),
+    ("commands", "/commands/available"),
+];
+
+/// Single-valued settings shown when they differ.
+const SETTINGS: &[(&str, &str)] = &[
+    ("distro", "/platform/distro_name"),
+    ("ui language", "/platform/ui_language"),
+    ("keyboard layout", "/platform/keyboard_layout"),
+    ("hyprland", "/platform/hyprland_available"),
+    ("ocr", "/capabilities/ocr_available"),
+    ("editor", "/editor/vscode_command"),
+    ("browser", "/desktop_apps/preferred_launchers/browser"),
+    ("terminal", "/desktop_apps/preferred_launchers/terminal"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionDiff {
+    pub name: &'static str,
+    /// On the other machine only.
+    pub missing: Vec<String>,
+    /// On this machine only.
+    pub extra: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingDiff {
+    pub name: &'static str,
+    pub here: String,
+    pub there: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CapabilityComparison {
+    pub sections: Vec<SectionDiff>,
+    pub settings: Vec<SettingDiff>,
+}
+
+impl CapabilityComparison {
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty() && self.settings.is_empty()
+    }
+}
+
+/// The export written by `capabilities export`.
+pub fn export(registry: &Value, tools: impl IntoIterator<Item = String>, host: &str) -> Value {
+    let mut export = registry.clone();
+    let tools: BTreeSet<String> = tools.into_iter().collect();
+    if let Some(object) = export.as_object_mut() {
+        object.insert("tools".to_string(), tools.into_iter().collect());
+        object.insert("host".to_string(), Value::from(host));
+        object.insert(
+            "exported_at".to_string(),
+            Value::from(chrono::Utc::now().timestamp()),
+        );
+    }
+    export
+}
+
+pub fn compare(here: &Value, there: &Value) -> CapabilityComparison {
+    let strings = |registry: &Value, pointer: &str| -> BTreeSet<String> {
+        registry
+            .pointer(pointer)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|item| item.as_str().map(str::to_string))
+            .collect()
+    };
+    let sections = SECTIONS
+        .iter()
+        .filter_map(|(name, pointer)| {
+            let mine = strings(here, pointer);
+            let theirs = strings(there, pointer);
+            let diff = SectionDiff {
+                name,
+                missing: theirs.difference(&mine).cloned().collect(),
+                extra: mine.difference(&theirs).cloned().collect(),
+            };
+            (!diff.missing.is_empty() || !diff.extra.is_empty()).then_some(diff)
+        })
+        .collect();
+
+    let setting = |registry: &Value, pointer: &str| match registry.pointer(pointer) {
+        Some(Value::String(value)) => value.clone(),
+        Some(Value::Null) | None => "unknown".to_string(),
+        Some(value) => value.to_string(),
+    };
+    let settings = SETTINGS
+        .iter()
+        .map(|(name, pointer)| SettingDiff {
+            name,
+            here: setting(here, pointer),
+            there: setting(there, pointer),
+        })
+        .filter(|diff| diff.here != diff.there)
+        .collect();
+
+    CapabilityComparison { sections, settings }
+}
+
+/// Report lines; `there` names the other machine.
+pub fn render(comparison: &CapabilityComparison, there: &str, max_items: usize) -> Vec<String> {
+    if comparison.is_empty() {
+        return vec![format!("Same capabilities as {there}.")];
+    }
+    let list = |items: &[String]| {
+        let mut shown = items
+            .iter()
+            .take(max_items)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ");
+        if items.len() > max_items {
+            shown.push_str(&format!(" … (+{})", items.len() - max_items));
+        }
+        shown
+    };
+    let mut lines = Vec::new();
+    for section in &comparison.sections {
+        if !section.missing.is_empty() {
+            lines.push(format!(
+                "✗ {} missing here: {}",
+                section.name,
+                list(&section.missing)
+            ));
+        }
+        if !section.extra.is_empty() {
+            lines.push(format!(
+                "+ {} only here: {}",
+                section.name,
+                list(&section.extra)
+            ));
+        }
+    }
+    for setting in &comparison.settings {
+        lines.push(format!(
+            "~ {}: '{}' here, '{}' on {there}",
+            setting.name, setting.here, setting.there
+        ));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn comparison_lists_what_each_side_lacks() {
+        let here = export(
+            &json!({
+                "platform": {"distro_name": "Arch Linux", "hyprland_available": true},
+                "capabilities": {
+                    "screenshot_backends": ["grim"],
+                    "input_backends": ["wtype"],
+                    "ocr_available": false
+                },
+                "editor": {"vscode_command": "code"}
+            }),
+            ["fs.read".to_string(), "desktop.type_text".to_string()],
+            "laptop",
+        );
+        let there = json!({
+            "platform": {"distro_name": "Arch Linux", "hyprland_available": true},
+            "capabilities": {
+                "screenshot_backends": ["grim"],
+                "input_backends": ["wtype", "ydotool"],
+                "ocr_available": true
+            },
+            "editor": {"vscode_command": "code"},
+            "tools": ["desktop.ocr_screen", "desktop.type_text", "fs.read"]
+        });
+
+        let comparison = compare(&here, &there);
+        assert_eq!(
+            comparison.sections,
+            vec![
+                SectionDiff {
+                    name: "input backends",
+                    missing: vec!["ydotool".into()],
+                    extra: vec![],
+                },
+                SectionDiff {
+                    name: "tools",
+                    missing: vec!["desktop.ocr_screen".into()],
+                    extra: vec![],
+                },
+            ]
+        );
+        assert_eq!(
+            render(&comparison, "desktop", 10),
+            vec![
+                "✗ input backends missing here: ydotool",
+                "✗ tools missing here: desktop.ocr_screen",
+                "~ ocr: 'false' here, 'true' on desktop",
+            ]
+        );
+        assert!(compare(&here, &here).is_empty());
+        assert_eq!(here["host"], "laptop");
+    }
+}
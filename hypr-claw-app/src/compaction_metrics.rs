@@ -0,0 +1,231 @@
+//! Compaction telemetry behind `hypr-claw metrics compactions`.
+//!
+//! Every compaction appends a row to `./data/metrics/compactions.jsonl`
+//! with the tokens it saved and what it summarized, and every later read of
+//! a target it summarized away appends a refetch row pointing back at it.
+//! A high refetch rate means summaries drop things the model still needs.
+
+use hypr_claw_runtime::CompactionEvent;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+pub const COMPACTIONS_PATH: &str = "./data/metrics/compactions.jsonl";
+
+/// Summary text kept per row.
+const MAX_SUMMARY_CHARS: usize = 2000;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CompactionRow {
+    Compaction {
+        compaction_id: String,
+        at: i64,
+        session: String,
+        tokens_before: usize,
+        tokens_after: usize,
+        summarized_messages: usize,
+        kept_messages: usize,
+        summarized_tools: BTreeMap<String, usize>,
+        targets: Vec<String>,
+        summary: String,
+    },
+    Refetch {
+        compaction_id: String,
+        at: i64,
+        session: String,
+        tool: String,
+        target: String,
+        same_run: bool,
+    },
+}
+
+impl CompactionRow {
+    pub fn from_event(event: &CompactionEvent, at: i64) -> Self {
+        match event {
+            CompactionEvent::Compacted {
+                session_key,
+                compaction_id,
+                report,
+            } => Self::Compaction {
+                compaction_id: compaction_id.clone(),
+                at,
+                session: session_key.clone(),
+                tokens_before: report.tokens_before,
+                tokens_after: report.tokens_after,
+                summarized_messages: report.summarized_messages,
+                kept_messages: report.kept_messages,
+                summarized_tools: report.summarized_tools.clone(),
+                targets: report.targets.clone(),
+                summary: report.summary.chars().take(MAX_SUMMARY_CHARS).collect(),
+            },
+            CompactionEvent::Refetched {
+                session_key,
+                compaction_id,
+                tool,
+                target,
+                same_run,
+            } => Self::Refetch {
+                compaction_id: compaction_id.clone(),
+                at,
+                session: session_key.clone(),
+                tool: tool.clone(),
+                target: target.clone(),
+                same_run: *same_run,
+            },
+        }
+    }
+
+    fn at(&self) -> i64 {
+        match self {
+            Self::Compaction { at, .. } | Self::Refetch { at, .. } => *at,
+        }
+    }
+}
+
+pub struct CompactionLog {
+    path: PathBuf,
+}
+
+impl CompactionLog {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn append(&self, row: &CompactionRow) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let line = serde_json::to_string(row).map_err(io::Error::other)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{line}")
+    }
+
+    /// Rows written at or after `since`, oldest first; unreadable lines are
+    /// skipped.
+    pub fn load(&self, since: Option<i64>) -> io::Result<Vec<CompactionRow>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str::<CompactionRow>(line).ok())
+            .filter(|row| since.is_none_or(|since| row.at() >= since))
+            .collect())
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CompactionStats {
+    pub compactions: usize,
+    pub tokens_saved: usize,
+    pub targets: usize,
+    pub refetched: usize,
+    pub refetched_same_run: usize,
+    /// Refetches by the kind of target (`file`, `screen`, `window`).
+    pub refetched_by_kind: BTreeMap<String, usize>,
+}
+
+impl CompactionStats {
+    pub fn from_rows(rows: &[CompactionRow]) -> Self {
+        let mut stats = Self::default();
+        for row in rows {
+            match row {
+                CompactionRow::Compaction {
+                    tokens_before,
+                    tokens_after,
+                    targets,
+                    ..
+                } => {
+                    stats.compactions += 1;
+                    stats.tokens_saved += tokens_before.saturating_sub(*tokens_after);
+                    stats.targets += targets.len();
+                }
+                CompactionRow::Refetch {
+                    target, same_run, ..
+                } => {
+                    stats.refetched += 1;
+                    if *same_run {
+                        stats.refetched_same_run += 1;
+                    }
+                    let kind = target.split(':').next().unwrap_or(target);
+                    *stats.refetched_by_kind.entry(kind.to_string()).or_insert(0) += 1;
+                }
+            }
+        }
+        stats
+    }
+
+    /// Share of summarized targets the model read again.
+    pub fn refetch_rate(&self) -> f64 {
+        if self.targets == 0 {
+            0.0
+        } else {
+            self.refetched as f64 / self.targets as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hypr_claw_runtime::CompactionReport;
+
+    #[test]
+    fn events_round_trip_and_add_up() {
+        let path = std::env::temp_dir().join(format!(
+            "hypr-claw-compactions-{}.jsonl",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let log = CompactionLog::new(&path);
+        let compacted = CompactionEvent::Compacted {
+            session_key: "ana:agent::thread::task-1".to_string(),
+            compaction_id: "c1".to_string(),
+            report: CompactionReport {
+                tokens_before: 9000,
+                tokens_after: 3000,
+                summarized_messages: 40,
+                kept_messages: 40,
+                summarized_tools: BTreeMap::from([("fs.read".to_string(), 3)]),
+                targets: vec![
+                    "file:/etc/hosts".to_string(),
+                    "file:/tmp/a".to_string(),
+                    "screen".to_string(),
+                ],
+                summary: "read three files".to_string(),
+            },
+        };
+        let refetched = CompactionEvent::Refetched {
+            session_key: "ana:agent::thread::task-1".to_string(),
+            compaction_id: "c1".to_string(),
+            tool: "fs.read".to_string(),
+            target: "file:/etc/hosts".to_string(),
+            same_run: true,
+        };
+        log.append(&CompactionRow::from_event(&compacted, 100))
+            .unwrap();
+        log.append(&CompactionRow::from_event(&refetched, 200))
+            .unwrap();
+
+        let rows = log.load(None).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(log.load(Some(150)).unwrap().len(), 1);
+        let stats = CompactionStats::from_rows(&rows);
+        assert_eq!(stats.compactions, 1);
+        assert_eq!(stats.tokens_saved, 6000);
+        assert_eq!(stats.refetched_same_run, 1);
+        assert_eq!(stats.refetched_by_kind.get("file"), Some(&1));
+        assert!((stats.refetch_rate() - 1.0 / 3.0).abs() < 1e-9);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,6 +1,6 @@
-use anyhow::{bail, Context, Result};
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::fmt;
 
 const CONFIG_PATH: &str = "./data/config.yaml";
 
@@ -8,6 +8,421 @@ const CONFIG_PATH: &str = "./data/config.yaml";
 pub struct Config {
     pub provider: LLMProvider,
     pub model: String,
+    #[serde(default)]
+    pub sandbox: SandboxConfig,
+    #[serde(default)]
+    pub backup: BackupConfig,
+    #[serde(default)]
+    pub plugins: PluginsConfig,
+    #[serde(default)]
+    pub response: ResponseConfig,
+    #[serde(default)]
+    pub verification: VerificationConfig,
+    #[serde(default)]
+    pub analytics: AnalyticsConfig,
+    #[serde(default)]
+    pub audit: AuditConfig,
+    #[serde(default)]
+    pub annealing: AnnealingConfig,
+    #[serde(default)]
+    pub watchdog: WatchdogConfig,
+    #[serde(default)]
+    pub update: UpdateConfig,
+    #[serde(default)]
+    pub context: ContextConfig,
+    /// Prices by model id, for the cost estimate of `preview`.
+    #[serde(default)]
+    pub pricing: std::collections::BTreeMap<String, ModelPricing>,
+    /// Request limits by provider name, shared by the foreground session and
+    /// background tasks.
+    #[serde(default)]
+    pub concurrency: std::collections::BTreeMap<String, ProviderConcurrency>,
+    #[serde(default)]
+    pub prefetch: PrefetchConfig,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// Default OCR language and per-language tuning.
+    #[serde(default)]
+    pub ocr: hypr_claw_tools::os_capabilities::ocr::OcrSettings,
+    #[serde(default)]
+    pub thermal: ThermalConfig,
+}
+
+/// Limits on what tools may expose to the model.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SandboxConfig {
+    /// Environment variables visible to `env.get` / `env.list`.
+    #[serde(default)]
+    pub env: hypr_claw_tools::sandbox::EnvPolicy,
+    /// Regex allow/deny rules for `hypr.exec` and `proc.spawn` command lines.
+    #[serde(default)]
+    pub exec: hypr_claw_tools::sandbox::ExecPolicy,
+    /// Bubblewrap isolation for processes started by `proc.spawn`.
+    #[serde(default)]
+    pub isolation: hypr_claw_tools::sandbox::IsolationPolicy,
+    /// Landlock and seccomp restrictions on the agent process itself.
+    #[serde(default)]
+    pub hardening: hypr_claw_tools::sandbox::HardeningPolicy,
+}
+
+/// Encrypted backups of the data directory (`hypr-claw backup ...`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackupConfig {
+    #[serde(default = "default_backup_dir")]
+    pub dir: String,
+    /// Hours between automatic backups while the agent runs; 0 disables them.
+    #[serde(default)]
+    pub interval_hours: u64,
+    /// Number of archives kept after an automatic backup.
+    #[serde(default = "default_backup_keep")]
+    pub keep: usize,
+}
+
+fn default_backup_dir() -> String {
+    "./backups".to_string()
+}
+
+fn default_backup_keep() -> usize {
+    7
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            dir: default_backup_dir(),
+            interval_hours: 0,
+            keep: default_backup_keep(),
+        }
+    }
+}
+
+/// Where `hypr-claw plugins ...` looks for installable plugins.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PluginsConfig {
+    /// Plugin index URL (http(s), file:// or a local path). Empty disables
+    /// index lookups; `--index <url>` overrides it per command.
+    #[serde(default)]
+    pub index_url: String,
+    /// `trusted_keys` and `allow_unsigned`, checked at install and load time.
+    #[serde(flatten)]
+    pub signatures: hypr_claw_tools::plugins::SignaturePolicy,
+}
+
+/// Checks applied to the agent's final answers.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ResponseConfig {
+    /// Final answers must cite the tool results backing each claim (`[t1]`).
+    #[serde(default)]
+    pub require_citations: bool,
+    /// Sent to the provider as stop sequences; answers are also cut at them.
+    #[serde(default)]
+    pub stop_sequences: Vec<String>,
+    /// Reply token budget, sent as `max_tokens` so a chatty model cannot
+    /// spend an iteration on prose. 0 keeps the model's default.
+    #[serde(default)]
+    pub max_narration_tokens: u32,
+}
+
+/// Smallest narration budget that still leaves room for a tool call.
+pub const MIN_NARRATION_TOKENS: u32 = 128;
+
+impl ResponseConfig {
+    pub fn output_guardrails(&self) -> hypr_claw_runtime::OutputGuardrails {
+        hypr_claw_runtime::OutputGuardrails {
+            stop_sequences: self.stop_sequences.clone(),
+            max_narration_tokens: self.max_narration_tokens,
+        }
+    }
+}
+
+/// Post-condition checks for action tasks, per autonomy mode. When enabled,
+/// a run cannot finish until a read-only tool call succeeded after its last
+/// mutating call.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VerificationConfig {
+    #[serde(default = "default_true")]
+    pub prompt_first: bool,
+    #[serde(default = "default_true")]
+    pub guarded: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for VerificationConfig {
+    fn default() -> Self {
+        Self {
+            prompt_first: true,
+            guarded: true,
+        }
+    }
+}
+
+/// Local usage analytics for the `stats` dashboard. Counts stay in
+/// `./data/analytics` and are never sent anywhere.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AnalyticsConfig {
+    /// Off until the user opts in with `stats on`.
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// Rotation and retention of `./data/audit.log`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AuditConfig {
+    #[serde(default)]
+    pub rotation: hypr_claw::infra::audit_logger::RotationPolicy,
+}
+
+/// Restart when the async runtime stops making progress, see
+/// [`crate::watchdog`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    pub enabled: bool,
+    /// How long the runtime may go without a heartbeat before it counts as
+    /// wedged. Capped below systemd's `WatchdogSec` when that is set.
+    pub timeout_secs: u64,
+}
+
+/// Shortest accepted watchdog timeout; long tool calls must not trip it.
+pub const MIN_WATCHDOG_TIMEOUT_SECS: u64 = 10;
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            timeout_secs: 90,
+        }
+    }
+}
+
+/// Release feed used by the startup update check and `self-update`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct UpdateConfig {
+    /// Check the feed at startup, at most once a day. Off until opted in.
+    pub check: bool,
+    pub feed_url: String,
+    /// minisign keys a release binary must be signed with.
+    pub trusted_keys: Vec<String>,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self {
+            check: false,
+            feed_url:
+                "https://github.com/bashmyhed/hypr-claw/releases/latest/download/release.json"
+                    .to_string(),
+            trusted_keys: Vec::new(),
+        }
+    }
+}
+
+/// Which [`crate::context_providers`] feed the system prompt, and how many
+/// tokens each may use.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ContextConfig {
+    #[serde(default)]
+    pub providers: std::collections::BTreeMap<String, ContextProviderConfig>,
+    /// Seconds into a run after which windows, time and battery are read
+    /// again for the model; 0 only refreshes when a tool finds a window gone.
+    #[serde(default = "default_context_refresh_after_secs")]
+    pub refresh_after_secs: u64,
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            providers: Default::default(),
+            refresh_after_secs: default_context_refresh_after_secs(),
+        }
+    }
+}
+
+fn default_context_refresh_after_secs() -> u64 {
+    120
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ContextProviderConfig {
+    pub enabled: bool,
+    /// Overrides the provider's default budget.
+    pub max_tokens: Option<usize>,
+}
+
+impl Default for ContextProviderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_tokens: None,
+        }
+    }
+}
+
+/// Read-only calls guessed from the prompt and run alongside the first
+/// model call, see [`crate::prefetch`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PrefetchConfig {
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// Limits on screenshots and generated artifacts, see [`crate::retention`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RetentionConfig {
+    #[serde(default = "default_screenshot_retention")]
+    pub screenshots: RetentionPolicy,
+    #[serde(default)]
+    pub artifacts: RetentionPolicy,
+    /// Minutes between background sweeps; 0 leaves cleanup to
+    /// `artifacts clean`.
+    #[serde(default = "default_retention_interval")]
+    pub interval_minutes: u64,
+}
+
+/// 0 turns a limit off.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Total size of the kind, pinned files included.
+    pub max_total_mb: u64,
+    pub max_age_days: u64,
+    /// Age at which files marked sensitive (e.g. a screenshot whose OCR
+    /// text holds a secret) expire. They are always wiped securely.
+    pub sensitive_max_age_minutes: u64,
+    /// Overwrite every removed file before unlinking it.
+    pub secure: bool,
+}
+
+fn default_screenshot_retention() -> RetentionPolicy {
+    RetentionPolicy {
+        max_total_mb: 500,
+        max_age_days: 3,
+        sensitive_max_age_minutes: 60,
+        secure: false,
+    }
+}
+
+fn default_retention_interval() -> u64 {
+    60
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            screenshots: default_screenshot_retention(),
+            artifacts: RetentionPolicy::default(),
+            interval_minutes: default_retention_interval(),
+        }
+    }
+}
+
+/// Temperature rules checked by the thermal monitor, see [`crate::thermal`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ThermalConfig {
+    /// Seconds between sensor reads; 0 turns the monitor off.
+    pub interval_secs: u64,
+    /// Degrees a sensor must drop below a rule's threshold to clear it.
+    pub hysteresis: f32,
+    /// Longest a request waits for a throttle rule to clear.
+    pub max_wait_secs: u64,
+    pub rules: Vec<ThermalRule>,
+}
+
+impl Default for ThermalConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 30,
+            hysteresis: 5.0,
+            max_wait_secs: 180,
+            rules: vec![ThermalRule {
+                sensor: None,
+                above: 90.0,
+                action: ThermalAction::Alert,
+            }],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ThermalRule {
+    /// Case-insensitive substring of `source/label`, e.g. `k10temp` or
+    /// `amdgpu/edge`; unset matches every sensor.
+    #[serde(default)]
+    pub sensor: Option<String>,
+    /// Degrees Celsius.
+    pub above: f32,
+    pub action: ThermalAction,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum ThermalAction {
+    /// Desktop notification and a task event.
+    Alert,
+    /// Also hold new requests until the sensor cools down.
+    Throttle,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProviderConcurrency {
+    /// Requests in flight at once; more wait their turn in arrival order.
+    pub max_concurrent: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ModelPricing {
+    /// USD per million input tokens.
+    pub input_per_mtok: f64,
+}
+
+/// Sampling temperature after failed recovery attempts, per autonomy mode:
+/// explore with the model's default first, then get more conservative.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AnnealingConfig {
+    #[serde(default)]
+    pub prompt_first: AnnealingPolicy,
+    #[serde(default)]
+    pub guarded: AnnealingPolicy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AnnealingPolicy {
+    pub enabled: bool,
+    /// Temperature multiplier applied per failed recovery attempt.
+    pub decay: f32,
+    /// Lowest temperature annealing goes down to.
+    pub floor: f32,
+}
+
+impl Default for AnnealingPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            decay: 0.5,
+            floor: 0.0,
+        }
+    }
+}
+
+impl AnnealingPolicy {
+    /// Temperature for the `attempt`-th recovery (1-based) of a run, or
+    /// `None` to keep the model's default.
+    pub fn temperature(&self, base: Option<f32>, attempt: u32) -> Option<f32> {
+        if !self.enabled || attempt == 0 {
+            return None;
+        }
+        let base = base?;
+        let annealed = base * self.decay.powi(attempt.min(i32::MAX as u32) as i32);
+        Some(annealed.max(self.floor).min(base))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -58,40 +473,323 @@ impl LLMProvider {
             LLMProvider::Nvidia | LLMProvider::Google | LLMProvider::Local { .. }
         )
     }
+
+    /// Name used as the `provider:` value and as the key of `concurrency`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LLMProvider::Nvidia => "nvidia",
+            LLMProvider::Google => "google",
+            LLMProvider::Local { .. } => "local",
+            LLMProvider::Antigravity => "antigravity",
+            LLMProvider::GeminiCli => "gemini-cli",
+            LLMProvider::Codex => "codex",
+        }
+    }
 }
 
+/// Providers whose requests go through a shared limiter.
+pub const LIMITED_PROVIDERS: &[&str] = &["nvidia", "google", "local"];
+
 impl Config {
     pub fn load() -> Result<Self> {
-        let content = std::fs::read_to_string(CONFIG_PATH).context("Failed to read config.yaml")?;
+        let content = std::fs::read_to_string(crate::data_dir::path(CONFIG_PATH))
+            .context("Failed to read config.yaml")?;
         serde_yaml::from_str(&content).context("Failed to parse config.yaml")
     }
 
     pub fn save(&self) -> Result<()> {
         let content = serde_yaml::to_string(self)?;
-        std::fs::write(CONFIG_PATH, content)?;
+        std::fs::write(crate::data_dir::path(CONFIG_PATH), content)?;
         Ok(())
     }
 
     pub fn exists() -> bool {
-        Path::new(CONFIG_PATH).exists()
+        crate::data_dir::path(CONFIG_PATH).exists()
     }
 
     pub fn delete() -> Result<()> {
         if Self::exists() {
-            std::fs::remove_file(CONFIG_PATH)?;
+            std::fs::remove_file(crate::data_dir::path(CONFIG_PATH))?;
         }
         Ok(())
     }
 
-    pub fn validate(&self) -> Result<()> {
-        if self.model.is_empty() {
-            bail!("Model cannot be empty");
+    /// Checks that need nothing beyond the file itself. Credentials and the
+    /// provider's model list are checked by the caller, see
+    /// [`Config::check_model`].
+    pub fn validate(&self) -> Result<(), Vec<ConfigIssue>> {
+        let mut issues = Vec::new();
+        if self.model.trim().is_empty() {
+            issues.push(ConfigIssue::new(
+                ConfigIssueKind::EmptyModel,
+                "model",
+                "model cannot be empty",
+                "pick one of the provider's models",
+            ));
         }
         if let LLMProvider::Local { base_url } = &self.provider {
-            if base_url.is_empty() {
-                bail!("Base URL cannot be empty for local provider");
+            if base_url.trim().is_empty() {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueKind::MissingBaseUrl,
+                    "provider.local.base_url",
+                    "base URL cannot be empty for the local provider",
+                    "set it to the server address, e.g. http://localhost:8080/v1",
+                ));
+            } else if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueKind::InvalidValue,
+                    "provider.local.base_url",
+                    format!("'{base_url}' is not an http(s) URL"),
+                    "prefix it with http:// or https://",
+                ));
             }
         }
-        Ok(())
+        if self.backup.keep == 0 {
+            issues.push(ConfigIssue::new(
+                ConfigIssueKind::InvalidValue,
+                "backup.keep",
+                "backup.keep must be at least 1",
+                "set backup.keep to 1 or more (default 7)",
+            ));
+        }
+        if self.response.max_narration_tokens > 0
+            && self.response.max_narration_tokens < MIN_NARRATION_TOKENS
+        {
+            issues.push(ConfigIssue::new(
+                ConfigIssueKind::InvalidValue,
+                "response.max_narration_tokens",
+                format!(
+                    "{} tokens leaves no room for tool-call arguments",
+                    self.response.max_narration_tokens
+                ),
+                format!("use 0 (model default) or at least {MIN_NARRATION_TOKENS}"),
+            ));
+        }
+        for (pattern, error) in self.sandbox.exec.invalid_patterns() {
+            issues.push(ConfigIssue::new(
+                ConfigIssueKind::InvalidValue,
+                "sandbox.exec",
+                format!("'{pattern}' is not a valid regex: {error}"),
+                "fix or remove the pattern",
+            ));
+        }
+        for path in self.sandbox.isolation.relative_paths() {
+            issues.push(ConfigIssue::new(
+                ConfigIssueKind::InvalidValue,
+                "sandbox.isolation",
+                format!("'{path}' is not an absolute path"),
+                "use an absolute path or one starting with ~/",
+            ));
+        }
+        for path in self.sandbox.hardening.relative_paths() {
+            issues.push(ConfigIssue::new(
+                ConfigIssueKind::InvalidValue,
+                "sandbox.hardening",
+                format!("'{path}' is not an absolute path"),
+                "use an absolute path or one starting with ~/",
+            ));
+        }
+        if self.sandbox.hardening.enabled && self.sandbox.isolation.is_enabled() {
+            issues.push(ConfigIssue::new(
+                ConfigIssueKind::InvalidValue,
+                "sandbox.hardening",
+                "hardening forbids the namespace setup bwrap isolation needs",
+                "enable either sandbox.hardening or sandbox.isolation, not both",
+            ));
+        }
+        if self.response.stop_sequences.len() > hypr_claw_runtime::guardrails::MAX_STOP_SEQUENCES {
+            issues.push(ConfigIssue::new(
+                ConfigIssueKind::InvalidValue,
+                "response.stop_sequences",
+                format!(
+                    "{} stop sequences; providers accept at most {}",
+                    self.response.stop_sequences.len(),
+                    hypr_claw_runtime::guardrails::MAX_STOP_SEQUENCES
+                ),
+                "keep the most important ones",
+            ));
+        }
+        for (i, rule) in self.thermal.rules.iter().enumerate() {
+            if !rule.above.is_finite() || rule.above <= 0.0 {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueKind::InvalidValue,
+                    format!("thermal.rules[{i}].above"),
+                    format!("{} is not a temperature", rule.above),
+                    "use degrees Celsius, e.g. 90",
+                ));
+            }
+        }
+        if !self.thermal.hysteresis.is_finite() || self.thermal.hysteresis < 0.0 {
+            issues.push(ConfigIssue::new(
+                ConfigIssueKind::InvalidValue,
+                "thermal.hysteresis",
+                format!(
+                    "{} is not a temperature difference",
+                    self.thermal.hysteresis
+                ),
+                "use 0 or more degrees (default 5)",
+            ));
+        }
+        for (model, pricing) in &self.pricing {
+            if !pricing.input_per_mtok.is_finite() || pricing.input_per_mtok < 0.0 {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueKind::InvalidValue,
+                    format!("pricing.{model}.input_per_mtok"),
+                    format!("{} is not a price", pricing.input_per_mtok),
+                    "use USD per million input tokens, e.g. 0.5",
+                ));
+            }
+        }
+        for (name, limit) in &self.concurrency {
+            if !LIMITED_PROVIDERS.contains(&name.as_str()) {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueKind::InvalidValue,
+                    format!("concurrency.{name}"),
+                    format!("requests to '{name}' cannot be limited"),
+                    format!("use one of {}", LIMITED_PROVIDERS.join(", ")),
+                ));
+            } else if limit.max_concurrent == 0 {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueKind::InvalidValue,
+                    format!("concurrency.{name}.max_concurrent"),
+                    "a limit of 0 would block every request",
+                    "allow at least 1 request at a time",
+                ));
+            }
+        }
+        for (name, provider) in &self.context.providers {
+            if !crate::context_providers::PROVIDER_NAMES.contains(&name.as_str()) {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueKind::InvalidValue,
+                    format!("context.providers.{name}"),
+                    format!("unknown context provider '{name}'"),
+                    format!(
+                        "use one of {}",
+                        crate::context_providers::PROVIDER_NAMES.join(", ")
+                    ),
+                ));
+            } else if provider.max_tokens == Some(0) {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueKind::InvalidValue,
+                    format!("context.providers.{name}.max_tokens"),
+                    "a budget of 0 tokens drops every line",
+                    format!("set context.providers.{name}.enabled to false instead"),
+                ));
+            }
+        }
+        for (mode, policy) in [
+            ("prompt_first", &self.annealing.prompt_first),
+            ("guarded", &self.annealing.guarded),
+        ] {
+            if policy.decay.is_nan() || policy.decay <= 0.0 || policy.decay > 1.0 {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueKind::InvalidValue,
+                    format!("annealing.{mode}.decay"),
+                    format!("decay {} is outside (0, 1]", policy.decay),
+                    "use a multiplier such as 0.5",
+                ));
+            }
+            if policy.floor.is_nan() || policy.floor < 0.0 {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueKind::InvalidValue,
+                    format!("annealing.{mode}.floor"),
+                    format!("floor {} is negative", policy.floor),
+                    "use 0.0 or a small positive temperature",
+                ));
+            }
+        }
+        if self.watchdog.timeout_secs < MIN_WATCHDOG_TIMEOUT_SECS {
+            issues.push(ConfigIssue::new(
+                ConfigIssueKind::InvalidValue,
+                "watchdog.timeout_secs",
+                format!(
+                    "timeout of {}s is below the {}s minimum",
+                    self.watchdog.timeout_secs, MIN_WATCHDOG_TIMEOUT_SECS
+                ),
+                "use 90 or disable the watchdog",
+            ));
+        }
+        let feed = &self.update.feed_url;
+        if !["http://", "https://", "file://"]
+            .iter()
+            .any(|scheme| feed.starts_with(scheme))
+        {
+            issues.push(ConfigIssue::new(
+                ConfigIssueKind::InvalidValue,
+                "update.feed_url",
+                format!("'{feed}' is not an http(s) or file URL"),
+                "remove it to use the official release feed",
+            ));
+        }
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+
+    /// Issue for a model the provider does not list. An empty list (the
+    /// provider could not be asked) is not an issue.
+    pub fn check_model(&self, available: &[String]) -> Option<ConfigIssue> {
+        if available.is_empty() || self.model.is_empty() || available.contains(&self.model) {
+            return None;
+        }
+        Some(ConfigIssue::new(
+            ConfigIssueKind::UnknownModel,
+            "model",
+            format!("provider does not offer '{}'", self.model),
+            "pick one of the provider's models",
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigIssueKind {
+    EmptyModel,
+    UnknownModel,
+    MissingApiKey,
+    MissingBaseUrl,
+    InvalidValue,
+}
+
+/// One problem found in the configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    pub kind: ConfigIssueKind,
+    /// Dotted path of the offending setting, e.g. `backup.keep`.
+    pub field: String,
+    pub problem: String,
+    pub suggestion: String,
+}
+
+impl ConfigIssue {
+    pub fn new(
+        kind: ConfigIssueKind,
+        field: impl Into<String>,
+        problem: impl Into<String>,
+        suggestion: impl Into<String>,
+    ) -> Self {
+        Self {
+            kind,
+            field: field.into(),
+            problem: problem.into(),
+            suggestion: suggestion.into(),
+        }
+    }
+
+    /// Whether the REPL can walk the user through fixing this.
+    pub fn is_fixable(&self) -> bool {
+        matches!(
+            self.kind,
+            ConfigIssueKind::EmptyModel
+                | ConfigIssueKind::UnknownModel
+                | ConfigIssueKind::MissingApiKey
+        )
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} ({})", self.field, self.problem, self.suggestion)
     }
 }
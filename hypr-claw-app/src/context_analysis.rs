@@ -0,0 +1,450 @@
+//! `context analyze`: what fills the context budget, with manual control
+//! over it instead of waiting for compaction.
+//!
+//! The analysis lists the oldest messages of the active thread, its biggest
+//! tool results and remembered facts that repeat one another, each under a
+//! number. `x<n>` prunes an item, `p<n>` pins a message so compaction keeps
+//! it verbatim (again to unpin), and `s<n>` replaces a message with a short
+//! digest of itself.
+
+use crate::context_providers::estimate_tokens;
+use hypr_claw_runtime::{Message, Origin, Provenance, Role};
+use serde_json::{json, Value};
+
+const OLDEST: usize = 5;
+const BIGGEST: usize = 5;
+const PREVIEW_CHARS: usize = 60;
+const DIGEST_CHARS: usize = 240;
+
+pub const ACTIONS_PROMPT: &str =
+    "Action [x<n> prune, p<n> pin/unpin, s<n> summarize, Enter to finish]: ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// Index into the thread history.
+    Message(usize),
+    /// Index into the remembered facts.
+    Fact(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    Oldest,
+    BigToolResult,
+    /// The fact repeats, or is part of, the fact at this index.
+    RedundantFact {
+        of: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    pub target: Target,
+    pub reason: Reason,
+    pub tokens: usize,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    pub history_tokens: usize,
+    pub facts_tokens: usize,
+    pub pinned: usize,
+    pub items: Vec<Item>,
+}
+
+fn message_tokens(message: &Message) -> usize {
+    estimate_tokens(&message.content.to_string())
+}
+
+fn is_pruned(message: &Message) -> bool {
+    message
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.get("pruned"))
+        .and_then(Value::as_bool)
+        == Some(true)
+}
+
+fn tool_name(message: &Message) -> Option<&str> {
+    message
+        .provenance
+        .as_ref()
+        .and_then(|provenance| provenance.tool_name.as_deref())
+        .or_else(|| message.metadata.as_ref()?.get("tool_name")?.as_str())
+}
+
+fn preview(text: &str) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match flat.char_indices().nth(PREVIEW_CHARS) {
+        Some((cut, _)) => format!("{}…", &flat[..cut]),
+        None => flat,
+    }
+}
+
+fn describe(message: &Message) -> String {
+    let who = match (&message.role, tool_name(message)) {
+        (Role::Tool, Some(tool)) => format!("{tool} result"),
+        (Role::Assistant, Some(tool)) => format!("call {tool}"),
+        (Role::User, _) => "user".to_string(),
+        (Role::Assistant, None) => "assistant".to_string(),
+        (Role::Tool, None) => "tool result".to_string(),
+        (Role::System, _) => "system".to_string(),
+    };
+    let text = match &message.content {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    let pin = if message.is_pinned() { "📌 " } else { "" };
+    format!("{pin}{who}: {}", preview(&text))
+}
+
+/// Lowercase words without punctuation, for comparing facts.
+fn normalize(fact: &str) -> String {
+    fact.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Facts that say nothing another fact does not: an older duplicate, or one
+/// contained in another. Paired with the fact that covers them.
+pub fn redundant_facts(facts: &[String]) -> Vec<(usize, usize)> {
+    let normalized: Vec<String> = facts.iter().map(|fact| normalize(fact)).collect();
+    let mut redundant = Vec::new();
+    for (i, fact) in normalized.iter().enumerate() {
+        if fact.is_empty() {
+            continue;
+        }
+        let covering = normalized.iter().enumerate().find(|(j, other)| {
+            *j != i
+                && if other.len() == fact.len() {
+                    *other == fact && *j > i
+                } else {
+                    other.len() > fact.len() && format!(" {other} ").contains(&format!(" {fact} "))
+                }
+        });
+        if let Some((j, _)) = covering {
+            redundant.push((i, j));
+        }
+    }
+    redundant
+}
+
+pub fn analyze(history: &[Message], facts: &[String]) -> Analysis {
+    let mut items: Vec<Item> = Vec::new();
+    let message_item = |index: usize, reason: Reason| Item {
+        target: Target::Message(index),
+        reason,
+        tokens: message_tokens(&history[index]),
+        label: describe(&history[index]),
+    };
+
+    let live: Vec<usize> = (0..history.len())
+        .filter(|&index| !is_pruned(&history[index]))
+        .collect();
+    for &index in live.iter().take(OLDEST) {
+        items.push(message_item(index, Reason::Oldest));
+    }
+    let mut results: Vec<usize> = live
+        .iter()
+        .copied()
+        .filter(|&index| history[index].role == Role::Tool)
+        .filter(|&index| {
+            !items
+                .iter()
+                .any(|item| item.target == Target::Message(index))
+        })
+        .collect();
+    results.sort_by_key(|&index| std::cmp::Reverse(message_tokens(&history[index])));
+    for index in results.into_iter().take(BIGGEST) {
+        items.push(message_item(index, Reason::BigToolResult));
+    }
+    for (index, of) in redundant_facts(facts) {
+        items.push(Item {
+            target: Target::Fact(index),
+            reason: Reason::RedundantFact { of },
+            tokens: estimate_tokens(&facts[index]),
+            label: format!(
+                "{} (covered by \"{}\")",
+                preview(&facts[index]),
+                preview(&facts[of])
+            ),
+        });
+    }
+
+    Analysis {
+        history_tokens: history.iter().map(message_tokens).sum(),
+        facts_tokens: facts.iter().map(|fact| estimate_tokens(fact)).sum(),
+        pinned: history.iter().filter(|message| message.is_pinned()).count(),
+        items,
+    }
+}
+
+impl Analysis {
+    /// Lines for the REPL, items numbered from 1 under their reason.
+    pub fn render(&self, threshold: usize) -> Vec<String> {
+        let mut lines = vec![format!(
+            "History ~{} tokens (compaction at {}), {} pinned; facts ~{} tokens",
+            self.history_tokens, threshold, self.pinned, self.facts_tokens
+        )];
+        let sections = [
+            ("Oldest messages:", Reason::Oldest),
+            ("Biggest tool results:", Reason::BigToolResult),
+        ];
+        for (title, reason) in sections {
+            let numbered = self.numbered(|item| item.reason == reason);
+            if !numbered.is_empty() {
+                lines.push(title.to_string());
+                lines.extend(numbered);
+            }
+        }
+        let facts = self.numbered(|item| matches!(item.reason, Reason::RedundantFact { .. }));
+        if !facts.is_empty() {
+            lines.push("Redundant facts:".to_string());
+            lines.extend(facts);
+        }
+        if self.items.is_empty() {
+            lines.push("Nothing to prune: the history and facts are empty.".to_string());
+        }
+        lines
+    }
+
+    fn numbered(&self, keep: impl Fn(&Item) -> bool) -> Vec<String> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| keep(item))
+            .map(|(index, item)| {
+                format!("  {:>2}. {:>6} tok  {}", index + 1, item.tokens, item.label)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Prune(usize),
+    Pin(usize),
+    Summarize(usize),
+}
+
+/// Parses `x3`, `p3` or `s3` against `items` listed items.
+pub fn parse_action(raw: &str, items: usize) -> Result<Action, String> {
+    let raw = raw.trim().to_ascii_lowercase();
+    let mut chars = raw.chars();
+    let key = chars.next().ok_or("Type an action")?;
+    let number: usize = chars
+        .as_str()
+        .trim()
+        .parse()
+        .map_err(|_| format!("Use x<n>, p<n> or s<n> with n from 1 to {items}"))?;
+    if number == 0 || number > items {
+        return Err(format!("No item {number}; items go from 1 to {items}"));
+    }
+    let index = number - 1;
+    match key {
+        'x' => Ok(Action::Prune(index)),
+        'p' => Ok(Action::Pin(index)),
+        's' => Ok(Action::Summarize(index)),
+        _ => Err(format!("Unknown action '{key}'; use x, p or s")),
+    }
+}
+
+/// Applies `action` to the item it names and says what changed.
+pub fn apply(
+    action: Action,
+    items: &[Item],
+    history: &mut [Message],
+    facts: &mut Vec<String>,
+) -> Result<String, String> {
+    let (Action::Prune(index) | Action::Pin(index) | Action::Summarize(index)) = action;
+    let item = items.get(index).ok_or("No such item")?;
+    match (action, item.target) {
+        (Action::Prune(_), Target::Fact(fact)) => {
+            let removed = facts.remove(fact);
+            Ok(format!("Forgot the fact \"{}\"", preview(&removed)))
+        }
+        (_, Target::Fact(_)) => Err("Facts can only be pruned".to_string()),
+        (Action::Prune(_), Target::Message(message)) => {
+            let message = &mut history[message];
+            let tokens = message_tokens(message);
+            message.content = match message.role {
+                Role::Tool => json!({"pruned": true, "tokens": tokens}),
+                _ => json!(format!("[pruned by the user, ~{tokens} tokens]")),
+            };
+            message.set_pinned(false);
+            if let Some(metadata) = message
+                .metadata
+                .get_or_insert_with(|| json!({}))
+                .as_object_mut()
+            {
+                metadata.insert("pruned".to_string(), Value::Bool(true));
+            }
+            Ok(format!("Pruned ~{tokens} tokens"))
+        }
+        (Action::Pin(_), Target::Message(message)) => {
+            let message = &mut history[message];
+            let pinned = !message.is_pinned();
+            message.set_pinned(pinned);
+            Ok(if pinned {
+                "Pinned; compaction keeps it verbatim".to_string()
+            } else {
+                "Unpinned".to_string()
+            })
+        }
+        (Action::Summarize(_), Target::Message(message)) => {
+            let message = &mut history[message];
+            let before = message_tokens(message);
+            let summary = digest(&message.content);
+            message.content = match message.role {
+                Role::Tool => json!({"summary": summary}),
+                _ => json!(summary),
+            };
+            let provenance = message
+                .provenance
+                .take()
+                .unwrap_or_else(|| Provenance::new(Origin::User));
+            message.provenance = Some(provenance.summarized(true));
+            Ok(format!(
+                "Summarized ~{before} tokens to ~{}",
+                message_tokens(message)
+            ))
+        }
+    }
+}
+
+/// Fields and opening text of `content`, for a summarized message.
+fn digest(content: &Value) -> String {
+    let parsed = match content {
+        Value::String(text) => serde_json::from_str(text).unwrap_or(Value::String(text.clone())),
+        other => other.clone(),
+    };
+    let fields = match &parsed {
+        Value::Object(map) => format!(
+            "fields: {}. ",
+            map.keys().cloned().collect::<Vec<_>>().join(", ")
+        ),
+        _ => String::new(),
+    };
+    let text = match &parsed {
+        Value::String(text) => text.clone(),
+        Value::Object(map) => map
+            .values()
+            .map(|value| match value {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        other => other.to_string(),
+    };
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let opening = match flat.char_indices().nth(DIGEST_CHARS) {
+        Some((cut, _)) => format!("{}…", &flat[..cut]),
+        None => flat,
+    };
+    format!("[summarized by the user] {fields}{opening}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history() -> Vec<Message> {
+        vec![
+            Message::new(Role::User, json!("list my downloads")),
+            Message::with_metadata(
+                Role::Tool,
+                json!({"entries": vec!["file.pdf"; 200]}),
+                json!({"tool_name": "fs.list"}),
+            ),
+            Message::new(Role::Assistant, json!("You have 200 files.")),
+            Message::new(Role::User, json!("and the screen?")),
+            Message::with_metadata(
+                Role::Tool,
+                json!({"text": "x".repeat(300)}),
+                json!({"tool_name": "desktop.ocr_screen"}),
+            ),
+            Message::new(Role::Assistant, json!("The screen shows a terminal.")),
+            Message::with_metadata(
+                Role::Tool,
+                json!({"text": "y".repeat(900)}),
+                json!({"tool_name": "desktop.ocr_screen"}),
+            ),
+        ]
+    }
+
+    #[test]
+    fn redundant_facts_are_covered_by_another() {
+        let facts = [
+            "Prefers dark mode".to_string(),
+            "editor: helix".to_string(),
+            "prefers dark mode.".to_string(),
+            "Lives in Lisbon".to_string(),
+            "The user lives in Lisbon, Portugal".to_string(),
+        ];
+        assert_eq!(redundant_facts(&facts), vec![(0, 2), (3, 4)]);
+    }
+
+    #[test]
+    fn the_analysis_lists_old_messages_big_results_and_redundant_facts() {
+        let facts = vec!["uses kitty".to_string(), "Uses kitty".to_string()];
+        let analysis = analyze(&history(), &facts);
+        let targets: Vec<Target> = analysis.items.iter().map(|item| item.target).collect();
+        assert_eq!(
+            targets,
+            vec![
+                Target::Message(0),
+                Target::Message(1),
+                Target::Message(2),
+                Target::Message(3),
+                Target::Message(4),
+                Target::Message(6),
+                Target::Fact(0),
+            ]
+        );
+        assert_eq!(
+            analysis.items[1].label.split(':').next(),
+            Some("fs.list result")
+        );
+        let lines = analysis.render(4000);
+        assert!(lines[0].contains("(compaction at 4000), 0 pinned"));
+        assert!(lines
+            .iter()
+            .any(|line| line.ends_with("uses kitty (covered by \"Uses kitty\")")));
+    }
+
+    #[test]
+    fn actions_prune_pin_and_summarize() {
+        let mut history = history();
+        let mut facts = vec!["uses kitty".to_string(), "Uses kitty".to_string()];
+        let items = analyze(&history, &facts).items;
+        assert_eq!(parse_action("s6", items.len()), Ok(Action::Summarize(5)));
+        assert!(parse_action("s8", items.len()).is_err());
+        assert!(parse_action("q1", items.len()).is_err());
+        assert!(parse_action("x", items.len()).is_err());
+
+        apply(Action::Summarize(5), &items, &mut history, &mut facts).unwrap();
+        let summary = history[6].content["summary"].as_str().unwrap();
+        assert!(summary.starts_with("[summarized by the user] fields: text. yyy"));
+        assert!(history[6].provenance.as_ref().unwrap().summarized);
+        assert!(message_tokens(&history[6]) < 100);
+
+        apply(Action::Pin(1), &items, &mut history, &mut facts).unwrap();
+        assert!(history[1].is_pinned());
+        assert_eq!(analyze(&history, &facts).pinned, 1);
+        apply(Action::Prune(1), &items, &mut history, &mut facts).unwrap();
+        assert!(!history[1].is_pinned());
+        assert_eq!(history[1].content["pruned"], json!(true));
+        assert!(!analyze(&history, &facts)
+            .items
+            .iter()
+            .any(|item| item.target == Target::Message(1)));
+
+        assert!(apply(Action::Pin(6), &items, &mut history, &mut facts).is_err());
+        apply(Action::Prune(6), &items, &mut history, &mut facts).unwrap();
+        assert_eq!(facts, vec!["Uses kitty"]);
+    }
+}
@@ -0,0 +1,731 @@
+//! Sources of the per-turn "Runtime context" block of the system prompt.
+//!
+//! Each [`ContextProvider`] contributes `- key: value` lines and gets a
+//! token budget; lines past the budget are dropped so one noisy source
+//! cannot crowd out the rest. Providers can be disabled or given another
+//! budget under `context.providers` in the config:
+//!
+//! ```yaml
+//! context:
+//!   providers:
+//!     battery: { enabled: false }
+//!     facts: { max_tokens: 100 }
+//! ```
+//!
+//! The block is rendered when a run starts. [`LiveContextRefresher`] reads
+//! the parts that change under a long run again when the runtime finds the
+//! context stale, see [`hypr_claw_runtime::context_refresh`].
+
+use crate::config::ContextConfig;
+use async_trait::async_trait;
+use hypr_claw_runtime::{ContextRefresher, RefreshReason};
+use hypr_claw_tools::os_capabilities::{desktop, hyprland};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Everything providers may draw on for one turn.
+pub struct TurnContext<'a> {
+    pub profile: &'a Value,
+    pub capability_registry: &'a Value,
+    pub allowed_tools: &'a HashSet<String>,
+    pub autonomy_mode: &'a str,
+    pub workflow_mode: &'a str,
+    /// Remembered facts about the user, oldest first.
+    pub facts: &'a [String],
+    pub running_tasks: &'a [hypr_claw_tasks::TaskInfo],
+    pub working_dir: Option<PathBuf>,
+    pub now: chrono::DateTime<chrono::FixedOffset>,
+}
+
+pub trait ContextProvider: Send + Sync {
+    /// Key under `context.providers`.
+    fn name(&self) -> &'static str;
+
+    /// Tokens the provider may use unless the config says otherwise.
+    fn default_budget(&self) -> usize;
+
+    /// Lines without the leading `- `, most important first.
+    fn lines(&self, ctx: &TurnContext) -> Vec<String>;
+}
+
+/// Names of the built-in providers, in prompt order.
+pub const PROVIDER_NAMES: &[&str] = &[
+    "capabilities",
+    "project",
+    "time",
+    "battery",
+    "running_tasks",
+    "facts",
+];
+
+pub fn builtin_providers() -> Vec<Box<dyn ContextProvider>> {
+    vec![
+        Box::new(CapabilitiesProvider),
+        Box::new(ProjectProvider),
+        Box::new(TimeProvider),
+        Box::new(BatteryProvider::new("/sys/class/power_supply")),
+        Box::new(RunningTasksProvider),
+        Box::new(FactsProvider),
+    ]
+}
+
+/// Rough token count, good enough for budgeting prompt text.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+#[derive(Default)]
+pub struct ContextRegistry {
+    providers: Vec<(Box<dyn ContextProvider>, usize)>,
+}
+
+impl ContextRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The built-in providers the config leaves enabled, with its budgets.
+    pub fn from_config(config: &ContextConfig) -> Self {
+        let mut registry = Self::new();
+        for provider in builtin_providers() {
+            let settings = config.providers.get(provider.name());
+            if settings.is_some_and(|s| !s.enabled) {
+                continue;
+            }
+            let budget = settings
+                .and_then(|s| s.max_tokens)
+                .unwrap_or_else(|| provider.default_budget());
+            registry.register(provider, budget);
+        }
+        registry
+    }
+
+    pub fn register(&mut self, provider: Box<dyn ContextProvider>, budget: usize) {
+        self.providers.push((provider, budget));
+    }
+
+    /// Enabled providers and their budgets, in prompt order.
+    pub fn budgets(&self) -> Vec<(&'static str, usize)> {
+        self.providers
+            .iter()
+            .map(|(provider, budget)| (provider.name(), *budget))
+            .collect()
+    }
+
+    /// The `- key: value` lines of every provider, each cut to its budget.
+    pub fn render(&self, ctx: &TurnContext) -> String {
+        self.render_sections(ctx)
+            .into_iter()
+            .map(|(_, text)| text)
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// [`Self::render`] split by provider, for `prompt inspect`.
+    pub fn render_sections(&self, ctx: &TurnContext) -> Vec<(&'static str, String)> {
+        let mut sections = Vec::new();
+        for (provider, budget) in &self.providers {
+            let lines = provider.lines(ctx);
+            let mut out = Vec::new();
+            let mut used = 0;
+            for (i, line) in lines.iter().enumerate() {
+                let line = format!("- {line}");
+                let cost = estimate_tokens(&line);
+                if used + cost > *budget {
+                    out.push(format!(
+                        "- ({} more {} lines omitted)",
+                        lines.len() - i,
+                        provider.name()
+                    ));
+                    break;
+                }
+                used += cost;
+                out.push(line);
+            }
+            sections.push((provider.name(), out.join("\n")));
+        }
+        sections
+    }
+}
+
+/// Providers whose lines change while a run goes on.
+const LIVE_PROVIDERS: &[&str] = &["time", "battery"];
+/// Windows listed in a refresh; the rest are counted.
+const MAX_REFRESH_WINDOWS: usize = 20;
+
+/// Reads the workspace, windows, time and battery again for a run whose
+/// context went stale. When a tool found the desktop changed, only the
+/// Hyprland state is read.
+pub struct LiveContextRefresher {
+    providers: ContextRegistry,
+    hyprland: bool,
+}
+
+impl LiveContextRefresher {
+    pub fn new(config: &ContextConfig, hyprland: bool) -> Self {
+        let mut providers = ContextRegistry::from_config(config);
+        providers
+            .providers
+            .retain(|(provider, _)| LIVE_PROVIDERS.contains(&provider.name()));
+        Self {
+            providers,
+            hyprland,
+        }
+    }
+}
+
+#[async_trait]
+impl ContextRefresher for LiveContextRefresher {
+    async fn refresh(&self, _session_key: &str, reason: &RefreshReason) -> Option<String> {
+        let mut sections = Vec::new();
+        if matches!(reason, RefreshReason::Stale { .. }) {
+            let (empty, tools) = (Value::Null, HashSet::new());
+            let ctx = TurnContext {
+                profile: &empty,
+                capability_registry: &empty,
+                allowed_tools: &tools,
+                autonomy_mode: "",
+                workflow_mode: "",
+                facts: &[],
+                running_tasks: &[],
+                working_dir: None,
+                now: chrono::Local::now().fixed_offset(),
+            };
+            sections.push(self.providers.render(&ctx));
+        }
+        if self.hyprland {
+            let workspace = hyprland::get_active_workspace().await.ok();
+            let active = desktop::active_window().await.ok();
+            let windows = desktop::list_windows(0).await.ok();
+            sections.push(window_lines(workspace, active.as_ref(), windows.as_deref()).join("\n"));
+        }
+        sections.retain(|section| !section.is_empty());
+        (!sections.is_empty()).then(|| sections.join("\n"))
+    }
+}
+
+/// `- key: value` lines for the active workspace and window and the open
+/// windows, from `hyprctl` JSON; parts `hyprctl` could not give are left
+/// out.
+fn window_lines(
+    workspace: Option<u32>,
+    active: Option<&Value>,
+    windows: Option<&[Value]>,
+) -> Vec<String> {
+    let describe = |window: &Value| {
+        let field = |name: &str| window.get(name).and_then(Value::as_str).unwrap_or_default();
+        format!(
+            "{} {} \"{}\"",
+            field("address"),
+            field("class"),
+            field("title")
+        )
+    };
+    let mut lines = Vec::new();
+    if let Some(workspace) = workspace {
+        lines.push(format!("- active_workspace: {workspace}"));
+    }
+    if let Some(active) = active.filter(|window| window.get("address").is_some()) {
+        lines.push(format!("- active_window: {}", describe(active)));
+    }
+    let Some(windows) = windows else {
+        return lines;
+    };
+    for window in windows.iter().take(MAX_REFRESH_WINDOWS) {
+        let workspace = window
+            .pointer("/workspace/name")
+            .and_then(Value::as_str)
+            .unwrap_or("?");
+        lines.push(format!(
+            "- window: {} (workspace {workspace})",
+            describe(window)
+        ));
+    }
+    if windows.len() > MAX_REFRESH_WINDOWS {
+        lines.push(format!(
+            "- ({} more windows omitted)",
+            windows.len() - MAX_REFRESH_WINDOWS
+        ));
+    }
+    if windows.is_empty() {
+        lines.push("- windows: none".to_string());
+    }
+    lines
+}
+
+fn read_string_array(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// A list from the capability registry, falling back to the profile.
+fn registry_list(ctx: &TurnContext, primary_path: &str, fallback_path: &str) -> String {
+    let primary = read_string_array(ctx.capability_registry.pointer(primary_path));
+    if !primary.is_empty() {
+        return primary.join(", ");
+    }
+    let fallback = read_string_array(ctx.profile.pointer(fallback_path));
+    if fallback.is_empty() {
+        "none".to_string()
+    } else {
+        fallback.join(", ")
+    }
+}
+
+/// Platform facts and backends from the capability registry, plus the tools
+/// allowed this turn.
+struct CapabilitiesProvider;
+
+impl ContextProvider for CapabilitiesProvider {
+    fn name(&self) -> &'static str {
+        "capabilities"
+    }
+
+    fn default_budget(&self) -> usize {
+        2000
+    }
+
+    fn lines(&self, ctx: &TurnContext) -> Vec<String> {
+        let registry_str = |path: &str| -> Option<String> {
+            ctx.capability_registry
+                .pointer(path)
+                .or_else(|| ctx.profile.pointer(path))
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        };
+        let workspace = ctx
+            .capability_registry
+            .pointer("/platform/active_workspace")
+            .and_then(|v| v.as_u64())
+            .or_else(|| {
+                ctx.profile
+                    .pointer("/desktop/active_workspace")
+                    .and_then(|v| v.as_u64())
+            })
+            .unwrap_or(0);
+        let preferred_launchers = ctx
+            .capability_registry
+            .pointer("/desktop_apps/preferred_launchers")
+            .and_then(|v| v.as_object())
+            .map(|obj| {
+                obj.iter()
+                    .filter_map(|(k, v)| v.as_str().map(|value| format!("{k}={value}")))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            })
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "none".to_string());
+        let generated_at = ctx
+            .capability_registry
+            .pointer("/generated_at")
+            .and_then(|v| v.as_i64())
+            .and_then(|ts| chrono::DateTime::<chrono::Utc>::from_timestamp(ts, 0))
+            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+            .unwrap_or_else(|| "n/a".to_string());
+        let installed_packages = ctx
+            .capability_registry
+            .pointer("/packages/total_count")
+            .and_then(|v| v.as_u64())
+            .or_else(|| {
+                ctx.profile
+                    .pointer("/deep_scan/packages/total_count")
+                    .and_then(|v| v.as_u64())
+            })
+            .unwrap_or(0);
+        let mut tools = ctx.allowed_tools.iter().cloned().collect::<Vec<String>>();
+        tools.sort();
+
+        vec![
+            format!("autonomy_mode: {}", ctx.autonomy_mode),
+            format!("workflow_mode: {}", ctx.workflow_mode),
+            format!("capability_registry_generated_at: {generated_at}"),
+            format!(
+                "distro: {}",
+                registry_str("/platform/distro_name").unwrap_or_else(|| "unknown".to_string())
+            ),
+            format!(
+                "kernel: {}",
+                registry_str("/platform/kernel").unwrap_or_else(|| "unknown".to_string())
+            ),
+            format!("active_workspace: {workspace}"),
+            format!(
+                "wallpaper_backends: {}",
+                registry_list(
+                    ctx,
+                    "/capabilities/wallpaper_backends",
+                    "/capabilities/wallpaper_backends"
+                )
+            ),
+            format!(
+                "screenshot_backends: {}",
+                registry_list(
+                    ctx,
+                    "/capabilities/screenshot_backends",
+                    "/capabilities/screenshot_backends"
+                )
+            ),
+            format!(
+                "input_backends: {}",
+                registry_list(
+                    ctx,
+                    "/capabilities/input_backends",
+                    "/capabilities/input_backends"
+                )
+            ),
+            format!(
+                "vscode_hint: {}",
+                registry_str("/editor/vscode_command").unwrap_or_else(|| "none".to_string())
+            ),
+            format!(
+                "launcher_commands: {}",
+                registry_list(
+                    ctx,
+                    "/desktop_apps/launcher_commands",
+                    "/deep_scan/desktop_apps/launcher_commands_sample"
+                )
+            ),
+            format!("preferred_launchers: {preferred_launchers}"),
+            format!(
+                "known_downloads_dir: {}",
+                registry_str("/paths/downloads").unwrap_or_default()
+            ),
+            format!("installed_packages: {installed_packages}"),
+            format!("allowed_tools_now: {}", tools.join(", ")),
+        ]
+    }
+}
+
+/// Build files that identify a project's kind.
+const PROJECT_MARKERS: &[(&str, &str)] = &[
+    ("Cargo.toml", "rust"),
+    ("package.json", "node"),
+    ("pyproject.toml", "python"),
+    ("go.mod", "go"),
+    (".git", "git"),
+];
+
+/// The working directory, what kind of project it is, and known projects.
+struct ProjectProvider;
+
+impl ContextProvider for ProjectProvider {
+    fn name(&self) -> &'static str {
+        "project"
+    }
+
+    fn default_budget(&self) -> usize {
+        300
+    }
+
+    fn lines(&self, ctx: &TurnContext) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(dir) = &ctx.working_dir {
+            let kinds = PROJECT_MARKERS
+                .iter()
+                .filter(|(marker, _)| dir.join(marker).exists())
+                .map(|(_, kind)| *kind)
+                .collect::<Vec<_>>();
+            if kinds.is_empty() {
+                lines.push(format!("working_dir: {}", dir.display()));
+            } else {
+                lines.push(format!(
+                    "working_dir: {} ({})",
+                    dir.display(),
+                    kinds.join(", ")
+                ));
+            }
+        }
+        lines.push(format!(
+            "known_projects: {}",
+            registry_list(
+                ctx,
+                "/projects/known_roots",
+                "/deep_scan/home_inventory/project_roots"
+            )
+        ));
+        lines
+    }
+}
+
+struct TimeProvider;
+
+impl ContextProvider for TimeProvider {
+    fn name(&self) -> &'static str {
+        "time"
+    }
+
+    fn default_budget(&self) -> usize {
+        30
+    }
+
+    fn lines(&self, ctx: &TurnContext) -> Vec<String> {
+        vec![format!(
+            "local_time: {}",
+            ctx.now.format("%Y-%m-%d %H:%M %a (UTC%:z)")
+        )]
+    }
+}
+
+/// Charge of the batteries under a `power_supply` sysfs directory.
+struct BatteryProvider {
+    power_supply_dir: PathBuf,
+}
+
+impl BatteryProvider {
+    fn new(power_supply_dir: impl AsRef<Path>) -> Self {
+        Self {
+            power_supply_dir: power_supply_dir.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl ContextProvider for BatteryProvider {
+    fn name(&self) -> &'static str {
+        "battery"
+    }
+
+    fn default_budget(&self) -> usize {
+        40
+    }
+
+    fn lines(&self, _ctx: &TurnContext) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(&self.power_supply_dir) else {
+            return Vec::new();
+        };
+        let read = |dir: &Path, file: &str| {
+            std::fs::read_to_string(dir.join(file))
+                .ok()
+                .map(|s| s.trim().to_string())
+        };
+        let mut batteries = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|dir| read(dir, "type").as_deref() == Some("Battery"))
+            .filter_map(|dir| {
+                let capacity = read(&dir, "capacity")?;
+                let status = read(&dir, "status").unwrap_or_else(|| "Unknown".to_string());
+                Some(format!("{capacity}% {}", status.to_lowercase()))
+            })
+            .collect::<Vec<_>>();
+        batteries.sort();
+        if batteries.is_empty() {
+            return Vec::new();
+        }
+        vec![format!("battery: {}", batteries.join(", "))]
+    }
+}
+
+/// Background tasks still running, so the model does not start them twice.
+struct RunningTasksProvider;
+
+impl ContextProvider for RunningTasksProvider {
+    fn name(&self) -> &'static str {
+        "running_tasks"
+    }
+
+    fn default_budget(&self) -> usize {
+        200
+    }
+
+    fn lines(&self, ctx: &TurnContext) -> Vec<String> {
+        ctx.running_tasks
+            .iter()
+            .filter(|task| task.status == hypr_claw_tasks::TaskStatus::Running)
+            .map(|task| {
+                format!(
+                    "running_task: {} {} ({:.0}%)",
+                    task.id,
+                    task.description,
+                    task.progress * 100.0
+                )
+            })
+            .collect()
+    }
+}
+
+/// Remembered facts, newest first so the budget keeps the freshest.
+struct FactsProvider;
+
+impl ContextProvider for FactsProvider {
+    fn name(&self) -> &'static str {
+        "facts"
+    }
+
+    fn default_budget(&self) -> usize {
+        200
+    }
+
+    fn lines(&self, ctx: &TurnContext) -> Vec<String> {
+        ctx.facts
+            .iter()
+            .rev()
+            .map(|fact| format!("fact: {fact}"))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ContextProviderConfig;
+    use serde_json::json;
+
+    fn turn<'a>(
+        facts: &'a [String],
+        tasks: &'a [hypr_claw_tasks::TaskInfo],
+        empty: &'a Value,
+        tools: &'a HashSet<String>,
+    ) -> TurnContext<'a> {
+        TurnContext {
+            profile: empty,
+            capability_registry: empty,
+            allowed_tools: tools,
+            autonomy_mode: "guarded",
+            workflow_mode: "legacy",
+            facts,
+            running_tasks: tasks,
+            working_dir: None,
+            now: chrono::DateTime::parse_from_rfc3339("2026-03-01T09:30:00+02:00").unwrap(),
+        }
+    }
+
+    #[test]
+    fn config_disables_providers_and_overrides_budgets() {
+        let mut config = ContextConfig::default();
+        config.providers.insert(
+            "battery".to_string(),
+            ContextProviderConfig {
+                enabled: false,
+                max_tokens: None,
+            },
+        );
+        config.providers.insert(
+            "facts".to_string(),
+            ContextProviderConfig {
+                enabled: true,
+                max_tokens: Some(12),
+            },
+        );
+        let budgets = ContextRegistry::from_config(&config).budgets();
+        let names = budgets.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec!["capabilities", "project", "time", "running_tasks", "facts"]
+        );
+        assert_eq!(budgets.last(), Some(&("facts", 12)));
+        assert_eq!(
+            builtin_providers()
+                .iter()
+                .map(|p| p.name())
+                .collect::<Vec<_>>(),
+            PROVIDER_NAMES
+        );
+    }
+
+    #[test]
+    fn lines_past_a_budget_are_dropped() {
+        let facts = ["likes tea".to_string(), "preferred_name:Ana".to_string()];
+        let (empty, tools) = (json!({}), HashSet::new());
+        let ctx = turn(&facts, &[], &empty, &tools);
+        let mut registry = ContextRegistry::new();
+        registry.register(Box::new(TimeProvider), 30);
+        registry.register(Box::new(FactsProvider), 8);
+        assert_eq!(
+            registry.render(&ctx),
+            "- local_time: 2026-03-01 09:30 Sun (UTC+02:00)\n\
+             - fact: preferred_name:Ana\n\
+             - (1 more facts lines omitted)"
+        );
+    }
+
+    #[test]
+    fn running_tasks_and_batteries_are_listed() {
+        let task = |id: &str, status| hypr_claw_tasks::TaskInfo {
+            id: id.to_string(),
+            description: "sync photos".to_string(),
+            status,
+            progress: 0.5,
+            created_at: 0,
+            updated_at: 0,
+            result: None,
+            error: None,
+        };
+        let tasks = [
+            task("bg-1", hypr_claw_tasks::TaskStatus::Running),
+            task("bg-2", hypr_claw_tasks::TaskStatus::Completed),
+        ];
+        let (empty, tools) = (json!({}), HashSet::new());
+        let ctx = turn(&[], &tasks, &empty, &tools);
+        assert_eq!(
+            RunningTasksProvider.lines(&ctx),
+            vec!["running_task: bg-1 sync photos (50%)"]
+        );
+
+        let dir =
+            std::env::temp_dir().join(format!("hypr-claw-power-supply-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (name, kind, capacity) in [("BAT0", "Battery", "81"), ("AC", "Mains", "")] {
+            let supply = dir.join(name);
+            std::fs::create_dir_all(&supply).unwrap();
+            std::fs::write(supply.join("type"), format!("{kind}\n")).unwrap();
+            std::fs::write(supply.join("capacity"), capacity).unwrap();
+            std::fs::write(supply.join("status"), "Discharging\n").unwrap();
+        }
+        assert_eq!(
+            BatteryProvider::new(&dir).lines(&ctx),
+            vec!["battery: 81% discharging"]
+        );
+        assert!(BatteryProvider::new(dir.join("missing"))
+            .lines(&ctx)
+            .is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn live_refresh_rereads_what_changes_during_a_run() {
+        let refresher = LiveContextRefresher::new(&ContextConfig::default(), false);
+        assert_eq!(
+            refresher.providers.budgets(),
+            vec![("time", 30), ("battery", 40)]
+        );
+        let diverged = RefreshReason::Diverged {
+            tool: "desktop.focus_window".to_string(),
+            error: "Not found: window 'firefox'".to_string(),
+        };
+        assert_eq!(refresher.refresh("s", &diverged).await, None);
+        let stale = RefreshReason::Stale {
+            age: std::time::Duration::from_secs(300),
+        };
+        let refreshed = refresher.refresh("s", &stale).await.unwrap();
+        assert!(refreshed.starts_with("- local_time: "));
+
+        let windows = [
+            json!({"address": "0x1", "class": "firefox", "title": "Docs",
+                   "workspace": {"name": "2"}}),
+            json!({"address": "0x2", "class": "kitty", "title": "~",
+                   "workspace": {"name": "special:claw-t1"}}),
+        ];
+        assert_eq!(
+            window_lines(Some(2), Some(&windows[0]), Some(&windows)),
+            vec![
+                "- active_workspace: 2",
+                "- active_window: 0x1 firefox \"Docs\"",
+                "- window: 0x1 firefox \"Docs\" (workspace 2)",
+                "- window: 0x2 kitty \"~\" (workspace special:claw-t1)",
+            ]
+        );
+        assert_eq!(
+            window_lines(None, Some(&json!({})), Some(&[])),
+            vec!["- windows: none"]
+        );
+        assert!(window_lines(None, None, None).is_empty());
+    }
+}
@@ -0,0 +1,263 @@
+//! Crash reports written by the panic hook.
+//!
+//! The hook saves the panic message, a backtrace, the most recent action
+//! events and the runs in progress to `./data/crashes/crash-<time>.json`.
+//! The next start lists reports it has not shown yet and marks tasks that
+//! were running at the time as interrupted by the crash.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+pub const CRASH_DIR: &str = "./data/crashes";
+
+/// Events kept for the next report.
+const MAX_EVENTS: usize = 50;
+
+/// Name of the file holding the newest report already shown at startup.
+const SURFACED_MARKER: &str = ".surfaced";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ActiveRun {
+    pub run_id: u64,
+    pub request: String,
+    pub started_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CrashReport {
+    pub at: i64,
+    pub version: String,
+    pub thread: String,
+    pub message: String,
+    pub location: Option<String>,
+    pub backtrace: String,
+    pub model: Option<String>,
+    /// Runs in progress, by session key.
+    pub active_runs: BTreeMap<String, ActiveRun>,
+    /// Most recent action events, oldest first.
+    pub events: Vec<String>,
+}
+
+#[derive(Default)]
+struct CrashContext {
+    model: Option<String>,
+    active_runs: BTreeMap<String, ActiveRun>,
+    events: VecDeque<String>,
+}
+
+fn context() -> &'static Mutex<CrashContext> {
+    static CONTEXT: OnceLock<Mutex<CrashContext>> = OnceLock::new();
+    CONTEXT.get_or_init(|| Mutex::new(CrashContext::default()))
+}
+
+fn with_context(f: impl FnOnce(&mut CrashContext)) {
+    if let Ok(mut ctx) = context().lock() {
+        f(&mut ctx);
+    }
+}
+
+pub fn set_model(model: &str) {
+    with_context(|ctx| ctx.model = Some(model.to_string()));
+}
+
+pub fn begin_run(session_key: &str, run_id: u64, request: &str) {
+    with_context(|ctx| {
+        ctx.active_runs.insert(
+            session_key.to_string(),
+            ActiveRun {
+                run_id,
+                request: request.to_string(),
+                started_at: chrono::Utc::now().timestamp(),
+            },
+        );
+    });
+}
+
+pub fn end_run(session_key: &str) {
+    with_context(|ctx| {
+        ctx.active_runs.remove(session_key);
+    });
+}
+
+pub fn record_event(event: &str) {
+    with_context(|ctx| {
+        if ctx.events.len() == MAX_EVENTS {
+            ctx.events.pop_front();
+        }
+        ctx.events.push_back(event.to_string());
+    });
+}
+
+fn panic_message(info: &std::panic::PanicHookInfo<'_>) -> String {
+    let payload = info.payload();
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "non-string panic payload".to_string())
+}
+
+/// Report with a backtrace of the calling thread and whatever context is not
+/// locked by the failing code itself.
+fn build_report(message: String, location: Option<String>) -> CrashReport {
+    let mut report = CrashReport {
+        at: chrono::Utc::now().timestamp(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        thread: std::thread::current()
+            .name()
+            .unwrap_or("unnamed")
+            .to_string(),
+        message,
+        location,
+        backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+        model: None,
+        active_runs: BTreeMap::new(),
+        events: Vec::new(),
+    };
+    // try_lock: the panic may have happened while the context was held.
+    if let Ok(ctx) = context().try_lock() {
+        report.model = ctx.model.clone();
+        report.active_runs = ctx.active_runs.clone();
+        report.events = ctx.events.iter().cloned().collect();
+    }
+    report
+}
+
+pub fn write_report(dir: &Path, report: &CrashReport) -> io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("crash-{}-{}.json", report.at, std::process::id()));
+    let json = serde_json::to_string_pretty(report).map_err(io::Error::other)?;
+    std::fs::write(&path, json)?;
+    Ok(path)
+}
+
+/// Writes a report to [`CRASH_DIR`] for every panic, then runs the previous
+/// hook (the default one prints the message to stderr).
+pub fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+        let report = build_report(panic_message(info), location);
+        previous(info);
+        match write_report(&crate::data_dir::path(CRASH_DIR), &report) {
+            Ok(path) => eprintln!("💥 Crash report saved to {}", path.display()),
+            Err(e) => eprintln!("⚠️  Failed to save crash report: {}", e),
+        }
+    }));
+}
+
+/// Writes a report for a failure that is not a panic, such as the runtime
+/// wedging (see [`crate::watchdog`]).
+pub fn report_now(message: &str) -> io::Result<PathBuf> {
+    write_report(
+        &crate::data_dir::path(CRASH_DIR),
+        &build_report(message.to_string(), None),
+    )
+}
+
+/// Reports newer than the last one shown, oldest first.
+pub fn unsurfaced_reports(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let marker = std::fs::read_to_string(dir.join(SURFACED_MARKER)).unwrap_or_default();
+    let marker = marker.trim();
+    let mut reports: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with("crash-") && n.ends_with(".json") && n > marker)
+            })
+            .collect(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    reports.sort();
+    Ok(reports)
+}
+
+/// Remembers `report` as shown, so the next start skips it and older ones.
+pub fn mark_surfaced(dir: &Path, report: &Path) -> io::Result<()> {
+    let name = report
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    std::fs::write(dir.join(SURFACED_MARKER), name)
+}
+
+pub fn load_report(path: &Path) -> io::Result<CrashReport> {
+    let content = std::fs::read_to_string(path)?;
+    serde_json::from_str(&content).map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(at: i64) -> CrashReport {
+        CrashReport {
+            at,
+            version: "0.1.0".to_string(),
+            thread: "main".to_string(),
+            message: "index out of bounds".to_string(),
+            location: Some("src/main.rs:1:1".to_string()),
+            backtrace: String::new(),
+            model: Some("m".to_string()),
+            active_runs: BTreeMap::from([(
+                "agent:user".to_string(),
+                ActiveRun {
+                    run_id: 7,
+                    request: "open kitty".to_string(),
+                    started_at: at,
+                },
+            )]),
+            events: vec!["{\"kind\":\"start\"}".to_string()],
+        }
+    }
+
+    #[test]
+    fn reports_are_surfaced_once() {
+        let dir = std::env::temp_dir().join(format!("hypr-claw-crashes-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(unsurfaced_reports(&dir).unwrap().is_empty());
+
+        let first = write_report(&dir, &report(1_700_000_000)).unwrap();
+        let second = write_report(&dir, &report(1_700_000_100)).unwrap();
+        assert_eq!(
+            unsurfaced_reports(&dir).unwrap(),
+            vec![first.clone(), second.clone()]
+        );
+        assert_eq!(load_report(&first).unwrap(), report(1_700_000_000));
+
+        mark_surfaced(&dir, &second).unwrap();
+        assert!(unsurfaced_reports(&dir).unwrap().is_empty());
+        let third = write_report(&dir, &report(1_700_000_200)).unwrap();
+        assert_eq!(unsurfaced_reports(&dir).unwrap(), vec![third]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn context_keeps_the_latest_events_and_open_runs() {
+        for i in 0..(MAX_EVENTS + 5) {
+            record_event(&format!("event {i}"));
+        }
+        begin_run("agent:crash-test", 3, "take a screenshot");
+        let ctx = context().lock().unwrap();
+        assert_eq!(ctx.events.len(), MAX_EVENTS);
+        // Other tests may log actions concurrently, so only check membership.
+        assert!(ctx.events.contains(&format!("event {}", MAX_EVENTS + 4)));
+        assert!(!ctx.events.contains(&"event 0".to_string()));
+        assert_eq!(ctx.active_runs["agent:crash-test"].run_id, 3);
+        drop(ctx);
+        end_run("agent:crash-test");
+        assert!(!context()
+            .lock()
+            .unwrap()
+            .active_runs
+            .contains_key("agent:crash-test"));
+    }
+}
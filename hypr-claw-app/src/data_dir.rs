@@ -0,0 +1,36 @@
+//! Where `./data` is.
+//!
+//! Data paths are spelled relative to the directory hypr-claw starts in, but
+//! `cd` changes the process working directory. Every one goes through
+//! [`path`], which resolves it against the directory pinned at startup.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+static BASE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Pins data paths to the current working directory. Call before anything
+/// can change directory; later calls and `cd` leave it where it is.
+pub fn pin() -> &'static Path {
+    BASE.get_or_init(|| std::env::current_dir().unwrap_or_default())
+}
+
+/// `relative` (such as `./data/audit.log`) under the pinned directory.
+/// Absolute paths are returned as given.
+pub fn path(relative: impl AsRef<Path>) -> PathBuf {
+    let relative = relative.as_ref();
+    pin().join(relative.strip_prefix(".").unwrap_or(relative))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_paths_stay_at_the_pinned_directory() {
+        let base = pin().to_path_buf();
+        assert_eq!(path("./data/audit.log"), base.join("data/audit.log"));
+        assert_eq!(path("data"), base.join("data"));
+        assert_eq!(path("/var/lib/x"), PathBuf::from("/var/lib/x"));
+    }
+}
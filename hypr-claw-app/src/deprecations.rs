@@ -0,0 +1,291 @@
+//! Notices for removed and renamed REPL commands, and for commands typed
+//! with a typo.
+//!
+//! Retiring a command means adding a [`Deprecation`] to [`REGISTRY`]; the
+//! REPL looks every input up here before running it. A notice names the
+//! command to use instead, or else the supported command spelled most like
+//! what was typed.
+
+/// Top-level REPL commands, for suggestions.
+pub const COMMANDS: &[&str] = &[
+    "actions",
+    "answer",
+    "approve",
+    "artifacts",
+    "audit",
+    "capabilities",
+    "cd",
+    "citations",
+    "clear",
+    "config",
+    "contacts",
+    "context",
+    "do",
+    "exit",
+    "explain",
+    "forms",
+    "help",
+    "history",
+    "interrupt",
+    "knowledge",
+    "models",
+    "policy",
+    "preset",
+    "preview",
+    "profile",
+    "prompt",
+    "pwd",
+    "questions",
+    "queue",
+    "quit",
+    "reminder",
+    "scan",
+    "setup",
+    "skill",
+    "skills",
+    "stats",
+    "status",
+    "tasks",
+    "thread",
+    "threads",
+    "triage",
+    "view",
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Deprecation {
+    /// Name usage stats count the entry under.
+    pub name: &'static str,
+    /// Inputs the entry covers: the input is one of them or starts with one
+    /// and a space. A leading `/` is optional unless the entry has one.
+    pub commands: &'static [&'static str],
+    pub notice: &'static str,
+    /// The command that took over, if any.
+    pub instead: Option<&'static str>,
+}
+
+pub const REGISTRY: &[Deprecation] = &[
+    Deprecation {
+        name: "tui",
+        commands: &["tui", "repl"],
+        notice: "TUI/REPL mode switching has been removed; there is one prompt workflow.",
+        instead: None,
+    },
+    Deprecation {
+        name: "dashboard",
+        commands: &["dashboard", "dash"],
+        notice: "Dashboard mode has been removed to reduce bloat.",
+        instead: Some("status"),
+    },
+    Deprecation {
+        name: "soul",
+        commands: &["soul"],
+        notice: "The soul system has been removed: the `power_agent` profile is always active, \
+                 and a project's `.hypr-claw/soul.md` overrides it inside that project.",
+        instead: None,
+    },
+    Deprecation {
+        name: "autonomy",
+        commands: &["autonomy"],
+        notice: "Autonomy modes were removed; supervision is set per task class and thread.",
+        instead: Some("policy set"),
+    },
+    Deprecation {
+        name: "trust",
+        commands: &["trust"],
+        notice: "Trust/safe mode toggles were removed; approvals follow the supervision policy.",
+        instead: Some("policy set"),
+    },
+    Deprecation {
+        name: "/task",
+        commands: &["/task"],
+        notice: "Task-thread commands were removed to simplify the workflow.",
+        instead: Some("queue"),
+    },
+    Deprecation {
+        name: "queue advanced",
+        commands: &[
+            "queue prune",
+            "queue inspect",
+            "queue events",
+            "queue stop",
+            "queue retry",
+            "queue auto",
+        ],
+        notice: "Advanced queue controls were removed. Supported queue commands: `queue`, \
+                 `queue status`, `queue add <prompt>`, `queue run`, `queue clear`.",
+        instead: None,
+    },
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Notice {
+    /// The input is a command that was removed.
+    Removed {
+        entry: &'static Deprecation,
+        suggestion: Option<&'static str>,
+    },
+    /// The input looks like a misspelt command.
+    Typo {
+        typed: String,
+        suggestion: &'static str,
+    },
+}
+
+impl Notice {
+    pub fn render(&self) -> String {
+        match self {
+            Self::Removed { entry, suggestion } => match (entry.instead, suggestion) {
+                (Some(instead), _) => format!("ℹ️ {} Use `{instead}` instead.", entry.notice),
+                (None, Some(suggestion)) => {
+                    format!("ℹ️ {} Did you mean `{suggestion}`?", entry.notice)
+                }
+                (None, None) => format!("ℹ️ {}", entry.notice),
+            },
+            Self::Typo { typed, suggestion } => {
+                format!(
+                    "ℹ️ `{typed}` is not a command. Did you mean `{suggestion}`? \
+                     (`help` lists them)"
+                )
+            }
+        }
+    }
+}
+
+/// The notice for `input`, if it is a removed command or a misspelt one.
+/// Anything else runs as usual.
+pub fn notice(input: &str) -> Option<Notice> {
+    let lower = input.trim().to_ascii_lowercase();
+    if let Some(entry) = REGISTRY.iter().find(|entry| entry.matches(&lower)) {
+        let first = lower.split_whitespace().next().unwrap_or_default();
+        return Some(Notice::Removed {
+            entry,
+            suggestion: nearest(first.trim_start_matches('/')),
+        });
+    }
+
+    // Only a lone word of letters can be a misspelt command; a bare one must
+    // be close enough that it is unlikely to be a one-word request.
+    let mut words = lower.split_whitespace();
+    let (Some(word), None) = (words.next(), words.next()) else {
+        return None;
+    };
+    let (bare, slashed) = match word.strip_prefix('/') {
+        Some(bare) => (bare, true),
+        None => (word, false),
+    };
+    if COMMANDS.contains(&bare) || !bare.chars().all(|c| c.is_ascii_lowercase()) {
+        return None;
+    }
+    let suggestion = nearest(bare)?;
+    let close = bare.len() >= 5 && edit_distance(bare, suggestion) == 1;
+    (slashed || close).then(|| Notice::Typo {
+        typed: word.to_string(),
+        suggestion,
+    })
+}
+
+impl Deprecation {
+    fn matches(&self, lower: &str) -> bool {
+        self.commands.iter().any(|command| {
+            let candidates = if command.starts_with('/') {
+                [Some(lower), None]
+            } else {
+                [Some(lower), lower.strip_prefix('/')]
+            };
+            candidates.into_iter().flatten().any(|input| {
+                input
+                    .strip_prefix(command)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+            })
+        })
+    }
+}
+
+/// The supported command closest to `word`, if it is close at all: at most
+/// a third of its letters, and at least one, differ.
+pub fn nearest(word: &str) -> Option<&'static str> {
+    let limit = (word.chars().count() / 3).max(1);
+    COMMANDS
+        .iter()
+        .map(|command| (edit_distance(word, command), *command))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, command)| command)
+}
+
+/// Levenshtein distance over chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn removed(input: &str) -> Option<&'static str> {
+        match notice(input)? {
+            Notice::Removed { entry, .. } => Some(entry.name),
+            Notice::Typo { .. } => None,
+        }
+    }
+
+    #[test]
+    fn removed_commands_match_whole_words() {
+        assert_eq!(removed("tui"), Some("tui"));
+        assert_eq!(removed("/REPL"), Some("tui"));
+        assert_eq!(removed("soul set coder"), Some("soul"));
+        assert_eq!(removed("/queue retry 3"), Some("queue advanced"));
+        assert_eq!(removed("/task list"), Some("/task"));
+        assert_eq!(removed("task: rename the photos"), None);
+        assert_eq!(removed("trustworthy sources on rust async"), None);
+        assert_eq!(notice("queue status"), None);
+
+        assert_eq!(
+            notice("dash").unwrap().render(),
+            "ℹ️ Dashboard mode has been removed to reduce bloat. Use `status` instead."
+        );
+    }
+
+    #[test]
+    fn typos_suggest_the_nearest_command() {
+        assert_eq!(edit_distance("histroy", "history"), 2);
+        assert_eq!(edit_distance("", "cd"), 2);
+        assert_eq!(nearest("histroy"), Some("history"));
+        assert_eq!(nearest("reminders"), Some("reminder"));
+        assert_eq!(nearest("screenshot"), None);
+
+        assert_eq!(
+            notice("/histroy"),
+            Some(Notice::Typo {
+                typed: "/histroy".to_string(),
+                suggestion: "history"
+            })
+        );
+        assert!(matches!(
+            notice("triag"),
+            Some(Notice::Typo {
+                suggestion: "triage",
+                ..
+            })
+        ));
+        // A lone word that is only loosely like a command is a request.
+        assert_eq!(notice("histroy"), None);
+        assert_eq!(notice("hello"), None);
+        assert_eq!(notice("/etc/hosts"), None);
+        assert_eq!(notice("status"), None);
+        assert_eq!(notice("/status"), None);
+        assert_eq!(notice("polcy set"), None);
+    }
+}
@@ -0,0 +1,129 @@
+//! Runs in flight across threads.
+//!
+//! The foreground run and the prompts started in other threads while it
+//! goes on each hold an [`InFlightGuard`] for their thread, so one thread
+//! never has two runs at once, and [`InFlightRuns::status_line`] lists every
+//! run still going.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Prompt text shown per run in the status line.
+const STATUS_PROMPT_CHARS: usize = 32;
+
+struct Entry {
+    prompt: String,
+    started: Instant,
+}
+
+#[derive(Clone, Default)]
+pub struct InFlightRuns {
+    runs: Arc<Mutex<BTreeMap<String, Entry>>>,
+}
+
+impl InFlightRuns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks a run of `prompt` in flight on `thread` until the guard is
+    /// dropped; `None` if that thread already has one.
+    pub fn start(&self, thread: &str, prompt: &str) -> Option<InFlightGuard> {
+        let mut runs = lock(&self.runs);
+        if runs.contains_key(thread) {
+            return None;
+        }
+        runs.insert(
+            thread.to_string(),
+            Entry {
+                prompt: prompt.to_string(),
+                started: Instant::now(),
+            },
+        );
+        Some(InFlightGuard {
+            runs: self.runs.clone(),
+            thread: thread.to_string(),
+        })
+    }
+
+    pub fn is_busy(&self, thread: &str) -> bool {
+        lock(&self.runs).contains_key(thread)
+    }
+
+    pub fn len(&self) -> usize {
+        lock(&self.runs).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// `in flight: task-1 12s "open firefox" | task-2 3s "…"`, or `None`
+    /// when nothing runs.
+    pub fn status_line(&self) -> Option<String> {
+        let runs = lock(&self.runs);
+        if runs.is_empty() {
+            return None;
+        }
+        let items = runs
+            .iter()
+            .map(|(thread, entry)| describe(thread, entry.started.elapsed(), &entry.prompt))
+            .collect::<Vec<_>>();
+        Some(format!("in flight: {}", items.join(" | ")))
+    }
+}
+
+fn describe(thread: &str, elapsed: Duration, prompt: &str) -> String {
+    let mut shown = prompt.chars().take(STATUS_PROMPT_CHARS).collect::<String>();
+    if prompt.chars().count() > STATUS_PROMPT_CHARS {
+        shown.push('…');
+    }
+    format!("{thread} {}s \"{shown}\"", elapsed.as_secs())
+}
+
+fn lock(runs: &Mutex<BTreeMap<String, Entry>>) -> MutexGuard<'_, BTreeMap<String, Entry>> {
+    runs.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Keeps a thread marked busy; dropping it ends the run.
+pub struct InFlightGuard {
+    runs: Arc<Mutex<BTreeMap<String, Entry>>>,
+    thread: String,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        lock(&self.runs).remove(&self.thread);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_run_per_thread_and_all_in_the_status_line() {
+        let runs = InFlightRuns::new();
+        assert_eq!(runs.status_line(), None);
+        let main = runs.start("task-1", "open firefox").unwrap();
+        assert!(runs.start("task-1", "again").is_none());
+        let side = runs
+            .start(
+                "task-2",
+                "summarize the build logs from this morning please",
+            )
+            .unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(
+            runs.status_line().unwrap(),
+            "in flight: task-1 0s \"open firefox\" | \
+             task-2 0s \"summarize the build logs from th…\""
+        );
+        drop(main);
+        assert!(!runs.is_busy("task-1"));
+        assert!(runs.is_busy("task-2"));
+        drop(side);
+        assert!(runs.is_empty());
+    }
+}
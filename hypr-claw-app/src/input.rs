@@ -0,0 +1,127 @@
+//! Line input shared by the prompt and running requests.
+//!
+//! A reader thread owns stdin and forwards every line, so text typed while a
+//! request runs is captured instead of being dropped or mixed into the
+//! action feed. Such lines are queued: a line starting with `+` is passed to
+//! the running request as a note, anything else runs once it finishes.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(40);
+
+static LINES: OnceLock<Mutex<Receiver<String>>> = OnceLock::new();
+
+fn lines() -> &'static Mutex<Receiver<String>> {
+    LINES.get_or_init(|| {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Mutex::new(rx)
+    })
+}
+
+/// Next line from stdin, waiting for it. `None` once stdin is closed.
+pub fn read_line() -> Option<String> {
+    lines().lock().ok()?.recv().ok()
+}
+
+/// Next line from stdin without blocking the runtime. `None` once stdin is
+/// closed.
+pub async fn next_line() -> Option<String> {
+    loop {
+        let polled = match lines().lock() {
+            Ok(rx) => rx.try_recv(),
+            Err(_) => return None,
+        };
+        match polled {
+            Ok(line) => return Some(line),
+            Err(TryRecvError::Disconnected) => return None,
+            Err(TryRecvError::Empty) => tokio::time::sleep(POLL_INTERVAL).await,
+        }
+    }
+}
+
+/// What to do with a line typed while a request is running.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypedAhead {
+    /// Hand to the running request before its next model call.
+    Note(String),
+    /// Run after the current request finishes.
+    Next(String),
+}
+
+impl TypedAhead {
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if let Some(note) = line.strip_prefix('+') {
+            let note = note.trim();
+            return (!note.is_empty()).then(|| Self::Note(note.to_string()));
+        }
+        (!line.is_empty()).then(|| Self::Next(line.to_string()))
+    }
+}
+
+/// Prompts typed during a run, started in order once it finishes.
+#[derive(Debug, Default)]
+pub struct PendingInputs {
+    lines: VecDeque<String>,
+}
+
+impl PendingInputs {
+    pub fn push(&mut self, line: String) {
+        self.lines.push_back(line);
+    }
+
+    pub fn pop(&mut self) -> Option<String> {
+        self.lines.pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_ahead_lines_are_notes_or_next_prompts() {
+        assert_eq!(
+            TypedAhead::parse("+ use the left monitor "),
+            Some(TypedAhead::Note("use the left monitor".to_string()))
+        );
+        assert_eq!(
+            TypedAhead::parse("open spotify"),
+            Some(TypedAhead::Next("open spotify".to_string()))
+        );
+        assert_eq!(TypedAhead::parse("+  "), None);
+        assert_eq!(TypedAhead::parse("   "), None);
+
+        let mut pending = PendingInputs::default();
+        pending.push("first".to_string());
+        pending.push("second".to_string());
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending.pop().as_deref(), Some("first"));
+        pending.clear();
+        assert!(pending.is_empty());
+    }
+}
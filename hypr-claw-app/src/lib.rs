@@ -1,3 +1,33 @@
+pub mod action_log;
+pub mod analytics;
 pub mod bootstrap;
+pub mod capability_compare;
+pub mod compaction_metrics;
 pub mod config;
+pub mod context_analysis;
+pub mod context_providers;
+pub mod crash;
+pub mod data_dir;
+pub mod deprecations;
+pub mod inflight;
+pub mod input;
+pub mod migrations;
+pub mod model_cache;
+pub mod prefetch;
+pub mod project_soul;
+pub mod queue_file;
+pub mod retention;
+pub mod run_metrics;
+pub mod safe_mode;
 pub mod scan;
+pub mod service;
+pub mod setup_manifest;
+pub mod side_effects;
+pub mod startup;
+pub mod suggestions;
+pub mod supervision;
+pub mod telemetry;
+pub mod thermal;
+pub mod triage;
+pub mod update;
+pub mod watchdog;
@@ -5,11 +5,43 @@ use std::io::{self, Write};
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 
+pub mod action_log;
+pub mod analytics;
 pub mod bootstrap;
+pub mod capability_compare;
+pub mod compaction_metrics;
 pub mod config;
+pub mod context_analysis;
+pub mod context_providers;
+pub mod crash;
+pub mod data_dir;
+pub mod deprecations;
+pub mod inflight;
+pub mod input;
+pub mod migrations;
+pub mod model_cache;
+pub mod prefetch;
+pub mod project_soul;
+pub mod queue_file;
+pub mod retention;
+pub mod run_metrics;
+pub mod safe_mode;
 pub mod scan;
-
-use config::{Config, LLMProvider};
+pub mod service;
+pub mod setup_manifest;
+pub mod side_effects;
+pub mod startup;
+pub mod suggestions;
+pub mod supervision;
+pub mod telemetry;
+pub mod thermal;
+pub mod triage;
+pub mod update;
+pub mod watchdog;
+
+use config::{Config, ConfigIssue, ConfigIssueKind, LLMProvider};
+use hypr_claw::infra::backup::BackupKey;
+use safe_mode::{StartupComponent, StartupFailure};
 
 enum UiInputEvent {
     Line(String),
@@ -19,28 +51,174 @@ enum UiInputEvent {
 
 const MAX_RECOVERY_ATTEMPTS: u32 = 2;
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// How often the daemon loop wakes up to pick up queued work.
+const DAEMON_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Before anything can `cd`, so ./data stays where the app started.
+    data_dir::pin();
+    // Landlock only restricts the calling thread and the threads it creates
+    // later, so hardening has to happen before the runtime spawns workers.
+    crash::install_panic_hook();
+    let args: Vec<String> = std::env::args().collect();
+    if !matches!(
+        args.get(1).map(String::as_str),
+        Some("config" | "service" | "self-update")
+    ) {
+        apply_hardening()?;
+    }
+    // A prompt blocked on stdin holds a worker; keep another free for the
+    // watchdog heartbeat even on a single core.
+    let workers = std::thread::available_parallelism().map_or(2, |n| n.get().max(2));
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(workers)
+        .enable_all()
+        .build()?
+        .block_on(run())
+}
+
+/// Applies `sandbox.hardening` from an existing config. Without a readable
+/// config (first run) there is nothing to apply; [`run`] reports the error.
+fn apply_hardening() -> Result<(), Box<dyn std::error::Error>> {
+    let Ok(config) = Config::load() else {
+        return Ok(());
+    };
+    let policy = &config.sandbox.hardening;
+    if !policy.enabled {
+        return Ok(());
+    }
+    let workdir = std::env::current_dir()?;
+    match policy.apply(&workdir) {
+        Ok(report) => {
+            eprintln!(
+                "🛡️  Hardening active: landlock {}, seccomp {}",
+                report.landlock,
+                if report.seccomp { "on" } else { "off" }
+            );
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to apply sandbox.hardening: {}", e);
+            Err(e)
+        }
+    }
+}
+
+/// Announces crash reports written since the last start and returns the
+/// newest one, so tasks cut short by it can point at the report.
+fn surface_crash_reports() -> Option<std::path::PathBuf> {
+    let dir = data_dir::path(crash::CRASH_DIR);
+    let reports = crash::unsurfaced_reports(&dir).ok()?;
+    let latest = reports.last()?.clone();
+    for path in &reports {
+        match crash::load_report(path) {
+            Ok(report) => eprintln!(
+                "⚠️  hypr-claw crashed at {}: {}",
+                report.location.as_deref().unwrap_or("unknown location"),
+                report.message
+            ),
+            Err(_) => eprintln!("⚠️  hypr-claw crashed during a previous run"),
+        }
+        eprintln!("   Report: {}", path.display());
+    }
+    if let Err(e) = crash::mark_surfaced(&dir, &latest) {
+        eprintln!("⚠️  Failed to mark crash reports as seen: {}", e);
+    }
+    Some(latest)
+}
+
+async fn run() -> Result<(), Box<dyn std::error::Error>> {
     // Parse CLI arguments
     let args: Vec<String> = std::env::args().collect();
     if args.len() > 1 && args[1] == "config" && args.get(2).map(|s| s.as_str()) == Some("reset") {
         return handle_config_reset();
     }
+    if args.len() > 1 && args[1] == "backup" {
+        return handle_backup_command(&args[2..]);
+    }
+    if args.len() > 1 && args[1] == "metrics" {
+        return handle_metrics_command(&args[2..]);
+    }
+    if args.len() > 1 && args[1] == "self-update" {
+        return handle_self_update(&args[2..]).await;
+    }
+    if args.len() > 1 && args[1] == "service" {
+        return handle_service_command(&args[2..]);
+    }
+    if args.len() > 1 && args[1] == "plugins" {
+        return handle_plugins_command(&args[2..]).await;
+    }
+    if args.len() > 1 && args[1] == "knowledge" {
+        let store =
+            hypr_claw_tools::knowledge::KnowledgeStore::open(data_dir::path(KNOWLEDGE_PATH))?;
+        run_knowledge_command(&store, &args[2..].join(" ")).await;
+        return Ok(());
+    }
+    if args.len() > 1 && args[1] == "actions" {
+        let log =
+            action_log::ActionLog::new(data_dir::path(ACTIONS_DIR), action_log::DEFAULT_KEEP_RUNS);
+        let never = tokio::sync::Notify::new();
+        return show_actions(&log, &args[2..].join(" "), &never)
+            .await
+            .map_err(|e| e.into());
+    }
+    if args.len() > 1 && args[1] == "observe" {
+        let log =
+            action_log::ActionLog::new(data_dir::path(ACTIONS_DIR), action_log::DEFAULT_KEEP_RUNS);
+        let stop = async {
+            tokio::signal::ctrl_c().await.ok();
+        };
+        return observe_runs(&log, &args[2..].join(" "), stop)
+            .await
+            .map_err(|e| e.into());
+    }
+
+    // Headless: no prompt, stdin is ignored and the loop only runs queued and
+    // reminder follow-up tasks. Started by the `hypr-claw service` unit.
+    let daemon_mode = args.get(1).map(String::as_str) == Some("daemon");
+    if daemon_mode && !Config::exists() {
+        eprintln!("❌ No configuration found; run 'hypr-claw' once interactively first");
+        return Err("daemon mode needs an existing configuration".into());
+    }
+
+    let mut startup_profile = startup::StartupProfile::new();
 
     // Initialize directories
     if let Err(e) = initialize_directories() {
         eprintln!("❌ Failed to initialize directories: {}", e);
         return Err(e);
     }
+    match migrations::migrate(&data_dir::path(DATA_DIR)) {
+        Ok(applied) if !applied.is_empty() => {
+            eprintln!("🔄 Migrated ./data to format {}", migrations::DATA_FORMAT);
+        }
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("❌ Cannot use ./data: {}", e);
+            return Err(e.into());
+        }
+    }
 
     // Load or bootstrap configuration
     let mut config = if Config::exists() {
         match Config::load() {
-            Ok(cfg) => {
-                if let Err(e) = cfg.validate() {
-                    eprintln!("❌ Invalid configuration: {}", e);
-                    eprintln!("💡 Tip: Run 'hypr-claw config reset' to reconfigure");
-                    return Err(e.into());
+            Ok(mut cfg) => {
+                let mut issues = cfg.validate().err().unwrap_or_default();
+                issues.extend(bootstrap::api_key_issue(&cfg.provider));
+                if !issues.is_empty() {
+                    print_config_issues(&issues);
+                    if let Some(model) = fix_config_issues(&cfg, &issues).await? {
+                        cfg.model = model;
+                        cfg.save()?;
+                    }
+                    if let Err(remaining) = cfg.validate() {
+                        eprintln!("💡 Tip: Run 'hypr-claw config reset' to reconfigure");
+                        return Err(format!(
+                            "Invalid configuration: {} issue(s) left",
+                            remaining.len()
+                        )
+                        .into());
+                    }
                 }
                 cfg
             }
@@ -67,6 +245,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let _ = config.save();
         println!("ℹ️  NVIDIA default model set to {}", config.model);
     }
+    crash::set_model(&config.model);
+    startup_profile.mark("config");
 
     // Provider info
     let provider_name = match &config.provider {
@@ -84,38 +264,86 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             provider_name
         );
         eprintln!("   Use NVIDIA, Google, or Local providers for autonomous execution.");
-        return Err("Provider capability check failed".into());
+        return run_safe_mode(
+            &config,
+            StartupFailure::new(
+                StartupComponent::Provider,
+                format!("{provider_name} does not support tool calling"),
+            ),
+        )
+        .await;
     }
 
     let agent_name = detect_agent_name();
     let user_id = detect_user_id();
     let session_key = format!("{}:{}", user_id, agent_name);
 
-    let context_manager = hypr_claw_memory::ContextManager::new("./data/context");
-    context_manager.initialize().await?;
-    let mut context = context_manager.load(&session_key).await?;
+    let context_manager = hypr_claw_memory::ContextManager::new(data_dir::path("./data/context"));
+    let loaded_context = match context_manager.initialize().await {
+        Ok(()) => context_manager.load(&session_key).await,
+        Err(e) => Err(e),
+    };
+    let mut context = match loaded_context {
+        Ok(context) => context,
+        Err(e) => {
+            return run_safe_mode(
+                &config,
+                StartupFailure::new(StartupComponent::SessionStore, e),
+            )
+            .await;
+        }
+    };
     if context.session_id.is_empty() {
         context.session_id = session_key.clone();
     }
+    startup_profile.mark("session");
 
     let active_soul_id = "power_agent".to_string();
     let active_soul = power_agent_profile();
     let mut agent_state = load_agent_os_state(&context);
+    hypr_claw_runtime::metrics::seed_tool_latencies(&agent_state.reliability.tool_latency);
     agent_state.soul_auto = false;
-    agent_state.autonomy_mode = AutonomyMode::PromptFirst;
+    agent_state.autonomy_mode = agent_state
+        .preset
+        .map(|preset| preset.autonomy_mode())
+        .unwrap_or(AutonomyMode::PromptFirst);
     agent_state.supervisor.auto_run = false;
     ensure_default_thread(&mut agent_state);
-    let recovered_stale = reconcile_supervisor_after_restart(&mut agent_state);
+    // `thread popout` starts a second instance pinned to one thread. The
+    // supervisor tasks it sees belong to the main instance and are not stale.
+    let pinned_thread = cli_flag_value(&args, "--thread").map(str::to_string);
+    if let Some(id) = &pinned_thread {
+        if agent_state
+            .task_threads
+            .iter()
+            .any(|thread| &thread.id == id && !thread.archived)
+        {
+            agent_state.active_thread_id = id.clone();
+        } else {
+            eprintln!(
+                "⚠️  No open thread '{}'; staying on {}",
+                id, agent_state.active_thread_id
+            );
+        }
+    }
+    let recovered_stale = if pinned_thread.is_some() {
+        0
+    } else {
+        reconcile_supervisor_after_restart(&mut agent_state)
+    };
     if recovered_stale > 0 {
         println!(
             "ℹ️  Recovered {} stale supervisor task(s) from previous session.",
             recovered_stale
         );
     }
+    let first_run = !agent_state.onboarding.completed;
     run_first_run_onboarding(&user_id, &mut context, &mut agent_state).await?;
+    // An outdated profile is rescanned in the background; the loop swaps the
+    // result in once it lands instead of holding up the first prompt.
+    let background_scan: Arc<Mutex<Option<Value>>> = Arc::new(Mutex::new(None));
     if profile_needs_capability_refresh(&agent_state.onboarding.system_profile) {
-        agent_state.onboarding.system_profile = scan::run_integrated_scan(&user_id, false).await?;
-        agent_state.onboarding.last_scan_at = Some(chrono::Utc::now().timestamp());
+        spawn_background_scan(user_id.clone(), background_scan.clone());
     }
     let (mut capability_registry, registry_loaded) = match load_capability_registry(&user_id) {
         Ok(registry) => (registry, true),
@@ -135,33 +363,53 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             eprintln!("⚠️  Failed to save capability registry: {}", e);
         }
     }
+    configure_ocr(&config.ocr, &capability_registry);
+    let context_registry = context_providers::ContextRegistry::from_config(&config.context);
     agent_state.onboarding.trusted_full_auto = false;
     context.active_soul_id = active_soul_id.clone();
     persist_agent_os_state(&mut context, &agent_state);
-    context_manager.save(&context).await?;
+    if first_run {
+        context_manager.save(&context).await?;
+    }
+    startup_profile.mark("capabilities");
 
     println!("\n🔧 Initializing system...");
 
     // Initialize infrastructure
-    let session_store = match hypr_claw::infra::session_store::SessionStore::new("./data/sessions")
-    {
-        Ok(store) => Arc::new(store),
-        Err(e) => {
-            eprintln!("❌ Failed to initialize session store: {}", e);
-            return Err(Box::new(e));
-        }
-    };
+    let session_store =
+        match hypr_claw::infra::session_store::SessionStore::new(data_dir::path("./data/sessions"))
+        {
+            Ok(store) => Arc::new(store),
+            Err(e) => {
+                eprintln!("❌ Failed to initialize session store: {}", e);
+                return run_safe_mode(
+                    &config,
+                    StartupFailure::new(StartupComponent::SessionStore, e),
+                )
+                .await;
+            }
+        };
 
     let lock_manager = Arc::new(hypr_claw::infra::lock_manager::LockManager::new(
         Duration::from_secs(300),
     ));
-    let permission_engine = Arc::new(hypr_claw::infra::permission_engine::PermissionEngine::new());
+    let permission_engine = Arc::new(
+        hypr_claw::infra::permission_engine::PermissionEngine::new()
+            .with_prompter(Arc::new(prompt_tool_approval)),
+    );
 
-    let audit_logger = match hypr_claw::infra::audit_logger::AuditLogger::new("./data/audit.log") {
+    let audit_logger = match hypr_claw::infra::audit_logger::AuditLogger::with_rotation(
+        data_dir::path("./data/audit.log"),
+        config.audit.rotation.clone(),
+    ) {
         Ok(logger) => Arc::new(logger),
         Err(e) => {
             eprintln!("❌ Failed to initialize audit logger: {}", e);
-            return Err(Box::new(e));
+            return run_safe_mode(
+                &config,
+                StartupFailure::new(StartupComponent::ToolRegistry, e),
+            )
+            .await;
         }
     };
 
@@ -169,6 +417,84 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let async_session = Arc::new(hypr_claw_runtime::AsyncSessionStore::new(session_store));
     let async_locks = Arc::new(hypr_claw_runtime::AsyncLockManager::new(lock_manager));
 
+    // The stores and plugin verification only read their own files, so load
+    // them side by side.
+    let plugin_policy = config.plugins.signatures.clone();
+    let plugin_audit = audit_logger.clone();
+    let (reminder_store, contact_store, knowledge_store, plugin_tools) = tokio::join!(
+        tokio::task::spawn_blocking(|| {
+            hypr_claw_tools::reminders::ReminderStore::open(data_dir::path(REMINDERS_PATH))
+        }),
+        tokio::task::spawn_blocking(|| {
+            hypr_claw_tools::contacts::ContactStore::open(data_dir::path(CONTACTS_PATH))
+        }),
+        tokio::task::spawn_blocking(|| {
+            hypr_claw_tools::knowledge::KnowledgeStore::open(data_dir::path(KNOWLEDGE_PATH))
+        }),
+        tokio::task::spawn_blocking(move || load_installed_plugins(&plugin_policy, &plugin_audit)),
+    );
+
+    let reminder_store = match reminder_store.map_err(io::Error::other).and_then(|r| r) {
+        Ok(store) => Arc::new(store),
+        Err(e) => {
+            eprintln!("❌ Failed to load reminders: {}", e);
+            return run_safe_mode(
+                &config,
+                StartupFailure::new(StartupComponent::ToolRegistry, e),
+            )
+            .await;
+        }
+    };
+
+    let contact_store = match contact_store.map_err(io::Error::other).and_then(|r| r) {
+        Ok(store) => Arc::new(store),
+        Err(e) => {
+            eprintln!("❌ Failed to load contacts: {}", e);
+            return run_safe_mode(
+                &config,
+                StartupFailure::new(StartupComponent::ToolRegistry, e),
+            )
+            .await;
+        }
+    };
+
+    let knowledge_store = match knowledge_store.map_err(io::Error::other).and_then(|r| r) {
+        Ok(store) => Arc::new(store),
+        Err(e) => {
+            eprintln!("❌ Failed to load knowledge index: {}", e);
+            return run_safe_mode(
+                &config,
+                StartupFailure::new(StartupComponent::ToolRegistry, e),
+            )
+            .await;
+        }
+    };
+
+    let question_inbox =
+        match hypr_claw_tools::questions::QuestionInbox::open(data_dir::path(QUESTIONS_PATH)) {
+            Ok(inbox) => Arc::new(inbox),
+            Err(e) => {
+                eprintln!("❌ Failed to load pending questions: {}", e);
+                return run_safe_mode(
+                    &config,
+                    StartupFailure::new(StartupComponent::ToolRegistry, e),
+                )
+                .await;
+            }
+        };
+
+    let form_memory = match hypr_claw_tools::forms::FormMemory::open(data_dir::path(FORMS_PATH)) {
+        Ok(memory) => Arc::new(memory),
+        Err(e) => {
+            eprintln!("❌ Failed to load saved forms: {}", e);
+            return run_safe_mode(
+                &config,
+                StartupFailure::new(StartupComponent::ToolRegistry, e),
+            )
+            .await;
+        }
+    };
+
     // Create tool registry
     let mut registry = hypr_claw_tools::ToolRegistryImpl::new();
     registry.register(Arc::new(hypr_claw_tools::tools::EchoTool));
@@ -189,7 +515,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     registry.register(Arc::new(hypr_claw_tools::os_tools::HyprWindowCloseTool));
     registry.register(Arc::new(hypr_claw_tools::os_tools::HyprWindowMoveTool));
     registry.register(Arc::new(hypr_claw_tools::os_tools::HyprExecTool));
-    registry.register(Arc::new(hypr_claw_tools::os_tools::ProcSpawnTool));
+    if config.sandbox.isolation.is_enabled()
+        && !hypr_claw_tools::sandbox::isolation::bwrap_available()
+    {
+        eprintln!("⚠️  sandbox.isolation is enabled but bwrap is not installed; proc.spawn will refuse to run");
+    }
+    registry.register(Arc::new(
+        hypr_claw_tools::os_tools::ProcSpawnTool::with_isolation(config.sandbox.isolation.clone()),
+    ));
+    // Snippets run in their own bwrap namespaces, which hardening's seccomp
+    // filter forbids.
+    if hypr_claw_tools::sandbox::isolation::bwrap_available() && !config.sandbox.hardening.enabled {
+        registry.register(Arc::new(hypr_claw_tools::os_tools::CodeRunSnippetTool));
+    }
     registry.register(Arc::new(hypr_claw_tools::os_tools::ProcKillTool));
     registry.register(Arc::new(hypr_claw_tools::os_tools::ProcListTool));
     registry.register(Arc::new(hypr_claw_tools::os_tools::DesktopOpenUrlTool));
@@ -226,35 +564,181 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     registry.register(Arc::new(
         hypr_claw_tools::os_tools::DesktopReadScreenStateTool,
     ));
+    let window_snapshots = Arc::new(hypr_claw_tools::window_snapshot::WindowSnapshots::new());
+    registry.register(Arc::new(
+        hypr_claw_tools::window_snapshot::DesktopWindowSnapshotTool::new(window_snapshots.clone()),
+    ));
+    registry.register(Arc::new(
+        hypr_claw_tools::window_snapshot::DesktopWindowDiffTool::new(window_snapshots),
+    ));
     registry.register(Arc::new(hypr_claw_tools::os_tools::WallpaperSetTool));
     registry.register(Arc::new(hypr_claw_tools::os_tools::SystemShutdownTool));
     registry.register(Arc::new(hypr_claw_tools::os_tools::SystemRebootTool));
     registry.register(Arc::new(hypr_claw_tools::os_tools::SystemBatteryTool));
     registry.register(Arc::new(hypr_claw_tools::os_tools::SystemMemoryTool));
+    registry.register(Arc::new(hypr_claw_tools::os_tools::SystemGpuTool));
+    registry.register(Arc::new(hypr_claw_tools::os_tools::SystemDisplaysTool));
+    registry.register(Arc::new(hypr_claw_tools::os_tools::SystemThermalTool));
+    registry.register(Arc::new(
+        hypr_claw_tools::os_tools::SystemPackageInstallTool,
+    ));
+    registry.register(Arc::new(hypr_claw_tools::os_tools::TimerSetTool::new(
+        reminder_store.clone(),
+    )));
+    registry.register(Arc::new(
+        hypr_claw_tools::os_tools::ContactsLookupTool::new(contact_store.clone()),
+    ));
+    registry.register(Arc::new(hypr_claw_tools::os_tools::MailComposeTool::new(
+        contact_store.clone(),
+    )));
+    registry.register(Arc::new(
+        hypr_claw_tools::os_tools::TelegramOpenChatTool::new(contact_store.clone()),
+    ));
+    registry.register(Arc::new(
+        hypr_claw_tools::os_tools::KnowledgeSearchTool::new(knowledge_store.clone()),
+    ));
+    registry.register(Arc::new(
+        hypr_claw_tools::os_tools::HelpLookupTool::default(),
+    ));
+    registry.register(Arc::new(hypr_claw_tools::os_tools::DocGenerateTool::new(
+        data_dir::path(ARTIFACTS_DIR),
+    )));
+    registry.register(Arc::new(hypr_claw_tools::os_tools::CalcEvaluateTool));
+    registry.register(Arc::new(hypr_claw_tools::os_tools::TimeParseTool));
+    registry.register(Arc::new(hypr_claw_tools::os_tools::TimeFormatTool));
+    registry.register(Arc::new(hypr_claw_tools::os_tools::CsvQueryTool));
+    registry.register(Arc::new(hypr_claw_tools::os_tools::CsvWriteTool));
+    registry.register(Arc::new(hypr_claw_tools::os_tools::DbQueryTool::new(
+        data_dir::path(ARTIFACTS_DIR),
+    )));
+    registry.register(Arc::new(hypr_claw_tools::os_tools::DbExecuteTool));
+    registry.register(Arc::new(hypr_claw_tools::os_tools::EnvGetTool::new(
+        config.sandbox.env.clone(),
+    )));
+    registry.register(Arc::new(hypr_claw_tools::os_tools::EnvListTool::new(
+        config.sandbox.env.clone(),
+    )));
+    registry.register(Arc::new(
+        hypr_claw_tools::os_tools::ScheduleSystemCreateTool,
+    ));
+    registry.register(Arc::new(hypr_claw_tools::os_tools::ScheduleSystemListTool));
+    registry.register(Arc::new(
+        hypr_claw_tools::os_tools::ScheduleSystemRemoveTool,
+    ));
+    let approval_book = Arc::new(hypr_claw_tools::approvals::ApprovalBook::new());
+    registry.register(Arc::new(hypr_claw_tools::approvals::PlanApprovalTool::new(
+        approval_book.clone(),
+        Arc::new(prompt_plan_approval),
+    )));
+    registry.register(Arc::new(hypr_claw_tools::questions::AskUserTool::new(
+        question_inbox.clone(),
+        Arc::new(prompt_ask_user),
+    )));
+    registry.register(Arc::new(hypr_claw_tools::forms::FormRememberTool::new(
+        form_memory.clone(),
+    )));
+    registry.register(Arc::new(hypr_claw_tools::forms::FormFillTool::new(
+        form_memory.clone(),
+        Arc::new(prompt_form_consent),
+    )));
+    let staging_area = Arc::new(hypr_claw_tools::staging::StagingArea::new());
+    registry.register(Arc::new(hypr_claw_tools::staging::FsStageTool::new(
+        staging_area.clone(),
+    )));
+    registry.register(Arc::new(
+        hypr_claw_tools::duplicates::FsFindDuplicatesTool::new(staging_area.clone()),
+    ));
+    registry.register(Arc::new(hypr_claw_tools::rename::FsBatchRenameTool::new(
+        staging_area.clone(),
+    )));
+    registry.register(Arc::new(hypr_claw_tools::staging::FsApplyStagedTool::new(
+        staging_area.clone(),
+        Arc::new(prompt_staged_apply),
+    )));
+    registry.register(Arc::new(hypr_claw_tools::staging::FsUndoApplyTool::new(
+        staging_area,
+    )));
+    match plugin_tools {
+        Ok(tools) => {
+            for tool in tools {
+                registry.register(tool);
+            }
+        }
+        Err(e) => eprintln!("⚠️  Failed to load plugins: {}", e),
+    }
+    let skill_dispatcher = hypr_claw_tools::skills::DispatcherSlot::default();
+    for tool in load_skills(&registry, &skill_dispatcher, &approval_book) {
+        registry.register(tool);
+    }
 
     let registry_arc = Arc::new(registry);
+    startup_profile.mark("tools");
 
-    // Create tool dispatcher
-    let dispatcher = Arc::new(hypr_claw_tools::ToolDispatcherImpl::new(
-        registry_arc.clone(),
+    let approval_gate = Arc::new(hypr_claw_tools::approvals::PlanApprovalGate::new(
         permission_engine as Arc<dyn hypr_claw_tools::PermissionEngine>,
-        audit_logger as Arc<dyn hypr_claw_tools::AuditLogger>,
-        5000,
+        approval_book,
     ));
+    if let Some(preset) = agent_state.preset {
+        approval_gate.set_prompt_from(preset.approval_tier());
+    }
+
+    // Create tool dispatcher
+    let exec_rules = match config.sandbox.exec.compile() {
+        Ok(rules) => rules,
+        Err(e) => {
+            eprintln!("❌ Invalid sandbox.exec rule: {}", e);
+            return Err(e.into());
+        }
+    };
+    let dispatcher = Arc::new(
+        hypr_claw_tools::ToolDispatcherImpl::new(
+            registry_arc.clone(),
+            approval_gate.clone() as Arc<dyn hypr_claw_tools::PermissionEngine>,
+            Arc::new(telemetry::SinkAudit) as Arc<dyn hypr_claw_tools::AuditLogger>,
+            5000,
+        )
+        .with_exec_rules(exec_rules),
+    );
+    let _ = skill_dispatcher.set(Arc::downgrade(&dispatcher));
+    let skill_runner = dispatcher.clone();
 
     let allowed_tools = derive_runtime_allowed_tools(&registry_arc, &capability_registry);
     if allowed_tools.is_empty() {
-        return Err("No runtime tools available after capability filtering".into());
+        return run_safe_mode(
+            &config,
+            StartupFailure::new(
+                StartupComponent::ToolRegistry,
+                "no runtime tools available after capability filtering",
+            ),
+        )
+        .await;
     }
-    let active_allowed_tools = allowed_tools.clone();
+    let mut active_allowed_tools = allowed_tools.clone();
     let allowed_tools_state = Arc::new(RwLock::new(allowed_tools.clone()));
     let action_feed: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
     let task_event_feed: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    let reminder_follow_ups: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    let generated_titles: Arc<Mutex<Vec<GeneratedTitle>>> = Arc::new(Mutex::new(Vec::new()));
+
+    let usage_tracker = Arc::new(analytics::UsageTracker::open(
+        analytics_file_path(&user_id),
+        config.analytics.enabled,
+    ));
+    let action_log = Arc::new(action_log::ActionLog::new(
+        data_dir::path(ACTIONS_DIR),
+        action_log::DEFAULT_KEEP_RUNS,
+    ));
+    let run_metrics_store =
+        run_metrics::RunMetricsStore::new(data_dir::path(run_metrics::METRICS_PATH));
 
     // Create runtime adapters
     let runtime_dispatcher = Arc::new(RuntimeDispatcherAdapter::new(
         dispatcher,
         action_feed.clone(),
+        active_allowed_tools.clone(),
+        &user_id,
+        usage_tracker.clone(),
+        action_log.clone(),
     ));
     let runtime_registry = Arc::new(RuntimeRegistryAdapter::new(
         registry_arc.clone(),
@@ -262,56 +746,32 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     ));
 
     // Initialize LLM client based on provider
-    let llm_client = match &config.provider {
-        LLMProvider::Nvidia => {
-            let api_key = match bootstrap::get_nvidia_api_key() {
-                Ok(key) => key,
-                Err(e) => {
-                    eprintln!("❌ Failed to retrieve NVIDIA API key: {}", e);
-                    eprintln!("💡 Tip: Run 'hypr-claw config reset' to reconfigure");
-                    return Err(e.into());
-                }
-            };
-            hypr_claw_runtime::LLMClientType::Standard(
-                hypr_claw_runtime::LLMClient::with_api_key_and_model(
-                    config.provider.base_url(),
-                    1,
-                    api_key,
-                    config.model.clone(),
-                ),
-            )
-        }
-        LLMProvider::Google => {
-            let api_key = match bootstrap::get_google_api_key() {
-                Ok(key) => key,
-                Err(e) => {
-                    eprintln!("❌ Failed to retrieve Google API key: {}", e);
-                    eprintln!("💡 Tip: Run 'hypr-claw config reset' to reconfigure");
-                    return Err(e.into());
-                }
-            };
-            hypr_claw_runtime::LLMClientType::Standard(
-                hypr_claw_runtime::LLMClient::with_api_key_and_model(
-                    config.provider.base_url(),
-                    1,
-                    api_key,
-                    config.model.clone(),
-                ),
-            )
-        }
-        LLMProvider::Local { .. } => hypr_claw_runtime::LLMClientType::Standard(
-            hypr_claw_runtime::LLMClient::new(config.provider.base_url(), 1),
-        ),
-        LLMProvider::Codex | LLMProvider::Antigravity | LLMProvider::GeminiCli => {
-            return Err("Provider does not support agent-mode tool calling".into());
+    for (provider, limit) in &config.concurrency {
+        hypr_claw_runtime::request_limits::configure(provider, limit.max_concurrent);
+    }
+    let llm_client = match build_llm_client_for_provider(&config.provider, &config.model) {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("❌ Failed to initialize {}: {}", provider_name, e);
+            return run_safe_mode(&config, StartupFailure::new(StartupComponent::Provider, e))
+                .await;
         }
     };
 
+    if model_cache::ModelCache::load(data_dir::path(model_cache::MODEL_CACHE_PATH))
+        .get(&model_cache::provider_key(&config.provider))
+        .is_none_or(|cached| cached.is_stale(chrono::Utc::now().timestamp()))
+    {
+        spawn_model_refresh(config.provider.clone(), config.model.clone());
+    }
+    startup_profile.mark("provider");
+
     // Create compactor
     let compactor = hypr_claw_runtime::Compactor::new(4000, SimpleSummarizer);
 
     // Create agent loop
-    let agent_loop = hypr_claw_runtime::AgentLoop::new(
+    // Shared with the runs started in other threads while one is in progress.
+    let agent_loop = Arc::new(hypr_claw_runtime::AgentLoop::new(
         async_session.clone(),
         async_locks.clone(),
         runtime_dispatcher.clone(),
@@ -319,15 +779,70 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         llm_client,
         compactor,
         active_soul.max_iterations,
+    ));
+    agent_loop.set_require_citations(config.response.require_citations);
+    agent_loop.set_output_guardrails(config.response.output_guardrails());
+    if config.prefetch.enabled {
+        agent_loop.set_prefetcher(Some(prefetch::prefetcher()));
+    }
+    // Decisions, compactions and tool audit entries reach their files
+    // through the telemetry sink.
+    hypr_claw_runtime::telemetry::install(Some(Arc::new(telemetry::FileTelemetry::new(
+        action_log.clone(),
+        compaction_metrics::CompactionLog::new(data_dir::path(
+            compaction_metrics::COMPACTIONS_PATH,
+        )),
+        audit_logger.clone(),
+    ))));
+    let hyprland_available = capability_registry
+        .pointer("/platform/hyprland_available")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let refresh_after = match config.context.refresh_after_secs {
+        0 => Duration::MAX,
+        secs => Duration::from_secs(secs),
+    };
+    agent_loop.set_context_refresher(
+        Some(Arc::new(context_providers::LiveContextRefresher::new(
+            &config.context,
+            hyprland_available,
+        ))),
+        refresh_after,
     );
 
     // Create task manager
     let task_manager = Arc::new(hypr_claw_tasks::TaskManager::with_state_file(
-        "./data/tasks/tasks.json",
+        data_dir::path("./data/tasks/tasks.json"),
     ));
-    task_manager.restore().await?;
+    let restore_reason = match surface_crash_reports() {
+        Some(report) => format!("Interrupted by crash (report: {})", report.display()),
+        None => "Interrupted by restart".to_string(),
+    };
+    if let Err(e) = task_manager.restore_with_reason(&restore_reason).await {
+        return run_safe_mode(
+            &config,
+            StartupFailure::new(StartupComponent::SessionStore, e),
+        )
+        .await;
+    }
     context.active_tasks = to_context_tasks(task_manager.list_tasks().await);
     context_manager.save(&context).await?;
+    startup_profile.mark("tasks");
+
+    if config.watchdog.enabled {
+        let tasks_for_flush = task_manager.clone();
+        let watchdog = watchdog::Watchdog::new(&config.watchdog).on_restart(Box::new(move || {
+            let tasks = tasks_for_flush.clone();
+            Box::pin(async move {
+                let _ = tasks.flush().await;
+            })
+        }));
+        if let Err(e) = watchdog.start() {
+            eprintln!("⚠️  Failed to start watchdog: {}", e);
+        }
+    }
+    // Startup is done; a no-op unless systemd started us with Type=notify.
+    let _ = sd_notify::notify(false, &[sd_notify::NotifyState::Ready]);
 
     // Run REPL loop
     let system_prompt = active_soul.system_prompt.clone();
@@ -341,6 +856,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         &session_key,
         &agent_state.active_thread_id,
     );
+    if startup::StartupProfile::enabled() {
+        println!("{}", ui_section("Startup"));
+        for line in startup_profile.render() {
+            println!("{line}");
+        }
+        println!();
+    }
 
     // Setup interrupt signal (Ctrl+C interrupts current request, does not exit process)
     let interrupt = Arc::new(tokio::sync::Notify::new());
@@ -351,9 +873,58 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             interrupt_clone.notify_waiters();
         }
     });
+    spawn_reminder_scheduler(
+        reminder_store.clone(),
+        task_event_feed.clone(),
+        reminder_follow_ups.clone(),
+    );
+    if config.backup.interval_hours > 0 {
+        spawn_backup_scheduler(config.backup.clone(), task_event_feed.clone());
+    }
+    if config.retention.interval_minutes > 0 {
+        spawn_retention_sweeper(config.retention.clone(), task_event_feed.clone());
+    }
+    let thermal_guard = Arc::new(thermal::ThermalGuard::new());
+    if config.thermal.interval_secs > 0 && !config.thermal.rules.is_empty() {
+        spawn_thermal_monitor(
+            config.thermal.clone(),
+            thermal_guard.clone(),
+            task_event_feed.clone(),
+        );
+    }
+    if config.update.check {
+        let update_config = config.update.clone();
+        tokio::spawn(async move {
+            let state = data_dir::path(update::CHECK_STATE_PATH);
+            if let Some(latest) = update::check_for_update(&update_config, &state).await {
+                eprintln!(
+                    "\n💡 hypr-claw {} is available (running {}); run 'hypr-claw self-update'",
+                    latest,
+                    update::CURRENT_VERSION
+                );
+            }
+        });
+    }
     let mut auto_queued_task: Option<SupervisedTask> = None;
     let mut queue_block_notice: Option<String> = None;
     let mut transcript_view_mode = true;
+    let mut last_run: Option<RunReport> = None;
+    // Prompt and successful calls of the last run `skill learn` can draft from.
+    let mut learnable_run: Option<(String, Vec<(String, Value)>)> = None;
+    // Soul of the project the working directory is in, if any.
+    let mut project: Option<project_soul::ProjectSoul> = None;
+    refresh_project_soul(&mut project);
+    // Shell commands suggested by the last answer, for `do <n>`.
+    let mut suggested_commands: Vec<String> = Vec::new();
+    let mut pending_inputs = input::PendingInputs::default();
+    let mut side_runs = SideRuns::new(
+        agent_loop.clone(),
+        runtime_dispatcher.clone(),
+        approval_gate.clone(),
+        action_log.clone(),
+        &session_key,
+        &agent_name,
+    );
     let mut background_task_index: HashMap<String, TaskStateDigest> = HashMap::new();
     let mut supervisor_background_map: HashMap<String, String> = HashMap::new();
     for task in &agent_state.supervisor.tasks {
@@ -382,6 +953,72 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         );
 
         let mut supervisor_state_changed = false;
+        let titles = generated_titles
+            .lock()
+            .map(|mut pending| std::mem::take(&mut *pending))
+            .unwrap_or_default();
+        for generated in titles {
+            supervisor_state_changed |= apply_generated_title(&mut agent_state, generated);
+        }
+        for outcome in std::mem::take(&mut side_runs.finished) {
+            record_side_run(&mut agent_state, &mut context, outcome);
+            supervisor_state_changed = true;
+        }
+        // A `thread switch` typed during the last run carries over to the prompt.
+        if let Some(thread) = side_runs.target.take() {
+            agent_state.active_thread_id = thread;
+            touch_active_thread(&mut agent_state);
+            ensure_default_thread(&mut agent_state);
+            supervisor_state_changed = true;
+        }
+        let scanned = background_scan.lock().ok().and_then(|mut slot| slot.take());
+        if let Some(mut scanned_profile) = scanned {
+            if let (Some(deep), Some(obj)) = (
+                agent_state
+                    .onboarding
+                    .system_profile
+                    .get("deep_scan")
+                    .cloned(),
+                scanned_profile.as_object_mut(),
+            ) {
+                obj.insert("deep_scan".to_string(), deep);
+            }
+            agent_state.onboarding.system_profile = scanned_profile;
+            agent_state.onboarding.last_scan_at = Some(chrono::Utc::now().timestamp());
+            if capability_registry_needs_refresh(
+                &capability_registry,
+                &agent_state.onboarding.system_profile,
+            ) {
+                capability_registry =
+                    build_capability_registry(&agent_state.onboarding.system_profile);
+                if let Err(e) = save_capability_registry(&user_id, &capability_registry) {
+                    eprintln!("⚠️  Failed to save capability registry: {}", e);
+                }
+                active_allowed_tools =
+                    derive_runtime_allowed_tools(&registry_arc, &capability_registry);
+                runtime_registry.set_allowed_tools(active_allowed_tools.clone());
+                runtime_dispatcher.set_available_tools(active_allowed_tools.clone());
+            }
+            supervisor_state_changed = true;
+        }
+        let follow_ups = reminder_follow_ups
+            .lock()
+            .map(|mut pending| std::mem::take(&mut *pending))
+            .unwrap_or_default();
+        for prompt in follow_ups {
+            let class = classify_supervised_task_class(&prompt);
+            let task_id = enqueue_supervised_task(&mut agent_state, prompt.clone(), class.clone());
+            push_task_event(
+                &task_event_feed,
+                format!(
+                    "sup {} queued from reminder class={} {}",
+                    task_id,
+                    class.as_str(),
+                    truncate_for_table(&prompt, 44)
+                ),
+            );
+            supervisor_state_changed = true;
+        }
         let mut finished_background_sup = Vec::<String>::new();
         for (sup_id, bg_id) in &supervisor_background_map {
             if let Some(bg_task) = latest_task_list.iter().find(|task| &task.id == bg_id) {
@@ -389,6 +1026,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     hypr_claw_tasks::TaskStatus::Completed => {
                         mark_supervised_task_completed(&mut agent_state, sup_id);
                         let summary = bg_task.result.clone().unwrap_or_else(|| "done".to_string());
+                        notify_background_outcome(
+                            agent_state.notifications,
+                            true,
+                            sup_id,
+                            &summary,
+                        );
                         push_task_event(
                             &task_event_feed,
                             format!(
@@ -406,6 +1049,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             format!("background task {} {:?}", bg_id, bg_task.status)
                         });
                         mark_supervised_task_failed(&mut agent_state, sup_id, err.clone());
+                        notify_background_outcome(agent_state.notifications, false, sup_id, &err);
                         push_task_event(
                             &task_event_feed,
                             format!(
@@ -447,12 +1091,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         for sup_id in finished_background_sup {
             supervisor_background_map.remove(&sup_id);
         }
+        for fact in form_memory.take_learned() {
+            remember_fact(&mut context.facts, fact);
+            supervisor_state_changed = true;
+        }
+        form_memory.sync_facts(&context.facts);
         if supervisor_state_changed {
             persist_agent_os_state(&mut context, &agent_state);
             context_manager.save(&context).await?;
         }
 
-        if auto_queued_task.is_none() && agent_state.supervisor.auto_run {
+        if auto_queued_task.is_none()
+            && agent_state.supervisor.auto_run
+            && !side_runs.in_flight.is_busy(&agent_state.active_thread_id)
+        {
             loop {
                 match start_next_queued_supervised_task(&mut agent_state) {
                     QueueStartResult::Started(task) => {
@@ -466,33 +1118,51 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             let task_prompt = task.prompt.clone();
                             let task_id = task.id.clone();
                             let task_class = task.class.clone();
-                            let timeout_bg =
-                                watchdog_timeout_for_class(&task_class, &agent_state.autonomy_mode);
+                            let task_session_key = format!("{}::sup::{}", session_key, task_id);
+                            let (mode_bg, supervision_bg) = apply_supervision(
+                                &agent_state,
+                                &approval_gate,
+                                &task_session_key,
+                                &task_class,
+                            );
                             let max_iter_bg = active_soul
                                 .max_iterations
                                 .min(
-                                    execution_budget_for_class(
-                                        &task_class,
-                                        &agent_state.autonomy_mode,
-                                    )
-                                    .max_iterations,
+                                    execution_budget_for_class(&task_class, &mode_bg)
+                                        .max_iterations,
                                 )
                                 .max(1);
+                            let timeout_bg = watchdog_with_tool_latency(
+                                watchdog_timeout_for_class(&task_class, &mode_bg),
+                                max_iter_bg,
+                                &active_allowed_tools,
+                                &hypr_claw_runtime::metrics::tool_latency_snapshot(),
+                            );
                             let provider_bg = config.provider.clone();
                             let model_bg = config.model.clone();
+                            let citations_bg = agent_loop.require_citations();
+                            let verify_bg = verification_required(&task_class, &mode_bg, &config);
                             let async_session_bg = async_session.clone();
                             let async_locks_bg = async_locks.clone();
                             let runtime_dispatcher_bg = runtime_dispatcher.clone();
                             let registry_arc_bg = registry_arc.clone();
                             let allowed_tools_bg = active_allowed_tools.clone();
-                            let task_session_key = format!("{}::sup::{}", session_key, task_id);
+                            runtime_dispatcher.set_task_label(&task_session_key, &task_prompt);
                             let agent_name_bg = agent_name.clone();
-                            let system_prompt_bg = augment_system_prompt_for_turn(
-                                &system_prompt,
-                                &agent_state.onboarding.system_profile,
-                                &capability_registry,
-                                &active_allowed_tools,
-                                &agent_state.autonomy_mode,
+                            let system_prompt_bg = with_supervision_instructions(
+                                augment_system_prompt_for_turn(
+                                    &system_prompt,
+                                    &context_registry,
+                                    &turn_context(
+                                        &agent_state,
+                                        &capability_registry,
+                                        &active_allowed_tools,
+                                        &context.facts,
+                                        &latest_task_list,
+                                    ),
+                                    &mode_bg,
+                                ),
+                                supervision_bg,
                             );
 
                             let spawn_result = task_manager
@@ -519,13 +1189,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                         let agent_loop_bg = hypr_claw_runtime::AgentLoop::new(
                                             async_session_bg,
                                             async_locks_bg,
-                                            runtime_dispatcher_bg,
+                                            runtime_dispatcher_bg.clone(),
                                             runtime_registry_bg,
                                             llm_client,
                                             compactor,
                                             max_iter_bg,
                                         );
-                                        match tokio::time::timeout(
+                                        agent_loop_bg.set_require_citations(citations_bg);
+                                        agent_loop_bg.set_require_verification(verify_bg);
+                                        let result = match tokio::time::timeout(
                                             timeout_bg,
                                             agent_loop_bg.run(
                                                 &task_session_key,
@@ -542,7 +1214,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                                 "Execution watchdog timeout after {}s",
                                                 timeout_bg.as_secs()
                                             )),
-                                        }
+                                        };
+                                        let changes =
+                                            runtime_dispatcher_bg.take_changes(&task_session_key);
+                                        result
+                                            .map(|response| with_run_changes(response, &changes))
+                                            .map_err(|e| with_run_changes(e, &changes))
                                     },
                                 )
                                 .await;
@@ -568,7 +1245,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                     );
                                     persist_agent_os_state(&mut context, &agent_state);
                                     context_manager.save(&context).await?;
-                                    println!(
+                                    eprintln!(
                                         "▶ Auto-running queued task {} in background ({})",
                                         task_id,
                                         task.class.as_str()
@@ -615,7 +1292,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         );
                         persist_agent_os_state(&mut context, &agent_state);
                         context_manager.save(&context).await?;
-                        println!(
+                        eprintln!(
                             "▶ Auto-running queued task {} ({})",
                             task.id,
                             task.class.as_str()
@@ -624,7 +1301,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     }
                     QueueStartResult::Blocked(reason) => {
                         if queue_block_notice.as_deref() != Some(reason.as_str()) {
-                            println!("⏸ Queue auto-run blocked: {}", reason);
+                            eprintln!("⏸ Queue auto-run blocked: {}", reason);
                             push_task_event(
                                 &task_event_feed,
                                 format!("sup queue blocked: {}", reason),
@@ -649,10 +1326,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 println!("\n^C received. No active request to interrupt. Use 'exit' to quit.");
                 continue;
             }
+            Some(joined) = side_runs.tasks.join_next(), if !side_runs.tasks.is_empty() => {
+                side_runs.finish(joined);
+                continue;
+            }
             result = async {
                 if let Some(task) = auto_queued_task.take() {
                     return UiInputEvent::RunQueued(task);
                 }
+                if let Some(line) = pending_inputs.pop() {
+                    let prompt = format!("queued[{} more]> ", pending_inputs.len());
+                    eprintln!("{}{}", ui_accent(&prompt), line);
+                    return UiInputEvent::Line(sanitize_single_line(&line));
+                }
+                if daemon_mode {
+                    tokio::time::sleep(DAEMON_POLL_INTERVAL).await;
+                    return UiInputEvent::Skip;
+                }
                 let running_tasks = task_list_snapshot
                     .iter()
                     .filter(|t| t.status == hypr_claw_tasks::TaskStatus::Running)
@@ -663,11 +1353,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     short_model_name(&config.model),
                     running_tasks
                 );
-                print!("{}", ui_accent(&prompt));
-                io::stdout().flush().ok();
+                // The prompt goes to stderr so stdout carries only the conversation.
+                eprint!("{}", ui_accent(&prompt));
+                io::stderr().flush().ok();
 
-                let mut input = String::new();
-                io::stdin().read_line(&mut input).ok();
+                let Some(input) = input::next_line().await else {
+                    return UiInputEvent::Line("exit".to_string());
+                };
                 let line = sanitize_single_line(input.trim());
                 if line.is_empty() {
                     UiInputEvent::Skip
@@ -686,15 +1378,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 };
 
                 if input.as_bytes().first() == Some(&0x1B) {
-                    println!("⚠ Ignored terminal escape input. Type a command or task request.");
+                    eprintln!("⚠ Ignored terminal escape input. Type a command or task request.");
                     continue;
                 }
-
                 if !input_from_queue {
-                    if let Some(msg) = removed_feature_notice(&input) {
-                        println!("{msg}");
+                    if let Some(notice) = deprecations::notice(&input) {
+                        if let deprecations::Notice::Removed { entry, .. } = &notice {
+                            usage_tracker.record_removed_command(entry.name);
+                        }
+                        println!("{}", notice.render());
                         continue;
                     }
+                    usage_tracker.record_command(&input);
                 }
 
                 if !input_from_queue {
@@ -731,6 +1426,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             &context,
                             &task_list,
                         );
+                        if let Some(status) = side_runs.in_flight.status_line() {
+                            println!("{}", status);
+                        }
                         println!();
                         continue;
                     }
@@ -759,20 +1457,55 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         print_capability_delta_history(&user_id, 20);
                         continue;
                     }
-                    "capabilities" | "/capabilities" => {
-                        print_capability_registry_summary(&user_id, &capability_registry);
+                    "capabilities gaps" | "/capabilities gaps" => {
+                        print_capability_gaps(&runtime_dispatcher.capability_gaps());
                         continue;
                     }
-                    "scan" | "/scan" => {
-                        if prompt_yes_no("Run a new system scan now? [Y/n] ", true)? {
-                            let deep_scan = prompt_yes_no(
-                                "Deep scan (home directory with user consent)? [Y/n] ",
-                                true,
-                            )?;
-
-                            let mut scanned_profile = scan::run_integrated_scan(&user_id, deep_scan).await?;
-
-                            print_system_profile_summary(&scanned_profile);
+                    "capabilities gaps clear" | "/capabilities gaps clear" => {
+                        match runtime_dispatcher.clear_capability_gaps() {
+                            Ok(()) => println!("✅ Capability gaps cleared"),
+                            Err(e) => eprintln!("⚠️  Failed to clear capability gaps: {}", e),
+                        }
+                        continue;
+                    }
+                    "capabilities input" | "/capabilities input" => {
+                        print_ydotool_health(
+                            &hypr_claw_tools::os_capabilities::ydotool::health().await,
+                        );
+                        continue;
+                    }
+                    "capabilities input setup" | "/capabilities input setup" => {
+                        setup_ydotool_service().await?;
+                        continue;
+                    }
+                    "capabilities" | "/capabilities" => {
+                        print_capability_registry_summary(&user_id, &capability_registry);
+                        let ydotool = hypr_claw_tools::os_capabilities::ydotool::health().await;
+                        if ydotool.client_installed && !ydotool.ready() {
+                            println!(
+                                "  ydotool is not ready: {}; run `capabilities input` for details.",
+                                ydotool.problems().join("; ")
+                            );
+                        }
+                        let gaps = runtime_dispatcher.capability_gaps();
+                        if !gaps.gaps.is_empty() {
+                            println!(
+                                "  {} capability gap(s) recorded; run `capabilities gaps` for details.\n",
+                                gaps.gaps.len()
+                            );
+                        }
+                        continue;
+                    }
+                    "scan" | "/scan" => {
+                        if prompt_yes_no("Run a new system scan now? [Y/n] ", true)? {
+                            let deep_scan = prompt_yes_no(
+                                "Deep scan (home directory with user consent)? [Y/n] ",
+                                true,
+                            )?;
+
+                            let mut scanned_profile = scan::run_integrated_scan(&user_id, deep_scan).await?;
+
+                            print_system_profile_summary(&scanned_profile);
                             let mut scanned_registry = build_capability_registry(&scanned_profile);
                             print_capability_registry_diff_summary(
                                 &capability_registry,
@@ -837,11 +1570,615 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         print_supervisor_queue_status(&agent_state);
                         continue;
                     }
+                    "explain" | "/explain" => {
+                        match &last_run {
+                            Some(report) => explain_last_run(&config, report).await,
+                            None => println!("No run to explain yet.\n"),
+                        }
+                        continue;
+                    }
+                    "triage" | "/triage" => {
+                        let Some(report) = last_run.as_ref().filter(|report| !report.succeeded())
+                        else {
+                            println!("Nothing to triage: the last run did not fail.\n");
+                            continue;
+                        };
+                        let events = action_log.load(report.run_id).unwrap_or_default();
+                        let fixes = run_triage(
+                            report,
+                            &events,
+                            &agent_state,
+                            approval_gate.prompt_from(),
+                            &config,
+                            &agent_loop,
+                        )
+                        .await?;
+                        for fix in fixes {
+                            pending_inputs.push(fix);
+                        }
+                        continue;
+                    }
+                    "thread list" | "/thread list" | "threads" | "/threads" => {
+                        print_thread_list(&agent_state);
+                        continue;
+                    }
+                    "history" | "/history" => {
+                        print_history(&agent_state, &context.recent_history, 20);
+                        continue;
+                    }
+                    "prompt inspect" | "/prompt inspect" => {
+                        let thread_key =
+                            thread_session_key(&session_key, &agent_state.active_thread_id);
+                        let history = match hypr_claw_runtime::SessionStore::load(
+                            async_session.as_ref(),
+                            &thread_key,
+                        )
+                        .await
+                        {
+                            Ok(history) => history,
+                            Err(e) => {
+                                println!("❌ Cannot load thread history: {}", e);
+                                continue;
+                            }
+                        };
+                        print_prompt_inspection(
+                            &agent_loop,
+                            &agent_name,
+                            &system_prompt,
+                            &context_registry,
+                            &turn_context(
+                                &agent_state,
+                                &capability_registry,
+                                &active_allowed_tools,
+                                &context.facts,
+                                &latest_task_list,
+                            ),
+                            &agent_state.autonomy_mode,
+                            &history,
+                        );
+                        continue;
+                    }
+                    "context analyze" | "/context analyze" => {
+                        let thread_key =
+                            thread_session_key(&session_key, &agent_state.active_thread_id);
+                        if let Err(e) = analyze_context(
+                            &agent_loop,
+                            async_session.as_ref(),
+                            &thread_key,
+                            &mut context.facts,
+                        )
+                        .await
+                        {
+                            println!("❌ Cannot analyze the context: {}", e);
+                            continue;
+                        }
+                        context_manager.save(&context).await?;
+                        continue;
+                    }
                     _ => {}
                 }
                 }
 
                 if !input_from_queue {
+                    if let Some(dir) = cd_argument(&input) {
+                        // The working directory is the process's, so it would
+                        // move under runs that are still resolving paths.
+                        let background_running = task_manager
+                            .list_tasks()
+                            .await
+                            .iter()
+                            .any(|t| t.status == hypr_claw_tasks::TaskStatus::Running);
+                        if background_running || !side_runs.tasks.is_empty() {
+                            println!("❌ cd {}: wait for the running tasks to finish", dir);
+                            continue;
+                        }
+                        match change_directory(dir) {
+                            Ok(cwd) => {
+                                println!("📂 {}", cwd.display());
+                                refresh_project_soul(&mut project);
+                            }
+                            Err(e) => println!("❌ cd {}: {}", dir, e),
+                        }
+                        continue;
+                    }
+                    if input == "pwd" || input == "/pwd" {
+                        match std::env::current_dir() {
+                            Ok(cwd) => println!("📂 {}", cwd.display()),
+                            Err(e) => println!("❌ {}", e),
+                        }
+                        continue;
+                    }
+                    if let Some(mode) = input
+                        .strip_prefix("citations ")
+                        .or_else(|| input.strip_prefix("/citations "))
+                        .map(str::trim)
+                    {
+                        let enabled = match mode {
+                            "on" | "required" => Some(true),
+                            "off" => Some(false),
+                            _ => None,
+                        };
+                        match enabled {
+                            Some(enabled) => {
+                                agent_loop.set_require_citations(enabled);
+                                config.response.require_citations = enabled;
+                                if let Err(e) = config.save() {
+                                    eprintln!("⚠️  Failed to save config: {}", e);
+                                }
+                                println!(
+                                    "✅ Citations {}",
+                                    if enabled { "required" } else { "off" }
+                                );
+                            }
+                            None => println!("Use: citations on | citations off"),
+                        }
+                        continue;
+                    }
+                    if let Some(arg) = input
+                        .strip_prefix("thread new")
+                        .or_else(|| input.strip_prefix("/thread new"))
+                        .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+                        .map(str::trim)
+                    {
+                        let mut thread = TaskThread::new(
+                            next_thread_id(&agent_state),
+                            if arg.is_empty() { "New thread" } else { arg }.to_string(),
+                        );
+                        thread.titled = !arg.is_empty();
+                        println!("🧵 Started thread {} ({})", thread.id, thread.title);
+                        agent_state.active_thread_id = thread.id.clone();
+                        agent_state.task_threads.push(thread);
+                        persist_agent_os_state(&mut context, &agent_state);
+                        context_manager.save(&context).await?;
+                        continue;
+                    }
+                    if let Some(id) = thread_switch_target(&input) {
+                        if !agent_state
+                            .task_threads
+                            .iter()
+                            .any(|thread| thread.id == id && !thread.archived)
+                        {
+                            println!("❌ No open thread '{}'", id);
+                            continue;
+                        }
+                        agent_state.active_thread_id = id.to_string();
+                        touch_active_thread(&mut agent_state);
+                        persist_agent_os_state(&mut context, &agent_state);
+                        context_manager.save(&context).await?;
+                        println!("🧵 Switched to thread {}", id);
+                        if let Some(status) = side_runs.in_flight.status_line() {
+                            println!("{}", status);
+                        }
+                        continue;
+                    }
+                    if let Some(arg) = input
+                        .strip_prefix("thread popout")
+                        .or_else(|| input.strip_prefix("/thread popout"))
+                        .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+                        .map(str::trim)
+                    {
+                        let id = if arg.is_empty() {
+                            agent_state.active_thread_id.clone()
+                        } else {
+                            arg.to_string()
+                        };
+                        if !agent_state
+                            .task_threads
+                            .iter()
+                            .any(|thread| thread.id == id && !thread.archived)
+                        {
+                            println!("❌ No open thread '{}'", id);
+                            continue;
+                        }
+                        match popout_thread(&capability_registry, &id).await {
+                            Ok(true) => println!("🪟 Opened thread {} on its special workspace", id),
+                            Ok(false) => println!("🪟 Toggled the popout for thread {}", id),
+                            Err(e) => println!("❌ Thread popout failed: {}", e),
+                        }
+                        continue;
+                    }
+                    if let Some(args) = input
+                        .strip_prefix("thread env")
+                        .or_else(|| input.strip_prefix("/thread env"))
+                        .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+                    {
+                        let command = match parse_thread_env_command(args) {
+                            Ok(command) => command,
+                            Err(e) => {
+                                println!("{}", e);
+                                continue;
+                            }
+                        };
+                        let active = agent_state.active_thread_id.clone();
+                        let Some(thread) = agent_state
+                            .task_threads
+                            .iter_mut()
+                            .find(|thread| thread.id == active)
+                        else {
+                            println!("No active thread.");
+                            continue;
+                        };
+                        let changed = match command {
+                            ThreadEnvCommand::Show => false,
+                            ThreadEnvCommand::Set(name, value) => {
+                                thread.env.insert(name, value);
+                                true
+                            }
+                            ThreadEnvCommand::Unset(name) => thread.env.remove(&name).is_some(),
+                            ThreadEnvCommand::Clear => {
+                                let had = !thread.env.is_empty();
+                                thread.env.clear();
+                                had
+                            }
+                        };
+                        if thread.env.is_empty() {
+                            println!("Thread '{}' adds no environment variables.", thread.title);
+                        } else {
+                            println!("Environment for processes started in '{}':", thread.title);
+                            let shown = hypr_claw_tools::sandbox::env_policy::redacted(&thread.env);
+                            for (name, value) in shown {
+                                println!("  {}={}", name, value);
+                            }
+                        }
+                        if changed {
+                            persist_agent_os_state(&mut context, &agent_state);
+                            context_manager.save(&context).await?;
+                        }
+                        continue;
+                    }
+                    if let Some(arg) = input
+                        .strip_prefix("stats")
+                        .or_else(|| input.strip_prefix("/stats"))
+                        .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+                    {
+                        match arg.trim() {
+                            "" => print_usage_stats(&usage_tracker, &active_allowed_tools),
+                            "on" | "off" => {
+                                let enabled = arg.trim() == "on";
+                                usage_tracker.set_enabled(enabled);
+                                config.analytics.enabled = enabled;
+                                if let Err(e) = config.save() {
+                                    eprintln!("⚠️  Failed to save config: {}", e);
+                                }
+                                println!(
+                                    "✅ Local analytics {} (stored in {}, never sent anywhere)",
+                                    if enabled { "on" } else { "off" },
+                                    usage_tracker.path().display()
+                                );
+                            }
+                            "clear" => match usage_tracker.clear() {
+                                Ok(()) => println!("✅ Local analytics cleared"),
+                                Err(e) => eprintln!("⚠️  Failed to clear analytics: {}", e),
+                            },
+                            _ => println!("Use: stats | stats on | stats off | stats clear"),
+                        }
+                        continue;
+                    }
+                    if let Some(arg) = input
+                        .strip_prefix("audit")
+                        .or_else(|| input.strip_prefix("/audit"))
+                        .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+                    {
+                        match parse_audit_query(arg, chrono::Utc::now().timestamp()) {
+                            Ok(query) => match audit_logger.query(&query) {
+                                Ok(entries) => print_audit_entries(&entries),
+                                Err(e) => eprintln!("⚠️  Failed to read audit log: {}", e),
+                            },
+                            Err(e) => println!("{e}"),
+                        }
+                        continue;
+                    }
+                    if let Some(arg) = input
+                        .strip_prefix("knowledge")
+                        .or_else(|| input.strip_prefix("/knowledge"))
+                        .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+                    {
+                        run_knowledge_command(&knowledge_store, arg).await;
+                        continue;
+                    }
+                    if let Some(arg) = input
+                        .strip_prefix("actions")
+                        .or_else(|| input.strip_prefix("/actions"))
+                        .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+                    {
+                        if let Err(e) = show_actions(&action_log, arg, &interrupt).await {
+                            println!("{e}");
+                        }
+                        continue;
+                    }
+                    if let Some((command, arg)) = ["capabilities export", "capabilities compare"]
+                        .into_iter()
+                        .find_map(|command| {
+                            input
+                                .strip_prefix(command)
+                                .or_else(|| input.strip_prefix(&format!("/{command}")))
+                                .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+                                .map(|rest| (command, rest.trim()))
+                        })
+                    {
+                        let tools =
+                            derive_runtime_allowed_tools(&registry_arc, &capability_registry);
+                        let local = capability_compare::export(
+                            &capability_registry,
+                            tools,
+                            &machine_host_name(),
+                        );
+                        if command == "capabilities export" {
+                            export_capabilities(&local, arg);
+                        } else {
+                            compare_capabilities(&local, arg);
+                        }
+                        continue;
+                    }
+                    if let Some(arg) = input
+                        .strip_prefix("artifacts")
+                        .or_else(|| input.strip_prefix("/artifacts"))
+                        .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+                    {
+                        run_artifacts_command(&config.retention, arg);
+                        continue;
+                    }
+                    if input == "preset" || input == "/preset" {
+                        print_preset(&agent_state, approval_gate.prompt_from());
+                        continue;
+                    }
+                    if let Some(args) = input
+                        .strip_prefix("policy")
+                        .or_else(|| input.strip_prefix("/policy"))
+                        .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+                    {
+                        let classes = SupervisedTaskClass::ALL.map(|class| class.as_str());
+                        match supervision::parse_command(args, &classes) {
+                            Ok(supervision::PolicyCommand::Show) => {
+                                print_supervision_policy(&agent_state, approval_gate.prompt_from());
+                            }
+                            Ok(supervision::PolicyCommand::Set(scope, level)) => {
+                                if let Some(thread) = scope.thread.as_deref().filter(|id| {
+                                    !agent_state.task_threads.iter().any(|t| &t.id == id)
+                                }) {
+                                    println!("❌ Unknown thread '{}'", thread);
+                                    continue;
+                                }
+                                println!("✅ {}: {}", scope.describe(), level.as_str());
+                                agent_state.supervision.set(scope, level);
+                                persist_agent_os_state(&mut context, &agent_state);
+                                context_manager.save(&context).await?;
+                            }
+                            Ok(supervision::PolicyCommand::Unset(scope)) => {
+                                if agent_state.supervision.unset(&scope) {
+                                    persist_agent_os_state(&mut context, &agent_state);
+                                    context_manager.save(&context).await?;
+                                    println!("✅ Removed the rule for {}", scope.describe());
+                                } else {
+                                    println!("No rule for {}", scope.describe());
+                                }
+                            }
+                            Err(e) => println!("{}", e),
+                        }
+                        continue;
+                    }
+                    if let Some(name) = input
+                        .strip_prefix("preset switch")
+                        .or_else(|| input.strip_prefix("/preset switch"))
+                    {
+                        let Some(preset) = OnboardingPreset::parse(name) else {
+                            println!("Use: preset switch cautious | balanced | power_user");
+                            continue;
+                        };
+                        apply_preset(&mut agent_state, preset);
+                        approval_gate.set_prompt_from(preset.approval_tier());
+                        persist_agent_os_state(&mut context, &agent_state);
+                        context_manager.save(&context).await?;
+                        println!("✅ Switched to {} preset", preset.as_str());
+                        print_preset(&agent_state, approval_gate.prompt_from());
+                        continue;
+                    }
+                    if let Some(arg) = input
+                        .strip_prefix("capabilities install")
+                        .or_else(|| input.strip_prefix("/capabilities install"))
+                    {
+                        let backend = match resolve_install_target(
+                            arg,
+                            &runtime_dispatcher.capability_gaps(),
+                        ) {
+                            Ok(backend) => backend,
+                            Err(msg) => {
+                                println!("{}", msg);
+                                continue;
+                            }
+                        };
+                        let Some(manager) =
+                            hypr_claw_tools::os_capabilities::system::package_manager().await
+                        else {
+                            println!(
+                                "❌ No supported package manager found (pacman, apt-get, dnf, zypper)."
+                            );
+                            continue;
+                        };
+                        let packages = packages_for_backend(&backend, manager);
+                        let unlocks = registry_arc
+                            .list()
+                            .into_iter()
+                            .filter(|tool| backends_for_tool(tool).contains(&backend.as_str()))
+                            .collect::<Vec<String>>();
+                        println!("\n{}", ui_title("Backend install"));
+                        println!("  backend  : {}", backend);
+                        println!("  manager  : {}", manager);
+                        println!("  packages : {}", packages.join(" "));
+                        println!(
+                            "  unlocks  : {}",
+                            truncate_for_table(&unlocks.join(", "), 92)
+                        );
+                        if !prompt_yes_no("Install these packages now? [y/N] ", false)? {
+                            println!("⏭ Install skipped.");
+                            continue;
+                        }
+                        if let Err(e) = hypr_claw_runtime::ToolDispatcher::execute(
+                            runtime_dispatcher.as_ref(),
+                            "system.package_install",
+                            &json!({ "packages": packages }),
+                            &session_key,
+                        )
+                        .await
+                        {
+                            eprintln!("❌ Install failed: {}", e);
+                            continue;
+                        }
+
+                        rescan_capability_registry(
+                            &user_id,
+                            &mut agent_state,
+                            &mut capability_registry,
+                        )
+                        .await?;
+                        configure_ocr(&config.ocr, &capability_registry);
+                        active_allowed_tools =
+                            derive_runtime_allowed_tools(&registry_arc, &capability_registry);
+                        runtime_registry.set_allowed_tools(active_allowed_tools.clone());
+                        runtime_dispatcher.set_available_tools(active_allowed_tools.clone());
+                        let resolved = match runtime_dispatcher.resolve_capability_gaps(&backend) {
+                            Ok(resolved) => resolved,
+                            Err(e) => {
+                                eprintln!("⚠️  Failed to update capability gaps: {}", e);
+                                0
+                            }
+                        };
+                        persist_agent_os_state(&mut context, &agent_state);
+                        context_manager.save(&context).await?;
+                        println!(
+                            "✅ {} installed; {} tools available, {} gap(s) resolved",
+                            backend,
+                            active_allowed_tools.len(),
+                            resolved
+                        );
+                        if backend == "ydotool"
+                            && !hypr_claw_tools::os_capabilities::ydotool::health()
+                                .await
+                                .ready()
+                        {
+                            setup_ydotool_service().await?;
+                        }
+                        continue;
+                    }
+                    if let Some(arg) = input
+                        .strip_prefix("setup apply")
+                        .or_else(|| input.strip_prefix("/setup apply"))
+                        .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+                        .map(str::trim)
+                    {
+                        if arg.is_empty() {
+                            println!("Usage: setup apply <manifest.yaml>");
+                            continue;
+                        }
+                        let manifest =
+                            match setup_manifest::SetupManifest::load(std::path::Path::new(arg)) {
+                                Ok(manifest) => manifest,
+                                Err(e) => {
+                                    println!("❌ Invalid setup manifest {arg}: {e}");
+                                    continue;
+                                }
+                            };
+                        let manager = hypr_claw_tools::os_capabilities::system::package_manager()
+                            .await
+                            .unwrap_or("pacman");
+                        let backend_packages = |backend: &str| {
+                            INSTALLABLE_BACKENDS
+                                .contains(&backend)
+                                .then(|| packages_for_backend(backend, manager))
+                        };
+                        let mut previous: Option<setup_manifest::SetupPlan> = None;
+                        for round in 1..=SETUP_MAX_ROUNDS {
+                            let observed = observe_setup_state(
+                                &manifest,
+                                &capability_registry,
+                                &active_allowed_tools,
+                            )
+                            .await;
+                            let plan = setup_manifest::plan(
+                                &manifest,
+                                &observed,
+                                backends_for_tool,
+                                backend_packages,
+                            );
+                            if plan.steps.is_empty() {
+                                break;
+                            }
+                            if previous.as_ref() == Some(&plan) {
+                                println!("⚠️  The last round changed nothing; stopping.");
+                                break;
+                            }
+                            println!("\n{}", ui_title(&format!("Setup round {round}")));
+                            for step in &plan.steps {
+                                println!("  • {}", step.describe());
+                            }
+                            if round == 1
+                                && !prompt_yes_no("Apply these steps? [y/N] ", false)?
+                            {
+                                println!("⏭ Setup skipped.");
+                                break;
+                            }
+                            let mut installed = false;
+                            for step in &plan.steps {
+                                match run_setup_step(
+                                    runtime_dispatcher.as_ref(),
+                                    step,
+                                    &session_key,
+                                )
+                                .await
+                                {
+                                    Ok(()) => println!("✅ {}", step.describe()),
+                                    Err(e) => println!("❌ {}: {}", step.describe(), e),
+                                }
+                                installed |= matches!(
+                                    step,
+                                    setup_manifest::SetupStep::InstallPackages { .. }
+                                );
+                            }
+                            if installed {
+                                rescan_capability_registry(
+                                    &user_id,
+                                    &mut agent_state,
+                                    &mut capability_registry,
+                                )
+                                .await?;
+                                active_allowed_tools = derive_runtime_allowed_tools(
+                                    &registry_arc,
+                                    &capability_registry,
+                                );
+                                runtime_registry
+                                    .set_allowed_tools(active_allowed_tools.clone());
+                                runtime_dispatcher
+                                    .set_available_tools(active_allowed_tools.clone());
+                                persist_agent_os_state(&mut context, &agent_state);
+                                context_manager.save(&context).await?;
+                            }
+                            previous = Some(plan);
+                        }
+                        let observed = observe_setup_state(
+                            &manifest,
+                            &capability_registry,
+                            &active_allowed_tools,
+                        )
+                        .await;
+                        let plan = setup_manifest::plan(
+                            &manifest,
+                            &observed,
+                            backends_for_tool,
+                            backend_packages,
+                        );
+                        if plan.is_done() {
+                            println!("✅ This machine matches {arg}");
+                        } else {
+                            println!("⚠️  Still different from {arg}:");
+                            for step in &plan.steps {
+                                println!("  • {}", step.describe());
+                            }
+                            for problem in &plan.unresolvable {
+                                println!("  • {problem}");
+                            }
+                        }
+                        continue;
+                    }
                     if let Some(mode) = input
                         .strip_prefix("view ")
                         .or_else(|| input.strip_prefix("/view "))
@@ -901,13 +2238,455 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
                 }
 
-                if !input_from_queue && (input == "queue clear" || input == "/queue clear") {
-                    let cleared = cancel_queued_supervised_tasks(&mut agent_state);
-                    persist_agent_os_state(&mut context, &agent_state);
-                    context_manager.save(&context).await?;
-                    push_task_event(&task_event_feed, format!("sup queue cleared {} queued tasks", cleared));
-                    println!("🧹 Cleared {} queued supervisor tasks", cleared);
-                    continue;
+                if !input_from_queue {
+                    if let Some(raw) = input
+                        .strip_prefix("queue export")
+                        .or_else(|| input.strip_prefix("/queue export"))
+                        .map(str::trim)
+                    {
+                        let yaml = match export_supervisor_queue(&agent_state).to_yaml() {
+                            Ok(yaml) => yaml,
+                            Err(e) => {
+                                println!("❌ Failed to export queue: {}", e);
+                                continue;
+                            }
+                        };
+                        if raw.is_empty() {
+                            print!("{}", yaml);
+                        } else if let Err(e) = std::fs::write(raw, yaml) {
+                            println!("❌ Failed to write {}: {}", raw, e);
+                        } else {
+                            println!("📤 Exported queued supervisor tasks to {}", raw);
+                        }
+                        continue;
+                    }
+                    if let Some(path) = input
+                        .strip_prefix("queue import")
+                        .or_else(|| input.strip_prefix("/queue import"))
+                        .map(str::trim)
+                    {
+                        if path.is_empty() {
+                            println!("Usage: queue import <file.yaml>");
+                            continue;
+                        }
+                        let imported = std::fs::read_to_string(path)
+                            .map_err(|e| format!("cannot read {}: {}", path, e))
+                            .and_then(|yaml| queue_file::QueueFile::parse(&yaml))
+                            .and_then(|file| import_supervisor_queue(&mut agent_state, &file));
+                        let ids = match imported {
+                            Ok(ids) => ids,
+                            Err(e) => {
+                                println!("❌ Queue import failed: {}", e);
+                                continue;
+                            }
+                        };
+                        persist_agent_os_state(&mut context, &agent_state);
+                        context_manager.save(&context).await?;
+                        push_task_event(
+                            &task_event_feed,
+                            format!("sup queue imported {} tasks from {}", ids.len(), path),
+                        );
+                        println!("📥 Queued {} tasks from {}: {}", ids.len(), path, ids.join(", "));
+                        continue;
+                    }
+                }
+
+                if !input_from_queue
+                    && matches!(
+                        input.as_str(),
+                        "contacts" | "/contacts" | "contacts list" | "/contacts list"
+                    )
+                {
+                    print_contacts(&contact_store.list());
+                    continue;
+                }
+
+                if !input_from_queue {
+                    if let Some(args) = input
+                        .strip_prefix("contacts add ")
+                        .or_else(|| input.strip_prefix("/contacts add "))
+                        .map(str::trim)
+                    {
+                        let Some((name, fields)) = parse_contact_add_args(args) else {
+                            println!("Usage: contacts add <name> [email=<addr>] [telegram=<@handle>] [phone=<number>]");
+                            continue;
+                        };
+                        let mut contact = contact_store.get(&name).unwrap_or_else(|| {
+                            hypr_claw_tools::contacts::Contact {
+                                name: name.clone(),
+                                ..Default::default()
+                            }
+                        });
+                        if let Some(err) = fields
+                            .iter()
+                            .find_map(|(field, value)| contact.set_field(field, value).err())
+                        {
+                            println!("❌ {}", err);
+                            continue;
+                        }
+                        match contact_store.upsert(contact.clone()) {
+                            Ok(()) => println!("✅ Saved contact {}", contact.name),
+                            Err(e) => println!("❌ Failed to save contact: {}", e),
+                        }
+                        continue;
+                    }
+                }
+
+                if !input_from_queue {
+                    if let Some(name) = input
+                        .strip_prefix("contacts remove ")
+                        .or_else(|| input.strip_prefix("/contacts remove "))
+                        .map(str::trim)
+                    {
+                        match contact_store.remove(name) {
+                            Ok(true) => println!("🧹 Removed contact {}", name),
+                            Ok(false) => println!("❌ No contact named '{}'", name),
+                            Err(e) => println!("❌ Failed to remove contact: {}", e),
+                        }
+                        continue;
+                    }
+                }
+
+                if !input_from_queue
+                    && matches!(
+                        input.as_str(),
+                        "skills" | "/skills" | "skill list" | "/skill list"
+                    )
+                {
+                    print_skills(&registry_arc);
+                    continue;
+                }
+
+                if !input_from_queue {
+                    if let Some(name) = input
+                        .strip_prefix("skill learn")
+                        .or_else(|| input.strip_prefix("/skill learn"))
+                        .map(str::trim)
+                    {
+                        if name.is_empty() {
+                            println!("Usage: skill learn <name>");
+                            continue;
+                        }
+                        let Some((prompt, calls)) = &learnable_run else {
+                            println!(
+                                "No run to learn from. Finish a run with at least {} successful tool calls first.",
+                                MIN_LEARNED_SKILL_STEPS
+                            );
+                            continue;
+                        };
+                        match draft_learned_skill(name, prompt, calls, &registry_arc) {
+                            Ok((path, yaml)) => {
+                                println!("{}", yaml);
+                                println!("📝 Draft saved to {}", path.display());
+                                if yaml.contains("\npermissions:") {
+                                    println!(
+                                        "   Its permissions are granted without prompting on every run; review them."
+                                    );
+                                }
+                                println!("   Review or edit it, then: skill save {}", name);
+                            }
+                            Err(e) => println!("❌ Cannot draft skill: {}", e),
+                        }
+                        continue;
+                    }
+                    if let Some(name) = input
+                        .strip_prefix("skill save")
+                        .or_else(|| input.strip_prefix("/skill save"))
+                        .map(str::trim)
+                    {
+                        if name.is_empty() {
+                            println!("Usage: skill save <name>");
+                            continue;
+                        }
+                        match save_skill_draft(name, &registry_arc) {
+                            Ok(path) => println!(
+                                "✅ Saved skill {} to {}; it is available as skill.{} after a restart",
+                                name,
+                                path.display(),
+                                name
+                            ),
+                            Err(e) => println!("❌ Cannot save skill: {}", e),
+                        }
+                        continue;
+                    }
+                    if let Some(prompt) = input
+                        .strip_prefix("preview ")
+                        .or_else(|| input.strip_prefix("/preview "))
+                        .or_else(|| input.strip_suffix(" --preview"))
+                        .map(str::trim)
+                        .filter(|prompt| !prompt.is_empty())
+                    {
+                        let class = classify_supervised_task_class(prompt);
+                        let focused = focused_tools_for_input(prompt, &active_allowed_tools);
+                        let use_focused = !strict_workflow_enabled()
+                            && use_focused_tools(
+                                &focused,
+                                &active_allowed_tools,
+                                &agent_state.autonomy_mode,
+                            );
+                        let turn_prompt = augment_system_prompt_for_turn(
+                            &system_prompt,
+                            &context_registry,
+                            &turn_context(
+                                &agent_state,
+                                &capability_registry,
+                                &active_allowed_tools,
+                                &context.facts,
+                                &latest_task_list,
+                            ),
+                            &agent_state.autonomy_mode,
+                        );
+                        if use_focused {
+                            runtime_registry.set_allowed_tools(focused);
+                        }
+                        let preview = agent_loop
+                            .preview_request(
+                                &thread_session_key(&session_key, &agent_state.active_thread_id),
+                                &agent_name,
+                                &turn_prompt,
+                                prompt,
+                            )
+                            .await;
+                        if use_focused {
+                            runtime_registry.set_allowed_tools(active_allowed_tools.clone());
+                        }
+                        match preview {
+                            Ok(preview) => print_request_preview(
+                                &preview,
+                                &config,
+                                &class,
+                                active_soul
+                                    .max_iterations
+                                    .min(
+                                        execution_budget_for_class(&class, &agent_state.autonomy_mode)
+                                            .max_iterations,
+                                    )
+                                    .max(1),
+                            ),
+                            Err(e) => println!("❌ Cannot compose the request: {}", e),
+                        }
+                        continue;
+                    }
+                    // Only `do <number>`, so prompts like "do you ..." still run.
+                    if let Some(n) = input
+                        .strip_prefix("do ")
+                        .or_else(|| input.strip_prefix("/do "))
+                        .and_then(|raw| raw.trim().parse::<usize>().ok())
+                    {
+                        let Some(command) = n.checked_sub(1).and_then(|i| suggested_commands.get(i))
+                        else {
+                            println!(
+                                "❌ No suggestion {} ({} available from the last answer)",
+                                n,
+                                suggested_commands.len()
+                            );
+                            continue;
+                        };
+                        match skill_runner
+                            .dispatch(
+                                session_key.clone(),
+                                "proc.spawn".to_string(),
+                                suggestions::spawn_input(command),
+                            )
+                            .await
+                        {
+                            Ok(result) if result.success => println!(
+                                "▶️  Started {} (pid {})\n",
+                                command,
+                                result
+                                    .output
+                                    .as_ref()
+                                    .and_then(|output| output.get("pid"))
+                                    .map(Value::to_string)
+                                    .unwrap_or_else(|| "?".to_string())
+                            ),
+                            Ok(result) => println!(
+                                "❌ {} failed: {}\n",
+                                command,
+                                result.error.unwrap_or_default()
+                            ),
+                            Err(e) => println!("❌ {} failed: {}\n", command, e),
+                        }
+                        continue;
+                    }
+                    if let Some(raw) = input
+                        .strip_prefix("skill run ")
+                        .or_else(|| input.strip_prefix("/skill run "))
+                        .map(str::trim)
+                    {
+                        let Some((name, params)) = parse_skill_run_args(raw) else {
+                            println!("Usage: skill run <name> [param=value ...]");
+                            continue;
+                        };
+                        let tool_name =
+                            format!("{}{}", hypr_claw_tools::skills::SKILL_TOOL_PREFIX, name);
+                        if registry_arc.get(&tool_name).is_none() {
+                            println!("❌ Unknown skill '{}'. Use: skills", name);
+                            continue;
+                        }
+                        match skill_runner
+                            .dispatch(session_key.clone(), tool_name, params)
+                            .await
+                        {
+                            Ok(result) => print_skill_result(&name, &result),
+                            Err(e) => println!("❌ Skill {} failed: {}", name, e),
+                        }
+                        continue;
+                    }
+                }
+
+                if !input_from_queue && (input == "reminder list" || input == "/reminder list") {
+                    print_reminders(&reminder_store.list());
+                    continue;
+                }
+
+                if !input_from_queue {
+                    if let Some(id) = input
+                        .strip_prefix("reminder cancel ")
+                        .or_else(|| input.strip_prefix("/reminder cancel "))
+                        .map(str::trim)
+                    {
+                        match reminder_store.cancel(id) {
+                            Ok(true) => {
+                                push_task_event(&task_event_feed, format!("reminder {} cancelled", id));
+                                println!("🧹 Cancelled reminder {}", id);
+                            }
+                            Ok(false) => println!("❌ No pending reminder with id '{}'", id),
+                            Err(e) => println!("❌ Failed to cancel reminder: {}", e),
+                        }
+                        continue;
+                    }
+                }
+
+                if !input_from_queue {
+                    if let Some(args) = input
+                        .strip_prefix("reminder add ")
+                        .or_else(|| input.strip_prefix("/reminder add "))
+                        .map(str::trim)
+                    {
+                        let Some(request) = parse_reminder_add_args(args) else {
+                            println!("Usage: reminder add <10m|1h30m|HH:MM> <message> [--speak] [--then <task prompt>]");
+                            continue;
+                        };
+                        let now = chrono::Utc::now().timestamp();
+                        let Some(due_at) = hypr_claw_tools::reminders::resolve_due_at(&request.when, now)
+                        else {
+                            println!("❌ Invalid time '{}'. Use e.g. 10m, 1h30m or HH:MM", request.when);
+                            continue;
+                        };
+                        match reminder_store.add(
+                            &request.message,
+                            due_at,
+                            request.speak,
+                            request.follow_up_prompt,
+                        ) {
+                            Ok(reminder) => {
+                                push_task_event(
+                                    &task_event_feed,
+                                    format!(
+                                        "reminder {} set for {} {}",
+                                        reminder.id,
+                                        format_timestamp(reminder.due_at),
+                                        truncate_for_table(&reminder.message, 44)
+                                    ),
+                                );
+                                println!(
+                                    "⏰ Reminder {} set for {}",
+                                    reminder.id,
+                                    format_timestamp(reminder.due_at)
+                                );
+                            }
+                            Err(e) => println!("❌ Failed to save reminder: {}", e),
+                        }
+                        continue;
+                    }
+                }
+
+                if !input_from_queue && (input == "forms" || input == "/forms") {
+                    print_form_profiles(&form_memory.profiles());
+                    continue;
+                }
+
+                if !input_from_queue {
+                    if let Some(name) = input
+                        .strip_prefix("forms forget ")
+                        .or_else(|| input.strip_prefix("/forms forget "))
+                        .map(str::trim)
+                    {
+                        match form_memory.forget(name) {
+                            Ok(true) => println!("🧹 Forgot form '{}'", name),
+                            Ok(false) => println!("❌ No saved form named '{}'", name),
+                            Err(e) => println!("❌ Failed to update saved forms: {}", e),
+                        }
+                        continue;
+                    }
+                }
+
+                if !input_from_queue && (input == "questions" || input == "/questions") {
+                    print_pending_questions(&question_inbox.pending());
+                    continue;
+                }
+
+                if !input_from_queue {
+                    if let Some((id, reply)) = input
+                        .strip_prefix("answer ")
+                        .or_else(|| input.strip_prefix("/answer "))
+                        .and_then(|rest| rest.trim().split_once(' '))
+                    {
+                        let answered = match question_inbox.answer(id, reply) {
+                            Ok(Some(question)) => question,
+                            Ok(None) => {
+                                println!("❌ No pending question with id '{}'", id);
+                                continue;
+                            }
+                            Err(e) => {
+                                println!("❌ Failed to save answer: {}", e);
+                                continue;
+                            }
+                        };
+                        let answer = answered.answer.clone().unwrap_or_default();
+                        let asked_by = answered.task.as_deref().and_then(|task_id| {
+                            agent_state
+                                .supervisor
+                                .tasks
+                                .iter()
+                                .find(|task| task.id == task_id)
+                                .cloned()
+                        });
+                        let Some(task) = asked_by else {
+                            println!("✅ Answered {}: {}", answered.id, answer);
+                            continue;
+                        };
+                        let prompt =
+                            answered_question_prompt(&task.prompt, &answered.question, &answer);
+                        let task_id =
+                            enqueue_supervised_task(&mut agent_state, prompt, task.class.clone());
+                        if let Some(retry) = agent_state
+                            .supervisor
+                            .tasks
+                            .iter_mut()
+                            .find(|t| t.id == task_id)
+                        {
+                            retry.attempt_log = task.attempt_log.clone();
+                        }
+                        persist_agent_os_state(&mut context, &agent_state);
+                        context_manager.save(&context).await?;
+                        push_task_event(
+                            &task_event_feed,
+                            format!("sup {} queued with answer to {}", task_id, answered.id),
+                        );
+                        println!("✅ Answered {}; {} continues as {}", answered.id, task.id, task_id);
+                        continue;
+                    }
+                }
+
+                if !input_from_queue && (input == "queue clear" || input == "/queue clear") {
+                    let cleared = cancel_queued_supervised_tasks(&mut agent_state);
+                    persist_agent_os_state(&mut context, &agent_state);
+                    context_manager.save(&context).await?;
+                    push_task_event(&task_event_feed, format!("sup queue cleared {} queued tasks", cleared));
+                    println!("🧹 Cleared {} queued supervisor tasks", cleared);
+                    continue;
                 }
 
                 if !input_from_queue {
@@ -1250,11 +3029,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             }
                             let mut retried = 0usize;
                             for task in candidates {
-                                let retry_id = enqueue_supervised_task(
-                                    &mut agent_state,
-                                    task.prompt.clone(),
-                                    task.class.clone(),
-                                );
+                                let retry_id = enqueue_supervised_retry(&mut agent_state, &task);
                                 push_task_event(
                                     &task_event_feed,
                                     format!("sup {} retried as {}", task.id, retry_id),
@@ -1280,11 +3055,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             }
                             let mut retried = 0usize;
                             for task in candidates {
-                                let retry_id = enqueue_supervised_task(
-                                    &mut agent_state,
-                                    task.prompt.clone(),
-                                    task.class.clone(),
-                                );
+                                let retry_id = enqueue_supervised_retry(&mut agent_state, &task);
                                 push_task_event(
                                     &task_event_feed,
                                     format!("sup {} retried as {}", task.id, retry_id),
@@ -1314,11 +3085,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             }
                             let mut retried = 0usize;
                             for task in candidates {
-                                let retry_id = enqueue_supervised_task(
-                                    &mut agent_state,
-                                    task.prompt.clone(),
-                                    task.class.clone(),
-                                );
+                                let retry_id = enqueue_supervised_retry(&mut agent_state, &task);
                                 push_task_event(
                                     &task_event_feed,
                                     format!("sup {} retried as {}", task.id, retry_id),
@@ -1354,11 +3121,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             );
                             continue;
                         }
-                        let retry_id = enqueue_supervised_task(
-                            &mut agent_state,
-                            task.prompt.clone(),
-                            task.class.clone(),
-                        );
+                        let retry_id = enqueue_supervised_retry(&mut agent_state, &task);
                         push_task_event(
                             &task_event_feed,
                             format!("sup {} retried as {}", target, retry_id),
@@ -1383,33 +3146,56 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                 let task_prompt = task.prompt.clone();
                                 let task_id = task.id.clone();
                                 let task_class = task.class.clone();
-                                let timeout_bg =
-                                    watchdog_timeout_for_class(&task_class, &agent_state.autonomy_mode);
+                                let task_session_key = format!("{}::sup::{}", session_key, task_id);
+                                let (mode_bg, supervision_bg) = apply_supervision(
+                                    &agent_state,
+                                    &approval_gate,
+                                    &task_session_key,
+                                    &task_class,
+                                );
                                 let max_iter_bg = active_soul
                                     .max_iterations
                                     .min(
-                                        execution_budget_for_class(
-                                            &task_class,
-                                            &agent_state.autonomy_mode,
-                                        )
-                                        .max_iterations,
+                                        execution_budget_for_class(&task_class, &mode_bg)
+                                            .max_iterations,
                                     )
                                     .max(1);
+                                let timeout_bg = watchdog_with_tool_latency(
+                                    watchdog_timeout_for_class(&task_class, &mode_bg),
+                                    max_iter_bg,
+                                    &active_allowed_tools,
+                                    &hypr_claw_runtime::metrics::tool_latency_snapshot(),
+                                );
                                 let provider_bg = config.provider.clone();
                                 let model_bg = config.model.clone();
+                                let citations_bg = agent_loop.require_citations();
+                                let verify_bg =
+                                    verification_required(&task_class, &mode_bg, &config);
                                 let async_session_bg = async_session.clone();
                                 let async_locks_bg = async_locks.clone();
                                 let runtime_dispatcher_bg = runtime_dispatcher.clone();
                                 let registry_arc_bg = registry_arc.clone();
                                 let allowed_tools_bg = active_allowed_tools.clone();
-                                let task_session_key = format!("{}::sup::{}", session_key, task_id);
+                                runtime_dispatcher.set_task_label(&task_session_key, &task_prompt);
+                                runtime_dispatcher.set_env_overlay(
+                                    &task_session_key,
+                                    active_thread_env(&agent_state),
+                                );
                                 let agent_name_bg = agent_name.clone();
-                                let system_prompt_bg = augment_system_prompt_for_turn(
-                                    &system_prompt,
-                                    &agent_state.onboarding.system_profile,
-                                    &capability_registry,
-                                    &active_allowed_tools,
-                                    &agent_state.autonomy_mode,
+                                let system_prompt_bg = with_supervision_instructions(
+                                    augment_system_prompt_for_turn(
+                                        &system_prompt,
+                                        &context_registry,
+                                        &turn_context(
+                                            &agent_state,
+                                            &capability_registry,
+                                            &active_allowed_tools,
+                                            &context.facts,
+                                            &latest_task_list,
+                                        ),
+                                        &mode_bg,
+                                    ),
+                                    supervision_bg,
                                 );
 
                                 let spawn_result = task_manager
@@ -1434,13 +3220,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                         let agent_loop_bg = hypr_claw_runtime::AgentLoop::new(
                                             async_session_bg,
                                             async_locks_bg,
-                                            runtime_dispatcher_bg,
+                                            runtime_dispatcher_bg.clone(),
                                             runtime_registry_bg,
                                             llm_client,
                                             compactor,
                                             max_iter_bg,
                                         );
-                                        match tokio::time::timeout(
+                                        agent_loop_bg.set_require_citations(citations_bg);
+                                        agent_loop_bg.set_require_verification(verify_bg);
+                                        let result = match tokio::time::timeout(
                                             timeout_bg,
                                             agent_loop_bg.run(
                                                 &task_session_key,
@@ -1457,7 +3245,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                                 "Execution watchdog timeout after {}s",
                                                 timeout_bg.as_secs()
                                             )),
-                                        }
+                                        };
+                                        let changes =
+                                            runtime_dispatcher_bg.take_changes(&task_session_key);
+                                        result
+                                            .map(|response| with_run_changes(response, &changes))
+                                            .map_err(|e| with_run_changes(e, &changes))
                                     })
                                     .await;
 
@@ -1478,7 +3271,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                                 truncate_for_table(&task.resources.join(","), 22)
                                             ),
                                         );
-                                        println!("▶ Started {} in background", task_id);
+                                        eprintln!("▶ Started {} in background", task_id);
                                     }
                                     Err(e) => {
                                         let err = e.to_string();
@@ -1523,7 +3316,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         }
                         QueueStartResult::Blocked(reason) => {
                             push_task_event(&task_event_feed, format!("sup queue blocked: {}", reason));
-                            println!("⏸ Cannot start queued task: {}", reason);
+                            eprintln!("⏸ Cannot start queued task: {}", reason);
                             continue;
                         }
                         QueueStartResult::Empty => {
@@ -1539,20 +3332,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         .current_model()
                         .unwrap_or_else(|| config.model.clone());
                     println!("\n🧠 Current model: {}", current_model);
-                    println!("Fetching provider models...");
-                    match agent_loop.list_models().await {
-                        Ok(models) => {
-                            let candidates = filter_agentic_models(&models);
+                    match provider_models(&config, &agent_loop, false).await {
+                        Ok((models, source)) => {
+                            let candidates = filter_agentic_models(&model_ids(&models));
                             if candidates.is_empty() {
                                 println!("No models returned by provider.");
                                 println!();
                                 continue;
                             }
                             let limit = candidates.len().min(20);
-                            println!("Top agent-friendly models:");
+                            println!("Top agent-friendly models ({source}):");
                             for (i, model) in candidates.iter().take(limit).enumerate() {
                                 let marker = if model == &current_model { "*" } else { " " };
-                                println!("  {} {:>2}. {}", marker, i + 1, model);
+                                println!(
+                                    "  {} {:>2}. {:<44} {}",
+                                    marker,
+                                    i + 1,
+                                    model,
+                                    ui_dim(&model_label(&models, model))
+                                );
                             }
                             println!("\nUse '/models set <model_id>' to switch.");
                             let choice = prompt_line("Select model number (Enter to keep): ")?;
@@ -1588,13 +3386,43 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     continue;
                 }
 
-                if input == "/models list" || input == "models list" {
-                    match agent_loop.list_models().await {
-                        Ok(models) => {
-                            let filtered = filter_agentic_models(&models);
-                            println!("\n📦 Provider models ({}):", filtered.len());
+                if input == "config check" || input == "/config check" {
+                    let mut issues = config.validate().err().unwrap_or_default();
+                    issues.extend(bootstrap::api_key_issue(&config.provider));
+                    if let Ok(models) = agent_loop.list_models().await {
+                        issues.extend(config.check_model(&models));
+                    }
+                    if issues.is_empty() {
+                        println!("✅ Configuration looks good.\n");
+                        continue;
+                    }
+                    print_config_issues(&issues);
+                    if let Some(model) = fix_config_issues(&config, &issues).await? {
+                        if let Err(e) = apply_model_switch(
+                            &model,
+                            &agent_loop,
+                            &mut config,
+                            &context_manager,
+                            &mut context,
+                        )
+                        .await
+                        {
+                            eprintln!("❌ Failed to switch model: {}", e);
+                        }
+                    }
+                    println!();
+                    continue;
+                }
+
+                let models_refresh = input == "/models refresh" || input == "models refresh";
+                if input == "/models list" || input == "models list" || models_refresh {
+                    match provider_models(&config, &agent_loop, models_refresh).await {
+                        Ok((models, source)) => {
+                            let filtered = filter_agentic_models(&model_ids(&models));
+                            println!("\n📦 Provider models ({}, {}):", filtered.len(), source);
                             for model in filtered.iter().take(60) {
-                                println!("  {}", model);
+                                let label = model_label(&models, model);
+                                println!("  {:<46} {}", model, ui_dim(&label));
                             }
                             if filtered.len() > 60 {
                                 println!("  ... and {} more", filtered.len() - 60);
@@ -1632,13 +3460,27 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     continue;
                 }
 
-                let (effective_input, task_class, supervisor_task_id) =
-                    if let Some(queued_task) = queued_execution.take() {
-                        println!(
-                            "▶ Running queued task {} ({})",
+                if side_runs.in_flight.is_busy(&agent_state.active_thread_id) {
+                    println!(
+                        "⏳ Thread {} still has a run in flight; wait for it or `thread switch` \
+                         to another thread.",
+                        agent_state.active_thread_id
+                    );
+                    if let Some(status) = side_runs.in_flight.status_line() {
+                        println!("{}", status);
+                    }
+                    continue;
+                }
+
+                let mut previous_attempt: Option<String> = None;
+                let (effective_input, task_class, supervisor_task_id) =
+                    if let Some(queued_task) = queued_execution.take() {
+                        println!(
+                            "▶ Running queued task {} ({})",
                             queued_task.id,
                             queued_task.class.as_str()
                         );
+                        previous_attempt = queued_task.attempt_log.clone();
                         (
                             queued_task.prompt.clone(),
                             queued_task.class.clone(),
@@ -1760,29 +3602,73 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
                 let task_session_key =
                     thread_session_key(&session_key, &agent_state.active_thread_id);
+                refresh_project_soul(&mut project);
+                // Inside a project, this run sees only the tools it allows.
+                let active_allowed_tools = match &project {
+                    Some(project) => project.narrow(&active_allowed_tools),
+                    None => active_allowed_tools.clone(),
+                };
+                runtime_registry.set_allowed_tools(active_allowed_tools.clone());
+                let run_setup = RunSetup {
+                    supervision: agent_state.supervision.clone(),
+                    autonomy_mode: agent_state.autonomy_mode.clone(),
+                    soul_prompt: project
+                        .as_ref()
+                        .map_or(&system_prompt, |project| &project.prompt)
+                        .clone(),
+                    runtime_context: context_registry.render(&turn_context(
+                        &agent_state,
+                        &capability_registry,
+                        &active_allowed_tools,
+                        &context.facts,
+                        &latest_task_list,
+                    )),
+                    roots: project.as_ref().map(|project| vec![project.root.clone()]),
+                };
+                let (run_mode, turn_system_prompt) = run_setup.prepare(
+                    &approval_gate,
+                    &task_session_key,
+                    &agent_state.active_thread_id,
+                    &task_class,
+                );
                 let strict_workflow = strict_workflow_enabled();
                 let focused_tools = focused_tools_for_input(&effective_input, &active_allowed_tools);
                 let use_focused = !strict_workflow
-                    && matches!(agent_state.autonomy_mode, AutonomyMode::Guarded)
-                    && !focused_tools.is_empty()
-                    && focused_tools.len() < active_allowed_tools.len();
+                    && use_focused_tools(
+                        &focused_tools,
+                        &active_allowed_tools,
+                        &run_mode,
+                    );
                 if use_focused {
                     runtime_registry.set_allowed_tools(focused_tools.clone());
                 }
 
                 let class_budget =
-                    execution_budget_for_class(&task_class, &agent_state.autonomy_mode);
-                let watchdog_timeout =
-                    watchdog_timeout_for_class(&task_class, &agent_state.autonomy_mode);
+                    execution_budget_for_class(&task_class, &run_mode);
                 let effective_max_iterations = active_soul
                     .max_iterations
                     .min(class_budget.max_iterations)
                     .max(1);
+                let watchdog_timeout = watchdog_with_tool_latency(
+                    watchdog_timeout_for_class(&task_class, &run_mode),
+                    effective_max_iterations,
+                    if use_focused {
+                        &focused_tools
+                    } else {
+                        &active_allowed_tools
+                    },
+                    &hypr_claw_runtime::metrics::tool_latency_snapshot(),
+                );
                 agent_loop.set_max_iterations(effective_max_iterations);
-                let run_mode = agent_state.autonomy_mode.clone();
+                agent_loop.set_require_verification(verification_required(
+                    &task_class,
+                    &run_mode,
+                    &config,
+                ));
                 let run_started_at = Instant::now();
                 let mut fallback_attempts = 0u32;
-                agent_state.reliability.run_id = agent_state.reliability.run_id.saturating_add(1);
+                agent_state.reliability.run_id =
+                    action_log.claim_run_id(agent_state.reliability.run_id.saturating_add(1));
                 agent_state.reliability.last_stage = "running".to_string();
                 agent_state.reliability.fallback_attempts = 0;
                 agent_state.reliability.last_duration_ms = 0;
@@ -1804,24 +3690,40 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     },
                 );
 
-                let turn_system_prompt = augment_system_prompt_for_turn(
-                    &system_prompt,
-                    &agent_state.onboarding.system_profile,
-                    &capability_registry,
-                    &active_allowed_tools,
-                    &agent_state.autonomy_mode,
-                );
+                wait_for_thermal_clearance(&thermal_guard, &config.thermal, &interrupt).await;
                 let run_action_start = action_feed_len(&action_feed);
+                let run_tokens_before = hypr_claw_runtime::metrics::token_usage();
+                runtime_dispatcher.take_succeeded_calls(&task_session_key);
+                runtime_dispatcher.take_changes(&task_session_key);
+                runtime_dispatcher.set_task_label(&task_session_key, &effective_input);
+                runtime_dispatcher
+                    .set_env_overlay(&task_session_key, active_thread_env(&agent_state));
+                if let Err(e) = action_log.begin_run(
+                    &task_session_key,
+                    agent_state.reliability.run_id,
+                    &effective_input,
+                ) {
+                    eprintln!("⚠️  Failed to start action log: {}", e);
+                }
                 let mut recovery_notes: Vec<String> = Vec::new();
 
+                let first_input = match &previous_attempt {
+                    Some(summary) => hypr_claw_runtime::attempts::with_previous_attempt(
+                        &effective_input,
+                        summary,
+                    ),
+                    None => effective_input.clone(),
+                };
+                side_runs.sync(&agent_state, run_setup);
                 let mut run_result = run_with_interrupt_and_timeout(
-                    &agent_loop,
+                    &mut side_runs,
                     &task_session_key,
                     &agent_name,
                     &turn_system_prompt,
-                    &effective_input,
+                    &first_input,
                     &interrupt,
                     watchdog_timeout,
+                    &mut pending_inputs,
                 )
                 .await;
 
@@ -1855,16 +3757,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                 fallback_attempts, MAX_RECOVERY_ATTEMPTS, wait_secs
                             );
                             recovery_notes.push(note.clone());
-                            println!("{note}");
+                            eprintln!("{note}");
                             tokio::time::sleep(Duration::from_secs(wait_secs)).await;
                             run_result = run_with_interrupt_and_timeout(
-                                &agent_loop,
+                                &mut side_runs,
                                 &task_session_key,
                                 &agent_name,
                                 &turn_system_prompt,
                                 &effective_input,
                                 &interrupt,
                                 watchdog_timeout,
+                                &mut pending_inputs,
                             )
                             .await;
                             continue;
@@ -1897,16 +3800,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     let mut recovery_prompt: Option<String> = None;
                     fallback_attempts += 1;
                     agent_state.reliability.fallback_attempts = fallback_attempts;
-                    let prompt_first_mode =
-                        matches!(agent_state.autonomy_mode, AutonomyMode::PromptFirst);
+                    let prompt_first_mode = matches!(run_mode, AutonomyMode::PromptFirst);
+                    let temperature = annealing_policy(&run_mode, &config)
+                        .temperature(agent_loop.base_temperature(), fallback_attempts);
+                    agent_loop.set_temperature_override(temperature);
                     let note = format!(
-                        "[recovery {}/{}] error={}",
+                        "[recovery {}/{}] error={}{}",
                         fallback_attempts,
                         MAX_RECOVERY_ATTEMPTS,
-                        truncate_for_table(&err_msg, 110)
+                        truncate_for_table(&err_msg, 110),
+                        temperature
+                            .map(|t| format!(" temperature={t:.2}"))
+                            .unwrap_or_default()
                     );
                     recovery_notes.push(note.clone());
-                    println!("{note}");
+                    eprintln!("{note}");
 
                     if is_tool_enforcement_error {
                         agent_state.reliability.last_stage = "recovery_tool_enforcement".to_string();
@@ -1938,13 +3846,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             {
                                 runtime_registry.set_allowed_tools(emergency_tools);
                                 run_result = run_with_interrupt_and_timeout(
-                                    &agent_loop,
+                                    &mut side_runs,
                                     &task_session_key,
                                     &agent_name,
                                     &turn_system_prompt,
                                     &effective_input,
                                     &interrupt,
                                     watchdog_timeout,
+                                    &mut pending_inputs,
                                 )
                                 .await;
                                 runtime_registry.set_allowed_tools(active_allowed_tools.clone());
@@ -1971,15 +3880,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         }
                     }
 
-                    if let Some(prompt) = recovery_prompt {
+                    if let Some(mut prompt) = recovery_prompt {
+                        if let Some(mut attempt) = agent_loop.last_attempt(&task_session_key) {
+                            attempt.error.get_or_insert_with(|| err_msg.clone());
+                            prompt = hypr_claw_runtime::attempts::with_previous_attempt(
+                                &prompt,
+                                &attempt.render(),
+                            );
+                        }
                         run_result = run_with_interrupt_and_timeout(
-                            &agent_loop,
+                            &mut side_runs,
                             &task_session_key,
                             &agent_name,
                             &turn_system_prompt,
                             &prompt,
                             &interrupt,
                             watchdog_timeout,
+                            &mut pending_inputs,
                         )
                         .await;
                         continue;
@@ -1989,7 +3906,30 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
 
                 agent_loop.set_max_iterations(active_soul.max_iterations);
+                agent_loop.set_temperature_override(None);
+                for note in agent_loop.take_observations(&task_session_key) {
+                    eprintln!("⏳ Run finished before your note; queued it as the next prompt.");
+                    pending_inputs.push(note);
+                }
+                agent_state.reliability.tool_latency =
+                    hypr_claw_runtime::metrics::tool_latency_snapshot();
                 let run_elapsed_ms = run_started_at.elapsed().as_millis() as u64;
+                let run_tokens =
+                    hypr_claw_runtime::metrics::token_usage().since(run_tokens_before);
+                let run_metrics_row = run_metrics::RunMetrics {
+                    run_id: agent_state.reliability.run_id,
+                    started_at: chrono::Utc::now().timestamp() - (run_elapsed_ms / 1000) as i64,
+                    session: task_session_key.clone(),
+                    mode: run_mode.as_str().to_string(),
+                    task_class: task_class.as_str().to_string(),
+                    stop_code: String::new(),
+                    duration_ms: run_elapsed_ms,
+                    fallback_attempts,
+                    tool_calls: 0,
+                    tool_failures: 0,
+                    prompt_tokens: run_tokens.prompt,
+                    completion_tokens: run_tokens.completion,
+                };
 
                 match run_result {
                     Ok(response) => {
@@ -2007,6 +3947,41 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             fallback_attempts,
                             "STOP_NONE",
                         );
+                        usage_tracker.record_run(None);
+                        if let Err(e) = action_log.record_reply(&task_session_key, &response) {
+                            eprintln!("⚠️  Failed to log reply: {}", e);
+                        }
+                        let changes = runtime_dispatcher.take_changes(&task_session_key);
+                        if let Err(e) = action_log.record_changes(&task_session_key, &changes) {
+                            eprintln!("⚠️  Failed to log changes: {}", e);
+                        }
+                        if let Err(e) =
+                            action_log.finish_run(&task_session_key, "STOP_NONE", run_elapsed_ms)
+                        {
+                            eprintln!("⚠️  Failed to close action log: {}", e);
+                        }
+                        record_run_metrics(
+                            &run_metrics_store,
+                            &action_log,
+                            run_metrics::RunMetrics {
+                                stop_code: "STOP_NONE".to_string(),
+                                ..run_metrics_row
+                            },
+                        );
+                        last_run = Some(RunReport {
+                            run_id: agent_state.reliability.run_id,
+                            thread_id: agent_state.active_thread_id.clone(),
+                            request: effective_input.clone(),
+                            task_class: task_class.as_str().to_string(),
+                            stop_code: "STOP_NONE".to_string(),
+                            duration_ms: run_elapsed_ms,
+                            recovery_notes: recovery_notes.clone(),
+                            tool_events: action_feed_since(&action_feed, run_action_start, 40),
+                            attempt: agent_loop
+                                .last_attempt(&task_session_key)
+                                .map(|attempt| attempt.render()),
+                            ..RunReport::default()
+                        });
                         if let Some(task_id) = &supervisor_task_id {
                             if supervised_task_status(&agent_state, task_id)
                                 != Some(SupervisedTaskStatus::Cancelled)
@@ -2029,6 +4004,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             content: format!("[thread:{}] {}", agent_state.active_thread_id, response),
                             token_count: None,
                         });
+                        if let Some(placeholder) =
+                            title_untitled_thread(&mut agent_state, &effective_input)
+                        {
+                            spawn_thread_title(
+                                config.provider.clone(),
+                                config.model.clone(),
+                                agent_state.active_thread_id.clone(),
+                                placeholder,
+                                effective_input.clone(),
+                                response.clone(),
+                                generated_titles.clone(),
+                            );
+                        }
                         mark_plan_completed(&mut context, &response);
                         context.active_tasks = to_context_tasks(task_manager.list_tasks().await);
                         persist_agent_os_state(&mut context, &agent_state);
@@ -2057,6 +4045,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         println!();
                         println!("{}", ui_section("Assistant"));
                         println!("{}\n", strip_ansi_and_controls(&response));
+                        print_citation_footnotes(&agent_loop.take_citations(&task_session_key));
+                        print_run_changes(&changes);
+                        if !daemon_mode && supervisor_task_id.is_none() {
+                            suggested_commands = suggestions::extract_commands(&response);
+                            print_suggested_commands(&suggested_commands);
+                        }
+                        let calls = runtime_dispatcher.take_succeeded_calls(&task_session_key);
+                        if !daemon_mode
+                            && supervisor_task_id.is_none()
+                            && calls.len() >= MIN_LEARNED_SKILL_STEPS
+                        {
+                            println!(
+                                "💡 {} tool calls succeeded. Save them as a reusable skill: skill learn <name>\n",
+                                calls.len()
+                            );
+                            learnable_run = Some((effective_input.clone(), calls));
+                        }
                     }
                     Err(e) => {
                         let error_msg = e.to_string();
@@ -2076,6 +4081,40 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             fallback_attempts,
                             stop_code,
                         );
+                        usage_tracker.record_run(Some(stop_code));
+                        let changes = runtime_dispatcher.take_changes(&task_session_key);
+                        if let Err(e) = action_log.record_changes(&task_session_key, &changes) {
+                            eprintln!("⚠️  Failed to log changes: {}", e);
+                        }
+                        if let Err(e) =
+                            action_log.finish_run(&task_session_key, stop_code, run_elapsed_ms)
+                        {
+                            eprintln!("⚠️  Failed to close action log: {}", e);
+                        }
+                        record_run_metrics(
+                            &run_metrics_store,
+                            &action_log,
+                            run_metrics::RunMetrics {
+                                stop_code: stop_code.to_string(),
+                                ..run_metrics_row
+                            },
+                        );
+                        last_run = Some(RunReport {
+                            run_id: agent_state.reliability.run_id,
+                            thread_id: agent_state.active_thread_id.clone(),
+                            request: effective_input.clone(),
+                            task_class: task_class.as_str().to_string(),
+                            stop_code: stop_code.to_string(),
+                            error: error_msg.clone(),
+                            hint: hint.clone(),
+                            duration_ms: run_elapsed_ms,
+                            recovery_notes: recovery_notes.clone(),
+                            tool_events: action_feed_since(&action_feed, run_action_start, 40),
+                            attempt: agent_loop.last_attempt(&task_session_key).map(|mut attempt| {
+                                attempt.error.get_or_insert_with(|| error_msg.clone());
+                                attempt.render()
+                            }),
+                        });
                         if let Some(task_id) = &supervisor_task_id {
                             if supervised_task_status(&agent_state, task_id)
                                 != Some(SupervisedTaskStatus::Cancelled)
@@ -2085,6 +4124,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                     task_id,
                                     error_msg.clone(),
                                 );
+                                if let Some(mut attempt) =
+                                    agent_loop.last_attempt(&task_session_key)
+                                {
+                                    attempt.error.get_or_insert_with(|| error_msg.clone());
+                                    record_supervised_task_attempt(
+                                        &mut agent_state,
+                                        task_id,
+                                        attempt.render(),
+                                    );
+                                }
                                 push_task_event(
                                     &task_event_feed,
                                     format!(
@@ -2125,14 +4174,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             );
                         }
                         if error_msg.contains("Interrupted by user") {
-                            println!("⏹ Request interrupted by user.\n");
+                            eprintln!("⏹ Request interrupted by user.\n");
                         } else {
                             eprintln!("❌ Error [{}]: {}", stop_code, e);
                             if !hint.is_empty() {
                                 eprintln!("💡 Next step: {}", hint);
                             }
+                            if triage::TRIAGE_CODES.contains(&stop_code) {
+                                eprintln!("🩺 Run `triage` to check what went wrong and fix it.");
+                            }
                             eprintln!();
                         }
+                        print_run_changes(&changes);
                     }
                 }
             }
@@ -2169,4209 +4222,9567 @@ fn handle_config_reset() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn initialize_directories() -> Result<(), Box<dyn std::error::Error>> {
-    std::fs::create_dir_all("./data/sessions")?;
-    std::fs::create_dir_all("./data/credentials")?;
-    std::fs::create_dir_all("./data/agents")?;
-    std::fs::create_dir_all("./data/context")?;
-    std::fs::create_dir_all("./data/tasks")?;
-    std::fs::create_dir_all("./data/capabilities")?;
-
-    if !std::path::Path::new("./data/audit.log").exists() {
-        std::fs::File::create("./data/audit.log")?;
+fn backup_key(passphrase_flag: bool) -> Result<BackupKey, Box<dyn std::error::Error>> {
+    if let Ok(passphrase) = std::env::var(BACKUP_PASSPHRASE_ENV) {
+        if !passphrase.is_empty() {
+            return Ok(BackupKey::Passphrase(passphrase));
+        }
     }
+    if passphrase_flag {
+        print!("Backup passphrase: ");
+        io::stdout().flush()?;
+        let passphrase = rpassword::read_password()?;
+        if passphrase.is_empty() {
+            return Err("passphrase cannot be empty".into());
+        }
+        return Ok(BackupKey::Passphrase(passphrase));
+    }
+    Ok(BackupKey::MasterKey(bootstrap::get_or_create_master_key()?))
+}
 
-    std::fs::create_dir_all("./sandbox")?;
-
-    // Create default agent config if it doesn't exist
-    let default_agent_config = "./data/agents/default.yaml";
-    let default_agent_soul = "./data/agents/default_soul.md";
+fn handle_backup_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    use hypr_claw::infra::backup;
 
-    if !std::path::Path::new(default_agent_config).exists() {
-        std::fs::write(
-            default_agent_config,
-            "id: default\nsoul: default_soul.md\ntools:\n  - echo\n  - fs.read\n  - fs.write\n  - fs.list\n  - fs.create_dir\n  - fs.move\n  - fs.copy\n  - fs.delete\n  - hypr.workspace.switch\n  - hypr.workspace.move_window\n  - hypr.window.focus\n  - hypr.window.close\n  - hypr.window.move\n  - hypr.exec\n  - proc.spawn\n  - proc.kill\n  - proc.list\n  - desktop.open_url\n  - desktop.launch_app\n  - desktop.launch_app_and_wait_text\n  - desktop.search_web\n  - desktop.open_gmail\n  - desktop.type_text\n  - desktop.key_press\n  - desktop.key_combo\n  - desktop.mouse_click\n  - desktop.capture_screen\n  - desktop.active_window\n  - desktop.list_windows\n  - desktop.cursor_position\n  - desktop.read_screen_state\n  - desktop.mouse_move\n  - desktop.mouse_move_and_verify\n  - desktop.click_at\n  - desktop.click_at_and_verify\n  - desktop.ocr_screen\n  - desktop.find_text\n  - desktop.click_text\n  - desktop.wait_for_text\n  - wallpaper.set\n  - system.memory\n  - system.battery\n"
-        )?;
-    }
+    let backup_config = Config::load().map(|c| c.backup).unwrap_or_default();
+    let backup_dir = std::path::PathBuf::from(&backup_config.dir);
+    let passphrase = args.iter().any(|a| a == "--passphrase");
+    let positional: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|a| !a.starts_with("--"))
+        .collect();
 
-    if !std::path::Path::new(default_agent_soul).exists() {
-        std::fs::write(
-            default_agent_soul,
-            "You are a local Linux + Hyprland OS assistant. Follow strict workflow: observe -> plan -> execute tool -> verify -> continue until done. Choose tools dynamically from allowed set. Ask permission before destructive/high-impact actions. Use mouse+keyboard style actions for GUI tasks.",
-        )?;
+    match positional.as_slice() {
+        ["create"] => {
+            let key = backup_key(passphrase)?;
+            let summary = backup::create_backup(
+                &data_dir::path(DATA_DIR),
+                &backup_dir,
+                &key,
+                backup::DEFAULT_ENTRIES,
+            )?;
+            println!(
+                "✅ Backup written to {} ({} bytes, key: {})",
+                summary.path.display(),
+                summary.bytes,
+                summary.header.kdf
+            );
+        }
+        ["list"] | [] => {
+            let backups = backup::list_backups(&backup_dir)?;
+            if backups.is_empty() {
+                println!("No backups in {}", backup_dir.display());
+            }
+            for summary in backups {
+                println!(
+                    "{}  {:>10} bytes  {:<10}  {}",
+                    summary.header.created_at,
+                    summary.bytes,
+                    summary.header.kdf,
+                    summary.path.display()
+                );
+            }
+        }
+        ["verify", path] => {
+            let key = backup_key(passphrase)?;
+            let manifest = backup::verify_backup(std::path::Path::new(path), &key)?;
+            println!(
+                "✅ {} verified: {} files, created {}",
+                path,
+                manifest.files.len(),
+                manifest.created_at
+            );
+        }
+        ["restore", path] => {
+            let key = backup_key(passphrase)?;
+            let (manifest, previous) = backup::restore_backup(
+                std::path::Path::new(path),
+                &key,
+                &data_dir::path(DATA_DIR),
+            )?;
+            println!(
+                "✅ Restored {} files from {} (backup created {})",
+                manifest.files.len(),
+                path,
+                manifest.created_at
+            );
+            println!("   Replaced data was moved to {}", previous.display());
+        }
+        _ => {
+            println!("Usage:");
+            println!("  hypr-claw backup create [--passphrase]");
+            println!("  hypr-claw backup list");
+            println!("  hypr-claw backup verify <file> [--passphrase]");
+            println!("  hypr-claw backup restore <file> [--passphrase]");
+            println!(
+                "  Set {} to use a passphrase non-interactively.",
+                BACKUP_PASSPHRASE_ENV
+            );
+        }
     }
-
     Ok(())
 }
 
-struct SoulProfile {
-    system_prompt: String,
-    max_iterations: usize,
+/// Appends a finished run's metrics row, with tool counts taken from its
+/// action log.
+fn record_run_metrics(
+    store: &run_metrics::RunMetricsStore,
+    log: &action_log::ActionLog,
+    mut row: run_metrics::RunMetrics,
+) {
+    if let Ok(events) = log.load(row.run_id) {
+        (row.tool_calls, row.tool_failures) = run_metrics::tool_counts(&events);
+    }
+    if let Err(e) = store.append(&row) {
+        eprintln!("⚠️  Failed to record run metrics: {}", e);
+    }
 }
 
-fn power_agent_profile() -> SoulProfile {
-    SoulProfile {
-        system_prompt: "You are a powerful local Linux + Hyprland OS assistant.\nFollow strict workflow: observe -> plan -> execute tool -> verify -> continue until done.\nFor GUI tasks, observe with desktop.read_screen_state/active_window/list_windows/cursor_position first, then act with cursor + keyboard tools.\nChoose tools dynamically from allowed_tools_now.\nAsk for permission before destructive/high-impact actions.\nAvoid hardcoded assumptions and adapt from live system/context.".to_string(),
-        max_iterations: 36,
+fn handle_metrics_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let flag = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|i| args.get(i + 1))
+            .map(String::as_str)
+    };
+    let usage = || {
+        println!("Usage:");
+        println!(
+            "  hypr-claw metrics export [--format json|csv] [--since YYYY-MM-DD] [--output FILE]"
+        );
+        println!("  One row per run: duration, tokens, stop code and tool counts.");
+        println!("  hypr-claw metrics compactions [--since YYYY-MM-DD] [--format json]");
+        println!("  Tokens compaction saved and how often the model re-read what it dropped.");
+    };
+    let since = match flag("--since") {
+        Some(value) => Some(
+            run_metrics::parse_since(value)
+                .ok_or_else(|| format!("invalid --since '{}'; use YYYY-MM-DD", value))?,
+        ),
+        None => None,
+    };
+    match args.first().map(String::as_str) {
+        Some("export") => {}
+        Some("compactions") => {
+            let rows = compaction_metrics::CompactionLog::new(data_dir::path(
+                compaction_metrics::COMPACTIONS_PATH,
+            ))
+            .load(since)?;
+            if flag("--format") == Some("json") {
+                println!("{}", serde_json::to_string_pretty(&rows)?);
+            } else {
+                print_compaction_stats(&compaction_metrics::CompactionStats::from_rows(&rows));
+            }
+            return Ok(());
+        }
+        _ => {
+            usage();
+            return Ok(());
+        }
+    }
+    let rows =
+        run_metrics::RunMetricsStore::new(data_dir::path(run_metrics::METRICS_PATH)).load(since)?;
+    let output = match flag("--format").unwrap_or("json") {
+        "json" => serde_json::to_string_pretty(&rows)? + "\n",
+        "csv" => run_metrics::to_csv(&rows),
+        other => {
+            usage();
+            return Err(format!("unknown format '{}'", other).into());
+        }
+    };
+    match flag("--output") {
+        Some(path) => {
+            std::fs::write(path, output)?;
+            eprintln!("✅ Exported {} runs to {}", rows.len(), path);
+        }
+        None => print!("{}", output),
     }
+    Ok(())
 }
 
-fn normalize_runtime_tool_name(name: &str) -> String {
-    match name {
-        "screen.capture" | "capture.screen" => "desktop.capture_screen".to_string(),
-        "screen.ocr" | "ocr.screen" => "desktop.ocr_screen".to_string(),
-        "screen.find_text" | "text.find_on_screen" => "desktop.find_text".to_string(),
-        "screen.click_text" | "text.click_on_screen" => "desktop.click_text".to_string(),
-        "gmail.open" => "desktop.open_gmail".to_string(),
-        "browser.open_url" => "desktop.open_url".to_string(),
-        "browser.search" => "desktop.search_web".to_string(),
-        "app.open" | "app.launch" => "desktop.launch_app".to_string(),
-        "process.spawn" => "proc.spawn".to_string(),
-        "process.kill" => "proc.kill".to_string(),
-        "process.list" => "proc.list".to_string(),
-        other => other.to_string(),
+fn print_compaction_stats(stats: &compaction_metrics::CompactionStats) {
+    if stats.compactions == 0 {
+        println!("No compactions recorded yet.");
+        return;
+    }
+    println!("Compactions      : {}", stats.compactions);
+    println!(
+        "Tokens saved     : {} (avg {} per compaction)",
+        stats.tokens_saved,
+        stats.tokens_saved / stats.compactions
+    );
+    println!("Targets dropped  : {}", stats.targets);
+    println!(
+        "Re-read later    : {} ({:.0}%), {} in the same run",
+        stats.refetched,
+        stats.refetch_rate() * 100.0,
+        stats.refetched_same_run
+    );
+    for (kind, count) in &stats.refetched_by_kind {
+        println!("  {:<14} : {}", kind, count);
     }
 }
 
-fn removed_feature_notice(input: &str) -> Option<&'static str> {
-    let cmd = input.trim().to_ascii_lowercase();
+async fn handle_self_update(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    use hypr_claw::infra::backup;
 
-    if cmd == "tui"
-        || cmd == "/tui"
-        || cmd.starts_with("tui ")
-        || cmd.starts_with("/tui ")
-        || cmd == "repl"
-        || cmd == "/repl"
-    {
-        return Some("ℹ️ TUI/REPL mode switching has been removed. Use the single streamlined prompt workflow.");
-    }
-    if cmd == "dashboard" || cmd == "dash" || cmd == "/dashboard" {
-        return Some("ℹ️ Dashboard mode has been removed to reduce bloat. Use `status` for a lightweight snapshot.");
-    }
-    if cmd.starts_with("soul") || cmd.starts_with("/soul ") {
-        return Some(
-            "ℹ️ Soul system has been removed. A single `power_agent` profile is always active.",
-        );
+    let check_only = args.iter().any(|a| a == "--check");
+    let config = Config::load().inspect_err(|_| {
+        eprintln!("💡 Tip: Run 'hypr-claw' once to create the configuration");
+    })?;
+    let release = update::fetch_release(&config.update).await?;
+    if !update::is_newer(&release.version, update::CURRENT_VERSION) {
+        println!("✅ hypr-claw {} is up to date", update::CURRENT_VERSION);
+        return Ok(());
     }
-    if cmd.starts_with("autonomy") || cmd.starts_with("/autonomy ") {
-        return Some(
-            "ℹ️ Autonomy modes were removed. The agent always runs in strict power workflow mode.",
-        );
+    println!(
+        "⬆️  hypr-claw {} is available (running {})",
+        release.version,
+        update::CURRENT_VERSION
+    );
+    if !release.notes.trim().is_empty() {
+        println!("{}", release.notes.trim());
     }
-    if cmd == "trust" || cmd == "/trust" || cmd.starts_with("trust ") || cmd.starts_with("/trust ")
-    {
-        return Some("ℹ️ Trust/safe mode toggles were removed. Permission flow is handled directly per action.");
-    }
-    if cmd == "/task" || cmd.starts_with("/task ") {
-        return Some("ℹ️ Task-thread commands were removed to simplify workflow. Use `queue add|run|status|clear`.");
-    }
-    if cmd.starts_with("queue prune")
-        || cmd.starts_with("/queue prune")
-        || cmd.starts_with("queue inspect")
-        || cmd.starts_with("/queue inspect")
-        || cmd.starts_with("queue events")
-        || cmd.starts_with("/queue events")
-        || cmd.starts_with("queue stop")
-        || cmd.starts_with("/queue stop")
-        || cmd.starts_with("queue retry")
-        || cmd.starts_with("/queue retry")
-        || cmd.starts_with("queue auto")
-        || cmd.starts_with("/queue auto")
-    {
-        return Some("ℹ️ Advanced queue controls were removed. Supported queue commands: `queue`, `queue status`, `queue add <prompt>`, `queue run`, `queue clear`.");
+    if check_only {
+        println!("💡 Run 'hypr-claw self-update' to install it");
+        return Ok(());
     }
 
-    None
+    let data_dir = data_dir::path(DATA_DIR);
+    let stored_format = migrations::stored_format(&data_dir)?;
+    if let Some(issue) = update::compatibility_issue(&release, stored_format) {
+        return Err(issue.into());
+    }
+    let binary = update::download(&config.update, &release).await?;
+    println!("🔏 Signature verified");
+    // The new binary migrates ./data on its first start; keep a way back.
+    if release.data_format > stored_format && data_dir.exists() {
+        let summary = backup::create_backup(
+            &data_dir,
+            std::path::Path::new(&config.backup.dir),
+            &backup_key(false)?,
+            backup::DEFAULT_ENTRIES,
+        )?;
+        println!("💾 Data backed up to {}", summary.path.display());
+    }
+    let previous = update::install(&std::env::current_exe()?, &binary)?;
+    println!(
+        "✅ Updated to {}; the previous binary is kept at {}",
+        release.version,
+        previous.display()
+    );
+    println!("💡 Restart running instances, e.g. systemctl --user restart hypr-claw");
+    Ok(())
 }
 
-fn derive_runtime_allowed_tools(
-    registry: &Arc<hypr_claw_tools::ToolRegistryImpl>,
-    capability_registry: &Value,
-) -> HashSet<String> {
-    let has_array_entries = |path: &str| {
-        capability_registry
-            .pointer(path)
-            .and_then(|v| v.as_array())
-            .map(|arr| !arr.is_empty())
-            .unwrap_or(false)
-    };
-    let ocr_available = capability_registry
-        .pointer("/capabilities/ocr_available")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
-    let hyprland_available = capability_registry
-        .pointer("/platform/hyprland_available")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
-
-    let input_backends =
-        read_string_array_from_value(capability_registry.pointer("/capabilities/input_backends"));
-    let has_keyboard_backend = input_backends
-        .iter()
-        .any(|b| matches!(b.as_str(), "wtype" | "ydotool"));
-    let has_pointer_backend = input_backends
+fn handle_service_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let positional: Vec<&str> = args
         .iter()
-        .any(|b| matches!(b.as_str(), "ydotool" | "wlrctl"));
-    let has_screenshot_backend = has_array_entries("/capabilities/screenshot_backends");
-    let has_wallpaper_backend = has_array_entries("/capabilities/wallpaper_backends");
+        .map(String::as_str)
+        .filter(|a| !a.starts_with("--"))
+        .collect();
+    let flag = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|i| args.get(i + 1))
+    };
 
-    registry
-        .list()
-        .into_iter()
-        .filter(|tool| match tool.as_str() {
-            "hypr.workspace.switch"
-            | "hypr.workspace.move_window"
-            | "hypr.window.focus"
-            | "hypr.window.close"
-            | "hypr.window.move"
-            | "hypr.exec" => hyprland_available,
-            "wallpaper.set" => has_wallpaper_backend,
-            "desktop.capture_screen" => has_screenshot_backend,
-            "desktop.ocr_screen" | "desktop.find_text" => has_screenshot_backend && ocr_available,
-            "desktop.wait_for_text" | "desktop.launch_app_and_wait_text" => {
-                has_screenshot_backend && ocr_available
-            }
-            "desktop.cursor_position" => hyprland_available,
-            "desktop.mouse_move_and_verify" | "desktop.click_at_and_verify" => {
-                has_pointer_backend && hyprland_available
-            }
-            "desktop.read_screen_state" => hyprland_available || has_screenshot_backend,
-            "desktop.type_text" | "desktop.key_press" | "desktop.key_combo" => {
-                has_keyboard_backend
+    match positional.as_slice() {
+        ["install", ..] => {
+            let config = Config::load().inspect_err(|_| {
+                eprintln!("💡 Tip: Run 'hypr-claw' once to create the configuration");
+            })?;
+            let mut limits = service::ServiceLimits::default();
+            if let Some(memory_max) = flag("--memory-max") {
+                limits.memory_max = memory_max.clone();
             }
-            "desktop.mouse_click" | "desktop.mouse_move" | "desktop.click_at" => {
-                has_pointer_backend
+            if let Some(tasks_max) = flag("--tasks-max") {
+                limits.tasks_max = tasks_max
+                    .parse()
+                    .map_err(|_| format!("invalid --tasks-max '{}'", tasks_max))?;
             }
-            "desktop.click_text" => has_pointer_backend && has_screenshot_backend && ocr_available,
-            _ => true,
-        })
-        .collect()
+            let unit = service::render_unit(
+                &std::env::current_exe()?,
+                &std::env::current_dir()?,
+                &config.watchdog,
+                &limits,
+            );
+            let path = service::install(&unit)?;
+            println!("✅ Installed and started {}", path.display());
+            println!("💡 Logs: journalctl --user -u {} -f", service::UNIT_NAME);
+            println!(
+                "💡 Desktop tools need Hyprland's environment in the user manager, e.g.\n   exec-once = dbus-update-activation-environment --systemd --all"
+            );
+        }
+        ["status"] => service::status()?,
+        ["uninstall"] => match service::uninstall()? {
+            Some(path) => println!("🧹 Removed {}", path.display()),
+            None => println!("{} is not installed", service::UNIT_NAME),
+        },
+        _ => {
+            println!("Usage:");
+            println!("  hypr-claw service install [--memory-max 2G] [--tasks-max 512]");
+            println!("  hypr-claw service status");
+            println!("  hypr-claw service uninstall");
+            println!("  The unit runs 'hypr-claw daemon' in the current directory.");
+        }
+    }
+    Ok(())
 }
 
-#[cfg(test)]
-fn max_view_offset(total: usize, page: usize) -> usize {
-    let page = page.max(1);
-    total.saturating_sub(page)
+/// Verified tools for every installed command plugin whose artifact still
+/// matches the hash recorded at install time and passes the signature
+/// policy; rejected plugins are skipped with a warning. Every check is
+/// written to the audit log.
+fn load_installed_plugins(
+    policy: &hypr_claw_tools::plugins::SignaturePolicy,
+    audit_logger: &hypr_claw::infra::audit_logger::AuditLogger,
+) -> Vec<Arc<dyn hypr_claw_tools::Tool>> {
+    let store = match hypr_claw_tools::plugins::PluginStore::open(data_dir::path(PLUGINS_DIR)) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("⚠️  Failed to load plugins: {}", e);
+            return Vec::new();
+        }
+    };
+    let mut tools: Vec<Arc<dyn hypr_claw_tools::Tool>> = Vec::new();
+    for plugin in store.list() {
+        let verification = store.verify(&plugin, policy);
+        audit_plugin_verification(
+            audit_logger,
+            "load",
+            &plugin.name,
+            &plugin.version,
+            &verification,
+        );
+        let loaded = verification.and_then(|_| {
+            hypr_claw_tools::plugins::PluginTool::new(&plugin, store.artifact_path(&plugin))
+        });
+        match loaded {
+            Ok(tool) => tools.push(Arc::new(tool)),
+            Err(e) => eprintln!("⚠️  Skipping plugin {}: {}", plugin.name, e),
+        }
+    }
+    tools
 }
 
-#[cfg(test)]
-fn tail_window_slice<T: Clone>(
-    items: &[T],
-    offset_from_latest: usize,
-    page_size: usize,
-) -> (Vec<T>, usize, usize, usize) {
-    if items.is_empty() {
-        return (Vec::new(), 0, 0, 0);
+/// Tools for every skill in [`SKILLS_DIR`] whose steps, parameters,
+/// conditions and permissions check out against `registry`; the rest are
+/// skipped with a warning. Permission manifests are granted through
+/// `approvals` while a skill runs.
+fn load_skills(
+    registry: &hypr_claw_tools::ToolRegistryImpl,
+    dispatcher: &hypr_claw_tools::skills::DispatcherSlot,
+    approvals: &Arc<hypr_claw_tools::approvals::ApprovalBook>,
+) -> Vec<Arc<dyn hypr_claw_tools::Tool>> {
+    use hypr_claw_tools::skills::{self, SkillTool};
+
+    let loaded = match skills::load_dir(&data_dir::path(SKILLS_DIR)) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("⚠️  Failed to load skills: {}", e);
+            return Vec::new();
+        }
+    };
+    let mut tools: Vec<Arc<dyn hypr_claw_tools::Tool>> = Vec::new();
+    for (path, skill) in loaded {
+        match skill.and_then(|skill| SkillTool::new(skill, registry, dispatcher.clone())) {
+            Ok(tool) => tools.push(Arc::new(tool.with_approvals(approvals.clone()))),
+            Err(e) => eprintln!("⚠️  Skipping skill {}: {}", path.display(), e),
+        }
     }
-    let page = page_size.max(1);
-    let total = items.len();
-    let clamped_offset = offset_from_latest.min(max_view_offset(total, page));
-    let end = total.saturating_sub(clamped_offset);
-    let start = end.saturating_sub(page);
-    (items[start..end].to_vec(), start + 1, end, total)
+    tools
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct TaskStateDigest {
-    status: String,
-    progress_percent: u16,
-    error: String,
-    result: String,
+/// Writes a draft skill replaying `calls` to [`SKILL_DRAFTS_DIR`], checked
+/// against `registry` so it will load once saved. Returns its path and YAML.
+fn draft_learned_skill(
+    name: &str,
+    prompt: &str,
+    calls: &[(String, Value)],
+    registry: &hypr_claw_tools::ToolRegistryImpl,
+) -> Result<(std::path::PathBuf, String), String> {
+    let skill = hypr_claw_tools::skills::draft_from_calls(name, prompt, calls, registry)?;
+    skill.verify(registry)?;
+    let yaml = skill.to_yaml()?;
+    std::fs::create_dir_all(data_dir::path(SKILL_DRAFTS_DIR)).map_err(|e| e.to_string())?;
+    let path = data_dir::path(SKILL_DRAFTS_DIR).join(format!("{}.yaml", name));
+    std::fs::write(&path, &yaml).map_err(|e| e.to_string())?;
+    Ok((path, yaml))
+}
+
+/// Moves a reviewed draft into [`SKILLS_DIR`] after checking it again, since
+/// it may have been edited.
+fn save_skill_draft(
+    name: &str,
+    registry: &hypr_claw_tools::ToolRegistryImpl,
+) -> Result<std::path::PathBuf, String> {
+    hypr_claw_tools::skills::validate_skill_name(name)?;
+    let draft = data_dir::path(SKILL_DRAFTS_DIR).join(format!("{}.yaml", name));
+    let yaml = std::fs::read_to_string(&draft)
+        .map_err(|e| format!("no draft at {}: {}", draft.display(), e))?;
+    hypr_claw_tools::skills::Skill::parse(name, &yaml)?.verify(registry)?;
+    let target = data_dir::path(SKILLS_DIR).join(format!("{}.yaml", name));
+    if target.exists() {
+        return Err(format!("{} already exists", target.display()));
+    }
+    std::fs::rename(&draft, &target).map_err(|e| e.to_string())?;
+    Ok(target)
+}
+
+/// Splits `skill run` arguments into the skill name and its parameters.
+/// Values that parse as JSON scalars (`true`, `3`) keep their type.
+fn parse_skill_run_args(raw: &str) -> Option<(String, Value)> {
+    let mut parts = raw.split_whitespace();
+    let name = parts.next()?.to_string();
+    let mut params = serde_json::Map::new();
+    for part in parts {
+        let (key, value) = part.split_once('=')?;
+        if key.is_empty() {
+            return None;
+        }
+        let value = match serde_json::from_str::<Value>(value) {
+            Ok(parsed @ (Value::Bool(_) | Value::Number(_))) => parsed,
+            _ => Value::String(value.to_string()),
+        };
+        params.insert(key.to_string(), value);
+    }
+    Some((name, Value::Object(params)))
 }
 
-fn now_hms() -> String {
-    chrono::Utc::now().format("%H:%M:%S").to_string()
-}
+fn print_skills(registry: &hypr_claw_tools::ToolRegistryImpl) {
+    use hypr_claw_tools::skills::SKILL_TOOL_PREFIX;
 
-fn push_task_event(feed: &Arc<Mutex<Vec<String>>>, message: impl Into<String>) {
-    let line = format!("{} {}", now_hms(), message.into());
-    if let Ok(mut rows) = feed.lock() {
-        rows.push(line);
-        if rows.len() > 320 {
-            let drop_count = rows.len() - 320;
-            rows.drain(0..drop_count);
+    println!("\n{}", ui_title("Skills"));
+    let mut names = registry
+        .list()
+        .into_iter()
+        .filter(|name| name.starts_with(SKILL_TOOL_PREFIX))
+        .collect::<Vec<_>>();
+    if names.is_empty() {
+        println!(
+            "  {}",
+            ui_dim(&format!(
+                "No skills. Add YAML files to {}",
+                data_dir::path(SKILLS_DIR).display()
+            ))
+        );
+        return;
+    }
+    names.sort();
+    for name in names {
+        if let Some(tool) = registry.get(&name) {
+            println!(
+                "  {:<24} {}",
+                &name[SKILL_TOOL_PREFIX.len()..],
+                truncate_for_table(tool.description(), 72)
+            );
         }
     }
 }
 
-fn digest_task_state(task: &hypr_claw_tasks::TaskInfo) -> TaskStateDigest {
-    TaskStateDigest {
-        status: format!("{:?}", task.status).to_lowercase(),
-        progress_percent: (task.progress.clamp(0.0, 1.0) * 100.0).round() as u16,
-        error: task.error.clone().unwrap_or_default(),
-        result: task.result.clone().unwrap_or_default(),
+fn print_skill_result(name: &str, result: &hypr_claw_tools::ToolResult) {
+    let steps = result
+        .output
+        .as_ref()
+        .and_then(|output| output.get("steps"))
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    for step in &steps {
+        let status = step["status"].as_str().unwrap_or("?");
+        let detail = match status {
+            "ok" => step["output"].to_string(),
+            "failed" => step["error"].as_str().unwrap_or_default().to_string(),
+            _ => String::new(),
+        };
+        println!(
+            "  {:<6} {:<8} {:<28} {}",
+            step["step"].as_str().unwrap_or("-"),
+            match status {
+                "ok" => ui_success("ok"),
+                "failed" => ui_danger("failed"),
+                other => ui_dim(other),
+            },
+            truncate_for_table(step["tool"].as_str().unwrap_or("-"), 28),
+            truncate_for_table(&detail, 72)
+        );
+    }
+    match &result.error {
+        None if result.success => println!("✅ Skill {} finished", name),
+        error => println!(
+            "❌ Skill {} failed: {}",
+            name,
+            error.as_deref().unwrap_or("unknown error")
+        ),
     }
 }
 
-fn sync_background_task_events(
-    task_list: &[hypr_claw_tasks::TaskInfo],
-    index: &mut HashMap<String, TaskStateDigest>,
-    feed: &Arc<Mutex<Vec<String>>>,
+fn audit_plugin_verification(
+    audit_logger: &hypr_claw::infra::audit_logger::AuditLogger,
+    stage: &str,
+    name: &str,
+    version: &str,
+    outcome: &Result<hypr_claw_tools::plugins::Verification, String>,
 ) {
-    let mut seen = HashSet::new();
+    use hypr_claw::infra::contracts::{AuditEntry, PermissionDecision};
 
-    for task in task_list {
-        seen.insert(task.id.clone());
-        let next = digest_task_state(task);
-        match index.get(&task.id) {
-            None => {
-                push_task_event(
-                    feed,
-                    format!(
-                        "bg {} created status={} {}%",
-                        task.id, next.status, next.progress_percent
-                    ),
-                );
-            }
-            Some(prev) if prev != &next => {
-                let mut detail = format!(
-                    "bg {} {}->{} {}%->{}%",
-                    task.id, prev.status, next.status, prev.progress_percent, next.progress_percent
-                );
-                if !next.error.is_empty() {
-                    detail.push_str(&format!(" err={}", truncate_for_table(&next.error, 40)));
-                } else if !next.result.is_empty() {
-                    detail.push_str(&format!(" out={}", truncate_for_table(&next.result, 40)));
-                }
-                push_task_event(feed, detail);
+    let mut result = HashMap::new();
+    let approval = match outcome {
+        Ok(verification) => {
+            if let Ok(Value::Object(fields)) = serde_json::to_value(verification) {
+                result.extend(fields);
             }
-            _ => {}
+            PermissionDecision::ALLOW
         }
-        index.insert(task.id.clone(), next);
+        Err(reason) => {
+            result.insert("status".to_string(), json!("rejected"));
+            result.insert("reason".to_string(), json!(reason));
+            PermissionDecision::DENY
+        }
+    };
+    let entry = AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        session: "system".to_string(),
+        tool: "plugin.verify".to_string(),
+        input: HashMap::from([
+            ("plugin".to_string(), json!(name)),
+            ("version".to_string(), json!(version)),
+            ("stage".to_string(), json!(stage)),
+        ]),
+        result,
+        approval,
+    };
+    if let Err(e) = audit_logger.log(&entry) {
+        eprintln!("⚠️  Failed to audit plugin verification: {}", e);
     }
+}
 
-    let removed = index
-        .keys()
-        .filter(|id| !seen.contains(*id))
-        .cloned()
-        .collect::<Vec<_>>();
-    for id in removed {
-        index.remove(&id);
-        push_task_event(feed, format!("bg {} removed from active cache", id));
+/// Resolves the artifact (and detached signature, if any) of an index entry.
+async fn fetch_plugin_artifact(
+    entry: &hypr_claw_tools::plugins::IndexEntry,
+    index_url: &str,
+) -> Result<(hypr_claw_tools::plugins::IndexEntry, Vec<u8>), Box<dyn std::error::Error>> {
+    use hypr_claw_tools::plugins;
+
+    let mut entry = entry.clone();
+    entry.url = plugins::resolve_url(&entry.url, index_url);
+    let artifact = plugins::fetch(&entry.url).await?;
+    if entry.signature.is_none() {
+        if let Some(signature_url) = &entry.signature_url {
+            let signature = plugins::fetch(&plugins::resolve_url(signature_url, index_url)).await?;
+            entry.signature = Some(String::from_utf8_lossy(&signature).to_string());
+        }
     }
+    Ok((entry, artifact))
 }
 
-#[cfg(test)]
-fn build_task_log_lines(
-    task_list: &[hypr_claw_tasks::TaskInfo],
-    agent_state: &AgentOsState,
-    task_event_feed: &Arc<Mutex<Vec<String>>>,
-) -> Vec<String> {
-    let mut rows: Vec<(i64, String)> = Vec::new();
+fn cli_flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
 
-    if let Ok(events) = task_event_feed.lock() {
-        for line in events.iter() {
-            rows.push((chrono::Utc::now().timestamp(), format!("evt {}", line)));
-        }
+async fn fetch_plugin_index(
+    index_url: &str,
+) -> Result<hypr_claw_tools::plugins::PluginIndex, Box<dyn std::error::Error>> {
+    if index_url.is_empty() {
+        return Err("no plugin index configured; set plugins.index_url in ./data/config.yaml or pass --index <url>".into());
     }
+    let bytes = hypr_claw_tools::plugins::fetch(index_url).await?;
+    Ok(hypr_claw_tools::plugins::PluginIndex::parse(&bytes)?)
+}
 
-    for task in task_list {
-        let mut line = format!(
-            "bg {} {} {:>3}% {}",
-            task.id,
-            format!("{:?}", task.status).to_lowercase(),
-            (task.progress.clamp(0.0, 1.0) * 100.0).round() as u16,
-            truncate_for_table(&task.description, 44),
-        );
-        if let Some(err) = &task.error {
-            line.push_str(&format!(" | err: {}", truncate_for_table(err, 36)));
-        } else if let Some(result) = &task.result {
-            line.push_str(&format!(" | out: {}", truncate_for_table(result, 36)));
+async fn handle_plugins_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    use hypr_claw_tools::plugins::{self, PluginStore};
+
+    let (settings, audit) = Config::load()
+        .map(|c| (c.plugins, c.audit))
+        .unwrap_or_default();
+    let policy = &settings.signatures;
+    let index_url = cli_flag_value(args, "--index")
+        .map(str::to_string)
+        .unwrap_or(settings.index_url.clone());
+    let audit_logger = hypr_claw::infra::audit_logger::AuditLogger::with_rotation(
+        data_dir::path("./data/audit.log"),
+        audit.rotation,
+    )?;
+    let mut positional = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg.starts_with("--") {
+            if arg != "--available" {
+                iter.next();
+            }
+            continue;
         }
-        rows.push((task.updated_at, line));
+        positional.push(arg.as_str());
     }
+    let store = PluginStore::open(data_dir::path(PLUGINS_DIR))?;
 
-    for task in &agent_state.supervisor.tasks {
-        let mut line = format!(
-            "sup {} {} {} {}",
-            task.id,
-            format!("{:?}", task.status).to_lowercase(),
-            task.class.as_str(),
-            truncate_for_table(&task.prompt, 36),
-        );
-        if !task.resources.is_empty() {
-            line.push_str(&format!(
-                " | res: {}",
-                truncate_for_table(&task.resources.join(","), 20)
-            ));
+    match positional.as_slice() {
+        ["list"] | [] => {
+            let installed = store.list();
+            if installed.is_empty() {
+                println!("No plugins installed.");
+            }
+            for plugin in &installed {
+                println!(
+                    "{:<20} {:<10} tier={:<15} publisher={} source={}",
+                    plugin.name,
+                    plugin.version,
+                    plugin.permission_tier,
+                    plugin.publisher.as_deref().unwrap_or("-"),
+                    plugin.source_url
+                );
+            }
+            if args.iter().any(|a| a == "--available") {
+                let index = fetch_plugin_index(&index_url).await?;
+                println!("\nAvailable from {}:", index_url);
+                for entry in &index.plugins {
+                    let marker = match store.get(&entry.name) {
+                        Some(p) if p.version == entry.version => "installed",
+                        Some(_) => "update",
+                        None => "",
+                    };
+                    println!(
+                        "{:<20} {:<10} {:<7} {:<9} {}",
+                        entry.name,
+                        entry.version,
+                        format!("{:?}", entry.kind).to_lowercase(),
+                        marker,
+                        entry.description
+                    );
+                }
+            }
         }
-        if let Some(bg_id) = &task.background_task_id {
-            line.push_str(&format!(" | bg: {}", truncate_for_table(bg_id, 18)));
+        ["install", name] => {
+            let tier = cli_flag_value(args, "--tier")
+                .and_then(plugins::parse_tier)
+                .ok_or("install requires --tier <read|write|execute|system_critical>; plugins never choose their own permission tier")?;
+            let index = fetch_plugin_index(&index_url).await?;
+            let entry = match cli_flag_value(args, "--version") {
+                Some(version) => index
+                    .plugins
+                    .iter()
+                    .find(|e| e.name == *name && e.version == version),
+                None => index.latest(name),
+            }
+            .ok_or_else(|| format!("plugin '{}' not found in {}", name, index_url))?;
+            let (entry, artifact) = fetch_plugin_artifact(entry, &index_url).await?;
+            let outcome = store.install(&entry, &artifact, tier, &index_url, policy);
+            audit_plugin_verification(
+                &audit_logger,
+                "install",
+                &entry.name,
+                &entry.version,
+                &outcome
+                    .as_ref()
+                    .map(|(_, v)| v.clone())
+                    .map_err(Clone::clone),
+            );
+            let (installed, _) = outcome?;
+            println!(
+                "✅ Installed {} {} as tool {}{} (tier {}, sha256 {}, signed by {})",
+                installed.name,
+                installed.version,
+                plugins::PLUGIN_TOOL_PREFIX,
+                installed.name,
+                installed.permission_tier,
+                installed.sha256,
+                installed.signed_by.as_deref().unwrap_or("nobody")
+            );
         }
-        if let Some(err) = &task.error {
-            line.push_str(&format!(" | err: {}", truncate_for_table(err, 34)));
+        ["update", rest @ ..] if rest.len() <= 1 => {
+            let targets: Vec<_> = store
+                .list()
+                .into_iter()
+                .filter(|p| rest.first().is_none_or(|name| p.name == *name))
+                .collect();
+            if targets.is_empty() {
+                return Err("no matching installed plugins".into());
+            }
+            for plugin in targets {
+                let source = if cli_flag_value(args, "--index").is_some() {
+                    index_url.clone()
+                } else {
+                    plugin.index_url.clone()
+                };
+                let index = fetch_plugin_index(&source).await?;
+                let Some(entry) = index.latest(&plugin.name) else {
+                    eprintln!("⚠️  {} is no longer listed in {}", plugin.name, source);
+                    continue;
+                };
+                if entry.version == plugin.version {
+                    println!("{} {} is up to date", plugin.name, plugin.version);
+                    continue;
+                }
+                let (entry, artifact) = fetch_plugin_artifact(entry, &source).await?;
+                let outcome = store.install(&entry, &artifact, plugin.tier(), &source, policy);
+                audit_plugin_verification(
+                    &audit_logger,
+                    "update",
+                    &entry.name,
+                    &entry.version,
+                    &outcome
+                        .as_ref()
+                        .map(|(_, v)| v.clone())
+                        .map_err(Clone::clone),
+                );
+                let (updated, _) = outcome?;
+                println!(
+                    "✅ Updated {} {} -> {} (tier {} kept)",
+                    plugin.name, plugin.version, updated.version, updated.permission_tier
+                );
+            }
+        }
+        ["verify", rest @ ..] if rest.len() <= 1 => {
+            let mut failed = 0;
+            for plugin in store
+                .list()
+                .into_iter()
+                .filter(|p| rest.first().is_none_or(|name| p.name == *name))
+            {
+                let outcome = store.verify(&plugin, policy);
+                audit_plugin_verification(
+                    &audit_logger,
+                    "verify",
+                    &plugin.name,
+                    &plugin.version,
+                    &outcome,
+                );
+                match outcome {
+                    Ok(plugins::Verification::Verified { key }) => println!(
+                        "✅ {} {} sha256 ok, signed by {}",
+                        plugin.name, plugin.version, key
+                    ),
+                    Ok(plugins::Verification::UnsignedAllowed) => println!(
+                        "⚠️  {} {} sha256 ok, unsigned (allowed by config)",
+                        plugin.name, plugin.version
+                    ),
+                    Err(e) => {
+                        failed += 1;
+                        println!("❌ {}: {}", plugin.name, e);
+                    }
+                }
+            }
+            if failed > 0 {
+                return Err(format!("{} plugin(s) failed verification", failed).into());
+            }
+        }
+        ["remove", name] => {
+            if store.remove(name)? {
+                println!("✅ Removed plugin {}", name);
+            } else {
+                println!("Plugin '{}' is not installed", name);
+            }
+        }
+        _ => {
+            println!("Usage:");
+            println!("  hypr-claw plugins list [--available] [--index <url>]");
+            println!("  hypr-claw plugins install <name> --tier <read|write|execute|system_critical> [--version <v>] [--index <url>]");
+            println!("  hypr-claw plugins update [name] [--index <url>]");
+            println!("  hypr-claw plugins verify [name]");
+            println!("  hypr-claw plugins remove <name>");
         }
-        rows.push((task.updated_at, line));
     }
-
-    rows.sort_by(|a, b| a.0.cmp(&b.0));
-    rows.into_iter().map(|(_, line)| line).collect()
+    Ok(())
 }
 
-const UI_RESET: &str = "\x1b[0m";
-const UI_BOLD: &str = "\x1b[1m";
-const UI_DIM: &str = "\x1b[2m";
-const UI_ACCENT: &str = "\x1b[38;5;39m";
-const UI_INFO: &str = "\x1b[38;5;81m";
-const UI_SUCCESS: &str = "\x1b[38;5;42m";
-const UI_WARN: &str = "\x1b[38;5;214m";
-const UI_DANGER: &str = "\x1b[38;5;203m";
-
-fn use_color() -> bool {
-    std::env::var_os("NO_COLOR").is_none()
-}
+fn initialize_directories() -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(data_dir::path("./data/sessions"))?;
+    std::fs::create_dir_all(data_dir::path("./data/credentials"))?;
+    std::fs::create_dir_all(data_dir::path("./data/agents"))?;
+    std::fs::create_dir_all(data_dir::path("./data/context"))?;
+    std::fs::create_dir_all(data_dir::path("./data/tasks"))?;
+    std::fs::create_dir_all(data_dir::path("./data/capabilities"))?;
+    std::fs::create_dir_all(data_dir::path(ARTIFACTS_DIR))?;
 
-fn paint(text: &str, style: &str) -> String {
-    if use_color() {
-        format!("{style}{text}{UI_RESET}")
-    } else {
-        text.to_string()
+    let audit_log = data_dir::path("./data/audit.log");
+    if !audit_log.exists() {
+        std::fs::File::create(audit_log)?;
     }
-}
 
-fn ui_title(text: &str) -> String {
-    paint(text, UI_BOLD)
-}
+    std::fs::create_dir_all("./sandbox")?;
 
-fn ui_accent(text: &str) -> String {
-    paint(text, UI_ACCENT)
-}
+    // Create default agent config if it doesn't exist
+    let default_agent_config = data_dir::path("./data/agents/default.yaml");
+    let default_agent_soul = data_dir::path("./data/agents/default_soul.md");
 
-fn ui_dim(text: &str) -> String {
-    paint(text, UI_DIM)
-}
+    if !default_agent_config.exists() {
+        std::fs::write(
+            default_agent_config,
+            "id: default\nsoul: default_soul.md\ntools:\n  - echo\n  - fs.read\n  - fs.write\n  - fs.list\n  - fs.create_dir\n  - fs.move\n  - fs.copy\n  - fs.delete\n  - fs.find_duplicates\n  - fs.batch_rename\n  - fs.stage\n  - fs.apply_staged\n  - fs.undo_apply\n  - hypr.workspace.switch\n  - hypr.workspace.move_window\n  - hypr.window.focus\n  - hypr.window.close\n  - hypr.window.move\n  - hypr.exec\n  - proc.spawn\n  - proc.kill\n  - proc.list\n  - desktop.open_url\n  - desktop.launch_app\n  - desktop.launch_app_and_wait_text\n  - desktop.search_web\n  - desktop.open_gmail\n  - desktop.type_text\n  - desktop.key_press\n  - desktop.key_combo\n  - desktop.mouse_click\n  - desktop.capture_screen\n  - desktop.active_window\n  - desktop.list_windows\n  - desktop.cursor_position\n  - desktop.read_screen_state\n  - desktop.window_snapshot\n  - desktop.window_diff\n  - desktop.mouse_move\n  - desktop.mouse_move_and_verify\n  - desktop.click_at\n  - desktop.click_at_and_verify\n  - desktop.ocr_screen\n  - desktop.find_text\n  - desktop.click_text\n  - desktop.wait_for_text\n  - wallpaper.set\n  - system.memory\n  - system.battery\n  - system.gpu\n  - system.displays\n  - system.thermal\n  - timer.set\n  - time.parse\n  - time.format\n  - contacts.lookup\n  - knowledge.search\n  - help.lookup\n  - mail.compose\n  - telegram.open_chat\n  - doc.generate\n  - calc.evaluate\n  - csv.query\n  - csv.write\n  - db.query\n  - db.execute\n  - code.run_snippet\n  - env.get\n  - env.list\n  - schedule.system_create\n  - schedule.system_list\n  - schedule.system_remove\n  - plan.request_approval\n  - ask_user\n  - form.remember\n  - form.fill\n"
+        )?;
+    }
 
-fn ui_info(text: &str) -> String {
-    paint(text, UI_INFO)
-}
+    if !default_agent_soul.exists() {
+        std::fs::write(
+            default_agent_soul,
+            "You are a local Linux + Hyprland OS assistant. Follow strict workflow: observe -> plan -> execute tool -> verify -> continue until done. Choose tools dynamically from allowed set. Ask permission before destructive/high-impact actions. Use mouse+keyboard style actions for GUI tasks.",
+        )?;
+    }
 
-fn ui_success(text: &str) -> String {
-    paint(text, UI_SUCCESS)
+    Ok(())
 }
 
-fn ui_warn(text: &str) -> String {
-    paint(text, UI_WARN)
+struct SoulProfile {
+    system_prompt: String,
+    max_iterations: usize,
 }
 
-fn ui_danger(text: &str) -> String {
-    paint(text, UI_DANGER)
+fn power_agent_profile() -> SoulProfile {
+    SoulProfile {
+        system_prompt: "You are a powerful local Linux + Hyprland OS assistant.\nFollow strict workflow: observe -> plan -> execute tool -> verify -> continue until done.\nFor GUI tasks, observe with desktop.read_screen_state/active_window/list_windows/cursor_position first, then act with cursor + keyboard tools.\nChoose tools dynamically from allowed_tools_now.\nAsk for permission before destructive/high-impact actions.\nAvoid hardcoded assumptions and adapt from live system/context.".to_string(),
+        max_iterations: 36,
+    }
 }
 
-fn ui_divider() -> String {
-    ui_accent(&"─".repeat(78))
+fn normalize_runtime_tool_name(name: &str) -> String {
+    match name {
+        "screen.capture" | "capture.screen" => "desktop.capture_screen".to_string(),
+        "screen.ocr" | "ocr.screen" => "desktop.ocr_screen".to_string(),
+        "screen.find_text" | "text.find_on_screen" => "desktop.find_text".to_string(),
+        "screen.click_text" | "text.click_on_screen" => "desktop.click_text".to_string(),
+        "gmail.open" => "desktop.open_gmail".to_string(),
+        "browser.open_url" => "desktop.open_url".to_string(),
+        "browser.search" => "desktop.search_web".to_string(),
+        "app.open" | "app.launch" => "desktop.launch_app".to_string(),
+        "process.spawn" => "proc.spawn".to_string(),
+        "process.kill" => "proc.kill".to_string(),
+        "process.list" => "proc.list".to_string(),
+        other => other.to_string(),
+    }
 }
 
-fn ui_section(title: &str) -> String {
-    let mut line = format!("─ {} ", title);
-    let fill = 78usize.saturating_sub(line.chars().count());
-    line.push_str(&"─".repeat(fill));
-    ui_accent(&line)
+fn derive_runtime_allowed_tools(
+    registry: &Arc<hypr_claw_tools::ToolRegistryImpl>,
+    capability_registry: &Value,
+) -> HashSet<String> {
+    let has_array_entries = |path: &str| {
+        capability_registry
+            .pointer(path)
+            .and_then(|v| v.as_array())
+            .map(|arr| !arr.is_empty())
+            .unwrap_or(false)
+    };
+    let ocr_available = capability_registry
+        .pointer("/capabilities/ocr_available")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let hyprland_available = capability_registry
+        .pointer("/platform/hyprland_available")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let input_backends =
+        read_string_array_from_value(capability_registry.pointer("/capabilities/input_backends"));
+    let has_keyboard_backend = input_backends
+        .iter()
+        .any(|b| matches!(b.as_str(), "wtype" | "ydotool"));
+    let has_pointer_backend = input_backends
+        .iter()
+        .any(|b| matches!(b.as_str(), "ydotool" | "wlrctl"));
+    let has_screenshot_backend = has_array_entries("/capabilities/screenshot_backends");
+    let has_wallpaper_backend = has_array_entries("/capabilities/wallpaper_backends");
+    // Tools with a degradation chain only need one step of it.
+    let input_or_hyprctl = |backend: &str| {
+        input_backends.iter().any(|b| b == backend) || (backend == "hyprctl" && hyprland_available)
+    };
+
+    registry
+        .list()
+        .into_iter()
+        .filter(|tool| match tool.as_str() {
+            "hypr.workspace.switch"
+            | "hypr.workspace.move_window"
+            | "hypr.window.focus"
+            | "hypr.window.close"
+            | "hypr.window.move"
+            | "hypr.exec" => hyprland_available,
+            "wallpaper.set" => has_wallpaper_backend,
+            "desktop.capture_screen" => has_screenshot_backend,
+            "desktop.ocr_screen" | "desktop.find_text" => has_screenshot_backend && ocr_available,
+            "desktop.wait_for_text" | "desktop.launch_app_and_wait_text" => {
+                has_screenshot_backend && ocr_available
+            }
+            "desktop.cursor_position" | "system.displays" => hyprland_available,
+            "desktop.mouse_move_and_verify" | "desktop.click_at_and_verify" => {
+                has_pointer_backend && hyprland_available
+            }
+            "desktop.read_screen_state" => hyprland_available || has_screenshot_backend,
+            "desktop.window_snapshot" | "desktop.window_diff" => {
+                hyprland_available && has_screenshot_backend
+            }
+            "desktop.key_press" | "desktop.key_combo" | "desktop.mouse_move" => {
+                hypr_claw_tools::os_capabilities::degradation::usable(tool, input_or_hyprctl)
+            }
+            "desktop.type_text" | "form.fill" => has_keyboard_backend,
+            "desktop.mouse_click" | "desktop.click_at" => has_pointer_backend,
+            "desktop.click_text" => has_pointer_backend && has_screenshot_backend && ocr_available,
+            _ => true,
+        })
+        .collect()
 }
 
-fn status_badge(status: &hypr_claw_tasks::TaskStatus) -> String {
-    match status {
-        hypr_claw_tasks::TaskStatus::Running => ui_info("RUN"),
-        hypr_claw_tasks::TaskStatus::Pending => ui_warn("PEND"),
-        hypr_claw_tasks::TaskStatus::Completed => ui_success("DONE"),
-        hypr_claw_tasks::TaskStatus::Failed => ui_danger("FAIL"),
-        hypr_claw_tasks::TaskStatus::Cancelled => ui_warn("STOP"),
+#[cfg(test)]
+fn max_view_offset(total: usize, page: usize) -> usize {
+    let page = page.max(1);
+    total.saturating_sub(page)
+}
+
+#[cfg(test)]
+fn tail_window_slice<T: Clone>(
+    items: &[T],
+    offset_from_latest: usize,
+    page_size: usize,
+) -> (Vec<T>, usize, usize, usize) {
+    if items.is_empty() {
+        return (Vec::new(), 0, 0, 0);
     }
+    let page = page_size.max(1);
+    let total = items.len();
+    let clamped_offset = offset_from_latest.min(max_view_offset(total, page));
+    let end = total.saturating_sub(clamped_offset);
+    let start = end.saturating_sub(page);
+    (items[start..end].to_vec(), start + 1, end, total)
 }
 
-fn progress_bar(progress: f32, width: usize) -> String {
-    let p = progress.clamp(0.0, 1.0);
-    let filled = (p * width as f32).round() as usize;
-    let filled = filled.min(width);
-    let mut bar = String::new();
-    bar.push('[');
-    bar.push_str(&"█".repeat(filled));
-    bar.push_str(&"░".repeat(width.saturating_sub(filled)));
-    bar.push(']');
-    bar
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TaskStateDigest {
+    status: String,
+    progress_percent: u16,
+    error: String,
+    result: String,
 }
 
-fn short_model_name(model: &str) -> String {
-    model.split('/').next_back().unwrap_or(model).to_string()
+fn now_hms() -> String {
+    chrono::Utc::now().format("%H:%M:%S").to_string()
 }
 
-fn format_timestamp(ts: i64) -> String {
-    chrono::DateTime::<chrono::Utc>::from_timestamp(ts, 0)
-        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
-        .unwrap_or_else(|| "n/a".to_string())
+fn push_task_event(feed: &Arc<Mutex<Vec<String>>>, message: impl Into<String>) {
+    let line = format!("{} {}", now_hms(), message.into());
+    if let Ok(mut rows) = feed.lock() {
+        rows.push(line);
+        if rows.len() > 320 {
+            let drop_count = rows.len() - 320;
+            rows.drain(0..drop_count);
+        }
+    }
 }
 
-fn truncate_for_table(value: &str, max: usize) -> String {
-    if value.chars().count() <= max {
-        return value.to_string();
+const DATA_DIR: &str = "./data";
+const BACKUP_PASSPHRASE_ENV: &str = "HYPR_CLAW_BACKUP_PASSPHRASE";
+const PLUGINS_DIR: &str = "./data/plugins";
+const SKILLS_DIR: &str = "./data/skills";
+/// Drafts from `skill learn`; not loaded until `skill save` moves them up.
+const SKILL_DRAFTS_DIR: &str = "./data/skills/drafts";
+/// Fewest successful tool calls for which a run is offered as a skill.
+const MIN_LEARNED_SKILL_STEPS: usize = 2;
+const REMINDERS_PATH: &str = "./data/reminders.json";
+const QUESTIONS_PATH: &str = "./data/pending_questions.json";
+const FORMS_PATH: &str = "./data/forms.json";
+const CONTACTS_PATH: &str = "./data/contacts.json";
+const KNOWLEDGE_PATH: &str = "./data/knowledge.json";
+const ARTIFACTS_DIR: &str = "./data/artifacts";
+const ACTIONS_DIR: &str = "./data/actions";
+
+/// Splits `contacts add` arguments into the contact name (all words before the
+/// first `field=value`) and the field assignments.
+fn parse_contact_add_args(args: &str) -> Option<(String, Vec<(String, String)>)> {
+    let mut name_parts = Vec::new();
+    let mut fields = Vec::new();
+    for word in args.split_whitespace() {
+        match word.split_once('=') {
+            Some((field, value)) => fields.push((field.to_lowercase(), value.to_string())),
+            None if fields.is_empty() => name_parts.push(word),
+            None => return None,
+        }
     }
-    let mut out: String = value.chars().take(max.saturating_sub(3)).collect();
-    out.push_str("...");
-    out
+    if name_parts.is_empty() {
+        return None;
+    }
+    Some((name_parts.join(" "), fields))
 }
 
-fn strip_ansi_and_controls(raw: &str) -> String {
-    let bytes = raw.as_bytes();
-    let mut out = String::with_capacity(raw.len());
-    let mut i = 0usize;
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum KnowledgeCommand {
+    List,
+    Import(String),
+    Search(String),
+    Remove(String),
+}
 
-    while i < bytes.len() {
-        if bytes[i] == 0x1B {
-            i += 1;
-            if i >= bytes.len() {
-                break;
+fn parse_knowledge_command(args: &str) -> Option<KnowledgeCommand> {
+    let args = args.trim();
+    let (verb, rest) = args.split_once(' ').unwrap_or((args, ""));
+    let rest = rest.trim().to_string();
+    match (verb, rest.is_empty()) {
+        ("" | "list", true) => Some(KnowledgeCommand::List),
+        ("import", false) => Some(KnowledgeCommand::Import(rest)),
+        ("search", false) => Some(KnowledgeCommand::Search(rest)),
+        ("remove", false) => Some(KnowledgeCommand::Remove(rest)),
+        _ => None,
+    }
+}
+
+async fn run_knowledge_command(store: &hypr_claw_tools::knowledge::KnowledgeStore, args: &str) {
+    let Some(command) = parse_knowledge_command(args) else {
+        println!("Usage: knowledge [list] | import <dir|url> | search <query> | remove <source>");
+        return;
+    };
+    match command {
+        KnowledgeCommand::List => {
+            println!("\n{}", ui_title("Knowledge"));
+            let sources = store.sources();
+            if sources.is_empty() {
+                println!(
+                    "  {}",
+                    ui_dim("Nothing imported. Use: knowledge import <dir|url>")
+                );
             }
-            match bytes[i] {
-                b'[' => {
-                    i += 1;
-                    while i < bytes.len() {
-                        let b = bytes[i];
-                        i += 1;
-                        if (0x40..=0x7E).contains(&b) {
-                            break;
-                        }
-                    }
-                    continue;
-                }
-                b']' => {
-                    i += 1;
-                    while i < bytes.len() {
-                        if bytes[i] == 0x07 {
-                            i += 1;
-                            break;
-                        }
-                        if bytes[i] == 0x1B && i + 1 < bytes.len() && bytes[i + 1] == b'\\' {
-                            i += 2;
-                            break;
-                        }
-                        i += 1;
-                    }
-                    continue;
-                }
-                _ => continue,
+            for (source, chunks) in sources {
+                println!("  {:>4} chunks  {}", chunks, source);
             }
+            println!();
         }
-
-        let rest = &raw[i..];
-        let Some(ch) = rest.chars().next() else {
-            break;
-        };
-        i += ch.len_utf8();
-        if ch.is_control() && ch != '\n' && ch != '\t' {
-            continue;
+        KnowledgeCommand::Import(target)
+            if target.starts_with("http://") || target.starts_with("https://") =>
+        {
+            eprintln!("📥 Fetching {}", target);
+            let imported = match hypr_claw_tools::knowledge::fetch_document(&target).await {
+                Ok(text) => store.add_document(&target, &text),
+                Err(e) => Err(e),
+            };
+            match imported {
+                Ok(chunks) => println!("✅ Imported {} ({} chunks)", target, chunks),
+                Err(e) => println!("❌ Failed to import {}: {}", target, e),
+            }
         }
-        out.push(ch);
+        KnowledgeCommand::Import(target) => {
+            let path = std::path::PathBuf::from(&target);
+            match store.import_path(&path) {
+                Ok(summary) => println!(
+                    "✅ Imported {} document(s), {} chunks from {} ({} skipped)",
+                    summary.documents, summary.chunks, target, summary.skipped
+                ),
+                Err(e) => println!("❌ Failed to import {}: {}", target, e),
+            }
+        }
+        KnowledgeCommand::Search(query) => {
+            let hits = store.search(&query, 5);
+            if hits.is_empty() {
+                println!("No matches for '{}'", query);
+            }
+            for hit in hits {
+                let heading = hit.chunk.heading.as_deref().unwrap_or("-");
+                println!("{} › {}", ui_accent(&hit.chunk.source), heading);
+                println!(
+                    "  {}",
+                    truncate_for_table(&hit.chunk.text.replace('\n', " "), 160)
+                );
+            }
+        }
+        KnowledgeCommand::Remove(source) => match store.remove_source(&source) {
+            Ok(0) => println!("❌ Nothing imported from '{}'", source),
+            Ok(chunks) => println!("🧹 Removed {} chunks from {}", chunks, source),
+            Err(e) => println!("❌ Failed to update knowledge index: {}", e),
+        },
     }
+}
 
-    out
+fn print_contacts(contacts: &[hypr_claw_tools::contacts::Contact]) {
+    println!("\n{}", ui_title("Contacts"));
+    if contacts.is_empty() {
+        println!(
+            "  {}",
+            ui_dim("No contacts. Use: contacts add <name> email=<addr> telegram=<@handle>")
+        );
+        return;
+    }
+    for contact in contacts {
+        println!(
+            "  {:<24} {:<32} {:<18} {}",
+            truncate_for_table(&contact.name, 24),
+            contact.email.as_deref().unwrap_or("-"),
+            contact
+                .telegram
+                .as_deref()
+                .map(|t| format!("@{t}"))
+                .unwrap_or_else(|| "-".to_string()),
+            contact.phone.as_deref().unwrap_or("-")
+        );
+    }
+    println!();
 }
 
-fn sanitize_single_line(raw: &str) -> String {
-    strip_ansi_and_controls(raw)
-        .replace('\n', " ")
-        .split_whitespace()
-        .collect::<Vec<_>>()
-        .join(" ")
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ReminderAddRequest {
+    when: String,
+    message: String,
+    speak: bool,
+    follow_up_prompt: Option<String>,
+}
+
+fn parse_reminder_add_args(args: &str) -> Option<ReminderAddRequest> {
+    let (head, follow_up_prompt) = match args.split_once("--then") {
+        Some((head, tail)) => (
+            head,
+            Some(tail.trim().to_string()).filter(|t| !t.is_empty()),
+        ),
+        None => (args, None),
+    };
+    let mut speak = false;
+    let mut words = Vec::new();
+    for word in head.split_whitespace() {
+        if word == "--speak" {
+            speak = true;
+        } else {
+            words.push(word);
+        }
+    }
+    if words.len() < 2 {
+        return None;
+    }
+    Some(ReminderAddRequest {
+        when: words[0].to_string(),
+        message: words[1..].join(" "),
+        speak,
+        follow_up_prompt,
+    })
 }
 
-fn sanitize_user_input_line(raw: &str) -> String {
-    let mut value = sanitize_single_line(raw.trim());
-    for _ in 0..4 {
-        if let Some(rest) = value.strip_prefix("tui> ") {
-            value = sanitize_single_line(rest.trim());
-            continue;
+fn print_reminders(reminders: &[hypr_claw_tools::reminders::Reminder]) {
+    println!("\n{}", ui_title("Reminders"));
+    if reminders.is_empty() {
+        println!(
+            "  {}",
+            ui_dim("No reminders. Use: reminder add 10m <message>")
+        );
+        return;
+    }
+    for reminder in reminders {
+        let status = match reminder.status {
+            hypr_claw_tools::reminders::ReminderStatus::Pending => ui_info("pending"),
+            hypr_claw_tools::reminders::ReminderStatus::Fired => ui_success("fired"),
+            hypr_claw_tools::reminders::ReminderStatus::Cancelled => ui_dim("cancelled"),
+        };
+        let mut flags = Vec::new();
+        if reminder.speak {
+            flags.push("speak");
         }
-        if value.starts_with("hypr[") {
-            if let Some((_, rest)) = value.rsplit_once("]>") {
-                let cleaned = rest.trim();
-                if !cleaned.is_empty() {
-                    value = sanitize_single_line(cleaned);
-                    continue;
-                }
-            }
+        if reminder.follow_up_prompt.is_some() {
+            flags.push("follow-up");
         }
-        break;
+        println!(
+            "  {:<8} {:<10} {:<20} {} {}",
+            reminder.id,
+            status,
+            format_timestamp(reminder.due_at),
+            truncate_for_table(&reminder.message, 48),
+            ui_dim(&flags.join(","))
+        );
     }
-    value
+    println!();
 }
 
-fn print_console_bootstrap(
-    provider_name: &str,
-    model: &str,
-    agent_name: &str,
-    display_name: &str,
-    user_id: &str,
-    session_key: &str,
-    active_thread_id: &str,
-) {
-    println!("{}", ui_section("Hypr-Claw"));
-    println!(
-        "{} {}  {} {}  {} {}",
-        ui_dim("provider"),
-        ui_info(&truncate_for_table(provider_name, 20)),
-        ui_dim("model"),
-        ui_info(&truncate_for_table(&short_model_name(model), 24)),
-        ui_dim("session"),
-        truncate_for_table(session_key, 26)
-    );
-    println!(
-        "{} {}  {} {}  {} {}",
-        ui_dim("user"),
-        truncate_for_table(&format!("{} ({})", display_name, user_id), 24),
-        ui_dim("agent"),
-        truncate_for_table(agent_name, 16),
-        ui_dim("thread"),
-        truncate_for_table(active_thread_id, 14)
-    );
-    println!(
-        "{} {}",
-        ui_dim("commands"),
-        truncate_for_table(
-            "help  status  scan  capabilities  /models  tasks  queue add/run/status/clear  view",
-            64
-        )
-    );
-    println!("{}", ui_divider());
+fn print_form_profiles(profiles: &[hypr_claw_tools::forms::FormProfile]) {
+    if profiles.is_empty() {
+        println!("No saved forms. Fill a form once and the agent remembers its fields.");
+        return;
+    }
+    println!("\n📝 Saved forms");
+    for profile in profiles {
+        println!(
+            "  {}  ({} fields, {}{})",
+            profile.name,
+            profile.fields.len(),
+            match profile.navigation {
+                hypr_claw_tools::forms::Navigation::Labels => "by label",
+                hypr_claw_tools::forms::Navigation::Tab => "by tab",
+            },
+            profile
+                .window_match
+                .as_deref()
+                .map(|m| format!(", window \"{m}\""))
+                .unwrap_or_default()
+        );
+        for field in &profile.fields {
+            let consent = match (field.sensitive, field.always_allowed) {
+                (false, _) => "",
+                (true, false) => "  [sensitive, asks]",
+                (true, true) => "  [sensitive, allowed]",
+            };
+            println!("      {} ← {}{}", field.label, field.fact, consent);
+        }
+    }
     println!();
 }
 
-fn print_run_panel(
-    prompt: &str,
-    class_label: &str,
-    max_iterations: usize,
-    timeout_secs: u64,
-    model: &str,
-    active_tools: usize,
-    focused_tools: Option<usize>,
-) {
-    println!();
-    println!("{}", ui_section("Run"));
-    println!(
-        "{} {}",
-        ui_dim("goal"),
-        truncate_for_table(&sanitize_single_line(prompt), 112)
-    );
-    println!(
-        "{} power  {}  iter={}  timeout={}s  model={}",
-        ui_dim("plan"),
-        class_label,
-        max_iterations,
-        timeout_secs,
-        short_model_name(model)
-    );
-    println!("{} observe -> plan -> execute -> verify", ui_dim("flow"));
-    let focused = focused_tools
-        .map(|count| format!(" (focused {})", count))
-        .unwrap_or_default();
-    println!("{} {} active{}", ui_dim("tools"), active_tools, focused);
+fn print_pending_questions(questions: &[hypr_claw_tools::questions::Question]) {
+    if questions.is_empty() {
+        println!("No pending questions.");
+        return;
+    }
+    println!("\n❓ Pending questions");
+    for question in questions {
+        println!(
+            "  {}  {}  {}",
+            question.id,
+            question.task.as_deref().unwrap_or("-"),
+            question.question
+        );
+        for (index, choice) in question.choices.iter().enumerate() {
+            println!("        {}. {}", index + 1, choice);
+        }
+    }
+    println!("Reply with: answer <id> <text or choice number>\n");
 }
 
-fn print_help() {
-    println!("\n{}", ui_title("Hypr-Claw Command Reference"));
-    println!("  {}", ui_accent("Core"));
-    println!("    help                  Show this help");
-    println!("    status                Runtime state snapshot");
-    println!("    scan                  Re-run standard/deep system learning scan");
-    println!("    capabilities          Show runtime capability registry summary");
-    println!("    clear                 Clear terminal");
-    println!("    interrupt             Send interrupt signal to active run");
-    println!("    exit | quit           Exit agent");
-    println!("  {}", ui_accent("Models"));
-    println!("    /models               Interactive model switch");
-    println!("    /models list          List provider models");
-    println!("    /models set <id>      Set model directly");
-    println!("  {}", ui_accent("Tasks"));
-    println!("    tasks                 Show background task table");
-    println!("    queue                 Show supervisor queue");
-    println!("    queue status          Compact queue summary + actionable IDs");
-    println!("    queue add <prompt>    Add task prompt to queue");
-    println!("    queue run             Run next queued task");
-    println!("    queue clear           Cancel queued items");
-    println!("  {}", ui_accent("System"));
-    println!("    profile               Show learned system profile");
-    println!("    scan                  Re-run system scan");
-    println!("    view                  Show current CLI view mode");
-    println!("    view transcript       Enable transcript panes");
-    println!("    view compact          Disable transcript panes");
-    println!();
+/// Prompt for the run that continues a background task after its question
+/// was answered.
+fn answered_question_prompt(task_prompt: &str, question: &str, answer: &str) -> String {
+    format!(
+        "{task_prompt}\n\nEarlier in this task you asked the user: {question}\nTheir answer: {answer}\nContinue the task using that answer."
+    )
 }
 
-fn action_feed_len(feed: &Arc<Mutex<Vec<String>>>) -> usize {
-    feed.lock().ok().map(|f| f.len()).unwrap_or(0)
+/// Polls the reminder store and delivers due reminders. Follow-up prompts are
+/// handed to the REPL loop, which owns the supervisor queue.
+fn spawn_reminder_scheduler(
+    store: Arc<hypr_claw_tools::reminders::ReminderStore>,
+    task_event_feed: Arc<Mutex<Vec<String>>>,
+    follow_ups: Arc<Mutex<Vec<String>>>,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(5));
+        loop {
+            ticker.tick().await;
+            let due = match store.take_due(chrono::Utc::now().timestamp()) {
+                Ok(due) => due,
+                Err(e) => {
+                    push_task_event(&task_event_feed, format!("reminder store error {}", e));
+                    continue;
+                }
+            };
+            for reminder in due {
+                println!("\n⏰ Reminder {}: {}", reminder.id, reminder.message);
+                if let Err(e) =
+                    hypr_claw_tools::os_capabilities::system::notify("Reminder", &reminder.message)
+                        .await
+                {
+                    push_task_event(
+                        &task_event_feed,
+                        format!("reminder {} notification failed {}", reminder.id, e),
+                    );
+                }
+                if reminder.speak {
+                    if let Err(e) =
+                        hypr_claw_tools::os_capabilities::system::speak(&reminder.message).await
+                    {
+                        push_task_event(
+                            &task_event_feed,
+                            format!("reminder {} tts failed {}", reminder.id, e),
+                        );
+                    }
+                }
+                push_task_event(
+                    &task_event_feed,
+                    format!(
+                        "reminder {} fired {}",
+                        reminder.id,
+                        truncate_for_table(&reminder.message, 44)
+                    ),
+                );
+                if let Some(prompt) = reminder.follow_up_prompt {
+                    if let Ok(mut pending) = follow_ups.lock() {
+                        pending.push(prompt);
+                    }
+                }
+            }
+        }
+    });
 }
 
-fn action_feed_since(feed: &Arc<Mutex<Vec<String>>>, start: usize, max_rows: usize) -> Vec<String> {
-    let rows = feed.lock().ok().map(|f| f.clone()).unwrap_or_default();
-    if start >= rows.len() {
-        return Vec::new();
-    }
-    let mut slice = rows[start..].to_vec();
-    if max_rows > 0 && slice.len() > max_rows {
-        let keep_from = slice.len().saturating_sub(max_rows);
-        slice = slice[keep_from..].to_vec();
-    }
-    slice
+/// Writes an encrypted backup whenever the newest archive is older than the
+/// configured interval, then prunes old archives.
+fn spawn_backup_scheduler(
+    settings: config::BackupConfig,
+    task_event_feed: Arc<Mutex<Vec<String>>>,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(600));
+        loop {
+            ticker.tick().await;
+            let settings = settings.clone();
+            let outcome = tokio::task::spawn_blocking(move || {
+                use hypr_claw::infra::backup;
+                let dir = std::path::PathBuf::from(&settings.dir);
+                let newest = backup::list_backups(&dir)
+                    .map_err(|e| e.to_string())?
+                    .into_iter()
+                    .next()
+                    .and_then(|b| chrono::DateTime::parse_from_rfc3339(&b.header.created_at).ok());
+                let interval = chrono::Duration::hours(settings.interval_hours as i64);
+                if newest.is_some_and(|t| chrono::Utc::now().signed_duration_since(t) < interval) {
+                    return Ok(None);
+                }
+                let key = backup_key(false).map_err(|e| e.to_string())?;
+                let summary = backup::create_backup(
+                    &data_dir::path(DATA_DIR),
+                    &dir,
+                    &key,
+                    backup::DEFAULT_ENTRIES,
+                )
+                .map_err(|e| e.to_string())?;
+                let _ = backup::prune_backups(&dir, settings.keep);
+                Ok::<_, String>(Some(summary.path))
+            })
+            .await;
+            match outcome {
+                Ok(Ok(Some(path))) => push_task_event(
+                    &task_event_feed,
+                    format!("backup written {}", path.display()),
+                ),
+                Ok(Ok(None)) => {}
+                Ok(Err(e)) => push_task_event(&task_event_feed, format!("backup failed {}", e)),
+                Err(e) => push_task_event(&task_event_feed, format!("backup failed {}", e)),
+            }
+        }
+    });
 }
 
-fn text_preview_lines(text: &str, max_lines: usize, max_width: usize) -> Vec<String> {
-    if text.trim().is_empty() {
-        return vec!["(empty)".to_string()];
-    }
-    let sanitized = strip_ansi_and_controls(text);
-    let mut lines = Vec::new();
-    for line in sanitized.lines().take(max_lines) {
-        lines.push(truncate_for_table(&sanitize_single_line(line), max_width));
-    }
-    if sanitized.lines().count() > max_lines {
-        lines.push(format!("... (truncated, {}+ lines)", max_lines));
-    }
-    if lines.is_empty() {
-        lines.push(truncate_for_table(&sanitize_single_line(&sanitized), max_width));
-    }
-    lines
+/// Applies the screenshot and artifact retention policies periodically.
+fn spawn_retention_sweeper(
+    settings: config::RetentionConfig,
+    task_event_feed: Arc<Mutex<Vec<String>>>,
+) {
+    tokio::spawn(async move {
+        let period = Duration::from_secs(settings.interval_minutes * 60);
+        let mut ticker = tokio::time::interval(period);
+        loop {
+            ticker.tick().await;
+            let settings = settings.clone();
+            let outcome = tokio::task::spawn_blocking(move || sweep_artifacts(&settings)).await;
+            let Ok(reports) = outcome else {
+                continue;
+            };
+            for (kind, report) in reports {
+                match report {
+                    Ok(report) if report.removed > 0 => push_task_event(
+                        &task_event_feed,
+                        format!(
+                            "retention removed {} {} ({})",
+                            report.removed,
+                            kind,
+                            format_megabytes(report.freed_bytes)
+                        ),
+                    ),
+                    Ok(_) => {}
+                    Err(e) => {
+                        push_task_event(&task_event_feed, format!("retention {kind} failed {e}"))
+                    }
+                }
+            }
+        }
+    });
 }
 
-fn print_transcript_panes(
-    prompt: &str,
-    model: &str,
-    class_label: &str,
-    run_elapsed_ms: u64,
-    tool_rows: &[String],
-    recovery_rows: &[String],
-    result_rows: &[String],
+/// Checks the thermal rules periodically, notifying when one trips.
+fn spawn_thermal_monitor(
+    settings: config::ThermalConfig,
+    guard: Arc<thermal::ThermalGuard>,
+    task_event_feed: Arc<Mutex<Vec<String>>>,
 ) {
-    println!("{}", ui_section("Transcript"));
-    println!(
-        "{} {}  {} {}  {} {}ms",
-        ui_dim("model"),
-        ui_info(&short_model_name(model)),
-        ui_dim("class"),
-        ui_info(class_label),
-        ui_dim("elapsed"),
-        ui_info(&run_elapsed_ms.to_string())
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(settings.interval_secs));
+        loop {
+            ticker.tick().await;
+            let Ok(reading) = hypr_claw_tools::os_capabilities::thermal::thermal().await else {
+                continue;
+            };
+            let trips = thermal::evaluate(
+                &settings.rules,
+                &reading,
+                settings.hysteresis,
+                &guard.active(),
+            );
+            let (started, cleared) = guard.update(trips);
+            for trip in started {
+                let holding = if trip.action == config::ThermalAction::Throttle {
+                    "; new requests wait until it cools down"
+                } else {
+                    ""
+                };
+                push_task_event(&task_event_feed, format!("thermal {}", trip.describe()));
+                let _ = hypr_claw_tools::os_capabilities::system::notify(
+                    "hypr-claw: running hot",
+                    &format!("{}{}", trip.describe(), holding),
+                )
+                .await;
+            }
+            for trip in cleared {
+                push_task_event(&task_event_feed, format!("thermal cleared {}", trip.sensor));
+            }
+        }
+    });
+}
+
+/// Holds a request while a throttle rule is tripped, up to
+/// `thermal.max_wait_secs`; Ctrl+C stops waiting.
+async fn wait_for_thermal_clearance(
+    guard: &thermal::ThermalGuard,
+    settings: &config::ThermalConfig,
+    interrupt: &tokio::sync::Notify,
+) {
+    let Some(trip) = guard.throttle() else {
+        return;
+    };
+    eprintln!(
+        "🌡️  {}; waiting up to {}s for it to cool down (Ctrl+C to go ahead)",
+        trip.describe(),
+        settings.max_wait_secs
     );
-    let prompt_rows = text_preview_lines(prompt, 2, 104);
-    if let Some(first) = prompt_rows.first() {
-        println!("{} {}", ui_dim("prompt"), first);
-    }
-    for row in prompt_rows.iter().skip(1) {
-        println!("{} {}", ui_dim("      "), row);
-    }
-    if tool_rows.is_empty() {
-        println!("{} {}", ui_dim("tools "), ui_dim("(no tool calls)"));
-    } else {
-        let mut first = true;
-        for row in tool_rows {
-            if first {
-                println!("{} {}", ui_dim("tools "), truncate_for_table(row, 104));
-                first = false;
-            } else {
-                println!("{} {}", ui_dim("      "), truncate_for_table(row, 104));
+    let deadline = tokio::time::sleep(Duration::from_secs(settings.max_wait_secs));
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            _ = &mut deadline => {
+                eprintln!("🌡️  Still hot; running the request anyway.");
+                return;
+            }
+            _ = interrupt.notified() => return,
+            _ = tokio::time::sleep(Duration::from_secs(2)) => {
+                if guard.throttle().is_none() {
+                    eprintln!("🌡️  Cooled down; continuing.");
+                    return;
+                }
             }
         }
     }
-    if recovery_rows.is_empty() {
-        println!("{} {}", ui_dim("retry "), ui_dim("(none)"));
-    } else {
-        let mut first = true;
-        for row in recovery_rows {
-            if first {
-                println!("{} {}", ui_dim("retry "), truncate_for_table(row, 104));
-                first = false;
-            } else {
-                println!("{} {}", ui_dim("      "), truncate_for_table(row, 104));
+}
+
+fn sweep_artifacts(
+    settings: &config::RetentionConfig,
+) -> Vec<(&'static str, io::Result<retention::SweepReport>)> {
+    let pins = retention::Pins::load(
+        &data_dir::path(retention::PINS_PATH),
+        &data_dir::path(ACTIONS_DIR),
+    );
+    retention::kinds(settings, &data_dir::path(ARTIFACTS_DIR))
+        .into_iter()
+        .map(|(kind, policy)| (kind.name, retention::sweep(&kind, &policy, &pins)))
+        .collect()
+}
+
+fn format_megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+fn run_artifacts_command(settings: &config::RetentionConfig, args: &str) {
+    let pins_path = data_dir::path(retention::PINS_PATH);
+    let args = args.trim();
+    match args.split_once(' ').unwrap_or((args, "")) {
+        ("" | "list", "") => {
+            println!("\n{}", ui_title("Artifacts"));
+            let pins = retention::Pins::load(&pins_path, &data_dir::path(ACTIONS_DIR));
+            for (kind, policy) in retention::kinds(settings, &data_dir::path(ARTIFACTS_DIR)) {
+                match kind.files() {
+                    Ok(files) => {
+                        let bytes: u64 = files.iter().map(|file| file.bytes).sum();
+                        let pinned = files
+                            .iter()
+                            .filter(|file| pins.contains(&file.path))
+                            .count();
+                        println!(
+                            "  {:<12} {:>5} files {:>10}  {} pinned  {}",
+                            kind.name,
+                            files.len(),
+                            format_megabytes(bytes),
+                            pinned,
+                            ui_dim(&retention::describe_policy(&policy))
+                        );
+                    }
+                    Err(e) => println!("  {:<12} {}", kind.name, e),
+                }
             }
+            println!();
         }
-    }
-    let mut first = true;
-    for row in result_rows {
-        if first {
-            println!("{} {}", ui_dim("result"), truncate_for_table(row, 104));
-            first = false;
-        } else {
-            println!("{} {}", ui_dim("      "), truncate_for_table(row, 104));
+        ("clean", "") => {
+            for (kind, report) in sweep_artifacts(settings) {
+                match report {
+                    Ok(report) => println!(
+                        "🧹 {}: removed {} ({}, {} wiped), kept {} ({})",
+                        kind,
+                        report.removed,
+                        format_megabytes(report.freed_bytes),
+                        report.wiped,
+                        report.kept,
+                        format_megabytes(report.kept_bytes)
+                    ),
+                    Err(e) => println!("❌ {kind}: {e}"),
+                }
+            }
         }
+        (action @ ("pin" | "unpin"), path) if !path.trim().is_empty() => {
+            let path = std::path::Path::new(path.trim());
+            match retention::set_pinned(&pins_path, path, action == "pin") {
+                Ok(true) => println!("✅ {}ned {}", action, path.display()),
+                Ok(false) => println!("Nothing to change for {}", path.display()),
+                Err(e) => println!("❌ Failed to update pins: {e}"),
+            }
+        }
+        _ => println!("Usage: artifacts [list] | clean | pin <path> | unpin <path>"),
     }
-    println!("{}", ui_divider());
 }
 
-fn print_tasks_panel(task_list: &[hypr_claw_tasks::TaskInfo]) {
-    println!("\n{}", ui_title("Background Task Monitor"));
-    println!(
-        "  {:<14} {:<8} {:>6}  {:<18} {}",
-        "ID", "STATE", "PROG%", "PROGRESS", "DESCRIPTION"
-    );
-    println!("  {}", ui_dim(&"─".repeat(88)));
-
-    if task_list.is_empty() {
-        println!(
-            "  {:<14} {:<8} {:>6}  {:<18} {}",
-            "-",
-            "-",
-            "-",
-            progress_bar(0.0, 16),
-            ui_dim("no tasks")
-        );
-        return;
-    }
-
-    let mut sorted = task_list.to_vec();
-    sorted.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
-    for task in sorted {
-        let prog = task.progress.clamp(0.0, 1.0);
-        let bar = progress_bar(prog, 16);
-        println!(
-            "  {:<14} {:<8} {:>5.0}%  {:<18} {}",
-            truncate_for_table(&task.id, 14),
-            status_badge(&task.status),
-            task.progress * 100.0,
-            bar,
-            truncate_for_table(&task.description, 44),
-        );
-    }
+fn machine_host_name() -> String {
+    std::fs::read_to_string("/etc/hostname")
+        .ok()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "this machine".to_string())
 }
 
-fn reliability_summary(state: &AgentOsState) -> String {
-    let rel = &state.reliability;
-    let err = if rel.last_error.is_empty() {
-        "none".to_string()
+fn export_capabilities(local: &Value, path: &str) {
+    let default_path = data_dir::path(format!("./data/capabilities-{}.json", machine_host_name()))
+        .to_string_lossy()
+        .into_owned();
+    let path = if path.is_empty() {
+        default_path.as_str()
     } else {
-        truncate_for_table(&rel.last_error, 56)
-    };
-    format!(
-        "run={} stage={} fallbacks={} duration={}ms break={} err={}",
-        rel.run_id,
-        rel.last_stage,
-        rel.fallback_attempts,
-        rel.last_duration_ms,
-        if rel.last_break_reason.is_empty() {
-            "none"
-        } else {
-            rel.last_break_reason.as_str()
-        },
-        err
-    )
-}
-
-fn record_autonomy_outcome(
-    state: &mut AgentOsState,
-    mode: &AutonomyMode,
-    succeeded: bool,
-    duration_ms: u64,
-    fallback_attempts: u32,
-    stop_code: &str,
-) {
-    let metrics = match mode {
-        AutonomyMode::PromptFirst => &mut state.autonomy_calibration.prompt_first,
-        AutonomyMode::Guarded => &mut state.autonomy_calibration.guarded,
+        path
     };
-    metrics.runs = metrics.runs.saturating_add(1);
-    if succeeded {
-        metrics.successes = metrics.successes.saturating_add(1);
-    } else {
-        metrics.failures = metrics.failures.saturating_add(1);
+    let written = serde_json::to_string_pretty(local)
+        .map_err(io::Error::other)
+        .and_then(|body| std::fs::write(path, body));
+    match written {
+        Ok(()) => println!(
+            "✅ Capabilities exported to {path}; run `capabilities compare <file>` on the other machine"
+        ),
+        Err(e) => println!("❌ Failed to export capabilities: {e}"),
     }
-    metrics.total_duration_ms = metrics.total_duration_ms.saturating_add(duration_ms);
-    metrics.total_fallback_attempts = metrics
-        .total_fallback_attempts
-        .saturating_add(fallback_attempts as u64);
-    let entry = metrics.stop_codes.entry(stop_code.to_string()).or_insert(0);
-    *entry = entry.saturating_add(1);
 }
 
-fn extract_retry_after_seconds(error_msg: &str) -> Option<u64> {
-    let mut num = String::new();
-    for ch in error_msg.chars() {
-        if ch.is_ascii_digit() || ch == '.' {
-            num.push(ch);
-            continue;
-        }
-        if matches!(ch, 's' | 'S') && !num.is_empty() {
-            if let Ok(value) = num.parse::<f64>() {
-                if value > 0.0 {
-                    return Some(value.ceil() as u64);
-                }
-            }
-        }
-        num.clear();
+fn compare_capabilities(local: &Value, path: &str) {
+    if path.is_empty() {
+        println!("Usage: capabilities compare <exported file>");
+        return;
     }
-    None
-}
-
-fn stop_code_for_error(error_msg: &str) -> &'static str {
-    let lower = error_msg.to_lowercase();
-    if lower.contains("interrupted by user") {
-        "STOP_USER_INTERRUPT"
-    } else if lower.contains("rate limit")
-        || lower.contains("too many requests")
-        || lower.contains("resource_exhausted")
-        || lower.contains("429")
-    {
-        "STOP_PROVIDER_RATE_LIMIT"
-    } else if lower.contains("watchdog timeout") {
-        "STOP_WATCHDOG_TIMEOUT"
-    } else if lower.contains("repetitive tool loop") {
-        "STOP_TOOL_LOOP"
-    } else if lower.contains("consecutive tool failures") {
-        "STOP_TOOL_FAILURE_STREAK"
-    } else if lower.contains("tool invocation required but not performed")
-        || lower.contains("no tool call completed successfully")
+    let other = match std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|body| serde_json::from_str::<Value>(&body).map_err(|e| e.to_string()))
     {
-        "STOP_TOOL_ENFORCEMENT"
-    } else if lower.contains("invalid_argument") || lower.contains("400 bad request") {
-        "STOP_PROVIDER_ARGUMENT"
-    } else if lower.contains("max iterations")
-        || lower.contains("iteration limit")
-        || lower.contains("reached after")
+        Ok(other) => other,
+        Err(e) => {
+            println!("❌ Failed to read {path}: {e}");
+            return;
+        }
+    };
+    let there = other
+        .get("host")
+        .and_then(Value::as_str)
+        .unwrap_or(path)
+        .to_string();
+    let comparison = capability_compare::compare(local, &other);
+    println!("\n{}", ui_title(&format!("Capabilities vs {there}")));
+    for line in capability_compare::render(&comparison, &there, 12) {
+        println!("  {line}");
+    }
+    if comparison
+        .sections
+        .iter()
+        .any(|section| section.name.ends_with("backends") && !section.missing.is_empty())
     {
-        "STOP_MAX_ITERATIONS"
-    } else {
-        "STOP_RUNTIME_ERROR"
+        println!("  Run `capabilities install <backend>` to add a missing backend.");
     }
+    println!();
 }
 
-fn resolve_stop_code(error_msg: &str, fallback_attempts: u32) -> &'static str {
-    let base = stop_code_for_error(error_msg);
-    if fallback_attempts >= MAX_RECOVERY_ATTEMPTS && base != "STOP_USER_INTERRUPT" {
-        "STOP_RECOVERY_BUDGET_EXHAUSTED"
-    } else {
-        base
+fn digest_task_state(task: &hypr_claw_tasks::TaskInfo) -> TaskStateDigest {
+    TaskStateDigest {
+        status: format!("{:?}", task.status).to_lowercase(),
+        progress_percent: (task.progress.clamp(0.0, 1.0) * 100.0).round() as u16,
+        error: task.error.clone().unwrap_or_default(),
+        result: task.result.clone().unwrap_or_default(),
     }
 }
 
-fn remediation_hint_for_stop_code(stop_code: &str, input: &str) -> String {
-    let lower = input.to_lowercase();
-    match stop_code {
-        "STOP_USER_INTERRUPT" => "Request was interrupted. Re-run the task or queue it with 'queue add <prompt>'.".to_string(),
-        "STOP_WATCHDOG_TIMEOUT" => "Task exceeded time budget. Break it into smaller steps or run one concrete action per prompt.".to_string(),
-        "STOP_TOOL_LOOP" => "Model got stuck on repeated tool calls. Try a more specific prompt with exact app/path/target window.".to_string(),
-        "STOP_TOOL_FAILURE_STREAK" => "Multiple tools failed in a row. Verify required apps/backends are installed and available in PATH.".to_string(),
-        "STOP_TOOL_ENFORCEMENT" => "No valid actionable tool path was produced. Use an imperative prompt like 'open X', 'create Y', or 'run Z'.".to_string(),
-        "STOP_PROVIDER_ARGUMENT" => "Provider rejected the request format. Switch model with '/models' or retry after reducing prompt complexity.".to_string(),
-        "STOP_PROVIDER_RATE_LIMIT" => "Provider quota/rate limit hit. Wait for retry window, switch to a lower-traffic model, or use a different provider key.".to_string(),
-        "STOP_MAX_ITERATIONS" => {
-            if lower.contains("gmail") || lower.contains("telegram") || lower.contains("mail") {
-                "For inbox/chat tasks, request one concrete step first (open + capture), then ask for summary in next prompt.".to_string()
-            } else if lower.contains("wallpaper") {
-                "Specify one backend explicitly (e.g. 'use caelestia wallpaper -f <path>').".to_string()
-            } else if lower.contains("vscode") || lower.contains("code") {
-                "Set an explicit launch command for your editor in system profile/capabilities, then retry.".to_string()
-            } else {
-                "Task planning hit iteration budget. Narrow the scope and retry with fewer combined actions.".to_string()
+fn sync_background_task_events(
+    task_list: &[hypr_claw_tasks::TaskInfo],
+    index: &mut HashMap<String, TaskStateDigest>,
+    feed: &Arc<Mutex<Vec<String>>>,
+) {
+    let mut seen = HashSet::new();
+
+    for task in task_list {
+        seen.insert(task.id.clone());
+        let next = digest_task_state(task);
+        match index.get(&task.id) {
+            None => {
+                push_task_event(
+                    feed,
+                    format!(
+                        "bg {} created status={} {}%",
+                        task.id, next.status, next.progress_percent
+                    ),
+                );
+            }
+            Some(prev) if prev != &next => {
+                let mut detail = format!(
+                    "bg {} {}->{} {}%->{}%",
+                    task.id, prev.status, next.status, prev.progress_percent, next.progress_percent
+                );
+                if !next.error.is_empty() {
+                    detail.push_str(&format!(" err={}", truncate_for_table(&next.error, 40)));
+                } else if !next.result.is_empty() {
+                    detail.push_str(&format!(" out={}", truncate_for_table(&next.result, 40)));
+                }
+                push_task_event(feed, detail);
             }
+            _ => {}
         }
-        "STOP_RECOVERY_BUDGET_EXHAUSTED" => "Automatic recovery attempts were exhausted. Retry with a narrower prompt or switch model.".to_string(),
-        _ => "Unexpected runtime failure. Run 'status' for telemetry, then retry with a narrower prompt.".to_string(),
+        index.insert(task.id.clone(), next);
+    }
+
+    let removed = index
+        .keys()
+        .filter(|id| !seen.contains(*id))
+        .cloned()
+        .collect::<Vec<_>>();
+    for id in removed {
+        index.remove(&id);
+        push_task_event(feed, format!("bg {} removed from active cache", id));
     }
 }
 
-fn print_status_panel(
-    session_key: &str,
-    agent_name: &str,
-    display_name: &str,
-    model: &str,
-    active_thread_id: &str,
-    agent_state: &AgentOsState,
-    context: &hypr_claw_memory::types::ContextData,
+#[cfg(test)]
+fn build_task_log_lines(
     task_list: &[hypr_claw_tasks::TaskInfo],
-) {
-    let running = task_list
-        .iter()
-        .filter(|t| t.status == hypr_claw_tasks::TaskStatus::Running)
-        .count();
-    let completed = task_list
-        .iter()
-        .filter(|t| t.status == hypr_claw_tasks::TaskStatus::Completed)
-        .count();
-    let failed = task_list
-        .iter()
-        .filter(|t| t.status == hypr_claw_tasks::TaskStatus::Failed)
-        .count();
-    let supervisor_queued = agent_state
-        .supervisor
-        .tasks
-        .iter()
-        .filter(|t| t.status == SupervisedTaskStatus::Queued)
-        .count();
-    let supervisor_running = agent_state
-        .supervisor
-        .tasks
-        .iter()
-        .filter(|t| t.status == SupervisedTaskStatus::Running)
-        .count();
+    agent_state: &AgentOsState,
+    task_event_feed: &Arc<Mutex<Vec<String>>>,
+) -> Vec<String> {
+    let mut rows: Vec<(i64, String)> = Vec::new();
 
-    println!("\n{}", ui_title("Runtime Status"));
-    println!("  Session      : {}", ui_dim(session_key));
-    println!(
-        "  Agent/User   : {} / {}",
-        ui_info(agent_name),
-        ui_info(display_name)
-    );
-    println!(
-        "  Mode         : {}",
-        ui_info("power")
-    );
-    println!(
-        "  Scan Depth   : {}",
-        if agent_state.onboarding.deep_scan_completed {
-            ui_success("deep")
-        } else {
-            ui_dim("standard")
-        }
-    );
-    println!("  Model        : {}", ui_info(model));
-    println!("  Thread       : {}", ui_dim(active_thread_id));
-    println!(
-        "  Threads      : {} active / {} total",
-        agent_state
-            .task_threads
-            .iter()
-            .filter(|t| !t.archived)
-            .count(),
-        agent_state.task_threads.len()
-    );
-    println!(
-        "  Tasks        : {} running / {} completed / {} failed",
-        ui_info(&running.to_string()),
-        ui_success(&completed.to_string()),
-        if failed > 0 {
-            ui_danger(&failed.to_string())
-        } else {
-            ui_success(&failed.to_string())
+    if let Ok(events) = task_event_feed.lock() {
+        for line in events.iter() {
+            rows.push((chrono::Utc::now().timestamp(), format!("evt {}", line)));
         }
-    );
-    println!(
-        "  Supervisor   : {} queued / {} running",
-        supervisor_queued,
-        supervisor_running
-    );
-    println!(
-        "  Memory       : history={} facts={} approvals={} pending={}",
-        context.recent_history.len(),
-        context.facts.len(),
-        context.approval_history.len(),
-        context.pending_approvals.len()
-    );
-    println!(
-        "  Tokens       : input={} output={} session={}",
-        context.token_usage.total_input,
-        context.token_usage.total_output,
-        context.token_usage.by_session
-    );
-    println!("  Reliability  : {}", reliability_summary(agent_state));
-    if let Some(plan) = &context.current_plan {
-        println!(
-            "  Plan         : {} [{} step {}/{}]",
-            truncate_for_table(&plan.goal, 36),
-            plan.status,
-            plan.current_step.saturating_add(1),
-            plan.steps.len()
+    }
+
+    for task in task_list {
+        let mut line = format!(
+            "bg {} {} {:>3}% {}",
+            task.id,
+            format!("{:?}", task.status).to_lowercase(),
+            (task.progress.clamp(0.0, 1.0) * 100.0).round() as u16,
+            truncate_for_table(&task.description, 44),
         );
-    } else {
-        println!("  Plan         : none");
+        if let Some(err) = &task.error {
+            line.push_str(&format!(" | err: {}", truncate_for_table(err, 36)));
+        } else if let Some(result) = &task.result {
+            line.push_str(&format!(" | out: {}", truncate_for_table(result, 36)));
+        }
+        rows.push((task.updated_at, line));
     }
-}
 
-fn model_priority(model_id: &str) -> usize {
-    match model_id {
-        "z-ai/glm4.7" => 0,
-        "z-ai/glm5" => 1,
-        "moonshotai/kimi-k2.5" => 2,
-        "moonshotai/kimi-k2-instruct-0905" => 3,
-        "qwen/qwen3-coder-480b-a35b-instruct" => 4,
-        "meta/llama-4-maverick-17b-128e-instruct" => 5,
-        _ => 100,
+    for task in &agent_state.supervisor.tasks {
+        let mut line = format!(
+            "sup {} {} {} {}",
+            task.id,
+            format!("{:?}", task.status).to_lowercase(),
+            task.class.as_str(),
+            truncate_for_table(&task.prompt, 36),
+        );
+        if !task.resources.is_empty() {
+            line.push_str(&format!(
+                " | res: {}",
+                truncate_for_table(&task.resources.join(","), 20)
+            ));
+        }
+        if let Some(bg_id) = &task.background_task_id {
+            line.push_str(&format!(" | bg: {}", truncate_for_table(bg_id, 18)));
+        }
+        if let Some(err) = &task.error {
+            line.push_str(&format!(" | err: {}", truncate_for_table(err, 34)));
+        }
+        rows.push((task.updated_at, line));
     }
-}
 
-fn filter_agentic_models(models: &[String]) -> Vec<String> {
-    let blocked_tokens = [
-        "embed",
-        "embedding",
-        "guard",
-        "safety",
-        "reward",
-        "retriever",
-        "parse",
-        "clip",
-        "deplot",
-        "kosmos",
-        "paligemma",
-        "vila",
-        "streampetr",
-    ];
+    rows.sort_by(|a, b| a.0.cmp(&b.0));
+    rows.into_iter().map(|(_, line)| line).collect()
+}
 
-    let mut filtered: Vec<String> = models
-        .iter()
-        .filter(|id| {
-            let lower = id.to_lowercase();
-            !blocked_tokens.iter().any(|token| lower.contains(token))
-        })
-        .cloned()
-        .collect();
+const UI_RESET: &str = "\x1b[0m";
+const UI_BOLD: &str = "\x1b[1m";
+const UI_DIM: &str = "\x1b[2m";
+const UI_ACCENT: &str = "\x1b[38;5;39m";
+const UI_INFO: &str = "\x1b[38;5;81m";
+const UI_SUCCESS: &str = "\x1b[38;5;42m";
+const UI_WARN: &str = "\x1b[38;5;214m";
+const UI_DANGER: &str = "\x1b[38;5;203m";
 
-    filtered.sort_by(|a, b| {
-        model_priority(a)
-            .cmp(&model_priority(b))
-            .then_with(|| a.cmp(b))
-    });
-    filtered
+fn use_color() -> bool {
+    std::env::var_os("NO_COLOR").is_none()
 }
 
-fn print_model_recommendations(provider: &LLMProvider, current_model: &str) {
-    match provider {
-        LLMProvider::Nvidia => {
-            println!("\nRecommended NVIDIA models for agentic tasks:");
-            let recommendations = [
-                "z-ai/glm4.7",
-                "z-ai/glm5",
-                "moonshotai/kimi-k2.5",
-                "qwen/qwen3-coder-480b-a35b-instruct",
-                "meta/llama-4-maverick-17b-128e-instruct",
-            ];
-            for model in recommendations {
-                let marker = if model == current_model { "*" } else { " " };
-                println!("  {} {}", marker, model);
-            }
-            println!("Use '/models set <model_id>' to switch.");
-        }
-        _ => {
-            println!("Model recommendations are currently tuned for NVIDIA provider.");
-        }
+fn paint(text: &str, style: &str) -> String {
+    if use_color() {
+        format!("{style}{text}{UI_RESET}")
+    } else {
+        text.to_string()
     }
 }
 
-async fn apply_model_switch<S, L, D, R, Sum>(
-    model_id: &str,
-    agent_loop: &hypr_claw_runtime::AgentLoop<S, L, D, R, Sum>,
-    config: &mut Config,
-    context_manager: &hypr_claw_memory::ContextManager,
-    context: &mut hypr_claw_memory::types::ContextData,
-) -> Result<(), String>
-where
-    S: hypr_claw_runtime::SessionStore,
-    L: hypr_claw_runtime::LockManager,
-    D: hypr_claw_runtime::ToolDispatcher,
-    R: hypr_claw_runtime::ToolRegistry,
-    Sum: hypr_claw_runtime::Summarizer,
-{
-    agent_loop.set_model(model_id).map_err(|e| e.to_string())?;
-    config.model = model_id.to_string();
-    config.save().map_err(|e| e.to_string())?;
+fn ui_title(text: &str) -> String {
+    paint(text, UI_BOLD)
+}
 
-    if !context.system_state.is_object() {
-        context.system_state = json!({});
-    }
-    if let Some(obj) = context.system_state.as_object_mut() {
-        obj.insert("active_model".to_string(), json!(model_id));
-    }
-    context_manager
-        .save(context)
-        .await
-        .map_err(|e| e.to_string())?;
+fn ui_accent(text: &str) -> String {
+    paint(text, UI_ACCENT)
+}
 
-    println!("✅ Model switched to {}", model_id);
-    if model_id == "z-ai/glm4.7" {
-        println!("ℹ️  GLM-4.7 profile enabled (agentic terminal tuning).");
-    }
-    Ok(())
+fn ui_dim(text: &str) -> String {
+    paint(text, UI_DIM)
 }
 
-async fn run_with_interrupt_and_timeout<S, L, D, R, Sum>(
-    agent_loop: &hypr_claw_runtime::AgentLoop<S, L, D, R, Sum>,
-    session_key: &str,
-    agent_name: &str,
-    system_prompt: &str,
-    prompt: &str,
-    interrupt: &Arc<tokio::sync::Notify>,
-    timeout: Duration,
-) -> Result<String, hypr_claw_runtime::RuntimeError>
-where
-    S: hypr_claw_runtime::SessionStore,
-    L: hypr_claw_runtime::LockManager,
-    D: hypr_claw_runtime::ToolDispatcher,
-    R: hypr_claw_runtime::ToolRegistry,
-    Sum: hypr_claw_runtime::Summarizer,
-{
-    tokio::select! {
-        res = agent_loop.run(session_key, agent_name, system_prompt, prompt) => res,
-        _ = interrupt.notified() => Err(hypr_claw_runtime::RuntimeError::LLMError(
-            "Interrupted by user".to_string()
-        )),
-        _ = tokio::time::sleep(timeout) => Err(hypr_claw_runtime::RuntimeError::LLMError(
-            format!("Execution watchdog timeout after {}s", timeout.as_secs())
-        )),
-    }
+fn ui_info(text: &str) -> String {
+    paint(text, UI_INFO)
 }
 
-fn build_llm_client_for_provider(
-    provider: &LLMProvider,
-    model: &str,
-) -> Result<hypr_claw_runtime::LLMClientType, String> {
-    match provider {
-        LLMProvider::Nvidia => {
-            let api_key = bootstrap::get_nvidia_api_key().map_err(|e| e.to_string())?;
-            Ok(hypr_claw_runtime::LLMClientType::Standard(
-                hypr_claw_runtime::LLMClient::with_api_key_and_model(
-                    provider.base_url(),
-                    1,
-                    api_key,
-                    model.to_string(),
-                ),
-            ))
-        }
-        LLMProvider::Google => {
-            let api_key = bootstrap::get_google_api_key().map_err(|e| e.to_string())?;
-            Ok(hypr_claw_runtime::LLMClientType::Standard(
-                hypr_claw_runtime::LLMClient::with_api_key_and_model(
-                    provider.base_url(),
-                    1,
-                    api_key,
-                    model.to_string(),
-                ),
-            ))
-        }
-        LLMProvider::Local { .. } => Ok(hypr_claw_runtime::LLMClientType::Standard(
-            hypr_claw_runtime::LLMClient::new(provider.base_url(), 1),
-        )),
-        LLMProvider::Codex | LLMProvider::Antigravity | LLMProvider::GeminiCli => {
-            Err("Provider does not support agent-mode tool calling".to_string())
-        }
-    }
+fn ui_success(text: &str) -> String {
+    paint(text, UI_SUCCESS)
 }
 
-fn to_context_tasks(
-    task_list: Vec<hypr_claw_tasks::TaskInfo>,
-) -> Vec<hypr_claw_memory::types::TaskState> {
-    task_list
-        .into_iter()
-        .map(|task| hypr_claw_memory::types::TaskState {
-            id: task.id,
-            description: task.description,
-            status: format!("{:?}", task.status).to_lowercase(),
-            progress: task.progress,
-            created_at: task.created_at,
-            updated_at: task.updated_at,
-        })
-        .collect()
-}
-
-fn plan_for_input(goal: &str) -> hypr_claw_memory::types::PlanState {
-    hypr_claw_memory::types::PlanState {
-        goal: goal.to_string(),
-        steps: vec![
-            hypr_claw_memory::types::PlanStepState {
-                id: 0,
-                description: "Analyze request".to_string(),
-                status: "completed".to_string(),
-                result: Some("Intent parsed".to_string()),
-            },
-            hypr_claw_memory::types::PlanStepState {
-                id: 1,
-                description: "Execute required tools".to_string(),
-                status: "in_progress".to_string(),
-                result: None,
-            },
-            hypr_claw_memory::types::PlanStepState {
-                id: 2,
-                description: "Report completion".to_string(),
-                status: "pending".to_string(),
-                result: None,
-            },
-        ],
-        current_step: 1,
-        status: "in_progress".to_string(),
-        updated_at: chrono::Utc::now().timestamp(),
-    }
+fn ui_warn(text: &str) -> String {
+    paint(text, UI_WARN)
 }
 
-fn mark_plan_completed(context: &mut hypr_claw_memory::types::ContextData, response: &str) {
-    if let Some(plan) = &mut context.current_plan {
-        if let Some(step) = plan.steps.get_mut(1) {
-            step.status = "completed".to_string();
-            step.result = Some("Tool execution completed".to_string());
-        }
-        if let Some(step) = plan.steps.get_mut(2) {
-            step.status = "completed".to_string();
-            step.result = Some(response.to_string());
-        }
-        plan.current_step = plan.steps.len();
-        plan.status = "completed".to_string();
-        plan.updated_at = chrono::Utc::now().timestamp();
-    }
+fn ui_danger(text: &str) -> String {
+    paint(text, UI_DANGER)
 }
 
-fn mark_plan_failed(context: &mut hypr_claw_memory::types::ContextData, error: &str) {
-    if let Some(plan) = &mut context.current_plan {
-        let step_index = plan.current_step.min(plan.steps.len().saturating_sub(1));
-        if let Some(step) = plan.steps.get_mut(step_index) {
-            step.status = "failed".to_string();
-            step.result = Some(error.to_string());
-        }
-        plan.status = "failed".to_string();
-        plan.updated_at = chrono::Utc::now().timestamp();
-    }
+fn ui_divider() -> String {
+    ui_accent(&"─".repeat(78))
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-enum SupervisedTaskClass {
-    Question,
-    Action,
-    Investigation,
+fn ui_section(title: &str) -> String {
+    let mut line = format!("─ {} ", title);
+    let fill = 78usize.saturating_sub(line.chars().count());
+    line.push_str(&"─".repeat(fill));
+    ui_accent(&line)
 }
 
-impl SupervisedTaskClass {
-    fn as_str(&self) -> &'static str {
-        match self {
-            Self::Question => "question",
-            Self::Action => "action",
-            Self::Investigation => "investigation",
-        }
+fn status_badge(status: &hypr_claw_tasks::TaskStatus) -> String {
+    match status {
+        hypr_claw_tasks::TaskStatus::Running => ui_info("RUN"),
+        hypr_claw_tasks::TaskStatus::Pending => ui_warn("PEND"),
+        hypr_claw_tasks::TaskStatus::Completed => ui_success("DONE"),
+        hypr_claw_tasks::TaskStatus::Failed => ui_danger("FAIL"),
+        hypr_claw_tasks::TaskStatus::Cancelled => ui_warn("STOP"),
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-enum SupervisedTaskStatus {
-    Queued,
-    Running,
-    Completed,
-    Failed,
-    Cancelled,
+fn progress_bar(progress: f32, width: usize) -> String {
+    let p = progress.clamp(0.0, 1.0);
+    let filled = (p * width as f32).round() as usize;
+    let filled = filled.min(width);
+    let mut bar = String::new();
+    bar.push('[');
+    bar.push_str(&"█".repeat(filled));
+    bar.push_str(&"░".repeat(width.saturating_sub(filled)));
+    bar.push(']');
+    bar
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct SupervisedTask {
-    id: String,
-    prompt: String,
-    class: SupervisedTaskClass,
-    #[serde(default)]
-    resources: Vec<String>,
-    #[serde(default)]
-    background_task_id: Option<String>,
-    status: SupervisedTaskStatus,
-    created_at: i64,
-    updated_at: i64,
-    error: Option<String>,
+fn short_model_name(model: &str) -> String {
+    model.split('/').next_back().unwrap_or(model).to_string()
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct SupervisorState {
-    #[serde(default = "default_supervisor_auto_run")]
-    auto_run: bool,
-    #[serde(default = "default_supervisor_next_id")]
-    next_id: u64,
-    #[serde(default)]
-    tasks: Vec<SupervisedTask>,
+fn format_timestamp(ts: i64) -> String {
+    chrono::DateTime::<chrono::Utc>::from_timestamp(ts, 0)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| "n/a".to_string())
 }
 
-fn default_supervisor_auto_run() -> bool {
-    false
+fn truncate_for_table(value: &str, max: usize) -> String {
+    if value.chars().count() <= max {
+        return value.to_string();
+    }
+    let mut out: String = value.chars().take(max.saturating_sub(3)).collect();
+    out.push_str("...");
+    out
 }
 
-fn default_supervisor_next_id() -> u64 {
-    1
-}
+fn strip_ansi_and_controls(raw: &str) -> String {
+    let bytes = raw.as_bytes();
+    let mut out = String::with_capacity(raw.len());
+    let mut i = 0usize;
 
-impl Default for SupervisorState {
-    fn default() -> Self {
-        Self {
-            auto_run: default_supervisor_auto_run(),
-            next_id: default_supervisor_next_id(),
-            tasks: Vec::new(),
+    while i < bytes.len() {
+        if bytes[i] == 0x1B {
+            i += 1;
+            if i >= bytes.len() {
+                break;
+            }
+            match bytes[i] {
+                b'[' => {
+                    i += 1;
+                    while i < bytes.len() {
+                        let b = bytes[i];
+                        i += 1;
+                        if (0x40..=0x7E).contains(&b) {
+                            break;
+                        }
+                    }
+                    continue;
+                }
+                b']' => {
+                    i += 1;
+                    while i < bytes.len() {
+                        if bytes[i] == 0x07 {
+                            i += 1;
+                            break;
+                        }
+                        if bytes[i] == 0x1B && i + 1 < bytes.len() && bytes[i + 1] == b'\\' {
+                            i += 2;
+                            break;
+                        }
+                        i += 1;
+                    }
+                    continue;
+                }
+                _ => continue,
+            }
+        }
+
+        let rest = &raw[i..];
+        let Some(ch) = rest.chars().next() else {
+            break;
+        };
+        i += ch.len_utf8();
+        if ch.is_control() && ch != '\n' && ch != '\t' {
+            continue;
         }
+        out.push(ch);
     }
-}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct AgentOsState {
-    #[serde(default)]
-    onboarding: OnboardingState,
-    #[serde(default)]
-    soul_auto: bool,
-    #[serde(default)]
-    task_threads: Vec<TaskThread>,
-    #[serde(default)]
-    active_thread_id: String,
-    #[serde(default)]
-    supervisor: SupervisorState,
-    #[serde(default)]
-    reliability: ReliabilityState,
-    #[serde(default = "default_autonomy_mode")]
-    autonomy_mode: AutonomyMode,
-    #[serde(default)]
-    autonomy_calibration: AutonomyCalibrationState,
+    out
 }
 
-impl Default for AgentOsState {
-    fn default() -> Self {
-        Self {
-            onboarding: OnboardingState::default(),
-            soul_auto: false,
-            task_threads: vec![TaskThread::new("task-1".to_string(), "Main".to_string())],
-            active_thread_id: "task-1".to_string(),
-            supervisor: SupervisorState::default(),
-            reliability: ReliabilityState::default(),
-            autonomy_mode: default_autonomy_mode(),
-            autonomy_calibration: AutonomyCalibrationState::default(),
-        }
-    }
+fn sanitize_single_line(raw: &str) -> String {
+    strip_ansi_and_controls(raw)
+        .replace('\n', " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct ReliabilityState {
-    #[serde(default)]
-    run_id: u64,
-    #[serde(default)]
-    last_stage: String,
-    #[serde(default)]
-    fallback_attempts: u32,
-    #[serde(default)]
-    last_duration_ms: u64,
-    #[serde(default)]
-    last_error: String,
-    #[serde(default)]
-    last_break_reason: String,
-    #[serde(default)]
-    updated_at: Option<i64>,
-}
-
-impl Default for ReliabilityState {
-    fn default() -> Self {
-        Self {
-            run_id: 0,
-            last_stage: "idle".to_string(),
-            fallback_attempts: 0,
-            last_duration_ms: 0,
-            last_error: String::new(),
-            last_break_reason: String::new(),
-            updated_at: None,
+fn sanitize_user_input_line(raw: &str) -> String {
+    let mut value = sanitize_single_line(raw.trim());
+    for _ in 0..4 {
+        if let Some(rest) = value.strip_prefix("tui> ") {
+            value = sanitize_single_line(rest.trim());
+            continue;
         }
-    }
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-#[serde(rename_all = "snake_case")]
-enum AutonomyMode {
-    PromptFirst,
-    Guarded,
-}
-
-impl AutonomyMode {
-    fn as_str(&self) -> &'static str {
-        match self {
-            Self::PromptFirst => "prompt_first",
-            Self::Guarded => "guarded",
+        if value.starts_with("hypr[") {
+            if let Some((_, rest)) = value.rsplit_once("]>") {
+                let cleaned = rest.trim();
+                if !cleaned.is_empty() {
+                    value = sanitize_single_line(cleaned);
+                    continue;
+                }
+            }
         }
+        break;
     }
+    value
 }
 
-fn default_autonomy_mode() -> AutonomyMode {
-    AutonomyMode::PromptFirst
-}
-
-fn strict_workflow_enabled() -> bool {
-    std::env::var("HYPR_CLAW_STRICT_WORKFLOW")
-        .ok()
-        .map(|raw| {
-            !matches!(
-                raw.trim().to_ascii_lowercase().as_str(),
-                "0" | "false" | "off"
-            )
-        })
-        .unwrap_or(true)
+fn print_console_bootstrap(
+    provider_name: &str,
+    model: &str,
+    agent_name: &str,
+    display_name: &str,
+    user_id: &str,
+    session_key: &str,
+    active_thread_id: &str,
+) {
+    println!("{}", ui_section("Hypr-Claw"));
+    println!(
+        "{} {}  {} {}  {} {}",
+        ui_dim("provider"),
+        ui_info(&truncate_for_table(provider_name, 20)),
+        ui_dim("model"),
+        ui_info(&truncate_for_table(&short_model_name(model), 24)),
+        ui_dim("session"),
+        truncate_for_table(session_key, 26)
+    );
+    println!(
+        "{} {}  {} {}  {} {}",
+        ui_dim("user"),
+        truncate_for_table(&format!("{} ({})", display_name, user_id), 24),
+        ui_dim("agent"),
+        truncate_for_table(agent_name, 16),
+        ui_dim("thread"),
+        truncate_for_table(active_thread_id, 14)
+    );
+    println!(
+        "{} {}",
+        ui_dim("commands"),
+        truncate_for_table(
+            "help  status  scan  capabilities  /models  tasks  queue add/run/status/clear  view",
+            64
+        )
+    );
+    println!("{}", ui_divider());
+    println!();
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-struct AutonomyModeMetrics {
-    #[serde(default)]
-    runs: u64,
-    #[serde(default)]
-    successes: u64,
-    #[serde(default)]
-    failures: u64,
-    #[serde(default)]
-    total_duration_ms: u64,
-    #[serde(default)]
-    total_fallback_attempts: u64,
-    #[serde(default)]
-    stop_codes: BTreeMap<String, u64>,
+fn print_run_panel(
+    prompt: &str,
+    class_label: &str,
+    max_iterations: usize,
+    timeout_secs: u64,
+    model: &str,
+    active_tools: usize,
+    focused_tools: Option<usize>,
+) {
+    eprintln!();
+    eprintln!("{}", ui_section("Run"));
+    eprintln!(
+        "{} {}",
+        ui_dim("goal"),
+        truncate_for_table(&sanitize_single_line(prompt), 112)
+    );
+    eprintln!(
+        "{} power  {}  iter={}  timeout={}s  model={}",
+        ui_dim("plan"),
+        class_label,
+        max_iterations,
+        timeout_secs,
+        short_model_name(model)
+    );
+    eprintln!("{} observe -> plan -> execute -> verify", ui_dim("flow"));
+    let focused = focused_tools
+        .map(|count| format!(" (focused {})", count))
+        .unwrap_or_default();
+    eprintln!("{} {} active{}", ui_dim("tools"), active_tools, focused);
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-struct AutonomyCalibrationState {
-    #[serde(default)]
-    prompt_first: AutonomyModeMetrics,
-    #[serde(default)]
-    guarded: AutonomyModeMetrics,
+fn print_help() {
+    println!("\n{}", ui_title("Hypr-Claw Command Reference"));
+    println!("  {}", ui_accent("Core"));
+    println!("    help                  Show this help");
+    println!("    status                Runtime state snapshot");
+    println!("    scan                  Re-run standard/deep system learning scan");
+    println!("    capabilities          Show runtime capability registry summary");
+    println!("    capabilities gaps     Missing tools/backends hit by runs [clear]");
+    println!("    capabilities install  Install a missing backend [grim|ydotool|tesseract|...]");
+    println!("    capabilities input    ydotool daemon health [setup: create user service]");
+    println!("    capabilities export   Write this machine's capabilities and tools [file]");
+    println!("    capabilities compare  Diff against another machine's export <file>");
+    println!("    setup apply <file>    Install, write and enable what a setup manifest lists");
+    println!("    clear                 Clear terminal");
+    println!("    interrupt             Send interrupt signal to active run");
+    println!("    +<note>               During a run: pass a note to it (other lines run next)");
+    println!("    exit | quit           Exit agent");
+    println!("  {}", ui_accent("Models"));
+    println!("    /models               Interactive model switch");
+    println!("    /models list          List provider models (cached; refresh re-fetches)");
+    println!("    /models set <id>      Set model directly");
+    println!("    config check          Validate settings and offer to fix model or API key");
+    println!("  {}", ui_accent("Tasks"));
+    println!("    tasks                 Show background task table");
+    println!("    queue                 Show supervisor queue");
+    println!("    queue status          Compact queue summary + actionable IDs");
+    println!("    queue add <prompt>    Add task prompt to queue");
+    println!("    queue run             Run next queued task");
+    println!("    queue clear           Cancel queued items");
+    println!("    thread list           Threads with their generated titles");
+    println!("    thread new [title]    Start a thread and make it the active one");
+    println!(
+        "    thread switch <id>    Make a thread active; during a run, start the next lines there"
+    );
+    println!(
+        "    thread env [set K=V | unset K | clear]  Variables for processes this thread starts"
+    );
+    println!(
+        "    thread popout [id]    Show/hide a terminal for the thread on a special workspace"
+    );
+    println!("    history               Recent exchanges, labelled by thread title");
+    println!("    explain               Plain-language account of the last run");
+    println!("    triage                Check why the last run failed and offer fixes");
+    println!("    cd <dir> | pwd        Working directory (/cd to force); entering a project applies its soul");
+    println!("    prompt inspect        Next turn's system prompt, token cost by section");
+    println!("    context analyze       What fills the context; prune, pin or summarize items");
+    println!("    preview <prompt>      Request a prompt would send, with a cost estimate");
+    println!("    do <n>                Run command suggestion n from the last answer");
+    println!("  {}", ui_accent("Reminders"));
+    println!(
+        "    reminder add <when> <msg>  Remind at 10m/1h30m/HH:MM [--speak] [--then <prompt>]"
+    );
+    println!("    reminder list         Show reminders");
+    println!("    reminder cancel <id>  Cancel a pending reminder");
+    println!("    questions             Questions background tasks are waiting on");
+    println!("    answer <id> <text>    Answer one (a number picks a choice); the task continues");
+    println!("    forms                 Saved form profiles and the facts their fields take");
+    println!("    forms forget <name>   Drop a saved form and its sensitive values");
+    println!("  {}", ui_accent("Contacts"));
+    println!("    contacts              List saved contacts");
+    println!("    contacts add <name> email=.. telegram=.. phone=..  Add or update a contact");
+    println!("    contacts remove <name>  Delete a contact");
+    println!("  {}", ui_accent("Knowledge"));
+    println!("    knowledge             List imported documentation");
+    println!("    knowledge import <dir|url>  Index man pages, docs or a wiki dump for the agent");
+    println!("    knowledge search <q>  Search imported docs; knowledge remove <source> drops one");
+    println!("  {}", ui_accent("System"));
+    println!("    profile               Show learned system profile");
+    println!("    scan                  Re-run system scan");
+    println!("    view                  Show current CLI view mode");
+    println!("    view transcript       Enable transcript panes");
+    println!("    view compact          Disable transcript panes");
+    println!("    citations on|off      Require answers to cite tool results");
+    println!("    preset                Show safety preset (approvals, autonomy, notifications)");
+    println!("    stats                 Local-only usage dashboard [on|off|clear]");
+    println!("    audit [tool] [24h]    Recent audit log entries, optionally by tool or window");
+    println!("    actions [run] [-f]    Tool-by-tool log of a run (default latest) [list]");
+    println!("    artifacts             Screenshot/artifact usage and limits [clean | pin | unpin <path>]");
+    println!("    preset switch <name>  Switch to cautious | balanced | power_user");
+    println!("    policy                Supervision per task class and thread [set | unset]");
+    println!();
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct OnboardingState {
-    #[serde(default)]
-    completed: bool,
-    #[serde(default)]
-    preferred_name: String,
-    #[serde(default)]
-    profile_confirmed: bool,
-    #[serde(default = "empty_json")]
-    system_profile: Value,
-    #[serde(default)]
-    last_scan_at: Option<i64>,
-    #[serde(default)]
-    deep_scan_completed: bool,
-    #[serde(default)]
-    trusted_full_auto: bool,
+fn print_suggested_commands(commands: &[String]) {
+    if commands.is_empty() {
+        return;
+    }
+    println!("{}", ui_section("Suggested commands"));
+    for (i, command) in commands.iter().enumerate() {
+        println!("  {}. {}", i + 1, strip_ansi_and_controls(command));
+    }
+    println!("  {}\n", ui_dim("Run one with: do <n>"));
 }
 
-impl Default for OnboardingState {
-    fn default() -> Self {
-        Self {
-            completed: false,
-            preferred_name: String::new(),
-            profile_confirmed: false,
-            system_profile: json!({}),
-            last_scan_at: None,
-            deep_scan_completed: false,
-            trusted_full_auto: false,
-        }
+fn print_citation_footnotes(citations: &[hypr_claw_runtime::Citation]) {
+    if citations.is_empty() {
+        return;
+    }
+    println!("{}", ui_section("Sources"));
+    for line in hypr_claw_runtime::citations::render_footnotes(citations) {
+        println!("  {}", ui_dim(&strip_ansi_and_controls(&line)));
     }
+    println!();
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct TaskThread {
-    id: String,
-    title: String,
-    archived: bool,
-    created_at: i64,
-    updated_at: i64,
+fn action_feed_len(feed: &Arc<Mutex<Vec<String>>>) -> usize {
+    feed.lock().ok().map(|f| f.len()).unwrap_or(0)
 }
 
-impl TaskThread {
-    fn new(id: String, title: String) -> Self {
-        let now = chrono::Utc::now().timestamp();
-        Self {
-            id,
-            title,
-            archived: false,
-            created_at: now,
-            updated_at: now,
-        }
+fn action_feed_since(feed: &Arc<Mutex<Vec<String>>>, start: usize, max_rows: usize) -> Vec<String> {
+    let rows = feed.lock().ok().map(|f| f.clone()).unwrap_or_default();
+    if start >= rows.len() {
+        return Vec::new();
     }
+    let mut slice = rows[start..].to_vec();
+    if max_rows > 0 && slice.len() > max_rows {
+        let keep_from = slice.len().saturating_sub(max_rows);
+        slice = slice[keep_from..].to_vec();
+    }
+    slice
 }
 
-fn empty_json() -> Value {
-    json!({})
-}
-
-fn load_agent_os_state(context: &hypr_claw_memory::types::ContextData) -> AgentOsState {
-    let maybe_state = context.system_state.get("agent_os_state").cloned();
-    let mut state = maybe_state
-        .as_ref()
-        .and_then(|v| serde_json::from_value::<AgentOsState>(v.clone()).ok())
-        .unwrap_or_default();
-
-    // Migration: if legacy state did not include soul_auto, default to disabled.
-    let soul_auto_present = maybe_state
-        .as_ref()
-        .and_then(|v| v.get("soul_auto"))
-        .is_some();
-    if !soul_auto_present {
-        state.soul_auto = false;
+fn text_preview_lines(text: &str, max_lines: usize, max_width: usize) -> Vec<String> {
+    if text.trim().is_empty() {
+        return vec!["(empty)".to_string()];
     }
-
-    state
+    let sanitized = strip_ansi_and_controls(text);
+    let mut lines = Vec::new();
+    for line in sanitized.lines().take(max_lines) {
+        lines.push(truncate_for_table(&sanitize_single_line(line), max_width));
+    }
+    if sanitized.lines().count() > max_lines {
+        lines.push(format!("... (truncated, {}+ lines)", max_lines));
+    }
+    if lines.is_empty() {
+        lines.push(truncate_for_table(&sanitize_single_line(&sanitized), max_width));
+    }
+    lines
 }
 
-fn persist_agent_os_state(
-    context: &mut hypr_claw_memory::types::ContextData,
-    state: &AgentOsState,
+fn print_transcript_panes(
+    prompt: &str,
+    model: &str,
+    class_label: &str,
+    run_elapsed_ms: u64,
+    tool_rows: &[String],
+    recovery_rows: &[String],
+    result_rows: &[String],
 ) {
-    if !context.system_state.is_object() {
-        context.system_state = json!({});
+    eprintln!("{}", ui_section("Transcript"));
+    eprintln!(
+        "{} {}  {} {}  {} {}ms",
+        ui_dim("model"),
+        ui_info(&short_model_name(model)),
+        ui_dim("class"),
+        ui_info(class_label),
+        ui_dim("elapsed"),
+        ui_info(&run_elapsed_ms.to_string())
+    );
+    let prompt_rows = text_preview_lines(prompt, 2, 104);
+    if let Some(first) = prompt_rows.first() {
+        eprintln!("{} {}", ui_dim("prompt"), first);
     }
-    if let Some(obj) = context.system_state.as_object_mut() {
-        let value = serde_json::to_value(state).unwrap_or_else(|_| json!({}));
-        obj.insert("agent_os_state".to_string(), value);
+    for row in prompt_rows.iter().skip(1) {
+        eprintln!("{} {}", ui_dim("      "), row);
     }
-}
-
-fn ensure_default_thread(state: &mut AgentOsState) {
-    if state.task_threads.is_empty() {
-        state
-            .task_threads
-            .push(TaskThread::new("task-1".to_string(), "Main".to_string()));
+    if tool_rows.is_empty() {
+        eprintln!("{} {}", ui_dim("tools "), ui_dim("(no tool calls)"));
+    } else {
+        let mut first = true;
+        for row in tool_rows {
+            if first {
+                eprintln!("{} {}", ui_dim("tools "), truncate_for_table(row, 104));
+                first = false;
+            } else {
+                eprintln!("{} {}", ui_dim("      "), truncate_for_table(row, 104));
+            }
+        }
     }
-    let active_exists = state
-        .task_threads
-        .iter()
-        .any(|thread| thread.id == state.active_thread_id && !thread.archived);
-    if !active_exists {
-        if let Some(thread) = state.task_threads.iter().find(|thread| !thread.archived) {
-            state.active_thread_id = thread.id.clone();
+    if recovery_rows.is_empty() {
+        eprintln!("{} {}", ui_dim("retry "), ui_dim("(none)"));
+    } else {
+        let mut first = true;
+        for row in recovery_rows {
+            if first {
+                eprintln!("{} {}", ui_dim("retry "), truncate_for_table(row, 104));
+                first = false;
+            } else {
+                eprintln!("{} {}", ui_dim("      "), truncate_for_table(row, 104));
+            }
+        }
+    }
+    let mut first = true;
+    for row in result_rows {
+        if first {
+            eprintln!("{} {}", ui_dim("result"), truncate_for_table(row, 104));
+            first = false;
         } else {
-            let fallback = TaskThread::new(next_thread_id(state), "Main".to_string());
-            state.active_thread_id = fallback.id.clone();
-            state.task_threads.push(fallback);
+            eprintln!("{} {}", ui_dim("      "), truncate_for_table(row, 104));
         }
     }
+    eprintln!("{}", ui_divider());
 }
 
-fn display_name(state: &AgentOsState, fallback_user_id: &str) -> String {
-    if state.onboarding.preferred_name.trim().is_empty() {
-        fallback_user_id.to_string()
-    } else {
-        state.onboarding.preferred_name.clone()
+fn print_tasks_panel(task_list: &[hypr_claw_tasks::TaskInfo]) {
+    println!("\n{}", ui_title("Background Task Monitor"));
+    println!(
+        "  {:<14} {:<8} {:>6}  {:<18} {}",
+        "ID", "STATE", "PROG%", "PROGRESS", "DESCRIPTION"
+    );
+    println!("  {}", ui_dim(&"─".repeat(88)));
+
+    if task_list.is_empty() {
+        println!(
+            "  {:<14} {:<8} {:>6}  {:<18} {}",
+            "-",
+            "-",
+            "-",
+            progress_bar(0.0, 16),
+            ui_dim("no tasks")
+        );
+        return;
     }
-}
 
-fn detect_agent_name() -> String {
-    if let Ok(from_env) = std::env::var("HYPR_CLAW_AGENT") {
-        let candidate = from_env.trim();
-        if !candidate.is_empty() {
-            let path = format!("./data/agents/{}.yaml", candidate);
-            if std::path::Path::new(&path).exists() {
-                return candidate.to_string();
-            }
-        }
+    let mut sorted = task_list.to_vec();
+    sorted.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    for task in sorted {
+        let prog = task.progress.clamp(0.0, 1.0);
+        let bar = progress_bar(prog, 16);
+        println!(
+            "  {:<14} {:<8} {:>5.0}%  {:<18} {}",
+            truncate_for_table(&task.id, 14),
+            status_badge(&task.status),
+            task.progress * 100.0,
+            bar,
+            truncate_for_table(&task.description, 44),
+        );
     }
+}
 
-    let default_path = "./data/agents/default.yaml";
-    if std::path::Path::new(default_path).exists() {
-        return "default".to_string();
+fn reliability_summary(state: &AgentOsState) -> String {
+    let rel = &state.reliability;
+    let err = if rel.last_error.is_empty() {
+        "none".to_string()
+    } else {
+        truncate_for_table(&rel.last_error, 56)
+    };
+    format!(
+        "run={} stage={} fallbacks={} duration={}ms break={} err={}",
+        rel.run_id,
+        rel.last_stage,
+        rel.fallback_attempts,
+        rel.last_duration_ms,
+        if rel.last_break_reason.is_empty() {
+            "none"
+        } else {
+            rel.last_break_reason.as_str()
+        },
+        err
+    )
+}
+
+fn record_autonomy_outcome(
+    state: &mut AgentOsState,
+    mode: &AutonomyMode,
+    succeeded: bool,
+    duration_ms: u64,
+    fallback_attempts: u32,
+    stop_code: &str,
+) {
+    let metrics = match mode {
+        AutonomyMode::PromptFirst => &mut state.autonomy_calibration.prompt_first,
+        AutonomyMode::Guarded => &mut state.autonomy_calibration.guarded,
+    };
+    metrics.runs = metrics.runs.saturating_add(1);
+    if succeeded {
+        metrics.successes = metrics.successes.saturating_add(1);
+    } else {
+        metrics.failures = metrics.failures.saturating_add(1);
     }
+    metrics.total_duration_ms = metrics.total_duration_ms.saturating_add(duration_ms);
+    metrics.total_fallback_attempts = metrics
+        .total_fallback_attempts
+        .saturating_add(fallback_attempts as u64);
+    let entry = metrics.stop_codes.entry(stop_code.to_string()).or_insert(0);
+    *entry = entry.saturating_add(1);
+}
 
-    if let Ok(entries) = std::fs::read_dir("./data/agents") {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.extension().and_then(|ext| ext.to_str()) == Some("yaml") {
-                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                    return stem.to_string();
+fn extract_retry_after_seconds(error_msg: &str) -> Option<u64> {
+    let mut num = String::new();
+    for ch in error_msg.chars() {
+        if ch.is_ascii_digit() || ch == '.' {
+            num.push(ch);
+            continue;
+        }
+        if matches!(ch, 's' | 'S') && !num.is_empty() {
+            if let Ok(value) = num.parse::<f64>() {
+                if value > 0.0 {
+                    return Some(value.ceil() as u64);
                 }
             }
         }
+        num.clear();
     }
-
-    "default".to_string()
+    None
 }
 
-fn detect_user_id() -> String {
-    std::env::var("HYPR_CLAW_USER")
-        .ok()
-        .filter(|s| !s.trim().is_empty())
-        .or_else(|| std::env::var("USER").ok().filter(|s| !s.trim().is_empty()))
-        .unwrap_or_else(|| "local_user".to_string())
+fn stop_code_for_error(error_msg: &str) -> &'static str {
+    let lower = error_msg.to_lowercase();
+    if lower.contains("interrupted by user") {
+        "STOP_USER_INTERRUPT"
+    } else if lower.contains("session conflict on") {
+        "STOP_SESSION_CONFLICT"
+    } else if lower.contains("rate limit")
+        || lower.contains("too many requests")
+        || lower.contains("resource_exhausted")
+        || lower.contains("429")
+    {
+        "STOP_PROVIDER_RATE_LIMIT"
+    } else if lower.contains("watchdog timeout") {
+        "STOP_WATCHDOG_TIMEOUT"
+    } else if lower.contains("repetitive tool loop") {
+        "STOP_TOOL_LOOP"
+    } else if lower.contains("consecutive tool failures") {
+        "STOP_TOOL_FAILURE_STREAK"
+    } else if lower.contains("tool invocation required but not performed")
+        || lower.contains("no tool call completed successfully")
+    {
+        "STOP_TOOL_ENFORCEMENT"
+    } else if lower.contains("invalid_argument") || lower.contains("400 bad request") {
+        "STOP_PROVIDER_ARGUMENT"
+    } else if lower.contains("max iterations")
+        || lower.contains("iteration limit")
+        || lower.contains("reached after")
+    {
+        "STOP_MAX_ITERATIONS"
+    } else {
+        "STOP_RUNTIME_ERROR"
+    }
 }
 
-fn prompt_line(prompt: &str) -> io::Result<String> {
-    print!("{prompt}");
-    io::stdout().flush()?;
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    Ok(input.trim().to_string())
+fn resolve_stop_code(error_msg: &str, fallback_attempts: u32) -> &'static str {
+    let base = stop_code_for_error(error_msg);
+    if fallback_attempts >= MAX_RECOVERY_ATTEMPTS && base != "STOP_USER_INTERRUPT" {
+        "STOP_RECOVERY_BUDGET_EXHAUSTED"
+    } else {
+        base
+    }
 }
 
-fn prompt_yes_no(prompt: &str, default_yes: bool) -> io::Result<bool> {
-    let input = prompt_line(prompt)?;
-    if input.is_empty() {
-        return Ok(default_yes);
-    }
-    match input.to_lowercase().as_str() {
-        "y" | "yes" => Ok(true),
-        "n" | "no" => Ok(false),
-        _ => Ok(default_yes),
+fn remediation_hint_for_stop_code(stop_code: &str, input: &str) -> String {
+    let lower = input.to_lowercase();
+    match stop_code {
+        "STOP_USER_INTERRUPT" => "Request was interrupted. Re-run the task or queue it with 'queue add <prompt>'.".to_string(),
+        "STOP_WATCHDOG_TIMEOUT" => "Task exceeded time budget. Break it into smaller steps or run one concrete action per prompt.".to_string(),
+        "STOP_TOOL_LOOP" => "Model got stuck on repeated tool calls. Try a more specific prompt with exact app/path/target window.".to_string(),
+        "STOP_TOOL_FAILURE_STREAK" => "Multiple tools failed in a row. Verify required apps/backends are installed and available in PATH.".to_string(),
+        "STOP_TOOL_ENFORCEMENT" => "No valid actionable tool path was produced. Use an imperative prompt like 'open X', 'create Y', or 'run Z'.".to_string(),
+        "STOP_PROVIDER_ARGUMENT" => "Provider rejected the request format. Switch model with '/models' or retry after reducing prompt complexity.".to_string(),
+        "STOP_PROVIDER_RATE_LIMIT" => "Provider quota/rate limit hit. Wait for retry window, switch to a lower-traffic model, or use a different provider key.".to_string(),
+        "STOP_MAX_ITERATIONS" => {
+            if lower.contains("gmail") || lower.contains("telegram") || lower.contains("mail") {
+                "For inbox/chat tasks, request one concrete step first (open + capture), then ask for summary in next prompt.".to_string()
+            } else if lower.contains("wallpaper") {
+                "Specify one backend explicitly (e.g. 'use caelestia wallpaper -f <path>').".to_string()
+            } else if lower.contains("vscode") || lower.contains("code") {
+                "Set an explicit launch command for your editor in system profile/capabilities, then retry.".to_string()
+            } else {
+                "Task planning hit iteration budget. Narrow the scope and retry with fewer combined actions.".to_string()
+            }
+        }
+        "STOP_RECOVERY_BUDGET_EXHAUSTED" => "Automatic recovery attempts were exhausted. Retry with a narrower prompt or switch model.".to_string(),
+        "STOP_SESSION_CONFLICT" => "Another run rewrote this thread's history while this one ran, so this run's turns were not saved. Check 'history' and re-run the request if it is still needed.".to_string(),
+        _ => "Unexpected runtime failure. Run 'status' for telemetry, then retry with a narrower prompt.".to_string(),
     }
 }
 
-async fn run_first_run_onboarding(
-    user_id: &str,
-    context: &mut hypr_claw_memory::types::ContextData,
-    state: &mut AgentOsState,
-) -> Result<(), Box<dyn std::error::Error>> {
-    if state.onboarding.completed {
-        return Ok(());
-    }
+fn print_status_panel(
+    session_key: &str,
+    agent_name: &str,
+    display_name: &str,
+    model: &str,
+    active_thread_id: &str,
+    agent_state: &AgentOsState,
+    context: &hypr_claw_memory::types::ContextData,
+    task_list: &[hypr_claw_tasks::TaskInfo],
+) {
+    let running = task_list
+        .iter()
+        .filter(|t| t.status == hypr_claw_tasks::TaskStatus::Running)
+        .count();
+    let completed = task_list
+        .iter()
+        .filter(|t| t.status == hypr_claw_tasks::TaskStatus::Completed)
+        .count();
+    let failed = task_list
+        .iter()
+        .filter(|t| t.status == hypr_claw_tasks::TaskStatus::Failed)
+        .count();
+    let supervisor_queued = agent_state
+        .supervisor
+        .tasks
+        .iter()
+        .filter(|t| t.status == SupervisedTaskStatus::Queued)
+        .count();
+    let supervisor_running = agent_state
+        .supervisor
+        .tasks
+        .iter()
+        .filter(|t| t.status == SupervisedTaskStatus::Running)
+        .count();
 
-    println!("\n🧭 First Run Onboarding");
-    if state.onboarding.preferred_name.trim().is_empty() {
-        let preferred = prompt_line("What should I call you? ")?;
-        state.onboarding.preferred_name = if preferred.trim().is_empty() {
-            user_id.to_string()
+    println!("\n{}", ui_title("Runtime Status"));
+    println!("  Session      : {}", ui_dim(session_key));
+    println!(
+        "  Agent/User   : {} / {}",
+        ui_info(agent_name),
+        ui_info(display_name)
+    );
+    println!(
+        "  Mode         : {}",
+        ui_info("power")
+    );
+    println!(
+        "  Scan Depth   : {}",
+        if agent_state.onboarding.deep_scan_completed {
+            ui_success("deep")
         } else {
-            preferred
-        };
+            ui_dim("standard")
+        }
+    );
+    println!("  Model        : {}", ui_info(model));
+    println!("  Thread       : {}", ui_dim(active_thread_id));
+    println!(
+        "  Threads      : {} active / {} total",
+        agent_state
+            .task_threads
+            .iter()
+            .filter(|t| !t.archived)
+            .count(),
+        agent_state.task_threads.len()
+    );
+    println!(
+        "  Tasks        : {} running / {} completed / {} failed",
+        ui_info(&running.to_string()),
+        ui_success(&completed.to_string()),
+        if failed > 0 {
+            ui_danger(&failed.to_string())
+        } else {
+            ui_success(&failed.to_string())
+        }
+    );
+    println!(
+        "  Supervisor   : {} queued / {} running",
+        supervisor_queued,
+        supervisor_running
+    );
+    println!(
+        "  Memory       : history={} facts={} approvals={} pending={}",
+        context.recent_history.len(),
+        context.facts.len(),
+        context.approval_history.len(),
+        context.pending_approvals.len()
+    );
+    println!(
+        "  Tokens       : input={} output={} session={}",
+        context.token_usage.total_input,
+        context.token_usage.total_output,
+        context.token_usage.by_session
+    );
+    println!("  Reliability  : {}", reliability_summary(agent_state));
+    for (provider, queue) in hypr_claw_runtime::request_limits::queue_snapshot() {
+        println!(
+            "  LLM queue    : {} {}/{} in flight, {} waiting, {} of {} requests queued \
+             (avg wait {:.0}ms, max {:.0}ms)",
+            provider,
+            queue.in_flight,
+            queue.max_concurrent,
+            queue.waiting,
+            queue.queued,
+            queue.requests,
+            queue.average_wait_ms(),
+            queue.max_wait_ms
+        );
+    }
+    if let Some(plan) = &context.current_plan {
+        println!(
+            "  Plan         : {} [{} step {}/{}]",
+            truncate_for_table(&plan.goal, 36),
+            plan.status,
+            plan.current_step.saturating_add(1),
+            plan.steps.len()
+        );
+    } else {
+        println!("  Plan         : none");
     }
+}
 
-    state.onboarding.trusted_full_auto = false;
+fn model_priority(model_id: &str) -> usize {
+    match model_id {
+        "z-ai/glm4.7" => 0,
+        "z-ai/glm5" => 1,
+        "moonshotai/kimi-k2.5" => 2,
+        "moonshotai/kimi-k2-instruct-0905" => 3,
+        "qwen/qwen3-coder-480b-a35b-instruct" => 4,
+        "meta/llama-4-maverick-17b-128e-instruct" => 5,
+        _ => 100,
+    }
+}
 
-    if prompt_yes_no("Allow first-time system study scan? [Y/n] ", true)? {
-        let deep_scan = prompt_yes_no(
-            "Run deep system learning scan (home directory with consent)? [Y/n] ",
-            true,
-        )?;
+fn filter_agentic_models(models: &[String]) -> Vec<String> {
+    let blocked_tokens = [
+        "embed",
+        "embedding",
+        "guard",
+        "safety",
+        "reward",
+        "retriever",
+        "parse",
+        "clip",
+        "deplot",
+        "kosmos",
+        "paligemma",
+        "vila",
+        "streampetr",
+    ];
 
-        state.onboarding.system_profile = scan::run_integrated_scan(user_id, deep_scan).await?;
-        state.onboarding.deep_scan_completed = deep_scan;
-        state.onboarding.last_scan_at = Some(chrono::Utc::now().timestamp());
-        print_system_profile_summary(&state.onboarding.system_profile);
+    let mut filtered: Vec<String> = models
+        .iter()
+        .filter(|id| {
+            let lower = id.to_lowercase();
+            !blocked_tokens.iter().any(|token| lower.contains(token))
+        })
+        .cloned()
+        .collect();
 
-        loop {
-            if prompt_yes_no("Is this system profile correct? [Y/n] ", true)? {
-                state.onboarding.profile_confirmed = true;
-                break;
-            }
-            if !prompt_yes_no("Edit profile now? [Y/n] ", true)? {
-                break;
-            }
-            edit_profile_interactively(&mut state.onboarding.system_profile)?;
-            print_system_profile_summary(&state.onboarding.system_profile);
+    filtered.sort_by(|a, b| {
+        model_priority(a)
+            .cmp(&model_priority(b))
+            .then_with(|| a.cmp(b))
+    });
+    filtered
+}
+
+/// Model catalog for `/models`: the cached list when there is one (refreshed
+/// in the background once stale), otherwise a live fetch. Also returns where
+/// the list came from.
+async fn provider_models<S, L, D, R, Sum>(
+    config: &Config,
+    agent_loop: &hypr_claw_runtime::AgentLoop<S, L, D, R, Sum>,
+    force_live: bool,
+) -> Result<(Vec<hypr_claw_runtime::ModelInfo>, String), String>
+where
+    S: hypr_claw_runtime::SessionStore,
+    L: hypr_claw_runtime::LockManager,
+    D: hypr_claw_runtime::ToolDispatcher,
+    R: hypr_claw_runtime::ToolRegistry,
+    Sum: hypr_claw_runtime::Summarizer,
+{
+    let key = model_cache::provider_key(&config.provider);
+    let mut cache = model_cache::ModelCache::load(data_dir::path(model_cache::MODEL_CACHE_PATH));
+    let now = chrono::Utc::now().timestamp();
+    let cached = cache.get(&key).cloned();
+    if let Some(cached) = cached.as_ref().filter(|_| !force_live) {
+        if cached.is_stale(now) {
+            spawn_model_refresh(config.provider.clone(), config.model.clone());
         }
-    } else {
-        println!("ℹ️  System study skipped for now. Use `scan` later.");
+        let age = model_cache::age_label(cached.fetched_at, now);
+        return Ok((cached.models.clone(), format!("cached {age}")));
     }
 
-    state.onboarding.completed = true;
-    if !context
-        .facts
-        .iter()
-        .any(|f| f.starts_with("preferred_name:"))
-    {
-        context.facts.push(format!(
-            "preferred_name:{}",
-            state.onboarding.preferred_name
-        ));
+    println!("Fetching provider models...");
+    match agent_loop.list_model_info().await {
+        Ok(models) => {
+            let models = model_cache::annotate(models);
+            cache.insert(&key, models.clone(), now);
+            if let Err(e) = cache.save(data_dir::path(model_cache::MODEL_CACHE_PATH)) {
+                eprintln!("⚠️  Failed to cache model list: {}", e);
+            }
+            Ok((models, "live".to_string()))
+        }
+        Err(e) => match cached {
+            Some(cached) => {
+                eprintln!("⚠️  {}; showing the cached list", e);
+                let age = model_cache::age_label(cached.fetched_at, now);
+                Ok((cached.models, format!("offline, cached {age}")))
+            }
+            None => Err(e.to_string()),
+        },
     }
-    Ok(())
 }
 
-fn print_system_profile_summary(profile: &Value) {
-    let distro = profile
-        .pointer("/platform/distro_name")
-        .and_then(|v| v.as_str())
-        .unwrap_or("unknown");
-    let kernel = profile
-        .pointer("/platform/kernel")
-        .and_then(|v| v.as_str())
-        .unwrap_or("unknown");
-    let hypr = profile
-        .pointer("/desktop/hyprland_available")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
-    let active_ws = profile
-        .pointer("/desktop/active_workspace")
-        .and_then(|v| v.as_u64())
-        .unwrap_or(0);
+/// Refresh the cached model list without holding up the caller. A failed
+/// refresh keeps the old list.
+fn spawn_model_refresh(provider: LLMProvider, model: String) {
+    tokio::spawn(async move {
+        let Ok(client) = build_llm_client_for_provider(&provider, &model) else {
+            return;
+        };
+        let Ok(models) = client.list_model_info().await else {
+            return;
+        };
+        let mut cache =
+            model_cache::ModelCache::load(data_dir::path(model_cache::MODEL_CACHE_PATH));
+        cache.insert(
+            &model_cache::provider_key(&provider),
+            model_cache::annotate(models),
+            chrono::Utc::now().timestamp(),
+        );
+        let _ = cache.save(data_dir::path(model_cache::MODEL_CACHE_PATH));
+    });
+}
 
-    println!("\n🖥️  Profile Summary");
-    println!("  Distro: {}", distro);
-    println!("  Kernel: {}", kernel);
-    println!("  Hyprland available: {}", if hypr { "yes" } else { "no" });
-    if hypr {
-        println!("  Active workspace: {}", active_ws);
-    }
-    println!(
-        "  Deep scan data: {}",
-        if profile.pointer("/deep_scan").is_some() {
-            "yes"
-        } else {
-            "no"
-        }
-    );
-    println!();
+fn model_ids(models: &[hypr_claw_runtime::ModelInfo]) -> Vec<String> {
+    models.iter().map(|model| model.id.clone()).collect()
 }
 
-fn edit_profile_interactively(profile: &mut Value) -> io::Result<()> {
-    println!("Profile edit mode.");
-    println!("Commands: show | set <path> <value> | done | accept | help");
-    println!("Path example: desktop.active_workspace");
-    loop {
-        let line = prompt_line("profile> ")?;
-        if line.is_empty() {
-            continue;
+fn model_label(models: &[hypr_claw_runtime::ModelInfo], id: &str) -> String {
+    models
+        .iter()
+        .find(|model| model.id == id)
+        .map(model_cache::describe)
+        .unwrap_or_default()
+}
+
+fn print_model_recommendations(provider: &LLMProvider, current_model: &str) {
+    match provider {
+        LLMProvider::Nvidia => {
+            println!("\nRecommended NVIDIA models for agentic tasks:");
+            let recommendations = [
+                "z-ai/glm4.7",
+                "z-ai/glm5",
+                "moonshotai/kimi-k2.5",
+                "qwen/qwen3-coder-480b-a35b-instruct",
+                "meta/llama-4-maverick-17b-128e-instruct",
+            ];
+            for model in recommendations {
+                let marker = if model == current_model { "*" } else { " " };
+                println!("  {} {}", marker, model);
+            }
+            println!("Use '/models set <model_id>' to switch.");
         }
-        if line == "done" || line == "accept" {
-            break;
+        _ => {
+            println!("Model recommendations are currently tuned for NVIDIA provider.");
         }
-        if line == "help" {
-            println!("Commands: show | set <path> <value> | done | accept");
-            continue;
+    }
+}
+
+fn print_config_issues(issues: &[ConfigIssue]) {
+    eprintln!("❌ Configuration has {} issue(s):", issues.len());
+    for issue in issues {
+        eprintln!("  - {}: {}", issue.field, issue.problem);
+        eprintln!("    💡 {}", issue.suggestion);
+    }
+}
+
+/// Walk the user through the issues the REPL can fix. An API key is stored
+/// right away; a chosen model is returned for the caller to apply.
+async fn fix_config_issues(config: &Config, issues: &[ConfigIssue]) -> io::Result<Option<String>> {
+    let mut fixable: Vec<&ConfigIssue> = issues.iter().filter(|i| i.is_fixable()).collect();
+    // Listing models needs a working key, so the key comes first.
+    fixable.sort_by_key(|issue| issue.kind != ConfigIssueKind::MissingApiKey);
+    let mut chosen_model = None;
+    for issue in fixable {
+        match issue.kind {
+            ConfigIssueKind::MissingApiKey => {
+                let answer = prompt_line("Enter the API key now? [Y/n]: ")?;
+                if answer.is_empty() || answer.eq_ignore_ascii_case("y") {
+                    match bootstrap::enter_api_key(&config.provider) {
+                        Ok(()) => println!("✅ API key saved"),
+                        Err(e) => eprintln!("❌ {}", e),
+                    }
+                }
+            }
+            ConfigIssueKind::EmptyModel | ConfigIssueKind::UnknownModel
+                if chosen_model.is_none() =>
+            {
+                chosen_model = pick_provider_model(config).await?;
+            }
+            _ => {}
         }
-        if line == "show" {
-            println!(
-                "{}",
-                serde_json::to_string_pretty(profile).unwrap_or_else(|_| "{}".to_string())
-            );
-            continue;
+    }
+    Ok(chosen_model)
+}
+
+async fn pick_provider_model(config: &Config) -> io::Result<Option<String>> {
+    let client = match build_llm_client_for_provider(&config.provider, &config.model) {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("❌ Cannot reach the provider to list models: {}", e);
+            return Ok(None);
         }
-        if let Some(rest) = line.strip_prefix("set ") {
-            let mut parts = rest.splitn(2, ' ');
-            let path = parts.next().unwrap_or_default();
-            let raw_value = parts.next().unwrap_or_default().trim();
-            if path.is_empty() || raw_value.is_empty() {
-                println!("Usage: set <path> <value>");
-                continue;
-            }
-            let parsed = serde_json::from_str::<Value>(raw_value)
-                .unwrap_or_else(|_| Value::String(raw_value.to_string()));
-            set_json_path(profile, path, parsed);
-            println!("Updated {}", path);
-            continue;
+    };
+    println!("Fetching provider models...");
+    let candidates = match client.list_models().await {
+        Ok(models) => filter_agentic_models(&models),
+        Err(e) => {
+            eprintln!("❌ Failed to fetch models: {}", e);
+            print_model_recommendations(&config.provider, &config.model);
+            return Ok(None);
+        }
+    };
+    if candidates.is_empty() {
+        println!("No models returned by provider.");
+        return Ok(None);
+    }
+    let limit = candidates.len().min(20);
+    for (i, model) in candidates.iter().take(limit).enumerate() {
+        println!("  {:>2}. {}", i + 1, model);
+    }
+    let choice = prompt_line("Select model number (Enter to skip): ")?;
+    match choice.parse::<usize>() {
+        Ok(index) if (1..=limit).contains(&index) => Ok(Some(candidates[index - 1].clone())),
+        _ if choice.is_empty() => Ok(None),
+        _ => {
+            eprintln!("❌ Invalid model index");
+            Ok(None)
         }
-        println!("Unknown command. Use show | set <path> <value> | done");
     }
-    Ok(())
 }
 
-fn set_json_path(root: &mut Value, path: &str, value: Value) {
-    let segments: Vec<&str> = path.split('.').filter(|s| !s.is_empty()).collect();
-    if segments.is_empty() {
-        *root = value;
-        return;
+async fn apply_model_switch<S, L, D, R, Sum>(
+    model_id: &str,
+    agent_loop: &hypr_claw_runtime::AgentLoop<S, L, D, R, Sum>,
+    config: &mut Config,
+    context_manager: &hypr_claw_memory::ContextManager,
+    context: &mut hypr_claw_memory::types::ContextData,
+) -> Result<(), String>
+where
+    S: hypr_claw_runtime::SessionStore,
+    L: hypr_claw_runtime::LockManager,
+    D: hypr_claw_runtime::ToolDispatcher,
+    R: hypr_claw_runtime::ToolRegistry,
+    Sum: hypr_claw_runtime::Summarizer,
+{
+    agent_loop.set_model(model_id).map_err(|e| e.to_string())?;
+    config.model = model_id.to_string();
+    crash::set_model(model_id);
+    config.save().map_err(|e| e.to_string())?;
+
+    if !context.system_state.is_object() {
+        context.system_state = json!({});
+    }
+    if let Some(obj) = context.system_state.as_object_mut() {
+        obj.insert("active_model".to_string(), json!(model_id));
     }
+    context_manager
+        .save(context)
+        .await
+        .map_err(|e| e.to_string())?;
 
-    let mut current = root;
-    let mut pending = Some(value);
-    for (index, segment) in segments.iter().enumerate() {
-        let is_last = index == segments.len() - 1;
-        if !current.is_object() {
-            *current = json!({});
-        }
-        let map = current.as_object_mut().expect("object was just created");
-        if is_last {
-            map.insert(
-                (*segment).to_string(),
-                pending.take().unwrap_or(Value::Null),
-            );
-            return;
+    println!("✅ Model switched to {}", model_id);
+    if model_id == "z-ai/glm4.7" {
+        println!("ℹ️  GLM-4.7 profile enabled (agentic terminal tuning).");
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_with_interrupt_and_timeout<S, L, D, R, Sum>(
+    side_runs: &mut SideRuns<S, L, D, R, Sum>,
+    session_key: &str,
+    agent_name: &str,
+    system_prompt: &str,
+    prompt: &str,
+    interrupt: &Arc<tokio::sync::Notify>,
+    timeout: Duration,
+    pending: &mut input::PendingInputs,
+) -> Result<String, hypr_claw_runtime::RuntimeError>
+where
+    S: hypr_claw_runtime::SessionStore + 'static,
+    L: hypr_claw_runtime::LockManager + 'static,
+    D: hypr_claw_runtime::ToolDispatcher + 'static,
+    R: hypr_claw_runtime::ToolRegistry + 'static,
+    Sum: hypr_claw_runtime::Summarizer + 'static,
+{
+    let agent_loop = side_runs.agent_loop.clone();
+    let _in_flight = side_runs.in_flight.start(&side_runs.foreground, prompt);
+    let run = agent_loop.run(session_key, agent_name, system_prompt, prompt);
+    let deadline = tokio::time::sleep(timeout);
+    tokio::pin!(run, deadline);
+    // Lines typed meanwhile are queued instead of waiting unread in the terminal,
+    // or start right away in the thread picked with `thread switch`.
+    let mut stdin_open = true;
+    loop {
+        tokio::select! {
+            res = &mut run => return res,
+            Some(joined) = side_runs.tasks.join_next(), if !side_runs.tasks.is_empty() => {
+                side_runs.finish(joined);
+            }
+            _ = interrupt.notified() => {
+                if !pending.is_empty() {
+                    eprintln!("Dropped {} queued prompt(s).", pending.len());
+                    pending.clear();
+                }
+                return Err(hypr_claw_runtime::RuntimeError::LLMError(
+                    "Interrupted by user".to_string(),
+                ));
+            }
+            _ = &mut deadline => return Err(hypr_claw_runtime::RuntimeError::LLMError(
+                format!("Execution watchdog timeout after {}s", timeout.as_secs())
+            )),
+            line = input::next_line(), if stdin_open => {
+                let Some(line) = line else {
+                    stdin_open = false;
+                    continue;
+                };
+                if let Some(thread) = thread_switch_target(&line) {
+                    side_runs.switch(thread);
+                    continue;
+                }
+                match input::TypedAhead::parse(&line) {
+                    Some(input::TypedAhead::Note(note)) => {
+                        agent_loop.push_observation(session_key, &note);
+                        eprintln!("📝 Note added to the running request.");
+                    }
+                    Some(input::TypedAhead::Next(line)) if side_runs.target.is_some() => {
+                        let thread = side_runs.target.clone().expect("target checked above");
+                        if side_runs.start(&thread, &line, timeout) {
+                            let status = side_runs.in_flight.status_line().unwrap_or_default();
+                            eprintln!("▶ Started in {}; {}", thread, status);
+                        } else {
+                            pending.push(line);
+                            eprintln!(
+                                "⏳ {} already has a run in flight; queued ({}) for after this run.",
+                                thread,
+                                pending.len()
+                            );
+                        }
+                    }
+                    Some(input::TypedAhead::Next(line)) => {
+                        pending.push(line);
+                        eprintln!(
+                            "⏳ Queued ({}) for after this run; '+text' adds a note instead.",
+                            pending.len()
+                        );
+                    }
+                    None => {}
+                }
+            }
         }
-        current = map
-            .entry((*segment).to_string())
-            .or_insert_with(|| json!({}));
     }
 }
 
-fn profile_needs_capability_refresh(profile: &Value) -> bool {
-    profile.pointer("/capabilities").is_none() || profile.pointer("/paths/downloads").is_none()
+/// A prompt run in another thread while a foreground run went on.
+struct SideRunOutcome {
+    thread_id: String,
+    prompt: String,
+    elapsed_ms: u64,
+    result: Result<String, String>,
+    changes: side_effects::Manifest,
 }
 
-fn sanitize_user_key_for_filename(user_id: &str) -> String {
-    let mut out = String::new();
-    for ch in user_id.chars() {
-        if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' {
-            out.push(ch);
+/// Runs started in other threads during a foreground run. After
+/// `thread switch <id>` mid-run, lines typed next start at once in that
+/// thread, on the shared agent loop with the thread's own session, lock and
+/// supervision, instead of queueing behind the foreground run.
+struct SideRuns<S, L, D, R, Sum>
+where
+    S: hypr_claw_runtime::SessionStore,
+    L: hypr_claw_runtime::LockManager,
+    D: hypr_claw_runtime::ToolDispatcher,
+    R: hypr_claw_runtime::ToolRegistry,
+    Sum: hypr_claw_runtime::Summarizer,
+{
+    agent_loop: Arc<hypr_claw_runtime::AgentLoop<S, L, D, R, Sum>>,
+    dispatcher: Arc<RuntimeDispatcherAdapter>,
+    gate: Arc<hypr_claw_tools::approvals::PlanApprovalGate>,
+    action_log: Arc<action_log::ActionLog>,
+    base_session_key: String,
+    agent_name: String,
+    in_flight: inflight::InFlightRuns,
+    /// What the foreground run was set up from, as of the last `sync`.
+    setup: Option<RunSetup>,
+    /// Thread of the foreground run as of the last `sync`.
+    foreground: String,
+    /// Environment of each open thread as of the last `sync`.
+    threads: BTreeMap<String, BTreeMap<String, String>>,
+    /// Thread lines typed during the foreground run start in, if another.
+    target: Option<String>,
+    tasks: tokio::task::JoinSet<SideRunOutcome>,
+    /// Outcomes not yet written to the history.
+    finished: Vec<SideRunOutcome>,
+}
+
+impl<S, L, D, R, Sum> SideRuns<S, L, D, R, Sum>
+where
+    S: hypr_claw_runtime::SessionStore + 'static,
+    L: hypr_claw_runtime::LockManager + 'static,
+    D: hypr_claw_runtime::ToolDispatcher + 'static,
+    R: hypr_claw_runtime::ToolRegistry + 'static,
+    Sum: hypr_claw_runtime::Summarizer + 'static,
+{
+    fn new(
+        agent_loop: Arc<hypr_claw_runtime::AgentLoop<S, L, D, R, Sum>>,
+        dispatcher: Arc<RuntimeDispatcherAdapter>,
+        gate: Arc<hypr_claw_tools::approvals::PlanApprovalGate>,
+        action_log: Arc<action_log::ActionLog>,
+        base_session_key: &str,
+        agent_name: &str,
+    ) -> Self {
+        Self {
+            agent_loop,
+            dispatcher,
+            gate,
+            action_log,
+            base_session_key: base_session_key.to_string(),
+            agent_name: agent_name.to_string(),
+            in_flight: inflight::InFlightRuns::new(),
+            setup: None,
+            foreground: String::new(),
+            threads: BTreeMap::new(),
+            target: None,
+            tasks: tokio::task::JoinSet::new(),
+            finished: Vec::new(),
+        }
+    }
+
+    /// Takes the active thread and the open threads before a foreground run.
+    fn sync(&mut self, state: &AgentOsState, setup: RunSetup) {
+        self.setup = Some(setup);
+        self.foreground = state.active_thread_id.clone();
+        self.threads = state
+            .task_threads
+            .iter()
+            .filter(|thread| !thread.archived)
+            .map(|thread| (thread.id.clone(), thread.env.clone()))
+            .collect();
+    }
+
+    /// `thread switch <id>` typed during the foreground run.
+    fn switch(&mut self, thread: &str) {
+        if thread == self.foreground {
+            self.target = None;
+            eprintln!(
+                "↩ Back on {}; lines typed now queue for after its run.",
+                thread
+            );
+        } else if self.threads.contains_key(thread) {
+            self.target = Some(thread.to_string());
+            eprintln!(
+                "🔀 Lines typed now start in {} while {} keeps running.",
+                thread, self.foreground
+            );
         } else {
-            out.push('_');
+            eprintln!("❌ No open thread '{}'", thread);
         }
     }
-    if out.is_empty() {
-        "local_user".to_string()
-    } else {
-        out
+
+    /// Starts `prompt` in `thread`; false if that thread has a run already.
+    fn start(&mut self, thread: &str, prompt: &str, timeout: Duration) -> bool {
+        // Side runs only start during a foreground run, after its `sync`.
+        let Some(setup) = &self.setup else {
+            return false;
+        };
+        let Some(guard) = self.in_flight.start(thread, prompt) else {
+            return false;
+        };
+        let session_key = thread_session_key(&self.base_session_key, thread);
+        let class = classify_supervised_task_class(prompt);
+        let (_, system_prompt) = setup.prepare(&self.gate, &session_key, thread, &class);
+        self.dispatcher.set_task_label(&session_key, prompt);
+        self.dispatcher.set_env_overlay(
+            &session_key,
+            self.threads.get(thread).cloned().unwrap_or_default(),
+        );
+        let run_id = self.action_log.claim_run_id(0);
+        if let Err(e) = self.action_log.begin_run(&session_key, run_id, prompt) {
+            eprintln!("⚠️  Failed to start action log: {}", e);
+        }
+        let agent_loop = self.agent_loop.clone();
+        let dispatcher = self.dispatcher.clone();
+        let action_log = self.action_log.clone();
+        let agent_name = self.agent_name.clone();
+        let thread_id = thread.to_string();
+        let prompt = prompt.to_string();
+        self.tasks.spawn(async move {
+            let _guard = guard;
+            let started = Instant::now();
+            let run = agent_loop.run(&session_key, &agent_name, &system_prompt, &prompt);
+            let result = match tokio::time::timeout(timeout, run).await {
+                Ok(result) => result.map_err(|e| e.to_string()),
+                Err(_) => Err(format!(
+                    "Execution watchdog timeout after {}s",
+                    timeout.as_secs()
+                )),
+            };
+            let elapsed_ms = started.elapsed().as_millis() as u64;
+            let changes = dispatcher.take_changes(&session_key);
+            if let Ok(response) = &result {
+                if let Err(e) = action_log.record_reply(&session_key, response) {
+                    eprintln!("⚠️  Failed to log reply: {}", e);
+                }
+            }
+            if let Err(e) = action_log.record_changes(&session_key, &changes) {
+                eprintln!("⚠️  Failed to log changes: {}", e);
+            }
+            let stop_code = match &result {
+                Ok(_) => "STOP_NONE",
+                Err(e) => stop_code_for_error(e),
+            };
+            if let Err(e) = action_log.finish_run(&session_key, stop_code, elapsed_ms) {
+                eprintln!("⚠️  Failed to close action log: {}", e);
+            }
+            SideRunOutcome {
+                thread_id,
+                prompt,
+                elapsed_ms,
+                result,
+                changes,
+            }
+        });
+        true
+    }
+
+    /// Prints a finished side run and keeps it for the history.
+    fn finish(&mut self, joined: Result<SideRunOutcome, tokio::task::JoinError>) {
+        match joined {
+            Ok(outcome) => {
+                match &outcome.result {
+                    Ok(response) => println!(
+                        "\n✅ [{}] done in {}ms\n{}\n",
+                        outcome.thread_id, outcome.elapsed_ms, response
+                    ),
+                    Err(e) => eprintln!(
+                        "❌ [{}] failed after {}ms: {}",
+                        outcome.thread_id, outcome.elapsed_ms, e
+                    ),
+                }
+                print_run_changes(&outcome.changes);
+                self.finished.push(outcome);
+            }
+            Err(e) => eprintln!("❌ A thread run stopped unexpectedly: {}", e),
+        }
+        if let Some(status) = self.in_flight.status_line() {
+            eprintln!("⏳ {}", status);
+        }
     }
 }
 
-fn capability_registry_file_path(user_id: &str) -> String {
-    format!(
-        "./data/capabilities/{}.json",
-        sanitize_user_key_for_filename(user_id)
-    )
+/// Writes a finished side run to the history under its thread.
+fn record_side_run(
+    state: &mut AgentOsState,
+    context: &mut hypr_claw_memory::types::ContextData,
+    outcome: SideRunOutcome,
+) {
+    let now = chrono::Utc::now().timestamp();
+    let reply = match outcome.result {
+        Ok(response) => response,
+        Err(e) => format!("failed: {}", e),
+    };
+    let reply = with_run_changes(reply, &outcome.changes);
+    for (role, text) in [("user", outcome.prompt), ("assistant", reply)] {
+        context
+            .recent_history
+            .push(hypr_claw_memory::types::HistoryEntry {
+                timestamp: now,
+                role: role.to_string(),
+                content: format!("[thread:{}] {}", outcome.thread_id, text),
+                token_count: None,
+            });
+    }
+    if let Some(thread) = state
+        .task_threads
+        .iter_mut()
+        .find(|thread| thread.id == outcome.thread_id)
+    {
+        thread.updated_at = now;
+    }
 }
 
-fn read_string_array_from_value(value: Option<&Value>) -> Vec<String> {
-    value
-        .and_then(|v| v.as_array())
-        .map(|arr| {
-            arr.iter()
-                .filter_map(|v| v.as_str())
-                .map(str::to_string)
-                .collect::<Vec<String>>()
-        })
-        .unwrap_or_default()
+/// Thread named by a `thread switch <id>` line.
+fn thread_switch_target(line: &str) -> Option<&str> {
+    let line = line.trim();
+    line.strip_prefix("thread switch ")
+        .or_else(|| line.strip_prefix("/thread switch "))
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && !id.contains(char::is_whitespace))
 }
 
-fn build_capability_registry(profile: &Value) -> Value {
-    let profile_scanned_at = profile
-        .pointer("/scanned_at")
-        .and_then(|v| v.as_i64())
-        .unwrap_or(0);
-
-    let has_deep_scan = profile.pointer("/deep_scan").is_some();
-
-    let wallpaper_backends =
-        read_string_array_from_value(profile.pointer("/capabilities/wallpaper_backends"));
-    let screenshot_backends =
-        read_string_array_from_value(profile.pointer("/capabilities/screenshot_backends"));
-    let input_backends =
-        read_string_array_from_value(profile.pointer("/capabilities/input_backends"));
-    let launcher_commands = read_string_array_from_value(
-        profile.pointer("/deep_scan/desktop_apps/launcher_commands_sample"),
-    );
-    let known_projects =
-        read_string_array_from_value(profile.pointer("/deep_scan/home_inventory/project_roots"));
+fn build_llm_client_for_provider(
+    provider: &LLMProvider,
+    model: &str,
+) -> Result<hypr_claw_runtime::LLMClientType, String> {
+    let client = match provider {
+        LLMProvider::Nvidia => {
+            let api_key = bootstrap::get_nvidia_api_key().map_err(|e| e.to_string())?;
+            hypr_claw_runtime::LLMClient::with_api_key_and_model(
+                provider.base_url(),
+                1,
+                api_key,
+                model.to_string(),
+            )
+        }
+        LLMProvider::Google => {
+            let api_key = bootstrap::get_google_api_key().map_err(|e| e.to_string())?;
+            hypr_claw_runtime::LLMClient::with_api_key_and_model(
+                provider.base_url(),
+                1,
+                api_key,
+                model.to_string(),
+            )
+        }
+        LLMProvider::Local { .. } => hypr_claw_runtime::LLMClient::new(provider.base_url(), 1),
+        LLMProvider::Codex | LLMProvider::Antigravity | LLMProvider::GeminiCli => {
+            return Err("Provider does not support agent-mode tool calling".to_string());
+        }
+    };
+    let client = match hypr_claw_runtime::request_limits::limiter_for(provider.name()) {
+        Some(limiter) => client.with_request_limiter(limiter),
+        None => client,
+    };
+    Ok(hypr_claw_runtime::LLMClientType::Standard(client))
+}
 
-    let available_commands = profile
-        .pointer("/commands")
-        .and_then(|v| v.as_object())
-        .map(|obj| {
-            obj.iter()
-                .filter_map(|(name, enabled)| {
-                    enabled.as_bool().unwrap_or(false).then_some(name.clone())
-                })
-                .collect::<Vec<String>>()
+fn to_context_tasks(
+    task_list: Vec<hypr_claw_tasks::TaskInfo>,
+) -> Vec<hypr_claw_memory::types::TaskState> {
+    task_list
+        .into_iter()
+        .map(|task| hypr_claw_memory::types::TaskState {
+            id: task.id,
+            description: task.description,
+            status: format!("{:?}", task.status).to_lowercase(),
+            progress: task.progress,
+            created_at: task.created_at,
+            updated_at: task.updated_at,
         })
-        .unwrap_or_default();
+        .collect()
+}
 
-    let vscode_command = ["code", "codium", "code-oss", "vscodium", "code-insiders"]
-        .iter()
-        .find(|name| available_commands.iter().any(|cmd| cmd == **name))
-        .map(|s| (*s).to_string())
-        .unwrap_or_else(|| "unknown".to_string());
-    let preferred_launchers =
-        build_preferred_launchers(&launcher_commands, &available_commands, &vscode_command);
+fn plan_for_input(goal: &str) -> hypr_claw_memory::types::PlanState {
+    hypr_claw_memory::types::PlanState {
+        goal: goal.to_string(),
+        steps: vec![
+            hypr_claw_memory::types::PlanStepState {
+                id: 0,
+                description: "Analyze request".to_string(),
+                status: "completed".to_string(),
+                result: Some("Intent parsed".to_string()),
+            },
+            hypr_claw_memory::types::PlanStepState {
+                id: 1,
+                description: "Execute required tools".to_string(),
+                status: "in_progress".to_string(),
+                result: None,
+            },
+            hypr_claw_memory::types::PlanStepState {
+                id: 2,
+                description: "Report completion".to_string(),
+                status: "pending".to_string(),
+                result: None,
+            },
+        ],
+        current_step: 1,
+        status: "in_progress".to_string(),
+        updated_at: chrono::Utc::now().timestamp(),
+    }
+}
 
-    json!({
-        "schema_version": 1,
-        "generated_at": chrono::Utc::now().timestamp(),
-        "source_profile_scanned_at": profile_scanned_at,
-        "source_has_deep_scan": has_deep_scan,
-        "platform": {
-            "distro_name": profile.pointer("/platform/distro_name").and_then(|v| v.as_str()).unwrap_or("unknown"),
-            "kernel": profile.pointer("/platform/kernel").and_then(|v| v.as_str()).unwrap_or("unknown"),
-            "arch": profile.pointer("/platform/arch").and_then(|v| v.as_str()).unwrap_or("unknown"),
-            "hyprland_available": profile.pointer("/desktop/hyprland_available").and_then(|v| v.as_bool()).unwrap_or(false),
-            "active_workspace": profile.pointer("/desktop/active_workspace").and_then(|v| v.as_u64()).unwrap_or(0),
-            "workspace_count": profile.pointer("/desktop/workspace_count").and_then(|v| v.as_u64()).unwrap_or(0)
-        },
-        "paths": {
-            "home": profile.pointer("/paths/home").and_then(|v| v.as_str()).unwrap_or(""),
-            "downloads": profile.pointer("/paths/downloads").and_then(|v| v.as_str()).unwrap_or(""),
-            "pictures": profile.pointer("/paths/pictures").and_then(|v| v.as_str()).unwrap_or(""),
-            "documents": profile.pointer("/paths/documents").and_then(|v| v.as_str()).unwrap_or("")
-        },
-        "capabilities": {
-            "wallpaper_backends": wallpaper_backends,
-            "screenshot_backends": screenshot_backends,
-            "input_backends": input_backends,
-            "ocr_available": profile.pointer("/capabilities/ocr_available").and_then(|v| v.as_bool()).unwrap_or(false)
-        },
-        "editor": {
-            "vscode_command": vscode_command
-        },
-        "desktop_apps": {
-            "launcher_commands": launcher_commands,
-            "preferred_launchers": preferred_launchers
-        },
-        "projects": {
-            "known_roots": known_projects
-        },
-        "hyprland": {
-            "main_config": profile.pointer("/deep_scan/hyprland/main_config").and_then(|v| v.as_str()).unwrap_or(""),
-            "binds_sample": profile.pointer("/deep_scan/hyprland/binds_sample").cloned().unwrap_or_else(|| json!([])),
-            "exec_once_sample": profile.pointer("/deep_scan/hyprland/exec_once_sample").cloned().unwrap_or_else(|| json!([])),
-            "workspace_rules_sample": profile.pointer("/deep_scan/hyprland/workspace_rules_sample").cloned().unwrap_or_else(|| json!([]))
-        },
-        "packages": {
-            "total_count": profile.pointer("/deep_scan/packages/total_count").and_then(|v| v.as_u64()).unwrap_or(0),
-            "pacman_explicit_count": profile.pointer("/deep_scan/packages/pacman_explicit_count").and_then(|v| v.as_u64()).unwrap_or(0),
-            "aur_count": profile.pointer("/deep_scan/packages/aur_count").and_then(|v| v.as_u64()).unwrap_or(0)
-        },
-        "commands": {
-            "available": available_commands
-        },
-        "usage": {
-            "top_commands": profile.pointer("/deep_scan/usage/shell_history_top").cloned().unwrap_or_else(|| json!([]))
+fn mark_plan_completed(context: &mut hypr_claw_memory::types::ContextData, response: &str) {
+    if let Some(plan) = &mut context.current_plan {
+        if let Some(step) = plan.steps.get_mut(1) {
+            step.status = "completed".to_string();
+            step.result = Some("Tool execution completed".to_string());
         }
-    })
+        if let Some(step) = plan.steps.get_mut(2) {
+            step.status = "completed".to_string();
+            step.result = Some(response.to_string());
+        }
+        plan.current_step = plan.steps.len();
+        plan.status = "completed".to_string();
+        plan.updated_at = chrono::Utc::now().timestamp();
+    }
 }
 
-fn build_preferred_launchers(
-    launcher_commands: &[String],
-    available_commands: &[String],
-    vscode_command: &str,
-) -> Value {
-    let mut known = std::collections::BTreeSet::new();
-    for command in launcher_commands {
-        known.insert(command.clone());
-    }
-    for command in available_commands {
-        known.insert(command.clone());
+fn mark_plan_failed(context: &mut hypr_claw_memory::types::ContextData, error: &str) {
+    if let Some(plan) = &mut context.current_plan {
+        let step_index = plan.current_step.min(plan.steps.len().saturating_sub(1));
+        if let Some(step) = plan.steps.get_mut(step_index) {
+            step.status = "failed".to_string();
+            step.result = Some(error.to_string());
+        }
+        plan.status = "failed".to_string();
+        plan.updated_at = chrono::Utc::now().timestamp();
     }
+}
 
-    let pick = |candidates: &[&str]| -> String {
-        candidates
-            .iter()
-            .find(|candidate| known.contains(**candidate))
-            .map(|candidate| (*candidate).to_string())
-            .unwrap_or_else(|| "unknown".to_string())
-    };
-
-    let vscode = if vscode_command == "unknown" {
-        pick(&["code", "code-insiders", "code-oss", "codium", "vscodium"])
-    } else {
-        vscode_command.to_string()
-    };
-
-    json!({
-        "vscode": vscode,
-        "browser": pick(&["firefox", "google-chrome-stable", "google-chrome", "chromium", "brave-browser"]),
-        "telegram": pick(&["telegram-desktop", "telegram", "org.telegram.desktop"]),
-        "terminal": pick(&["kitty", "alacritty", "wezterm", "gnome-terminal", "xterm"])
-    })
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+enum SupervisedTaskClass {
+    Question,
+    Action,
+    Investigation,
 }
 
-fn capability_registry_needs_refresh(registry: &Value, profile: &Value) -> bool {
-    if registry.pointer("/schema_version").is_none() {
-        return true;
-    }
-    if registry.pointer("/paths/downloads").is_none() {
-        return true;
-    }
-    if registry
-        .pointer("/capabilities/wallpaper_backends")
-        .is_none()
-    {
-        return true;
-    }
-    if registry
-        .pointer("/desktop_apps/preferred_launchers")
-        .is_none()
-    {
-        return true;
-    }
+impl SupervisedTaskClass {
+    const ALL: [Self; 3] = [Self::Question, Self::Action, Self::Investigation];
 
-    let profile_scanned_at = profile
-        .pointer("/scanned_at")
-        .and_then(|v| v.as_i64())
-        .unwrap_or(0);
-    let registry_profile_scanned_at = registry
-        .pointer("/source_profile_scanned_at")
-        .and_then(|v| v.as_i64())
-        .unwrap_or(0);
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Question => "question",
+            Self::Action => "action",
+            Self::Investigation => "investigation",
+        }
+    }
 
-    if profile_scanned_at > registry_profile_scanned_at {
-        return true;
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "question" => Some(Self::Question),
+            "action" => Some(Self::Action),
+            "investigation" => Some(Self::Investigation),
+            _ => None,
+        }
     }
+}
 
-    let profile_has_deep = profile.pointer("/deep_scan").is_some();
-    let registry_has_deep = registry
-        .pointer("/source_has_deep_scan")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
-    profile_has_deep && !registry_has_deep
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+enum SupervisedTaskStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
 }
 
-fn load_capability_registry(user_id: &str) -> io::Result<Value> {
-    let path = capability_registry_file_path(user_id);
-    let raw = std::fs::read_to_string(path)?;
-    serde_json::from_str::<Value>(&raw)
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SupervisedTask {
+    id: String,
+    prompt: String,
+    class: SupervisedTaskClass,
+    #[serde(default)]
+    resources: Vec<String>,
+    #[serde(default)]
+    background_task_id: Option<String>,
+    status: SupervisedTaskStatus,
+    created_at: i64,
+    updated_at: i64,
+    error: Option<String>,
+    /// Summary of the last failed attempt at this prompt, fed to the next run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    attempt_log: Option<String>,
+    /// Higher starts first among queued tasks that are ready.
+    #[serde(default)]
+    priority: i32,
+    /// Supervisor tasks that must complete before this one starts.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    depends_on: Vec<String>,
 }
 
-fn save_capability_registry(user_id: &str, registry: &Value) -> io::Result<()> {
-    std::fs::create_dir_all("./data/capabilities")?;
-    let path = capability_registry_file_path(user_id);
-    let payload = serde_json::to_string_pretty(registry)
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
-    std::fs::write(path, payload)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SupervisorState {
+    #[serde(default = "default_supervisor_auto_run")]
+    auto_run: bool,
+    #[serde(default = "default_supervisor_next_id")]
+    next_id: u64,
+    #[serde(default)]
+    tasks: Vec<SupervisedTask>,
 }
 
-/// Appends a timestamped capability delta record to the user's delta history (JSONL).
-fn append_capability_delta_history(
-    user_id: &str,
-    old_registry: &Value,
-    new_registry: &Value,
-) -> io::Result<()> {
-    std::fs::create_dir_all("./data/capabilities")?;
-    let path = format!(
-        "./data/capabilities/{}.deltas.jsonl",
-        sanitize_user_key_for_filename(user_id)
-    );
-    let diff_lines = capability_registry_diff_lines(old_registry, new_registry);
-    let summary = if diff_lines.is_empty() {
-        "no changes".to_string()
-    } else {
-        format!("{} change(s)", diff_lines.len())
-    };
-    let at = chrono::Utc::now().timestamp();
-    let record = json!({
-        "at": at,
-        "summary": summary,
-        "diff_lines": diff_lines,
-    });
-    let line = serde_json::to_string(&record)
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
-    let mut content = line;
-    content.push('\n');
-    std::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(path)?
-        .write_all(content.as_bytes())
+fn default_supervisor_auto_run() -> bool {
+    false
 }
 
-/// Reads the last N lines from the user's capability deltas JSONL file (newest last in file).
-fn read_capability_delta_history(user_id: &str, limit: usize) -> Vec<Value> {
-    let path = format!(
-        "./data/capabilities/{}.deltas.jsonl",
-        sanitize_user_key_for_filename(user_id)
-    );
-    let content = match std::fs::read_to_string(&path) {
-        Ok(c) => c,
-        Err(_) => return Vec::new(),
-    };
-    let lines: Vec<&str> = content.lines().filter(|s| !s.trim().is_empty()).collect();
-    let start = lines.len().saturating_sub(limit);
-    lines[start..]
-        .iter()
-        .filter_map(|line| serde_json::from_str(line).ok())
-        .collect()
+fn default_supervisor_next_id() -> u64 {
+    1
 }
 
-fn print_capability_delta_history(user_id: &str, limit: usize) {
-    let records = read_capability_delta_history(user_id, limit);
-    println!("\n{}", ui_title("Capability delta history"));
-    if records.is_empty() {
-        println!("  No delta history yet. Run `scan` and apply changes to record deltas.");
-        return;
-    }
-    for (i, rec) in records.iter().enumerate() {
-        let at = rec.get("at").and_then(|v| v.as_i64()).unwrap_or(0);
-        let summary = rec.get("summary").and_then(|v| v.as_str()).unwrap_or("?");
-        println!("  {}  {}  {}", i + 1, format_timestamp(at), summary);
-        if let Some(arr) = rec.get("diff_lines").and_then(|v| v.as_array()) {
-            for line in arr.iter().take(5) {
-                if let Some(s) = line.as_str() {
-                    println!("      - {}", truncate_for_table(s, 72));
-                }
-            }
-            if arr.len() > 5 {
-                println!("      ... and {} more", arr.len() - 5);
-            }
+impl Default for SupervisorState {
+    fn default() -> Self {
+        Self {
+            auto_run: default_supervisor_auto_run(),
+            next_id: default_supervisor_next_id(),
+            tasks: Vec::new(),
         }
     }
-    println!();
 }
 
-fn print_capability_registry_summary(user_id: &str, registry: &Value) {
-    let generated = registry
-        .pointer("/generated_at")
-        .and_then(|v| v.as_i64())
-        .map(format_timestamp)
-        .unwrap_or_else(|| "n/a".to_string());
-    let distro = registry
-        .pointer("/platform/distro_name")
-        .and_then(|v| v.as_str())
-        .unwrap_or("unknown");
-    let kernel = registry
-        .pointer("/platform/kernel")
-        .and_then(|v| v.as_str())
-        .unwrap_or("unknown");
-    let workspace = registry
-        .pointer("/platform/active_workspace")
-        .and_then(|v| v.as_u64())
-        .unwrap_or(0);
-    let downloads = registry
-        .pointer("/paths/downloads")
-        .and_then(|v| v.as_str())
-        .unwrap_or("");
-    let wallpaper =
-        read_string_array_from_value(registry.pointer("/capabilities/wallpaper_backends"));
-    let screenshot =
-        read_string_array_from_value(registry.pointer("/capabilities/screenshot_backends"));
-    let input = read_string_array_from_value(registry.pointer("/capabilities/input_backends"));
-    let launchers =
-        read_string_array_from_value(registry.pointer("/desktop_apps/launcher_commands"));
-    let preferred_launchers = registry
-        .pointer("/desktop_apps/preferred_launchers")
-        .and_then(|v| v.as_object())
-        .map(|obj| {
-            obj.iter()
-                .filter_map(|(k, v)| v.as_str().map(|value| format!("{k}={value}")))
-                .collect::<Vec<String>>()
-        })
-        .unwrap_or_default();
-    let registry_path = capability_registry_file_path(user_id);
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AgentOsState {
+    #[serde(default)]
+    onboarding: OnboardingState,
+    #[serde(default)]
+    soul_auto: bool,
+    #[serde(default)]
+    task_threads: Vec<TaskThread>,
+    #[serde(default)]
+    active_thread_id: String,
+    #[serde(default)]
+    supervisor: SupervisorState,
+    #[serde(default)]
+    reliability: ReliabilityState,
+    #[serde(default = "default_autonomy_mode")]
+    autonomy_mode: AutonomyMode,
+    #[serde(default)]
+    autonomy_calibration: AutonomyCalibrationState,
+    /// Overrides the preset's approvals and autonomy per task class and thread.
+    #[serde(default)]
+    supervision: supervision::SupervisionPolicy,
+    #[serde(default)]
+    preset: Option<OnboardingPreset>,
+    #[serde(default)]
+    notifications: NotifyLevel,
+}
 
-    println!("\n{}", ui_title("Capability Registry"));
-    println!("  file         : {}", ui_dim(&registry_path));
-    println!("  generated_at : {}", generated);
-    println!(
-        "  platform     : {} | kernel {} | workspace {}",
-        distro, kernel, workspace
-    );
-    println!("  downloads    : {}", downloads);
-    println!(
-        "  backends     : wallpaper=[{}] screenshot=[{}] input=[{}]",
-        wallpaper.join(", "),
-        screenshot.join(", "),
-        input.join(", ")
-    );
-    println!(
-        "  launcher cmds: {}",
-        truncate_for_table(&launchers.join(", "), 92)
-    );
-    println!(
-        "  preferred    : {}",
-        truncate_for_table(&preferred_launchers.join(", "), 92)
-    );
-    println!();
+impl Default for AgentOsState {
+    fn default() -> Self {
+        Self {
+            onboarding: OnboardingState::default(),
+            soul_auto: false,
+            task_threads: vec![TaskThread::new("task-1".to_string(), "Main".to_string())],
+            active_thread_id: "task-1".to_string(),
+            supervisor: SupervisorState::default(),
+            reliability: ReliabilityState::default(),
+            autonomy_mode: default_autonomy_mode(),
+            autonomy_calibration: AutonomyCalibrationState::default(),
+            supervision: supervision::SupervisionPolicy::default(),
+            preset: None,
+            notifications: NotifyLevel::default(),
+        }
+    }
 }
 
-fn capability_registry_diff_lines(old_registry: &Value, new_registry: &Value) -> Vec<String> {
-    let old_distro = old_registry
-        .pointer("/platform/distro_name")
-        .and_then(|v| v.as_str())
-        .unwrap_or("unknown");
-    let new_distro = new_registry
-        .pointer("/platform/distro_name")
-        .and_then(|v| v.as_str())
-        .unwrap_or("unknown");
-    let old_kernel = old_registry
-        .pointer("/platform/kernel")
-        .and_then(|v| v.as_str())
-        .unwrap_or("unknown");
-    let new_kernel = new_registry
-        .pointer("/platform/kernel")
-        .and_then(|v| v.as_str())
-        .unwrap_or("unknown");
-    let old_workspace = old_registry
-        .pointer("/platform/active_workspace")
-        .and_then(|v| v.as_u64())
-        .unwrap_or(0);
-    let new_workspace = new_registry
-        .pointer("/platform/active_workspace")
-        .and_then(|v| v.as_u64())
-        .unwrap_or(0);
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ReliabilityState {
+    #[serde(default)]
+    run_id: u64,
+    #[serde(default)]
+    last_stage: String,
+    #[serde(default)]
+    fallback_attempts: u32,
+    #[serde(default)]
+    last_duration_ms: u64,
+    #[serde(default)]
+    last_error: String,
+    #[serde(default)]
+    last_break_reason: String,
+    #[serde(default)]
+    updated_at: Option<i64>,
+    /// Average tool latencies carried across restarts.
+    #[serde(default)]
+    tool_latency: BTreeMap<String, hypr_claw_runtime::metrics::ToolLatency>,
+}
 
-    let old_wallpaper =
-        read_string_array_from_value(old_registry.pointer("/capabilities/wallpaper_backends"));
-    let new_wallpaper =
-        read_string_array_from_value(new_registry.pointer("/capabilities/wallpaper_backends"));
-    let old_screenshot =
-        read_string_array_from_value(old_registry.pointer("/capabilities/screenshot_backends"));
-    let new_screenshot =
-        read_string_array_from_value(new_registry.pointer("/capabilities/screenshot_backends"));
-    let old_input =
-        read_string_array_from_value(old_registry.pointer("/capabilities/input_backends"));
-    let new_input =
-        read_string_array_from_value(new_registry.pointer("/capabilities/input_backends"));
-
-    let old_launchers =
-        read_string_array_from_value(old_registry.pointer("/desktop_apps/launcher_commands"));
-    let new_launchers =
-        read_string_array_from_value(new_registry.pointer("/desktop_apps/launcher_commands"));
-    let old_projects = read_string_array_from_value(old_registry.pointer("/projects/known_roots"));
-    let new_projects = read_string_array_from_value(new_registry.pointer("/projects/known_roots"));
-    let old_vscode = old_registry
-        .pointer("/editor/vscode_command")
-        .and_then(|v| v.as_str())
-        .unwrap_or("none");
-    let new_vscode = new_registry
-        .pointer("/editor/vscode_command")
-        .and_then(|v| v.as_str())
-        .unwrap_or("none");
-
-    let mut changes = Vec::<String>::new();
-    if old_distro != new_distro {
-        changes.push(format!(
-            "platform distro: '{}' -> '{}'",
-            old_distro, new_distro
-        ));
-    }
-    if old_kernel != new_kernel {
-        changes.push(format!(
-            "platform kernel: '{}' -> '{}'",
-            old_kernel, new_kernel
-        ));
-    }
-    if old_workspace != new_workspace {
-        changes.push(format!(
-            "active workspace: {} -> {}",
-            old_workspace, new_workspace
-        ));
-    }
-    if old_wallpaper != new_wallpaper {
-        changes.push(format!(
-            "wallpaper backends: [{}] -> [{}]",
-            old_wallpaper.join(", "),
-            new_wallpaper.join(", ")
-        ));
-    }
-    if old_screenshot != new_screenshot {
-        changes.push(format!(
-            "screenshot backends: [{}] -> [{}]",
-            old_screenshot.join(", "),
-            new_screenshot.join(", ")
-        ));
-    }
-    if old_input != new_input {
-        changes.push(format!(
-            "input backends: [{}] -> [{}]",
-            old_input.join(", "),
-            new_input.join(", ")
-        ));
-    }
-    if old_launchers != new_launchers {
-        changes.push(format!(
-            "launcher commands changed ({} -> {} entries)",
-            old_launchers.len(),
-            new_launchers.len()
-        ));
-    }
-    if old_projects != new_projects {
-        changes.push(format!(
-            "known project roots changed ({} -> {} entries)",
-            old_projects.len(),
-            new_projects.len()
-        ));
-    }
-    if old_vscode != new_vscode {
-        changes.push(format!(
-            "vscode command: '{}' -> '{}'",
-            old_vscode, new_vscode
-        ));
+impl Default for ReliabilityState {
+    fn default() -> Self {
+        Self {
+            run_id: 0,
+            last_stage: "idle".to_string(),
+            fallback_attempts: 0,
+            last_duration_ms: 0,
+            last_error: String::new(),
+            last_break_reason: String::new(),
+            updated_at: None,
+            tool_latency: BTreeMap::new(),
+        }
     }
+}
 
-    changes
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum AutonomyMode {
+    PromptFirst,
+    Guarded,
 }
 
-fn print_capability_registry_diff_summary(old_registry: &Value, new_registry: &Value) {
-    let changes = capability_registry_diff_lines(old_registry, new_registry);
-    println!("\n{}", ui_title("Scan Diff Summary"));
-    if changes.is_empty() {
-        println!("  no major capability changes detected");
-    } else {
-        for change in changes {
-            println!("  - {}", truncate_for_table(&change, 104));
+impl AutonomyMode {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::PromptFirst => "prompt_first",
+            Self::Guarded => "guarded",
         }
     }
-    println!();
 }
 
-fn next_thread_id(state: &AgentOsState) -> String {
-    let max_id = state
-        .task_threads
-        .iter()
-        .filter_map(|thread| thread.id.strip_prefix("task-"))
-        .filter_map(|suffix| suffix.parse::<u32>().ok())
-        .max()
-        .unwrap_or(0);
-    format!("task-{}", max_id + 1)
+fn default_autonomy_mode() -> AutonomyMode {
+    AutonomyMode::PromptFirst
 }
 
-#[derive(Debug, Clone, Copy)]
-struct ExecutionBudget {
-    max_iterations: usize,
+/// Which background task outcomes raise a desktop notification.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+enum NotifyLevel {
+    All,
+    Failures,
+    #[default]
+    Off,
 }
 
-fn classify_supervised_task_class(input: &str) -> SupervisedTaskClass {
-    let lower = input.to_lowercase();
-    let investigation_tokens = [
-        "diagnose",
-        "why",
-        "analyze",
-        "investigate",
-        "debug",
-        "compare",
-        "inspect",
-        "check",
-        "status",
-        "trace",
-        "benchmark",
-    ];
-    if investigation_tokens
-        .iter()
-        .any(|token| lower.contains(token))
-    {
-        return SupervisedTaskClass::Investigation;
+impl NotifyLevel {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::All => "all",
+            Self::Failures => "failures",
+            Self::Off => "off",
+        }
     }
 
-    let action_tokens = [
-        "open",
-        "create",
-        "delete",
-        "remove",
-        "move",
-        "copy",
-        "write",
-        "edit",
-        "run",
-        "build",
-        "install",
-        "switch",
-        "focus",
-        "close",
-        "search",
-        "reply",
-        "send",
-        "play",
-        "pause",
-        "lockscreen",
-        "wallpaper",
-        "volume",
-    ];
-    if action_tokens.iter().any(|token| lower.contains(token)) {
-        return SupervisedTaskClass::Action;
+    fn wants(&self, succeeded: bool) -> bool {
+        match self {
+            Self::All => true,
+            Self::Failures => !succeeded,
+            Self::Off => false,
+        }
     }
+}
 
-    SupervisedTaskClass::Question
+/// Safety posture picked at onboarding and changed with `preset switch`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum OnboardingPreset {
+    Cautious,
+    Balanced,
+    PowerUser,
 }
 
-fn infer_supervised_task_resources(input: &str) -> Vec<String> {
-    let lower = input.to_lowercase();
-    let mut resources = Vec::<String>::new();
+impl OnboardingPreset {
+    const ALL: [Self; 3] = [Self::Cautious, Self::Balanced, Self::PowerUser];
 
-    let add = |name: &str, resources: &mut Vec<String>| {
-        if !resources.iter().any(|existing| existing == name) {
-            resources.push(name.to_string());
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Cautious => "cautious",
+            Self::Balanced => "balanced",
+            Self::PowerUser => "power_user",
         }
-    };
+    }
 
-    if [
-        "open",
-        "launch",
-        "switch workspace",
-        "workspace",
-        "window",
-        "click",
-        "type",
-        "press",
-        "key",
-        "mouse",
-        "screen",
-        "ocr",
-        "telegram",
-        "gmail",
-        "mail",
-        "browser",
-    ]
-    .iter()
-    .any(|token| lower.contains(token))
-    {
-        add("desktop_input", &mut resources);
+    /// Accepts the name (`power user`, `power-user`, `power`) or its 1-based
+    /// position in the onboarding list.
+    fn parse(raw: &str) -> Option<Self> {
+        let normalized = raw.trim().to_ascii_lowercase().replace(['-', ' '], "_");
+        match normalized.as_str() {
+            "1" | "cautious" => Some(Self::Cautious),
+            "2" | "balanced" => Some(Self::Balanced),
+            "3" | "power_user" | "power" => Some(Self::PowerUser),
+            _ => None,
+        }
     }
 
-    if [
-        "file", "folder", "dir", "read", "write", "create", "delete", "copy", "move", "project",
-    ]
-    .iter()
-    .any(|token| lower.contains(token))
-    {
-        add("filesystem", &mut resources);
+    fn description(&self) -> &'static str {
+        match self {
+            Self::Cautious => "approve every file, process and system change; basic scan; notify on all background results",
+            Self::Balanced => "approve system-critical actions; deep scan; notify on background failures",
+            Self::PowerUser => "approve system-critical actions; guarded autonomy with fallbacks; deep scan; no notifications",
+        }
     }
 
-    if [
-        "web", "search", "download", "upload", "api", "http", "gmail", "telegram",
-    ]
-    .iter()
-    .any(|token| lower.contains(token))
-    {
-        add("network", &mut resources);
+    /// Lowest permission tier that asks before running.
+    fn approval_tier(&self) -> hypr_claw_tools::PermissionTier {
+        match self {
+            Self::Cautious => hypr_claw_tools::PermissionTier::Write,
+            Self::Balanced | Self::PowerUser => hypr_claw_tools::PermissionTier::SystemCritical,
+        }
     }
 
-    if [
-        "run",
-        "build",
-        "compile",
-        "install",
-        "process",
-        "spawn",
-        "kill",
-        "benchmark",
-        "test",
-    ]
-    .iter()
-    .any(|token| lower.contains(token))
-    {
-        add("compute", &mut resources);
+    fn autonomy_mode(&self) -> AutonomyMode {
+        match self {
+            Self::Cautious | Self::Balanced => AutonomyMode::PromptFirst,
+            Self::PowerUser => AutonomyMode::Guarded,
+        }
     }
 
-    if resources.is_empty() {
-        add("general", &mut resources);
+    fn notifications(&self) -> NotifyLevel {
+        match self {
+            Self::Cautious => NotifyLevel::All,
+            Self::Balanced => NotifyLevel::Failures,
+            Self::PowerUser => NotifyLevel::Off,
+        }
     }
 
-    resources
+    fn deep_scan(&self) -> bool {
+        !matches!(self, Self::Cautious)
+    }
 }
 
-fn can_run_supervisor_in_background(task: &SupervisedTask) -> bool {
-    !task.resources.iter().any(|resource| {
-        matches!(
-            resource.as_str(),
-            "desktop_input" | "filesystem" | "general"
-        )
-    })
-}
-
-fn resource_is_shared(resource: &str) -> bool {
-    matches!(resource, "network" | "compute")
+fn apply_preset(state: &mut AgentOsState, preset: OnboardingPreset) {
+    state.preset = Some(preset);
+    state.autonomy_mode = preset.autonomy_mode();
+    state.notifications = preset.notifications();
 }
 
-fn resource_conflicts(lhs: &[String], rhs: &[String]) -> Vec<String> {
-    let left = lhs
-        .iter()
-        .map(|r| r.trim().to_lowercase())
-        .filter(|r| !r.is_empty())
-        .collect::<HashSet<_>>();
-    let right = rhs
-        .iter()
-        .map(|r| r.trim().to_lowercase())
-        .filter(|r| !r.is_empty())
-        .collect::<HashSet<_>>();
-
-    if left.is_empty() || right.is_empty() {
-        return Vec::new();
-    }
-    if left.contains("general") || right.contains("general") {
-        return vec!["general".to_string()];
-    }
-
-    let mut conflicts = BTreeSet::new();
-    for resource in left.intersection(&right) {
-        if !resource_is_shared(resource) {
-            conflicts.insert(resource.to_string());
-        }
+fn approval_tier_label(tier: hypr_claw_tools::PermissionTier) -> &'static str {
+    match tier {
+        hypr_claw_tools::PermissionTier::Read => "every action",
+        hypr_claw_tools::PermissionTier::Write => "file, process and system changes",
+        hypr_claw_tools::PermissionTier::Execute => "process and system actions",
+        hypr_claw_tools::PermissionTier::SystemCritical => "system-critical actions only",
     }
-    conflicts.into_iter().collect::<Vec<_>>()
 }
 
-fn running_supervisor_conflicts_for_resources(
-    required: &[String],
-    running: &[(String, Vec<String>)],
-) -> Vec<String> {
-    let mut details = Vec::new();
-    for (task_id, resources) in running {
-        let overlap = resource_conflicts(required, resources);
-        if !overlap.is_empty() {
-            details.push(format!(
-                "{} [{}]",
-                task_id,
-                truncate_for_table(&overlap.join("+"), 28)
-            ));
-        }
+fn print_preset(state: &AgentOsState, approval_tier: hypr_claw_tools::PermissionTier) {
+    println!(
+        "Preset: {}",
+        state.preset.map(|p| p.as_str()).unwrap_or("none")
+    );
+    println!("  approvals: {}", approval_tier_label(approval_tier));
+    println!("  autonomy: {}", state.autonomy_mode.as_str());
+    println!("  notifications: {}", state.notifications.as_str());
+    if let Some(preset) = state.preset {
+        println!(
+            "  scan depth: {}",
+            if preset.deep_scan() { "deep" } else { "basic" }
+        );
     }
-    details
+    println!(
+        "Switch with: preset switch <{}>",
+        OnboardingPreset::ALL.map(|p| p.as_str()).join("|")
+    );
 }
 
-fn execution_budget_for_class(class: &SupervisedTaskClass, mode: &AutonomyMode) -> ExecutionBudget {
-    match mode {
-        AutonomyMode::PromptFirst => match class {
-            SupervisedTaskClass::Question => ExecutionBudget { max_iterations: 14 },
-            SupervisedTaskClass::Action => ExecutionBudget { max_iterations: 28 },
-            SupervisedTaskClass::Investigation => ExecutionBudget { max_iterations: 40 },
-        },
-        AutonomyMode::Guarded => match class {
-            SupervisedTaskClass::Question => ExecutionBudget { max_iterations: 8 },
-            SupervisedTaskClass::Action => ExecutionBudget { max_iterations: 16 },
-            SupervisedTaskClass::Investigation => ExecutionBudget { max_iterations: 24 },
-        },
-    }
+/// Applies the supervision policy for a run of `class` in the active thread to
+/// the approval gate's rules for `session_key`. Returns the run's autonomy
+/// mode and the supervision the policy picked, if a rule matched.
+fn apply_supervision(
+    state: &AgentOsState,
+    gate: &hypr_claw_tools::approvals::PlanApprovalGate,
+    session_key: &str,
+    class: &SupervisedTaskClass,
+) -> (AutonomyMode, Option<supervision::Supervision>) {
+    supervise_run(
+        &state.supervision,
+        &state.autonomy_mode,
+        gate,
+        session_key,
+        &state.active_thread_id,
+        class,
+    )
 }
 
-fn watchdog_timeout_for_class(class: &SupervisedTaskClass, mode: &AutonomyMode) -> Duration {
-    match mode {
-        AutonomyMode::PromptFirst => match class {
-            SupervisedTaskClass::Question => Duration::from_secs(60),
-            SupervisedTaskClass::Action => Duration::from_secs(120),
-            SupervisedTaskClass::Investigation => Duration::from_secs(210),
-        },
-        AutonomyMode::Guarded => match class {
-            SupervisedTaskClass::Question => Duration::from_secs(45),
-            SupervisedTaskClass::Action => Duration::from_secs(90),
-            SupervisedTaskClass::Investigation => Duration::from_secs(150),
-        },
-    }
+fn supervise_run(
+    policy: &supervision::SupervisionPolicy,
+    default_mode: &AutonomyMode,
+    gate: &hypr_claw_tools::approvals::PlanApprovalGate,
+    session_key: &str,
+    thread: &str,
+    class: &SupervisedTaskClass,
+) -> (AutonomyMode, Option<supervision::Supervision>) {
+    let level = policy
+        .resolve(thread, class.as_str())
+        .map(|rule| rule.level);
+    gate.supervise(session_key, level.map(|level| level.session()));
+    let mode = match level {
+        Some(supervision::Supervision::Autonomous) => AutonomyMode::Guarded,
+        Some(_) => AutonomyMode::PromptFirst,
+        None => default_mode.clone(),
+    };
+    (mode, level)
 }
 
-fn next_supervised_task_id(state: &mut AgentOsState) -> String {
-    let id = format!("sup-{}", state.supervisor.next_id);
-    state.supervisor.next_id += 1;
-    id
+/// What a REPL run is set up from. Taken before each foreground run and
+/// kept by [`SideRuns`], so a run started in another thread meanwhile gets
+/// that thread's supervision and the same project confinement.
+#[derive(Debug, Clone)]
+struct RunSetup {
+    supervision: supervision::SupervisionPolicy,
+    autonomy_mode: AutonomyMode,
+    /// The soul's prompt, or the project's inside a project.
+    soul_prompt: String,
+    /// Runtime context rendered for the foreground run.
+    runtime_context: String,
+    /// Roots file arguments are confined to inside a project.
+    roots: Option<Vec<std::path::PathBuf>>,
+}
+
+impl RunSetup {
+    /// Applies supervision and confinement for a run of `class` in `thread`
+    /// to the gate's rules for `session_key`. Returns the run's autonomy
+    /// mode and system prompt.
+    fn prepare(
+        &self,
+        gate: &hypr_claw_tools::approvals::PlanApprovalGate,
+        session_key: &str,
+        thread: &str,
+        class: &SupervisedTaskClass,
+    ) -> (AutonomyMode, String) {
+        let (mode, level) = supervise_run(
+            &self.supervision,
+            &self.autonomy_mode,
+            gate,
+            session_key,
+            thread,
+            class,
+        );
+        gate.confine(session_key, self.roots.clone());
+        let prompt = with_supervision_instructions(
+            system_prompt_with_context(&self.soul_prompt, &self.runtime_context, &mode),
+            level,
+        );
+        (mode, prompt)
+    }
 }
 
-fn start_supervised_task(
-    state: &mut AgentOsState,
+fn with_supervision_instructions(
     prompt: String,
-    class: SupervisedTaskClass,
+    level: Option<supervision::Supervision>,
 ) -> String {
-    let now = chrono::Utc::now().timestamp();
-    let id = next_supervised_task_id(state);
-    let resources = infer_supervised_task_resources(&prompt);
-    state.supervisor.tasks.push(SupervisedTask {
-        id: id.clone(),
-        prompt,
-        class,
-        resources,
-        background_task_id: None,
-        status: SupervisedTaskStatus::Running,
-        created_at: now,
-        updated_at: now,
-        error: None,
-    });
-    id
+    match level.and_then(|level| level.instructions()) {
+        Some(instructions) => format!("{prompt}\n{instructions}"),
+        None => prompt,
+    }
 }
 
-fn enqueue_supervised_task(
-    state: &mut AgentOsState,
-    prompt: String,
-    class: SupervisedTaskClass,
-) -> String {
-    let now = chrono::Utc::now().timestamp();
-    let id = next_supervised_task_id(state);
-    let resources = infer_supervised_task_resources(&prompt);
-    state.supervisor.tasks.push(SupervisedTask {
-        id: id.clone(),
-        prompt,
-        class,
-        resources,
-        background_task_id: None,
-        status: SupervisedTaskStatus::Queued,
-        created_at: now,
-        updated_at: now,
-        error: None,
+fn print_supervision_policy(state: &AgentOsState, approval_tier: hypr_claw_tools::PermissionTier) {
+    println!("Supervision policy (most specific first):");
+    let rules = state.supervision.rules();
+    if rules.is_empty() {
+        println!("  (no rules)");
+    }
+    for rule in rules {
+        println!("  {:<36} {}", rule.scope.describe(), rule.level.as_str());
+    }
+    println!(
+        "  otherwise the {} preset: approvals for {}, autonomy {}",
+        state.preset.map(|p| p.as_str()).unwrap_or("default"),
+        approval_tier_label(approval_tier),
+        state.autonomy_mode.as_str()
+    );
+    println!("In thread {}:", state.active_thread_id);
+    for class in SupervisedTaskClass::ALL {
+        let level = state
+            .supervision
+            .resolve(&state.active_thread_id, class.as_str())
+            .map(|rule| rule.level.as_str())
+            .unwrap_or("preset");
+        println!("  {:<14} {}", class.as_str(), level);
+    }
+    println!("{}", supervision::USAGE);
+}
+
+/// Desktop notification for a finished background task, if the user's
+/// notification level asks for it.
+fn notify_background_outcome(level: NotifyLevel, succeeded: bool, task_id: &str, detail: &str) {
+    if !level.wants(succeeded) {
+        return;
+    }
+    let summary = if succeeded {
+        "Background task finished"
+    } else {
+        "Background task failed"
+    };
+    let body = format!("{} {}", task_id, truncate_for_table(detail, 80));
+    tokio::spawn(async move {
+        let _ = hypr_claw_tools::os_capabilities::system::notify(summary, &body).await;
     });
-    id
 }
 
-enum QueueStartResult {
-    Started(SupervisedTask),
-    Blocked(String),
-    Empty,
+fn strict_workflow_enabled() -> bool {
+    std::env::var("HYPR_CLAW_STRICT_WORKFLOW")
+        .ok()
+        .map(|raw| {
+            !matches!(
+                raw.trim().to_ascii_lowercase().as_str(),
+                "0" | "false" | "off"
+            )
+        })
+        .unwrap_or(true)
 }
 
-fn start_next_queued_supervised_task(state: &mut AgentOsState) -> QueueStartResult {
-    let running = state
-        .supervisor
-        .tasks
-        .iter()
-        .filter(|task| task.status == SupervisedTaskStatus::Running)
-        .map(|task| (task.id.clone(), task.resources.clone()))
-        .collect::<Vec<_>>();
-
-    let now = chrono::Utc::now().timestamp();
-    let mut blocked_reason: Option<String> = None;
-    for task in state.supervisor.tasks.iter_mut() {
-        if task.status != SupervisedTaskStatus::Queued {
-            continue;
-        }
-        let conflicts = running_supervisor_conflicts_for_resources(&task.resources, &running);
-        if conflicts.is_empty() {
-            task.status = SupervisedTaskStatus::Running;
-            task.updated_at = now;
-            task.background_task_id = None;
-            return QueueStartResult::Started(task.clone());
-        }
-        if blocked_reason.is_none() {
-            blocked_reason = Some(format!(
-                "{} waiting on {} (conflict resources: {})",
-                task.id,
-                conflicts.join(", "),
-                truncate_for_table(&task.resources.join(","), 28)
-            ));
-        }
-    }
-    if let Some(reason) = blocked_reason {
-        return QueueStartResult::Blocked(reason);
-    }
-    QueueStartResult::Empty
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+struct AutonomyModeMetrics {
+    #[serde(default)]
+    runs: u64,
+    #[serde(default)]
+    successes: u64,
+    #[serde(default)]
+    failures: u64,
+    #[serde(default)]
+    total_duration_ms: u64,
+    #[serde(default)]
+    total_fallback_attempts: u64,
+    #[serde(default)]
+    stop_codes: BTreeMap<String, u64>,
 }
 
-fn mark_supervised_task_completed(state: &mut AgentOsState, task_id: &str) {
-    let now = chrono::Utc::now().timestamp();
-    if let Some(task) = state
-        .supervisor
-        .tasks
-        .iter_mut()
-        .find(|task| task.id == task_id)
-    {
-        task.status = SupervisedTaskStatus::Completed;
-        task.updated_at = now;
-        task.error = None;
-        task.background_task_id = None;
-    }
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+struct AutonomyCalibrationState {
+    #[serde(default)]
+    prompt_first: AutonomyModeMetrics,
+    #[serde(default)]
+    guarded: AutonomyModeMetrics,
 }
 
-fn mark_supervised_task_failed(state: &mut AgentOsState, task_id: &str, error: String) {
-    let now = chrono::Utc::now().timestamp();
-    if let Some(task) = state
-        .supervisor
-        .tasks
-        .iter_mut()
-        .find(|task| task.id == task_id)
-    {
-        task.status = SupervisedTaskStatus::Failed;
-        task.updated_at = now;
-        task.error = Some(error);
-        task.background_task_id = None;
-    }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OnboardingState {
+    #[serde(default)]
+    completed: bool,
+    #[serde(default)]
+    preferred_name: String,
+    #[serde(default)]
+    profile_confirmed: bool,
+    #[serde(default = "empty_json")]
+    system_profile: Value,
+    #[serde(default)]
+    last_scan_at: Option<i64>,
+    #[serde(default)]
+    deep_scan_completed: bool,
+    #[serde(default)]
+    trusted_full_auto: bool,
 }
 
-fn mark_supervised_task_cancelled(state: &mut AgentOsState, task_id: &str, reason: Option<String>) {
-    let now = chrono::Utc::now().timestamp();
-    if let Some(task) = state
-        .supervisor
-        .tasks
-        .iter_mut()
-        .find(|task| task.id == task_id)
-    {
-        task.status = SupervisedTaskStatus::Cancelled;
-        task.updated_at = now;
-        task.error = reason;
-        task.background_task_id = None;
+impl Default for OnboardingState {
+    fn default() -> Self {
+        Self {
+            completed: false,
+            preferred_name: String::new(),
+            profile_confirmed: false,
+            system_profile: json!({}),
+            last_scan_at: None,
+            deep_scan_completed: false,
+            trusted_full_auto: false,
+        }
     }
 }
 
-fn supervised_task_status(state: &AgentOsState, task_id: &str) -> Option<SupervisedTaskStatus> {
-    state
-        .supervisor
-        .tasks
-        .iter()
-        .find(|task| task.id == task_id)
-        .map(|task| task.status.clone())
-}
-
-fn resolve_supervisor_task<'a>(
-    target: &str,
-    agent_state: &'a AgentOsState,
-) -> Option<&'a SupervisedTask> {
-    agent_state
-        .supervisor
-        .tasks
-        .iter()
-        .find(|task| task.id == target || task.background_task_id.as_deref() == Some(target))
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TaskThread {
+    id: String,
+    title: String,
+    archived: bool,
+    created_at: i64,
+    updated_at: i64,
+    /// Title was derived from the thread's first exchange.
+    #[serde(default)]
+    titled: bool,
+    /// Variables set for processes the thread's runs start.
+    #[serde(default)]
+    env: BTreeMap<String, String>,
 }
 
-fn print_supervisor_task_inspect(task: &SupervisedTask) {
-    println!("Supervisor task: {}", task.id);
-    println!("  prompt: {}", truncate_for_table(&task.prompt, 72));
-    println!("  class: {}", task.class.as_str());
-    println!(
-        "  status: {}",
-        match &task.status {
-            SupervisedTaskStatus::Queued => "queued",
-            SupervisedTaskStatus::Running => "running",
-            SupervisedTaskStatus::Completed => "completed",
-            SupervisedTaskStatus::Failed => "failed",
-            SupervisedTaskStatus::Cancelled => "cancelled",
+impl TaskThread {
+    fn new(id: String, title: String) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        Self {
+            id,
+            title,
+            archived: false,
+            created_at: now,
+            updated_at: now,
+            titled: false,
+            env: BTreeMap::new(),
         }
-    );
-    if !task.resources.is_empty() {
-        println!("  resources: {}", task.resources.join(", "));
-    }
-    if let Some(bg) = &task.background_task_id {
-        println!("  background_task_id: {}", bg);
-    }
-    println!("  created_at: {}", task.created_at);
-    println!("  updated_at: {}", task.updated_at);
-    if let Some(err) = &task.error {
-        println!("  error: {}", err);
     }
 }
 
-fn collect_supervisor_task_events(
-    task: &SupervisedTask,
-    task_event_feed: &Arc<Mutex<Vec<String>>>,
-    limit: usize,
-) -> Vec<String> {
-    let mut out: Vec<String> = Vec::new();
-    if let Ok(events) = task_event_feed.lock() {
-        for line in events.iter() {
-            let mention = line.contains(&task.id)
-                || task
-                    .background_task_id
-                    .as_ref()
-                    .map_or(false, |bg| line.contains(bg.as_str()));
-            if mention {
-                out.push(line.clone());
-            }
-        }
-    }
-    let len = out.len();
-    if len <= limit {
-        out
-    } else {
-        out.into_iter().skip(len - limit).collect()
+/// Title produced by the model for a thread, replacing `placeholder`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GeneratedTitle {
+    thread_id: String,
+    placeholder: String,
+    title: String,
+}
+
+const THREAD_TITLE_MAX_CHARS: usize = 48;
+
+/// Few-word title taken straight from the request, used until (or instead
+/// of) a generated one.
+fn fallback_thread_title(request: &str) -> String {
+    let words: Vec<&str> = request.split_whitespace().take(6).collect();
+    let mut title = truncate_for_table(&words.join(" "), THREAD_TITLE_MAX_CHARS);
+    if let Some(first) = title.chars().next() {
+        title.replace_range(..first.len_utf8(), &first.to_uppercase().to_string());
     }
+    title
 }
 
-fn print_supervisor_task_events(task: &SupervisedTask, event_rows: &[String], _limit: usize) {
-    println!(
-        "Events for supervisor task {} ({} entries):",
-        task.id,
-        event_rows.len()
-    );
-    for row in event_rows {
-        println!("  {}", row);
+/// First line of a model reply, stripped of quotes, labels and trailing
+/// punctuation. `None` if nothing usable is left.
+fn clean_generated_title(raw: &str) -> Option<String> {
+    let line = raw.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let line = line
+        .strip_prefix("Title:")
+        .or_else(|| line.strip_prefix("title:"))
+        .unwrap_or(line);
+    let title = line
+        .trim()
+        .trim_matches(|c: char| matches!(c, '"' | '\'' | '`' | '*' | '#'))
+        .trim_end_matches(['.', '!', ':'])
+        .trim();
+    if title.is_empty() || title.split_whitespace().count() > 10 {
+        return None;
     }
+    Some(truncate_for_table(title, THREAD_TITLE_MAX_CHARS))
 }
 
-fn set_supervised_task_background_id(
-    state: &mut AgentOsState,
-    task_id: &str,
-    background_task_id: Option<String>,
-) {
-    if let Some(task) = state
-        .supervisor
-        .tasks
+/// Give the active thread a placeholder title from `request` if it has
+/// none yet. Returns the placeholder so a generated title can replace it.
+fn title_untitled_thread(state: &mut AgentOsState, request: &str) -> Option<String> {
+    let thread = state
+        .task_threads
         .iter_mut()
-        .find(|task| task.id == task_id)
-    {
-        task.background_task_id = background_task_id;
-        task.updated_at = chrono::Utc::now().timestamp();
+        .find(|thread| thread.id == state.active_thread_id && !thread.titled)?;
+    let placeholder = fallback_thread_title(request);
+    if placeholder.is_empty() {
+        return None;
     }
+    thread.title = placeholder.clone();
+    thread.titled = true;
+    Some(placeholder)
 }
 
-fn cancel_queued_supervised_tasks(state: &mut AgentOsState) -> usize {
-    let now = chrono::Utc::now().timestamp();
-    let mut changed = 0usize;
-    for task in state.supervisor.tasks.iter_mut() {
-        if task.status == SupervisedTaskStatus::Queued {
-            task.status = SupervisedTaskStatus::Cancelled;
-            task.updated_at = now;
-            task.background_task_id = None;
-            changed += 1;
+/// Apply a generated title unless the thread was renamed in the meantime.
+fn apply_generated_title(state: &mut AgentOsState, generated: GeneratedTitle) -> bool {
+    match state
+        .task_threads
+        .iter_mut()
+        .find(|thread| thread.id == generated.thread_id)
+    {
+        Some(thread) if thread.title == generated.placeholder => {
+            thread.title = generated.title;
+            true
         }
+        _ => false,
     }
-    changed
 }
 
-fn prune_supervisor_tasks(state: &mut AgentOsState, keep_terminal: usize) -> usize {
-    let mut terminal = state
-        .supervisor
-        .tasks
-        .iter()
-        .filter(|task| {
-            matches!(
-                task.status,
-                SupervisedTaskStatus::Completed
-                    | SupervisedTaskStatus::Failed
-                    | SupervisedTaskStatus::Cancelled
-            )
-        })
-        .map(|task| (task.id.clone(), task.updated_at))
-        .collect::<Vec<_>>();
+/// What happened in the most recent foreground run, kept for `explain`
+/// and `triage`.
+#[derive(Debug, Clone, Default)]
+struct RunReport {
+    run_id: u64,
+    thread_id: String,
+    request: String,
+    task_class: String,
+    stop_code: String,
+    error: String,
+    hint: String,
+    duration_ms: u64,
+    recovery_notes: Vec<String>,
+    tool_events: Vec<String>,
+    attempt: Option<String>,
+}
 
-    if terminal.len() <= keep_terminal {
-        return 0;
-    }
+const EXPLAIN_SYSTEM_PROMPT: &str = "You explain agent runs to the person who requested them. From the run log, write a short plain-language narrative: what was attempted, in order; what failed and the most likely reason; and what the user could do next. Do not invent steps that are not in the log. No more than 150 words.";
 
-    terminal.sort_by(|a, b| b.1.cmp(&a.1));
-    let keep_ids = terminal
-        .into_iter()
-        .take(keep_terminal)
-        .map(|(id, _)| id)
-        .collect::<HashSet<_>>();
+impl RunReport {
+    fn succeeded(&self) -> bool {
+        self.stop_code == "STOP_NONE"
+    }
 
-    let before = state.supervisor.tasks.len();
-    state.supervisor.tasks.retain(|task| {
-        if matches!(
-            task.status,
-            SupervisedTaskStatus::Queued | SupervisedTaskStatus::Running
-        ) {
-            true
+    /// Plain-text event log handed to the model.
+    fn render(&self) -> String {
+        let mut lines = vec![
+            format!("Request: {}", self.request),
+            format!("Task class: {}", self.task_class),
+            format!(
+                "Outcome: {} after {}ms",
+                if self.succeeded() {
+                    "completed"
+                } else {
+                    "failed"
+                },
+                self.duration_ms
+            ),
+            format!("Stop code: {}", self.stop_code),
+        ];
+        if !self.error.is_empty() {
+            lines.push(format!("Error: {}", self.error));
+        }
+        if !self.hint.is_empty() {
+            lines.push(format!("Suggested next step: {}", self.hint));
+        }
+        if self.tool_events.is_empty() {
+            lines.push("Tool events: none".to_string());
         } else {
-            keep_ids.contains(&task.id)
+            lines.push("Tool events:".to_string());
+            lines.extend(self.tool_events.iter().map(|row| format!("  {row}")));
         }
-    });
-    before.saturating_sub(state.supervisor.tasks.len())
+        if !self.recovery_notes.is_empty() {
+            lines.push("Recovery attempts:".to_string());
+            lines.extend(self.recovery_notes.iter().map(|note| format!("  {note}")));
+        }
+        if let Some(attempt) = &self.attempt {
+            lines.push(format!("Final attempt:\n{attempt}"));
+        }
+        lines.join("\n")
+    }
 }
 
-fn reconcile_supervisor_after_restart(state: &mut AgentOsState) -> usize {
-    let now = chrono::Utc::now().timestamp();
-    let mut recovered = 0usize;
-    for task in state.supervisor.tasks.iter_mut() {
-        if task.status == SupervisedTaskStatus::Running {
-            task.status = SupervisedTaskStatus::Failed;
-            task.updated_at = now;
-            task.background_task_id = None;
-            task.error =
-                Some("Recovered after restart: previous run did not finish cleanly".to_string());
-            recovered += 1;
+async fn explain_last_run(config: &Config, report: &RunReport) {
+    println!("\n{}", ui_section("Explain"));
+    match ask_model_once(
+        &config.provider,
+        &config.model,
+        EXPLAIN_SYSTEM_PROMPT,
+        report.render(),
+        Duration::from_secs(60),
+    )
+    .await
+    {
+        Ok(narrative) => println!("{}\n", strip_ansi_and_controls(narrative.trim())),
+        Err(e) => {
+            println!("Could not get an explanation from the model ({e}). Raw run log:\n");
+            println!("{}\n", report.render());
         }
     }
-    recovered
 }
 
-fn print_supervisor_queue(state: &AgentOsState) {
-    println!("\n🧰 Supervisor Queue");
-    println!(
-        "  actions: queue status | queue add <prompt> | queue run | queue clear"
-    );
-    if state.supervisor.tasks.is_empty() {
-        println!("  empty");
-        println!();
-        return;
+/// The directory of a `cd` line. `/cd` and a bare `cd` always change
+/// directory; `cd <dir>` only when `<dir>` exists, so a prompt such as
+/// "cd into Downloads and list the PDFs" still reaches the model.
+fn cd_argument(input: &str) -> Option<&str> {
+    let rest = |command: &str| {
+        input
+            .strip_prefix(command)
+            .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+            .map(str::trim)
+    };
+    if let Some(dir) = rest("/cd") {
+        return Some(dir);
     }
+    rest("cd").filter(|dir| dir.is_empty() || cd_target(dir).is_ok_and(|target| target.is_dir()))
+}
 
-    let mut tasks = state.supervisor.tasks.clone();
-    tasks.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
-    for task in tasks.iter().take(20) {
-        let bg = task.background_task_id.as_deref().unwrap_or("-");
-        println!(
-            "  {:<8} {:<13} {:<14} {:<14} {:<12} {}",
-            truncate_for_table(&task.id, 8),
-            format!("{:?}", task.status).to_lowercase(),
-            task.class.as_str(),
-            truncate_for_table(&task.resources.join(","), 14),
-            truncate_for_table(bg, 12),
-            truncate_for_table(&task.prompt, 52)
-        );
-    }
-    if tasks.len() > 20 {
-        println!("  ... and {} more", tasks.len() - 20);
+/// Where `cd dir` goes, `~` and no argument meaning the home directory.
+fn cd_target(dir: &str) -> io::Result<std::path::PathBuf> {
+    let dir = if dir.is_empty() { "~" } else { dir };
+    Ok(match dir.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => std::env::var_os("HOME")
+            .map(std::path::PathBuf::from)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))?
+            .join(rest.trim_start_matches('/')),
+        _ => std::path::PathBuf::from(dir),
+    })
+}
+
+/// `cd` for the session: changes the working directory. Data paths stay
+/// where [`data_dir::pin`] put them.
+fn change_directory(dir: &str) -> io::Result<std::path::PathBuf> {
+    std::env::set_current_dir(cd_target(dir)?)?;
+    std::env::current_dir()
+}
+
+/// Re-reads the project soul for the working directory, announcing when
+/// the session enters or leaves a project.
+fn refresh_project_soul(current: &mut Option<project_soul::ProjectSoul>) {
+    let found = match std::env::current_dir()
+        .map_err(|e| e.to_string())
+        .and_then(|cwd| project_soul::ProjectSoul::discover(&cwd))
+    {
+        Ok(found) => found,
+        Err(e) => {
+            eprintln!("⚠️  Ignoring the project soul: {}", e);
+            None
+        }
+    };
+    let before = current.as_ref().map(|project| &project.root);
+    let after = found.as_ref().map(|project| &project.root);
+    if before != after {
+        if let Some(root) = before {
+            println!("📁 Left {}; the global profile is back.", root.display());
+        }
+        if let Some(project) = &found {
+            let tools = match &project.tools {
+                Some(tools) => format!("{} tool pattern(s)", tools.len()),
+                None => "all tools".to_string(),
+            };
+            println!(
+                "📁 Project soul from {} is active ({}, files confined to the project).",
+                project.root.display(),
+                tools
+            );
+        }
     }
-    println!();
+    *current = found;
 }
 
-fn print_supervisor_queue_status(state: &AgentOsState) {
-    let queued = state
-        .supervisor
-        .tasks
-        .iter()
-        .filter(|task| task.status == SupervisedTaskStatus::Queued)
-        .count();
-    let running = state
-        .supervisor
-        .tasks
-        .iter()
-        .filter(|task| task.status == SupervisedTaskStatus::Running)
-        .count();
-    let completed = state
-        .supervisor
-        .tasks
-        .iter()
-        .filter(|task| task.status == SupervisedTaskStatus::Completed)
-        .count();
-    let failed = state
-        .supervisor
-        .tasks
-        .iter()
-        .filter(|task| task.status == SupervisedTaskStatus::Failed)
-        .count();
-    let cancelled = state
-        .supervisor
-        .tasks
-        .iter()
-        .filter(|task| task.status == SupervisedTaskStatus::Cancelled)
-        .count();
-
-    println!("\n📌 Supervisor Queue Status");
+/// Walks through the checks for a failed run, showing what each finds, and
+/// returns the fixes the user accepted as REPL commands to run next.
+async fn run_triage<S, L, D, R, Sum>(
+    report: &RunReport,
+    events: &[action_log::ActionEvent],
+    state: &AgentOsState,
+    approval_tier: hypr_claw_tools::PermissionTier,
+    config: &Config,
+    agent_loop: &hypr_claw_runtime::AgentLoop<S, L, D, R, Sum>,
+) -> io::Result<Vec<String>>
+where
+    S: hypr_claw_runtime::SessionStore,
+    L: hypr_claw_runtime::LockManager,
+    D: hypr_claw_runtime::ToolDispatcher,
+    R: hypr_claw_runtime::ToolRegistry,
+    Sum: hypr_claw_runtime::Summarizer,
+{
+    println!("\n{}", ui_section("Triage"));
     println!(
-        "  queued={} running={} done={} failed={} cancelled={}",
-        queued,
-        running,
-        completed,
-        failed,
-        cancelled
+        "Run {} stopped with {}: {}",
+        report.run_id,
+        report.stop_code,
+        truncate_for_table(&report.error, 92)
     );
+    let calls = triage::failed_calls(events);
+    let checks = triage::plan(&report.stop_code, &report.error, &calls, |tool, error| {
+        missing_backend_hint(error).map(|names| {
+            if names.is_empty() {
+                backends_for_tool(tool)
+                    .iter()
+                    .map(|b| b.to_string())
+                    .collect()
+            } else {
+                names
+            }
+        })
+    });
+    if checks.is_empty() {
+        println!("No check applies to this failure; `explain` walks through the run instead.");
+    }
 
-    let mut running_rows = state
-        .supervisor
-        .tasks
-        .iter()
-        .filter(|task| task.status == SupervisedTaskStatus::Running)
-        .cloned()
-        .collect::<Vec<_>>();
-    running_rows.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
-    if !running_rows.is_empty() {
-        println!("  running:");
-        for task in running_rows.iter().take(6) {
-            println!(
-                "    {} bg={} res={} prompt={}",
-                truncate_for_table(&task.id, 10),
-                truncate_for_table(task.background_task_id.as_deref().unwrap_or("-"), 14),
-                truncate_for_table(&task.resources.join(","), 16),
-                truncate_for_table(&task.prompt, 46)
-            );
+    let mut fixes = Vec::new();
+    for (index, check) in checks.iter().enumerate() {
+        println!("\n{}. {}", index + 1, check.title());
+        let mut found = Vec::new();
+        match check {
+            triage::Check::Backend { backends, .. } if backends.is_empty() => {
+                println!("  The error names no backend; `capabilities` lists what is missing.");
+            }
+            triage::Check::Backend { backends, .. } => {
+                for backend in backends {
+                    if triage_backend_present(backend).await {
+                        println!("  ✅ {} is installed", backend);
+                    } else if INSTALLABLE_BACKENDS.contains(&backend.as_str())
+                        || ocr_language_backend(backend).is_some()
+                    {
+                        println!("  ❌ {} is missing", backend);
+                        found.push(format!("capabilities install {}", backend));
+                    } else {
+                        println!("  ❌ {} is missing and has to be set up by hand", backend);
+                    }
+                }
+            }
+            triage::Check::Provider => {
+                let model = agent_loop
+                    .current_model()
+                    .unwrap_or_else(|| config.model.clone());
+                match tokio::time::timeout(Duration::from_secs(20), agent_loop.list_model_info())
+                    .await
+                {
+                    Err(_) => println!("  ❌ No answer within 20s; check the network connection."),
+                    Ok(Err(e)) => {
+                        println!("  ❌ {}", truncate_for_table(&e.to_string(), 100));
+                        println!("  Check the network connection and the provider's API key.");
+                    }
+                    Ok(Ok(models)) if !models.iter().any(|m| m.id == model) => {
+                        println!("  ⚠️  The provider answers but does not offer {}", model);
+                        found.push("models".to_string());
+                    }
+                    Ok(Ok(_)) => {
+                        println!("  ✅ The provider answers and offers {}", model);
+                        if report.stop_code == "STOP_PROVIDER_RATE_LIMIT" {
+                            println!("  Rate limits lift with time; retry in a minute.");
+                        }
+                    }
+                }
+            }
+            triage::Check::Permission {
+                tool,
+                denial,
+                error,
+            } => {
+                println!("  {}", truncate_for_table(error, 100));
+                match denial {
+                    triage::Denial::Plan => {
+                        let rule = state
+                            .supervision
+                            .resolve(&report.thread_id, &report.task_class)
+                            .map(|rule| rule.scope.describe())
+                            .unwrap_or_else(|| "default".to_string());
+                        println!(
+                            "  The {} supervision rule wants {} in an accepted plan.",
+                            rule, tool
+                        );
+                        found.push(format!(
+                            "policy set thread {} class {} action",
+                            report.thread_id, report.task_class
+                        ));
+                    }
+                    triage::Denial::Gate => println!(
+                        "  Approvals are asked for {}; retry and approve the call when asked.",
+                        approval_tier_label(approval_tier)
+                    ),
+                    triage::Denial::Os => println!(
+                        "  The system refused access; check the owner and mode of the target."
+                    ),
+                }
+            }
+        }
+        for fix in found {
+            if prompt_yes_no(&format!("  Apply `{}`? [Y/n] ", fix), true)? {
+                fixes.push(fix);
+            }
         }
     }
 
-    let mut retry_rows = state
-        .supervisor
-        .tasks
-        .iter()
-        .filter(|task| {
-            matches!(
-                task.status,
-                SupervisedTaskStatus::Completed
-                    | SupervisedTaskStatus::Failed
-                    | SupervisedTaskStatus::Cancelled
-            )
-        })
-        .cloned()
-        .collect::<Vec<_>>();
-    retry_rows.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
-    if !retry_rows.is_empty() {
-        println!("  retry candidates:");
-        for task in retry_rows.iter().take(6) {
-            println!(
-                "    {} status={} class={} prompt={}",
-                truncate_for_table(&task.id, 10),
-                truncate_for_table(&format!("{:?}", task.status).to_lowercase(), 10),
-                truncate_for_table(task.class.as_str(), 12),
-                truncate_for_table(&task.prompt, 42)
-            );
-        }
+    if !report.request.is_empty() && prompt_yes_no("\nRetry the request afterwards? [y/N] ", false)?
+    {
+        fixes.push(report.request.clone());
     }
     println!();
+    Ok(fixes)
 }
 
-fn running_background_conflict_reason(
-    input: &str,
-    running_tasks: &[hypr_claw_tasks::TaskInfo],
-) -> Option<String> {
-    let required = infer_supervised_task_resources(input);
-    let mut collisions = Vec::new();
-
-    for task in running_tasks {
-        let running_resources = infer_supervised_task_resources(&task.description);
-        let overlap = resource_conflicts(&required, &running_resources);
-        if !overlap.is_empty() {
-            collisions.push(format!("{} [{}]", task.id, overlap.join("+")));
-        }
+/// Whether a backend named by a triage check is on this machine.
+async fn triage_backend_present(backend: &str) -> bool {
+    if let Some(lang) = ocr_language_backend(backend) {
+        return hypr_claw_tools::os_capabilities::ocr::installed_languages()
+            .await
+            .map(|installed| installed.iter().any(|l| l == lang))
+            .unwrap_or(false);
     }
-
-    if collisions.is_empty() {
-        None
+    let command = if backend == "hyprland" {
+        "hyprctl"
     } else {
-        Some(format!(
-            "required [{}] conflicts with {}",
-            required.join(","),
-            collisions.join(", ")
-        ))
-    }
+        backend
+    };
+    tokio::process::Command::new("which")
+        .arg(command)
+        .output()
+        .await
+        .map(|output| output.status.success())
+        .unwrap_or(false)
 }
 
-fn touch_active_thread(state: &mut AgentOsState) {
-    for thread in &mut state.task_threads {
-        if thread.id == state.active_thread_id {
-            thread.updated_at = chrono::Utc::now().timestamp();
+/// Single model call outside the agent loop. The reply may come back as
+/// text or as a call to the lone `answer` function.
+async fn ask_model_once(
+    provider: &LLMProvider,
+    model: &str,
+    system_prompt: &str,
+    request: String,
+    timeout: Duration,
+) -> Result<String, String> {
+    let client = build_llm_client_for_provider(provider, model)?;
+    let schemas = [json!({
+        "type": "function",
+        "function": {
+            "name": "answer",
+            "description": "Reply to the request",
+            "parameters": {
+                "type": "object",
+                "properties": {"text": {"type": "string"}},
+                "required": ["text"]
+            }
+        }
+    })];
+    let messages = [hypr_claw_runtime::Message::new(
+        hypr_claw_runtime::Role::User,
+        json!(request),
+    )];
+    let call = client.call(system_prompt, &messages, &schemas);
+    match tokio::time::timeout(timeout, call).await {
+        Ok(Ok(hypr_claw_runtime::LLMResponse::Final { content, .. })) => Ok(content),
+        Ok(Ok(hypr_claw_runtime::LLMResponse::ToolCall { input, .. })) => input
+            .get("text")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| "model replied without text".to_string()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!("no reply within {}s", timeout.as_secs())),
+    }
+}
+
+/// Ask the model for a short title in the background; providers without
+/// tool calling keep the placeholder.
+fn spawn_thread_title(
+    provider: LLMProvider,
+    model: String,
+    thread_id: String,
+    placeholder: String,
+    request: String,
+    reply: String,
+    sink: Arc<Mutex<Vec<GeneratedTitle>>>,
+) {
+    tokio::spawn(async move {
+        let exchange = format!(
+            "User: {}\nAssistant: {}",
+            truncate_for_table(&request, 400),
+            truncate_for_table(&reply, 400)
+        );
+        let Ok(raw) = ask_model_once(
+            &provider,
+            &model,
+            "Name this conversation in 3 to 6 words. Reply with the title only, no quotes.",
+            exchange,
+            Duration::from_secs(20),
+        )
+        .await
+        else {
             return;
+        };
+        let Some(title) = clean_generated_title(&raw) else {
+            return;
+        };
+        if let Ok(mut pending) = sink.lock() {
+            pending.push(GeneratedTitle {
+                thread_id,
+                placeholder,
+                title,
+            });
         }
-    }
+    });
 }
 
-fn thread_session_key(base_session_key: &str, thread_id: &str) -> String {
-    format!("{base_session_key}::thread::{thread_id}")
+/// Special workspace holding the popout terminal of `thread_id`.
+fn popout_workspace(thread_id: &str) -> String {
+    format!("claw-{thread_id}")
 }
 
-fn augment_system_prompt_for_turn(
-    base_prompt: &str,
-    profile: &Value,
-    capability_registry: &Value,
-    allowed_tools: &HashSet<String>,
-    autonomy_mode: &AutonomyMode,
-) -> String {
-    let from_registry_str = |path: &str| -> Option<String> {
-        capability_registry
-            .pointer(path)
-            .and_then(|v| v.as_str())
-            .map(str::to_string)
-    };
-
-    let distro = from_registry_str("/platform/distro_name")
-        .or_else(|| {
-            profile
-                .pointer("/platform/distro_name")
-                .and_then(|v| v.as_str())
-                .map(str::to_string)
-        })
-        .unwrap_or_else(|| "unknown".to_string());
-    let kernel = from_registry_str("/platform/kernel")
-        .or_else(|| {
-            profile
-                .pointer("/platform/kernel")
-                .and_then(|v| v.as_str())
-                .map(str::to_string)
-        })
-        .unwrap_or_else(|| "unknown".to_string());
-    let workspace = capability_registry
-        .pointer("/platform/active_workspace")
-        .and_then(|v| v.as_u64())
-        .or_else(|| {
-            profile
-                .pointer("/desktop/active_workspace")
-                .and_then(|v| v.as_u64())
-        })
-        .unwrap_or(0);
-
-    let list_from_registry = |primary_path: &str, fallback_path: &str| -> String {
-        let primary = read_string_array_from_value(capability_registry.pointer(primary_path));
-        if !primary.is_empty() {
-            return primary.join(", ");
-        }
-        let fallback = read_string_array_from_value(profile.pointer(fallback_path));
-        if fallback.is_empty() {
-            "none".to_string()
-        } else {
-            fallback.join(", ")
-        }
-    };
+/// `terminal` running `program --thread <id>`, for the terminals
+/// `build_preferred_launchers` picks from.
+fn popout_terminal_command(terminal: &str, program: &str, thread_id: &str) -> Option<String> {
+    let quote = |value: &str| format!("'{}'", value.replace('\'', "'\\''"));
+    let run = format!("{} --thread {}", quote(program), quote(thread_id));
+    let class = "hypr-claw-popout";
+    Some(match terminal {
+        "kitty" | "alacritty" => format!("{terminal} --class {class} -e {run}"),
+        "wezterm" => format!("wezterm start --class {class} -- {run}"),
+        "gnome-terminal" => format!("gnome-terminal -- {run}"),
+        "xterm" => format!("xterm -class {class} -e {run}"),
+        _ => return None,
+    })
+}
 
-    let launcher_hints = list_from_registry(
-        "/desktop_apps/launcher_commands",
-        "/deep_scan/desktop_apps/launcher_commands_sample",
-    );
-    let preferred_launchers = capability_registry
-        .pointer("/desktop_apps/preferred_launchers")
-        .and_then(|v| v.as_object())
-        .map(|obj| {
-            obj.iter()
-                .filter_map(|(k, v)| v.as_str().map(|value| format!("{k}={value}")))
-                .collect::<Vec<String>>()
-                .join(", ")
-        })
-        .filter(|s| !s.is_empty())
-        .unwrap_or_else(|| "none".to_string());
-    let project_hints = list_from_registry(
-        "/projects/known_roots",
-        "/deep_scan/home_inventory/project_roots",
-    );
-    let vscode_hint =
-        from_registry_str("/editor/vscode_command").unwrap_or_else(|| "none".to_string());
-    let registry_generated_at = capability_registry
-        .pointer("/generated_at")
-        .and_then(|v| v.as_i64())
-        .map(format_timestamp)
-        .unwrap_or_else(|| "n/a".to_string());
-    let downloads_dir = from_registry_str("/paths/downloads")
-        .or_else(|| {
-            profile
-                .pointer("/paths/downloads")
-                .and_then(|v| v.as_str())
-                .map(str::to_string)
-        })
-        .unwrap_or_default();
-    let installed_packages = capability_registry
-        .pointer("/packages/total_count")
-        .and_then(|v| v.as_u64())
-        .or_else(|| {
-            profile
-                .pointer("/deep_scan/packages/total_count")
-                .and_then(|v| v.as_u64())
-        })
-        .unwrap_or(0);
+/// Shows or hides the popout of `thread_id`, starting it on its special
+/// workspace first if it is not running. Returns whether it was started.
+async fn popout_thread(capability_registry: &Value, thread_id: &str) -> Result<bool, String> {
+    use hypr_claw_tools::os_capabilities::hyprland;
 
-    let mut tools = allowed_tools.iter().cloned().collect::<Vec<String>>();
-    tools.sort();
-    let policy_block = if strict_workflow_enabled() {
-        "Strict workflow:\n1) Observe first using desktop.read_screen_state/active_window/list_windows/cursor_position before GUI actions.\n2) Plan short and execute using tools, not explanation-only text.\n3) Prefer one decisive tool call at a time with valid JSON input.\n4) After each action, verify with tools (cursor/window/screen/file/process checks) and continue until done.\n5) Ask for user permission before high-impact or destructive actions.\n6) Stop only when truly blocked and report exact blocker + next best option."
-    } else {
-        match autonomy_mode {
-            AutonomyMode::PromptFirst => {
-                "Execution policy:\n1) Perform actions using tools, not explanation.\n2) You control fallback order dynamically; adapt tool strategy from observed failures.\n3) Prefer one decisive tool call at a time with valid JSON input.\n4) For opening local apps, prefer desktop.launch_app before proc.spawn/open_url and use launcher_commands/vscode_hint.\n5) End with concise result and what changed.\n6) Stop only when truly blocked after trying multiple tool paths."
-            }
-            AutonomyMode::Guarded => {
-                "Execution policy:\n1) Perform actions using tools, not explanation.\n2) If a tool fails, choose an alternative backend/tool and continue.\n3) For opening local apps, try desktop.launch_app first, then proc.spawn as fallback; use launcher_commands/vscode_hint.\n4) End with concise result and what changed.\n5) Only stop as blocked after trying alternatives available in runtime context."
-            }
-        }
-    };
+    let hyprland_available = capability_registry
+        .pointer("/platform/hyprland_available")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    if !hyprland_available {
+        return Err("needs Hyprland".to_string());
+    }
+    let workspace = popout_workspace(thread_id);
+    let running = hyprland::special_workspace_windows(&workspace)
+        .await
+        .map_err(|e| e.to_string())?;
+    if running.is_empty() {
+        let terminal = capability_registry
+            .pointer("/desktop_apps/preferred_launchers/terminal")
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown");
+        let program = std::env::current_exe().map_err(|e| e.to_string())?;
+        let command = popout_terminal_command(terminal, &program.to_string_lossy(), thread_id)
+            .ok_or_else(|| format!("no supported terminal found (got '{terminal}')"))?;
+        // The popout shares ./data, so it has to start in our directory.
+        let cwd = std::env::current_dir().map_err(|e| e.to_string())?;
+        let command = format!(
+            "cd '{}' && {}",
+            cwd.to_string_lossy().replace('\'', "'\\''"),
+            command
+        );
+        hyprland::exec_on_special(&workspace, &command)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    hyprland::toggle_special(&workspace)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(running.is_empty())
+}
 
-    format!(
-        "{}\n\nRuntime context:\n- autonomy_mode: {}\n- workflow_mode: {}\n- capability_registry_generated_at: {}\n- distro: {}\n- kernel: {}\n- active_workspace: {}\n- wallpaper_backends: {}\n- screenshot_backends: {}\n- input_backends: {}\n- vscode_hint: {}\n- launcher_commands: {}\n- preferred_launchers: {}\n- known_projects: {}\n- known_downloads_dir: {}\n- installed_packages: {}\n- allowed_tools_now: {}\n\n{}",
-        base_prompt,
-        autonomy_mode.as_str(),
-        if strict_workflow_enabled() { "strict" } else { "legacy" },
-        registry_generated_at,
-        distro,
-        kernel,
-        workspace,
-        list_from_registry("/capabilities/wallpaper_backends", "/capabilities/wallpaper_backends"),
-        list_from_registry("/capabilities/screenshot_backends", "/capabilities/screenshot_backends"),
-        list_from_registry("/capabilities/input_backends", "/capabilities/input_backends"),
-        vscode_hint,
-        launcher_hints,
-        preferred_launchers,
-        project_hints,
-        downloads_dir,
-        installed_packages,
-        tools.join(", "),
-        policy_block
-    )
+fn print_thread_list(state: &AgentOsState) {
+    println!("\n{}", ui_title("Threads"));
+    let mut threads: Vec<&TaskThread> = state.task_threads.iter().collect();
+    threads.sort_by_key(|thread| std::cmp::Reverse(thread.updated_at));
+    for thread in threads {
+        println!(
+            "{} {:<8} {:<48} {}{}",
+            if thread.id == state.active_thread_id {
+                "*"
+            } else {
+                " "
+            },
+            thread.id,
+            thread.title,
+            format_timestamp(thread.updated_at),
+            if thread.archived { " (archived)" } else { "" }
+        );
+    }
+    println!();
 }
 
-fn focused_tools_for_input(input: &str, allowed: &HashSet<String>) -> HashSet<String> {
-    let lower = input.to_lowercase();
-    let mut preferred = HashSet::new();
+/// Thread id and text of a `[thread:<id>] text` history entry.
+fn split_thread_tag(content: &str) -> (Option<&str>, &str) {
+    content
+        .strip_prefix("[thread:")
+        .and_then(|rest| rest.split_once("] "))
+        .map(|(id, text)| (Some(id), text))
+        .unwrap_or((None, content))
+}
 
-    let add = |set: &mut HashSet<String>, name: &str, allowed: &HashSet<String>| {
-        if allowed.contains(name) {
-            set.insert(name.to_string());
-        }
-    };
+fn print_history(
+    state: &AgentOsState,
+    history: &[hypr_claw_memory::types::HistoryEntry],
+    limit: usize,
+) {
+    println!("\n{}", ui_title("History"));
+    if history.is_empty() {
+        println!("No history yet.");
+        return;
+    }
+    let start = history.len().saturating_sub(limit);
+    for entry in &history[start..] {
+        let (thread_id, text) = split_thread_tag(&entry.content);
+        let thread = thread_id
+            .map(|id| {
+                state
+                    .task_threads
+                    .iter()
+                    .find(|thread| thread.id == id)
+                    .map(|thread| thread.title.clone())
+                    .unwrap_or_else(|| id.to_string())
+            })
+            .unwrap_or_default();
+        println!(
+            "{} {:<9} {:<24} {}",
+            ui_dim(&format_timestamp(entry.timestamp)),
+            entry.role,
+            truncate_for_table(&thread, 24),
+            truncate_for_table(&sanitize_single_line(text), 80)
+        );
+    }
+    println!();
+}
 
-    if lower.contains("wallpaper") || lower.contains("workspace") || lower.contains("window") {
-        add(&mut preferred, "wallpaper.set", allowed);
-        add(&mut preferred, "hypr.workspace.switch", allowed);
-        add(&mut preferred, "hypr.workspace.move_window", allowed);
-        add(&mut preferred, "hypr.window.focus", allowed);
-        add(&mut preferred, "hypr.window.close", allowed);
-        add(&mut preferred, "hypr.window.move", allowed);
-        add(&mut preferred, "hypr.exec", allowed);
+fn empty_json() -> Value {
+    json!({})
+}
+
+fn load_agent_os_state(context: &hypr_claw_memory::types::ContextData) -> AgentOsState {
+    let maybe_state = context.system_state.get("agent_os_state").cloned();
+    let mut state = maybe_state
+        .as_ref()
+        .and_then(|v| serde_json::from_value::<AgentOsState>(v.clone()).ok())
+        .unwrap_or_default();
+
+    // Migration: if legacy state did not include soul_auto, default to disabled.
+    let soul_auto_present = maybe_state
+        .as_ref()
+        .and_then(|v| v.get("soul_auto"))
+        .is_some();
+    if !soul_auto_present {
+        state.soul_auto = false;
     }
 
-    if lower.contains("file")
-        || lower.contains("folder")
-        || lower.contains("dir")
-        || lower.contains("read")
-        || lower.contains("write")
-        || lower.contains("create")
-        || lower.contains("delete")
-        || lower.contains("copy")
-        || lower.contains("move")
-    {
-        add(&mut preferred, "fs.read", allowed);
-        add(&mut preferred, "fs.write", allowed);
-        add(&mut preferred, "fs.list", allowed);
-        add(&mut preferred, "fs.create_dir", allowed);
-        add(&mut preferred, "fs.delete", allowed);
-        add(&mut preferred, "fs.copy", allowed);
-        add(&mut preferred, "fs.move", allowed);
+    state
+}
+
+fn persist_agent_os_state(
+    context: &mut hypr_claw_memory::types::ContextData,
+    state: &AgentOsState,
+) {
+    if !context.system_state.is_object() {
+        context.system_state = json!({});
+    }
+    if let Some(obj) = context.system_state.as_object_mut() {
+        let value = serde_json::to_value(state).unwrap_or_else(|_| json!({}));
+        obj.insert("agent_os_state".to_string(), value);
     }
+}
 
-    if lower.contains("open")
-        || lower.contains("browser")
-        || lower.contains("search")
-        || lower.contains("web")
-        || lower.contains("gmail")
-        || lower.contains("mail")
-    {
-        add(&mut preferred, "desktop.launch_app", allowed);
-        if lower.contains("gmail") || lower.contains("mail") {
-            add(&mut preferred, "desktop.open_gmail", allowed);
-            add(&mut preferred, "desktop.open_url", allowed);
-            add(&mut preferred, "desktop.search_web", allowed);
-        } else if lower.contains("browser") || lower.contains("search") || lower.contains("web") {
-            add(&mut preferred, "desktop.search_web", allowed);
-            add(&mut preferred, "desktop.open_url", allowed);
+fn ensure_default_thread(state: &mut AgentOsState) {
+    if state.task_threads.is_empty() {
+        state
+            .task_threads
+            .push(TaskThread::new("task-1".to_string(), "Main".to_string()));
+    }
+    let active_exists = state
+        .task_threads
+        .iter()
+        .any(|thread| thread.id == state.active_thread_id && !thread.archived);
+    if !active_exists {
+        if let Some(thread) = state.task_threads.iter().find(|thread| !thread.archived) {
+            state.active_thread_id = thread.id.clone();
         } else {
-            add(&mut preferred, "desktop.open_url", allowed);
-            add(&mut preferred, "desktop.search_web", allowed);
+            let fallback = TaskThread::new(next_thread_id(state), "Main".to_string());
+            state.active_thread_id = fallback.id.clone();
+            state.task_threads.push(fallback);
         }
     }
+}
 
-    if lower.contains("type")
-        || lower.contains("press")
-        || lower.contains("shortcut")
-        || lower.contains("hotkey")
-        || lower.contains("click")
-        || lower.contains("mouse")
-        || lower.contains("screenshot")
-        || lower.contains("screen")
-    {
-        add(&mut preferred, "desktop.read_screen_state", allowed);
-        add(&mut preferred, "desktop.cursor_position", allowed);
-        add(&mut preferred, "desktop.type_text", allowed);
-        add(&mut preferred, "desktop.key_press", allowed);
-        add(&mut preferred, "desktop.key_combo", allowed);
-        add(&mut preferred, "desktop.mouse_click", allowed);
-        add(&mut preferred, "desktop.capture_screen", allowed);
-        add(&mut preferred, "desktop.active_window", allowed);
-        add(&mut preferred, "desktop.list_windows", allowed);
-        add(&mut preferred, "desktop.mouse_move", allowed);
-        add(&mut preferred, "desktop.mouse_move_and_verify", allowed);
-        add(&mut preferred, "desktop.click_at", allowed);
-        add(&mut preferred, "desktop.click_at_and_verify", allowed);
-        add(&mut preferred, "desktop.ocr_screen", allowed);
-        add(&mut preferred, "desktop.find_text", allowed);
-        add(&mut preferred, "desktop.click_text", allowed);
+fn display_name(state: &AgentOsState, fallback_user_id: &str) -> String {
+    if state.onboarding.preferred_name.trim().is_empty() {
+        fallback_user_id.to_string()
+    } else {
+        state.onboarding.preferred_name.clone()
     }
+}
 
-    if lower.contains("run")
-        || lower.contains("build")
-        || lower.contains("start")
-        || lower.contains("execute")
-        || lower.contains("kill")
-        || lower.contains("process")
-    {
-        add(&mut preferred, "proc.spawn", allowed);
-        add(&mut preferred, "proc.kill", allowed);
-        add(&mut preferred, "proc.list", allowed);
-        add(&mut preferred, "hypr.exec", allowed);
+fn detect_agent_name() -> String {
+    if let Ok(from_env) = std::env::var("HYPR_CLAW_AGENT") {
+        let candidate = from_env.trim();
+        if !candidate.is_empty() {
+            let path = data_dir::path(format!("./data/agents/{}.yaml", candidate));
+            if path.exists() {
+                return candidate.to_string();
+            }
+        }
     }
 
-    if lower.contains("battery") || lower.contains("memory") || lower.contains("system") {
-        add(&mut preferred, "system.battery", allowed);
-        add(&mut preferred, "system.memory", allowed);
+    if data_dir::path("./data/agents/default.yaml").exists() {
+        return "default".to_string();
     }
 
-    if preferred.is_empty() {
-        return allowed.clone();
+    if let Ok(entries) = std::fs::read_dir(data_dir::path("./data/agents")) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) == Some("yaml") {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    return stem.to_string();
+                }
+            }
+        }
     }
 
-    add(&mut preferred, "echo", allowed);
-    preferred
+    "default".to_string()
 }
 
-fn emergency_tool_subset(input: &str, allowed: &HashSet<String>) -> HashSet<String> {
-    let focused = focused_tools_for_input(input, allowed);
-    if focused.is_empty() {
-        return HashSet::new();
+fn detect_user_id() -> String {
+    std::env::var("HYPR_CLAW_USER")
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .or_else(|| std::env::var("USER").ok().filter(|s| !s.trim().is_empty()))
+        .unwrap_or_else(|| "local_user".to_string())
+}
+
+/// Chat-only console for when startup fails, so the user can read what
+/// broke and ask for help instead of being dropped back to the shell.
+async fn run_safe_mode(
+    config: &Config,
+    failure: StartupFailure,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut failures = vec![failure];
+    let mut chat = match build_llm_client_for_provider(&config.provider, &config.model) {
+        Ok(client) if failures[0].component != StartupComponent::Provider => Some(client),
+        Ok(_) => None,
+        Err(e) => {
+            if failures[0].component != StartupComponent::Provider {
+                failures.push(StartupFailure::new(StartupComponent::Provider, e));
+            }
+            None
+        }
     }
-    if focused.len() <= 10 {
-        return focused;
+    .map(|client| safe_mode::SafeChat::new(client, &failures));
+
+    let print_panel = |failures: &[StartupFailure]| {
+        println!("\n{}", ui_section("Safe mode"));
+        println!(
+            "{}",
+            ui_warn("Startup failed. Tools are disabled and this session is not saved.")
+        );
+        for line in safe_mode::diagnostics(failures) {
+            println!("{line}");
+        }
+        println!(
+            "{}",
+            ui_dim("Commands: diag, help, exit. Anything else is sent to the model.")
+        );
+        println!("{}\n", ui_divider());
+    };
+    print_panel(&failures);
+    if chat.is_none() {
+        println!("Chat is unavailable until the provider is repaired.\n");
+    }
+
+    loop {
+        print!("{} ", ui_warn("safe>"));
+        io::stdout().flush()?;
+        let Some(input) = input::read_line() else {
+            return Ok(());
+        };
+        match input.trim() {
+            "" => {}
+            "exit" | "quit" | "/exit" | "/quit" => return Ok(()),
+            "diag" | "/diag" | "status" | "/status" => print_panel(&failures),
+            "help" | "/help" => {
+                println!("  diag   Show what failed and how to repair it");
+                println!("  exit   Leave safe mode; restart hypr-claw once repaired");
+                println!("  Anything else is a chat message (no tools, nothing is saved).\n");
+            }
+            text => match chat.as_mut() {
+                Some(chat) => match chat.ask(text).await {
+                    Ok(reply) => println!("{}\n", strip_ansi_and_controls(reply.trim())),
+                    Err(e) => eprintln!("❌ {e}\n"),
+                },
+                None => println!("Chat is unavailable: the provider failed to start.\n"),
+            },
+        }
     }
-    let mut names: Vec<String> = focused.into_iter().collect();
-    names.sort();
-    names.into_iter().take(10).collect()
 }
 
-fn fallback_playbook_for_input(input: &str, allowed: &HashSet<String>) -> String {
-    let lower = input.to_lowercase();
-    let mut rows: Vec<(&str, Vec<&str>)> = Vec::new();
+fn prompt_line(prompt: &str) -> io::Result<String> {
+    print!("{prompt}");
+    io::stdout().flush()?;
+    let input = input::read_line().unwrap_or_default();
+    Ok(input.trim().to_string())
+}
 
-    let wallpaper_intent = lower.contains("wallpaper") || lower.contains("background");
-    if wallpaper_intent {
-        rows.push((
-            "wallpaper",
-            vec!["wallpaper.set", "hypr.exec", "desktop.open_url"],
-        ));
+/// Consolidated prompt for `plan.request_approval`: the whole itemized plan,
+/// answered once with the items to accept.
+fn prompt_plan_approval(actions: &[hypr_claw_tools::approvals::PlannedAction]) -> Vec<bool> {
+    println!("\n🛑 Plan approval: {} destructive step(s)", actions.len());
+    for (index, action) in actions.iter().enumerate() {
+        println!("  {}. {}", index + 1, action.describe());
+    }
+    for _ in 0..3 {
+        let Ok(answer) = prompt_line(
+            "Accept which steps? [all / none / numbers like 1,3 or 2-4] (default none): ",
+        ) else {
+            break;
+        };
+        match hypr_claw_tools::approvals::parse_selection(&answer, actions.len()) {
+            Some(accepted) => {
+                let count = accepted.iter().filter(|a| **a).count();
+                println!("✅ Accepted {count}, skipped {}.", actions.len() - count);
+                return accepted;
+            }
+            None => println!("Unrecognized selection '{answer}'."),
+        }
     }
+    println!("Skipping all steps.");
+    vec![false; actions.len()]
+}
 
-    let desktop_intent = lower.contains("open")
-        || lower.contains("launch")
-        || lower.contains("gmail")
-        || lower.contains("telegram")
-        || lower.contains("browser")
-        || lower.contains("vscode");
-    if desktop_intent {
-        rows.push((
-            "desktop-open",
-            vec![
-                "desktop.launch_app",
-                "desktop.open_url",
-                "proc.spawn",
-                "hypr.exec",
-            ],
-        ));
+/// Preview for `fs.apply_staged`: what the staged deletes and moves add up
+/// to, accepted or declined as a whole.
+fn prompt_staged_apply(preview: &hypr_claw_tools::staging::StagingPreview) -> bool {
+    println!("\n🛑 Apply staged file changes");
+    for line in preview.render() {
+        println!("  {line}");
     }
+    prompt_line("Apply all of them? [y/N] ")
+        .map(|answer| matches!(answer.to_lowercase().as_str(), "y" | "yes"))
+        .unwrap_or(false)
+}
 
-    let screen_intent = lower.contains("screen")
-        || lower.contains("ocr")
-        || lower.contains("read my")
-        || lower.contains("summarise");
-    if screen_intent {
-        rows.push((
-            "screen-automation",
-            vec![
-                "desktop.read_screen_state",
-                "desktop.cursor_position",
-                "desktop.capture_screen",
-                "desktop.ocr_screen",
-                "desktop.find_text",
-                "desktop.mouse_move_and_verify",
-                "desktop.click_at_and_verify",
-                "desktop.click_text",
-                "desktop.mouse_click",
-            ],
-        ));
-    }
-
-    let process_intent = lower.contains("run")
-        || lower.contains("build")
-        || lower.contains("install")
-        || lower.contains("process")
-        || lower.contains("command");
-    if process_intent {
-        rows.push((
-            "process",
-            vec!["proc.spawn", "hypr.exec", "proc.list", "proc.kill"],
-        ));
+/// Stores a `key:value` fact, replacing an older value for the same key.
+fn remember_fact(facts: &mut Vec<String>, fact: String) {
+    if let Some((key, _)) = fact.split_once(':') {
+        let prefix = format!("{key}:");
+        facts.retain(|existing| !existing.starts_with(&prefix));
     }
+    facts.push(fact);
+}
 
-    let fs_intent = lower.contains("file")
-        || lower.contains("folder")
-        || lower.contains("dir")
-        || lower.contains("write")
-        || lower.contains("read")
-        || lower.contains("create");
-    if fs_intent {
-        rows.push((
-            "filesystem",
-            vec![
-                "fs.list",
-                "fs.create_dir",
-                "fs.write",
-                "fs.read",
-                "fs.copy",
-                "fs.move",
-                "fs.delete",
-            ],
-        ));
+/// Foreground prompt for `ask_user`; a number picks one of the choices.
+fn prompt_ask_user(question: &str, choices: &[String]) -> Option<String> {
+    println!("\n❓ {question}");
+    for (index, choice) in choices.iter().enumerate() {
+        println!("  {}. {}", index + 1, choice);
     }
+    prompt_line("Answer: ")
+        .ok()
+        .filter(|answer| !answer.is_empty())
+}
 
-    if rows.is_empty() {
-        let fallback = emergency_tool_subset(input, allowed)
-            .into_iter()
-            .collect::<Vec<_>>();
-        if fallback.is_empty() {
-            return "- no fallback tools available".to_string();
-        }
-        return format!("- generic: {}", fallback.join(" -> "));
-    }
+/// Consent for `form.fill` to type a sensitive saved value.
+fn prompt_form_consent(form: &str, label: &str, fact: &str) -> hypr_claw_tools::forms::Consent {
+    use hypr_claw_tools::forms::Consent;
 
-    let mut lines = Vec::new();
-    for (label, chain) in rows {
-        let available = chain
-            .into_iter()
-            .filter(|tool| allowed.contains(*tool))
-            .collect::<Vec<_>>();
-        if !available.is_empty() {
-            lines.push(format!("- {}: {}", label, available.join(" -> ")));
-        }
-    }
-    if lines.is_empty() {
-        "- no fallback tools available".to_string()
-    } else {
-        lines.join("\n")
+    println!("\n🛑 Form '{form}' wants to fill '{label}' with your saved {fact}");
+    match prompt_line("Fill it? [y/N/a=always for this field] ")
+        .map(|answer| answer.to_lowercase())
+        .as_deref()
+    {
+        Ok("y" | "yes") => Consent::Once,
+        Ok("a" | "always") => Consent::Always,
+        _ => Consent::Deny,
     }
 }
 
-// Adapter for ToolDispatcher
-struct RuntimeDispatcherAdapter {
-    inner: Arc<hypr_claw_tools::ToolDispatcherImpl>,
-    action_feed: Arc<Mutex<Vec<String>>>,
-    action_counter: Arc<Mutex<HashMap<String, u64>>>,
+/// Approval prompt for a single tool call. Reads through the shared input
+/// reader, which owns stdin while the REPL runs.
+fn prompt_tool_approval(description: &str) -> bool {
+    println!("\n🛑 Approve action: {description}");
+    prompt_line("Run it? [y/N] ")
+        .map(|answer| matches!(answer.to_lowercase().as_str(), "y" | "yes"))
+        .unwrap_or(false)
 }
 
-impl RuntimeDispatcherAdapter {
-    fn new(
-        inner: Arc<hypr_claw_tools::ToolDispatcherImpl>,
-        action_feed: Arc<Mutex<Vec<String>>>,
-    ) -> Self {
-        Self {
-            inner,
-            action_feed,
-            action_counter: Arc::new(Mutex::new(HashMap::new())),
-        }
+fn prompt_yes_no(prompt: &str, default_yes: bool) -> io::Result<bool> {
+    let input = prompt_line(prompt)?;
+    if input.is_empty() {
+        return Ok(default_yes);
+    }
+    match input.to_lowercase().as_str() {
+        "y" | "yes" => Ok(true),
+        "n" | "no" => Ok(false),
+        _ => Ok(default_yes),
     }
+}
 
-    /// Prefix with [task_id] when session_key is a supervisor task (e.g. "user:agent::sup::sup-1").
-    pub(crate) fn task_tag(session_key: &str) -> Option<&str> {
-        session_key.split("::sup::").nth(1)
+async fn run_first_run_onboarding(
+    user_id: &str,
+    context: &mut hypr_claw_memory::types::ContextData,
+    state: &mut AgentOsState,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if state.onboarding.completed {
+        return Ok(());
     }
 
-    fn push_action(&self, session_key: &str, line: String) {
-        let tagged = if let Some(tag) = Self::task_tag(session_key) {
-            format!("[{}] {}", tag, line)
+    println!("\n🧭 First Run Onboarding");
+    if state.onboarding.preferred_name.trim().is_empty() {
+        let preferred = prompt_line("What should I call you? ")?;
+        state.onboarding.preferred_name = if preferred.trim().is_empty() {
+            user_id.to_string()
         } else {
-            line
+            preferred
         };
-        if let Ok(mut feed) = self.action_feed.lock() {
-            feed.push(tagged);
-            if feed.len() > 256 {
-                let drop_count = feed.len() - 256;
-                feed.drain(0..drop_count);
-            }
-        }
     }
 
-    fn next_action_index(&self, session_key: &str) -> u64 {
-        if let Ok(mut counters) = self.action_counter.lock() {
-            let entry = counters.entry(session_key.to_string()).or_insert(0);
-            *entry = entry.saturating_add(1);
-            return *entry;
-        }
-        0
-    }
+    state.onboarding.trusted_full_auto = false;
 
-    fn print_action(
-        &self,
-        session_key: &str,
-        index: u64,
-        status: &str,
-        tool_name: &str,
-        detail: &str,
-    ) {
-        let tool = truncate_for_table(tool_name, 30);
-        let detail_clean = truncate_for_table(&sanitize_single_line(detail), 108);
-        let line = format!(
-            "{:>3}. {:<7} {:<30} {}",
-            index,
-            status,
-            tool,
-            detail_clean
-        );
-        self.push_action(session_key, line.clone());
-        let status_badge = match status {
-            "ok" => ui_success("OK"),
-            "tool" => ui_info("TOOL"),
-            "alias" => ui_warn("MAP"),
-            "fail" => ui_danger("FAIL"),
-            "error" => ui_danger("ERR"),
-            _ => ui_dim(status),
-        };
+    println!("Choose a safety preset (change later with `preset switch`):");
+    for (index, preset) in OnboardingPreset::ALL.iter().enumerate() {
         println!(
-            "{} {} {} {}",
-            ui_dim(&format!("{:>3}.", index)),
-            status_badge,
-            truncate_for_table(tool_name, 30),
-            detail_clean
+            "  {}. {:<10} {}",
+            index + 1,
+            preset.as_str(),
+            preset.description()
         );
     }
-}
-
-#[async_trait::async_trait]
-impl hypr_claw_runtime::ToolDispatcher for RuntimeDispatcherAdapter {
-    async fn execute(
-        &self,
-        tool_name: &str,
-        input: &serde_json::Value,
-        session_key: &str,
-    ) -> Result<serde_json::Value, hypr_claw_runtime::RuntimeError> {
-        let normalized_tool_name = normalize_runtime_tool_name(tool_name);
-        let started = std::time::Instant::now();
-        let action_index = self.next_action_index(session_key);
-        self.print_action(
-            session_key,
-            action_index,
-            "tool",
-            &normalized_tool_name,
-            &format!("input={}", truncate_for_table(&input.to_string(), 112)),
-        );
-        if normalized_tool_name != tool_name {
-            self.print_action(
-                session_key,
-                action_index,
-                "alias",
-                &normalized_tool_name,
-                &format!(
-                    "remapped '{}' -> '{}'",
-                    truncate_for_table(tool_name, 24),
-                    truncate_for_table(&normalized_tool_name, 24)
-                ),
-            );
+    let preset = loop {
+        let choice = prompt_line("Preset [1-3] (default 2): ")?;
+        if choice.is_empty() {
+            break OnboardingPreset::Balanced;
         }
-        let result = self
-            .inner
-            .dispatch(
-                session_key.to_string(),
-                normalized_tool_name.clone(),
-                input.clone(),
-            )
-            .await;
+        match OnboardingPreset::parse(&choice) {
+            Some(preset) => break preset,
+            None => println!("Pick 1, 2 or 3."),
+        }
+    };
+    apply_preset(state, preset);
 
-        match result {
-            Ok(tool_result) => {
-                if tool_result.success {
-                    let output = tool_result.output.unwrap_or(serde_json::json!({}));
-                    let elapsed = started.elapsed().as_millis();
-                    self.print_action(
-                        session_key,
-                        action_index,
-                        "ok",
-                        &normalized_tool_name,
-                        &format!(
-                            "done in {}ms output={}",
-                            elapsed,
-                            truncate_for_table(&output.to_string(), 112)
-                        ),
-                    );
-                    Ok(output)
-                } else {
-                    let base_detail = tool_result
-                        .error
-                        .clone()
-                        .unwrap_or_else(|| "Unknown error".to_string());
-                    let alternatives = fallback_tools_for_tool(&normalized_tool_name);
-                    let detail = if alternatives.is_empty() {
-                        base_detail
-                    } else {
-                        format!(
-                            "{} | fallback_tools: {}",
-                            base_detail,
-                            alternatives.join(", ")
-                        )
-                    };
-                    self.print_action(
-                        session_key,
-                        action_index,
-                        "fail",
-                        &normalized_tool_name,
-                        &format!(
-                            "failed in {}ms {}",
-                            started.elapsed().as_millis(),
-                            truncate_for_table(&detail, 112)
-                        ),
-                    );
-                    Err(hypr_claw_runtime::RuntimeError::ToolError(detail))
-                }
+    if prompt_yes_no("Allow first-time system study scan? [Y/n] ", true)? {
+        let deep_scan = preset.deep_scan()
+            && prompt_yes_no(
+                "Run deep system learning scan (home directory with consent)? [Y/n] ",
+                true,
+            )?;
+
+        state.onboarding.system_profile = scan::run_integrated_scan(user_id, deep_scan).await?;
+        state.onboarding.deep_scan_completed = deep_scan;
+        state.onboarding.last_scan_at = Some(chrono::Utc::now().timestamp());
+        print_system_profile_summary(&state.onboarding.system_profile);
+
+        loop {
+            if prompt_yes_no("Is this system profile correct? [Y/n] ", true)? {
+                state.onboarding.profile_confirmed = true;
+                break;
             }
-            Err(e) => {
-                let base_detail = e.to_string();
-                let alternatives = fallback_tools_for_tool(&normalized_tool_name);
-                let detail = if alternatives.is_empty() {
-                    base_detail
-                } else {
-                    format!(
-                        "{} | fallback_tools: {}",
-                        base_detail,
-                        alternatives.join(", ")
-                    )
-                };
-                self.print_action(
-                    session_key,
-                    action_index,
-                    "error",
-                    &normalized_tool_name,
-                    &format!(
-                        "dispatcher in {}ms {}",
-                        started.elapsed().as_millis(),
-                        truncate_for_table(&detail, 112)
-                    ),
-                );
-                Err(hypr_claw_runtime::RuntimeError::ToolError(detail))
+            if !prompt_yes_no("Edit profile now? [Y/n] ", true)? {
+                break;
             }
+            edit_profile_interactively(&mut state.onboarding.system_profile)?;
+            print_system_profile_summary(&state.onboarding.system_profile);
         }
+    } else {
+        println!("ℹ️  System study skipped for now. Use `scan` later.");
+    }
+
+    state.onboarding.completed = true;
+    if !context
+        .facts
+        .iter()
+        .any(|f| f.starts_with("preferred_name:"))
+    {
+        context.facts.push(format!(
+            "preferred_name:{}",
+            state.onboarding.preferred_name
+        ));
     }
+    Ok(())
 }
 
-fn fallback_tools_for_tool(tool_name: &str) -> Vec<&'static str> {
-    match tool_name {
-        "wallpaper.set" => vec!["hypr.exec"],
-        "desktop.open_gmail" => vec!["desktop.open_url", "desktop.launch_app"],
-        "desktop.open_url" => vec!["desktop.search_web", "desktop.launch_app"],
-        "desktop.launch_app" => vec!["proc.spawn", "hypr.exec"],
-        "proc.spawn" => vec!["hypr.exec", "desktop.launch_app"],
-        "desktop.capture_screen" => vec!["hypr.exec"],
-        "desktop.ocr_screen" => vec!["desktop.capture_screen"],
-        "desktop.click_text" => vec!["desktop.find_text", "desktop.mouse_click"],
-        "desktop.find_text" => vec!["desktop.ocr_screen", "desktop.capture_screen"],
-        "desktop.read_screen_state" => vec![
-            "desktop.active_window",
-            "desktop.list_windows",
-            "desktop.cursor_position",
-            "desktop.capture_screen",
-            "desktop.ocr_screen",
-        ],
-        "desktop.mouse_move" => vec!["desktop.mouse_move_and_verify", "desktop.click_at"],
-        "desktop.click_at" => vec!["desktop.click_at_and_verify", "desktop.mouse_click"],
-        "desktop.click_at_and_verify" => vec!["desktop.mouse_move_and_verify", "desktop.click_at"],
-        "hypr.workspace.switch" => vec!["hypr.exec"],
-        "hypr.window.focus" => vec!["hypr.exec"],
-        "hypr.window.move" => vec!["hypr.exec"],
-        "hypr.window.close" => vec!["hypr.exec"],
-        "hypr.exec" => vec!["proc.spawn"],
-        "fs.list" => vec!["hypr.exec"],
-        "fs.read" => vec!["hypr.exec"],
-        "fs.write" => vec!["hypr.exec"],
-        _ => Vec::new(),
+fn print_system_profile_summary(profile: &Value) {
+    let distro = profile
+        .pointer("/platform/distro_name")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown");
+    let kernel = profile
+        .pointer("/platform/kernel")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown");
+    let hypr = profile
+        .pointer("/desktop/hyprland_available")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let active_ws = profile
+        .pointer("/desktop/active_workspace")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+
+    println!("\n🖥️  Profile Summary");
+    println!("  Distro: {}", distro);
+    println!("  Kernel: {}", kernel);
+    println!("  Hyprland available: {}", if hypr { "yes" } else { "no" });
+    if hypr {
+        println!("  Active workspace: {}", active_ws);
     }
+    println!(
+        "  Deep scan data: {}",
+        if profile.pointer("/deep_scan").is_some() {
+            "yes"
+        } else {
+            "no"
+        }
+    );
+    println!();
 }
 
-// Adapter for ToolRegistry
-struct RuntimeRegistryAdapter {
-    inner: Arc<hypr_claw_tools::ToolRegistryImpl>,
-    allowed_tools: Arc<RwLock<HashSet<String>>>,
+fn edit_profile_interactively(profile: &mut Value) -> io::Result<()> {
+    println!("Profile edit mode.");
+    println!("Commands: show | set <path> <value> | done | accept | help");
+    println!("Path example: desktop.active_workspace");
+    loop {
+        let line = prompt_line("profile> ")?;
+        if line.is_empty() {
+            continue;
+        }
+        if line == "done" || line == "accept" {
+            break;
+        }
+        if line == "help" {
+            println!("Commands: show | set <path> <value> | done | accept");
+            continue;
+        }
+        if line == "show" {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(profile).unwrap_or_else(|_| "{}".to_string())
+            );
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("set ") {
+            let mut parts = rest.splitn(2, ' ');
+            let path = parts.next().unwrap_or_default();
+            let raw_value = parts.next().unwrap_or_default().trim();
+            if path.is_empty() || raw_value.is_empty() {
+                println!("Usage: set <path> <value>");
+                continue;
+            }
+            let parsed = serde_json::from_str::<Value>(raw_value)
+                .unwrap_or_else(|_| Value::String(raw_value.to_string()));
+            set_json_path(profile, path, parsed);
+            println!("Updated {}", path);
+            continue;
+        }
+        println!("Unknown command. Use show | set <path> <value> | done");
+    }
+    Ok(())
+}
+
+fn set_json_path(root: &mut Value, path: &str, value: Value) {
+    let segments: Vec<&str> = path.split('.').filter(|s| !s.is_empty()).collect();
+    if segments.is_empty() {
+        *root = value;
+        return;
+    }
+
+    let mut current = root;
+    let mut pending = Some(value);
+    for (index, segment) in segments.iter().enumerate() {
+        let is_last = index == segments.len() - 1;
+        if !current.is_object() {
+            *current = json!({});
+        }
+        let map = current.as_object_mut().expect("object was just created");
+        if is_last {
+            map.insert(
+                (*segment).to_string(),
+                pending.take().unwrap_or(Value::Null),
+            );
+            return;
+        }
+        current = map
+            .entry((*segment).to_string())
+            .or_insert_with(|| json!({}));
+    }
+}
+
+/// Rescan the system without blocking startup; the REPL loop picks the
+/// profile up from `slot`.
+fn spawn_background_scan(user_id: String, slot: Arc<Mutex<Option<Value>>>) {
+    tokio::spawn(async move {
+        match scan::run_integrated_scan(&user_id, false).await {
+            Ok(profile) => {
+                if let Ok(mut slot) = slot.lock() {
+                    *slot = Some(profile);
+                }
+            }
+            Err(e) => eprintln!("⚠️  Background system scan failed: {}", e),
+        }
+    });
+}
+
+fn profile_needs_capability_refresh(profile: &Value) -> bool {
+    profile.pointer("/capabilities").is_none() || profile.pointer("/paths/downloads").is_none()
+}
+
+fn sanitize_user_key_for_filename(user_id: &str) -> String {
+    let mut out = String::new();
+    for ch in user_id.chars() {
+        if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' {
+            out.push(ch);
+        } else {
+            out.push('_');
+        }
+    }
+    if out.is_empty() {
+        "local_user".to_string()
+    } else {
+        out
+    }
+}
+
+fn capability_registry_file_path(user_id: &str) -> String {
+    data_dir::path(format!(
+        "./data/capabilities/{}.json",
+        sanitize_user_key_for_filename(user_id)
+    ))
+    .to_string_lossy()
+    .into_owned()
+}
+
+fn read_string_array_from_value(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str())
+                .map(str::to_string)
+                .collect::<Vec<String>>()
+        })
+        .unwrap_or_default()
+}
+
+/// `eDP-1 2880x1800@120Hz x2` for each display in a system profile.
+fn display_summaries(displays: Option<&Value>) -> Vec<String> {
+    displays
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|display| {
+            let number = |key: &str| display.get(key).and_then(Value::as_f64).unwrap_or(0.0);
+            format!(
+                "{} {}x{}@{}Hz x{}",
+                display.get("name").and_then(Value::as_str).unwrap_or("?"),
+                number("width"),
+                number("height"),
+                number("refresh_hz").round(),
+                number("scale")
+            )
+        })
+        .collect()
+}
+
+/// `nvidia NVIDIA GeForce RTX 3060 (12288 MB)` for each GPU in a system profile.
+fn gpu_summaries(gpus: Option<&Value>) -> Vec<String> {
+    gpus.and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|gpu| {
+            let mut summary = gpu
+                .get("vendor")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown")
+                .to_string();
+            if let Some(name) = gpu.get("name").and_then(Value::as_str) {
+                summary.push(' ');
+                summary.push_str(name);
+            }
+            if let Some(vram) = gpu.get("vram_total_mb").and_then(Value::as_u64) {
+                summary.push_str(&format!(" ({vram} MB)"));
+            }
+            summary
+        })
+        .collect()
 }
 
-impl RuntimeRegistryAdapter {
-    fn new(
-        inner: Arc<hypr_claw_tools::ToolRegistryImpl>,
-        allowed_tools: Arc<RwLock<HashSet<String>>>,
-    ) -> Self {
-        Self {
-            inner,
-            allowed_tools,
-        }
+fn build_capability_registry(profile: &Value) -> Value {
+    let profile_scanned_at = profile
+        .pointer("/scanned_at")
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0);
+
+    let has_deep_scan = profile.pointer("/deep_scan").is_some();
+
+    let wallpaper_backends =
+        read_string_array_from_value(profile.pointer("/capabilities/wallpaper_backends"));
+    let screenshot_backends =
+        read_string_array_from_value(profile.pointer("/capabilities/screenshot_backends"));
+    let input_backends =
+        read_string_array_from_value(profile.pointer("/capabilities/input_backends"));
+    let launcher_commands = read_string_array_from_value(
+        profile.pointer("/deep_scan/desktop_apps/launcher_commands_sample"),
+    );
+    let known_projects =
+        read_string_array_from_value(profile.pointer("/deep_scan/home_inventory/project_roots"));
+
+    let available_commands = profile
+        .pointer("/commands")
+        .and_then(|v| v.as_object())
+        .map(|obj| {
+            obj.iter()
+                .filter_map(|(name, enabled)| {
+                    enabled.as_bool().unwrap_or(false).then_some(name.clone())
+                })
+                .collect::<Vec<String>>()
+        })
+        .unwrap_or_default();
+
+    let vscode_command = ["code", "codium", "code-oss", "vscodium", "code-insiders"]
+        .iter()
+        .find(|name| available_commands.iter().any(|cmd| cmd == **name))
+        .map(|s| (*s).to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    let preferred_launchers =
+        build_preferred_launchers(&launcher_commands, &available_commands, &vscode_command);
+
+    json!({
+        "schema_version": 1,
+        "generated_at": chrono::Utc::now().timestamp(),
+        "source_profile_scanned_at": profile_scanned_at,
+        "source_has_deep_scan": has_deep_scan,
+        "platform": {
+            "distro_name": profile.pointer("/platform/distro_name").and_then(|v| v.as_str()).unwrap_or("unknown"),
+            "kernel": profile.pointer("/platform/kernel").and_then(|v| v.as_str()).unwrap_or("unknown"),
+            "arch": profile.pointer("/platform/arch").and_then(|v| v.as_str()).unwrap_or("unknown"),
+            "ui_language": profile.pointer("/desktop/ui_language").and_then(|v| v.as_str()).unwrap_or(""),
+            "keyboard_layout": profile.pointer("/desktop/keyboard_layout").and_then(|v| v.as_str()).unwrap_or(""),
+            "displays": display_summaries(profile.pointer("/desktop/displays")),
+            "gpus": gpu_summaries(profile.pointer("/hardware/gpus")),
+            "hyprland_available": profile.pointer("/desktop/hyprland_available").and_then(|v| v.as_bool()).unwrap_or(false),
+            "active_workspace": profile.pointer("/desktop/active_workspace").and_then(|v| v.as_u64()).unwrap_or(0),
+            "workspace_count": profile.pointer("/desktop/workspace_count").and_then(|v| v.as_u64()).unwrap_or(0)
+        },
+        "paths": {
+            "home": profile.pointer("/paths/home").and_then(|v| v.as_str()).unwrap_or(""),
+            "downloads": profile.pointer("/paths/downloads").and_then(|v| v.as_str()).unwrap_or(""),
+            "pictures": profile.pointer("/paths/pictures").and_then(|v| v.as_str()).unwrap_or(""),
+            "documents": profile.pointer("/paths/documents").and_then(|v| v.as_str()).unwrap_or("")
+        },
+        "capabilities": {
+            "wallpaper_backends": wallpaper_backends,
+            "screenshot_backends": screenshot_backends,
+            "input_backends": input_backends,
+            "ocr_available": profile.pointer("/capabilities/ocr_available").and_then(|v| v.as_bool()).unwrap_or(false),
+            "ocr_languages": read_string_array_from_value(profile.pointer("/capabilities/ocr_languages"))
+        },
+        "editor": {
+            "vscode_command": vscode_command
+        },
+        "desktop_apps": {
+            "launcher_commands": launcher_commands,
+            "preferred_launchers": preferred_launchers
+        },
+        "projects": {
+            "known_roots": known_projects
+        },
+        "hyprland": {
+            "main_config": profile.pointer("/deep_scan/hyprland/main_config").and_then(|v| v.as_str()).unwrap_or(""),
+            "binds_sample": profile.pointer("/deep_scan/hyprland/binds_sample").cloned().unwrap_or_else(|| json!([])),
+            "exec_once_sample": profile.pointer("/deep_scan/hyprland/exec_once_sample").cloned().unwrap_or_else(|| json!([])),
+            "workspace_rules_sample": profile.pointer("/deep_scan/hyprland/workspace_rules_sample").cloned().unwrap_or_else(|| json!([]))
+        },
+        "packages": {
+            "total_count": profile.pointer("/deep_scan/packages/total_count").and_then(|v| v.as_u64()).unwrap_or(0),
+            "pacman_explicit_count": profile.pointer("/deep_scan/packages/pacman_explicit_count").and_then(|v| v.as_u64()).unwrap_or(0),
+            "aur_count": profile.pointer("/deep_scan/packages/aur_count").and_then(|v| v.as_u64()).unwrap_or(0)
+        },
+        "commands": {
+            "available": available_commands
+        },
+        "usage": {
+            "top_commands": profile.pointer("/deep_scan/usage/shell_history_top").cloned().unwrap_or_else(|| json!([]))
+        }
+    })
+}
+
+fn build_preferred_launchers(
+    launcher_commands: &[String],
+    available_commands: &[String],
+    vscode_command: &str,
+) -> Value {
+    let mut known = std::collections::BTreeSet::new();
+    for command in launcher_commands {
+        known.insert(command.clone());
+    }
+    for command in available_commands {
+        known.insert(command.clone());
+    }
+
+    let pick = |candidates: &[&str]| -> String {
+        candidates
+            .iter()
+            .find(|candidate| known.contains(**candidate))
+            .map(|candidate| (*candidate).to_string())
+            .unwrap_or_else(|| "unknown".to_string())
+    };
+
+    let vscode = if vscode_command == "unknown" {
+        pick(&["code", "code-insiders", "code-oss", "codium", "vscodium"])
+    } else {
+        vscode_command.to_string()
+    };
+
+    json!({
+        "vscode": vscode,
+        "browser": pick(&["firefox", "google-chrome-stable", "google-chrome", "chromium", "brave-browser"]),
+        "telegram": pick(&["telegram-desktop", "telegram", "org.telegram.desktop"]),
+        "terminal": pick(&["kitty", "alacritty", "wezterm", "gnome-terminal", "xterm"])
+    })
+}
+
+fn capability_registry_needs_refresh(registry: &Value, profile: &Value) -> bool {
+    if registry.pointer("/schema_version").is_none() {
+        return true;
+    }
+    if registry.pointer("/paths/downloads").is_none() {
+        return true;
+    }
+    if registry
+        .pointer("/capabilities/wallpaper_backends")
+        .is_none()
+    {
+        return true;
+    }
+    if registry
+        .pointer("/desktop_apps/preferred_launchers")
+        .is_none()
+    {
+        return true;
+    }
+
+    let profile_scanned_at = profile
+        .pointer("/scanned_at")
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0);
+    let registry_profile_scanned_at = registry
+        .pointer("/source_profile_scanned_at")
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0);
+
+    if profile_scanned_at > registry_profile_scanned_at {
+        return true;
+    }
+
+    let profile_has_deep = profile.pointer("/deep_scan").is_some();
+    let registry_has_deep = registry
+        .pointer("/source_has_deep_scan")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    profile_has_deep && !registry_has_deep
+}
+
+fn load_capability_registry(user_id: &str) -> io::Result<Value> {
+    let path = capability_registry_file_path(user_id);
+    let raw = std::fs::read_to_string(path)?;
+    serde_json::from_str::<Value>(&raw)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+}
+
+fn save_capability_registry(user_id: &str, registry: &Value) -> io::Result<()> {
+    std::fs::create_dir_all(data_dir::path("./data/capabilities"))?;
+    let path = capability_registry_file_path(user_id);
+    let payload = serde_json::to_string_pretty(registry)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    std::fs::write(path, payload)
+}
+
+/// Appends a timestamped capability delta record to the user's delta history (JSONL).
+fn append_capability_delta_history(
+    user_id: &str,
+    old_registry: &Value,
+    new_registry: &Value,
+) -> io::Result<()> {
+    std::fs::create_dir_all(data_dir::path("./data/capabilities"))?;
+    let path = data_dir::path(format!(
+        "./data/capabilities/{}.deltas.jsonl",
+        sanitize_user_key_for_filename(user_id)
+    ));
+    let diff_lines = capability_registry_diff_lines(old_registry, new_registry);
+    let summary = if diff_lines.is_empty() {
+        "no changes".to_string()
+    } else {
+        format!("{} change(s)", diff_lines.len())
+    };
+    let at = chrono::Utc::now().timestamp();
+    let record = json!({
+        "at": at,
+        "summary": summary,
+        "diff_lines": diff_lines,
+    });
+    let line = serde_json::to_string(&record)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    let mut content = line;
+    content.push('\n');
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(content.as_bytes())
+}
+
+/// Reads the last N lines from the user's capability deltas JSONL file (newest last in file).
+fn read_capability_delta_history(user_id: &str, limit: usize) -> Vec<Value> {
+    let path = data_dir::path(format!(
+        "./data/capabilities/{}.deltas.jsonl",
+        sanitize_user_key_for_filename(user_id)
+    ));
+    let content = match std::fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(_) => return Vec::new(),
+    };
+    let lines: Vec<&str> = content.lines().filter(|s| !s.trim().is_empty()).collect();
+    let start = lines.len().saturating_sub(limit);
+    lines[start..]
+        .iter()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+fn print_capability_delta_history(user_id: &str, limit: usize) {
+    let records = read_capability_delta_history(user_id, limit);
+    println!("\n{}", ui_title("Capability delta history"));
+    if records.is_empty() {
+        println!("  No delta history yet. Run `scan` and apply changes to record deltas.");
+        return;
+    }
+    for (i, rec) in records.iter().enumerate() {
+        let at = rec.get("at").and_then(|v| v.as_i64()).unwrap_or(0);
+        let summary = rec.get("summary").and_then(|v| v.as_str()).unwrap_or("?");
+        println!("  {}  {}  {}", i + 1, format_timestamp(at), summary);
+        if let Some(arr) = rec.get("diff_lines").and_then(|v| v.as_array()) {
+            for line in arr.iter().take(5) {
+                if let Some(s) = line.as_str() {
+                    println!("      - {}", truncate_for_table(s, 72));
+                }
+            }
+            if arr.len() > 5 {
+                println!("      ... and {} more", arr.len() - 5);
+            }
+        }
+    }
+    println!();
+}
+
+/// What `actions [list | <run_id>] [--follow]` asks for.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct ActionsRequest {
+    run_id: Option<u64>,
+    list: bool,
+    follow: bool,
+}
+
+fn parse_actions_args(arg: &str) -> Result<ActionsRequest, String> {
+    let mut request = ActionsRequest::default();
+    for word in arg.split_whitespace() {
+        match word {
+            "list" => request.list = true,
+            "--follow" | "-f" => request.follow = true,
+            id => {
+                request.run_id = Some(
+                    id.parse()
+                        .map_err(|_| "Use: actions [list | <run_id>] [--follow]".to_string())?,
+                )
+            }
+        }
+    }
+    Ok(request)
+}
+
+/// Print a run's action log; with `--follow`, keep printing new actions
+/// until the run ends or `stop` fires.
+async fn show_actions(
+    log: &action_log::ActionLog,
+    arg: &str,
+    stop: &tokio::sync::Notify,
+) -> Result<(), String> {
+    let request = parse_actions_args(arg)?;
+    let runs = log.runs().map_err(|e| e.to_string())?;
+    if request.list {
+        println!("\n{}", ui_title("Logged runs"));
+        for run_id in runs.iter().rev().take(20) {
+            let summary = log
+                .load(*run_id)
+                .ok()
+                .and_then(|events| events.first().map(action_log::render_event))
+                .unwrap_or_default();
+            println!("  {}", summary);
+        }
+        println!();
+        return Ok(());
+    }
+    let Some(run_id) = request.run_id.or_else(|| runs.last().copied()) else {
+        return Err("No runs logged yet.".to_string());
+    };
+    let mut reader = action_log::RunReader::open(&log.run_path(run_id))
+        .map_err(|e| format!("No action log for run {run_id}: {e}"))?;
+    println!("\n{}", ui_title(&format!("Actions for run {run_id}")));
+    loop {
+        let events = reader.read_new().map_err(|e| e.to_string())?;
+        let ended = events
+            .iter()
+            .any(|event| matches!(event, action_log::ActionEvent::End { .. }));
+        for event in &events {
+            println!("{}", action_log::render_event(event));
+        }
+        if ended || !request.follow {
+            break;
+        }
+        tokio::select! {
+            _ = stop.notified() => break,
+            _ = tokio::time::sleep(Duration::from_millis(500)) => {}
+        }
+    }
+    println!();
+    Ok(())
+}
+
+/// `hypr-claw observe [--json]`: follow the runs of the instance running
+/// elsewhere, one after another, until `stop` resolves. Only the action logs
+/// are read, so an observer can watch but never steer the session. `--json`
+/// prints the raw events for other front ends.
+async fn observe_runs(
+    log: &action_log::ActionLog,
+    arg: &str,
+    stop: impl std::future::Future<Output = ()>,
+) -> Result<(), String> {
+    let json = match arg.trim() {
+        "" => false,
+        "--json" => true,
+        _ => return Err("Use: hypr-claw observe [--json]".to_string()),
+    };
+    if !json {
+        println!(
+            "\n{}",
+            ui_title("Observing runs (read-only, Ctrl-C to stop)")
+        );
+    }
+    tokio::pin!(stop);
+    let mut follower = action_log::RunFollower::new(log).map_err(|e| e.to_string())?;
+    loop {
+        // Runs in other threads go on alongside the foreground one, so every
+        // line says which run it belongs to.
+        for (run_id, event) in follower.poll(log).map_err(|e| e.to_string())? {
+            if json {
+                let mut value = serde_json::to_value(&event).map_err(|e| e.to_string())?;
+                value["run_id"] = json!(run_id);
+                println!("{}", value);
+            } else {
+                println!("[run {}] {}", run_id, action_log::render_event(&event));
+                if matches!(event, action_log::ActionEvent::End { .. }) {
+                    println!();
+                }
+            }
+        }
+        tokio::select! {
+            _ = &mut stop => break,
+            _ = tokio::time::sleep(Duration::from_millis(500)) => {}
+        }
+    }
+    Ok(())
+}
+
+const AUDIT_QUERY_LIMIT: usize = 20;
+
+/// `audit [tool] [<hours>h]` as a query ending at `now`.
+fn parse_audit_query(
+    arg: &str,
+    now: i64,
+) -> Result<hypr_claw::infra::audit_logger::AuditQuery, String> {
+    let mut query = hypr_claw::infra::audit_logger::AuditQuery {
+        limit: Some(AUDIT_QUERY_LIMIT),
+        ..Default::default()
+    };
+    for word in arg.split_whitespace() {
+        if let Some(hours) = word.strip_suffix('h').and_then(|h| h.parse::<i64>().ok()) {
+            query.since = Some(now - hours * 3600);
+        } else if query.tool.is_none() {
+            query.tool = Some(word.to_string());
+        } else {
+            return Err("Use: audit [tool] [<hours>h], e.g. audit fs.delete 24h".to_string());
+        }
+    }
+    Ok(query)
+}
+
+fn print_audit_entries(entries: &[hypr_claw::infra::contracts::AuditEntry]) {
+    println!("\n{}", ui_title("Audit log"));
+    if entries.is_empty() {
+        println!("No matching entries.\n");
+        return;
+    }
+    for entry in entries {
+        let status = entry
+            .result
+            .get("status")
+            .and_then(|v| v.as_str())
+            .unwrap_or("-");
+        println!(
+            "{} {:<28} {:<8} {:<16} {}",
+            ui_dim(entry.timestamp.get(..19).unwrap_or(&entry.timestamp)),
+            truncate_for_table(&entry.tool, 28),
+            status,
+            format!("{:?}", entry.approval),
+            truncate_for_table(&entry.session, 24)
+        );
+    }
+    println!();
+}
+
+fn analytics_file_path(user_id: &str) -> String {
+    data_dir::path(format!(
+        "./data/analytics/{}.json",
+        sanitize_user_key_for_filename(user_id)
+    ))
+    .to_string_lossy()
+    .into_owned()
+}
+
+fn print_usage_stats(tracker: &analytics::UsageTracker, available_tools: &HashSet<String>) {
+    println!("\n{}", ui_title("Usage (local only)"));
+    if !tracker.enabled() {
+        println!(
+            "Local analytics is off. Turn it on with `stats on`; counts stay in ./data/analytics."
+        );
+        return;
+    }
+    let stats = tracker.snapshot();
+    if let Some(since) = stats.since {
+        println!("Since {}", format_timestamp(since));
+    }
+    for line in stats.render(available_tools) {
+        println!("{line}");
+    }
+    println!();
+}
+
+fn capability_gap_file_path(user_id: &str) -> String {
+    data_dir::path(format!(
+        "./data/capabilities/{}.gaps.json",
+        sanitize_user_key_for_filename(user_id)
+    ))
+    .to_string_lossy()
+    .into_owned()
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+enum CapabilityGapKind {
+    /// The model asked for a tool that is not registered at all.
+    UnknownTool,
+    /// The tool exists but was filtered out by the capability registry.
+    NotAllowed,
+    /// The tool ran but found no backend to do the work.
+    MissingBackend,
+}
+
+impl CapabilityGapKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::UnknownTool => "unknown tool",
+            Self::NotAllowed => "not allowed",
+            Self::MissingBackend => "missing backend",
+        }
+    }
+}
+
+/// One capability the agent needed and did not have, aggregated over runs.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct CapabilityGap {
+    capability: String,
+    kind: CapabilityGapKind,
+    /// Backends that would unlock the capability, when known.
+    #[serde(default)]
+    install: Vec<String>,
+    /// Most recent distinct tasks that hit the gap, newest last.
+    #[serde(default)]
+    tasks: Vec<String>,
+    count: u64,
+    first_seen: i64,
+    last_seen: i64,
+    #[serde(default)]
+    last_error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+struct CapabilityGapLog {
+    #[serde(default)]
+    gaps: Vec<CapabilityGap>,
+}
+
+const CAPABILITY_GAP_TASKS_KEPT: usize = 5;
+
+impl CapabilityGapLog {
+    fn record(
+        &mut self,
+        capability: &str,
+        kind: CapabilityGapKind,
+        install: Vec<String>,
+        task: &str,
+        error: &str,
+        now: i64,
+    ) {
+        let task = truncate_for_table(&sanitize_single_line(task), 80);
+        let index = match self
+            .gaps
+            .iter()
+            .position(|gap| gap.capability == capability && gap.kind == kind)
+        {
+            Some(index) => index,
+            None => {
+                self.gaps.push(CapabilityGap {
+                    capability: capability.to_string(),
+                    kind,
+                    install: Vec::new(),
+                    tasks: Vec::new(),
+                    count: 0,
+                    first_seen: now,
+                    last_seen: now,
+                    last_error: String::new(),
+                });
+                self.gaps.len() - 1
+            }
+        };
+        let gap = &mut self.gaps[index];
+        gap.count = gap.count.saturating_add(1);
+        gap.last_seen = now;
+        gap.last_error = truncate_for_table(&sanitize_single_line(error), 160);
+        for backend in install {
+            if !gap.install.contains(&backend) {
+                gap.install.push(backend);
+            }
+        }
+        if !task.is_empty() {
+            gap.tasks.retain(|existing| existing != &task);
+            gap.tasks.push(task);
+            if gap.tasks.len() > CAPABILITY_GAP_TASKS_KEPT {
+                let drop_count = gap.tasks.len() - CAPABILITY_GAP_TASKS_KEPT;
+                gap.tasks.drain(0..drop_count);
+            }
+        }
+    }
+
+    /// Gaps ordered by how often they were hit, most frequent first.
+    fn ranked(&self) -> Vec<&CapabilityGap> {
+        let mut gaps = self.gaps.iter().collect::<Vec<_>>();
+        gaps.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then(b.last_seen.cmp(&a.last_seen))
+                .then(a.capability.cmp(&b.capability))
+        });
+        gaps
+    }
+
+    /// Drops gaps that installing `backend` fixed: missing-backend gaps that
+    /// named it, and filtered tools that are now in `available_tools`.
+    fn resolve(&mut self, backend: &str, available_tools: &HashSet<String>) -> usize {
+        let before = self.gaps.len();
+        self.gaps.retain(|gap| match gap.kind {
+            CapabilityGapKind::UnknownTool => true,
+            CapabilityGapKind::NotAllowed => !available_tools.contains(&gap.capability),
+            CapabilityGapKind::MissingBackend => !gap.install.iter().any(|b| b == backend),
+        });
+        before - self.gaps.len()
+    }
+
+    /// Backends to install, each with the number of failures it would unlock.
+    fn install_suggestions(&self) -> Vec<(String, u64)> {
+        let mut totals = BTreeMap::<String, u64>::new();
+        for gap in &self.gaps {
+            for backend in &gap.install {
+                let total = totals.entry(backend.clone()).or_insert(0);
+                *total = total.saturating_add(gap.count);
+            }
+        }
+        let mut suggestions = totals.into_iter().collect::<Vec<_>>();
+        suggestions.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        suggestions
+    }
+}
+
+fn load_capability_gaps(user_id: &str) -> CapabilityGapLog {
+    std::fs::read_to_string(capability_gap_file_path(user_id))
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn save_capability_gaps(user_id: &str, log: &CapabilityGapLog) -> io::Result<()> {
+    std::fs::create_dir_all(data_dir::path("./data/capabilities"))?;
+    let payload = serde_json::to_string_pretty(log)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    std::fs::write(capability_gap_file_path(user_id), payload)
+}
+
+/// Backends a tool depends on, mirroring the checks in `derive_runtime_allowed_tools`.
+fn backends_for_tool(tool_name: &str) -> Vec<&'static str> {
+    match tool_name {
+        "wallpaper.set" => vec!["swww"],
+        "desktop.capture_screen" => vec!["grim"],
+        "desktop.ocr_screen"
+        | "desktop.find_text"
+        | "desktop.wait_for_text"
+        | "desktop.launch_app_and_wait_text" => vec!["grim", "tesseract"],
+        "desktop.click_text" => vec!["grim", "tesseract", "ydotool"],
+        "desktop.type_text" | "desktop.key_press" | "desktop.key_combo" | "form.fill" => {
+            vec!["wtype"]
+        }
+        "desktop.mouse_click" | "desktop.mouse_move" | "desktop.click_at" => vec!["ydotool"],
+        "desktop.mouse_move_and_verify" | "desktop.click_at_and_verify" => {
+            vec!["ydotool", "hyprland"]
+        }
+        "desktop.cursor_position" | "desktop.read_screen_state" | "system.displays" => {
+            vec!["hyprland"]
+        }
+        "desktop.window_snapshot" | "desktop.window_diff" => vec!["grim", "hyprland"],
+        "code.run_snippet" => vec!["bwrap"],
+        name if name.starts_with("hypr.") => vec!["hyprland"],
+        _ => Vec::new(),
+    }
+}
+
+/// Backends `capabilities install` knows how to install.
+const INSTALLABLE_BACKENDS: &[&str] = &[
+    "grim",
+    "hyprshot",
+    "tesseract",
+    "wtype",
+    "ydotool",
+    "wlrctl",
+    "swww",
+    "hyprpaper",
+    "bwrap",
+];
+
+/// Distro packages that provide `backend` under `manager`; `tesseract-<lang>`
+/// is an OCR language pack.
+fn packages_for_backend(backend: &str, manager: &str) -> Vec<String> {
+    if let Some(lang) = ocr_language_backend(backend) {
+        return vec![hypr_claw_tools::os_capabilities::ocr::language_pack(
+            lang, manager,
+        )];
+    }
+    let packages: &[&str] = match (backend, manager) {
+        ("tesseract", "pacman") => &["tesseract", "tesseract-data-eng"],
+        ("tesseract", "apt-get") => &["tesseract-ocr", "tesseract-ocr-eng"],
+        ("tesseract", "dnf") => &["tesseract", "tesseract-langpack-eng"],
+        ("bwrap", _) => &["bubblewrap"],
+        _ => &[backend],
+    };
+    packages.iter().map(|p| p.to_string()).collect()
+}
+
+/// The backend to install for `capabilities install [backend]`: the one
+/// named, or else the installable backend that unlocks the most failures.
+fn resolve_install_target(arg: &str, gaps: &CapabilityGapLog) -> Result<String, String> {
+    let arg = arg.trim();
+    if arg.is_empty() {
+        return gaps
+            .install_suggestions()
+            .into_iter()
+            .map(|(backend, _)| backend)
+            .find(|backend| INSTALLABLE_BACKENDS.contains(&backend.as_str()))
+            .ok_or_else(|| {
+                format!(
+                    "No recorded gap needs an installable backend. Use: capabilities install <{}>",
+                    INSTALLABLE_BACKENDS.join("|")
+                )
+            });
+    }
+    if INSTALLABLE_BACKENDS.contains(&arg) || ocr_language_backend(arg).is_some() {
+        return Ok(arg.to_string());
+    }
+    Err(format!(
+        "Unknown backend '{}'. Installable: {}, tesseract-<lang> (OCR language pack)",
+        arg,
+        INSTALLABLE_BACKENDS.join(", ")
+    ))
+}
+
+/// The language of a `tesseract-<lang>` backend, e.g. `deu` or `chi_sim`.
+fn ocr_language_backend(backend: &str) -> Option<&str> {
+    backend
+        .strip_prefix("tesseract-")
+        .filter(|lang| !lang.contains('+'))
+        .filter(|lang| hypr_claw_tools::os_capabilities::ocr::valid_language(lang))
+}
+
+/// Apply the `ocr` config. Without a configured language, OCR reads the
+/// desktop's UI language (plus English) when its pack is installed.
+fn configure_ocr(settings: &hypr_claw_tools::os_capabilities::ocr::OcrSettings, registry: &Value) {
+    use hypr_claw_tools::os_capabilities::ocr;
+
+    let mut settings = settings.clone();
+    if settings.language.is_none() {
+        let installed =
+            read_string_array_from_value(registry.pointer("/capabilities/ocr_languages"));
+        let ui_language = registry
+            .pointer("/platform/ui_language")
+            .and_then(Value::as_str);
+        settings.language = ocr::default_language(ui_language, &installed);
+    }
+    ocr::configure(settings);
+}
+
+/// Backends named by a tool error that reports a missing backend, or `None`
+/// when the error is about something else.
+fn missing_backend_hint(error: &str) -> Option<Vec<String>> {
+    let lower = error.to_ascii_lowercase();
+    if let Some(start) = lower.find("(install ") {
+        let hint = &error[start..];
+        let hint = &hint[..hint.find(')').unwrap_or(hint.len())];
+        let names = hint
+            .split('\'')
+            .skip(1)
+            .step_by(2)
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        return Some(names);
+    }
+    if lower.contains("backend") && (lower.contains("found") || lower.contains("succeeded")) {
+        let names = lower
+            .find("(tried: ")
+            .map(|start| {
+                let tried = &error[start + "(tried: ".len()..];
+                tried[..tried.find(')').unwrap_or(tried.len())]
+                    .split(',')
+                    .filter_map(|item| item.split_whitespace().next())
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        return Some(names);
+    }
+    if let Some(end) = lower.find(" is not installed") {
+        let command = error[..end].split_whitespace().last().unwrap_or_default();
+        return Some(if command.is_empty() {
+            Vec::new()
+        } else {
+            vec![command.to_string()]
+        });
+    }
+    None
+}
+
+/// Classifies a failed tool call as a capability gap, if it is one.
+fn detect_capability_gap(
+    tool_name: &str,
+    error: &str,
+    available_tools: &HashSet<String>,
+) -> Option<(CapabilityGapKind, Vec<String>)> {
+    if error.contains("Tool not found") {
+        return Some((CapabilityGapKind::UnknownTool, Vec::new()));
+    }
+    let known_backends = || {
+        backends_for_tool(tool_name)
+            .into_iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    if let Some(named) = missing_backend_hint(error) {
+        let install = if named.is_empty() {
+            known_backends()
+        } else {
+            named
+        };
+        return Some((CapabilityGapKind::MissingBackend, install));
+    }
+    if !available_tools.contains(tool_name) {
+        return Some((CapabilityGapKind::NotAllowed, known_backends()));
+    }
+    None
+}
+
+fn print_capability_gaps(log: &CapabilityGapLog) {
+    println!("\n{}", ui_title("Capability gaps"));
+    if log.gaps.is_empty() {
+        println!(
+            "  No gaps recorded. Failed tool calls for missing tools or backends show up here."
+        );
+        println!();
+        return;
+    }
+    for gap in log.ranked() {
+        println!(
+            "  {:>4}x  {:<32} {:<15} last {}",
+            gap.count,
+            truncate_for_table(&gap.capability, 32),
+            gap.kind.as_str(),
+            format_timestamp(gap.last_seen)
+        );
+        if !gap.install.is_empty() {
+            println!("         install: {}", gap.install.join(", "));
+        }
+        if let Some(task) = gap.tasks.last() {
+            println!("         task   : {}", task);
+        }
+        if !gap.last_error.is_empty() {
+            println!(
+                "         error  : {}",
+                truncate_for_table(&gap.last_error, 92)
+            );
+        }
+    }
+    let suggestions = log.install_suggestions();
+    if !suggestions.is_empty() {
+        println!("  {}", ui_accent("Install to unlock"));
+        for (backend, failures) in suggestions {
+            println!("    {:<16} {} failed call(s)", backend, failures);
+        }
+    }
+    println!();
+}
+
+fn print_ydotool_health(health: &hypr_claw_tools::os_capabilities::ydotool::YdotoolHealth) {
+    let yes_no = |value: bool| if value { "yes" } else { "no" };
+    println!("\n{}", ui_title("Input daemon (ydotool)"));
+    println!(
+        "  status       : {}",
+        if health.ready() {
+            ui_success("ready")
+        } else {
+            ui_warn("not ready")
+        }
+    );
+    println!(
+        "  installed    : ydotool={} ydotoold={}",
+        yes_no(health.client_installed),
+        yes_no(health.daemon_installed)
+    );
+    println!("  daemon       : {}", yes_no(health.daemon_running));
+    println!(
+        "  socket       : {}",
+        health
+            .socket
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| "not found".to_string())
+    );
+    println!(
+        "  /dev/uinput  : writable={}",
+        yes_no(health.uinput_writable)
+    );
+    println!("  user service : {}", yes_no(health.service_installed));
+    for problem in health.problems() {
+        println!("  - {}", problem);
+    }
+    if !health.ready() && health.daemon_installed && !health.service_installed {
+        println!("  Run `capabilities input setup` to create and start the ydotoold user service.");
+    } else if !health.client_installed {
+        println!("  Run `capabilities install ydotool` to install it.");
+    }
+    println!();
+}
+
+/// Offers to create the ydotoold user service, then reports health.
+/// Rounds of `setup apply` before it gives up on reaching the manifest.
+const SETUP_MAX_ROUNDS: usize = 3;
+
+async fn observe_setup_state(
+    manifest: &setup_manifest::SetupManifest,
+    capability_registry: &Value,
+    allowed_tools: &HashSet<String>,
+) -> setup_manifest::Observed {
+    let mut backends: BTreeSet<String> = [
+        "/capabilities/wallpaper_backends",
+        "/capabilities/screenshot_backends",
+        "/capabilities/input_backends",
+    ]
+    .into_iter()
+    .flat_map(|pointer| read_string_array_from_value(capability_registry.pointer(pointer)))
+    .collect();
+    for (backend, pointer) in [
+        ("tesseract", "/capabilities/ocr_available"),
+        ("hyprland", "/platform/hyprland_available"),
+    ] {
+        if capability_registry
+            .pointer(pointer)
+            .and_then(Value::as_bool)
+            == Some(true)
+        {
+            backends.insert(backend.to_string());
+        }
+    }
+    let home = std::env::var("HOME").ok().map(std::path::PathBuf::from);
+    let mut active_services = BTreeSet::new();
+    for service in &manifest.services {
+        let mut command = tokio::process::Command::new("systemctl");
+        if service.user() {
+            command.arg("--user");
+        }
+        let active = command
+            .args(["is-active", "--quiet", service.name()])
+            .status()
+            .await
+            .is_ok_and(|status| status.success());
+        if active {
+            active_services.insert(service.name().to_string());
+        }
+    }
+    setup_manifest::Observed {
+        backends,
+        tools: allowed_tools.iter().cloned().collect(),
+        commands: manifest
+            .packages
+            .iter()
+            .map(|package| package.command())
+            .filter(|command| setup_manifest::on_path(command))
+            .map(str::to_string)
+            .collect(),
+        active_services,
+        matching_files: manifest
+            .files
+            .iter()
+            .filter(|file| {
+                std::fs::read_to_string(setup_manifest::expand_home(&file.path, home.as_deref()))
+                    .is_ok_and(|content| content == file.content)
+            })
+            .map(|file| file.path.clone())
+            .collect(),
+    }
+}
+
+/// Run one setup step through the dispatcher, so it gets the same permission
+/// checks and approvals as a tool call from the agent.
+async fn run_setup_step(
+    dispatcher: &dyn hypr_claw_runtime::ToolDispatcher,
+    step: &setup_manifest::SetupStep,
+    session_key: &str,
+) -> Result<(), hypr_claw_runtime::RuntimeError> {
+    use setup_manifest::SetupStep;
+
+    match step {
+        SetupStep::InstallPackages { packages, .. } => {
+            dispatcher
+                .execute(
+                    "system.package_install",
+                    &json!({ "packages": packages }),
+                    session_key,
+                )
+                .await?;
+        }
+        SetupStep::WriteFile { path, content } => {
+            let home = std::env::var("HOME").ok().map(std::path::PathBuf::from);
+            let path = setup_manifest::expand_home(path, home.as_deref());
+            if let Some(parent) = path.parent().filter(|parent| !parent.exists()) {
+                dispatcher
+                    .execute("fs.create_dir", &json!({ "path": parent }), session_key)
+                    .await?;
+            }
+            dispatcher
+                .execute(
+                    "fs.write",
+                    &json!({ "path": path, "content": content }),
+                    session_key,
+                )
+                .await?;
+        }
+        SetupStep::EnableService { name, user } => {
+            let mut args = vec!["enable", "--now", name.as_str()];
+            if *user {
+                args.insert(0, "--user");
+            }
+            dispatcher
+                .execute(
+                    "proc.spawn",
+                    &json!({ "command": "systemctl", "args": args }),
+                    session_key,
+                )
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Re-scan the system after an install and swap in the new capability
+/// registry, printing what changed and recording the delta.
+async fn rescan_capability_registry(
+    user_id: &str,
+    agent_state: &mut AgentOsState,
+    capability_registry: &mut Value,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("🔎 Re-scanning capabilities...");
+    let mut scanned_profile = scan::run_integrated_scan(user_id, false).await?;
+    if let (Some(deep), Some(obj)) = (
+        agent_state
+            .onboarding
+            .system_profile
+            .get("deep_scan")
+            .cloned(),
+        scanned_profile.as_object_mut(),
+    ) {
+        obj.insert("deep_scan".to_string(), deep);
+    }
+    let scanned_registry = build_capability_registry(&scanned_profile);
+    print_capability_registry_diff_summary(capability_registry, &scanned_registry);
+    agent_state.onboarding.system_profile = scanned_profile;
+    agent_state.onboarding.last_scan_at = Some(chrono::Utc::now().timestamp());
+    let old_registry_for_history = std::mem::replace(capability_registry, scanned_registry);
+    if let Err(e) = save_capability_registry(user_id, capability_registry) {
+        eprintln!("⚠️  Failed to save capability registry: {}", e);
+    }
+    if let Err(e) =
+        append_capability_delta_history(user_id, &old_registry_for_history, capability_registry)
+    {
+        eprintln!("⚠️  Failed to append capability delta history: {}", e);
+    }
+    Ok(())
+}
+
+async fn setup_ydotool_service() -> io::Result<()> {
+    use hypr_claw_tools::os_capabilities::ydotool;
+
+    let health = ydotool::health().await;
+    if health.ready() {
+        println!("✅ ydotool is ready ({} running)", ydotool::SERVICE_NAME);
+        return Ok(());
+    }
+    if !health.daemon_installed {
+        print_ydotool_health(&health);
+        return Ok(());
+    }
+    if !health.service_installed {
+        println!(
+            "ydotool needs its daemon running. This writes ~/.config/systemd/user/{} and enables it with systemctl --user.",
+            ydotool::SERVICE_NAME
+        );
+        if !prompt_yes_no("Create and start the ydotoold user service? [y/N] ", false)? {
+            println!("⏭ ydotoold service setup skipped.");
+            return Ok(());
+        }
+        match ydotool::install_user_service().await {
+            Ok(path) => println!("✅ Created {}", path.display()),
+            Err(e) => eprintln!("❌ Failed to create ydotoold service: {}", e),
+        }
+    }
+    print_ydotool_health(&ydotool::ensure_daemon().await);
+    Ok(())
+}
+
+fn print_capability_registry_summary(user_id: &str, registry: &Value) {
+    let generated = registry
+        .pointer("/generated_at")
+        .and_then(|v| v.as_i64())
+        .map(format_timestamp)
+        .unwrap_or_else(|| "n/a".to_string());
+    let distro = registry
+        .pointer("/platform/distro_name")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown");
+    let kernel = registry
+        .pointer("/platform/kernel")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown");
+    let workspace = registry
+        .pointer("/platform/active_workspace")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    let downloads = registry
+        .pointer("/paths/downloads")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    let wallpaper =
+        read_string_array_from_value(registry.pointer("/capabilities/wallpaper_backends"));
+    let screenshot =
+        read_string_array_from_value(registry.pointer("/capabilities/screenshot_backends"));
+    let input = read_string_array_from_value(registry.pointer("/capabilities/input_backends"));
+    let launchers =
+        read_string_array_from_value(registry.pointer("/desktop_apps/launcher_commands"));
+    let preferred_launchers = registry
+        .pointer("/desktop_apps/preferred_launchers")
+        .and_then(|v| v.as_object())
+        .map(|obj| {
+            obj.iter()
+                .filter_map(|(k, v)| v.as_str().map(|value| format!("{k}={value}")))
+                .collect::<Vec<String>>()
+        })
+        .unwrap_or_default();
+    let registry_path = capability_registry_file_path(user_id);
+
+    println!("\n{}", ui_title("Capability Registry"));
+    println!("  file         : {}", ui_dim(&registry_path));
+    println!("  generated_at : {}", generated);
+    println!(
+        "  platform     : {} | kernel {} | workspace {}",
+        distro, kernel, workspace
+    );
+    println!("  downloads    : {}", downloads);
+    println!(
+        "  backends     : wallpaper=[{}] screenshot=[{}] input=[{}]",
+        wallpaper.join(", "),
+        screenshot.join(", "),
+        input.join(", ")
+    );
+    println!(
+        "  launcher cmds: {}",
+        truncate_for_table(&launchers.join(", "), 92)
+    );
+    println!(
+        "  preferred    : {}",
+        truncate_for_table(&preferred_launchers.join(", "), 92)
+    );
+    let ocr_languages =
+        read_string_array_from_value(registry.pointer("/capabilities/ocr_languages"));
+    let ui_language = registry
+        .pointer("/platform/ui_language")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    let keyboard_layout = registry
+        .pointer("/platform/keyboard_layout")
+        .and_then(|v| v.as_str())
+        .filter(|layout| !layout.is_empty())
+        .unwrap_or("unknown");
+    println!("  keyboard     : {}", keyboard_layout);
+    let displays = read_string_array_from_value(registry.pointer("/platform/displays"));
+    if !displays.is_empty() {
+        println!("  displays     : {}", displays.join(", "));
+    }
+    let gpus = read_string_array_from_value(registry.pointer("/platform/gpus"));
+    if !gpus.is_empty() {
+        println!("  gpu          : {}", gpus.join(", "));
+    }
+    println!(
+        "  ocr          : languages=[{}] default={} ui={}",
+        ocr_languages.join(", "),
+        hypr_claw_tools::os_capabilities::ocr::language(None).unwrap_or_else(|| "eng".to_string()),
+        if ui_language.is_empty() {
+            "unknown"
+        } else {
+            ui_language
+        }
+    );
+    if let Some(code) = hypr_claw_tools::os_capabilities::ocr::language_for_locale(ui_language)
+        .filter(|code| !ocr_languages.iter().any(|have| have == code))
+    {
+        println!(
+            "  OCR has no pack for the UI language; run `capabilities install tesseract-{}`.",
+            code
+        );
+    }
+    println!();
+}
+
+fn capability_registry_diff_lines(old_registry: &Value, new_registry: &Value) -> Vec<String> {
+    let old_distro = old_registry
+        .pointer("/platform/distro_name")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown");
+    let new_distro = new_registry
+        .pointer("/platform/distro_name")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown");
+    let old_kernel = old_registry
+        .pointer("/platform/kernel")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown");
+    let new_kernel = new_registry
+        .pointer("/platform/kernel")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown");
+    let old_workspace = old_registry
+        .pointer("/platform/active_workspace")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    let new_workspace = new_registry
+        .pointer("/platform/active_workspace")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+
+    let old_wallpaper =
+        read_string_array_from_value(old_registry.pointer("/capabilities/wallpaper_backends"));
+    let new_wallpaper =
+        read_string_array_from_value(new_registry.pointer("/capabilities/wallpaper_backends"));
+    let old_screenshot =
+        read_string_array_from_value(old_registry.pointer("/capabilities/screenshot_backends"));
+    let new_screenshot =
+        read_string_array_from_value(new_registry.pointer("/capabilities/screenshot_backends"));
+    let old_input =
+        read_string_array_from_value(old_registry.pointer("/capabilities/input_backends"));
+    let new_input =
+        read_string_array_from_value(new_registry.pointer("/capabilities/input_backends"));
+
+    let old_launchers =
+        read_string_array_from_value(old_registry.pointer("/desktop_apps/launcher_commands"));
+    let new_launchers =
+        read_string_array_from_value(new_registry.pointer("/desktop_apps/launcher_commands"));
+    let old_projects = read_string_array_from_value(old_registry.pointer("/projects/known_roots"));
+    let new_projects = read_string_array_from_value(new_registry.pointer("/projects/known_roots"));
+    let old_vscode = old_registry
+        .pointer("/editor/vscode_command")
+        .and_then(|v| v.as_str())
+        .unwrap_or("none");
+    let new_vscode = new_registry
+        .pointer("/editor/vscode_command")
+        .and_then(|v| v.as_str())
+        .unwrap_or("none");
+
+    let mut changes = Vec::<String>::new();
+    if old_distro != new_distro {
+        changes.push(format!(
+            "platform distro: '{}' -> '{}'",
+            old_distro, new_distro
+        ));
+    }
+    if old_kernel != new_kernel {
+        changes.push(format!(
+            "platform kernel: '{}' -> '{}'",
+            old_kernel, new_kernel
+        ));
+    }
+    if old_workspace != new_workspace {
+        changes.push(format!(
+            "active workspace: {} -> {}",
+            old_workspace, new_workspace
+        ));
+    }
+    if old_wallpaper != new_wallpaper {
+        changes.push(format!(
+            "wallpaper backends: [{}] -> [{}]",
+            old_wallpaper.join(", "),
+            new_wallpaper.join(", ")
+        ));
+    }
+    if old_screenshot != new_screenshot {
+        changes.push(format!(
+            "screenshot backends: [{}] -> [{}]",
+            old_screenshot.join(", "),
+            new_screenshot.join(", ")
+        ));
+    }
+    if old_input != new_input {
+        changes.push(format!(
+            "input backends: [{}] -> [{}]",
+            old_input.join(", "),
+            new_input.join(", ")
+        ));
+    }
+    if old_launchers != new_launchers {
+        changes.push(format!(
+            "launcher commands changed ({} -> {} entries)",
+            old_launchers.len(),
+            new_launchers.len()
+        ));
+    }
+    if old_projects != new_projects {
+        changes.push(format!(
+            "known project roots changed ({} -> {} entries)",
+            old_projects.len(),
+            new_projects.len()
+        ));
+    }
+    if old_vscode != new_vscode {
+        changes.push(format!(
+            "vscode command: '{}' -> '{}'",
+            old_vscode, new_vscode
+        ));
+    }
+
+    changes
+}
+
+fn print_capability_registry_diff_summary(old_registry: &Value, new_registry: &Value) {
+    let changes = capability_registry_diff_lines(old_registry, new_registry);
+    println!("\n{}", ui_title("Scan Diff Summary"));
+    if changes.is_empty() {
+        println!("  no major capability changes detected");
+    } else {
+        for change in changes {
+            println!("  - {}", truncate_for_table(&change, 104));
+        }
+    }
+    println!();
+}
+
+fn next_thread_id(state: &AgentOsState) -> String {
+    let max_id = state
+        .task_threads
+        .iter()
+        .filter_map(|thread| thread.id.strip_prefix("task-"))
+        .filter_map(|suffix| suffix.parse::<u32>().ok())
+        .max()
+        .unwrap_or(0);
+    format!("task-{}", max_id + 1)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ExecutionBudget {
+    max_iterations: usize,
+}
+
+fn classify_supervised_task_class(input: &str) -> SupervisedTaskClass {
+    let lower = input.to_lowercase();
+    let investigation_tokens = [
+        "diagnose",
+        "why",
+        "analyze",
+        "investigate",
+        "debug",
+        "compare",
+        "inspect",
+        "check",
+        "status",
+        "trace",
+        "benchmark",
+    ];
+    if investigation_tokens
+        .iter()
+        .any(|token| lower.contains(token))
+    {
+        return SupervisedTaskClass::Investigation;
+    }
+
+    let action_tokens = [
+        "open",
+        "create",
+        "delete",
+        "remove",
+        "move",
+        "copy",
+        "write",
+        "edit",
+        "run",
+        "build",
+        "install",
+        "switch",
+        "focus",
+        "close",
+        "search",
+        "reply",
+        "send",
+        "play",
+        "pause",
+        "lockscreen",
+        "wallpaper",
+        "volume",
+    ];
+    if action_tokens.iter().any(|token| lower.contains(token)) {
+        return SupervisedTaskClass::Action;
+    }
+
+    SupervisedTaskClass::Question
+}
+
+fn infer_supervised_task_resources(input: &str) -> Vec<String> {
+    let lower = input.to_lowercase();
+    let mut resources = Vec::<String>::new();
+
+    let add = |name: &str, resources: &mut Vec<String>| {
+        if !resources.iter().any(|existing| existing == name) {
+            resources.push(name.to_string());
+        }
+    };
+
+    if [
+        "open",
+        "launch",
+        "switch workspace",
+        "workspace",
+        "window",
+        "click",
+        "type",
+        "press",
+        "key",
+        "mouse",
+        "screen",
+        "ocr",
+        "telegram",
+        "gmail",
+        "mail",
+        "browser",
+    ]
+    .iter()
+    .any(|token| lower.contains(token))
+    {
+        add("desktop_input", &mut resources);
+    }
+
+    if [
+        "file", "folder", "dir", "read", "write", "create", "delete", "copy", "move", "project",
+    ]
+    .iter()
+    .any(|token| lower.contains(token))
+    {
+        add("filesystem", &mut resources);
+    }
+
+    if [
+        "web", "search", "download", "upload", "api", "http", "gmail", "telegram",
+    ]
+    .iter()
+    .any(|token| lower.contains(token))
+    {
+        add("network", &mut resources);
+    }
+
+    if [
+        "run",
+        "build",
+        "compile",
+        "install",
+        "process",
+        "spawn",
+        "kill",
+        "benchmark",
+        "test",
+    ]
+    .iter()
+    .any(|token| lower.contains(token))
+    {
+        add("compute", &mut resources);
+    }
+
+    if resources.is_empty() {
+        add("general", &mut resources);
+    }
+
+    resources
+}
+
+fn can_run_supervisor_in_background(task: &SupervisedTask) -> bool {
+    !task.resources.iter().any(|resource| {
+        matches!(
+            resource.as_str(),
+            "desktop_input" | "filesystem" | "general"
+        )
+    })
+}
+
+fn resource_is_shared(resource: &str) -> bool {
+    matches!(resource, "network" | "compute")
+}
+
+fn resource_conflicts(lhs: &[String], rhs: &[String]) -> Vec<String> {
+    let left = lhs
+        .iter()
+        .map(|r| r.trim().to_lowercase())
+        .filter(|r| !r.is_empty())
+        .collect::<HashSet<_>>();
+    let right = rhs
+        .iter()
+        .map(|r| r.trim().to_lowercase())
+        .filter(|r| !r.is_empty())
+        .collect::<HashSet<_>>();
+
+    if left.is_empty() || right.is_empty() {
+        return Vec::new();
+    }
+    if left.contains("general") || right.contains("general") {
+        return vec!["general".to_string()];
+    }
+
+    let mut conflicts = BTreeSet::new();
+    for resource in left.intersection(&right) {
+        if !resource_is_shared(resource) {
+            conflicts.insert(resource.to_string());
+        }
+    }
+    conflicts.into_iter().collect::<Vec<_>>()
+}
+
+fn running_supervisor_conflicts_for_resources(
+    required: &[String],
+    running: &[(String, Vec<String>)],
+) -> Vec<String> {
+    let mut details = Vec::new();
+    for (task_id, resources) in running {
+        let overlap = resource_conflicts(required, resources);
+        if !overlap.is_empty() {
+            details.push(format!(
+                "{} [{}]",
+                task_id,
+                truncate_for_table(&overlap.join("+"), 28)
+            ));
+        }
+    }
+    details
+}
+
+fn execution_budget_for_class(class: &SupervisedTaskClass, mode: &AutonomyMode) -> ExecutionBudget {
+    match mode {
+        AutonomyMode::PromptFirst => match class {
+            SupervisedTaskClass::Question => ExecutionBudget { max_iterations: 14 },
+            SupervisedTaskClass::Action => ExecutionBudget { max_iterations: 28 },
+            SupervisedTaskClass::Investigation => ExecutionBudget { max_iterations: 40 },
+        },
+        AutonomyMode::Guarded => match class {
+            SupervisedTaskClass::Question => ExecutionBudget { max_iterations: 8 },
+            SupervisedTaskClass::Action => ExecutionBudget { max_iterations: 16 },
+            SupervisedTaskClass::Investigation => ExecutionBudget { max_iterations: 24 },
+        },
+    }
+}
+
+/// Action tasks must verify their post-condition when the mode enables it.
+fn verification_required(
+    class: &SupervisedTaskClass,
+    mode: &AutonomyMode,
+    config: &Config,
+) -> bool {
+    *class == SupervisedTaskClass::Action
+        && match mode {
+            AutonomyMode::PromptFirst => config.verification.prompt_first,
+            AutonomyMode::Guarded => config.verification.guarded,
+        }
+}
+
+fn annealing_policy<'a>(mode: &AutonomyMode, config: &'a Config) -> &'a config::AnnealingPolicy {
+    match mode {
+        AutonomyMode::PromptFirst => &config.annealing.prompt_first,
+        AutonomyMode::Guarded => &config.annealing.guarded,
+    }
+}
+
+fn watchdog_timeout_for_class(class: &SupervisedTaskClass, mode: &AutonomyMode) -> Duration {
+    match mode {
+        AutonomyMode::PromptFirst => match class {
+            SupervisedTaskClass::Question => Duration::from_secs(60),
+            SupervisedTaskClass::Action => Duration::from_secs(120),
+            SupervisedTaskClass::Investigation => Duration::from_secs(210),
+        },
+        AutonomyMode::Guarded => match class {
+            SupervisedTaskClass::Question => Duration::from_secs(45),
+            SupervisedTaskClass::Action => Duration::from_secs(90),
+            SupervisedTaskClass::Investigation => Duration::from_secs(150),
+        },
+    }
+}
+
+/// Tools averaging at least this long count as slow for watchdog budgeting.
+const SLOW_TOOL_MS: f64 = 1000.0;
+
+/// Stretches `base` when the run's tools are historically slow: half of one
+/// call to the slowest tool per iteration on top, capped at double `base`.
+fn watchdog_with_tool_latency(
+    base: Duration,
+    max_iterations: usize,
+    tools: &HashSet<String>,
+    latencies: &BTreeMap<String, hypr_claw_runtime::metrics::ToolLatency>,
+) -> Duration {
+    let slowest_ms = tools
+        .iter()
+        .filter_map(|tool| latencies.get(tool))
+        .filter(|latency| latency.calls > 0)
+        .map(|latency| latency.average_ms())
+        .fold(0.0, f64::max);
+    if slowest_ms < SLOW_TOOL_MS {
+        return base;
+    }
+    let extra = Duration::from_millis((slowest_ms * max_iterations as f64 / 2.0) as u64);
+    (base + extra).min(base * 2)
+}
+
+fn next_supervised_task_id(state: &mut AgentOsState) -> String {
+    let id = format!("sup-{}", state.supervisor.next_id);
+    state.supervisor.next_id += 1;
+    id
+}
+
+fn start_supervised_task(
+    state: &mut AgentOsState,
+    prompt: String,
+    class: SupervisedTaskClass,
+) -> String {
+    let now = chrono::Utc::now().timestamp();
+    let id = next_supervised_task_id(state);
+    let resources = infer_supervised_task_resources(&prompt);
+    state.supervisor.tasks.push(SupervisedTask {
+        id: id.clone(),
+        prompt,
+        class,
+        resources,
+        background_task_id: None,
+        status: SupervisedTaskStatus::Running,
+        created_at: now,
+        updated_at: now,
+        error: None,
+        attempt_log: None,
+        priority: 0,
+        depends_on: Vec::new(),
+    });
+    id
+}
+
+fn enqueue_supervised_task(
+    state: &mut AgentOsState,
+    prompt: String,
+    class: SupervisedTaskClass,
+) -> String {
+    let now = chrono::Utc::now().timestamp();
+    let id = next_supervised_task_id(state);
+    let resources = infer_supervised_task_resources(&prompt);
+    state.supervisor.tasks.push(SupervisedTask {
+        id: id.clone(),
+        prompt,
+        class,
+        resources,
+        background_task_id: None,
+        status: SupervisedTaskStatus::Queued,
+        created_at: now,
+        updated_at: now,
+        error: None,
+        attempt_log: None,
+        priority: 0,
+        depends_on: Vec::new(),
+    });
+    id
+}
+
+/// Queue another run of `task`, carrying over what its last attempt did.
+fn enqueue_supervised_retry(state: &mut AgentOsState, task: &SupervisedTask) -> String {
+    let id = enqueue_supervised_task(state, task.prompt.clone(), task.class.clone());
+    if let Some(retry) = state.supervisor.tasks.iter_mut().find(|t| t.id == id) {
+        retry.attempt_log = task.attempt_log.clone();
+    }
+    id
+}
+
+fn record_supervised_task_attempt(state: &mut AgentOsState, task_id: &str, attempt_log: String) {
+    if let Some(task) = state
+        .supervisor
+        .tasks
+        .iter_mut()
+        .find(|task| task.id == task_id)
+    {
+        task.attempt_log = Some(attempt_log);
+    }
+}
+
+enum QueueStartResult {
+    Started(SupervisedTask),
+    Blocked(String),
+    Empty,
+}
+
+fn start_next_queued_supervised_task(state: &mut AgentOsState) -> QueueStartResult {
+    let running = state
+        .supervisor
+        .tasks
+        .iter()
+        .filter(|task| task.status == SupervisedTaskStatus::Running)
+        .map(|task| (task.id.clone(), task.resources.clone()))
+        .collect::<Vec<_>>();
+
+    cancel_supervised_tasks_with_failed_dependencies(state);
+    let statuses = state
+        .supervisor
+        .tasks
+        .iter()
+        .map(|task| (task.id.clone(), task.status.clone()))
+        .collect::<HashMap<_, _>>();
+
+    let now = chrono::Utc::now().timestamp();
+    let mut blocked_reason: Option<String> = None;
+    let mut queued = state
+        .supervisor
+        .tasks
+        .iter_mut()
+        .filter(|task| task.status == SupervisedTaskStatus::Queued)
+        .collect::<Vec<_>>();
+    // Stable, so equal priorities keep queue order.
+    queued.sort_by_key(|task| std::cmp::Reverse(task.priority));
+    for task in queued {
+        // Dependencies pruned from the queue had finished; failed ones were
+        // handled above.
+        let pending = task
+            .depends_on
+            .iter()
+            .filter(|dep| {
+                statuses
+                    .get(dep.as_str())
+                    .is_some_and(|status| *status != SupervisedTaskStatus::Completed)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        if !pending.is_empty() {
+            if blocked_reason.is_none() {
+                blocked_reason = Some(format!(
+                    "{} waiting on dependencies {}",
+                    task.id,
+                    pending.join(", ")
+                ));
+            }
+            continue;
+        }
+        let conflicts = running_supervisor_conflicts_for_resources(&task.resources, &running);
+        if conflicts.is_empty() {
+            task.status = SupervisedTaskStatus::Running;
+            task.updated_at = now;
+            task.background_task_id = None;
+            return QueueStartResult::Started(task.clone());
+        }
+        if blocked_reason.is_none() {
+            blocked_reason = Some(format!(
+                "{} waiting on {} (conflict resources: {})",
+                task.id,
+                conflicts.join(", "),
+                truncate_for_table(&task.resources.join(","), 28)
+            ));
+        }
+    }
+    if let Some(reason) = blocked_reason {
+        return QueueStartResult::Blocked(reason);
+    }
+    QueueStartResult::Empty
+}
+
+/// Queued tasks as a queue file. Dependencies on tasks outside the export
+/// (running or finished) are left out.
+fn export_supervisor_queue(state: &AgentOsState) -> queue_file::QueueFile {
+    let queued = state
+        .supervisor
+        .tasks
+        .iter()
+        .filter(|task| task.status == SupervisedTaskStatus::Queued)
+        .collect::<Vec<_>>();
+    let tasks = queued
+        .iter()
+        .map(|task| queue_file::QueueFileTask {
+            id: task.id.clone(),
+            prompt: task.prompt.clone(),
+            class: Some(task.class.as_str().to_string()),
+            priority: task.priority,
+            depends_on: task
+                .depends_on
+                .iter()
+                .filter(|dep| queued.iter().any(|t| t.id == **dep))
+                .cloned()
+                .collect(),
+        })
+        .collect();
+    queue_file::QueueFile { tasks }
+}
+
+/// Queues every task of `file` under a fresh id, or none if the file is
+/// invalid. Returns the new ids in file order.
+fn import_supervisor_queue(
+    state: &mut AgentOsState,
+    file: &queue_file::QueueFile,
+) -> Result<Vec<String>, String> {
+    let existing = state
+        .supervisor
+        .tasks
+        .iter()
+        .map(|task| task.id.clone())
+        .collect::<HashSet<_>>();
+    let order = file.import_order(&existing)?;
+    let mut ids = HashMap::new();
+    for i in order {
+        let entry = &file.tasks[i];
+        let class = entry
+            .class
+            .as_deref()
+            .and_then(SupervisedTaskClass::parse)
+            .unwrap_or_else(|| classify_supervised_task_class(&entry.prompt));
+        let depends_on = entry
+            .depends_on
+            .iter()
+            .map(|dep| ids.get(dep).cloned().unwrap_or_else(|| dep.clone()))
+            .collect();
+        let id = enqueue_supervised_task(state, entry.prompt.trim().to_string(), class);
+        if let Some(task) = state.supervisor.tasks.iter_mut().find(|t| t.id == id) {
+            task.priority = entry.priority;
+            task.depends_on = depends_on;
+        }
+        ids.insert(entry.id.clone(), id);
+    }
+    Ok(file
+        .tasks
+        .iter()
+        .map(|task| ids[&task.id].clone())
+        .collect())
+}
+
+/// Cancels queued tasks whose dependencies failed or were cancelled, and in
+/// turn their dependents. Returns the cancelled ids.
+fn cancel_supervised_tasks_with_failed_dependencies(state: &mut AgentOsState) -> Vec<String> {
+    let mut cancelled = Vec::new();
+    loop {
+        let failed = state
+            .supervisor
+            .tasks
+            .iter()
+            .filter(|task| {
+                matches!(
+                    task.status,
+                    SupervisedTaskStatus::Failed | SupervisedTaskStatus::Cancelled
+                )
+            })
+            .map(|task| task.id.clone())
+            .collect::<HashSet<_>>();
+        let blocked = state
+            .supervisor
+            .tasks
+            .iter()
+            .filter(|task| task.status == SupervisedTaskStatus::Queued)
+            .find_map(|task| {
+                let dep = task.depends_on.iter().find(|dep| failed.contains(*dep))?;
+                Some((task.id.clone(), dep.clone()))
+            });
+        let Some((task_id, dep)) = blocked else {
+            return cancelled;
+        };
+        mark_supervised_task_cancelled(
+            state,
+            &task_id,
+            Some(format!("dependency {} did not complete", dep)),
+        );
+        cancelled.push(task_id);
+    }
+}
+
+fn mark_supervised_task_completed(state: &mut AgentOsState, task_id: &str) {
+    let now = chrono::Utc::now().timestamp();
+    if let Some(task) = state
+        .supervisor
+        .tasks
+        .iter_mut()
+        .find(|task| task.id == task_id)
+    {
+        task.status = SupervisedTaskStatus::Completed;
+        task.updated_at = now;
+        task.error = None;
+        task.background_task_id = None;
+    }
+}
+
+fn mark_supervised_task_failed(state: &mut AgentOsState, task_id: &str, error: String) {
+    let now = chrono::Utc::now().timestamp();
+    if let Some(task) = state
+        .supervisor
+        .tasks
+        .iter_mut()
+        .find(|task| task.id == task_id)
+    {
+        task.status = SupervisedTaskStatus::Failed;
+        task.updated_at = now;
+        task.error = Some(error);
+        task.background_task_id = None;
+    }
+}
+
+fn mark_supervised_task_cancelled(state: &mut AgentOsState, task_id: &str, reason: Option<String>) {
+    let now = chrono::Utc::now().timestamp();
+    if let Some(task) = state
+        .supervisor
+        .tasks
+        .iter_mut()
+        .find(|task| task.id == task_id)
+    {
+        task.status = SupervisedTaskStatus::Cancelled;
+        task.updated_at = now;
+        task.error = reason;
+        task.background_task_id = None;
+    }
+}
+
+fn supervised_task_status(state: &AgentOsState, task_id: &str) -> Option<SupervisedTaskStatus> {
+    state
+        .supervisor
+        .tasks
+        .iter()
+        .find(|task| task.id == task_id)
+        .map(|task| task.status.clone())
+}
+
+fn resolve_supervisor_task<'a>(
+    target: &str,
+    agent_state: &'a AgentOsState,
+) -> Option<&'a SupervisedTask> {
+    agent_state
+        .supervisor
+        .tasks
+        .iter()
+        .find(|task| task.id == target || task.background_task_id.as_deref() == Some(target))
+}
+
+fn print_supervisor_task_inspect(task: &SupervisedTask) {
+    println!("Supervisor task: {}", task.id);
+    println!("  prompt: {}", truncate_for_table(&task.prompt, 72));
+    println!("  class: {}", task.class.as_str());
+    println!(
+        "  status: {}",
+        match &task.status {
+            SupervisedTaskStatus::Queued => "queued",
+            SupervisedTaskStatus::Running => "running",
+            SupervisedTaskStatus::Completed => "completed",
+            SupervisedTaskStatus::Failed => "failed",
+            SupervisedTaskStatus::Cancelled => "cancelled",
+        }
+    );
+    if !task.resources.is_empty() {
+        println!("  resources: {}", task.resources.join(", "));
+    }
+    if task.priority != 0 {
+        println!("  priority: {}", task.priority);
+    }
+    if !task.depends_on.is_empty() {
+        println!("  depends_on: {}", task.depends_on.join(", "));
+    }
+    if let Some(bg) = &task.background_task_id {
+        println!("  background_task_id: {}", bg);
+    }
+    println!("  created_at: {}", task.created_at);
+    println!("  updated_at: {}", task.updated_at);
+    if let Some(err) = &task.error {
+        println!("  error: {}", err);
+    }
+}
+
+fn collect_supervisor_task_events(
+    task: &SupervisedTask,
+    task_event_feed: &Arc<Mutex<Vec<String>>>,
+    limit: usize,
+) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    if let Ok(events) = task_event_feed.lock() {
+        for line in events.iter() {
+            let mention = line.contains(&task.id)
+                || task
+                    .background_task_id
+                    .as_ref()
+                    .map_or(false, |bg| line.contains(bg.as_str()));
+            if mention {
+                out.push(line.clone());
+            }
+        }
+    }
+    let len = out.len();
+    if len <= limit {
+        out
+    } else {
+        out.into_iter().skip(len - limit).collect()
+    }
+}
+
+fn print_supervisor_task_events(task: &SupervisedTask, event_rows: &[String], _limit: usize) {
+    println!(
+        "Events for supervisor task {} ({} entries):",
+        task.id,
+        event_rows.len()
+    );
+    for row in event_rows {
+        println!("  {}", row);
+    }
+}
+
+fn set_supervised_task_background_id(
+    state: &mut AgentOsState,
+    task_id: &str,
+    background_task_id: Option<String>,
+) {
+    if let Some(task) = state
+        .supervisor
+        .tasks
+        .iter_mut()
+        .find(|task| task.id == task_id)
+    {
+        task.background_task_id = background_task_id;
+        task.updated_at = chrono::Utc::now().timestamp();
+    }
+}
+
+fn cancel_queued_supervised_tasks(state: &mut AgentOsState) -> usize {
+    let now = chrono::Utc::now().timestamp();
+    let mut changed = 0usize;
+    for task in state.supervisor.tasks.iter_mut() {
+        if task.status == SupervisedTaskStatus::Queued {
+            task.status = SupervisedTaskStatus::Cancelled;
+            task.updated_at = now;
+            task.background_task_id = None;
+            changed += 1;
+        }
+    }
+    changed
+}
+
+fn prune_supervisor_tasks(state: &mut AgentOsState, keep_terminal: usize) -> usize {
+    let mut terminal = state
+        .supervisor
+        .tasks
+        .iter()
+        .filter(|task| {
+            matches!(
+                task.status,
+                SupervisedTaskStatus::Completed
+                    | SupervisedTaskStatus::Failed
+                    | SupervisedTaskStatus::Cancelled
+            )
+        })
+        .map(|task| (task.id.clone(), task.updated_at))
+        .collect::<Vec<_>>();
+
+    if terminal.len() <= keep_terminal {
+        return 0;
+    }
+
+    terminal.sort_by(|a, b| b.1.cmp(&a.1));
+    let keep_ids = terminal
+        .into_iter()
+        .take(keep_terminal)
+        .map(|(id, _)| id)
+        .collect::<HashSet<_>>();
+
+    let before = state.supervisor.tasks.len();
+    state.supervisor.tasks.retain(|task| {
+        if matches!(
+            task.status,
+            SupervisedTaskStatus::Queued | SupervisedTaskStatus::Running
+        ) {
+            true
+        } else {
+            keep_ids.contains(&task.id)
+        }
+    });
+    before.saturating_sub(state.supervisor.tasks.len())
+}
+
+fn reconcile_supervisor_after_restart(state: &mut AgentOsState) -> usize {
+    let now = chrono::Utc::now().timestamp();
+    let mut recovered = 0usize;
+    for task in state.supervisor.tasks.iter_mut() {
+        if task.status == SupervisedTaskStatus::Running {
+            task.status = SupervisedTaskStatus::Failed;
+            task.updated_at = now;
+            task.background_task_id = None;
+            task.error =
+                Some("Recovered after restart: previous run did not finish cleanly".to_string());
+            recovered += 1;
+        }
+    }
+    recovered
+}
+
+fn print_supervisor_queue(state: &AgentOsState) {
+    println!("\n🧰 Supervisor Queue");
+    println!(
+        "  actions: queue status | queue add <prompt> | queue run | queue clear | queue export [file] | queue import <file>"
+    );
+    if state.supervisor.tasks.is_empty() {
+        println!("  empty");
+        println!();
+        return;
+    }
+
+    let mut tasks = state.supervisor.tasks.clone();
+    tasks.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    for task in tasks.iter().take(20) {
+        let bg = task.background_task_id.as_deref().unwrap_or("-");
+        println!(
+            "  {:<8} {:<13} {:<14} {:<14} {:<12} {}",
+            truncate_for_table(&task.id, 8),
+            format!("{:?}", task.status).to_lowercase(),
+            task.class.as_str(),
+            truncate_for_table(&task.resources.join(","), 14),
+            truncate_for_table(bg, 12),
+            truncate_for_table(&task.prompt, 52)
+        );
+    }
+    if tasks.len() > 20 {
+        println!("  ... and {} more", tasks.len() - 20);
+    }
+    println!();
+}
+
+fn print_supervisor_queue_status(state: &AgentOsState) {
+    let queued = state
+        .supervisor
+        .tasks
+        .iter()
+        .filter(|task| task.status == SupervisedTaskStatus::Queued)
+        .count();
+    let running = state
+        .supervisor
+        .tasks
+        .iter()
+        .filter(|task| task.status == SupervisedTaskStatus::Running)
+        .count();
+    let completed = state
+        .supervisor
+        .tasks
+        .iter()
+        .filter(|task| task.status == SupervisedTaskStatus::Completed)
+        .count();
+    let failed = state
+        .supervisor
+        .tasks
+        .iter()
+        .filter(|task| task.status == SupervisedTaskStatus::Failed)
+        .count();
+    let cancelled = state
+        .supervisor
+        .tasks
+        .iter()
+        .filter(|task| task.status == SupervisedTaskStatus::Cancelled)
+        .count();
+
+    println!("\n📌 Supervisor Queue Status");
+    println!(
+        "  queued={} running={} done={} failed={} cancelled={}",
+        queued,
+        running,
+        completed,
+        failed,
+        cancelled
+    );
+
+    let mut running_rows = state
+        .supervisor
+        .tasks
+        .iter()
+        .filter(|task| task.status == SupervisedTaskStatus::Running)
+        .cloned()
+        .collect::<Vec<_>>();
+    running_rows.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    if !running_rows.is_empty() {
+        println!("  running:");
+        for task in running_rows.iter().take(6) {
+            println!(
+                "    {} bg={} res={} prompt={}",
+                truncate_for_table(&task.id, 10),
+                truncate_for_table(task.background_task_id.as_deref().unwrap_or("-"), 14),
+                truncate_for_table(&task.resources.join(","), 16),
+                truncate_for_table(&task.prompt, 46)
+            );
+        }
+    }
+
+    let mut retry_rows = state
+        .supervisor
+        .tasks
+        .iter()
+        .filter(|task| {
+            matches!(
+                task.status,
+                SupervisedTaskStatus::Completed
+                    | SupervisedTaskStatus::Failed
+                    | SupervisedTaskStatus::Cancelled
+            )
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    retry_rows.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    if !retry_rows.is_empty() {
+        println!("  retry candidates:");
+        for task in retry_rows.iter().take(6) {
+            println!(
+                "    {} status={} class={} prompt={}",
+                truncate_for_table(&task.id, 10),
+                truncate_for_table(&format!("{:?}", task.status).to_lowercase(), 10),
+                truncate_for_table(task.class.as_str(), 12),
+                truncate_for_table(&task.prompt, 42)
+            );
+        }
+    }
+    println!();
+}
+
+fn running_background_conflict_reason(
+    input: &str,
+    running_tasks: &[hypr_claw_tasks::TaskInfo],
+) -> Option<String> {
+    let required = infer_supervised_task_resources(input);
+    let mut collisions = Vec::new();
+
+    for task in running_tasks {
+        let running_resources = infer_supervised_task_resources(&task.description);
+        let overlap = resource_conflicts(&required, &running_resources);
+        if !overlap.is_empty() {
+            collisions.push(format!("{} [{}]", task.id, overlap.join("+")));
+        }
+    }
+
+    if collisions.is_empty() {
+        None
+    } else {
+        Some(format!(
+            "required [{}] conflicts with {}",
+            required.join(","),
+            collisions.join(", ")
+        ))
+    }
+}
+
+fn touch_active_thread(state: &mut AgentOsState) {
+    for thread in &mut state.task_threads {
+        if thread.id == state.active_thread_id {
+            thread.updated_at = chrono::Utc::now().timestamp();
+            return;
+        }
+    }
+}
+
+fn active_thread_env(state: &AgentOsState) -> BTreeMap<String, String> {
+    state
+        .task_threads
+        .iter()
+        .find(|thread| thread.id == state.active_thread_id)
+        .map(|thread| thread.env.clone())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ThreadEnvCommand {
+    Show,
+    Set(String, String),
+    Unset(String),
+    Clear,
+}
+
+/// `thread env [set NAME=value | unset NAME | clear]`, after `thread env`.
+fn parse_thread_env_command(args: &str) -> Result<ThreadEnvCommand, String> {
+    let args = args.trim();
+    let (verb, rest) = args.split_once(' ').unwrap_or((args, ""));
+    let rest = rest.trim();
+    match verb {
+        "" => Ok(ThreadEnvCommand::Show),
+        "clear" if rest.is_empty() => Ok(ThreadEnvCommand::Clear),
+        "unset" if hypr_claw_tools::sandbox::env_policy::valid_name(rest) => {
+            Ok(ThreadEnvCommand::Unset(rest.to_string()))
+        }
+        "set" => {
+            let (name, value) = rest
+                .split_once('=')
+                .ok_or_else(|| "Use: thread env set NAME=value".to_string())?;
+            if !hypr_claw_tools::sandbox::env_policy::valid_name(name) {
+                return Err(format!("'{name}' is not a valid variable name"));
+            }
+            if value.contains(['\n', '\0']) {
+                return Err("values cannot contain newlines or NUL".to_string());
+            }
+            Ok(ThreadEnvCommand::Set(name.to_string(), value.to_string()))
+        }
+        _ => Err("Use: thread env [set NAME=value | unset NAME | clear]".to_string()),
+    }
+}
+
+fn thread_session_key(base_session_key: &str, thread_id: &str) -> String {
+    format!("{base_session_key}::thread::{thread_id}")
+}
+
+/// What the context providers see for a turn of `state`'s session.
+fn turn_context<'a>(
+    state: &'a AgentOsState,
+    capability_registry: &'a Value,
+    allowed_tools: &'a HashSet<String>,
+    facts: &'a [String],
+    running_tasks: &'a [hypr_claw_tasks::TaskInfo],
+) -> context_providers::TurnContext<'a> {
+    context_providers::TurnContext {
+        profile: &state.onboarding.system_profile,
+        capability_registry,
+        allowed_tools,
+        autonomy_mode: state.autonomy_mode.as_str(),
+        workflow_mode: if strict_workflow_enabled() {
+            "strict"
+        } else {
+            "legacy"
+        },
+        facts,
+        running_tasks,
+        working_dir: std::env::current_dir().ok(),
+        now: chrono::Local::now().fixed_offset(),
+    }
+}
+
+fn augment_system_prompt_for_turn(
+    base_prompt: &str,
+    providers: &context_providers::ContextRegistry,
+    turn: &context_providers::TurnContext,
+    autonomy_mode: &AutonomyMode,
+) -> String {
+    system_prompt_with_context(base_prompt, &providers.render(turn), autonomy_mode)
+}
+
+fn system_prompt_with_context(
+    base_prompt: &str,
+    runtime_context: &str,
+    autonomy_mode: &AutonomyMode,
+) -> String {
+    format!(
+        "{}\n\nRuntime context:\n{}\n\n{}",
+        base_prompt,
+        runtime_context,
+        execution_policy(autonomy_mode)
+    )
+}
+
+fn execution_policy(autonomy_mode: &AutonomyMode) -> &'static str {
+    if strict_workflow_enabled() {
+        "Strict workflow:\n1) Observe first using desktop.read_screen_state/active_window/list_windows/cursor_position before GUI actions.\n2) Plan short and execute using tools, not explanation-only text.\n3) Prefer one decisive tool call at a time with valid JSON input.\n4) After each action, verify with tools (cursor/window/screen/file/process checks) and continue until done; to check a GUI action, take desktop.window_snapshot before it and desktop.window_diff after instead of re-reading the whole screen.\n5) Ask for user permission before high-impact or destructive actions; when a plan has two or more destructive steps, request them together once with plan.request_approval before running any; for bulk file cleanups or reorganizing, stage every delete and move with fs.stage and apply them once with fs.apply_staged.\n6) If you need a decision or detail from the user, call ask_user and continue with the answer instead of ending the run to ask.\n7) Stop only when truly blocked and report exact blocker + next best option."
+    } else {
+        match autonomy_mode {
+            AutonomyMode::PromptFirst => {
+                "Execution policy:\n1) Perform actions using tools, not explanation.\n2) You control fallback order dynamically; adapt tool strategy from observed failures.\n3) Prefer one decisive tool call at a time with valid JSON input.\n4) For opening local apps, prefer desktop.launch_app before proc.spawn/open_url and use launcher_commands/vscode_hint.\n5) End with concise result and what changed.\n6) Stop only when truly blocked after trying multiple tool paths."
+            }
+            AutonomyMode::Guarded => {
+                "Execution policy:\n1) Perform actions using tools, not explanation.\n2) If a tool fails, choose an alternative backend/tool and continue.\n3) For opening local apps, try desktop.launch_app first, then proc.spawn as fallback; use launcher_commands/vscode_hint.\n4) End with concise result and what changed.\n5) Only stop as blocked after trying alternatives available in runtime context."
+            }
+        }
+    }
+}
+
+/// `preview <prompt>`: the first request a run of the prompt would send,
+/// and what it and a full run are likely to cost.
+fn print_request_preview(
+    preview: &hypr_claw_runtime::RequestPreview,
+    config: &Config,
+    class: &SupervisedTaskClass,
+    max_iterations: usize,
+) {
+    println!("{}", ui_section("Request preview (not sent)"));
+    println!("  model   : {}", config.model);
+    println!(
+        "  class   : {} (up to {} model calls)",
+        class.as_str(),
+        max_iterations
+    );
+    println!(
+        "\nSystem prompt (~{} tokens):\n{}\n",
+        context_providers::estimate_tokens(&preview.system_prompt),
+        preview.system_prompt
+    );
+    println!("Messages ({}):", preview.messages.len());
+    for message in &preview.messages {
+        let content = match message.content.as_str() {
+            Some(text) => text.to_string(),
+            None => message.content.to_string(),
+        };
+        println!(
+            "  {:<9} {}",
+            format!("{:?}", message.role).to_lowercase(),
+            truncate_for_table(&sanitize_single_line(&content), 100)
+        );
+    }
+    let tool_names = preview
+        .tool_schemas
+        .iter()
+        .filter_map(|schema| schema.pointer("/function/name").and_then(|n| n.as_str()))
+        .collect::<Vec<_>>();
+    println!(
+        "Tools ({}, ~{} tokens): {}",
+        tool_names.len(),
+        context_providers::estimate_tokens(
+            &serde_json::to_string(&preview.tool_schemas).unwrap_or_default()
+        ),
+        tool_names.join(", ")
+    );
+
+    let tokens = preview.estimated_tokens();
+    let cost = |tokens: usize| match config.pricing.get(&config.model) {
+        Some(pricing) => format!(
+            " (≈ ${:.4})",
+            tokens as f64 * pricing.input_per_mtok / 1_000_000.0
+        ),
+        None => String::new(),
+    };
+    println!("{}", ui_section("Estimate"));
+    println!("  first call : ~{} input tokens{}", tokens, cost(tokens));
+    println!(
+        "  full run   : each call re-sends at least this, so ~{}+ input tokens{} if all {} calls are used",
+        tokens * max_iterations,
+        cost(tokens * max_iterations),
+        max_iterations
+    );
+    if preview.summarized > 0 {
+        println!(
+            "  compaction : the oldest {} messages will be summarized first",
+            preview.summarized
+        );
+    }
+    if !config.pricing.contains_key(&config.model) {
+        println!(
+            "  Add pricing.{}.input_per_mtok to config.yaml for a cost in USD.",
+            config.model
+        );
+    }
+    println!();
+}
+
+/// `prompt inspect`: the system prompt the next turn of `history`'s thread
+/// would send, then its token cost by section and what compaction would
+/// summarize away.
+fn print_prompt_inspection<S, L, D, R, Sum>(
+    agent_loop: &hypr_claw_runtime::AgentLoop<S, L, D, R, Sum>,
+    agent_name: &str,
+    base_prompt: &str,
+    providers: &context_providers::ContextRegistry,
+    turn: &context_providers::TurnContext,
+    autonomy_mode: &AutonomyMode,
+    history: &[hypr_claw_runtime::Message],
+) where
+    S: hypr_claw_runtime::SessionStore,
+    L: hypr_claw_runtime::LockManager,
+    D: hypr_claw_runtime::ToolDispatcher,
+    R: hypr_claw_runtime::ToolRegistry,
+    Sum: hypr_claw_runtime::Summarizer,
+{
+    use context_providers::estimate_tokens;
+
+    let augmented = augment_system_prompt_for_turn(base_prompt, providers, turn, autonomy_mode);
+    let tool_schemas = agent_loop.tool_schemas(agent_name);
+    let composed = agent_loop.compose_system_prompt(&augmented, &tool_schemas);
+    println!("{}", ui_section("System prompt"));
+    println!("{}\n", composed);
+
+    let mut rows = vec![("soul".to_string(), estimate_tokens(base_prompt))];
+    for (name, text) in providers.render_sections(turn) {
+        rows.push((format!("context: {name}"), estimate_tokens(&text)));
+    }
+    rows.push((
+        "execution policy".to_string(),
+        estimate_tokens(execution_policy(autonomy_mode)),
+    ));
+    rows.push((
+        "tool list".to_string(),
+        estimate_tokens(composed.strip_prefix(&augmented).unwrap_or_default()),
+    ));
+    let schemas_json = serde_json::to_string(&tool_schemas).unwrap_or_default();
+    rows.push((
+        format!("tool schemas ({})", tool_schemas.len()),
+        estimate_tokens(&schemas_json),
+    ));
+    let preview = agent_loop.compaction_preview(history);
+    rows.push((
+        format!("history ({} messages)", history.len()),
+        preview.tokens,
+    ));
+    let total = rows.iter().map(|(_, tokens)| tokens).sum::<usize>();
+
+    println!(
+        "{}",
+        ui_section("Token budget (estimated, 4 chars per token)")
+    );
+    for (name, tokens) in &rows {
+        println!("  {:<30} {:>7}", name, tokens);
+    }
+    println!("  {:<30} {:>7}", "total", total);
+    if preview.summarized == 0 {
+        println!(
+            "\nCompaction: history is within its {}-token threshold; nothing is dropped.\n",
+            preview.threshold
+        );
+    } else {
+        let dropped = agent_loop
+            .compaction_preview(&history[..preview.summarized])
+            .tokens;
+        println!(
+            "\nCompaction: history exceeds its {}-token threshold; the oldest {} messages (~{} tokens) will be replaced by a summary.\n",
+            preview.threshold, preview.summarized, dropped
+        );
+    }
+}
+
+/// `text` followed by what the run changed, for background and side run
+/// results that are kept after the REPL output scrolls away.
+fn with_run_changes(text: String, changes: &side_effects::Manifest) -> String {
+    if changes.is_empty() {
+        return text;
+    }
+    format!("{text}\n\nChanges:\n{}", changes.render().join("\n"))
+}
+
+/// The end-of-run list of what the run changed; nothing for a run that
+/// only looked.
+fn print_run_changes(changes: &side_effects::Manifest) {
+    if changes.is_empty() {
+        return;
+    }
+    println!("{}", ui_section("Changes"));
+    for line in changes.render() {
+        println!("{line}");
+    }
+    println!();
+}
+
+/// `context analyze`: shows what fills the thread's context and applies the
+/// user's prune, pin and summarize actions until they press Enter.
+async fn analyze_context<S, L, D, R, Sum>(
+    agent_loop: &hypr_claw_runtime::AgentLoop<S, L, D, R, Sum>,
+    sessions: &impl hypr_claw_runtime::SessionStore,
+    thread_key: &str,
+    facts: &mut Vec<String>,
+) -> Result<(), hypr_claw_runtime::RuntimeError>
+where
+    S: hypr_claw_runtime::SessionStore,
+    L: hypr_claw_runtime::LockManager,
+    D: hypr_claw_runtime::ToolDispatcher,
+    R: hypr_claw_runtime::ToolRegistry,
+    Sum: hypr_claw_runtime::Summarizer,
+{
+    let mut history = sessions.load(thread_key).await?;
+    loop {
+        let analysis = context_analysis::analyze(&history, facts);
+        println!("\n{}", ui_title("Context"));
+        let threshold = agent_loop.compaction_preview(&history).threshold;
+        for line in analysis.render(threshold) {
+            println!("{line}");
+        }
+        if analysis.items.is_empty() {
+            println!();
+            return Ok(());
+        }
+        let answer = prompt_line(context_analysis::ACTIONS_PROMPT).unwrap_or_default();
+        if answer.is_empty() {
+            println!();
+            return Ok(());
+        }
+        let action = match context_analysis::parse_action(&answer, analysis.items.len()) {
+            Ok(action) => action,
+            Err(e) => {
+                println!("⚠️ {e}");
+                continue;
+            }
+        };
+        match context_analysis::apply(action, &analysis.items, &mut history, facts) {
+            Ok(done) => {
+                sessions.save(thread_key, &history).await?;
+                println!("✅ {done}");
+            }
+            Err(e) => println!("⚠️ {e}"),
+        }
+    }
+}
+
+/// Whether a guarded run narrows its tools to `focused`.
+fn use_focused_tools(
+    focused: &HashSet<String>,
+    allowed: &HashSet<String>,
+    mode: &AutonomyMode,
+) -> bool {
+    matches!(mode, AutonomyMode::Guarded) && !focused.is_empty() && focused.len() < allowed.len()
+}
+
+fn focused_tools_for_input(input: &str, allowed: &HashSet<String>) -> HashSet<String> {
+    let lower = input.to_lowercase();
+    let mut preferred = HashSet::new();
+
+    let add = |set: &mut HashSet<String>, name: &str, allowed: &HashSet<String>| {
+        if allowed.contains(name) {
+            set.insert(name.to_string());
+        }
+    };
+
+    if lower.contains("wallpaper") || lower.contains("workspace") || lower.contains("window") {
+        add(&mut preferred, "wallpaper.set", allowed);
+        add(&mut preferred, "hypr.workspace.switch", allowed);
+        add(&mut preferred, "hypr.workspace.move_window", allowed);
+        add(&mut preferred, "hypr.window.focus", allowed);
+        add(&mut preferred, "hypr.window.close", allowed);
+        add(&mut preferred, "hypr.window.move", allowed);
+        add(&mut preferred, "hypr.exec", allowed);
+    }
+
+    if lower.contains("file")
+        || lower.contains("folder")
+        || lower.contains("dir")
+        || lower.contains("read")
+        || lower.contains("write")
+        || lower.contains("create")
+        || lower.contains("delete")
+        || lower.contains("copy")
+        || lower.contains("move")
+        || lower.contains("duplicate")
+        || lower.contains("clean up")
+        || lower.contains("rename")
+    {
+        add(&mut preferred, "fs.read", allowed);
+        add(&mut preferred, "fs.write", allowed);
+        add(&mut preferred, "fs.list", allowed);
+        add(&mut preferred, "fs.create_dir", allowed);
+        add(&mut preferred, "fs.delete", allowed);
+        add(&mut preferred, "fs.copy", allowed);
+        add(&mut preferred, "fs.move", allowed);
+        add(&mut preferred, "fs.find_duplicates", allowed);
+        add(&mut preferred, "fs.stage", allowed);
+        add(&mut preferred, "fs.apply_staged", allowed);
+        add(&mut preferred, "fs.batch_rename", allowed);
+        add(&mut preferred, "fs.undo_apply", allowed);
+    }
+
+    if lower.contains("open")
+        || lower.contains("browser")
+        || lower.contains("search")
+        || lower.contains("web")
+        || lower.contains("gmail")
+        || lower.contains("mail")
+    {
+        add(&mut preferred, "desktop.launch_app", allowed);
+        if lower.contains("gmail") || lower.contains("mail") {
+            add(&mut preferred, "desktop.open_gmail", allowed);
+            add(&mut preferred, "desktop.open_url", allowed);
+            add(&mut preferred, "desktop.search_web", allowed);
+        } else if lower.contains("browser") || lower.contains("search") || lower.contains("web") {
+            add(&mut preferred, "desktop.search_web", allowed);
+            add(&mut preferred, "desktop.open_url", allowed);
+        } else {
+            add(&mut preferred, "desktop.open_url", allowed);
+            add(&mut preferred, "desktop.search_web", allowed);
+        }
+    }
+
+    if lower.contains("type")
+        || lower.contains("press")
+        || lower.contains("shortcut")
+        || lower.contains("hotkey")
+        || lower.contains("click")
+        || lower.contains("mouse")
+        || lower.contains("screenshot")
+        || lower.contains("screen")
+    {
+        add(&mut preferred, "desktop.read_screen_state", allowed);
+        add(&mut preferred, "desktop.window_snapshot", allowed);
+        add(&mut preferred, "desktop.window_diff", allowed);
+        add(&mut preferred, "desktop.cursor_position", allowed);
+        add(&mut preferred, "desktop.type_text", allowed);
+        add(&mut preferred, "desktop.key_press", allowed);
+        add(&mut preferred, "desktop.key_combo", allowed);
+        add(&mut preferred, "desktop.mouse_click", allowed);
+        add(&mut preferred, "desktop.capture_screen", allowed);
+        add(&mut preferred, "desktop.active_window", allowed);
+        add(&mut preferred, "desktop.list_windows", allowed);
+        add(&mut preferred, "desktop.mouse_move", allowed);
+        add(&mut preferred, "desktop.mouse_move_and_verify", allowed);
+        add(&mut preferred, "desktop.click_at", allowed);
+        add(&mut preferred, "desktop.click_at_and_verify", allowed);
+        add(&mut preferred, "desktop.ocr_screen", allowed);
+        add(&mut preferred, "desktop.find_text", allowed);
+        add(&mut preferred, "desktop.click_text", allowed);
+    }
+
+    if lower.contains("run")
+        || lower.contains("build")
+        || lower.contains("start")
+        || lower.contains("execute")
+        || lower.contains("kill")
+        || lower.contains("process")
+    {
+        add(&mut preferred, "proc.spawn", allowed);
+        add(&mut preferred, "proc.kill", allowed);
+        add(&mut preferred, "proc.list", allowed);
+        add(&mut preferred, "hypr.exec", allowed);
+        add(&mut preferred, "help.lookup", allowed);
+    }
+
+    if lower.contains("battery") || lower.contains("memory") || lower.contains("system") {
+        add(&mut preferred, "system.battery", allowed);
+        add(&mut preferred, "system.memory", allowed);
+    }
+
+    if lower.contains("gpu")
+        || lower.contains("graphics")
+        || lower.contains("vram")
+        || lower.contains("display")
+        || lower.contains("monitor")
+        || lower.contains("resolution")
+    {
+        add(&mut preferred, "system.gpu", allowed);
+        add(&mut preferred, "system.displays", allowed);
+    }
+
+    if lower.contains("temperature")
+        || lower.contains("thermal")
+        || lower.contains("overheat")
+        || lower.contains("fan")
+        || lower.contains("hot")
+    {
+        add(&mut preferred, "system.thermal", allowed);
+    }
+
+    if lower.contains("email")
+        || lower.contains("mail")
+        || lower.contains("telegram")
+        || lower.contains("contact")
+        || lower.contains("message")
+    {
+        add(&mut preferred, "contacts.lookup", allowed);
+        add(&mut preferred, "mail.compose", allowed);
+        add(&mut preferred, "telegram.open_chat", allowed);
+    }
+
+    if lower.contains("docs")
+        || lower.contains("documentation")
+        || lower.contains("man page")
+        || lower.contains("flag")
+        || lower.contains("option")
+        || lower.contains("wiki")
+    {
+        add(&mut preferred, "knowledge.search", allowed);
+        add(&mut preferred, "help.lookup", allowed);
+    }
+
+    if lower.contains("report")
+        || lower.contains("invoice")
+        || lower.contains("document")
+        || lower.contains("template")
+        || lower.contains("pdf")
+    {
+        add(&mut preferred, "doc.generate", allowed);
+        add(&mut preferred, "fs.read", allowed);
+        add(&mut preferred, "fs.list", allowed);
+    }
+
+    if lower.contains("csv") || lower.contains("spreadsheet") || lower.contains("tsv") {
+        add(&mut preferred, "csv.query", allowed);
+        add(&mut preferred, "csv.write", allowed);
+        add(&mut preferred, "fs.list", allowed);
+    }
+
+    if lower.contains("sqlite") || lower.contains("database") || lower.contains(".db") {
+        add(&mut preferred, "db.query", allowed);
+        add(&mut preferred, "db.execute", allowed);
+        add(&mut preferred, "fs.list", allowed);
+    }
+
+    if lower.contains("env") || lower.contains("variable") {
+        add(&mut preferred, "env.get", allowed);
+        add(&mut preferred, "env.list", allowed);
+    }
+
+    if lower.contains("cron")
+        || lower.contains("systemd")
+        || lower.contains("recurring")
+        || lower.contains("every day")
+        || lower.contains("schedule")
+    {
+        add(&mut preferred, "schedule.system_create", allowed);
+        add(&mut preferred, "schedule.system_list", allowed);
+        add(&mut preferred, "schedule.system_remove", allowed);
+    }
+
+    if lower.contains("form") || lower.contains("fill in") || lower.contains("fill out") {
+        add(&mut preferred, "form.fill", allowed);
+        add(&mut preferred, "form.remember", allowed);
+        add(&mut preferred, "desktop.read_screen_state", allowed);
+        add(&mut preferred, "desktop.click_text", allowed);
+        add(&mut preferred, "desktop.type_text", allowed);
+        add(&mut preferred, "desktop.key_press", allowed);
+    }
+
+    if lower.contains("remind") || lower.contains("timer") || lower.contains("alarm") {
+        add(&mut preferred, "timer.set", allowed);
+    }
+
+    let names_weekday = ["mon", "tues", "wednes", "thurs", "fri", "satur", "sun"]
+        .iter()
+        .any(|day| lower.contains(&format!("{day}day")));
+    if names_weekday
+        || lower.contains("remind")
+        || lower.contains("schedule")
+        || lower.contains("tomorrow")
+        || lower.contains("next week")
+    {
+        add(&mut preferred, "time.parse", allowed);
+        add(&mut preferred, "time.format", allowed);
+    }
+
+    if lower.contains("calculate")
+        || lower.contains("compute")
+        || lower.contains("convert")
+        || lower.contains("how many days")
+        || lower.contains("regex")
+    {
+        add(&mut preferred, "calc.evaluate", allowed);
+        add(&mut preferred, "code.run_snippet", allowed);
+    }
+
+    if preferred.is_empty() {
+        return allowed.clone();
+    }
+
+    add(&mut preferred, "echo", allowed);
+    add(&mut preferred, "ask_user", allowed);
+    preferred
+}
+
+fn emergency_tool_subset(input: &str, allowed: &HashSet<String>) -> HashSet<String> {
+    let focused = focused_tools_for_input(input, allowed);
+    if focused.is_empty() {
+        return HashSet::new();
+    }
+    if focused.len() <= 10 {
+        return focused;
+    }
+    let mut names: Vec<String> = focused.into_iter().collect();
+    names.sort();
+    names.into_iter().take(10).collect()
+}
+
+fn fallback_playbook_for_input(input: &str, allowed: &HashSet<String>) -> String {
+    let lower = input.to_lowercase();
+    let mut rows: Vec<(&str, Vec<&str>)> = Vec::new();
+
+    let wallpaper_intent = lower.contains("wallpaper") || lower.contains("background");
+    if wallpaper_intent {
+        rows.push((
+            "wallpaper",
+            vec!["wallpaper.set", "hypr.exec", "desktop.open_url"],
+        ));
+    }
+
+    let desktop_intent = lower.contains("open")
+        || lower.contains("launch")
+        || lower.contains("gmail")
+        || lower.contains("telegram")
+        || lower.contains("browser")
+        || lower.contains("vscode");
+    if desktop_intent {
+        rows.push((
+            "desktop-open",
+            vec![
+                "desktop.launch_app",
+                "desktop.open_url",
+                "proc.spawn",
+                "hypr.exec",
+            ],
+        ));
+    }
+
+    let screen_intent = lower.contains("screen")
+        || lower.contains("ocr")
+        || lower.contains("read my")
+        || lower.contains("summarise");
+    if screen_intent {
+        rows.push((
+            "screen-automation",
+            vec![
+                "desktop.read_screen_state",
+                "desktop.cursor_position",
+                "desktop.capture_screen",
+                "desktop.window_snapshot",
+                "desktop.window_diff",
+                "desktop.ocr_screen",
+                "desktop.find_text",
+                "desktop.mouse_move_and_verify",
+                "desktop.click_at_and_verify",
+                "desktop.click_text",
+                "desktop.mouse_click",
+            ],
+        ));
+    }
+
+    let process_intent = lower.contains("run")
+        || lower.contains("build")
+        || lower.contains("install")
+        || lower.contains("process")
+        || lower.contains("command");
+    if process_intent {
+        rows.push((
+            "process",
+            vec!["proc.spawn", "hypr.exec", "proc.list", "proc.kill"],
+        ));
+    }
+
+    let fs_intent = lower.contains("file")
+        || lower.contains("folder")
+        || lower.contains("dir")
+        || lower.contains("write")
+        || lower.contains("read")
+        || lower.contains("create");
+    if fs_intent {
+        rows.push((
+            "filesystem",
+            vec![
+                "fs.list",
+                "fs.create_dir",
+                "fs.write",
+                "fs.read",
+                "fs.copy",
+                "fs.move",
+                "fs.delete",
+                "fs.find_duplicates",
+                "fs.stage",
+                "fs.apply_staged",
+                "fs.batch_rename",
+                "fs.undo_apply",
+            ],
+        ));
+    }
+
+    if rows.is_empty() {
+        let fallback = emergency_tool_subset(input, allowed)
+            .into_iter()
+            .collect::<Vec<_>>();
+        if fallback.is_empty() {
+            return "- no fallback tools available".to_string();
+        }
+        return format!("- generic: {}", fallback.join(" -> "));
+    }
+
+    let mut lines = Vec::new();
+    for (label, chain) in rows {
+        let available = chain
+            .into_iter()
+            .filter(|tool| allowed.contains(*tool))
+            .collect::<Vec<_>>();
+        if !available.is_empty() {
+            lines.push(format!("- {}: {}", label, available.join(" -> ")));
+        }
+    }
+    if lines.is_empty() {
+        "- no fallback tools available".to_string()
+    } else {
+        lines.join("\n")
+    }
+}
+
+// Adapter for ToolDispatcher
+struct RuntimeDispatcherAdapter {
+    inner: Arc<hypr_claw_tools::ToolDispatcherImpl>,
+    action_feed: Arc<Mutex<Vec<String>>>,
+    action_counter: Arc<Mutex<HashMap<String, u64>>>,
+    /// Tools left after capability filtering; calls outside it are gaps.
+    available_tools: RwLock<HashSet<String>>,
+    gap_user: String,
+    gap_log: Arc<Mutex<CapabilityGapLog>>,
+    /// What each session is working on, for attributing gaps to a task.
+    task_labels: Arc<Mutex<HashMap<String, String>>>,
+    usage: Arc<analytics::UsageTracker>,
+    actions: Arc<action_log::ActionLog>,
+    /// Tool name and input of each successful call per session, the raw
+    /// material for `skill learn`.
+    succeeded_calls: Mutex<HashMap<String, Vec<(String, Value)>>>,
+    /// What each session's current run changed so far.
+    changes: Mutex<HashMap<String, side_effects::Manifest>>,
+}
+
+impl RuntimeDispatcherAdapter {
+    fn new(
+        inner: Arc<hypr_claw_tools::ToolDispatcherImpl>,
+        action_feed: Arc<Mutex<Vec<String>>>,
+        available_tools: HashSet<String>,
+        gap_user: &str,
+        usage: Arc<analytics::UsageTracker>,
+        actions: Arc<action_log::ActionLog>,
+    ) -> Self {
+        Self {
+            inner,
+            action_feed,
+            action_counter: Arc::new(Mutex::new(HashMap::new())),
+            available_tools: RwLock::new(available_tools),
+            gap_user: gap_user.to_string(),
+            gap_log: Arc::new(Mutex::new(load_capability_gaps(gap_user))),
+            task_labels: Arc::new(Mutex::new(HashMap::new())),
+            usage,
+            actions,
+            succeeded_calls: Mutex::new(HashMap::new()),
+            changes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// What `session_key` changed since the last take.
+    fn take_changes(&self, session_key: &str) -> side_effects::Manifest {
+        self.changes
+            .lock()
+            .ok()
+            .and_then(|mut changes| changes.remove(session_key))
+            .unwrap_or_default()
+    }
+
+    /// Successful calls of `session_key` since the last take, in order.
+    fn take_succeeded_calls(&self, session_key: &str) -> Vec<(String, Value)> {
+        self.succeeded_calls
+            .lock()
+            .ok()
+            .and_then(|mut calls| calls.remove(session_key))
+            .unwrap_or_default()
+    }
+
+    fn set_task_label(&self, session_key: &str, label: &str) {
+        if let Ok(mut labels) = self.task_labels.lock() {
+            labels.insert(session_key.to_string(), label.to_string());
+        }
+    }
+
+    fn set_env_overlay(&self, session_key: &str, env: BTreeMap<String, String>) {
+        self.inner.set_env_overlay(session_key, env);
+    }
+
+    fn set_available_tools(&self, available_tools: HashSet<String>) {
+        if let Ok(mut guard) = self.available_tools.write() {
+            *guard = available_tools;
+        }
+    }
+
+    /// Drops gaps fixed by installing `backend`; returns how many were dropped.
+    fn resolve_capability_gaps(&self, backend: &str) -> io::Result<usize> {
+        let available = self
+            .available_tools
+            .read()
+            .map(|tools| tools.clone())
+            .unwrap_or_default();
+        let mut log = self
+            .gap_log
+            .lock()
+            .map_err(|_| io::Error::other("capability gap log lock poisoned"))?;
+        let resolved = log.resolve(backend, &available);
+        save_capability_gaps(&self.gap_user, &log)?;
+        Ok(resolved)
+    }
+
+    fn capability_gaps(&self) -> CapabilityGapLog {
+        self.gap_log
+            .lock()
+            .map(|log| log.clone())
+            .unwrap_or_default()
+    }
+
+    fn clear_capability_gaps(&self) -> io::Result<()> {
+        let mut log = self
+            .gap_log
+            .lock()
+            .map_err(|_| io::Error::other("capability gap log lock poisoned"))?;
+        *log = CapabilityGapLog::default();
+        save_capability_gaps(&self.gap_user, &log)
+    }
+
+    fn record_capability_gap(&self, session_key: &str, tool_name: &str, error: &str) {
+        let detected = match self.available_tools.read() {
+            Ok(available) => detect_capability_gap(tool_name, error, &available),
+            Err(_) => None,
+        };
+        let Some((kind, install)) = detected else {
+            return;
+        };
+        let task = self
+            .task_labels
+            .lock()
+            .ok()
+            .and_then(|labels| labels.get(session_key).cloned())
+            .or_else(|| Self::task_tag(session_key).map(str::to_string))
+            .unwrap_or_default();
+        let Ok(mut log) = self.gap_log.lock() else {
+            return;
+        };
+        log.record(
+            tool_name,
+            kind,
+            install,
+            &task,
+            error,
+            chrono::Utc::now().timestamp(),
+        );
+        if let Err(e) = save_capability_gaps(&self.gap_user, &log) {
+            eprintln!("⚠️  Failed to save capability gaps: {}", e);
+        }
+    }
+
+    /// Prefix with [task_id] when session_key is a supervisor task (e.g. "user:agent::sup::sup-1").
+    pub(crate) fn task_tag(session_key: &str) -> Option<&str> {
+        session_key.split("::sup::").nth(1)
+    }
+
+    fn push_action(&self, session_key: &str, line: String) {
+        let tagged = if let Some(tag) = Self::task_tag(session_key) {
+            format!("[{}] {}", tag, line)
+        } else {
+            line
+        };
+        if let Ok(mut feed) = self.action_feed.lock() {
+            feed.push(tagged);
+            if feed.len() > 256 {
+                let drop_count = feed.len() - 256;
+                feed.drain(0..drop_count);
+            }
+        }
+    }
+
+    fn next_action_index(&self, session_key: &str) -> u64 {
+        if let Ok(mut counters) = self.action_counter.lock() {
+            let entry = counters.entry(session_key.to_string()).or_insert(0);
+            *entry = entry.saturating_add(1);
+            return *entry;
+        }
+        0
+    }
+
+    fn print_action(
+        &self,
+        session_key: &str,
+        index: u64,
+        status: &str,
+        tool_name: &str,
+        detail: &str,
+        elapsed_ms: Option<u64>,
+    ) {
+        let tool = truncate_for_table(tool_name, 30);
+        let detail_clean = truncate_for_table(&sanitize_single_line(detail), 108);
+        if let Err(e) = self.actions.record(
+            session_key,
+            index,
+            status,
+            tool_name,
+            &detail_clean,
+            elapsed_ms,
+        ) {
+            eprintln!("⚠️  Failed to record action: {}", e);
+        }
+        let line = format!(
+            "{:>3}. {:<7} {:<30} {}",
+            index,
+            status,
+            tool,
+            detail_clean
+        );
+        self.push_action(session_key, line.clone());
+        let status_badge = match status {
+            "ok" => ui_success("OK"),
+            "tool" => ui_info("TOOL"),
+            "alias" => ui_warn("MAP"),
+            "fail" => ui_danger("FAIL"),
+            "error" => ui_danger("ERR"),
+            _ => ui_dim(status),
+        };
+        eprintln!(
+            "{} {} {} {}",
+            ui_dim(&format!("{:>3}.", index)),
+            status_badge,
+            truncate_for_table(tool_name, 30),
+            detail_clean
+        );
+    }
+}
+
+#[async_trait::async_trait]
+impl hypr_claw_runtime::ToolDispatcher for RuntimeDispatcherAdapter {
+    async fn execute(
+        &self,
+        tool_name: &str,
+        input: &serde_json::Value,
+        session_key: &str,
+    ) -> Result<serde_json::Value, hypr_claw_runtime::RuntimeError> {
+        let normalized_tool_name = normalize_runtime_tool_name(tool_name);
+        let started = std::time::Instant::now();
+        let action_index = self.next_action_index(session_key);
+        self.print_action(
+            session_key,
+            action_index,
+            "tool",
+            &normalized_tool_name,
+            &format!("input={}", truncate_for_table(&input.to_string(), 112)),
+            None,
+        );
+        if normalized_tool_name != tool_name {
+            self.print_action(
+                session_key,
+                action_index,
+                "alias",
+                &normalized_tool_name,
+                &format!(
+                    "remapped '{}' -> '{}'",
+                    truncate_for_table(tool_name, 24),
+                    truncate_for_table(&normalized_tool_name, 24)
+                ),
+                None,
+            );
+        }
+        let result = self
+            .inner
+            .dispatch(
+                session_key.to_string(),
+                normalized_tool_name.clone(),
+                input.clone(),
+            )
+            .await;
+
+        match result {
+            Ok(tool_result) => {
+                if tool_result.success {
+                    self.usage.record_tool(&normalized_tool_name, None);
+                    if let Ok(mut calls) = self.succeeded_calls.lock() {
+                        calls
+                            .entry(session_key.to_string())
+                            .or_default()
+                            .push((normalized_tool_name.clone(), input.clone()));
+                    }
+                    let output = tool_result.output.unwrap_or(serde_json::json!({}));
+                    if let Ok(mut changes) = self.changes.lock() {
+                        changes.entry(session_key.to_string()).or_default().record(
+                            &normalized_tool_name,
+                            input,
+                            &output,
+                        );
+                    }
+                    let elapsed = started.elapsed().as_millis();
+                    self.print_action(
+                        session_key,
+                        action_index,
+                        "ok",
+                        &normalized_tool_name,
+                        &format!(
+                            "done in {}ms output={}",
+                            elapsed,
+                            truncate_for_table(&output.to_string(), 112)
+                        ),
+                        Some(elapsed as u64),
+                    );
+                    Ok(output)
+                } else {
+                    let base_detail = tool_result
+                        .error
+                        .clone()
+                        .unwrap_or_else(|| "Unknown error".to_string());
+                    self.record_capability_gap(session_key, &normalized_tool_name, &base_detail);
+                    self.usage
+                        .record_tool(&normalized_tool_name, Some(&base_detail));
+                    let alternatives = fallback_tools_for_tool(&normalized_tool_name);
+                    let detail = if alternatives.is_empty() {
+                        base_detail
+                    } else {
+                        format!(
+                            "{} | fallback_tools: {}",
+                            base_detail,
+                            alternatives.join(", ")
+                        )
+                    };
+                    self.print_action(
+                        session_key,
+                        action_index,
+                        "fail",
+                        &normalized_tool_name,
+                        &format!(
+                            "failed in {}ms {}",
+                            started.elapsed().as_millis(),
+                            truncate_for_table(&detail, 112)
+                        ),
+                        Some(started.elapsed().as_millis() as u64),
+                    );
+                    Err(hypr_claw_runtime::RuntimeError::ToolError(detail))
+                }
+            }
+            Err(e) => {
+                let base_detail = e.to_string();
+                self.record_capability_gap(session_key, &normalized_tool_name, &base_detail);
+                self.usage
+                    .record_tool(&normalized_tool_name, Some(&base_detail));
+                let alternatives = fallback_tools_for_tool(&normalized_tool_name);
+                let detail = if alternatives.is_empty() {
+                    base_detail
+                } else {
+                    format!(
+                        "{} | fallback_tools: {}",
+                        base_detail,
+                        alternatives.join(", ")
+                    )
+                };
+                self.print_action(
+                    session_key,
+                    action_index,
+                    "error",
+                    &normalized_tool_name,
+                    &format!(
+                        "dispatcher in {}ms {}",
+                        started.elapsed().as_millis(),
+                        truncate_for_table(&detail, 112)
+                    ),
+                    Some(started.elapsed().as_millis() as u64),
+                );
+                Err(hypr_claw_runtime::RuntimeError::ToolError(detail))
+            }
+        }
+    }
+}
+
+fn fallback_tools_for_tool(tool_name: &str) -> Vec<&'static str> {
+    match tool_name {
+        "desktop.open_gmail" => vec!["desktop.open_url", "desktop.launch_app"],
+        "desktop.open_url" => vec!["desktop.search_web", "desktop.launch_app"],
+        "desktop.launch_app" => vec!["proc.spawn", "hypr.exec"],
+        "proc.spawn" => vec!["hypr.exec", "desktop.launch_app"],
+        "desktop.ocr_screen" => vec!["desktop.capture_screen"],
+        "desktop.window_diff" => vec!["desktop.ocr_screen", "desktop.read_screen_state"],
+        "desktop.click_text" => vec!["desktop.find_text", "desktop.mouse_click"],
+        "form.fill" => vec!["desktop.click_text", "desktop.type_text"],
+        "desktop.find_text" => vec!["desktop.ocr_screen", "desktop.capture_screen"],
+        "desktop.read_screen_state" => vec![
+            "desktop.active_window",
+            "desktop.list_windows",
+            "desktop.cursor_position",
+            "desktop.capture_screen",
+            "desktop.ocr_screen",
+        ],
+        "desktop.mouse_move" => vec!["desktop.mouse_move_and_verify", "desktop.click_at"],
+        "desktop.click_at" => vec!["desktop.click_at_and_verify", "desktop.mouse_click"],
+        "desktop.click_at_and_verify" => vec!["desktop.mouse_move_and_verify", "desktop.click_at"],
+        "hypr.workspace.switch" => vec!["hypr.exec"],
+        "hypr.window.focus" => vec!["hypr.exec"],
+        "hypr.window.move" => vec!["hypr.exec"],
+        "hypr.window.close" => vec!["hypr.exec"],
+        "hypr.exec" => vec!["proc.spawn"],
+        "fs.list" => vec!["hypr.exec"],
+        "fs.read" => vec!["hypr.exec"],
+        "fs.write" => vec!["hypr.exec"],
+        _ => Vec::new(),
+    }
+}
+
+// Adapter for ToolRegistry
+struct RuntimeRegistryAdapter {
+    inner: Arc<hypr_claw_tools::ToolRegistryImpl>,
+    allowed_tools: Arc<RwLock<HashSet<String>>>,
+}
+
+impl RuntimeRegistryAdapter {
+    fn new(
+        inner: Arc<hypr_claw_tools::ToolRegistryImpl>,
+        allowed_tools: Arc<RwLock<HashSet<String>>>,
+    ) -> Self {
+        Self {
+            inner,
+            allowed_tools,
+        }
+    }
+
+    fn set_allowed_tools(&self, allowed_tools: HashSet<String>) {
+        if let Ok(mut guard) = self.allowed_tools.write() {
+            *guard = allowed_tools;
+        }
+    }
+}
+
+impl hypr_claw_runtime::ToolRegistry for RuntimeRegistryAdapter {
+    fn get_active_tools(&self, _agent_id: &str) -> Vec<String> {
+        let allowed = self.allowed_tools.read().ok();
+        let Some(allowed) = allowed else {
+            return Vec::new();
+        };
+        self.inner
+            .list()
+            .into_iter()
+            .filter(|name| allowed.contains(name))
+            .collect()
+    }
+
+    fn get_tool_schemas(&self, _agent_id: &str) -> Vec<serde_json::Value> {
+        let allowed = self.allowed_tools.read().ok();
+        let Some(allowed) = allowed else {
+            return Vec::new();
+        };
+        self.inner
+            .schemas()
+            .into_iter()
+            .filter(|schema| {
+                schema
+                    .get("function")
+                    .and_then(|f| f.get("name"))
+                    .and_then(|n| n.as_str())
+                    .map(|name| allowed.contains(name))
+                    .unwrap_or(false)
+            })
+            .collect()
+    }
+
+    fn is_read_only(&self, tool_name: &str) -> bool {
+        self.inner
+            .get(&normalize_runtime_tool_name(tool_name))
+            .is_some_and(|tool| tool.permission_tier() == hypr_claw_tools::PermissionTier::Read)
+    }
+}
+
+// Simple summarizer implementation
+struct SimpleSummarizer;
+
+impl hypr_claw_runtime::Summarizer for SimpleSummarizer {
+    fn summarize(
+        &self,
+        messages: &[hypr_claw_runtime::Message],
+    ) -> Result<String, hypr_claw_runtime::RuntimeError> {
+        Ok(format!("Summary of {} messages", messages.len()))
+    }
+}
+
+#[cfg(test)]
+mod reliability_policy_tests {
+    use super::*;
+
+    #[test]
+    fn stop_code_mapping_basic_paths() {
+        assert_eq!(
+            stop_code_for_error("Interrupted by user"),
+            "STOP_USER_INTERRUPT"
+        );
+        assert_eq!(
+            stop_code_for_error("Execution watchdog timeout after 90s"),
+            "STOP_WATCHDOG_TIMEOUT"
+        );
+        assert_eq!(
+            stop_code_for_error("Detected repetitive tool loop for 'desktop.capture_screen'"),
+            "STOP_TOOL_LOOP"
+        );
+        assert_eq!(
+            stop_code_for_error("Too many consecutive tool failures. Halting this run."),
+            "STOP_TOOL_FAILURE_STREAK"
+        );
+        assert_eq!(
+            stop_code_for_error("Tool invocation required but not performed"),
+            "STOP_TOOL_ENFORCEMENT"
+        );
+        assert_eq!(
+            stop_code_for_error("Action requested, but no tool call completed successfully."),
+            "STOP_TOOL_ENFORCEMENT"
+        );
+        assert_eq!(
+            stop_code_for_error("HTTP error: 400 Bad Request. Details: INVALID_ARGUMENT"),
+            "STOP_PROVIDER_ARGUMENT"
+        );
+        assert_eq!(
+            stop_code_for_error(
+                "LLM error: Rate limit exceeded. Details: {\"status\":429,\"title\":\"Too Many Requests\"}"
+            ),
+            "STOP_PROVIDER_RATE_LIMIT"
+        );
+        assert_eq!(
+            stop_code_for_error("Max iterations (16) reached after 16 tool calls"),
+            "STOP_MAX_ITERATIONS"
+        );
+        assert_eq!(
+            stop_code_for_error("Session conflict on agent:main: expected version 429, found 430"),
+            "STOP_SESSION_CONFLICT"
+        );
+        assert_eq!(
+            stop_code_for_error("some unknown runtime"),
+            "STOP_RUNTIME_ERROR"
+        );
+    }
+
+    #[test]
+    fn stop_code_resolution_respects_recovery_budget() {
+        let err = "Max iterations (16) reached after 16 tool calls";
+        assert_eq!(resolve_stop_code(err, 0), "STOP_MAX_ITERATIONS");
+        assert_eq!(
+            resolve_stop_code(err, MAX_RECOVERY_ATTEMPTS),
+            "STOP_RECOVERY_BUDGET_EXHAUSTED"
+        );
+    }
+
+    #[test]
+    fn stop_code_resolution_keeps_user_interrupt_code() {
+        assert_eq!(
+            resolve_stop_code("Interrupted by user", MAX_RECOVERY_ATTEMPTS + 3),
+            "STOP_USER_INTERRUPT"
+        );
+    }
+
+    #[test]
+    fn retry_after_parser_supports_fractional_seconds() {
+        let err =
+            "Rate limit exceeded. Please retry in 18.932776315s. quotaId=GenerateRequestsPerMinute";
+        assert_eq!(extract_retry_after_seconds(err), Some(19));
+    }
+
+    #[test]
+    fn remediation_hints_are_contextual() {
+        let mail_hint =
+            remediation_hint_for_stop_code("STOP_MAX_ITERATIONS", "open gmail and summarise");
+        assert!(mail_hint.contains("open + capture"));
+
+        let wallpaper_hint =
+            remediation_hint_for_stop_code("STOP_MAX_ITERATIONS", "change wallpaper");
+        assert!(wallpaper_hint.contains("caelestia"));
+
+        let code_hint = remediation_hint_for_stop_code("STOP_MAX_ITERATIONS", "open vscode");
+        assert!(code_hint.contains("launch command"));
+
+        let generic_hint = remediation_hint_for_stop_code("STOP_RUNTIME_ERROR", "anything");
+        assert!(generic_hint.contains("status"));
+    }
+
+    #[test]
+    fn autonomy_defaults_to_prompt_first() {
+        assert_eq!(default_autonomy_mode(), AutonomyMode::PromptFirst);
+    }
+
+    #[test]
+    fn prompt_first_budget_is_higher_than_guarded() {
+        let q_prompt =
+            execution_budget_for_class(&SupervisedTaskClass::Question, &AutonomyMode::PromptFirst)
+                .max_iterations;
+        let q_guarded =
+            execution_budget_for_class(&SupervisedTaskClass::Question, &AutonomyMode::Guarded)
+                .max_iterations;
+        assert!(q_prompt > q_guarded);
+    }
+
+    #[test]
+    fn autonomy_calibration_records_by_mode() {
+        let mut state = AgentOsState::default();
+        record_autonomy_outcome(
+            &mut state,
+            &AutonomyMode::PromptFirst,
+            true,
+            1200,
+            1,
+            "STOP_NONE",
+        );
+        record_autonomy_outcome(
+            &mut state,
+            &AutonomyMode::Guarded,
+            false,
+            900,
+            2,
+            "STOP_MAX_ITERATIONS",
+        );
+
+        assert_eq!(state.autonomy_calibration.prompt_first.runs, 1);
+        assert_eq!(state.autonomy_calibration.prompt_first.successes, 1);
+        assert_eq!(
+            *state
+                .autonomy_calibration
+                .prompt_first
+                .stop_codes
+                .get("STOP_NONE")
+                .unwrap_or(&0),
+            1
+        );
+
+        assert_eq!(state.autonomy_calibration.guarded.runs, 1);
+        assert_eq!(state.autonomy_calibration.guarded.failures, 1);
+        assert_eq!(
+            *state
+                .autonomy_calibration
+                .guarded
+                .stop_codes
+                .get("STOP_MAX_ITERATIONS")
+                .unwrap_or(&0),
+            1
+        );
+    }
+
+    #[test]
+    fn capability_registry_path_is_sanitized() {
+        let path = capability_registry_file_path("rick@local host");
+        assert!(path.contains("rick_local_host"));
+        assert!(path.ends_with(".json"));
+    }
+
+    #[test]
+    fn capability_registry_refreshes_when_profile_is_newer() {
+        let profile = json!({
+            "scanned_at": 100,
+            "paths": { "downloads": "/home/rick/Downloads" },
+            "capabilities": { "wallpaper_backends": ["caelestia"] }
+        });
+        let registry = json!({
+            "schema_version": 1,
+            "source_profile_scanned_at": 90,
+            "paths": { "downloads": "/home/rick/Downloads" },
+            "capabilities": { "wallpaper_backends": ["caelestia"] },
+            "source_has_deep_scan": false
+        });
+        assert!(capability_registry_needs_refresh(&registry, &profile));
+    }
+
+    #[test]
+    fn capability_registry_diff_lines_report_key_changes() {
+        let old_registry = json!({
+            "platform": {
+                "distro_name": "Arch Linux",
+                "kernel": "6.18.1",
+                "active_workspace": 2
+            },
+            "capabilities": {
+                "wallpaper_backends": ["hyprpaper"],
+                "screenshot_backends": ["grim"],
+                "input_backends": ["wtype"]
+            },
+            "desktop_apps": {
+                "launcher_commands": ["firefox"]
+            },
+            "projects": {
+                "known_roots": ["/home/rick/hypr-claw"]
+            },
+            "editor": {
+                "vscode_command": "code"
+            }
+        });
+        let new_registry = json!({
+            "platform": {
+                "distro_name": "Arch Linux",
+                "kernel": "6.18.7",
+                "active_workspace": 4
+            },
+            "capabilities": {
+                "wallpaper_backends": ["caelestia"],
+                "screenshot_backends": ["grim", "grimblast"],
+                "input_backends": ["ydotool"]
+            },
+            "desktop_apps": {
+                "launcher_commands": ["firefox", "kitty"]
+            },
+            "projects": {
+                "known_roots": ["/home/rick/hypr-claw", "/home/rick/other"]
+            },
+            "editor": {
+                "vscode_command": "code-oss"
+            }
+        });
+
+        let lines = capability_registry_diff_lines(&old_registry, &new_registry);
+        assert!(lines.iter().any(|line| line.contains("platform kernel")));
+        assert!(lines.iter().any(|line| line.contains("active workspace")));
+        assert!(lines.iter().any(|line| line.contains("wallpaper backends")));
+        assert!(lines.iter().any(|line| line.contains("vscode command")));
+    }
+
+    #[test]
+    fn preferred_launchers_pick_known_commands() {
+        let launcher_commands = vec![
+            "firefox".to_string(),
+            "telegram-desktop".to_string(),
+            "kitty".to_string(),
+        ];
+        let available_commands = vec!["code-oss".to_string()];
+        let preferred =
+            build_preferred_launchers(&launcher_commands, &available_commands, "code-oss");
+
+        assert_eq!(
+            preferred.pointer("/vscode").and_then(|v| v.as_str()),
+            Some("code-oss")
+        );
+        assert_eq!(
+            preferred.pointer("/browser").and_then(|v| v.as_str()),
+            Some("firefox")
+        );
+        assert_eq!(
+            preferred.pointer("/telegram").and_then(|v| v.as_str()),
+            Some("telegram-desktop")
+        );
+        assert_eq!(
+            preferred.pointer("/terminal").and_then(|v| v.as_str()),
+            Some("kitty")
+        );
+    }
+
+    #[test]
+    fn inferred_resources_include_desktop_and_network() {
+        let resources = infer_supervised_task_resources("open telegram and summarize messages");
+        assert!(resources.iter().any(|r| r == "desktop_input"));
+        assert!(resources.iter().any(|r| r == "network"));
+    }
+
+    #[test]
+    fn shared_resources_do_not_conflict() {
+        let a = vec!["network".to_string()];
+        let b = vec!["network".to_string(), "compute".to_string()];
+        let conflicts = resource_conflicts(&a, &b);
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn cd_only_takes_lines_naming_a_directory() {
+        let dir = std::env::temp_dir();
+        let existing = format!("cd {}", dir.display());
+        assert_eq!(cd_argument(&existing), dir.to_str());
+        assert_eq!(cd_argument("cd"), Some(""));
+        assert_eq!(cd_argument("cd into Downloads and list the PDFs"), None);
+        assert_eq!(cd_argument("cdrom status"), None);
+        assert_eq!(cd_argument("/cd nowhere/at/all"), Some("nowhere/at/all"));
+    }
+
+    #[test]
+    fn running_background_conflict_reason_reports_overlap() {
+        let now = chrono::Utc::now().timestamp();
+        let running = vec![hypr_claw_tasks::TaskInfo {
+            id: "bg-99".to_string(),
+            description: "open browser and click login".to_string(),
+            status: hypr_claw_tasks::TaskStatus::Running,
+            progress: 0.3,
+            created_at: now - 10,
+            updated_at: now - 2,
+            result: None,
+            error: None,
+        }];
+
+        let reason = running_background_conflict_reason("open gmail and reply", &running)
+            .unwrap_or_default();
+        assert!(reason.contains("bg-99"));
+        assert!(reason.contains("desktop_input"));
+    }
+
+    #[test]
+    fn queue_start_is_blocked_when_another_task_is_running() {
+        let mut state = AgentOsState::default();
+        start_supervised_task(
+            &mut state,
+            "open firefox".to_string(),
+            SupervisedTaskClass::Action,
+        );
+        enqueue_supervised_task(
+            &mut state,
+            "open telegram".to_string(),
+            SupervisedTaskClass::Action,
+        );
+        match start_next_queued_supervised_task(&mut state) {
+            QueueStartResult::Blocked(_) => {}
+            _ => panic!("expected queue start to be blocked by running task"),
+        }
+    }
+
+    #[test]
+    fn queue_start_allows_non_conflicting_resources() {
+        let mut state = AgentOsState::default();
+        start_supervised_task(
+            &mut state,
+            "open firefox".to_string(),
+            SupervisedTaskClass::Action,
+        );
+        enqueue_supervised_task(
+            &mut state,
+            "search web api docs".to_string(),
+            SupervisedTaskClass::Action,
+        );
+        match start_next_queued_supervised_task(&mut state) {
+            QueueStartResult::Started(task) => {
+                assert_eq!(task.status, SupervisedTaskStatus::Running);
+                assert!(task.resources.iter().any(|r| r == "network"));
+            }
+            QueueStartResult::Blocked(reason) => {
+                panic!(
+                    "expected non-conflicting task to start, got blocked: {}",
+                    reason
+                )
+            }
+            QueueStartResult::Empty => panic!("expected queued task to start"),
+        }
+    }
+
+    #[test]
+    fn mark_cancelled_clears_background_link() {
+        let mut state = AgentOsState::default();
+        let task_id = start_supervised_task(
+            &mut state,
+            "search docs".to_string(),
+            SupervisedTaskClass::Action,
+        );
+        set_supervised_task_background_id(&mut state, &task_id, Some("supbg-1".to_string()));
+        mark_supervised_task_cancelled(&mut state, &task_id, Some("Cancelled by test".to_string()));
+        let task = state
+            .supervisor
+            .tasks
+            .iter()
+            .find(|task| task.id == task_id)
+            .expect("task must exist");
+        assert_eq!(task.status, SupervisedTaskStatus::Cancelled);
+        assert!(task.background_task_id.is_none());
+    }
+
+    #[test]
+    fn prune_supervisor_tasks_keeps_running_and_latest_terminal() {
+        let now = chrono::Utc::now().timestamp();
+        let mut state = AgentOsState::default();
+        state.supervisor.tasks = vec![
+            SupervisedTask {
+                id: "sup-run".to_string(),
+                prompt: "running".to_string(),
+                class: SupervisedTaskClass::Action,
+                resources: vec!["network".to_string()],
+                background_task_id: Some("supbg-run".to_string()),
+                status: SupervisedTaskStatus::Running,
+                created_at: now - 50,
+                updated_at: now - 5,
+                error: None,
+                attempt_log: None,
+                priority: 0,
+                depends_on: Vec::new(),
+            },
+            SupervisedTask {
+                id: "sup-old-1".to_string(),
+                prompt: "old1".to_string(),
+                class: SupervisedTaskClass::Action,
+                resources: vec!["general".to_string()],
+                background_task_id: None,
+                status: SupervisedTaskStatus::Completed,
+                created_at: now - 100,
+                updated_at: now - 100,
+                error: None,
+                attempt_log: None,
+                priority: 0,
+                depends_on: Vec::new(),
+            },
+            SupervisedTask {
+                id: "sup-old-2".to_string(),
+                prompt: "old2".to_string(),
+                class: SupervisedTaskClass::Action,
+                resources: vec!["general".to_string()],
+                background_task_id: None,
+                status: SupervisedTaskStatus::Failed,
+                created_at: now - 90,
+                updated_at: now - 90,
+                error: Some("x".to_string()),
+                attempt_log: None,
+                priority: 0,
+                depends_on: Vec::new(),
+            },
+            SupervisedTask {
+                id: "sup-new".to_string(),
+                prompt: "new".to_string(),
+                class: SupervisedTaskClass::Action,
+                resources: vec!["general".to_string()],
+                background_task_id: None,
+                status: SupervisedTaskStatus::Cancelled,
+                created_at: now - 10,
+                updated_at: now - 1,
+                error: Some("y".to_string()),
+                attempt_log: None,
+                priority: 0,
+                depends_on: Vec::new(),
+            },
+        ];
+
+        let removed = prune_supervisor_tasks(&mut state, 1);
+        assert_eq!(removed, 2);
+        assert!(state.supervisor.tasks.iter().any(|t| t.id == "sup-run"));
+        assert!(state.supervisor.tasks.iter().any(|t| t.id == "sup-new"));
+        assert!(!state.supervisor.tasks.iter().any(|t| t.id == "sup-old-1"));
+        assert!(!state.supervisor.tasks.iter().any(|t| t.id == "sup-old-2"));
     }
 
-    fn set_allowed_tools(&self, allowed_tools: HashSet<String>) {
-        if let Ok(mut guard) = self.allowed_tools.write() {
-            *guard = allowed_tools;
-        }
+    #[test]
+    fn reconcile_marks_stale_running_tasks_as_failed() {
+        let mut state = AgentOsState::default();
+        start_supervised_task(
+            &mut state,
+            "open firefox".to_string(),
+            SupervisedTaskClass::Action,
+        );
+        let recovered = reconcile_supervisor_after_restart(&mut state);
+        assert_eq!(recovered, 1);
+        assert!(state
+            .supervisor
+            .tasks
+            .iter()
+            .any(|t| t.status == SupervisedTaskStatus::Failed));
     }
-}
 
-impl hypr_claw_runtime::ToolRegistry for RuntimeRegistryAdapter {
-    fn get_active_tools(&self, _agent_id: &str) -> Vec<String> {
-        let allowed = self.allowed_tools.read().ok();
-        let Some(allowed) = allowed else {
-            return Vec::new();
-        };
-        self.inner
-            .list()
-            .into_iter()
-            .filter(|name| allowed.contains(name))
-            .collect()
-    }
+    #[test]
+    fn tail_window_slice_paginates_from_latest() {
+        let rows: Vec<u32> = (1..=12).collect();
+        let (latest, start, end, total) = tail_window_slice(&rows, 0, 5);
+        assert_eq!(latest, vec![8, 9, 10, 11, 12]);
+        assert_eq!((start, end, total), (8, 12, 12));
 
-    fn get_tool_schemas(&self, _agent_id: &str) -> Vec<serde_json::Value> {
-        let allowed = self.allowed_tools.read().ok();
-        let Some(allowed) = allowed else {
-            return Vec::new();
-        };
-        self.inner
-            .schemas()
-            .into_iter()
-            .filter(|schema| {
-                schema
-                    .get("function")
-                    .and_then(|f| f.get("name"))
-                    .and_then(|n| n.as_str())
-                    .map(|name| allowed.contains(name))
-                    .unwrap_or(false)
-            })
-            .collect()
+        let (older, start, end, total) = tail_window_slice(&rows, 5, 5);
+        assert_eq!(older, vec![3, 4, 5, 6, 7]);
+        assert_eq!((start, end, total), (3, 7, 12));
     }
-}
 
-// Simple summarizer implementation
-struct SimpleSummarizer;
+    #[test]
+    fn task_log_lines_include_background_and_supervisor_entries() {
+        let now = chrono::Utc::now().timestamp();
+        let bg_tasks = vec![hypr_claw_tasks::TaskInfo {
+            id: "bg-1".to_string(),
+            description: "compile project".to_string(),
+            status: hypr_claw_tasks::TaskStatus::Completed,
+            progress: 1.0,
+            created_at: now - 5,
+            updated_at: now - 2,
+            result: Some("done".to_string()),
+            error: None,
+        }];
 
-impl hypr_claw_runtime::Summarizer for SimpleSummarizer {
-    fn summarize(
-        &self,
-        messages: &[hypr_claw_runtime::Message],
-    ) -> Result<String, hypr_claw_runtime::RuntimeError> {
-        Ok(format!("Summary of {} messages", messages.len()))
-    }
-}
+        let mut state = AgentOsState::default();
+        state.supervisor.tasks.push(SupervisedTask {
+            id: "sup-1".to_string(),
+            prompt: "open firefox".to_string(),
+            class: SupervisedTaskClass::Action,
+            resources: vec!["desktop_input".to_string()],
+            background_task_id: None,
+            status: SupervisedTaskStatus::Failed,
+            created_at: now - 4,
+            updated_at: now - 1,
+            error: Some("missing binary".to_string()),
+            attempt_log: None,
+            priority: 0,
+            depends_on: Vec::new(),
+        });
 
-#[cfg(test)]
-mod reliability_policy_tests {
-    use super::*;
+        let task_event_feed: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let lines = build_task_log_lines(&bg_tasks, &state, &task_event_feed);
+        assert!(lines.iter().any(|line| line.contains("bg bg-1")));
+        assert!(lines.iter().any(|line| line.contains("sup sup-1")));
+    }
 
     #[test]
-    fn stop_code_mapping_basic_paths() {
-        assert_eq!(
-            stop_code_for_error("Interrupted by user"),
-            "STOP_USER_INTERRUPT"
-        );
-        assert_eq!(
-            stop_code_for_error("Execution watchdog timeout after 90s"),
-            "STOP_WATCHDOG_TIMEOUT"
-        );
-        assert_eq!(
-            stop_code_for_error("Detected repetitive tool loop for 'desktop.capture_screen'"),
-            "STOP_TOOL_LOOP"
-        );
-        assert_eq!(
-            stop_code_for_error("Too many consecutive tool failures. Halting this run."),
-            "STOP_TOOL_FAILURE_STREAK"
-        );
-        assert_eq!(
-            stop_code_for_error("Tool invocation required but not performed"),
-            "STOP_TOOL_ENFORCEMENT"
-        );
+    fn action_feed_task_tag_extracts_supervisor_id() {
         assert_eq!(
-            stop_code_for_error("Action requested, but no tool call completed successfully."),
-            "STOP_TOOL_ENFORCEMENT"
+            RuntimeDispatcherAdapter::task_tag("user:agent::sup::sup-1"),
+            Some("sup-1")
         );
         assert_eq!(
-            stop_code_for_error("HTTP error: 400 Bad Request. Details: INVALID_ARGUMENT"),
-            "STOP_PROVIDER_ARGUMENT"
+            RuntimeDispatcherAdapter::task_tag("alice:hypr-claw::sup::sup-42"),
+            Some("sup-42")
         );
-        assert_eq!(
-            stop_code_for_error(
-                "LLM error: Rate limit exceeded. Details: {\"status\":429,\"title\":\"Too Many Requests\"}"
-            ),
-            "STOP_PROVIDER_RATE_LIMIT"
+        assert_eq!(RuntimeDispatcherAdapter::task_tag("user:agent"), None);
+        assert_eq!(RuntimeDispatcherAdapter::task_tag(""), None);
+    }
+
+    #[test]
+    fn sanitize_single_line_strips_ansi_and_controls() {
+        let raw = "hello\u{1b}[31m red\u{1b}[0m world\u{0008}";
+        let cleaned = sanitize_single_line(raw);
+        assert_eq!(cleaned, "hello red world");
+        assert!(!cleaned.contains('\u{1b}'));
+    }
+
+    #[test]
+    fn sanitize_user_input_removes_prompt_wrappers() {
+        let raw = "hypr[task-1|glm4.7|run:0]> open mail\u{1b}[D";
+        let cleaned = sanitize_user_input_line(raw);
+        assert_eq!(cleaned, "open mail");
+    }
+
+    #[test]
+    fn capability_delta_history_roundtrip() {
+        let old_r = json!({"platform": {"distro_name": "old"}, "generated_at": 100});
+        let new_r = json!({"platform": {"distro_name": "arch"}, "generated_at": 200});
+        let diff = capability_registry_diff_lines(&old_r, &new_r);
+        assert!(
+            !diff.is_empty(),
+            "diff should be non-empty when registries differ"
         );
+        let record = json!({
+            "at": chrono::Utc::now().timestamp(),
+            "summary": format!("{} change(s)", diff.len()),
+            "diff_lines": diff,
+        });
+        let line = serde_json::to_string(&record).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&line).unwrap();
+        let read_back = parsed.get("diff_lines").unwrap().as_array().unwrap();
+        assert!(read_back.len() > 0);
+    }
+
+    #[test]
+    fn start_next_returns_empty_when_no_queued_tasks() {
+        let mut state = AgentOsState::default();
+        let result = start_next_queued_supervised_task(&mut state);
+        assert!(matches!(result, QueueStartResult::Empty));
+        state.supervisor.tasks.push(SupervisedTask {
+            id: "sup-1".to_string(),
+            prompt: "done task".to_string(),
+            class: SupervisedTaskClass::Action,
+            resources: vec!["network".to_string()],
+            background_task_id: None,
+            status: SupervisedTaskStatus::Completed,
+            created_at: 0,
+            updated_at: 0,
+            error: None,
+            attempt_log: None,
+            priority: 0,
+            depends_on: Vec::new(),
+        });
+        let result2 = start_next_queued_supervised_task(&mut state);
+        assert!(matches!(result2, QueueStartResult::Empty));
+    }
+
+    #[test]
+    fn start_next_honors_priority_and_dependencies() {
+        let mut state = AgentOsState::default();
+        let file = queue_file::QueueFile::parse(
+            "tasks:\n\
+             - id: report\n  prompt: write the report\n  depends_on: [fetch]\n  priority: 50\n\
+             - id: fetch\n  prompt: fetch the data\n\
+             - id: urgent\n  prompt: answer the question\n  class: question\n  priority: 5\n",
+        )
+        .unwrap();
+        let ids = import_supervisor_queue(&mut state, &file).unwrap();
+        let (report, fetch, urgent) = (ids[0].clone(), ids[1].clone(), ids[2].clone());
+        let report_task = state
+            .supervisor
+            .tasks
+            .iter()
+            .find(|t| t.id == report)
+            .unwrap();
+        assert_eq!(report_task.depends_on, vec![fetch.clone()]);
+        assert_eq!(export_supervisor_queue(&state).tasks.len(), 3);
+
+        let started = |state: &mut AgentOsState| match start_next_queued_supervised_task(state) {
+            QueueStartResult::Started(task) => task.id,
+            _ => panic!("expected a task to start"),
+        };
+        assert_eq!(started(&mut state), urgent);
+        mark_supervised_task_completed(&mut state, &urgent);
+        assert_eq!(started(&mut state), fetch);
+        assert!(matches!(
+            start_next_queued_supervised_task(&mut state),
+            QueueStartResult::Blocked(reason) if reason.contains("waiting on dependencies")
+        ));
+        mark_supervised_task_failed(&mut state, &fetch, "offline".to_string());
+        assert!(matches!(
+            start_next_queued_supervised_task(&mut state),
+            QueueStartResult::Empty
+        ));
         assert_eq!(
-            stop_code_for_error("Max iterations (16) reached after 16 tool calls"),
-            "STOP_MAX_ITERATIONS"
+            supervised_task_status(&state, &report),
+            Some(SupervisedTaskStatus::Cancelled)
         );
+    }
+
+    #[test]
+    fn skill_run_args_keep_scalar_types() {
+        let (name, params) =
+            parse_skill_run_args("organize-downloads dir=/tmp/dl dry_run=true depth=2").unwrap();
+        assert_eq!(name, "organize-downloads");
         assert_eq!(
-            stop_code_for_error("some unknown runtime"),
-            "STOP_RUNTIME_ERROR"
+            params,
+            json!({"dir": "/tmp/dl", "dry_run": true, "depth": 2})
         );
+        assert_eq!(parse_skill_run_args("tidy").unwrap().1, json!({}));
+        assert!(parse_skill_run_args("tidy stray").is_none());
+        assert!(parse_skill_run_args("").is_none());
     }
 
     #[test]
-    fn stop_code_resolution_respects_recovery_budget() {
-        let err = "Max iterations (16) reached after 16 tool calls";
-        assert_eq!(resolve_stop_code(err, 0), "STOP_MAX_ITERATIONS");
+    fn prompt_sections_make_up_the_turn_prompt() {
+        let (empty, tools) = (json!({}), HashSet::from(["fs.read".to_string()]));
+        let facts = vec!["preferred_name:Ana".to_string()];
+        let turn = context_providers::TurnContext {
+            profile: &empty,
+            capability_registry: &empty,
+            allowed_tools: &tools,
+            autonomy_mode: "guarded",
+            workflow_mode: "legacy",
+            facts: &facts,
+            running_tasks: &[],
+            working_dir: None,
+            now: chrono::Local::now().fixed_offset(),
+        };
+        let providers =
+            context_providers::ContextRegistry::from_config(&config::ContextConfig::default());
+        let prompt =
+            augment_system_prompt_for_turn("soul", &providers, &turn, &AutonomyMode::Guarded);
+        assert!(prompt.starts_with("soul\n\nRuntime context:\n"));
+        assert!(prompt.ends_with(execution_policy(&AutonomyMode::Guarded)));
+        for (name, text) in providers.render_sections(&turn) {
+            assert!(prompt.contains(&text), "{name} section missing");
+        }
+        assert!(prompt.contains("- fact: preferred_name:Ana"));
+    }
+
+    #[test]
+    fn reminder_add_args_parse_flags_and_follow_up() {
+        let request =
+            parse_reminder_add_args("10m stretch your legs --speak --then open spotify").unwrap();
+        assert_eq!(request.when, "10m");
+        assert_eq!(request.message, "stretch your legs");
+        assert!(request.speak);
+        assert_eq!(request.follow_up_prompt.as_deref(), Some("open spotify"));
+
+        let plain = parse_reminder_add_args("18:30 call mom").unwrap();
+        assert!(!plain.speak);
+        assert!(plain.follow_up_prompt.is_none());
+
+        assert!(parse_reminder_add_args("10m").is_none());
+        assert!(parse_reminder_add_args("--speak 10m").is_none());
+    }
+
+    #[test]
+    fn focused_tools_keep_ask_user_available() {
+        let allowed: HashSet<String> = ["fs.list", "fs.delete", "ask_user", "desktop.open_url"]
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let focused = focused_tools_for_input("delete the old files", &allowed);
+        assert!(focused.contains("fs.delete"));
+        assert!(focused.contains("ask_user"));
+        assert!(!focused.contains("desktop.open_url"));
+
+        let prompt = answered_question_prompt("tidy ~/Downloads", "Keep the ISOs?", "no");
+        assert!(prompt.starts_with("tidy ~/Downloads\n\n"));
+        assert!(prompt.contains("Their answer: no"));
+    }
+
+    #[test]
+    fn remembered_facts_replace_older_values_for_the_same_key() {
+        let mut facts = vec![
+            "preferred_name:Ana".to_string(),
+            "address_city:Lisbon".to_string(),
+        ];
+        remember_fact(&mut facts, "address_city:Porto".to_string());
+        remember_fact(&mut facts, "address_zip:4000-001".to_string());
         assert_eq!(
-            resolve_stop_code(err, MAX_RECOVERY_ATTEMPTS),
-            "STOP_RECOVERY_BUDGET_EXHAUSTED"
+            facts,
+            vec![
+                "preferred_name:Ana",
+                "address_city:Porto",
+                "address_zip:4000-001"
+            ]
         );
     }
 
     #[test]
-    fn stop_code_resolution_keeps_user_interrupt_code() {
+    fn popout_terminal_commands_run_this_binary_on_the_thread() {
         assert_eq!(
-            resolve_stop_code("Interrupted by user", MAX_RECOVERY_ATTEMPTS + 3),
-            "STOP_USER_INTERRUPT"
+            popout_terminal_command("kitty", "/usr/bin/hypr-claw", "task-2").as_deref(),
+            Some("kitty --class hypr-claw-popout -e '/usr/bin/hypr-claw' --thread 'task-2'")
+        );
+        assert_eq!(
+            popout_terminal_command("wezterm", "/opt/it's/hypr-claw", "task-2").as_deref(),
+            Some(
+                "wezterm start --class hypr-claw-popout -- '/opt/it'\\''s/hypr-claw' --thread 'task-2'"
+            )
+        );
+        assert_eq!(
+            popout_terminal_command("unknown", "/usr/bin/hypr-claw", "task-2"),
+            None
         );
+        assert_eq!(popout_workspace("task-2"), "claw-task-2");
     }
 
     #[test]
-    fn retry_after_parser_supports_fractional_seconds() {
-        let err =
-            "Rate limit exceeded. Please retry in 18.932776315s. quotaId=GenerateRequestsPerMinute";
-        assert_eq!(extract_retry_after_seconds(err), Some(19));
+    fn contact_add_args_split_name_and_fields() {
+        let (name, fields) =
+            parse_contact_add_args("Alice Smith email=alice@example.com telegram=@alice").unwrap();
+        assert_eq!(name, "Alice Smith");
+        assert_eq!(
+            fields,
+            vec![
+                ("email".to_string(), "alice@example.com".to_string()),
+                ("telegram".to_string(), "@alice".to_string()),
+            ]
+        );
+        assert!(parse_contact_add_args("email=a@b.c").is_none());
+        assert!(parse_contact_add_args("Bob phone=1 stray").is_none());
     }
 
     #[test]
-    fn remediation_hints_are_contextual() {
-        let mail_hint =
-            remediation_hint_for_stop_code("STOP_MAX_ITERATIONS", "open gmail and summarise");
-        assert!(mail_hint.contains("open + capture"));
-
-        let wallpaper_hint =
-            remediation_hint_for_stop_code("STOP_MAX_ITERATIONS", "change wallpaper");
-        assert!(wallpaper_hint.contains("caelestia"));
-
-        let code_hint = remediation_hint_for_stop_code("STOP_MAX_ITERATIONS", "open vscode");
-        assert!(code_hint.contains("launch command"));
+    fn verification_applies_to_action_tasks_per_mode() {
+        let mut config: Config = serde_yaml::from_str("provider: nvidia\nmodel: m\n").unwrap();
+        let action = SupervisedTaskClass::Action;
+        assert!(verification_required(
+            &action,
+            &AutonomyMode::PromptFirst,
+            &config
+        ));
+        assert!(!verification_required(
+            &SupervisedTaskClass::Question,
+            &AutonomyMode::Guarded,
+            &config
+        ));
 
-        let generic_hint = remediation_hint_for_stop_code("STOP_RUNTIME_ERROR", "anything");
-        assert!(generic_hint.contains("status"));
+        config.verification.prompt_first = false;
+        assert!(!verification_required(
+            &action,
+            &AutonomyMode::PromptFirst,
+            &config
+        ));
+        assert!(verification_required(
+            &action,
+            &AutonomyMode::Guarded,
+            &config
+        ));
     }
 
     #[test]
-    fn autonomy_defaults_to_prompt_first() {
-        assert_eq!(default_autonomy_mode(), AutonomyMode::PromptFirst);
-    }
+    fn registry_adapter_treats_read_tier_as_verification() {
+        use hypr_claw_runtime::ToolRegistry;
 
-    #[test]
-    fn prompt_first_budget_is_higher_than_guarded() {
-        let q_prompt =
-            execution_budget_for_class(&SupervisedTaskClass::Question, &AutonomyMode::PromptFirst)
-                .max_iterations;
-        let q_guarded =
-            execution_budget_for_class(&SupervisedTaskClass::Question, &AutonomyMode::Guarded)
-                .max_iterations;
-        assert!(q_prompt > q_guarded);
+        let mut registry = hypr_claw_tools::ToolRegistryImpl::new();
+        registry.register(Arc::new(hypr_claw_tools::os_tools::ProcListTool));
+        registry.register(Arc::new(hypr_claw_tools::os_tools::ProcSpawnTool::default()));
+        let adapter =
+            RuntimeRegistryAdapter::new(Arc::new(registry), Arc::new(RwLock::new(HashSet::new())));
+        assert!(adapter.is_read_only("proc.list"));
+        assert!(adapter.is_read_only("process.list"));
+        assert!(!adapter.is_read_only("proc.spawn"));
+        assert!(!adapter.is_read_only("unknown.tool"));
     }
 
     #[test]
-    fn autonomy_calibration_records_by_mode() {
+    fn supervised_retry_carries_previous_attempt() {
         let mut state = AgentOsState::default();
-        record_autonomy_outcome(
+        let original = start_supervised_task(
             &mut state,
-            &AutonomyMode::PromptFirst,
-            true,
-            1200,
-            1,
-            "STOP_NONE",
+            "open firefox".to_string(),
+            SupervisedTaskClass::Action,
         );
-        record_autonomy_outcome(
+        mark_supervised_task_failed(&mut state, &original, "missing binary".to_string());
+        record_supervised_task_attempt(
             &mut state,
-            &AutonomyMode::Guarded,
-            false,
-            900,
-            2,
-            "STOP_MAX_ITERATIONS",
+            &original,
+            "Previous attempt (run r) failed: missing binary".to_string(),
         );
 
-        assert_eq!(state.autonomy_calibration.prompt_first.runs, 1);
-        assert_eq!(state.autonomy_calibration.prompt_first.successes, 1);
-        assert_eq!(
-            *state
-                .autonomy_calibration
-                .prompt_first
-                .stop_codes
-                .get("STOP_NONE")
-                .unwrap_or(&0),
-            1
-        );
-
-        assert_eq!(state.autonomy_calibration.guarded.runs, 1);
-        assert_eq!(state.autonomy_calibration.guarded.failures, 1);
+        let failed = state.supervisor.tasks[0].clone();
+        let retry = enqueue_supervised_retry(&mut state, &failed);
+        let retry_task = state
+            .supervisor
+            .tasks
+            .iter()
+            .find(|task| task.id == retry)
+            .unwrap();
+        assert_eq!(retry_task.status, SupervisedTaskStatus::Queued);
+        assert_eq!(retry_task.prompt, "open firefox");
         assert_eq!(
-            *state
-                .autonomy_calibration
-                .guarded
-                .stop_codes
-                .get("STOP_MAX_ITERATIONS")
-                .unwrap_or(&0),
-            1
+            retry_task.attempt_log.as_deref(),
+            Some("Previous attempt (run r) failed: missing binary")
         );
     }
 
     #[test]
-    fn capability_registry_path_is_sanitized() {
-        let path = capability_registry_file_path("rick@local host");
-        assert!(path.contains("rick_local_host"));
-        assert!(path.ends_with(".json"));
-    }
+    fn capability_gaps_are_classified_from_tool_errors() {
+        let available = ["desktop.open_url".to_string()]
+            .into_iter()
+            .collect::<HashSet<String>>();
 
-    #[test]
-    fn capability_registry_refreshes_when_profile_is_newer() {
-        let profile = json!({
-            "scanned_at": 100,
-            "paths": { "downloads": "/home/rick/Downloads" },
-            "capabilities": { "wallpaper_backends": ["caelestia"] }
-        });
-        let registry = json!({
-            "schema_version": 1,
-            "source_profile_scanned_at": 90,
-            "paths": { "downloads": "/home/rick/Downloads" },
-            "capabilities": { "wallpaper_backends": ["caelestia"] },
-            "source_has_deep_scan": false
-        });
-        assert!(capability_registry_needs_refresh(&registry, &profile));
-    }
+        let (kind, install) = detect_capability_gap(
+            "desktop.teleport",
+            "Validation error: Tool not found: desktop.teleport",
+            &available,
+        )
+        .unwrap();
+        assert_eq!(kind, CapabilityGapKind::UnknownTool);
+        assert!(install.is_empty());
 
-    #[test]
-    fn capability_registry_diff_lines_report_key_changes() {
-        let old_registry = json!({
-            "platform": {
-                "distro_name": "Arch Linux",
-                "kernel": "6.18.1",
-                "active_workspace": 2
-            },
-            "capabilities": {
-                "wallpaper_backends": ["hyprpaper"],
-                "screenshot_backends": ["grim"],
-                "input_backends": ["wtype"]
-            },
-            "desktop_apps": {
-                "launcher_commands": ["firefox"]
-            },
-            "projects": {
-                "known_roots": ["/home/rick/hypr-claw"]
-            },
-            "editor": {
-                "vscode_command": "code"
-            }
-        });
-        let new_registry = json!({
-            "platform": {
-                "distro_name": "Arch Linux",
-                "kernel": "6.18.7",
-                "active_workspace": 4
-            },
-            "capabilities": {
-                "wallpaper_backends": ["caelestia"],
-                "screenshot_backends": ["grim", "grimblast"],
-                "input_backends": ["ydotool"]
-            },
-            "desktop_apps": {
-                "launcher_commands": ["firefox", "kitty"]
-            },
-            "projects": {
-                "known_roots": ["/home/rick/hypr-claw", "/home/rick/other"]
-            },
-            "editor": {
-                "vscode_command": "code-oss"
-            }
-        });
+        let (kind, install) = detect_capability_gap(
+            "desktop.capture_screen",
+            "Operation failed: No screenshot backend found (install 'grim' or 'hyprshot')",
+            &available,
+        )
+        .unwrap();
+        assert_eq!(kind, CapabilityGapKind::MissingBackend);
+        assert_eq!(install, vec!["grim", "hyprshot"]);
 
-        let lines = capability_registry_diff_lines(&old_registry, &new_registry);
-        assert!(lines.iter().any(|line| line.contains("platform kernel")));
-        assert!(lines.iter().any(|line| line.contains("active workspace")));
-        assert!(lines.iter().any(|line| line.contains("wallpaper backends")));
-        assert!(lines.iter().any(|line| line.contains("vscode command")));
+        let (kind, install) = detect_capability_gap(
+            "desktop.ocr_screen",
+            "Operation failed: tesseract not found (install 'tesseract' package)",
+            &available,
+        )
+        .unwrap();
+        assert_eq!(kind, CapabilityGapKind::MissingBackend);
+        assert_eq!(install, vec!["tesseract"]);
+
+        let (kind, install) =
+            detect_capability_gap("desktop.type_text", "Execution failed: exit 1", &available)
+                .unwrap();
+        assert_eq!(kind, CapabilityGapKind::NotAllowed);
+        assert_eq!(install, vec!["wtype"]);
+
+        assert!(detect_capability_gap("desktop.open_url", "timeout", &available).is_none());
     }
 
     #[test]
-    fn preferred_launchers_pick_known_commands() {
-        let launcher_commands = vec![
-            "firefox".to_string(),
-            "telegram-desktop".to_string(),
-            "kitty".to_string(),
-        ];
-        let available_commands = vec!["code-oss".to_string()];
-        let preferred =
-            build_preferred_launchers(&launcher_commands, &available_commands, "code-oss");
-
-        assert_eq!(
-            preferred.pointer("/vscode").and_then(|v| v.as_str()),
-            Some("code-oss")
-        );
-        assert_eq!(
-            preferred.pointer("/browser").and_then(|v| v.as_str()),
-            Some("firefox")
+    fn capability_gap_log_aggregates_frequency_and_tasks() {
+        let mut log = CapabilityGapLog::default();
+        for (task, now) in [("take a screenshot", 10), ("read the screen", 20)] {
+            log.record(
+                "desktop.capture_screen",
+                CapabilityGapKind::MissingBackend,
+                vec!["grim".to_string()],
+                task,
+                "No screenshot backend found",
+                now,
+            );
+        }
+        log.record(
+            "desktop.ocr_screen",
+            CapabilityGapKind::NotAllowed,
+            vec!["grim".to_string(), "tesseract".to_string()],
+            "take a screenshot",
+            "not allowed",
+            30,
         );
+
+        let ranked = log.ranked();
+        assert_eq!(ranked[0].capability, "desktop.capture_screen");
+        assert_eq!(ranked[0].count, 2);
+        assert_eq!(ranked[0].first_seen, 10);
+        assert_eq!(ranked[0].last_seen, 20);
         assert_eq!(
-            preferred.pointer("/telegram").and_then(|v| v.as_str()),
-            Some("telegram-desktop")
+            ranked[0].tasks,
+            vec!["take a screenshot", "read the screen"]
         );
         assert_eq!(
-            preferred.pointer("/terminal").and_then(|v| v.as_str()),
-            Some("kitty")
+            log.install_suggestions(),
+            vec![("grim".to_string(), 3), ("tesseract".to_string(), 1)]
         );
-    }
 
-    #[test]
-    fn inferred_resources_include_desktop_and_network() {
-        let resources = infer_supervised_task_resources("open telegram and summarize messages");
-        assert!(resources.iter().any(|r| r == "desktop_input"));
-        assert!(resources.iter().any(|r| r == "network"));
+        let restored: CapabilityGapLog =
+            serde_json::from_str(&serde_json::to_string(&log).unwrap()).unwrap();
+        assert_eq!(restored, log);
     }
 
     #[test]
-    fn shared_resources_do_not_conflict() {
-        let a = vec!["network".to_string()];
-        let b = vec!["network".to_string(), "compute".to_string()];
-        let conflicts = resource_conflicts(&a, &b);
-        assert!(conflicts.is_empty());
+    fn capabilities_install_targets_the_most_needed_backend() {
+        let mut gaps = CapabilityGapLog::default();
+        assert!(resolve_install_target("", &gaps).is_err());
+        for now in 0..2 {
+            gaps.record(
+                "desktop.ocr_screen",
+                CapabilityGapKind::NotAllowed,
+                vec!["grim".to_string(), "tesseract".to_string()],
+                "read the screen",
+                "not allowed",
+                now,
+            );
+        }
+        gaps.record(
+            "system.speak",
+            CapabilityGapKind::MissingBackend,
+            vec!["spd-say".to_string()],
+            "say hi",
+            "No TTS backend succeeded (tried: spd-say)",
+            5,
+        );
+
+        assert_eq!(resolve_install_target("", &gaps).unwrap(), "grim");
+        assert_eq!(
+            resolve_install_target(" ydotool", &gaps).unwrap(),
+            "ydotool"
+        );
+        assert!(resolve_install_target("spd-say", &gaps).is_err());
+        assert_eq!(
+            packages_for_backend("tesseract", "apt-get"),
+            vec!["tesseract-ocr", "tesseract-ocr-eng"]
+        );
+        assert_eq!(packages_for_backend("grim", "pacman"), vec!["grim"]);
+        assert_eq!(packages_for_backend("bwrap", "apt-get"), vec!["bubblewrap"]);
     }
 
     #[test]
-    fn running_background_conflict_reason_reports_overlap() {
-        let now = chrono::Utc::now().timestamp();
-        let running = vec![hypr_claw_tasks::TaskInfo {
-            id: "bg-99".to_string(),
-            description: "open browser and click login".to_string(),
-            status: hypr_claw_tasks::TaskStatus::Running,
-            progress: 0.3,
-            created_at: now - 10,
-            updated_at: now - 2,
-            result: None,
-            error: None,
-        }];
+    fn installing_a_backend_resolves_matching_gaps() {
+        let mut gaps = CapabilityGapLog::default();
+        gaps.record(
+            "desktop.capture_screen",
+            CapabilityGapKind::MissingBackend,
+            vec!["grim".to_string(), "hyprshot".to_string()],
+            "",
+            "No screenshot backend found",
+            1,
+        );
+        gaps.record(
+            "desktop.ocr_screen",
+            CapabilityGapKind::NotAllowed,
+            vec!["grim".to_string(), "tesseract".to_string()],
+            "",
+            "not allowed",
+            1,
+        );
+        gaps.record(
+            "desktop.teleport",
+            CapabilityGapKind::UnknownTool,
+            Vec::new(),
+            "",
+            "Tool not found",
+            1,
+        );
 
-        let reason = running_background_conflict_reason("open gmail and reply", &running)
-            .unwrap_or_default();
-        assert!(reason.contains("bg-99"));
-        assert!(reason.contains("desktop_input"));
+        // grim alone does not make OCR available, so that gap stays.
+        let available = ["desktop.capture_screen".to_string()]
+            .into_iter()
+            .collect::<HashSet<String>>();
+        assert_eq!(gaps.resolve("grim", &available), 1);
+        let left = gaps
+            .gaps
+            .iter()
+            .map(|gap| gap.capability.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(left, vec!["desktop.ocr_screen", "desktop.teleport"]);
     }
 
     #[test]
-    fn queue_start_is_blocked_when_another_task_is_running() {
-        let mut state = AgentOsState::default();
-        start_supervised_task(
-            &mut state,
-            "open firefox".to_string(),
-            SupervisedTaskClass::Action,
+    fn watchdog_stretches_only_for_historically_slow_tools() {
+        let latency = |calls: u64, total_ms: f64| hypr_claw_runtime::metrics::ToolLatency {
+            calls,
+            total_ms,
+            max_ms: total_ms,
+        };
+        let latencies = BTreeMap::from([
+            ("desktop.ocr_screen".to_string(), latency(2, 6000.0)),
+            ("desktop.active_window".to_string(), latency(3, 90.0)),
+        ]);
+        let base = Duration::from_secs(60);
+        let fast_only = ["desktop.active_window".to_string()]
+            .into_iter()
+            .collect::<HashSet<String>>();
+        assert_eq!(
+            watchdog_with_tool_latency(base, 10, &fast_only, &latencies),
+            base
         );
-        enqueue_supervised_task(
-            &mut state,
-            "open telegram".to_string(),
-            SupervisedTaskClass::Action,
+
+        let with_ocr = ["desktop.active_window", "desktop.ocr_screen"]
+            .into_iter()
+            .map(str::to_string)
+            .collect::<HashSet<String>>();
+        assert_eq!(
+            watchdog_with_tool_latency(base, 10, &with_ocr, &latencies),
+            Duration::from_secs(75)
+        );
+        assert_eq!(
+            watchdog_with_tool_latency(base, 100, &with_ocr, &latencies),
+            Duration::from_secs(120)
         );
-        match start_next_queued_supervised_task(&mut state) {
-            QueueStartResult::Blocked(_) => {}
-            _ => panic!("expected queue start to be blocked by running task"),
-        }
     }
 
     #[test]
-    fn queue_start_allows_non_conflicting_resources() {
-        let mut state = AgentOsState::default();
-        start_supervised_task(
-            &mut state,
-            "open firefox".to_string(),
-            SupervisedTaskClass::Action,
+    fn presets_parse_by_name_or_position() {
+        assert_eq!(
+            OnboardingPreset::parse("Power User"),
+            Some(OnboardingPreset::PowerUser)
         );
-        enqueue_supervised_task(
-            &mut state,
-            "search web api docs".to_string(),
-            SupervisedTaskClass::Action,
+        assert_eq!(
+            OnboardingPreset::parse("power-user"),
+            Some(OnboardingPreset::PowerUser)
         );
-        match start_next_queued_supervised_task(&mut state) {
-            QueueStartResult::Started(task) => {
-                assert_eq!(task.status, SupervisedTaskStatus::Running);
-                assert!(task.resources.iter().any(|r| r == "network"));
-            }
-            QueueStartResult::Blocked(reason) => {
-                panic!(
-                    "expected non-conflicting task to start, got blocked: {}",
-                    reason
-                )
-            }
-            QueueStartResult::Empty => panic!("expected queued task to start"),
+        assert_eq!(
+            OnboardingPreset::parse(" 1 "),
+            Some(OnboardingPreset::Cautious)
+        );
+        assert_eq!(OnboardingPreset::parse("reckless"), None);
+        for preset in OnboardingPreset::ALL {
+            assert_eq!(OnboardingPreset::parse(preset.as_str()), Some(preset));
         }
     }
 
     #[test]
-    fn mark_cancelled_clears_background_link() {
+    fn presets_shape_the_safety_posture() {
         let mut state = AgentOsState::default();
-        let task_id = start_supervised_task(
-            &mut state,
-            "search docs".to_string(),
-            SupervisedTaskClass::Action,
+        assert_eq!(state.preset, None);
+        assert_eq!(state.notifications, NotifyLevel::Off);
+
+        apply_preset(&mut state, OnboardingPreset::Cautious);
+        assert_eq!(
+            OnboardingPreset::Cautious.approval_tier(),
+            hypr_claw_tools::PermissionTier::Write
         );
-        set_supervised_task_background_id(&mut state, &task_id, Some("supbg-1".to_string()));
-        mark_supervised_task_cancelled(&mut state, &task_id, Some("Cancelled by test".to_string()));
-        let task = state
-            .supervisor
-            .tasks
-            .iter()
-            .find(|task| task.id == task_id)
-            .expect("task must exist");
-        assert_eq!(task.status, SupervisedTaskStatus::Cancelled);
-        assert!(task.background_task_id.is_none());
+        assert!(!OnboardingPreset::Cautious.deep_scan());
+        assert_eq!(state.autonomy_mode, AutonomyMode::PromptFirst);
+        assert!(state.notifications.wants(true));
+
+        apply_preset(&mut state, OnboardingPreset::PowerUser);
+        assert_eq!(state.preset, Some(OnboardingPreset::PowerUser));
+        assert_eq!(state.autonomy_mode, AutonomyMode::Guarded);
+        assert!(!state.notifications.wants(false));
+
+        apply_preset(&mut state, OnboardingPreset::Balanced);
+        assert!(!state.notifications.wants(true));
+        assert!(state.notifications.wants(false));
+
+        let restored: AgentOsState =
+            serde_json::from_value(serde_json::to_value(&state).unwrap()).unwrap();
+        assert_eq!(restored.preset, Some(OnboardingPreset::Balanced));
+        assert_eq!(restored.notifications, NotifyLevel::Failures);
     }
 
     #[test]
-    fn prune_supervisor_tasks_keeps_running_and_latest_terminal() {
-        let now = chrono::Utc::now().timestamp();
+    fn supervision_rules_override_the_preset_per_run() {
+        let gate = hypr_claw_tools::approvals::PlanApprovalGate::new(
+            Arc::new(hypr_claw::infra::permission_engine::PermissionEngine::new()),
+            Arc::new(hypr_claw_tools::approvals::ApprovalBook::new()),
+        );
         let mut state = AgentOsState::default();
-        state.supervisor.tasks = vec![
-            SupervisedTask {
-                id: "sup-run".to_string(),
-                prompt: "running".to_string(),
-                class: SupervisedTaskClass::Action,
-                resources: vec!["network".to_string()],
-                background_task_id: Some("supbg-run".to_string()),
-                status: SupervisedTaskStatus::Running,
-                created_at: now - 50,
-                updated_at: now - 5,
-                error: None,
-            },
-            SupervisedTask {
-                id: "sup-old-1".to_string(),
-                prompt: "old1".to_string(),
-                class: SupervisedTaskClass::Action,
-                resources: vec!["general".to_string()],
-                background_task_id: None,
-                status: SupervisedTaskStatus::Completed,
-                created_at: now - 100,
-                updated_at: now - 100,
-                error: None,
-            },
-            SupervisedTask {
-                id: "sup-old-2".to_string(),
-                prompt: "old2".to_string(),
-                class: SupervisedTaskClass::Action,
-                resources: vec!["general".to_string()],
-                background_task_id: None,
-                status: SupervisedTaskStatus::Failed,
-                created_at: now - 90,
-                updated_at: now - 90,
-                error: Some("x".to_string()),
+        apply_preset(&mut state, OnboardingPreset::PowerUser);
+        let run = |state: &AgentOsState, class| apply_supervision(state, &gate, "s", &class);
+        assert_eq!(
+            run(&state, SupervisedTaskClass::Action),
+            (AutonomyMode::Guarded, None)
+        );
+
+        state.supervision.set(
+            supervision::Scope {
+                thread: None,
+                class: Some("action".to_string()),
             },
-            SupervisedTask {
-                id: "sup-new".to_string(),
-                prompt: "new".to_string(),
-                class: SupervisedTaskClass::Action,
-                resources: vec!["general".to_string()],
-                background_task_id: None,
-                status: SupervisedTaskStatus::Cancelled,
-                created_at: now - 10,
-                updated_at: now - 1,
-                error: Some("y".to_string()),
+            supervision::Supervision::PlanApproval,
+        );
+        assert_eq!(
+            run(&state, SupervisedTaskClass::Action),
+            (
+                AutonomyMode::PromptFirst,
+                Some(supervision::Supervision::PlanApproval)
+            )
+        );
+        assert_eq!(
+            run(&state, SupervisedTaskClass::Question),
+            (AutonomyMode::Guarded, None)
+        );
+        let prompt = with_supervision_instructions(
+            "base".to_string(),
+            Some(supervision::Supervision::PlanApproval),
+        );
+        assert!(prompt.starts_with("base\nSupervision: plan approval."));
+
+        let restored: AgentOsState =
+            serde_json::from_value(serde_json::to_value(&state).unwrap()).unwrap();
+        assert_eq!(restored.supervision, state.supervision);
+    }
+
+    #[test]
+    fn run_setup_applies_the_policy_of_the_run_thread() {
+        let gate = hypr_claw_tools::approvals::PlanApprovalGate::new(
+            Arc::new(hypr_claw::infra::permission_engine::PermissionEngine::new()),
+            Arc::new(hypr_claw_tools::approvals::ApprovalBook::new()),
+        );
+        let mut setup = RunSetup {
+            supervision: supervision::SupervisionPolicy::default(),
+            autonomy_mode: AutonomyMode::Guarded,
+            soul_prompt: "soul".to_string(),
+            runtime_context: "context".to_string(),
+            roots: None,
+        };
+        setup.supervision.set(
+            supervision::Scope {
+                thread: Some("side".to_string()),
+                class: None,
             },
-        ];
+            supervision::Supervision::PlanApproval,
+        );
+        let class = SupervisedTaskClass::Action;
 
-        let removed = prune_supervisor_tasks(&mut state, 1);
-        assert_eq!(removed, 2);
-        assert!(state.supervisor.tasks.iter().any(|t| t.id == "sup-run"));
-        assert!(state.supervisor.tasks.iter().any(|t| t.id == "sup-new"));
-        assert!(!state.supervisor.tasks.iter().any(|t| t.id == "sup-old-1"));
-        assert!(!state.supervisor.tasks.iter().any(|t| t.id == "sup-old-2"));
+        let (mode, prompt) = setup.prepare(&gate, "s::main", "main", &class);
+        assert_eq!(mode, AutonomyMode::Guarded);
+        assert!(prompt.starts_with("soul\n\nRuntime context:\ncontext"));
+        assert!(!prompt.contains("Supervision:"));
+
+        let (mode, prompt) = setup.prepare(&gate, "s::side", "side", &class);
+        assert_eq!(mode, AutonomyMode::PromptFirst);
+        assert!(prompt.contains("\nSupervision: plan approval."));
     }
 
     #[test]
-    fn reconcile_marks_stale_running_tasks_as_failed() {
+    fn thread_titles_come_from_the_first_exchange() {
+        assert_eq!(
+            fallback_thread_title("open firefox and check the  weather for tomorrow"),
+            "Open firefox and check the weather"
+        );
+        assert_eq!(
+            clean_generated_title("Title: \"Weather check in Firefox.\"\nextra"),
+            Some("Weather check in Firefox".to_string())
+        );
+        assert_eq!(clean_generated_title("  \n"), None);
+
         let mut state = AgentOsState::default();
-        start_supervised_task(
-            &mut state,
-            "open firefox".to_string(),
-            SupervisedTaskClass::Action,
+        let placeholder = title_untitled_thread(&mut state, "open firefox").unwrap();
+        assert_eq!(placeholder, "Open firefox");
+        assert_eq!(title_untitled_thread(&mut state, "second request"), None);
+
+        let generated = GeneratedTitle {
+            thread_id: "task-1".to_string(),
+            placeholder: placeholder.clone(),
+            title: "Browser launch".to_string(),
+        };
+        assert!(apply_generated_title(&mut state, generated.clone()));
+        assert_eq!(state.task_threads[0].title, "Browser launch");
+        // A thread renamed since the request keeps its new name.
+        assert!(!apply_generated_title(&mut state, generated));
+
+        assert_eq!(
+            split_thread_tag("[thread:task-2] done"),
+            (Some("task-2"), "done")
         );
-        let recovered = reconcile_supervisor_after_restart(&mut state);
-        assert_eq!(recovered, 1);
-        assert!(state
-            .supervisor
-            .tasks
-            .iter()
-            .any(|t| t.status == SupervisedTaskStatus::Failed));
+        assert_eq!(split_thread_tag("plain"), (None, "plain"));
     }
 
     #[test]
-    fn tail_window_slice_paginates_from_latest() {
-        let rows: Vec<u32> = (1..=12).collect();
-        let (latest, start, end, total) = tail_window_slice(&rows, 0, 5);
-        assert_eq!(latest, vec![8, 9, 10, 11, 12]);
-        assert_eq!((start, end, total), (8, 12, 12));
-
-        let (older, start, end, total) = tail_window_slice(&rows, 5, 5);
-        assert_eq!(older, vec![3, 4, 5, 6, 7]);
-        assert_eq!((start, end, total), (3, 7, 12));
+    fn run_report_renders_the_event_log() {
+        let report = RunReport {
+            request: "open gmail".to_string(),
+            task_class: "browser".to_string(),
+            stop_code: "STOP_MAX_ITERATIONS".to_string(),
+            error: "Max iterations (16) reached".to_string(),
+            duration_ms: 900,
+            tool_events: vec!["desktop.open_url ok".to_string()],
+            recovery_notes: vec!["retry with fallback tools".to_string()],
+            ..RunReport::default()
+        };
+        assert!(!report.succeeded());
+        let log = report.render();
+        assert!(log.contains("Outcome: failed after 900ms"));
+        assert!(log.contains("Error: Max iterations (16) reached"));
+        assert!(log.contains("  desktop.open_url ok"));
+        assert!(log.contains("Recovery attempts:\n  retry with fallback tools"));
+        assert!(!log.contains("Final attempt"));
+
+        let ok = RunReport {
+            stop_code: "STOP_NONE".to_string(),
+            ..RunReport::default()
+        };
+        assert!(ok.succeeded());
+        assert!(ok.render().contains("Tool events: none"));
     }
 
     #[test]
-    fn task_log_lines_include_background_and_supervisor_entries() {
-        let now = chrono::Utc::now().timestamp();
-        let bg_tasks = vec![hypr_claw_tasks::TaskInfo {
-            id: "bg-1".to_string(),
-            description: "compile project".to_string(),
-            status: hypr_claw_tasks::TaskStatus::Completed,
-            progress: 1.0,
-            created_at: now - 5,
-            updated_at: now - 2,
-            result: Some("done".to_string()),
-            error: None,
-        }];
+    fn audit_query_takes_tool_and_window() {
+        let query = parse_audit_query("", 10_000).unwrap();
+        assert_eq!(query.limit, Some(AUDIT_QUERY_LIMIT));
+        assert_eq!(query.tool, None);
+        assert_eq!(query.since, None);
 
-        let mut state = AgentOsState::default();
-        state.supervisor.tasks.push(SupervisedTask {
-            id: "sup-1".to_string(),
-            prompt: "open firefox".to_string(),
-            class: SupervisedTaskClass::Action,
-            resources: vec!["desktop_input".to_string()],
-            background_task_id: None,
-            status: SupervisedTaskStatus::Failed,
-            created_at: now - 4,
-            updated_at: now - 1,
-            error: Some("missing binary".to_string()),
-        });
+        let query = parse_audit_query(" fs.delete 2h", 10_000).unwrap();
+        assert_eq!(query.tool.as_deref(), Some("fs.delete"));
+        assert_eq!(query.since, Some(2_800));
 
-        let task_event_feed: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
-        let lines = build_task_log_lines(&bg_tasks, &state, &task_event_feed);
-        assert!(lines.iter().any(|line| line.contains("bg bg-1")));
-        assert!(lines.iter().any(|line| line.contains("sup sup-1")));
+        assert!(parse_audit_query("fs.delete proc.kill", 0).is_err());
     }
 
     #[test]
-    fn action_feed_task_tag_extracts_supervisor_id() {
+    fn actions_args_pick_run_and_follow() {
+        assert_eq!(parse_actions_args("").unwrap(), ActionsRequest::default());
         assert_eq!(
-            RuntimeDispatcherAdapter::task_tag("user:agent::sup::sup-1"),
-            Some("sup-1")
+            parse_actions_args(" 42 --follow").unwrap(),
+            ActionsRequest {
+                run_id: Some(42),
+                list: false,
+                follow: true,
+            }
         );
+        assert!(parse_actions_args("list").unwrap().list);
+        assert!(parse_actions_args("latest").is_err());
+    }
+
+    #[test]
+    fn annealing_lowers_temperature_per_failed_recovery() {
+        let mut config: Config = serde_yaml::from_str(
+            "provider: nvidia\nmodel: m\nannealing:\n  guarded:\n    enabled: true\n    floor: 0.1\n",
+        )
+        .unwrap();
+        let guarded = annealing_policy(&AutonomyMode::Guarded, &config);
+        assert_eq!(guarded.temperature(Some(0.7), 0), None);
+        assert_eq!(guarded.temperature(Some(0.7), 1), Some(0.35));
+        assert_eq!(guarded.temperature(Some(0.7), 3), Some(0.1));
+        assert_eq!(guarded.temperature(Some(0.05), 1), Some(0.05));
+        assert_eq!(guarded.temperature(None, 1), None);
         assert_eq!(
-            RuntimeDispatcherAdapter::task_tag("alice:hypr-claw::sup::sup-42"),
-            Some("sup-42")
+            annealing_policy(&AutonomyMode::PromptFirst, &config).temperature(Some(0.7), 1),
+            None
         );
-        assert_eq!(RuntimeDispatcherAdapter::task_tag("user:agent"), None);
-        assert_eq!(RuntimeDispatcherAdapter::task_tag(""), None);
+
+        config.annealing.guarded.decay = 0.0;
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .iter()
+            .any(|issue| issue.field == "annealing.guarded.decay"));
     }
 
     #[test]
-    fn sanitize_single_line_strips_ansi_and_controls() {
-        let raw = "hello\u{1b}[31m red\u{1b}[0m world\u{0008}";
-        let cleaned = sanitize_single_line(raw);
-        assert_eq!(cleaned, "hello red world");
-        assert!(!cleaned.contains('\u{1b}'));
+    fn knowledge_commands_need_an_argument_except_list() {
+        assert_eq!(parse_knowledge_command(""), Some(KnowledgeCommand::List));
+        assert_eq!(
+            parse_knowledge_command(" list "),
+            Some(KnowledgeCommand::List)
+        );
+        assert_eq!(
+            parse_knowledge_command("import ~/docs/hyprland wiki"),
+            Some(KnowledgeCommand::Import("~/docs/hyprland wiki".to_string()))
+        );
+        assert_eq!(
+            parse_knowledge_command("search gaps_in"),
+            Some(KnowledgeCommand::Search("gaps_in".to_string()))
+        );
+        assert_eq!(parse_knowledge_command("import"), None);
+        assert_eq!(parse_knowledge_command("list extra"), None);
+        assert_eq!(parse_knowledge_command("reindex"), None);
     }
 
     #[test]
-    fn sanitize_user_input_removes_prompt_wrappers() {
-        let raw = "hypr[task-1|glm4.7|run:0]> open mail\u{1b}[D";
-        let cleaned = sanitize_user_input_line(raw);
-        assert_eq!(cleaned, "open mail");
+    fn registry_summarises_displays_and_gpus() {
+        let registry = build_capability_registry(&json!({
+            "desktop": {"displays": [
+                {"name": "eDP-1", "width": 2880, "height": 1800, "refresh_hz": 120.0, "scale": 2.0},
+                {"name": "DP-1", "width": 2560, "height": 1440, "refresh_hz": 59.95, "scale": 1.25}
+            ]},
+            "hardware": {"gpus": [
+                {"vendor": "nvidia", "name": "NVIDIA GeForce RTX 3060", "vram_total_mb": 12288},
+                {"vendor": "intel", "name": null}
+            ]}
+        }));
+        assert_eq!(
+            registry["platform"]["displays"],
+            json!(["eDP-1 2880x1800@120Hz x2", "DP-1 2560x1440@60Hz x1.25"])
+        );
+        assert_eq!(
+            registry["platform"]["gpus"],
+            json!(["nvidia NVIDIA GeForce RTX 3060 (12288 MB)", "intel"])
+        );
     }
 
     #[test]
-    fn capability_delta_history_roundtrip() {
-        let old_r = json!({"platform": {"distro_name": "old"}, "generated_at": 100});
-        let new_r = json!({"platform": {"distro_name": "arch"}, "generated_at": 200});
-        let diff = capability_registry_diff_lines(&old_r, &new_r);
-        assert!(
-            !diff.is_empty(),
-            "diff should be non-empty when registries differ"
+    fn thread_env_commands_validate_names() {
+        assert_eq!(parse_thread_env_command(""), Ok(ThreadEnvCommand::Show));
+        assert_eq!(
+            parse_thread_env_command(" set RUST_LOG=debug,hyper=info"),
+            Ok(ThreadEnvCommand::Set(
+                "RUST_LOG".to_string(),
+                "debug,hyper=info".to_string()
+            ))
         );
-        let record = json!({
-            "at": chrono::Utc::now().timestamp(),
-            "summary": format!("{} change(s)", diff.len()),
-            "diff_lines": diff,
-        });
-        let line = serde_json::to_string(&record).unwrap();
-        let parsed: serde_json::Value = serde_json::from_str(&line).unwrap();
-        let read_back = parsed.get("diff_lines").unwrap().as_array().unwrap();
-        assert!(read_back.len() > 0);
+        assert_eq!(
+            parse_thread_env_command(" unset RUST_LOG"),
+            Ok(ThreadEnvCommand::Unset("RUST_LOG".to_string()))
+        );
+        assert_eq!(
+            parse_thread_env_command(" clear"),
+            Ok(ThreadEnvCommand::Clear)
+        );
+        assert!(parse_thread_env_command(" set 1X=y").is_err());
+        assert!(parse_thread_env_command(" set NAME").is_err());
+        assert!(parse_thread_env_command(" unset A B").is_err());
     }
 
     #[test]
-    fn start_next_returns_empty_when_no_queued_tasks() {
-        let mut state = AgentOsState::default();
-        let result = start_next_queued_supervised_task(&mut state);
-        assert!(matches!(result, QueueStartResult::Empty));
-        state.supervisor.tasks.push(SupervisedTask {
-            id: "sup-1".to_string(),
-            prompt: "done task".to_string(),
-            class: SupervisedTaskClass::Action,
-            resources: vec!["network".to_string()],
-            background_task_id: None,
-            status: SupervisedTaskStatus::Completed,
-            created_at: 0,
-            updated_at: 0,
-            error: None,
-        });
-        let result2 = start_next_queued_supervised_task(&mut state);
-        assert!(matches!(result2, QueueStartResult::Empty));
+    fn thread_switch_lines_name_one_thread() {
+        assert_eq!(thread_switch_target("thread switch task-2"), Some("task-2"));
+        assert_eq!(
+            thread_switch_target(" /thread switch  task-3 "),
+            Some("task-3")
+        );
+        assert_eq!(thread_switch_target("thread switch"), None);
+        assert_eq!(thread_switch_target("thread switch to the other one"), None);
+        assert_eq!(thread_switch_target("thread switches are neat"), None);
     }
 }
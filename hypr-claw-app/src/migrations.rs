@@ -0,0 +1,101 @@
+//! Versioned upgrades of the `./data` layout.
+//!
+//! `./data/FORMAT` records the data format the directory was last written
+//! in. At startup every migration above it runs in order and the file is
+//! bumped to [`DATA_FORMAT`]. A directory written by a newer binary is
+//! refused, so a downgrade cannot silently corrupt it.
+
+use std::io;
+use std::path::Path;
+
+/// Data format this binary reads and writes.
+pub const DATA_FORMAT: u32 = 1;
+
+const FORMAT_FILE: &str = "FORMAT";
+
+/// Upgrades data from format `n - 1` to format `n`.
+type Migration = fn(&Path) -> io::Result<()>;
+
+/// Migrations by target format, oldest first. Format 1 is the layout that
+/// existed before formats were recorded, so there is nothing to migrate yet.
+const MIGRATIONS: &[(u32, Migration)] = &[];
+
+/// Format `data_dir` was last written in. A directory without a record is
+/// format 1.
+pub fn stored_format(data_dir: &Path) -> io::Result<u32> {
+    match std::fs::read_to_string(data_dir.join(FORMAT_FILE)) {
+        Ok(content) => content
+            .trim()
+            .parse()
+            .map_err(|_| io::Error::other(format!("unreadable {FORMAT_FILE}: {}", content.trim()))),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(1),
+        Err(e) => Err(e),
+    }
+}
+
+/// Brings `data_dir` to [`DATA_FORMAT`]. Returns the formats migrated to.
+pub fn migrate(data_dir: &Path) -> io::Result<Vec<u32>> {
+    migrate_with(data_dir, MIGRATIONS, DATA_FORMAT)
+}
+
+fn migrate_with(
+    data_dir: &Path,
+    migrations: &[(u32, Migration)],
+    target: u32,
+) -> io::Result<Vec<u32>> {
+    let stored = stored_format(data_dir)?;
+    if stored > target {
+        return Err(io::Error::other(format!(
+            "data format {stored} was written by a newer hypr-claw (this one reads {target}); \
+             update hypr-claw or restore a backup"
+        )));
+    }
+    let mut applied = Vec::new();
+    for (format, migration) in migrations {
+        if *format > stored && *format <= target {
+            migration(data_dir)?;
+            write_format(data_dir, *format)?;
+            applied.push(*format);
+        }
+    }
+    write_format(data_dir, target)?;
+    Ok(applied)
+}
+
+fn write_format(data_dir: &Path, format: u32) -> io::Result<()> {
+    std::fs::create_dir_all(data_dir)?;
+    std::fs::write(data_dir.join(FORMAT_FILE), format!("{format}\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rename_notes(dir: &Path) -> io::Result<()> {
+        std::fs::rename(dir.join("notes.txt"), dir.join("notes.md"))
+    }
+
+    fn add_index(dir: &Path) -> io::Result<()> {
+        std::fs::write(dir.join("index.json"), "[]")
+    }
+
+    #[test]
+    fn migrations_run_once_in_order_and_newer_data_is_refused() {
+        let dir = std::env::temp_dir().join(format!("hypr-claw-migrations-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("notes.txt"), "hi").unwrap();
+        assert_eq!(stored_format(&dir).unwrap(), 1);
+
+        let migrations: &[(u32, Migration)] = &[(2, rename_notes), (3, add_index)];
+        assert_eq!(migrate_with(&dir, migrations, 3).unwrap(), vec![2, 3]);
+        assert!(dir.join("notes.md").exists());
+        assert!(dir.join("index.json").exists());
+        assert_eq!(stored_format(&dir).unwrap(), 3);
+        assert!(migrate_with(&dir, migrations, 3).unwrap().is_empty());
+
+        let err = migrate_with(&dir, migrations, 2).unwrap_err();
+        assert!(err.to_string().contains("newer hypr-claw"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,192 @@
+//! Cached provider model catalogs for `/models`.
+//!
+//! Each provider's list is kept in `./data/models.json` with the time it was
+//! fetched, so `/models` answers at once (and offline) while a stale list is
+//! refreshed in the background. Entries carry context window and tool
+//! support: from the provider where it reports them, otherwise from the
+//! bundled table below.
+
+use crate::config::LLMProvider;
+use hypr_claw_runtime::ModelInfo;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+pub const MODEL_CACHE_PATH: &str = "./data/models.json";
+
+/// A cached list older than this is refreshed in the background.
+pub const MAX_AGE_SECS: i64 = 24 * 3600;
+
+/// Known models: (id, context window in tokens, supports tool calls).
+const KNOWN_MODELS: &[(&str, u64, bool)] = &[
+    ("z-ai/glm4.7", 200_000, true),
+    ("moonshotai/kimi-k2.5", 262_144, true),
+    ("qwen/qwen3-coder-480b-a35b-instruct", 262_144, true),
+    ("meta/llama-4-maverick-17b-128e-instruct", 1_048_576, true),
+    ("gemini-2.5-pro", 1_048_576, true),
+    ("gemini-2.5-flash", 1_048_576, true),
+    ("gemini-2.0-flash", 1_048_576, true),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedModels {
+    /// Unix seconds.
+    pub fetched_at: i64,
+    pub models: Vec<ModelInfo>,
+}
+
+impl CachedModels {
+    pub fn is_stale(&self, now: i64) -> bool {
+        now - self.fetched_at >= MAX_AGE_SECS
+    }
+
+    pub fn ids(&self) -> Vec<String> {
+        self.models.iter().map(|model| model.id.clone()).collect()
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ModelCache {
+    #[serde(default)]
+    providers: BTreeMap<String, CachedModels>,
+}
+
+impl ModelCache {
+    /// A missing or unreadable cache is treated as empty.
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(
+            &tmp,
+            serde_json::to_vec_pretty(self).map_err(io::Error::other)?,
+        )?;
+        std::fs::rename(tmp, path)
+    }
+
+    pub fn get(&self, provider_key: &str) -> Option<&CachedModels> {
+        self.providers.get(provider_key)
+    }
+
+    pub fn insert(&mut self, provider_key: &str, models: Vec<ModelInfo>, fetched_at: i64) {
+        self.providers.insert(
+            provider_key.to_string(),
+            CachedModels { fetched_at, models },
+        );
+    }
+}
+
+/// Cache key: the provider name, plus the server for local providers.
+pub fn provider_key(provider: &LLMProvider) -> String {
+    match provider {
+        LLMProvider::Nvidia => "nvidia".to_string(),
+        LLMProvider::Google => "google".to_string(),
+        LLMProvider::Local { base_url } => format!("local:{}", base_url.trim_end_matches('/')),
+        LLMProvider::Antigravity => "antigravity".to_string(),
+        LLMProvider::GeminiCli => "gemini-cli".to_string(),
+        LLMProvider::Codex => "codex".to_string(),
+    }
+}
+
+/// Fill in metadata the provider did not report from the bundled table.
+pub fn annotate(mut models: Vec<ModelInfo>) -> Vec<ModelInfo> {
+    for model in &mut models {
+        let known = KNOWN_MODELS
+            .iter()
+            .find(|(id, _, _)| model.id == *id || model.id.strip_prefix("models/") == Some(*id));
+        if let Some((_, context_window, tools)) = known {
+            model.context_window.get_or_insert(*context_window);
+            model.supports_tools.get_or_insert(*tools);
+        }
+    }
+    models
+}
+
+/// Short metadata label, e.g. `200k ctx · tools`.
+pub fn describe(model: &ModelInfo) -> String {
+    let mut parts = Vec::new();
+    if let Some(tokens) = model.context_window {
+        parts.push(if tokens >= 1_000_000 {
+            format!("{}M ctx", tokens / 1_000_000)
+        } else {
+            format!("{}k ctx", tokens / 1000)
+        });
+    }
+    match model.supports_tools {
+        Some(true) => parts.push("tools".to_string()),
+        Some(false) => parts.push("no tools".to_string()),
+        None => {}
+    }
+    parts.join(" · ")
+}
+
+pub fn age_label(fetched_at: i64, now: i64) -> String {
+    let secs = (now - fetched_at).max(0);
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86_399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86_400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(id: &str, context_window: Option<u64>) -> ModelInfo {
+        ModelInfo {
+            id: id.to_string(),
+            context_window,
+            supports_tools: None,
+        }
+    }
+
+    #[test]
+    fn cache_round_trips_and_goes_stale() {
+        let path =
+            std::env::temp_dir().join(format!("hypr-claw-models-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        assert!(ModelCache::load(&path).get("nvidia").is_none());
+
+        let mut cache = ModelCache::default();
+        cache.insert("nvidia", vec![model("z-ai/glm4.7", None)], 1_000);
+        cache.save(&path).unwrap();
+
+        let loaded = ModelCache::load(&path);
+        let cached = loaded.get("nvidia").unwrap();
+        assert_eq!(cached.ids(), vec!["z-ai/glm4.7"]);
+        assert!(!cached.is_stale(1_000 + MAX_AGE_SECS - 1));
+        assert!(cached.is_stale(1_000 + MAX_AGE_SECS));
+        assert_eq!(age_label(1_000, 1_000 + 7_200), "2h ago");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn provider_metadata_wins_over_the_bundled_table() {
+        let models = annotate(vec![
+            model("z-ai/glm4.7", None),
+            model("models/gemini-2.5-flash", Some(32_768)),
+            model("someone/unknown", None),
+        ]);
+        assert_eq!(describe(&models[0]), "200k ctx · tools");
+        assert_eq!(describe(&models[1]), "32k ctx · tools");
+        assert_eq!(describe(&models[2]), "");
+        assert_eq!(
+            provider_key(&LLMProvider::Local {
+                base_url: "http://localhost:8080/v1/".to_string()
+            }),
+            "local:http://localhost:8080/v1"
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Guesses of read-only calls a prompt will need, for `prefetch.enabled`.
+//!
+//! A prompt naming an existing file ("summarize ~/notes/today.md") gets an
+//! `fs.read` of it, and one about windows gets the window list, both run
+//! while the model reads the prompt.
+
+use hypr_claw_runtime::{PrefetchCall, Prefetcher};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Files larger than this are left for the model to ask for.
+pub const MAX_PREFETCH_BYTES: u64 = 256 * 1024;
+const MAX_PREFETCH_FILES: usize = 3;
+
+pub fn prefetcher() -> Prefetcher {
+    Arc::new(|prompt: &str| {
+        let home = std::env::var("HOME").ok().map(PathBuf::from);
+        let cwd = std::env::current_dir().ok();
+        prefetch_calls(prompt, home.as_deref(), cwd.as_deref())
+    })
+}
+
+pub fn prefetch_calls(prompt: &str, home: Option<&Path>, cwd: Option<&Path>) -> Vec<PrefetchCall> {
+    let mut calls = Vec::new();
+    for token in prompt.split_whitespace() {
+        if calls.len() == MAX_PREFETCH_FILES {
+            break;
+        }
+        let Some(path) = mentioned_path(token, home, cwd) else {
+            continue;
+        };
+        let small_file = std::fs::metadata(&path)
+            .is_ok_and(|meta| meta.is_file() && meta.len() <= MAX_PREFETCH_BYTES);
+        let call = PrefetchCall::new("fs.read", json!({"path": path.display().to_string()}));
+        if small_file && !calls.contains(&call) {
+            calls.push(call);
+        }
+    }
+
+    let lower = prompt.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    let this_window = words.windows(2).any(|pair| {
+        matches!(pair[0], "active" | "current" | "focused" | "this") && pair[1] == "window"
+    });
+    if this_window {
+        calls.push(PrefetchCall::new("desktop.active_window", json!({})));
+    } else if words
+        .iter()
+        .any(|word| matches!(*word, "window" | "windows"))
+    {
+        calls.push(PrefetchCall::new("desktop.list_windows", json!({})));
+    }
+    calls
+}
+
+/// `token` as a path when it is written like one: absolute, `~/` or
+/// explicitly relative.
+fn mentioned_path(token: &str, home: Option<&Path>, cwd: Option<&Path>) -> Option<PathBuf> {
+    let token = token
+        .trim_start_matches(['"', '\'', '`', '(', '<'])
+        .trim_end_matches(['"', '\'', '`', ')', '>', '.', ',', ';', ':', '!', '?']);
+    if let Some(rest) = token.strip_prefix("~/") {
+        return Some(home?.join(rest));
+    }
+    if token.starts_with('/') && token.len() > 1 {
+        return Some(PathBuf::from(token));
+    }
+    if token.starts_with("./") || token.starts_with("../") {
+        return Some(cwd?.join(token));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompts_naming_files_and_windows_are_prefetched() {
+        let dir = std::env::temp_dir().join(format!("hypr-claw-prefetch-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("notes")).unwrap();
+        std::fs::write(dir.join("notes/today.md"), "buy milk").unwrap();
+        std::fs::write(
+            dir.join("big.log"),
+            vec![b'x'; MAX_PREFETCH_BYTES as usize + 1],
+        )
+        .unwrap();
+        let read =
+            |path: &Path| PrefetchCall::new("fs.read", json!({"path": path.display().to_string()}));
+
+        let calls = prefetch_calls(
+            "summarize \"~/notes/today.md\", then ./big.log and ~/missing.txt",
+            Some(&dir),
+            Some(&dir),
+        );
+        assert_eq!(calls, vec![read(&dir.join("notes/today.md"))]);
+
+        let calls = prefetch_calls("which windows are open?", None, None);
+        assert_eq!(calls[0].tool_name, "desktop.list_windows");
+        let calls = prefetch_calls("close the active window", None, None);
+        assert_eq!(calls[0].tool_name, "desktop.active_window");
+        assert!(prefetch_calls("open a new terminal at /", None, None).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
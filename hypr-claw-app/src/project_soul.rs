@@ -0,0 +1,139 @@
+//! Per-project souls: a `.hypr-claw/` directory in a project overrides the
+//! global profile while the session works inside that project.
+//!
+//! `.hypr-claw/soul.md` replaces the soul's system prompt. An optional
+//! `.hypr-claw/tools` lists the tools the project allows, one per line, with
+//! `prefix.*` for a whole family; `#` starts a comment. While a project is
+//! active, file calls are confined to its root. Leaving the directory brings
+//! back the global profile.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+pub const PROJECT_DIR: &str = ".hypr-claw";
+const SOUL_FILE: &str = "soul.md";
+const TOOLS_FILE: &str = "tools";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectSoul {
+    /// The directory holding `.hypr-claw/`.
+    pub root: PathBuf,
+    pub prompt: String,
+    /// Allowed tool names and `prefix.*` patterns; `None` keeps every tool.
+    pub tools: Option<Vec<String>>,
+}
+
+impl ProjectSoul {
+    /// The project soul of the nearest directory at or above `cwd` that has
+    /// a `.hypr-claw/soul.md`.
+    pub fn discover(cwd: &Path) -> Result<Option<Self>, String> {
+        let Some(root) = cwd
+            .ancestors()
+            .find(|dir| dir.join(PROJECT_DIR).join(SOUL_FILE).is_file())
+        else {
+            return Ok(None);
+        };
+        let dir = root.join(PROJECT_DIR);
+        let read = |name: &str| {
+            std::fs::read_to_string(dir.join(name))
+                .map_err(|e| format!("cannot read {}: {e}", dir.join(name).display()))
+        };
+        let prompt = read(SOUL_FILE)?.trim().to_string();
+        if prompt.is_empty() {
+            return Err(format!("{} is empty", dir.join(SOUL_FILE).display()));
+        }
+        let tools = if dir.join(TOOLS_FILE).exists() {
+            Some(parse_tools(&read(TOOLS_FILE)?))
+        } else {
+            None
+        };
+        Ok(Some(Self {
+            root: root.to_path_buf(),
+            prompt,
+            tools,
+        }))
+    }
+
+    pub fn allows(&self, tool: &str) -> bool {
+        let Some(patterns) = &self.tools else {
+            return true;
+        };
+        patterns
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => tool.starts_with(prefix),
+                None => pattern == tool,
+            })
+    }
+
+    /// The subset of `tools` the project allows.
+    pub fn narrow(&self, tools: &HashSet<String>) -> HashSet<String> {
+        tools
+            .iter()
+            .filter(|tool| self.allows(tool))
+            .cloned()
+            .collect()
+    }
+}
+
+fn parse_tools(raw: &str) -> Vec<String> {
+    raw.lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("hypr-claw-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join(PROJECT_DIR)).unwrap();
+        std::fs::create_dir_all(root.join("src/deep")).unwrap();
+        root
+    }
+
+    #[test]
+    fn the_nearest_project_soul_applies_below_its_root() {
+        let root = project("project-soul");
+        assert_eq!(ProjectSoul::discover(&root.join("src")).unwrap(), None);
+
+        std::fs::write(
+            root.join(PROJECT_DIR).join(SOUL_FILE),
+            "You maintain this crate.\n",
+        )
+        .unwrap();
+        let soul = ProjectSoul::discover(&root.join("src/deep"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(soul.root, root);
+        assert_eq!(soul.prompt, "You maintain this crate.");
+        assert_eq!(soul.tools, None);
+        assert!(soul.allows("desktop.capture_screen"));
+
+        std::fs::write(root.join(PROJECT_DIR).join(SOUL_FILE), "  \n").unwrap();
+        assert!(ProjectSoul::discover(&root).is_err());
+    }
+
+    #[test]
+    fn the_allowlist_narrows_the_tools() {
+        let root = project("project-tools");
+        std::fs::write(root.join(PROJECT_DIR).join(SOUL_FILE), "Docs only.").unwrap();
+        std::fs::write(
+            root.join(PROJECT_DIR).join(TOOLS_FILE),
+            "# reading and writing files\nfs.*\n\ncalc.evaluate  # sums\n",
+        )
+        .unwrap();
+        let soul = ProjectSoul::discover(&root).unwrap().unwrap();
+        let tools = ["fs.read", "fs.write", "calc.evaluate", "proc.spawn"]
+            .into_iter()
+            .map(str::to_string)
+            .collect::<HashSet<_>>();
+        let mut narrowed = soul.narrow(&tools).into_iter().collect::<Vec<_>>();
+        narrowed.sort();
+        assert_eq!(narrowed, vec!["calc.evaluate", "fs.read", "fs.write"]);
+    }
+}
@@ -0,0 +1,170 @@
+//! YAML batches of supervisor tasks for `queue export` / `queue import`.
+//!
+//! A file lists tasks under `tasks:`. Each task has an `id` local to the
+//! file that `depends_on` entries of other tasks refer to; on import the
+//! ids are replaced by fresh `sup-N` ids. `depends_on` may also name tasks
+//! already in the queue.
+//!
+//! ```yaml
+//! tasks:
+//!   - id: fetch
+//!     prompt: download the latest report
+//!     priority: 10
+//!   - id: summarize
+//!     prompt: summarize the downloaded report
+//!     class: investigation
+//!     depends_on: [fetch]
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Values accepted for `class`; the prompt is classified when it is absent.
+pub const TASK_CLASSES: &[&str] = &["question", "action", "investigation"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct QueueFile {
+    #[serde(default)]
+    pub tasks: Vec<QueueFileTask>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct QueueFileTask {
+    pub id: String,
+    pub prompt: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    /// Higher runs first among tasks that are ready.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub priority: i32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+}
+
+fn is_zero(value: &i32) -> bool {
+    *value == 0
+}
+
+impl QueueFile {
+    pub fn parse(yaml: &str) -> Result<Self, String> {
+        serde_yaml::from_str(yaml).map_err(|e| format!("invalid queue file: {e}"))
+    }
+
+    pub fn to_yaml(&self) -> Result<String, String> {
+        serde_yaml::to_string(self).map_err(|e| e.to_string())
+    }
+
+    /// Checks the batch can be queued next to `existing` task ids and
+    /// returns the indices of its tasks with every dependency before its
+    /// dependents, otherwise keeping file order.
+    pub fn import_order(&self, existing: &HashSet<String>) -> Result<Vec<usize>, String> {
+        let mut index = HashMap::new();
+        for (i, task) in self.tasks.iter().enumerate() {
+            if task.id.trim().is_empty() {
+                return Err(format!("task {} has an empty id", i + 1));
+            }
+            if task.prompt.trim().is_empty() {
+                return Err(format!("task '{}' has an empty prompt", task.id));
+            }
+            if let Some(class) = &task.class {
+                if !TASK_CLASSES.contains(&class.as_str()) {
+                    return Err(format!(
+                        "task '{}' has unknown class '{}' (expected {})",
+                        task.id,
+                        class,
+                        TASK_CLASSES.join(", ")
+                    ));
+                }
+            }
+            if index.insert(task.id.as_str(), i).is_some() {
+                return Err(format!("duplicate task id '{}'", task.id));
+            }
+        }
+        for task in &self.tasks {
+            for dep in &task.depends_on {
+                if dep == &task.id {
+                    return Err(format!("task '{}' depends on itself", task.id));
+                }
+                if !index.contains_key(dep.as_str()) && !existing.contains(dep) {
+                    return Err(format!(
+                        "task '{}' depends on unknown task '{}'",
+                        task.id, dep
+                    ));
+                }
+            }
+        }
+
+        let mut order = Vec::with_capacity(self.tasks.len());
+        let mut placed = vec![false; self.tasks.len()];
+        while order.len() < self.tasks.len() {
+            let ready = (0..self.tasks.len()).find(|&i| {
+                !placed[i]
+                    && self.tasks[i]
+                        .depends_on
+                        .iter()
+                        .all(|dep| index.get(dep.as_str()).is_none_or(|&d| placed[d]))
+            });
+            let Some(i) = ready else {
+                let cycle = (0..self.tasks.len())
+                    .filter(|&i| !placed[i])
+                    .map(|i| self.tasks[i].id.as_str())
+                    .collect::<Vec<_>>();
+                return Err(format!("dependency cycle among: {}", cycle.join(", ")));
+            };
+            placed[i] = true;
+            order.push(i);
+        }
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn existing(ids: &[&str]) -> HashSet<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_and_orders_dependencies_first() {
+        let file = QueueFile::parse(
+            "tasks:\n\
+             - id: summarize\n  prompt: summarize the report\n  class: investigation\n  \
+               depends_on: [fetch, sup-3]\n\
+             - id: fetch\n  prompt: download the report\n  priority: 10\n",
+        )
+        .unwrap();
+        assert_eq!(file.tasks[1].priority, 10);
+        assert_eq!(file.tasks[1].class, None);
+        assert_eq!(
+            file.import_order(&existing(&["sup-3"])).unwrap(),
+            vec![1, 0]
+        );
+
+        let round_trip = QueueFile::parse(&file.to_yaml().unwrap()).unwrap();
+        assert_eq!(round_trip, file);
+    }
+
+    #[test]
+    fn rejects_invalid_batches() {
+        let check = |yaml: &str| {
+            QueueFile::parse(yaml)
+                .and_then(|file| file.import_order(&existing(&[])))
+                .unwrap_err()
+        };
+        assert!(check("tasks:\n- id: a\n  prompt: x\n  when: now\n").contains("invalid"));
+        assert!(
+            check("tasks:\n- id: a\n  prompt: x\n- id: a\n  prompt: y\n").contains("duplicate")
+        );
+        assert!(check("tasks:\n- id: a\n  prompt: x\n  class: chore\n").contains("unknown class"));
+        assert!(check("tasks:\n- id: a\n  prompt: x\n  depends_on: [b]\n").contains("unknown task"));
+        assert!(check(
+            "tasks:\n- id: a\n  prompt: x\n  depends_on: [b]\n\
+             - id: b\n  prompt: y\n  depends_on: [a]\n"
+        )
+        .contains("cycle among: a, b"));
+    }
+}
@@ -0,0 +1,375 @@
+//! Size and age limits for screenshots and generated artifacts.
+//!
+//! Screenshots and OCR captures land in the temp directory and generated
+//! documents or query spills in `./data/artifacts`; both grow without bound.
+//! Each kind has a [`RetentionPolicy`] enforced by a periodic sweep and by
+//! `artifacts clean`. Pinned files are never removed: those pinned with
+//! `artifacts pin <path>` and those named in a kept run's action log, so
+//! what a saved run points at stays there. Captures marked sensitive expire
+//! sooner and, like every file of a kind with `secure: true`, are wiped
+//! rather than just unlinked.
+
+use crate::config::{RetentionConfig, RetentionPolicy};
+use hypr_claw_tools::os_capabilities::wipe;
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+pub const PINS_PATH: &str = "./data/pinned_artifacts.json";
+
+/// A kind of artifact: where its files live and how they are named.
+#[derive(Debug, Clone)]
+pub struct ArtifactKind {
+    pub name: &'static str,
+    pub dir: PathBuf,
+    /// File name prefixes owned by this kind; empty means every file.
+    pub prefixes: &'static [&'static str],
+}
+
+impl ArtifactKind {
+    pub fn screenshots() -> Self {
+        Self {
+            name: "screenshots",
+            dir: std::env::temp_dir(),
+            prefixes: &["hypr-claw-shot-", "hypr-claw-readback-"],
+        }
+    }
+
+    pub fn artifacts(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            name: "artifacts",
+            dir: dir.into(),
+            prefixes: &[],
+        }
+    }
+
+    /// Files of this kind, oldest first. Only the top level of a shared
+    /// directory is listed; an owned one is walked.
+    pub fn files(&self) -> io::Result<Vec<ArtifactFile>> {
+        let mut files = Vec::new();
+        let mut pending = vec![self.dir.clone()];
+        while let Some(dir) = pending.pop() {
+            let entries = match std::fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            for entry in entries.filter_map(|e| e.ok()) {
+                let path = entry.path();
+                let Ok(meta) = path.symlink_metadata() else {
+                    continue;
+                };
+                if meta.is_dir() {
+                    if self.prefixes.is_empty() {
+                        pending.push(path);
+                    }
+                    continue;
+                }
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let owned = self.prefixes.is_empty()
+                    || self.prefixes.iter().any(|prefix| name.starts_with(prefix));
+                if meta.is_file() && owned && !name.ends_with(wipe::SENSITIVE_SUFFIX) {
+                    files.push(ArtifactFile {
+                        sensitive: wipe::is_sensitive(&path),
+                        path,
+                        bytes: meta.len(),
+                        modified: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                    });
+                }
+            }
+        }
+        files.sort_by(|a, b| a.modified.cmp(&b.modified).then(a.path.cmp(&b.path)));
+        Ok(files)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactFile {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub modified: SystemTime,
+    pub sensitive: bool,
+}
+
+/// Files that must not be removed.
+#[derive(Debug, Clone, Default)]
+pub struct Pins {
+    paths: HashSet<PathBuf>,
+    /// Text of the kept run logs; a file whose name appears in it is pinned.
+    referenced: String,
+}
+
+impl Pins {
+    pub fn load(pins_path: &Path, actions_dir: &Path) -> Self {
+        let paths = load_pins(pins_path).into_iter().collect();
+        let mut referenced = String::new();
+        if let Ok(entries) = std::fs::read_dir(actions_dir) {
+            for entry in entries.filter_map(|e| e.ok()) {
+                if let Ok(text) = std::fs::read_to_string(entry.path()) {
+                    referenced.push_str(&text);
+                }
+            }
+        }
+        Self { paths, referenced }
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        if self.paths.contains(&absolute(path)) {
+            return true;
+        }
+        path.file_name()
+            .map(|name| name.to_string_lossy())
+            .is_some_and(|name| self.referenced.contains(name.as_ref()))
+    }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn load_pins(pins_path: &Path) -> Vec<PathBuf> {
+    std::fs::read_to_string(pins_path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// Pin or unpin `path`. Returns false when nothing changed.
+pub fn set_pinned(pins_path: &Path, path: &Path, pinned: bool) -> io::Result<bool> {
+    let path = absolute(path);
+    let mut pins = load_pins(pins_path);
+    let present = pins.contains(&path);
+    if present == pinned {
+        return Ok(false);
+    }
+    if pinned {
+        pins.push(path);
+    } else {
+        pins.retain(|pin| *pin != path);
+    }
+    if let Some(parent) = pins_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string_pretty(&pins).map_err(io::Error::other)?;
+    std::fs::write(pins_path, json)?;
+    Ok(true)
+}
+
+/// Files to remove from `files` (oldest first) under `policy`: everything
+/// unpinned past the age limit (the sensitive one for marked files), then
+/// the oldest unpinned ones until the total, pinned files included, fits
+/// the size limit.
+pub fn select_expired<'a>(
+    files: &'a [ArtifactFile],
+    policy: &RetentionPolicy,
+    is_pinned: impl Fn(&Path) -> bool,
+    now: SystemTime,
+) -> Vec<&'a ArtifactFile> {
+    let max_age = Duration::from_secs(policy.max_age_days * 24 * 60 * 60);
+    let sensitive_max_age = Duration::from_secs(policy.sensitive_max_age_minutes * 60);
+    let max_bytes = policy.max_total_mb.saturating_mul(1024 * 1024);
+    let mut total: u64 = files.iter().map(|file| file.bytes).sum();
+    let mut expired = Vec::new();
+    for file in files {
+        if is_pinned(&file.path) {
+            continue;
+        }
+        let age = now.duration_since(file.modified).unwrap_or_default();
+        let too_old = (policy.max_age_days > 0 && age > max_age)
+            || (file.sensitive && policy.sensitive_max_age_minutes > 0 && age > sensitive_max_age);
+        let over_quota = policy.max_total_mb > 0 && total > max_bytes;
+        if too_old || over_quota {
+            total -= file.bytes;
+            expired.push(file);
+        }
+    }
+    expired
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SweepReport {
+    pub removed: usize,
+    /// Removed files whose contents were overwritten first.
+    pub wiped: usize,
+    pub freed_bytes: u64,
+    pub kept: usize,
+    pub kept_bytes: u64,
+}
+
+/// Apply `policy` to the files of `kind`.
+pub fn sweep(
+    kind: &ArtifactKind,
+    policy: &RetentionPolicy,
+    pins: &Pins,
+) -> io::Result<SweepReport> {
+    let files = kind.files()?;
+    let expired = select_expired(
+        &files,
+        policy,
+        |path| pins.contains(path),
+        SystemTime::now(),
+    );
+    let mut report = SweepReport::default();
+    let mut removed = HashSet::new();
+    for file in expired {
+        let deleted = if policy.secure || file.sensitive {
+            wipe::secure_delete(&file.path).map(|overwritten| {
+                report.wiped += usize::from(overwritten);
+            })
+        } else {
+            std::fs::remove_file(&file.path)
+        };
+        if deleted.is_ok() {
+            let _ = std::fs::remove_file(wipe::sensitive_marker(&file.path));
+            report.removed += 1;
+            report.freed_bytes += file.bytes;
+            removed.insert(&file.path);
+        }
+    }
+    for file in files.iter().filter(|file| !removed.contains(&file.path)) {
+        report.kept += 1;
+        report.kept_bytes += file.bytes;
+    }
+    Ok(report)
+}
+
+/// Each kind with its policy from `config`.
+pub fn kinds(
+    config: &RetentionConfig,
+    artifacts_dir: &Path,
+) -> Vec<(ArtifactKind, RetentionPolicy)> {
+    vec![
+        (ArtifactKind::screenshots(), config.screenshots.clone()),
+        (
+            ArtifactKind::artifacts(artifacts_dir),
+            config.artifacts.clone(),
+        ),
+    ]
+}
+
+pub fn describe_policy(policy: &RetentionPolicy) -> String {
+    let size = match policy.max_total_mb {
+        0 => "no size limit".to_string(),
+        mb => format!("max {mb} MB"),
+    };
+    let age = match policy.max_age_days {
+        0 => "no age limit".to_string(),
+        days => format!("max {days} day(s)"),
+    };
+    let mut description = format!("{size}, {age}");
+    if policy.sensitive_max_age_minutes > 0 {
+        description.push_str(&format!(
+            ", sensitive after {} min",
+            policy.sensitive_max_age_minutes
+        ));
+    }
+    if policy.secure {
+        description.push_str(", secure wipe");
+    }
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, mb: u64, age_days: u64, now: SystemTime) -> ArtifactFile {
+        ArtifactFile {
+            path: PathBuf::from(name),
+            bytes: mb * 1024 * 1024,
+            modified: now - Duration::from_secs(age_days * 24 * 60 * 60),
+            sensitive: false,
+        }
+    }
+
+    #[test]
+    fn old_and_over_quota_files_expire_unless_pinned() {
+        let now = SystemTime::now();
+        let files = vec![
+            file("a.png", 40, 9, now),
+            file("b.png", 40, 5, now),
+            file("c.png", 40, 4, now),
+            file("d.png", 40, 1, now),
+        ];
+        let policy = RetentionPolicy {
+            max_total_mb: 100,
+            max_age_days: 7,
+            ..RetentionPolicy::default()
+        };
+        let names = |expired: Vec<&ArtifactFile>| {
+            expired
+                .iter()
+                .map(|file| file.path.display().to_string())
+                .collect::<Vec<_>>()
+        };
+        // a is too old; removing b brings 160 MB down to 80 MB.
+        let expired = select_expired(&files, &policy, |_| false, now);
+        assert_eq!(names(expired), vec!["a.png", "b.png"]);
+
+        // Pinned files stay even when old, and still count toward the quota.
+        let expired = select_expired(&files, &policy, |p| p == Path::new("a.png"), now);
+        assert_eq!(names(expired), vec!["b.png", "c.png"]);
+
+        let unlimited = RetentionPolicy::default();
+        assert!(select_expired(&files, &unlimited, |_| false, now).is_empty());
+
+        // A capture with a secret in it goes after an hour.
+        let secret = ArtifactFile {
+            path: PathBuf::from("e.png"),
+            bytes: 1,
+            modified: now - Duration::from_secs(2 * 60 * 60),
+            sensitive: true,
+        };
+        let policy = RetentionPolicy {
+            sensitive_max_age_minutes: 60,
+            ..RetentionPolicy::default()
+        };
+        let files = [files[3].clone(), secret];
+        let expired = select_expired(&files, &policy, |_| false, now);
+        assert_eq!(names(expired), vec!["e.png"]);
+    }
+
+    #[test]
+    fn files_named_in_run_logs_or_pinned_are_kept() {
+        let dir = std::env::temp_dir().join(format!("hypr-claw-retention-{}", std::process::id()));
+        let actions = dir.join("actions");
+        let artifacts = dir.join("artifacts");
+        std::fs::create_dir_all(&actions).unwrap();
+        std::fs::create_dir_all(artifacts.join("reports")).unwrap();
+        std::fs::write(
+            actions.join("run-1.jsonl"),
+            r#"{"kind":"action","detail":"saved reports/q3.pdf"}"#,
+        )
+        .unwrap();
+        for name in ["reports/q3.pdf", "query-1.csv", "query-2.csv"] {
+            std::fs::write(artifacts.join(name), "x").unwrap();
+        }
+        let pins_path = dir.join("pins.json");
+        assert!(set_pinned(&pins_path, &artifacts.join("query-2.csv"), true).unwrap());
+        assert!(!set_pinned(&pins_path, &artifacts.join("query-2.csv"), true).unwrap());
+
+        let month_ago = SystemTime::now() - Duration::from_secs(30 * 24 * 60 * 60);
+        for name in ["reports/q3.pdf", "query-1.csv", "query-2.csv"] {
+            std::fs::File::options()
+                .write(true)
+                .open(artifacts.join(name))
+                .unwrap()
+                .set_modified(month_ago)
+                .unwrap();
+        }
+
+        let pins = Pins::load(&pins_path, &actions);
+        let kind = ArtifactKind::artifacts(&artifacts);
+        assert_eq!(kind.files().unwrap().len(), 3);
+        let policy = RetentionPolicy {
+            max_age_days: 7,
+            ..RetentionPolicy::default()
+        };
+        let report = sweep(&kind, &policy, &pins).unwrap();
+        assert_eq!((report.removed, report.kept), (1, 2));
+        assert!(!artifacts.join("query-1.csv").exists());
+        assert!(artifacts.join("reports/q3.pdf").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
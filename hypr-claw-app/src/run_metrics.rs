@@ -0,0 +1,223 @@
+//! Per-run metrics behind `hypr-claw metrics export`.
+//!
+//! Every foreground run appends one row to `./data/metrics/runs.jsonl` when
+//! it finishes. Rows are never pruned, unlike the action logs they are
+//! partly derived from, so long-term performance can be analyzed offline.
+
+use crate::action_log::ActionEvent;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+pub const METRICS_PATH: &str = "./data/metrics/runs.jsonl";
+
+/// Columns of the CSV export, in [`RunMetrics`] field order.
+const CSV_HEADER: &str = "run_id,started_at,session,mode,task_class,stop_code,duration_ms,fallback_attempts,tool_calls,tool_failures,prompt_tokens,completion_tokens";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RunMetrics {
+    pub run_id: u64,
+    pub started_at: i64,
+    pub session: String,
+    pub mode: String,
+    pub task_class: String,
+    pub stop_code: String,
+    pub duration_ms: u64,
+    pub fallback_attempts: u32,
+    pub tool_calls: u64,
+    pub tool_failures: u64,
+    /// Zero for providers that do not report usage.
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+/// Tool calls and failed calls in one run's action log.
+pub fn tool_counts(events: &[ActionEvent]) -> (u64, u64) {
+    let mut calls = 0;
+    let mut failures = 0;
+    for event in events {
+        if let ActionEvent::Action { status, .. } = event {
+            match status.as_str() {
+                "tool" => calls += 1,
+                "fail" | "error" => failures += 1,
+                _ => {}
+            }
+        }
+    }
+    (calls, failures)
+}
+
+pub struct RunMetricsStore {
+    path: PathBuf,
+}
+
+impl RunMetricsStore {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn append(&self, row: &RunMetrics) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let line = serde_json::to_string(row).map_err(io::Error::other)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{line}")
+    }
+
+    /// Rows of runs started at or after `since`, oldest first. Unreadable
+    /// lines (e.g. one cut short by a crash) are skipped.
+    pub fn load(&self, since: Option<i64>) -> io::Result<Vec<RunMetrics>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str::<RunMetrics>(line).ok())
+            .filter(|row| since.is_none_or(|since| row.started_at >= since))
+            .collect())
+    }
+}
+
+/// Unix time for `YYYY-MM-DD` (midnight UTC) or an RFC 3339 timestamp.
+pub fn parse_since(value: &str) -> Option<i64> {
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp());
+    }
+    chrono::DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|t| t.timestamp())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn to_csv(rows: &[RunMetrics]) -> String {
+    let mut out = String::from(CSV_HEADER);
+    out.push('\n');
+    for row in rows {
+        let fields = [
+            row.run_id.to_string(),
+            row.started_at.to_string(),
+            csv_field(&row.session),
+            csv_field(&row.mode),
+            csv_field(&row.task_class),
+            csv_field(&row.stop_code),
+            row.duration_ms.to_string(),
+            row.fallback_attempts.to_string(),
+            row.tool_calls.to_string(),
+            row.tool_failures.to_string(),
+            row.prompt_tokens.to_string(),
+            row.completion_tokens.to_string(),
+        ];
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(run_id: u64, started_at: i64) -> RunMetrics {
+        RunMetrics {
+            run_id,
+            started_at,
+            session: "agent:user".to_string(),
+            mode: "guarded".to_string(),
+            task_class: "desktop".to_string(),
+            stop_code: "STOP_NONE".to_string(),
+            duration_ms: 1200,
+            fallback_attempts: 1,
+            tool_calls: 3,
+            tool_failures: 1,
+            prompt_tokens: 900,
+            completion_tokens: 80,
+        }
+    }
+
+    #[test]
+    fn rows_round_trip_and_filter_by_start() {
+        let path =
+            std::env::temp_dir().join(format!("hypr-claw-metrics-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let store = RunMetricsStore::new(&path);
+        assert!(store.load(None).unwrap().is_empty());
+
+        store.append(&row(1, 1_700_000_000)).unwrap();
+        // A row cut short by a crash.
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"run_id\":\n")
+            .unwrap();
+        store.append(&row(2, 1_700_100_000)).unwrap();
+
+        assert_eq!(store.load(None).unwrap().len(), 2);
+        assert_eq!(
+            store.load(Some(1_700_050_000)).unwrap(),
+            vec![row(2, 1_700_100_000)]
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn csv_has_header_and_quotes_fields() {
+        let mut quoted = row(7, 0);
+        quoted.task_class = "say \"hi\", then leave".to_string();
+        let csv = to_csv(&[quoted]);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some(CSV_HEADER));
+        assert_eq!(
+            lines.next(),
+            Some(
+                "7,0,agent:user,guarded,\"say \"\"hi\"\", then leave\",STOP_NONE,1200,1,3,1,900,80"
+            )
+        );
+    }
+
+    #[test]
+    fn since_accepts_dates_and_timestamps() {
+        assert_eq!(parse_since("2024-01-01"), Some(1_704_067_200));
+        assert_eq!(
+            parse_since("2024-01-01T01:00:00+01:00"),
+            Some(1_704_067_200)
+        );
+        assert_eq!(parse_since("yesterday"), None);
+    }
+
+    #[test]
+    fn tool_counts_come_from_the_action_log() {
+        let action = |status: &str| ActionEvent::Action {
+            index: 1,
+            status: status.to_string(),
+            tool: "fs.read".to_string(),
+            detail: String::new(),
+            elapsed_ms: None,
+            at: 0,
+        };
+        let events = vec![
+            action("tool"),
+            action("ok"),
+            action("tool"),
+            action("alias"),
+            action("fail"),
+        ];
+        assert_eq!(tool_counts(&events), (2, 1));
+    }
+}
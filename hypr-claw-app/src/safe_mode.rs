@@ -0,0 +1,177 @@
+//! Degraded startup used when a core component fails to come up.
+//!
+//! Instead of exiting before the user can do anything, the app drops into a
+//! chat-only console: no tools, an in-memory conversation, and a panel that
+//! says what failed and how to repair it.
+
+use hypr_claw_runtime::{LLMClientType, LLMResponse, Message, Role};
+use serde_json::json;
+use std::fmt;
+
+/// Messages kept in the in-memory conversation before the oldest are dropped.
+const MAX_HISTORY: usize = 24;
+
+pub const SAFE_MODE_SYSTEM_PROMPT: &str = "You are running in safe mode: startup failed, so no tools are available and nothing you say can change the system. Answer questions and help the user repair the failures listed below. Do not claim to have performed any action.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartupComponent {
+    SessionStore,
+    ToolRegistry,
+    Provider,
+}
+
+impl StartupComponent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::SessionStore => "session store",
+            Self::ToolRegistry => "tool registry",
+            Self::Provider => "provider",
+        }
+    }
+
+    pub fn repair_suggestions(self) -> &'static [&'static str] {
+        match self {
+            Self::SessionStore => &[
+                "Check that ./data is writable: ls -ld ./data ./data/context ./data/sessions",
+                "Move a corrupt file out of ./data/context or ./data/sessions and restart",
+                "Restore a known-good copy with 'hypr-claw backup restore <file>'",
+            ],
+            Self::ToolRegistry => &[
+                "Check ./data/audit.log, ./data/reminders.json and ./data/contacts.json are valid",
+                "Move a corrupt reminders or contacts file aside; it is recreated empty",
+                "Review installed plugins with 'hypr-claw plugins list'",
+            ],
+            Self::Provider => &[
+                "Run 'hypr-claw config reset' to pick a provider or re-enter the API key",
+                "For a local provider, make sure its server is running and reachable",
+                "Codex, Antigravity and Gemini CLI cannot run tools; choose another provider",
+            ],
+        }
+    }
+}
+
+impl fmt::Display for StartupComponent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StartupFailure {
+    pub component: StartupComponent,
+    pub error: String,
+}
+
+impl StartupFailure {
+    pub fn new(component: StartupComponent, error: impl fmt::Display) -> Self {
+        Self {
+            component,
+            error: error.to_string(),
+        }
+    }
+}
+
+/// Diagnostic panel: each failure with its repair suggestions.
+pub fn diagnostics(failures: &[StartupFailure]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for failure in failures {
+        lines.push(format!("✗ {}: {}", failure.component, failure.error));
+        for suggestion in failure.component.repair_suggestions() {
+            lines.push(format!("    - {suggestion}"));
+        }
+    }
+    lines
+}
+
+/// System prompt for safe-mode chat, naming what failed.
+pub fn system_prompt(failures: &[StartupFailure]) -> String {
+    let failed: Vec<String> = failures
+        .iter()
+        .map(|f| format!("- {}: {}", f.component, f.error))
+        .collect();
+    format!(
+        "{SAFE_MODE_SYSTEM_PROMPT}\n\nStartup failures:\n{}",
+        failed.join("\n")
+    )
+}
+
+/// Chat without tools over an in-memory conversation.
+pub struct SafeChat {
+    client: LLMClientType,
+    system_prompt: String,
+    history: Vec<Message>,
+}
+
+impl SafeChat {
+    pub fn new(client: LLMClientType, failures: &[StartupFailure]) -> Self {
+        Self {
+            client,
+            system_prompt: system_prompt(failures),
+            history: Vec::new(),
+        }
+    }
+
+    pub async fn ask(&mut self, text: &str) -> Result<String, String> {
+        self.history
+            .push(Message::new(Role::User, json!(text.to_string())));
+        // Providers reject calls without tools, so offer a single reply function.
+        let schemas = [json!({
+            "type": "function",
+            "function": {
+                "name": "answer",
+                "description": "Reply to the user",
+                "parameters": {
+                    "type": "object",
+                    "properties": {"text": {"type": "string"}},
+                    "required": ["text"]
+                }
+            }
+        })];
+        let reply = match self
+            .client
+            .call(&self.system_prompt, &self.history, &schemas)
+            .await
+        {
+            Ok(LLMResponse::Final { content, .. }) => content,
+            Ok(LLMResponse::ToolCall { input, .. }) => input
+                .get("text")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            Err(e) => {
+                self.history.pop();
+                return Err(e.to_string());
+            }
+        };
+        self.history
+            .push(Message::new(Role::Assistant, json!(reply.clone())));
+        if self.history.len() > MAX_HISTORY {
+            self.history.drain(..self.history.len() - MAX_HISTORY);
+        }
+        Ok(reply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagnostics_list_failures_with_repairs() {
+        let failures = vec![
+            StartupFailure::new(StartupComponent::SessionStore, "permission denied"),
+            StartupFailure::new(StartupComponent::Provider, "missing API key"),
+        ];
+        let lines = diagnostics(&failures);
+        assert_eq!(lines[0], "✗ session store: permission denied");
+        assert!(lines[1].contains("./data is writable"));
+        assert!(lines
+            .iter()
+            .any(|line| line == "✗ provider: missing API key"));
+        assert!(lines.iter().any(|line| line.contains("config reset")));
+
+        let prompt = system_prompt(&failures);
+        assert!(prompt.starts_with(SAFE_MODE_SYSTEM_PROMPT));
+        assert!(prompt.ends_with("- provider: missing API key"));
+    }
+}
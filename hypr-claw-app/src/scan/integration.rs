@@ -105,6 +105,33 @@ async fn collect_basic_system_profile(user_id: &str) -> Value {
 
     let home = std::env::var("HOME").unwrap_or_default();
 
+    let wallpaper_backends = installed_commands(&["swww", "hyprpaper", "caelestia"]).await;
+    let screenshot_backends = installed_commands(&["grim", "hyprshot"]).await;
+    let input_backends = installed_commands(&["wtype", "ydotool", "wlrctl"]).await;
+    let ocr_available = command_exists("tesseract").await;
+    let ocr_languages = if ocr_available {
+        hypr_claw_tools::os_capabilities::ocr::installed_languages()
+            .await
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let ui_language = hypr_claw_tools::os_capabilities::ocr::ui_locale().unwrap_or_default();
+    let keyboard_layout = hypr_claw_tools::os_capabilities::keyboard::active_layout()
+        .await
+        .map(|layout| layout.describe())
+        .unwrap_or_default();
+    let displays = if hyprland_available {
+        hypr_claw_tools::os_capabilities::hardware::displays()
+            .await
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let gpus = hypr_claw_tools::os_capabilities::hardware::gpus()
+        .await
+        .unwrap_or_default();
+
     json!({
         "scanned_at": chrono::Utc::now().timestamp(),
         "platform": {
@@ -124,15 +151,39 @@ async fn collect_basic_system_profile(user_id: &str) -> Value {
         "desktop": {
             "session": std::env::var("XDG_SESSION_TYPE").unwrap_or_default(),
             "desktop_env": std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default(),
+            "ui_language": ui_language,
+            "keyboard_layout": keyboard_layout,
             "hyprland_available": hyprland_available,
             "active_workspace": active_workspace,
+            "displays": displays,
+        },
+        "hardware": {
+            "gpus": gpus,
         },
         "paths": {
             "home": home,
+        },
+        "capabilities": {
+            "wallpaper_backends": wallpaper_backends,
+            "screenshot_backends": screenshot_backends,
+            "input_backends": input_backends,
+            "ocr_available": ocr_available,
+            "ocr_languages": ocr_languages
         }
     })
 }
 
+/// The subset of `commands` found on PATH, in the given order.
+async fn installed_commands(commands: &[&str]) -> Vec<String> {
+    let mut found = Vec::new();
+    for command in commands {
+        if command_exists(command).await {
+            found.push((*command).to_string());
+        }
+    }
+    found
+}
+
 async fn build_deep_scan_data(scan_results: &[ScanResult], user_dirs: &UserDirectories) -> Value {
     let mut config_files = Vec::new();
     let mut script_files = Vec::new();
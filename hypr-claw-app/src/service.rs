@@ -0,0 +1,160 @@
+//! `hypr-claw service`: the systemd user unit that runs `hypr-claw daemon`.
+//!
+//! The unit uses `Type=notify`, so systemd considers the agent started only
+//! once startup finished, restarts it on failure and, when the watchdog is
+//! enabled, expects its `WATCHDOG=1` pings.
+
+use crate::config::WatchdogConfig;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub const UNIT_NAME: &str = "hypr-claw.service";
+
+/// Resource limits written into the unit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceLimits {
+    /// `MemoryMax=`, e.g. `2G`.
+    pub memory_max: String,
+    /// `TasksMax=`: threads and processes, including launched tools.
+    pub tasks_max: u32,
+}
+
+impl Default for ServiceLimits {
+    fn default() -> Self {
+        Self {
+            memory_max: "2G".to_string(),
+            tasks_max: 512,
+        }
+    }
+}
+
+pub fn unit_dir() -> io::Result<PathBuf> {
+    let base = std::env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|_| std::env::var("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .map_err(|_| io::Error::other("cannot resolve config directory"))?;
+    Ok(base.join("systemd").join("user"))
+}
+
+/// Unit running `exe daemon` in `workdir`, where `./data` lives. The
+/// systemd watchdog is only set when the agent's own watchdog pings it, at
+/// twice its timeout so the agent restarts itself before systemd steps in.
+pub fn render_unit(
+    exe: &Path,
+    workdir: &Path,
+    watchdog: &WatchdogConfig,
+    limits: &ServiceLimits,
+) -> String {
+    let watchdog_line = if watchdog.enabled {
+        format!("WatchdogSec={}\n", watchdog.timeout_secs * 2)
+    } else {
+        String::new()
+    };
+    format!(
+        "[Unit]\n\
+         Description=hypr-claw agent daemon\n\
+         After=graphical-session.target\n\
+         StartLimitIntervalSec=300\n\
+         StartLimitBurst=5\n\
+         \n\
+         [Service]\n\
+         Type=notify\n\
+         NotifyAccess=main\n\
+         ExecStart={} daemon\n\
+         WorkingDirectory={}\n\
+         Restart=on-failure\n\
+         RestartSec=5\n\
+         {watchdog_line}\
+         MemoryMax={}\n\
+         TasksMax={}\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        exe.display(),
+        workdir.display(),
+        limits.memory_max,
+        limits.tasks_max
+    )
+}
+
+fn systemctl(args: &[&str]) -> io::Result<()> {
+    let output = Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .output()?;
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(io::Error::other(
+        String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    ))
+}
+
+/// Writes, enables and starts the unit. Returns its path.
+pub fn install(unit: &str) -> io::Result<PathBuf> {
+    let dir = unit_dir()?;
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(UNIT_NAME);
+    std::fs::write(&path, unit)?;
+    systemctl(&["daemon-reload"])?;
+    systemctl(&["enable", "--now", UNIT_NAME])?;
+    Ok(path)
+}
+
+/// Stops, disables and removes the unit. Returns its path, or `None` if it
+/// was not installed.
+pub fn uninstall() -> io::Result<Option<PathBuf>> {
+    let path = unit_dir()?.join(UNIT_NAME);
+    if !path.exists() {
+        return Ok(None);
+    }
+    // A unit that failed to start cannot be stopped; removing it still works.
+    let _ = systemctl(&["disable", "--now", UNIT_NAME]);
+    std::fs::remove_file(&path)?;
+    systemctl(&["daemon-reload"])?;
+    Ok(Some(path))
+}
+
+/// Prints `systemctl status` for the unit. Its exit code only says whether
+/// the unit runs, so it is not treated as an error.
+pub fn status() -> io::Result<()> {
+    Command::new("systemctl")
+        .args(["--user", "status", "--no-pager", UNIT_NAME])
+        .status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_runs_the_daemon_with_limits_and_watchdog() {
+        let unit = render_unit(
+            Path::new("/usr/bin/hypr-claw"),
+            Path::new("/home/u/agent"),
+            &WatchdogConfig::default(),
+            &ServiceLimits::default(),
+        );
+        assert!(unit.contains("Type=notify\n"));
+        assert!(unit.contains("ExecStart=/usr/bin/hypr-claw daemon\n"));
+        assert!(unit.contains("WorkingDirectory=/home/u/agent\n"));
+        assert!(unit.contains("Restart=on-failure\n"));
+        assert!(unit.contains("WatchdogSec=180\n"));
+        assert!(unit.contains("MemoryMax=2G\n"));
+        assert!(unit.contains("TasksMax=512\n"));
+
+        let unwatched = render_unit(
+            Path::new("/usr/bin/hypr-claw"),
+            Path::new("/home/u/agent"),
+            &WatchdogConfig {
+                enabled: false,
+                ..WatchdogConfig::default()
+            },
+            &ServiceLimits::default(),
+        );
+        assert!(!unwatched.contains("WatchdogSec"));
+        assert!(unwatched.contains("RestartSec=5\nMemoryMax=2G\n"));
+    }
+}
@@ -0,0 +1,398 @@
+//! `setup apply <manifest.yaml>`: a desired-capabilities manifest for a
+//! machine, and the steps that bring a live install in line with it.
+//!
+//! ```yaml
+//! backends: [grim, tesseract, ydotool]
+//! tools: [desktop.click_text]
+//! packages:
+//!   - ripgrep
+//!   - { name: fd, command: fd }
+//! services:
+//!   - ydotoold
+//!   - { name: bluetooth, user: false }
+//! files:
+//!   - path: ~/.config/foot/foot.ini
+//!     content: |
+//!       font=monospace:size=11
+//! ```
+//!
+//! Planning is pure: the caller observes the machine (capability registry,
+//! commands on `PATH`, active services, file contents) and runs each step
+//! through the tool dispatcher, then observes again until nothing is left.
+
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SetupManifest {
+    /// Capability backends that must show up in the registry.
+    pub backends: Vec<String>,
+    /// Tools that must end up available; their backends are installed.
+    pub tools: Vec<String>,
+    pub packages: Vec<PackageSpec>,
+    pub services: Vec<ServiceSpec>,
+    pub files: Vec<FileSpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum PackageSpec {
+    Name(String),
+    Full {
+        name: String,
+        /// Command whose presence means the package is installed.
+        command: Option<String>,
+    },
+}
+
+impl PackageSpec {
+    pub fn name(&self) -> &str {
+        match self {
+            PackageSpec::Name(name) | PackageSpec::Full { name, .. } => name,
+        }
+    }
+
+    pub fn command(&self) -> &str {
+        match self {
+            PackageSpec::Full {
+                command: Some(command),
+                ..
+            } => command,
+            _ => self.name(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum ServiceSpec {
+    Name(String),
+    Full {
+        name: String,
+        /// `systemctl --user` (the default) or a system service.
+        #[serde(default = "default_user_service")]
+        user: bool,
+    },
+}
+
+fn default_user_service() -> bool {
+    true
+}
+
+impl ServiceSpec {
+    pub fn name(&self) -> &str {
+        match self {
+            ServiceSpec::Name(name) | ServiceSpec::Full { name, .. } => name,
+        }
+    }
+
+    pub fn user(&self) -> bool {
+        match self {
+            ServiceSpec::Name(_) => true,
+            ServiceSpec::Full { user, .. } => *user,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileSpec {
+    pub path: String,
+    pub content: String,
+}
+
+impl SetupManifest {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let body = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let manifest: Self = serde_yaml::from_str(&body).map_err(|e| e.to_string())?;
+        if let Some(bad) = manifest
+            .packages
+            .iter()
+            .map(PackageSpec::name)
+            .find(|name| !hypr_claw_tools::os_capabilities::system::valid_package_name(name))
+        {
+            return Err(format!("invalid package name '{bad}'"));
+        }
+        if let Some(bad) = manifest
+            .services
+            .iter()
+            .map(ServiceSpec::name)
+            .find(|name| {
+                name.is_empty()
+                    || name.starts_with('-')
+                    || name.contains(|c: char| c.is_whitespace() || c == '/')
+            })
+        {
+            return Err(format!("invalid service name '{bad}'"));
+        }
+        Ok(manifest)
+    }
+}
+
+/// `~/` in manifest paths means `home`.
+pub fn expand_home(path: &str, home: Option<&Path>) -> PathBuf {
+    match (path.strip_prefix("~/"), home) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Whether `command` is an executable file on `PATH`.
+pub fn on_path(command: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| {
+            dir.join(command)
+                .metadata()
+                .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        })
+    })
+}
+
+/// What the machine looks like right now, as far as the manifest cares.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Observed {
+    pub backends: BTreeSet<String>,
+    pub tools: BTreeSet<String>,
+    /// Manifest package commands found on `PATH`.
+    pub commands: BTreeSet<String>,
+    pub active_services: BTreeSet<String>,
+    /// Manifest file paths whose content already matches.
+    pub matching_files: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetupStep {
+    InstallPackages {
+        packages: Vec<String>,
+        /// The backends, tools and packages that asked for them.
+        reasons: Vec<String>,
+    },
+    WriteFile {
+        path: String,
+        content: String,
+    },
+    EnableService {
+        name: String,
+        user: bool,
+    },
+}
+
+impl SetupStep {
+    pub fn describe(&self) -> String {
+        match self {
+            SetupStep::InstallPackages { packages, reasons } => format!(
+                "install {} (for {})",
+                packages.join(" "),
+                reasons.join(", ")
+            ),
+            SetupStep::WriteFile { path, content } => {
+                format!("write {} ({} bytes)", path, content.len())
+            }
+            SetupStep::EnableService { name, user: true } => {
+                format!("enable and start user service {name}")
+            }
+            SetupStep::EnableService { name, user: false } => {
+                format!("enable and start system service {name}")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SetupPlan {
+    /// Packages first, then files, then services, since services usually
+    /// need both.
+    pub steps: Vec<SetupStep>,
+    /// Requirements no step can satisfy, e.g. a tool whose backend cannot be
+    /// installed.
+    pub unresolvable: Vec<String>,
+}
+
+impl SetupPlan {
+    pub fn is_done(&self) -> bool {
+        self.steps.is_empty() && self.unresolvable.is_empty()
+    }
+}
+
+/// Steps that move `observed` towards `manifest`. `tool_backends` names the
+/// backends a tool needs; `backend_packages` the packages providing an
+/// installable backend, or `None` when it cannot be installed.
+pub fn plan(
+    manifest: &SetupManifest,
+    observed: &Observed,
+    tool_backends: impl Fn(&str) -> Vec<&'static str>,
+    backend_packages: impl Fn(&str) -> Option<Vec<String>>,
+) -> SetupPlan {
+    let mut plan = SetupPlan::default();
+    let mut wanted_backends: Vec<(String, String)> = manifest
+        .backends
+        .iter()
+        .map(|backend| (backend.clone(), format!("backend {backend}")))
+        .collect();
+    for tool in manifest
+        .tools
+        .iter()
+        .filter(|tool| !observed.tools.contains(*tool))
+    {
+        let missing: Vec<_> = tool_backends(tool)
+            .into_iter()
+            .filter(|backend| !observed.backends.contains(*backend))
+            .collect();
+        if missing.is_empty() {
+            plan.unresolvable.push(format!(
+                "tool {tool} is unavailable for a reason setup cannot fix"
+            ));
+        }
+        for backend in missing {
+            wanted_backends.push((backend.to_string(), format!("tool {tool}")));
+        }
+    }
+
+    let mut packages: Vec<String> = Vec::new();
+    let mut reasons: Vec<String> = Vec::new();
+    let mut add = |names: Vec<String>, reason: String| {
+        for name in names {
+            if !packages.contains(&name) {
+                packages.push(name);
+            }
+        }
+        if !reasons.contains(&reason) {
+            reasons.push(reason);
+        }
+    };
+    for (backend, reason) in wanted_backends {
+        if observed.backends.contains(&backend) {
+            continue;
+        }
+        match backend_packages(&backend) {
+            Some(names) => add(names, reason),
+            None => plan
+                .unresolvable
+                .push(format!("{reason}: backend {backend} cannot be installed")),
+        }
+    }
+    for package in manifest
+        .packages
+        .iter()
+        .filter(|package| !observed.commands.contains(package.command()))
+    {
+        add(
+            vec![package.name().to_string()],
+            format!("package {}", package.name()),
+        );
+    }
+    if !packages.is_empty() {
+        plan.steps
+            .push(SetupStep::InstallPackages { packages, reasons });
+    }
+
+    for file in manifest
+        .files
+        .iter()
+        .filter(|file| !observed.matching_files.contains(&file.path))
+    {
+        plan.steps.push(SetupStep::WriteFile {
+            path: file.path.clone(),
+            content: file.content.clone(),
+        });
+    }
+    for service in manifest
+        .services
+        .iter()
+        .filter(|service| !observed.active_services.contains(service.name()))
+    {
+        plan.steps.push(SetupStep::EnableService {
+            name: service.name().to_string(),
+            user: service.user(),
+        });
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool_backends(tool: &str) -> Vec<&'static str> {
+        match tool {
+            "desktop.click_text" => vec!["grim", "tesseract", "ydotool"],
+            "desktop.cursor_position" => vec!["hyprland"],
+            _ => Vec::new(),
+        }
+    }
+
+    fn backend_packages(backend: &str) -> Option<Vec<String>> {
+        match backend {
+            "tesseract" => Some(vec!["tesseract".into(), "tesseract-data-eng".into()]),
+            "hyprland" => None,
+            other => Some(vec![other.to_string()]),
+        }
+    }
+
+    #[test]
+    fn plan_covers_what_the_machine_lacks() {
+        let manifest: SetupManifest = serde_yaml::from_str(
+            "backends: [grim]\n\
+             tools: [desktop.click_text, desktop.cursor_position]\n\
+             packages: [ripgrep, {name: fd, command: fdfind}]\n\
+             services: [ydotoold, {name: bluetooth, user: false}]\n\
+             files:\n  - path: ~/.config/foot/foot.ini\n    content: \"font=mono\\n\"\n",
+        )
+        .unwrap();
+        let observed = Observed {
+            backends: ["grim".to_string()].into(),
+            commands: ["fdfind".to_string()].into(),
+            active_services: ["bluetooth".to_string()].into(),
+            ..Default::default()
+        };
+
+        let plan = plan(&manifest, &observed, tool_backends, backend_packages);
+        assert_eq!(
+            plan.steps,
+            vec![
+                SetupStep::InstallPackages {
+                    packages: vec![
+                        "tesseract".into(),
+                        "tesseract-data-eng".into(),
+                        "ydotool".into(),
+                        "ripgrep".into(),
+                    ],
+                    reasons: vec!["tool desktop.click_text".into(), "package ripgrep".into()],
+                },
+                SetupStep::WriteFile {
+                    path: "~/.config/foot/foot.ini".into(),
+                    content: "font=mono\n".into(),
+                },
+                SetupStep::EnableService {
+                    name: "ydotoold".into(),
+                    user: true,
+                },
+            ]
+        );
+        assert_eq!(
+            plan.unresolvable,
+            vec!["tool desktop.cursor_position: backend hyprland cannot be installed"]
+        );
+        assert!(!plan.is_done());
+    }
+
+    #[test]
+    fn satisfied_manifest_plans_nothing() {
+        let manifest: SetupManifest =
+            serde_yaml::from_str("backends: [grim]\ntools: [desktop.click_text]\n").unwrap();
+        let observed = Observed {
+            backends: ["grim".to_string()].into(),
+            tools: ["desktop.click_text".to_string()].into(),
+            ..Default::default()
+        };
+        assert!(plan(&manifest, &observed, tool_backends, backend_packages).is_done());
+        assert!(serde_yaml::from_str::<SetupManifest>("packagez: [x]\n").is_err());
+        assert_eq!(
+            expand_home("~/.config/foot.ini", Some(Path::new("/home/me"))),
+            PathBuf::from("/home/me/.config/foot.ini")
+        );
+    }
+}
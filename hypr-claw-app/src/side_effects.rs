@@ -0,0 +1,370 @@
+//! What a run changed: files, processes, windows, URLs and artifacts.
+//!
+//! The tool dispatcher feeds every successful call, with its input and
+//! result, into the run's [`Manifest`]; read-only tools leave no trace. At
+//! the end of the run the REPL prints the manifest and appends it to the
+//! run's action log, so `actions <run>` still answers "what did it change?"
+//! later. Staged file changes count once `fs.apply_staged` applies them,
+//! whichever tool staged them.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Entries shown per line before the rest are only counted.
+const SHOWN_PER_LINE: usize = 5;
+
+/// Tools that type, click or press keys in whatever window has focus.
+const INPUT_TOOLS: &[&str] = &[
+    "desktop.type_text",
+    "desktop.key_press",
+    "desktop.key_combo",
+    "desktop.mouse_click",
+    "desktop.click_at",
+    "desktop.click_at_and_verify",
+    "desktop.click_text",
+    "form.fill",
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Manifest {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub created: Vec<String>,
+    /// Files written; the tools do not say whether they existed before.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub written: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deleted: Vec<String>,
+    /// `from → to`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub moved: Vec<String>,
+    /// Processes started or stopped.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub processes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub windows: Vec<String>,
+    /// Keyboard and mouse actions sent to the focused window.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub inputs: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub urls: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<String>,
+    /// Changes outside the categories above, e.g. packages or timers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub other: Vec<String>,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) if !text.is_empty() => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+fn push_unique(list: &mut Vec<String>, entry: String) {
+    if !list.contains(&entry) {
+        list.push(entry);
+    }
+}
+
+impl Manifest {
+    /// Adds the effects of one successful call of `tool`.
+    pub fn record(&mut self, tool: &str, input: &Value, output: &Value) {
+        let field = |value: &Value, key: &str| text(&value[key]);
+        let mut add = |list: fn(&mut Self) -> &mut Vec<String>, entry: Option<String>| {
+            if let Some(entry) = entry {
+                push_unique(list(self), entry);
+            }
+        };
+        match tool {
+            "fs.create_dir" => add(|m| &mut m.created, field(input, "path")),
+            "fs.copy" => add(|m| &mut m.created, field(input, "to")),
+            "fs.write" | "file.write" | "csv.write" => {
+                add(|m| &mut m.written, field(input, "path"))
+            }
+            "fs.delete" => add(|m| &mut m.deleted, field(input, "path")),
+            "fs.move" => add(
+                |m| &mut m.moved,
+                field(input, "from")
+                    .zip(field(input, "to"))
+                    .map(|(from, to)| format!("{from} → {to}")),
+            ),
+            "fs.apply_staged" => {
+                for path in output["deleted_paths"].as_array().into_iter().flatten() {
+                    add(|m| &mut m.deleted, text(path));
+                }
+                for change in output["moved_paths"].as_array().into_iter().flatten() {
+                    add(
+                        |m| &mut m.moved,
+                        text(&change["from"])
+                            .zip(text(&change["to"]))
+                            .map(|(from, to)| format!("{from} → {to}")),
+                    );
+                }
+            }
+            "fs.undo_apply" => add(
+                |m| &mut m.other,
+                field(output, "restored").map(|n| format!("undid {n} staged changes")),
+            ),
+            "proc.spawn" | "shell.exec" => add(
+                |m| &mut m.processes,
+                field(input, "command").map(|command| match field(output, "pid") {
+                    Some(pid) => format!("{command} (pid {pid})"),
+                    None => command,
+                }),
+            ),
+            "desktop.launch_app" | "desktop.launch_app_and_wait_text" => add(
+                |m| &mut m.processes,
+                field(output, "app")
+                    .or_else(|| field(input, "app"))
+                    .map(|app| match field(output, "pid") {
+                        Some(pid) => format!("{app} (pid {pid})"),
+                        None => app,
+                    }),
+            ),
+            "hypr.exec" => add(|m| &mut m.processes, field(output, "executed")),
+            "code.run_snippet" => add(
+                |m| &mut m.processes,
+                field(input, "language").map(|language| format!("{language} snippet")),
+            ),
+            "proc.kill" => add(
+                |m| &mut m.processes,
+                field(input, "pid").map(|pid| format!("killed pid {pid}")),
+            ),
+            "hypr.window.focus" | "hypr.window.close" => {
+                let verb = tool.rsplit('.').next().unwrap_or_default();
+                add(
+                    |m| &mut m.windows,
+                    field(input, "window_id").map(|id| format!("{verb} {id}")),
+                )
+            }
+            "hypr.window.move" | "hypr.workspace.move_window" => add(
+                |m| &mut m.windows,
+                field(input, "window_id")
+                    .zip(field(input, "workspace_id"))
+                    .map(|(id, workspace)| format!("move {id} to workspace {workspace}")),
+            ),
+            "hypr.workspace.switch" => add(
+                |m| &mut m.windows,
+                field(input, "workspace_id").map(|id| format!("switch to workspace {id}")),
+            ),
+            tool if INPUT_TOOLS.contains(&tool) => self.inputs += 1,
+            "desktop.open_url" => add(
+                |m| &mut m.urls,
+                field(output, "opened").or_else(|| field(input, "url")),
+            ),
+            "desktop.search_web" => add(
+                |m| &mut m.urls,
+                field(input, "query").map(|query| format!("web search: {query}")),
+            ),
+            "desktop.open_gmail" => add(|m| &mut m.urls, Some("https://mail.google.com".into())),
+            "telegram.open_chat" => add(
+                |m| &mut m.urls,
+                field(input, "to").map(|to| format!("telegram chat: {to}")),
+            ),
+            "desktop.capture_screen" => add(|m| &mut m.artifacts, field(output, "path")),
+            "doc.generate" => {
+                add(|m| &mut m.artifacts, field(output, "source_path"));
+                add(|m| &mut m.artifacts, field(output, "pdf_path"));
+            }
+            "mail.compose" => add(
+                |m| &mut m.other,
+                field(input, "to").map(|to| format!("mail draft to {to}")),
+            ),
+            "wallpaper.set" => add(
+                |m| &mut m.other,
+                field(output, "wallpaper").map(|path| format!("wallpaper {path}")),
+            ),
+            "system.package_install" => {
+                let packages = input["packages"]
+                    .as_array()
+                    .map(|packages| packages.iter().filter_map(text).collect::<Vec<_>>())
+                    .unwrap_or_default();
+                if !packages.is_empty() {
+                    add(
+                        |m| &mut m.other,
+                        Some(format!("installed {}", packages.join(" "))),
+                    );
+                }
+            }
+            "timer.set" => add(
+                |m| &mut m.other,
+                field(input, "message").map(|message| format!("timer: {message}")),
+            ),
+            "schedule.system_create" | "schedule.system_remove" => {
+                let verb = tool.rsplit('_').next().unwrap_or_default();
+                add(
+                    |m| &mut m.other,
+                    field(input, "name").map(|name| format!("{verb} schedule {name}")),
+                )
+            }
+            "db.execute" => add(
+                |m| &mut m.other,
+                field(input, "path").map(|path| format!("database {path}")),
+            ),
+            "system.shutdown" | "system.reboot" => add(|m| &mut m.other, Some(tool.into())),
+            _ => {}
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows().is_empty()
+    }
+
+    fn rows(&self) -> Vec<(&'static str, Vec<String>)> {
+        let inputs = if self.inputs == 0 {
+            Vec::new()
+        } else {
+            vec![format!(
+                "{} keyboard/mouse action(s) in the focused window",
+                self.inputs
+            )]
+        };
+        [
+            ("Files created", self.created.clone()),
+            ("Files written", self.written.clone()),
+            ("Files deleted", self.deleted.clone()),
+            ("Files moved", self.moved.clone()),
+            ("Processes", self.processes.clone()),
+            ("Windows", [self.windows.clone(), inputs].concat()),
+            ("URLs opened", self.urls.clone()),
+            ("Artifacts", self.artifacts.clone()),
+            ("Other", self.other.clone()),
+        ]
+        .into_iter()
+        .filter(|(_, entries)| !entries.is_empty())
+        .collect()
+    }
+
+    /// One line per kind of change, for the end of a run.
+    pub fn render(&self) -> Vec<String> {
+        self.rows()
+            .into_iter()
+            .map(|(label, entries)| {
+                let mut shown = entries
+                    .iter()
+                    .take(SHOWN_PER_LINE)
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if entries.len() > SHOWN_PER_LINE {
+                    shown.push_str(&format!(" (+{} more)", entries.len() - SHOWN_PER_LINE));
+                }
+                format!("  {label:<14}: {shown}")
+            })
+            .collect()
+    }
+
+    /// Counts per kind, e.g. `files written: 2, urls opened: 1`.
+    pub fn summary(&self) -> String {
+        self.rows()
+            .into_iter()
+            .map(|(label, entries)| format!("{}: {}", label.to_lowercase(), entries.len()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn successful_calls_become_changes() {
+        let mut manifest = Manifest::default();
+        manifest.record(
+            "fs.read",
+            &json!({"path": "/tmp/a"}),
+            &json!({"content": ""}),
+        );
+        assert!(manifest.is_empty());
+
+        manifest.record(
+            "fs.write",
+            &json!({"path": "/tmp/a", "content": "x"}),
+            &json!({"written": "/tmp/a"}),
+        );
+        manifest.record(
+            "fs.write",
+            &json!({"path": "/tmp/a", "content": "y"}),
+            &json!({"written": "/tmp/a"}),
+        );
+        manifest.record(
+            "proc.spawn",
+            &json!({"command": "kitty"}),
+            &json!({"pid": 4242, "isolated": false}),
+        );
+        manifest.record(
+            "desktop.open_url",
+            &json!({"url": "https://example.org"}),
+            &json!({"opened": "https://example.org"}),
+        );
+        manifest.record("desktop.type_text", &json!({"text": "hi"}), &json!({}));
+        manifest.record("desktop.key_press", &json!({"key": "Return"}), &json!({}));
+        manifest.record(
+            "desktop.capture_screen",
+            &json!({}),
+            &json!({"path": "/tmp/shot.png"}),
+        );
+
+        assert_eq!(manifest.written, vec!["/tmp/a"]);
+        assert_eq!(manifest.processes, vec!["kitty (pid 4242)"]);
+        assert_eq!(manifest.inputs, 2);
+        assert_eq!(
+            manifest.render(),
+            vec![
+                "  Files written : /tmp/a",
+                "  Processes     : kitty (pid 4242)",
+                "  Windows       : 2 keyboard/mouse action(s) in the focused window",
+                "  URLs opened   : https://example.org",
+                "  Artifacts     : /tmp/shot.png",
+            ]
+        );
+        assert_eq!(
+            manifest.summary(),
+            "files written: 1, processes: 1, windows: 1, urls opened: 1, artifacts: 1"
+        );
+    }
+
+    #[test]
+    fn staged_changes_count_once_applied() {
+        let mut manifest = Manifest::default();
+        manifest.record(
+            "fs.stage",
+            &json!({"deletes": ["/d/old.log"], "moves": [{"from": "/d/a", "to": "/d/b"}]}),
+            &json!({"staged": 2}),
+        );
+        assert!(manifest.is_empty());
+        manifest.record("fs.apply_staged", &json!({}), &json!({"applied": false}));
+        assert!(manifest.is_empty());
+        manifest.record(
+            "fs.apply_staged",
+            &json!({}),
+            &json!({
+                "applied": true,
+                "deleted": 1,
+                "moved": 1,
+                "deleted_paths": ["/d/old.log"],
+                "moved_paths": [{"from": "/d/a", "to": "/d/b"}],
+            }),
+        );
+        assert_eq!(manifest.deleted, vec!["/d/old.log"]);
+        assert_eq!(manifest.moved, vec!["/d/a → /d/b"]);
+
+        let persisted = serde_json::to_value(&manifest).unwrap();
+        assert_eq!(
+            persisted,
+            json!({"deleted": ["/d/old.log"], "moved": ["/d/a → /d/b"]})
+        );
+        assert_eq!(
+            serde_json::from_value::<Manifest>(persisted).unwrap(),
+            manifest
+        );
+    }
+}
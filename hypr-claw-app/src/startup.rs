@@ -0,0 +1,106 @@
+//! Startup phase timings.
+//!
+//! Set `HYPR_CLAW_PROFILE_STARTUP=1` to print how long each phase took
+//! before the first prompt. The budget for a warm start is
+//! [`WARM_START_BUDGET`].
+
+use std::time::{Duration, Instant};
+
+pub const PROFILE_ENV: &str = "HYPR_CLAW_PROFILE_STARTUP";
+pub const WARM_START_BUDGET: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone)]
+pub struct StartupProfile {
+    started: Instant,
+    last: Instant,
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Default for StartupProfile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StartupProfile {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            started: now,
+            last: now,
+            phases: Vec::new(),
+        }
+    }
+
+    /// Close the current phase under `name`.
+    pub fn mark(&mut self, name: &'static str) {
+        let now = Instant::now();
+        self.phases.push((name, now - self.last));
+        self.last = now;
+    }
+
+    pub fn total(&self) -> Duration {
+        self.last - self.started
+    }
+
+    pub fn phases(&self) -> &[(&'static str, Duration)] {
+        &self.phases
+    }
+
+    pub fn enabled() -> bool {
+        std::env::var(PROFILE_ENV).is_ok_and(|value| !value.is_empty() && value != "0")
+    }
+
+    /// One line per phase, slowest marked, then the total against budget.
+    pub fn render(&self) -> Vec<String> {
+        let slowest = self
+            .phases
+            .iter()
+            .map(|(_, elapsed)| *elapsed)
+            .max()
+            .unwrap_or_default();
+        let mut lines: Vec<String> = self
+            .phases
+            .iter()
+            .map(|(name, elapsed)| {
+                format!(
+                    "  {:<18} {:>6}ms{}",
+                    name,
+                    elapsed.as_millis(),
+                    if *elapsed == slowest && !slowest.is_zero() {
+                        "  <- slowest"
+                    } else {
+                        ""
+                    }
+                )
+            })
+            .collect();
+        lines.push(format!(
+            "  {:<18} {:>6}ms (budget {}ms)",
+            "total",
+            self.total().as_millis(),
+            WARM_START_BUDGET.as_millis()
+        ));
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases_add_up_to_total() {
+        let mut profile = StartupProfile::new();
+        std::thread::sleep(Duration::from_millis(2));
+        profile.mark("config");
+        profile.mark("session");
+        let sum: Duration = profile.phases().iter().map(|(_, elapsed)| *elapsed).sum();
+        assert_eq!(sum, profile.total());
+
+        let lines = profile.render();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("config") && lines[0].ends_with("<- slowest"));
+        assert!(lines[2].contains("(budget 300ms)"));
+    }
+}
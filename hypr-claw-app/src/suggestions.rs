@@ -0,0 +1,129 @@
+//! Shell commands suggested in a final answer, runnable with `do <n>`.
+//!
+//! Commands are taken from fenced code blocks tagged as shell (or not
+//! tagged at all). In blocks that show a prompt, only the `$ ` lines are
+//! commands and the rest is sample output.
+
+use serde_json::{json, Value};
+
+const SHELL_LANGUAGES: &[&str] = &["", "sh", "bash", "shell", "zsh", "fish", "console"];
+
+/// Syntax that needs a shell to mean what it says. `proc.spawn` splits a
+/// plain command on whitespace, so quoting and escapes count too.
+const SHELL_SYNTAX: &[&str] = &[
+    "|", "&&", ";", ">", "<", "$", "`", "*", "~", "\"", "'", "\\",
+];
+
+pub fn extract_commands(answer: &str) -> Vec<String> {
+    let mut commands: Vec<String> = Vec::new();
+    // Lines of the open fenced block; `None` inside a non-shell block.
+    let mut block: Option<Option<Vec<&str>>> = None;
+    for line in answer.lines() {
+        let trimmed = line.trim();
+        if let Some(language) = trimmed.strip_prefix("```") {
+            match block.take() {
+                Some(Some(lines)) => commands.extend(block_commands(&lines)),
+                Some(None) => {}
+                None => {
+                    let shell = SHELL_LANGUAGES.contains(&language.trim());
+                    block = Some(shell.then(Vec::new));
+                }
+            }
+            continue;
+        }
+        if let Some(Some(lines)) = &mut block {
+            lines.push(trimmed);
+        }
+    }
+    let mut unique = Vec::new();
+    for command in commands {
+        if !unique.contains(&command) {
+            unique.push(command);
+        }
+    }
+    unique
+}
+
+fn block_commands(lines: &[&str]) -> Vec<String> {
+    let prompted = lines.iter().any(|line| line.starts_with("$ "));
+    let mut commands = Vec::new();
+    let mut continued: Option<String> = None;
+    for line in lines {
+        let line = match continued.take() {
+            Some(head) => format!("{head} {line}"),
+            None if prompted => match line.strip_prefix("$ ") {
+                Some(command) => command.to_string(),
+                None => continue,
+            },
+            None => line.to_string(),
+        };
+        if let Some(head) = line.strip_suffix('\\') {
+            continued = Some(head.trim_end().to_string());
+            continue;
+        }
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            commands.push(line.to_string());
+        }
+    }
+    commands.extend(continued.filter(|line| !line.is_empty()));
+    commands
+}
+
+/// `proc.spawn` input for `command`, through `sh -c` when it uses pipes,
+/// redirects, quotes, variables or other shell syntax.
+pub fn spawn_input(command: &str) -> Value {
+    if SHELL_SYNTAX.iter().any(|syntax| command.contains(syntax)) {
+        json!({"command": "sh", "args": ["-c", command]})
+    } else {
+        json!({"command": command})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_come_from_shell_blocks_only() {
+        let answer = "Update first:\n\
+            ```bash\n\
+            # refresh the package list\n\
+            sudo pacman -Syu\n\
+            systemctl --user restart \\\n  waybar\n\
+            ```\n\
+            Config:\n\
+            ```toml\n\
+            [bar]\n\
+            ```\n\
+            Check it:\n\
+            ```console\n\
+            $ systemctl --user status waybar | head -3\n\
+            ● waybar.service - Highly customizable bar\n\
+            $ sudo pacman -Syu\n\
+            ```\n";
+        assert_eq!(
+            extract_commands(answer),
+            vec![
+                "sudo pacman -Syu",
+                "systemctl --user restart waybar",
+                "systemctl --user status waybar | head -3",
+            ]
+        );
+        assert!(extract_commands("Just use `ls`.").is_empty());
+
+        assert_eq!(spawn_input("ls -la"), json!({"command": "ls -la"}));
+        assert_eq!(
+            spawn_input("ls | wc -l")["args"],
+            json!(["-c", "ls | wc -l"])
+        );
+        for quoted in [
+            "git commit -m \"fix bug\"",
+            "grep 'a b' file",
+            "touch my\\ file",
+            "echo $HOME",
+        ] {
+            assert_eq!(spawn_input(quoted)["args"], json!(["-c", quoted]));
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! How closely runs are supervised, per task class and per thread.
+//!
+//! The policy is a list of rules, each scoped to a thread, a task class,
+//! both, or neither (the default). A run takes the most specific rule that
+//! matches its thread and class: thread and class, then thread, then class,
+//! then the default. Without any match the preset's approvals and autonomy
+//! apply unchanged.
+
+use hypr_claw_tools::approvals::SessionSupervision;
+use hypr_claw_tools::PermissionTier;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Supervision {
+    /// Changes run only once the user accepted them in a plan.
+    PlanApproval,
+    /// Every change asks the user first.
+    PerAction,
+    /// Only system-critical and hazardous actions ask first.
+    Autonomous,
+}
+
+impl Supervision {
+    pub const ALL: [Self; 3] = [Self::PlanApproval, Self::PerAction, Self::Autonomous];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PlanApproval => "plan_approval",
+            Self::PerAction => "per_action",
+            Self::Autonomous => "autonomous",
+        }
+    }
+
+    pub fn parse(raw: &str) -> Option<Self> {
+        let normalized = raw.trim().to_ascii_lowercase().replace(['-', ' '], "_");
+        match normalized.as_str() {
+            "plan" | "plan_approval" => Some(Self::PlanApproval),
+            "action" | "per_action" => Some(Self::PerAction),
+            "auto" | "autonomous" | "autonomy" => Some(Self::Autonomous),
+            _ => None,
+        }
+    }
+
+    /// Approval rules the gate applies to the run's session.
+    pub fn session(&self) -> SessionSupervision {
+        match self {
+            Self::PlanApproval => SessionSupervision {
+                prompt_from: PermissionTier::SystemCritical,
+                require_plan: true,
+            },
+            Self::PerAction => SessionSupervision {
+                prompt_from: PermissionTier::Write,
+                require_plan: false,
+            },
+            Self::Autonomous => SessionSupervision {
+                prompt_from: PermissionTier::SystemCritical,
+                require_plan: false,
+            },
+        }
+    }
+
+    /// What the model is told about the run's supervision, if anything.
+    pub fn instructions(&self) -> Option<&'static str> {
+        match self {
+            Self::PlanApproval => Some(
+                "Supervision: plan approval. Observe freely, but before changing anything list \
+                 every change in one plan.request_approval call; changes outside an accepted \
+                 plan are refused.",
+            ),
+            Self::PerAction => Some(
+                "Supervision: per action. The user approves each change before it runs, so keep \
+                 changes few and say why each is needed.",
+            ),
+            Self::Autonomous => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Scope {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+}
+
+impl Scope {
+    fn matches(&self, thread: &str, class: &str) -> bool {
+        self.thread.as_deref().is_none_or(|t| t == thread)
+            && self.class.as_deref().is_none_or(|c| c == class)
+    }
+
+    /// Thread rules outrank class rules, which outrank the default.
+    fn rank(&self) -> u8 {
+        u8::from(self.thread.is_some()) * 2 + u8::from(self.class.is_some())
+    }
+
+    pub fn describe(&self) -> String {
+        match (&self.thread, &self.class) {
+            (Some(thread), Some(class)) => format!("thread {thread}, class {class}"),
+            (Some(thread), None) => format!("thread {thread}"),
+            (None, Some(class)) => format!("class {class}"),
+            (None, None) => "default".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PolicyRule {
+    #[serde(flatten)]
+    pub scope: Scope,
+    pub level: Supervision,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SupervisionPolicy {
+    #[serde(default)]
+    rules: Vec<PolicyRule>,
+}
+
+impl SupervisionPolicy {
+    /// The most specific rule for a run of `class` in `thread`.
+    pub fn resolve(&self, thread: &str, class: &str) -> Option<&PolicyRule> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.scope.matches(thread, class))
+            .max_by_key(|rule| rule.scope.rank())
+    }
+
+    pub fn set(&mut self, scope: Scope, level: Supervision) {
+        match self.rules.iter_mut().find(|rule| rule.scope == scope) {
+            Some(rule) => rule.level = level,
+            None => self.rules.push(PolicyRule { scope, level }),
+        }
+    }
+
+    /// Removes the rule for exactly `scope`; false if there was none.
+    pub fn unset(&mut self, scope: &Scope) -> bool {
+        let before = self.rules.len();
+        self.rules.retain(|rule| &rule.scope != scope);
+        self.rules.len() != before
+    }
+
+    /// Rules from the most to the least specific.
+    pub fn rules(&self) -> Vec<&PolicyRule> {
+        let mut rules: Vec<&PolicyRule> = self.rules.iter().collect();
+        rules.sort_by_key(|rule| std::cmp::Reverse(rule.scope.rank()));
+        rules
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PolicyCommand {
+    Show,
+    Set(Scope, Supervision),
+    Unset(Scope),
+}
+
+pub const USAGE: &str = "Use: policy show | policy set [thread <id>] [class <class>] \
+                         <plan|action|auto> | policy unset [thread <id>] [class <class>] \
+                         (no thread or class: the default)";
+
+/// Parses the words after `policy`. `classes` are the valid task classes.
+pub fn parse_command(args: &str, classes: &[&str]) -> Result<PolicyCommand, String> {
+    let mut words = args.split_whitespace();
+    let verb = words.next().unwrap_or("show");
+    if verb == "show" {
+        return match words.next() {
+            None => Ok(PolicyCommand::Show),
+            Some(_) => Err(USAGE.to_string()),
+        };
+    }
+    if verb != "set" && verb != "unset" {
+        return Err(USAGE.to_string());
+    }
+
+    let mut scope = Scope::default();
+    let mut level = None;
+    while let Some(word) = words.next() {
+        match word {
+            "thread" => {
+                let id = words.next().ok_or("thread needs an id")?;
+                scope.thread = Some(id.to_string());
+            }
+            "class" => {
+                let class = words.next().ok_or("class needs a name")?;
+                if !classes.contains(&class) {
+                    return Err(format!(
+                        "Unknown task class '{class}' (one of {})",
+                        classes.join(", ")
+                    ));
+                }
+                scope.class = Some(class.to_string());
+            }
+            "default" => {}
+            other if verb == "set" && level.is_none() => {
+                level = Some(Supervision::parse(other).ok_or_else(|| {
+                    format!(
+                        "Unknown supervision '{other}' (one of {})",
+                        Supervision::ALL.map(|s| s.as_str()).join(", ")
+                    )
+                })?);
+            }
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    match (verb, level) {
+        ("set", Some(level)) => Ok(PolicyCommand::Set(scope, level)),
+        ("unset", None) => Ok(PolicyCommand::Unset(scope)),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLASSES: &[&str] = &["question", "action", "investigation"];
+
+    fn scope(thread: Option<&str>, class: Option<&str>) -> Scope {
+        Scope {
+            thread: thread.map(str::to_string),
+            class: class.map(str::to_string),
+        }
+    }
+
+    fn level(policy: &SupervisionPolicy, thread: &str, class: &str) -> Option<Supervision> {
+        policy.resolve(thread, class).map(|rule| rule.level)
+    }
+
+    #[test]
+    fn the_most_specific_rule_wins() {
+        let mut policy = SupervisionPolicy::default();
+        assert_eq!(policy.resolve("task-1", "action"), None);
+
+        policy.set(scope(None, None), Supervision::Autonomous);
+        policy.set(scope(None, Some("action")), Supervision::PlanApproval);
+        policy.set(scope(Some("task-2"), None), Supervision::PerAction);
+        policy.set(
+            scope(Some("task-2"), Some("question")),
+            Supervision::Autonomous,
+        );
+        assert_eq!(
+            level(&policy, "task-1", "question"),
+            Some(Supervision::Autonomous)
+        );
+        assert_eq!(
+            level(&policy, "task-1", "action"),
+            Some(Supervision::PlanApproval)
+        );
+        assert_eq!(
+            level(&policy, "task-2", "action"),
+            Some(Supervision::PerAction)
+        );
+        assert_eq!(
+            level(&policy, "task-2", "question"),
+            Some(Supervision::Autonomous)
+        );
+
+        policy.set(scope(None, Some("action")), Supervision::PerAction);
+        assert_eq!(policy.rules().len(), 4);
+        assert_eq!(
+            policy.rules()[0].scope.describe(),
+            "thread task-2, class question"
+        );
+        assert!(policy.unset(&scope(None, None)));
+        assert!(!policy.unset(&scope(None, None)));
+        assert_eq!(level(&policy, "task-1", "question"), None);
+        assert_eq!(
+            level(&policy, "task-1", "action"),
+            Some(Supervision::PerAction)
+        );
+    }
+
+    #[test]
+    fn commands_parse_scopes_and_levels() {
+        assert_eq!(parse_command("", CLASSES), Ok(PolicyCommand::Show));
+        assert_eq!(
+            parse_command("set class action plan", CLASSES),
+            Ok(PolicyCommand::Set(
+                scope(None, Some("action")),
+                Supervision::PlanApproval
+            ))
+        );
+        assert_eq!(
+            parse_command("set thread task-3 class investigation per-action", CLASSES),
+            Ok(PolicyCommand::Set(
+                scope(Some("task-3"), Some("investigation")),
+                Supervision::PerAction
+            ))
+        );
+        assert_eq!(
+            parse_command("set default auto", CLASSES),
+            Ok(PolicyCommand::Set(
+                scope(None, None),
+                Supervision::Autonomous
+            ))
+        );
+        assert_eq!(
+            parse_command("unset thread task-3", CLASSES),
+            Ok(PolicyCommand::Unset(scope(Some("task-3"), None)))
+        );
+        for bad in [
+            "set class chores plan",
+            "set class action",
+            "set class action sometimes",
+            "unset class action plan",
+            "set thread",
+            "delete",
+        ] {
+            assert!(parse_command(bad, CLASSES).is_err(), "{bad:?} should fail");
+        }
+    }
+
+    #[test]
+    fn policies_round_trip_through_agent_state() {
+        let mut policy = SupervisionPolicy::default();
+        policy.set(
+            scope(Some("task-2"), Some("action")),
+            Supervision::PlanApproval,
+        );
+        let value = serde_json::to_value(&policy).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"rules": [
+                {"thread": "task-2", "class": "action", "level": "plan_approval"}
+            ]})
+        );
+        assert_eq!(
+            serde_json::from_value::<SupervisionPolicy>(value).unwrap(),
+            policy
+        );
+        assert_eq!(
+            serde_json::from_value::<SupervisionPolicy>(serde_json::json!({})).unwrap(),
+            SupervisionPolicy::default()
+        );
+    }
+}
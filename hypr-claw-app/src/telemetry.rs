@@ -0,0 +1,112 @@
+//! The bundled telemetry sink: the files under `./data` the app has always
+//! written, now behind [`hypr_claw_runtime::TelemetrySink`] so an embedder
+//! can route the same stream elsewhere, alongside or instead.
+//!
+//! Tool decisions go to the per-run action logs, compactions and refetches
+//! to `compactions.jsonl`, and tool audit entries to `audit.log`. Metric
+//! samples are left to the runtime's own counters and exporters.
+
+use crate::action_log::ActionLog;
+use crate::compaction_metrics::{CompactionLog, CompactionRow};
+use hypr_claw::infra::audit_logger::AuditLogger;
+use hypr_claw_runtime::{TelemetryEvent, TelemetrySink};
+use serde_json::Value;
+use std::sync::Arc;
+
+pub struct FileTelemetry {
+    actions: Arc<ActionLog>,
+    compactions: CompactionLog,
+    audit: Arc<AuditLogger>,
+}
+
+impl FileTelemetry {
+    pub fn new(
+        actions: Arc<ActionLog>,
+        compactions: CompactionLog,
+        audit: Arc<AuditLogger>,
+    ) -> Self {
+        Self {
+            actions,
+            compactions,
+            audit,
+        }
+    }
+}
+
+impl TelemetrySink for FileTelemetry {
+    fn event(&self, event: &TelemetryEvent<'_>) {
+        match event {
+            TelemetryEvent::Decision(decision) => {
+                if let Err(e) = self.actions.record_decision(decision) {
+                    eprintln!("⚠️  Failed to log tool decision: {}", e);
+                }
+            }
+            TelemetryEvent::Compaction(event) => {
+                let row = CompactionRow::from_event(event, chrono::Utc::now().timestamp());
+                if let Err(e) = self.compactions.append(&row) {
+                    eprintln!("⚠️  Failed to log compaction: {}", e);
+                }
+            }
+        }
+    }
+
+    fn audit(&self, entry: &Value) {
+        if let Err(e) = self.audit.log_tool_call(entry) {
+            eprintln!("⚠️  Failed to write audit entry: {}", e);
+        }
+    }
+}
+
+/// Audit logger for the tool dispatcher that hands each entry to the
+/// installed telemetry sink.
+pub struct SinkAudit;
+
+#[async_trait::async_trait]
+impl hypr_claw_tools::AuditLogger for SinkAudit {
+    async fn log(&self, entry: Value) {
+        hypr_claw_runtime::telemetry::audit(&entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hypr_claw_runtime::ToolDecision;
+
+    #[test]
+    fn decisions_and_audit_entries_reach_their_files() {
+        let dir = std::env::temp_dir().join(format!("hc-telemetry-{}", std::process::id()));
+        let actions = Arc::new(ActionLog::new(dir.join("actions"), 5));
+        let audit = Arc::new(AuditLogger::new(dir.join("audit.log")).unwrap());
+        let sink = FileTelemetry::new(
+            actions.clone(),
+            CompactionLog::new(dir.join("compactions.jsonl")),
+            audit.clone(),
+        );
+
+        actions.begin_run("s", 1, "open firefox").unwrap();
+        sink.event(&TelemetryEvent::Decision(&ToolDecision {
+            session_key: "s".to_string(),
+            iteration: 1,
+            offered: vec!["desktop.launch_app".to_string()],
+            chosen: Some("desktop.launch_app".to_string()),
+            rationale: None,
+        }));
+        sink.audit(&serde_json::json!({
+            "timestamp": "2026-01-01T00:00:00Z",
+            "session": "s",
+            "tool": "desktop.launch_app",
+            "input": {"app": "firefox"},
+            "result": {"success": true},
+        }));
+
+        let audit_log = std::fs::read_to_string(dir.join("audit.log")).unwrap();
+        assert!(audit_log.contains("desktop.launch_app"));
+        let logged = std::fs::read_dir(dir.join("actions"))
+            .unwrap()
+            .filter_map(|entry| std::fs::read_to_string(entry.ok()?.path()).ok())
+            .collect::<String>();
+        assert!(logged.contains("desktop.launch_app"), "{logged}");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,172 @@
+//! Thermal rules: alert or hold agent work while the machine runs hot.
+//!
+//! A background monitor reads the sensors every `thermal.interval_secs` and
+//! checks each [`ThermalRule`] against the hottest sensor it matches. A rule
+//! that trips stays tripped until that sensor drops `hysteresis` degrees
+//! below the threshold, so a fan spinning up does not make it flap. Alert
+//! rules notify once per trip; throttle rules also make new requests wait
+//! for the machine to cool down, which matters most on laptops running a
+//! local model.
+
+use crate::config::{ThermalAction, ThermalRule};
+use hypr_claw_tools::os_capabilities::thermal::Thermal;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trip {
+    /// Index into `thermal.rules`.
+    pub rule: usize,
+    pub action: ThermalAction,
+    /// `source/label` of the sensor.
+    pub sensor: String,
+    pub celsius: f32,
+    pub above: f32,
+}
+
+impl Trip {
+    pub fn describe(&self) -> String {
+        format!(
+            "{} at {:.0}°C (limit {:.0}°C)",
+            self.sensor, self.celsius, self.above
+        )
+    }
+}
+
+/// Rules tripped by `reading`; those in `active` clear only once their
+/// sensor is `hysteresis` below the threshold.
+pub fn evaluate(
+    rules: &[ThermalRule],
+    reading: &Thermal,
+    hysteresis: f32,
+    active: &[Trip],
+) -> Vec<Trip> {
+    rules
+        .iter()
+        .enumerate()
+        .filter_map(|(index, rule)| {
+            let filter = rule.sensor.as_deref().map(str::to_lowercase);
+            let hottest = reading
+                .sensors
+                .iter()
+                .filter(|sensor| {
+                    filter
+                        .as_deref()
+                        .is_none_or(|filter| sensor.id().to_lowercase().contains(filter))
+                })
+                .max_by(|a, b| a.celsius.total_cmp(&b.celsius))?;
+            let threshold = if active.iter().any(|trip| trip.rule == index) {
+                rule.above - hysteresis
+            } else {
+                rule.above
+            };
+            (hottest.celsius > threshold).then(|| Trip {
+                rule: index,
+                action: rule.action,
+                sensor: hottest.id(),
+                celsius: hottest.celsius,
+                above: rule.above,
+            })
+        })
+        .collect()
+}
+
+/// Rules currently tripped, shared between the monitor and the REPL.
+#[derive(Default)]
+pub struct ThermalGuard {
+    trips: Mutex<Vec<Trip>>,
+}
+
+impl ThermalGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn active(&self) -> Vec<Trip> {
+        self.trips.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Stores `trips` and returns the rules that just tripped and the ones
+    /// that just cleared.
+    pub fn update(&self, trips: Vec<Trip>) -> (Vec<Trip>, Vec<Trip>) {
+        let mut current = self.trips.lock().unwrap_or_else(|e| e.into_inner());
+        let started = trips
+            .iter()
+            .filter(|trip| !current.iter().any(|old| old.rule == trip.rule))
+            .cloned()
+            .collect();
+        let cleared = current
+            .iter()
+            .filter(|old| !trips.iter().any(|trip| trip.rule == old.rule))
+            .cloned()
+            .collect();
+        *current = trips;
+        (started, cleared)
+    }
+
+    /// The tripped throttle rule, if any, new requests should wait for.
+    pub fn throttle(&self) -> Option<Trip> {
+        self.active()
+            .into_iter()
+            .find(|trip| trip.action == ThermalAction::Throttle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hypr_claw_tools::os_capabilities::thermal::Sensor;
+
+    fn reading(cpu: f32, gpu: f32) -> Thermal {
+        let sensor = |source: &str, label: &str, celsius| Sensor {
+            source: source.to_string(),
+            label: label.to_string(),
+            celsius,
+            high: None,
+            critical: None,
+        };
+        Thermal {
+            sensors: vec![
+                sensor("k10temp", "Tctl", cpu),
+                sensor("amdgpu", "edge", gpu),
+            ],
+            fans: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn rules_trip_on_matching_sensors_and_clear_with_hysteresis() {
+        let rules = vec![
+            ThermalRule {
+                sensor: None,
+                above: 90.0,
+                action: ThermalAction::Alert,
+            },
+            ThermalRule {
+                sensor: Some("AMDGPU".to_string()),
+                above: 80.0,
+                action: ThermalAction::Throttle,
+            },
+        ];
+        let guard = ThermalGuard::new();
+
+        let trips = evaluate(&rules, &reading(92.0, 70.0), 5.0, &guard.active());
+        let (started, cleared) = guard.update(trips);
+        assert_eq!(started.len(), 1);
+        assert_eq!(started[0].describe(), "k10temp/Tctl at 92°C (limit 90°C)");
+        assert!(cleared.is_empty());
+        assert_eq!(guard.throttle(), None);
+
+        let trips = evaluate(&rules, &reading(87.0, 83.0), 5.0, &guard.active());
+        let (started, cleared) = guard.update(trips);
+        assert_eq!(started[0].sensor, "amdgpu/edge");
+        assert!(cleared.is_empty(), "87°C is within the hysteresis band");
+        assert_eq!(guard.throttle().map(|trip| trip.rule), Some(1));
+
+        let trips = evaluate(&rules, &reading(84.0, 76.0), 5.0, &guard.active());
+        let (started, cleared) = guard.update(trips);
+        assert!(started.is_empty());
+        assert_eq!(cleared.len(), 1);
+        assert_eq!(cleared[0].rule, 0);
+        assert!(guard.throttle().is_some());
+    }
+}
@@ -0,0 +1,308 @@
+//! Checks behind `triage`, the guided walk through a failed run.
+//!
+//! From the run's stop code, error and failed tool calls, [`plan`] picks the
+//! checks worth running: whether a backend a tool needed is installed,
+//! whether the provider answers and offers the model, and why a call was
+//! refused. The REPL runs each check for real and offers the fixes it finds.
+
+use crate::action_log::ActionEvent;
+
+/// Stop codes whose failures a triage can usually explain.
+pub const TRIAGE_CODES: &[&str] = &[
+    "STOP_TOOL_FAILURE_STREAK",
+    "STOP_TOOL_ENFORCEMENT",
+    "STOP_PROVIDER_ARGUMENT",
+    "STOP_PROVIDER_RATE_LIMIT",
+    "STOP_RECOVERY_BUDGET_EXHAUSTED",
+    "STOP_RUNTIME_ERROR",
+];
+
+/// Stop codes that point at the provider rather than the tools.
+const PROVIDER_CODES: &[&str] = &["STOP_PROVIDER_ARGUMENT", "STOP_PROVIDER_RATE_LIMIT"];
+
+/// Error text of a provider that could not be reached or refused the key.
+const PROVIDER_ERRORS: &[&str] = &[
+    "connection",
+    "connect error",
+    "dns",
+    "timed out",
+    "unauthorized",
+    "401",
+    "403",
+    "api key",
+    "invalid_api_key",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedCall {
+    pub tool: String,
+    pub error: String,
+}
+
+/// Tool calls that failed in a run's log, with the timing prefix removed.
+pub fn failed_calls(events: &[ActionEvent]) -> Vec<FailedCall> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            ActionEvent::Action {
+                status,
+                tool,
+                detail,
+                ..
+            } if status == "fail" || status == "error" => Some(FailedCall {
+                tool: tool.clone(),
+                error: strip_timing(detail).to_string(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `failed in 12ms <error>` or `dispatcher in 12ms <error>` without the
+/// first three words.
+fn strip_timing(detail: &str) -> &str {
+    let mut words = detail.splitn(4, ' ');
+    match (words.next(), words.next(), words.next(), words.next()) {
+        (Some("failed" | "dispatcher"), Some("in"), Some(ms), Some(rest)) if ms.ends_with("ms") => {
+            rest
+        }
+        _ => detail,
+    }
+}
+
+/// Who refused a call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Denial {
+    /// The supervision policy wanted the call in an approved plan.
+    Plan,
+    /// The approval gate, an exec rule or the user said no.
+    Gate,
+    /// The operating system refused access to a file or process.
+    Os,
+}
+
+pub fn denial(error: &str) -> Option<Denial> {
+    let lower = error.to_lowercase();
+    if lower.contains("(os error 13)")
+        || lower.contains("(os error 1)")
+        || lower.contains("operation not permitted")
+    {
+        Some(Denial::Os)
+    } else if lower.contains("needs plan approval") {
+        Some(Denial::Plan)
+    } else if lower.contains("permission denied")
+        || lower.contains("approval required")
+        || lower.contains("in the approved plan")
+    {
+        Some(Denial::Gate)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Check {
+    /// `tool` reported that none of `backends` is installed.
+    Backend { tool: String, backends: Vec<String> },
+    /// The provider answers and offers the configured model.
+    Provider,
+    /// `tool` was refused.
+    Permission {
+        tool: String,
+        denial: Denial,
+        error: String,
+    },
+}
+
+impl Check {
+    pub fn title(&self) -> String {
+        match self {
+            Self::Backend { tool, backends } if backends.is_empty() => {
+                format!("Is a backend for {tool} installed?")
+            }
+            Self::Backend { tool, backends } => {
+                format!("Is {} installed for {tool}?", backends.join(" or "))
+            }
+            Self::Provider => "Is the provider reachable?".to_string(),
+            Self::Permission { tool, .. } => format!("Why was {tool} denied?"),
+        }
+    }
+}
+
+/// Checks for a run that stopped with `stop_code` and `error` after the
+/// `calls` failed. `missing_backends` names the backends a tool error says
+/// are missing, if it says so.
+pub fn plan(
+    stop_code: &str,
+    error: &str,
+    calls: &[FailedCall],
+    missing_backends: impl Fn(&str, &str) -> Option<Vec<String>>,
+) -> Vec<Check> {
+    let mut checks = Vec::new();
+    for call in calls {
+        let check = if let Some(backends) = missing_backends(&call.tool, &call.error) {
+            Check::Backend {
+                tool: call.tool.clone(),
+                backends,
+            }
+        } else if let Some(denial) = denial(&call.error) {
+            Check::Permission {
+                tool: call.tool.clone(),
+                denial,
+                error: call.error.clone(),
+            }
+        } else {
+            continue;
+        };
+        let seen = checks.iter().any(|seen| match (seen, &check) {
+            (Check::Backend { backends: a, .. }, Check::Backend { backends: b, .. }) => a == b,
+            (Check::Permission { tool: a, .. }, Check::Permission { tool: b, .. }) => a == b,
+            _ => false,
+        });
+        if !seen {
+            checks.push(check);
+        }
+    }
+
+    let lower = error.to_lowercase();
+    let provider_failure = PROVIDER_CODES.contains(&stop_code)
+        || PROVIDER_ERRORS.iter().any(|needle| lower.contains(needle))
+        || (stop_code == "STOP_RUNTIME_ERROR" && calls.is_empty());
+    if provider_failure {
+        checks.insert(0, Check::Provider);
+    }
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(status: &str, tool: &str, detail: &str) -> ActionEvent {
+        ActionEvent::Action {
+            index: 1,
+            status: status.to_string(),
+            tool: tool.to_string(),
+            detail: detail.to_string(),
+            elapsed_ms: None,
+            at: 0,
+        }
+    }
+
+    fn backends(tool: &str, error: &str) -> Option<Vec<String>> {
+        (tool == "desktop.capture_screen" && error.contains("not installed"))
+            .then(|| vec!["grim".to_string()])
+    }
+
+    #[test]
+    fn failed_calls_come_from_the_run_log() {
+        let events = [
+            action("tool", "fs.read", "input={}"),
+            action("ok", "fs.read", "done in 3ms output={}"),
+            action(
+                "fail",
+                "desktop.capture_screen",
+                "failed in 12ms grim is not installed",
+            ),
+            action(
+                "error",
+                "fs.delete",
+                "dispatcher in 0ms Permission denied: user declined",
+            ),
+        ];
+        assert_eq!(
+            failed_calls(&events),
+            vec![
+                FailedCall {
+                    tool: "desktop.capture_screen".to_string(),
+                    error: "grim is not installed".to_string(),
+                },
+                FailedCall {
+                    tool: "fs.delete".to_string(),
+                    error: "Permission denied: user declined".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn denials_are_told_apart() {
+        assert_eq!(
+            denial("Execution failed: Permission denied (os error 13)"),
+            Some(Denial::Os)
+        );
+        assert_eq!(
+            denial("Permission denied: fs.delete /tmp/a needs plan approval first"),
+            Some(Denial::Plan)
+        );
+        assert_eq!(
+            denial("Permission denied: the user skipped fs.delete /a in the approved plan"),
+            Some(Denial::Gate)
+        );
+        assert_eq!(denial("Approval required"), Some(Denial::Gate));
+        assert_eq!(denial("file not found"), None);
+    }
+
+    #[test]
+    fn checks_follow_the_failures() {
+        let calls = [
+            FailedCall {
+                tool: "desktop.capture_screen".to_string(),
+                error: "grim is not installed".to_string(),
+            },
+            FailedCall {
+                tool: "desktop.capture_screen".to_string(),
+                error: "grim is not installed".to_string(),
+            },
+            FailedCall {
+                tool: "fs.delete".to_string(),
+                error: "Permission denied: fs.delete /a needs plan approval first".to_string(),
+            },
+            FailedCall {
+                tool: "fs.read".to_string(),
+                error: "no such file".to_string(),
+            },
+        ];
+        let checks = plan(
+            "STOP_TOOL_FAILURE_STREAK",
+            "3 consecutive tool failures",
+            &calls,
+            backends,
+        );
+        assert_eq!(checks.len(), 2);
+        assert_eq!(
+            checks[0].title(),
+            "Is grim installed for desktop.capture_screen?"
+        );
+        assert!(matches!(
+            &checks[1],
+            Check::Permission { tool, denial: Denial::Plan, .. } if tool == "fs.delete"
+        ));
+
+        assert_eq!(
+            plan(
+                "STOP_PROVIDER_RATE_LIMIT",
+                "429 Too Many Requests",
+                &[],
+                backends
+            ),
+            vec![Check::Provider]
+        );
+        assert_eq!(
+            plan(
+                "STOP_RUNTIME_ERROR",
+                "error sending request: connection refused",
+                &calls[..1],
+                backends
+            )[0],
+            Check::Provider
+        );
+        assert!(plan(
+            "STOP_MAX_ITERATIONS",
+            "Max iterations reached",
+            &[],
+            backends
+        )
+        .is_empty());
+    }
+}
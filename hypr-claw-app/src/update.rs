@@ -0,0 +1,293 @@
+//! Release feed checks and `hypr-claw self-update`.
+//!
+//! The feed is a JSON document describing the latest release: its version,
+//! the binary's URL (relative URLs resolve against the feed), a minisign
+//! signature of the binary and the data format it reads. Binaries are only
+//! installed when the signature matches one of `update.trusted_keys` and its
+//! trusted comment, which the signature covers, names the same version and
+//! data format as the feed, e.g. `hypr-claw version=0.2.0 data_format=3`.
+//! The feed itself is unsigned, so a signed version that is not newer than
+//! the running one is refused as a rollback.
+
+use crate::config::UpdateConfig;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Remembers the last feed check so startup asks at most once a day.
+pub const CHECK_STATE_PATH: &str = "./data/update-check.json";
+const CHECK_INTERVAL_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Release {
+    pub version: String,
+    pub url: String,
+    /// minisign signature (`.minisig` contents) of the binary.
+    pub signature: String,
+    /// [`crate::migrations::DATA_FORMAT`] of the release.
+    pub data_format: u32,
+    #[serde(default)]
+    pub notes: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CheckState {
+    pub checked_at: i64,
+    pub latest: Option<String>,
+}
+
+/// `major.minor.patch`, ignoring a leading `v` and any pre-release suffix.
+pub fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.trim().trim_start_matches('v');
+    let core = core.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|p| p.parse::<u64>().ok());
+    let version = (parts.next()??, parts.next()??, parts.next()??);
+    parts.next().is_none().then_some(version)
+}
+
+pub fn is_newer(candidate: &str, current: &str) -> bool {
+    match (parse_version(candidate), parse_version(current)) {
+        (Some(candidate), Some(current)) => candidate > current,
+        _ => false,
+    }
+}
+
+/// Why `release` cannot replace this binary on data in `stored_format`, if
+/// it cannot.
+pub fn compatibility_issue(release: &Release, stored_format: u32) -> Option<String> {
+    if release.data_format < stored_format {
+        return Some(format!(
+            "{} reads data format {}, but ./data is already at format {}",
+            release.version, release.data_format, stored_format
+        ));
+    }
+    None
+}
+
+pub async fn fetch_release(config: &UpdateConfig) -> Result<Release, String> {
+    let body = hypr_claw_tools::plugins::fetch(&config.feed_url).await?;
+    serde_json::from_slice(&body).map_err(|e| format!("invalid release feed: {e}"))
+}
+
+/// The `version=` and `data_format=` fields of a release signature's trusted
+/// comment.
+pub fn signed_release(trusted_comment: &str) -> Result<(String, u32), String> {
+    let field = |key: &str| {
+        trusted_comment
+            .split_whitespace()
+            .find_map(|word| word.strip_prefix(key)?.strip_prefix('='))
+    };
+    let version =
+        field("version").ok_or("release signature names no version in its trusted comment")?;
+    let data_format = field("data_format")
+        .and_then(|format| format.parse().ok())
+        .ok_or("release signature names no data format in its trusted comment")?;
+    Ok((version.to_string(), data_format))
+}
+
+/// Why the signed `trusted_comment` does not vouch for `release` as an
+/// upgrade from `current`, if it does not.
+pub fn check_signed_release(
+    release: &Release,
+    trusted_comment: &str,
+    current: &str,
+) -> Result<(), String> {
+    let (version, data_format) = signed_release(trusted_comment)?;
+    if version != release.version || data_format != release.data_format {
+        return Err(format!(
+            "the feed announces {} with data format {}, but the signature is for {} with data format {}",
+            release.version, release.data_format, version, data_format
+        ));
+    }
+    if !is_newer(&version, current) {
+        return Err(format!(
+            "refusing {version}: it is not newer than the running {current}"
+        ));
+    }
+    Ok(())
+}
+
+/// Downloads the release binary and checks its signature, and that the
+/// signature vouches for the feed's version and data format.
+pub async fn download(config: &UpdateConfig, release: &Release) -> Result<Vec<u8>, String> {
+    use hypr_claw_tools::plugins::{self, SignaturePolicy, Verification};
+
+    if config.trusted_keys.is_empty() {
+        return Err("update.trusted_keys is empty; add the release signing key".to_string());
+    }
+    let url = plugins::resolve_url(&release.url, &config.feed_url);
+    let binary = plugins::fetch(&url).await?;
+    let policy = SignaturePolicy {
+        trusted_keys: config.trusted_keys.clone(),
+        allow_unsigned: false,
+    };
+    match plugins::verify_signature(&binary, Some(&release.signature), &policy)? {
+        Verification::Verified { .. } => {
+            let comment = plugins::trusted_comment(&release.signature)?;
+            check_signed_release(release, &comment, CURRENT_VERSION)?;
+            Ok(binary)
+        }
+        Verification::UnsignedAllowed => Err("release is unsigned".to_string()),
+    }
+}
+
+/// Replaces `exe` with `binary`, keeping the old one as `<exe>.old`. The
+/// new file is written next to `exe` first so the swap is a rename.
+pub fn install(exe: &Path, binary: &[u8]) -> io::Result<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let staged = exe.with_extension("new");
+    let previous = exe.with_extension("old");
+    std::fs::write(&staged, binary)?;
+    std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))?;
+    std::fs::rename(exe, &previous)?;
+    if let Err(e) = std::fs::rename(&staged, exe) {
+        let _ = std::fs::rename(&previous, exe);
+        return Err(e);
+    }
+    Ok(previous)
+}
+
+fn load_check_state(path: &Path) -> CheckState {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Newer version announced by the feed, asking it at most once a day.
+pub async fn check_for_update(config: &UpdateConfig, state_path: &Path) -> Option<String> {
+    let now = chrono::Utc::now().timestamp();
+    let mut state = load_check_state(state_path);
+    if now - state.checked_at >= CHECK_INTERVAL_SECS {
+        state = CheckState {
+            checked_at: now,
+            latest: fetch_release(config).await.ok().map(|r| r.version),
+        };
+        if let Ok(json) = serde_json::to_string(&state) {
+            let _ = std::fs::write(state_path, json);
+        }
+    }
+    state
+        .latest
+        .filter(|latest| is_newer(latest, CURRENT_VERSION))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(version: &str, data_format: u32) -> Release {
+        Release {
+            version: version.to_string(),
+            url: "hypr-claw".to_string(),
+            signature: String::new(),
+            data_format,
+            notes: String::new(),
+        }
+    }
+
+    #[test]
+    fn versions_compare_numerically() {
+        assert_eq!(parse_version("v1.2.3"), Some((1, 2, 3)));
+        assert_eq!(parse_version("0.10.0-rc.1"), Some((0, 10, 0)));
+        assert_eq!(parse_version("1.2"), None);
+        assert_eq!(parse_version("1.2.3.4"), None);
+        assert!(is_newer("0.10.0", "0.9.9"));
+        assert!(!is_newer("0.1.0", "0.1.0"));
+        assert!(!is_newer("garbage", "0.1.0"));
+    }
+
+    #[test]
+    fn older_data_formats_are_refused() {
+        assert!(compatibility_issue(&release("0.2.0", 1), 1).is_none());
+        assert!(compatibility_issue(&release("0.2.0", 2), 1).is_none());
+        let issue = compatibility_issue(&release("0.2.0", 1), 2).unwrap();
+        assert!(issue.contains("format 2"));
+    }
+
+    // Signed with a throwaway key over the bytes `new binary`.
+    const TEST_KEY: &str = "RWQ/COwYWi4mmMunlZK/PYVwTDDdlIxlhvZdcg9W1LWRNnjYMoBDIvx0";
+    const TEST_SIGNATURE: &str = "untrusted comment: hypr-claw release
+RUQ/COwYWi4mmAXcKaTWimwUwT6aJnEX78Q6WnlxIZCNCOTdkcz85XU38HLfplVA0s7x2WSoruDm5M4TkZnrI6dOIZN4d2id2Ak=
+trusted comment: hypr-claw version=999.0.0 data_format=3
+Pp5oyuBoygCLw26xLSh2BosL/UtbYWp0T7F8W0oa4j13ngbxbHpynQxzIAFoMQGSpCIL4B42YMhnkZvAr7WNDQ==";
+
+    #[test]
+    fn signed_version_and_format_must_match_the_feed_and_be_newer() {
+        let comment = "hypr-claw version=0.3.0 data_format=2";
+        assert_eq!(signed_release(comment).unwrap(), ("0.3.0".to_string(), 2));
+        assert!(signed_release("timestamp:1556193335\tfile:hypr-claw").is_err());
+
+        assert!(check_signed_release(&release("0.3.0", 2), comment, "0.2.0").is_ok());
+        // A feed relabelling an old binary or lying about its data format.
+        assert!(check_signed_release(&release("0.4.0", 2), comment, "0.2.0").is_err());
+        assert!(check_signed_release(&release("0.3.0", 3), comment, "0.2.0").is_err());
+        // A validly signed older release is a rollback.
+        let err = check_signed_release(&release("0.3.0", 2), comment, "0.3.0").unwrap_err();
+        assert!(err.contains("not newer"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn download_checks_the_signed_release_against_the_feed() {
+        let dir = std::env::temp_dir().join(format!("hypr-claw-feed-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("hypr-claw"), b"new binary").unwrap();
+        let config = UpdateConfig {
+            check: true,
+            feed_url: format!("{}/release.json", dir.display()),
+            trusted_keys: vec![TEST_KEY.to_string()],
+        };
+        let mut feed = release("999.0.0", 3);
+        feed.signature = TEST_SIGNATURE.to_string();
+        assert_eq!(download(&config, &feed).await.unwrap(), b"new binary");
+
+        feed.data_format = 2;
+        assert!(download(&config, &feed).await.is_err());
+        feed.data_format = 3;
+        feed.version = "999.1.0".to_string();
+        assert!(download(&config, &feed).await.is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn install_swaps_binary_and_keeps_previous() {
+        let dir = std::env::temp_dir().join(format!("hypr-claw-update-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let exe = dir.join("hypr-claw");
+        std::fs::write(&exe, "old").unwrap();
+
+        let previous = install(&exe, b"new").unwrap();
+        assert_eq!(std::fs::read_to_string(&exe).unwrap(), "new");
+        assert_eq!(std::fs::read_to_string(previous).unwrap(), "old");
+        assert!(!dir.join("hypr-claw.new").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn check_is_cached_for_a_day() {
+        let path = std::env::temp_dir().join(format!(
+            "hypr-claw-update-check-{}.json",
+            std::process::id()
+        ));
+        let cached = CheckState {
+            checked_at: chrono::Utc::now().timestamp(),
+            latest: Some("999.0.0".to_string()),
+        };
+        std::fs::write(&path, serde_json::to_string(&cached).unwrap()).unwrap();
+        // The feed URL is never fetched while the cached check is fresh.
+        let config = UpdateConfig {
+            check: true,
+            feed_url: "file:///nonexistent/release.json".to_string(),
+            trusted_keys: Vec::new(),
+        };
+        assert_eq!(
+            check_for_update(&config, &path).await,
+            Some("999.0.0".to_string())
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,186 @@
+//! Self-monitor that restarts a wedged runtime.
+//!
+//! A task on the async runtime records a heartbeat and a plain OS thread
+//! checks it. When the heartbeat is older than the timeout (the event loop
+//! stopped, or every worker is blocked), the watchdog writes a crash report,
+//! flushes registered state on a fresh runtime and restarts: under systemd
+//! it exits so the unit's `Restart=` policy takes over, otherwise it
+//! re-executes itself. Under systemd it also sends, while the heartbeat is
+//! fresh, the `WATCHDOG=1` pings `WatchdogSec` expects.
+
+use crate::config::WatchdogConfig;
+use sd_notify::NotifyState;
+use std::future::Future;
+use std::os::unix::process::CommandExt;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Time allowed for all flush hooks together before restarting anyway.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Re-executions allowed in one session before the watchdog gives up.
+const MAX_RESTARTS: u32 = 3;
+
+/// Carries the restart count across re-executions.
+const RESTARTS_ENV: &str = "HYPR_CLAW_WATCHDOG_RESTARTS";
+
+/// Saves state before a restart. Runs on a fresh runtime, so it must not
+/// depend on tasks of the wedged one.
+pub type FlushHook = Box<dyn Fn() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+pub struct Watchdog {
+    timeout: Duration,
+    systemd: bool,
+    flush: Vec<FlushHook>,
+}
+
+impl Watchdog {
+    pub fn new(config: &WatchdogConfig) -> Self {
+        let mut usec = 0;
+        let systemd_watchdog = sd_notify::watchdog_enabled(false, &mut usec).then_some(usec);
+        Self {
+            timeout: effective_timeout(config.timeout_secs, systemd_watchdog),
+            systemd: std::env::var_os("NOTIFY_SOCKET").is_some(),
+            flush: Vec::new(),
+        }
+    }
+
+    pub fn on_restart(mut self, hook: FlushHook) -> Self {
+        self.flush.push(hook);
+        self
+    }
+
+    /// Starts the heartbeat on the current runtime and the monitor thread.
+    pub fn start(self) -> std::io::Result<()> {
+        let origin = Instant::now();
+        let beat = Arc::new(AtomicU64::new(0));
+        let interval = (self.timeout / 4).max(Duration::from_millis(100));
+
+        let heartbeat = beat.clone();
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(interval);
+            loop {
+                tick.tick().await;
+                heartbeat.store(origin.elapsed().as_millis() as u64, Ordering::Relaxed);
+            }
+        });
+
+        std::thread::Builder::new()
+            .name("watchdog".to_string())
+            .spawn(move || loop {
+                std::thread::sleep(interval);
+                let last = Duration::from_millis(beat.load(Ordering::Relaxed));
+                let age = origin.elapsed().saturating_sub(last);
+                if age < self.timeout {
+                    if self.systemd {
+                        let _ = sd_notify::notify(false, &[NotifyState::Watchdog]);
+                    }
+                    continue;
+                }
+                self.restart(age);
+            })?;
+        Ok(())
+    }
+
+    fn restart(&self, age: Duration) -> ! {
+        let message = format!("watchdog: runtime unresponsive for {}s", age.as_secs());
+        eprintln!(
+            "\n⚠️  Runtime unresponsive for {}s, restarting",
+            age.as_secs()
+        );
+        match crate::crash::report_now(&message) {
+            Ok(path) => eprintln!("💥 Crash report saved to {}", path.display()),
+            Err(e) => eprintln!("⚠️  Failed to save crash report: {}", e),
+        }
+        self.flush_state();
+
+        if self.systemd {
+            let _ = sd_notify::notify(
+                false,
+                &[NotifyState::Stopping, NotifyState::Status(&message)],
+            );
+            std::process::exit(1);
+        }
+        let Some(restarts) = next_restart(std::env::var(RESTARTS_ENV).ok().as_deref()) else {
+            eprintln!(
+                "❌ Watchdog restarted {} times already, giving up",
+                MAX_RESTARTS
+            );
+            std::process::exit(1);
+        };
+        let error = match std::env::current_exe() {
+            Ok(exe) => std::process::Command::new(exe)
+                .args(std::env::args_os().skip(1))
+                .env(RESTARTS_ENV, restarts.to_string())
+                .exec(),
+            Err(e) => e,
+        };
+        eprintln!("❌ Watchdog restart failed: {}", error);
+        std::process::exit(1);
+    }
+
+    fn flush_state(&self) {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        else {
+            return;
+        };
+        let flushed = runtime.block_on(async {
+            tokio::time::timeout(FLUSH_TIMEOUT, async {
+                for hook in &self.flush {
+                    hook().await;
+                }
+            })
+            .await
+        });
+        if flushed.is_err() {
+            eprintln!("⚠️  State flush timed out; restarting without it");
+        }
+    }
+}
+
+/// The configured timeout, kept below systemd's `WatchdogSec` (given in
+/// microseconds) so the watchdog can flush state before systemd kills it.
+fn effective_timeout(timeout_secs: u64, systemd_watchdog_usec: Option<u64>) -> Duration {
+    let configured = Duration::from_secs(timeout_secs);
+    match systemd_watchdog_usec {
+        Some(usec) if usec > 0 => configured.min(Duration::from_micros(usec / 4 * 3)),
+        _ => configured,
+    }
+}
+
+/// Restart count for the next re-execution, or `None` past [`MAX_RESTARTS`].
+fn next_restart(previous: Option<&str>) -> Option<u32> {
+    let previous = previous.and_then(|v| v.parse::<u32>().ok()).unwrap_or(0);
+    (previous < MAX_RESTARTS).then_some(previous + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeout_stays_below_systemd_watchdog() {
+        assert_eq!(effective_timeout(90, None), Duration::from_secs(90));
+        assert_eq!(effective_timeout(90, Some(0)), Duration::from_secs(90));
+        assert_eq!(
+            effective_timeout(90, Some(60_000_000)),
+            Duration::from_secs(45)
+        );
+        assert_eq!(
+            effective_timeout(30, Some(600_000_000)),
+            Duration::from_secs(30)
+        );
+    }
+
+    #[test]
+    fn restarts_are_capped() {
+        assert_eq!(next_restart(None), Some(1));
+        assert_eq!(next_restart(Some("garbage")), Some(1));
+        assert_eq!(next_restart(Some("2")), Some(3));
+        assert_eq!(next_restart(Some("3")), None);
+    }
+}
@@ -77,6 +77,23 @@ fn test_config_save_and_load() {
     let config = hypr_claw_app::config::Config {
         provider: hypr_claw_app::config::LLMProvider::Nvidia,
         model: "test-model".to_string(),
+        sandbox: Default::default(),
+        backup: Default::default(),
+        plugins: Default::default(),
+        response: Default::default(),
+        verification: Default::default(),
+        analytics: Default::default(),
+        audit: Default::default(),
+        annealing: Default::default(),
+        watchdog: Default::default(),
+        update: Default::default(),
+        context: Default::default(),
+        pricing: Default::default(),
+        concurrency: Default::default(),
+        prefetch: Default::default(),
+        retention: Default::default(),
+        ocr: Default::default(),
+        thermal: Default::default(),
     };
 
     let yaml = serde_yaml::to_string(&config).unwrap();
@@ -99,12 +116,46 @@ fn test_config_validation() {
     let valid_config = hypr_claw_app::config::Config {
         provider: hypr_claw_app::config::LLMProvider::Nvidia,
         model: "test-model".to_string(),
+        sandbox: Default::default(),
+        backup: Default::default(),
+        plugins: Default::default(),
+        response: Default::default(),
+        verification: Default::default(),
+        analytics: Default::default(),
+        audit: Default::default(),
+        annealing: Default::default(),
+        watchdog: Default::default(),
+        update: Default::default(),
+        context: Default::default(),
+        pricing: Default::default(),
+        concurrency: Default::default(),
+        prefetch: Default::default(),
+        retention: Default::default(),
+        ocr: Default::default(),
+        thermal: Default::default(),
     };
     assert!(valid_config.validate().is_ok());
 
     let invalid_config = hypr_claw_app::config::Config {
         provider: hypr_claw_app::config::LLMProvider::Nvidia,
         model: "".to_string(),
+        sandbox: Default::default(),
+        backup: Default::default(),
+        plugins: Default::default(),
+        response: Default::default(),
+        verification: Default::default(),
+        analytics: Default::default(),
+        audit: Default::default(),
+        annealing: Default::default(),
+        watchdog: Default::default(),
+        update: Default::default(),
+        context: Default::default(),
+        pricing: Default::default(),
+        concurrency: Default::default(),
+        prefetch: Default::default(),
+        retention: Default::default(),
+        ocr: Default::default(),
+        thermal: Default::default(),
     };
     assert!(invalid_config.validate().is_err());
 
@@ -113,6 +164,399 @@ fn test_config_validation() {
             base_url: "".to_string(),
         },
         model: "test".to_string(),
+        sandbox: Default::default(),
+        backup: Default::default(),
+        plugins: Default::default(),
+        response: Default::default(),
+        verification: Default::default(),
+        analytics: Default::default(),
+        audit: Default::default(),
+        annealing: Default::default(),
+        watchdog: Default::default(),
+        update: Default::default(),
+        context: Default::default(),
+        pricing: Default::default(),
+        concurrency: Default::default(),
+        prefetch: Default::default(),
+        retention: Default::default(),
+        ocr: Default::default(),
+        thermal: Default::default(),
     };
     assert!(invalid_local.validate().is_err());
 }
+
+#[test]
+fn test_config_sandbox_section_defaults_and_parses() {
+    let legacy: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\n").unwrap();
+    assert_eq!(legacy.sandbox.env.allow, vec!["*".to_string()]);
+    assert!(legacy.sandbox.env.redact_secrets);
+
+    let custom: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\nsandbox:\n  env:\n    allow: [\"XDG_*\", HOME]\n    deny: [XDG_RUNTIME_DIR]\n",
+    )
+    .unwrap();
+    assert!(custom.sandbox.env.is_exposed("HOME"));
+    assert!(!custom.sandbox.env.is_exposed("XDG_RUNTIME_DIR"));
+    assert!(!custom.sandbox.env.is_exposed("PATH"));
+}
+
+#[test]
+fn test_config_backup_section_defaults_and_validates() {
+    let legacy: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\n").unwrap();
+    assert_eq!(legacy.backup.dir, "./backups");
+    assert_eq!(legacy.backup.interval_hours, 0);
+    assert_eq!(legacy.backup.keep, 7);
+
+    let custom: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\nbackup:\n  interval_hours: 24\n  keep: 0\n",
+    )
+    .unwrap();
+    assert_eq!(custom.backup.interval_hours, 24);
+    assert!(custom.validate().is_err());
+}
+
+#[test]
+fn test_config_plugins_section_parses_signature_policy() {
+    let legacy: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\n").unwrap();
+    assert!(legacy.plugins.index_url.is_empty());
+    assert!(!legacy.plugins.signatures.allow_unsigned);
+
+    let custom: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\nplugins:\n  index_url: https://example.com/index.json\n  trusted_keys: [RWQabc]\n  allow_unsigned: true\n",
+    )
+    .unwrap();
+    assert_eq!(custom.plugins.index_url, "https://example.com/index.json");
+    assert_eq!(
+        custom.plugins.signatures.trusted_keys,
+        vec!["RWQabc".to_string()]
+    );
+    assert!(custom.plugins.signatures.allow_unsigned);
+}
+
+#[test]
+fn test_config_response_citations_default_off() {
+    let legacy: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\n").unwrap();
+    assert!(!legacy.response.require_citations);
+
+    let custom: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\nresponse:\n  require_citations: true\n")
+            .unwrap();
+    assert!(custom.response.require_citations);
+}
+
+#[test]
+fn test_config_verification_defaults_on_for_both_modes() {
+    let legacy: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\n").unwrap();
+    assert!(legacy.verification.prompt_first);
+    assert!(legacy.verification.guarded);
+
+    let custom: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\nverification:\n  prompt_first: false\n")
+            .unwrap();
+    assert!(!custom.verification.prompt_first);
+    assert!(custom.verification.guarded);
+}
+
+#[test]
+fn test_config_validation_lists_typed_issues() {
+    use hypr_claw_app::config::ConfigIssueKind;
+
+    let config: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: !local\n  base_url: localhost:8080\nmodel: ''\nbackup:\n  keep: 0\n",
+    )
+    .unwrap();
+    let issues = config.validate().unwrap_err();
+    let kinds: Vec<_> = issues.iter().map(|issue| issue.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ConfigIssueKind::EmptyModel,
+            ConfigIssueKind::InvalidValue,
+            ConfigIssueKind::InvalidValue
+        ]
+    );
+    assert_eq!(issues[1].field, "provider.local.base_url");
+    assert!(issues[0].is_fixable());
+    assert!(!issues[2].is_fixable());
+    assert!(issues[2].to_string().starts_with("backup.keep: "));
+}
+
+#[test]
+fn test_config_check_model_against_provider_list() {
+    let config: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: retired-model\n").unwrap();
+    let listed = vec!["z-ai/glm4.7".to_string()];
+    let issue = config.check_model(&listed).unwrap();
+    assert_eq!(
+        issue.kind,
+        hypr_claw_app::config::ConfigIssueKind::UnknownModel
+    );
+    assert!(issue.problem.contains("retired-model"));
+    // Nothing to compare against when the provider could not be asked.
+    assert!(config.check_model(&[]).is_none());
+}
+
+#[test]
+fn test_config_response_guardrails() {
+    let config: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\nresponse:\n  stop_sequences: ['</final>']\n  max_narration_tokens: 512\n",
+    )
+    .unwrap();
+    let guardrails = config.response.output_guardrails();
+    assert_eq!(guardrails.stop_sequences, vec!["</final>"]);
+    assert_eq!(guardrails.max_narration_tokens, 512);
+    assert!(config.validate().is_ok());
+
+    let tiny: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\nresponse:\n  max_narration_tokens: 16\n")
+            .unwrap();
+    assert_eq!(
+        tiny.validate().unwrap_err()[0].field,
+        "response.max_narration_tokens"
+    );
+}
+
+#[test]
+fn test_config_sandbox_exec_rules() {
+    let legacy: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\n").unwrap();
+    assert!(legacy.sandbox.exec.compile().unwrap().is_empty());
+
+    let config: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\nsandbox:\n  exec:\n    deny: ['\\bsudo\\b', '^dd\\b', 'pacman\\s+-R']\n",
+    )
+    .unwrap();
+    assert!(config.validate().is_ok());
+    let rules = config.sandbox.exec.compile().unwrap();
+    assert!(rules.check("sudo pacman -Syu").is_some());
+    assert!(rules.check("pacman -Rns vlc").is_some());
+    assert!(rules.check("kitty").is_none());
+
+    let broken: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\nsandbox:\n  exec:\n    allow: ['(kitty']\n",
+    )
+    .unwrap();
+    assert_eq!(broken.validate().unwrap_err()[0].field, "sandbox.exec");
+}
+
+#[test]
+fn test_config_sandbox_isolation() {
+    let legacy: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\n").unwrap();
+    assert!(!legacy.sandbox.isolation.is_enabled());
+
+    let config: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\nsandbox:\n  isolation:\n    mode: bwrap\n    writable: ['~/Downloads']\n",
+    )
+    .unwrap();
+    assert!(config.validate().is_ok());
+    assert!(config.sandbox.isolation.is_enabled());
+    assert!(!config.sandbox.isolation.network);
+
+    let relative: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\nsandbox:\n  isolation:\n    mode: bwrap\n    writable: ['out']\n",
+    )
+    .unwrap();
+    assert_eq!(
+        relative.validate().unwrap_err()[0].field,
+        "sandbox.isolation"
+    );
+}
+
+#[test]
+fn test_config_sandbox_hardening() {
+    let legacy: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\n").unwrap();
+    assert!(!legacy.sandbox.hardening.enabled);
+
+    let config: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\nsandbox:\n  hardening:\n    enabled: true\n    read: ['~/Documents']\n",
+    )
+    .unwrap();
+    assert!(config.validate().is_ok());
+    assert!(config.sandbox.hardening.seccomp);
+
+    let both: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\nsandbox:\n  hardening:\n    enabled: true\n  isolation:\n    mode: bwrap\n",
+    )
+    .unwrap();
+    assert_eq!(both.validate().unwrap_err()[0].field, "sandbox.hardening");
+}
+
+#[test]
+fn test_config_watchdog() {
+    let legacy: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\n").unwrap();
+    assert!(legacy.watchdog.enabled);
+    assert_eq!(legacy.watchdog.timeout_secs, 90);
+
+    let short: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\nwatchdog:\n  timeout_secs: 2\n").unwrap();
+    assert!(short.watchdog.enabled);
+    assert_eq!(
+        short.validate().unwrap_err()[0].field,
+        "watchdog.timeout_secs"
+    );
+}
+
+#[test]
+fn test_config_update() {
+    let legacy: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\n").unwrap();
+    assert!(!legacy.update.check);
+    assert!(legacy.update.feed_url.starts_with("https://"));
+
+    let bad: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\nupdate:\n  check: true\n  feed_url: releases.json\n",
+    )
+    .unwrap();
+    assert!(bad.update.check);
+    assert_eq!(bad.validate().unwrap_err()[0].field, "update.feed_url");
+}
+
+#[test]
+fn test_config_context_providers() {
+    let config: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\ncontext:\n  providers:\n    battery: { enabled: false }\n    facts: { max_tokens: 50 }\n",
+    )
+    .unwrap();
+    assert!(!config.context.providers["battery"].enabled);
+    assert!(config.context.providers["facts"].enabled);
+    assert!(config.validate().is_ok());
+
+    let bad: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\ncontext:\n  providers:\n    weather: {}\n",
+    )
+    .unwrap();
+    assert_eq!(
+        bad.validate().unwrap_err()[0].field,
+        "context.providers.weather"
+    );
+}
+
+#[test]
+fn test_config_pricing() {
+    let config: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\npricing:\n  m: { input_per_mtok: 0.5 }\n",
+    )
+    .unwrap();
+    assert_eq!(config.pricing["m"].input_per_mtok, 0.5);
+    assert!(config.validate().is_ok());
+
+    let bad: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\npricing:\n  m: { input_per_mtok: -1 }\n")
+            .unwrap();
+    assert_eq!(
+        bad.validate().unwrap_err()[0].field,
+        "pricing.m.input_per_mtok"
+    );
+}
+
+#[test]
+fn test_config_concurrency() {
+    let config: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\nconcurrency:\n  nvidia: { max_concurrent: 2 }\n",
+    )
+    .unwrap();
+    assert_eq!(config.concurrency["nvidia"].max_concurrent, 2);
+    assert!(config.validate().is_ok());
+
+    let bad: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\nconcurrency:\n  nvidia: { max_concurrent: 0 }\n  codex: { max_concurrent: 1 }\n",
+    )
+    .unwrap();
+    let fields = bad
+        .validate()
+        .unwrap_err()
+        .into_iter()
+        .map(|issue| issue.field)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        fields,
+        vec!["concurrency.codex", "concurrency.nvidia.max_concurrent"]
+    );
+}
+
+#[test]
+fn test_config_prefetch_is_opt_in() {
+    let config: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\n").unwrap();
+    assert!(!config.prefetch.enabled);
+    let config: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\nprefetch:\n  enabled: true\n").unwrap();
+    assert!(config.prefetch.enabled);
+}
+
+#[test]
+fn test_config_retention_limits_screenshots_by_default() {
+    let config: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\nretention:\n  artifacts:\n    max_age_days: 30\n",
+    )
+    .unwrap();
+    assert_eq!(config.retention.screenshots.max_total_mb, 500);
+    assert_eq!(config.retention.screenshots.max_age_days, 3);
+    assert_eq!(config.retention.screenshots.sensitive_max_age_minutes, 60);
+    assert_eq!(config.retention.artifacts.max_age_days, 30);
+    assert_eq!(config.retention.artifacts.max_total_mb, 0);
+    assert_eq!(config.retention.interval_minutes, 60);
+}
+
+#[test]
+fn test_config_ocr_language_and_tuning() {
+    let config: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\n").unwrap();
+    assert_eq!(config.ocr.language, None);
+    let config: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\nocr:\n  language: jpn+eng\n  tuning:\n    jpn:\n      psm: 6\n      min_confidence: 15\n",
+    )
+    .unwrap();
+    assert_eq!(config.ocr.language.as_deref(), Some("jpn+eng"));
+    assert_eq!(config.ocr.tuning["jpn"].psm, Some(6));
+    assert_eq!(config.ocr.tuning["jpn"].min_confidence, Some(15.0));
+}
+
+#[test]
+fn test_config_thermal_rules() {
+    let config: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\n").unwrap();
+    assert_eq!(config.thermal.rules.len(), 1);
+    assert_eq!(
+        config.thermal.rules[0].action,
+        hypr_claw_app::config::ThermalAction::Alert
+    );
+    let mut config: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\nthermal:\n  rules:\n    - { sensor: k10temp, above: 95, action: throttle }\n",
+    )
+    .unwrap();
+    assert_eq!(config.thermal.interval_secs, 30);
+    assert_eq!(config.thermal.rules[0].sensor.as_deref(), Some("k10temp"));
+    assert_eq!(
+        config.thermal.rules[0].action,
+        hypr_claw_app::config::ThermalAction::Throttle
+    );
+    assert!(config.validate().is_ok());
+    config.thermal.rules[0].above = f32::NAN;
+    assert!(config
+        .validate()
+        .unwrap_err()
+        .iter()
+        .any(|issue| issue.field == "thermal.rules[0].above"));
+}
+
+#[test]
+fn test_config_context_refresh_after() {
+    let config: hypr_claw_app::config::Config =
+        serde_yaml::from_str("provider: nvidia\nmodel: m\n").unwrap();
+    assert_eq!(config.context.refresh_after_secs, 120);
+    let config: hypr_claw_app::config::Config = serde_yaml::from_str(
+        "provider: nvidia\nmodel: m\ncontext:\n  refresh_after_secs: 0\n  providers:\n    battery: { enabled: false }\n",
+    )
+    .unwrap();
+    assert_eq!(config.context.refresh_after_secs, 0);
+    assert!(!config.context.providers["battery"].enabled);
+}
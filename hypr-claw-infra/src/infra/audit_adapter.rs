@@ -1,12 +1,13 @@
-use crate::infra::audit_logger::AuditLogger;
+use crate::infra::audit_logger::{AuditLogger, AuditLoggerError};
 use crate::infra::contracts::AuditEntry;
 use async_trait::async_trait;
 use hypr_claw_tools::AuditLogger as AuditLoggerTrait;
 use std::collections::HashMap;
 
-#[async_trait]
-impl AuditLoggerTrait for AuditLogger {
-    async fn log(&self, entry: serde_json::Value) {
+impl AuditLogger {
+    /// Writes one tool dispatcher audit entry (`timestamp`, `session`,
+    /// `tool`, `input`, `result`).
+    pub fn log_tool_call(&self, entry: &serde_json::Value) -> Result<(), AuditLoggerError> {
         // Convert JSON to AuditEntry
         let input_map: HashMap<String, serde_json::Value> = entry
             .get("input")
@@ -41,6 +42,13 @@ impl AuditLoggerTrait for AuditLogger {
             approval: crate::infra::contracts::PermissionDecision::ALLOW,
         };
 
-        let _ = self.log(&audit_entry);
+        self.log(&audit_entry)
+    }
+}
+
+#[async_trait]
+impl AuditLoggerTrait for AuditLogger {
+    async fn log(&self, entry: serde_json::Value) {
+        let _ = self.log_tool_call(&entry);
     }
 }
@@ -1,7 +1,17 @@
+//! Append-only audit log with rotation and a segment index.
+//!
+//! Entries go to the active file (e.g. `audit.log`). Once it passes the
+//! policy's size or age limit it becomes a numbered segment
+//! (`audit.log.000001.gz`), and `audit.log.index.json` records the time
+//! range each segment covers so range queries only open the segments that
+//! can match.
+
 use crate::infra::contracts::AuditEntry;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 use std::fs::{File, OpenOptions};
-use std::io::Write;
+use std::io::{BufRead, BufReader, Read, Write};
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
@@ -13,36 +23,318 @@ pub enum AuditLoggerError {
     Json(#[from] serde_json::Error),
 }
 
+/// When the active file is rotated and how long segments are kept. A zero
+/// limit disables that rule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RotationPolicy {
+    /// Rotate before the active file would grow past this many bytes.
+    pub max_bytes: u64,
+    /// Rotate once the oldest entry in the active file is this many hours old.
+    pub max_age_hours: u64,
+    /// Gzip rotated segments.
+    pub compress: bool,
+    /// Segments kept; the oldest beyond this are deleted.
+    pub keep_segments: usize,
+    /// Delete segments whose newest entry is older than this many days.
+    pub retention_days: u64,
+}
+
+impl Default for RotationPolicy {
+    fn default() -> Self {
+        Self {
+            max_bytes: 8 * 1024 * 1024,
+            max_age_hours: 24 * 7,
+            compress: true,
+            keep_segments: 52,
+            retention_days: 365,
+        }
+    }
+}
+
+/// A rotated part of the log and the entries it holds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Segment {
+    pub file: String,
+    pub seq: u64,
+    /// Unix seconds of the first and last entry.
+    pub first: i64,
+    pub last: i64,
+    pub entries: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SegmentIndex {
+    segments: Vec<Segment>,
+}
+
+/// Filter for [`AuditLogger::query`]. Bounds are unix seconds, inclusive.
+#[derive(Debug, Clone, Default)]
+pub struct AuditQuery {
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub tool: Option<String>,
+    pub session: Option<String>,
+    /// Keep only the most recent matches.
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    fn overlaps(&self, first: i64, last: i64) -> bool {
+        self.since.is_none_or(|since| last >= since)
+            && self.until.is_none_or(|until| first <= until)
+    }
+
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        let at = entry_time(entry);
+        self.since.is_none_or(|since| at >= since)
+            && self.until.is_none_or(|until| at <= until)
+            && self.tool.as_ref().is_none_or(|tool| &entry.tool == tool)
+            && self
+                .session
+                .as_ref()
+                .is_none_or(|session| &entry.session == session)
+    }
+}
+
+struct ActiveFile {
+    file: File,
+    bytes: u64,
+    first: Option<i64>,
+    last: Option<i64>,
+    entries: u64,
+}
+
+struct State {
+    active: ActiveFile,
+    index: SegmentIndex,
+}
+
 pub struct AuditLogger {
-    #[allow(dead_code)]
     log_path: PathBuf,
-    file: Mutex<File>,
+    policy: RotationPolicy,
+    state: Mutex<State>,
+}
+
+fn entry_time(entry: &AuditEntry) -> i64 {
+    chrono::DateTime::parse_from_rfc3339(&entry.timestamp)
+        .map(|at| at.timestamp())
+        .unwrap_or_else(|_| chrono::Utc::now().timestamp())
 }
 
 impl AuditLogger {
     pub fn new<P: AsRef<Path>>(log_path: P) -> Result<Self, AuditLoggerError> {
+        Self::with_rotation(log_path, RotationPolicy::default())
+    }
+
+    pub fn with_rotation<P: AsRef<Path>>(
+        log_path: P,
+        policy: RotationPolicy,
+    ) -> Result<Self, AuditLoggerError> {
         let log_path = log_path.as_ref().to_path_buf();
 
         if let Some(parent) = log_path.parent() {
             std::fs::create_dir_all(parent)?;
         }
 
-        let file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&log_path)?;
+        let index = match std::fs::read_to_string(index_path(&log_path)) {
+            Ok(raw) => serde_json::from_str(&raw)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => SegmentIndex::default(),
+            Err(e) => return Err(e.into()),
+        };
+        let active = open_active(&log_path)?;
 
         Ok(Self {
             log_path,
-            file: Mutex::new(file),
+            policy,
+            state: Mutex::new(State { active, index }),
         })
     }
 
     pub fn log(&self, entry: &AuditEntry) -> Result<(), AuditLoggerError> {
         let json = serde_json::to_string(entry)?;
-        let mut file = self.file.lock();
-        writeln!(file, "{}", json)?;
-        file.sync_all()?;
+        let at = entry_time(entry);
+        let mut state = self.state.lock();
+        if self.should_rotate(&state.active, json.len() as u64 + 1, at) {
+            self.rotate(&mut state)?;
+        }
+        let active = &mut state.active;
+        writeln!(active.file, "{}", json)?;
+        active.file.sync_all()?;
+        active.bytes += json.len() as u64 + 1;
+        active.first.get_or_insert(at);
+        active.last = Some(at);
+        active.entries += 1;
         Ok(())
     }
+
+    /// Rotated segments, oldest first.
+    pub fn segments(&self) -> Vec<Segment> {
+        self.state.lock().index.segments.clone()
+    }
+
+    /// Entries matching `query`, oldest first. Segments outside the time
+    /// range are not opened.
+    pub fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>, AuditLoggerError> {
+        let (segments, active_range) = {
+            let state = self.state.lock();
+            let active = &state.active;
+            (state.index.segments.clone(), active.first.zip(active.last))
+        };
+        let dir = self.dir();
+
+        // Newest source first, so a limited query can stop early.
+        let mut found: Vec<AuditEntry> = Vec::new();
+        if active_range.is_some_and(|(first, last)| query.overlaps(first, last)) {
+            let mut entries = read_entries(&self.log_path, query)?;
+            entries.append(&mut found);
+            found = entries;
+        }
+        for segment in segments.iter().rev() {
+            if query.limit.is_some_and(|limit| found.len() >= limit) {
+                break;
+            }
+            if !query.overlaps(segment.first, segment.last) {
+                continue;
+            }
+            let mut entries = read_entries(&dir.join(&segment.file), query)?;
+            entries.append(&mut found);
+            found = entries;
+        }
+        if let Some(limit) = query.limit {
+            found.drain(..found.len().saturating_sub(limit));
+        }
+        Ok(found)
+    }
+
+    fn dir(&self) -> PathBuf {
+        self.log_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    }
+
+    fn should_rotate(&self, active: &ActiveFile, incoming: u64, at: i64) -> bool {
+        if active.entries == 0 {
+            return false;
+        }
+        let too_big = self.policy.max_bytes > 0 && active.bytes + incoming > self.policy.max_bytes;
+        let too_old = self.policy.max_age_hours > 0
+            && active
+                .first
+                .is_some_and(|first| at - first >= self.policy.max_age_hours as i64 * 3600);
+        too_big || too_old
+    }
+
+    fn rotate(&self, state: &mut State) -> Result<(), AuditLoggerError> {
+        let seq = state.index.segments.last().map_or(1, |s| s.seq + 1);
+        let name = self
+            .log_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "audit.log".to_string());
+        let plain = format!("{name}.{seq:06}");
+        let dir = self.dir();
+        std::fs::rename(&self.log_path, dir.join(&plain))?;
+
+        let file = if self.policy.compress {
+            let compressed = format!("{plain}.gz");
+            let mut encoder =
+                GzEncoder::new(File::create(dir.join(&compressed))?, Compression::default());
+            std::io::copy(&mut File::open(dir.join(&plain))?, &mut encoder)?;
+            encoder.finish()?.sync_all()?;
+            std::fs::remove_file(dir.join(&plain))?;
+            compressed
+        } else {
+            plain
+        };
+
+        let active = std::mem::replace(&mut state.active, open_active(&self.log_path)?);
+        state.index.segments.push(Segment {
+            file,
+            seq,
+            first: active.first.unwrap_or_default(),
+            last: active.last.unwrap_or_default(),
+            entries: active.entries,
+        });
+        self.apply_retention(&mut state.index);
+        let tmp = index_path(&self.log_path).with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&state.index)?)?;
+        std::fs::rename(&tmp, index_path(&self.log_path))?;
+        Ok(())
+    }
+
+    fn apply_retention(&self, index: &mut SegmentIndex) {
+        let cutoff = (self.policy.retention_days > 0)
+            .then(|| chrono::Utc::now().timestamp() - self.policy.retention_days as i64 * 86_400);
+        let excess = if self.policy.keep_segments > 0 {
+            index
+                .segments
+                .len()
+                .saturating_sub(self.policy.keep_segments)
+        } else {
+            0
+        };
+        let dir = self.dir();
+        let mut position = 0;
+        index.segments.retain(|segment| {
+            position += 1;
+            let expired = position <= excess || cutoff.is_some_and(|cutoff| segment.last < cutoff);
+            if expired {
+                // A segment that is already gone needs no cleanup.
+                let _ = std::fs::remove_file(dir.join(&segment.file));
+            }
+            !expired
+        });
+    }
+}
+
+fn index_path(log_path: &Path) -> PathBuf {
+    let mut name = log_path.as_os_str().to_owned();
+    name.push(".index.json");
+    PathBuf::from(name)
+}
+
+fn open_active(log_path: &Path) -> Result<ActiveFile, AuditLoggerError> {
+    let mut active = ActiveFile {
+        file: OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_path)?,
+        bytes: 0,
+        first: None,
+        last: None,
+        entries: 0,
+    };
+    for line in BufReader::new(File::open(log_path)?).lines() {
+        let line = line?;
+        active.bytes += line.len() as u64 + 1;
+        if let Ok(entry) = serde_json::from_str::<AuditEntry>(&line) {
+            let at = entry_time(&entry);
+            active.first.get_or_insert(at);
+            active.last = Some(at);
+        }
+        active.entries += 1;
+    }
+    Ok(active)
+}
+
+fn read_entries(path: &Path, query: &AuditQuery) -> Result<Vec<AuditEntry>, AuditLoggerError> {
+    let file = File::open(path)?;
+    let reader: Box<dyn Read> = if path.extension().is_some_and(|ext| ext == "gz") {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    let mut entries = Vec::new();
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        // Torn or foreign lines are skipped rather than failing the query.
+        if let Ok(entry) = serde_json::from_str::<AuditEntry>(&line) {
+            if query.matches(&entry) {
+                entries.push(entry);
+            }
+        }
+    }
+    Ok(entries)
 }
@@ -0,0 +1,482 @@
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+use zeroize::Zeroize;
+
+const MAGIC: &[u8; 7] = b"HCBAK1\n";
+const FORMAT_VERSION: u32 = 1;
+const MANIFEST_NAME: &str = "MANIFEST.json";
+const EXTENSION: &str = "hcbak";
+
+/// Entries under the data directory that make up a backup. The master key is
+/// never archived: credentials are kept as encrypted references only.
+pub const DEFAULT_ENTRIES: &[&str] = &[
+    "config.yaml",
+    "credentials",
+    "sessions",
+    "capabilities",
+    "agents",
+    "context",
+    "tasks",
+    "reminders.json",
+    "contacts.json",
+];
+const EXCLUDED_NAMES: &[&str] = &[".master_key"];
+
+#[derive(Error, Debug)]
+pub enum BackupError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Not a hypr-claw backup: {0}")]
+    Format(String),
+
+    #[error("Unsupported backup format version {0}")]
+    Version(u32),
+
+    #[error("Decryption failed (wrong key or passphrase, or the archive was modified)")]
+    Decryption,
+
+    #[error("Encryption error")]
+    Encryption,
+
+    #[error("Integrity check failed: {0}")]
+    Integrity(String),
+}
+
+/// How the archive key is obtained.
+pub enum BackupKey {
+    /// The local master key; only restorable on a machine that still has it.
+    MasterKey([u8; 32]),
+    /// A passphrase stretched with Argon2id; portable across machines.
+    Passphrase(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupHeader {
+    pub version: u32,
+    pub created_at: String,
+    pub kdf: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+    pub nonce: String,
+    /// SHA-256 of the compressed archive before encryption.
+    pub archive_sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub version: u32,
+    pub created_at: String,
+    /// Relative path (forward slashes) -> size and hash.
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BackupSummary {
+    pub path: PathBuf,
+    pub header: BackupHeader,
+    pub bytes: u64,
+}
+
+fn derive_key(key: &BackupKey, salt: Option<&[u8]>) -> Result<[u8; 32], BackupError> {
+    match key {
+        BackupKey::MasterKey(bytes) => Ok(*bytes),
+        BackupKey::Passphrase(passphrase) => {
+            let salt = salt.ok_or_else(|| BackupError::Format("missing salt".to_string()))?;
+            let mut out = [0u8; 32];
+            Argon2::default()
+                .hash_password_into(passphrase.as_bytes(), salt, &mut out)
+                .map_err(|_| BackupError::Encryption)?;
+            Ok(out)
+        }
+    }
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn relative_name(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn collect_files(data_dir: &Path, rel: &Path, out: &mut Vec<PathBuf>) -> Result<(), BackupError> {
+    let full = data_dir.join(rel);
+    let meta = match fs::symlink_metadata(&full) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let name = rel
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if EXCLUDED_NAMES.contains(&name.as_str()) || meta.file_type().is_symlink() {
+        return Ok(());
+    }
+    if meta.is_dir() {
+        let mut children: Vec<_> = fs::read_dir(&full)?.collect::<Result<_, _>>()?;
+        children.sort_by_key(|e| e.file_name());
+        for child in children {
+            collect_files(data_dir, &rel.join(child.file_name()), out)?;
+        }
+    } else if meta.is_file() {
+        out.push(rel.to_path_buf());
+    }
+    Ok(())
+}
+
+fn build_archive(
+    data_dir: &Path,
+    entries: &[&str],
+    created_at: &str,
+) -> Result<Vec<u8>, BackupError> {
+    let mut files = Vec::new();
+    for entry in entries {
+        collect_files(data_dir, Path::new(entry), &mut files)?;
+    }
+
+    let mut manifest = BackupManifest {
+        version: FORMAT_VERSION,
+        created_at: created_at.to_string(),
+        files: BTreeMap::new(),
+    };
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for rel in &files {
+        let content = fs::read(data_dir.join(rel))?;
+        let name = relative_name(rel);
+        manifest.files.insert(
+            name.clone(),
+            ManifestEntry {
+                size: content.len() as u64,
+                sha256: sha256_hex(&content),
+            },
+        );
+        append_bytes(&mut builder, &name, &content)?;
+    }
+    let manifest_json =
+        serde_json::to_vec_pretty(&manifest).map_err(|e| BackupError::Format(e.to_string()))?;
+    append_bytes(&mut builder, MANIFEST_NAME, &manifest_json)?;
+    Ok(builder.into_inner()?.finish()?)
+}
+
+fn append_bytes<W: Write>(
+    builder: &mut tar::Builder<W>,
+    name: &str,
+    content: &[u8],
+) -> Result<(), BackupError> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o600);
+    header.set_cksum();
+    builder.append_data(&mut header, name, content)?;
+    Ok(())
+}
+
+/// Create an encrypted, timestamped archive of `entries` under `data_dir`.
+pub fn create_backup(
+    data_dir: &Path,
+    backup_dir: &Path,
+    key: &BackupKey,
+    entries: &[&str],
+) -> Result<BackupSummary, BackupError> {
+    let now = chrono::Utc::now();
+    let created_at = now.to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    let archive = build_archive(data_dir, entries, &created_at)?;
+
+    let mut nonce_bytes = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut nonce_bytes);
+    let (kdf, salt) = match key {
+        BackupKey::MasterKey(_) => ("master-key", None),
+        BackupKey::Passphrase(_) => {
+            let mut salt = [0u8; 16];
+            rand::thread_rng().fill_bytes(&mut salt);
+            ("argon2id", Some(salt))
+        }
+    };
+    let header = BackupHeader {
+        version: FORMAT_VERSION,
+        created_at,
+        kdf: kdf.to_string(),
+        salt: salt.map(|s| BASE64.encode(s)),
+        nonce: BASE64.encode(nonce_bytes),
+        archive_sha256: sha256_hex(&archive),
+    };
+    let header_json =
+        serde_json::to_vec(&header).map_err(|e| BackupError::Format(e.to_string()))?;
+
+    let mut key_bytes = derive_key(key, salt.as_ref().map(|s| s.as_slice()))?;
+    let cipher = Aes256Gcm::new((&key_bytes).into());
+    key_bytes.zeroize();
+    // The header is authenticated so its hash and KDF settings cannot be swapped.
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce_bytes),
+            Payload {
+                msg: &archive,
+                aad: &header_json,
+            },
+        )
+        .map_err(|_| BackupError::Encryption)?;
+
+    fs::create_dir_all(backup_dir)?;
+    let path = backup_dir.join(format!(
+        "hypr-claw-{}.{}",
+        now.format("%Y%m%d-%H%M%S%3f"),
+        EXTENSION
+    ));
+    let tmp = path.with_extension("tmp");
+    {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(MAGIC)?;
+        file.write_all(&(header_json.len() as u32).to_le_bytes())?;
+        file.write_all(&header_json)?;
+        file.write_all(&ciphertext)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, &path)?;
+    let bytes = fs::metadata(&path)?.len();
+    Ok(BackupSummary {
+        path,
+        header,
+        bytes,
+    })
+}
+
+fn read_container(path: &Path) -> Result<(BackupHeader, Vec<u8>, Vec<u8>), BackupError> {
+    let data = fs::read(path)?;
+    if data.len() < MAGIC.len() + 4 || &data[..MAGIC.len()] != MAGIC {
+        return Err(BackupError::Format(path.display().to_string()));
+    }
+    let len_start = MAGIC.len();
+    let header_len = u32::from_le_bytes(
+        data[len_start..len_start + 4]
+            .try_into()
+            .unwrap_or_default(),
+    ) as usize;
+    let header_start = len_start + 4;
+    if data.len() < header_start + header_len {
+        return Err(BackupError::Format("truncated header".to_string()));
+    }
+    let header_json = data[header_start..header_start + header_len].to_vec();
+    let header: BackupHeader =
+        serde_json::from_slice(&header_json).map_err(|e| BackupError::Format(e.to_string()))?;
+    if header.version != FORMAT_VERSION {
+        return Err(BackupError::Version(header.version));
+    }
+    Ok((
+        header,
+        header_json,
+        data[header_start + header_len..].to_vec(),
+    ))
+}
+
+/// Read only the unencrypted header of a backup file.
+pub fn read_header(path: &Path) -> Result<BackupHeader, BackupError> {
+    read_container(path).map(|(header, _, _)| header)
+}
+
+fn decrypt_archive(path: &Path, key: &BackupKey) -> Result<(BackupHeader, Vec<u8>), BackupError> {
+    let (header, header_json, ciphertext) = read_container(path)?;
+    match (key, header.kdf.as_str()) {
+        (BackupKey::MasterKey(_), "master-key") | (BackupKey::Passphrase(_), "argon2id") => {}
+        (_, kdf) => {
+            return Err(BackupError::Format(format!(
+                "backup was encrypted with '{kdf}'; supply the matching key"
+            )))
+        }
+    }
+    let decode = |value: &str| {
+        BASE64
+            .decode(value)
+            .map_err(|e| BackupError::Format(e.to_string()))
+    };
+    let salt = header.salt.as_deref().map(decode).transpose()?;
+    let nonce = decode(&header.nonce)?;
+    if nonce.len() != 12 {
+        return Err(BackupError::Format("invalid nonce".to_string()));
+    }
+
+    let mut key_bytes = derive_key(key, salt.as_deref())?;
+    let cipher = Aes256Gcm::new((&key_bytes).into());
+    key_bytes.zeroize();
+    let archive = cipher
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: &header_json,
+            },
+        )
+        .map_err(|_| BackupError::Decryption)?;
+    if sha256_hex(&archive) != header.archive_sha256 {
+        return Err(BackupError::Integrity("archive hash mismatch".to_string()));
+    }
+    Ok((header, archive))
+}
+
+type RestoredFile = (PathBuf, Vec<u8>);
+
+fn safe_relative(name: &str) -> Result<PathBuf, BackupError> {
+    let path = PathBuf::from(name);
+    if name.is_empty()
+        || path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Err(BackupError::Integrity(format!("unsafe path '{name}'")));
+    }
+    Ok(path)
+}
+
+/// Decrypt and unpack into memory, checking every file against the manifest.
+fn unpack_verified(
+    path: &Path,
+    key: &BackupKey,
+) -> Result<(BackupManifest, Vec<RestoredFile>), BackupError> {
+    let (_, archive) = decrypt_archive(path, key)?;
+    let mut tar = tar::Archive::new(GzDecoder::new(archive.as_slice()));
+    let mut manifest = None;
+    let mut files = Vec::new();
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().to_string();
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+        if name == MANIFEST_NAME {
+            manifest = Some(
+                serde_json::from_slice::<BackupManifest>(&content)
+                    .map_err(|e| BackupError::Integrity(e.to_string()))?,
+            );
+        } else {
+            files.push((safe_relative(&name)?, content));
+        }
+    }
+    let manifest =
+        manifest.ok_or_else(|| BackupError::Integrity("manifest missing".to_string()))?;
+
+    if files.len() != manifest.files.len() {
+        return Err(BackupError::Integrity(format!(
+            "manifest lists {} files, archive has {}",
+            manifest.files.len(),
+            files.len()
+        )));
+    }
+    for (rel, content) in &files {
+        let name = relative_name(rel);
+        let expected = manifest
+            .files
+            .get(&name)
+            .ok_or_else(|| BackupError::Integrity(format!("'{name}' not in manifest")))?;
+        if expected.size != content.len() as u64 || expected.sha256 != sha256_hex(content) {
+            return Err(BackupError::Integrity(format!("'{name}' hash mismatch")));
+        }
+    }
+    Ok((manifest, files))
+}
+
+/// Decrypt a backup and verify its archive hash and every file hash.
+pub fn verify_backup(path: &Path, key: &BackupKey) -> Result<BackupManifest, BackupError> {
+    unpack_verified(path, key).map(|(manifest, _)| manifest)
+}
+
+/// Verify and restore a backup into `data_dir`. Top-level entries being
+/// replaced are moved to `data_dir/.pre-restore-<timestamp>/` first.
+pub fn restore_backup(
+    path: &Path,
+    key: &BackupKey,
+    data_dir: &Path,
+) -> Result<(BackupManifest, PathBuf), BackupError> {
+    let (manifest, files) = unpack_verified(path, key)?;
+    let stamp = chrono::Utc::now().format("%Y%m%d-%H%M%S").to_string();
+    let staging = data_dir.join(format!(".restore-{stamp}"));
+    let previous = data_dir.join(format!(".pre-restore-{stamp}"));
+    fs::create_dir_all(&staging)?;
+    for (rel, content) in &files {
+        let target = staging.join(rel);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(target, content)?;
+    }
+
+    let mut top_level: Vec<PathBuf> = files
+        .iter()
+        .filter_map(|(rel, _)| rel.components().next())
+        .map(|c| PathBuf::from(c.as_os_str()))
+        .collect();
+    top_level.sort();
+    top_level.dedup();
+
+    fs::create_dir_all(&previous)?;
+    for entry in &top_level {
+        let current = data_dir.join(entry);
+        if current.exists() {
+            fs::rename(&current, previous.join(entry))?;
+        }
+        fs::rename(staging.join(entry), &current)?;
+    }
+    fs::remove_dir_all(&staging)?;
+    Ok((manifest, previous))
+}
+
+/// Backups in `backup_dir`, newest first. Unreadable files are skipped.
+pub fn list_backups(backup_dir: &Path) -> Result<Vec<BackupSummary>, BackupError> {
+    let mut out = Vec::new();
+    let entries = match fs::read_dir(backup_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(out),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(EXTENSION) {
+            continue;
+        }
+        if let Ok(header) = read_header(&path) {
+            let bytes = fs::metadata(&path)?.len();
+            out.push(BackupSummary {
+                path,
+                header,
+                bytes,
+            });
+        }
+    }
+    out.sort_by(|a, b| b.header.created_at.cmp(&a.header.created_at));
+    Ok(out)
+}
+
+/// Delete all but the newest `keep` backups. Returns the removed paths.
+pub fn prune_backups(backup_dir: &Path, keep: usize) -> Result<Vec<PathBuf>, BackupError> {
+    let mut removed = Vec::new();
+    for summary in list_backups(backup_dir)?.into_iter().skip(keep) {
+        fs::remove_file(&summary.path)?;
+        removed.push(summary.path);
+    }
+    Ok(removed)
+}
@@ -1,6 +1,7 @@
 pub mod audit_adapter;
 pub mod audit_logger;
 pub mod audit_logger_chained;
+pub mod backup;
 pub mod contracts;
 pub mod credential_store;
 pub mod distributed;
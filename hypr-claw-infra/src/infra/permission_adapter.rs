@@ -1,4 +1,4 @@
-use crate::infra::permission_engine::PermissionEngine;
+use crate::infra::permission_engine::{ApprovalPrompter, PermissionEngine};
 use async_trait::async_trait;
 use hypr_claw_tools::{
     PermissionDecision, PermissionEngine as PermissionEngineTrait, PermissionRequest,
@@ -11,7 +11,8 @@ use tokio::time::{timeout, Duration};
 
 #[async_trait]
 impl PermissionEngineTrait for PermissionEngine {
-    async fn check(&self, request: PermissionRequest) -> PermissionDecision {
+    async fn check(&self, mut request: PermissionRequest) -> PermissionDecision {
+        let command_risk = request.command_risk.take();
         // Convert to infra types
         let input_map: HashMap<String, serde_json::Value> = request
             .input
@@ -41,15 +42,20 @@ impl PermissionEngineTrait for PermissionEngine {
                 PermissionDecision::Deny("Permission denied".to_string())
             }
             crate::infra::contracts::PermissionDecision::REQUIRE_APPROVAL => {
-                if is_full_auto_mode_enabled() {
+                // Full auto mode still asks about hazardous commands.
+                let hazardous = command_risk.as_ref().is_some_and(|r| r.needs_approval());
+                if is_full_auto_mode_enabled() && !hazardous {
                     return PermissionDecision::Allow;
                 }
-                let description = format!(
+                let mut description = format!(
                     "{} with input {}",
                     infra_request.tool_name,
                     serde_json::to_string(&infra_request.input).unwrap_or_default()
                 );
-                if prompt_user_approval(&description).await {
+                for hazard in command_risk.iter().flat_map(|r| &r.hazards) {
+                    description.push_str(&format!("\n  ⚠ {hazard}"));
+                }
+                if prompt_user_approval(self.prompter(), description).await {
                     PermissionDecision::Allow
                 } else {
                     PermissionDecision::Deny("Approval denied or timed out".to_string())
@@ -63,18 +69,17 @@ fn is_full_auto_mode_enabled() -> bool {
     Path::new("./data/full_auto_mode.flag").exists()
 }
 
-async fn prompt_user_approval(description: &str) -> bool {
-    print!("Approve action: {} [y/N] ", description);
-    let _ = io::stdout().flush();
-
-    let task = tokio::task::spawn_blocking(|| {
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).ok();
-        input
+async fn prompt_user_approval(prompter: Option<ApprovalPrompter>, description: String) -> bool {
+    let task = tokio::task::spawn_blocking(move || match prompter {
+        Some(prompter) => prompter(&description),
+        None => {
+            print!("Approve action: {} [y/N] ", description);
+            let _ = io::stdout().flush();
+            let mut input = String::new();
+            io::stdin().read_line(&mut input).ok();
+            input.trim().eq_ignore_ascii_case("y")
+        }
     });
 
-    match timeout(Duration::from_secs(30), task).await {
-        Ok(Ok(input)) => input.trim().eq_ignore_ascii_case("y"),
-        _ => false,
-    }
+    matches!(timeout(Duration::from_secs(30), task).await, Ok(Ok(true)))
 }
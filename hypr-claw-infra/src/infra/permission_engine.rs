@@ -1,8 +1,14 @@
 use crate::infra::contracts::{PermissionDecision, PermissionLevel, PermissionRequest};
+use std::sync::Arc;
 
 const BLOCKED_PATTERNS: &[&str] = &["sudo", "rm", "chmod", "curl|sh", "|sh"];
 
-pub struct PermissionEngine;
+/// Shows an approval request and returns whether the user accepted it.
+pub type ApprovalPrompter = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+pub struct PermissionEngine {
+    prompter: Option<ApprovalPrompter>,
+}
 
 impl Default for PermissionEngine {
     fn default() -> Self {
@@ -12,7 +18,18 @@ impl Default for PermissionEngine {
 
 impl PermissionEngine {
     pub fn new() -> Self {
-        Self
+        Self { prompter: None }
+    }
+
+    /// Ask for approvals through `prompter` instead of reading stdin, for
+    /// front ends that already own the terminal input.
+    pub fn with_prompter(mut self, prompter: ApprovalPrompter) -> Self {
+        self.prompter = Some(prompter);
+        self
+    }
+
+    pub fn prompter(&self) -> Option<ApprovalPrompter> {
+        self.prompter.clone()
     }
 
     pub fn check(&self, request: &PermissionRequest) -> PermissionDecision {
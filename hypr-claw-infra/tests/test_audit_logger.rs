@@ -1,4 +1,4 @@
-use hypr_claw::infra::audit_logger::AuditLogger;
+use hypr_claw::infra::audit_logger::{AuditLogger, AuditQuery, RotationPolicy};
 use hypr_claw::infra::contracts::{AuditEntry, PermissionDecision};
 use std::collections::HashMap;
 use std::fs::File;
@@ -155,3 +155,129 @@ fn test_creates_parent_directory() {
 
     assert!(log_path.exists());
 }
+
+fn entry_at(tool: &str, timestamp: i64) -> AuditEntry {
+    let mut entry = create_entry("session1", tool);
+    entry.timestamp = chrono::DateTime::from_timestamp(timestamp, 0)
+        .unwrap()
+        .to_rfc3339();
+    entry
+}
+
+fn small_policy() -> RotationPolicy {
+    RotationPolicy {
+        max_bytes: 600,
+        max_age_hours: 0,
+        compress: true,
+        keep_segments: 0,
+        retention_days: 0,
+    }
+}
+
+#[test]
+fn test_rotates_into_compressed_indexed_segments() {
+    let temp = TempDir::new().unwrap();
+    let log_path = temp.path().join("audit.log");
+    let logger = AuditLogger::with_rotation(&log_path, small_policy()).unwrap();
+
+    for i in 0..20 {
+        logger.log(&entry_at("tool", 1_000 + i)).unwrap();
+    }
+
+    let segments = logger.segments();
+    assert!(segments.len() >= 2);
+    assert_eq!(segments[0].file, "audit.log.000001.gz");
+    assert_eq!(segments[0].first, 1_000);
+    assert!(segments.windows(2).all(|w| w[0].last < w[1].first));
+    for segment in &segments {
+        assert!(temp.path().join(&segment.file).exists());
+    }
+    assert!(std::fs::metadata(&log_path).unwrap().len() <= 600);
+    assert!(temp.path().join("audit.log.index.json").exists());
+
+    let logged: u64 = segments.iter().map(|s| s.entries).sum();
+    let all = logger.query(&AuditQuery::default()).unwrap();
+    assert_eq!(all.len(), 20);
+    assert!(logged < 20);
+
+    // The index survives a restart.
+    drop(logger);
+    let reopened = AuditLogger::with_rotation(&log_path, small_policy()).unwrap();
+    assert_eq!(reopened.segments(), segments);
+    assert_eq!(reopened.query(&AuditQuery::default()).unwrap().len(), 20);
+}
+
+#[test]
+fn test_query_filters_by_range_tool_and_limit() {
+    let temp = TempDir::new().unwrap();
+    let log_path = temp.path().join("audit.log");
+    let logger = AuditLogger::with_rotation(&log_path, small_policy()).unwrap();
+
+    for i in 0..20 {
+        let tool = if i % 2 == 0 { "fs.read" } else { "fs.write" };
+        logger.log(&entry_at(tool, 1_000 + i)).unwrap();
+    }
+
+    let range = logger
+        .query(&AuditQuery {
+            since: Some(1_005),
+            until: Some(1_009),
+            ..AuditQuery::default()
+        })
+        .unwrap();
+    assert_eq!(range.len(), 5);
+
+    let writes = logger
+        .query(&AuditQuery {
+            tool: Some("fs.write".to_string()),
+            limit: Some(3),
+            ..AuditQuery::default()
+        })
+        .unwrap();
+    let times: Vec<&str> = writes.iter().map(|e| e.timestamp.as_str()).collect();
+    assert_eq!(writes.len(), 3);
+    assert!(writes.iter().all(|e| e.tool == "fs.write"));
+    assert!(times.windows(2).all(|w| w[0] < w[1]));
+    assert!(times[2].contains("00:16:59"));
+}
+
+#[test]
+fn test_retention_drops_oldest_segments() {
+    let temp = TempDir::new().unwrap();
+    let log_path = temp.path().join("audit.log");
+    let policy = RotationPolicy {
+        keep_segments: 2,
+        compress: false,
+        ..small_policy()
+    };
+    let logger = AuditLogger::with_rotation(&log_path, policy).unwrap();
+
+    for i in 0..40 {
+        logger.log(&entry_at("tool", 1_000 + i)).unwrap();
+    }
+
+    let segments = logger.segments();
+    assert_eq!(segments.len(), 2);
+    assert!(segments[0].seq > 1);
+    assert!(!temp.path().join("audit.log.000001").exists());
+    assert!(temp.path().join(&segments[0].file).exists());
+}
+
+#[test]
+fn test_rotates_by_age() {
+    let temp = TempDir::new().unwrap();
+    let log_path = temp.path().join("audit.log");
+    let policy = RotationPolicy {
+        max_bytes: 0,
+        max_age_hours: 1,
+        ..small_policy()
+    };
+    let logger = AuditLogger::with_rotation(&log_path, policy).unwrap();
+
+    logger.log(&entry_at("tool", 0)).unwrap();
+    logger.log(&entry_at("tool", 1_800)).unwrap();
+    assert!(logger.segments().is_empty());
+    logger.log(&entry_at("tool", 3_600)).unwrap();
+    assert_eq!(logger.segments().len(), 1);
+    assert_eq!(logger.segments()[0].entries, 2);
+}
@@ -0,0 +1,124 @@
+use hypr_claw::infra::backup::{
+    create_backup, list_backups, prune_backups, read_header, restore_backup, verify_backup,
+    BackupError, BackupKey, DEFAULT_ENTRIES,
+};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn seed_data_dir(dir: &Path) {
+    fs::create_dir_all(dir.join("sessions")).unwrap();
+    fs::create_dir_all(dir.join("credentials")).unwrap();
+    fs::write(dir.join("config.yaml"), "provider: nvidia\nmodel: x\n").unwrap();
+    fs::write(
+        dir.join("sessions/user:default.jsonl"),
+        "{\"role\":\"user\"}\n",
+    )
+    .unwrap();
+    fs::write(dir.join("credentials/abc.enc"), [1u8, 2, 3]).unwrap();
+    fs::write(dir.join(".master_key"), [7u8; 32]).unwrap();
+}
+
+#[test]
+fn test_create_and_verify_with_master_key() {
+    let temp = TempDir::new().unwrap();
+    let data = temp.path().join("data");
+    seed_data_dir(&data);
+    let key = BackupKey::MasterKey([42u8; 32]);
+
+    let summary =
+        create_backup(&data, &temp.path().join("backups"), &key, DEFAULT_ENTRIES).unwrap();
+    assert_eq!(summary.header.kdf, "master-key");
+
+    let manifest = verify_backup(&summary.path, &key).unwrap();
+    assert_eq!(manifest.files.len(), 3);
+    assert!(manifest.files.contains_key("sessions/user:default.jsonl"));
+    assert!(!manifest.files.keys().any(|k| k.contains("master_key")));
+}
+
+#[test]
+fn test_wrong_passphrase_is_rejected() {
+    let temp = TempDir::new().unwrap();
+    let data = temp.path().join("data");
+    seed_data_dir(&data);
+
+    let summary = create_backup(
+        &data,
+        &temp.path().join("backups"),
+        &BackupKey::Passphrase("correct horse".to_string()),
+        DEFAULT_ENTRIES,
+    )
+    .unwrap();
+    assert_eq!(read_header(&summary.path).unwrap().kdf, "argon2id");
+
+    let result = verify_backup(&summary.path, &BackupKey::Passphrase("wrong".to_string()));
+    assert!(matches!(result, Err(BackupError::Decryption)));
+}
+
+#[test]
+fn test_tampered_archive_fails_verification() {
+    let temp = TempDir::new().unwrap();
+    let data = temp.path().join("data");
+    seed_data_dir(&data);
+    let key = BackupKey::MasterKey([42u8; 32]);
+    let summary =
+        create_backup(&data, &temp.path().join("backups"), &key, DEFAULT_ENTRIES).unwrap();
+
+    let mut bytes = fs::read(&summary.path).unwrap();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xff;
+    fs::write(&summary.path, bytes).unwrap();
+
+    assert!(verify_backup(&summary.path, &key).is_err());
+}
+
+#[test]
+fn test_restore_replaces_entries_and_keeps_previous_copy() {
+    let temp = TempDir::new().unwrap();
+    let data = temp.path().join("data");
+    seed_data_dir(&data);
+    let key = BackupKey::MasterKey([42u8; 32]);
+    let summary =
+        create_backup(&data, &temp.path().join("backups"), &key, DEFAULT_ENTRIES).unwrap();
+
+    fs::write(data.join("config.yaml"), "broken").unwrap();
+    fs::remove_file(data.join("sessions/user:default.jsonl")).unwrap();
+
+    let (manifest, previous) = restore_backup(&summary.path, &key, &data).unwrap();
+    assert_eq!(manifest.files.len(), 3);
+    assert_eq!(
+        fs::read_to_string(data.join("config.yaml")).unwrap(),
+        "provider: nvidia\nmodel: x\n"
+    );
+    assert!(data.join("sessions/user:default.jsonl").exists());
+    assert_eq!(
+        fs::read_to_string(previous.join("config.yaml")).unwrap(),
+        "broken"
+    );
+    assert!(data.join(".master_key").exists());
+}
+
+#[test]
+fn test_list_and_prune_keep_newest() {
+    let temp = TempDir::new().unwrap();
+    let data = temp.path().join("data");
+    let backups = temp.path().join("backups");
+    seed_data_dir(&data);
+    let key = BackupKey::MasterKey([42u8; 32]);
+
+    let mut created = Vec::new();
+    for _ in 0..3 {
+        created.push(
+            create_backup(&data, &backups, &key, DEFAULT_ENTRIES)
+                .unwrap()
+                .path,
+        );
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+
+    let removed = prune_backups(&backups, 2).unwrap();
+    assert_eq!(removed, vec![created[0].clone()]);
+    let remaining = list_backups(&backups).unwrap();
+    assert_eq!(remaining.len(), 2);
+    assert_eq!(remaining[0].path, created[2]);
+}
@@ -0,0 +1,172 @@
+//! Minimal chat agent built on the public runtime API.
+//!
+//! One [`AgentLoop`] with file-backed sessions and locks, a single `clock`
+//! tool and a line-per-turn REPL on stdin. It talks to an OpenAI-compatible
+//! endpoint when `HYPR_CLAW_LLM_URL`, `HYPR_CLAW_MODEL` and
+//! `HYPR_CLAW_API_KEY` are set, and otherwise to a scripted local model, so
+//! it also runs offline:
+//!
+//! ```text
+//! echo "what time is it?" | cargo run -p hypr-claw-runtime --example chat_agent
+//! ```
+
+use async_trait::async_trait;
+use hypr_claw_runtime::chaos::{Chaos, ChaosConfig, FakeProvider, Responder};
+use hypr_claw_runtime::{
+    AgentLoop, AsyncLockManager, AsyncSessionStore, Compactor, LLMClient, LLMClientType,
+    LLMResponse, Message, Role, RuntimeError, Summarizer, ToolDispatcher, ToolRegistry,
+    SCHEMA_VERSION,
+};
+use serde_json::{json, Value};
+use std::error::Error;
+use std::io::BufRead;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+type ChatAgent = AgentLoop<AsyncSessionStore, AsyncLockManager, Clock, Clock, FirstLines>;
+
+/// The one tool: seconds since the Unix epoch.
+struct Clock;
+
+#[async_trait]
+impl ToolDispatcher for Clock {
+    async fn execute(
+        &self,
+        tool_name: &str,
+        _input: &Value,
+        _session_key: &str,
+    ) -> Result<Value, RuntimeError> {
+        if tool_name != "clock" {
+            return Err(RuntimeError::ToolError(format!(
+                "Unknown tool: {tool_name}"
+            )));
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| RuntimeError::ToolError(e.to_string()))?;
+        Ok(json!({"unix_seconds": now.as_secs()}))
+    }
+}
+
+impl ToolRegistry for Clock {
+    fn get_active_tools(&self, _agent_id: &str) -> Vec<String> {
+        vec!["clock".to_string()]
+    }
+
+    fn get_tool_schemas(&self, _agent_id: &str) -> Vec<Value> {
+        vec![json!({
+            "type": "function",
+            "function": {
+                "name": "clock",
+                "description": "Current time as seconds since the Unix epoch",
+                "parameters": {"type": "object", "properties": {}}
+            }
+        })]
+    }
+
+    fn is_read_only(&self, _tool_name: &str) -> bool {
+        true
+    }
+}
+
+/// Compacts old history to the first line of each message.
+struct FirstLines;
+
+impl Summarizer for FirstLines {
+    fn summarize(&self, messages: &[Message]) -> Result<String, RuntimeError> {
+        Ok(messages
+            .iter()
+            .filter_map(|m| m.content.as_str()?.lines().next())
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+/// Stands in for a model: asks the clock once, then answers with it.
+fn scripted_model() -> Responder {
+    Arc::new(|messages, _tools| match messages.last() {
+        Some(last) if last.role == Role::Tool => LLMResponse::Final {
+            schema_version: SCHEMA_VERSION,
+            content: format!("The clock says {}.", last.content),
+        },
+        _ => LLMResponse::ToolCall {
+            schema_version: SCHEMA_VERSION,
+            tool_name: "clock".to_string(),
+            input: json!({}),
+            rationale: Some("The user asked for the time".to_string()),
+        },
+    })
+}
+
+fn build_agent(client: LLMClientType, state_dir: &Path) -> Result<ChatAgent, Box<dyn Error>> {
+    let sessions = hypr_claw::infra::session_store::SessionStore::new(state_dir.join("sessions"))?;
+    let locks = hypr_claw::infra::lock_manager::LockManager::new(Duration::from_secs(30));
+    let clock = Arc::new(Clock);
+    Ok(AgentLoop::new(
+        Arc::new(AsyncSessionStore::new(Arc::new(sessions))),
+        Arc::new(AsyncLockManager::new(Arc::new(locks))),
+        clock.clone(),
+        clock,
+        client,
+        Compactor::new(4000, FirstLines),
+        8,
+    ))
+}
+
+/// A client for the configured endpoint, if the environment names one.
+fn configured_client() -> Option<LLMClientType> {
+    let url = std::env::var("HYPR_CLAW_LLM_URL").ok()?;
+    let model = std::env::var("HYPR_CLAW_MODEL").ok()?;
+    let api_key = std::env::var("HYPR_CLAW_API_KEY").unwrap_or_default();
+    Some(LLMClientType::Standard(LLMClient::with_api_key_and_model(
+        url, 2, api_key, model,
+    )))
+}
+
+const SYSTEM_PROMPT: &str = "You are a terse assistant. Use the clock tool for the time.";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    // The scripted model serves until `_scripted` is dropped at exit.
+    let (client, _scripted) = match configured_client() {
+        Some(client) => (client, None),
+        None => {
+            eprintln!("(HYPR_CLAW_LLM_URL and HYPR_CLAW_MODEL unset; using a scripted model)");
+            let provider =
+                FakeProvider::start(Chaos::new(ChaosConfig::default()), scripted_model()).await?;
+            let client = LLMClient::new(provider.url().to_string(), 1);
+            (LLMClientType::Standard(client), Some(provider))
+        }
+    };
+    let state_dir = std::env::temp_dir().join(format!("hypr-claw-chat-{}", std::process::id()));
+    let agent = build_agent(client, &state_dir)?;
+
+    for line in std::io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match agent.run("chat", "example", SYSTEM_PROMPT, &line).await {
+            Ok(answer) => println!("{answer}"),
+            Err(e) => eprintln!("error: {e}"),
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn answers_with_the_clock() -> Result<(), Box<dyn Error>> {
+    let provider =
+        FakeProvider::start(Chaos::new(ChaosConfig::default()), scripted_model()).await?;
+    let client = LLMClientType::Standard(LLMClient::new(provider.url().to_string(), 1));
+    let state_dir = tempfile::tempdir()?;
+    let agent = build_agent(client, state_dir.path())?;
+
+    let answer = agent
+        .run("chat", "example", SYSTEM_PROMPT, "what time is it?")
+        .await?;
+    assert!(answer.starts_with("The clock says"), "{answer}");
+    assert!(answer.contains("unix_seconds"), "{answer}");
+    Ok(())
+}
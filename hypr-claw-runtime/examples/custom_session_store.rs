@@ -0,0 +1,208 @@
+//! Plugging a custom session backend into the runtime.
+//!
+//! [`VersionedMemoryStore`] keeps each session's history in memory, the
+//! place a database or remote store would go. It implements the versioned
+//! half of [`SessionStore`] too, so a write based on a history another
+//! writer has since changed is rejected with
+//! [`RuntimeError::SessionConflict`] instead of silently dropping turns.
+//!
+//! ```text
+//! cargo run -p hypr-claw-runtime --example custom_session_store
+//! ```
+
+use async_trait::async_trait;
+use hypr_claw_runtime::chaos::{Chaos, ChaosConfig, FakeProvider, Responder};
+use hypr_claw_runtime::{
+    AgentLoop, AsyncLockManager, Compactor, LLMClient, LLMClientType, LLMResponse, Message, Role,
+    RuntimeError, SessionStore, SessionVersion, Summarizer, ToolDispatcher, ToolRegistry,
+    SCHEMA_VERSION,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// History of one session and the number of writes it has seen.
+#[derive(Default)]
+struct Stored {
+    version: u64,
+    messages: Vec<Message>,
+}
+
+#[derive(Default)]
+struct VersionedMemoryStore {
+    sessions: Mutex<HashMap<String, Stored>>,
+}
+
+impl VersionedMemoryStore {
+    fn sessions(&self) -> MutexGuard<'_, HashMap<String, Stored>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn len(&self, session_key: &str) -> usize {
+        self.sessions()
+            .get(session_key)
+            .map_or(0, |stored| stored.messages.len())
+    }
+}
+
+#[async_trait]
+impl SessionStore for VersionedMemoryStore {
+    async fn load(&self, session_key: &str) -> Result<Vec<Message>, RuntimeError> {
+        Ok(self.load_versioned(session_key).await?.0)
+    }
+
+    async fn save(&self, session_key: &str, messages: &[Message]) -> Result<(), RuntimeError> {
+        let mut sessions = self.sessions();
+        let stored = sessions.entry(session_key.to_string()).or_default();
+        stored.version += 1;
+        stored.messages = messages.to_vec();
+        Ok(())
+    }
+
+    async fn load_versioned(
+        &self,
+        session_key: &str,
+    ) -> Result<(Vec<Message>, SessionVersion), RuntimeError> {
+        let sessions = self.sessions();
+        let Some(stored) = sessions.get(session_key) else {
+            return Ok((Vec::new(), SessionVersion { version: 0, len: 0 }));
+        };
+        let version = SessionVersion {
+            version: stored.version,
+            len: stored.messages.len(),
+        };
+        Ok((stored.messages.clone(), version))
+    }
+
+    async fn save_versioned(
+        &self,
+        session_key: &str,
+        messages: &[Message],
+        base: SessionVersion,
+    ) -> Result<SessionVersion, RuntimeError> {
+        let mut sessions = self.sessions();
+        let stored = sessions.entry(session_key.to_string()).or_default();
+        if stored.version != base.version {
+            return Err(RuntimeError::SessionConflict {
+                session_key: session_key.to_string(),
+                expected: base.version,
+                found: stored.version,
+            });
+        }
+        stored.version += 1;
+        stored.messages = messages.to_vec();
+        Ok(SessionVersion {
+            version: stored.version,
+            len: messages.len(),
+        })
+    }
+}
+
+/// A single `echo` tool; the runtime refuses to run without any.
+struct Echo;
+
+#[async_trait]
+impl ToolDispatcher for Echo {
+    async fn execute(
+        &self,
+        _tool_name: &str,
+        input: &Value,
+        _session_key: &str,
+    ) -> Result<Value, RuntimeError> {
+        Ok(input.clone())
+    }
+}
+
+impl ToolRegistry for Echo {
+    fn get_active_tools(&self, _agent_id: &str) -> Vec<String> {
+        vec!["echo".to_string()]
+    }
+
+    fn get_tool_schemas(&self, _agent_id: &str) -> Vec<Value> {
+        vec![json!({
+            "type": "function",
+            "function": {
+                "name": "echo",
+                "description": "Echo the input",
+                "parameters": {"type": "object", "properties": {}}
+            }
+        })]
+    }
+}
+
+struct CountSummarizer;
+
+impl Summarizer for CountSummarizer {
+    fn summarize(&self, messages: &[Message]) -> Result<String, RuntimeError> {
+        Ok(format!("{} earlier messages", messages.len()))
+    }
+}
+
+/// Answers with how many user turns the loaded history holds, which only
+/// grows if the store kept the earlier ones.
+fn counting_model() -> Responder {
+    Arc::new(|messages, _tools| {
+        let turns = messages.iter().filter(|m| m.role == Role::User).count();
+        LLMResponse::Final {
+            schema_version: SCHEMA_VERSION,
+            content: format!("That makes {turns} message(s) from you."),
+        }
+    })
+}
+
+type Agent = AgentLoop<VersionedMemoryStore, AsyncLockManager, Echo, Echo, CountSummarizer>;
+
+fn build_agent(store: Arc<VersionedMemoryStore>, provider: &FakeProvider) -> Agent {
+    let locks = hypr_claw::infra::lock_manager::LockManager::new(Duration::from_secs(30));
+    let echo = Arc::new(Echo);
+    AgentLoop::new(
+        store,
+        Arc::new(AsyncLockManager::new(Arc::new(locks))),
+        echo.clone(),
+        echo,
+        LLMClientType::Standard(LLMClient::new(provider.url().to_string(), 1)),
+        Compactor::new(4000, CountSummarizer),
+        4,
+    )
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let provider =
+        FakeProvider::start(Chaos::new(ChaosConfig::default()), counting_model()).await?;
+    let store = Arc::new(VersionedMemoryStore::default());
+    let agent = build_agent(store.clone(), &provider);
+
+    for prompt in ["hello", "how are you?", "bye"] {
+        let answer = agent.run("memory", "example", "", prompt).await?;
+        println!("> {prompt}\n{answer}");
+    }
+    println!("stored messages: {}", store.len("memory"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn history_lives_in_the_custom_store() -> Result<(), Box<dyn Error>> {
+    let provider =
+        FakeProvider::start(Chaos::new(ChaosConfig::default()), counting_model()).await?;
+    let store = Arc::new(VersionedMemoryStore::default());
+    let agent = build_agent(store.clone(), &provider);
+
+    assert_eq!(
+        agent.run("memory", "example", "", "hello").await?,
+        "That makes 1 message(s) from you."
+    );
+    assert_eq!(
+        agent.run("memory", "example", "", "again").await?,
+        "That makes 2 message(s) from you."
+    );
+    assert!(store.len("memory") >= 4);
+
+    let (messages, base) = store.load_versioned("memory").await?;
+    store.save("memory", &messages).await?;
+    let stale = store.save_versioned("memory", &messages, base).await;
+    assert!(matches!(stale, Err(RuntimeError::SessionConflict { .. })));
+    Ok(())
+}
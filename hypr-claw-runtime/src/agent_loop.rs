@@ -1,14 +1,59 @@
 //! Agent loop - the core runtime kernel.
 
+use crate::attempts::AttemptLog;
+use crate::citations::{self, Citation, Evidence};
+use crate::compaction_events::{self, CompactionEvent, CompactionObserver};
 use crate::compactor::{Compactor, Summarizer};
-use crate::interfaces::{LockManager, RuntimeError, SessionStore, ToolDispatcher, ToolRegistry};
+use crate::context_refresh::{self, ContextRefresher, RefreshReason};
+use crate::decisions::{DecisionObserver, ToolDecision};
+use crate::guardrails::OutputGuardrails;
+use crate::interfaces::{
+    LockManager, RuntimeError, SessionStore, SessionVersion, ToolDispatcher, ToolRegistry,
+};
+use crate::llm_client::ModelInfo;
 use crate::llm_client_type::LLMClientType;
-use crate::types::{LLMResponse, Message, Role};
+use crate::prefetch::{self, PrefetchCall, Prefetcher};
+use crate::types::{LLMResponse, Message, Origin, Provenance, Role};
+use parking_lot::Mutex;
 use serde_json::json;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
 
+/// Saves of a compacted history tried before a run that keeps racing other
+/// writers gives up with a `SessionConflict`.
+const COMPACTED_SAVE_ATTEMPTS: usize = 3;
+
+/// The first request [`AgentLoop::run`] would send, composed without
+/// calling the model or touching the session.
+#[derive(Debug, Clone)]
+pub struct RequestPreview {
+    pub system_prompt: String,
+    /// History after compaction, then the user message. A summary the
+    /// compactor would write stands in as a placeholder.
+    pub messages: Vec<Message>,
+    pub tool_schemas: Vec<serde_json::Value>,
+    /// Oldest stored messages compaction would fold into a summary.
+    pub summarized: usize,
+}
+
+impl RequestPreview {
+    /// Estimated input tokens, at 4 characters per token like the compactor.
+    pub fn estimated_tokens(&self) -> usize {
+        let messages: usize = self
+            .messages
+            .iter()
+            .map(|msg| msg.content.to_string().len())
+            .sum();
+        let tools = serde_json::to_string(&self.tool_schemas)
+            .map(|s| s.len())
+            .unwrap_or(0);
+        (self.system_prompt.len() + messages + tools) / 4
+    }
+}
+
 /// Core agent execution loop.
 pub struct AgentLoop<S, L, D, R, Sum>
 where
@@ -25,6 +70,31 @@ where
     llm_client: LLMClientType,
     compactor: Compactor<Sum>,
     max_iterations: Arc<AtomicUsize>,
+    require_verification: Arc<AtomicBool>,
+    require_citations: Arc<AtomicBool>,
+    last_citations: Arc<Mutex<HashMap<String, Vec<Citation>>>>,
+    attempts: Arc<Mutex<HashMap<String, AttemptLog>>>,
+    observations: Arc<Mutex<HashMap<String, Vec<String>>>>,
+    guardrails: Arc<Mutex<OutputGuardrails>>,
+    decision_observer: Arc<Mutex<Option<DecisionObserver>>>,
+    prefetcher: Arc<Mutex<Option<Prefetcher>>>,
+    compaction_observer: Arc<Mutex<Option<CompactionObserver>>>,
+    /// Per session, the last compaction's targets not read again yet.
+    compacted_targets: Arc<Mutex<HashMap<String, CompactedTargets>>>,
+    context_refresh: Arc<Mutex<Option<ContextRefresh>>>,
+}
+
+/// Source of fresh runtime context and the age at which a run needs it.
+#[derive(Clone)]
+struct ContextRefresh {
+    refresher: Arc<dyn ContextRefresher>,
+    max_age: Duration,
+}
+
+struct CompactedTargets {
+    compaction_id: String,
+    run_id: String,
+    targets: HashSet<String>,
 }
 
 impl<S, L, D, R, Sum> AgentLoop<S, L, D, R, Sum>
@@ -54,6 +124,17 @@ where
             llm_client,
             compactor,
             max_iterations: Arc::new(AtomicUsize::new(max_iterations.max(1))),
+            require_verification: Arc::new(AtomicBool::new(false)),
+            require_citations: Arc::new(AtomicBool::new(false)),
+            last_citations: Arc::new(Mutex::new(HashMap::new())),
+            attempts: Arc::new(Mutex::new(HashMap::new())),
+            observations: Arc::new(Mutex::new(HashMap::new())),
+            guardrails: Arc::new(Mutex::new(OutputGuardrails::default())),
+            decision_observer: Arc::new(Mutex::new(None)),
+            prefetcher: Arc::new(Mutex::new(None)),
+            compaction_observer: Arc::new(Mutex::new(None)),
+            compacted_targets: Arc::new(Mutex::new(HashMap::new())),
+            context_refresh: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -72,6 +153,61 @@ where
         self.llm_client.list_models().await
     }
 
+    /// List available LLM models with provider metadata.
+    pub async fn list_model_info(&self) -> Result<Vec<ModelInfo>, RuntimeError> {
+        self.llm_client.list_model_info().await
+    }
+
+    /// Override the sampling temperature for later model calls; `None`
+    /// restores the model's default.
+    pub fn set_temperature_override(&self, temperature: Option<f32>) {
+        self.llm_client.set_temperature_override(temperature);
+    }
+
+    /// Stop sequences and narration budget: sent to the provider, and
+    /// enforced on final answers for providers that ignore them.
+    pub fn set_output_guardrails(&self, guardrails: OutputGuardrails) {
+        self.llm_client.set_output_guardrails(guardrails.clone());
+        *self.guardrails.lock() = guardrails;
+    }
+
+    pub fn output_guardrails(&self) -> OutputGuardrails {
+        self.guardrails.lock().clone()
+    }
+
+    /// Default sampling temperature of the active model, when known.
+    pub fn base_temperature(&self) -> Option<f32> {
+        self.llm_client.base_temperature()
+    }
+
+    /// Called with every model response: the tool it chose, if any, out of
+    /// the ones it was offered.
+    pub fn set_decision_observer(&self, observer: DecisionObserver) {
+        *self.decision_observer.lock() = Some(observer);
+    }
+
+    /// Receive compactions and reads of what they summarized away.
+    pub fn set_compaction_observer(&self, observer: CompactionObserver) {
+        *self.compaction_observer.lock() = Some(observer);
+    }
+
+    /// Refresh the runtime context of runs older than `max_age`, or whose
+    /// tools find the desktop changed; `None` turns refreshing off.
+    pub fn set_context_refresher(
+        &self,
+        refresher: Option<Arc<dyn ContextRefresher>>,
+        max_age: Duration,
+    ) {
+        *self.context_refresh.lock() =
+            refresher.map(|refresher| ContextRefresh { refresher, max_age });
+    }
+
+    /// Guess read-only calls to run alongside the first model call of each
+    /// run; `None` turns prefetching off.
+    pub fn set_prefetcher(&self, prefetcher: Option<Prefetcher>) {
+        *self.prefetcher.lock() = prefetcher;
+    }
+
     /// Update max iteration budget at runtime (used when switching souls).
     pub fn set_max_iterations(&self, value: usize) {
         self.max_iterations.store(value.max(1), Ordering::SeqCst);
@@ -82,6 +218,60 @@ where
         self.max_iterations.load(Ordering::SeqCst)
     }
 
+    /// Refuse to finish a run while a successful mutating tool call has not
+    /// been followed by a successful read-only (verification) call.
+    pub fn set_require_verification(&self, value: bool) {
+        self.require_verification.store(value, Ordering::SeqCst);
+    }
+
+    /// Whether mutating tool calls must be verified before finishing.
+    pub fn require_verification(&self) -> bool {
+        self.require_verification.load(Ordering::SeqCst)
+    }
+
+    /// Require final responses to cite the tool results that support them.
+    pub fn set_require_citations(&self, value: bool) {
+        self.require_citations.store(value, Ordering::SeqCst);
+    }
+
+    /// Whether final responses must cite tool results.
+    pub fn require_citations(&self) -> bool {
+        self.require_citations.load(Ordering::SeqCst)
+    }
+
+    /// Tool calls and outcome of the latest run for `session_key`, including
+    /// runs that failed or were cut short.
+    pub fn last_attempt(&self, session_key: &str) -> Option<AttemptLog> {
+        self.attempts.lock().get(session_key).cloned()
+    }
+
+    /// Citations of the last completed run for `session_key`, if any.
+    pub fn take_citations(&self, session_key: &str) -> Vec<Citation> {
+        self.last_citations
+            .lock()
+            .remove(session_key)
+            .unwrap_or_default()
+    }
+
+    /// Queue a note from the user for the run in progress on `session_key`.
+    /// It is added to the conversation before the next model call.
+    pub fn push_observation(&self, session_key: &str, text: &str) {
+        self.observations
+            .lock()
+            .entry(session_key.to_string())
+            .or_default()
+            .push(text.to_string());
+    }
+
+    /// Notes for `session_key` that no model call has picked up yet, e.g.
+    /// because the run finished first.
+    pub fn take_observations(&self, session_key: &str) -> Vec<String> {
+        self.observations
+            .lock()
+            .remove(session_key)
+            .unwrap_or_default()
+    }
+
     /// Execute agent loop for a user message.
     ///
     /// # Arguments
@@ -107,6 +297,11 @@ where
         let result = self
             .run_inner(session_key, agent_id, system_prompt, user_message)
             .await;
+        if let Err(e) = &result {
+            if let Some(attempt) = self.attempts.lock().get_mut(session_key) {
+                attempt.error = Some(e.to_string());
+            }
+        }
 
         // Release lock
         info!("Releasing lock for session: {}", session_key);
@@ -124,16 +319,49 @@ where
     ) -> Result<String, RuntimeError> {
         // Load session
         debug!("Loading session: {}", session_key);
-        let mut messages = self.session_store.load(session_key).await?;
+        let (loaded, base_version) = self.session_store.load_versioned(session_key).await?;
 
         // Compact if needed
-        messages = self.compactor.compact(messages)?;
+        let (mut messages, report) = self.compactor.compact_with_report(loaded.clone())?;
+        // A compacted history no longer starts with what the store holds, so
+        // the save merges this run's messages itself.
+        let rewritten = report.is_some().then_some((loaded, messages.len()));
+
+        let run_id = uuid::Uuid::new_v4().to_string();
+        if let Some(report) = report {
+            let compaction_id = uuid::Uuid::new_v4().to_string();
+            self.compacted_targets.lock().insert(
+                session_key.to_string(),
+                CompactedTargets {
+                    compaction_id: compaction_id.clone(),
+                    run_id: run_id.clone(),
+                    targets: report.targets.iter().cloned().collect(),
+                },
+            );
+            let event = CompactionEvent::Compacted {
+                session_key: session_key.to_string(),
+                compaction_id,
+                report,
+            };
+            let observer = self.compaction_observer.lock().clone();
+            if let Some(observer) = observer {
+                observer(&event);
+            }
+            #[cfg(feature = "telemetry")]
+            crate::telemetry::event(crate::telemetry::TelemetryEvent::Compaction(&event));
+        }
+        self.attempts
+            .lock()
+            .insert(session_key.to_string(), AttemptLog::new(&run_id));
 
         // Append user message
-        messages.push(Message::new(Role::User, json!(user_message)));
+        messages.push(
+            Message::new(Role::User, json!(user_message))
+                .with_provenance(Provenance::new(Origin::User).run(&run_id)),
+        );
 
-        // Get available tool schemas
-        let tool_schemas = self.tool_registry.get_tool_schemas(agent_id);
+        // Get available tool schemas, annotated with how long each tends to take
+        let tool_schemas = self.tool_schemas(agent_id);
 
         // CRITICAL: Fail early if no tools available
         if tool_schemas.is_empty() {
@@ -150,9 +378,10 @@ where
         );
 
         // Execute LLM loop
-        let final_response = self
+        let (final_response, citations) = self
             .execute_loop(
                 session_key,
+                &run_id,
                 system_prompt,
                 user_message,
                 &mut messages,
@@ -161,24 +390,93 @@ where
             .await?;
 
         // Append final response
-        messages.push(Message::new(Role::Assistant, json!(final_response.clone())));
+        let final_message = if citations.is_empty() {
+            Message::new(Role::Assistant, json!(final_response.clone()))
+        } else {
+            Message::with_metadata(
+                Role::Assistant,
+                json!(final_response.clone()),
+                json!({"citations": citations}),
+            )
+        };
+        messages
+            .push(final_message.with_provenance(Provenance::new(Origin::Assistant).run(&run_id)));
 
         // Save session
         debug!("Saving session: {}", session_key);
-        self.session_store.save(session_key, &messages).await?;
+        match rewritten {
+            None => {
+                self.session_store
+                    .save_versioned(session_key, &messages, base_version)
+                    .await?;
+            }
+            Some((loaded, own_from)) => {
+                self.save_compacted(session_key, &loaded, base_version, &messages, own_from)
+                    .await?
+            }
+        }
+        if self.require_citations() {
+            self.last_citations
+                .lock()
+                .insert(session_key.to_string(), citations);
+        }
 
         Ok(final_response)
     }
 
-    async fn execute_loop(
+    /// Saves `messages`, a history compacted from `loaded` whose own messages
+    /// start at `own_from`. The store merges concurrent writes onto the
+    /// history as loaded, which compaction rewrote, so when another writer
+    /// appended meanwhile their messages are put between the compacted
+    /// prefix and this run's messages here. Any other change to the stored
+    /// history is a `SessionConflict`.
+    async fn save_compacted(
         &self,
         session_key: &str,
+        loaded: &[Message],
+        mut base: SessionVersion,
+        messages: &[Message],
+        own_from: usize,
+    ) -> Result<(), RuntimeError> {
+        let mut merged = messages.to_vec();
+        let mut attempts = 0;
+        loop {
+            let conflict = match self
+                .session_store
+                .save_versioned(session_key, &merged, base)
+                .await
+            {
+                Err(conflict @ RuntimeError::SessionConflict { .. }) => conflict,
+                other => return other.map(|_| ()),
+            };
+            attempts += 1;
+            let (stored, current) = self.session_store.load_versioned(session_key).await?;
+            let appended_only =
+                stored.len() >= loaded.len() && same_history(&stored[..loaded.len()], loaded);
+            if !appended_only || attempts == COMPACTED_SAVE_ATTEMPTS {
+                return Err(conflict);
+            }
+            warn!(
+                "Merging concurrent writes into compacted session {} (version {} -> {})",
+                session_key, base.version, current.version
+            );
+            merged = [
+                &messages[..own_from],
+                &stored[loaded.len()..],
+                &messages[own_from..],
+            ]
+            .concat();
+            base = current;
+        }
+    }
+
+    /// The system prompt sent to the model: `system_prompt` reinforced with
+    /// the available tools and, when required, citation instructions.
+    pub fn compose_system_prompt(
+        &self,
         system_prompt: &str,
-        user_message: &str,
-        messages: &mut Vec<Message>,
         tool_schemas: &[serde_json::Value],
-    ) -> Result<String, RuntimeError> {
-        // Reinforce system prompt with tool capability
+    ) -> String {
         let tool_names: Vec<String> = tool_schemas
             .iter()
             .filter_map(|schema| {
@@ -190,11 +488,150 @@ where
             })
             .collect();
 
-        let reinforced_prompt = format!(
+        let mut reinforced_prompt = format!(
             "{}\n\nYou are a local autonomous Linux agent. You MUST use tools to perform file, process, wallpaper, or system operations. Do not describe actions — call the appropriate tool.\n\nAvailable tools: {}",
             system_prompt,
             tool_names.join(", ")
         );
+        if self.require_citations() {
+            reinforced_prompt.push_str("\n\n");
+            reinforced_prompt.push_str(citations::CITATION_INSTRUCTIONS);
+        }
+        reinforced_prompt
+    }
+
+    /// Tool schemas offered to `agent_id` on the next run, as
+    /// [`Self::run`] sends them.
+    pub fn tool_schemas(&self, agent_id: &str) -> Vec<serde_json::Value> {
+        let mut tool_schemas = self.tool_registry.get_tool_schemas(agent_id);
+        crate::metrics::annotate_schemas_with_latency(
+            &mut tool_schemas,
+            &crate::metrics::tool_latency_snapshot(),
+        );
+        tool_schemas
+    }
+
+    /// Composes the first request a [`Self::run`] with these arguments
+    /// would send, without taking the session lock, calling the model or
+    /// saving anything.
+    pub async fn preview_request(
+        &self,
+        session_key: &str,
+        agent_id: &str,
+        system_prompt: &str,
+        user_message: &str,
+    ) -> Result<RequestPreview, RuntimeError> {
+        let mut messages = self.session_store.load(session_key).await?;
+        let summarized = self.compactor.preview(&messages).summarized;
+        if summarized > 0 {
+            let newer = messages.split_off(summarized);
+            messages = vec![Message::new(
+                Role::System,
+                json!(format!("[summary of {summarized} older messages]")),
+            )];
+            messages.extend(newer);
+        }
+        messages.push(Message::new(Role::User, json!(user_message)));
+        let tool_schemas = self.tool_schemas(agent_id);
+        Ok(RequestPreview {
+            system_prompt: self.compose_system_prompt(system_prompt, &tool_schemas),
+            messages,
+            tool_schemas,
+            summarized,
+        })
+    }
+
+    /// Run `calls` one after another, keeping the ones that succeed within
+    /// [`prefetch::PREFETCH_TIMEOUT`].
+    async fn prefetch(
+        &self,
+        calls: Vec<PrefetchCall>,
+        session_key: &str,
+    ) -> Vec<(PrefetchCall, serde_json::Value)> {
+        let mut results = Vec::new();
+        if calls.is_empty() {
+            return results;
+        }
+        let run_all = async {
+            for call in calls {
+                debug!("Prefetching {}", call.tool_name);
+                match self
+                    .tool_dispatcher
+                    .execute(&call.tool_name, &call.input, session_key)
+                    .await
+                {
+                    Ok(result) if result.get("error").is_none() => results.push((call, result)),
+                    Ok(_) | Err(_) => debug!("Prefetch of {} failed", call.tool_name),
+                }
+            }
+        };
+        if tokio::time::timeout(prefetch::PREFETCH_TIMEOUT, run_all)
+            .await
+            .is_err()
+        {
+            warn!("Prefetch timed out; keeping {} results", results.len());
+        }
+        results
+    }
+
+    /// Reports a call that reads a target the session's last compaction
+    /// summarized away, once per target.
+    fn note_refetch(
+        &self,
+        session_key: &str,
+        run_id: &str,
+        tool_name: &str,
+        input: &serde_json::Value,
+    ) {
+        let Some(target) = compaction_events::read_target(tool_name, input) else {
+            return;
+        };
+        let event = {
+            let mut compacted = self.compacted_targets.lock();
+            let Some(entry) = compacted.get_mut(session_key) else {
+                return;
+            };
+            if !entry.targets.remove(&target) {
+                return;
+            }
+            CompactionEvent::Refetched {
+                session_key: session_key.to_string(),
+                compaction_id: entry.compaction_id.clone(),
+                tool: tool_name.to_string(),
+                target,
+                same_run: entry.run_id == run_id,
+            }
+        };
+        let observer = self.compaction_observer.lock().clone();
+        if let Some(observer) = observer {
+            observer(&event);
+        }
+        #[cfg(feature = "telemetry")]
+        crate::telemetry::event(crate::telemetry::TelemetryEvent::Compaction(&event));
+    }
+
+    /// What compaction would do to `messages` before the next run.
+    pub fn compaction_preview(&self, messages: &[Message]) -> crate::compactor::CompactionPreview {
+        self.compactor.preview(messages)
+    }
+
+    async fn execute_loop(
+        &self,
+        session_key: &str,
+        run_id: &str,
+        system_prompt: &str,
+        user_message: &str,
+        messages: &mut Vec<Message>,
+        tool_schemas: &[serde_json::Value],
+    ) -> Result<(String, Vec<Citation>), RuntimeError> {
+        let reinforced_prompt = self.compose_system_prompt(system_prompt, tool_schemas);
+        let require_citations = self.require_citations();
+        let offered_tools: Vec<String> = tool_schemas
+            .iter()
+            .filter_map(|schema| schema.pointer("/function/name").and_then(|n| n.as_str()))
+            .map(str::to_string)
+            .collect();
+        let mut evidence: Vec<Evidence> = Vec::new();
 
         let action_requires_tool = requires_tool_call_for_user_message(user_message);
         let mut saw_tool_call = false;
@@ -205,28 +642,95 @@ where
         let mut same_tool_signature_count = 0usize;
         let mut consecutive_tool_failures = 0usize;
         let max_iterations = self.max_iterations();
+        let require_verification = self.require_verification();
+        // Last successful mutating tool with no successful read-only call after it.
+        let mut unverified_mutation: Option<String> = None;
+        let prefetcher = self.prefetcher.lock().clone();
+        let mut pending_prefetch: Vec<PrefetchCall> = prefetcher
+            .map(|prefetcher| prefetcher(user_message))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|call| {
+                offered_tools.contains(&call.tool_name)
+                    && self.tool_registry.is_read_only(&call.tool_name)
+            })
+            .collect();
+        let mut prefetched: Vec<(PrefetchCall, serde_json::Value)> = Vec::new();
+        let context_refresh = self.context_refresh.lock().clone();
+        let mut context_at = Instant::now();
+        let mut divergence: Option<RefreshReason> = None;
 
         for iteration in 0..max_iterations {
             debug!("LLM loop iteration {}/{}", iteration + 1, max_iterations);
 
+            for note in self.take_observations(session_key) {
+                info!("Adding user note to run {}", run_id);
+                messages.push(
+                    Message::new(Role::User, json!(format!("[User note during run] {note}")))
+                        .with_provenance(Provenance::new(Origin::User).run(run_id)),
+                );
+            }
+
+            if let Some(ContextRefresh { refresher, max_age }) = &context_refresh {
+                let age = context_at.elapsed();
+                let reason = divergence
+                    .take()
+                    .or_else(|| (age >= *max_age).then_some(RefreshReason::Stale { age }));
+                if let Some(reason) = reason {
+                    context_at = Instant::now();
+                    if let Some(context) = refresher.refresh(session_key, &reason).await {
+                        info!(
+                            "Refreshing runtime context of run {} ({})",
+                            run_id,
+                            reason.describe()
+                        );
+                        messages.push(
+                            Message::new(
+                                Role::System,
+                                json!(format!(
+                                    "Runtime context refreshed ({}); it replaces the matching lines above:\n{}",
+                                    reason.describe(),
+                                    context
+                                )),
+                            )
+                            .with_provenance(Provenance::new(Origin::SystemInjector).run(run_id)),
+                        );
+                    }
+                }
+            }
+
             // Call LLM with reinforced prompt
             let llm_start = std::time::Instant::now();
 
-            let response = self
-                .llm_client
-                .call(&reinforced_prompt, messages, tool_schemas)
-                .await
-                .map_err(|e| {
-                    error!("LLM call failed: {}", e);
-                    e
-                })?;
+            let calls = std::mem::take(&mut pending_prefetch);
+            let (response, results) = tokio::join!(
+                self.llm_client
+                    .call(&reinforced_prompt, messages, tool_schemas),
+                self.prefetch(calls, session_key)
+            );
+            prefetched.extend(results);
+            let response = response.map_err(|e| {
+                error!("LLM call failed: {}", e);
+                e
+            })?;
 
             let llm_duration = llm_start.elapsed();
             info!("LLM call took {:?}", llm_duration);
+            let decision = ToolDecision::new(session_key, iteration + 1, &offered_tools, &response);
+            let observer = self.decision_observer.lock().clone();
+            if let Some(observer) = observer {
+                observer(&decision);
+            }
+            #[cfg(feature = "telemetry")]
+            crate::telemetry::event(crate::telemetry::TelemetryEvent::Decision(&decision));
 
             // Handle response type
             match response {
                 LLMResponse::Final { content, .. } => {
+                    let (content, trimmed) = self.guardrails.lock().apply(&content);
+                    if trimmed {
+                        warn!("Final response cut by output guardrails");
+                    }
                     if action_requires_tool && !saw_tool_call {
                         return Err(RuntimeError::ToolError(
                             "Tool invocation required but not performed".to_string(),
@@ -238,17 +742,88 @@ where
                                 .to_string(),
                         ));
                     }
-                    info!(
-                        "LLM returned final response after {} iterations",
-                        iteration + 1
-                    );
-                    return Ok(content);
+                    let last_iteration = iteration + 1 >= max_iterations;
+                    if require_verification {
+                        if let Some(tool) = &unverified_mutation {
+                            if last_iteration {
+                                return Err(RuntimeError::ToolError(format!(
+                                    "Action not verified: no check succeeded after '{tool}'"
+                                )));
+                            }
+                            warn!("Rejecting final response: '{}' was not verified", tool);
+                            push_rejection(
+                                messages,
+                                run_id,
+                                content,
+                                &format!(
+                                    "Your answer was rejected: '{tool}' changed the system but nothing verified the result. Call a read-only tool to confirm the post-condition (file exists, window present, process running) before answering."
+                                ),
+                            );
+                            continue;
+                        }
+                    }
+                    if !require_citations {
+                        info!(
+                            "LLM returned final response after {} iterations",
+                            iteration + 1
+                        );
+                        return Ok((content, Vec::new()));
+                    }
+                    match citations::validate(&content, &evidence) {
+                        Ok(cited) => {
+                            info!(
+                                "LLM returned final response citing {} tool results after {} iterations",
+                                cited.len(),
+                                iteration + 1
+                            );
+                            return Ok((content, cited));
+                        }
+                        Err(reason) if last_iteration => {
+                            return Err(RuntimeError::LLMError(format!(
+                                "Final response failed citation check: {reason}"
+                            )));
+                        }
+                        Err(reason) => {
+                            warn!("Rejecting uncited final response: {}", reason);
+                            push_rejection(
+                                messages,
+                                run_id,
+                                content,
+                                &format!(
+                                    "Your answer was rejected: {reason}. Rewrite it, citing the call id of the supporting tool result after each claim."
+                                ),
+                            );
+                            continue;
+                        }
+                    }
                 }
                 LLMResponse::ToolCall {
                     tool_name, input, ..
                 } => {
+                    let prefetch_hit = prefetch::take_result(&mut prefetched, &tool_name, &input);
+                    for (call, result) in std::mem::take(&mut prefetched) {
+                        info!(
+                            "Adding prefetched {} result to run {}",
+                            call.tool_name, run_id
+                        );
+                        tool_call_count += 1;
+                        let call_id = citations::call_id(tool_call_count);
+                        let item = Evidence {
+                            call_id: call_id.clone(),
+                            tool_name: call.tool_name,
+                            input: call.input,
+                            succeeded: true,
+                            output: result,
+                        };
+                        push_prefetched(messages, run_id, &item, require_citations);
+                        if let Some(attempt) = self.attempts.lock().get_mut(session_key) {
+                            attempt.tool_calls.push(item.clone());
+                        }
+                        evidence.push(item);
+                    }
                     saw_tool_call = true;
                     tool_call_count += 1;
+                    let call_id = citations::call_id(tool_call_count);
                     info!(
                         "Executing tool: {} (iteration {})",
                         tool_name,
@@ -269,38 +844,63 @@ where
                         )));
                     }
                     info!("LLM requested tool: {}", tool_name);
+                    self.note_refetch(session_key, run_id, &tool_name, &input);
 
                     // Append tool call message
-                    messages.push(Message::with_metadata(
-                        Role::Assistant,
-                        json!(format!("Calling tool: {}", tool_name)),
-                        json!({
-                            "tool_call": true,
-                            "tool_name": tool_name.clone(),
-                            "input": input.clone()
-                        }),
-                    ));
-
-                    // Execute tool
+                    messages.push(
+                        Message::with_metadata(
+                            Role::Assistant,
+                            json!(format!("Calling tool: {}", tool_name)),
+                            json!({
+                                "tool_call": true,
+                                "tool_name": tool_name.clone(),
+                                "input": input.clone()
+                            }),
+                        )
+                        .with_provenance(
+                            Provenance::new(Origin::Assistant)
+                                .tool(&tool_name)
+                                .run(run_id)
+                                .call(&call_id),
+                        ),
+                    );
+
+                    // Execute tool, unless it already ran as a prefetch
                     let mut tool_failed = false;
-                    let tool_result = match self
-                        .tool_dispatcher
-                        .execute(&tool_name, &input, session_key)
-                        .await
-                    {
-                        Ok(result) => result,
-                        Err(e) => {
-                            tool_failed = true;
-                            warn!("Tool execution failed: {}", e);
-                            json!({"error": e.to_string()})
-                        }
+                    let tool_result = if let Some(result) = prefetch_hit {
+                        info!("Using prefetched result for {}", tool_name);
+                        result
+                    } else {
+                        let result = match self
+                            .tool_dispatcher
+                            .execute(&tool_name, &input, session_key)
+                            .await
+                        {
+                            Ok(result) => result,
+                            Err(e) => {
+                                tool_failed = true;
+                                warn!("Tool execution failed: {}", e);
+                                json!({"error": e.to_string()})
+                            }
+                        };
+                        let tool_duration = tool_start.elapsed();
+                        info!("Tool {} took {:?}", tool_name, tool_duration);
+                        crate::metrics::record_tool_call(
+                            &tool_name,
+                            tool_duration.as_secs_f64() * 1000.0,
+                        );
+                        result
                     };
-                    let tool_duration = tool_start.elapsed();
-                    info!("Tool {} took {:?}", tool_name, tool_duration);
 
                     if let Some(err) = tool_result.get("error").and_then(|v| v.as_str()) {
                         tool_failed = true;
                         last_tool_error = Some(err.to_string());
+                        if context_refresh::diverged(err) {
+                            divergence = Some(RefreshReason::Diverged {
+                                tool: tool_name.clone(),
+                                error: err.to_string(),
+                            });
+                        }
                     }
                     if tool_failed {
                         consecutive_tool_failures += 1;
@@ -308,6 +908,11 @@ where
                         consecutive_tool_failures = 0;
                         successful_tool_calls += 1;
                         last_tool_error = None;
+                        if self.tool_registry.is_read_only(&tool_name) {
+                            unverified_mutation = None;
+                        } else {
+                            unverified_mutation = Some(tool_name.clone());
+                        }
                     }
                     if consecutive_tool_failures >= 4 {
                         return Err(RuntimeError::ToolError(
@@ -316,11 +921,38 @@ where
                     }
 
                     // Append tool result
-                    messages.push(Message::with_metadata(
-                        Role::Tool,
-                        tool_result,
-                        json!({"tool_name": tool_name}),
-                    ));
+                    let truncated = ["truncated", "row_cap_hit"]
+                        .iter()
+                        .any(|flag| tool_result.get(flag).and_then(|v| v.as_bool()) == Some(true));
+                    let provenance = Provenance::new(Origin::Tool)
+                        .tool(&tool_name)
+                        .run(run_id)
+                        .call(&call_id)
+                        .truncated(truncated);
+                    let item = Evidence {
+                        call_id: call_id.clone(),
+                        tool_name: tool_name.clone(),
+                        input: input.clone(),
+                        succeeded: !tool_failed,
+                        output: tool_result.clone(),
+                    };
+                    if let Some(attempt) = self.attempts.lock().get_mut(session_key) {
+                        attempt.tool_calls.push(item.clone());
+                    }
+                    evidence.push(item);
+                    let content = if require_citations {
+                        json!({"call_id": call_id, "result": tool_result})
+                    } else {
+                        tool_result
+                    };
+                    messages.push(
+                        Message::with_metadata(
+                            Role::Tool,
+                            content,
+                            json!({"tool_name": tool_name}),
+                        )
+                        .with_provenance(provenance),
+                    );
 
                     // Continue loop
                 }
@@ -345,6 +977,70 @@ where
     }
 }
 
+/// Add a prefetched call and its result as if the model had made the call.
+fn push_prefetched(
+    messages: &mut Vec<Message>,
+    run_id: &str,
+    item: &Evidence,
+    require_citations: bool,
+) {
+    let provenance = Provenance::new(Origin::Tool)
+        .tool(&item.tool_name)
+        .run(run_id)
+        .call(&item.call_id);
+    messages.push(
+        Message::with_metadata(
+            Role::Assistant,
+            json!(format!("Calling tool: {}", item.tool_name)),
+            json!({
+                "tool_call": true,
+                "tool_name": item.tool_name.clone(),
+                "input": item.input.clone(),
+                "prefetched": true
+            }),
+        )
+        .with_provenance(
+            Provenance::new(Origin::Assistant)
+                .tool(&item.tool_name)
+                .run(run_id)
+                .call(&item.call_id),
+        ),
+    );
+    let content = if require_citations {
+        json!({"call_id": item.call_id, "result": item.output})
+    } else {
+        item.output.clone()
+    };
+    messages.push(
+        Message::with_metadata(
+            Role::Tool,
+            content,
+            json!({"tool_name": item.tool_name, "prefetched": true}),
+        )
+        .with_provenance(provenance),
+    );
+}
+
+/// Whether `a` and `b` hold the same messages, compared as stored.
+fn same_history(a: &[Message], b: &[Message]) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .all(|(a, b)| serde_json::to_value(a).ok() == serde_json::to_value(b).ok())
+}
+
+/// Keep a rejected final answer in the transcript and tell the model why.
+fn push_rejection(messages: &mut Vec<Message>, run_id: &str, content: String, reason: &str) {
+    messages.push(
+        Message::new(Role::Assistant, json!(content))
+            .with_provenance(Provenance::new(Origin::Assistant).run(run_id)),
+    );
+    messages.push(
+        Message::new(Role::System, json!(reason))
+            .with_provenance(Provenance::new(Origin::SystemInjector).run(run_id)),
+    );
+}
+
 fn requires_tool_call_for_user_message(user_message: &str) -> bool {
     let lower = user_message.to_lowercase();
     let action_tokens = [
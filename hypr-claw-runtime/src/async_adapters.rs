@@ -1,5 +1,6 @@
 use crate::interfaces::{
     LockManager as LockManagerTrait, RuntimeError, SessionStore as SessionStoreTrait,
+    SessionVersion,
 };
 use crate::types::Message;
 use async_trait::async_trait;
@@ -8,16 +9,46 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 /// Async wrapper for sync SessionStore
+///
+/// Tracks an in-process write version per session so that runs sharing this
+/// store (the REPL foreground run and background tasks) cannot silently
+/// overwrite each other's history.
 pub struct AsyncSessionStore {
     inner: Arc<hypr_claw::infra::session_store::SessionStore>,
+    versions: Arc<Mutex<HashMap<String, u64>>>,
 }
 
 impl AsyncSessionStore {
     pub fn new(inner: Arc<hypr_claw::infra::session_store::SessionStore>) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            versions: Arc::new(Mutex::new(HashMap::new())),
+        }
     }
 }
 
+fn to_values(messages: &[Message]) -> Vec<serde_json::Value> {
+    messages
+        .iter()
+        .filter_map(|m| serde_json::to_value(m).ok())
+        .collect()
+}
+
+fn load_values(
+    inner: &hypr_claw::infra::session_store::SessionStore,
+    key: &str,
+) -> Result<Vec<serde_json::Value>, RuntimeError> {
+    let values = inner
+        .load(key)
+        .map_err(|e| RuntimeError::SessionError(e.to_string()))?;
+    // Normalise through Message so stored and in-memory histories compare equal.
+    let messages: Vec<Message> = values
+        .into_iter()
+        .filter_map(|v| serde_json::from_value(v).ok())
+        .collect();
+    Ok(to_values(&messages))
+}
+
 #[async_trait]
 impl SessionStoreTrait for AsyncSessionStore {
     async fn load(&self, session_key: &str) -> Result<Vec<Message>, RuntimeError> {
@@ -41,16 +72,101 @@ impl SessionStoreTrait for AsyncSessionStore {
 
     async fn save(&self, session_key: &str, messages: &[Message]) -> Result<(), RuntimeError> {
         let inner = self.inner.clone();
+        let versions = self.versions.clone();
         let key = session_key.to_string();
-        let msgs: Vec<serde_json::Value> = messages
-            .iter()
-            .filter_map(|m| serde_json::to_value(m).ok())
-            .collect();
+        let msgs = to_values(messages);
 
         tokio::task::spawn_blocking(move || {
+            let mut versions = versions.lock();
             inner
                 .save(&key, &msgs)
-                .map_err(|e| RuntimeError::SessionError(e.to_string()))
+                .map_err(|e| RuntimeError::SessionError(e.to_string()))?;
+            *versions.entry(key).or_insert(0) += 1;
+            Ok(())
+        })
+        .await
+        .map_err(|e| RuntimeError::SessionError(e.to_string()))?
+    }
+
+    async fn load_versioned(
+        &self,
+        session_key: &str,
+    ) -> Result<(Vec<Message>, SessionVersion), RuntimeError> {
+        let inner = self.inner.clone();
+        let versions = self.versions.clone();
+        let key = session_key.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let versions = versions.lock();
+            let values = load_values(&inner, &key)?;
+            let messages: Vec<Message> = values
+                .into_iter()
+                .filter_map(|v| serde_json::from_value(v).ok())
+                .collect();
+            let version = SessionVersion {
+                version: versions.get(&key).copied().unwrap_or(0),
+                len: messages.len(),
+            };
+            Ok((messages, version))
+        })
+        .await
+        .map_err(|e| RuntimeError::SessionError(e.to_string()))?
+    }
+
+    /// Saves `messages` if nobody wrote since `base`. Otherwise, if both this
+    /// history and the stored one still start with the `base.len` messages
+    /// that were loaded, the new messages are appended after the other
+    /// writer's; any other divergence is a `SessionConflict`.
+    async fn save_versioned(
+        &self,
+        session_key: &str,
+        messages: &[Message],
+        base: SessionVersion,
+    ) -> Result<SessionVersion, RuntimeError> {
+        let inner = self.inner.clone();
+        let versions = self.versions.clone();
+        let key = session_key.to_string();
+        let msgs = to_values(messages);
+
+        tokio::task::spawn_blocking(move || {
+            let mut versions = versions.lock();
+            let current = versions.get(&key).copied().unwrap_or(0);
+
+            let to_write = if current == base.version {
+                msgs
+            } else {
+                let stored = load_values(&inner, &key)?;
+                let shared = base.len;
+                let mergeable = stored.len() >= shared
+                    && msgs.len() >= shared
+                    && stored[..shared] == msgs[..shared];
+                if !mergeable {
+                    return Err(RuntimeError::SessionConflict {
+                        session_key: key,
+                        expected: base.version,
+                        found: current,
+                    });
+                }
+                tracing::warn!(
+                    "Merging concurrent writes to session {} (version {} -> {})",
+                    key,
+                    base.version,
+                    current
+                );
+                let mut merged = stored;
+                merged.extend_from_slice(&msgs[shared..]);
+                merged
+            };
+
+            inner
+                .save(&key, &to_write)
+                .map_err(|e| RuntimeError::SessionError(e.to_string()))?;
+            let version = current + 1;
+            versions.insert(key, version);
+            Ok(SessionVersion {
+                version,
+                len: to_write.len(),
+            })
         })
         .await
         .map_err(|e| RuntimeError::SessionError(e.to_string()))?
@@ -0,0 +1,67 @@
+//! What a run did, kept so a retry can build on it instead of repeating it.
+
+use crate::citations::{excerpt, Evidence};
+
+/// Tool calls and outcome of one run of the agent loop.
+#[derive(Debug, Clone, Default)]
+pub struct AttemptLog {
+    pub run_id: String,
+    pub tool_calls: Vec<Evidence>,
+    /// Why the run stopped, if it failed inside the loop. Runs cut short by
+    /// an interrupt or watchdog leave this empty.
+    pub error: Option<String>,
+}
+
+impl AttemptLog {
+    pub fn new(run_id: impl Into<String>) -> Self {
+        Self {
+            run_id: run_id.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Plain-text summary of the calls, failures and completed steps.
+    pub fn render(&self) -> String {
+        let mut lines = vec![match &self.error {
+            Some(error) => format!("Previous attempt (run {}) failed: {}", self.run_id, error),
+            None => format!("Previous attempt (run {}) did not finish.", self.run_id),
+        }];
+
+        if self.tool_calls.is_empty() {
+            lines.push("It made no tool calls.".to_string());
+            return lines.join("\n");
+        }
+
+        lines.push(format!("Tool calls ({}):", self.tool_calls.len()));
+        for call in &self.tool_calls {
+            lines.push(format!(
+                "  {} {} {} {} -> {}",
+                call.call_id,
+                if call.succeeded { "ok" } else { "FAILED" },
+                call.tool_name,
+                excerpt(&call.input),
+                excerpt(&call.output)
+            ));
+        }
+
+        let completed: Vec<String> = self
+            .tool_calls
+            .iter()
+            .filter(|call| call.succeeded)
+            .map(|call| format!("{} {}", call.call_id, call.tool_name))
+            .collect();
+        lines.push(if completed.is_empty() {
+            "Completed steps: none".to_string()
+        } else {
+            format!("Completed steps: {}", completed.join(", "))
+        });
+        lines.join("\n")
+    }
+}
+
+/// `prompt` followed by a previous attempt's summary and how to use it.
+pub fn with_previous_attempt(prompt: &str, summary: &str) -> String {
+    format!(
+        "{prompt}\n\n{summary}\nDo not repeat a failed call with the same input; keep the completed steps and continue from there."
+    )
+}
@@ -0,0 +1,359 @@
+//! Long-run soak harness.
+//!
+//! Runs scripted tasks through a real [`AgentLoop`] with file-backed session
+//! and lock stores against a local fake provider, sampling resident memory,
+//! open file descriptors and the size of the state directory as it goes.
+//! Exits non-zero when memory or descriptors grow past the allowed budget.
+//!
+//! ```text
+//! cargo run -p hypr-claw-runtime --features chaos --bin soak -- --tasks 2000 --duration 3h
+//! ```
+
+use hypr_claw_runtime::chaos::{
+    tool_then_final, Chaos, ChaosConfig, ChaosToolDispatcher, FakeProvider,
+};
+use hypr_claw_runtime::{
+    AgentLoop, AsyncLockManager, AsyncSessionStore, Compactor, LLMClient, LLMClientType, Message,
+    RuntimeError, Summarizer, ToolDispatcher, ToolRegistry,
+};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "usage: soak [--tasks N] [--duration 90s|30m|2h] [--sessions N] [--dir PATH]
+            [--report-every N] [--pace-ms N] [--seed N] [--tool-failure P]
+            [--llm-timeout P] [--rate-limit P] [--max-rss-growth-mb N] [--max-fd-growth N]";
+
+/// Prompts cycled through by the scripted tasks.
+const TASKS: &[&str] = &[
+    "open a terminal on workspace 2",
+    "list the files in my downloads folder",
+    "how much memory is free",
+    "remind me to stretch in ten minutes",
+    "switch to workspace 3 and focus firefox",
+    "summarize the last error in the audit log",
+];
+
+#[derive(Debug, Clone)]
+struct Options {
+    tasks: usize,
+    duration: Option<Duration>,
+    sessions: usize,
+    dir: PathBuf,
+    report_every: usize,
+    pace: Duration,
+    chaos: ChaosConfig,
+    max_rss_growth_kb: u64,
+    max_fd_growth: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            tasks: 500,
+            duration: None,
+            sessions: 8,
+            dir: std::env::temp_dir().join(format!("hypr-claw-soak-{}", std::process::id())),
+            report_every: 50,
+            pace: Duration::ZERO,
+            chaos: ChaosConfig {
+                tool_failure: 0.05,
+                llm_timeout: 0.0,
+                rate_limit: 0.0,
+                stall: Duration::from_secs(2),
+                ..ChaosConfig::default()
+            },
+            max_rss_growth_kb: 64 * 1024,
+            max_fd_growth: 16,
+        }
+    }
+}
+
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (number, unit) = value
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or((value, ""), |at| value.split_at(at));
+    let number: u64 = number.parse().ok()?;
+    let seconds = match unit {
+        "" | "s" => number,
+        "m" => number * 60,
+        "h" => number * 3600,
+        _ => return None,
+    };
+    Some(Duration::from_secs(seconds))
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        let value = iter.next().ok_or_else(|| format!("{flag} needs a value"))?;
+        let bad = || format!("invalid value for {flag}: {value}");
+        let number = || value.parse::<usize>().map_err(|_| bad());
+        let probability = || {
+            value
+                .parse::<f64>()
+                .ok()
+                .filter(|p| (0.0..=1.0).contains(p))
+                .ok_or_else(bad)
+        };
+        match flag.as_str() {
+            "--tasks" => options.tasks = number()?,
+            "--duration" => options.duration = Some(parse_duration(value).ok_or_else(bad)?),
+            "--sessions" => options.sessions = number()?.max(1),
+            "--dir" => options.dir = PathBuf::from(value),
+            "--report-every" => options.report_every = number()?.max(1),
+            "--pace-ms" => options.pace = Duration::from_millis(number()? as u64),
+            "--seed" => options.chaos.seed = value.parse().map_err(|_| bad())?,
+            "--tool-failure" => options.chaos.tool_failure = probability()?,
+            "--llm-timeout" => options.chaos.llm_timeout = probability()?,
+            "--rate-limit" => options.chaos.rate_limit = probability()?,
+            "--max-rss-growth-mb" => options.max_rss_growth_kb = number()? as u64 * 1024,
+            "--max-fd-growth" => options.max_fd_growth = number()?,
+            _ => return Err(format!("unknown option {flag}")),
+        }
+    }
+    Ok(options)
+}
+
+/// One measurement of the process and its state directory.
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    task: usize,
+    elapsed: Duration,
+    rss_kb: u64,
+    open_fds: usize,
+    state_bytes: u64,
+    largest_file_bytes: u64,
+    task_ms: f64,
+}
+
+fn rss_kb() -> u64 {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix("VmRSS:"))
+                .and_then(|v| v.split_whitespace().next())
+                .and_then(|v| v.parse().ok())
+        })
+        .unwrap_or(0)
+}
+
+fn open_fds() -> usize {
+    std::fs::read_dir("/proc/self/fd").map_or(0, |entries| entries.count())
+}
+
+/// Total and largest file size under `dir`.
+fn state_size(dir: &Path) -> (u64, u64) {
+    let mut total = 0;
+    let mut largest = 0;
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                pending.push(entry.path());
+            } else {
+                total += meta.len();
+                largest = largest.max(meta.len());
+            }
+        }
+    }
+    (total, largest)
+}
+
+struct EchoDispatcher;
+
+#[async_trait::async_trait]
+impl ToolDispatcher for EchoDispatcher {
+    async fn execute(
+        &self,
+        tool_name: &str,
+        input: &Value,
+        _session_key: &str,
+    ) -> Result<Value, RuntimeError> {
+        Ok(json!({"tool": tool_name, "input": input, "output": "ok"}))
+    }
+}
+
+struct SoakTools;
+
+impl ToolRegistry for SoakTools {
+    fn get_active_tools(&self, _agent_id: &str) -> Vec<String> {
+        vec!["echo".to_string()]
+    }
+
+    fn get_tool_schemas(&self, _agent_id: &str) -> Vec<Value> {
+        vec![json!({
+            "type": "function",
+            "function": {
+                "name": "echo",
+                "description": "Echo the input",
+                "parameters": {"type": "object", "properties": {}}
+            }
+        })]
+    }
+}
+
+struct CountingSummarizer;
+
+impl Summarizer for CountingSummarizer {
+    fn summarize(&self, messages: &[Message]) -> Result<String, RuntimeError> {
+        Ok(format!("Summary of {} messages", messages.len()))
+    }
+}
+
+fn print_sample(sample: &Sample, failures: usize) {
+    println!(
+        "{:>7} {:>9.0}s {:>10} {:>6} {:>12} {:>12} {:>9.1} {:>8}",
+        sample.task,
+        sample.elapsed.as_secs_f64(),
+        sample.rss_kb,
+        sample.open_fds,
+        sample.state_bytes,
+        sample.largest_file_bytes,
+        sample.task_ms,
+        failures
+    );
+}
+
+async fn soak(options: Options) -> Result<bool, Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(&options.dir)?;
+    let chaos = Chaos::new(options.chaos);
+    let provider = FakeProvider::start(chaos.clone(), tool_then_final()).await?;
+    let client =
+        LLMClient::new(provider.url().to_string(), 2).with_request_timeout(options.chaos.stall / 2);
+    let sessions =
+        hypr_claw::infra::session_store::SessionStore::new(options.dir.join("sessions"))?;
+    let locks = hypr_claw::infra::lock_manager::LockManager::new(Duration::from_secs(30));
+    let agent = AgentLoop::new(
+        Arc::new(AsyncSessionStore::new(Arc::new(sessions))),
+        Arc::new(AsyncLockManager::new(Arc::new(locks))),
+        Arc::new(ChaosToolDispatcher::new(
+            Arc::new(EchoDispatcher),
+            chaos.clone(),
+        )),
+        Arc::new(SoakTools),
+        LLMClientType::Standard(client),
+        Compactor::new(4000, CountingSummarizer),
+        10,
+    );
+
+    println!("soak: state in {}", options.dir.display());
+    println!(
+        "{:>7} {:>10} {:>10} {:>6} {:>12} {:>12} {:>9} {:>8}",
+        "task", "elapsed", "rss_kb", "fds", "state_bytes", "largest", "task_ms", "failed"
+    );
+    let started = Instant::now();
+    let mut samples: Vec<Sample> = Vec::new();
+    let mut failures = 0;
+    let mut window = Duration::ZERO;
+    let mut window_tasks = 0;
+    for task in 1..=options.tasks {
+        if options
+            .duration
+            .is_some_and(|limit| started.elapsed() >= limit)
+        {
+            break;
+        }
+        let session = format!("soak-{}", task % options.sessions);
+        let prompt = TASKS[task % TASKS.len()];
+        let task_started = Instant::now();
+        if agent.run(&session, "soak", "", prompt).await.is_err() {
+            failures += 1;
+        }
+        window += task_started.elapsed();
+        window_tasks += 1;
+
+        if task % options.report_every == 0 || task == options.tasks {
+            let (state_bytes, largest_file_bytes) = state_size(&options.dir);
+            let sample = Sample {
+                task,
+                elapsed: started.elapsed(),
+                rss_kb: rss_kb(),
+                open_fds: open_fds(),
+                state_bytes,
+                largest_file_bytes,
+                task_ms: window.as_secs_f64() * 1000.0 / window_tasks as f64,
+            };
+            print_sample(&sample, failures);
+            samples.push(sample);
+            window = Duration::ZERO;
+            window_tasks = 0;
+        }
+        if !options.pace.is_zero() {
+            tokio::time::sleep(options.pace).await;
+        }
+    }
+
+    // The first sample includes one-off allocations (TLS, connection pool),
+    // so growth is measured from it rather than from process start.
+    let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+        println!("soak: no samples taken");
+        return Ok(true);
+    };
+    let rss_growth = last.rss_kb.saturating_sub(first.rss_kb);
+    let fd_growth = last.open_fds.saturating_sub(first.open_fds);
+    let counts = chaos.stats();
+    println!(
+        "soak: {} tasks in {:.0}s, {} failed; injected {} tool failures, {} timeouts, {} rate limits",
+        last.task,
+        last.elapsed.as_secs_f64(),
+        failures,
+        counts.tool_failures,
+        counts.timed_out,
+        counts.rate_limited
+    );
+    println!(
+        "soak: rss +{} KiB, fds +{}, state {} bytes, task time {:.1}ms -> {:.1}ms",
+        rss_growth, fd_growth, last.state_bytes, first.task_ms, last.task_ms
+    );
+    let mut ok = true;
+    if rss_growth > options.max_rss_growth_kb {
+        println!(
+            "soak: FAIL memory grew {} KiB (budget {} KiB)",
+            rss_growth, options.max_rss_growth_kb
+        );
+        ok = false;
+    }
+    if fd_growth > options.max_fd_growth {
+        println!(
+            "soak: FAIL {} file descriptors leaked (budget {})",
+            fd_growth, options.max_fd_growth
+        );
+        ok = false;
+    }
+    Ok(ok)
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{USAGE}");
+        return;
+    }
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("soak: {e}\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    match soak(options).await {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("soak: {e}");
+            std::process::exit(2);
+        }
+    }
+}
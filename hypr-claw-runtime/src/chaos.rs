@@ -0,0 +1,288 @@
+//! Fault injection for resilience tests (`chaos` feature).
+//!
+//! [`FakeProvider`] is a local LLM endpoint that answers with scripted
+//! responses but, with configurable probabilities, returns HTTP 429 or stalls
+//! until the client times out. [`ChaosToolDispatcher`] wraps a dispatcher and
+//! fails a share of tool calls. Both draw from one seeded generator, so a
+//! failing seed replays the same fault sequence.
+
+use crate::interfaces::{RuntimeError, ToolDispatcher};
+use crate::types::{LLMResponse, Message, Role, SCHEMA_VERSION};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// Fault probabilities, each in `0.0..=1.0`.
+#[derive(Debug, Clone, Copy)]
+pub struct ChaosConfig {
+    pub seed: u64,
+    pub tool_failure: f64,
+    pub llm_timeout: f64,
+    pub rate_limit: f64,
+    /// How long a timed-out request hangs before the connection is dropped.
+    /// Set the client timeout below this.
+    pub stall: Duration,
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0x5eed,
+            tool_failure: 0.0,
+            llm_timeout: 0.0,
+            rate_limit: 0.0,
+            stall: Duration::from_secs(2),
+        }
+    }
+}
+
+/// xorshift64*: small, seedable and good enough to pick faults.
+struct ChaosRng(u64);
+
+impl ChaosRng {
+    fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        let value = self.0.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        (value >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        probability > 0.0 && self.next_f64() < probability
+    }
+}
+
+/// Counts of injected faults and served requests.
+#[derive(Debug, Default)]
+struct ChaosStats {
+    llm_requests: AtomicUsize,
+    rate_limited: AtomicUsize,
+    timed_out: AtomicUsize,
+    tool_calls: AtomicUsize,
+    tool_failures: AtomicUsize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ChaosCounts {
+    pub llm_requests: usize,
+    pub rate_limited: usize,
+    pub timed_out: usize,
+    pub tool_calls: usize,
+    pub tool_failures: usize,
+}
+
+impl ChaosStats {
+    fn snapshot(&self) -> ChaosCounts {
+        ChaosCounts {
+            llm_requests: self.llm_requests.load(Ordering::Relaxed),
+            rate_limited: self.rate_limited.load(Ordering::Relaxed),
+            timed_out: self.timed_out.load(Ordering::Relaxed),
+            tool_calls: self.tool_calls.load(Ordering::Relaxed),
+            tool_failures: self.tool_failures.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Shared generator and counters for one chaos run.
+#[derive(Clone)]
+pub struct Chaos {
+    config: ChaosConfig,
+    rng: Arc<Mutex<ChaosRng>>,
+    stats: Arc<ChaosStats>,
+}
+
+impl Chaos {
+    pub fn new(config: ChaosConfig) -> Self {
+        Self {
+            config,
+            rng: Arc::new(Mutex::new(ChaosRng::new(config.seed))),
+            stats: Arc::new(ChaosStats::default()),
+        }
+    }
+
+    pub fn config(&self) -> ChaosConfig {
+        self.config
+    }
+
+    pub fn stats(&self) -> ChaosCounts {
+        self.stats.snapshot()
+    }
+
+    fn chance(&self, probability: f64) -> bool {
+        self.rng.lock().chance(probability)
+    }
+}
+
+/// Decides the model's reply to a request's message history.
+pub type Responder = Arc<dyn Fn(&[Message], &[Value]) -> LLMResponse + Send + Sync>;
+
+/// Calls the first offered tool once, then answers.
+pub fn tool_then_final() -> Responder {
+    Arc::new(|messages, tools| {
+        let last_is_tool = messages.last().is_some_and(|m| m.role == Role::Tool);
+        let tool = tools
+            .first()
+            .and_then(|t| t["function"]["name"].as_str())
+            .map(str::to_string);
+        match tool {
+            Some(tool_name) if !last_is_tool => LLMResponse::ToolCall {
+                schema_version: SCHEMA_VERSION,
+                tool_name,
+                input: json!({"step": messages.len()}),
+                rationale: None,
+            },
+            _ => LLMResponse::Final {
+                schema_version: SCHEMA_VERSION,
+                content: "done".to_string(),
+            },
+        }
+    })
+}
+
+/// Local LLM endpoint speaking the runtime's own request format (a client
+/// without a model set). Stops serving when dropped.
+pub struct FakeProvider {
+    url: String,
+    server: JoinHandle<()>,
+}
+
+impl FakeProvider {
+    pub async fn start(chaos: Chaos, responder: Responder) -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let server = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let chaos = chaos.clone();
+                let responder = responder.clone();
+                tokio::spawn(async move {
+                    let _ = serve(stream, chaos, responder).await;
+                });
+            }
+        });
+        Ok(Self { url, server })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Drop for FakeProvider {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+async fn serve(mut stream: TcpStream, chaos: Chaos, responder: Responder) -> std::io::Result<()> {
+    let Some(body) = read_request_body(&mut stream).await? else {
+        return Ok(());
+    };
+    chaos.stats.llm_requests.fetch_add(1, Ordering::Relaxed);
+
+    if chaos.chance(chaos.config.llm_timeout) {
+        chaos.stats.timed_out.fetch_add(1, Ordering::Relaxed);
+        tokio::time::sleep(chaos.config.stall).await;
+        return Ok(());
+    }
+    let (status, reply) = if chaos.chance(chaos.config.rate_limit) {
+        chaos.stats.rate_limited.fetch_add(1, Ordering::Relaxed);
+        (
+            "429 Too Many Requests",
+            json!({"error": {"message": "Too many requests, retry in 1s"}}),
+        )
+    } else {
+        let request: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+        let messages: Vec<Message> =
+            serde_json::from_value(request["messages"].clone()).unwrap_or_default();
+        let tools: Vec<Value> =
+            serde_json::from_value(request["tools"].clone()).unwrap_or_default();
+        let response = responder(&messages, &tools);
+        (
+            "200 OK",
+            serde_json::to_value(response).unwrap_or(Value::Null),
+        )
+    };
+    let reply = reply.to_string();
+    let head = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        reply.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(reply.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Body of one HTTP request, or `None` if the client hung up first.
+async fn read_request_body(stream: &mut TcpStream) -> std::io::Result<Option<Vec<u8>>> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let header_end = loop {
+        if let Some(at) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break at + 4;
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_lowercase();
+    let length = head
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length:"))
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    while buf.len() < header_end + length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    Ok(Some(buf[header_end..header_end + length].to_vec()))
+}
+
+/// Fails a share of tool calls before they reach `inner`.
+pub struct ChaosToolDispatcher<D> {
+    inner: Arc<D>,
+    chaos: Chaos,
+}
+
+impl<D: ToolDispatcher> ChaosToolDispatcher<D> {
+    pub fn new(inner: Arc<D>, chaos: Chaos) -> Self {
+        Self { inner, chaos }
+    }
+}
+
+#[async_trait]
+impl<D: ToolDispatcher> ToolDispatcher for ChaosToolDispatcher<D> {
+    async fn execute(
+        &self,
+        tool_name: &str,
+        input: &Value,
+        session_key: &str,
+    ) -> Result<Value, RuntimeError> {
+        self.chaos.stats.tool_calls.fetch_add(1, Ordering::Relaxed);
+        if self.chaos.chance(self.chaos.config.tool_failure) {
+            self.chaos
+                .stats
+                .tool_failures
+                .fetch_add(1, Ordering::Relaxed);
+            return Err(RuntimeError::ToolError(format!(
+                "chaos: injected failure in {tool_name}"
+            )));
+        }
+        self.inner.execute(tool_name, input, session_key).await
+    }
+}
@@ -0,0 +1,111 @@
+//! Citations from a final response back to the tool calls that support it.
+//!
+//! Each tool call in a run gets an id (`t1`, `t2`, ...). When citations are
+//! required the model must end every claim with the id of a successful call,
+//! e.g. `Created notes.txt [t1]`, and the runtime rejects answers that cite
+//! nothing or cite calls that failed or never happened.
+
+use serde::{Deserialize, Serialize};
+
+/// Instructions appended to the system prompt when citations are required.
+pub const CITATION_INSTRUCTIONS: &str = "Every tool result is wrapped as {\"call_id\": \"tN\", \"result\": ...}. In your final answer, end each claim about what you did or found with the call id of a successful tool result that supports it, in square brackets, e.g. \"Created notes.txt [t1]\". Never cite a failed call, and do not claim actions no tool result supports.";
+
+/// Maximum characters of tool input or output kept in an excerpt.
+const EXCERPT_CHARS: usize = 80;
+
+/// A tool result produced during a run.
+#[derive(Debug, Clone)]
+pub struct Evidence {
+    pub call_id: String,
+    pub tool_name: String,
+    pub input: serde_json::Value,
+    pub succeeded: bool,
+    pub output: serde_json::Value,
+}
+
+/// A reference from the final response to one tool result.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Citation {
+    pub call_id: String,
+    pub tool_name: String,
+    /// Short, single-line rendering of the cited output.
+    pub excerpt: String,
+}
+
+/// Id for the `index`-th (1-based) tool call of a run.
+pub fn call_id(index: usize) -> String {
+    format!("t{index}")
+}
+
+/// Call ids cited in `text`, in first-seen order without duplicates.
+pub fn extract_call_ids(text: &str) -> Vec<String> {
+    let mut ids: Vec<String> = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("[t") {
+        let after = &rest[start + 2..];
+        let digits = after.chars().take_while(|c| c.is_ascii_digit()).count();
+        if digits > 0 && after[digits..].starts_with(']') {
+            let id = format!("t{}", &after[..digits]);
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        rest = after;
+    }
+    ids
+}
+
+/// Check the citations in `response` against the run's evidence.
+///
+/// A run that produced no successful tool results has nothing to cite and
+/// passes as long as it cites nothing.
+pub fn validate(response: &str, evidence: &[Evidence]) -> Result<Vec<Citation>, String> {
+    let cited = extract_call_ids(response);
+    let has_support = evidence.iter().any(|e| e.succeeded);
+    if cited.is_empty() {
+        if has_support {
+            return Err("response cites no tool results".to_string());
+        }
+        return Ok(Vec::new());
+    }
+
+    let mut citations = Vec::with_capacity(cited.len());
+    for id in cited {
+        let Some(item) = evidence.iter().find(|e| e.call_id == id) else {
+            return Err(format!("response cites unknown call [{id}]"));
+        };
+        if !item.succeeded {
+            return Err(format!(
+                "response cites [{id}] ({}), which failed",
+                item.tool_name
+            ));
+        }
+        citations.push(Citation {
+            call_id: id,
+            tool_name: item.tool_name.clone(),
+            excerpt: excerpt(&item.output),
+        });
+    }
+    Ok(citations)
+}
+
+/// Footnote lines for `citations`, one per cited call.
+pub fn render_footnotes(citations: &[Citation]) -> Vec<String> {
+    citations
+        .iter()
+        .map(|c| format!("[{}] {}: {}", c.call_id, c.tool_name, c.excerpt))
+        .collect()
+}
+
+pub(crate) fn excerpt(output: &serde_json::Value) -> String {
+    let text = match output {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() <= EXCERPT_CHARS {
+        return flat;
+    }
+    let cut: String = flat.chars().take(EXCERPT_CHARS).collect();
+    format!("{cut}...")
+}
@@ -1,7 +1,11 @@
 //! Codex provider adapter - bridges runtime and providers crate type systems.
+//!
+//! Tool calls cross the Codex backend as text, in the envelopes of
+//! [`crate::codex_protocol`].
 
+use crate::codex_protocol;
 use crate::interfaces::RuntimeError;
-use crate::types::{LLMResponse, Message as RuntimeMessage, Role, SCHEMA_VERSION};
+use crate::types::{LLMResponse, Message as RuntimeMessage, Role};
 use hypr_claw_memory::types::OAuthTokens;
 use hypr_claw_providers::codex::{types::OAuthTokens as CodexTokens, CodexProvider};
 use hypr_claw_providers::traits::{LLMProvider, Message as ProviderMessage};
@@ -63,21 +67,24 @@ impl CodexAdapter {
         &self,
         system_prompt: &str,
         messages: &[RuntimeMessage],
-        _tool_schemas: &[serde_json::Value],
+        tool_schemas: &[serde_json::Value],
     ) -> Result<LLMResponse, RuntimeError> {
-        let provider_messages = self.convert_messages(system_prompt, messages)?;
-
-        // NOTE: Codex API doesn't support OpenAI-style function calling
-        // Tools must be handled via prompt engineering (bridge prompt approach)
-        // For now, Codex only returns text responses
+        let provider_messages = self.convert_messages(system_prompt, messages, tool_schemas)?;
 
+        // The Codex backend gets no native tool definitions: the offered tools
+        // and the envelope format travel in the system instructions instead.
         let response = self
             .provider
             .generate(&provider_messages, None)
             .await
             .map_err(|e| RuntimeError::LLMError(e.to_string()))?;
 
-        self.convert_response(response)
+        let offered: Vec<String> = tool_schemas
+            .iter()
+            .filter_map(|schema| schema.pointer("/function/name").and_then(|n| n.as_str()))
+            .map(str::to_string)
+            .collect();
+        self.convert_response(response, &offered)
     }
 
     /// Convert runtime messages to provider messages.
@@ -85,22 +92,44 @@ impl CodexAdapter {
         &self,
         system_prompt: &str,
         messages: &[RuntimeMessage],
+        tool_schemas: &[serde_json::Value],
     ) -> Result<Vec<ProviderMessage>, RuntimeError> {
         let mut provider_messages = Vec::new();
 
         // Codex doesn't support system messages - prepend to first user message instead
-        let mut system_prefix = if !system_prompt.is_empty() {
-            format!("[System Instructions: {}]\n\n", system_prompt)
+        let instructions = [
+            Some(system_prompt.to_string()).filter(|s| !s.is_empty()),
+            codex_protocol::instructions(tool_schemas),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join("\n\n");
+        let mut system_prefix = if !instructions.is_empty() {
+            format!("[System Instructions: {}]\n\n", instructions)
         } else {
             String::new()
         };
 
         // Convert runtime messages
         for msg in messages {
+            let tool_name = msg
+                .metadata
+                .as_ref()
+                .and_then(|m| m.get("tool_name"))
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown_tool");
+            let is_tool_call = msg
+                .metadata
+                .as_ref()
+                .and_then(|m| m.get("tool_call"))
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+
+            // Tool results go back as user turns, since Codex has no tool role.
             let role = match msg.role {
-                Role::User => "user",
+                Role::User | Role::Tool => "user",
                 Role::Assistant => "assistant",
-                Role::Tool => "tool",
                 Role::System => {
                     // Skip system messages, already handled
                     continue;
@@ -108,10 +137,19 @@ impl CodexAdapter {
             }
             .to_string();
 
-            // Convert JSON content to string
-            let mut content = match &msg.content {
-                serde_json::Value::String(s) => s.clone(),
-                other => serde_json::to_string(other)?,
+            let mut content = match (&msg.role, &msg.content) {
+                (Role::Assistant, _) if is_tool_call => {
+                    let input = msg
+                        .metadata
+                        .as_ref()
+                        .and_then(|m| m.get("input"))
+                        .cloned()
+                        .unwrap_or_else(|| serde_json::json!({}));
+                    codex_protocol::encode_tool_call(tool_name, &input)
+                }
+                (Role::Tool, output) => codex_protocol::encode_tool_result(tool_name, output),
+                (_, serde_json::Value::String(s)) => s.clone(),
+                (_, other) => serde_json::to_string(other)?,
             };
 
             // Prepend system instructions to first user message
@@ -130,18 +168,17 @@ impl CodexAdapter {
     fn convert_response(
         &self,
         response: hypr_claw_providers::traits::GenerateResponse,
+        offered: &[String],
     ) -> Result<LLMResponse, RuntimeError> {
-        // Codex doesn't support tool calling - always return Final response
-        Ok(LLMResponse::Final {
-            schema_version: SCHEMA_VERSION,
-            content: response.content.unwrap_or_default(),
-        })
+        // Native tool calls are not requested, so only the text is read.
+        codex_protocol::parse_reply(&response.content.unwrap_or_default(), offered)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::SCHEMA_VERSION;
     use serde_json::json;
 
     #[test]
@@ -156,16 +193,18 @@ mod tests {
                 role: Role::User,
                 content: json!("Hello"),
                 metadata: None,
+                provenance: None,
             },
             RuntimeMessage {
                 schema_version: SCHEMA_VERSION,
                 role: Role::Assistant,
                 content: json!({"text": "Hi there"}),
                 metadata: None,
+                provenance: None,
             },
         ];
 
-        let result = adapter.convert_messages("You are helpful", &runtime_messages);
+        let result = adapter.convert_messages("You are helpful", &runtime_messages, &[]);
         assert!(result.is_ok());
 
         let provider_messages = result.expect("Should have messages");
@@ -188,7 +227,7 @@ mod tests {
             finish_reason: "stop".to_string(),
         };
 
-        let result = adapter.convert_response(provider_response);
+        let result = adapter.convert_response(provider_response, &[]);
         assert!(result.is_ok());
 
         if let Ok(LLMResponse::Final { content, .. }) = result {
@@ -204,25 +243,29 @@ mod tests {
             provider: Arc::new(CodexProvider::new("test".to_string())),
         };
 
-        // Codex doesn't support tool calling - even with tool_calls in response,
-        // it should return Final response
+        // Native tool calls are ignored; the call travels as a bridge envelope
+        // in the reply text.
         let provider_response = hypr_claw_providers::traits::GenerateResponse {
-            content: None,
+            content: Some(codex_protocol::encode_tool_call(
+                "echo",
+                &json!({"message": "test"}),
+            )),
             tool_calls: vec![hypr_claw_providers::traits::ToolCall {
-                name: "echo".to_string(),
-                arguments: json!({"message": "test"}),
+                name: "ignored".to_string(),
+                arguments: json!({}),
             }],
             finish_reason: "tool_calls".to_string(),
         };
 
-        let result = adapter.convert_response(provider_response);
-        assert!(result.is_ok());
-
-        // Codex adapter always returns Final, never ToolCall
-        if let Ok(LLMResponse::Final { content, .. }) = result {
-            assert_eq!(content, ""); // No content provided
-        } else {
-            panic!("Expected Final response from Codex adapter");
+        let result = adapter.convert_response(provider_response, &["echo".to_string()]);
+        match result {
+            Ok(LLMResponse::ToolCall {
+                tool_name, input, ..
+            }) => {
+                assert_eq!(tool_name, "echo");
+                assert_eq!(input, json!({"message": "test"}));
+            }
+            other => panic!("Expected ToolCall response, got {other:?}"),
         }
     }
 }
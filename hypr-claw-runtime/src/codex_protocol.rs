@@ -0,0 +1,192 @@
+//! The versioned JSON bridge that carries tool calls over the Codex backend.
+//!
+//! The Codex responses endpoint is driven with plain text messages, so tool
+//! calls travel inside the text as JSON envelopes tagged with the protocol
+//! version they were written for. Version 1:
+//!
+//! ```text
+//! model -> runtime   {"hypr_claw_bridge": 1, "tool_call": {"name": "<tool>", "arguments": {...}}}
+//! model -> runtime   {"hypr_claw_bridge": 1, "final": "<answer>"}   (or plain text)
+//! runtime -> model   {"hypr_claw_bridge": 1, "tool_result": {"name": "<tool>", "output": ...}}
+//! ```
+//!
+//! [`instructions`] tells the model which version to speak and which tools it
+//! has; [`parse_reply`] accepts any version in [`SUPPORTED_VERSIONS`] and
+//! ignores fields it does not know, so a later version can add fields without
+//! breaking this one. A reply declaring a version outside that range, naming
+//! a tool that was not offered, or shaped like a tool call without any
+//! version is an error rather than a final answer, so a change on the Codex
+//! side shows up as a failed call instead of a silently dropped tool call.
+//! Text before an envelope is kept as the call's rationale.
+
+use crate::interfaces::RuntimeError;
+use crate::types::{LLMResponse, SCHEMA_VERSION};
+use serde_json::{json, Value};
+use std::ops::RangeInclusive;
+
+/// Version this build writes and asks the model to write.
+pub const PROTOCOL_VERSION: u64 = 1;
+
+/// Versions this build reads.
+pub const SUPPORTED_VERSIONS: RangeInclusive<u64> = 1..=PROTOCOL_VERSION;
+
+/// Key holding the protocol version in every envelope.
+pub const VERSION_KEY: &str = "hypr_claw_bridge";
+
+/// Protocol description for the system prompt, listing the offered tools;
+/// `None` when no tools are offered.
+pub fn instructions(tool_schemas: &[Value]) -> Option<String> {
+    if tool_schemas.is_empty() {
+        return None;
+    }
+    let mut text = format!(
+        "Tools are called through the hypr-claw bridge protocol v{PROTOCOL_VERSION}. To call a \
+         tool, reply with only this JSON object and nothing after it: \
+         {{\"{VERSION_KEY}\": {PROTOCOL_VERSION}, \"tool_call\": {{\"name\": \"<tool>\", \
+         \"arguments\": {{...}}}}}}. Its result comes back as \
+         {{\"{VERSION_KEY}\": {PROTOCOL_VERSION}, \"tool_result\": {{\"name\": \"<tool>\", \
+         \"output\": ...}}}}. Call one tool at a time. When you are done, answer in plain text.\n\
+         Available tools:"
+    );
+    for schema in tool_schemas {
+        let function = schema.get("function").unwrap_or(schema);
+        let Some(name) = function.get("name").and_then(Value::as_str) else {
+            continue;
+        };
+        let description = function
+            .get("description")
+            .and_then(Value::as_str)
+            .unwrap_or("");
+        let parameters = function.get("parameters").cloned().unwrap_or(json!({}));
+        text.push_str(&format!(
+            "\n- {name}: {description} Arguments: {parameters}"
+        ));
+    }
+    Some(text)
+}
+
+/// The assistant turn for a tool call the runtime made, in envelope form.
+pub fn encode_tool_call(name: &str, arguments: &Value) -> String {
+    json!({
+        VERSION_KEY: PROTOCOL_VERSION,
+        "tool_call": {"name": name, "arguments": arguments},
+    })
+    .to_string()
+}
+
+/// The turn handing a tool's output back to the model.
+pub fn encode_tool_result(name: &str, output: &Value) -> String {
+    json!({
+        VERSION_KEY: PROTOCOL_VERSION,
+        "tool_result": {"name": name, "output": output},
+    })
+    .to_string()
+}
+
+/// Reads a model reply: an envelope becomes a tool call or final answer, and
+/// text without one is the final answer. `offered` holds the tool names of
+/// the request.
+pub fn parse_reply(text: &str, offered: &[String]) -> Result<LLMResponse, RuntimeError> {
+    let Some((prefix, envelope)) = find_envelope(text) else {
+        if let Ok(Value::Object(object)) = serde_json::from_str::<Value>(unfence(text)) {
+            if object.contains_key("tool_call") {
+                return Err(protocol_error(format!(
+                    "reply has a tool_call without a `{VERSION_KEY}` version"
+                )));
+            }
+        }
+        return Ok(final_response(text.to_string()));
+    };
+
+    let version = envelope
+        .get(VERSION_KEY)
+        .and_then(Value::as_u64)
+        .ok_or_else(|| protocol_error(format!("`{VERSION_KEY}` must be a version number")))?;
+    if !SUPPORTED_VERSIONS.contains(&version) {
+        return Err(protocol_error(format!(
+            "reply speaks v{version}, this build reads v{}..=v{}",
+            SUPPORTED_VERSIONS.start(),
+            SUPPORTED_VERSIONS.end()
+        )));
+    }
+
+    if let Some(call) = envelope.get("tool_call") {
+        let name = call
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| protocol_error("tool_call has no name".to_string()))?;
+        if !offered.iter().any(|tool| tool == name) {
+            return Err(protocol_error(format!(
+                "tool_call names `{name}`, which was not offered ({})",
+                offered.join(", ")
+            )));
+        }
+        let input = match call.get("arguments") {
+            None | Some(Value::Null) => json!({}),
+            // Function-call style: the arguments as a JSON string.
+            Some(Value::String(raw)) => serde_json::from_str(raw)
+                .map_err(|e| protocol_error(format!("tool_call arguments are not JSON: {e}")))?,
+            Some(arguments) => arguments.clone(),
+        };
+        return Ok(LLMResponse::ToolCall {
+            schema_version: SCHEMA_VERSION,
+            tool_name: name.to_string(),
+            input,
+            rationale: rationale(prefix),
+        });
+    }
+    if let Some(answer) = envelope.get("final").and_then(Value::as_str) {
+        return Ok(final_response(answer.to_string()));
+    }
+    Err(protocol_error(
+        "envelope has neither a tool_call nor a final answer".to_string(),
+    ))
+}
+
+/// The first JSON object in `text` carrying a version key, and the text
+/// before it.
+fn find_envelope(text: &str) -> Option<(&str, Value)> {
+    text.match_indices('{').find_map(|(start, _)| {
+        let value = serde_json::Deserializer::from_str(&text[start..])
+            .into_iter::<Value>()
+            .next()?
+            .ok()?;
+        value
+            .get(VERSION_KEY)
+            .is_some()
+            .then(|| (&text[..start], value))
+    })
+}
+
+/// `text` without a Markdown code fence around it.
+fn unfence(text: &str) -> &str {
+    let text = text.trim();
+    match text.strip_prefix("```").and_then(|t| t.strip_suffix("```")) {
+        Some(body) => body
+            .trim_start_matches(|c: char| c.is_ascii_alphanumeric())
+            .trim(),
+        None => text,
+    }
+}
+
+/// Text before an envelope, without the code fence opening it.
+fn rationale(prefix: &str) -> Option<String> {
+    let prefix = prefix.trim_end();
+    let prefix = match prefix.rfind("```") {
+        Some(i) if prefix[i + 3..].chars().all(|c| c.is_ascii_alphanumeric()) => &prefix[..i],
+        _ => prefix,
+    };
+    let prefix = prefix.trim();
+    (!prefix.is_empty()).then(|| prefix.to_string())
+}
+
+fn final_response(content: String) -> LLMResponse {
+    LLMResponse::Final {
+        schema_version: SCHEMA_VERSION,
+        content,
+    }
+}
+
+fn protocol_error(detail: String) -> RuntimeError {
+    RuntimeError::LLMError(format!("Codex bridge protocol: {detail}"))
+}
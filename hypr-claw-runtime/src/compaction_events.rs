@@ -0,0 +1,58 @@
+//! How well compaction summaries hold up.
+//!
+//! Each compaction is handed to an observer set with
+//! [`crate::AgentLoop::set_compaction_observer`], with how many tokens it
+//! saved and what the summarized tool calls had read. When a later tool call
+//! in the session reads one of those targets again, the model most likely
+//! needed something the summary dropped, and a [`CompactionEvent::Refetched`]
+//! follows. The ratio of the two is what summarizer changes should improve.
+
+use crate::compactor::CompactionReport;
+use serde_json::Value;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompactionEvent {
+    Compacted {
+        session_key: String,
+        compaction_id: String,
+        report: CompactionReport,
+    },
+    /// A tool call read a target the compaction had summarized away.
+    Refetched {
+        session_key: String,
+        compaction_id: String,
+        tool: String,
+        target: String,
+        /// Whether it happened in the run that compacted.
+        same_run: bool,
+    },
+}
+
+pub type CompactionObserver = Arc<dyn Fn(&CompactionEvent) + Send + Sync>;
+
+/// What a read-only call looked at: `file:<path>` for file reads and
+/// listings, `screen` (or `window:<selector>`) for captures and OCR.
+/// Calls that do not read a fixed target return `None`.
+pub fn read_target(tool_name: &str, input: &Value) -> Option<String> {
+    let text = |key: &str| {
+        input
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+    };
+    match tool_name {
+        "fs.read" | "fs.list" | "file.read" | "file.list" | "csv.query" => {
+            Some(format!("file:{}", text("path")?.trim_end_matches('/')))
+        }
+        "desktop.window_snapshot" | "desktop.window_diff" => {
+            Some(format!("window:{}", text("window").unwrap_or("active")))
+        }
+        "desktop.ocr_screen"
+        | "desktop.read_screen_state"
+        | "desktop.capture_screen"
+        | "desktop.find_text" => Some("screen".to_string()),
+        _ => None,
+    }
+}
@@ -1,8 +1,9 @@
 //! Message compactor for managing context window size.
 
 use crate::interfaces::RuntimeError;
-use crate::types::{Message, Role};
+use crate::types::{Message, Origin, Provenance, Role};
 use serde_json::json;
+use std::collections::BTreeMap;
 use tracing::{debug, info, warn};
 
 /// Trait for message summarization.
@@ -11,6 +12,37 @@ pub trait Summarizer: Send + Sync {
     fn summarize(&self, messages: &[Message]) -> Result<String, RuntimeError>;
 }
 
+/// What [`Compactor::compact`] would do to a history, without summarizing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactionPreview {
+    /// Estimated tokens of the history as stored.
+    pub tokens: usize,
+    pub threshold: usize,
+    /// Oldest messages that would be replaced by a summary; pinned ones
+    /// among them are kept.
+    pub summarized: usize,
+}
+
+/// What one compaction did, for tuning the summarizer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactionReport {
+    pub tokens_before: usize,
+    pub tokens_after: usize,
+    pub summarized_messages: usize,
+    pub kept_messages: usize,
+    /// Tool calls among the summarized messages, by tool name.
+    pub summarized_tools: BTreeMap<String, usize>,
+    /// What those calls read, see [`crate::compaction_events::read_target`].
+    pub targets: Vec<String>,
+    pub summary: String,
+}
+
+impl CompactionReport {
+    pub fn tokens_saved(&self) -> usize {
+        self.tokens_before.saturating_sub(self.tokens_after)
+    }
+}
+
 /// Message compactor for token-based history management.
 pub struct Compactor<S: Summarizer> {
     threshold: usize,
@@ -38,6 +70,15 @@ impl<S: Summarizer> Compactor<S> {
     /// # Returns
     /// Compacted message list (or original if below threshold)
     pub fn compact(&self, messages: Vec<Message>) -> Result<Vec<Message>, RuntimeError> {
+        self.compact_with_report(messages)
+            .map(|(messages, _)| messages)
+    }
+
+    /// [`Self::compact`], plus a report when it compacted.
+    pub fn compact_with_report(
+        &self,
+        messages: Vec<Message>,
+    ) -> Result<(Vec<Message>, Option<CompactionReport>), RuntimeError> {
         let token_count = self.estimate_tokens(&messages);
 
         if token_count <= self.threshold {
@@ -45,7 +86,7 @@ impl<S: Summarizer> Compactor<S> {
                 "Token count {} below threshold {}, no compaction needed",
                 token_count, self.threshold
             );
-            return Ok(messages);
+            return Ok((messages, None));
         }
 
         info!(
@@ -60,24 +101,33 @@ impl<S: Summarizer> Compactor<S> {
 
         if split_point == 0 {
             warn!("Single message exceeds threshold, cannot compact");
-            return Ok(messages);
+            return Ok((messages, None));
         }
 
         let (older_messages, newer_messages) = messages.split_at(split_point);
+        // Pinned messages are kept verbatim, right after the summary
+        let (pinned, older_messages): (Vec<Message>, Vec<Message>) =
+            older_messages.iter().cloned().partition(Message::is_pinned);
+        if older_messages.is_empty() {
+            debug!("Every older message is pinned, nothing to compact");
+            return Ok((messages, None));
+        }
 
         // Summarize older messages
-        let summary_text = self.summarizer.summarize(older_messages)?;
+        let summary_text = self.summarizer.summarize(&older_messages)?;
         let summary_message = Message::with_metadata(
             Role::System,
-            json!(summary_text),
+            json!(summary_text.clone()),
             json!({
                 "compacted": true,
                 "original_count": older_messages.len()
             }),
-        );
+        )
+        .with_provenance(Provenance::new(Origin::SystemInjector).summarized(true));
 
         // Return summary + newer messages
         let mut compacted = vec![summary_message];
+        compacted.extend(pinned);
         compacted.extend_from_slice(newer_messages);
 
         info!(
@@ -86,7 +136,44 @@ impl<S: Summarizer> Compactor<S> {
             compacted.len()
         );
 
-        Ok(compacted)
+        let mut summarized_tools = BTreeMap::new();
+        let mut targets = Vec::new();
+        for (tool, input) in older_messages.iter().filter_map(tool_call) {
+            *summarized_tools.entry(tool.to_string()).or_insert(0) += 1;
+            if let Some(target) = crate::compaction_events::read_target(tool, input) {
+                if !targets.contains(&target) {
+                    targets.push(target);
+                }
+            }
+        }
+        let report = CompactionReport {
+            tokens_before: token_count,
+            tokens_after: self.estimate_tokens(&compacted),
+            summarized_messages: older_messages.len(),
+            kept_messages: compacted.len() - 1,
+            summarized_tools,
+            targets,
+            summary: summary_text,
+        };
+
+        Ok((compacted, Some(report)))
+    }
+
+    /// What [`Self::compact`] would do to `messages`.
+    pub fn preview(&self, messages: &[Message]) -> CompactionPreview {
+        let tokens = self.estimate_tokens(messages);
+        CompactionPreview {
+            tokens,
+            threshold: self.threshold,
+            summarized: if tokens > self.threshold {
+                messages[..messages.len() / 2]
+                    .iter()
+                    .filter(|message| !message.is_pinned())
+                    .count()
+            } else {
+                0
+            },
+        }
     }
 
     /// Estimate token count using simple length-based heuristic.
@@ -106,6 +193,18 @@ impl<S: Summarizer> Compactor<S> {
     }
 }
 
+/// Tool name and input of a tool-call message.
+fn tool_call(message: &Message) -> Option<(&str, &serde_json::Value)> {
+    let metadata = message.metadata.as_ref()?;
+    if metadata.get("tool_call").and_then(|v| v.as_bool()) != Some(true) {
+        return None;
+    }
+    Some((
+        metadata.get("tool_name")?.as_str()?,
+        metadata.get("input").unwrap_or(&serde_json::Value::Null),
+    ))
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
 mod tests {
@@ -154,6 +253,23 @@ mod tests {
             .contains("Summary of 2 messages"));
     }
 
+    #[test]
+    fn test_preview_matches_compaction() {
+        let compactor = Compactor::new(10, MockSummarizer);
+        let messages = vec![
+            Message::new(Role::User, json!("A".repeat(50))),
+            Message::new(Role::Assistant, json!("B".repeat(50))),
+            Message::new(Role::User, json!("C".repeat(50))),
+        ];
+
+        let preview = compactor.preview(&messages);
+        assert_eq!(preview.threshold, 10);
+        assert!(preview.tokens > 10);
+        let compacted = compactor.compact(messages.clone()).unwrap();
+        assert_eq!(compacted.len(), messages.len() - preview.summarized + 1);
+        assert_eq!(compactor.preview(&messages[..0]).summarized, 0);
+    }
+
     #[test]
     fn test_preserves_recent_messages() {
         let compactor = Compactor::new(10, MockSummarizer);
@@ -269,5 +385,9 @@ mod tests {
         let metadata = result[0].metadata.as_ref().unwrap();
         assert_eq!(metadata["compacted"], true);
         assert_eq!(metadata["original_count"], 2);
+
+        let provenance = result[0].provenance.as_ref().unwrap();
+        assert_eq!(provenance.origin, Origin::SystemInjector);
+        assert!(provenance.summarized);
     }
 }
@@ -0,0 +1,55 @@
+//! Keeping a run's runtime context from going stale.
+//!
+//! The system prompt describes the desktop (windows, workspace, time) as it
+//! was when the run started. Once a run has gone on longer than the
+//! freshness threshold, or a tool fails because something the context
+//! listed is gone, the loop asks the [`ContextRefresher`] set with
+//! [`crate::AgentLoop::set_context_refresher`] for fresh lines and adds them
+//! before the next model call, so the model does not act on the old picture.
+
+use async_trait::async_trait;
+use std::time::Duration;
+
+/// Words in a tool error that, next to `window` or `workspace`, mean the
+/// desktop changed under the run.
+const DIVERGENCE_MARKERS: &[&str] = &[
+    "not found",
+    "no such",
+    "no longer",
+    "does not exist",
+    "gone",
+    "closed",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RefreshReason {
+    /// The context is older than the freshness threshold.
+    Stale { age: Duration },
+    /// A tool failed because the desktop no longer matches the context.
+    Diverged { tool: String, error: String },
+}
+
+impl RefreshReason {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Stale { age } => format!("{}s old", age.as_secs()),
+            Self::Diverged { tool, .. } => format!("{tool} found the desktop changed"),
+        }
+    }
+}
+
+#[async_trait]
+pub trait ContextRefresher: Send + Sync {
+    /// Fresh `- key: value` context lines for the run on `session_key`, or
+    /// `None` if there is nothing newer to say.
+    async fn refresh(&self, session_key: &str, reason: &RefreshReason) -> Option<String>;
+}
+
+/// Whether a tool error says a window or workspace from the context is gone.
+pub fn diverged(error: &str) -> bool {
+    let error = error.to_lowercase();
+    (error.contains("window") || error.contains("workspace"))
+        && DIVERGENCE_MARKERS
+            .iter()
+            .any(|marker| error.contains(marker))
+}
@@ -0,0 +1,57 @@
+//! Which tool the model chose each iteration, out of the ones it was offered.
+//!
+//! Decisions are handed to an observer set with
+//! [`crate::AgentLoop::set_decision_observer`], so callers can log them and
+//! check whether schema pruning or tool descriptions steer the model badly.
+
+use crate::types::LLMResponse;
+use std::sync::Arc;
+
+/// One model response of a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolDecision {
+    pub session_key: String,
+    /// 1-based iteration of the run.
+    pub iteration: usize,
+    /// Tools in the request, in schema order.
+    pub offered: Vec<String>,
+    /// `None` when the model answered instead of calling a tool.
+    pub chosen: Option<String>,
+    /// Text the model sent along with its tool call, if any.
+    pub rationale: Option<String>,
+}
+
+impl ToolDecision {
+    pub fn new(
+        session_key: &str,
+        iteration: usize,
+        offered: &[String],
+        response: &LLMResponse,
+    ) -> Self {
+        let (chosen, rationale) = match response {
+            LLMResponse::Final { .. } => (None, None),
+            LLMResponse::ToolCall {
+                tool_name,
+                rationale,
+                ..
+            } => (Some(tool_name.clone()), rationale.clone()),
+        };
+        Self {
+            session_key: session_key.to_string(),
+            iteration,
+            offered: offered.to_vec(),
+            chosen,
+            rationale,
+        }
+    }
+
+    /// Whether the chosen tool was among those offered; answers count as
+    /// offered.
+    pub fn chose_offered(&self) -> bool {
+        self.chosen
+            .as_ref()
+            .is_none_or(|tool| self.offered.contains(tool))
+    }
+}
+
+pub type DecisionObserver = Arc<dyn Fn(&ToolDecision) + Send + Sync>;
@@ -0,0 +1,70 @@
+//! Limits on model output: stop sequences and how much prose a reply may
+//! carry.
+//!
+//! Both are sent with the provider request. Providers that ignore them are
+//! caught afterwards: the agent loop cuts final answers at the first stop
+//! sequence and trims them to the narration budget.
+
+/// Most providers accept at most this many stop sequences.
+pub const MAX_STOP_SEQUENCES: usize = 4;
+
+/// Rough size of a token, used to turn the token budget into characters.
+const CHARS_PER_TOKEN: usize = 4;
+
+const TRIM_MARKER: &str = " …[trimmed]";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutputGuardrails {
+    pub stop_sequences: Vec<String>,
+    /// Cap on reply tokens, sent as `max_tokens`. It also bounds tool-call
+    /// arguments, so leave room for them. 0 keeps the model's default.
+    pub max_narration_tokens: u32,
+}
+
+impl OutputGuardrails {
+    /// Stop sequences to send, empty ones dropped.
+    pub fn request_stop(&self) -> Option<Vec<String>> {
+        let stop: Vec<String> = self
+            .stop_sequences
+            .iter()
+            .filter(|s| !s.is_empty())
+            .take(MAX_STOP_SEQUENCES)
+            .cloned()
+            .collect();
+        (!stop.is_empty()).then_some(stop)
+    }
+
+    /// `max_tokens` for a request whose model default is `default`.
+    pub fn request_max_tokens(&self, default: Option<u32>) -> Option<u32> {
+        match (self.max_narration_tokens, default) {
+            (0, default) => default,
+            (limit, Some(default)) => Some(limit.min(default)),
+            (limit, None) => Some(limit),
+        }
+    }
+
+    /// `content` cut at the first stop sequence and trimmed to the narration
+    /// budget, plus whether anything was removed.
+    pub fn apply(&self, content: &str) -> (String, bool) {
+        let mut end = content.len();
+        for stop in self.stop_sequences.iter().filter(|s| !s.is_empty()) {
+            if let Some(at) = content[..end].find(stop.as_str()) {
+                end = at;
+            }
+        }
+        let mut out = content[..end].trim_end().to_string();
+        let mut trimmed = end < content.len();
+
+        let max_chars = self.max_narration_tokens as usize * CHARS_PER_TOKEN;
+        if max_chars > 0 && out.chars().count() > max_chars {
+            let cut = out
+                .char_indices()
+                .nth(max_chars)
+                .map_or(out.len(), |(at, _)| at);
+            out.truncate(cut);
+            out.push_str(TRIM_MARKER);
+            trimmed = true;
+        }
+        (out, trimmed)
+    }
+}
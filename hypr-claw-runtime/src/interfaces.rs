@@ -13,6 +13,13 @@ pub enum RuntimeError {
     #[error("Lock error: {0}")]
     LockError(String),
 
+    #[error("Session conflict on {session_key}: expected version {expected}, found {found}")]
+    SessionConflict {
+        session_key: String,
+        expected: u64,
+        found: u64,
+    },
+
     #[error("Tool error: {0}")]
     ToolError(String),
 
@@ -37,6 +44,43 @@ pub trait SessionStore: Send + Sync {
 
     /// Save message history for a session.
     async fn save(&self, session_key: &str, messages: &[Message]) -> Result<(), RuntimeError>;
+
+    /// Load message history together with the version it was read at.
+    async fn load_versioned(
+        &self,
+        session_key: &str,
+    ) -> Result<(Vec<Message>, SessionVersion), RuntimeError> {
+        let messages = self.load(session_key).await?;
+        let version = SessionVersion {
+            version: 0,
+            len: messages.len(),
+        };
+        Ok((messages, version))
+    }
+
+    /// Save history that was loaded at `base`. Versioned stores merge or
+    /// reject writes that raced another writer; the default just saves.
+    async fn save_versioned(
+        &self,
+        session_key: &str,
+        messages: &[Message],
+        base: SessionVersion,
+    ) -> Result<SessionVersion, RuntimeError> {
+        self.save(session_key, messages).await?;
+        Ok(SessionVersion {
+            version: base.version + 1,
+            len: messages.len(),
+        })
+    }
+}
+
+/// Optimistic concurrency token for a session's persisted history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionVersion {
+    /// Number of writes the store has seen for the session.
+    pub version: u64,
+    /// Number of messages in the history at that version.
+    pub len: usize,
 }
 
 /// Session locking interface.
@@ -68,6 +112,12 @@ pub trait ToolRegistry: Send + Sync {
 
     /// Get full tool schemas for an agent in OpenAI function format.
     fn get_tool_schemas(&self, agent_id: &str) -> Vec<serde_json::Value>;
+
+    /// Whether a tool only observes state. A successful read-only call after
+    /// a mutating one counts as verifying it. Unknown tools are mutating.
+    fn is_read_only(&self, _tool_name: &str) -> bool {
+        false
+    }
 }
 
 #[cfg(test)]
@@ -5,24 +5,49 @@
 pub mod agent_config;
 pub mod agent_loop;
 pub mod async_adapters;
+pub mod attempts;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod citations;
 pub mod codex_adapter;
+pub mod codex_protocol;
+pub mod compaction_events;
 pub mod compactor;
+pub mod context_refresh;
+pub mod decisions;
 pub mod gateway;
+pub mod guardrails;
 pub mod interfaces;
 pub mod llm_client;
 pub mod llm_client_type;
 pub mod metrics;
+pub mod prefetch;
+pub mod request_limits;
 pub mod runtime_controller;
+#[cfg(feature = "telemetry")]
+pub mod telemetry;
 pub mod types;
 
 pub use agent_config::{load_agent_config, AgentConfig};
-pub use agent_loop::AgentLoop;
+pub use agent_loop::{AgentLoop, RequestPreview};
 pub use async_adapters::{AsyncLockManager, AsyncSessionStore};
+pub use attempts::AttemptLog;
+pub use citations::Citation;
 pub use codex_adapter::CodexAdapter;
-pub use compactor::{Compactor, Summarizer};
+pub use compaction_events::{CompactionEvent, CompactionObserver};
+pub use compactor::{CompactionPreview, CompactionReport, Compactor, Summarizer};
+pub use context_refresh::{ContextRefresher, RefreshReason};
+pub use decisions::{DecisionObserver, ToolDecision};
 pub use gateway::resolve_session;
-pub use interfaces::{LockManager, RuntimeError, SessionStore, ToolDispatcher, ToolRegistry};
-pub use llm_client::LLMClient;
+pub use guardrails::OutputGuardrails;
+pub use interfaces::{
+    LockManager, RuntimeError, SessionStore, SessionVersion, ToolDispatcher, ToolRegistry,
+};
+pub use llm_client::{LLMClient, ModelInfo};
 pub use llm_client_type::LLMClientType;
+pub use prefetch::{PrefetchCall, Prefetcher};
+pub use request_limits::{QueueStats, RequestLimiter};
 pub use runtime_controller::RuntimeController;
-pub use types::{LLMResponse, Message, Role, SCHEMA_VERSION};
+#[cfg(feature = "telemetry")]
+pub use telemetry::{TelemetryEvent, TelemetrySink};
+pub use types::{LLMResponse, Message, Origin, Provenance, Role, SCHEMA_VERSION};
@@ -1,6 +1,8 @@
 //! LLM client for HTTP communication with Python service.
 
+use crate::guardrails::OutputGuardrails;
 use crate::interfaces::RuntimeError;
+use crate::request_limits::RequestLimiter;
 use crate::types::{LLMResponse, Message};
 use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
@@ -32,12 +34,24 @@ struct OpenAIRequest {
     top_p: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
 }
 
 /// OpenAI-compatible response format
 #[derive(Debug, Deserialize)]
 struct OpenAIResponse {
     choices: Vec<OpenAIChoice>,
+    #[serde(default)]
+    usage: Option<OpenAIUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIUsage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -64,12 +78,63 @@ struct OpenAIFunction {
 
 #[derive(Debug, Deserialize)]
 struct OpenAIModelsResponse {
-    data: Vec<OpenAIModelEntry>,
+    data: Vec<serde_json::Value>,
 }
 
-#[derive(Debug, Deserialize)]
-struct OpenAIModelEntry {
-    id: String,
+/// A model from the provider's catalog, with whatever metadata it reports.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supports_tools: Option<bool>,
+}
+
+impl ModelInfo {
+    fn from_entry(entry: &serde_json::Value) -> Option<Self> {
+        let id = entry.get("id")?.as_str()?.to_string();
+        // Field names differ between OpenAI-compatible servers.
+        let context_window = [
+            "context_length",
+            "context_window",
+            "max_model_len",
+            "max_context_length",
+        ]
+        .iter()
+        .find_map(|key| entry.get(*key).and_then(|v| v.as_u64()));
+        let supports_tools = entry
+            .get("supported_parameters")
+            .and_then(|v| v.as_array())
+            .map(|params| params.iter().any(|p| p.as_str() == Some("tools")))
+            .or_else(|| {
+                let capabilities = entry.get("capabilities")?;
+                ["function_calling", "tool_calling", "tools"]
+                    .iter()
+                    .find_map(|key| capabilities.get(*key).and_then(|v| v.as_bool()))
+            });
+        Some(Self {
+            id,
+            context_window,
+            supports_tools,
+        })
+    }
+}
+
+/// Models listed in a `/models` response body, sorted by id.
+pub fn parse_models_response(body: &str) -> Vec<ModelInfo> {
+    let mut models: Vec<ModelInfo> = serde_json::from_str::<OpenAIModelsResponse>(body)
+        .map(|parsed| {
+            parsed
+                .data
+                .iter()
+                .filter_map(ModelInfo::from_entry)
+                .collect()
+        })
+        .unwrap_or_default();
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    models.dedup_by(|a, b| a.id == b.id);
+    models
 }
 
 /// Circuit breaker state.
@@ -135,6 +200,9 @@ pub struct LLMClient {
     circuit_breaker: Arc<CircuitBreaker>,
     api_key: Option<String>,
     model: Arc<RwLock<Option<String>>>,
+    temperature_override: Arc<RwLock<Option<f32>>>,
+    guardrails: Arc<RwLock<OutputGuardrails>>,
+    limiter: Option<Arc<RequestLimiter>>,
 }
 
 impl LLMClient {
@@ -156,9 +224,28 @@ impl LLMClient {
             circuit_breaker: Arc::new(CircuitBreaker::new(5, Duration::from_secs(30))),
             api_key: None,
             model: Arc::new(RwLock::new(None)),
+            temperature_override: Arc::new(RwLock::new(None)),
+            guardrails: Arc::new(RwLock::new(OutputGuardrails::default())),
+            limiter: None,
         }
     }
 
+    /// Replace the default 60s per-request timeout.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+        self
+    }
+
+    /// Queue requests behind `limiter`, shared with other clients of the
+    /// same provider.
+    pub fn with_request_limiter(mut self, limiter: Arc<RequestLimiter>) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
     /// Create a new LLM client with API key for authentication.
     pub fn with_api_key(base_url: String, max_retries: u32, api_key: String) -> Self {
         let mut client = Self::new(base_url, max_retries);
@@ -193,8 +280,36 @@ impl LLMClient {
         self.model.read().clone()
     }
 
+    /// Sample at `temperature` instead of the model's default; `None`
+    /// restores the default.
+    pub fn set_temperature_override(&self, temperature: Option<f32>) {
+        *self.temperature_override.write() = temperature;
+    }
+
+    /// Stop sequences and narration budget sent with each request.
+    pub fn set_output_guardrails(&self, guardrails: OutputGuardrails) {
+        *self.guardrails.write() = guardrails;
+    }
+
+    /// Default sampling temperature of the active model.
+    pub fn base_temperature(&self) -> Option<f32> {
+        let model = self.current_model()?;
+        self.model_generation_profile(&model).0
+    }
+
     /// List models from provider OpenAI-compatible `/models` endpoint.
     pub async fn list_models(&self) -> Result<Vec<String>, RuntimeError> {
+        Ok(self
+            .list_model_info()
+            .await?
+            .into_iter()
+            .map(|model| model.id)
+            .collect())
+    }
+
+    /// Provider model catalog with context window and tool support, where the
+    /// provider reports them.
+    pub async fn list_model_info(&self) -> Result<Vec<ModelInfo>, RuntimeError> {
         let url = format!("{}/models", self.base_url.trim_end_matches('/'));
         let mut req_builder = self.client.get(&url);
 
@@ -222,26 +337,15 @@ impl LLMClient {
             RuntimeError::LLMError(format!("Failed to read models response: {}", e))
         })?;
 
-        let mut models: Vec<String> = Vec::new();
-
-        if let Ok(parsed) = serde_json::from_str::<OpenAIModelsResponse>(&body) {
-            models.extend(parsed.data.into_iter().map(|m| m.id));
-        } else if let Ok(value) = serde_json::from_str::<serde_json::Value>(&body) {
-            if let Some(items) = value.get("data").and_then(|v| v.as_array()) {
-                for item in items {
-                    if let Some(id) = item.get("id").and_then(|v| v.as_str()) {
-                        models.push(id.to_string());
-                    }
-                }
-            }
-        }
-
-        models.sort();
-        models.dedup();
+        let models = parse_models_response(&body);
 
         if models.is_empty() {
             if let Some(current) = self.current_model() {
-                return Ok(vec![current]);
+                return Ok(vec![ModelInfo {
+                    id: current,
+                    context_window: None,
+                    supports_tools: None,
+                }]);
             }
             return Err(RuntimeError::LLMError(
                 "Models endpoint returned no model IDs".to_string(),
@@ -340,7 +444,14 @@ impl LLMClient {
             attempts_used = attempt + 1;
             debug!("LLM call attempt {}/{}", attempt + 1, self.max_retries + 1);
 
-            match self.call_once(system_prompt, messages, tool_schemas).await {
+            // Taken per attempt, so a retry backoff does not hold a slot.
+            let permit = match &self.limiter {
+                Some(limiter) => Some(limiter.acquire().await?),
+                None => None,
+            };
+            let result = self.call_once(system_prompt, messages, tool_schemas).await;
+            drop(permit);
+            match result {
                 Ok(response) => {
                     self.circuit_breaker.record_success();
                     return Ok(response);
@@ -466,6 +577,8 @@ impl LLMClient {
             }
 
             let (temperature, top_p, max_tokens) = self.model_generation_profile(model);
+            let temperature = self.temperature_override.read().or(temperature);
+            let guardrails = self.guardrails.read().clone();
             let openai_request = OpenAIRequest {
                 model: model.clone(),
                 messages: openai_messages,
@@ -473,7 +586,8 @@ impl LLMClient {
                 tool_choice: Some("auto".to_string()),
                 temperature,
                 top_p,
-                max_tokens,
+                max_tokens: guardrails.request_max_tokens(max_tokens),
+                stop: guardrails.request_stop(),
             };
 
             debug!("llm url={}", url);
@@ -553,37 +667,32 @@ impl LLMClient {
             return Err(RuntimeError::LLMError(error_msg));
         }
 
-        let llm_response: LLMResponse =
-            if active_model.is_some() {
-                // Parse OpenAI format response
-                let openai_response: OpenAIResponse = response.json().await.map_err(|e| {
-                    RuntimeError::LLMError(format!("Failed to parse response: {}", e))
-                })?;
-
-                // Convert to our format
-                if let Some(choice) = openai_response.choices.first() {
-                    if let Some(tool_calls) = &choice.message.tool_calls {
-                        if let Some(tool_call) = tool_calls.first() {
-                            LLMResponse::ToolCall {
-                                schema_version: crate::types::SCHEMA_VERSION,
-                                tool_name: tool_call.function.name.clone(),
-                                input: serde_json::from_str(&tool_call.function.arguments)
-                                    .unwrap_or(serde_json::json!({})),
-                            }
-                        } else {
-                            let content = choice.message.content.clone().unwrap_or_default();
-                            if let Some((tool_name, input)) = parse_inline_tool_call(&content) {
-                                LLMResponse::ToolCall {
-                                    schema_version: crate::types::SCHEMA_VERSION,
-                                    tool_name,
-                                    input,
-                                }
-                            } else {
-                                LLMResponse::Final {
-                                    schema_version: crate::types::SCHEMA_VERSION,
-                                    content,
-                                }
-                            }
+        let llm_response: LLMResponse = if active_model.is_some() {
+            // Parse OpenAI format response
+            let openai_response: OpenAIResponse = response
+                .json()
+                .await
+                .map_err(|e| RuntimeError::LLMError(format!("Failed to parse response: {}", e)))?;
+            if let Some(usage) = &openai_response.usage {
+                crate::metrics::record_token_usage(usage.prompt_tokens, usage.completion_tokens);
+            }
+
+            // Convert to our format
+            if let Some(choice) = openai_response.choices.first() {
+                if let Some(tool_calls) = &choice.message.tool_calls {
+                    if let Some(tool_call) = tool_calls.first() {
+                        LLMResponse::ToolCall {
+                            schema_version: crate::types::SCHEMA_VERSION,
+                            tool_name: tool_call.function.name.clone(),
+                            input: serde_json::from_str(&tool_call.function.arguments)
+                                .unwrap_or(serde_json::json!({})),
+                            rationale: choice
+                                .message
+                                .content
+                                .as_deref()
+                                .map(str::trim)
+                                .filter(|text| !text.is_empty())
+                                .map(str::to_string),
                         }
                     } else {
                         let content = choice.message.content.clone().unwrap_or_default();
@@ -592,6 +701,7 @@ impl LLMClient {
                                 schema_version: crate::types::SCHEMA_VERSION,
                                 tool_name,
                                 input,
+                                rationale: None,
                             }
                         } else {
                             LLMResponse::Final {
@@ -601,14 +711,31 @@ impl LLMClient {
                         }
                     }
                 } else {
-                    return Err(RuntimeError::LLMError("No choices in response".to_string()));
+                    let content = choice.message.content.clone().unwrap_or_default();
+                    if let Some((tool_name, input)) = parse_inline_tool_call(&content) {
+                        LLMResponse::ToolCall {
+                            schema_version: crate::types::SCHEMA_VERSION,
+                            tool_name,
+                            input,
+                            rationale: None,
+                        }
+                    } else {
+                        LLMResponse::Final {
+                            schema_version: crate::types::SCHEMA_VERSION,
+                            content,
+                        }
+                    }
                 }
             } else {
-                // Parse custom format response
-                response.json().await.map_err(|e| {
-                    RuntimeError::LLMError(format!("Failed to parse response: {}", e))
-                })?
-            };
+                return Err(RuntimeError::LLMError("No choices in response".to_string()));
+            }
+        } else {
+            // Parse custom format response
+            response
+                .json()
+                .await
+                .map_err(|e| RuntimeError::LLMError(format!("Failed to parse response: {}", e)))?
+        };
 
         self.validate_response(&llm_response)?;
 
@@ -779,6 +906,7 @@ mod tests {
             schema_version: crate::types::SCHEMA_VERSION,
             tool_name: "search".to_string(),
             input: json!({"query": "test"}),
+            rationale: None,
         };
         assert!(client.validate_response(&response).is_ok());
     }
@@ -791,6 +919,7 @@ mod tests {
             schema_version: crate::types::SCHEMA_VERSION,
             tool_name: "".to_string(),
             input: json!({"query": "test"}),
+            rationale: None,
         };
         let result = client.validate_response(&response);
         assert!(result.is_err());
@@ -1,8 +1,9 @@
 //! LLM client type wrapper - supports both standard HTTP and Codex providers.
 
 use crate::codex_adapter::CodexAdapter;
+use crate::guardrails::OutputGuardrails;
 use crate::interfaces::RuntimeError;
-use crate::llm_client::LLMClient;
+use crate::llm_client::{LLMClient, ModelInfo};
 use crate::types::{LLMResponse, Message};
 
 /// Enum wrapper for different LLM client types.
@@ -43,6 +44,30 @@ impl LLMClientType {
         }
     }
 
+    /// Override the sampling temperature; ignored by providers that do not
+    /// take one.
+    pub fn set_temperature_override(&self, temperature: Option<f32>) {
+        if let Self::Standard(client) = self {
+            client.set_temperature_override(temperature);
+        }
+    }
+
+    /// Stop sequences and narration budget for provider requests; ignored
+    /// by providers that do not take them.
+    pub fn set_output_guardrails(&self, guardrails: OutputGuardrails) {
+        if let Self::Standard(client) = self {
+            client.set_output_guardrails(guardrails);
+        }
+    }
+
+    /// Default sampling temperature of the active model, when known.
+    pub fn base_temperature(&self) -> Option<f32> {
+        match self {
+            Self::Standard(client) => client.base_temperature(),
+            Self::Codex(_) => None,
+        }
+    }
+
     /// Query provider model catalog when supported.
     pub async fn list_models(&self) -> Result<Vec<String>, RuntimeError> {
         match self {
@@ -52,6 +77,16 @@ impl LLMClientType {
             )),
         }
     }
+
+    /// Provider model catalog with metadata, when supported.
+    pub async fn list_model_info(&self) -> Result<Vec<ModelInfo>, RuntimeError> {
+        match self {
+            Self::Standard(client) => client.list_model_info().await,
+            Self::Codex(_) => Err(RuntimeError::LLMError(
+                "Model listing is not supported for this provider".to_string(),
+            )),
+        }
+    }
 }
 
 #[cfg(test)]
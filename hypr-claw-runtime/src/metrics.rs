@@ -1,30 +1,184 @@
 //! Metrics instrumentation for runtime observability.
 
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
 use std::time::Instant;
 
 /// Record LLM request latency.
 pub fn record_llm_latency(duration_ms: f64) {
     metrics::histogram!("llm_request_latency", duration_ms);
+    #[cfg(feature = "telemetry")]
+    crate::telemetry::metric("llm_request_latency", duration_ms);
 }
 
 /// Record tool execution latency.
 pub fn record_tool_latency(duration_ms: f64) {
     metrics::histogram!("tool_execution_latency", duration_ms);
+    #[cfg(feature = "telemetry")]
+    crate::telemetry::metric("tool_execution_latency", duration_ms);
+}
+
+/// Running latency totals for one tool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolLatency {
+    pub calls: u64,
+    pub total_ms: f64,
+    pub max_ms: f64,
+}
+
+impl ToolLatency {
+    pub fn average_ms(&self) -> f64 {
+        if self.calls == 0 {
+            return 0.0;
+        }
+        self.total_ms / self.calls as f64
+    }
+}
+
+fn tool_latencies() -> &'static Mutex<BTreeMap<String, ToolLatency>> {
+    static LATENCIES: OnceLock<Mutex<BTreeMap<String, ToolLatency>>> = OnceLock::new();
+    LATENCIES.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+/// Record one call of `tool_name`, both to the histogram and to the
+/// per-tool averages used for schema hints.
+pub fn record_tool_call(tool_name: &str, duration_ms: f64) {
+    record_tool_latency(duration_ms);
+    let mut latencies = tool_latencies().lock();
+    let entry = latencies.entry(tool_name.to_string()).or_default();
+    entry.calls = entry.calls.saturating_add(1);
+    entry.total_ms += duration_ms;
+    entry.max_ms = entry.max_ms.max(duration_ms);
+}
+
+/// Per-tool latency measured so far in this process, plus any seeded history.
+pub fn tool_latency_snapshot() -> BTreeMap<String, ToolLatency> {
+    tool_latencies().lock().clone()
+}
+
+/// Load latency history from a previous process. Tools already measured in
+/// this process keep their live numbers.
+pub fn seed_tool_latencies(history: &BTreeMap<String, ToolLatency>) {
+    let mut latencies = tool_latencies().lock();
+    for (tool_name, latency) in history {
+        latencies.entry(tool_name.clone()).or_insert(*latency);
+    }
+}
+
+/// Append each tool's average latency to its schema description so the
+/// model can prefer faster tools when several would do.
+pub fn annotate_schemas_with_latency(
+    schemas: &mut [serde_json::Value],
+    latencies: &BTreeMap<String, ToolLatency>,
+) {
+    for schema in schemas {
+        let Some(function) = schema.get_mut("function") else {
+            continue;
+        };
+        let Some(latency) = function
+            .get("name")
+            .and_then(|name| name.as_str())
+            .and_then(|name| latencies.get(name))
+            .filter(|latency| latency.calls > 0)
+        else {
+            continue;
+        };
+        let hint = format!(
+            "[avg latency {} over {} call{}]",
+            format_latency(latency.average_ms()),
+            latency.calls,
+            if latency.calls == 1 { "" } else { "s" }
+        );
+        let description = function
+            .get("description")
+            .and_then(|d| d.as_str())
+            .unwrap_or_default();
+        let annotated = if description.is_empty() {
+            hint
+        } else {
+            format!("{description} {hint}")
+        };
+        if let Some(function) = function.as_object_mut() {
+            function.insert(
+                "description".to_string(),
+                serde_json::Value::String(annotated),
+            );
+        }
+    }
+}
+
+fn format_latency(ms: f64) -> String {
+    if ms >= 1000.0 {
+        format!("{:.1}s", ms / 1000.0)
+    } else {
+        format!("{}ms", ms.round() as u64)
+    }
+}
+
+/// Tokens reported by providers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt: u64,
+    pub completion: u64,
+}
+
+impl TokenUsage {
+    /// Tokens used since `earlier`, a previous [`token_usage`] reading.
+    pub fn since(self, earlier: TokenUsage) -> TokenUsage {
+        TokenUsage {
+            prompt: self.prompt.saturating_sub(earlier.prompt),
+            completion: self.completion.saturating_sub(earlier.completion),
+        }
+    }
+}
+
+static PROMPT_TOKENS: AtomicU64 = AtomicU64::new(0);
+static COMPLETION_TOKENS: AtomicU64 = AtomicU64::new(0);
+
+/// Record the token counts of one provider response.
+pub fn record_token_usage(prompt: u64, completion: u64) {
+    PROMPT_TOKENS.fetch_add(prompt, Ordering::Relaxed);
+    COMPLETION_TOKENS.fetch_add(completion, Ordering::Relaxed);
+    metrics::counter!("llm_prompt_tokens", prompt);
+    metrics::counter!("llm_completion_tokens", completion);
+    #[cfg(feature = "telemetry")]
+    {
+        crate::telemetry::metric("llm_prompt_tokens", prompt as f64);
+        crate::telemetry::metric("llm_completion_tokens", completion as f64);
+    }
+}
+
+/// Tokens used by this process so far. Providers that do not report usage
+/// are not counted.
+pub fn token_usage() -> TokenUsage {
+    TokenUsage {
+        prompt: PROMPT_TOKENS.load(Ordering::Relaxed),
+        completion: COMPLETION_TOKENS.load(Ordering::Relaxed),
+    }
 }
 
 /// Record session duration.
 pub fn record_session_duration(duration_ms: f64) {
     metrics::histogram!("session_duration", duration_ms);
+    #[cfg(feature = "telemetry")]
+    crate::telemetry::metric("session_duration", duration_ms);
 }
 
 /// Record lock wait duration.
 pub fn record_lock_wait(duration_ms: f64) {
     metrics::histogram!("lock_wait_duration", duration_ms);
+    #[cfg(feature = "telemetry")]
+    crate::telemetry::metric("lock_wait_duration", duration_ms);
 }
 
 /// Increment compaction counter.
 pub fn increment_compaction_count() {
     metrics::counter!("compaction_count", 1);
+    #[cfg(feature = "telemetry")]
+    crate::telemetry::metric("compaction_count", 1.0);
 }
 
 /// RAII timer for automatic metric recording.
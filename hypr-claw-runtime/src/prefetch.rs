@@ -0,0 +1,46 @@
+//! Read-only tool calls started alongside the first model call of a run.
+//!
+//! A [`Prefetcher`] set with [`crate::AgentLoop::set_prefetcher`] guesses
+//! calls from the user message (e.g. `fs.read` of a path it names). They run
+//! while the model thinks; if the model then asks for the same call its
+//! result is already there, and if it asks for something else the prefetched
+//! results are added as observations so it need not ask later. Answers that
+//! call no tool discard them.
+
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Longest a prefetch may hold up the first model response.
+pub const PREFETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrefetchCall {
+    pub tool_name: String,
+    pub input: Value,
+}
+
+impl PrefetchCall {
+    pub fn new(tool_name: &str, input: Value) -> Self {
+        Self {
+            tool_name: tool_name.to_string(),
+            input,
+        }
+    }
+}
+
+/// Calls worth prefetching for a user message. Only read-only tools that
+/// are offered to the model are run.
+pub type Prefetcher = Arc<dyn Fn(&str) -> Vec<PrefetchCall> + Send + Sync>;
+
+/// Remove and return the result prefetched for exactly this call.
+pub(crate) fn take_result(
+    prefetched: &mut Vec<(PrefetchCall, Value)>,
+    tool_name: &str,
+    input: &Value,
+) -> Option<Value> {
+    let index = prefetched
+        .iter()
+        .position(|(call, _)| call.tool_name == tool_name && &call.input == input)?;
+    Some(prefetched.remove(index).1)
+}
@@ -0,0 +1,124 @@
+//! Per-provider limits on concurrent LLM requests.
+//!
+//! Every background task builds its own client, so a limit kept on one
+//! client would not stop several tasks from hitting the same API key at
+//! once. Limiters are shared by provider name instead: [`configure`] sets
+//! one up and [`limiter_for`] hands it to each new client. Requests beyond
+//! the limit wait in arrival order (tokio semaphores are fair) and their
+//! wait time is recorded.
+
+use crate::interfaces::RuntimeError;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Queueing seen by one provider's limiter since startup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueueStats {
+    pub max_concurrent: usize,
+    pub in_flight: usize,
+    pub waiting: usize,
+    pub requests: u64,
+    /// Requests that had to wait for a slot.
+    pub queued: u64,
+    pub total_wait_ms: f64,
+    pub max_wait_ms: f64,
+}
+
+impl QueueStats {
+    /// Average wait over all requests, including those that did not wait.
+    pub fn average_wait_ms(&self) -> f64 {
+        if self.requests == 0 {
+            return 0.0;
+        }
+        self.total_wait_ms / self.requests as f64
+    }
+}
+
+pub struct RequestLimiter {
+    semaphore: Semaphore,
+    stats: Mutex<QueueStats>,
+}
+
+impl RequestLimiter {
+    pub fn new(max_concurrent: usize) -> Self {
+        let max_concurrent = max_concurrent.max(1);
+        Self {
+            semaphore: Semaphore::new(max_concurrent),
+            stats: Mutex::new(QueueStats {
+                max_concurrent,
+                ..QueueStats::default()
+            }),
+        }
+    }
+
+    /// Wait for a free slot; the request may run while the permit is held.
+    pub async fn acquire(&self) -> Result<SemaphorePermit<'_>, RuntimeError> {
+        let started = Instant::now();
+        let permit = match self.semaphore.try_acquire() {
+            Ok(permit) => permit,
+            Err(_) => {
+                self.stats.lock().waiting += 1;
+                let permit = self.semaphore.acquire().await;
+                let mut stats = self.stats.lock();
+                stats.waiting = stats.waiting.saturating_sub(1);
+                stats.queued += 1;
+                drop(stats);
+                permit.map_err(|e| RuntimeError::LLMError(format!("Request queue closed: {e}")))?
+            }
+        };
+        let wait_ms = started.elapsed().as_secs_f64() * 1000.0;
+        metrics::histogram!("llm_queue_wait", wait_ms);
+        let mut stats = self.stats.lock();
+        stats.requests += 1;
+        stats.total_wait_ms += wait_ms;
+        stats.max_wait_ms = stats.max_wait_ms.max(wait_ms);
+        Ok(permit)
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        let mut stats = *self.stats.lock();
+        stats.in_flight = stats
+            .max_concurrent
+            .saturating_sub(self.semaphore.available_permits());
+        stats
+    }
+}
+
+fn limiters() -> &'static Mutex<BTreeMap<String, Arc<RequestLimiter>>> {
+    static LIMITERS: OnceLock<Mutex<BTreeMap<String, Arc<RequestLimiter>>>> = OnceLock::new();
+    LIMITERS.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+/// Allow at most `max_concurrent` requests to `provider` at a time. Clients
+/// built before a changed limit keep the old one.
+pub fn configure(provider: &str, max_concurrent: usize) {
+    let mut limiters = limiters().lock();
+    if limiters
+        .get(provider)
+        .is_some_and(|limiter| limiter.stats().max_concurrent == max_concurrent)
+    {
+        return;
+    }
+    limiters.insert(
+        provider.to_string(),
+        Arc::new(RequestLimiter::new(max_concurrent)),
+    );
+}
+
+/// The shared limiter for `provider`, if one is configured.
+pub fn limiter_for(provider: &str) -> Option<Arc<RequestLimiter>> {
+    limiters().lock().get(provider).cloned()
+}
+
+/// Queue statistics of every configured provider.
+pub fn queue_snapshot() -> BTreeMap<String, QueueStats> {
+    limiters()
+        .lock()
+        .iter()
+        .map(|(provider, limiter)| (provider.clone(), limiter.stats()))
+        .collect()
+}
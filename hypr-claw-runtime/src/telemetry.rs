@@ -0,0 +1,93 @@
+//! Routing agent telemetry into an embedder's observability stack
+//! (`telemetry` feature).
+//!
+//! The [`TelemetrySink`] set with [`install`] receives the tool decisions and
+//! compaction events of every agent loop in the process, the runtime's metric
+//! samples, and the tool audit entries handed to [`audit`]. The bundled app
+//! installs one that writes its usual files under `./data`; an embedder can
+//! install its own instead, or both through a [`FanOut`].
+
+use crate::compaction_events::CompactionEvent;
+use crate::decisions::ToolDecision;
+use parking_lot::RwLock;
+use serde_json::Value;
+use std::sync::{Arc, OnceLock};
+
+#[derive(Debug, Clone, Copy)]
+pub enum TelemetryEvent<'a> {
+    Decision(&'a ToolDecision),
+    Compaction(&'a CompactionEvent),
+}
+
+/// Destination for telemetry. Every method defaults to dropping its input,
+/// so a sink implements only what it routes. Calls come from the agent
+/// loops and tool dispatches themselves and should return quickly.
+pub trait TelemetrySink: Send + Sync {
+    fn event(&self, _event: &TelemetryEvent<'_>) {}
+
+    /// One sample of a runtime metric: milliseconds for `*_latency` and
+    /// `*_duration` metrics, an amount to add for counters (`*_tokens`,
+    /// `compaction_count`).
+    fn metric(&self, _name: &str, _value: f64) {}
+
+    /// One tool call's audit entry, as the tool dispatcher records it.
+    fn audit(&self, _entry: &Value) {}
+}
+
+/// Hands everything to each of its sinks in order.
+pub struct FanOut(pub Vec<Arc<dyn TelemetrySink>>);
+
+impl TelemetrySink for FanOut {
+    fn event(&self, event: &TelemetryEvent<'_>) {
+        for sink in &self.0 {
+            sink.event(event);
+        }
+    }
+
+    fn metric(&self, name: &str, value: f64) {
+        for sink in &self.0 {
+            sink.metric(name, value);
+        }
+    }
+
+    fn audit(&self, entry: &Value) {
+        for sink in &self.0 {
+            sink.audit(entry);
+        }
+    }
+}
+
+fn slot() -> &'static RwLock<Option<Arc<dyn TelemetrySink>>> {
+    static SINK: OnceLock<RwLock<Option<Arc<dyn TelemetrySink>>>> = OnceLock::new();
+    SINK.get_or_init(|| RwLock::new(None))
+}
+
+/// Sets the process-wide sink, replacing any earlier one; `None` drops
+/// telemetry again.
+pub fn install(sink: Option<Arc<dyn TelemetrySink>>) {
+    *slot().write() = sink;
+}
+
+pub fn installed() -> Option<Arc<dyn TelemetrySink>> {
+    slot().read().clone()
+}
+
+pub(crate) fn event(event: TelemetryEvent<'_>) {
+    if let Some(sink) = installed() {
+        sink.event(&event);
+    }
+}
+
+pub(crate) fn metric(name: &str, value: f64) {
+    if let Some(sink) = installed() {
+        sink.metric(name, value);
+    }
+}
+
+/// Forwards a tool audit entry to the installed sink; for the audit logger
+/// given to the tool dispatcher.
+pub fn audit(entry: &Value) {
+    if let Some(sink) = installed() {
+        sink.audit(entry);
+    }
+}
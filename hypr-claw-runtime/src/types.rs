@@ -24,12 +24,86 @@ pub struct Message {
     pub content: serde_json::Value,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
 }
 
 fn default_schema_version() -> u32 {
     SCHEMA_VERSION
 }
 
+/// Who produced a message.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Origin {
+    User,
+    Assistant,
+    Tool,
+    /// Text the runtime inserted itself, such as compaction summaries.
+    SystemInjector,
+}
+
+/// Where a persisted message came from and what was done to it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    pub origin: Origin,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    /// Id of the tool call within its run (`t1`, `t2`, ...), used by citations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub call_id: Option<String>,
+    /// Content is a cut-down version of what the producer returned.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub truncated: bool,
+    /// Content summarises earlier messages rather than quoting them.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub summarized: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+impl Provenance {
+    pub fn new(origin: Origin) -> Self {
+        Self {
+            origin,
+            tool_name: None,
+            run_id: None,
+            call_id: None,
+            truncated: false,
+            summarized: false,
+        }
+    }
+
+    pub fn tool(mut self, tool_name: impl Into<String>) -> Self {
+        self.tool_name = Some(tool_name.into());
+        self
+    }
+
+    pub fn run(mut self, run_id: impl Into<String>) -> Self {
+        self.run_id = Some(run_id.into());
+        self
+    }
+
+    pub fn call(mut self, call_id: impl Into<String>) -> Self {
+        self.call_id = Some(call_id.into());
+        self
+    }
+
+    pub fn truncated(mut self, truncated: bool) -> Self {
+        self.truncated = truncated;
+        self
+    }
+
+    pub fn summarized(mut self, summarized: bool) -> Self {
+        self.summarized = summarized;
+        self
+    }
+}
+
 /// Structured response from LLM.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -44,6 +118,9 @@ pub enum LLMResponse {
         schema_version: u32,
         tool_name: String,
         input: serde_json::Value,
+        /// Text the model sent along with the call, e.g. why it chose it.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rationale: Option<String>,
     },
 }
 
@@ -55,6 +132,7 @@ impl Message {
             role,
             content,
             metadata: None,
+            provenance: None,
         }
     }
 
@@ -69,6 +147,47 @@ impl Message {
             role,
             content,
             metadata: Some(metadata),
+            provenance: None,
+        }
+    }
+
+    /// Attach provenance to this message.
+    pub fn with_provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
+    /// Recorded origin, or one inferred from the role for messages saved
+    /// before provenance was tracked.
+    pub fn origin(&self) -> Origin {
+        match (&self.provenance, &self.role) {
+            (Some(provenance), _) => provenance.origin,
+            (None, Role::User) => Origin::User,
+            (None, Role::Assistant) => Origin::Assistant,
+            (None, Role::Tool) => Origin::Tool,
+            (None, Role::System) => Origin::SystemInjector,
+        }
+    }
+
+    /// Whether the user pinned the message; compaction keeps it verbatim.
+    pub fn is_pinned(&self) -> bool {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("pinned"))
+            .and_then(|pinned| pinned.as_bool())
+            == Some(true)
+    }
+
+    pub fn set_pinned(&mut self, pinned: bool) {
+        let metadata = self
+            .metadata
+            .get_or_insert_with(|| serde_json::Value::Object(Default::default()));
+        if let Some(metadata) = metadata.as_object_mut() {
+            if pinned {
+                metadata.insert("pinned".to_string(), serde_json::Value::Bool(true));
+            } else {
+                metadata.remove("pinned");
+            }
         }
     }
 
@@ -165,6 +284,7 @@ mod tests {
             schema_version: SCHEMA_VERSION,
             tool_name: "search".to_string(),
             input: json!({"query": "test"}),
+            rationale: None,
         };
         let serialized = serde_json::to_string(&response).unwrap();
         let deserialized: LLMResponse = serde_json::from_str(&serialized).unwrap();
@@ -0,0 +1,47 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+//! Attempt log rendering tests.
+
+use hypr_claw_runtime::attempts::with_previous_attempt;
+use hypr_claw_runtime::citations::Evidence;
+use hypr_claw_runtime::AttemptLog;
+use serde_json::json;
+
+#[test]
+fn test_render_lists_calls_failures_and_progress() {
+    let mut log = AttemptLog::new("run-1");
+    log.error = Some("Tool error: Too many consecutive tool failures".to_string());
+    log.tool_calls.push(Evidence {
+        call_id: "t1".to_string(),
+        tool_name: "fs.create_dir".to_string(),
+        input: json!({"path": "site"}),
+        succeeded: true,
+        output: json!({"created": "site"}),
+    });
+    log.tool_calls.push(Evidence {
+        call_id: "t2".to_string(),
+        tool_name: "proc.spawn".to_string(),
+        input: json!({"command": "npm"}),
+        succeeded: false,
+        output: json!({"error": "npm not found"}),
+    });
+
+    let rendered = log.render();
+    assert!(rendered.starts_with("Previous attempt (run run-1) failed: Tool error"));
+    assert!(
+        rendered.contains(r#"t2 FAILED proc.spawn {"command":"npm"} -> {"error":"npm not found"}"#)
+    );
+    assert!(rendered.ends_with("Completed steps: t1 fs.create_dir"));
+
+    let prompt = with_previous_attempt("build the site", &rendered);
+    assert!(prompt.starts_with("build the site\n\nPrevious attempt"));
+    assert!(prompt.contains("Do not repeat a failed call"));
+}
+
+#[test]
+fn test_render_without_tool_calls() {
+    let rendered = AttemptLog::new("run-2").render();
+    assert_eq!(
+        rendered,
+        "Previous attempt (run run-2) did not finish.\nIt made no tool calls."
+    );
+}
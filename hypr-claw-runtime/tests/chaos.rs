@@ -0,0 +1,256 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+//! Recovery under injected tool failures, LLM timeouts and rate limits.
+
+use async_trait::async_trait;
+use hypr_claw_runtime::chaos::{
+    tool_then_final, Chaos, ChaosConfig, ChaosToolDispatcher, FakeProvider, Responder,
+};
+use hypr_claw_runtime::*;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Default)]
+struct MemoryStore {
+    sessions: Mutex<HashMap<String, Vec<Message>>>,
+}
+
+#[async_trait]
+impl SessionStore for MemoryStore {
+    async fn load(&self, session_key: &str) -> Result<Vec<Message>, RuntimeError> {
+        Ok(self
+            .sessions
+            .lock()
+            .unwrap()
+            .get(session_key)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn save(&self, session_key: &str, messages: &[Message]) -> Result<(), RuntimeError> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(session_key.to_string(), messages.to_vec());
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Locks {
+    held: Mutex<HashSet<String>>,
+}
+
+#[async_trait]
+impl LockManager for Locks {
+    async fn acquire(&self, session_key: &str) -> Result<(), RuntimeError> {
+        if !self.held.lock().unwrap().insert(session_key.to_string()) {
+            return Err(RuntimeError::LockError(format!("{session_key} is held")));
+        }
+        Ok(())
+    }
+
+    async fn release(&self, session_key: &str) {
+        self.held.lock().unwrap().remove(session_key);
+    }
+}
+
+struct EchoDispatcher;
+
+#[async_trait]
+impl ToolDispatcher for EchoDispatcher {
+    async fn execute(
+        &self,
+        tool_name: &str,
+        input: &serde_json::Value,
+        _session_key: &str,
+    ) -> Result<serde_json::Value, RuntimeError> {
+        Ok(json!({"tool": tool_name, "input": input}))
+    }
+}
+
+struct OneTool;
+
+impl ToolRegistry for OneTool {
+    fn get_active_tools(&self, _agent_id: &str) -> Vec<String> {
+        vec!["echo".to_string()]
+    }
+
+    fn get_tool_schemas(&self, _agent_id: &str) -> Vec<serde_json::Value> {
+        vec![json!({
+            "type": "function",
+            "function": {
+                "name": "echo",
+                "description": "Echo the input",
+                "parameters": {"type": "object", "properties": {}}
+            }
+        })]
+    }
+}
+
+struct NoSummary;
+
+impl Summarizer for NoSummary {
+    fn summarize(&self, _messages: &[Message]) -> Result<String, RuntimeError> {
+        Ok(String::new())
+    }
+}
+
+type ChaosLoop =
+    AgentLoop<MemoryStore, Locks, ChaosToolDispatcher<EchoDispatcher>, OneTool, NoSummary>;
+
+struct Harness {
+    agent: ChaosLoop,
+    store: Arc<MemoryStore>,
+    locks: Arc<Locks>,
+    chaos: Chaos,
+    _provider: FakeProvider,
+}
+
+async fn harness(config: ChaosConfig, responder: Responder, max_retries: u32) -> Harness {
+    let chaos = Chaos::new(config);
+    let provider = FakeProvider::start(chaos.clone(), responder).await.unwrap();
+    let client = LLMClient::new(provider.url().to_string(), max_retries)
+        .with_request_timeout(Duration::from_millis(300));
+    let store = Arc::new(MemoryStore::default());
+    let locks = Arc::new(Locks::default());
+    let agent = AgentLoop::new(
+        store.clone(),
+        locks.clone(),
+        Arc::new(ChaosToolDispatcher::new(
+            Arc::new(EchoDispatcher),
+            chaos.clone(),
+        )),
+        Arc::new(OneTool),
+        LLMClientType::Standard(client),
+        Compactor::new(100_000, NoSummary),
+        8,
+    );
+    Harness {
+        agent,
+        store,
+        locks,
+        chaos,
+        _provider: provider,
+    }
+}
+
+#[tokio::test]
+async fn provider_faults_are_retried_and_never_leak_locks() {
+    let config = ChaosConfig {
+        seed: 7,
+        llm_timeout: 0.2,
+        rate_limit: 0.2,
+        ..ChaosConfig::default()
+    };
+    let h = harness(config, tool_then_final(), 3).await;
+
+    let mut completed = 0;
+    for run in 0..6 {
+        let session = format!("chaos:{run}");
+        match h
+            .agent
+            .run(&session, "agent", "", "check the echo tool")
+            .await
+        {
+            Ok(answer) => {
+                completed += 1;
+                assert_eq!(answer, "done");
+                let history = h.store.load(&session).await.unwrap();
+                let roles: Vec<Role> = history.iter().map(|m| m.role.clone()).collect();
+                assert_eq!(
+                    roles,
+                    vec![Role::User, Role::Assistant, Role::Tool, Role::Assistant]
+                );
+            }
+            Err(e) => assert!(
+                e.to_string().contains("LLM call failed after 4 attempts"),
+                "unexpected failure: {e}"
+            ),
+        }
+        assert!(h.locks.held.lock().unwrap().is_empty());
+    }
+
+    let stats = h.chaos.stats();
+    assert!(stats.rate_limited > 0, "{stats:?}");
+    assert!(stats.timed_out > 0, "{stats:?}");
+    assert!(
+        completed >= 5,
+        "{completed} of 6 runs recovered ({stats:?})"
+    );
+}
+
+#[tokio::test]
+async fn intermittent_tool_failures_are_recovered_within_a_run() {
+    let config = ChaosConfig {
+        seed: 11,
+        tool_failure: 0.4,
+        ..ChaosConfig::default()
+    };
+    // Retries the tool until one call succeeds, like a model reading errors.
+    let retry_until_ok: Responder = Arc::new(|messages, _tools| {
+        let last = messages.last();
+        let succeeded =
+            last.is_some_and(|m| m.role == Role::Tool && m.content.get("error").is_none());
+        if succeeded {
+            LLMResponse::Final {
+                schema_version: SCHEMA_VERSION,
+                content: "done".to_string(),
+            }
+        } else {
+            LLMResponse::ToolCall {
+                schema_version: SCHEMA_VERSION,
+                tool_name: "echo".to_string(),
+                input: json!({"attempt": messages.len()}),
+                rationale: None,
+            }
+        }
+    });
+    let h = harness(config, retry_until_ok, 0).await;
+
+    for run in 0..8 {
+        let session = format!("tools:{run}");
+        let result = h
+            .agent
+            .run(&session, "agent", "", "run the echo tool")
+            .await;
+        match result {
+            Ok(answer) => assert_eq!(answer, "done"),
+            Err(e) => assert!(
+                e.to_string().contains("consecutive tool failures"),
+                "unexpected failure: {e}"
+            ),
+        }
+        assert!(h.locks.held.lock().unwrap().is_empty());
+    }
+    let stats = h.chaos.stats();
+    assert!(stats.tool_failures > 0, "{stats:?}");
+    assert!(stats.tool_failures < stats.tool_calls, "{stats:?}");
+}
+
+#[tokio::test]
+async fn a_failure_streak_halts_the_run() {
+    let config = ChaosConfig {
+        tool_failure: 1.0,
+        ..ChaosConfig::default()
+    };
+    let always_call: Responder = Arc::new(|messages, _tools| LLMResponse::ToolCall {
+        schema_version: SCHEMA_VERSION,
+        tool_name: "echo".to_string(),
+        input: json!({"attempt": messages.len()}),
+        rationale: None,
+    });
+    let h = harness(config, always_call, 0).await;
+
+    let err = h
+        .agent
+        .run("streak", "agent", "", "run the echo tool")
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Too many consecutive tool failures"));
+    assert_eq!(h.chaos.stats().tool_failures, 4);
+    assert!(h.locks.held.lock().unwrap().is_empty());
+}
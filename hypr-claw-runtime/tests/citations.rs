@@ -0,0 +1,60 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+//! Citation extraction and validation tests.
+
+use hypr_claw_runtime::citations::{
+    call_id, extract_call_ids, render_footnotes, validate, Evidence,
+};
+use serde_json::json;
+
+fn evidence() -> Vec<Evidence> {
+    vec![
+        Evidence {
+            call_id: call_id(1),
+            tool_name: "fs.write".to_string(),
+            input: json!({"path": "notes.txt"}),
+            succeeded: true,
+            output: json!({"written": "notes.txt"}),
+        },
+        Evidence {
+            call_id: call_id(2),
+            tool_name: "proc.spawn".to_string(),
+            input: json!({"command": "rm"}),
+            succeeded: false,
+            output: json!({"error": "not allowed"}),
+        },
+    ]
+}
+
+#[test]
+fn test_extract_call_ids_ignores_other_brackets() {
+    let text = "Wrote it [t1]. See [1] and [tx] and [t12], again [t1].";
+    assert_eq!(extract_call_ids(text), vec!["t1", "t12"]);
+}
+
+#[test]
+fn test_validate_accepts_supported_claims() {
+    let citations = validate("Created notes.txt [t1]", &evidence()).unwrap();
+    assert_eq!(citations.len(), 1);
+    assert_eq!(citations[0].tool_name, "fs.write");
+    assert_eq!(
+        render_footnotes(&citations),
+        vec![r#"[t1] fs.write: {"written":"notes.txt"}"#]
+    );
+}
+
+#[test]
+fn test_validate_rejects_missing_failed_and_unknown_citations() {
+    assert!(validate("All done!", &evidence()).is_err());
+    assert!(validate("Started the process [t2]", &evidence())
+        .unwrap_err()
+        .contains("failed"));
+    assert!(validate("Done [t9]", &evidence())
+        .unwrap_err()
+        .contains("unknown"));
+}
+
+#[test]
+fn test_validate_allows_plain_answers_without_tool_results() {
+    assert!(validate("Hello there", &[]).unwrap().is_empty());
+    assert!(validate("I did it [t1]", &[]).is_err());
+}
@@ -0,0 +1,138 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+//! The versioned tool-call bridge spoken over the Codex backend.
+
+use hypr_claw_runtime::codex_protocol::{
+    encode_tool_call, encode_tool_result, instructions, parse_reply, PROTOCOL_VERSION,
+    SUPPORTED_VERSIONS,
+};
+use hypr_claw_runtime::{LLMResponse, RuntimeError};
+use serde_json::{json, Value};
+
+fn offered() -> Vec<String> {
+    vec!["desktop.launch_app".to_string(), "fs.read".to_string()]
+}
+
+fn tool_call(response: LLMResponse) -> (String, Value, Option<String>) {
+    match response {
+        LLMResponse::ToolCall {
+            tool_name,
+            input,
+            rationale,
+            ..
+        } => (tool_name, input, rationale),
+        other => panic!("expected a tool call, got {other:?}"),
+    }
+}
+
+fn protocol_error(result: Result<LLMResponse, RuntimeError>) -> String {
+    match result {
+        Err(RuntimeError::LLMError(message)) => message,
+        other => panic!("expected a protocol error, got {other:?}"),
+    }
+}
+
+#[test]
+fn v1_envelopes_keep_their_shape() {
+    // Changing these is a protocol change: bump PROTOCOL_VERSION with it.
+    assert_eq!(PROTOCOL_VERSION, 1);
+    assert_eq!(
+        serde_json::from_str::<Value>(&encode_tool_call("fs.read", &json!({"path": "a"}))).unwrap(),
+        json!({"hypr_claw_bridge": 1, "tool_call": {"name": "fs.read", "arguments": {"path": "a"}}})
+    );
+    assert_eq!(
+        serde_json::from_str::<Value>(&encode_tool_result("fs.read", &json!("text"))).unwrap(),
+        json!({"hypr_claw_bridge": 1, "tool_result": {"name": "fs.read", "output": "text"}})
+    );
+}
+
+#[test]
+fn encoded_tool_calls_parse_back() {
+    let text = encode_tool_call("desktop.launch_app", &json!({"app": "firefox"}));
+    let (name, input, rationale) = tool_call(parse_reply(&text, &offered()).unwrap());
+    assert_eq!(name, "desktop.launch_app");
+    assert_eq!(input, json!({"app": "firefox"}));
+    assert_eq!(rationale, None);
+}
+
+#[test]
+fn text_before_a_fenced_envelope_is_the_rationale() {
+    let text = "Firefox is not running yet.\n```json\n{\"hypr_claw_bridge\": 1, \"tool_call\": \
+                {\"name\": \"desktop.launch_app\", \"arguments\": \"{\\\"app\\\": \\\"firefox\\\"}\"}}\n```";
+    let (name, input, rationale) = tool_call(parse_reply(text, &offered()).unwrap());
+    assert_eq!(name, "desktop.launch_app");
+    assert_eq!(input, json!({"app": "firefox"}));
+    assert_eq!(rationale.as_deref(), Some("Firefox is not running yet."));
+}
+
+#[test]
+fn plain_text_and_final_envelopes_are_answers() {
+    for (text, answer) in [
+        ("Firefox is open.", "Firefox is open."),
+        ("{\"hypr_claw_bridge\": 1, \"final\": \"done\"}", "done"),
+        ("{\"windows\": 3}", "{\"windows\": 3}"),
+    ] {
+        match parse_reply(text, &offered()).unwrap() {
+            LLMResponse::Final { content, .. } => assert_eq!(content, answer),
+            other => panic!("expected a final answer for {text:?}, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn unknown_fields_are_ignored() {
+    let text = r#"{"hypr_claw_bridge": 1, "tool_call": {"name": "fs.read", "arguments": {}, "id": "c1"}, "trace": 7}"#;
+    let (name, input, _) = tool_call(parse_reply(text, &offered()).unwrap());
+    assert_eq!(name, "fs.read");
+    assert_eq!(input, json!({}));
+}
+
+#[test]
+fn replies_outside_the_protocol_are_errors() {
+    let newer = SUPPORTED_VERSIONS.end() + 1;
+    let message = protocol_error(parse_reply(
+        &format!(r#"{{"hypr_claw_bridge": {newer}, "tool_call": {{"name": "fs.read"}}}}"#),
+        &offered(),
+    ));
+    assert!(message.contains(&format!("v{newer}")), "{message}");
+
+    let message = protocol_error(parse_reply(
+        r#"{"hypr_claw_bridge": 1, "tool_call": {"name": "shell.exec", "arguments": {}}}"#,
+        &offered(),
+    ));
+    assert!(message.contains("shell.exec"), "{message}");
+
+    let message = protocol_error(parse_reply(
+        "```json\n{\"tool_call\": {\"name\": \"fs.read\"}}\n```",
+        &offered(),
+    ));
+    assert!(message.contains("without"), "{message}");
+
+    for text in [
+        r#"{"hypr_claw_bridge": "1", "final": "done"}"#,
+        r#"{"hypr_claw_bridge": 1, "tool_call": {"arguments": {}}}"#,
+        r#"{"hypr_claw_bridge": 1, "tool_call": {"name": "fs.read", "arguments": "{not json"}}"#,
+        r#"{"hypr_claw_bridge": 1, "tool_result": {"name": "fs.read", "output": 1}}"#,
+    ] {
+        protocol_error(parse_reply(text, &offered()));
+    }
+}
+
+#[test]
+fn instructions_name_the_version_and_tools() {
+    assert_eq!(instructions(&[]), None);
+    let text = instructions(&[json!({
+        "type": "function",
+        "function": {
+            "name": "fs.read",
+            "description": "Read a file.",
+            "parameters": {"type": "object", "properties": {"path": {"type": "string"}}}
+        }
+    })])
+    .unwrap();
+    assert!(text.contains("protocol v1"), "{text}");
+    assert!(text.contains("\"hypr_claw_bridge\": 1"), "{text}");
+    assert!(
+        text.contains("- fs.read: Read a file. Arguments: {"),
+        "{text}"
+    );
+}
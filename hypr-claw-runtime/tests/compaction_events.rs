@@ -0,0 +1,192 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+//! Compaction reports and the targets they summarized away.
+
+use async_trait::async_trait;
+use hypr_claw_runtime::compaction_events::read_target;
+use hypr_claw_runtime::*;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+fn tool_call(tool: &str, input: serde_json::Value) -> Message {
+    Message::with_metadata(
+        Role::Assistant,
+        json!(format!("Calling tool: {tool}")),
+        json!({"tool_call": true, "tool_name": tool, "input": input}),
+    )
+}
+
+fn history() -> Vec<Message> {
+    vec![
+        tool_call("fs.read", json!({"path": "/home/ana/notes.md"})),
+        Message::new(Role::Tool, json!("x".repeat(400))),
+        tool_call("desktop.ocr_screen", json!({})),
+        Message::new(Role::Tool, json!("y".repeat(400))),
+        Message::new(Role::User, json!("and now?")),
+        Message::new(Role::Assistant, json!("z".repeat(200))),
+        Message::new(Role::User, json!("thanks")),
+        Message::new(Role::Assistant, json!("done")),
+    ]
+}
+
+struct Summary;
+
+impl Summarizer for Summary {
+    fn summarize(&self, messages: &[Message]) -> Result<String, RuntimeError> {
+        Ok(format!(
+            "read notes and the screen ({} messages)",
+            messages.len()
+        ))
+    }
+}
+
+#[test]
+fn reports_count_saved_tokens_and_summarized_targets() {
+    let (messages, report) = Compactor::new(100, Summary)
+        .compact_with_report(history())
+        .unwrap();
+    let report = report.unwrap();
+    assert_eq!(messages.len(), 5);
+    assert_eq!(report.summarized_messages, 4);
+    assert_eq!(report.kept_messages, 4);
+    assert!(report.tokens_saved() > 150);
+    assert_eq!(report.summarized_tools.get("fs.read"), Some(&1));
+    assert_eq!(report.targets, vec!["file:/home/ana/notes.md", "screen"]);
+    assert_eq!(report.summary, "read notes and the screen (4 messages)");
+
+    let (_, none) = Compactor::new(100_000, Summary)
+        .compact_with_report(history())
+        .unwrap();
+    assert!(none.is_none());
+}
+
+#[test]
+fn pinned_messages_survive_compaction() {
+    let mut messages = history();
+    messages[1].set_pinned(true);
+    assert!(messages[1].is_pinned());
+    let compactor = Compactor::new(100, Summary);
+    assert_eq!(compactor.preview(&messages).summarized, 3);
+
+    let (compacted, report) = compactor.compact_with_report(messages).unwrap();
+    let report = report.unwrap();
+    assert_eq!(report.summarized_messages, 3);
+    assert_eq!(report.kept_messages, 5);
+    assert_eq!(report.targets, vec!["file:/home/ana/notes.md", "screen"]);
+    assert_eq!(compacted[1].content, json!("x".repeat(400)));
+    assert!(compacted[1].is_pinned());
+
+    let mut pinned = history();
+    for message in &mut pinned[..4] {
+        message.set_pinned(true);
+    }
+    let (kept, none) = compactor.compact_with_report(pinned).unwrap();
+    assert!(none.is_none());
+    assert_eq!(kept.len(), 8);
+
+    let mut unpinned = kept[0].clone();
+    unpinned.set_pinned(false);
+    assert!(!unpinned.is_pinned());
+}
+
+#[test]
+fn read_targets_normalize_paths_and_skip_other_tools() {
+    assert_eq!(
+        read_target("fs.list", &json!({"path": "/tmp/"})).as_deref(),
+        Some("file:/tmp")
+    );
+    assert_eq!(
+        read_target("desktop.window_snapshot", &json!({})).as_deref(),
+        Some("window:active")
+    );
+    assert_eq!(read_target("fs.write", &json!({"path": "/tmp/a"})), None);
+    assert_eq!(read_target("fs.read", &json!({})), None);
+}
+
+struct HistoryStore;
+
+#[async_trait]
+impl SessionStore for HistoryStore {
+    async fn load(&self, _session_key: &str) -> Result<Vec<Message>, RuntimeError> {
+        Ok(history())
+    }
+
+    async fn save(&self, _session_key: &str, _messages: &[Message]) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+}
+
+struct NoopLocks;
+
+#[async_trait]
+impl LockManager for NoopLocks {
+    async fn acquire(&self, _session_key: &str) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    async fn release(&self, _session_key: &str) {}
+}
+
+struct NoopDispatcher;
+
+#[async_trait]
+impl ToolDispatcher for NoopDispatcher {
+    async fn execute(
+        &self,
+        _tool_name: &str,
+        _input: &serde_json::Value,
+        _session_key: &str,
+    ) -> Result<serde_json::Value, RuntimeError> {
+        Ok(json!({}))
+    }
+}
+
+struct OneTool;
+
+impl ToolRegistry for OneTool {
+    fn get_active_tools(&self, _agent_id: &str) -> Vec<String> {
+        vec!["echo".to_string()]
+    }
+
+    fn get_tool_schemas(&self, _agent_id: &str) -> Vec<serde_json::Value> {
+        vec![json!({
+            "type": "function",
+            "function": {
+                "name": "echo",
+                "description": "Echo a message",
+                "parameters": {"type": "object", "properties": {}}
+            }
+        })]
+    }
+}
+
+#[tokio::test]
+async fn runs_report_their_compaction_to_the_observer() {
+    let agent_loop = AgentLoop::new(
+        Arc::new(HistoryStore),
+        Arc::new(NoopLocks),
+        Arc::new(NoopDispatcher),
+        Arc::new(OneTool),
+        LLMClientType::Standard(LLMClient::new("http://mock".to_string(), 0)),
+        Compactor::new(100, Summary),
+        3,
+    );
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let seen = events.clone();
+    agent_loop.set_compaction_observer(Arc::new(move |event| {
+        seen.lock().unwrap().push(event.clone());
+    }));
+
+    // The mock provider is unreachable; compaction happens before the call.
+    assert!(agent_loop.run("s", "agent", "system", "hi").await.is_err());
+    let events = events.lock().unwrap();
+    let [CompactionEvent::Compacted {
+        session_key,
+        report,
+        ..
+    }] = &events[..]
+    else {
+        panic!("expected one compaction, got {events:?}");
+    };
+    assert_eq!(session_key, "s");
+    assert_eq!(report.targets.len(), 2);
+}
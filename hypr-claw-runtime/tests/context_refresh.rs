@@ -0,0 +1,142 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+//! Refreshing the runtime context of long or diverged runs.
+
+use async_trait::async_trait;
+use hypr_claw_runtime::context_refresh::diverged;
+use hypr_claw_runtime::*;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+struct MemoryStore;
+
+#[async_trait]
+impl SessionStore for MemoryStore {
+    async fn load(&self, _session_key: &str) -> Result<Vec<Message>, RuntimeError> {
+        Ok(vec![])
+    }
+
+    async fn save(&self, _session_key: &str, _messages: &[Message]) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+}
+
+struct NoopLocks;
+
+#[async_trait]
+impl LockManager for NoopLocks {
+    async fn acquire(&self, _session_key: &str) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    async fn release(&self, _session_key: &str) {}
+}
+
+struct NoopDispatcher;
+
+#[async_trait]
+impl ToolDispatcher for NoopDispatcher {
+    async fn execute(
+        &self,
+        _tool_name: &str,
+        _input: &serde_json::Value,
+        _session_key: &str,
+    ) -> Result<serde_json::Value, RuntimeError> {
+        Ok(json!({}))
+    }
+}
+
+struct OneTool;
+
+impl ToolRegistry for OneTool {
+    fn get_active_tools(&self, _agent_id: &str) -> Vec<String> {
+        vec!["echo".to_string()]
+    }
+
+    fn get_tool_schemas(&self, _agent_id: &str) -> Vec<serde_json::Value> {
+        vec![json!({
+            "type": "function",
+            "function": {
+                "name": "echo",
+                "description": "Echo a message",
+                "parameters": {"type": "object", "properties": {}}
+            }
+        })]
+    }
+}
+
+struct CountSummarizer;
+
+impl Summarizer for CountSummarizer {
+    fn summarize(&self, messages: &[Message]) -> Result<String, RuntimeError> {
+        Ok(format!("{} messages", messages.len()))
+    }
+}
+
+#[derive(Default)]
+struct RecordingRefresher {
+    reasons: Mutex<Vec<(String, RefreshReason)>>,
+}
+
+#[async_trait]
+impl ContextRefresher for RecordingRefresher {
+    async fn refresh(&self, session_key: &str, reason: &RefreshReason) -> Option<String> {
+        self.reasons
+            .lock()
+            .unwrap()
+            .push((session_key.to_string(), reason.clone()));
+        Some("- active_workspace: 2".to_string())
+    }
+}
+
+fn agent_loop() -> AgentLoop<MemoryStore, NoopLocks, NoopDispatcher, OneTool, CountSummarizer> {
+    AgentLoop::new(
+        Arc::new(MemoryStore),
+        Arc::new(NoopLocks),
+        Arc::new(NoopDispatcher),
+        Arc::new(OneTool),
+        LLMClientType::Standard(LLMClient::new("http://mock".to_string(), 0)),
+        Compactor::new(1000, CountSummarizer),
+        3,
+    )
+}
+
+#[tokio::test]
+async fn stale_context_is_refreshed_before_the_next_model_call() {
+    let agent_loop = agent_loop();
+    let refresher = Arc::new(RecordingRefresher::default());
+    agent_loop.set_context_refresher(Some(refresher.clone()), Duration::ZERO);
+
+    // The mock provider is unreachable; the refresh comes before the call.
+    assert!(agent_loop.run("s", "agent", "system", "hi").await.is_err());
+    let reasons = refresher.reasons.lock().unwrap();
+    assert_eq!(reasons.len(), 1);
+    assert_eq!(reasons[0].0, "s");
+    assert!(matches!(reasons[0].1, RefreshReason::Stale { .. }));
+}
+
+#[tokio::test]
+async fn fresh_context_is_left_alone() {
+    let agent_loop = agent_loop();
+    let refresher = Arc::new(RecordingRefresher::default());
+    agent_loop.set_context_refresher(Some(refresher.clone()), Duration::from_secs(600));
+
+    assert!(agent_loop.run("s", "agent", "system", "hi").await.is_err());
+    assert!(refresher.reasons.lock().unwrap().is_empty());
+}
+
+#[test]
+fn missing_windows_count_as_divergence() {
+    assert!(diverged("Not found: window 'firefox'"));
+    assert!(diverged("No such window found"));
+    assert!(diverged("workspace 4 no longer exists"));
+    assert!(!diverged("Not found: /tmp/report.pdf"));
+    assert!(!diverged("window capture timed out"));
+    assert_eq!(
+        RefreshReason::Stale {
+            age: Duration::from_secs(95)
+        }
+        .describe(),
+        "95s old"
+    );
+}
@@ -64,6 +64,7 @@ fn test_llm_response_deep_nested_json() {
         schema_version: hypr_claw_runtime::SCHEMA_VERSION,
         tool_name: "test".to_string(),
         input: nested,
+        rationale: None,
     };
 
     // Should serialize and deserialize without panic
@@ -155,6 +156,7 @@ proptest! {
         schema_version: hypr_claw_runtime::SCHEMA_VERSION,
             tool_name,
             input: json!({"query": query}),
+        rationale: None,
         };
 
         // Should serialize without panic
@@ -206,6 +208,7 @@ fn test_empty_strings_handled_safely() {
         schema_version: hypr_claw_runtime::SCHEMA_VERSION,
         tool_name: "".to_string(),
         input: json!({}),
+        rationale: None,
     };
 
     // Serialization works
@@ -0,0 +1,49 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+//! Stop sequences and narration limits on model output.
+
+use hypr_claw_runtime::OutputGuardrails;
+
+#[test]
+fn test_stop_sequences_cut_the_answer() {
+    let guardrails = OutputGuardrails {
+        stop_sequences: vec!["</answer>".to_string(), "\nUser:".to_string()],
+        max_narration_tokens: 0,
+    };
+    let (content, trimmed) = guardrails.apply("Opened Firefox. \nUser: now close it</answer>");
+    assert_eq!(content, "Opened Firefox.");
+    assert!(trimmed);
+
+    let (content, trimmed) = guardrails.apply("Nothing to cut");
+    assert_eq!(content, "Nothing to cut");
+    assert!(!trimmed);
+}
+
+#[test]
+fn test_narration_budget_trims_long_prose() {
+    let guardrails = OutputGuardrails {
+        stop_sequences: Vec::new(),
+        max_narration_tokens: 2,
+    };
+    let (content, trimmed) = guardrails.apply("ééééééééééé and more");
+    assert_eq!(content, "éééééééé …[trimmed]");
+    assert!(trimmed);
+}
+
+#[test]
+fn test_request_parameters() {
+    let none = OutputGuardrails::default();
+    assert_eq!(none.request_stop(), None);
+    assert_eq!(none.request_max_tokens(Some(4096)), Some(4096));
+
+    let limited = OutputGuardrails {
+        stop_sequences: (0..6)
+            .map(|i| format!("s{i}"))
+            .chain([String::new()])
+            .collect(),
+        max_narration_tokens: 512,
+    };
+    assert_eq!(limited.request_stop().unwrap().len(), 4);
+    assert_eq!(limited.request_max_tokens(Some(4096)), Some(512));
+    assert_eq!(limited.request_max_tokens(Some(256)), Some(256));
+    assert_eq!(limited.request_max_tokens(None), Some(512));
+}
@@ -0,0 +1,29 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+//! Parsing of provider `/models` responses.
+
+use hypr_claw_runtime::llm_client::parse_models_response;
+
+#[test]
+fn test_models_response_keeps_reported_metadata() {
+    let body = r#"{"data": [
+        {"id": "b/model", "context_length": 131072, "supported_parameters": ["tools", "temperature"]},
+        {"id": "a/model", "max_model_len": 32768, "capabilities": {"function_calling": false}},
+        {"id": "b/model"},
+        {"object": "model"}
+    ]}"#;
+    let models = parse_models_response(body);
+    assert_eq!(models.len(), 2);
+    assert_eq!(models[0].id, "a/model");
+    assert_eq!(models[0].context_window, Some(32768));
+    assert_eq!(models[0].supports_tools, Some(false));
+    assert_eq!(models[1].context_window, Some(131072));
+    assert_eq!(models[1].supports_tools, Some(true));
+}
+
+#[test]
+fn test_models_response_without_metadata_or_data() {
+    let models = parse_models_response(r#"{"data": [{"id": "plain"}]}"#);
+    assert_eq!(models[0].context_window, None);
+    assert_eq!(models[0].supports_tools, None);
+    assert!(parse_models_response("not json").is_empty());
+}
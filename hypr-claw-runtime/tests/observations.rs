@@ -0,0 +1,115 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+//! Notes typed by the user while a run is in progress.
+
+use async_trait::async_trait;
+use hypr_claw_runtime::*;
+use serde_json::json;
+use std::sync::Arc;
+
+struct MemoryStore;
+
+#[async_trait]
+impl SessionStore for MemoryStore {
+    async fn load(&self, _session_key: &str) -> Result<Vec<Message>, RuntimeError> {
+        Ok(vec![])
+    }
+
+    async fn save(&self, _session_key: &str, _messages: &[Message]) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+}
+
+struct NoopLocks;
+
+#[async_trait]
+impl LockManager for NoopLocks {
+    async fn acquire(&self, _session_key: &str) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    async fn release(&self, _session_key: &str) {}
+}
+
+struct NoopDispatcher;
+
+#[async_trait]
+impl ToolDispatcher for NoopDispatcher {
+    async fn execute(
+        &self,
+        _tool_name: &str,
+        _input: &serde_json::Value,
+        _session_key: &str,
+    ) -> Result<serde_json::Value, RuntimeError> {
+        Ok(json!({}))
+    }
+}
+
+struct OneTool;
+
+impl ToolRegistry for OneTool {
+    fn get_active_tools(&self, _agent_id: &str) -> Vec<String> {
+        vec!["echo".to_string()]
+    }
+
+    fn get_tool_schemas(&self, _agent_id: &str) -> Vec<serde_json::Value> {
+        vec![json!({
+            "type": "function",
+            "function": {
+                "name": "echo",
+                "description": "Echo a message",
+                "parameters": {"type": "object", "properties": {}}
+            }
+        })]
+    }
+}
+
+struct CountSummarizer;
+
+impl Summarizer for CountSummarizer {
+    fn summarize(&self, messages: &[Message]) -> Result<String, RuntimeError> {
+        Ok(format!("{} messages", messages.len()))
+    }
+}
+
+fn agent_loop() -> AgentLoop<MemoryStore, NoopLocks, NoopDispatcher, OneTool, CountSummarizer> {
+    AgentLoop::new(
+        Arc::new(MemoryStore),
+        Arc::new(NoopLocks),
+        Arc::new(NoopDispatcher),
+        Arc::new(OneTool),
+        LLMClientType::Standard(LLMClient::new("http://mock".to_string(), 0)),
+        Compactor::new(1000, CountSummarizer),
+        3,
+    )
+}
+
+#[test]
+fn test_observations_are_kept_per_session() {
+    let agent_loop = agent_loop();
+    agent_loop.push_observation("a", "use the left monitor");
+    agent_loop.push_observation("a", "and skip the dock");
+    agent_loop.push_observation("b", "unrelated");
+
+    assert_eq!(
+        agent_loop.take_observations("a"),
+        vec!["use the left monitor", "and skip the dock"]
+    );
+    assert!(agent_loop.take_observations("a").is_empty());
+    assert_eq!(agent_loop.take_observations("b"), vec!["unrelated"]);
+}
+
+#[tokio::test]
+async fn test_run_picks_up_pending_observations() {
+    let agent_loop = agent_loop();
+    agent_loop.push_observation("s", "also close the old window");
+    agent_loop.push_observation("other", "not mine");
+
+    // The mock provider is unreachable, but the note is consumed before the
+    // first model call.
+    assert!(agent_loop
+        .run("s", "agent", "system", "open firefox")
+        .await
+        .is_err());
+    assert!(agent_loop.take_observations("s").is_empty());
+    assert_eq!(agent_loop.take_observations("other"), vec!["not mine"]);
+}
@@ -0,0 +1,225 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+//! Read-only calls prefetched alongside the first model call.
+
+use async_trait::async_trait;
+use hypr_claw_runtime::chaos::{Chaos, ChaosConfig, FakeProvider, Responder};
+use hypr_claw_runtime::*;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct MemoryStore {
+    sessions: Mutex<HashMap<String, Vec<Message>>>,
+}
+
+#[async_trait]
+impl SessionStore for MemoryStore {
+    async fn load(&self, session_key: &str) -> Result<Vec<Message>, RuntimeError> {
+        Ok(self
+            .sessions
+            .lock()
+            .unwrap()
+            .get(session_key)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn save(&self, session_key: &str, messages: &[Message]) -> Result<(), RuntimeError> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(session_key.to_string(), messages.to_vec());
+        Ok(())
+    }
+}
+
+struct NoopLocks;
+
+#[async_trait]
+impl LockManager for NoopLocks {
+    async fn acquire(&self, _session_key: &str) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    async fn release(&self, _session_key: &str) {}
+}
+
+/// Echoes each call and remembers it.
+#[derive(Default)]
+struct RecordingDispatcher {
+    calls: Mutex<Vec<String>>,
+}
+
+#[async_trait]
+impl ToolDispatcher for RecordingDispatcher {
+    async fn execute(
+        &self,
+        tool_name: &str,
+        input: &serde_json::Value,
+        _session_key: &str,
+    ) -> Result<serde_json::Value, RuntimeError> {
+        self.calls.lock().unwrap().push(tool_name.to_string());
+        Ok(json!({"tool": tool_name, "input": input}))
+    }
+}
+
+struct FsTools;
+
+impl ToolRegistry for FsTools {
+    fn get_active_tools(&self, _agent_id: &str) -> Vec<String> {
+        vec!["fs.read".into(), "fs.list".into(), "fs.write".into()]
+    }
+
+    fn get_tool_schemas(&self, agent_id: &str) -> Vec<serde_json::Value> {
+        self.get_active_tools(agent_id)
+            .into_iter()
+            .map(|name| {
+                json!({
+                    "type": "function",
+                    "function": {
+                        "name": name,
+                        "description": "",
+                        "parameters": {"type": "object", "properties": {}}
+                    }
+                })
+            })
+            .collect()
+    }
+
+    fn is_read_only(&self, tool_name: &str) -> bool {
+        tool_name != "fs.write"
+    }
+}
+
+struct NoSummary;
+
+impl Summarizer for NoSummary {
+    fn summarize(&self, _messages: &[Message]) -> Result<String, RuntimeError> {
+        Ok(String::new())
+    }
+}
+
+type FsLoop = AgentLoop<MemoryStore, NoopLocks, RecordingDispatcher, FsTools, NoSummary>;
+
+struct Harness {
+    agent: FsLoop,
+    store: Arc<MemoryStore>,
+    dispatcher: Arc<RecordingDispatcher>,
+    _provider: FakeProvider,
+}
+
+/// Makes `first` the model's first call, then answers.
+async fn harness(first: Option<(&'static str, serde_json::Value)>) -> Harness {
+    let responder: Responder = Arc::new(move |messages, _tools| {
+        let called = messages.iter().any(|m| m.role == Role::Tool);
+        match &first {
+            Some((tool_name, input)) if !called => LLMResponse::ToolCall {
+                schema_version: SCHEMA_VERSION,
+                tool_name: tool_name.to_string(),
+                input: input.clone(),
+                rationale: None,
+            },
+            _ => LLMResponse::Final {
+                schema_version: SCHEMA_VERSION,
+                content: "done".to_string(),
+            },
+        }
+    });
+    let provider = FakeProvider::start(Chaos::new(ChaosConfig::default()), responder)
+        .await
+        .unwrap();
+    let store = Arc::new(MemoryStore::default());
+    let dispatcher = Arc::new(RecordingDispatcher::default());
+    let agent = AgentLoop::new(
+        store.clone(),
+        Arc::new(NoopLocks),
+        dispatcher.clone(),
+        Arc::new(FsTools),
+        LLMClientType::Standard(LLMClient::new(provider.url().to_string(), 0)),
+        Compactor::new(100_000, NoSummary),
+        4,
+    );
+    agent.set_prefetcher(Some(Arc::new(|_message: &str| {
+        vec![
+            PrefetchCall::new("fs.read", json!({"path": "/notes/today.md"})),
+            PrefetchCall::new("fs.write", json!({"path": "/notes/today.md"})),
+        ]
+    })));
+    Harness {
+        agent,
+        store,
+        dispatcher,
+        _provider: provider,
+    }
+}
+
+fn prefetched(message: &Message) -> bool {
+    message.metadata.as_ref().and_then(|m| m.get("prefetched")) == Some(&json!(true))
+}
+
+#[tokio::test]
+async fn matching_first_call_reuses_the_prefetched_result() {
+    let h = harness(Some(("fs.read", json!({"path": "/notes/today.md"})))).await;
+    let answer = h
+        .agent
+        .run("s", "agent", "", "summarize /notes/today.md")
+        .await
+        .unwrap();
+    assert_eq!(answer, "done");
+    // Run once as a prefetch, never again for the model; fs.write is not
+    // read-only and is never prefetched.
+    assert_eq!(*h.dispatcher.calls.lock().unwrap(), vec!["fs.read"]);
+
+    let history = h.store.load("s").await.unwrap();
+    let roles: Vec<Role> = history.iter().map(|m| m.role.clone()).collect();
+    assert_eq!(
+        roles,
+        vec![Role::User, Role::Assistant, Role::Tool, Role::Assistant]
+    );
+    assert_eq!(history[2].content["input"]["path"], "/notes/today.md");
+    assert!(!history.iter().any(prefetched));
+}
+
+#[tokio::test]
+async fn other_first_call_gets_the_prefetch_as_an_observation() {
+    let h = harness(Some(("fs.list", json!({"path": "/notes"})))).await;
+    h.agent
+        .run("s", "agent", "", "summarize /notes/today.md")
+        .await
+        .unwrap();
+    assert_eq!(
+        *h.dispatcher.calls.lock().unwrap(),
+        vec!["fs.read", "fs.list"]
+    );
+
+    let history = h.store.load("s").await.unwrap();
+    let tools: Vec<(&str, bool)> = history
+        .iter()
+        .filter(|m| m.role == Role::Tool)
+        .map(|m| (m.content["tool"].as_str().unwrap(), prefetched(m)))
+        .collect();
+    assert_eq!(tools, vec![("fs.read", true), ("fs.list", false)]);
+    let attempt = h.agent.last_attempt("s").unwrap();
+    assert_eq!(attempt.tool_calls.len(), 2);
+}
+
+#[tokio::test]
+async fn answers_without_tools_discard_the_prefetch() {
+    let h = harness(None).await;
+    h.agent
+        .run("s", "agent", "", "what is in /notes/today.md?")
+        .await
+        .unwrap();
+    let history = h.store.load("s").await.unwrap();
+    assert_eq!(history.len(), 2);
+
+    // Turned off: nothing runs before the model asks.
+    h.agent.set_prefetcher(None);
+    h.dispatcher.calls.lock().unwrap().clear();
+    h.agent
+        .run("t", "agent", "", "what is in /notes/today.md?")
+        .await
+        .unwrap();
+    assert!(h.dispatcher.calls.lock().unwrap().is_empty());
+}
@@ -0,0 +1,56 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+//! Per-provider request queueing.
+
+use hypr_claw_runtime::request_limits;
+use hypr_claw_runtime::RequestLimiter;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn excess_requests_wait_in_arrival_order() {
+    let limiter = Arc::new(RequestLimiter::new(1));
+    let order = Arc::new(parking_lot::Mutex::new(Vec::new()));
+    let first = limiter.acquire().await.unwrap();
+
+    let mut waiters = Vec::new();
+    for i in 0..3 {
+        let limiter = limiter.clone();
+        let order = order.clone();
+        waiters.push(tokio::spawn(async move {
+            let _permit = limiter.acquire().await.unwrap();
+            order.lock().push(i);
+        }));
+        // Let each waiter queue before the next one arrives.
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let stats = limiter.stats();
+    assert_eq!((stats.in_flight, stats.waiting), (1, 3));
+
+    drop(first);
+    for waiter in waiters {
+        waiter.await.unwrap();
+    }
+    assert_eq!(*order.lock(), vec![0, 1, 2]);
+    let stats = limiter.stats();
+    assert_eq!((stats.requests, stats.queued, stats.waiting), (4, 3, 0));
+    assert!(stats.max_wait_ms >= 10.0);
+    assert!(stats.average_wait_ms() > 0.0);
+}
+
+#[test]
+fn limiters_are_shared_by_provider() {
+    assert!(request_limits::limiter_for("test-provider").is_none());
+    request_limits::configure("test-provider", 2);
+    let a = request_limits::limiter_for("test-provider").unwrap();
+    request_limits::configure("test-provider", 2);
+    let b = request_limits::limiter_for("test-provider").unwrap();
+    assert!(Arc::ptr_eq(&a, &b));
+    assert_eq!(
+        request_limits::queue_snapshot()["test-provider"].max_concurrent,
+        2
+    );
+
+    request_limits::configure("test-provider", 3);
+    let c = request_limits::limiter_for("test-provider").unwrap();
+    assert_eq!(c.stats().max_concurrent, 3);
+}
@@ -0,0 +1,132 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+//! Composing the first request of a run without sending it.
+
+use async_trait::async_trait;
+use hypr_claw_runtime::*;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct MemoryStore {
+    sessions: Mutex<HashMap<String, Vec<Message>>>,
+    saves: Mutex<usize>,
+}
+
+#[async_trait]
+impl SessionStore for MemoryStore {
+    async fn load(&self, session_key: &str) -> Result<Vec<Message>, RuntimeError> {
+        Ok(self
+            .sessions
+            .lock()
+            .unwrap()
+            .get(session_key)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn save(&self, session_key: &str, messages: &[Message]) -> Result<(), RuntimeError> {
+        *self.saves.lock().unwrap() += 1;
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(session_key.to_string(), messages.to_vec());
+        Ok(())
+    }
+}
+
+/// Fails every acquire, so a preview that took the lock would error.
+struct NoLocks;
+
+#[async_trait]
+impl LockManager for NoLocks {
+    async fn acquire(&self, session_key: &str) -> Result<(), RuntimeError> {
+        Err(RuntimeError::LockError(format!("{session_key} is held")))
+    }
+
+    async fn release(&self, _session_key: &str) {}
+}
+
+struct NoDispatch;
+
+#[async_trait]
+impl ToolDispatcher for NoDispatch {
+    async fn execute(
+        &self,
+        tool_name: &str,
+        _input: &serde_json::Value,
+        _session_key: &str,
+    ) -> Result<serde_json::Value, RuntimeError> {
+        panic!("preview dispatched {tool_name}")
+    }
+}
+
+struct OneTool;
+
+impl ToolRegistry for OneTool {
+    fn get_active_tools(&self, _agent_id: &str) -> Vec<String> {
+        vec!["echo".to_string()]
+    }
+
+    fn get_tool_schemas(&self, _agent_id: &str) -> Vec<serde_json::Value> {
+        vec![json!({
+            "type": "function",
+            "function": {
+                "name": "echo",
+                "description": "Echo the input",
+                "parameters": {"type": "object", "properties": {}}
+            }
+        })]
+    }
+}
+
+struct NoSummary;
+
+impl Summarizer for NoSummary {
+    fn summarize(&self, _messages: &[Message]) -> Result<String, RuntimeError> {
+        panic!("preview summarized history")
+    }
+}
+
+#[tokio::test]
+async fn preview_composes_the_request_without_side_effects() {
+    let store = Arc::new(MemoryStore::default());
+    store.sessions.lock().unwrap().insert(
+        "s".to_string(),
+        (0..4)
+            .map(|i| Message::new(Role::User, json!(format!("{i}: {}", "x".repeat(40)))))
+            .collect(),
+    );
+    // Nothing listens on port 9; the model is never called.
+    let client = LLMClient::new("http://127.0.0.1:9".to_string(), 0);
+    let agent = AgentLoop::new(
+        store.clone(),
+        Arc::new(NoLocks),
+        Arc::new(NoDispatch),
+        Arc::new(OneTool),
+        LLMClientType::Standard(client),
+        Compactor::new(20, NoSummary),
+        8,
+    );
+
+    let preview = agent
+        .preview_request("s", "agent", "Be brief.", "list my files")
+        .await
+        .unwrap();
+    assert!(preview.system_prompt.starts_with("Be brief.\n\n"));
+    assert!(preview.system_prompt.ends_with("Available tools: echo"));
+    assert_eq!(preview.summarized, 2);
+    let texts = preview
+        .messages
+        .iter()
+        .map(|m| m.content.as_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(texts[0], "[summary of 2 older messages]");
+    assert!(texts[1].starts_with("2: "));
+    assert_eq!(texts.last().unwrap(), "list my files");
+    assert_eq!(preview.tool_schemas.len(), 1);
+    assert!(preview.estimated_tokens() > 50);
+
+    assert_eq!(*store.saves.lock().unwrap(), 0);
+    assert_eq!(store.load("s").await.unwrap().len(), 4);
+}
@@ -2,7 +2,7 @@
 
 #![allow(clippy::unwrap_used)]
 
-use hypr_claw_runtime::{LLMResponse, Message, Role, SCHEMA_VERSION};
+use hypr_claw_runtime::{LLMResponse, Message, Origin, Provenance, Role, SCHEMA_VERSION};
 use serde_json::json;
 
 #[test]
@@ -103,3 +103,35 @@ fn test_version_error_message() {
     assert!(err.contains("expected 1"));
     assert!(err.contains("got 2"));
 }
+
+#[test]
+fn test_message_provenance_roundtrip() {
+    let msg = Message::new(Role::Tool, json!({"rows": []})).with_provenance(
+        Provenance::new(Origin::Tool)
+            .tool("db.query")
+            .run("run-1")
+            .truncated(true),
+    );
+
+    let serialized = serde_json::to_value(&msg).unwrap();
+    assert_eq!(
+        serialized["provenance"],
+        json!({"origin": "tool", "tool_name": "db.query", "run_id": "run-1", "truncated": true})
+    );
+
+    let deserialized: Message = serde_json::from_value(serialized).unwrap();
+    assert_eq!(deserialized.provenance, msg.provenance);
+}
+
+#[test]
+fn test_message_without_provenance_infers_origin() {
+    let json_str = r#"{
+        "role": "system",
+        "content": "Summary of 4 messages"
+    }"#;
+
+    let msg: Message = serde_json::from_str(json_str).unwrap();
+    assert!(msg.provenance.is_none());
+    assert_eq!(msg.origin(), Origin::SystemInjector);
+    assert!(!serde_json::to_string(&msg).unwrap().contains("provenance"));
+}
@@ -0,0 +1,207 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+//! Optimistic versioning tests for AsyncSessionStore.
+
+use hypr_claw_runtime::*;
+use serde_json::json;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+fn store(dir: &TempDir) -> AsyncSessionStore {
+    let inner = hypr_claw::infra::session_store::SessionStore::new(dir.path()).unwrap();
+    AsyncSessionStore::new(Arc::new(inner))
+}
+
+fn msg(role: Role, text: &str) -> Message {
+    Message::new(role, json!(text))
+}
+
+fn texts(messages: &[Message]) -> Vec<String> {
+    messages
+        .iter()
+        .map(|m| m.content.as_str().unwrap_or_default().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_unconflicted_saves_bump_version() {
+    let dir = TempDir::new().unwrap();
+    let store = store(&dir);
+
+    let (messages, base) = store.load_versioned("s").await.unwrap();
+    assert!(messages.is_empty());
+    assert_eq!(base.version, 0);
+
+    let next = store
+        .save_versioned("s", &[msg(Role::User, "hi")], base)
+        .await
+        .unwrap();
+    assert_eq!(next, SessionVersion { version: 1, len: 1 });
+
+    let (_, reloaded) = store.load_versioned("s").await.unwrap();
+    assert_eq!(reloaded, next);
+}
+
+#[tokio::test]
+async fn test_interleaved_appends_are_merged() {
+    let dir = TempDir::new().unwrap();
+    let store = store(&dir);
+    store.save("s", &[msg(Role::User, "base")]).await.unwrap();
+
+    let (mut foreground, fg_base) = store.load_versioned("s").await.unwrap();
+    let (mut background, bg_base) = store.load_versioned("s").await.unwrap();
+
+    background.push(msg(Role::Assistant, "from background"));
+    store
+        .save_versioned("s", &background, bg_base)
+        .await
+        .unwrap();
+
+    foreground.push(msg(Role::Assistant, "from foreground"));
+    let merged = store
+        .save_versioned("s", &foreground, fg_base)
+        .await
+        .unwrap();
+    assert_eq!(merged.len, 3);
+
+    assert_eq!(
+        texts(&store.load("s").await.unwrap()),
+        vec!["base", "from background", "from foreground"]
+    );
+}
+
+#[tokio::test]
+async fn test_rewritten_history_is_rejected() {
+    let dir = TempDir::new().unwrap();
+    let store = store(&dir);
+    store
+        .save("s", &[msg(Role::User, "one"), msg(Role::User, "two")])
+        .await
+        .unwrap();
+
+    let (mut stale, base) = store.load_versioned("s").await.unwrap();
+
+    // Another writer compacts the history underneath us.
+    store
+        .save("s", &[msg(Role::System, "summary")])
+        .await
+        .unwrap();
+
+    stale.push(msg(Role::Assistant, "late"));
+    let err = store.save_versioned("s", &stale, base).await.unwrap_err();
+    match err {
+        RuntimeError::SessionConflict {
+            session_key,
+            expected,
+            found,
+        } => {
+            assert_eq!(session_key, "s");
+            assert_eq!(expected, base.version);
+            assert_eq!(found, base.version + 1);
+        }
+        other => panic!("expected SessionConflict, got {other:?}"),
+    }
+    assert_eq!(texts(&store.load("s").await.unwrap()), vec!["summary"]);
+}
+
+struct NoopLocks;
+
+#[async_trait::async_trait]
+impl LockManager for NoopLocks {
+    async fn acquire(&self, _session_key: &str) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    async fn release(&self, _session_key: &str) {}
+}
+
+/// Appends a message to the session on every call, like a run in another
+/// thread writing while this one waits for its tool.
+struct AppendingDispatcher {
+    store: Arc<AsyncSessionStore>,
+}
+
+#[async_trait::async_trait]
+impl ToolDispatcher for AppendingDispatcher {
+    async fn execute(
+        &self,
+        _tool_name: &str,
+        _input: &serde_json::Value,
+        session_key: &str,
+    ) -> Result<serde_json::Value, RuntimeError> {
+        let (mut messages, base) = self.store.load_versioned(session_key).await?;
+        messages.push(msg(Role::Assistant, "from the other run"));
+        self.store
+            .save_versioned(session_key, &messages, base)
+            .await?;
+        Ok(json!({"ok": true}))
+    }
+}
+
+struct EchoTool;
+
+impl ToolRegistry for EchoTool {
+    fn get_active_tools(&self, _agent_id: &str) -> Vec<String> {
+        vec!["echo".to_string()]
+    }
+
+    fn get_tool_schemas(&self, _agent_id: &str) -> Vec<serde_json::Value> {
+        vec![json!({
+            "type": "function",
+            "function": {
+                "name": "echo",
+                "description": "Echo a message",
+                "parameters": {"type": "object", "properties": {}}
+            }
+        })]
+    }
+}
+
+struct Summary;
+
+impl Summarizer for Summary {
+    fn summarize(&self, _messages: &[Message]) -> Result<String, RuntimeError> {
+        Ok("summary".to_string())
+    }
+}
+
+#[tokio::test]
+async fn test_compacting_run_keeps_a_concurrent_append() {
+    use hypr_claw_runtime::chaos::{tool_then_final, Chaos, ChaosConfig, FakeProvider};
+
+    let dir = TempDir::new().unwrap();
+    let store = Arc::new(store(&dir));
+    let history: Vec<Message> = ["one", "two", "three", "four"]
+        .iter()
+        .map(|text| msg(Role::User, &text.repeat(100)))
+        .collect();
+    store.save("s", &history).await.unwrap();
+
+    let provider = FakeProvider::start(Chaos::new(ChaosConfig::default()), tool_then_final())
+        .await
+        .unwrap();
+    let agent = AgentLoop::new(
+        store.clone(),
+        Arc::new(NoopLocks),
+        Arc::new(AppendingDispatcher {
+            store: store.clone(),
+        }),
+        Arc::new(EchoTool),
+        LLMClientType::Standard(LLMClient::new(provider.url().to_string(), 0)),
+        Compactor::new(100, Summary),
+        4,
+    );
+    agent.run("s", "agent", "", "hi").await.unwrap();
+
+    let saved = store.load("s").await.unwrap();
+    let texts = texts(&saved);
+    // The compaction is kept, and so is what the other run appended, ahead
+    // of this run's messages.
+    assert!(texts[0].contains("summary"), "{texts:?}");
+    assert!(!texts.contains(&"one".repeat(100)));
+    let other = texts
+        .iter()
+        .position(|text| text == "from the other run")
+        .unwrap();
+    let own = texts.iter().position(|text| text == "hi").unwrap();
+    assert!(other < own, "{texts:?}");
+}
@@ -0,0 +1,135 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+//! Routing decisions, metrics and audit entries through an installed sink.
+
+use async_trait::async_trait;
+use hypr_claw_runtime::chaos::{tool_then_final, Chaos, ChaosConfig, FakeProvider};
+use hypr_claw_runtime::telemetry::{self, FanOut};
+use hypr_claw_runtime::*;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+struct MemoryStore;
+
+#[async_trait]
+impl SessionStore for MemoryStore {
+    async fn load(&self, _session_key: &str) -> Result<Vec<Message>, RuntimeError> {
+        Ok(vec![])
+    }
+
+    async fn save(&self, _session_key: &str, _messages: &[Message]) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+}
+
+struct NoopLocks;
+
+#[async_trait]
+impl LockManager for NoopLocks {
+    async fn acquire(&self, _session_key: &str) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    async fn release(&self, _session_key: &str) {}
+}
+
+struct EchoTool;
+
+#[async_trait]
+impl ToolDispatcher for EchoTool {
+    async fn execute(
+        &self,
+        _tool_name: &str,
+        input: &serde_json::Value,
+        _session_key: &str,
+    ) -> Result<serde_json::Value, RuntimeError> {
+        Ok(input.clone())
+    }
+}
+
+impl ToolRegistry for EchoTool {
+    fn get_active_tools(&self, _agent_id: &str) -> Vec<String> {
+        vec!["echo".to_string()]
+    }
+
+    fn get_tool_schemas(&self, _agent_id: &str) -> Vec<serde_json::Value> {
+        vec![json!({
+            "type": "function",
+            "function": {
+                "name": "echo",
+                "description": "Echo the input",
+                "parameters": {"type": "object", "properties": {}}
+            }
+        })]
+    }
+}
+
+struct NoSummary;
+
+impl Summarizer for NoSummary {
+    fn summarize(&self, _messages: &[Message]) -> Result<String, RuntimeError> {
+        Ok(String::new())
+    }
+}
+
+#[derive(Default)]
+struct Recorder {
+    lines: Mutex<Vec<String>>,
+}
+
+impl TelemetrySink for Recorder {
+    fn event(&self, event: &TelemetryEvent<'_>) {
+        let line = match event {
+            TelemetryEvent::Decision(decision) => {
+                format!("decision {:?}", decision.chosen)
+            }
+            TelemetryEvent::Compaction(_) => "compaction".to_string(),
+        };
+        self.lines.lock().unwrap().push(line);
+    }
+
+    fn metric(&self, name: &str, _value: f64) {
+        self.lines.lock().unwrap().push(format!("metric {name}"));
+    }
+
+    fn audit(&self, entry: &serde_json::Value) {
+        self.lines
+            .lock()
+            .unwrap()
+            .push(format!("audit {}", entry["tool"]));
+    }
+}
+
+#[tokio::test]
+async fn installed_sinks_see_decisions_metrics_and_audit() {
+    let provider = FakeProvider::start(Chaos::new(ChaosConfig::default()), tool_then_final())
+        .await
+        .unwrap();
+    let tool = Arc::new(EchoTool);
+    let agent = AgentLoop::new(
+        Arc::new(MemoryStore),
+        Arc::new(NoopLocks),
+        tool.clone(),
+        tool,
+        LLMClientType::Standard(LLMClient::new(provider.url().to_string(), 0)),
+        Compactor::new(100_000, NoSummary),
+        4,
+    );
+    let first = Arc::new(Recorder::default());
+    let second = Arc::new(Recorder::default());
+    telemetry::install(Some(Arc::new(FanOut(vec![first.clone(), second.clone()]))));
+
+    assert_eq!(agent.run("s", "agent", "", "hi").await.unwrap(), "done");
+    telemetry::audit(&json!({"tool": "echo"}));
+    telemetry::install(None);
+    telemetry::audit(&json!({"tool": "dropped"}));
+
+    let lines = first.lines.lock().unwrap().clone();
+    assert_eq!(lines, *second.lines.lock().unwrap());
+    let decisions = lines
+        .iter()
+        .filter(|line| line.starts_with("decision"))
+        .collect::<Vec<_>>();
+    assert_eq!(decisions, ["decision Some(\"echo\")", "decision None"]);
+    assert!(lines.contains(&"metric tool_execution_latency".to_string()));
+    assert_eq!(lines.last().unwrap(), "audit \"echo\"");
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+
 #[cfg(test)]
 mod llm_client_tests {
     use hypr_claw_runtime::LLMClient;
@@ -78,4 +80,19 @@ mod llm_client_tests {
         // Client should be created successfully with API key
         drop(client);
     }
+
+    #[test]
+    fn test_base_temperature_follows_model_profile() {
+        let client = LLMClient::new("http://localhost:8080".to_string(), 1);
+        assert_eq!(client.base_temperature(), None);
+
+        client.set_model("z-ai/glm4.7").unwrap();
+        assert_eq!(client.base_temperature(), Some(0.7));
+        client.set_model("other/model").unwrap();
+        assert_eq!(client.base_temperature(), Some(0.2));
+
+        // The override changes what is sent, not the model's default.
+        client.set_temperature_override(Some(0.05));
+        assert_eq!(client.base_temperature(), Some(0.2));
+    }
 }
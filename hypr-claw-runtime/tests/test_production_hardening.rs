@@ -184,6 +184,7 @@ async fn test_malformed_llm_output() {
         schema_version: hypr_claw_runtime::SCHEMA_VERSION,
         tool_name: "".to_string(),
         input: json!({}),
+        rationale: None,
     };
     let serialized = serde_json::to_string(&response).unwrap();
     assert!(serialized.contains("tool_call"));
@@ -303,6 +304,7 @@ fn test_llm_response_serialization_roundtrip() {
         schema_version: hypr_claw_runtime::SCHEMA_VERSION,
         tool_name: "search".to_string(),
         input: json!({"query": "test"}),
+        rationale: None,
     };
     let serialized = serde_json::to_string(&response).unwrap();
     let deserialized: LLMResponse = serde_json::from_str(&serialized).unwrap();
@@ -0,0 +1,26 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+//! Tool choices reported to the decision observer.
+
+use hypr_claw_runtime::*;
+use serde_json::json;
+
+#[test]
+fn decisions_record_choice_and_rationale() {
+    let offered = vec!["fs.read".to_string(), "fs.list".to_string()];
+    let call = LLMResponse::ToolCall {
+        schema_version: SCHEMA_VERSION,
+        tool_name: "fs.delete".to_string(),
+        input: json!({}),
+        rationale: Some("clean up first".to_string()),
+    };
+    let decision = ToolDecision::new("s", 2, &offered, &call);
+    assert_eq!(decision.chosen.as_deref(), Some("fs.delete"));
+    assert_eq!(decision.rationale.as_deref(), Some("clean up first"));
+    assert!(!decision.chose_offered());
+
+    let answer = LLMResponse::Final {
+        schema_version: SCHEMA_VERSION,
+        content: "done".to_string(),
+    };
+    assert!(ToolDecision::new("s", 3, &offered, &answer).chose_offered());
+}
@@ -0,0 +1,104 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+//! Per-tool latency tracking, schema hints and token usage.
+
+use hypr_claw_runtime::metrics::{
+    annotate_schemas_with_latency, record_token_usage, record_tool_call, seed_tool_latencies,
+    token_usage, tool_latency_snapshot, TokenUsage, ToolLatency,
+};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+fn schema(name: &str, description: &str) -> serde_json::Value {
+    json!({
+        "type": "function",
+        "function": {"name": name, "description": description, "parameters": {}}
+    })
+}
+
+#[test]
+fn test_record_tool_call_tracks_average_and_max() {
+    record_tool_call("latency_test.average", 100.0);
+    record_tool_call("latency_test.average", 300.0);
+
+    let latency = tool_latency_snapshot()["latency_test.average"];
+    assert_eq!(latency.calls, 2);
+    assert_eq!(latency.average_ms(), 200.0);
+    assert_eq!(latency.max_ms, 300.0);
+}
+
+#[test]
+fn test_seeded_history_does_not_override_live_numbers() {
+    record_tool_call("latency_test.live", 50.0);
+    let history = BTreeMap::from([
+        (
+            "latency_test.live".to_string(),
+            ToolLatency {
+                calls: 9,
+                total_ms: 9000.0,
+                max_ms: 2000.0,
+            },
+        ),
+        (
+            "latency_test.seeded".to_string(),
+            ToolLatency {
+                calls: 2,
+                total_ms: 4000.0,
+                max_ms: 2500.0,
+            },
+        ),
+    ]);
+    seed_tool_latencies(&history);
+
+    let snapshot = tool_latency_snapshot();
+    assert_eq!(snapshot["latency_test.live"].calls, 1);
+    assert_eq!(snapshot["latency_test.seeded"].average_ms(), 2000.0);
+}
+
+#[test]
+fn test_schemas_get_latency_hints_for_measured_tools_only() {
+    let latencies = BTreeMap::from([
+        (
+            "desktop.ocr_screen".to_string(),
+            ToolLatency {
+                calls: 4,
+                total_ms: 9800.0,
+                max_ms: 3100.0,
+            },
+        ),
+        (
+            "desktop.active_window".to_string(),
+            ToolLatency {
+                calls: 1,
+                total_ms: 42.4,
+                max_ms: 42.4,
+            },
+        ),
+    ]);
+    let mut schemas = vec![
+        schema("desktop.ocr_screen", "OCR the screen"),
+        schema("desktop.active_window", "Focused window"),
+        schema("fs.read", "Read a file"),
+    ];
+    annotate_schemas_with_latency(&mut schemas, &latencies);
+
+    assert_eq!(
+        schemas[0]["function"]["description"],
+        "OCR the screen [avg latency 2.5s over 4 calls]"
+    );
+    assert_eq!(
+        schemas[1]["function"]["description"],
+        "Focused window [avg latency 42ms over 1 call]"
+    );
+    assert_eq!(schemas[2]["function"]["description"], "Read a file");
+}
+
+#[test]
+fn test_token_usage_accumulates() {
+    let before = token_usage();
+    record_token_usage(120, 30);
+    record_token_usage(80, 10);
+    let used = token_usage().since(before);
+    // Other tests may record usage concurrently, so only a lower bound holds.
+    assert!(used.prompt >= 200 && used.completion >= 40, "{used:?}");
+    assert_eq!(before.since(token_usage()), TokenUsage::default());
+}
@@ -0,0 +1,182 @@
+//! Registering a custom tool and dispatching calls to it.
+//!
+//! `text.word_count` sits in a [`ToolRegistryImpl`] next to the built-in
+//! echo tool, and every call goes through [`ToolDispatcherImpl`]: the
+//! permission engine decides by tier, the tool runs with a timeout and panic
+//! isolation, and each call lands in the audit log. An agent loop gets these
+//! tools by implementing the runtime's `ToolRegistry` with
+//! [`ToolRegistryImpl::schemas`] and its `ToolDispatcher` with
+//! [`ToolDispatcherImpl::dispatch`], as the bundled app does.
+//!
+//! ```text
+//! cargo run -p hypr_claw_tools --example custom_tool
+//! ```
+
+use async_trait::async_trait;
+use hypr_claw_tools::tools::EchoTool;
+use hypr_claw_tools::{
+    AuditLogger, ExecutionContext, PermissionDecision, PermissionEngine, PermissionRequest,
+    PermissionTier, Tool, ToolDispatcherImpl, ToolError, ToolRegistryImpl, ToolResult,
+};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+/// Counts the words and lines of a text.
+struct WordCountTool;
+
+#[async_trait]
+impl Tool for WordCountTool {
+    fn name(&self) -> &'static str {
+        "text.word_count"
+    }
+
+    fn description(&self) -> &'static str {
+        "Count the words and lines of a text"
+    }
+
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "text": {"type": "string"}
+            },
+            "required": ["text"]
+        })
+    }
+
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+
+    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let text = input["text"]
+            .as_str()
+            .ok_or_else(|| ToolError::ValidationError("text must be a string".to_string()))?;
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({
+                "words": text.split_whitespace().count(),
+                "lines": text.lines().count(),
+            })),
+            error: None,
+        })
+    }
+}
+
+/// Lets read-only tools run and asks for approval for anything else.
+struct ReadOnlyPolicy;
+
+#[async_trait]
+impl PermissionEngine for ReadOnlyPolicy {
+    async fn check(&self, request: PermissionRequest) -> PermissionDecision {
+        if request.permission_tier == PermissionTier::Read {
+            PermissionDecision::Allow
+        } else {
+            PermissionDecision::RequireApproval(format!("{} changes state", request.tool_name))
+        }
+    }
+}
+
+/// Keeps audit entries in memory; a real one would append to a file.
+#[derive(Default)]
+struct MemoryAudit {
+    entries: Mutex<Vec<Value>>,
+}
+
+impl MemoryAudit {
+    fn entries(&self) -> Vec<Value> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+#[async_trait]
+impl AuditLogger for MemoryAudit {
+    async fn log(&self, entry: Value) {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(entry);
+    }
+}
+
+fn build_dispatcher(audit: Arc<MemoryAudit>) -> ToolDispatcherImpl {
+    let mut registry = ToolRegistryImpl::new();
+    registry
+        .register(Arc::new(WordCountTool))
+        .register(Arc::new(EchoTool));
+    ToolDispatcherImpl::new(Arc::new(registry), Arc::new(ReadOnlyPolicy), audit, 5_000)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), ToolError> {
+    let audit = Arc::new(MemoryAudit::default());
+    let dispatcher = build_dispatcher(audit.clone());
+
+    let counted = dispatcher
+        .dispatch(
+            "example".to_string(),
+            "text.word_count".to_string(),
+            json!({"text": "the quick brown fox\njumps over the lazy dog"}),
+        )
+        .await?;
+    println!("text.word_count -> {}", json!(counted));
+
+    // Echo keeps the default write tier, so this policy holds it for approval.
+    let echoed = dispatcher
+        .dispatch(
+            "example".to_string(),
+            "echo".to_string(),
+            json!({"message": "hi"}),
+        )
+        .await?;
+    println!("echo -> {}", json!(echoed));
+
+    // Audit entries are written in the background.
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    for entry in audit.entries() {
+        println!("audit: {} {}", entry["tool"], entry["approval_decision"]);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn custom_tool_runs_through_the_dispatcher() -> Result<(), ToolError> {
+    let audit = Arc::new(MemoryAudit::default());
+    let dispatcher = build_dispatcher(audit.clone());
+
+    let counted = dispatcher
+        .dispatch(
+            "test".to_string(),
+            "text.word_count".to_string(),
+            json!({"text": "one two\nthree"}),
+        )
+        .await?;
+    assert!(counted.success);
+    assert_eq!(counted.output, Some(json!({"words": 3, "lines": 2})));
+
+    let held = dispatcher
+        .dispatch(
+            "test".to_string(),
+            "echo".to_string(),
+            json!({"message": "hi"}),
+        )
+        .await?;
+    assert!(!held.success);
+    assert_eq!(held.error.as_deref(), Some("Approval required"));
+
+    let missing = dispatcher
+        .dispatch("test".to_string(), "text.nope".to_string(), json!({}))
+        .await;
+    assert!(matches!(missing, Err(ToolError::ValidationError(_))));
+
+    for _ in 0..100 {
+        if audit.entries().len() == 2 {
+            return Ok(());
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("both dispatched calls should be audited");
+}
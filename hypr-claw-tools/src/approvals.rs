@@ -0,0 +1,940 @@
+//! One approval for a plan with several destructive steps.
+//!
+//! Instead of stopping the run at every delete, overwrite or kill, the model
+//! lists those steps once through `plan.request_approval`. The user accepts or
+//! skips each item in a single prompt, and [`PlanApprovalGate`] then lets the
+//! accepted calls through without asking again and denies the skipped ones.
+
+use crate::error::ToolError;
+use crate::execution_context::ExecutionContext;
+use crate::sandbox::command_analysis::CommandAnalysis;
+use crate::tools::base::DEFAULT_PATH_INPUTS;
+use crate::tools::{Tool, ToolResult};
+use crate::traits::{PermissionDecision, PermissionEngine, PermissionRequest, PermissionTier};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Component, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::time::{timeout, Duration};
+
+/// Tools whose calls can destroy data or state, with the verb shown to the user.
+pub const DESTRUCTIVE_TOOLS: &[(&str, &str)] = &[
+    ("fs.delete", "delete"),
+    ("fs.write", "overwrite"),
+    ("fs.move", "move"),
+    ("proc.kill", "kill"),
+    ("hypr.window.close", "close window"),
+    ("db.execute", "modify database"),
+    ("csv.write", "overwrite"),
+    ("schedule.system_remove", "remove schedule"),
+    ("system.package_install", "install"),
+    ("system.shutdown", "shut down"),
+    ("system.reboot", "reboot"),
+];
+
+/// Input fields that name what a call acts on, most specific first.
+const TARGET_FIELDS: &[&str] = &["path", "from", "pid", "window_id", "name", "id", "packages"];
+
+/// How long the user has to answer the consolidated prompt.
+const DECISION_TIMEOUT: Duration = Duration::from_secs(120);
+
+pub fn destructive_verb(tool: &str) -> Option<&'static str> {
+    DESTRUCTIVE_TOOLS
+        .iter()
+        .find(|(name, _)| *name == tool)
+        .map(|(_, verb)| *verb)
+}
+
+/// What a call acts on, e.g. the path of `fs.delete` or the pid of
+/// `proc.kill`. Calls are matched to plan items by tool and target, so a
+/// retry with reformatted input still counts as the approved step.
+pub fn action_target(input: &Value) -> String {
+    for field in TARGET_FIELDS {
+        match input.get(field) {
+            Some(Value::String(s)) => return s.trim().to_string(),
+            Some(Value::Null) | None => {}
+            Some(other) => return other.to_string(),
+        }
+    }
+    input.to_string()
+}
+
+/// One step of a plan, as shown to the user.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PlannedAction {
+    pub tool: String,
+    pub target: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl PlannedAction {
+    pub fn describe(&self) -> String {
+        let verb = destructive_verb(&self.tool).unwrap_or("run");
+        match &self.reason {
+            Some(reason) => format!("{verb} {} ({}) - {reason}", self.target, self.tool),
+            None => format!("{verb} {} ({})", self.target, self.tool),
+        }
+    }
+}
+
+/// Per-item answer to a selection prompt: `all`/`y`, `none`/`n`/empty, or
+/// item numbers and ranges to accept such as `1,3` or `2-4`. `None` when the
+/// answer cannot be read, so callers can treat it as skipping everything.
+pub fn parse_selection(answer: &str, count: usize) -> Option<Vec<bool>> {
+    let answer = answer.trim().to_ascii_lowercase();
+    match answer.as_str() {
+        "a" | "all" | "y" | "yes" => return Some(vec![true; count]),
+        "" | "n" | "no" | "none" => return Some(vec![false; count]),
+        _ => {}
+    }
+    let mut accepted = vec![false; count];
+    for part in answer.split([',', ' ']).filter(|p| !p.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.parse::<usize>().ok()?, end.parse::<usize>().ok()?),
+            None => {
+                let index = part.parse::<usize>().ok()?;
+                (index, index)
+            }
+        };
+        if start == 0 || end < start || end > count {
+            return None;
+        }
+        for slot in &mut accepted[start - 1..end] {
+            *slot = true;
+        }
+    }
+    Some(accepted)
+}
+
+/// A call that may run without asking while a reviewed skill runs: `tool`
+/// acting on `target` (see [`action_target`]). A trailing `*` in `target`
+/// matches any target starting with the rest.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PermissionGrant {
+    pub tool: String,
+    pub target: String,
+    /// Pattern for the call's other file arguments, e.g. where `fs.move`
+    /// puts the file. A call with such arguments is covered only when each
+    /// of them matches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+}
+
+impl PermissionGrant {
+    /// Grant for exactly the call `tool` made with `input`.
+    pub fn for_call(tool: &str, input: &Value) -> Self {
+        Self {
+            tool: tool.to_string(),
+            target: action_target(input),
+            to: other_paths(input).into_iter().next(),
+        }
+    }
+
+    pub fn covers(&self, tool: &str, input: &Value) -> bool {
+        self.tool == tool
+            && pattern_matches(&self.target, &action_target(input))
+            && other_paths(input).iter().all(|path| {
+                self.to
+                    .as_deref()
+                    .is_some_and(|to| pattern_matches(to, path))
+            })
+    }
+}
+
+fn pattern_matches(pattern: &str, value: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        // `..` could climb out of the granted prefix.
+        Some(prefix) => value.starts_with(prefix) && !value.split('/').any(|part| part == ".."),
+        None => pattern == value,
+    }
+}
+
+/// File arguments of `input` besides its target, such as a move's `to`.
+fn other_paths(input: &Value) -> Vec<String> {
+    let target = action_target(input);
+    let mut skipped_target = false;
+    let mut paths = Vec::new();
+    for field in DEFAULT_PATH_INPUTS {
+        let Some(path) = input.get(field).and_then(Value::as_str) else {
+            continue;
+        };
+        let path = path.trim();
+        if !skipped_target && path == target {
+            skipped_target = true;
+        } else if !paths.iter().any(|p| p == path) {
+            paths.push(path.to_string());
+        }
+    }
+    paths
+}
+
+#[derive(Debug, Clone)]
+struct Decision {
+    action: PlannedAction,
+    approved: bool,
+}
+
+/// Answers given to plan approvals, per session.
+///
+/// An accepted item covers one call; a skipped item keeps denying matching
+/// calls until the session's plan is replaced or cleared. Grants cover any
+/// number of calls until they are revoked.
+#[derive(Default)]
+pub struct ApprovalBook {
+    sessions: Mutex<HashMap<String, Vec<Decision>>>,
+    grants: Mutex<HashMap<String, Vec<PermissionGrant>>>,
+}
+
+impl ApprovalBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the session's plan with `actions` and their answers.
+    pub fn record(&self, session_key: &str, actions: &[PlannedAction], approved: &[bool]) {
+        let decisions = actions
+            .iter()
+            .zip(approved)
+            .map(|(action, approved)| Decision {
+                action: action.clone(),
+                approved: *approved,
+            })
+            .collect();
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.insert(session_key.to_string(), decisions);
+        }
+    }
+
+    /// The answer for a call, if the session's plan or grants cover it.
+    /// Accepting consumes a plan item but not a grant.
+    pub fn take(&self, session_key: &str, tool: &str, input: &Value) -> Option<bool> {
+        let target = action_target(input);
+        if let Some(decisions) = self.sessions.lock().ok()?.get_mut(session_key) {
+            let matches = |d: &Decision| d.action.tool == tool && d.action.target == target;
+            if let Some(index) = decisions.iter().position(|d| d.approved && matches(d)) {
+                decisions.remove(index);
+                return Some(true);
+            }
+            if decisions.iter().any(matches) {
+                return Some(false);
+            }
+        }
+        let grants = self.grants.lock().ok()?;
+        grants
+            .get(session_key)?
+            .iter()
+            .any(|grant| grant.covers(tool, input))
+            .then_some(true)
+    }
+
+    /// Lets the session's calls covered by `grants` through until
+    /// [`Self::revoke`].
+    pub fn grant(&self, session_key: &str, grants: &[PermissionGrant]) {
+        if let Ok(mut sessions) = self.grants.lock() {
+            sessions.insert(session_key.to_string(), grants.to_vec());
+        }
+    }
+
+    pub fn revoke(&self, session_key: &str) {
+        if let Ok(mut sessions) = self.grants.lock() {
+            sessions.remove(session_key);
+        }
+    }
+
+    pub fn clear(&self, session_key: &str) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.remove(session_key);
+        }
+    }
+}
+
+/// Asks the user about a whole plan at once; returns one answer per item.
+pub type PlanDecider = Arc<dyn Fn(&[PlannedAction]) -> Vec<bool> + Send + Sync>;
+
+pub struct PlanApprovalTool {
+    book: Arc<ApprovalBook>,
+    decide: PlanDecider,
+}
+
+impl PlanApprovalTool {
+    pub fn new(book: Arc<ApprovalBook>, decide: PlanDecider) -> Self {
+        Self { book, decide }
+    }
+}
+
+#[async_trait]
+impl Tool for PlanApprovalTool {
+    fn name(&self) -> &'static str {
+        "plan.request_approval"
+    }
+    fn description(&self) -> &'static str {
+        "Before running a plan with two or more destructive steps (delete, overwrite, move, kill, close window, shutdown), list them all here once. The user accepts or skips each item; run accepted steps with the same tool and target, and do not run skipped ones"
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "actions": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "tool": {"type": "string"},
+                            "input": {"type": "object", "description": "Input the step will be called with"},
+                            "reason": {"type": "string"}
+                        },
+                        "required": ["tool", "input"]
+                    }
+                }
+            },
+            "required": ["actions"],
+            "additionalProperties": false
+        })
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let items = input
+            .get("actions")
+            .and_then(|v| v.as_array())
+            .filter(|items| !items.is_empty())
+            .ok_or_else(|| {
+                ToolError::ValidationError("'actions' must list at least one step".into())
+            })?;
+        let mut actions = Vec::with_capacity(items.len());
+        for item in items {
+            let tool = item
+                .get("tool")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| ToolError::ValidationError("every action needs a 'tool'".into()))?;
+            actions.push(PlannedAction {
+                tool: tool.to_string(),
+                target: action_target(item.get("input").unwrap_or(&Value::Null)),
+                reason: item
+                    .get("reason")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
+            });
+        }
+
+        let decide = self.decide.clone();
+        let shown = actions.clone();
+        let approved = match timeout(
+            DECISION_TIMEOUT,
+            tokio::task::spawn_blocking(move || decide(&shown)),
+        )
+        .await
+        {
+            Ok(Ok(answers)) if answers.len() == actions.len() => answers,
+            _ => vec![false; actions.len()],
+        };
+        self.book.record(&ctx.session_key, &actions, &approved);
+
+        let listed = |want: bool| -> Vec<Value> {
+            actions
+                .iter()
+                .zip(&approved)
+                .enumerate()
+                .filter(|(_, (_, approved))| **approved == want)
+                .map(|(index, (action, _))| {
+                    json!({"item": index + 1, "tool": action.tool, "target": action.target})
+                })
+                .collect()
+        };
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({
+                "approved": listed(true),
+                "skipped": listed(false),
+                "note": "Run approved steps with the same tool and target; skipped steps will be denied."
+            })),
+            error: None,
+        })
+    }
+}
+
+/// Permission engine that applies command hazards, plan answers and the
+/// approval threshold before deferring to `inner`.
+///
+/// Commands whose hazards reach the block score are refused, even if a plan
+/// accepted them. Accepted calls are still checked by `inner` for hard
+/// blocks, but at most at the Execute tier so they are not prompted for a
+/// second time. Other calls at or above the threshold, or with hazards worth
+/// asking about, are raised to SystemCritical so `inner` asks for them;
+/// Read-tier calls are otherwise never raised. Sessions under
+/// [`SessionSupervision::require_plan`] have their other calls above the Read
+/// tier denied until a plan accepts them, and sessions confined with
+/// [`PlanApprovalGate::confine`] have calls on paths outside their roots
+/// denied outright.
+pub struct PlanApprovalGate {
+    inner: Arc<dyn PermissionEngine>,
+    book: Arc<ApprovalBook>,
+    prompt_from: RwLock<PermissionTier>,
+    sessions: RwLock<HashMap<String, SessionSupervision>>,
+    roots: RwLock<HashMap<String, Vec<PathBuf>>>,
+}
+
+/// Approval rules for one session, in place of the gate's threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionSupervision {
+    /// Lowest tier that asks the user before running.
+    pub prompt_from: PermissionTier,
+    /// Calls above the Read tier run only once a plan accepted them.
+    pub require_plan: bool,
+}
+
+impl PlanApprovalGate {
+    pub fn new(inner: Arc<dyn PermissionEngine>, book: Arc<ApprovalBook>) -> Self {
+        Self {
+            inner,
+            book,
+            prompt_from: RwLock::new(PermissionTier::SystemCritical),
+            sessions: RwLock::new(HashMap::new()),
+            roots: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Lowest tier that asks the user before running.
+    pub fn prompt_from(&self) -> PermissionTier {
+        self.prompt_from
+            .read()
+            .map(|tier| *tier)
+            .unwrap_or(PermissionTier::SystemCritical)
+    }
+
+    pub fn set_prompt_from(&self, tier: PermissionTier) {
+        if let Ok(mut current) = self.prompt_from.write() {
+            *current = tier;
+        }
+    }
+
+    /// Applies `supervision` to the session's calls, or goes back to the
+    /// gate's threshold with `None`.
+    pub fn supervise(&self, session_key: &str, supervision: Option<SessionSupervision>) {
+        if let Ok(mut sessions) = self.sessions.write() {
+            match supervision {
+                Some(supervision) => sessions.insert(session_key.to_string(), supervision),
+                None => sessions.remove(session_key),
+            };
+        }
+    }
+
+    /// Confines the session's file and directory arguments to `roots`, or
+    /// lifts the confinement with `None`.
+    pub fn confine(&self, session_key: &str, roots: Option<Vec<PathBuf>>) {
+        if let Ok(mut confined) = self.roots.write() {
+            match roots {
+                Some(roots) => {
+                    let roots = roots
+                        .into_iter()
+                        .map(|root| std::fs::canonicalize(&root).unwrap_or(root))
+                        .collect();
+                    confined.insert(session_key.to_string(), roots)
+                }
+                None => confined.remove(session_key),
+            };
+        }
+    }
+
+    /// The first of `paths` outside the session's roots, if the session is
+    /// confined.
+    fn outside_roots(&self, session_key: &str, paths: &[String]) -> Option<PathBuf> {
+        let roots = self.roots.read().ok()?.get(session_key)?.clone();
+        paths
+            .iter()
+            .map(|path| resolve_path(path))
+            .find(|path| !roots.iter().any(|root| path.starts_with(root)))
+    }
+
+    fn supervision(&self, session_key: &str) -> SessionSupervision {
+        self.sessions
+            .read()
+            .ok()
+            .and_then(|sessions| sessions.get(session_key).copied())
+            .unwrap_or_else(|| SessionSupervision {
+                prompt_from: self.prompt_from(),
+                require_plan: false,
+            })
+    }
+}
+
+#[async_trait]
+impl PermissionEngine for PlanApprovalGate {
+    async fn check(&self, mut request: PermissionRequest) -> PermissionDecision {
+        if let Some(risk) = request.command_risk.as_ref().filter(|r| r.is_blocked()) {
+            return PermissionDecision::Deny(format!(
+                "{} refused: {}",
+                request.tool_name,
+                risk.describe()
+            ));
+        }
+        if let Some(path) = self.outside_roots(&request.session_key, &request.paths) {
+            return PermissionDecision::Deny(format!(
+                "{} {} is outside the roots this session is confined to",
+                request.tool_name,
+                path.display()
+            ));
+        }
+        let risky = request
+            .command_risk
+            .as_ref()
+            .is_some_and(CommandAnalysis::needs_approval);
+        let target = action_target(&request.input);
+        match self
+            .book
+            .take(&request.session_key, &request.tool_name, &request.input)
+        {
+            Some(true) => {
+                if request.permission_tier == PermissionTier::SystemCritical {
+                    request.permission_tier = PermissionTier::Execute;
+                }
+                self.inner.check(request).await
+            }
+            Some(false) => PermissionDecision::Deny(format!(
+                "the user skipped {} {} in the approved plan",
+                request.tool_name, target
+            )),
+            None => {
+                let supervision = self.supervision(&request.session_key);
+                if supervision.require_plan && request.permission_tier > PermissionTier::Read {
+                    return PermissionDecision::Deny(format!(
+                        "{} {} needs plan approval first: list it with plan.request_approval",
+                        request.tool_name, target
+                    ));
+                }
+                if risky
+                    || (request.permission_tier > PermissionTier::Read
+                        && request.permission_tier >= supervision.prompt_from)
+                {
+                    request.permission_tier = PermissionTier::SystemCritical;
+                }
+                self.inner.check(request).await
+            }
+        }
+    }
+}
+
+/// `raw` as an absolute path without `.` or `..`, resolving symlinks when
+/// the path exists. Relative paths are taken from the working directory,
+/// as the file tools take them.
+fn resolve_path(raw: &str) -> PathBuf {
+    let path = crate::sandbox::expand_home(raw.trim());
+    let path = match std::env::current_dir() {
+        Ok(cwd) if path.is_relative() => cwd.join(path),
+        _ => path,
+    };
+    if let Ok(canonical) = std::fs::canonicalize(&path) {
+        return canonical;
+    }
+    let mut clean = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                clean.pop();
+            }
+            Component::CurDir => {}
+            other => clean.push(other),
+        }
+    }
+    clean
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_accepts_lists_ranges_and_keywords() {
+        assert_eq!(parse_selection("all", 2), Some(vec![true, true]));
+        assert_eq!(parse_selection("", 2), Some(vec![false, false]));
+        assert_eq!(
+            parse_selection("1, 3-4", 5),
+            Some(vec![true, false, true, true, false])
+        );
+        assert_eq!(parse_selection("0", 2), None);
+        assert_eq!(parse_selection("3", 2), None);
+        assert_eq!(parse_selection("maybe", 2), None);
+    }
+
+    #[test]
+    fn accepted_items_are_used_once_and_skipped_items_stay_denied() {
+        let book = ApprovalBook::new();
+        let delete = PlannedAction {
+            tool: "fs.delete".to_string(),
+            target: "/tmp/a".to_string(),
+            reason: None,
+        };
+        let kill = PlannedAction {
+            tool: "proc.kill".to_string(),
+            target: "42".to_string(),
+            reason: None,
+        };
+        book.record("s", &[delete, kill], &[true, false]);
+
+        assert_eq!(
+            book.take("s", "fs.delete", &json!({"path": "/tmp/a"})),
+            Some(true)
+        );
+        assert_eq!(
+            book.take("s", "fs.delete", &json!({"path": "/tmp/a"})),
+            None
+        );
+        assert_eq!(
+            book.take("s", "proc.kill", &json!({"pid": 42})),
+            Some(false)
+        );
+        assert_eq!(
+            book.take("s", "proc.kill", &json!({"pid": 42})),
+            Some(false)
+        );
+        assert_eq!(book.take("other", "proc.kill", &json!({"pid": 42})), None);
+    }
+
+    #[test]
+    fn grants_cover_matching_calls_until_revoked() {
+        let book = ApprovalBook::new();
+        let grants = [
+            PermissionGrant {
+                tool: "fs.move".to_string(),
+                target: "/home/u/Downloads/*".to_string(),
+                to: Some("/home/u/Documents/*".to_string()),
+            },
+            PermissionGrant {
+                tool: "fs.delete".to_string(),
+                target: "/tmp/cache".to_string(),
+                to: None,
+            },
+        ];
+        book.grant("s", &grants);
+        let moving = |from: &str, to: &str| json!({"from": from, "to": to});
+
+        let granted = moving("/home/u/Downloads/a.pdf", "/home/u/Documents/a.pdf");
+        assert_eq!(book.take("s", "fs.move", &granted), Some(true));
+        assert_eq!(book.take("s", "fs.move", &granted), Some(true));
+        assert_eq!(
+            book.take(
+                "s",
+                "fs.move",
+                &moving("/home/u/Documents/a.pdf", "/home/u/Documents/b.pdf")
+            ),
+            None
+        );
+        assert_eq!(
+            book.take(
+                "s",
+                "fs.move",
+                &moving("/home/u/Downloads/../.ssh/id", "/home/u/Documents/id")
+            ),
+            None
+        );
+        let delete = |path: &str| json!({"path": path});
+        assert_eq!(
+            book.take("s", "fs.delete", &delete("/tmp/cache")),
+            Some(true)
+        );
+        assert_eq!(book.take("s", "fs.delete", &delete("/tmp/cache/x")), None);
+        assert_eq!(book.take("s", "fs.write", &delete("/tmp/cache")), None);
+        assert_eq!(book.take("other", "fs.delete", &delete("/tmp/cache")), None);
+
+        // A skipped plan item still wins over a grant.
+        let skipped = PlannedAction {
+            tool: "fs.delete".to_string(),
+            target: "/tmp/cache".to_string(),
+            reason: None,
+        };
+        book.record("s", &[skipped], &[false]);
+        assert_eq!(
+            book.take("s", "fs.delete", &delete("/tmp/cache")),
+            Some(false)
+        );
+
+        book.revoke("s");
+        assert_eq!(book.take("s", "fs.move", &granted), None);
+    }
+
+    #[test]
+    fn grants_cover_every_path_of_the_call() {
+        let book = ApprovalBook::new();
+        let copy = json!({"from": "/home/u/a.txt", "to": "/home/u/backup/a.txt"});
+        let grant = PermissionGrant::for_call("fs.copy", &copy);
+        assert_eq!(grant.to.as_deref(), Some("/home/u/backup/a.txt"));
+        book.grant("s", &[grant]);
+
+        assert_eq!(book.take("s", "fs.copy", &copy), Some(true));
+        let elsewhere = json!({"from": "/home/u/a.txt", "to": "/home/u/.ssh/a.txt"});
+        assert_eq!(book.take("s", "fs.copy", &elsewhere), None);
+
+        // A grant without a destination covers no call that has one.
+        book.grant(
+            "s",
+            &[PermissionGrant {
+                tool: "fs.move".to_string(),
+                target: "/home/u/a.txt".to_string(),
+                to: None,
+            }],
+        );
+        assert_eq!(book.take("s", "fs.move", &copy), None);
+    }
+
+    struct TierEcho;
+
+    #[async_trait]
+    impl PermissionEngine for TierEcho {
+        async fn check(&self, request: PermissionRequest) -> PermissionDecision {
+            PermissionDecision::RequireApproval(format!("{:?}", request.permission_tier))
+        }
+    }
+
+    fn exec_request(command: &str) -> PermissionRequest {
+        let input = json!({"command": command});
+        PermissionRequest {
+            session_key: "s".to_string(),
+            tool_name: "hypr.exec".to_string(),
+            command_risk: crate::sandbox::command_analysis::analyze_call("hypr.exec", &input),
+            input,
+            permission_tier: PermissionTier::Execute,
+            timestamp: String::new(),
+            paths: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn command_hazards_block_or_raise_the_tier() {
+        let gate = PlanApprovalGate::new(Arc::new(TierEcho), Arc::new(ApprovalBook::new()));
+        let tier = |decision| match decision {
+            PermissionDecision::RequireApproval(tier) => tier,
+            other => format!("{other:?}"),
+        };
+        assert_eq!(tier(gate.check(exec_request("kitty")).await), "Execute");
+        assert_eq!(
+            tier(gate.check(exec_request("rm -rf build")).await),
+            "SystemCritical"
+        );
+        match gate.check(exec_request("rm -rf ~")).await {
+            PermissionDecision::Deny(reason) => {
+                assert!(reason.starts_with("hypr.exec refused: recursive delete of a broad path"))
+            }
+            other => panic!("expected a denial, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn session_supervision_overrides_the_threshold() {
+        let book = Arc::new(ApprovalBook::new());
+        let gate = PlanApprovalGate::new(Arc::new(TierEcho), book.clone());
+        let tier = |decision| match decision {
+            PermissionDecision::RequireApproval(tier) => tier,
+            other => format!("{other:?}"),
+        };
+        gate.supervise(
+            "s",
+            Some(SessionSupervision {
+                prompt_from: PermissionTier::Write,
+                require_plan: false,
+            }),
+        );
+        assert_eq!(
+            tier(gate.check(exec_request("kitty")).await),
+            "SystemCritical"
+        );
+
+        gate.supervise(
+            "s",
+            Some(SessionSupervision {
+                prompt_from: PermissionTier::SystemCritical,
+                require_plan: true,
+            }),
+        );
+        match gate.check(exec_request("kitty")).await {
+            PermissionDecision::Deny(reason) => assert!(reason.contains("plan.request_approval")),
+            other => panic!("expected a denial, got {other:?}"),
+        }
+        let planned = PlannedAction {
+            tool: "hypr.exec".to_string(),
+            target: action_target(&json!({"command": "kitty"})),
+            reason: None,
+        };
+        book.record("s", &[planned], &[true]);
+        assert_eq!(tier(gate.check(exec_request("kitty")).await), "Execute");
+
+        gate.supervise("s", None);
+        assert_eq!(tier(gate.check(exec_request("kitty")).await), "Execute");
+    }
+
+    #[tokio::test]
+    async fn confined_sessions_keep_paths_inside_their_roots() {
+        let root = std::env::temp_dir().join(format!("hypr-claw-confine-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        let gate = PlanApprovalGate::new(Arc::new(TierEcho), Arc::new(ApprovalBook::new()));
+        let write = |input: Value| PermissionRequest {
+            session_key: "s".to_string(),
+            tool_name: "fs.move".to_string(),
+            command_risk: None,
+            paths: ["from", "to"]
+                .iter()
+                .filter_map(|field| input.get(field)?.as_str().map(str::to_string))
+                .collect(),
+            input,
+            permission_tier: PermissionTier::Write,
+            timestamp: String::new(),
+        };
+        let inside = root.join("src/new.rs").display().to_string();
+        let escape = root.join("src/../../etc/passwd").display().to_string();
+        assert!(!matches!(
+            gate.check(write(json!({"from": escape, "to": inside})))
+                .await,
+            PermissionDecision::Deny(_)
+        ));
+
+        gate.confine("s", Some(vec![root.clone()]));
+        assert!(!matches!(
+            gate.check(write(json!({"from": inside, "to": inside})))
+                .await,
+            PermissionDecision::Deny(_)
+        ));
+        match gate
+            .check(write(json!({"from": inside, "to": escape})))
+            .await
+        {
+            PermissionDecision::Deny(reason) => {
+                assert!(reason.contains("is outside the roots"), "{reason}")
+            }
+            other => panic!("expected a denial, got {other:?}"),
+        }
+
+        gate.confine("s", None);
+        assert!(!matches!(
+            gate.check(write(json!({"to": escape}))).await,
+            PermissionDecision::Deny(_)
+        ));
+    }
+
+    struct NoAudit;
+
+    #[async_trait]
+    impl crate::traits::AuditLogger for NoAudit {
+        async fn log(&self, _entry: Value) {}
+    }
+
+    /// Dispatches `input` to `tool` in a session confined to a scratch root
+    /// and returns the denial, if the gate refused the call.
+    async fn confined_denial(tool: Arc<dyn Tool>, input: Value) -> Option<String> {
+        let root = std::env::temp_dir().join(format!("hypr-claw-declared-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let gate = PlanApprovalGate::new(Arc::new(TierEcho), Arc::new(ApprovalBook::new()));
+        gate.confine("s", Some(vec![root]));
+        let mut registry = crate::ToolRegistryImpl::new();
+        let name = tool.name();
+        registry.register(tool);
+        let dispatcher = crate::ToolDispatcherImpl::new(
+            Arc::new(registry),
+            Arc::new(gate),
+            Arc::new(NoAudit),
+            5000,
+        );
+        match dispatcher.dispatch("s".into(), name.into(), input).await {
+            Err(ToolError::PermissionDenied(reason)) => Some(reason),
+            _ => None,
+        }
+    }
+
+    fn assert_confined(denial: Option<String>) {
+        let reason = denial.expect("the call escaped the session's roots");
+        assert!(reason.contains("is outside the roots"), "{reason}");
+    }
+
+    #[tokio::test]
+    async fn confinement_covers_doc_templates() {
+        use crate::os_tools::DocGenerateTool;
+        let tool = Arc::new(DocGenerateTool::new(std::env::temp_dir()));
+        assert_confined(
+            confined_denial(
+                tool,
+                json!({"template_path": "/etc/passwd", "data": {}, "format": "markdown"}),
+            )
+            .await,
+        );
+    }
+
+    #[tokio::test]
+    async fn confinement_covers_csv_sources() {
+        let root = std::env::temp_dir().join(format!("hypr-claw-declared-{}", std::process::id()));
+        let input = json!({
+            "path": root.join("out.csv").display().to_string(),
+            "source": "/etc/passwd"
+        });
+        assert_confined(confined_denial(Arc::new(crate::os_tools::CsvWriteTool), input).await);
+    }
+
+    #[tokio::test]
+    async fn confinement_covers_dotenv_files() {
+        use crate::os_tools::{EnvGetTool, EnvListTool};
+        use crate::sandbox::env_policy::EnvPolicy;
+        let tool = Arc::new(EnvGetTool::new(EnvPolicy::default()));
+        let input = json!({"name": "HOME", "dotenv_path": "/etc/environment"});
+        assert_confined(confined_denial(tool, input).await);
+        let tool = Arc::new(EnvListTool::new(EnvPolicy::default()));
+        let input = json!({"dotenv_path": "/etc/environment"});
+        assert_confined(confined_denial(tool, input).await);
+    }
+
+    #[tokio::test]
+    async fn confinement_covers_schedule_working_dirs() {
+        let input = json!({
+            "name": "nightly",
+            "command": ["/usr/bin/true"],
+            "working_dir": "/etc",
+            "cron": "0 2 * * *"
+        });
+        let tool = Arc::new(crate::os_tools::ScheduleSystemCreateTool);
+        assert_confined(confined_denial(tool, input).await);
+    }
+
+    #[tokio::test]
+    async fn confinement_covers_wallpaper_images() {
+        let input = json!({"image_path": "/etc/passwd"});
+        let tool = Arc::new(crate::os_tools::WallpaperSetTool);
+        assert_confined(confined_denial(tool, input).await);
+    }
+
+    #[tokio::test]
+    async fn confinement_covers_batch_rename_lists() {
+        let root = std::env::temp_dir().join(format!("hypr-claw-declared-{}", std::process::id()));
+        let input = json!({
+            "template": "{n}",
+            "paths": [root.join("a.txt").display().to_string(), "/etc/passwd"]
+        });
+        let tool = Arc::new(crate::rename::FsBatchRenameTool::new(Arc::new(
+            crate::staging::StagingArea::new(),
+        )));
+        assert_confined(confined_denial(tool, input).await);
+    }
+
+    #[tokio::test]
+    async fn confinement_lets_declared_paths_inside_the_roots_through() {
+        let root = std::env::temp_dir().join(format!("hypr-claw-declared-{}", std::process::id()));
+        let input = json!({
+            "template": "{n}",
+            "paths": [root.join("a.txt").display().to_string()]
+        });
+        let tool = Arc::new(crate::rename::FsBatchRenameTool::new(Arc::new(
+            crate::staging::StagingArea::new(),
+        )));
+        assert_eq!(confined_denial(tool, input).await, None);
+    }
+
+    #[test]
+    fn targets_come_from_the_acted_on_field() {
+        assert_eq!(
+            action_target(&json!({"path": " /tmp/x ", "content": "y"})),
+            "/tmp/x"
+        );
+        assert_eq!(action_target(&json!({"pid": 42})), "42");
+        assert_eq!(action_target(&json!({"other": 1})), "{\"other\":1}");
+    }
+}
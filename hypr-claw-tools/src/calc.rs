@@ -0,0 +1,625 @@
+//! Deterministic arithmetic, unit conversion and date arithmetic for
+//! `calc.evaluate`.
+//!
+//! Three kinds of expression are understood:
+//! - arithmetic: `(1920 - 24) / 3`, `2^10 % 7`, `round(sqrt(2) * 100)`, with
+//!   `+ - * / % ^`, parentheses, the constants `pi` and `e`, and the
+//!   functions `sqrt abs round floor ceil ln log10 sin cos tan min max`;
+//! - conversions: `3.5 km to mi`, `(1024 * 3) MiB in GB`, `100 F to C`, where
+//!   the amount is itself arithmetic;
+//! - dates: `2026-10-18 + 45 days`, `today - 2 weeks + 1 month`, and
+//!   `2026-12-25 - 2026-10-18` for the days between two dates.
+
+use chrono::{Datelike, Months, NaiveDate};
+use regex::Regex;
+use serde_json::{json, Value};
+use std::sync::OnceLock;
+
+/// A result: the value itself (a number or a `YYYY-MM-DD` date) and a line
+/// for the user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Answer {
+    pub value: Value,
+    pub text: String,
+}
+
+/// Evaluates `expression`; `today` stands in for `today` in dates.
+pub fn evaluate(expression: &str, today: NaiveDate) -> Result<Answer, String> {
+    let expression = expression.trim();
+    if expression.is_empty() {
+        return Err("empty expression".to_string());
+    }
+    if date_literal().is_match(&expression.to_lowercase()) {
+        return evaluate_date(expression, today);
+    }
+    if let Some((amount, target)) = split_conversion(expression) {
+        return convert(amount, target);
+    }
+    let number = arithmetic(expression)?;
+    Ok(Answer {
+        value: rounded(number),
+        text: format!("{expression} = {}", format_number(number)),
+    })
+}
+
+/// Value of a purely arithmetic expression.
+pub fn arithmetic(expression: &str) -> Result<f64, String> {
+    let tokens = tokenize(expression)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let value = parser.expr()?;
+    match parser.tokens.get(parser.pos) {
+        None => finite(value),
+        Some(token) => Err(format!("unexpected {token:?}")),
+    }
+}
+
+/// `n` with at most ten decimals and no trailing zeros.
+pub fn format_number(n: f64) -> String {
+    let text = format!("{:.10}", n);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" => "0".to_string(),
+        text => text.to_string(),
+    }
+}
+
+/// `n` rounded to ten decimals, so `12 in in cm` is 30.48 rather than
+/// 30.479999999999997.
+fn rounded(n: f64) -> Value {
+    if n.abs() < 1e15 {
+        json!((n * 1e10).round() / 1e10)
+    } else {
+        json!(n)
+    }
+}
+
+fn finite(n: f64) -> Result<f64, String> {
+    if n.is_finite() {
+        Ok(n)
+    } else {
+        Err("result is not a finite number".to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '0'..='9' | '.' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                // Exponent, as in `1.5e3`.
+                if i + 1 < chars.len()
+                    && matches!(chars[i], 'e' | 'E')
+                    && (chars[i + 1].is_ascii_digit()
+                        || (matches!(chars[i + 1], '+' | '-')
+                            && chars.get(i + 2).is_some_and(char::is_ascii_digit)))
+                {
+                    i += 2;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+                let literal: String = chars[start..i].iter().collect();
+                let number = literal
+                    .parse()
+                    .map_err(|_| format!("invalid number '{literal}'"))?;
+                tokens.push(Token::Number(number));
+            }
+            c if c.is_alphabetic() => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            }
+            '+' | '-' | '*' | '/' | '%' | '^' => {
+                tokens.push(Token::Op(c));
+                i += 1;
+            }
+            '×' => {
+                tokens.push(Token::Op('*'));
+                i += 1;
+            }
+            '÷' => {
+                tokens.push(Token::Op('/'));
+                i += 1;
+            }
+            '(' => {
+                tokens.push(Token::Open);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            other => return Err(format!("unexpected character '{other}'")),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over `expr := term (+|- term)*`,
+/// `term := unary (*|/|% unary)*`, `unary := - unary | power`,
+/// `power := primary (^ unary)?`, so `-2^2` is -4 and `^` is
+/// right-associative.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.tokens.get(self.pos) == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Result<f64, String> {
+        let mut value = self.term()?;
+        loop {
+            if self.eat(&Token::Op('+')) {
+                value += self.term()?;
+            } else if self.eat(&Token::Op('-')) {
+                value -= self.term()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat(&Token::Op('*')) {
+                value *= self.unary()?;
+            } else if self.eat(&Token::Op('/')) {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return Err("division by zero".to_string());
+                }
+                value /= divisor;
+            } else if self.eat(&Token::Op('%')) {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return Err("division by zero".to_string());
+                }
+                value %= divisor;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<f64, String> {
+        if self.eat(&Token::Op('-')) {
+            return Ok(-self.unary()?);
+        }
+        if self.eat(&Token::Op('+')) {
+            return self.unary();
+        }
+        let base = self.primary()?;
+        if self.eat(&Token::Op('^')) {
+            return Ok(base.powf(self.unary()?));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<f64, String> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(n),
+            Some(Token::Open) => {
+                let value = self.expr()?;
+                if !self.eat(&Token::Close) {
+                    return Err("missing ')'".to_string());
+                }
+                Ok(value)
+            }
+            Some(Token::Ident(name)) => {
+                if self.eat(&Token::Open) {
+                    let mut args = vec![self.expr()?];
+                    while self.eat(&Token::Comma) {
+                        args.push(self.expr()?);
+                    }
+                    if !self.eat(&Token::Close) {
+                        return Err(format!("missing ')' after {name}("));
+                    }
+                    return function(&name, &args);
+                }
+                match name.to_lowercase().as_str() {
+                    "pi" => Ok(std::f64::consts::PI),
+                    "e" => Ok(std::f64::consts::E),
+                    _ => Err(format!("unknown name '{name}'")),
+                }
+            }
+            Some(token) => Err(format!("unexpected {token:?}")),
+            None => Err("expression ends too early".to_string()),
+        }
+    }
+}
+
+fn function(name: &str, args: &[f64]) -> Result<f64, String> {
+    let one = |f: fn(f64) -> f64| match args {
+        [x] => Ok(f(*x)),
+        _ => Err(format!("{name} takes one argument")),
+    };
+    match name.to_lowercase().as_str() {
+        "sqrt" => one(f64::sqrt),
+        "abs" => one(f64::abs),
+        "round" => one(f64::round),
+        "floor" => one(f64::floor),
+        "ceil" => one(f64::ceil),
+        "ln" => one(f64::ln),
+        "log10" | "log" => one(f64::log10),
+        "sin" => one(f64::sin),
+        "cos" => one(f64::cos),
+        "tan" => one(f64::tan),
+        "min" => args
+            .iter()
+            .copied()
+            .reduce(f64::min)
+            .ok_or_else(|| "min needs an argument".to_string()),
+        "max" => args
+            .iter()
+            .copied()
+            .reduce(f64::max)
+            .ok_or_else(|| "max needs an argument".to_string()),
+        _ => Err(format!("unknown function '{name}'")),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dimension {
+    Length,
+    Mass,
+    Time,
+    Data,
+    Volume,
+    Speed,
+    Temperature,
+}
+
+/// A unit: `base = value * factor + offset`, in metres, kilograms, seconds,
+/// bytes, litres, metres per second or kelvin.
+struct Unit {
+    names: &'static [&'static str],
+    dimension: Dimension,
+    factor: f64,
+    offset: f64,
+}
+
+const fn unit(names: &'static [&'static str], dimension: Dimension, factor: f64) -> Unit {
+    Unit {
+        names,
+        dimension,
+        factor,
+        offset: 0.0,
+    }
+}
+
+const UNITS: &[Unit] = &[
+    unit(
+        &["m", "meter", "meters", "metre", "metres"],
+        Dimension::Length,
+        1.0,
+    ),
+    unit(
+        &["km", "kilometer", "kilometers", "kilometre", "kilometres"],
+        Dimension::Length,
+        1e3,
+    ),
+    unit(
+        &["cm", "centimeter", "centimeters"],
+        Dimension::Length,
+        1e-2,
+    ),
+    unit(
+        &["mm", "millimeter", "millimeters"],
+        Dimension::Length,
+        1e-3,
+    ),
+    unit(&["mi", "mile", "miles"], Dimension::Length, 1609.344),
+    unit(&["yd", "yard", "yards"], Dimension::Length, 0.9144),
+    unit(&["ft", "foot", "feet"], Dimension::Length, 0.3048),
+    unit(&["in", "inch", "inches"], Dimension::Length, 0.0254),
+    unit(&["nmi"], Dimension::Length, 1852.0),
+    unit(&["kg", "kilogram", "kilograms"], Dimension::Mass, 1.0),
+    unit(&["g", "gram", "grams"], Dimension::Mass, 1e-3),
+    unit(&["mg", "milligram", "milligrams"], Dimension::Mass, 1e-6),
+    unit(&["t", "tonne", "tonnes"], Dimension::Mass, 1e3),
+    unit(
+        &["lb", "lbs", "pound", "pounds"],
+        Dimension::Mass,
+        0.45359237,
+    ),
+    unit(&["oz", "ounce", "ounces"], Dimension::Mass, 0.028349523125),
+    unit(&["st", "stone"], Dimension::Mass, 6.35029318),
+    unit(
+        &["ms", "millisecond", "milliseconds"],
+        Dimension::Time,
+        1e-3,
+    ),
+    unit(
+        &["s", "sec", "secs", "second", "seconds"],
+        Dimension::Time,
+        1.0,
+    ),
+    unit(&["min", "mins", "minute", "minutes"], Dimension::Time, 60.0),
+    unit(
+        &["h", "hr", "hrs", "hour", "hours"],
+        Dimension::Time,
+        3600.0,
+    ),
+    unit(&["d", "day", "days"], Dimension::Time, 86400.0),
+    unit(&["wk", "week", "weeks"], Dimension::Time, 604800.0),
+    unit(&["bit", "bits"], Dimension::Data, 0.125),
+    unit(&["b", "byte", "bytes"], Dimension::Data, 1.0),
+    unit(&["kb"], Dimension::Data, 1e3),
+    unit(&["mb"], Dimension::Data, 1e6),
+    unit(&["gb"], Dimension::Data, 1e9),
+    unit(&["tb"], Dimension::Data, 1e12),
+    unit(&["kib"], Dimension::Data, 1024.0),
+    unit(&["mib"], Dimension::Data, 1048576.0),
+    unit(&["gib"], Dimension::Data, 1073741824.0),
+    unit(&["tib"], Dimension::Data, 1099511627776.0),
+    unit(
+        &["l", "liter", "liters", "litre", "litres"],
+        Dimension::Volume,
+        1.0,
+    ),
+    unit(
+        &["ml", "milliliter", "milliliters"],
+        Dimension::Volume,
+        1e-3,
+    ),
+    unit(
+        &["gal", "gallon", "gallons"],
+        Dimension::Volume,
+        3.785411784,
+    ),
+    unit(&["qt", "quart", "quarts"], Dimension::Volume, 0.946352946),
+    unit(&["cup", "cups"], Dimension::Volume, 0.2365882365),
+    unit(&["floz"], Dimension::Volume, 0.0295735295625),
+    unit(&["m/s", "mps"], Dimension::Speed, 1.0),
+    unit(&["km/h", "kmh", "kph"], Dimension::Speed, 1.0 / 3.6),
+    unit(&["mph"], Dimension::Speed, 0.44704),
+    unit(&["kn", "knot", "knots"], Dimension::Speed, 1852.0 / 3600.0),
+    unit(&["k", "kelvin"], Dimension::Temperature, 1.0),
+    Unit {
+        names: &["c", "°c", "celsius"],
+        dimension: Dimension::Temperature,
+        factor: 1.0,
+        offset: 273.15,
+    },
+    Unit {
+        names: &["f", "°f", "fahrenheit"],
+        dimension: Dimension::Temperature,
+        factor: 5.0 / 9.0,
+        offset: 459.67 * 5.0 / 9.0,
+    },
+];
+
+fn find_unit(name: &str) -> Option<&'static Unit> {
+    let name = name.trim().to_lowercase();
+    UNITS
+        .iter()
+        .find(|unit| unit.names.contains(&name.as_str()))
+}
+
+/// `(amount with unit, target unit)` of `<amount> <unit> to|in <unit>`.
+fn split_conversion(expression: &str) -> Option<(&str, &str)> {
+    [" to ", " in ", " as "].iter().find_map(|separator| {
+        let (amount, target) = expression.rsplit_once(separator)?;
+        find_unit(target)?;
+        Some((amount, target))
+    })
+}
+
+fn convert(amount: &str, target: &str) -> Result<Answer, String> {
+    let amount = amount.trim();
+    let split = amount
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphabetic() || matches!(c, '°' | '/'))
+        .last()
+        .map(|(i, _)| i)
+        .ok_or_else(|| format!("'{amount}' has no unit"))?;
+    let (number, from_name) = amount.split_at(split);
+    let from = find_unit(from_name).ok_or_else(|| format!("unknown unit '{from_name}'"))?;
+    let to = find_unit(target).ok_or_else(|| format!("unknown unit '{target}'"))?;
+    if from.dimension != to.dimension {
+        return Err(format!("cannot convert {from_name} to {}", target.trim()));
+    }
+    let number = if number.trim().is_empty() {
+        1.0
+    } else {
+        arithmetic(number)?
+    };
+    let base = number * from.factor + from.offset;
+    let converted = finite((base - to.offset) / to.factor)?;
+    Ok(Answer {
+        value: rounded(converted),
+        text: format!(
+            "{} {} = {} {}",
+            format_number(number),
+            from_name.trim(),
+            format_number(converted),
+            target.trim()
+        ),
+    })
+}
+
+fn date_literal() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\d{4}-\d{2}-\d{2}|\btoday\b").expect("valid regex"))
+}
+
+fn parse_date(text: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    match text.trim() {
+        "today" => Ok(today),
+        date => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| format!("'{date}' is not a valid YYYY-MM-DD date")),
+    }
+}
+
+fn evaluate_date(expression: &str, today: NaiveDate) -> Result<Answer, String> {
+    static BETWEEN: OnceLock<Regex> = OnceLock::new();
+    static START: OnceLock<Regex> = OnceLock::new();
+    static STEP: OnceLock<Regex> = OnceLock::new();
+    let date = r"(\d{4}-\d{2}-\d{2}|today)";
+    let lower = expression.to_lowercase();
+
+    let between = BETWEEN
+        .get_or_init(|| Regex::new(&format!(r"^\s*{date}\s*-\s*{date}\s*$")).expect("valid regex"));
+    if let Some(captures) = between.captures(&lower) {
+        let end = parse_date(&captures[1], today)?;
+        let start = parse_date(&captures[2], today)?;
+        let days = (end - start).num_days();
+        return Ok(Answer {
+            value: json!(days),
+            text: format!("{end} - {start} = {days} days"),
+        });
+    }
+
+    let start = START.get_or_init(|| {
+        Regex::new(&format!(r"^\s*{date}((?:\s*[+-]\s*\d+\s*[a-z]+)*)\s*$")).expect("valid regex")
+    });
+    let captures = start.captures(&lower).ok_or_else(|| {
+        "date expressions look like `2026-10-18 + 45 days` or `2026-12-25 - 2026-10-18`".to_string()
+    })?;
+    let mut result = parse_date(&captures[1], today)?;
+    let step = STEP.get_or_init(|| Regex::new(r"([+-])\s*(\d+)\s*([a-z]+)").expect("valid regex"));
+    for step in step.captures_iter(&captures[2]) {
+        let sign = if &step[1] == "-" { -1 } else { 1 };
+        let n: i64 = step[2]
+            .parse()
+            .map_err(|_| format!("'{}' is too large", &step[2]))?;
+        result =
+            shift(result, sign * n, &step[3]).ok_or_else(|| "date out of range".to_string())?;
+    }
+    Ok(Answer {
+        value: json!(result.to_string()),
+        text: format!(
+            "{} = {result} ({})",
+            expression.trim(),
+            weekday_name(result)
+        ),
+    })
+}
+
+fn shift(date: NaiveDate, n: i64, unit: &str) -> Option<NaiveDate> {
+    let months = |n: i64| -> Option<NaiveDate> {
+        let months = Months::new(u32::try_from(n.unsigned_abs()).ok()?);
+        if n < 0 {
+            date.checked_sub_months(months)
+        } else {
+            date.checked_add_months(months)
+        }
+    };
+    match unit {
+        "d" | "day" | "days" => date.checked_add_signed(chrono::Duration::try_days(n)?),
+        "w" | "wk" | "week" | "weeks" => date.checked_add_signed(chrono::Duration::try_weeks(n)?),
+        "month" | "months" => months(n),
+        "y" | "year" | "years" => months(n.checked_mul(12)?),
+        _ => None,
+    }
+}
+
+fn weekday_name(date: NaiveDate) -> &'static str {
+    match date.weekday() {
+        chrono::Weekday::Mon => "Monday",
+        chrono::Weekday::Tue => "Tuesday",
+        chrono::Weekday::Wed => "Wednesday",
+        chrono::Weekday::Thu => "Thursday",
+        chrono::Weekday::Fri => "Friday",
+        chrono::Weekday::Sat => "Saturday",
+        chrono::Weekday::Sun => "Sunday",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 18).unwrap()
+    }
+
+    fn value(expression: &str) -> Value {
+        evaluate(expression, today()).unwrap().value
+    }
+
+    #[test]
+    fn arithmetic_follows_precedence() {
+        assert_eq!(value("(1920 - 24) / 3"), json!(632.0));
+        assert_eq!(value("2 + 3 * 4 ^ 2"), json!(50.0));
+        assert_eq!(value("-2^2"), json!(-4.0));
+        assert_eq!(value("2^3^2"), json!(512.0));
+        assert_eq!(value("17 % 5 + max(1, 4, 2)"), json!(6.0));
+        assert_eq!(value("round(sqrt(2) * 100)"), json!(141.0));
+        assert_eq!(value("1.5e3 × 2"), json!(3000.0));
+        assert_eq!(format_number(0.1 + 0.2), "0.3");
+        assert_eq!(format_number(-1e-12), "0");
+        assert!(evaluate("1 / (2 - 2)", today()).is_err());
+        assert!(evaluate("2 +", today()).is_err());
+        assert!(evaluate("foo(1)", today()).is_err());
+    }
+
+    #[test]
+    fn conversions_stay_within_a_dimension() {
+        assert_eq!(
+            evaluate("3.5 km to mi", today()).unwrap().text,
+            "3.5 km = 2.1747991728 mi"
+        );
+        assert_eq!(value("(1024 * 3) MiB in GB"), json!(3.221225472));
+        assert_eq!(value("100 F to C"), json!(37.7777777778));
+        assert_eq!(value("12 in in cm"), json!(30.48));
+        assert_eq!(value("90 km/h to mph"), json!(55.9234073014));
+        assert_eq!(value("2h to min"), json!(120.0));
+        let err = evaluate("3 kg to m", today()).unwrap_err();
+        assert_eq!(err, "cannot convert kg to m");
+    }
+
+    #[test]
+    fn dates_shift_and_subtract() {
+        assert_eq!(
+            evaluate("2026-10-18 + 45 days", today()).unwrap().text,
+            "2026-10-18 + 45 days = 2026-12-02 (Wednesday)"
+        );
+        assert_eq!(value("today - 2 weeks + 1 month"), json!("2026-11-04"));
+        assert_eq!(value("2024-01-31 + 1 month"), json!("2024-02-29"));
+        assert_eq!(value("2026-12-25 - 2026-10-18"), json!(68));
+        assert_eq!(value("2026-10-18 - 2026-12-25"), json!(-68));
+        assert!(evaluate("2026-02-30 + 1 day", today()).is_err());
+        assert!(evaluate("2026-10-18 + 3 fortnights", today()).is_err());
+    }
+}
@@ -0,0 +1,226 @@
+//! Local contact vault used to resolve recipients for communication tools.
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Contact {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub telegram: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+}
+
+impl Contact {
+    /// Sets a field by name (`email`, `telegram`, `phone`). An empty value clears it.
+    pub fn set_field(&mut self, field: &str, value: &str) -> Result<(), String> {
+        let value = Some(value.trim().to_string()).filter(|v| !v.is_empty());
+        match field {
+            "email" => {
+                if let Some(email) = &value {
+                    if !looks_like_email(email) {
+                        return Err(format!("'{email}' is not a valid email address"));
+                    }
+                }
+                self.email = value;
+            }
+            "telegram" => self.telegram = value.map(|v| v.trim_start_matches('@').to_string()),
+            "phone" => self.phone = value,
+            other => return Err(format!("Unknown contact field '{other}'")),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContactMatch {
+    Found(Contact),
+    Ambiguous(Vec<String>),
+    NotFound,
+}
+
+/// JSON-file backed contact list keyed by case-insensitive name.
+pub struct ContactStore {
+    path: PathBuf,
+    contacts: Mutex<Vec<Contact>>,
+}
+
+impl ContactStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let contacts = if path.exists() {
+            let raw = std::fs::read_to_string(&path)?;
+            if raw.trim().is_empty() {
+                Vec::new()
+            } else {
+                serde_json::from_str(&raw)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?
+            }
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            path,
+            contacts: Mutex::new(contacts),
+        })
+    }
+
+    pub fn list(&self) -> Vec<Contact> {
+        let mut contacts = self.lock().clone();
+        contacts.sort_by_key(|c| c.name.to_lowercase());
+        contacts
+    }
+
+    /// Inserts or replaces the contact with the same name.
+    pub fn upsert(&self, contact: Contact) -> io::Result<()> {
+        let mut contacts = self.lock();
+        match contacts
+            .iter_mut()
+            .find(|c| c.name.eq_ignore_ascii_case(&contact.name))
+        {
+            Some(existing) => *existing = contact,
+            None => contacts.push(contact),
+        }
+        self.persist(&contacts)
+    }
+
+    pub fn get(&self, name: &str) -> Option<Contact> {
+        self.lock()
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name.trim()))
+            .cloned()
+    }
+
+    pub fn remove(&self, name: &str) -> io::Result<bool> {
+        let mut contacts = self.lock();
+        let before = contacts.len();
+        contacts.retain(|c| !c.name.eq_ignore_ascii_case(name.trim()));
+        if contacts.len() == before {
+            return Ok(false);
+        }
+        self.persist(&contacts)?;
+        Ok(true)
+    }
+
+    /// Resolves a recipient by exact name first, then by unique first-name or
+    /// prefix match. Never guesses between several candidates.
+    pub fn resolve(&self, query: &str) -> ContactMatch {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return ContactMatch::NotFound;
+        }
+        let contacts = self.lock();
+        if let Some(contact) = contacts.iter().find(|c| c.name.to_lowercase() == query) {
+            return ContactMatch::Found(contact.clone());
+        }
+        let candidates: Vec<&Contact> = contacts
+            .iter()
+            .filter(|c| {
+                let name = c.name.to_lowercase();
+                name.starts_with(&query) || name.split_whitespace().any(|part| part == query)
+            })
+            .collect();
+        match candidates.as_slice() {
+            [] => ContactMatch::NotFound,
+            [single] => ContactMatch::Found((*single).clone()),
+            many => ContactMatch::Ambiguous(many.iter().map(|c| c.name.clone()).collect()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Contact>> {
+        self.contacts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn persist(&self, contacts: &[Contact]) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let payload = serde_json::to_string_pretty(contacts)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, payload)?;
+        std::fs::rename(tmp, &self.path)
+    }
+}
+
+pub fn looks_like_email(value: &str) -> bool {
+    let Some((local, domain)) = value.split_once('@') else {
+        return false;
+    };
+    !local.is_empty() && domain.contains('.') && !value.contains(char::is_whitespace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contact(name: &str, email: &str) -> Contact {
+        Contact {
+            name: name.to_string(),
+            email: Some(email.to_string()),
+            ..Contact::default()
+        }
+    }
+
+    #[test]
+    fn resolve_prefers_exact_then_unique_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ContactStore::open(dir.path().join("contacts.json")).unwrap();
+        store
+            .upsert(contact("Alice Smith", "alice@example.com"))
+            .unwrap();
+        store
+            .upsert(contact("Alan Turing", "alan@example.com"))
+            .unwrap();
+        store.upsert(contact("Al", "al@example.com")).unwrap();
+
+        assert!(matches!(store.resolve("al"), ContactMatch::Found(c) if c.name == "Al"));
+        assert!(
+            matches!(store.resolve("alice"), ContactMatch::Found(c) if c.name == "Alice Smith")
+        );
+        assert!(matches!(store.resolve("smith"), ContactMatch::Found(_)));
+        assert!(matches!(store.resolve("ala"), ContactMatch::Found(c) if c.name == "Alan Turing"));
+        assert_eq!(store.resolve("bob"), ContactMatch::NotFound);
+
+        store
+            .upsert(contact("Alice Jones", "aj@example.com"))
+            .unwrap();
+        assert!(
+            matches!(store.resolve("alice"), ContactMatch::Ambiguous(names) if names.len() == 2)
+        );
+    }
+
+    #[test]
+    fn store_persists_upserts_and_removals() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("contacts.json");
+        let store = ContactStore::open(&path).unwrap();
+        store.upsert(contact("Bob", "bob@example.com")).unwrap();
+        store.upsert(contact("bob", "robert@example.com")).unwrap();
+
+        let reopened = ContactStore::open(&path).unwrap();
+        assert_eq!(reopened.list().len(), 1);
+        assert_eq!(
+            reopened.get("BOB").unwrap().email.as_deref(),
+            Some("robert@example.com")
+        );
+        assert!(reopened.remove("Bob").unwrap());
+        assert!(!reopened.remove("Bob").unwrap());
+    }
+
+    #[test]
+    fn set_field_validates_and_normalizes() {
+        let mut c = Contact::default();
+        assert!(c.set_field("email", "not-an-email").is_err());
+        c.set_field("telegram", "@alice").unwrap();
+        assert_eq!(c.telegram.as_deref(), Some("alice"));
+        assert!(c.set_field("fax", "123").is_err());
+    }
+}
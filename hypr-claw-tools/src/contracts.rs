@@ -0,0 +1,330 @@
+//! Contract tests for tools: canned inputs and the observable behavior that
+//! prompts and skills rely on.
+//!
+//! Each fixture file under `contracts/` names a tool and lists cases. A case
+//! runs the tool against a mocked OS layer: a scratch directory stands in for
+//! the filesystem (`$ROOT` in a fixture is its path) and fake executables,
+//! the only ones on `PATH`, stand in for the commands the backends run. The
+//! case then checks the fields of the output or the error, the command lines
+//! the tool ran and the files it left behind. Run them with
+//! `cargo test --features tool-contracts`.
+//!
+//! ```yaml
+//! tool: hypr.workspace.switch
+//! cases:
+//!   - name: switches with hyprctl
+//!     commands:
+//!       hyprctl: { stdout: "ok" }
+//!     input: { workspace_id: 3 }
+//!     output: { workspace: 3 }
+//!     invoked: ["hyprctl dispatch workspace 3"]
+//! ```
+
+use crate::execution_context::ExecutionContext;
+use crate::tools::Tool;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// How long a case may run before it counts as hung.
+const CASE_TIMEOUT_MS: u64 = 10_000;
+
+/// Commands the mocked OS keeps from the host, so backend probes still work.
+const HOST_COMMANDS: &[&str] = &["which"];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Fixture {
+    pub tool: String,
+    pub cases: Vec<Case>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Case {
+    pub name: String,
+    /// Files under `$ROOT` before the call, by relative path.
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+    /// Fake executables by name.
+    #[serde(default)]
+    pub commands: BTreeMap<String, FakeCommand>,
+    /// Environment variables set for the call, e.g. `TZ`.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    pub input: Value,
+    /// Fields the output must have; other fields are not checked.
+    #[serde(default)]
+    pub output: Option<Value>,
+    /// Text the error must contain. Without it the call must succeed.
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Every fake command run, in order, as `name arg…`.
+    #[serde(default)]
+    pub invoked: Option<Vec<String>>,
+    /// Contents of files under `$ROOT` after the call; `null` for a file
+    /// that must not exist.
+    #[serde(default)]
+    pub files_after: BTreeMap<String, Option<String>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FakeCommand {
+    #[serde(default)]
+    pub stdout: String,
+    #[serde(default)]
+    pub stderr: String,
+    #[serde(default)]
+    pub status: i32,
+}
+
+/// Fixture files in `dir`, by file name.
+pub fn load(dir: &Path) -> Result<Vec<(PathBuf, Fixture)>, String> {
+    let mut paths = std::fs::read_dir(dir)
+        .map_err(|e| format!("cannot read {}: {e}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "yaml"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let raw = std::fs::read_to_string(&path)
+                .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+            let fixture = serde_yaml::from_str(&raw)
+                .map_err(|e| format!("invalid fixture {}: {e}", path.display()))?;
+            Ok((path, fixture))
+        })
+        .collect()
+}
+
+/// Runs every case of `fixture` against `tool` and returns the failures.
+/// Cases change the process's `PATH` and environment, so fixtures must not
+/// run concurrently with each other or with other tests that spawn commands.
+pub async fn run(fixture: &Fixture, tool: &dyn Tool) -> Vec<String> {
+    let mut failures = Vec::new();
+    for case in &fixture.cases {
+        if let Err(e) = run_case(tool, case).await {
+            failures.push(format!("{} / {}: {e}", fixture.tool, case.name));
+        }
+    }
+    failures
+}
+
+async fn run_case(tool: &dyn Tool, case: &Case) -> Result<(), String> {
+    let os = MockOs::install(case).map_err(|e| format!("cannot set up the mocked OS: {e}"))?;
+    let ctx = ExecutionContext::new("contract".to_string(), CASE_TIMEOUT_MS);
+    let call = tool.execute(ctx, os.substitute(&case.input));
+    let result = tokio::time::timeout(std::time::Duration::from_millis(CASE_TIMEOUT_MS), call)
+        .await
+        .map_err(|_| format!("no result within {CASE_TIMEOUT_MS}ms"))?;
+    let outcome = match result {
+        Ok(result) if result.success => Ok(result.output.unwrap_or(Value::Null)),
+        Ok(result) => Err(result.error.unwrap_or_default()),
+        Err(e) => Err(e.to_string()),
+    };
+    match (outcome, &case.error) {
+        (Ok(output), None) => {
+            if let Some(expected) = &case.output {
+                if let Some(mismatch) = mismatch(&os.substitute(expected), &output, "output") {
+                    return Err(format!("{mismatch} in {output}"));
+                }
+            }
+        }
+        (Ok(output), Some(expected)) => {
+            return Err(format!("expected an error with {expected:?}, got {output}"));
+        }
+        (Err(error), None) => return Err(format!("unexpected error: {error}")),
+        (Err(error), Some(expected)) => {
+            let expected = os.substitute_str(expected);
+            if !error.contains(&expected) {
+                return Err(format!("error {error:?} does not contain {expected:?}"));
+            }
+        }
+    }
+    if let Some(expected) = &case.invoked {
+        let expected = expected
+            .iter()
+            .map(|line| os.substitute_str(line))
+            .collect::<Vec<_>>();
+        let invoked = os.invoked();
+        if invoked != expected {
+            return Err(format!("ran {invoked:?}, expected {expected:?}"));
+        }
+    }
+    for (path, expected) in &case.files_after {
+        let actual = std::fs::read_to_string(os.root.join(path)).ok();
+        if &actual != expected {
+            return Err(format!("{path} holds {actual:?}, expected {expected:?}"));
+        }
+    }
+    Ok(())
+}
+
+/// Where `actual` differs from `expected`: objects need the expected keys
+/// only, arrays and scalars must match exactly.
+pub fn mismatch(expected: &Value, actual: &Value, at: &str) -> Option<String> {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            expected.iter().find_map(|(key, value)| {
+                let at = format!("{at}.{key}");
+                match actual.get(key) {
+                    Some(actual) => mismatch(value, actual, &at),
+                    None => Some(format!("{at} is missing")),
+                }
+            })
+        }
+        (Value::Array(expected), Value::Array(actual)) if expected.len() == actual.len() => {
+            expected
+                .iter()
+                .zip(actual)
+                .enumerate()
+                .find_map(|(i, (expected, actual))| {
+                    mismatch(expected, actual, &format!("{at}[{i}]"))
+                })
+        }
+        (Value::Number(a), Value::Number(b)) if a.as_f64() == b.as_f64() => None,
+        _ if expected == actual => None,
+        _ => Some(format!("{at} is {actual}, expected {expected}")),
+    }
+}
+
+/// The scratch directory and fake commands of one case. Dropping it puts
+/// `PATH` and the environment back.
+struct MockOs {
+    base: PathBuf,
+    root: PathBuf,
+    calls: PathBuf,
+    saved_env: Vec<(String, Option<OsString>)>,
+}
+
+impl MockOs {
+    fn install(case: &Case) -> io::Result<Self> {
+        static CASES: AtomicUsize = AtomicUsize::new(0);
+        let base = std::env::temp_dir().join(format!(
+            "hypr-claw-contract-{}-{}",
+            std::process::id(),
+            CASES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&base);
+        let root = base.join("root");
+        let bin = base.join("bin");
+        std::fs::create_dir_all(&root)?;
+        std::fs::create_dir_all(&bin)?;
+        let mut os = Self {
+            calls: base.join("calls"),
+            base,
+            root,
+            saved_env: Vec::new(),
+        };
+
+        for (path, content) in &case.files {
+            let path = os.root.join(path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, content)?;
+        }
+        for name in HOST_COMMANDS {
+            let host = ["/usr/bin", "/bin"]
+                .iter()
+                .map(|dir| Path::new(dir).join(name))
+                .find(|path| path.exists());
+            if let Some(host) = host {
+                std::os::unix::fs::symlink(host, bin.join(name))?;
+            }
+        }
+        for (name, fake) in &case.commands {
+            let script = format!(
+                "#!/bin/sh\nprintf '%s\\n' \"${{0##*/}} $*\" >> {}\nprintf '%s' {}\nprintf '%s' {} >&2\nexit {}\n",
+                shell_quote(&os.calls.display().to_string()),
+                shell_quote(&fake.stdout),
+                shell_quote(&fake.stderr),
+                fake.status
+            );
+            let path = bin.join(name);
+            std::fs::write(&path, script)?;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+        }
+
+        os.set_env("PATH", &bin.display().to_string());
+        for (key, value) in &case.env {
+            os.set_env(key, &os.substitute_str(value));
+        }
+        Ok(os)
+    }
+
+    fn set_env(&mut self, key: &str, value: &str) {
+        self.saved_env
+            .push((key.to_string(), std::env::var_os(key)));
+        std::env::set_var(key, value);
+    }
+
+    fn substitute_str(&self, raw: &str) -> String {
+        raw.replace("$ROOT", &self.root.display().to_string())
+    }
+
+    fn substitute(&self, value: &Value) -> Value {
+        match value {
+            Value::String(s) => Value::String(self.substitute_str(s)),
+            Value::Array(items) => Value::Array(items.iter().map(|v| self.substitute(v)).collect()),
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), self.substitute(v)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+
+    fn invoked(&self) -> Vec<String> {
+        std::fs::read_to_string(&self.calls)
+            .map(|calls| {
+                calls
+                    .lines()
+                    .map(|line| line.trim_end().to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for MockOs {
+    fn drop(&mut self) {
+        for (key, value) in self.saved_env.drain(..).rev() {
+            match value {
+                Some(value) => std::env::set_var(&key, value),
+                None => std::env::remove_var(&key),
+            }
+        }
+        let _ = std::fs::remove_dir_all(&self.base);
+    }
+}
+
+fn shell_quote(raw: &str) -> String {
+    format!("'{}'", raw.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn expected_fields_are_a_subset_of_the_output() {
+        let actual = json!({"value": 632.0, "text": "632", "extra": [1, 2]});
+        assert_eq!(mismatch(&json!({"value": 632}), &actual, "output"), None);
+        assert_eq!(
+            mismatch(&json!({"text": "633"}), &actual, "output"),
+            Some("output.text is \"632\", expected \"633\"".to_string())
+        );
+        assert_eq!(
+            mismatch(&json!({"unit": "km"}), &actual, "output"),
+            Some("output.unit is missing".to_string())
+        );
+        assert!(mismatch(&json!({"extra": [1]}), &actual, "output").is_some());
+    }
+}
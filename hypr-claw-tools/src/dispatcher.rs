@@ -1,10 +1,12 @@
 use crate::error::ToolError;
-use crate::execution_context::ExecutionContext;
+use crate::execution_context::{Capabilities, ExecutionContext};
 use crate::registry::ToolRegistryImpl;
+use crate::sandbox::{command_analysis, env_policy, ExecRules};
 use crate::tools::ToolResult;
 use crate::traits::{AuditLogger, PermissionDecision, PermissionEngine, PermissionRequest};
 use serde_json::json;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
 use tokio::time::{timeout, Duration};
 use tracing::{error, info, warn};
 
@@ -13,6 +15,11 @@ pub struct ToolDispatcherImpl {
     permission: Arc<dyn PermissionEngine>,
     audit: Arc<dyn AuditLogger>,
     timeout_ms: u64,
+    capabilities: Capabilities,
+    exec_rules: ExecRules,
+    /// Environment overlay per session, applied to the processes its calls
+    /// start.
+    env_overlays: RwLock<HashMap<String, BTreeMap<String, String>>>,
 }
 
 impl ToolDispatcherImpl {
@@ -27,14 +34,63 @@ impl ToolDispatcherImpl {
             permission,
             audit,
             timeout_ms,
+            capabilities: Capabilities::default(),
+            exec_rules: ExecRules::default(),
+            env_overlays: RwLock::new(HashMap::new()),
         }
     }
 
+    /// Allow/deny rules for `hypr.exec` and `proc.spawn` command lines,
+    /// applied before the permission engine is consulted.
+    pub fn with_exec_rules(mut self, exec_rules: ExecRules) -> Self {
+        self.exec_rules = exec_rules;
+        self
+    }
+
+    /// Capabilities granted to calls made through [`Self::dispatch`].
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Variables processes started by `session_key`'s calls get on top of
+    /// the agent's environment; an empty map removes the overlay.
+    pub fn set_env_overlay(&self, session_key: &str, env: BTreeMap<String, String>) {
+        let mut overlays = self.env_overlays.write().unwrap_or_else(|e| e.into_inner());
+        if env.is_empty() {
+            overlays.remove(session_key);
+        } else {
+            overlays.insert(session_key.to_string(), env);
+        }
+    }
+
+    pub fn env_overlay(&self, session_key: &str) -> BTreeMap<String, String> {
+        self.env_overlays
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(session_key)
+            .cloned()
+            .unwrap_or_default()
+    }
+
     pub async fn dispatch(
         &self,
         session_key: String,
         tool_name: String,
         input: serde_json::Value,
+    ) -> Result<ToolResult, ToolError> {
+        self.dispatch_with_capabilities(session_key, tool_name, input, self.capabilities.clone())
+            .await
+    }
+
+    /// Dispatch one call with its own capability set, e.g. a narrower sandbox
+    /// root or no network for a single untrusted step.
+    pub async fn dispatch_with_capabilities(
+        &self,
+        session_key: String,
+        tool_name: String,
+        input: serde_json::Value,
+        capabilities: Capabilities,
     ) -> Result<ToolResult, ToolError> {
         info!(
             "Dispatching tool: {} for session: {}",
@@ -55,6 +111,8 @@ impl ToolDispatcherImpl {
             ));
         }
 
+        let input = tool.resolve_input(input)?;
+
         // 3. Build permission request
         let perm_request = PermissionRequest {
             session_key: session_key.clone(),
@@ -62,11 +120,22 @@ impl ToolDispatcherImpl {
             input: input.clone(),
             permission_tier: tool.permission_tier(),
             timestamp: chrono::Utc::now().to_rfc3339(),
+            command_risk: command_analysis::analyze_call(&tool_name, &input)
+                .filter(|analysis| !analysis.hazards.is_empty()),
+            paths: tool
+                .path_inputs()
+                .iter()
+                .flat_map(|field| declared_paths(&input, field))
+                .collect(),
         };
 
         // 4. Check permission
         let permission_tier = perm_request.permission_tier;
-        let decision = self.permission.check(perm_request).await;
+        let env = self.env_overlay(&session_key);
+        let decision = match self.exec_rules.check_call(&tool_name, &input) {
+            Some(reason) => PermissionDecision::Deny(reason),
+            None => self.permission.check(perm_request).await,
+        };
 
         let result = match &decision {
             PermissionDecision::Deny(reason) => {
@@ -79,7 +148,10 @@ impl ToolDispatcherImpl {
                 error: Some("Approval required".into()),
             }),
             PermissionDecision::Allow => {
-                let ctx = ExecutionContext::new(session_key.clone(), self.timeout_ms);
+                let timeout_ms = tool.timeout_ms().unwrap_or(self.timeout_ms);
+                let ctx = ExecutionContext::new(session_key.clone(), timeout_ms)
+                    .with_capabilities(capabilities.clone())
+                    .with_env(env.clone());
                 self.execute_with_protection(tool, ctx, input.clone()).await
             }
         };
@@ -92,6 +164,8 @@ impl ToolDispatcherImpl {
             &result,
             &decision,
             permission_tier,
+            &capabilities,
+            &env,
         )
         .await;
 
@@ -147,6 +221,7 @@ impl ToolDispatcherImpl {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn log_audit_isolated(
         &self,
         session_key: &str,
@@ -155,9 +230,11 @@ impl ToolDispatcherImpl {
         result: &Result<ToolResult, ToolError>,
         decision: &PermissionDecision,
         permission_tier: crate::traits::PermissionTier,
+        capabilities: &Capabilities,
+        env: &BTreeMap<String, String>,
     ) {
         // Audit logging must never fail the operation
-        let log_entry = json!({
+        let mut log_entry = json!({
             "timestamp": chrono::Utc::now().to_rfc3339(),
             "session": session_key,
             "tool": tool_name,
@@ -168,11 +245,15 @@ impl ToolDispatcherImpl {
                 PermissionDecision::RequireApproval(_) => "REQUIRE_APPROVAL",
             },
             "permission_tier": format!("{permission_tier:?}"),
+            "capabilities": capabilities,
             "result": match result {
                 Ok(r) => json!({"success": r.success, "output": &r.output, "error": &r.error}),
                 Err(e) => json!({"error": e.to_string()}),
             }
         });
+        if !env.is_empty() {
+            log_entry["env"] = json!(env_policy::redacted(env));
+        }
 
         // Fire and forget - don't await
         let audit = self.audit.clone();
@@ -181,3 +262,25 @@ impl ToolDispatcherImpl {
         });
     }
 }
+
+/// String values under `field` of `input`, where `field` is a name or
+/// `list.field` (see [`Tool::path_inputs`](crate::tools::Tool::path_inputs)).
+fn declared_paths(input: &serde_json::Value, field: &str) -> Vec<String> {
+    let (head, rest) = match field.split_once('.') {
+        Some((head, rest)) => (head, Some(rest)),
+        None => (field, None),
+    };
+    let values: Vec<&serde_json::Value> = match input.get(head) {
+        Some(serde_json::Value::Array(items)) => items.iter().collect(),
+        Some(value) => vec![value],
+        None => Vec::new(),
+    };
+    values
+        .into_iter()
+        .flat_map(|value| match (rest, value) {
+            (Some(rest), _) => declared_paths(value, rest),
+            (None, serde_json::Value::String(path)) => vec![path.clone()],
+            _ => Vec::new(),
+        })
+        .collect()
+}
@@ -0,0 +1,310 @@
+//! Duplicate file finder for "clean up my Pictures" tasks.
+//!
+//! Files are grouped by size first, so only files sharing a size are read.
+//! Those are hashed in full, except files above [`SAMPLE_ABOVE`] bytes, which
+//! are fingerprinted from their start, middle and end and reported as
+//! `sampled`. In each group the oldest copy is suggested to keep; the others
+//! can be staged for deletion through [`crate::staging`] so the user reviews
+//! them in one preview.
+
+use crate::error::ToolError;
+use crate::execution_context::ExecutionContext;
+use crate::staging::{StagedOp, StagingArea};
+use crate::tools::{Tool, ToolResult};
+use crate::traits::PermissionTier;
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Files larger than this are fingerprinted from samples instead of hashed
+/// in full.
+pub const SAMPLE_ABOVE: u64 = 64 * 1024 * 1024;
+const SAMPLE_BYTES: u64 = 1024 * 1024;
+/// Files visited before a scan stops and reports itself truncated.
+const MAX_FILES: usize = 50_000;
+/// Groups listed in the tool output; unlisted ones are still counted and staged.
+const MAX_LISTED_GROUPS: usize = 50;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DuplicateGroup {
+    pub size: u64,
+    /// Matched on sampled content rather than a full hash.
+    pub sampled: bool,
+    pub keep: PathBuf,
+    pub delete: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    /// Bytes freed by deleting the suggested copies.
+    pub fn reclaimable(&self) -> u64 {
+        self.size * self.delete.len() as u64
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DuplicateScan {
+    pub groups: Vec<DuplicateGroup>,
+    pub files_scanned: usize,
+    pub truncated: bool,
+}
+
+/// Find files under `root` with identical content. Empty files and files
+/// smaller than `min_bytes` are ignored; symlinks are not followed.
+pub fn find_duplicates(root: &Path, recursive: bool, min_bytes: u64) -> io::Result<DuplicateScan> {
+    let mut scan = DuplicateScan::default();
+    let mut by_size: HashMap<u64, Vec<(PathBuf, SystemTime)>> = HashMap::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if dir == root => return Err(e),
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let Ok(meta) = entry.path().symlink_metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                if recursive {
+                    pending.push(entry.path());
+                }
+                continue;
+            }
+            if !meta.is_file() || meta.len() == 0 || meta.len() < min_bytes {
+                continue;
+            }
+            if scan.files_scanned == MAX_FILES {
+                scan.truncated = true;
+                pending.clear();
+                break;
+            }
+            scan.files_scanned += 1;
+            let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            by_size
+                .entry(meta.len())
+                .or_default()
+                .push((entry.path(), modified));
+        }
+    }
+
+    for (size, files) in by_size {
+        if files.len() < 2 {
+            continue;
+        }
+        let mut by_hash: HashMap<String, Vec<(PathBuf, SystemTime)>> = HashMap::new();
+        for (path, modified) in files {
+            if let Ok(hash) = fingerprint(&path, size) {
+                by_hash.entry(hash).or_default().push((path, modified));
+            }
+        }
+        for (_, mut copies) in by_hash {
+            if copies.len() < 2 {
+                continue;
+            }
+            // Oldest first; ties go to the shorter, then alphabetically first path.
+            copies.sort_by(|a, b| {
+                a.1.cmp(&b.1)
+                    .then(a.0.as_os_str().len().cmp(&b.0.as_os_str().len()))
+                    .then(a.0.cmp(&b.0))
+            });
+            let mut paths = copies.into_iter().map(|(path, _)| path);
+            let keep = paths.next().unwrap_or_default();
+            scan.groups.push(DuplicateGroup {
+                size,
+                sampled: size > SAMPLE_ABOVE,
+                keep,
+                delete: paths.collect(),
+            });
+        }
+    }
+    scan.groups.sort_by(|a, b| {
+        b.reclaimable()
+            .cmp(&a.reclaimable())
+            .then(a.keep.cmp(&b.keep))
+    });
+    Ok(scan)
+}
+
+/// SHA-256 of the whole file, or of its size and three samples when it is
+/// larger than [`SAMPLE_ABOVE`].
+fn fingerprint(path: &Path, size: u64) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    if size <= SAMPLE_ABOVE {
+        io::copy(&mut file, &mut hasher)?;
+    } else {
+        hasher.update(size.to_le_bytes());
+        let mut buf = vec![0; SAMPLE_BYTES as usize];
+        for offset in [0, size / 2 - SAMPLE_BYTES / 2, size - SAMPLE_BYTES] {
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut buf)?;
+            hasher.update(&buf);
+        }
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub struct FsFindDuplicatesTool {
+    area: Arc<StagingArea>,
+}
+
+impl FsFindDuplicatesTool {
+    pub fn new(area: Arc<StagingArea>) -> Self {
+        Self { area }
+    }
+}
+
+#[async_trait]
+impl Tool for FsFindDuplicatesTool {
+    fn name(&self) -> &'static str {
+        "fs.find_duplicates"
+    }
+    fn description(&self) -> &'static str {
+        "Find files with identical content under a directory, grouped with a suggested copy to keep. With stage=true the other copies are staged for deletion; confirm with fs.apply_staged"
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string"},
+                "recursive": {"type": "boolean", "description": "Default true"},
+                "min_bytes": {"type": "integer", "minimum": 0},
+                "stage": {"type": "boolean", "description": "Stage the suggested deletes"},
+                "include_sampled": {
+                    "type": "boolean",
+                    "description": "Also stage huge files matched on sampled content"
+                }
+            },
+            "required": ["path"],
+            "additionalProperties": false
+        })
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let root = input["path"]
+            .as_str()
+            .ok_or_else(|| ToolError::ValidationError("'path' is required".into()))?;
+        let root = ctx.capabilities.confine(root)?;
+        let recursive = input["recursive"].as_bool().unwrap_or(true);
+        let min_bytes = input["min_bytes"].as_u64().unwrap_or(0);
+        let scan =
+            tokio::task::spawn_blocking(move || find_duplicates(&root, recursive, min_bytes))
+                .await
+                .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?
+                .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+
+        let mut staged = 0;
+        if input["stage"].as_bool() == Some(true) {
+            let include_sampled = input["include_sampled"].as_bool() == Some(true);
+            let ops: Vec<StagedOp> = scan
+                .groups
+                .iter()
+                .filter(|group| include_sampled || !group.sampled)
+                .flat_map(|group| group.delete.iter())
+                .map(|path| StagedOp::Delete {
+                    path: path.clone(),
+                    secure: false,
+                })
+                .collect();
+            staged = ops.len();
+            self.area
+                .stage(&ctx.session_key, ops)
+                .map_err(ToolError::ValidationError)?;
+        }
+
+        let duplicates: usize = scan.groups.iter().map(|group| group.delete.len()).sum();
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({
+                "files_scanned": scan.files_scanned,
+                "truncated": scan.truncated,
+                "duplicate_files": duplicates,
+                "reclaimable_bytes": scan.groups.iter().map(DuplicateGroup::reclaimable).sum::<u64>(),
+                "groups": scan.groups.iter().take(MAX_LISTED_GROUPS).collect::<Vec<_>>(),
+                "groups_not_listed": scan.groups.len().saturating_sub(MAX_LISTED_GROUPS),
+                "staged_deletes": staged,
+            })),
+            error: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_files_are_grouped_with_the_oldest_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name);
+        std::fs::create_dir(path("2024")).unwrap();
+        std::fs::write(path("beach.jpg"), "same bytes").unwrap();
+        std::fs::write(path("2024/beach (1).jpg"), "same bytes").unwrap();
+        std::fs::write(path("2024/beach copy.jpg"), "same bytes").unwrap();
+        // Same size, different content.
+        std::fs::write(path("other.jpg"), "diff bytes").unwrap();
+        std::fs::write(path("empty-a"), "").unwrap();
+        std::fs::write(path("empty-b"), "").unwrap();
+        let old = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        File::options()
+            .write(true)
+            .open(path("2024/beach copy.jpg"))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+
+        let scan = find_duplicates(dir.path(), true, 0).unwrap();
+        assert_eq!(scan.files_scanned, 4);
+        assert_eq!(
+            scan.groups,
+            vec![DuplicateGroup {
+                size: 10,
+                sampled: false,
+                keep: path("2024/beach copy.jpg"),
+                delete: vec![path("beach.jpg"), path("2024/beach (1).jpg")],
+            }]
+        );
+        assert_eq!(scan.groups[0].reclaimable(), 20);
+
+        assert!(find_duplicates(dir.path(), false, 0)
+            .unwrap()
+            .groups
+            .is_empty());
+        assert!(find_duplicates(dir.path(), true, 11)
+            .unwrap()
+            .groups
+            .is_empty());
+    }
+
+    #[test]
+    fn huge_files_are_compared_on_samples() {
+        let dir = tempfile::tempdir().unwrap();
+        let size = SAMPLE_ABOVE + 4 * SAMPLE_BYTES;
+        let write = |name: &str, marks: &[u64]| {
+            let mut file = File::create(dir.path().join(name)).unwrap();
+            file.set_len(size).unwrap();
+            for mark in marks {
+                file.seek(SeekFrom::Start(*mark)).unwrap();
+                std::io::Write::write_all(&mut file, b"x").unwrap();
+            }
+        };
+        write("a.iso", &[0]);
+        // Differs only between the samples, so it is (knowingly) matched.
+        write("b.iso", &[0, SAMPLE_BYTES + 1]);
+        write("c.iso", &[size - 1]);
+
+        let scan = find_duplicates(dir.path(), true, 0).unwrap();
+        assert_eq!(scan.groups.len(), 1);
+        assert!(scan.groups[0].sampled);
+        assert_eq!(scan.groups[0].delete.len(), 1);
+    }
+}
@@ -1,4 +1,8 @@
+use crate::error::ToolError;
+use crate::sandbox::PathGuard;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionContext {
@@ -6,6 +10,14 @@ pub struct ExecutionContext {
     pub timeout_ms: u64,
     pub audit_ref: String,
     pub permission_ref: String,
+    /// Powers granted to this call. Tools check these instead of assuming
+    /// ambient access to the filesystem, network or clipboard.
+    #[serde(default)]
+    pub capabilities: Capabilities,
+    /// Variables set on top of the agent's own environment for processes
+    /// this call starts (`proc.spawn`, `hypr.exec`).
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
 }
 
 impl ExecutionContext {
@@ -15,6 +27,189 @@ impl ExecutionContext {
             timeout_ms,
             audit_ref: uuid::Uuid::new_v4().to_string(),
             permission_ref: uuid::Uuid::new_v4().to_string(),
+            capabilities: Capabilities::default(),
+            env: BTreeMap::new(),
         }
     }
+
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    pub fn with_env(mut self, env: BTreeMap<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkAccess {
+    Denied,
+    /// Only these hosts (and their subdomains).
+    Hosts(Vec<String>),
+    Any,
+}
+
+/// Capability handles carried by a single dispatch.
+///
+/// The default grants what tools had before capabilities existed: their own
+/// sandbox root, any network host and the clipboard. Narrow it per call with
+/// [`ToolDispatcherImpl::dispatch_with_capabilities`](crate::ToolDispatcherImpl::dispatch_with_capabilities).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Directory file tools are confined to. Must lie inside the tool's own
+    /// sandbox root; `None` keeps the tool's root.
+    #[serde(default)]
+    pub sandbox_root: Option<PathBuf>,
+    #[serde(default = "default_network")]
+    pub network: NetworkAccess,
+    #[serde(default = "default_true")]
+    pub clipboard: bool,
+}
+
+fn default_network() -> NetworkAccess {
+    NetworkAccess::Any
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            sandbox_root: None,
+            network: NetworkAccess::Any,
+            clipboard: true,
+        }
+    }
+}
+
+impl Capabilities {
+    /// No filesystem narrowing, no network, no clipboard.
+    pub fn none() -> Self {
+        Self {
+            sandbox_root: None,
+            network: NetworkAccess::Denied,
+            clipboard: false,
+        }
+    }
+
+    /// Path guard for a file tool whose own root is `tool_root`. A granted
+    /// root outside `tool_root` is a sandbox violation, never a widening.
+    pub fn path_guard(&self, tool_root: impl AsRef<Path>) -> Result<PathGuard, ToolError> {
+        let Some(granted) = &self.sandbox_root else {
+            return PathGuard::new(tool_root);
+        };
+        let tool_root = std::fs::canonicalize(tool_root)
+            .map_err(|e| ToolError::SandboxViolation(format!("Invalid sandbox root: {e}")))?;
+        let granted_path = if granted.is_absolute() {
+            granted.clone()
+        } else {
+            tool_root.join(granted)
+        };
+        let granted_path = std::fs::canonicalize(&granted_path)
+            .map_err(|e| ToolError::SandboxViolation(format!("Invalid granted root: {e}")))?;
+        if !granted_path.starts_with(&tool_root) {
+            return Err(ToolError::SandboxViolation(format!(
+                "granted root {} is outside {}",
+                granted_path.display(),
+                tool_root.display()
+            )));
+        }
+        PathGuard::new(granted_path)
+    }
+
+    /// Path for a tool that addresses the whole filesystem (`fs.*`, `csv.*`,
+    /// `db.*`, `doc.generate`): returned as given without a granted root,
+    /// otherwise resolved and required to stay inside it.
+    pub fn confine(&self, path: impl AsRef<Path>) -> Result<PathBuf, ToolError> {
+        if self.sandbox_root.is_none() {
+            return Ok(path.as_ref().to_path_buf());
+        }
+        self.path_guard("/")?.contain(path)
+    }
+
+    pub fn allows_host(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        match &self.network {
+            NetworkAccess::Denied => false,
+            NetworkAccess::Any => true,
+            NetworkAccess::Hosts(hosts) => hosts.iter().any(|allowed| {
+                let allowed = allowed.to_ascii_lowercase();
+                host == allowed || host.ends_with(&format!(".{allowed}"))
+            }),
+        }
+    }
+
+    /// Fails unless this call may reach the host of `url`.
+    pub fn require_network(&self, url: &str) -> Result<(), ToolError> {
+        let host = url_host(url).unwrap_or_default();
+        if self.allows_host(host) {
+            return Ok(());
+        }
+        Err(ToolError::PermissionDenied(format!(
+            "network access to '{host}' was not granted for this call"
+        )))
+    }
+
+    pub fn require_clipboard(&self) -> Result<(), ToolError> {
+        if self.clipboard {
+            return Ok(());
+        }
+        Err(ToolError::PermissionDenied(
+            "clipboard access was not granted for this call".to_string(),
+        ))
+    }
+}
+
+fn url_host(url: &str) -> Option<&str> {
+    let rest = url.split_once("://").map(|(_, rest)| rest)?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+    Some(host.split(':').next().unwrap_or(host))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_hosts_match_subdomains_only() {
+        let caps = Capabilities {
+            network: NetworkAccess::Hosts(vec!["google.com".to_string()]),
+            ..Capabilities::default()
+        };
+        assert!(caps.require_network("https://mail.google.com/mail").is_ok());
+        assert!(caps.require_network("https://user@google.com:443/").is_ok());
+        assert!(caps.require_network("https://evilgoogle.com").is_err());
+        assert!(Capabilities::none()
+            .require_network("https://google.com")
+            .is_err());
+        assert!(Capabilities::default()
+            .require_network("https://a.b")
+            .is_ok());
+    }
+
+    #[test]
+    fn granted_root_can_only_narrow() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("inner")).unwrap();
+        let narrow = Capabilities {
+            sandbox_root: Some(PathBuf::from("inner")),
+            ..Capabilities::default()
+        };
+        assert!(narrow.path_guard(dir.path()).is_ok());
+
+        let outside = Capabilities {
+            sandbox_root: Some(std::env::temp_dir()),
+            ..Capabilities::default()
+        };
+        assert!(matches!(
+            outside.path_guard(dir.path()),
+            Err(ToolError::SandboxViolation(_))
+        ));
+    }
 }
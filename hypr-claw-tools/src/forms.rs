@@ -0,0 +1,670 @@
+//! Remembered forms: which fact goes into which field.
+//!
+//! The first time the user has a form filled, the model saves a profile with
+//! `form.remember`: the field labels in order, the fact each one takes, and
+//! any values the user had to provide. `form.fill` then fills the same form
+//! deterministically, clicking each label (or tabbing between fields) and
+//! typing the value, without asking again.
+//!
+//! Plain values are facts about the user: the REPL owns `context.facts`,
+//! refreshes the snapshot here before every run and drains facts learned
+//! during it. Sensitive values (card numbers, IDs, passwords) stay in the
+//! form store instead, so they never reach the model's prompt, and every
+//! sensitive field asks for consent before it is typed unless the user
+//! allowed that field for good.
+
+use crate::error::ToolError;
+use crate::execution_context::ExecutionContext;
+use crate::os_capabilities::desktop;
+use crate::questions::BACKGROUND_MARKER;
+use crate::tools::{Tool, ToolResult};
+use crate::traits::PermissionTier;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::time::{sleep, timeout, Duration};
+
+/// How long the user has to answer a consent prompt.
+const DECISION_TIMEOUT: Duration = Duration::from_secs(120);
+/// Pause after focusing a field before typing into it.
+const FOCUS_DELAY: Duration = Duration::from_millis(150);
+/// Labels treated as sensitive when the profile does not say.
+const SENSITIVE_LABELS: &[&str] = &[
+    "password",
+    "passcode",
+    "pin",
+    "card",
+    "cvv",
+    "cvc",
+    "expiry",
+    "iban",
+    "account",
+    "routing",
+    "ssn",
+    "social security",
+    "passport",
+    "tax",
+    "license",
+    "licence",
+    "national id",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Navigation {
+    /// Click each field's label with OCR, then type.
+    #[default]
+    Labels,
+    /// Start in the focused first field and press Tab between fields.
+    Tab,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormField {
+    /// Label as shown on screen.
+    pub label: String,
+    /// Fact key the value comes from, e.g. `address_street`.
+    pub fact: String,
+    #[serde(default)]
+    pub sensitive: bool,
+    /// The user allowed this sensitive field without asking each time.
+    #[serde(default)]
+    pub always_allowed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormProfile {
+    pub name: String,
+    /// Window title substring that identifies the form.
+    #[serde(default)]
+    pub window_match: Option<String>,
+    #[serde(default)]
+    pub navigation: Navigation,
+    pub fields: Vec<FormField>,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FormFile {
+    #[serde(default)]
+    profiles: Vec<FormProfile>,
+    /// Values of sensitive facts, kept out of `context.facts`.
+    #[serde(default)]
+    sensitive_values: BTreeMap<String, String>,
+}
+
+/// Whether `label` looks like it asks for sensitive data. Words match at
+/// their start, so `PIN code` does but `Shipping` does not.
+pub fn looks_sensitive(label: &str) -> bool {
+    let words: Vec<String> = label
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_string)
+        .collect();
+    let label = format!(" {}", words.join(" "));
+    SENSITIVE_LABELS
+        .iter()
+        .any(|word| label.contains(&format!(" {word}")))
+}
+
+/// `key:value` facts, as stored in `context.facts`.
+fn fact_value<'a>(facts: &'a [String], key: &str) -> Option<&'a str> {
+    facts.iter().rev().find_map(|fact| {
+        let (k, v) = fact.split_once(':')?;
+        (k.trim() == key).then(|| v.trim())
+    })
+}
+
+/// JSON-file backed form profiles, plus the fact snapshot they fill from.
+pub struct FormMemory {
+    path: PathBuf,
+    file: Mutex<FormFile>,
+    facts: RwLock<Vec<String>>,
+    learned: Mutex<Vec<String>>,
+}
+
+impl FormMemory {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = if path.exists() {
+            let raw = std::fs::read_to_string(&path)?;
+            if raw.trim().is_empty() {
+                FormFile::default()
+            } else {
+                serde_json::from_str(&raw)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?
+            }
+        } else {
+            FormFile::default()
+        };
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+            facts: RwLock::new(Vec::new()),
+            learned: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Replaces the fact snapshot with the session's current facts.
+    pub fn sync_facts(&self, facts: &[String]) {
+        *self.facts.write().unwrap_or_else(|e| e.into_inner()) = facts.to_vec();
+    }
+
+    /// Plain facts learned since the last call, as `key:value`.
+    pub fn take_learned(&self) -> Vec<String> {
+        std::mem::take(&mut *self.learned.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    pub fn value(&self, fact: &str) -> Option<String> {
+        if let Some(value) = self.lock().sensitive_values.get(fact) {
+            return Some(value.clone());
+        }
+        let learned = self.learned.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(value) = fact_value(&learned, fact) {
+            return Some(value.to_string());
+        }
+        let facts = self.facts.read().unwrap_or_else(|e| e.into_inner());
+        fact_value(&facts, fact).map(str::to_string)
+    }
+
+    pub fn profiles(&self) -> Vec<FormProfile> {
+        self.lock().profiles.clone()
+    }
+
+    /// Profile called `name`, or without a name the one whose
+    /// `window_match` appears in `window_title`.
+    pub fn find(&self, name: Option<&str>, window_title: &str) -> Option<FormProfile> {
+        let title = window_title.to_lowercase();
+        self.lock()
+            .profiles
+            .iter()
+            .find(|profile| match name {
+                Some(name) => profile.name.eq_ignore_ascii_case(name),
+                None => profile
+                    .window_match
+                    .as_deref()
+                    .is_some_and(|m| !m.is_empty() && title.contains(&m.to_lowercase())),
+            })
+            .cloned()
+    }
+
+    /// Saves `profile`, replacing one with the same name but keeping the
+    /// standing consent of fields it already had. `values` are stored as
+    /// facts, or in the form store for sensitive fields.
+    pub fn remember(
+        &self,
+        mut profile: FormProfile,
+        values: &[(String, String)],
+    ) -> io::Result<()> {
+        let mut file = self.lock();
+        if let Some(old) = file
+            .profiles
+            .iter()
+            .position(|p| p.name.eq_ignore_ascii_case(&profile.name))
+        {
+            let old = file.profiles.remove(old);
+            for field in &mut profile.fields {
+                field.always_allowed = old
+                    .fields
+                    .iter()
+                    .any(|o| o.label == field.label && o.fact == field.fact && o.always_allowed);
+            }
+        }
+        for (fact, value) in values {
+            let sensitive = profile
+                .fields
+                .iter()
+                .any(|field| &field.fact == fact && field.sensitive);
+            if sensitive {
+                file.sensitive_values.insert(fact.clone(), value.clone());
+            } else {
+                let mut learned = self.learned.lock().unwrap_or_else(|e| e.into_inner());
+                learned.retain(|f| f.split_once(':').map(|(k, _)| k) != Some(fact.as_str()));
+                learned.push(format!("{fact}:{value}"));
+            }
+        }
+        profile.updated_at = chrono::Utc::now().timestamp();
+        file.profiles.push(profile);
+        self.persist(&file)
+    }
+
+    /// Lets a sensitive field be filled without asking from now on.
+    pub fn allow_always(&self, form: &str, label: &str) -> io::Result<()> {
+        let mut file = self.lock();
+        for profile in file.profiles.iter_mut().filter(|p| p.name == form) {
+            for field in profile.fields.iter_mut().filter(|f| f.label == label) {
+                field.always_allowed = true;
+            }
+        }
+        self.persist(&file)
+    }
+
+    /// Drops a profile; its sensitive values go too unless another profile
+    /// uses them. Returns `false` if there was none.
+    pub fn forget(&self, name: &str) -> io::Result<bool> {
+        let mut file = self.lock();
+        let Some(index) = file
+            .profiles
+            .iter()
+            .position(|p| p.name.eq_ignore_ascii_case(name))
+        else {
+            return Ok(false);
+        };
+        file.profiles.remove(index);
+        let profiles = std::mem::take(&mut file.profiles);
+        file.sensitive_values.retain(|fact, _| {
+            profiles
+                .iter()
+                .flat_map(|p| &p.fields)
+                .any(|f| &f.fact == fact)
+        });
+        file.profiles = profiles;
+        self.persist(&file)?;
+        Ok(true)
+    }
+
+    /// Each field with its value, or the fields that have none yet.
+    pub fn plan(&self, profile: &FormProfile) -> Result<Vec<(FormField, String)>, Vec<FormField>> {
+        let mut ready = Vec::new();
+        let mut missing = Vec::new();
+        for field in &profile.fields {
+            match self.value(&field.fact) {
+                Some(value) => ready.push((field.clone(), value)),
+                None => missing.push(field.clone()),
+            }
+        }
+        if missing.is_empty() {
+            Ok(ready)
+        } else {
+            Err(missing)
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, FormFile> {
+        self.file
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn persist(&self, file: &FormFile) -> io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let payload = serde_json::to_string_pretty(file)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, payload)?;
+        // Sensitive values live here.
+        std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600))?;
+        std::fs::rename(tmp, &self.path)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Consent {
+    Once,
+    Always,
+    Deny,
+}
+
+/// Asks whether a sensitive field may be filled: `(form, label, fact)`.
+pub type ConsentPrompt = Arc<dyn Fn(&str, &str, &str) -> Consent + Send + Sync>;
+
+pub struct FormRememberTool {
+    memory: Arc<FormMemory>,
+}
+
+impl FormRememberTool {
+    pub fn new(memory: Arc<FormMemory>) -> Self {
+        Self { memory }
+    }
+}
+
+#[async_trait]
+impl Tool for FormRememberTool {
+    fn name(&self) -> &'static str {
+        "form.remember"
+    }
+    fn description(&self) -> &'static str {
+        "Save how to fill a recurring form: its field labels in order, the fact key each takes (e.g. address_city), and values the user just gave you, so form.fill can fill it next time without asking"
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "form": {"type": "string", "description": "Short name, e.g. 'dentist intake'"},
+                "window_match": {"type": "string", "description": "Window title text that identifies the form"},
+                "navigation": {"type": "string", "enum": ["labels", "tab"], "default": "labels"},
+                "fields": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "label": {"type": "string"},
+                            "fact": {"type": "string"},
+                            "value": {"type": "string", "description": "Only when the user just provided it"},
+                            "sensitive": {"type": "boolean"}
+                        },
+                        "required": ["label", "fact"]
+                    }
+                }
+            },
+            "required": ["form", "fields"],
+            "additionalProperties": false
+        })
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Write
+    }
+    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let name = input
+            .get("form")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|n| !n.is_empty())
+            .ok_or_else(|| ToolError::ValidationError("'form' must not be empty".into()))?;
+        let navigation = match input.get("navigation").and_then(|v| v.as_str()) {
+            None | Some("labels") => Navigation::Labels,
+            Some("tab") => Navigation::Tab,
+            Some(other) => {
+                return Err(ToolError::ValidationError(format!(
+                    "unknown navigation '{other}'; use labels or tab"
+                )))
+            }
+        };
+        let items = input
+            .get("fields")
+            .and_then(|v| v.as_array())
+            .filter(|items| !items.is_empty())
+            .ok_or_else(|| {
+                ToolError::ValidationError("'fields' must list at least one field".into())
+            })?;
+        let mut fields = Vec::with_capacity(items.len());
+        let mut values = Vec::new();
+        for item in items {
+            let text = |key: &str| {
+                item.get(key)
+                    .and_then(|v| v.as_str())
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+            };
+            let (Some(label), Some(fact)) = (text("label"), text("fact")) else {
+                return Err(ToolError::ValidationError(
+                    "every field needs a 'label' and a 'fact'".into(),
+                ));
+            };
+            if fact.contains(':') {
+                return Err(ToolError::ValidationError(format!(
+                    "fact key '{fact}' must not contain ':'"
+                )));
+            }
+            if let Some(value) = text("value") {
+                values.push((fact.to_string(), value.to_string()));
+            }
+            fields.push(FormField {
+                label: label.to_string(),
+                fact: fact.to_string(),
+                sensitive: item
+                    .get("sensitive")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or_else(|| looks_sensitive(label)),
+                always_allowed: false,
+            });
+        }
+        let sensitive: Vec<&str> = fields
+            .iter()
+            .filter(|f| f.sensitive)
+            .map(|f| f.label.as_str())
+            .collect();
+        let output = json!({
+            "form": name,
+            "fields": fields.len(),
+            "values_saved": values.len(),
+            "sensitive_fields": sensitive,
+        });
+        let profile = FormProfile {
+            name: name.to_string(),
+            window_match: input
+                .get("window_match")
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|m| !m.is_empty())
+                .map(str::to_string),
+            navigation,
+            fields,
+            updated_at: 0,
+        };
+        self.memory
+            .remember(profile, &values)
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        Ok(ToolResult {
+            success: true,
+            output: Some(output),
+            error: None,
+        })
+    }
+}
+
+pub struct FormFillTool {
+    memory: Arc<FormMemory>,
+    consent: ConsentPrompt,
+}
+
+impl FormFillTool {
+    pub fn new(memory: Arc<FormMemory>, consent: ConsentPrompt) -> Self {
+        Self { memory, consent }
+    }
+
+    async fn consent(&self, background: bool, form: &str, field: &FormField) -> Consent {
+        if !field.sensitive || field.always_allowed {
+            return Consent::Once;
+        }
+        if background {
+            return Consent::Deny;
+        }
+        let ask = self.consent.clone();
+        let (form, label, fact) = (form.to_string(), field.label.clone(), field.fact.clone());
+        match timeout(
+            DECISION_TIMEOUT,
+            tokio::task::spawn_blocking(move || ask(&form, &label, &fact)),
+        )
+        .await
+        {
+            Ok(Ok(consent)) => consent,
+            _ => Consent::Deny,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for FormFillTool {
+    fn name(&self) -> &'static str {
+        "form.fill"
+    }
+    fn description(&self) -> &'static str {
+        "Fill a form saved with form.remember from the user's facts. Without 'form' it picks the profile matching the active window. Reports missing facts instead of typing anything; ask the user for them, save them with form.remember and call this again"
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "form": {"type": "string"}
+            },
+            "additionalProperties": false
+        })
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Write
+    }
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let name = input.get("form").and_then(|v| v.as_str()).map(str::trim);
+        let title = desktop::active_window()
+            .await
+            .ok()
+            .and_then(|w| w.get("title").and_then(Value::as_str).map(str::to_string))
+            .unwrap_or_default();
+        let profile = self.memory.find(name, &title).ok_or_else(|| {
+            let known: Vec<String> = self.memory.profiles().into_iter().map(|p| p.name).collect();
+            ToolError::ValidationError(format!(
+                "no saved form matches; known forms: {}",
+                if known.is_empty() {
+                    "none".to_string()
+                } else {
+                    known.join(", ")
+                }
+            ))
+        })?;
+
+        let fields = match self.memory.plan(&profile) {
+            Ok(fields) => fields,
+            Err(missing) => {
+                let missing: Vec<Value> = missing
+                    .iter()
+                    .map(|f| json!({"label": f.label, "fact": f.fact, "sensitive": f.sensitive}))
+                    .collect();
+                return Ok(ToolResult {
+                    success: false,
+                    output: Some(json!({"form": profile.name, "missing": missing})),
+                    error: Some(
+                        "some fields have no saved value; nothing was typed. Ask the user for them with ask_user, save them with form.remember, then call form.fill again".into(),
+                    ),
+                });
+            }
+        };
+
+        let background = ctx.session_key.contains(BACKGROUND_MARKER);
+        let mut filled = Vec::new();
+        let mut skipped = Vec::new();
+        let mut first = true;
+        for (field, value) in fields {
+            if profile.navigation == Navigation::Tab && !first {
+                desktop::key_press("Tab")
+                    .await
+                    .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+            }
+            first = false;
+            match self.consent(background, &profile.name, &field).await {
+                Consent::Deny => {
+                    skipped.push(field.label);
+                    continue;
+                }
+                Consent::Always => self
+                    .memory
+                    .allow_always(&profile.name, &field.label)
+                    .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?,
+                Consent::Once => {}
+            }
+            if profile.navigation == Navigation::Labels {
+                desktop::click_text(&field.label, 0, "left", false, None)
+                    .await
+                    .map_err(|e| {
+                        ToolError::ExecutionFailed(format!("field '{}': {e}", field.label))
+                    })?;
+                sleep(FOCUS_DELAY).await;
+            }
+            desktop::type_text(&value, ctx.capabilities.clipboard)
+                .await
+                .map_err(|e| ToolError::ExecutionFailed(format!("field '{}': {e}", field.label)))?;
+            filled.push(field.label);
+        }
+
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({
+                "form": profile.name,
+                "filled": filled,
+                "skipped": skipped,
+                "note": "Values are not echoed. Verify the form before submitting it."
+            })),
+            error: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(label: &str, fact: &str) -> FormField {
+        FormField {
+            label: label.to_string(),
+            fact: fact.to_string(),
+            sensitive: looks_sensitive(label),
+            always_allowed: false,
+        }
+    }
+
+    #[test]
+    fn profiles_fill_from_facts_and_keep_sensitive_values_out_of_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("forms.json");
+        let memory = FormMemory::open(&path).unwrap();
+        memory.sync_facts(&[
+            "preferred_name:Ana".to_string(),
+            "address_city:Lisbon".to_string(),
+        ]);
+        let profile = FormProfile {
+            name: "Clinic intake".to_string(),
+            window_match: Some("Patient registration".to_string()),
+            navigation: Navigation::Labels,
+            fields: vec![
+                field("Name", "preferred_name"),
+                field("City", "address_city"),
+                field("Street", "address_street"),
+                field("Insurance card number", "insurance_card"),
+            ],
+            updated_at: 0,
+        };
+        assert!(profile.fields[3].sensitive);
+        assert!(!looks_sensitive("Shipping address"));
+        assert!(looks_sensitive("PIN code"));
+        memory.remember(profile.clone(), &[]).unwrap();
+
+        let missing = memory.plan(&profile).unwrap_err();
+        let missing: Vec<&str> = missing.iter().map(|f| f.fact.as_str()).collect();
+        assert_eq!(missing, vec!["address_street", "insurance_card"]);
+
+        memory
+            .remember(
+                profile.clone(),
+                &[
+                    ("address_street".to_string(), "Rua Augusta 1".to_string()),
+                    ("insurance_card".to_string(), "9876 5432".to_string()),
+                ],
+            )
+            .unwrap();
+        memory
+            .allow_always("Clinic intake", "Insurance card number")
+            .unwrap();
+        assert_eq!(memory.take_learned(), vec!["address_street:Rua Augusta 1"]);
+        memory.sync_facts(&[
+            "preferred_name:Ana".to_string(),
+            "address_city:Lisbon".to_string(),
+            "address_street:Rua Augusta 1".to_string(),
+        ]);
+
+        let reopened = FormMemory::open(&path).unwrap();
+        reopened.sync_facts(&["address_city:Porto".to_string()]);
+        let found = reopened
+            .find(None, "Patient Registration — Firefox")
+            .unwrap();
+        assert!(found.fields[3].always_allowed);
+        assert_eq!(
+            reopened.value("insurance_card").as_deref(),
+            Some("9876 5432")
+        );
+        assert_eq!(reopened.value("address_city").as_deref(), Some("Porto"));
+        assert!(reopened.find(None, "Inbox").is_none());
+
+        assert!(reopened.forget("clinic intake").unwrap());
+        assert_eq!(reopened.value("insurance_card"), None);
+        assert!(!reopened.forget("clinic intake").unwrap());
+    }
+}
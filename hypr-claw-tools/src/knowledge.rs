@@ -0,0 +1,480 @@
+//! Local documentation the agent can consult before answering.
+//!
+//! `knowledge import` splits man pages, markdown docs or a wiki dump into
+//! heading-sized chunks kept in a JSON index. `knowledge.search` ranks them
+//! with BM25 over their words, so answers about the local setup can quote
+//! and cite the docs instead of guessing flags.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Sections longer than this are split at paragraph breaks.
+const MAX_CHUNK_CHARS: usize = 1_500;
+
+/// Files larger than this are skipped on import.
+const MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+
+const TEXT_EXTENSIONS: &[&str] = &["md", "markdown", "txt", "rst", "adoc", "org", "html", "htm"];
+
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KnowledgeChunk {
+    /// File path or URL the chunk came from.
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heading: Option<String>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KnowledgeHit {
+    pub chunk: KnowledgeChunk,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub documents: usize,
+    pub chunks: usize,
+    /// Files that were not text or were too large.
+    pub skipped: usize,
+}
+
+/// JSON-file backed index of documentation chunks.
+pub struct KnowledgeStore {
+    path: PathBuf,
+    chunks: Mutex<Vec<KnowledgeChunk>>,
+}
+
+impl KnowledgeStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let chunks = if path.exists() {
+            let raw = std::fs::read_to_string(&path)?;
+            if raw.trim().is_empty() {
+                Vec::new()
+            } else {
+                serde_json::from_str(&raw)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?
+            }
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            path,
+            chunks: Mutex::new(chunks),
+        })
+    }
+
+    /// Indexed sources with their chunk counts, sorted by source.
+    pub fn sources(&self) -> Vec<(String, usize)> {
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for chunk in self.lock().iter() {
+            *counts.entry(chunk.source.clone()).or_default() += 1;
+        }
+        counts.into_iter().collect()
+    }
+
+    /// Replaces everything indexed from `source` with chunks of `text`.
+    /// Returns the number of chunks stored.
+    pub fn add_document(&self, source: &str, text: &str) -> io::Result<usize> {
+        let new_chunks = chunk_document(source, text);
+        let count = new_chunks.len();
+        let mut chunks = self.lock();
+        chunks.retain(|c| c.source != source);
+        chunks.extend(new_chunks);
+        self.persist(&chunks)?;
+        Ok(count)
+    }
+
+    /// Imports every text file under `dir` (or `dir` itself if it is a file).
+    pub fn import_path(&self, dir: &Path) -> io::Result<ImportSummary> {
+        let mut files = Vec::new();
+        collect_files(dir, &mut files)?;
+        files.sort();
+
+        let mut summary = ImportSummary::default();
+        let mut documents = Vec::new();
+        for file in files {
+            match read_document(&file) {
+                Some(text) => documents.push((file.display().to_string(), text)),
+                None => summary.skipped += 1,
+            }
+        }
+
+        let mut chunks = self.lock();
+        for (source, text) in documents {
+            let new_chunks = chunk_document(&source, &text);
+            if new_chunks.is_empty() {
+                summary.skipped += 1;
+                continue;
+            }
+            summary.documents += 1;
+            summary.chunks += new_chunks.len();
+            chunks.retain(|c| c.source != source);
+            chunks.extend(new_chunks);
+        }
+        self.persist(&chunks)?;
+        Ok(summary)
+    }
+
+    /// Drops every chunk whose source starts with `prefix`. Returns how many
+    /// were removed.
+    pub fn remove_source(&self, prefix: &str) -> io::Result<usize> {
+        let mut chunks = self.lock();
+        let before = chunks.len();
+        chunks.retain(|c| !c.source.starts_with(prefix));
+        let removed = before - chunks.len();
+        if removed > 0 {
+            self.persist(&chunks)?;
+        }
+        Ok(removed)
+    }
+
+    /// Best-matching chunks for `query`, highest score first.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<KnowledgeHit> {
+        let terms = {
+            let mut terms = tokenize(query);
+            terms.sort();
+            terms.dedup();
+            terms
+        };
+        if terms.is_empty() || limit == 0 {
+            return Vec::new();
+        }
+
+        let chunks = self.lock();
+        let docs: Vec<Vec<String>> = chunks.iter().map(indexed_terms).collect();
+        if docs.is_empty() {
+            return Vec::new();
+        }
+        let avg_len = docs.iter().map(Vec::len).sum::<usize>() as f64 / docs.len() as f64;
+        let total = docs.len() as f64;
+        let idf: HashMap<&str, f64> = terms
+            .iter()
+            .map(|term| {
+                let containing = docs.iter().filter(|doc| doc.contains(term)).count() as f64;
+                let idf = ((total - containing + 0.5) / (containing + 0.5) + 1.0).ln();
+                (term.as_str(), idf)
+            })
+            .collect();
+
+        let mut hits: Vec<KnowledgeHit> = chunks
+            .iter()
+            .zip(&docs)
+            .filter_map(|(chunk, doc)| {
+                let len = doc.len() as f64;
+                let score: f64 = terms
+                    .iter()
+                    .map(|term| {
+                        let tf = doc.iter().filter(|t| *t == term).count() as f64;
+                        if tf == 0.0 {
+                            return 0.0;
+                        }
+                        let norm = BM25_K1 * (1.0 - BM25_B + BM25_B * len / avg_len.max(1.0));
+                        idf[term.as_str()] * tf * (BM25_K1 + 1.0) / (tf + norm)
+                    })
+                    .sum();
+                (score > 0.0).then(|| KnowledgeHit {
+                    chunk: chunk.clone(),
+                    score,
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(limit);
+        hits
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<KnowledgeChunk>> {
+        self.chunks
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn persist(&self, chunks: &[KnowledgeChunk]) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let payload = serde_json::to_string(chunks)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, payload)?;
+        std::fs::rename(tmp, &self.path)
+    }
+}
+
+/// Downloads a page for import, returning its text with HTML markup removed.
+pub async fn fetch_document(url: &str) -> io::Result<String> {
+    let response = reqwest::get(url)
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(io::Error::other)?;
+    let is_html = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("html"));
+    let body = response.text().await.map_err(io::Error::other)?;
+    Ok(if is_html { strip_html(&body) } else { body })
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let meta = std::fs::metadata(path)?;
+    if meta.is_file() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_files(&entry.path(), files)?;
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// Text of a documentation file, or `None` if it is not one we can read.
+fn read_document(path: &Path) -> Option<String> {
+    if std::fs::metadata(path).ok()?.len() > MAX_FILE_BYTES {
+        return None;
+    }
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    let is_man = ext
+        .as_deref()
+        .is_some_and(|e| e.len() == 1 && e.chars().all(|c| c.is_ascii_digit()));
+    if !is_man && !ext.as_deref().is_some_and(|e| TEXT_EXTENSIONS.contains(&e)) {
+        return None;
+    }
+    let raw = std::fs::read_to_string(path).ok()?;
+    Some(match ext.as_deref() {
+        Some("html" | "htm") => strip_html(&raw),
+        _ if is_man => strip_roff(&raw),
+        _ => raw,
+    })
+}
+
+/// Splits a document into sections at markdown headings (or man page
+/// sections), then splits long sections at paragraph breaks.
+fn chunk_document(source: &str, text: &str) -> Vec<KnowledgeChunk> {
+    let mut sections: Vec<(Option<String>, String)> = vec![(None, String::new())];
+    for line in text.lines() {
+        if let Some(heading) = heading_of(line) {
+            sections.push((Some(heading), String::new()));
+            continue;
+        }
+        if let Some((_, body)) = sections.last_mut() {
+            body.push_str(line);
+            body.push('\n');
+        }
+    }
+
+    let mut chunks = Vec::new();
+    for (heading, body) in sections {
+        let mut current = String::new();
+        for paragraph in body.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+            if !current.is_empty() && current.len() + paragraph.len() > MAX_CHUNK_CHARS {
+                chunks.push(KnowledgeChunk {
+                    source: source.to_string(),
+                    heading: heading.clone(),
+                    text: std::mem::take(&mut current),
+                });
+            }
+            if !current.is_empty() {
+                current.push_str("\n\n");
+            }
+            current.push_str(paragraph);
+        }
+        if !current.is_empty() {
+            chunks.push(KnowledgeChunk {
+                source: source.to_string(),
+                heading,
+                text: current,
+            });
+        }
+    }
+    chunks
+}
+
+fn heading_of(line: &str) -> Option<String> {
+    let trimmed = line.trim_start();
+    if let Some(rest) = trimmed.strip_prefix('#') {
+        let title = rest.trim_start_matches('#');
+        if title.starts_with(' ') {
+            return Some(title.trim().to_string()).filter(|t| !t.is_empty());
+        }
+    }
+    trimmed
+        .strip_prefix(".SH ")
+        .or_else(|| trimmed.strip_prefix(".SS "))
+        .map(|title| title.trim().trim_matches('"').to_string())
+        .filter(|t| !t.is_empty())
+}
+
+/// Plain text of a man page source, keeping `.SH`/`.SS` lines as headings.
+fn strip_roff(raw: &str) -> String {
+    let mut out = String::new();
+    for line in raw.lines() {
+        if line.starts_with(".SH ") || line.starts_with(".SS ") {
+            out.push_str(line);
+            out.push('\n');
+        } else if line.starts_with(".PP") || line == ".P" {
+            out.push('\n');
+        } else if let Some(rest) = line
+            .strip_prefix(".B ")
+            .or_else(|| line.strip_prefix(".I "))
+        {
+            out.push_str(rest);
+            out.push('\n');
+        } else if line.starts_with('.') || line.starts_with('\'') {
+            continue;
+        } else {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out.replace("\\fB", "")
+        .replace("\\fI", "")
+        .replace("\\fR", "")
+        .replace("\\fP", "")
+        .replace("\\-", "-")
+        .replace("\\(em", "—")
+}
+
+fn strip_html(raw: &str) -> String {
+    let mut out = String::new();
+    let mut in_tag = false;
+    let mut tag = String::new();
+    let mut skipping = false;
+    for c in raw.chars() {
+        match (in_tag, c) {
+            (false, '<') => {
+                in_tag = true;
+                tag.clear();
+            }
+            (true, '>') => {
+                in_tag = false;
+                let name = tag
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .to_ascii_lowercase();
+                match name.as_str() {
+                    "script" | "style" => skipping = true,
+                    "/script" | "/style" => skipping = false,
+                    "h1" | "h2" | "h3" | "h4" => out.push_str("\n\n# "),
+                    "p" | "/p" | "br" | "br/" | "li" | "/h1" | "/h2" | "/h3" | "/h4" | "pre"
+                    | "/pre" | "tr" | "div" | "/div" => out.push_str("\n\n"),
+                    _ => {}
+                }
+            }
+            (true, c) => tag.push(c),
+            (false, c) if !skipping => out.push(c),
+            _ => {}
+        }
+    }
+    out.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// Lowercased words, keeping `-`, `_` and `.` inside them so flags and
+/// option names such as `--no-sandbox` or `general.gaps_in` stay whole.
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        .map(|word| word.trim_matches(|c| matches!(c, '-' | '.')))
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Terms a chunk is found by; the heading counts twice.
+fn indexed_terms(chunk: &KnowledgeChunk) -> Vec<String> {
+    let mut terms = tokenize(&chunk.text);
+    if let Some(heading) = &chunk.heading {
+        let heading_terms = tokenize(heading);
+        terms.extend(heading_terms.iter().cloned());
+        terms.extend(heading_terms);
+    }
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn import_chunks_by_heading_and_search_ranks_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        let docs = dir.path().join("docs");
+        std::fs::create_dir_all(docs.join("wiki")).unwrap();
+        std::fs::write(
+            docs.join("wiki/variables.md"),
+            "# General\n\nSet gaps_in and gaps_out for spacing.\n\n## Decoration\n\nrounding sets corner radius in px.\n",
+        )
+        .unwrap();
+        std::fs::write(
+            docs.join("grim.1"),
+            ".TH GRIM 1\n.SH OPTIONS\n\\fB\\-g\\fR \\fIgeometry\\fR\nCapture a region.\n",
+        )
+        .unwrap();
+        std::fs::write(docs.join("image.png"), [0u8, 159, 146, 150]).unwrap();
+
+        let store = KnowledgeStore::open(dir.path().join("knowledge.json")).unwrap();
+        let summary = store.import_path(&docs).unwrap();
+        assert_eq!(summary.documents, 2);
+        assert_eq!(summary.chunks, 3);
+        assert_eq!(summary.skipped, 1);
+
+        let hits = store.search("corner rounding", 5);
+        assert_eq!(hits[0].chunk.heading.as_deref(), Some("Decoration"));
+        assert!(hits[0].chunk.source.ends_with("variables.md"));
+
+        let hits = store.search("grim -g region", 1);
+        assert_eq!(hits[0].chunk.heading.as_deref(), Some("OPTIONS"));
+        assert!(hits[0].chunk.text.starts_with("-g geometry"));
+        assert!(store.search("bluetooth", 5).is_empty());
+
+        // Re-importing replaces a source's chunks instead of duplicating them.
+        store.import_path(&docs.join("grim.1")).unwrap();
+        let reopened = KnowledgeStore::open(dir.path().join("knowledge.json")).unwrap();
+        assert_eq!(reopened.sources().len(), 2);
+        assert_eq!(
+            reopened.remove_source(&docs.display().to_string()).unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn long_sections_split_at_paragraphs() {
+        let paragraph = "word ".repeat(200);
+        let text = format!("# Big\n\n{paragraph}\n\n{paragraph}\n\n{paragraph}");
+        let chunks = chunk_document("big.md", &text);
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|c| c.heading.as_deref() == Some("Big")));
+
+        let html = strip_html("<h2>Binds</h2><script>x()</script><p>exec, kitty &amp; more</p>");
+        let chunks = chunk_document("binds.html", &html);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].heading.as_deref(), Some("Binds"));
+        assert_eq!(chunks[0].text, "exec, kitty & more");
+    }
+}
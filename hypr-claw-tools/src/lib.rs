@@ -1,18 +1,36 @@
+pub mod approvals;
 pub mod audit_adapter;
+pub mod calc;
+pub mod contacts;
+#[cfg(feature = "tool-contracts")]
+pub mod contracts;
 pub mod dispatcher;
+pub mod duplicates;
 pub mod error;
 pub mod execution_context;
+pub mod forms;
+pub mod knowledge;
+pub mod natural_time;
 pub mod os_capabilities;
 pub mod os_tools;
 pub mod permission_adapter;
+pub mod plugins;
+pub mod questions;
 pub mod registry;
+pub mod reminders;
+pub mod rename;
 pub mod sandbox;
+pub mod skills;
+pub mod snippets;
+pub mod staging;
+pub mod tabular;
 pub mod tools;
 pub mod traits;
+pub mod window_snapshot;
 
 pub use dispatcher::ToolDispatcherImpl;
 pub use error::ToolError;
-pub use execution_context::ExecutionContext;
+pub use execution_context::{Capabilities, ExecutionContext, NetworkAccess};
 pub use registry::ToolRegistryImpl;
 pub use tools::{Tool, ToolResult};
 pub use traits::{
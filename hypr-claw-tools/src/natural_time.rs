@@ -0,0 +1,652 @@
+//! Natural-language times for `time.parse` / `time.format`, resolved against
+//! a clock and time zone the caller passes in, so the same text at the same
+//! moment always gives the same answer.
+//!
+//! Rules that usually cause off-by-one-day mistakes:
+//! - a weekday alone or with `this` is its next occurrence still ahead, which
+//!   is today only while the requested time has not passed; `next <weekday>`
+//!   is never today, and `last <weekday>` is never today either;
+//! - a time without a day is today if still ahead, otherwise tomorrow;
+//! - a date without a year is this year if still ahead, otherwise next year;
+//! - a day without a time is 09:00, reported as an assumed time;
+//! - a bare hour from 1 to 12 (`at 3`) is refused as ambiguous;
+//! - numeric dates (`03/04`) follow the locale's day/month order unless one
+//!   part is over 12.
+
+use chrono::{
+    DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Weekday,
+};
+
+/// Date and clock conventions of a locale such as `en_US.UTF-8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeLocale {
+    /// Numeric dates are day first (`23/10`) rather than month first.
+    pub day_first: bool,
+    /// Times are shown with AM/PM.
+    pub clock_12h: bool,
+}
+
+impl TimeLocale {
+    pub fn from_name(locale: &str) -> Self {
+        let tag = locale.split(['.', '@']).next().unwrap_or_default();
+        Self {
+            day_first: !matches!(tag, "en_US" | "es_US" | "en_PH"),
+            clock_12h: matches!(
+                tag,
+                "en_US" | "es_US" | "en_PH" | "en_CA" | "en_AU" | "en_NZ" | "en_IN"
+            ),
+        }
+    }
+
+    /// The locale for dates and times from `LC_ALL`, `LC_TIME` or `LANG`.
+    pub fn system() -> Self {
+        let name = ["LC_ALL", "LC_TIME", "LANG"]
+            .into_iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        Self::from_name(&name)
+    }
+}
+
+/// Name of the system time zone, e.g. `Europe/Berlin`, from `TZ` or the
+/// `/etc/localtime` link.
+pub fn zone_name() -> Option<String> {
+    if let Ok(tz) = std::env::var("TZ") {
+        let tz = tz.trim_start_matches(':');
+        if !tz.is_empty() {
+            return Some(tz.to_string());
+        }
+    }
+    let target = std::fs::read_link("/etc/localtime").ok()?;
+    let target = target.to_string_lossy();
+    let (_, name) = target.split_once("zoneinfo/")?;
+    Some(name.to_string())
+}
+
+/// A parsed time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolved<Tz: TimeZone> {
+    pub at: DateTime<Tz>,
+    /// No time of day was given, so 09:00 was used.
+    pub assumed_time: bool,
+}
+
+/// Time of day used when only a day is given.
+const DEFAULT_TIME: (u32, u32) = (9, 0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WeekdayMode {
+    Upcoming,
+    Next,
+    Last,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DaySpec {
+    /// Days from today.
+    Relative(i64),
+    Date(NaiveDate),
+    /// Month and day without a year.
+    MonthDay(u32, u32),
+    Weekday(Weekday, WeekdayMode),
+}
+
+#[derive(Debug, Default)]
+struct Parts {
+    day: Option<DaySpec>,
+    time: Option<NaiveTime>,
+    /// Offset from now, e.g. `in 90 minutes` or `2 days ago`.
+    offset: Option<Duration>,
+    exact_now: bool,
+}
+
+/// Resolves `text` ("next friday 3pm", "tomorrow morning", "in 90 minutes",
+/// "oct 23 at 8:15pm", "2026-10-23 15:00") relative to `now`.
+pub fn parse<Tz: TimeZone>(
+    text: &str,
+    now: &DateTime<Tz>,
+    locale: TimeLocale,
+) -> Result<Resolved<Tz>, String> {
+    let lower = text.to_lowercase().replace(',', " ");
+    let words: Vec<&str> = lower
+        .split_whitespace()
+        .filter(|w| !matches!(*w, "at" | "on" | "the" | "of"))
+        .collect();
+    if words.is_empty() {
+        return Err("empty time".to_string());
+    }
+    let parts = parse_parts(&words, locale)?;
+    resolve(parts, now).ok_or_else(|| format!("'{text}' does not name a valid time"))
+}
+
+fn parse_parts(words: &[&str], locale: TimeLocale) -> Result<Parts, String> {
+    let mut parts = Parts::default();
+    let mut i = 0;
+    let set_day = |parts: &mut Parts, day: DaySpec| {
+        if parts.day.replace(day).is_some() {
+            return Err("more than one day given".to_string());
+        }
+        Ok(())
+    };
+    let set_time = |parts: &mut Parts, time: NaiveTime| {
+        if parts.time.replace(time).is_some() {
+            return Err("more than one time of day given".to_string());
+        }
+        Ok(())
+    };
+    while i < words.len() {
+        let word = words[i];
+        let next = words.get(i + 1).copied();
+        i += 1;
+        match word {
+            "now" => parts.exact_now = true,
+            "today" => set_day(&mut parts, DaySpec::Relative(0))?,
+            "tomorrow" => set_day(&mut parts, DaySpec::Relative(1))?,
+            "yesterday" => set_day(&mut parts, DaySpec::Relative(-1))?,
+            "tonight" => {
+                set_day(&mut parts, DaySpec::Relative(0))?;
+                set_time(&mut parts, hm(20, 0))?;
+            }
+            "morning" => set_time(&mut parts, hm(9, 0))?,
+            "noon" | "midday" => set_time(&mut parts, hm(12, 0))?,
+            "afternoon" => set_time(&mut parts, hm(15, 0))?,
+            "evening" => set_time(&mut parts, hm(18, 0))?,
+            "midnight" => set_time(&mut parts, hm(0, 0))?,
+            "this" | "next" | "last" => {
+                let target = next.ok_or_else(|| format!("'{word}' needs a weekday"))?;
+                i += 1;
+                let day = match (word, target) {
+                    ("next", "week") => DaySpec::Relative(7),
+                    ("last", "week") => DaySpec::Relative(-7),
+                    (_, target) => {
+                        let weekday = weekday(target)
+                            .ok_or_else(|| format!("'{word} {target}' is not a weekday"))?;
+                        let mode = match word {
+                            "next" => WeekdayMode::Next,
+                            "last" => WeekdayMode::Last,
+                            _ => WeekdayMode::Upcoming,
+                        };
+                        DaySpec::Weekday(weekday, mode)
+                    }
+                };
+                set_day(&mut parts, day)?;
+            }
+            "in" => {
+                let (offset, used) = durations(&words[i..])?;
+                i += used;
+                parts.offset = Some(offset);
+            }
+            _ if weekday(word).is_some() => {
+                let weekday = weekday(word).unwrap_or(Weekday::Mon);
+                set_day(&mut parts, DaySpec::Weekday(weekday, WeekdayMode::Upcoming))?;
+            }
+            _ if month(word).is_some() => {
+                let month = month(word).unwrap_or(1);
+                let day = next
+                    .and_then(day_of_month)
+                    .ok_or_else(|| format!("'{word}' needs a day of the month"))?;
+                i += 1;
+                set_day(&mut parts, with_year(month, day, words.get(i), &mut i)?)?;
+            }
+            _ if day_of_month(word).is_some() && next.and_then(month).is_some() => {
+                let day = day_of_month(word).unwrap_or(1);
+                let month = next.and_then(month).unwrap_or(1);
+                i += 1;
+                set_day(&mut parts, with_year(month, day, words.get(i), &mut i)?)?;
+            }
+            _ if word.chars().next().is_some_and(|c| c.is_ascii_digit()) => {
+                if let Some(day) = numeric_date(word, locale)? {
+                    set_day(&mut parts, day)?;
+                } else if let Some((offset, used)) = durations(&words[i - 1..])
+                    .ok()
+                    .filter(|_| matches!(words.get(i + 1), Some(&"ago") | Some(&"from")))
+                {
+                    i += used - 1;
+                    let ago = words.get(i) == Some(&"ago");
+                    i += if ago { 1 } else { 2 };
+                    parts.offset = Some(if ago { -offset } else { offset });
+                } else {
+                    let meridiem = next.filter(|n| matches!(*n, "am" | "pm" | "a.m." | "p.m."));
+                    if meridiem.is_some() {
+                        i += 1;
+                    }
+                    set_time(&mut parts, clock_time(word, meridiem)?)?;
+                }
+            }
+            other => return Err(format!("'{other}' is not understood")),
+        }
+    }
+    Ok(parts)
+}
+
+fn hm(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap_or(NaiveTime::MIN)
+}
+
+fn weekday(word: &str) -> Option<Weekday> {
+    Some(match word {
+        "monday" | "mon" => Weekday::Mon,
+        "tuesday" | "tue" | "tues" => Weekday::Tue,
+        "wednesday" | "wed" => Weekday::Wed,
+        "thursday" | "thu" | "thurs" => Weekday::Thu,
+        "friday" | "fri" => Weekday::Fri,
+        "saturday" | "sat" => Weekday::Sat,
+        "sunday" | "sun" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+fn month(word: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "january",
+        "february",
+        "march",
+        "april",
+        "may",
+        "june",
+        "july",
+        "august",
+        "september",
+        "october",
+        "november",
+        "december",
+    ];
+    let word = word.trim_end_matches('.');
+    MONTHS
+        .iter()
+        .position(|m| *m == word || (word.len() >= 3 && m.starts_with(word)))
+        .map(|i| i as u32 + 1)
+}
+
+/// `23`, `23rd`, `1st`.
+fn day_of_month(word: &str) -> Option<u32> {
+    let digits = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let suffix = &word[digits.len()..];
+    if !matches!(suffix, "" | "st" | "nd" | "rd" | "th") {
+        return None;
+    }
+    digits.parse().ok().filter(|d| (1..=31).contains(d))
+}
+
+/// Month and day, with the year that follows them if there is one.
+fn with_year(month: u32, day: u32, next: Option<&&str>, i: &mut usize) -> Result<DaySpec, String> {
+    match next
+        .and_then(|w| w.parse::<i32>().ok())
+        .filter(|y| *y >= 1000)
+    {
+        Some(year) => {
+            *i += 1;
+            NaiveDate::from_ymd_opt(year, month, day)
+                .map(DaySpec::Date)
+                .ok_or_else(|| format!("{year}-{month:02}-{day:02} is not a date"))
+        }
+        None => Ok(DaySpec::MonthDay(month, day)),
+    }
+}
+
+/// `2026-10-23`, or `23/10`, `23.10.2026` and `10/23/2026` in the locale's
+/// order; `None` when `word` is not a date.
+fn numeric_date(word: &str, locale: TimeLocale) -> Result<Option<DaySpec>, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(word, "%Y-%m-%d") {
+        return Ok(Some(DaySpec::Date(date)));
+    }
+    let fields: Vec<&str> = word.split(['/', '.']).collect();
+    if fields.len() < 2 || fields.len() > 3 || word.contains(':') {
+        return Ok(None);
+    }
+    let numbers: Vec<u32> = match fields.iter().map(|f| f.parse()).collect() {
+        Ok(numbers) => numbers,
+        Err(_) => return Ok(None),
+    };
+    let (first, second) = (numbers[0], numbers[1]);
+    let day_first = if first > 12 {
+        true
+    } else if second > 12 {
+        false
+    } else {
+        locale.day_first
+    };
+    let (day, month) = if day_first {
+        (first, second)
+    } else {
+        (second, first)
+    };
+    let invalid = || format!("'{word}' is not a date");
+    match numbers.get(2) {
+        Some(&year) => {
+            let year = if year < 100 { 2000 + year } else { year };
+            let year = i32::try_from(year).map_err(|_| invalid())?;
+            NaiveDate::from_ymd_opt(year, month, day)
+                .map(|d| Some(DaySpec::Date(d)))
+                .ok_or_else(invalid)
+        }
+        None => {
+            NaiveDate::from_ymd_opt(2000, month, day).ok_or_else(invalid)?;
+            Ok(Some(DaySpec::MonthDay(month, day)))
+        }
+    }
+}
+
+/// `3pm`, `3:30pm`, `3 pm` (with `meridiem`), `15:00`, `15`.
+fn clock_time(word: &str, meridiem: Option<&str>) -> Result<NaiveTime, String> {
+    let (digits, suffix) = match word.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(at) => (&word[..at], Some(&word[at..])),
+        None => (word, None),
+    };
+    let meridiem = suffix.or(meridiem).map(|m| m.replace('.', ""));
+    let (hour, minute) = match digits.split_once(':') {
+        Some((h, m)) => (h.parse::<u32>(), m.parse::<u32>()),
+        None => (digits.parse::<u32>(), Ok(0)),
+    };
+    let (Ok(hour), Ok(minute)) = (hour, minute) else {
+        return Err(format!("'{word}' is not a time"));
+    };
+    let hour = match meridiem.as_deref() {
+        Some("am") if (1..=12).contains(&hour) => hour % 12,
+        Some("pm") if (1..=12).contains(&hour) => hour % 12 + 12,
+        Some(_) => return Err(format!("'{word}' is not a time")),
+        None if (1..=12).contains(&hour) && !digits.contains(':') => {
+            return Err(format!("'{word}' is ambiguous; say {hour}am or {hour}pm"));
+        }
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0).ok_or_else(|| format!("'{word}' is not a time"))
+}
+
+/// `2 hours 30 minutes`, `90m`, `3 days`: the total and the words used.
+fn durations(words: &[&str]) -> Result<(Duration, usize), String> {
+    let mut total = Duration::zero();
+    let mut used = 0;
+    while used < words.len() {
+        let word = words[used];
+        let (amount, unit, width) = match word.find(|c: char| c.is_ascii_alphabetic()) {
+            Some(at) if at > 0 => (&word[..at], &word[at..], 1),
+            None => match words.get(used + 1) {
+                Some(unit) => (word, *unit, 2),
+                None => break,
+            },
+            Some(_) => break,
+        };
+        let (Ok(amount), Some(seconds)) = (amount.parse::<i64>(), unit_seconds(unit)) else {
+            break;
+        };
+        total += Duration::seconds(amount.saturating_mul(seconds));
+        used += width;
+        if words.get(used) == Some(&"and") {
+            used += 1;
+        }
+    }
+    if used == 0 {
+        return Err("expected a duration like '90 minutes' or '2 days'".to_string());
+    }
+    Ok((total, used))
+}
+
+fn unit_seconds(unit: &str) -> Option<i64> {
+    Some(match unit {
+        "s" | "sec" | "secs" | "second" | "seconds" => 1,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60,
+        "h" | "hr" | "hrs" | "hour" | "hours" => 3600,
+        "d" | "day" | "days" => 86_400,
+        "w" | "wk" | "week" | "weeks" => 604_800,
+        _ => return None,
+    })
+}
+
+fn resolve<Tz: TimeZone>(parts: Parts, now: &DateTime<Tz>) -> Option<Resolved<Tz>> {
+    let tz = now.timezone();
+    let today = now.date_naive();
+    let local = |date: NaiveDate, time: NaiveTime| -> Option<DateTime<Tz>> {
+        let naive = NaiveDateTime::new(date, time);
+        // A time inside a DST gap moves to the hour after.
+        tz.from_local_datetime(&naive).earliest().or_else(|| {
+            tz.from_local_datetime(&(naive + Duration::hours(1)))
+                .earliest()
+        })
+    };
+
+    if let Some(offset) = parts.offset {
+        let whole_days = offset.num_seconds() % 86_400 == 0;
+        return match (parts.day, parts.time) {
+            (None, None) => Some(Resolved {
+                at: now.clone() + offset,
+                assumed_time: false,
+            }),
+            (None, Some(time)) if whole_days => Some(Resolved {
+                at: local(today + offset, time)?,
+                assumed_time: false,
+            }),
+            _ => None,
+        };
+    }
+    if parts.exact_now && parts.day.is_none() && parts.time.is_none() {
+        return Some(Resolved {
+            at: now.clone(),
+            assumed_time: false,
+        });
+    }
+
+    let assumed_time = parts.time.is_none();
+    let time = parts
+        .time
+        .unwrap_or_else(|| hm(DEFAULT_TIME.0, DEFAULT_TIME.1));
+    let ahead = |date: NaiveDate| local(date, time).is_some_and(|at| at > *now);
+    let date = match parts.day {
+        None if ahead(today) => today,
+        None => today.succ_opt()?,
+        Some(DaySpec::Relative(days)) => today + Duration::days(days),
+        Some(DaySpec::Date(date)) => date,
+        Some(DaySpec::MonthDay(month, day)) => {
+            let this_year = NaiveDate::from_ymd_opt(today.year(), month, day);
+            match this_year.filter(|d| *d > today || (*d == today && ahead(*d))) {
+                Some(date) => date,
+                None => {
+                    (1..=8).find_map(|y| NaiveDate::from_ymd_opt(today.year() + y, month, day))?
+                }
+            }
+        }
+        Some(DaySpec::Weekday(weekday, mode)) => {
+            let days_ahead = (7 + weekday.num_days_from_monday() as i64
+                - today.weekday().num_days_from_monday() as i64)
+                % 7;
+            match mode {
+                WeekdayMode::Upcoming if days_ahead == 0 && !ahead(today) => {
+                    today + Duration::days(7)
+                }
+                WeekdayMode::Upcoming => today + Duration::days(days_ahead),
+                WeekdayMode::Next if days_ahead == 0 => today + Duration::days(7),
+                WeekdayMode::Next => today + Duration::days(days_ahead),
+                WeekdayMode::Last if days_ahead == 0 => today - Duration::days(7),
+                WeekdayMode::Last => today - Duration::days(7 - days_ahead),
+            }
+        }
+    };
+    Some(Resolved {
+        at: local(date, time)?,
+        assumed_time,
+    })
+}
+
+/// How [`format`] renders a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// Weekday, date and time.
+    Full,
+    Date,
+    Time,
+    /// `in 3 days`, `2 hours ago`.
+    Relative,
+}
+
+impl Style {
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "full" => Self::Full,
+            "date" => Self::Date,
+            "time" => Self::Time,
+            "relative" => Self::Relative,
+            _ => return None,
+        })
+    }
+}
+
+/// `at` in the locale's conventions, e.g. `Fri, Oct 23, 2026, 3:00 PM` for
+/// `en_US` and `Fri 23 Oct 2026, 15:00` elsewhere.
+pub fn format<Tz: TimeZone>(
+    at: &DateTime<Tz>,
+    style: Style,
+    locale: TimeLocale,
+    now: &DateTime<Tz>,
+) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let date = if locale.day_first {
+        "%a %-d %b %Y"
+    } else {
+        "%a, %b %-d, %Y"
+    };
+    let time = if locale.clock_12h {
+        "%-I:%M %p"
+    } else {
+        "%H:%M"
+    };
+    match style {
+        Style::Full => at.format(&format!("{date}, {time}")).to_string(),
+        Style::Date => at.format(date).to_string(),
+        Style::Time => at.format(time).to_string(),
+        Style::Relative => relative(at.clone().signed_duration_since(now.clone())),
+    }
+}
+
+/// `in 3 days`, `2 hours ago`, `now`, in the largest whole unit.
+pub fn relative(delta: Duration) -> String {
+    let seconds = delta.num_seconds();
+    let magnitude = seconds.unsigned_abs();
+    let (amount, unit) = [
+        (604_800, "week"),
+        (86_400, "day"),
+        (3600, "hour"),
+        (60, "minute"),
+    ]
+    .into_iter()
+    .find(|(size, _)| magnitude >= *size)
+    .map(|(size, unit)| (magnitude / size, unit))
+    .unwrap_or((0, "minute"));
+    if amount == 0 {
+        return "now".to_string();
+    }
+    let plural = if amount == 1 { "" } else { "s" };
+    if seconds > 0 {
+        format!("in {amount} {unit}{plural}")
+    } else {
+        format!("{amount} {unit}{plural} ago")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    const GB: TimeLocale = TimeLocale {
+        day_first: true,
+        clock_12h: false,
+    };
+    const US: TimeLocale = TimeLocale {
+        day_first: false,
+        clock_12h: true,
+    };
+
+    /// Sunday 2026-10-18 14:30 at +02:00.
+    fn now() -> DateTime<FixedOffset> {
+        FixedOffset::east_opt(2 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2026, 10, 18, 14, 30, 0)
+            .unwrap()
+    }
+
+    fn at(text: &str, locale: TimeLocale) -> String {
+        let resolved = parse(text, &now(), locale).unwrap();
+        let at = resolved.at.format("%Y-%m-%d %H:%M").to_string();
+        if resolved.assumed_time {
+            format!("{at}?")
+        } else {
+            at
+        }
+    }
+
+    #[test]
+    fn weekdays_resolve_without_off_by_one_days() {
+        assert_eq!(at("next Friday 3pm", GB), "2026-10-23 15:00");
+        assert_eq!(at("friday", GB), "2026-10-23 09:00?");
+        assert_eq!(at("sunday", GB), "2026-10-25 09:00?");
+        assert_eq!(at("this sunday 6pm", GB), "2026-10-18 18:00");
+        assert_eq!(at("next sunday 6pm", GB), "2026-10-25 18:00");
+        assert_eq!(at("last friday", GB), "2026-10-16 09:00?");
+        assert_eq!(at("last sunday at noon", GB), "2026-10-11 12:00");
+        assert_eq!(at("next week", GB), "2026-10-25 09:00?");
+    }
+
+    #[test]
+    fn times_and_relative_days() {
+        assert_eq!(at("3pm", GB), "2026-10-18 15:00");
+        assert_eq!(at("2:15 p.m.", GB), "2026-10-19 14:15");
+        assert_eq!(at("midnight", GB), "2026-10-19 00:00");
+        assert_eq!(at("tomorrow morning", GB), "2026-10-19 09:00");
+        assert_eq!(at("tonight", GB), "2026-10-18 20:00");
+        assert_eq!(at("yesterday 17:45", GB), "2026-10-17 17:45");
+        assert_eq!(at("in 90 minutes", GB), "2026-10-18 16:00");
+        assert_eq!(at("in 1h 15m", GB), "2026-10-18 15:45");
+        assert_eq!(at("in 2 days at noon", GB), "2026-10-20 12:00");
+        assert_eq!(at("3 hours ago", GB), "2026-10-18 11:30");
+        assert_eq!(at("2 days from now", GB), "2026-10-20 14:30");
+        assert_eq!(at("now", GB), "2026-10-18 14:30");
+    }
+
+    #[test]
+    fn dates_follow_the_locale_and_roll_to_next_year() {
+        assert_eq!(at("23/10", GB), "2026-10-23 09:00?");
+        assert_eq!(at("10/23", US), "2026-10-23 09:00?");
+        assert_eq!(at("03/04/2027 8am", US), "2027-03-04 08:00");
+        assert_eq!(at("03/04/2027 8am", GB), "2027-04-03 08:00");
+        assert_eq!(at("oct 5", GB), "2027-10-05 09:00?");
+        assert_eq!(at("October 23rd at 8:15pm", US), "2026-10-23 20:15");
+        assert_eq!(at("23 october 2026 15:00", GB), "2026-10-23 15:00");
+        assert_eq!(at("2026-12-24 18:00", GB), "2026-12-24 18:00");
+    }
+
+    #[test]
+    fn ambiguous_or_unknown_text_is_refused() {
+        for text in [
+            "at 3",
+            "",
+            "whenever",
+            "31/02",
+            "friday saturday",
+            "in 2 hours at 5pm",
+            "13pm",
+        ] {
+            assert!(parse(text, &now(), GB).is_err(), "{text:?} should fail");
+        }
+    }
+
+    #[test]
+    fn formatting_follows_the_locale() {
+        let at = parse("next friday 3pm", &now(), GB).unwrap().at;
+        assert_eq!(
+            format(&at, Style::Full, US, &now()),
+            "Fri, Oct 23, 2026, 3:00 PM"
+        );
+        assert_eq!(
+            format(&at, Style::Full, GB, &now()),
+            "Fri 23 Oct 2026, 15:00"
+        );
+        assert_eq!(format(&at, Style::Relative, GB, &now()), "in 5 days");
+        assert_eq!(relative(Duration::minutes(-150)), "2 hours ago");
+        assert_eq!(relative(Duration::seconds(20)), "now");
+        assert_eq!(TimeLocale::from_name("en_US.UTF-8"), US);
+        assert_eq!(TimeLocale::from_name("de_DE.UTF-8"), GB);
+    }
+}
@@ -0,0 +1,221 @@
+//! SQLite access - parameterized, single-statement queries with result caps
+
+use super::{OsError, OsResult};
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{Connection, OpenFlags};
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Longest text/blob cell returned inline before truncation.
+pub const MAX_CELL_BYTES: usize = 2048;
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct QueryOutput {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+    /// True when more rows were available than `max_rows`.
+    pub truncated: bool,
+}
+
+/// Reject SQL that embeds string literals or chains several statements.
+/// Values must be passed as `?` parameters.
+pub fn validate_parameterized(sql: &str) -> OsResult<()> {
+    if sql.trim().is_empty() {
+        return Err(OsError::InvalidArgument("sql is empty".to_string()));
+    }
+    if sql.contains('\'') {
+        return Err(OsError::InvalidArgument(
+            "string literals are not allowed; use ? placeholders and pass values in params"
+                .to_string(),
+        ));
+    }
+    let body = sql.trim().trim_end_matches(';');
+    if body.contains(';') {
+        return Err(OsError::InvalidArgument(
+            "only a single statement is allowed".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn to_sql_params(params: &[Value]) -> OsResult<Vec<SqlValue>> {
+    params
+        .iter()
+        .map(|p| match p {
+            Value::Null => Ok(SqlValue::Null),
+            Value::Bool(b) => Ok(SqlValue::Integer(i64::from(*b))),
+            Value::Number(n) => Ok(n
+                .as_i64()
+                .map(SqlValue::Integer)
+                .unwrap_or_else(|| SqlValue::Real(n.as_f64().unwrap_or_default()))),
+            Value::String(s) => Ok(SqlValue::Text(s.clone())),
+            other => Err(OsError::InvalidArgument(format!(
+                "unsupported parameter value {other}"
+            ))),
+        })
+        .collect()
+}
+
+fn cell_to_json(value: ValueRef<'_>) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(i) => json!(i),
+        ValueRef::Real(f) => json!(f),
+        ValueRef::Text(bytes) => {
+            let text = String::from_utf8_lossy(bytes);
+            if text.len() > MAX_CELL_BYTES {
+                let mut cut = MAX_CELL_BYTES;
+                while !text.is_char_boundary(cut) {
+                    cut -= 1;
+                }
+                json!(format!("{}… ({} bytes)", &text[..cut], text.len()))
+            } else {
+                json!(text)
+            }
+        }
+        ValueRef::Blob(bytes) => json!(format!("<blob {} bytes>", bytes.len())),
+    }
+}
+
+fn open(path: &Path, writable: bool) -> OsResult<Connection> {
+    if !path.exists() {
+        return Err(OsError::NotFound(path.display().to_string()));
+    }
+    let flags = if writable {
+        OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX
+    } else {
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX
+    };
+    Connection::open_with_flags(path, flags).map_err(|e| OsError::OperationFailed(e.to_string()))
+}
+
+/// Run a read-only query. The database is opened read-only and the statement
+/// must also be read-only according to SQLite.
+pub fn query(path: &Path, sql: &str, params: &[Value], max_rows: usize) -> OsResult<QueryOutput> {
+    validate_parameterized(sql)?;
+    let conn = open(path, false)?;
+    let mut stmt = conn
+        .prepare(sql)
+        .map_err(|e| OsError::InvalidArgument(e.to_string()))?;
+    if !stmt.readonly() {
+        return Err(OsError::PermissionDenied(
+            "statement modifies the database; use db.execute".to_string(),
+        ));
+    }
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    let params = to_sql_params(params)?;
+    let mut rows = stmt
+        .query(rusqlite::params_from_iter(params))
+        .map_err(|e| OsError::OperationFailed(e.to_string()))?;
+
+    let mut out = Vec::new();
+    let mut truncated = false;
+    while let Some(row) = rows
+        .next()
+        .map_err(|e| OsError::OperationFailed(e.to_string()))?
+    {
+        if out.len() >= max_rows {
+            truncated = true;
+            break;
+        }
+        let mut values = Vec::with_capacity(columns.len());
+        for i in 0..columns.len() {
+            let value = row
+                .get_ref(i)
+                .map_err(|e| OsError::OperationFailed(e.to_string()))?;
+            values.push(cell_to_json(value));
+        }
+        out.push(values);
+    }
+    Ok(QueryOutput {
+        columns,
+        rows: out,
+        truncated,
+    })
+}
+
+/// Run a single modifying statement inside a transaction. Returns affected rows.
+pub fn execute(path: &Path, sql: &str, params: &[Value]) -> OsResult<usize> {
+    validate_parameterized(sql)?;
+    let mut conn = open(path, true)?;
+    let params = to_sql_params(params)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| OsError::OperationFailed(e.to_string()))?;
+    let affected = tx
+        .execute(sql, rusqlite::params_from_iter(params))
+        .map_err(|e| OsError::OperationFailed(e.to_string()))?;
+    tx.commit()
+        .map_err(|e| OsError::OperationFailed(e.to_string()))?;
+    Ok(affected)
+}
+
+/// List tables and views with their CREATE statements.
+pub fn schema(path: &Path) -> OsResult<QueryOutput> {
+    query(
+        path,
+        "SELECT type, name, sql FROM sqlite_master WHERE type IN (?, ?) ORDER BY name",
+        &[json!("table"), json!("view")],
+        1000,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> (tempfile::TempDir, std::path::PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.db");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY, title TEXT, body BLOB);
+             INSERT INTO notes (title, body) VALUES ('a', x'00'), ('b', NULL), ('c', NULL);",
+        )
+        .unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn query_caps_rows_and_binds_params() {
+        let (_dir, path) = fixture();
+        let out = query(&path, "SELECT id, title, body FROM notes", &[], 2).unwrap();
+        assert_eq!(out.columns, vec!["id", "title", "body"]);
+        assert_eq!(out.rows.len(), 2);
+        assert!(out.truncated);
+        assert_eq!(out.rows[0][2], json!("<blob 1 bytes>"));
+
+        let out = query(
+            &path,
+            "SELECT title FROM notes WHERE id > ?",
+            &[json!(2)],
+            10,
+        )
+        .unwrap();
+        assert_eq!(out.rows, vec![vec![json!("c")]]);
+    }
+
+    #[test]
+    fn query_rejects_writes_literals_and_batches() {
+        let (_dir, path) = fixture();
+        assert!(matches!(
+            query(&path, "DELETE FROM notes", &[], 10),
+            Err(OsError::PermissionDenied(_))
+        ));
+        assert!(query(&path, "SELECT * FROM notes WHERE title = 'a'", &[], 10).is_err());
+        assert!(query(&path, "SELECT 1; DROP TABLE notes", &[], 10).is_err());
+    }
+
+    #[test]
+    fn execute_applies_parameterized_writes() {
+        let (_dir, path) = fixture();
+        let affected = execute(
+            &path,
+            "UPDATE notes SET title = ? WHERE id = ?",
+            &[json!("z"), json!(1)],
+        )
+        .unwrap();
+        assert_eq!(affected, 1);
+        assert_eq!(schema(&path).unwrap().rows.len(), 1);
+    }
+}
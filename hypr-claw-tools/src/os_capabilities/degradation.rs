@@ -0,0 +1,170 @@
+//! Ordered fallbacks for desktop tools whose backend may be missing.
+//!
+//! Each chain lists the ways a tool can do its job, best first: the native
+//! backend, an alternate one, then a recipe built on `hyprctl`. The tool
+//! walks its chain itself, skipping backends that are not installed, and
+//! when nothing works reports every step it tried. Fallbacks therefore
+//! behave the same whichever model is driving, instead of depending on it
+//! picking another tool from prompt hints.
+
+use super::{OsError, OsResult};
+use std::future::Future;
+use tokio::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepKind {
+    Native,
+    Alternate,
+    /// Done through `hyprctl` dispatchers or plugins.
+    HyprRecipe,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    pub kind: StepKind,
+    /// Command the step needs on `PATH`.
+    pub backend: &'static str,
+}
+
+const fn native(backend: &'static str) -> Step {
+    Step {
+        kind: StepKind::Native,
+        backend,
+    }
+}
+
+const fn alternate(backend: &'static str) -> Step {
+    Step {
+        kind: StepKind::Alternate,
+        backend,
+    }
+}
+
+const HYPR_RECIPE: Step = Step {
+    kind: StepKind::HyprRecipe,
+    backend: "hyprctl",
+};
+
+/// Degradation chains by tool name; the steps of each are in fallback order.
+pub const CHAINS: &[(&str, &[Step])] = &[
+    (
+        "desktop.capture_screen",
+        &[native("grim"), alternate("hyprshot")],
+    ),
+    ("desktop.key_press", &[native("wtype"), HYPR_RECIPE]),
+    ("desktop.key_combo", &[native("wtype"), HYPR_RECIPE]),
+    (
+        "desktop.mouse_move",
+        &[native("wlrctl"), alternate("ydotool"), HYPR_RECIPE],
+    ),
+    (
+        "desktop.mouse_click",
+        &[native("ydotool"), alternate("wlrctl")],
+    ),
+    (
+        "wallpaper.set",
+        &[native("swww"), alternate("caelestia"), HYPR_RECIPE],
+    ),
+];
+
+/// The chain of `tool`, empty for tools without one.
+pub fn chain(tool: &str) -> &'static [Step] {
+    CHAINS
+        .iter()
+        .find(|(name, _)| *name == tool)
+        .map(|(_, steps)| *steps)
+        .unwrap_or(&[])
+}
+
+/// Whether some step of `tool`'s chain can run with the `installed`
+/// commands; tools without a chain are always usable.
+pub fn usable(tool: &str, installed: impl Fn(&str) -> bool) -> bool {
+    let steps = chain(tool);
+    steps.is_empty() || steps.iter().any(|step| installed(step.backend))
+}
+
+async fn command_exists(command: &str) -> bool {
+    Command::new("which")
+        .arg(command)
+        .output()
+        .await
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// Runs `attempt` with the backend of each installed step of `tool`'s chain
+/// in order and returns the first success with the backend that gave it.
+/// Invalid arguments stop the walk, since no other backend would accept them.
+pub async fn run<T, F, Fut>(tool: &str, mut attempt: F) -> OsResult<(T, &'static str)>
+where
+    F: FnMut(&'static str) -> Fut,
+    Fut: Future<Output = OsResult<T>>,
+{
+    let mut outcomes = Vec::new();
+    for step in chain(tool) {
+        if !command_exists(step.backend).await {
+            outcomes.push((step.backend, "not installed".to_string()));
+            continue;
+        }
+        match attempt(step.backend).await {
+            Ok(value) => {
+                if step.kind != StepKind::Native {
+                    tracing::info!("{} fell back to {}", tool, step.backend);
+                }
+                return Ok((value, step.backend));
+            }
+            Err(e @ OsError::InvalidArgument(_)) => return Err(e),
+            Err(e) => outcomes.push((step.backend, e.to_string())),
+        }
+    }
+    Err(unavailable(tool, &outcomes))
+}
+
+/// The error of a chain where no step worked: `(tried: a, b)` names the
+/// backends for install hints, then what happened to each.
+fn unavailable(tool: &str, outcomes: &[(&str, String)]) -> OsError {
+    let tried = outcomes
+        .iter()
+        .map(|(backend, _)| *backend)
+        .collect::<Vec<_>>()
+        .join(", ");
+    let details = outcomes
+        .iter()
+        .map(|(backend, outcome)| format!("{backend}: {}", outcome.trim()))
+        .collect::<Vec<_>>()
+        .join("; ");
+    OsError::OperationFailed(format!(
+        "No {tool} backend succeeded (tried: {tried}); {details}"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chains_degrade_from_native_to_hyprctl() {
+        for (tool, steps) in CHAINS {
+            assert_eq!(steps[0].kind, StepKind::Native, "{tool}");
+            let kinds = steps.iter().map(|step| step.kind as u8).collect::<Vec<_>>();
+            assert!(kinds.windows(2).all(|w| w[0] <= w[1]), "{tool}");
+        }
+        assert!(chain("fs.read").is_empty());
+        assert!(usable("fs.read", |_| false));
+        assert!(usable("desktop.mouse_move", |backend| backend == "hyprctl"));
+        assert!(!usable("desktop.mouse_click", |backend| backend == "hyprctl"));
+
+        let error = unavailable(
+            "desktop.capture_screen",
+            &[
+                ("grim", "not installed".to_string()),
+                ("hyprshot", "no outputs\n".to_string()),
+            ],
+        );
+        assert_eq!(
+            error.to_string(),
+            "Operation failed: No desktop.capture_screen backend succeeded \
+             (tried: grim, hyprshot); grim: not installed; hyprshot: no outputs"
+        );
+    }
+}
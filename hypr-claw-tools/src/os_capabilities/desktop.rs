@@ -1,6 +1,6 @@
 //! Desktop operations - launching apps, browser ops, and GUI automation.
 
-use super::{OsError, OsResult};
+use super::{degradation, ydotool, OsError, OsResult};
 use serde_json::{json, Value};
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
@@ -562,14 +562,19 @@ pub async fn open_url(url: &str) -> OsResult<()> {
     Ok(())
 }
 
-/// Search the web with a selected search engine.
-pub async fn search_web(query: &str, engine: Option<&str>) -> OsResult<String> {
+/// Results URL for `query` on the selected search engine.
+pub fn search_url(query: &str, engine: Option<&str>) -> String {
     let encoded = encode_query(query);
-    let target = match engine.unwrap_or("duckduckgo").to_lowercase().as_str() {
+    match engine.unwrap_or("duckduckgo").to_lowercase().as_str() {
         "google" => format!("https://www.google.com/search?q={encoded}"),
         "bing" => format!("https://www.bing.com/search?q={encoded}"),
         _ => format!("https://duckduckgo.com/?q={encoded}"),
-    };
+    }
+}
+
+/// Search the web with a selected search engine.
+pub async fn search_web(query: &str, engine: Option<&str>) -> OsResult<String> {
+    let target = search_url(query, engine);
     open_url(&target).await?;
     Ok(target)
 }
@@ -579,29 +584,300 @@ pub async fn open_gmail() -> OsResult<()> {
     open_url("https://mail.google.com").await
 }
 
-/// Type text into the currently focused window.
-pub async fn type_text(text: &str) -> OsResult<()> {
+/// Open a Gmail compose window with prefilled recipient, subject and body.
+pub async fn compose_gmail(to: &str, subject: &str, body: &str) -> OsResult<String> {
+    let target = format!(
+        "https://mail.google.com/mail/?view=cm&fs=1&to={}&su={}&body={}",
+        encode_query(to),
+        encode_query(subject),
+        encode_query(body)
+    );
+    open_url(&target).await?;
+    Ok(target)
+}
+
+/// Open a Telegram chat by username via t.me, which hands off to the desktop client.
+pub async fn open_telegram_chat(username: &str) -> OsResult<String> {
+    let username = username.trim().trim_start_matches('@');
+    if username.is_empty()
+        || !username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err(OsError::InvalidArgument(format!(
+            "invalid telegram username '{username}'"
+        )));
+    }
+    let target = format!("https://t.me/{username}");
+    open_url(&target).await?;
+    Ok(target)
+}
+
+/// Type text into the currently focused window. Text the backend would
+/// type lossily (emoji and CJK through ydotool, non-ASCII into XWayland
+/// windows through wtype) is pasted through the clipboard instead when
+/// wl-copy is available and `clipboard` grants its use.
+pub async fn type_text(text: &str, clipboard: bool) -> OsResult<TypedText> {
     validate_text(text)?;
     if command_exists("wtype").await {
-        return run_checked("wtype", &[text]).await;
+        let window = active_window().await.ok();
+        let xwayland = window
+            .as_ref()
+            .and_then(|w| w.get("xwayland"))
+            .and_then(Value::as_bool)
+            == Some(true);
+        let lossy = xwayland && !text.is_ascii();
+        if lossy && clipboard && command_exists("wl-copy").await {
+            paste_text(text, window.as_ref(), PasteKeys::Wtype).await?;
+            return Ok(TypedText {
+                backend: "wtype",
+                layout: None,
+                pasted: true,
+                warning: None,
+            });
+        }
+        run_checked("wtype", &[text]).await?;
+        return Ok(TypedText {
+            backend: "wtype",
+            layout: None,
+            pasted: false,
+            warning: (lossy && !clipboard).then(|| {
+                "clipboard access was not granted, so non-ASCII text was typed into an XWayland window and may come out wrong".to_string()
+            }),
+        });
     }
     if command_exists("ydotool").await {
-        return run_checked("ydotool", &["type", text]).await;
+        return type_with_ydotool(text, clipboard).await;
     }
     Err(OsError::OperationFailed(
         "No text input backend found (install 'wtype' or 'ydotool')".to_string(),
     ))
 }
 
+/// How `type_text` typed, for the tool output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypedText {
+    pub backend: &'static str,
+    /// Active layout, when the backend depends on it.
+    pub layout: Option<String>,
+    /// Sent through the clipboard and a paste shortcut.
+    pub pasted: bool,
+    pub warning: Option<String>,
+}
+
+/// ydotool sends US keycodes, so text is first transcoded for the active
+/// layout. Text the layout cannot produce is pasted instead, or refused
+/// rather than typed as garbage when there is no clipboard tool or grant.
+async fn type_with_ydotool(text: &str, clipboard: bool) -> OsResult<TypedText> {
+    use super::keyboard::{self, Transcoded};
+
+    let layout = keyboard::active_layout().await;
+    let transcoded = match &layout {
+        Some(layout) => keyboard::transcode_for_us_keycodes(text, layout),
+        None => Transcoded::UnknownLayout,
+    };
+    let describe = || {
+        layout
+            .as_ref()
+            .map(|layout| layout.describe())
+            .unwrap_or_else(|| "unknown".to_string())
+    };
+    let lossy = match &transcoded {
+        Transcoded::Text(_) => false,
+        Transcoded::Unsupported(_) => true,
+        Transcoded::UnknownLayout => !text.is_ascii(),
+    };
+    if lossy && clipboard && command_exists("wl-copy").await {
+        let window = active_window().await.ok();
+        paste_text(text, window.as_ref(), PasteKeys::Ydotool).await?;
+        return Ok(TypedText {
+            backend: "ydotool",
+            layout: layout.as_ref().map(|layout| layout.describe()),
+            pasted: true,
+            warning: None,
+        });
+    }
+    let (typed, warning) = match transcoded {
+        Transcoded::Text(typed) => (typed, None),
+        Transcoded::Unsupported(chars) => {
+            let paste_hint = if clipboard {
+                "or wl-clipboard to paste it"
+            } else {
+                "or allow clipboard access to paste it"
+            };
+            return Err(OsError::InvalidArgument(format!(
+                "ydotool cannot type {:?} on the {} keyboard layout; install wtype, which is layout-independent, {paste_hint}",
+                chars.iter().collect::<String>(),
+                describe()
+            )));
+        }
+        Transcoded::UnknownLayout => (
+            text.to_string(),
+            Some(match &layout {
+                Some(layout) => format!(
+                    "no transcoding for the {} layout; ydotool types US key positions, so letters and symbols may come out wrong (wtype avoids this)",
+                    layout.describe()
+                ),
+                None => "keyboard layout unknown; ydotool types US key positions".to_string(),
+            }),
+        ),
+    };
+    ydotool::run(&["type", &typed]).await?;
+    Ok(TypedText {
+        backend: "ydotool",
+        layout: layout.as_ref().map(|layout| layout.describe()),
+        pasted: false,
+        warning,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PasteKeys {
+    Wtype,
+    Ydotool,
+}
+
+/// Terminals paste with ctrl+shift+v; everything else with ctrl+v.
+fn pastes_with_shift(window: Option<&Value>) -> bool {
+    const TERMINALS: &[&str] = &[
+        "kitty",
+        "foot",
+        "alacritty",
+        "wezterm",
+        "ghostty",
+        "konsole",
+        "terminal",
+        "terminator",
+        "tilix",
+    ];
+    let class = window
+        .and_then(|w| w.get("class"))
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_lowercase();
+    TERMINALS.iter().any(|terminal| class.contains(terminal))
+}
+
+/// Put `text` on the clipboard, press the paste shortcut for `window`, then
+/// restore the previous clipboard text (or clear it) so the typed text does
+/// not linger there.
+async fn paste_text(text: &str, window: Option<&Value>, keys: PasteKeys) -> OsResult<()> {
+    let previous = run_output("wl-paste", &["--no-newline", "--type", "text/plain"])
+        .await
+        .ok();
+    copy_to_clipboard(text).await?;
+    // Give the compositor a moment to announce the new selection.
+    sleep(Duration::from_millis(50)).await;
+    let shift = pastes_with_shift(window);
+    let pressed = match keys {
+        PasteKeys::Wtype => {
+            let mut args = vec!["-M", "ctrl"];
+            if shift {
+                args.extend(["-M", "shift"]);
+            }
+            args.extend(["-k", "v"]);
+            if shift {
+                args.extend(["-m", "shift"]);
+            }
+            args.extend(["-m", "ctrl"]);
+            run_checked("wtype", &args).await
+        }
+        PasteKeys::Ydotool => {
+            // 29 is KEY_LEFTCTRL, 42 KEY_LEFTSHIFT and 47 KEY_V.
+            let args: &[&str] = if shift {
+                &["key", "29:1", "42:1", "47:1", "47:0", "42:0", "29:0"]
+            } else {
+                &["key", "29:1", "47:1", "47:0", "29:0"]
+            };
+            ydotool::run(args).await
+        }
+    };
+    // The target reads the clipboard asynchronously after the shortcut.
+    sleep(Duration::from_millis(300)).await;
+    let restored = match previous {
+        Some(previous) => copy_to_clipboard(&previous).await,
+        None => run_checked("wl-copy", &["--clear"]).await,
+    };
+    pressed.and(restored)
+}
+
+/// Text goes through stdin so it never shows up in the process list.
+async fn copy_to_clipboard(text: &str) -> OsResult<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut child = Command::new("wl-copy")
+        .args(["--type", "text/plain;charset=utf-8"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes()).await?;
+    }
+    let output = child.wait_with_output().await?;
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(OsError::OperationFailed(
+        String::from_utf8_lossy(&output.stderr).to_string(),
+    ))
+}
+
 /// Press a single key in the focused window.
 pub async fn key_press(key: &str) -> OsResult<()> {
     validate_key_token(key)?;
-    if !command_exists("wtype").await {
-        return Err(OsError::OperationFailed(
-            "wtype not found for key presses".to_string(),
-        ));
+    degradation::run("desktop.key_press", |backend| async move {
+        match backend {
+            "wtype" => run_checked("wtype", &["-k", key]).await,
+            _ => send_shortcut(&[], key).await,
+        }
+    })
+    .await
+    .map(|_| ())
+}
+
+/// Hyprland's name for a wtype modifier.
+fn hypr_modifier(modifier: &str) -> OsResult<&'static str> {
+    match modifier.to_lowercase().as_str() {
+        "ctrl" | "control" => Ok("CTRL"),
+        "shift" => Ok("SHIFT"),
+        "alt" => Ok("ALT"),
+        "super" | "logo" | "meta" | "win" => Ok("SUPER"),
+        other => Err(OsError::InvalidArgument(format!(
+            "unsupported modifier for hyprctl sendshortcut: {other}"
+        ))),
     }
-    run_checked("wtype", &["-k", key]).await
+}
+
+/// Send `key` with `modifiers` to the active window through Hyprland's
+/// `sendshortcut` dispatcher, for when wtype is missing.
+async fn send_shortcut(modifiers: &[String], key: &str) -> OsResult<()> {
+    let mods = modifiers
+        .iter()
+        .map(|modifier| hypr_modifier(modifier))
+        .collect::<OsResult<Vec<_>>>()?
+        .join(" ");
+    hyprctl_dispatch(&["sendshortcut", &format!("{mods}, {key}")]).await
+}
+
+/// `hyprctl dispatch`, which exits 0 and prints the error when a dispatcher
+/// fails.
+async fn hyprctl_dispatch(args: &[&str]) -> OsResult<()> {
+    let output = Command::new("hyprctl")
+        .arg("dispatch")
+        .args(args)
+        .output()
+        .await?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if output.status.success() && (stdout.is_empty() || stdout == "ok") {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    Err(OsError::OperationFailed(if stderr.is_empty() {
+        stdout
+    } else {
+        stderr
+    }))
 }
 
 /// Press a key combination (modifiers + key), e.g. ctrl+l.
@@ -611,12 +887,6 @@ pub async fn key_combo(keys: &[String]) -> OsResult<()> {
             "key_combo requires at least one modifier and one key".to_string(),
         ));
     }
-    if !command_exists("wtype").await {
-        return Err(OsError::OperationFailed(
-            "wtype not found for key combos".to_string(),
-        ));
-    }
-
     for key in keys {
         validate_key_token(key)?;
     }
@@ -637,7 +907,18 @@ pub async fn key_combo(keys: &[String]) -> OsResult<()> {
     }
 
     let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
-    run_checked("wtype", &arg_refs).await
+    let main_key = keys.last().map(String::as_str).unwrap_or_default();
+    degradation::run("desktop.key_combo", |backend| {
+        let arg_refs = &arg_refs;
+        async move {
+            match backend {
+                "wtype" => run_checked("wtype", arg_refs).await,
+                _ => send_shortcut(modifiers, main_key).await,
+            }
+        }
+    })
+    .await
+    .map(|_| ())
 }
 
 fn parse_mouse_button(button: &str) -> OsResult<&'static str> {
@@ -654,15 +935,14 @@ fn parse_mouse_button(button: &str) -> OsResult<&'static str> {
 /// Click mouse button in current cursor position.
 pub async fn mouse_click(button: &str) -> OsResult<()> {
     let code = parse_mouse_button(button)?;
-    if command_exists("ydotool").await {
-        return run_checked("ydotool", &["click", code]).await;
-    }
-    if command_exists("wlrctl").await {
-        return run_checked("wlrctl", &["pointer", "click", button]).await;
-    }
-    Err(OsError::OperationFailed(
-        "No click backend found (install 'ydotool' or 'wlrctl')".to_string(),
-    ))
+    degradation::run("desktop.mouse_click", |backend| async move {
+        match backend {
+            "ydotool" => ydotool::run(&["click", code]).await,
+            _ => run_checked("wlrctl", &["pointer", "click", button]).await,
+        }
+    })
+    .await
+    .map(|_| ())
 }
 
 /// Move cursor to absolute coordinate.
@@ -673,16 +953,19 @@ pub async fn mouse_move_absolute(x: i32, y: i32) -> OsResult<()> {
     let xs = x.to_string();
     let ys = y.to_string();
 
-    if command_exists("wlrctl").await {
-        return run_checked("wlrctl", &["pointer", "move", &xs, &ys]).await;
-    }
-    if command_exists("ydotool").await {
-        // ydotool mousemove supports absolute mode on newer versions.
-        return run_checked("ydotool", &["mousemove", "--absolute", &xs, &ys]).await;
-    }
-    Err(OsError::OperationFailed(
-        "No mouse move backend found (install 'wlrctl' or 'ydotool')".to_string(),
-    ))
+    degradation::run("desktop.mouse_move", |backend| {
+        let (xs, ys) = (xs.as_str(), ys.as_str());
+        async move {
+            match backend {
+                "wlrctl" => run_checked("wlrctl", &["pointer", "move", xs, ys]).await,
+                // ydotool mousemove supports absolute mode on newer versions.
+                "ydotool" => ydotool::run(&["mousemove", "--absolute", xs, ys]).await,
+                _ => hyprctl_dispatch(&["movecursor", xs, ys]).await,
+            }
+        }
+    })
+    .await
+    .map(|_| ())
 }
 
 /// Click at absolute coordinate.
@@ -884,18 +1167,18 @@ pub async fn capture_screen(path: Option<&str>) -> OsResult<String> {
         }
     }
 
-    if command_exists("grim").await {
-        run_checked("grim", &[&target]).await?;
-        return Ok(target);
-    }
-    if command_exists("hyprshot").await {
-        // hyprshot -m output prints path, but we pass explicit output path.
-        run_checked("hyprshot", &["-m", "output", "-o", &target]).await?;
-        return Ok(target);
-    }
-    Err(OsError::OperationFailed(
-        "No screenshot backend found (install 'grim' or 'hyprshot')".to_string(),
-    ))
+    degradation::run("desktop.capture_screen", |backend| {
+        let target = target.as_str();
+        async move {
+            match backend {
+                "grim" => run_checked("grim", &[target]).await,
+                // hyprshot -m output prints path, but we pass explicit output path.
+                _ => run_checked("hyprshot", &["-m", "output", "-o", target]).await,
+            }
+        }
+    })
+    .await?;
+    Ok(target)
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -1112,21 +1395,66 @@ pub async fn ocr_screen(
         ));
     }
 
-    let mut args = vec![image_path.as_str(), "stdout", "tsv"];
-    if let Some(lang) = lang {
+    let lang = super::ocr::language(lang);
+    if let Some(lang) = lang
+        .as_deref()
+        .filter(|lang| !super::ocr::valid_language(lang))
+    {
+        return Err(OsError::InvalidArgument(format!(
+            "invalid OCR language '{lang}' (use tesseract codes like deu or chi_sim+eng)"
+        )));
+    }
+    let psm = super::ocr::tuning(lang.as_deref())
+        .psm
+        .map(|psm| psm.to_string());
+    let mut args = vec![image_path.as_str(), "stdout"];
+    if let Some(lang) = &lang {
         args.push("-l");
         args.push(lang);
     }
-    let tsv = run_output("tesseract", &args).await?;
+    if let Some(psm) = &psm {
+        args.push("--psm");
+        args.push(psm);
+    }
+    args.push("tsv");
+    let tsv = match run_output("tesseract", &args).await {
+        Ok(tsv) => tsv,
+        Err(e) => return Err(missing_language_error(lang.as_deref()).await.unwrap_or(e)),
+    };
     let words = parse_tesseract_tsv(&tsv);
     let full_text = words
         .iter()
         .map(|m| m.text.clone())
         .collect::<Vec<String>>()
         .join(" ");
+    let own_capture = Path::new(&image_path)
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with("hypr-claw-"));
+    if own_capture
+        && words
+            .iter()
+            .any(|word| crate::sandbox::env_policy::looks_secret("", &word.text))
+    {
+        // Retention wipes marked captures early; failing to mark is not fatal.
+        let _ = super::wipe::mark_sensitive(Path::new(&image_path));
+    }
     Ok((full_text, words))
 }
 
+/// A clearer error than tesseract's when `lang` names a pack that is not
+/// installed.
+async fn missing_language_error(lang: Option<&str>) -> Option<OsError> {
+    let installed = super::ocr::installed_languages().await.ok()?;
+    let missing = super::ocr::missing_languages(lang?, &installed);
+    let first = missing.first()?;
+    Some(OsError::OperationFailed(format!(
+        "OCR language pack(s) not installed: {} (installed: {}); run `capabilities install tesseract-{}`",
+        missing.join(", "),
+        installed.join(", "),
+        first
+    )))
+}
+
 /// Find text matches from OCR output.
 pub async fn find_text(
     query: &str,
@@ -1145,7 +1473,7 @@ pub async fn find_text(
         query,
         &words,
         case_sensitive,
-        25.0,
+        super::ocr::min_confidence(lang),
         if limit == 0 { usize::MAX } else { limit },
     ))
 }
@@ -1163,7 +1491,15 @@ pub async fn click_text(
         let matches = find_text(query, case_sensitive, occurrence + 1, lang).await?;
         if matches.len() > occurrence {
             let target = matches[occurrence].clone();
-            click_at(target.center_x, target.center_y, button).await?;
+            // OCR works on capture pixels; on scaled monitors the pointer
+            // needs layout coordinates.
+            let (x, y) = match super::hardware::displays().await {
+                Ok(displays) => {
+                    super::hardware::pixel_to_logical(&displays, target.center_x, target.center_y)
+                }
+                Err(_) => (target.center_x, target.center_y),
+            };
+            click_at(x, y, button).await?;
             return Ok(target);
         }
 
@@ -1223,6 +1559,145 @@ pub async fn wait_for_text(
     }
 }
 
+/// How [`type_text_and_verify`] clears a failed attempt before retyping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeCorrection {
+    /// Erase as many characters as were sent.
+    Backspace,
+    /// Select the whole field (ctrl+a) so the retry replaces it.
+    SelectAll,
+    /// Report the mismatch without retrying.
+    None,
+}
+
+impl TypeCorrection {
+    pub fn parse(value: &str) -> OsResult<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "backspace" => Ok(Self::Backspace),
+            "select_all" => Ok(Self::SelectAll),
+            "none" => Ok(Self::None),
+            other => Err(OsError::InvalidArgument(format!(
+                "unsupported correction: {other} (expected backspace, select_all or none)"
+            ))),
+        }
+    }
+}
+
+/// Press backspace `count` times in the focused window.
+async fn erase_chars(count: usize) -> OsResult<()> {
+    if count == 0 {
+        return Ok(());
+    }
+    if command_exists("wtype").await {
+        let args: Vec<&str> = std::iter::repeat_n(["-k", "BackSpace"], count)
+            .flatten()
+            .collect();
+        return run_checked("wtype", &args).await;
+    }
+    if command_exists("ydotool").await {
+        // 14 is KEY_BACKSPACE.
+        let mut args = vec!["key"];
+        args.extend(std::iter::repeat_n(["14:1", "14:0"], count).flatten());
+        return ydotool::run(&args).await;
+    }
+    Err(OsError::OperationFailed(
+        "No key input backend found (install 'wtype' or 'ydotool')".to_string(),
+    ))
+}
+
+/// grim geometry (`x,y wxh`) of a Hyprland window from `activewindow -j`.
+pub fn window_region(window: &Value) -> Option<String> {
+    let at = window.get("at")?.as_array()?;
+    let size = window.get("size")?.as_array()?;
+    let (x, y) = (at.first()?.as_i64()?, at.get(1)?.as_i64()?);
+    let (width, height) = (size.first()?.as_i64()?, size.get(1)?.as_i64()?);
+    (width > 0 && height > 0).then(|| format!("{x},{y} {width}x{height}"))
+}
+
+/// OCR the focused window, or the whole screen when its region is unknown.
+async fn read_back_focused(lang: Option<&str>) -> OsResult<Vec<OcrMatch>> {
+    let region = active_window()
+        .await
+        .ok()
+        .and_then(|window| window_region(&window));
+    let region_shot = match region {
+        Some(region) if command_exists("grim").await => {
+            let target = format!(
+                "/tmp/hypr-claw-readback-{}.png",
+                chrono::Utc::now().timestamp_millis()
+            );
+            run_checked("grim", &["-g", &region, &target]).await?;
+            Some(target)
+        }
+        _ => None,
+    };
+    let result = ocr_screen(region_shot.as_deref(), lang).await;
+    if let Some(path) = region_shot {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+    Ok(result?.1)
+}
+
+/// Whether OCR words contain `text` as a phrase, ignoring case and punctuation.
+pub fn typed_text_visible(text: &str, words: &[OcrMatch]) -> bool {
+    !build_phrase_matches(text, words, false, super::ocr::min_confidence(None), 1).is_empty()
+}
+
+/// Type text, then read the focused window back with OCR to confirm it
+/// landed intact. On a mismatch the attempt is cleared with `correction` and
+/// retyped, up to `max_attempts` times in total. `clipboard` is passed on
+/// to [`type_text`].
+pub async fn type_text_and_verify(
+    text: &str,
+    clipboard: bool,
+    correction: TypeCorrection,
+    max_attempts: usize,
+    lang: Option<&str>,
+) -> OsResult<Value> {
+    if normalize_for_match(text, false).is_empty() {
+        type_text(text, clipboard).await?;
+        return Ok(json!({
+            "verified": false,
+            "attempts": 1,
+            "reason": "text has nothing OCR can read back"
+        }));
+    }
+
+    let attempts = if correction == TypeCorrection::None {
+        1
+    } else {
+        max_attempts.max(1)
+    };
+    let mut observed = String::new();
+    for attempt in 1..=attempts {
+        if attempt > 1 {
+            match correction {
+                TypeCorrection::Backspace => erase_chars(text.chars().count()).await?,
+                TypeCorrection::SelectAll => {
+                    key_combo(&["ctrl".to_string(), "a".to_string()]).await?
+                }
+                TypeCorrection::None => break,
+            }
+        }
+        type_text(text, clipboard).await?;
+        sleep(Duration::from_millis(150)).await;
+        let words = read_back_focused(lang).await?;
+        if typed_text_visible(text, &words) {
+            return Ok(json!({"verified": true, "attempts": attempt}));
+        }
+        observed = words
+            .iter()
+            .map(|w| w.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+
+    let excerpt: String = observed.chars().take(200).collect();
+    Err(OsError::OperationFailed(format!(
+        "typed text was not confirmed on screen after {attempts} attempt(s); OCR read: '{excerpt}'"
+    )))
+}
+
 /// Return current active window metadata from Hyprland.
 pub async fn active_window() -> OsResult<Value> {
     let output = Command::new("hyprctl")
@@ -1258,10 +1733,65 @@ pub async fn list_windows(limit: usize) -> OsResult<Vec<Value>> {
     Ok(json)
 }
 
+/// A Hyprland window: the active one, the one at an `0x…` address, or the
+/// first whose class or title contains `selector` (ignoring case).
+pub async fn find_window(selector: Option<&str>) -> OsResult<Value> {
+    let Some(selector) = selector
+        .map(str::trim)
+        .filter(|s| !s.is_empty() && *s != "active")
+    else {
+        return active_window().await;
+    };
+    let needle = selector.to_lowercase();
+    list_windows(0)
+        .await?
+        .into_iter()
+        .find(|window| {
+            let field = |name: &str| {
+                window
+                    .get(name)
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_lowercase()
+            };
+            if selector.starts_with("0x") {
+                field("address") == needle
+            } else {
+                field("class").contains(&needle) || field("title").contains(&needle)
+            }
+        })
+        .ok_or_else(|| OsError::NotFound(format!("window '{selector}'")))
+}
+
+/// Capture a grim region (`x,y wxh`) to a temporary file and return its path.
+pub async fn capture_region(region: &str) -> OsResult<String> {
+    if !command_exists("grim").await {
+        return Err(OsError::OperationFailed(
+            "grim not found for window captures".to_string(),
+        ));
+    }
+    let target = format!(
+        "/tmp/hypr-claw-shot-window-{}.png",
+        chrono::Utc::now().timestamp_millis()
+    );
+    run_checked("grim", &["-g", region, &target]).await?;
+    Ok(target)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn terminals_paste_with_shift() {
+        assert!(pastes_with_shift(Some(&json!({"class": "kitty"}))));
+        assert!(pastes_with_shift(Some(
+            &json!({"class": "org.gnome.Terminal"})
+        )));
+        assert!(!pastes_with_shift(Some(&json!({"class": "firefox"}))));
+        assert!(!pastes_with_shift(None));
+    }
+
     #[test]
     fn canonical_app_key_normalizes_common_forms() {
         assert_eq!(canonical_app_key("Visual Studio Code"), "visualstudiocode");
@@ -1295,4 +1825,35 @@ mod tests {
         assert!(aliases.iter().any(|v| v == "code-oss"));
         assert!(aliases.iter().any(|v| v == "com.visualstudio.code"));
     }
+
+    fn word(text: &str, x: i32) -> OcrMatch {
+        OcrMatch {
+            text: text.to_string(),
+            confidence: 90.0,
+            x,
+            y: 10,
+            width: 40,
+            height: 12,
+            center_x: x + 20,
+            center_y: 16,
+        }
+    }
+
+    #[test]
+    fn typed_text_is_found_across_ocr_words() {
+        let words = vec![
+            word("Subject:", 0),
+            word("Quarterly", 50),
+            word("report,", 100),
+        ];
+        assert!(typed_text_visible("quarterly report", &words));
+        assert!(!typed_text_visible("quarterly reprot", &words));
+    }
+
+    #[test]
+    fn window_region_uses_hyprland_geometry() {
+        let window = json!({"at": [10, 20], "size": [800, 600]});
+        assert_eq!(window_region(&window).as_deref(), Some("10,20 800x600"));
+        assert_eq!(window_region(&json!({"at": [0, 0], "size": [0, 0]})), None);
+    }
 }
@@ -0,0 +1,318 @@
+//! Document generation - template rendering and optional compilation
+
+use super::{OsError, OsResult};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocFormat {
+    Markdown,
+    Typst,
+    Latex,
+}
+
+impl DocFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "md" | "markdown" => Some(Self::Markdown),
+            "typ" | "typst" => Some(Self::Typst),
+            "tex" | "latex" => Some(Self::Latex),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::parse)
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Typst => "typ",
+            Self::Latex => "tex",
+        }
+    }
+
+    fn escape(self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        for ch in text.chars() {
+            match (self, ch) {
+                (Self::Latex, '\\') => out.push_str("\\textbackslash{}"),
+                (Self::Latex, '~') => out.push_str("\\textasciitilde{}"),
+                (Self::Latex, '^') => out.push_str("\\textasciicircum{}"),
+                (Self::Latex, '&' | '%' | '$' | '#' | '_' | '{' | '}') => {
+                    out.push('\\');
+                    out.push(ch);
+                }
+                (
+                    Self::Typst,
+                    '\\' | '#' | '$' | '*' | '_' | '`' | '@' | '<' | '>' | '[' | ']' | '=' | '~',
+                ) => {
+                    out.push('\\');
+                    out.push(ch);
+                }
+                _ => out.push(ch),
+            }
+        }
+        out
+    }
+}
+
+/// Render a template with `{{path.to.value}}` placeholders and
+/// `{{#each list}}...{{/each}}` blocks. Inside a block, keys resolve against
+/// the current item first (`{{this}}` is the item itself), then the root data.
+/// Values are escaped for the target format; missing keys are an error.
+pub fn render_template(template: &str, data: &Value, format: DocFormat) -> OsResult<String> {
+    render_scoped(template, &[data], format)
+}
+
+fn render_scoped(template: &str, scopes: &[&Value], format: DocFormat) -> OsResult<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| OsError::InvalidArgument("unclosed '{{' in template".to_string()))?;
+        let tag = after[..end].trim();
+        rest = &after[end + 2..];
+
+        if let Some(path) = tag.strip_prefix("#each ") {
+            let (body, remaining) = split_each_block(rest)?;
+            rest = remaining;
+            let items = lookup(scopes, path.trim())
+                .and_then(|v| v.as_array())
+                .ok_or_else(|| {
+                    OsError::InvalidArgument(format!("'{}' is not a list in data", path.trim()))
+                })?;
+            for item in items {
+                let mut nested = vec![item];
+                nested.extend_from_slice(scopes);
+                out.push_str(&render_scoped(body, &nested, format)?);
+            }
+            continue;
+        }
+        if tag == "/each" {
+            return Err(OsError::InvalidArgument(
+                "'{{/each}}' without matching '{{#each}}'".to_string(),
+            ));
+        }
+
+        let value = lookup(scopes, tag)
+            .ok_or_else(|| OsError::InvalidArgument(format!("missing template value '{tag}'")))?;
+        let text = match value {
+            Value::String(s) => s.clone(),
+            Value::Null => String::new(),
+            other => other.to_string(),
+        };
+        out.push_str(&format.escape(&text));
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Split at the `{{/each}}` closing the current block, honouring nesting.
+fn split_each_block(source: &str) -> OsResult<(&str, &str)> {
+    let mut depth = 1usize;
+    let mut cursor = 0usize;
+    while let Some(offset) = source[cursor..].find("{{") {
+        let start = cursor + offset;
+        let end = source[start..]
+            .find("}}")
+            .map(|e| start + e + 2)
+            .ok_or_else(|| OsError::InvalidArgument("unclosed '{{' in template".to_string()))?;
+        let tag = source[start + 2..end - 2].trim();
+        if tag.starts_with("#each ") {
+            depth += 1;
+        } else if tag == "/each" {
+            depth -= 1;
+            if depth == 0 {
+                return Ok((&source[..start], &source[end..]));
+            }
+        }
+        cursor = end;
+    }
+    Err(OsError::InvalidArgument(
+        "'{{#each}}' without matching '{{/each}}'".to_string(),
+    ))
+}
+
+fn lookup<'a>(scopes: &[&'a Value], path: &str) -> Option<&'a Value> {
+    if path == "this" {
+        return scopes.first().copied();
+    }
+    let path = path.strip_prefix("this.").unwrap_or(path);
+    scopes.iter().find_map(|scope| {
+        path.split('.')
+            .try_fold(*scope, |current, key| match current {
+                Value::Object(map) => map.get(key),
+                Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+                _ => None,
+            })
+    })
+}
+
+async fn command_exists(command: &str) -> bool {
+    Command::new("which")
+        .arg(command)
+        .output()
+        .await
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// A compiler invocation. It runs in the source's own directory and may only
+/// read files from there: Typst is rooted at it, LaTeX runs without shell
+/// escape and with kpathsea's paranoid `openin_any`/`openout_any`, which
+/// refuse absolute paths, `..` and dotfiles.
+struct Compiler {
+    program: &'static str,
+    args: Vec<String>,
+    env: &'static [(&'static str, &'static str)],
+}
+
+fn compilers(source_name: &str, pdf_name: &str, format: DocFormat) -> Vec<Compiler> {
+    match format {
+        DocFormat::Markdown => Vec::new(),
+        DocFormat::Typst => vec![Compiler {
+            program: "typst",
+            args: vec![
+                "compile".into(),
+                "--root".into(),
+                ".".into(),
+                source_name.into(),
+                pdf_name.into(),
+            ],
+            env: &[],
+        }],
+        DocFormat::Latex => vec![Compiler {
+            program: "pdflatex",
+            args: vec![
+                "-interaction=nonstopmode".into(),
+                "-halt-on-error".into(),
+                "-no-shell-escape".into(),
+                source_name.into(),
+            ],
+            env: &[
+                ("openin_any", "p"),
+                ("openout_any", "p"),
+                ("shell_escape", "f"),
+            ],
+        }],
+    }
+}
+
+/// Compile a rendered Typst or LaTeX source to PDF next to the source file.
+/// Returns `Ok(None)` for formats that need no compilation.
+pub async fn compile(source: &Path, format: DocFormat) -> OsResult<Option<PathBuf>> {
+    let pdf = source.with_extension("pdf");
+    let dir = source
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let name = |path: &Path| {
+        path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+
+    let candidates = compilers(&name(source), &name(&pdf), format);
+    if candidates.is_empty() {
+        return Ok(None);
+    }
+    let mut tried = Vec::new();
+    for compiler in candidates {
+        tried.push(compiler.program);
+        if !command_exists(compiler.program).await {
+            continue;
+        }
+        let output = Command::new(compiler.program)
+            .args(&compiler.args)
+            .envs(compiler.env.iter().copied())
+            .current_dir(dir)
+            .output()
+            .await?;
+        if output.status.success() && pdf.exists() {
+            return Ok(Some(pdf));
+        }
+        return Err(OsError::OperationFailed(format!(
+            "{} failed: {}",
+            compiler.program,
+            String::from_utf8_lossy(&output.stderr)
+                .lines()
+                .chain(String::from_utf8_lossy(&output.stdout).lines())
+                .filter(|l| !l.trim().is_empty())
+                .take(8)
+                .collect::<Vec<_>>()
+                .join(" | ")
+        )));
+    }
+    Err(OsError::NotFound(format!(
+        "no compiler installed (tried: {})",
+        tried.join(", ")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn renders_values_and_each_blocks() {
+        let data = json!({
+            "customer": {"name": "ACME"},
+            "currency": "EUR",
+            "items": [{"name": "Widget", "qty": 2}, {"name": "Gadget", "qty": 1}]
+        });
+        let template = "Invoice for {{customer.name}}\n{{#each items}}- {{name}} x{{qty}} {{currency}}\n{{/each}}";
+        let out = render_template(template, &data, DocFormat::Markdown).unwrap();
+        assert_eq!(out, "Invoice for ACME\n- Widget x2 EUR\n- Gadget x1 EUR\n");
+    }
+
+    #[test]
+    fn escapes_for_latex_and_typst() {
+        let data = json!({"v": "50% & $5_x #1"});
+        assert_eq!(
+            render_template("{{v}}", &data, DocFormat::Latex).unwrap(),
+            "50\\% \\& \\$5\\_x \\#1"
+        );
+        assert_eq!(
+            render_template("{{v}}", &data, DocFormat::Typst).unwrap(),
+            "50% & \\$5\\_x \\#1"
+        );
+    }
+
+    #[test]
+    fn reports_missing_values_and_unbalanced_blocks() {
+        let data = json!({"items": [1, 2]});
+        assert!(render_template("{{nope}}", &data, DocFormat::Markdown).is_err());
+        assert!(render_template("{{#each items}}{{this}}", &data, DocFormat::Markdown).is_err());
+        assert_eq!(
+            render_template(
+                "{{#each items}}{{this}},{{/each}}",
+                &data,
+                DocFormat::Markdown
+            )
+            .unwrap(),
+            "1,2,"
+        );
+    }
+
+    #[test]
+    fn compilers_cannot_reach_outside_the_source_directory() {
+        let typst = compilers("doc.typ", "doc.pdf", DocFormat::Typst);
+        assert_eq!(typst[0].args[1..3], ["--root", "."]);
+
+        let latex = compilers("doc.tex", "doc.pdf", DocFormat::Latex);
+        assert!(latex[0].args.contains(&"-no-shell-escape".to_string()));
+        assert!(latex[0].env.contains(&("openin_any", "p")));
+        assert!(latex[0].args.iter().all(|a| !a.starts_with('/')));
+
+        assert!(compilers("doc.md", "doc.pdf", DocFormat::Markdown).is_empty());
+    }
+}
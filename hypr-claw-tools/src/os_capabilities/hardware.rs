@@ -0,0 +1,313 @@
+//! GPU and display information.
+//!
+//! GPUs come from `/sys/class/drm`, named through `lspci` and filled in by
+//! `nvidia-smi` for NVIDIA cards; amdgpu reports VRAM and load in sysfs.
+//! Displays come from `hyprctl monitors -j`. A full-screen grim capture is
+//! rendered at the highest monitor scale, so screenshot pixels have to be
+//! mapped back to layout coordinates before the pointer can use them.
+
+use super::{OsError, OsResult};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+use tokio::fs;
+use tokio::process::Command;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Display {
+    pub name: String,
+    pub description: String,
+    /// Mode in physical pixels.
+    pub width: u32,
+    pub height: u32,
+    pub refresh_hz: f64,
+    pub scale: f64,
+    /// Position in the layout, in logical pixels.
+    pub x: i32,
+    pub y: i32,
+    /// Wayland transform; odd values are rotated by 90 or 270 degrees.
+    pub transform: u8,
+    pub focused: bool,
+}
+
+impl Display {
+    /// Size in layout coordinates, after scale and rotation.
+    pub fn logical_size(&self) -> (u32, u32) {
+        let scale = if self.scale > 0.0 { self.scale } else { 1.0 };
+        let width = (self.width as f64 / scale).round() as u32;
+        let height = (self.height as f64 / scale).round() as u32;
+        if self.transform % 2 == 1 {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+}
+
+pub async fn displays() -> OsResult<Vec<Display>> {
+    let output = Command::new("hyprctl")
+        .args(["monitors", "-j"])
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(OsError::OperationFailed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    parse_monitors(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_monitors(raw: &str) -> OsResult<Vec<Display>> {
+    let json: Value = serde_json::from_str(raw)
+        .map_err(|e| OsError::OperationFailed(format!("hyprctl monitors: {e}")))?;
+    let monitors = json
+        .as_array()
+        .ok_or_else(|| OsError::OperationFailed("hyprctl monitors: not a list".to_string()))?;
+    Ok(monitors
+        .iter()
+        .map(|monitor| {
+            let text = |key: &str| {
+                monitor
+                    .get(key)
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            let number = |key: &str| monitor.get(key).and_then(Value::as_f64).unwrap_or(0.0);
+            Display {
+                name: text("name"),
+                description: text("description"),
+                width: number("width") as u32,
+                height: number("height") as u32,
+                refresh_hz: (number("refreshRate") * 100.0).round() / 100.0,
+                scale: monitor.get("scale").and_then(Value::as_f64).unwrap_or(1.0),
+                x: number("x") as i32,
+                y: number("y") as i32,
+                transform: number("transform") as u8,
+                focused: monitor.get("focused").and_then(Value::as_bool) == Some(true),
+            }
+        })
+        .collect())
+}
+
+/// Scale of a full-screen grim capture: the highest monitor scale.
+pub fn capture_scale(displays: &[Display]) -> f64 {
+    displays
+        .iter()
+        .map(|display| display.scale)
+        .fold(1.0, f64::max)
+}
+
+/// Layout coordinates of a pixel in a full-screen capture. The capture
+/// covers the layout's bounding box at [`capture_scale`].
+pub fn pixel_to_logical(displays: &[Display], x: i32, y: i32) -> (i32, i32) {
+    let scale = capture_scale(displays);
+    let left = displays.iter().map(|display| display.x).min().unwrap_or(0);
+    let top = displays.iter().map(|display| display.y).min().unwrap_or(0);
+    (
+        left + (x as f64 / scale).round() as i32,
+        top + (y as f64 / scale).round() as i32,
+    )
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Gpu {
+    /// `nvidia`, `amd`, `intel` or the PCI vendor id.
+    pub vendor: String,
+    pub name: Option<String>,
+    /// Kernel driver, e.g. `amdgpu`.
+    pub driver: String,
+    pub driver_version: Option<String>,
+    pub vram_total_mb: Option<u64>,
+    pub vram_used_mb: Option<u64>,
+    pub utilization_percent: Option<u8>,
+    pub pci_slot: String,
+}
+
+pub async fn gpus() -> OsResult<Vec<Gpu>> {
+    let mut gpus: Vec<Gpu> = Vec::new();
+    let mut entries = fs::read_dir("/sys/class/drm").await?;
+    let mut cards = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        // `card0`, not the connector entries like `card0-DP-1`.
+        if name.starts_with("card") && name[4..].chars().all(|c| c.is_ascii_digit()) {
+            cards.push(entry.path().join("device"));
+        }
+    }
+    cards.sort();
+    for device in cards {
+        let Some(gpu) = sysfs_gpu(&device).await else {
+            continue;
+        };
+        if !gpus.iter().any(|have| have.pci_slot == gpu.pci_slot) {
+            gpus.push(gpu);
+        }
+    }
+
+    if gpus.iter().any(|gpu| gpu.vendor == "nvidia") {
+        if let Some(raw) = output(
+            "nvidia-smi",
+            &[
+                "--query-gpu=pci.bus_id,name,driver_version,memory.total,memory.used,utilization.gpu",
+                "--format=csv,noheader,nounits",
+            ],
+        )
+        .await
+        {
+            merge_nvidia_smi(&mut gpus, &raw);
+        }
+    }
+    for gpu in gpus.iter_mut().filter(|gpu| gpu.name.is_none()) {
+        if let Some(raw) = output("lspci", &["-mm", "-s", &gpu.pci_slot]).await {
+            gpu.name = parse_lspci_name(&raw);
+        }
+    }
+    Ok(gpus)
+}
+
+async fn sysfs_gpu(device: &Path) -> Option<Gpu> {
+    let read = |file: &str| {
+        let path = device.join(file);
+        async move {
+            fs::read_to_string(path)
+                .await
+                .ok()
+                .map(|s| s.trim().to_string())
+        }
+    };
+    let vendor_id = read("vendor").await?;
+    let pci_slot = read("uevent")
+        .await?
+        .lines()
+        .find_map(|line| line.strip_prefix("PCI_SLOT_NAME="))?
+        .to_string();
+    let driver = fs::read_link(device.join("driver"))
+        .await
+        .ok()
+        .and_then(|link| link.file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_default();
+    let driver_version = match driver.as_str() {
+        "" => None,
+        driver => fs::read_to_string(format!("/sys/module/{driver}/version"))
+            .await
+            .ok()
+            .map(|s| s.trim().to_string()),
+    };
+    let mb = |bytes: Option<String>| bytes?.parse::<u64>().ok().map(|b| b / (1024 * 1024));
+    Some(Gpu {
+        vendor: vendor_name(&vendor_id),
+        name: None,
+        driver,
+        driver_version,
+        vram_total_mb: mb(read("mem_info_vram_total").await),
+        vram_used_mb: mb(read("mem_info_vram_used").await),
+        utilization_percent: read("gpu_busy_percent").await.and_then(|s| s.parse().ok()),
+        pci_slot,
+    })
+}
+
+fn vendor_name(id: &str) -> String {
+    match id {
+        "0x10de" => "nvidia".to_string(),
+        "0x1002" => "amd".to_string(),
+        "0x8086" => "intel".to_string(),
+        other => other.to_string(),
+    }
+}
+
+async fn output(command: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(command).args(args).output().await.ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Fill NVIDIA cards from `nvidia-smi` CSV rows, matched by PCI bus id.
+/// nvidia-smi pads the PCI domain to eight digits, sysfs to four.
+fn merge_nvidia_smi(gpus: &mut [Gpu], raw: &str) {
+    for line in raw.lines() {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let [bus_id, name, driver_version, total, used, utilization] = fields[..] else {
+            continue;
+        };
+        let bus = bus_id.rsplit_once(':').map(|(head, function)| {
+            let bus = head.rsplit(':').next().unwrap_or_default();
+            format!("{bus}:{function}").to_lowercase()
+        });
+        let Some(gpu) = gpus.iter_mut().find(|gpu| {
+            bus.as_deref()
+                .is_some_and(|bus| gpu.pci_slot.to_lowercase().ends_with(bus))
+        }) else {
+            continue;
+        };
+        gpu.name = Some(name.to_string());
+        gpu.driver_version = Some(driver_version.to_string());
+        gpu.vram_total_mb = total.parse().ok();
+        gpu.vram_used_mb = used.parse().ok();
+        gpu.utilization_percent = utilization.parse().ok();
+    }
+}
+
+/// Device name from `lspci -mm -s <slot>`: the fourth quoted field.
+fn parse_lspci_name(raw: &str) -> Option<String> {
+    let line = raw.lines().next()?;
+    let device = line.split('"').nth(5)?.trim();
+    (!device.is_empty()).then(|| device.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monitors_map_capture_pixels_to_layout_coordinates() {
+        let raw = r#"[
+            {"name": "eDP-1", "description": "BOE 0x0BCA", "width": 2880, "height": 1800,
+             "refreshRate": 120.00000, "x": 0, "y": 0, "scale": 2.00, "transform": 0, "focused": true},
+            {"name": "DP-1", "description": "Dell U2720Q", "width": 2560, "height": 1440,
+             "refreshRate": 59.95100, "x": 1440, "y": 0, "scale": 1.00, "transform": 1, "focused": false}
+        ]"#;
+        let displays = parse_monitors(raw).unwrap();
+        assert_eq!(displays[0].logical_size(), (1440, 900));
+        assert_eq!(displays[1].logical_size(), (1440, 2560));
+        assert_eq!(displays[1].refresh_hz, 59.95);
+        assert_eq!(capture_scale(&displays), 2.0);
+        assert_eq!(pixel_to_logical(&displays, 1000, 600), (500, 300));
+        assert_eq!(pixel_to_logical(&[], 1000, 600), (1000, 600));
+    }
+
+    #[test]
+    fn nvidia_smi_fills_the_matching_card() {
+        let mut gpus = vec![
+            Gpu {
+                vendor: "intel".into(),
+                driver: "i915".into(),
+                pci_slot: "0000:00:02.0".into(),
+                ..Default::default()
+            },
+            Gpu {
+                vendor: "nvidia".into(),
+                driver: "nvidia".into(),
+                pci_slot: "0000:01:00.0".into(),
+                ..Default::default()
+            },
+        ];
+        merge_nvidia_smi(
+            &mut gpus,
+            "00000000:01:00.0, NVIDIA GeForce RTX 3060, 550.78, 12288, 512, 7\n",
+        );
+        assert_eq!(gpus[1].name.as_deref(), Some("NVIDIA GeForce RTX 3060"));
+        assert_eq!(gpus[1].vram_total_mb, Some(12288));
+        assert_eq!(gpus[1].utilization_percent, Some(7));
+        assert_eq!(gpus[0].name, None);
+        assert_eq!(
+            parse_lspci_name(
+                "00:02.0 \"VGA compatible controller\" \"Intel Corporation\" \"Alder Lake-P GT2 [Iris Xe Graphics]\" -r0c \"Lenovo\" \"Device 3a48\"\n"
+            )
+            .as_deref(),
+            Some("Alder Lake-P GT2 [Iris Xe Graphics]")
+        );
+    }
+}
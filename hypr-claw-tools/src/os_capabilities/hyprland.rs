@@ -1,6 +1,7 @@
 //! Hyprland control - workspace and window management
 
 use super::{OsError, OsResult};
+use std::collections::BTreeMap;
 use tokio::process::Command;
 
 fn validate_workspace_id(id: u32) -> OsResult<()> {
@@ -40,9 +41,28 @@ fn validate_exec_command(command: &str) -> OsResult<()> {
             "command contains invalid control characters".to_string(),
         ));
     }
+    let program = command.split_whitespace().next().unwrap_or_default();
+    if matches!(program, "env" | "printenv" | "set" | "export" | "declare") {
+        return Err(OsError::PermissionDenied(
+            "environment dumps are blocked; use env.get or env.list".to_string(),
+        ));
+    }
     Ok(())
 }
 
+/// `command` behind `env 'NAME=value' …`, quoted for the shell Hyprland
+/// runs it with.
+fn with_env_prefix(command: &str, env: &BTreeMap<String, String>) -> String {
+    if env.is_empty() {
+        return command.to_string();
+    }
+    let assignments: Vec<String> = env
+        .iter()
+        .map(|(name, value)| format!("'{}={}'", name, value.replace('\'', "'\\''")))
+        .collect();
+    format!("env {} {}", assignments.join(" "), command)
+}
+
 /// Switch to a workspace
 pub async fn workspace_switch(id: u32) -> OsResult<()> {
     validate_workspace_id(id)?;
@@ -118,9 +138,17 @@ pub async fn window_close(window_id: &str) -> OsResult<()> {
 
 /// Execute a program in Hyprland
 pub async fn exec(command: &str) -> OsResult<()> {
+    exec_with_env(command, &BTreeMap::new()).await
+}
+
+/// [`exec`] with `env` set for the launched command. Hyprland starts it
+/// from the compositor's environment, not ours, so the variables are passed
+/// through an `env` prefix.
+pub async fn exec_with_env(command: &str, env: &BTreeMap<String, String>) -> OsResult<()> {
     validate_exec_command(command)?;
+    let command = with_env_prefix(command, env);
     let output = Command::new("hyprctl")
-        .args(&["dispatch", "exec", command])
+        .args(["dispatch", "exec", &command])
         .output()
         .await?;
 
@@ -160,3 +188,110 @@ pub async fn get_active_workspace() -> OsResult<u32> {
 
     Ok(id as u32)
 }
+
+fn validate_special_name(name: &str) -> OsResult<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '_' | '-'))
+    {
+        return Err(OsError::InvalidArgument(
+            "special workspace name must be letters, digits, '-' or '_'".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+async fn dispatch(args: &[&str]) -> OsResult<()> {
+    let output = Command::new("hyprctl")
+        .arg("dispatch")
+        .args(args)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(OsError::OperationFailed(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Launch `command` onto the special workspace `name` without showing it.
+pub async fn exec_on_special(name: &str, command: &str) -> OsResult<()> {
+    validate_special_name(name)?;
+    validate_exec_command(command)?;
+    dispatch(&[
+        "exec",
+        &format!("[workspace special:{name} silent] {command}"),
+    ])
+    .await
+}
+
+/// Show the special workspace `name`, or hide it if it is showing.
+pub async fn toggle_special(name: &str) -> OsResult<()> {
+    validate_special_name(name)?;
+    dispatch(&["togglespecialworkspace", name]).await
+}
+
+/// Addresses of the windows on the special workspace `name`.
+pub async fn special_workspace_windows(name: &str) -> OsResult<Vec<String>> {
+    validate_special_name(name)?;
+    let output = Command::new("hyprctl")
+        .args(["clients", "-j"])
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(OsError::OperationFailed(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+    let clients: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| OsError::OperationFailed(e.to_string()))?;
+    Ok(windows_on_workspace(&clients, &format!("special:{name}")))
+}
+
+fn windows_on_workspace(clients: &serde_json::Value, workspace: &str) -> Vec<String> {
+    clients
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|client| {
+            client.pointer("/workspace/name").and_then(|v| v.as_str()) == Some(workspace)
+        })
+        .filter_map(|client| client.get("address").and_then(|v| v.as_str()))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exec_env_is_quoted_into_an_env_prefix() {
+        assert_eq!(with_env_prefix("kitty", &BTreeMap::new()), "kitty");
+        let env = BTreeMap::from([
+            ("RUST_LOG".to_string(), "debug".to_string()),
+            ("GREETING".to_string(), "it's $HOME".to_string()),
+        ]);
+        assert_eq!(
+            with_env_prefix("kitty -e cargo run", &env),
+            "env 'GREETING=it'\\''s $HOME' 'RUST_LOG=debug' kitty -e cargo run"
+        );
+    }
+
+    #[test]
+    fn special_workspace_windows_are_picked_by_workspace_name() {
+        let clients = serde_json::json!([
+            {"address": "0x1", "workspace": {"id": -98, "name": "special:claw-task-2"}},
+            {"address": "0x2", "workspace": {"id": 1, "name": "1"}},
+            {"address": "0x3", "workspace": {"id": -98, "name": "special:claw-task-2"}}
+        ]);
+        assert_eq!(
+            windows_on_workspace(&clients, "special:claw-task-2"),
+            vec!["0x1", "0x3"]
+        );
+        assert!(validate_special_name("claw-task-2").is_ok());
+        assert!(validate_special_name("claw task").is_err());
+    }
+}
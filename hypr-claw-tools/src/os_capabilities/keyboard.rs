@@ -0,0 +1,287 @@
+//! Keyboard layout detection and transcoding for keycode-based typing.
+//!
+//! `ydotool type` sends the US keycode for each character, so on a German or
+//! French layout `z` comes out as `y` and `@` as `"`. wtype uploads its own
+//! keymap and is unaffected. For ydotool, text is transcoded to the US
+//! characters whose key positions produce it on the active layout; characters
+//! the layout only reaches through AltGr, dead keys or the extra ISO key
+//! cannot be typed this way and are reported instead.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use tokio::process::Command;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyboardLayout {
+    /// XKB layout, e.g. `de`.
+    pub layout: String,
+    /// XKB variant, e.g. `nodeadkeys`; empty for the default.
+    pub variant: String,
+}
+
+impl KeyboardLayout {
+    pub fn describe(&self) -> String {
+        if self.variant.is_empty() {
+            self.layout.clone()
+        } else {
+            format!("{} ({})", self.layout, self.variant)
+        }
+    }
+}
+
+/// The active layout of the main keyboard, from Hyprland or else localectl.
+pub async fn active_layout() -> Option<KeyboardLayout> {
+    if let Some(raw) = output("hyprctl", &["devices", "-j"]).await {
+        if let Some(layout) = parse_hyprctl_devices(&raw) {
+            return Some(layout);
+        }
+    }
+    parse_localectl(&output("localectl", &["status"]).await?)
+}
+
+async fn output(command: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(command).args(args).output().await.ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Layout of the main keyboard in `hyprctl devices -j`. `layout` and
+/// `variant` may list several comma-separated layouts; the active one is
+/// picked by `active_layout_index` when present.
+fn parse_hyprctl_devices(raw: &str) -> Option<KeyboardLayout> {
+    let json: Value = serde_json::from_str(raw).ok()?;
+    let keyboards = json.get("keyboards")?.as_array()?;
+    let keyboard = keyboards
+        .iter()
+        .find(|kb| kb.get("main").and_then(Value::as_bool) == Some(true))
+        .or_else(|| keyboards.first())?;
+    let index = keyboard
+        .get("active_layout_index")
+        .and_then(Value::as_u64)
+        .unwrap_or(0) as usize;
+    let pick = |field: &str| {
+        keyboard
+            .get(field)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .split(',')
+            .nth(index)
+            .unwrap_or_default()
+            .trim()
+            .to_string()
+    };
+    let layout = pick("layout");
+    (!layout.is_empty()).then(|| KeyboardLayout {
+        layout,
+        variant: pick("variant"),
+    })
+}
+
+fn parse_localectl(raw: &str) -> Option<KeyboardLayout> {
+    let field = |name: &str| {
+        raw.lines()
+            .find_map(|line| line.trim().strip_prefix(name))
+            .and_then(|rest| rest.trim().strip_prefix(':'))
+            .map(|value| {
+                value
+                    .split(',')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string()
+            })
+            .unwrap_or_default()
+    };
+    let layout = field("X11 Layout");
+    (!layout.is_empty()).then(|| KeyboardLayout {
+        layout,
+        variant: field("X11 Variant"),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transcoded {
+    /// Text to send as US keycodes.
+    Text(String),
+    /// Characters the layout cannot produce without AltGr or dead keys.
+    Unsupported(Vec<char>),
+    /// No table for the layout; the text is sent unchanged.
+    UnknownLayout,
+}
+
+/// Variants whose base keys match the layout's default.
+const COMPATIBLE_VARIANTS: &[&str] = &["", "basic", "nodeadkeys", "oss", "latin9", "extd"];
+
+/// Characters to send as US keycodes so `text` appears on `layout`.
+pub fn transcode_for_us_keycodes(text: &str, layout: &KeyboardLayout) -> Transcoded {
+    if !COMPATIBLE_VARIANTS.contains(&layout.variant.as_str()) {
+        return Transcoded::UnknownLayout;
+    }
+    let table: HashMap<char, char> = match layout.layout.as_str() {
+        "us" => HashMap::new(),
+        "gb" => GB.iter().copied().collect(),
+        "de" => DE.iter().copied().collect(),
+        "fr" => FR.iter().copied().collect(),
+        _ => return Transcoded::UnknownLayout,
+    };
+    let unreachable = match layout.layout.as_str() {
+        "gb" => GB_UNREACHABLE,
+        "de" => DE_UNREACHABLE,
+        "fr" => FR_UNREACHABLE,
+        _ => "",
+    };
+    let mut out = String::with_capacity(text.len());
+    let mut unsupported = Vec::new();
+    for c in text.chars() {
+        match table.get(&c) {
+            Some(us) => out.push(*us),
+            None if c.is_ascii() && !unreachable.contains(c) => out.push(c),
+            None => {
+                if !unsupported.contains(&c) {
+                    unsupported.push(c);
+                }
+            }
+        }
+    }
+    if unsupported.is_empty() {
+        Transcoded::Text(out)
+    } else {
+        Transcoded::Unsupported(unsupported)
+    }
+}
+
+/// (wanted character, US character on the key that produces it).
+const GB: &[(char, char)] = &[('"', '@'), ('@', '"'), ('£', '#'), ('#', '\\'), ('~', '|')];
+/// On the extra ISO key or behind AltGr.
+const GB_UNREACHABLE: &str = "\\|";
+
+const DE: &[(char, char)] = &[
+    ('y', 'z'),
+    ('z', 'y'),
+    ('Y', 'Z'),
+    ('Z', 'Y'),
+    ('ü', '['),
+    ('Ü', '{'),
+    ('ö', ';'),
+    ('Ö', ':'),
+    ('ä', '\''),
+    ('Ä', '"'),
+    ('ß', '-'),
+    ('"', '@'),
+    ('§', '#'),
+    ('&', '^'),
+    ('/', '&'),
+    ('(', '*'),
+    (')', '('),
+    ('=', ')'),
+    ('?', '_'),
+    ('+', ']'),
+    ('*', '}'),
+    ('#', '\\'),
+    ('\'', '|'),
+    ('-', '/'),
+    ('_', '?'),
+    (';', '<'),
+    (':', '>'),
+];
+const DE_UNREACHABLE: &str = "@[]{}\\|~^`<>";
+
+const FR: &[(char, char)] = &[
+    ('a', 'q'),
+    ('q', 'a'),
+    ('A', 'Q'),
+    ('Q', 'A'),
+    ('z', 'w'),
+    ('w', 'z'),
+    ('Z', 'W'),
+    ('W', 'Z'),
+    ('m', ';'),
+    ('M', ':'),
+    (',', 'm'),
+    ('?', 'M'),
+    (';', ','),
+    ('.', '<'),
+    (':', '.'),
+    ('/', '>'),
+    ('!', '/'),
+    ('§', '?'),
+    ('1', '!'),
+    ('2', '@'),
+    ('3', '#'),
+    ('4', '$'),
+    ('5', '%'),
+    ('6', '^'),
+    ('7', '&'),
+    ('8', '*'),
+    ('9', '('),
+    ('0', ')'),
+    ('&', '1'),
+    ('é', '2'),
+    ('"', '3'),
+    ('\'', '4'),
+    ('(', '5'),
+    ('-', '6'),
+    ('è', '7'),
+    ('_', '8'),
+    ('ç', '9'),
+    ('à', '0'),
+    (')', '-'),
+    ('°', '_'),
+    ('ù', '\''),
+    ('%', '"'),
+    ('*', '\\'),
+    ('µ', '|'),
+    ('$', ']'),
+    ('£', '}'),
+];
+const FR_UNREACHABLE: &str = "@[]{}\\|~^`<>#";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(layout: &str, variant: &str) -> KeyboardLayout {
+        KeyboardLayout {
+            layout: layout.to_string(),
+            variant: variant.to_string(),
+        }
+    }
+
+    #[test]
+    fn active_layout_comes_from_the_main_keyboard() {
+        let raw = r#"{"keyboards": [
+            {"name": "power-button", "layout": "us", "variant": "", "main": false},
+            {"name": "at-keyboard", "layout": "us,de", "variant": ",nodeadkeys",
+             "active_keymap": "German (no dead keys)", "active_layout_index": 1, "main": true}
+        ]}"#;
+        assert_eq!(parse_hyprctl_devices(raw), Some(layout("de", "nodeadkeys")));
+        let raw = "   System Locale: LANG=fr_FR.UTF-8\n       VC Keymap: fr\n      X11 Layout: fr\n       X11 Model: pc105\n";
+        assert_eq!(parse_localectl(raw), Some(layout("fr", "")));
+    }
+
+    #[test]
+    fn text_is_transcoded_to_us_key_positions() {
+        assert_eq!(
+            transcode_for_us_keycodes("Grüße, Zoe (42)?", &layout("de", "nodeadkeys")),
+            Transcoded::Text("Gr[-e, Yoe *42(_".to_string())
+        );
+        assert_eq!(
+            transcode_for_us_keycodes("a1,m", &layout("fr", "")),
+            Transcoded::Text("q!m;".to_string())
+        );
+        assert_eq!(
+            transcode_for_us_keycodes("me@example.com", &layout("de", "")),
+            Transcoded::Unsupported(vec!['@'])
+        );
+        assert_eq!(
+            transcode_for_us_keycodes("café", &layout("us", "")),
+            Transcoded::Unsupported(vec!['é'])
+        );
+        assert_eq!(
+            transcode_for_us_keycodes("hello", &layout("fr", "bepo")),
+            Transcoded::UnknownLayout
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Man page and `--help` lookup for installed commands
+//!
+//! Used before building a shell invocation so flags come from the installed
+//! version of a tool rather than from memory. Pages are reduced to the
+//! synopsis and option list and capped in size.
+
+use super::{OsError, OsResult};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Man page width, so option descriptions wrap predictably.
+const MAN_WIDTH: &str = "100";
+
+/// Default cap on a summarized page.
+pub const MAX_SUMMARY_CHARS: usize = 6_000;
+
+const TRUNCATED_MARKER: &str = "\n…[truncated]";
+
+/// Sections kept whole in a summary.
+const KEPT_SECTIONS: &[&str] = &["NAME", "SYNOPSIS", "USAGE"];
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct HelpPage {
+    pub command: String,
+    /// `man`, `--help` or `-h`.
+    pub source: String,
+    pub text: String,
+}
+
+/// Whether `name` looks like a bare command name (no paths, options or shell
+/// syntax).
+pub fn valid_command_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '+'))
+}
+
+async fn command_exists(command: &str) -> bool {
+    Command::new("which")
+        .arg(command)
+        .output()
+        .await
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// Output of a lookup command, stdout first and stderr if stdout is empty
+/// (many tools print usage to stderr). `None` on failure, timeout or empty
+/// output.
+async fn capture(program: &str, args: &[&str]) -> Option<String> {
+    let child = Command::new(program)
+        .args(args)
+        .env("MANPAGER", "cat")
+        .env("MANWIDTH", MAN_WIDTH)
+        .env("PAGER", "cat")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .ok()?;
+    let output = tokio::time::timeout(LOOKUP_TIMEOUT, child.wait_with_output())
+        .await
+        .ok()?
+        .ok()?;
+    [output.stdout, output.stderr]
+        .iter()
+        .map(|bytes| strip_overstrike(&String::from_utf8_lossy(bytes)))
+        .find(|text| !text.trim().is_empty())
+}
+
+/// Man page for `command`, falling back to `--help` and then `-h`.
+pub async fn lookup(command: &str) -> OsResult<HelpPage> {
+    if !valid_command_name(command) {
+        return Err(OsError::InvalidArgument(format!(
+            "'{command}' is not a command name"
+        )));
+    }
+    if !command_exists(command).await {
+        return Err(OsError::NotFound(format!("{command} is not installed")));
+    }
+
+    let page = |source: &str, text: String| HelpPage {
+        command: command.to_string(),
+        source: source.to_string(),
+        text,
+    };
+    if command_exists("man").await {
+        if let Some(text) = capture("man", &[command]).await {
+            return Ok(page("man", text));
+        }
+    }
+    for flag in ["--help", "-h"] {
+        if let Some(text) = capture(command, &[flag]).await {
+            return Ok(page(flag, text));
+        }
+    }
+    Err(OsError::NotFound(format!(
+        "no man page or --help output for {command}"
+    )))
+}
+
+/// Removes the `X\bX` bold and `_\bX` underline sequences `man` emits when
+/// it is not writing to a terminal.
+pub fn strip_overstrike(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '\u{8}' {
+            out.pop();
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Reduces a page to its synopsis and option entries, keeping only options
+/// that mention `topic` when one is given, and caps it at `max_chars`.
+/// Returns the summary and whether anything was cut for length.
+pub fn summarize(text: &str, topic: Option<&str>, max_chars: usize) -> (String, bool) {
+    let topic = topic
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty());
+    let mut out = String::new();
+    let mut section = String::new();
+    // Lines of the option entry being collected.
+    let mut entry: Vec<&str> = Vec::new();
+    let mut entry_indent = 0;
+
+    let flush = |entry: &mut Vec<&str>, out: &mut String| {
+        if entry.is_empty() {
+            return;
+        }
+        let text = entry.join("\n");
+        let keep = topic
+            .as_deref()
+            .is_none_or(|topic| text.to_lowercase().contains(topic));
+        if keep {
+            out.push_str(&text);
+            out.push('\n');
+        }
+        entry.clear();
+    };
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        let is_heading = indent == 0
+            && !trimmed.is_empty()
+            && trimmed
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || matches!(c, ' ' | '-' | ':'));
+        if is_heading {
+            flush(&mut entry, &mut out);
+            section = trimmed.trim_end_matches(':').to_string();
+            if KEPT_SECTIONS.contains(&section.as_str()) && topic.is_none() {
+                out.push_str(trimmed);
+                out.push('\n');
+            }
+            continue;
+        }
+        if KEPT_SECTIONS.contains(&section.as_str()) && topic.is_none() {
+            if !trimmed.is_empty() {
+                out.push_str(line);
+                out.push('\n');
+            }
+            continue;
+        }
+        if trimmed.starts_with('-') {
+            flush(&mut entry, &mut out);
+            entry.push(line);
+            entry_indent = indent;
+        } else if !entry.is_empty() && !trimmed.is_empty() && indent > entry_indent {
+            entry.push(line);
+        } else {
+            flush(&mut entry, &mut out);
+        }
+    }
+    flush(&mut entry, &mut out);
+
+    if out.trim().is_empty() && topic.is_none() {
+        out = text.to_string();
+    }
+    let out = out.trim_end();
+    if out.chars().count() <= max_chars {
+        return (out.to_string(), false);
+    }
+    let cut = out
+        .char_indices()
+        .nth(max_chars)
+        .map_or(out.len(), |(at, _)| at);
+    (format!("{}{}", &out[..cut], TRUNCATED_MARKER), true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "GRIM(1)\n\nNAME\n       grim - grab images from a Wayland compositor\n\nSYNOPSIS\n       grim [options...] [output-file]\n\nDESCRIPTION\n       grim is a screenshot utility.\n\nOPTIONS\n       -h     Show help message and quit.\n\n       -g \"<x>,<y> <width>x<height>\"\n              Set the region to capture.\n\n       -o <output>\n              Set the output name to capture.\n\nAUTHORS\n       Simon Ser\n";
+
+    #[test]
+    fn summary_keeps_synopsis_and_options() {
+        let (summary, truncated) = summarize(PAGE, None, MAX_SUMMARY_CHARS);
+        assert!(!truncated);
+        assert!(summary.starts_with("NAME\n       grim - grab images"));
+        assert!(summary.contains("grim [options...] [output-file]"));
+        assert!(summary.contains("-g \"<x>,<y> <width>x<height>\"\n              Set the region"));
+        assert!(!summary.contains("screenshot utility"));
+        assert!(!summary.contains("Simon Ser"));
+
+        let (summary, _) = summarize(PAGE, Some("region"), MAX_SUMMARY_CHARS);
+        assert_eq!(
+            summary,
+            "       -g \"<x>,<y> <width>x<height>\"\n              Set the region to capture."
+        );
+
+        let (summary, truncated) = summarize(PAGE, None, 20);
+        assert!(truncated);
+        assert!(summary.ends_with(TRUNCATED_MARKER));
+    }
+
+    #[test]
+    fn overstrike_and_command_names() {
+        assert_eq!(
+            strip_overstrike("N\u{8}NA\u{8}AM\u{8}ME\u{8}E _\u{8}x"),
+            "NAME x"
+        );
+        assert!(valid_command_name("hyprctl"));
+        assert!(valid_command_name("g++"));
+        assert!(!valid_command_name("rm -rf"));
+        assert!(!valid_command_name("../bin/sh"));
+        assert!(!valid_command_name("--help"));
+    }
+}
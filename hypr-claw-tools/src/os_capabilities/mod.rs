@@ -5,12 +5,34 @@
 //! - Process management
 //! - Hyprland control
 //! - System operations
+//! - Document generation
+//! - SQLite databases
+//! - Man page and --help lookup
+//! - Scheduled jobs (systemd user timers, crontab)
+//! - ydotool daemon health and user service
+//! - Secure deletion
+//! - OCR languages and tuning
+//! - Keyboard layout detection for typing
+//! - GPU and display information
+//! - Temperatures and fan speeds
+//! - Backend degradation chains for desktop tools
 
+pub mod database;
+pub mod degradation;
 pub mod desktop;
+pub mod documents;
 pub mod filesystem;
+pub mod hardware;
 pub mod hyprland;
+pub mod keyboard;
+pub mod manual;
+pub mod ocr;
 pub mod process;
+pub mod schedule;
 pub mod system;
+pub mod thermal;
+pub mod wipe;
+pub mod ydotool;
 
 /// OS capability error types
 #[derive(Debug, thiserror::Error)]
@@ -0,0 +1,237 @@
+//! OCR languages: the default tesseract language, per-language tuning, the
+//! installed language packs and the desktop's UI language.
+//!
+//! Settings are process-wide and set once from config at startup, since the
+//! OCR tools and the type/click verification paths all share them. A `lang`
+//! passed to a single call still wins over the default.
+
+use super::{OsError, OsResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use tokio::process::Command;
+
+/// Word confidence below which OCR words are ignored when matching text.
+pub const DEFAULT_MIN_CONFIDENCE: f32 = 25.0;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct OcrSettings {
+    /// Tesseract language used when a call gives none, e.g. `deu+eng`.
+    /// Unset means the UI language (when its pack is installed) plus English.
+    pub language: Option<String>,
+    /// Tuning by language, keyed by the full language (`jpn+eng`) or its
+    /// first component (`jpn`).
+    pub tuning: BTreeMap<String, OcrTuning>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct OcrTuning {
+    /// Tesseract page segmentation mode (`--psm`).
+    pub psm: Option<u8>,
+    /// Replaces [`DEFAULT_MIN_CONFIDENCE`] for find/click/wait matching.
+    pub min_confidence: Option<f32>,
+}
+
+static SETTINGS: RwLock<OcrSettings> = RwLock::new(OcrSettings {
+    language: None,
+    tuning: BTreeMap::new(),
+});
+
+pub fn configure(settings: OcrSettings) {
+    *SETTINGS.write().unwrap_or_else(|e| e.into_inner()) = settings;
+}
+
+/// The language for a call: its own `lang`, else the configured default.
+pub fn language(per_call: Option<&str>) -> Option<String> {
+    per_call
+        .map(str::to_string)
+        .or_else(|| {
+            SETTINGS
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .language
+                .clone()
+        })
+        .filter(|lang| !lang.is_empty())
+}
+
+/// Tuning for the language a call resolves to.
+pub fn tuning(per_call: Option<&str>) -> OcrTuning {
+    let Some(lang) = language(per_call) else {
+        return OcrTuning::default();
+    };
+    let settings = SETTINGS.read().unwrap_or_else(|e| e.into_inner());
+    let primary = lang.split('+').next().unwrap_or_default();
+    settings
+        .tuning
+        .get(&lang)
+        .or_else(|| settings.tuning.get(primary))
+        .cloned()
+        .unwrap_or_default()
+}
+
+pub fn min_confidence(per_call: Option<&str>) -> f32 {
+    tuning(per_call)
+        .min_confidence
+        .unwrap_or(DEFAULT_MIN_CONFIDENCE)
+}
+
+/// Tesseract language codes joined by `+`, e.g. `chi_sim+eng`.
+pub fn valid_language(lang: &str) -> bool {
+    !lang.is_empty()
+        && lang.split('+').all(|code| {
+            !code.is_empty()
+                && code
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        })
+}
+
+/// Languages tesseract has packs for.
+pub async fn installed_languages() -> OsResult<Vec<String>> {
+    let output = Command::new("tesseract")
+        .arg("--list-langs")
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(OsError::OperationFailed(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+    Ok(parse_list_langs(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_list_langs(raw: &str) -> Vec<String> {
+    raw.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("List of"))
+        .filter(|line| *line != "osd")
+        .map(str::to_string)
+        .collect()
+}
+
+/// Codes of `lang` without an installed pack.
+pub fn missing_languages(lang: &str, installed: &[String]) -> Vec<String> {
+    lang.split('+')
+        .filter(|code| !installed.iter().any(|have| have == code))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Distro package holding the tesseract pack for `code`.
+pub fn language_pack(code: &str, manager: &str) -> String {
+    match manager {
+        "apt-get" => format!("tesseract-ocr-{}", code.replace('_', "-")),
+        "dnf" => format!("tesseract-langpack-{code}"),
+        "zypper" => format!("tesseract-ocr-traineddata-{code}"),
+        _ => format!("tesseract-data-{code}"),
+    }
+}
+
+/// The desktop's UI locale from `LC_ALL`, `LC_MESSAGES` or `LANG`, if it is
+/// a real language (not `C`/`POSIX`).
+pub fn ui_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .filter(|value| !matches!(value.as_str(), "C" | "POSIX") && !value.starts_with("C."))
+}
+
+/// Tesseract code for a locale like `de_DE.UTF-8` or `zh_TW`.
+pub fn language_for_locale(locale: &str) -> Option<&'static str> {
+    let tag = locale.split(['.', '@']).next().unwrap_or_default();
+    let (language, region) = tag.split_once('_').unwrap_or((tag, ""));
+    Some(match (language, region) {
+        ("zh", "TW" | "HK") => "chi_tra",
+        ("zh", _) => "chi_sim",
+        ("en", _) => "eng",
+        ("de", _) => "deu",
+        ("fr", _) => "fra",
+        ("es", _) => "spa",
+        ("it", _) => "ita",
+        ("pt", _) => "por",
+        ("nl", _) => "nld",
+        ("pl", _) => "pol",
+        ("cs", _) => "ces",
+        ("sv", _) => "swe",
+        ("da", _) => "dan",
+        ("nb" | "no", _) => "nor",
+        ("fi", _) => "fin",
+        ("tr", _) => "tur",
+        ("ru", _) => "rus",
+        ("uk", _) => "ukr",
+        ("el", _) => "ell",
+        ("ar", _) => "ara",
+        ("he", _) => "heb",
+        ("hi", _) => "hin",
+        ("ja", _) => "jpn",
+        ("ko", _) => "kor",
+        ("vi", _) => "vie",
+        ("th", _) => "tha",
+        _ => return None,
+    })
+}
+
+/// Default language when config sets none: the UI language plus English,
+/// if its pack is installed.
+pub fn default_language(ui_locale: Option<&str>, installed: &[String]) -> Option<String> {
+    let code = language_for_locale(ui_locale?)?;
+    if code == "eng" || !installed.iter().any(|have| have == code) {
+        return None;
+    }
+    Some(if installed.iter().any(|have| have == "eng") {
+        format!("{code}+eng")
+    } else {
+        code.to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn languages_follow_the_locale_and_installed_packs() {
+        let installed = parse_list_langs(
+            "List of available languages in \"/usr/share/tessdata/\" (3):\neng\ndeu\nosd\n",
+        );
+        assert_eq!(installed, vec!["eng", "deu"]);
+        assert_eq!(
+            default_language(Some("de_DE.UTF-8"), &installed).as_deref(),
+            Some("deu+eng")
+        );
+        assert_eq!(default_language(Some("ja_JP.UTF-8"), &installed), None);
+        assert_eq!(default_language(Some("en_US.UTF-8"), &installed), None);
+        assert_eq!(language_for_locale("zh_TW.UTF-8"), Some("chi_tra"));
+        assert_eq!(missing_languages("jpn+eng", &installed), vec!["jpn"]);
+        assert_eq!(language_pack("chi_sim", "apt-get"), "tesseract-ocr-chi-sim");
+        assert_eq!(language_pack("jpn", "pacman"), "tesseract-data-jpn");
+        assert!(valid_language("chi_sim+eng"));
+        assert!(!valid_language("eng; rm"));
+        assert!(!valid_language("deu+"));
+    }
+
+    #[test]
+    fn per_call_language_wins_and_tuning_matches_the_primary_code() {
+        configure(OcrSettings {
+            language: Some("deu+eng".to_string()),
+            tuning: [(
+                "jpn".to_string(),
+                OcrTuning {
+                    psm: Some(6),
+                    min_confidence: Some(10.0),
+                },
+            )]
+            .into(),
+        });
+        assert_eq!(language(None).as_deref(), Some("deu+eng"));
+        assert_eq!(language(Some("jpn+eng")).as_deref(), Some("jpn+eng"));
+        assert_eq!(tuning(Some("jpn+eng")).psm, Some(6));
+        assert_eq!(min_confidence(Some("jpn")), 10.0);
+        assert_eq!(min_confidence(None), DEFAULT_MIN_CONFIDENCE);
+        configure(OcrSettings::default());
+    }
+}
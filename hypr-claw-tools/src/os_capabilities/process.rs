@@ -1,12 +1,36 @@
 //! Process management - spawn, kill, list processes
 
 use super::{OsError, OsResult};
+use std::collections::BTreeMap;
 use sysinfo::System;
 use tokio::process::Command;
 use tokio::task;
 
 /// Spawn a process
 pub async fn spawn(command: &str, args: &[&str]) -> OsResult<u32> {
+    spawn_with_env(command, args, &BTreeMap::new()).await
+}
+
+/// [`spawn`] with `env` set on top of the inherited environment.
+pub async fn spawn_with_env(
+    command: &str,
+    args: &[&str],
+    env: &BTreeMap<String, String>,
+) -> OsResult<u32> {
+    let (program, normalized_args) = split_command(command, args)?;
+    let child = Command::new(program)
+        .args(normalized_args.iter().map(String::as_str))
+        .envs(env)
+        .spawn()?;
+
+    Ok(child
+        .id()
+        .ok_or_else(|| OsError::OperationFailed("Failed to get process ID".to_string()))?)
+}
+
+/// Program and arguments for a spawn request. A command with spaces and no
+/// separate arguments is split on whitespace.
+pub fn split_command(command: &str, args: &[&str]) -> OsResult<(String, Vec<String>)> {
     let trimmed = command.trim();
     if trimmed.is_empty() {
         return Err(OsError::InvalidArgument(
@@ -36,14 +60,7 @@ pub async fn spawn(command: &str, args: &[&str]) -> OsResult<u32> {
                     .collect::<Vec<String>>(),
             )
         };
-
-    let child = Command::new(program)
-        .args(normalized_args.iter().map(String::as_str))
-        .spawn()?;
-
-    Ok(child
-        .id()
-        .ok_or_else(|| OsError::OperationFailed("Failed to get process ID".to_string()))?)
+    Ok((program, normalized_args))
 }
 
 /// Kill a process by PID
@@ -0,0 +1,413 @@
+//! Scheduled jobs - user systemd timers and crontab entries
+
+use super::{OsError, OsResult};
+use std::path::PathBuf;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Prefix for every unit/crontab entry created by hypr-claw, so listing and
+/// removal never touch jobs the user wrote by hand.
+pub const UNIT_PREFIX: &str = "hypr-claw-";
+const CRON_MARKER: &str = "# hypr-claw:";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobSpec {
+    pub name: String,
+    pub description: String,
+    pub command: Vec<String>,
+    pub working_dir: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ScheduledJob {
+    pub name: String,
+    pub backend: String,
+    pub schedule: String,
+    pub command: String,
+    pub next_run: Option<String>,
+    pub files: Vec<String>,
+}
+
+pub fn validate_name(name: &str) -> OsResult<()> {
+    if name.is_empty()
+        || name.len() > 48
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        || name.starts_with('-')
+    {
+        return Err(OsError::InvalidArgument(
+            "name must be 1-48 chars of lowercase letters, digits and '-'".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_command(command: &[String]) -> OsResult<()> {
+    let Some(program) = command.first() else {
+        return Err(OsError::InvalidArgument(
+            "command cannot be empty".to_string(),
+        ));
+    };
+    if !program.starts_with('/') {
+        return Err(OsError::InvalidArgument(
+            "command[0] must be an absolute path".to_string(),
+        ));
+    }
+    if command
+        .iter()
+        .any(|arg| arg.contains('\n') || arg.contains('\0') || arg.contains('%'))
+    {
+        return Err(OsError::InvalidArgument(
+            "command arguments cannot contain newlines, NUL or '%'".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Validates a five-field cron expression (or an `@daily`-style shortcut).
+pub fn validate_cron(expr: &str) -> OsResult<()> {
+    let expr = expr.trim();
+    if expr.starts_with('@') {
+        return match expr {
+            "@hourly" | "@daily" | "@weekly" | "@monthly" | "@yearly" | "@reboot" => Ok(()),
+            _ => Err(OsError::InvalidArgument(format!(
+                "unknown cron shortcut '{expr}'"
+            ))),
+        };
+    }
+    let fields: Vec<&str> = expr.split_whitespace().collect();
+    if fields.len() != 5 {
+        return Err(OsError::InvalidArgument(
+            "cron expression must have 5 fields (min hour dom month dow)".to_string(),
+        ));
+    }
+    if let Some(bad) = fields.iter().find(|f| {
+        !f.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "*/,-".contains(c))
+    }) {
+        return Err(OsError::InvalidArgument(format!(
+            "invalid cron field '{bad}'"
+        )));
+    }
+    Ok(())
+}
+
+fn quote_arg(arg: &str) -> String {
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/._-=:,+@".contains(c))
+    {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+pub fn render_service_unit(spec: &JobSpec) -> String {
+    let exec: Vec<String> = spec.command.iter().map(|a| quote_arg(a)).collect();
+    let mut unit = format!(
+        "[Unit]\nDescription={}\n\n[Service]\nType=oneshot\nExecStart={}\n",
+        spec.description,
+        exec.join(" ")
+    );
+    if let Some(dir) = &spec.working_dir {
+        unit.push_str(&format!("WorkingDirectory={dir}\n"));
+    }
+    unit
+}
+
+pub fn render_timer_unit(spec: &JobSpec, on_calendar: &str, persistent: bool) -> String {
+    format!(
+        "[Unit]\nDescription=Timer for {}\n\n[Timer]\nOnCalendar={}\nPersistent={}\n\n[Install]\nWantedBy=timers.target\n",
+        spec.description, on_calendar, persistent
+    )
+}
+
+/// Returns `existing` crontab text with the named entry replaced or appended.
+pub fn crontab_with_entry(existing: &str, spec: &JobSpec, cron: &str) -> String {
+    let mut out = crontab_without_entry(existing, &spec.name);
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    let mut command: Vec<String> = spec.command.iter().map(|a| quote_arg(a)).collect();
+    if let Some(dir) = &spec.working_dir {
+        command.insert(0, format!("cd {} &&", quote_arg(dir)));
+    }
+    out.push_str(&format!(
+        "{cron} {} {CRON_MARKER}{}\n",
+        command.join(" "),
+        spec.name
+    ));
+    out
+}
+
+pub fn crontab_without_entry(existing: &str, name: &str) -> String {
+    let marker = format!("{CRON_MARKER}{name}");
+    existing
+        .lines()
+        .filter(|line| !line.trim_end().ends_with(&marker))
+        .map(|line| format!("{line}\n"))
+        .collect()
+}
+
+fn parse_crontab_jobs(crontab: &str) -> Vec<ScheduledJob> {
+    crontab
+        .lines()
+        .filter_map(|line| {
+            let (body, name) = line.rsplit_once(CRON_MARKER)?;
+            let body = body.trim();
+            let split = if body.starts_with('@') { 1 } else { 5 };
+            let parts: Vec<&str> = body.splitn(split + 1, ' ').collect();
+            Some(ScheduledJob {
+                name: name.trim().to_string(),
+                backend: "cron".to_string(),
+                schedule: parts[..split.min(parts.len())].join(" "),
+                command: parts.get(split).unwrap_or(&"").to_string(),
+                next_run: None,
+                files: Vec::new(),
+            })
+        })
+        .collect()
+}
+
+fn unit_dir() -> OsResult<PathBuf> {
+    let base = std::env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|_| std::env::var("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .map_err(|_| OsError::OperationFailed("cannot resolve config directory".to_string()))?;
+    Ok(base.join("systemd").join("user"))
+}
+
+async fn run_checked(command: &str, args: &[&str]) -> OsResult<String> {
+    let output = Command::new(command).args(args).output().await?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).to_string());
+    }
+    Err(OsError::OperationFailed(
+        String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    ))
+}
+
+/// Validate an OnCalendar expression with systemd-analyze.
+pub async fn validate_on_calendar(expr: &str) -> OsResult<()> {
+    if expr.trim().is_empty() || expr.contains('\n') {
+        return Err(OsError::InvalidArgument(
+            "on_calendar cannot be empty".to_string(),
+        ));
+    }
+    run_checked("systemd-analyze", &["calendar", expr])
+        .await
+        .map(|_| ())
+        .map_err(|e| OsError::InvalidArgument(format!("invalid on_calendar '{expr}': {e}")))
+}
+
+/// Write, enable and start a user systemd timer. Returns the unit file paths.
+pub async fn create_systemd_timer(
+    spec: &JobSpec,
+    on_calendar: &str,
+    persistent: bool,
+) -> OsResult<Vec<String>> {
+    validate_name(&spec.name)?;
+    validate_command(&spec.command)?;
+    validate_on_calendar(on_calendar).await?;
+
+    let dir = unit_dir()?;
+    fs::create_dir_all(&dir).await?;
+    let unit = format!("{UNIT_PREFIX}{}", spec.name);
+    let service_path = dir.join(format!("{unit}.service"));
+    let timer_path = dir.join(format!("{unit}.timer"));
+    fs::write(&service_path, render_service_unit(spec)).await?;
+    fs::write(
+        &timer_path,
+        render_timer_unit(spec, on_calendar, persistent),
+    )
+    .await?;
+
+    run_checked("systemctl", &["--user", "daemon-reload"]).await?;
+    run_checked(
+        "systemctl",
+        &["--user", "enable", "--now", &format!("{unit}.timer")],
+    )
+    .await?;
+    Ok(vec![
+        service_path.display().to_string(),
+        timer_path.display().to_string(),
+    ])
+}
+
+async fn read_crontab() -> OsResult<String> {
+    let output = Command::new("crontab").arg("-l").output().await?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).to_string());
+    }
+    // `crontab -l` fails when the user has no crontab yet.
+    Ok(String::new())
+}
+
+async fn write_crontab(content: &str) -> OsResult<()> {
+    let mut child = Command::new("crontab")
+        .arg("-")
+        .stdin(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(content.as_bytes()).await?;
+    }
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        return Err(OsError::OperationFailed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Install or replace a crontab entry tagged with the job name.
+pub async fn create_cron_entry(spec: &JobSpec, cron: &str) -> OsResult<()> {
+    validate_name(&spec.name)?;
+    validate_command(&spec.command)?;
+    validate_cron(cron)?;
+    let existing = read_crontab().await?;
+    write_crontab(&crontab_with_entry(&existing, spec, cron)).await
+}
+
+/// List jobs created by hypr-claw across both backends.
+pub async fn list_jobs() -> OsResult<Vec<ScheduledJob>> {
+    let mut jobs = Vec::new();
+    if let Ok(dir) = unit_dir() {
+        if let Ok(mut entries) = fs::read_dir(&dir).await {
+            while let Some(entry) = entries.next_entry().await? {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                let Some(unit) = file_name.strip_suffix(".timer") else {
+                    continue;
+                };
+                let Some(name) = unit.strip_prefix(UNIT_PREFIX) else {
+                    continue;
+                };
+                let timer = fs::read_to_string(entry.path()).await.unwrap_or_default();
+                let service_path = dir.join(format!("{unit}.service"));
+                let service = fs::read_to_string(&service_path).await.unwrap_or_default();
+                let field = |text: &str, key: &str| {
+                    text.lines()
+                        .find_map(|l| l.strip_prefix(key))
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                let next_run = run_checked(
+                    "systemctl",
+                    &[
+                        "--user",
+                        "show",
+                        &file_name,
+                        "--property=NextElapseUSecRealtime",
+                        "--value",
+                    ],
+                )
+                .await
+                .ok()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty());
+                jobs.push(ScheduledJob {
+                    name: name.to_string(),
+                    backend: "systemd".to_string(),
+                    schedule: field(&timer, "OnCalendar="),
+                    command: field(&service, "ExecStart="),
+                    next_run,
+                    files: vec![
+                        service_path.display().to_string(),
+                        entry.path().display().to_string(),
+                    ],
+                });
+            }
+        }
+    }
+    jobs.extend(parse_crontab_jobs(&read_crontab().await?));
+    jobs.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(jobs)
+}
+
+/// Remove a job from whichever backend holds it. Returns false if not found.
+pub async fn remove_job(name: &str) -> OsResult<bool> {
+    validate_name(name)?;
+    let mut removed = false;
+
+    let dir = unit_dir()?;
+    let unit = format!("{UNIT_PREFIX}{name}");
+    let timer_path = dir.join(format!("{unit}.timer"));
+    if timer_path.exists() {
+        let _ = run_checked(
+            "systemctl",
+            &["--user", "disable", "--now", &format!("{unit}.timer")],
+        )
+        .await;
+        fs::remove_file(&timer_path).await?;
+        let service_path = dir.join(format!("{unit}.service"));
+        if service_path.exists() {
+            fs::remove_file(service_path).await?;
+        }
+        let _ = run_checked("systemctl", &["--user", "daemon-reload"]).await;
+        removed = true;
+    }
+
+    let crontab = read_crontab().await?;
+    let filtered = crontab_without_entry(&crontab, name);
+    if filtered.len() != crontab.len() {
+        write_crontab(&filtered).await?;
+        removed = true;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> JobSpec {
+        JobSpec {
+            name: "nightly-backup".to_string(),
+            description: "Nightly backup".to_string(),
+            command: vec![
+                "/home/u/bin/backup.sh".to_string(),
+                "--to".to_string(),
+                "my disk".to_string(),
+            ],
+            working_dir: Some("/home/u".to_string()),
+        }
+    }
+
+    #[test]
+    fn renders_units_with_quoted_args() {
+        let service = render_service_unit(&spec());
+        assert!(service.contains("ExecStart=/home/u/bin/backup.sh --to 'my disk'\n"));
+        assert!(service.contains("WorkingDirectory=/home/u\n"));
+        let timer = render_timer_unit(&spec(), "*-*-* 02:00:00", true);
+        assert!(timer.contains("OnCalendar=*-*-* 02:00:00\n"));
+        assert!(timer.contains("Persistent=true\n"));
+    }
+
+    #[test]
+    fn crontab_entries_replace_and_remove_by_name() {
+        let existing = "0 1 * * * /usr/bin/true\n";
+        let once = crontab_with_entry(existing, &spec(), "0 2 * * *");
+        let twice = crontab_with_entry(&once, &spec(), "30 3 * * *");
+        assert_eq!(twice.lines().count(), 2);
+        assert!(twice.contains("30 3 * * * cd /home/u && /home/u/bin/backup.sh"));
+        let jobs = parse_crontab_jobs(&twice);
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].schedule, "30 3 * * *");
+        assert_eq!(crontab_without_entry(&twice, "nightly-backup"), existing);
+    }
+
+    #[test]
+    fn validates_names_commands_and_cron() {
+        assert!(validate_name("nightly-backup").is_ok());
+        assert!(validate_name("Bad Name").is_err());
+        assert!(validate_command(&["backup.sh".to_string()]).is_err());
+        assert!(validate_cron("0 2 * * *").is_ok());
+        assert!(validate_cron("@daily").is_ok());
+        assert!(validate_cron("0 2 * *").is_err());
+        assert!(validate_cron("0 2 * * * ; rm").is_err());
+    }
+}
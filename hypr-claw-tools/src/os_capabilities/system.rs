@@ -1,6 +1,6 @@
 //! System operations - wallpaper, power, system info
 
-use super::{OsError, OsResult};
+use super::{degradation, OsError, OsResult};
 use std::path::Path;
 use sysinfo::System;
 use tokio::fs;
@@ -26,45 +26,37 @@ async fn run_checked(command: &str, args: &[&str]) -> OsResult<()> {
     ))
 }
 
-/// Set wallpaper using any available backend.
+/// Set wallpaper, walking the `wallpaper.set` degradation chain.
 pub async fn wallpaper_set(image_path: &str) -> OsResult<()> {
     if !Path::new(image_path).exists() {
         return Err(OsError::NotFound(image_path.to_string()));
     }
 
-    if command_exists("swww").await && run_checked("swww", &["img", image_path]).await.is_ok() {
-        return Ok(());
-    }
-
-    if command_exists("hyprctl").await {
-        let preload = run_checked("hyprctl", &["hyprpaper", "preload", image_path]).await;
-        let wallpaper_arg = format!(",{image_path}");
-        let set_wallpaper = run_checked(
-            "hyprctl",
-            &["hyprpaper", "wallpaper", wallpaper_arg.as_str()],
-        )
-        .await;
-        if preload.is_ok() && set_wallpaper.is_ok() {
-            return Ok(());
-        }
-    }
-
-    if command_exists("caelestia").await {
-        if run_checked("caelestia", &["wallpaper", "-f", image_path])
-            .await
-            .is_ok()
-            || run_checked("caelestia", &["wallpaper", "-h", image_path])
+    degradation::run("wallpaper.set", |backend| async move {
+        match backend {
+            "swww" => run_checked("swww", &["img", image_path]).await,
+            "caelestia" => {
+                if run_checked("caelestia", &["wallpaper", "-f", image_path])
+                    .await
+                    .is_ok()
+                {
+                    return Ok(());
+                }
+                run_checked("caelestia", &["wallpaper", "-h", image_path]).await
+            }
+            _ => {
+                run_checked("hyprctl", &["hyprpaper", "preload", image_path]).await?;
+                let wallpaper_arg = format!(",{image_path}");
+                run_checked(
+                    "hyprctl",
+                    &["hyprpaper", "wallpaper", wallpaper_arg.as_str()],
+                )
                 .await
-                .is_ok()
-        {
-            return Ok(());
+            }
         }
-    }
-
-    Err(OsError::OperationFailed(
-        "No wallpaper backend succeeded (tried: swww, hyprpaper via hyprctl, caelestia)"
-            .to_string(),
-    ))
+    })
+    .await
+    .map(|_| ())
 }
 
 /// Get battery level
@@ -143,9 +135,125 @@ pub async fn reboot() -> OsResult<()> {
     Ok(())
 }
 
+/// Show a desktop notification via notify-send.
+pub async fn notify(summary: &str, body: &str) -> OsResult<()> {
+    if !command_exists("notify-send").await {
+        return Err(OsError::OperationFailed(
+            "notify-send is not installed".to_string(),
+        ));
+    }
+    run_checked("notify-send", &["--app-name=hypr-claw", summary, body]).await
+}
+
+/// Speak text aloud using any available TTS backend.
+pub async fn speak(text: &str) -> OsResult<()> {
+    if text.trim().is_empty() {
+        return Err(OsError::InvalidArgument("text is empty".to_string()));
+    }
+    for backend in ["spd-say", "espeak-ng", "espeak"] {
+        if command_exists(backend).await && run_checked(backend, &[text]).await.is_ok() {
+            return Ok(());
+        }
+    }
+    Err(OsError::OperationFailed(
+        "No TTS backend succeeded (tried: spd-say, espeak-ng, espeak)".to_string(),
+    ))
+}
+
+/// Supported package managers, in detection order, with the arguments for a
+/// non-interactive install.
+const PACKAGE_MANAGERS: &[(&str, &[&str])] = &[
+    ("pacman", &["-S", "--needed", "--noconfirm"]),
+    ("apt-get", &["install", "-y"]),
+    ("dnf", &["install", "-y"]),
+    ("zypper", &["--non-interactive", "install"]),
+];
+
+/// The first supported package manager found on PATH.
+pub async fn package_manager() -> Option<&'static str> {
+    for (manager, _) in PACKAGE_MANAGERS {
+        if command_exists(manager).await {
+            return Some(manager);
+        }
+    }
+    None
+}
+
+/// Whether `name` is safe to pass to a package manager as a package name.
+pub fn valid_package_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '+' | '@'))
+}
+
+/// Install packages with the system package manager and return its name.
+///
+/// Elevates through pkexec when available and otherwise through sudo without
+/// a password prompt, since there is no terminal to prompt on.
+pub async fn package_install(packages: &[String]) -> OsResult<&'static str> {
+    if packages.is_empty() {
+        return Err(OsError::InvalidArgument("no packages given".to_string()));
+    }
+    if let Some(bad) = packages.iter().find(|p| !valid_package_name(p)) {
+        return Err(OsError::InvalidArgument(format!(
+            "invalid package name '{bad}'"
+        )));
+    }
+    let Some(manager) = package_manager().await else {
+        return Err(OsError::OperationFailed(
+            "No supported package manager found (tried: pacman, apt-get, dnf, zypper)".to_string(),
+        ));
+    };
+    let install_args = PACKAGE_MANAGERS
+        .iter()
+        .find(|(name, _)| *name == manager)
+        .map(|(_, args)| *args)
+        .unwrap_or_default();
+
+    let (elevate, elevate_args): (&str, &[&str]) = if command_exists("pkexec").await {
+        ("pkexec", &[])
+    } else {
+        ("sudo", &["-n"])
+    };
+    let mut args: Vec<&str> = elevate_args.to_vec();
+    args.push(manager);
+    args.extend_from_slice(install_args);
+    args.extend(packages.iter().map(String::as_str));
+    run_checked(elevate, &args).await?;
+    Ok(manager)
+}
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct MemoryInfo {
     pub total_mb: u64,
     pub used_mb: u64,
     pub available_mb: u64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn package_names_reject_flags_and_shell_syntax() {
+        assert!(valid_package_name("tesseract-data-eng"));
+        assert!(valid_package_name("libstdc++6"));
+        assert!(!valid_package_name("--noconfirm"));
+        assert!(!valid_package_name("grim; reboot"));
+        assert!(!valid_package_name(""));
+    }
+
+    #[tokio::test]
+    async fn package_install_validates_before_running() {
+        assert!(matches!(
+            package_install(&[]).await,
+            Err(OsError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            package_install(&["-y".to_string()]).await,
+            Err(OsError::InvalidArgument(_))
+        ));
+    }
+}
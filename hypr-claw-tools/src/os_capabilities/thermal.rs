@@ -0,0 +1,185 @@
+//! Temperatures and fan speeds from hwmon.
+//!
+//! Every `/sys/class/hwmon/hwmon*` device exposes `tempN_input` in
+//! millidegrees (with optional `_label`, `_max` and `_crit`) and
+//! `fanN_input` in RPM. Machines without hwmon sensors fall back to the
+//! ACPI thermal zones, which have a temperature and nothing else.
+
+use super::{OsError, OsResult};
+use serde::Serialize;
+use std::path::Path;
+use tokio::task;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Sensor {
+    /// hwmon driver or thermal zone type, e.g. `k10temp` or `acpitz`.
+    pub source: String,
+    /// `Tctl`, `Package id 0`, or `temp1` when unlabelled.
+    pub label: String,
+    pub celsius: f32,
+    pub high: Option<f32>,
+    pub critical: Option<f32>,
+}
+
+impl Sensor {
+    /// `k10temp/Tctl`, what thermal rules match against.
+    pub fn id(&self) -> String {
+        format!("{}/{}", self.source, self.label)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Fan {
+    pub source: String,
+    pub label: String,
+    pub rpm: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Thermal {
+    pub sensors: Vec<Sensor>,
+    pub fans: Vec<Fan>,
+}
+
+impl Thermal {
+    pub fn hottest(&self) -> Option<&Sensor> {
+        self.sensors
+            .iter()
+            .max_by(|a, b| a.celsius.total_cmp(&b.celsius))
+    }
+}
+
+pub async fn thermal() -> OsResult<Thermal> {
+    task::spawn_blocking(|| {
+        let mut thermal = read_hwmon(Path::new("/sys/class/hwmon"));
+        if thermal.sensors.is_empty() {
+            thermal.sensors = read_thermal_zones(Path::new("/sys/class/thermal"));
+        }
+        thermal
+    })
+    .await
+    .map_err(|e| OsError::OperationFailed(e.to_string()))
+}
+
+fn read(dir: &Path, file: &str) -> Option<String> {
+    std::fs::read_to_string(dir.join(file))
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+fn millidegrees(dir: &Path, file: &str) -> Option<f32> {
+    read(dir, file)?
+        .parse::<i64>()
+        .ok()
+        .map(|m| m as f32 / 1000.0)
+}
+
+fn sorted_dirs(root: &Path, prefix: &str) -> Vec<std::path::PathBuf> {
+    let mut dirs: Vec<_> = std::fs::read_dir(root)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(prefix))
+        .map(|entry| entry.path())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+fn read_hwmon(root: &Path) -> Thermal {
+    let mut thermal = Thermal::default();
+    for dir in sorted_dirs(root, "hwmon") {
+        let source = read(&dir, "name").unwrap_or_else(|| "hwmon".to_string());
+        let mut inputs: Vec<String> = std::fs::read_dir(&dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| name.ends_with("_input"))
+            .collect();
+        inputs.sort();
+        for input in inputs {
+            let channel = input.trim_end_matches("_input");
+            let label =
+                read(&dir, &format!("{channel}_label")).unwrap_or_else(|| channel.to_string());
+            if channel.starts_with("temp") {
+                let Some(celsius) = millidegrees(&dir, &input) else {
+                    continue;
+                };
+                thermal.sensors.push(Sensor {
+                    source: source.clone(),
+                    label,
+                    celsius,
+                    high: millidegrees(&dir, &format!("{channel}_max")),
+                    critical: millidegrees(&dir, &format!("{channel}_crit")),
+                });
+            } else if channel.starts_with("fan") {
+                let Some(rpm) = read(&dir, &input).and_then(|s| s.parse().ok()) else {
+                    continue;
+                };
+                thermal.fans.push(Fan {
+                    source: source.clone(),
+                    label,
+                    rpm,
+                });
+            }
+        }
+    }
+    thermal
+}
+
+fn read_thermal_zones(root: &Path) -> Vec<Sensor> {
+    sorted_dirs(root, "thermal_zone")
+        .into_iter()
+        .filter_map(|dir| {
+            Some(Sensor {
+                source: read(&dir, "type").unwrap_or_else(|| "thermal_zone".to_string()),
+                label: dir.file_name()?.to_string_lossy().to_string(),
+                celsius: millidegrees(&dir, "temp")?,
+                high: None,
+                critical: None,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hwmon_sensors_and_fans_are_read() {
+        let root = tempfile::tempdir().unwrap();
+        let cpu = root.path().join("hwmon0");
+        std::fs::create_dir(&cpu).unwrap();
+        for (file, value) in [
+            ("name", "k10temp"),
+            ("temp1_input", "71250"),
+            ("temp1_label", "Tctl"),
+            ("temp1_crit", "100000"),
+            ("temp3_input", "48000"),
+        ] {
+            std::fs::write(cpu.join(file), format!("{value}\n")).unwrap();
+        }
+        let fan = root.path().join("hwmon1");
+        std::fs::create_dir(&fan).unwrap();
+        std::fs::write(fan.join("name"), "thinkpad\n").unwrap();
+        std::fs::write(fan.join("fan1_input"), "2900\n").unwrap();
+
+        let thermal = read_hwmon(root.path());
+        assert_eq!(thermal.sensors.len(), 2);
+        let hottest = thermal.hottest().unwrap();
+        assert_eq!(hottest.id(), "k10temp/Tctl");
+        assert_eq!(hottest.celsius, 71.25);
+        assert_eq!(hottest.critical, Some(100.0));
+        assert_eq!(thermal.sensors[1].label, "temp3");
+        assert_eq!(
+            thermal.fans,
+            vec![Fan {
+                source: "thinkpad".into(),
+                label: "fan1".into(),
+                rpm: 2900
+            }]
+        );
+    }
+}
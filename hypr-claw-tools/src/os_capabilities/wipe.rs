@@ -0,0 +1,128 @@
+//! Secure deletion: overwrite file contents before unlinking them.
+//!
+//! Overwriting only reaches the old blocks on filesystems that write in
+//! place. On copy-on-write filesystems (btrfs, ZFS, bcachefs) the new zeros
+//! land elsewhere, and a file with other hard links would lose its data
+//! under those names too, so such files are just unlinked and the result
+//! says the wipe was not complete.
+//!
+//! Files can also be marked sensitive with an empty `<file>.sensitive`
+//! sidecar, e.g. a screenshot whose OCR text holds a secret, so retention
+//! wipes them early.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+pub const SENSITIVE_SUFFIX: &str = ".sensitive";
+
+const BTRFS_SUPER_MAGIC: i64 = 0x9123_683e;
+const ZFS_SUPER_MAGIC: i64 = 0x2fc1_2fc1;
+const BCACHEFS_SUPER_MAGIC: i64 = 0xca45_1a4e;
+
+const CHUNK: usize = 64 * 1024;
+
+/// Overwrite and unlink `path`, recursing into directories; symlinks are
+/// unlinked without touching their target. Returns whether every file's
+/// contents were overwritten.
+pub fn secure_delete(path: &Path) -> io::Result<bool> {
+    let meta = path.symlink_metadata()?;
+    if meta.is_dir() {
+        let mut overwritten = true;
+        for entry in fs::read_dir(path)? {
+            overwritten &= secure_delete(&entry?.path())?;
+        }
+        fs::remove_dir(path)?;
+        return Ok(overwritten);
+    }
+    let overwritten = if meta.is_file() && meta.nlink() == 1 && overwrite_reaches_disk(path) {
+        overwrite(path, meta.len())?;
+        true
+    } else {
+        !meta.is_file() || meta.len() == 0
+    };
+    fs::remove_file(path)?;
+    let _ = fs::remove_file(sensitive_marker(path));
+    Ok(overwritten)
+}
+
+fn overwrite(path: &Path, len: u64) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let zeros = vec![0u8; CHUNK];
+    let mut left = len;
+    while left > 0 {
+        let n = left.min(CHUNK as u64) as usize;
+        file.write_all(&zeros[..n])?;
+        left -= n as u64;
+    }
+    file.sync_all()
+}
+
+/// False on copy-on-write filesystems, where overwriting writes new blocks.
+fn overwrite_reaches_disk(path: &Path) -> bool {
+    let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: `stat` is a plain C struct that statfs fills in, and `c_path`
+    // is a valid NUL-terminated string for the duration of the call.
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return false;
+    }
+    !matches!(
+        stat.f_type as i64,
+        BTRFS_SUPER_MAGIC | ZFS_SUPER_MAGIC | BCACHEFS_SUPER_MAGIC
+    )
+}
+
+pub fn sensitive_marker(path: &Path) -> PathBuf {
+    let mut marker = path.as_os_str().to_owned();
+    marker.push(SENSITIVE_SUFFIX);
+    PathBuf::from(marker)
+}
+
+pub fn mark_sensitive(path: &Path) -> io::Result<()> {
+    File::create(sensitive_marker(path)).map(|_| ())
+}
+
+pub fn is_sensitive(path: &Path) -> bool {
+    sensitive_marker(path).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn secure_delete_overwrites_before_unlinking() {
+        let dir = tempfile::tempdir().unwrap();
+        let secret = dir.path().join("shot.png");
+        fs::write(&secret, "token=ghp_abc").unwrap();
+        mark_sensitive(&secret).unwrap();
+        assert!(is_sensitive(&secret));
+
+        // Keep a descriptor open to see what the unlinked inode holds.
+        let mut open = File::open(&secret).unwrap();
+        let overwritten = secure_delete(&secret).unwrap();
+        assert!(!secret.exists());
+        assert!(!sensitive_marker(&secret).exists());
+        let mut left = Vec::new();
+        open.read_to_end(&mut left).unwrap();
+        assert_eq!(overwritten, left.iter().all(|b| *b == 0));
+
+        // A second name for the same data is never overwritten.
+        let nested = dir.path().join("nested");
+        fs::create_dir(&nested).unwrap();
+        fs::write(nested.join("a.txt"), "keep me").unwrap();
+        fs::hard_link(nested.join("a.txt"), dir.path().join("link.txt")).unwrap();
+        assert!(!secure_delete(&nested).unwrap());
+        assert!(!nested.exists());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("link.txt")).unwrap(),
+            "keep me"
+        );
+    }
+}
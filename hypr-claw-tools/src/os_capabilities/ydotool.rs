@@ -0,0 +1,242 @@
+//! ydotool daemon management - health checks and the ydotoold user service.
+//!
+//! The `ydotool` client only talks to a running `ydotoold` over a socket, and
+//! the daemon needs write access to /dev/uinput. Without both, every input
+//! call fails with an opaque socket error, so callers check health first.
+
+use super::{OsError, OsResult};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::process::Command;
+use tokio::time::{sleep, Duration};
+
+pub const SERVICE_NAME: &str = "ydotoold.service";
+const SOCKET_FILE: &str = ".ydotool_socket";
+
+/// State of the ydotool client, daemon and their prerequisites.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct YdotoolHealth {
+    pub client_installed: bool,
+    pub daemon_installed: bool,
+    pub daemon_running: bool,
+    /// Socket the client should use, if one exists.
+    pub socket: Option<PathBuf>,
+    pub uinput_writable: bool,
+    pub service_installed: bool,
+}
+
+impl YdotoolHealth {
+    pub fn ready(&self) -> bool {
+        self.client_installed && self.daemon_running && self.socket.is_some()
+    }
+
+    /// What is wrong, most fundamental first. Empty when ready.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !self.client_installed {
+            problems.push("ydotool is not installed".to_string());
+            return problems;
+        }
+        if !self.daemon_installed {
+            problems.push("ydotoold is not installed".to_string());
+            return problems;
+        }
+        if !self.daemon_running {
+            if !self.uinput_writable {
+                problems.push(
+                    "/dev/uinput is not writable (add your user to the 'input' group or a udev rule)"
+                        .to_string(),
+                );
+            }
+            problems.push(if self.service_installed {
+                format!("ydotoold is not running ({SERVICE_NAME} is installed but stopped)")
+            } else {
+                format!("ydotoold is not running and no {SERVICE_NAME} user service exists")
+            });
+        } else if self.socket.is_none() {
+            problems.push("ydotoold is running but its socket was not found".to_string());
+        }
+        problems
+    }
+}
+
+async fn command_path(command: &str) -> Option<String> {
+    let output = Command::new("which").arg(command).output().await.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!path.is_empty()).then_some(path)
+}
+
+async fn run_checked(command: &str, args: &[&str]) -> OsResult<()> {
+    let output = Command::new(command).args(args).output().await?;
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(OsError::OperationFailed(
+        String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    ))
+}
+
+fn unit_dir() -> OsResult<PathBuf> {
+    let base = std::env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|_| std::env::var("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .map_err(|_| OsError::OperationFailed("cannot resolve config directory".to_string()))?;
+    Ok(base.join("systemd").join("user"))
+}
+
+/// Socket paths ydotool may use, in the order the client tries them.
+pub fn socket_candidates() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Ok(explicit) = std::env::var("YDOTOOL_SOCKET") {
+        candidates.push(PathBuf::from(explicit));
+    }
+    if let Ok(runtime_dir) = std::env::var("XDG_RUNTIME_DIR") {
+        candidates.push(Path::new(&runtime_dir).join(SOCKET_FILE));
+    }
+    candidates.push(Path::new("/tmp").join(SOCKET_FILE));
+    candidates
+}
+
+fn find_socket() -> Option<PathBuf> {
+    socket_candidates().into_iter().find(|path| path.exists())
+}
+
+fn uinput_writable() -> bool {
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open("/dev/uinput")
+        .is_ok()
+}
+
+/// Unit file that runs `ydotoold` for the user, with its socket in the
+/// user's runtime directory.
+pub fn render_service_unit(ydotoold_path: &str) -> String {
+    format!(
+        "[Unit]\nDescription=ydotool input daemon (hypr-claw)\n\n[Service]\nExecStart={ydotoold_path} --socket-path=%t/{SOCKET_FILE}\nRestart=on-failure\nRestartSec=2\n\n[Install]\nWantedBy=default.target\n"
+    )
+}
+
+pub async fn health() -> YdotoolHealth {
+    let daemon_running = Command::new("pgrep")
+        .args(["-x", "ydotoold"])
+        .output()
+        .await
+        .map(|output| output.status.success())
+        .unwrap_or(false);
+    let service_installed = unit_dir()
+        .map(|dir| dir.join(SERVICE_NAME).exists())
+        .unwrap_or(false);
+    YdotoolHealth {
+        client_installed: command_path("ydotool").await.is_some(),
+        daemon_installed: command_path("ydotoold").await.is_some(),
+        daemon_running,
+        socket: find_socket(),
+        uinput_writable: uinput_writable(),
+        service_installed,
+    }
+}
+
+/// Write, enable and start the ydotoold user service. Returns the unit path.
+pub async fn install_user_service() -> OsResult<PathBuf> {
+    let Some(ydotoold) = command_path("ydotoold").await else {
+        return Err(OsError::NotFound(
+            "ydotoold (install 'ydotool')".to_string(),
+        ));
+    };
+    let dir = unit_dir()?;
+    fs::create_dir_all(&dir).await?;
+    let path = dir.join(SERVICE_NAME);
+    fs::write(&path, render_service_unit(&ydotoold)).await?;
+    run_checked("systemctl", &["--user", "daemon-reload"]).await?;
+    run_checked("systemctl", &["--user", "enable", "--now", SERVICE_NAME]).await?;
+    Ok(path)
+}
+
+/// Health after starting the daemon through its user service, if needed.
+///
+/// Only a service the user already created is started; without one this
+/// reports the problem instead of spawning a daemon on its own.
+pub async fn ensure_daemon() -> YdotoolHealth {
+    let current = health().await;
+    if current.ready() || current.daemon_running || !current.service_installed {
+        return current;
+    }
+    if run_checked("systemctl", &["--user", "start", SERVICE_NAME])
+        .await
+        .is_err()
+    {
+        return current;
+    }
+    for _ in 0..10 {
+        sleep(Duration::from_millis(100)).await;
+        let next = health().await;
+        if next.ready() {
+            return next;
+        }
+    }
+    health().await
+}
+
+/// Run the ydotool client once the daemon is up, pointing it at the socket
+/// that actually exists.
+pub async fn run(args: &[&str]) -> OsResult<()> {
+    let health = ensure_daemon().await;
+    let Some(socket) = health.socket.as_ref().filter(|_| health.ready()) else {
+        return Err(OsError::OperationFailed(format!(
+            "ydotool is not ready: {}",
+            health.problems().join("; ")
+        )));
+    };
+    let output = Command::new("ydotool")
+        .args(args)
+        .env("YDOTOOL_SOCKET", socket)
+        .output()
+        .await?;
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(OsError::OperationFailed(
+        String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn problems_explain_the_first_missing_piece() {
+        let missing = YdotoolHealth::default();
+        assert_eq!(missing.problems(), vec!["ydotool is not installed"]);
+
+        let stopped = YdotoolHealth {
+            client_installed: true,
+            daemon_installed: true,
+            uinput_writable: true,
+            service_installed: true,
+            ..YdotoolHealth::default()
+        };
+        assert!(!stopped.ready());
+        assert_eq!(stopped.problems().len(), 1);
+        assert!(stopped.problems()[0].contains("installed but stopped"));
+
+        let ready = YdotoolHealth {
+            daemon_running: true,
+            socket: Some(PathBuf::from("/run/user/1000/.ydotool_socket")),
+            ..stopped
+        };
+        assert!(ready.ready());
+        assert!(ready.problems().is_empty());
+    }
+
+    #[test]
+    fn service_unit_puts_socket_in_runtime_dir() {
+        let unit = render_service_unit("/usr/bin/ydotoold");
+        assert!(unit.contains("ExecStart=/usr/bin/ydotoold --socket-path=%t/.ydotool_socket\n"));
+        assert!(unit.contains("WantedBy=default.target\n"));
+    }
+}
@@ -1,12 +1,28 @@
 //! Structured OS capability tool wrappers.
 
+use crate::calc;
+use crate::contacts::{ContactMatch, ContactStore};
 use crate::error::ToolError;
-use crate::execution_context::ExecutionContext;
-use crate::os_capabilities::{desktop, filesystem, hyprland, process, system};
+use crate::execution_context::{ExecutionContext, NetworkAccess};
+use crate::knowledge::KnowledgeStore;
+use crate::natural_time;
+use crate::os_capabilities::database;
+use crate::os_capabilities::documents::{self, DocFormat};
+use crate::os_capabilities::schedule::{self, JobSpec};
+use crate::os_capabilities::{
+    desktop, filesystem, hardware, hyprland, manual, process, system, thermal, wipe, OsError,
+};
+use crate::reminders::{self, ReminderStore};
+use crate::sandbox::env_policy::{self, EnvPolicy};
+use crate::sandbox::isolation::{self, IsolationPolicy};
+use crate::snippets;
+use crate::tabular;
 use crate::tools::base::{Tool, ToolResult};
 use crate::traits::PermissionTier;
 use async_trait::async_trait;
 use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
 
 fn required_str<'a>(input: &'a Value, field: &str) -> Result<&'a str, ToolError> {
     input[field]
@@ -48,9 +64,9 @@ impl Tool for FsCreateDirTool {
             "additionalProperties": false
         })
     }
-    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
         let path = required_str(&input, "path")?;
-        filesystem::create_dir(path)
+        filesystem::create_dir(ctx.capabilities.confine(path)?)
             .await
             .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
         Ok(ToolResult {
@@ -67,7 +83,7 @@ impl Tool for FsDeleteTool {
         "fs.delete"
     }
     fn description(&self) -> &'static str {
-        "Delete a file or directory"
+        "Delete a file or directory. For bulk cleanups stage everything with fs.stage instead"
     }
     fn permission_tier(&self) -> PermissionTier {
         PermissionTier::Write
@@ -75,14 +91,32 @@ impl Tool for FsDeleteTool {
     fn schema(&self) -> Value {
         json!({
             "type": "object",
-            "properties": { "path": {"type": "string"} },
+            "properties": {
+                "path": {"type": "string"},
+                "secure": {
+                    "type": "boolean",
+                    "description": "Overwrite contents before unlinking, for sensitive files"
+                }
+            },
             "required": ["path"],
             "additionalProperties": false
         })
     }
-    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
         let path = required_str(&input, "path")?;
-        filesystem::delete(path)
+        let target = ctx.capabilities.confine(path)?;
+        if input["secure"].as_bool() == Some(true) {
+            let overwritten = tokio::task::spawn_blocking(move || wipe::secure_delete(&target))
+                .await
+                .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?
+                .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+            return Ok(ToolResult {
+                success: true,
+                output: Some(json!({"deleted": path, "overwritten": overwritten})),
+                error: None,
+            });
+        }
+        filesystem::delete(target)
             .await
             .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
         Ok(ToolResult {
@@ -99,7 +133,7 @@ impl Tool for FsMoveTool {
         "fs.move"
     }
     fn description(&self) -> &'static str {
-        "Move/rename a file or directory"
+        "Move/rename a file or directory. For bulk reorganizing stage everything with fs.stage instead"
     }
     fn permission_tier(&self) -> PermissionTier {
         PermissionTier::Write
@@ -115,12 +149,15 @@ impl Tool for FsMoveTool {
             "additionalProperties": false
         })
     }
-    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
         let from = required_str(&input, "from")?;
         let to = required_str(&input, "to")?;
-        filesystem::move_path(from, to)
-            .await
-            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        filesystem::move_path(
+            ctx.capabilities.confine(from)?,
+            ctx.capabilities.confine(to)?,
+        )
+        .await
+        .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
         Ok(ToolResult {
             success: true,
             output: Some(json!({"from": from, "to": to})),
@@ -151,12 +188,15 @@ impl Tool for FsCopyTool {
             "additionalProperties": false
         })
     }
-    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
         let from = required_str(&input, "from")?;
         let to = required_str(&input, "to")?;
-        filesystem::copy_file(from, to)
-            .await
-            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        filesystem::copy_file(
+            ctx.capabilities.confine(from)?,
+            ctx.capabilities.confine(to)?,
+        )
+        .await
+        .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
         Ok(ToolResult {
             success: true,
             output: Some(json!({"from": from, "to": to})),
@@ -184,9 +224,9 @@ impl Tool for FsReadTool {
             "additionalProperties": false
         })
     }
-    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
         let path = required_str(&input, "path")?;
-        let content = filesystem::read(path)
+        let content = filesystem::read(ctx.capabilities.confine(path)?)
             .await
             .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
         Ok(ToolResult {
@@ -219,10 +259,10 @@ impl Tool for FsWriteTool {
             "additionalProperties": false
         })
     }
-    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
         let path = required_str(&input, "path")?;
         let content = required_str(&input, "content")?;
-        filesystem::write(path, content)
+        filesystem::write(ctx.capabilities.confine(path)?, content)
             .await
             .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
         Ok(ToolResult {
@@ -252,9 +292,9 @@ impl Tool for FsListTool {
             "additionalProperties": false
         })
     }
-    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
         let path = required_str(&input, "path")?;
-        let entries = filesystem::list(path)
+        let entries = filesystem::list(ctx.capabilities.confine(path)?)
             .await
             .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
         let entries: Vec<String> = entries
@@ -269,7 +309,18 @@ impl Tool for FsListTool {
     }
 }
 
-pub struct ProcSpawnTool;
+/// `proc.spawn`, optionally running commands under an [`IsolationPolicy`].
+#[derive(Default)]
+pub struct ProcSpawnTool {
+    isolation: IsolationPolicy,
+}
+
+impl ProcSpawnTool {
+    pub fn with_isolation(isolation: IsolationPolicy) -> Self {
+        Self { isolation }
+    }
+}
+
 pub struct ProcKillTool;
 pub struct ProcListTool;
 pub struct DesktopOpenUrlTool;
@@ -317,7 +368,7 @@ impl Tool for ProcSpawnTool {
             "additionalProperties": false
         })
     }
-    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
         let command = required_str(&input, "command")?;
         let args_vec: Vec<String> = input["args"]
             .as_array()
@@ -328,12 +379,30 @@ impl Tool for ProcSpawnTool {
             })
             .unwrap_or_default();
         let arg_refs: Vec<&str> = args_vec.iter().map(String::as_str).collect();
-        let pid = process::spawn(command, &arg_refs)
-            .await
-            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        let pid = if self.isolation.is_enabled() {
+            if !isolation::bwrap_available() {
+                return Err(ToolError::SandboxViolation(
+                    "process isolation is enabled but bwrap is not installed".to_string(),
+                ));
+            }
+            let (program, args) = process::split_command(command, &arg_refs)
+                .map_err(|e| ToolError::ValidationError(e.to_string()))?;
+            // bwrap cannot filter by host, so a host list keeps the network off.
+            let network_granted = ctx.capabilities.network == NetworkAccess::Any;
+            let bwrap_args = self.isolation.bwrap_args(&program, &args, network_granted);
+            let bwrap_refs: Vec<&str> = bwrap_args.iter().map(String::as_str).collect();
+            process::spawn_with_env(isolation::BWRAP, &bwrap_refs, &ctx.env).await
+        } else {
+            process::spawn_with_env(command, &arg_refs, &ctx.env).await
+        }
+        .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
         Ok(ToolResult {
             success: true,
-            output: Some(json!({"pid": pid})),
+            output: Some(json!({
+                "pid": pid,
+                "isolated": self.isolation.is_enabled(),
+                "env": ctx.env.keys().collect::<Vec<_>>(),
+            })),
             error: None,
         })
     }
@@ -426,8 +495,9 @@ impl Tool for DesktopOpenUrlTool {
             "additionalProperties": false
         })
     }
-    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
         let url = required_str(&input, "url")?;
+        ctx.capabilities.require_network(url)?;
         desktop::open_url(url)
             .await
             .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
@@ -503,7 +573,7 @@ impl Tool for DesktopLaunchAppAndWaitTextTool {
                 "timeout_ms": {"type": "number"},
                 "poll_interval_ms": {"type": "number"},
                 "case_sensitive": {"type": "boolean"},
-                "lang": {"type": "string"}
+                "lang": {"type": "string", "description": "Tesseract language, e.g. deu or jpn+eng; defaults to ocr.language"}
             },
             "required": ["app", "query"],
             "additionalProperties": false
@@ -563,9 +633,11 @@ impl Tool for DesktopSearchWebTool {
             "additionalProperties": false
         })
     }
-    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
         let query = required_str(&input, "query")?;
         let engine = input["engine"].as_str();
+        ctx.capabilities
+            .require_network(&desktop::search_url(query, engine))?;
         let url = desktop::search_web(query, engine)
             .await
             .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
@@ -595,11 +667,9 @@ impl Tool for DesktopOpenGmailTool {
             "additionalProperties": false
         })
     }
-    async fn execute(
-        &self,
-        _ctx: ExecutionContext,
-        _input: Value,
-    ) -> Result<ToolResult, ToolError> {
+    async fn execute(&self, ctx: ExecutionContext, _input: Value) -> Result<ToolResult, ToolError> {
+        ctx.capabilities
+            .require_network("https://mail.google.com")?;
         desktop::open_gmail()
             .await
             .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
@@ -617,7 +687,7 @@ impl Tool for DesktopTypeTextTool {
         "desktop.type_text"
     }
     fn description(&self) -> &'static str {
-        "Type text into the currently focused window"
+        "Type text into the currently focused window. With ydotool the text is adapted to the active keyboard layout; emoji, CJK and other characters the backend cannot type are pasted through the clipboard. Set verify to read it back with OCR and retype on a mismatch before moving on (not for passwords or hidden fields)"
     }
     fn permission_tier(&self) -> PermissionTier {
         PermissionTier::Execute
@@ -625,19 +695,73 @@ impl Tool for DesktopTypeTextTool {
     fn schema(&self) -> Value {
         json!({
             "type": "object",
-            "properties": { "text": {"type": "string"} },
+            "properties": {
+                "text": {"type": "string"},
+                "verify": {"type": "boolean"},
+                "max_attempts": {"type": "integer", "minimum": 1, "maximum": 5},
+                "correction": {"type": "string", "enum": ["backspace", "select_all", "none"], "description": "How to clear a garbled attempt before retyping (default backspace)"},
+                "lang": {"type": "string", "description": "Tesseract language, e.g. deu or jpn+eng; defaults to ocr.language"}
+            },
             "required": ["text"],
             "additionalProperties": false
         })
     }
-    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
         let text = required_str(&input, "text")?;
-        desktop::type_text(text)
-            .await
-            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        if !input
+            .get("verify")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+        {
+            let typed = desktop::type_text(text, ctx.capabilities.clipboard)
+                .await
+                .map_err(|e| match e {
+                    OsError::InvalidArgument(msg) => ToolError::ValidationError(msg),
+                    other => ToolError::ExecutionFailed(other.to_string()),
+                })?;
+            let mut output = json!({
+                "typed": text.len(),
+                "backend": typed.backend,
+                "pasted": typed.pasted,
+            });
+            if let Some(layout) = typed.layout {
+                output["layout"] = json!(layout);
+            }
+            if let Some(warning) = typed.warning {
+                output["warning"] = json!(warning);
+            }
+            return Ok(ToolResult {
+                success: true,
+                output: Some(output),
+                error: None,
+            });
+        }
+
+        let correction = desktop::TypeCorrection::parse(
+            input
+                .get("correction")
+                .and_then(|v| v.as_str())
+                .unwrap_or("backspace"),
+        )
+        .map_err(|e| ToolError::ValidationError(e.to_string()))?;
+        let max_attempts = input
+            .get("max_attempts")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(2)
+            .clamp(1, 5) as usize;
+        let lang = input.get("lang").and_then(|v| v.as_str());
+        let verification = desktop::type_text_and_verify(
+            text,
+            ctx.capabilities.clipboard,
+            correction,
+            max_attempts,
+            lang,
+        )
+        .await
+        .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
         Ok(ToolResult {
             success: true,
-            output: Some(json!({"typed": text.len()})),
+            output: Some(json!({"typed": text.len(), "verification": verification})),
             error: None,
         })
     }
@@ -941,7 +1065,7 @@ impl Tool for DesktopOcrScreenTool {
             "type": "object",
             "properties": {
                 "path": {"type": "string"},
-                "lang": {"type": "string"}
+                "lang": {"type": "string", "description": "Tesseract language, e.g. deu or jpn+eng; defaults to ocr.language"}
             },
             "additionalProperties": false
         })
@@ -978,7 +1102,7 @@ impl Tool for DesktopFindTextTool {
                 "query": {"type": "string"},
                 "case_sensitive": {"type": "boolean"},
                 "limit": {"type": "number"},
-                "lang": {"type": "string"}
+                "lang": {"type": "string", "description": "Tesseract language, e.g. deu or jpn+eng; defaults to ocr.language"}
             },
             "required": ["query"],
             "additionalProperties": false
@@ -1019,7 +1143,7 @@ impl Tool for DesktopClickTextTool {
                 "occurrence": {"type": "number"},
                 "button": {"type": "string", "enum": ["left", "middle", "right"]},
                 "case_sensitive": {"type": "boolean"},
-                "lang": {"type": "string"}
+                "lang": {"type": "string", "description": "Tesseract language, e.g. deu or jpn+eng; defaults to ocr.language"}
             },
             "required": ["query"],
             "additionalProperties": false
@@ -1061,7 +1185,7 @@ impl Tool for DesktopWaitForTextTool {
                 "timeout_ms": {"type": "number"},
                 "poll_interval_ms": {"type": "number"},
                 "case_sensitive": {"type": "boolean"},
-                "lang": {"type": "string"}
+                "lang": {"type": "string", "description": "Tesseract language, e.g. deu or jpn+eng; defaults to ocr.language"}
             },
             "required": ["query"],
             "additionalProperties": false
@@ -1221,7 +1345,7 @@ impl Tool for DesktopReadScreenStateTool {
                 "include_windows": {"type": "boolean"},
                 "include_cursor": {"type": "boolean"},
                 "include_screenshot": {"type": "boolean"},
-                "lang": {"type": "string"},
+                "lang": {"type": "string", "description": "Tesseract language, e.g. deu or jpn+eng; defaults to ocr.language"},
                 "window_limit": {"type": "number"},
                 "max_ocr_matches": {"type": "number"}
             },
@@ -1451,14 +1575,17 @@ impl Tool for HyprExecTool {
             "additionalProperties": false
         })
     }
-    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
         let command = required_str(&input, "command")?;
-        hyprland::exec(command)
+        hyprland::exec_with_env(command, &ctx.env)
             .await
             .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
         Ok(ToolResult {
             success: true,
-            output: Some(json!({"executed": command})),
+            output: Some(json!({
+                "executed": command,
+                "env": ctx.env.keys().collect::<Vec<_>>(),
+            })),
             error: None,
         })
     }
@@ -1469,6 +1596,10 @@ pub struct SystemShutdownTool;
 pub struct SystemRebootTool;
 pub struct SystemBatteryTool;
 pub struct SystemMemoryTool;
+pub struct SystemGpuTool;
+pub struct SystemDisplaysTool;
+pub struct SystemThermalTool;
+pub struct SystemPackageInstallTool;
 
 #[async_trait]
 impl Tool for WallpaperSetTool {
@@ -1489,6 +1620,9 @@ impl Tool for WallpaperSetTool {
             "additionalProperties": false
         })
     }
+    fn path_inputs(&self) -> &'static [&'static str] {
+        &["image_path"]
+    }
     async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
         let image_path = required_str(&input, "image_path")?;
         system::wallpaper_set(image_path)
@@ -1637,3 +1771,1578 @@ impl Tool for SystemMemoryTool {
         })
     }
 }
+
+#[async_trait]
+impl Tool for SystemGpuTool {
+    fn name(&self) -> &'static str {
+        "system.gpu"
+    }
+    fn description(&self) -> &'static str {
+        "List GPUs with vendor, driver, VRAM and current utilization where the driver reports them"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {},
+            "additionalProperties": false
+        })
+    }
+    async fn execute(
+        &self,
+        _ctx: ExecutionContext,
+        _input: Value,
+    ) -> Result<ToolResult, ToolError> {
+        let gpus = hardware::gpus()
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({"gpus": gpus})),
+            error: None,
+        })
+    }
+}
+
+#[async_trait]
+impl Tool for SystemDisplaysTool {
+    fn name(&self) -> &'static str {
+        "system.displays"
+    }
+    fn description(&self) -> &'static str {
+        "List monitors with resolution, refresh rate, scale and layout position; capture_scale is the factor between full-screen screenshot pixels and cursor coordinates"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {},
+            "additionalProperties": false
+        })
+    }
+    async fn execute(
+        &self,
+        _ctx: ExecutionContext,
+        _input: Value,
+    ) -> Result<ToolResult, ToolError> {
+        let displays = hardware::displays()
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        let listed: Vec<Value> = displays
+            .iter()
+            .map(|display| {
+                let (logical_width, logical_height) = display.logical_size();
+                let mut entry = json!(display);
+                entry["logical_width"] = json!(logical_width);
+                entry["logical_height"] = json!(logical_height);
+                entry
+            })
+            .collect();
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({
+                "displays": listed,
+                "capture_scale": hardware::capture_scale(&displays),
+            })),
+            error: None,
+        })
+    }
+}
+
+#[async_trait]
+impl Tool for SystemThermalTool {
+    fn name(&self) -> &'static str {
+        "system.thermal"
+    }
+    fn description(&self) -> &'static str {
+        "Read temperature sensors (with their high and critical limits) and fan speeds"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {},
+            "additionalProperties": false
+        })
+    }
+    async fn execute(
+        &self,
+        _ctx: ExecutionContext,
+        _input: Value,
+    ) -> Result<ToolResult, ToolError> {
+        let reading = thermal::thermal()
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({
+                "hottest": reading.hottest(),
+                "sensors": reading.sensors,
+                "fans": reading.fans,
+            })),
+            error: None,
+        })
+    }
+}
+
+#[async_trait]
+impl Tool for SystemPackageInstallTool {
+    fn name(&self) -> &'static str {
+        "system.package_install"
+    }
+    fn description(&self) -> &'static str {
+        "Install packages with the system package manager"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::SystemCritical
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "packages": {"type": "array", "items": {"type": "string"}, "minItems": 1}
+            },
+            "required": ["packages"],
+            "additionalProperties": false
+        })
+    }
+    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let packages: Vec<String> = input["packages"]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let manager = system::package_install(&packages)
+            .await
+            .map_err(|e| match e {
+                crate::os_capabilities::OsError::InvalidArgument(msg) => {
+                    ToolError::ValidationError(msg)
+                }
+                other => ToolError::ExecutionFailed(other.to_string()),
+            })?;
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({"manager": manager, "installed": packages})),
+            error: None,
+        })
+    }
+}
+
+pub struct TimerSetTool {
+    store: Arc<ReminderStore>,
+}
+
+impl TimerSetTool {
+    pub fn new(store: Arc<ReminderStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl Tool for TimerSetTool {
+    fn name(&self) -> &'static str {
+        "timer.set"
+    }
+    fn description(&self) -> &'static str {
+        "Set a reminder that fires as a desktop notification (optionally spoken) after a delay like '10m', at a local time 'HH:MM', or at a time like 'next friday 3pm'"
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "message": {"type": "string"},
+                "when": {"type": "string", "description": "Delay such as 90s, 10m, 1h30m, local time HH:MM, or e.g. 'tomorrow 9am'"},
+                "speak": {"type": "boolean"},
+                "follow_up_prompt": {"type": "string", "description": "Agent task to queue when the reminder fires"}
+            },
+            "required": ["message", "when"],
+            "additionalProperties": false
+        })
+    }
+    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let message = required_str(&input, "message")?;
+        let when = required_str(&input, "when")?;
+        let speak = input
+            .get("speak")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let follow_up_prompt = input
+            .get("follow_up_prompt")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+
+        let now = chrono::Utc::now().timestamp();
+        let due_at = reminders::resolve_due_at(when, now).ok_or_else(|| {
+            ToolError::ValidationError(format!(
+                "Invalid 'when' value '{when}' (expected e.g. 10m, 1h30m, HH:MM or 'next friday 3pm')"
+            ))
+        })?;
+        let reminder = self
+            .store
+            .add(message, due_at, speak, follow_up_prompt)
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({
+                "id": reminder.id,
+                "due_at": reminder.due_at,
+                "in_seconds": reminder.due_at - now
+            })),
+            error: None,
+        })
+    }
+}
+
+/// Resolves a recipient that is either a literal address/handle or a contact
+/// name. Returns the contact name (if any) and the resolved value. Telegram
+/// handles keep their `@`, so resolving a resolved value changes nothing.
+fn resolve_recipient(
+    store: &ContactStore,
+    to: &str,
+    field: &str,
+) -> Result<(Option<String>, String), ToolError> {
+    let to = to.trim();
+    let literal = match field {
+        "email" => crate::contacts::looks_like_email(to),
+        "telegram" => to.starts_with('@'),
+        _ => false,
+    };
+    if literal {
+        return Ok((None, to.to_string()));
+    }
+    match store.resolve(to) {
+        ContactMatch::Found(contact) => {
+            let value = match field {
+                "email" => contact.email.clone(),
+                "telegram" => contact.telegram.as_ref().map(|handle| format!("@{handle}")),
+                _ => contact.phone.clone(),
+            };
+            value
+                .map(|v| (Some(contact.name.clone()), v))
+                .ok_or_else(|| {
+                    ToolError::ValidationError(format!("Contact '{}' has no {field}", contact.name))
+                })
+        }
+        ContactMatch::Ambiguous(names) => Err(ToolError::ValidationError(format!(
+            "'{to}' matches several contacts ({}); use the full name",
+            names.join(", ")
+        ))),
+        ContactMatch::NotFound => Err(ToolError::ValidationError(format!(
+            "No contact named '{to}'. Add one with: contacts add <name> {field}=<value>"
+        ))),
+    }
+}
+
+fn with_resolved_recipient(
+    store: &ContactStore,
+    mut input: Value,
+    field: &str,
+) -> Result<Value, ToolError> {
+    let to = required_str(&input, "to")?.to_string();
+    let (contact, resolved) = resolve_recipient(store, &to, field)?;
+    input["to"] = json!(resolved);
+    if let Some(name) = contact {
+        input["to_contact"] = json!(name);
+    }
+    Ok(input)
+}
+
+pub struct ContactsLookupTool {
+    store: Arc<ContactStore>,
+}
+
+impl ContactsLookupTool {
+    pub fn new(store: Arc<ContactStore>) -> Self {
+        Self { store }
+    }
+}
+
+pub struct MailComposeTool {
+    store: Arc<ContactStore>,
+}
+
+impl MailComposeTool {
+    pub fn new(store: Arc<ContactStore>) -> Self {
+        Self { store }
+    }
+}
+
+pub struct TelegramOpenChatTool {
+    store: Arc<ContactStore>,
+}
+
+impl TelegramOpenChatTool {
+    pub fn new(store: Arc<ContactStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl Tool for ContactsLookupTool {
+    fn name(&self) -> &'static str {
+        "contacts.lookup"
+    }
+    fn description(&self) -> &'static str {
+        "Look up a saved contact (email, telegram, phone) by name"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {"name": {"type": "string"}},
+            "required": ["name"],
+            "additionalProperties": false
+        })
+    }
+    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let name = required_str(&input, "name")?;
+        let output = match self.store.resolve(name) {
+            ContactMatch::Found(contact) => json!({"found": true, "contact": contact}),
+            ContactMatch::Ambiguous(names) => json!({"found": false, "candidates": names}),
+            ContactMatch::NotFound => json!({"found": false, "candidates": []}),
+        };
+        Ok(ToolResult {
+            success: true,
+            output: Some(output),
+            error: None,
+        })
+    }
+}
+
+#[async_trait]
+impl Tool for MailComposeTool {
+    fn name(&self) -> &'static str {
+        "mail.compose"
+    }
+    fn description(&self) -> &'static str {
+        "Open a Gmail compose window to a contact name or email address with subject and body prefilled"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::SystemCritical
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "to": {"type": "string", "description": "Contact name or email address"},
+                "subject": {"type": "string"},
+                "body": {"type": "string"}
+            },
+            "required": ["to", "subject", "body"],
+            "additionalProperties": false
+        })
+    }
+    fn resolve_input(&self, input: Value) -> Result<Value, ToolError> {
+        with_resolved_recipient(&self.store, input, "email")
+    }
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        ctx.capabilities
+            .require_network("https://mail.google.com")?;
+        let to = required_str(&input, "to")?;
+        let subject = required_str(&input, "subject")?;
+        let body = required_str(&input, "body")?;
+        let url = desktop::compose_gmail(to, subject, body)
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({"to": to, "to_contact": input.get("to_contact"), "url": url})),
+            error: None,
+        })
+    }
+}
+
+#[async_trait]
+impl Tool for TelegramOpenChatTool {
+    fn name(&self) -> &'static str {
+        "telegram.open_chat"
+    }
+    fn description(&self) -> &'static str {
+        "Open a Telegram chat with a contact name or @username"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::SystemCritical
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "to": {"type": "string", "description": "Contact name or @username"}
+            },
+            "required": ["to"],
+            "additionalProperties": false
+        })
+    }
+    fn resolve_input(&self, input: Value) -> Result<Value, ToolError> {
+        with_resolved_recipient(&self.store, input, "telegram")
+    }
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        ctx.capabilities.require_network("https://t.me")?;
+        let to = required_str(&input, "to")?;
+        let url = desktop::open_telegram_chat(to)
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({"to": to, "to_contact": input.get("to_contact"), "url": url})),
+            error: None,
+        })
+    }
+}
+
+pub struct KnowledgeSearchTool {
+    store: Arc<KnowledgeStore>,
+}
+
+impl KnowledgeSearchTool {
+    pub fn new(store: Arc<KnowledgeStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl Tool for KnowledgeSearchTool {
+    fn name(&self) -> &'static str {
+        "knowledge.search"
+    }
+    fn description(&self) -> &'static str {
+        "Search imported local documentation (man pages, project docs, wiki) and cite the source of any flag or option you use"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": {"type": "string"},
+                "limit": {"type": "number"}
+            },
+            "required": ["query"],
+            "additionalProperties": false
+        })
+    }
+    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let query = required_str(&input, "query")?;
+        let limit = input["limit"].as_u64().unwrap_or(5).min(20) as usize;
+        let results: Vec<Value> = self
+            .store
+            .search(query, limit)
+            .into_iter()
+            .map(|hit| {
+                json!({
+                    "source": hit.chunk.source,
+                    "heading": hit.chunk.heading,
+                    "text": hit.chunk.text,
+                })
+            })
+            .collect();
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({"query": query, "results": results})),
+            error: None,
+        })
+    }
+}
+
+/// Looks up man pages and `--help` output. Pages are cached for the life of
+/// the process, since installed tools rarely change under a session.
+#[derive(Default)]
+pub struct HelpLookupTool {
+    cache: std::sync::Mutex<std::collections::HashMap<String, manual::HelpPage>>,
+}
+
+impl HelpLookupTool {
+    async fn page(&self, command: &str) -> Result<manual::HelpPage, ToolError> {
+        let cached = self
+            .cache
+            .lock()
+            .ok()
+            .and_then(|cache| cache.get(command).cloned());
+        if let Some(page) = cached {
+            return Ok(page);
+        }
+        let page = manual::lookup(command).await.map_err(|e| match e {
+            OsError::InvalidArgument(msg) => ToolError::ValidationError(msg),
+            other => ToolError::ExecutionFailed(other.to_string()),
+        })?;
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(command.to_string(), page.clone());
+        }
+        Ok(page)
+    }
+}
+
+#[async_trait]
+impl Tool for HelpLookupTool {
+    fn name(&self) -> &'static str {
+        "help.lookup"
+    }
+    fn description(&self) -> &'static str {
+        "Read the man page or --help of an installed command (synopsis and options, optionally filtered by topic). Use before hypr.exec/proc.spawn with flags you have not checked"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "command": {"type": "string", "description": "Bare command name, e.g. grim"},
+                "topic": {"type": "string", "description": "Only options mentioning this word"}
+            },
+            "required": ["command"],
+            "additionalProperties": false
+        })
+    }
+    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let command = required_str(&input, "command")?.trim();
+        let topic = input["topic"].as_str();
+        let page = self.page(command).await?;
+        let (summary, truncated) = manual::summarize(&page.text, topic, manual::MAX_SUMMARY_CHARS);
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({
+                "command": page.command,
+                "source": page.source,
+                "topic": topic,
+                "summary": summary,
+                "truncated": truncated,
+            })),
+            error: None,
+        })
+    }
+}
+
+pub struct DocGenerateTool {
+    artifacts_dir: PathBuf,
+}
+
+impl DocGenerateTool {
+    pub fn new<P: Into<PathBuf>>(artifacts_dir: P) -> Self {
+        Self {
+            artifacts_dir: artifacts_dir.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for DocGenerateTool {
+    fn name(&self) -> &'static str {
+        "doc.generate"
+    }
+    fn description(&self) -> &'static str {
+        "Fill a Markdown/Typst/LaTeX template ({{key}}, {{#each list}}...{{/each}}) with JSON data, compile Typst/LaTeX to PDF, and save the result as an artifact"
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "template_path": {"type": "string"},
+                "data": {"type": "object"},
+                "format": {"type": "string", "enum": ["markdown", "typst", "latex"]},
+                "output_name": {"type": "string"},
+                "compile": {"type": "boolean"}
+            },
+            "required": ["template_path", "data"],
+            "additionalProperties": false
+        })
+    }
+    fn path_inputs(&self) -> &'static [&'static str] {
+        &["template_path"]
+    }
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let template_path = ctx
+            .capabilities
+            .confine(required_str(&input, "template_path")?)?;
+        let data = input
+            .get("data")
+            .filter(|v| v.is_object())
+            .ok_or_else(|| ToolError::ValidationError("Missing or invalid 'data'".into()))?;
+        let format = match input.get("format").and_then(|v| v.as_str()) {
+            Some(f) => DocFormat::parse(f),
+            None => DocFormat::from_path(&template_path),
+        }
+        .ok_or_else(|| {
+            ToolError::ValidationError(
+                "Cannot infer template format; pass 'format' (markdown, typst, latex)".into(),
+            )
+        })?;
+        let compile = input
+            .get("compile")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        let template = filesystem::read(&template_path)
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        let rendered = documents::render_template(&template, data, format)
+            .map_err(|e| ToolError::ValidationError(e.to_string()))?;
+
+        let stem = input
+            .get("output_name")
+            .and_then(|v| v.as_str())
+            .or_else(|| template_path.file_stem().and_then(|s| s.to_str()))
+            .unwrap_or("document");
+        let stem: String = stem
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let run_dir = self.artifacts_dir.join(format!(
+            "{}-{}",
+            chrono::Utc::now().format("%Y%m%d-%H%M%S"),
+            uuid::Uuid::new_v4().simple()
+        ));
+        filesystem::create_dir(&run_dir)
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        let source_path = run_dir.join(format!("{stem}.{}", format.extension()));
+        filesystem::write(&source_path, &rendered)
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+
+        let (pdf_path, compile_error) = if compile {
+            match documents::compile(&source_path, format).await {
+                Ok(pdf) => (pdf, None),
+                Err(e) => (None, Some(e.to_string())),
+            }
+        } else {
+            (None, None)
+        };
+
+        Ok(ToolResult {
+            success: compile_error.is_none(),
+            output: Some(json!({
+                "source_path": source_path.display().to_string(),
+                "pdf_path": pdf_path.map(|p| p.display().to_string()),
+                "format": format.extension(),
+                "bytes": rendered.len()
+            })),
+            error: compile_error,
+        })
+    }
+}
+
+fn optional_str_list(input: &Value, field: &str) -> Result<Vec<String>, ToolError> {
+    match input.get(field) {
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|v| {
+                v.as_str().map(str::to_string).ok_or_else(|| {
+                    ToolError::ValidationError(format!("'{field}' must be a list of strings"))
+                })
+            })
+            .collect(),
+        Some(_) => Err(ToolError::ValidationError(format!(
+            "'{field}' must be a list of strings"
+        ))),
+    }
+}
+
+fn csv_delimiter(input: &Value) -> Result<u8, ToolError> {
+    match input.get("delimiter").and_then(|v| v.as_str()) {
+        None => Ok(b','),
+        Some("\\t") | Some("tab") => Ok(b'\t'),
+        Some(d) if d.len() == 1 => Ok(d.as_bytes()[0]),
+        Some(d) => Err(ToolError::ValidationError(format!(
+            "Invalid delimiter '{d}'"
+        ))),
+    }
+}
+
+fn csv_query_from_input(input: &Value) -> Result<tabular::Query, ToolError> {
+    Ok(tabular::Query {
+        filter: input
+            .get("where")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.trim().is_empty())
+            .map(str::to_string),
+        select: optional_str_list(input, "select")?,
+        group_by: optional_str_list(input, "group_by")?,
+        aggregates: optional_str_list(input, "aggregate")?,
+        order_by: input
+            .get("order_by")
+            .and_then(|v| v.as_str())
+            .map(str::to_string),
+        descending: input.get("desc").and_then(|v| v.as_bool()).unwrap_or(false),
+        limit: None,
+    })
+}
+
+fn csv_query_schema_properties() -> Value {
+    json!({
+        "where": {"type": "string", "description": "Filter, e.g. amount > 100 and status == 'paid'. Operators: == != < <= > >= contains startswith endswith, and/or/not"},
+        "select": {"type": "array", "items": {"type": "string"}},
+        "group_by": {"type": "array", "items": {"type": "string"}},
+        "aggregate": {"type": "array", "items": {"type": "string"}, "description": "count, sum(col), avg(col), min(col), max(col)"},
+        "order_by": {"type": "string"},
+        "desc": {"type": "boolean"},
+        "delimiter": {"type": "string"}
+    })
+}
+
+async fn read_csv_table(path: PathBuf, delimiter: u8) -> Result<tabular::Table, ToolError> {
+    tokio::task::spawn_blocking(move || tabular::Table::read(&path, delimiter))
+        .await
+        .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?
+        .map_err(ToolError::ExecutionFailed)
+}
+
+pub struct CsvQueryTool;
+pub struct CsvWriteTool;
+
+#[async_trait]
+impl Tool for CsvQueryTool {
+    fn name(&self) -> &'static str {
+        "csv.query"
+    }
+    fn description(&self) -> &'static str {
+        "Filter, select, group and aggregate rows of a CSV file without loading it into context"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    fn schema(&self) -> Value {
+        let mut properties = csv_query_schema_properties();
+        properties["path"] = json!({"type": "string"});
+        properties["limit"] = json!({"type": "integer", "minimum": 1, "maximum": 1000});
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": ["path"],
+            "additionalProperties": false
+        })
+    }
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let path = ctx.capabilities.confine(required_str(&input, "path")?)?;
+        let delimiter = csv_delimiter(&input)?;
+        let query = csv_query_from_input(&input)?;
+        let limit = input
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(100)
+            .clamp(1, 1000) as usize;
+
+        let table = read_csv_table(path, delimiter).await?;
+        let (mut result, stats) =
+            tabular::run_query(&table, &query).map_err(ToolError::ValidationError)?;
+        let total = result.rows.len();
+        result.rows.truncate(limit);
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({
+                "columns": result.headers,
+                "rows": result.to_json_rows(),
+                "scanned_rows": stats.scanned_rows,
+                "matched_rows": stats.matched_rows,
+                "result_rows": total,
+                "truncated": total > limit
+            })),
+            error: None,
+        })
+    }
+}
+
+#[async_trait]
+impl Tool for CsvWriteTool {
+    fn name(&self) -> &'static str {
+        "csv.write"
+    }
+    fn description(&self) -> &'static str {
+        "Write rows to a CSV file, either given inline or as the result of a query over a source CSV (where/select/group_by/aggregate)"
+    }
+    fn schema(&self) -> Value {
+        let mut properties = csv_query_schema_properties();
+        properties["path"] = json!({"type": "string"});
+        properties["source"] =
+            json!({"type": "string", "description": "CSV file to query instead of passing rows"});
+        properties["columns"] = json!({"type": "array", "items": {"type": "string"}});
+        properties["rows"] = json!({"type": "array", "items": {"type": ["array", "object"]}});
+        properties["append"] = json!({"type": "boolean"});
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": ["path"],
+            "additionalProperties": false
+        })
+    }
+    fn path_inputs(&self) -> &'static [&'static str] {
+        &["path", "source"]
+    }
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let path = ctx.capabilities.confine(required_str(&input, "path")?)?;
+        let delimiter = csv_delimiter(&input)?;
+        let append = input
+            .get("append")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let table = match (
+            input.get("source").and_then(|v| v.as_str()),
+            input.get("rows"),
+        ) {
+            (Some(source), None) => {
+                let source_table =
+                    read_csv_table(ctx.capabilities.confine(source)?, delimiter).await?;
+                let query = csv_query_from_input(&input)?;
+                tabular::run_query(&source_table, &query)
+                    .map_err(ToolError::ValidationError)?
+                    .0
+            }
+            (None, Some(Value::Array(rows))) => {
+                let mut columns = optional_str_list(&input, "columns")?;
+                if columns.is_empty() && append && path.exists() {
+                    columns = read_csv_table(path.clone(), delimiter).await?.headers;
+                }
+                let columns = (!columns.is_empty()).then_some(columns);
+                tabular::Table::from_json(columns, rows).map_err(ToolError::ValidationError)?
+            }
+            _ => {
+                return Err(ToolError::ValidationError(
+                    "Provide exactly one of 'source' or 'rows'".into(),
+                ))
+            }
+        };
+
+        let rows_written = table.rows.len();
+        let target = path.clone();
+        tokio::task::spawn_blocking(move || table.write(&target, delimiter, append))
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?
+            .map_err(ToolError::ExecutionFailed)?;
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({
+                "path": path.display().to_string(),
+                "rows_written": rows_written,
+                "appended": append
+            })),
+            error: None,
+        })
+    }
+}
+
+/// Rows fetched before a result is cut off entirely; anything past the inline
+/// limit is spilled to an artifact file.
+const DB_SPILL_MAX_ROWS: usize = 20_000;
+const DB_INLINE_MAX_BYTES: usize = 64 * 1024;
+
+fn db_params(input: &Value) -> Result<Vec<Value>, ToolError> {
+    match input.get("params") {
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(Value::Array(items)) => Ok(items.clone()),
+        Some(_) => Err(ToolError::ValidationError(
+            "'params' must be an array".into(),
+        )),
+    }
+}
+
+pub struct DbQueryTool {
+    artifacts_dir: PathBuf,
+}
+
+impl DbQueryTool {
+    pub fn new<P: Into<PathBuf>>(artifacts_dir: P) -> Self {
+        Self {
+            artifacts_dir: artifacts_dir.into(),
+        }
+    }
+}
+
+pub struct DbExecuteTool;
+
+#[async_trait]
+impl Tool for DbQueryTool {
+    fn name(&self) -> &'static str {
+        "db.query"
+    }
+    fn description(&self) -> &'static str {
+        "Run a read-only, parameterized SQL query against a local SQLite file (omit sql to list tables). Values go in params as ? placeholders; large results spill to an artifact CSV"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string"},
+                "sql": {"type": "string"},
+                "params": {"type": "array"},
+                "max_rows": {"type": "integer", "minimum": 1, "maximum": 500}
+            },
+            "required": ["path"],
+            "additionalProperties": false
+        })
+    }
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let path = ctx.capabilities.confine(required_str(&input, "path")?)?;
+        let sql = input
+            .get("sql")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+        let params = db_params(&input)?;
+        let inline_rows = input
+            .get("max_rows")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(100)
+            .clamp(1, 500) as usize;
+
+        let query_path = path.clone();
+        let result = tokio::task::spawn_blocking(move || match sql {
+            Some(sql) => database::query(&query_path, &sql, &params, DB_SPILL_MAX_ROWS),
+            None => database::schema(&query_path),
+        })
+        .await
+        .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?
+        .map_err(|e| match e {
+            crate::os_capabilities::OsError::PermissionDenied(msg) => {
+                ToolError::PermissionDenied(msg)
+            }
+            crate::os_capabilities::OsError::InvalidArgument(msg) => {
+                ToolError::ValidationError(msg)
+            }
+            other => ToolError::ExecutionFailed(other.to_string()),
+        })?;
+
+        let total_rows = result.rows.len();
+        let inline: Vec<Vec<Value>> = result.rows.iter().take(inline_rows).cloned().collect();
+        let inline_bytes = serde_json::to_string(&inline).map(|s| s.len()).unwrap_or(0);
+        let mut artifact = None;
+        if total_rows > inline.len() || inline_bytes > DB_INLINE_MAX_BYTES {
+            let table = tabular::Table {
+                headers: result.columns.clone(),
+                rows: result
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|v| match v {
+                                Value::String(s) => s.clone(),
+                                Value::Null => String::new(),
+                                other => other.to_string(),
+                            })
+                            .collect()
+                    })
+                    .collect(),
+            };
+            let target = self.artifacts_dir.join(format!(
+                "db-query-{}.csv",
+                chrono::Utc::now().format("%Y%m%d-%H%M%S%3f")
+            ));
+            filesystem::create_dir(&self.artifacts_dir)
+                .await
+                .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+            let write_target = target.clone();
+            tokio::task::spawn_blocking(move || table.write(&write_target, b',', false))
+                .await
+                .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?
+                .map_err(ToolError::ExecutionFailed)?;
+            artifact = Some(target.display().to_string());
+        }
+
+        let mut inline = inline;
+        while inline.len() > 1
+            && serde_json::to_string(&inline).map(|s| s.len()).unwrap_or(0) > DB_INLINE_MAX_BYTES
+        {
+            inline.truncate(inline.len() / 2);
+        }
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({
+                "columns": result.columns,
+                "rows": inline,
+                "returned_rows": inline.len(),
+                "total_rows": total_rows,
+                "row_cap_hit": result.truncated,
+                "artifact_path": artifact
+            })),
+            error: None,
+        })
+    }
+}
+
+#[async_trait]
+impl Tool for DbExecuteTool {
+    fn name(&self) -> &'static str {
+        "db.execute"
+    }
+    fn description(&self) -> &'static str {
+        "Run a single parameterized INSERT/UPDATE/DELETE/DDL statement against a local SQLite file inside a transaction"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::SystemCritical
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string"},
+                "sql": {"type": "string"},
+                "params": {"type": "array"}
+            },
+            "required": ["path", "sql"],
+            "additionalProperties": false
+        })
+    }
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let path = ctx.capabilities.confine(required_str(&input, "path")?)?;
+        let sql = required_str(&input, "sql")?.to_string();
+        let params = db_params(&input)?;
+        let affected = tokio::task::spawn_blocking(move || database::execute(&path, &sql, &params))
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({"rows_affected": affected})),
+            error: None,
+        })
+    }
+}
+
+pub struct EnvGetTool {
+    policy: EnvPolicy,
+}
+
+impl EnvGetTool {
+    pub fn new(policy: EnvPolicy) -> Self {
+        Self { policy }
+    }
+}
+
+pub struct EnvListTool {
+    policy: EnvPolicy,
+}
+
+impl EnvListTool {
+    pub fn new(policy: EnvPolicy) -> Self {
+        Self { policy }
+    }
+}
+
+async fn env_source(input: &Value) -> Result<(Vec<(String, String)>, String), ToolError> {
+    match input.get("dotenv_path").and_then(|v| v.as_str()) {
+        Some(path) => {
+            let content = filesystem::read(path)
+                .await
+                .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+            Ok((env_policy::parse_dotenv(&content), path.to_string()))
+        }
+        None => Ok((std::env::vars().collect(), "process".to_string())),
+    }
+}
+
+#[async_trait]
+impl Tool for EnvGetTool {
+    fn name(&self) -> &'static str {
+        "env.get"
+    }
+    fn description(&self) -> &'static str {
+        "Read one environment variable (or a key from a .env file); secret-looking values are redacted"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"},
+                "dotenv_path": {"type": "string"}
+            },
+            "required": ["name"],
+            "additionalProperties": false
+        })
+    }
+    fn path_inputs(&self) -> &'static [&'static str] {
+        &["dotenv_path"]
+    }
+    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let name = required_str(&input, "name")?;
+        if !self.policy.is_exposed(name) {
+            return Err(ToolError::PermissionDenied(format!(
+                "Environment variable '{name}' is not exposed by sandbox policy"
+            )));
+        }
+        let (vars, source) = env_source(&input).await?;
+        let value = vars
+            .into_iter()
+            .rev()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v);
+        let (shown, redacted) = match &value {
+            Some(v) => {
+                let (shown, redacted) = self.policy.present(name, v);
+                (Some(shown), redacted)
+            }
+            None => (None, false),
+        };
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({
+                "name": name,
+                "set": value.is_some(),
+                "value": shown,
+                "redacted": redacted,
+                "source": source
+            })),
+            error: None,
+        })
+    }
+}
+
+#[async_trait]
+impl Tool for EnvListTool {
+    fn name(&self) -> &'static str {
+        "env.list"
+    }
+    fn description(&self) -> &'static str {
+        "List environment variables (or a .env file) allowed by sandbox policy, with secret-looking values redacted"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "prefix": {"type": "string"},
+                "dotenv_path": {"type": "string"}
+            },
+            "additionalProperties": false
+        })
+    }
+    fn path_inputs(&self) -> &'static [&'static str] {
+        &["dotenv_path"]
+    }
+    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let prefix = input.get("prefix").and_then(|v| v.as_str()).unwrap_or("");
+        let (vars, source) = env_source(&input).await?;
+        let mut hidden = 0usize;
+        let mut redacted = 0usize;
+        let mut entries = serde_json::Map::new();
+        for (name, value) in vars {
+            if !name.starts_with(prefix) {
+                continue;
+            }
+            if !self.policy.is_exposed(&name) {
+                hidden += 1;
+                continue;
+            }
+            let (shown, was_redacted) = self.policy.present(&name, &value);
+            if was_redacted {
+                redacted += 1;
+            }
+            entries.insert(name, json!(shown));
+        }
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({
+                "source": source,
+                "variables": entries,
+                "hidden_by_policy": hidden,
+                "redacted": redacted
+            })),
+            error: None,
+        })
+    }
+}
+
+pub struct ScheduleSystemCreateTool;
+pub struct ScheduleSystemListTool;
+pub struct ScheduleSystemRemoveTool;
+
+fn schedule_error(e: crate::os_capabilities::OsError) -> ToolError {
+    match e {
+        crate::os_capabilities::OsError::InvalidArgument(msg) => ToolError::ValidationError(msg),
+        other => ToolError::ExecutionFailed(other.to_string()),
+    }
+}
+
+#[async_trait]
+impl Tool for ScheduleSystemCreateTool {
+    fn name(&self) -> &'static str {
+        "schedule.system_create"
+    }
+    fn description(&self) -> &'static str {
+        "Install a recurring OS-level job that runs even when hypr-claw is closed: a systemd user timer (on_calendar, e.g. '*-*-* 02:00:00' or 'Mon *-*-* 09:00') or a crontab entry (cron, e.g. '0 2 * * *'). command is an argv list with an absolute program path"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::SystemCritical
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string", "pattern": "^[a-z0-9][a-z0-9-]{0,47}$"},
+                "command": {"type": "array", "items": {"type": "string"}, "minItems": 1},
+                "description": {"type": "string"},
+                "working_dir": {"type": "string"},
+                "backend": {"type": "string", "enum": ["systemd", "cron"]},
+                "on_calendar": {"type": "string"},
+                "cron": {"type": "string"},
+                "persistent": {"type": "boolean"}
+            },
+            "required": ["name", "command"],
+            "additionalProperties": false
+        })
+    }
+    fn path_inputs(&self) -> &'static [&'static str] {
+        &["working_dir"]
+    }
+    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let name = required_str(&input, "name")?.to_string();
+        let spec = JobSpec {
+            description: input
+                .get("description")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("hypr-claw job {name}")),
+            command: optional_str_list(&input, "command")?,
+            working_dir: input
+                .get("working_dir")
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+            name,
+        };
+        let backend = input.get("backend").and_then(|v| v.as_str()).unwrap_or(
+            if input.get("cron").is_some() {
+                "cron"
+            } else {
+                "systemd"
+            },
+        );
+
+        let (schedule_expr, files) = match backend {
+            "systemd" => {
+                let on_calendar = required_str(&input, "on_calendar")?;
+                let persistent = input
+                    .get("persistent")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(true);
+                let files = schedule::create_systemd_timer(&spec, on_calendar, persistent)
+                    .await
+                    .map_err(schedule_error)?;
+                (on_calendar.to_string(), files)
+            }
+            "cron" => {
+                let cron = required_str(&input, "cron")?;
+                schedule::create_cron_entry(&spec, cron)
+                    .await
+                    .map_err(schedule_error)?;
+                (cron.to_string(), Vec::new())
+            }
+            other => {
+                return Err(ToolError::ValidationError(format!(
+                    "unknown backend '{other}'"
+                )))
+            }
+        };
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({
+                "name": spec.name,
+                "backend": backend,
+                "schedule": schedule_expr,
+                "files": files
+            })),
+            error: None,
+        })
+    }
+}
+
+#[async_trait]
+impl Tool for ScheduleSystemListTool {
+    fn name(&self) -> &'static str {
+        "schedule.system_list"
+    }
+    fn description(&self) -> &'static str {
+        "List OS-level scheduled jobs created by hypr-claw (systemd user timers and crontab entries) with their next run"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {},
+            "additionalProperties": false
+        })
+    }
+    async fn execute(
+        &self,
+        _ctx: ExecutionContext,
+        _input: Value,
+    ) -> Result<ToolResult, ToolError> {
+        let jobs = schedule::list_jobs().await.map_err(schedule_error)?;
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({"jobs": jobs})),
+            error: None,
+        })
+    }
+}
+
+#[async_trait]
+impl Tool for ScheduleSystemRemoveTool {
+    fn name(&self) -> &'static str {
+        "schedule.system_remove"
+    }
+    fn description(&self) -> &'static str {
+        "Disable and delete an OS-level scheduled job created by hypr-claw"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::SystemCritical
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"}
+            },
+            "required": ["name"],
+            "additionalProperties": false
+        })
+    }
+    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let name = required_str(&input, "name")?;
+        let removed = schedule::remove_job(name).await.map_err(schedule_error)?;
+        if !removed {
+            return Err(ToolError::ExecutionFailed(format!(
+                "no scheduled job named '{name}'"
+            )));
+        }
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({"removed": name})),
+            error: None,
+        })
+    }
+}
+
+/// `code.run_snippet`: a short Python or Bash snippet in the sandbox of
+/// [`snippets`], for computing things without shell access.
+pub struct CodeRunSnippetTool;
+
+#[async_trait]
+impl Tool for CodeRunSnippetTool {
+    fn name(&self) -> &'static str {
+        "code.run_snippet"
+    }
+    fn description(&self) -> &'static str {
+        "Run a short Python or Bash snippet in a sandbox with no network, only a scratch directory and a few seconds of CPU; returns stdout and stderr. For calculations, date math and text transforms, not for touching the user's files or system"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Execute
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "language": {"type": "string", "enum": ["python", "bash"]},
+                "code": {"type": "string", "maxLength": snippets::MAX_CODE_BYTES}
+            },
+            "required": ["language", "code"],
+            "additionalProperties": false
+        })
+    }
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let language = required_str(&input, "language")?;
+        let language = snippets::Language::parse(language).ok_or_else(|| {
+            ToolError::ValidationError(format!("unsupported language '{language}'"))
+        })?;
+        let code = required_str(&input, "code")?;
+        let wall_limit = std::time::Duration::from_millis(ctx.timeout_ms).min(snippets::WALL_LIMIT);
+        let output = snippets::run(language, code, wall_limit)
+            .await
+            .map_err(ToolError::ExecutionFailed)?;
+        let success = output.exit_code == Some(0);
+        let error = (!success).then(|| match output.exit_code {
+            Some(code) => format!("snippet exited with status {code}"),
+            None => "snippet was killed, e.g. for exceeding its CPU or memory limit".to_string(),
+        });
+        Ok(ToolResult {
+            success,
+            output: Some(json!(output)),
+            error,
+        })
+    }
+}
+
+/// `calc.evaluate`: arithmetic, unit conversion and date arithmetic by
+/// [`calc`], so numbers in a run are computed rather than guessed.
+pub struct CalcEvaluateTool;
+
+#[async_trait]
+impl Tool for CalcEvaluateTool {
+    fn name(&self) -> &'static str {
+        "calc.evaluate"
+    }
+    fn description(&self) -> &'static str {
+        "Compute exactly: arithmetic like '(1920 - 24) / 3' or 'round(sqrt(2) * 100)', unit conversions like '3.5 km to mi' or '100 F to C', and dates like '2026-10-18 + 45 days', 'today - 2 weeks' or '2026-12-25 - 2026-10-18' (days between). Use it instead of doing math in your head"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "expression": {"type": "string"}
+            },
+            "required": ["expression"],
+            "additionalProperties": false
+        })
+    }
+    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let expression = required_str(&input, "expression")?;
+        let answer = calc::evaluate(expression, chrono::Local::now().date_naive())
+            .map_err(ToolError::ValidationError)?;
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({"value": answer.value, "text": answer.text})),
+            error: None,
+        })
+    }
+}
+
+pub struct TimeParseTool;
+
+#[async_trait]
+impl Tool for TimeParseTool {
+    fn name(&self) -> &'static str {
+        "time.parse"
+    }
+    fn description(&self) -> &'static str {
+        "Resolve a natural-language time like 'next friday 3pm', 'tomorrow morning', 'in 90 minutes' or '23/10 18:00' to an exact local date and time in the system time zone. Use it before scheduling or setting reminders instead of working out dates yourself; 'on_calendar' plugs into schedule.system_create"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "text": {"type": "string"}
+            },
+            "required": ["text"],
+            "additionalProperties": false
+        })
+    }
+    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let text = required_str(&input, "text")?;
+        let locale = natural_time::TimeLocale::system();
+        let now = chrono::Local::now();
+        let resolved =
+            natural_time::parse(text, &now, locale).map_err(ToolError::ValidationError)?;
+        let at = resolved.at;
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({
+                "iso": at.to_rfc3339(),
+                "timestamp": at.timestamp(),
+                "date": at.format("%Y-%m-%d").to_string(),
+                "time": at.format("%H:%M").to_string(),
+                "weekday": at.format("%A").to_string(),
+                "utc_offset": at.format("%:z").to_string(),
+                "time_zone": natural_time::zone_name(),
+                "assumed_time": resolved.assumed_time,
+                "on_calendar": at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                "display": natural_time::format(&at, natural_time::Style::Full, locale, &now),
+                "relative": natural_time::format(&at, natural_time::Style::Relative, locale, &now)
+            })),
+            error: None,
+        })
+    }
+}
+
+pub struct TimeFormatTool;
+
+#[async_trait]
+impl Tool for TimeFormatTool {
+    fn name(&self) -> &'static str {
+        "time.format"
+    }
+    fn description(&self) -> &'static str {
+        "Format a time (unix timestamp, ISO 8601 or natural language) in the system time zone and locale, as 'full', 'date', 'time' or 'relative' (e.g. 'in 3 days'), or with a strftime pattern such as '%A %d %B'"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "time": {"type": ["string", "integer"], "description": "Unix timestamp, ISO 8601 time or e.g. 'next friday 3pm'"},
+                "style": {"type": "string", "enum": ["full", "date", "time", "relative"]},
+                "pattern": {"type": "string", "description": "strftime pattern; overrides style"}
+            },
+            "required": ["time"],
+            "additionalProperties": false
+        })
+    }
+    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        use chrono::TimeZone;
+
+        let locale = natural_time::TimeLocale::system();
+        let now = chrono::Local::now();
+        let at = match input.get("time") {
+            Some(Value::Number(n)) => n
+                .as_i64()
+                .and_then(|ts| chrono::Local.timestamp_opt(ts, 0).single()),
+            Some(Value::String(text)) => match chrono::DateTime::parse_from_rfc3339(text) {
+                Ok(at) => Some(at.with_timezone(&chrono::Local)),
+                Err(_) => Some(
+                    natural_time::parse(text, &now, locale)
+                        .map_err(ToolError::ValidationError)?
+                        .at,
+                ),
+            },
+            _ => None,
+        }
+        .ok_or_else(|| {
+            ToolError::ValidationError("'time' must be a timestamp or a time string".to_string())
+        })?;
+
+        let text = match input.get("pattern").and_then(Value::as_str) {
+            Some(pattern) => {
+                use std::fmt::Write;
+                let mut text = String::new();
+                write!(text, "{}", at.format(pattern)).map_err(|_| {
+                    ToolError::ValidationError(format!("Invalid pattern '{pattern}'"))
+                })?;
+                text
+            }
+            None => {
+                let name = input.get("style").and_then(Value::as_str).unwrap_or("full");
+                let style = natural_time::Style::parse(name)
+                    .ok_or_else(|| ToolError::ValidationError(format!("Unknown style '{name}'")))?;
+                natural_time::format(&at, style, locale, &now)
+            }
+        };
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({"text": text, "timestamp": at.timestamp()})),
+            error: None,
+        })
+    }
+}
@@ -0,0 +1,767 @@
+//! Third-party tool plugins installed from a plugin index.
+//!
+//! An index is a JSON document listing plugin artifacts with their content
+//! hash. Installed plugins are recorded with their provenance and the
+//! permission tier the user assigned at install time. Artifacts may carry a
+//! minisign signature, checked against the configured trusted keys both at
+//! install time and every time the plugin is loaded. A loaded plugin runs
+//! from a private read-only copy of the verified bytes, so replacing the
+//! installed artifact afterwards has no effect until the next load.
+
+use crate::error::ToolError;
+use crate::execution_context::ExecutionContext;
+use crate::tools::base::{Tool, ToolResult};
+use crate::traits::PermissionTier;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// Registered tool names are `plugin.<name>`.
+pub const PLUGIN_TOOL_PREFIX: &str = "plugin.";
+const RECORD_FILE: &str = "installed.json";
+const EXEC_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PluginKind {
+    /// An executable that reads JSON input on stdin and writes JSON to stdout.
+    Command,
+    /// A WebAssembly module. Installable, but not executable in this build.
+    Wasm,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IndexEntry {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    pub kind: PluginKind,
+    pub url: String,
+    pub sha256: String,
+    #[serde(default = "default_schema")]
+    pub schema: Value,
+    #[serde(default)]
+    pub publisher: Option<String>,
+    /// Inline minisign signature (`.minisig` file contents) for the artifact.
+    #[serde(default)]
+    pub signature: Option<String>,
+    /// Location of the `.minisig` file, used when `signature` is absent.
+    #[serde(default)]
+    pub signature_url: Option<String>,
+}
+
+fn default_schema() -> Value {
+    json!({"type": "object", "properties": {}})
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PluginIndex {
+    pub plugins: Vec<IndexEntry>,
+}
+
+impl PluginIndex {
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let index: PluginIndex =
+            serde_json::from_slice(bytes).map_err(|e| format!("invalid plugin index: {e}"))?;
+        for entry in &index.plugins {
+            validate_plugin_name(&entry.name)?;
+        }
+        Ok(index)
+    }
+
+    /// Newest entry for `name`, comparing dotted numeric versions.
+    pub fn latest(&self, name: &str) -> Option<&IndexEntry> {
+        self.plugins
+            .iter()
+            .filter(|e| e.name == name)
+            .max_by(|a, b| version_key(&a.version).cmp(&version_key(&b.version)))
+    }
+}
+
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['.', '-', '+'])
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+pub fn validate_plugin_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || name.len() > 40
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+    {
+        return Err(format!(
+            "invalid plugin name '{name}': use lowercase letters, digits, '-' or '_'"
+        ));
+    }
+    Ok(())
+}
+
+pub fn parse_tier(value: &str) -> Option<PermissionTier> {
+    match value.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+        "read" => Some(PermissionTier::Read),
+        "write" => Some(PermissionTier::Write),
+        "execute" => Some(PermissionTier::Execute),
+        "system_critical" | "critical" => Some(PermissionTier::SystemCritical),
+        _ => None,
+    }
+}
+
+pub fn tier_label(tier: PermissionTier) -> &'static str {
+    match tier {
+        PermissionTier::Read => "read",
+        PermissionTier::Write => "write",
+        PermissionTier::Execute => "execute",
+        PermissionTier::SystemCritical => "system_critical",
+    }
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Which plugin signatures are trusted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SignaturePolicy {
+    /// minisign public keys, either the base64 key line or a full `minisign.pub`.
+    #[serde(default)]
+    pub trusted_keys: Vec<String>,
+    /// Load plugins that carry no signature at all. Invalid signatures are
+    /// refused regardless.
+    #[serde(default)]
+    pub allow_unsigned: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Verification {
+    Verified { key: String },
+    UnsignedAllowed,
+}
+
+fn decode_public_key(key: &str) -> Option<(String, minisign_verify::PublicKey)> {
+    let key = key.trim();
+    let public_key = if key.contains('\n') {
+        minisign_verify::PublicKey::decode(key).ok()?
+    } else {
+        minisign_verify::PublicKey::from_base64(key).ok()?
+    };
+    let label = key.lines().last().unwrap_or(key).trim().to_string();
+    Some((label, public_key))
+}
+
+/// Check `artifact` against an optional minisign signature under `policy`.
+pub fn verify_signature(
+    artifact: &[u8],
+    signature: Option<&str>,
+    policy: &SignaturePolicy,
+) -> Result<Verification, String> {
+    let Some(signature) = signature.map(str::trim).filter(|s| !s.is_empty()) else {
+        if policy.allow_unsigned {
+            return Ok(Verification::UnsignedAllowed);
+        }
+        return Err(
+            "plugin is unsigned; set plugins.allow_unsigned: true to load unsigned plugins"
+                .to_string(),
+        );
+    };
+    let signature = minisign_verify::Signature::decode(signature)
+        .map_err(|e| format!("malformed signature: {e}"))?;
+    if policy.trusted_keys.is_empty() {
+        return Err("plugin is signed but plugins.trusted_keys is empty".to_string());
+    }
+    for key in &policy.trusted_keys {
+        let Some((label, public_key)) = decode_public_key(key) else {
+            continue;
+        };
+        if public_key.verify(artifact, &signature, false).is_ok() {
+            return Ok(Verification::Verified { key: label });
+        }
+    }
+    Err("signature does not match any trusted key".to_string())
+}
+
+/// The trusted comment of a minisign `signature`. The signature covers it,
+/// so it can be relied on once [`verify_signature`] has accepted the
+/// signature.
+pub fn trusted_comment(signature: &str) -> Result<String, String> {
+    minisign_verify::Signature::decode(signature.trim())
+        .map(|signature| signature.trusted_comment().to_string())
+        .map_err(|e| format!("malformed signature: {e}"))
+}
+
+/// Resolve an artifact URL relative to the index it was listed in.
+pub fn resolve_url(url: &str, index_url: &str) -> String {
+    if url.contains("://") || url.starts_with('/') {
+        return url.to_string();
+    }
+    let dir = index_url
+        .rsplit_once('/')
+        .map(|(dir, _)| dir)
+        .unwrap_or(".");
+    format!("{dir}/{url}")
+}
+
+/// Fetch an index or artifact from an http(s) URL, a `file://` URL or a path.
+pub async fn fetch(url: &str) -> Result<Vec<u8>, String> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        let response = reqwest::get(url)
+            .await
+            .map_err(|e| format!("fetch {url}: {e}"))?;
+        if !response.status().is_success() {
+            return Err(format!("fetch {url}: HTTP {}", response.status()));
+        }
+        return response
+            .bytes()
+            .await
+            .map(|b| b.to_vec())
+            .map_err(|e| format!("fetch {url}: {e}"));
+    }
+    let path = url.strip_prefix("file://").unwrap_or(url);
+    tokio::fs::read(path)
+        .await
+        .map_err(|e| format!("read {path}: {e}"))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct InstalledPlugin {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    pub kind: PluginKind,
+    pub sha256: String,
+    pub schema: Value,
+    /// Tier chosen by the user at install time; never taken from the index.
+    pub permission_tier: String,
+    pub source_url: String,
+    pub index_url: String,
+    #[serde(default)]
+    pub publisher: Option<String>,
+    pub installed_at: String,
+    /// Artifact file name inside the plugin directory.
+    pub file: String,
+    #[serde(default)]
+    pub signature: Option<String>,
+    /// Trusted key that verified the signature at install time.
+    #[serde(default)]
+    pub signed_by: Option<String>,
+}
+
+impl InstalledPlugin {
+    pub fn tier(&self) -> PermissionTier {
+        parse_tier(&self.permission_tier).unwrap_or(PermissionTier::SystemCritical)
+    }
+}
+
+/// Installed plugins under `<dir>/<name>/`, recorded in `<dir>/installed.json`.
+pub struct PluginStore {
+    dir: PathBuf,
+    plugins: Mutex<Vec<InstalledPlugin>>,
+}
+
+impl PluginStore {
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let record = dir.join(RECORD_FILE);
+        let plugins = if record.exists() {
+            let raw = std::fs::read_to_string(&record)?;
+            if raw.trim().is_empty() {
+                Vec::new()
+            } else {
+                serde_json::from_str(&raw)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?
+            }
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            dir,
+            plugins: Mutex::new(plugins),
+        })
+    }
+
+    pub fn list(&self) -> Vec<InstalledPlugin> {
+        let mut plugins = self.lock().clone();
+        plugins.sort_by(|a, b| a.name.cmp(&b.name));
+        plugins
+    }
+
+    pub fn get(&self, name: &str) -> Option<InstalledPlugin> {
+        self.lock().iter().find(|p| p.name == name).cloned()
+    }
+
+    pub fn artifact_path(&self, plugin: &InstalledPlugin) -> PathBuf {
+        self.dir.join(&plugin.name).join(&plugin.file)
+    }
+
+    /// Verify `artifact` against the index hash and signature policy, then
+    /// write it and record the plugin. Replaces an existing install of the
+    /// same name.
+    pub fn install(
+        &self,
+        entry: &IndexEntry,
+        artifact: &[u8],
+        tier: PermissionTier,
+        index_url: &str,
+        policy: &SignaturePolicy,
+    ) -> Result<(InstalledPlugin, Verification), String> {
+        validate_plugin_name(&entry.name)?;
+        let actual = sha256_hex(artifact);
+        if !actual.eq_ignore_ascii_case(entry.sha256.trim()) {
+            return Err(format!(
+                "hash mismatch for {} {}: index says {}, artifact is {}",
+                entry.name, entry.version, entry.sha256, actual
+            ));
+        }
+        let verification = verify_signature(artifact, entry.signature.as_deref(), policy)?;
+
+        let file = match entry.kind {
+            PluginKind::Command => format!("{}-{}", entry.name, entry.version),
+            PluginKind::Wasm => format!("{}-{}.wasm", entry.name, entry.version),
+        };
+        if file.contains('/') || file.contains("..") {
+            return Err(format!("invalid plugin version '{}'", entry.version));
+        }
+        let plugin_dir = self.dir.join(&entry.name);
+        std::fs::create_dir_all(&plugin_dir).map_err(|e| e.to_string())?;
+        let target = plugin_dir.join(&file);
+        let tmp = plugin_dir.join(format!(".{file}.tmp"));
+        std::fs::write(&tmp, artifact).map_err(|e| e.to_string())?;
+        if entry.kind == PluginKind::Command {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o755))
+                .map_err(|e| e.to_string())?;
+        }
+        std::fs::rename(&tmp, &target).map_err(|e| e.to_string())?;
+
+        let record = InstalledPlugin {
+            name: entry.name.clone(),
+            version: entry.version.clone(),
+            description: entry.description.clone(),
+            kind: entry.kind,
+            sha256: actual,
+            schema: entry.schema.clone(),
+            permission_tier: tier_label(tier).to_string(),
+            source_url: entry.url.clone(),
+            index_url: index_url.to_string(),
+            publisher: entry.publisher.clone(),
+            installed_at: chrono::Utc::now().to_rfc3339(),
+            file,
+            signature: entry.signature.clone(),
+            signed_by: match &verification {
+                Verification::Verified { key } => Some(key.clone()),
+                Verification::UnsignedAllowed => None,
+            },
+        };
+        let mut plugins = self.lock();
+        let previous = plugins.iter().position(|p| p.name == record.name);
+        let old_file = previous.map(|i| plugins.remove(i).file);
+        plugins.push(record.clone());
+        self.persist(&plugins).map_err(|e| e.to_string())?;
+        if let Some(old_file) = old_file.filter(|f| *f != record.file) {
+            let _ = std::fs::remove_file(plugin_dir.join(old_file));
+        }
+        Ok((record, verification))
+    }
+
+    pub fn remove(&self, name: &str) -> io::Result<bool> {
+        let mut plugins = self.lock();
+        let before = plugins.len();
+        plugins.retain(|p| p.name != name);
+        if plugins.len() == before {
+            return Ok(false);
+        }
+        self.persist(&plugins)?;
+        let plugin_dir = self.dir.join(name);
+        if plugin_dir.exists() {
+            std::fs::remove_dir_all(plugin_dir)?;
+        }
+        Ok(true)
+    }
+
+    /// Re-hash the installed artifact, compare it with the recorded hash and
+    /// re-check its signature against the current trusted keys.
+    pub fn verify(
+        &self,
+        plugin: &InstalledPlugin,
+        policy: &SignaturePolicy,
+    ) -> Result<Verification, String> {
+        let path = self.artifact_path(plugin);
+        let bytes = std::fs::read(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let actual = sha256_hex(&bytes);
+        if actual != plugin.sha256 {
+            return Err(format!(
+                "{} was modified after install (expected {}, found {})",
+                path.display(),
+                plugin.sha256,
+                actual
+            ));
+        }
+        verify_signature(&bytes, plugin.signature.as_deref(), policy)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<InstalledPlugin>> {
+        self.plugins
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn persist(&self, plugins: &[InstalledPlugin]) -> io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let payload = serde_json::to_string_pretty(plugins)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        let record = self.dir.join(RECORD_FILE);
+        let tmp = record.with_extension("json.tmp");
+        std::fs::write(&tmp, payload)?;
+        std::fs::rename(tmp, record)
+    }
+}
+
+/// Exposes an installed command plugin as a tool.
+pub struct PluginTool {
+    name: &'static str,
+    description: &'static str,
+    schema: Value,
+    tier: PermissionTier,
+    /// The private copy that is executed.
+    path: PathBuf,
+}
+
+impl PluginTool {
+    /// Copies the artifact at `path` to a private read-only location, after
+    /// checking it still has the hash recorded at install time, and runs
+    /// that copy from then on.
+    pub fn new(plugin: &InstalledPlugin, path: PathBuf) -> Result<Self, String> {
+        if plugin.kind != PluginKind::Command {
+            return Err(format!(
+                "plugin '{}' is a wasm module; wasm plugins are not supported by this build",
+                plugin.name
+            ));
+        }
+        let bytes = std::fs::read(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let actual = sha256_hex(&bytes);
+        if actual != plugin.sha256 {
+            return Err(format!(
+                "{} changed after it was verified (expected {}, found {})",
+                path.display(),
+                plugin.sha256,
+                actual
+            ));
+        }
+        let path = private_copy(&plugin.file, &bytes)
+            .map_err(|e| format!("failed to copy plugin '{}': {e}", plugin.name))?;
+        // Tool names and descriptions are 'static; plugins are loaded once at startup.
+        let name: &'static str =
+            Box::leak(format!("{PLUGIN_TOOL_PREFIX}{}", plugin.name).into_boxed_str());
+        let description: &'static str = Box::leak(
+            format!(
+                "{} (plugin {} {})",
+                plugin.description, plugin.name, plugin.version
+            )
+            .into_boxed_str(),
+        );
+        Ok(Self {
+            name,
+            description,
+            schema: plugin.schema.clone(),
+            tier: plugin.tier(),
+            path,
+        })
+    }
+}
+
+impl Drop for PluginTool {
+    fn drop(&mut self) {
+        if let Some(dir) = self.path.parent() {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+}
+
+/// Writes `bytes` as `file` into a fresh directory only this user can open,
+/// under `$XDG_RUNTIME_DIR` when set, readable and executable but not
+/// writable.
+fn private_copy(file: &str, bytes: &[u8]) -> io::Result<PathBuf> {
+    use std::io::Write;
+    use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+
+    let base = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir);
+    let dir = base.join(format!("hypr-claw-plugin-{}", uuid::Uuid::new_v4()));
+    std::fs::DirBuilder::new().mode(0o700).create(&dir)?;
+    let path = dir.join(file);
+    let mut copy = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o500)
+        .open(&path)?;
+    copy.write_all(bytes)?;
+    copy.sync_all()?;
+    Ok(path)
+}
+
+#[async_trait]
+impl Tool for PluginTool {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+    fn description(&self) -> &'static str {
+        self.description
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        self.tier
+    }
+    fn schema(&self) -> Value {
+        self.schema.clone()
+    }
+    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let mut child = tokio::process::Command::new(&self.path)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(input.to_string().as_bytes())
+                .await
+                .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        }
+        let output = tokio::time::timeout(EXEC_TIMEOUT, child.wait_with_output())
+            .await
+            .map_err(|_| ToolError::Timeout)?
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+
+        let mut stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        if stdout.len() > MAX_OUTPUT_BYTES {
+            let mut cut = MAX_OUTPUT_BYTES;
+            while !stdout.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            stdout.truncate(cut);
+        }
+        if !output.status.success() {
+            return Ok(ToolResult {
+                success: false,
+                output: None,
+                error: Some(format!(
+                    "plugin exited with {}: {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )),
+            });
+        }
+        let value = serde_json::from_str(stdout.trim()).unwrap_or(Value::String(stdout));
+        Ok(ToolResult {
+            success: true,
+            output: Some(value),
+            error: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &[u8] = b"#!/bin/sh\nread input\necho \"{\\\"echo\\\": $input}\"\n";
+
+    fn entry(version: &str, bytes: &[u8]) -> IndexEntry {
+        IndexEntry {
+            name: "echo-json".to_string(),
+            version: version.to_string(),
+            description: "Echo input".to_string(),
+            kind: PluginKind::Command,
+            url: format!("echo-json-{version}"),
+            sha256: sha256_hex(bytes),
+            schema: default_schema(),
+            publisher: Some("tests".to_string()),
+            signature: None,
+            signature_url: None,
+        }
+    }
+
+    fn unsigned_ok() -> SignaturePolicy {
+        SignaturePolicy {
+            trusted_keys: Vec::new(),
+            allow_unsigned: true,
+        }
+    }
+
+    // Test vector from the minisign reference implementation: a prehashed
+    // signature over the bytes `test`.
+    const TEST_KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+    const TEST_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==";
+
+    #[test]
+    fn signature_policy_verifies_refuses_unsigned_and_rejects_bad_signatures() {
+        let trusted = SignaturePolicy {
+            trusted_keys: vec![TEST_KEY.to_string()],
+            allow_unsigned: false,
+        };
+        assert_eq!(
+            verify_signature(b"test", Some(TEST_SIGNATURE), &trusted).unwrap(),
+            Verification::Verified {
+                key: TEST_KEY.to_string()
+            }
+        );
+        assert!(verify_signature(b"Test", Some(TEST_SIGNATURE), &trusted).is_err());
+        assert!(verify_signature(b"test", None, &trusted).is_err());
+        assert_eq!(
+            verify_signature(b"test", None, &unsigned_ok()).unwrap(),
+            Verification::UnsignedAllowed
+        );
+        // allow_unsigned never excuses a signature from an untrusted key.
+        assert!(verify_signature(b"test", Some(TEST_SIGNATURE), &unsigned_ok()).is_err());
+    }
+
+    #[test]
+    fn index_picks_latest_version() {
+        let index = PluginIndex {
+            plugins: vec![
+                entry("1.2.0", b"a"),
+                entry("1.10.0", b"b"),
+                entry("1.9.9", b"c"),
+            ],
+        };
+        assert_eq!(index.latest("echo-json").unwrap().version, "1.10.0");
+        assert!(index.latest("missing").is_none());
+        assert_eq!(
+            resolve_url("a-1.0", "https://x.dev/plugins/index.json"),
+            "https://x.dev/plugins/a-1.0"
+        );
+        assert_eq!(resolve_url("/opt/a", "https://x.dev/index.json"), "/opt/a");
+        assert!(PluginIndex::parse(br#"{"plugins":[{"name":"Bad Name","version":"1","kind":"command","url":"x","sha256":"y"}]}"#).is_err());
+    }
+
+    #[test]
+    fn install_rejects_hash_mismatch_and_records_provenance() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PluginStore::open(dir.path()).unwrap();
+        let mut bad = entry("1.0.0", SCRIPT);
+        bad.sha256 = sha256_hex(b"other");
+        assert!(store
+            .install(
+                &bad,
+                SCRIPT,
+                PermissionTier::Read,
+                "file:///index.json",
+                &unsigned_ok()
+            )
+            .is_err());
+        assert!(store
+            .install(
+                &entry("1.0.0", SCRIPT),
+                SCRIPT,
+                PermissionTier::Read,
+                "file:///index.json",
+                &SignaturePolicy::default(),
+            )
+            .is_err());
+        assert!(store.list().is_empty());
+
+        let (installed, verification) = store
+            .install(
+                &entry("1.0.0", SCRIPT),
+                SCRIPT,
+                PermissionTier::Read,
+                "file:///index.json",
+                &unsigned_ok(),
+            )
+            .unwrap();
+        assert_eq!(verification, Verification::UnsignedAllowed);
+        assert_eq!(installed.permission_tier, "read");
+        assert_eq!(installed.index_url, "file:///index.json");
+        store.verify(&installed, &unsigned_ok()).unwrap();
+        assert!(store
+            .verify(&installed, &SignaturePolicy::default())
+            .is_err());
+
+        let reopened = PluginStore::open(dir.path()).unwrap();
+        assert_eq!(reopened.list(), vec![installed.clone()]);
+
+        std::fs::write(store.artifact_path(&installed), b"tampered").unwrap();
+        assert!(store.verify(&installed, &unsigned_ok()).is_err());
+        assert!(store.remove("echo-json").unwrap());
+        assert!(!dir.path().join("echo-json").exists());
+    }
+
+    #[tokio::test]
+    async fn plugin_tool_runs_command_with_json_stdin() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PluginStore::open(dir.path()).unwrap();
+        let (installed, _) = store
+            .install(
+                &entry("1.0.0", SCRIPT),
+                SCRIPT,
+                PermissionTier::Write,
+                "index",
+                &unsigned_ok(),
+            )
+            .unwrap();
+        let tool = PluginTool::new(&installed, store.artifact_path(&installed)).unwrap();
+        assert_eq!(tool.name(), "plugin.echo-json");
+        assert_eq!(tool.permission_tier(), PermissionTier::Write);
+
+        let result = tool
+            .execute(
+                ExecutionContext::new("s".to_string(), 5000),
+                json!({"x": 1}),
+            )
+            .await
+            .unwrap();
+        assert!(result.success);
+        assert_eq!(result.output.unwrap(), json!({"echo": {"x": 1}}));
+    }
+
+    #[tokio::test]
+    async fn plugin_tool_runs_the_verified_bytes_not_the_installed_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PluginStore::open(dir.path()).unwrap();
+        let (installed, _) = store
+            .install(
+                &entry("1.0.0", SCRIPT),
+                SCRIPT,
+                PermissionTier::Write,
+                "index",
+                &unsigned_ok(),
+            )
+            .unwrap();
+        let artifact = store.artifact_path(&installed);
+        let tool = PluginTool::new(&installed, artifact.clone()).unwrap();
+        std::fs::write(&artifact, b"#!/bin/sh\necho swapped\n").unwrap();
+        assert!(PluginTool::new(&installed, artifact).is_err());
+
+        let result = tool
+            .execute(
+                ExecutionContext::new("s".to_string(), 5000),
+                json!({"x": 1}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.output.unwrap(), json!({"echo": {"x": 1}}));
+
+        let copy = tool.path.clone();
+        drop(tool);
+        assert!(!copy.exists());
+    }
+}
@@ -0,0 +1,298 @@
+//! Questions the model asks the user in the middle of a run.
+//!
+//! `ask_user` keeps the run going instead of ending it to ask something. In
+//! the foreground it shows the question (and numbered choices) at the prompt
+//! and returns the answer. Background supervisor runs have nobody watching,
+//! so the question is filed in the pending-questions inbox and the run is
+//! told to stop; answering it from the REPL queues the task again with the
+//! answer attached.
+
+use crate::error::ToolError;
+use crate::execution_context::ExecutionContext;
+use crate::tools::{Tool, ToolResult};
+use crate::traits::PermissionTier;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::time::{timeout, Duration};
+
+/// How long a foreground question waits for an answer.
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(300);
+const MAX_CHOICES: usize = 9;
+/// Background supervisor runs use `<session>::sup::<task id>` session keys.
+pub(crate) const BACKGROUND_MARKER: &str = "::sup::";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Question {
+    pub id: String,
+    pub session_key: String,
+    /// Supervisor task that asked, for background questions.
+    #[serde(default)]
+    pub task: Option<String>,
+    pub question: String,
+    #[serde(default)]
+    pub choices: Vec<String>,
+    pub asked_at: i64,
+    #[serde(default)]
+    pub answer: Option<String>,
+    #[serde(default)]
+    pub answered_at: Option<i64>,
+}
+
+/// JSON-file backed inbox of questions asked by background runs.
+pub struct QuestionInbox {
+    path: PathBuf,
+    questions: Mutex<Vec<Question>>,
+}
+
+impl QuestionInbox {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let questions = if path.exists() {
+            let raw = std::fs::read_to_string(&path)?;
+            if raw.trim().is_empty() {
+                Vec::new()
+            } else {
+                serde_json::from_str(&raw)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?
+            }
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            path,
+            questions: Mutex::new(questions),
+        })
+    }
+
+    pub fn ask(
+        &self,
+        session_key: &str,
+        task: Option<String>,
+        question: &str,
+        choices: Vec<String>,
+    ) -> io::Result<Question> {
+        let mut questions = self.lock();
+        let next_id = questions
+            .iter()
+            .filter_map(|q| q.id.strip_prefix("q-"))
+            .filter_map(|n| n.parse::<u64>().ok())
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let question = Question {
+            id: format!("q-{next_id}"),
+            session_key: session_key.to_string(),
+            task,
+            question: question.to_string(),
+            choices,
+            asked_at: chrono::Utc::now().timestamp(),
+            answer: None,
+            answered_at: None,
+        };
+        questions.push(question.clone());
+        self.persist(&questions)?;
+        Ok(question)
+    }
+
+    pub fn pending(&self) -> Vec<Question> {
+        self.lock()
+            .iter()
+            .filter(|q| q.answer.is_none())
+            .cloned()
+            .collect()
+    }
+
+    /// Records the answer to a pending question; a number picks that choice.
+    /// Returns `None` if no pending question has that id.
+    pub fn answer(&self, id: &str, reply: &str) -> io::Result<Option<Question>> {
+        let mut questions = self.lock();
+        let Some(question) = questions
+            .iter_mut()
+            .find(|q| q.id == id && q.answer.is_none())
+        else {
+            return Ok(None);
+        };
+        question.answer = Some(resolve_choice(&question.choices, reply));
+        question.answered_at = Some(chrono::Utc::now().timestamp());
+        let answered = question.clone();
+        self.persist(&questions)?;
+        Ok(Some(answered))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Question>> {
+        self.questions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn persist(&self, questions: &[Question]) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let payload = serde_json::to_string_pretty(questions)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, payload)?;
+        std::fs::rename(tmp, &self.path)
+    }
+}
+
+/// `2` picks the second choice; anything else is taken as typed.
+pub fn resolve_choice(choices: &[String], reply: &str) -> String {
+    let reply = reply.trim();
+    reply
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_sub(1))
+        .and_then(|index| choices.get(index))
+        .cloned()
+        .unwrap_or_else(|| reply.to_string())
+}
+
+/// Shows a question with its choices and returns the typed reply, or `None`
+/// if the user gave none.
+pub type AskPrompt = Arc<dyn Fn(&str, &[String]) -> Option<String> + Send + Sync>;
+
+pub struct AskUserTool {
+    inbox: Arc<QuestionInbox>,
+    prompt: AskPrompt,
+}
+
+impl AskUserTool {
+    pub fn new(inbox: Arc<QuestionInbox>, prompt: AskPrompt) -> Self {
+        Self { inbox, prompt }
+    }
+}
+
+#[async_trait]
+impl Tool for AskUserTool {
+    fn name(&self) -> &'static str {
+        "ask_user"
+    }
+    fn description(&self) -> &'static str {
+        "Ask the user a question when the task cannot go on without their input (which file, which option, a missing detail), optionally with choices. Use this instead of ending the run to ask; the answer comes back as the result"
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "question": {"type": "string"},
+                "choices": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Options to pick from; the user may still type something else"
+                }
+            },
+            "required": ["question"],
+            "additionalProperties": false
+        })
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let question = input
+            .get("question")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|q| !q.is_empty())
+            .ok_or_else(|| ToolError::ValidationError("'question' must not be empty".into()))?
+            .to_string();
+        let choices: Vec<String> = input
+            .get("choices")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_str())
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(str::to_string)
+            .collect();
+        if choices.len() > MAX_CHOICES {
+            return Err(ToolError::ValidationError(format!(
+                "at most {MAX_CHOICES} choices"
+            )));
+        }
+
+        if let Some((_, task)) = ctx.session_key.split_once(BACKGROUND_MARKER) {
+            let filed = self
+                .inbox
+                .ask(&ctx.session_key, Some(task.to_string()), &question, choices)
+                .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+            return Ok(ToolResult {
+                success: true,
+                output: Some(json!({
+                    "queued": true,
+                    "question_id": filed.id,
+                    "note": "This is a background run, so the question went to the user's inbox. Stop here without guessing; the task runs again once they answer."
+                })),
+                error: None,
+            });
+        }
+
+        let prompt = self.prompt.clone();
+        let shown = (question.clone(), choices.clone());
+        let reply = timeout(
+            ANSWER_TIMEOUT,
+            tokio::task::spawn_blocking(move || prompt(&shown.0, &shown.1)),
+        )
+        .await;
+        let output = match reply {
+            Ok(Ok(Some(reply))) if !reply.trim().is_empty() => {
+                json!({"answer": resolve_choice(&choices, &reply)})
+            }
+            _ => json!({
+                "answer": null,
+                "note": "The user did not answer. Ask again only if you cannot continue; otherwise go on with a safe default and say which."
+            }),
+        };
+        Ok(ToolResult {
+            success: true,
+            output: Some(output),
+            error: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(session_key: &str) -> ExecutionContext {
+        ExecutionContext::new(session_key.to_string(), 5_000)
+    }
+
+    #[tokio::test]
+    async fn foreground_questions_prompt_and_background_ones_go_to_the_inbox() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("questions.json");
+        let inbox = Arc::new(QuestionInbox::open(&path).unwrap());
+        let tool = AskUserTool::new(inbox.clone(), Arc::new(|_, _| Some("2".to_string())));
+        let input = json!({"question": "Which folder?", "choices": ["Documents", "Downloads"]});
+
+        let result = tool
+            .execute(ctx("main::thread::t1"), input.clone())
+            .await
+            .unwrap();
+        assert_eq!(result.output.unwrap()["answer"], "Downloads");
+        assert!(inbox.pending().is_empty());
+
+        let result = tool.execute(ctx("main::sup::sup-4"), input).await.unwrap();
+        let output = result.output.unwrap();
+        assert_eq!(output["queued"], true);
+        assert_eq!(output["question_id"], "q-1");
+
+        let reopened = QuestionInbox::open(&path).unwrap();
+        let pending = reopened.pending();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].task.as_deref(), Some("sup-4"));
+        let answered = reopened.answer("q-1", "1").unwrap().unwrap();
+        assert_eq!(answered.answer.as_deref(), Some("Documents"));
+        assert!(reopened.pending().is_empty());
+        assert!(reopened.answer("q-1", "again").unwrap().is_none());
+    }
+}
@@ -0,0 +1,284 @@
+//! Persistent reminders shared by the `timer.set` tool and the `reminder` commands.
+
+use crate::natural_time;
+use chrono::{Local, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReminderStatus {
+    Pending,
+    Fired,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reminder {
+    pub id: String,
+    pub message: String,
+    pub due_at: i64,
+    pub created_at: i64,
+    pub status: ReminderStatus,
+    #[serde(default)]
+    pub speak: bool,
+    #[serde(default)]
+    pub follow_up_prompt: Option<String>,
+    #[serde(default)]
+    pub fired_at: Option<i64>,
+}
+
+/// JSON-file backed reminder list. Every mutation is flushed to disk so
+/// pending reminders survive restarts.
+pub struct ReminderStore {
+    path: PathBuf,
+    reminders: Mutex<Vec<Reminder>>,
+}
+
+impl ReminderStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let reminders = if path.exists() {
+            let raw = std::fs::read_to_string(&path)?;
+            if raw.trim().is_empty() {
+                Vec::new()
+            } else {
+                serde_json::from_str(&raw)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?
+            }
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            path,
+            reminders: Mutex::new(reminders),
+        })
+    }
+
+    pub fn add(
+        &self,
+        message: &str,
+        due_at: i64,
+        speak: bool,
+        follow_up_prompt: Option<String>,
+    ) -> io::Result<Reminder> {
+        let mut reminders = self.lock();
+        let next_id = reminders
+            .iter()
+            .filter_map(|r| r.id.strip_prefix("rem-"))
+            .filter_map(|n| n.parse::<u64>().ok())
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let reminder = Reminder {
+            id: format!("rem-{next_id}"),
+            message: message.to_string(),
+            due_at,
+            created_at: chrono::Utc::now().timestamp(),
+            status: ReminderStatus::Pending,
+            speak,
+            follow_up_prompt: follow_up_prompt.filter(|p| !p.trim().is_empty()),
+            fired_at: None,
+        };
+        reminders.push(reminder.clone());
+        self.persist(&reminders)?;
+        Ok(reminder)
+    }
+
+    pub fn list(&self) -> Vec<Reminder> {
+        let mut reminders = self.lock().clone();
+        reminders.sort_by_key(|r| r.due_at);
+        reminders
+    }
+
+    pub fn pending(&self) -> Vec<Reminder> {
+        self.list()
+            .into_iter()
+            .filter(|r| r.status == ReminderStatus::Pending)
+            .collect()
+    }
+
+    /// Cancels a pending reminder. Returns `false` if it was not pending.
+    pub fn cancel(&self, id: &str) -> io::Result<bool> {
+        let mut reminders = self.lock();
+        let Some(reminder) = reminders
+            .iter_mut()
+            .find(|r| r.id == id && r.status == ReminderStatus::Pending)
+        else {
+            return Ok(false);
+        };
+        reminder.status = ReminderStatus::Cancelled;
+        self.persist(&reminders)?;
+        Ok(true)
+    }
+
+    /// Marks every pending reminder due at or before `now` as fired and
+    /// returns them for delivery.
+    pub fn take_due(&self, now: i64) -> io::Result<Vec<Reminder>> {
+        let mut reminders = self.lock();
+        let mut due = Vec::new();
+        for reminder in reminders.iter_mut() {
+            if reminder.status == ReminderStatus::Pending && reminder.due_at <= now {
+                reminder.status = ReminderStatus::Fired;
+                reminder.fired_at = Some(now);
+                due.push(reminder.clone());
+            }
+        }
+        if !due.is_empty() {
+            self.persist(&reminders)?;
+        }
+        Ok(due)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Reminder>> {
+        self.reminders
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn persist(&self, reminders: &[Reminder]) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let payload = serde_json::to_string_pretty(reminders)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, payload)?;
+        std::fs::rename(tmp, &self.path)
+    }
+}
+
+/// Parses a relative delay such as `90s`, `10m`, `1h30m` or `2d` into seconds.
+/// A bare number is treated as minutes.
+pub fn parse_delay(spec: &str) -> Option<i64> {
+    let spec = spec.trim().to_ascii_lowercase();
+    if spec.is_empty() {
+        return None;
+    }
+    if let Ok(minutes) = spec.parse::<i64>() {
+        return minutes.checked_mul(60).filter(|seconds| *seconds > 0);
+    }
+
+    let mut total = 0i64;
+    let mut digits = String::new();
+    for ch in spec.chars() {
+        if ch.is_ascii_digit() {
+            digits.push(ch);
+            continue;
+        }
+        let value = digits.parse::<i64>().ok()?;
+        digits.clear();
+        let unit = match ch {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86_400,
+            _ => return None,
+        };
+        total = total.checked_add(value.checked_mul(unit)?)?;
+    }
+    if !digits.is_empty() || total <= 0 {
+        return None;
+    }
+    Some(total)
+}
+
+/// Resolves a reminder time spec to a unix timestamp. Accepts relative delays
+/// (see [`parse_delay`]) or a local wall-clock time `HH:MM`, which rolls over
+/// to tomorrow when it has already passed today, or a future natural-language
+/// time such as `next friday 3pm` (see [`natural_time::parse`]).
+pub fn resolve_due_at(spec: &str, now: i64) -> Option<i64> {
+    if let Some(delay) = parse_delay(spec) {
+        return now.checked_add(delay);
+    }
+    let Ok(time) = NaiveTime::parse_from_str(spec.trim(), "%H:%M") else {
+        let now_local = Local.timestamp_opt(now, 0).single()?;
+        let due = natural_time::parse(spec, &now_local, natural_time::TimeLocale::system())
+            .ok()?
+            .at
+            .timestamp();
+        return (due > now).then_some(due);
+    };
+    let now_local = Local.timestamp_opt(now, 0).single()?;
+    let mut date = now_local.date_naive();
+    loop {
+        let candidate = Local
+            .from_local_datetime(&date.and_time(time))
+            .earliest()?
+            .timestamp();
+        if candidate > now {
+            return Some(candidate);
+        }
+        date = date.succ_opt()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_delay_supports_compound_units() {
+        assert_eq!(parse_delay("90s"), Some(90));
+        assert_eq!(parse_delay("10m"), Some(600));
+        assert_eq!(parse_delay("1h30m"), Some(5400));
+        assert_eq!(parse_delay("5"), Some(300));
+        assert_eq!(parse_delay("10x"), None);
+        assert_eq!(parse_delay("m"), None);
+        assert_eq!(parse_delay("0m"), None);
+    }
+
+    #[test]
+    fn huge_delays_are_rejected_not_overflowed() {
+        assert_eq!(parse_delay(&i64::MAX.to_string()), None);
+        assert_eq!(parse_delay("200000000000000000"), None);
+        assert_eq!(
+            parse_delay("9000000000000000000s"),
+            Some(9_000_000_000_000_000_000)
+        );
+        assert_eq!(
+            resolve_due_at("9000000000000000000s", 1_000_000_000_000_000_000),
+            None
+        );
+    }
+
+    #[test]
+    fn resolve_due_at_rolls_clock_time_forward() {
+        let now = Local::now().timestamp();
+        let due = resolve_due_at("00:00", now).expect("clock time should parse");
+        assert!(due > now);
+        assert!(due - now <= 86_400);
+
+        let due = resolve_due_at("tomorrow 9am", now).expect("natural time should parse");
+        assert!(due > now && due - now <= 2 * 86_400);
+        assert_eq!(resolve_due_at("yesterday 9am", now), None);
+    }
+
+    #[test]
+    fn store_persists_and_fires_due_reminders() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("reminders.json");
+
+        let store = ReminderStore::open(&path).unwrap();
+        let first = store.add("stretch", 100, false, None).unwrap();
+        let second = store
+            .add("standup", 500, true, Some("open meet".to_string()))
+            .unwrap();
+        assert_eq!(first.id, "rem-1");
+        assert_eq!(second.id, "rem-2");
+
+        let reopened = ReminderStore::open(&path).unwrap();
+        assert_eq!(reopened.pending().len(), 2);
+
+        let due = reopened.take_due(200).unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id, "rem-1");
+        assert!(reopened.take_due(200).unwrap().is_empty());
+
+        assert!(reopened.cancel("rem-2").unwrap());
+        assert!(!reopened.cancel("rem-2").unwrap());
+        assert!(ReminderStore::open(&path).unwrap().pending().is_empty());
+    }
+}
@@ -0,0 +1,393 @@
+//! Template-based batch renaming.
+//!
+//! `fs.batch_rename` turns a set of files into new names from one template
+//! instead of one `fs.move` per file. The renames are staged through
+//! [`crate::staging`], so the user sees a single preview in
+//! `fs.apply_staged` and `fs.undo_apply` can put the old names back.
+//!
+//! Templates give the new name without its extension, which is kept:
+//! `{name}` is the old name, `{date}` (or `{date:%Y%m%d}`) the date found in
+//! the name such as `IMG_20240312_...` or the modification date otherwise,
+//! and `{n}` (or `{n:3}` for zero padding) a counter in date order.
+
+use crate::error::ToolError;
+use crate::execution_context::ExecutionContext;
+use crate::staging::{StagedOp, StagingArea};
+use crate::tools::{Tool, ToolResult};
+use crate::traits::PermissionTier;
+use async_trait::async_trait;
+use chrono::format::{Item, StrftimeItems};
+use chrono::NaiveDate;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+/// Renames echoed back in the tool output.
+const PREVIEW_RENAMES: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Name,
+    Date(String),
+    Number { width: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameTemplate {
+    parts: Vec<Part>,
+}
+
+impl RenameTemplate {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            if open > 0 {
+                parts.push(Part::Text(rest[..open].to_string()));
+            }
+            let close = rest[open..]
+                .find('}')
+                .map(|close| open + close)
+                .ok_or_else(|| format!("unclosed '{{' in template '{template}'"))?;
+            let field = &rest[open + 1..close];
+            let (key, arg) = match field.split_once(':') {
+                Some((key, arg)) => (key, Some(arg)),
+                None => (field, None),
+            };
+            parts.push(match (key, arg) {
+                ("name", None) => Part::Name,
+                ("date", format) => {
+                    let format = format.unwrap_or(DEFAULT_DATE_FORMAT);
+                    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+                        return Err(format!("invalid date format '{format}'"));
+                    }
+                    Part::Date(format.to_string())
+                }
+                ("n", width) => Part::Number {
+                    width: match width {
+                        Some(width) => width
+                            .parse()
+                            .map_err(|_| format!("invalid counter width '{width}'"))?,
+                        None => 1,
+                    },
+                },
+                _ => return Err(format!("unknown placeholder '{{{field}}}'")),
+            });
+            rest = &rest[close + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_string()));
+        }
+        if !parts.iter().any(|part| !matches!(part, Part::Text(_))) {
+            return Err("the template needs {name}, {date} or {n} to tell files apart".into());
+        }
+        Ok(Self { parts })
+    }
+
+    fn render(&self, name: &str, date: NaiveDate, number: usize) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.clone(),
+                Part::Name => name.to_string(),
+                Part::Date(format) => date.format(format).to_string(),
+                Part::Number { width } => format!("{number:0width$}"),
+            })
+            .collect()
+    }
+}
+
+/// A date written in a file name: `2024-03-12`, `2024_03_12`, `20240312`...
+pub fn date_in_name(name: &str) -> Option<NaiveDate> {
+    static DATE: OnceLock<Regex> = OnceLock::new();
+    let date = DATE.get_or_init(|| {
+        Regex::new(r"(?:^|\D)((?:19|20)\d{2})[-_.]?(\d{2})[-_.]?(\d{2})(?:\D|$)")
+            .expect("date pattern is valid")
+    });
+    date.captures_iter(name).find_map(|caps| {
+        NaiveDate::from_ymd_opt(
+            caps[1].parse().ok()?,
+            caps[2].parse().ok()?,
+            caps[3].parse().ok()?,
+        )
+    })
+}
+
+/// Keep letters, digits, `-`, `_` and `.`; anything else becomes a single
+/// `-`.
+pub fn sanitize(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') {
+            out.push(c);
+        } else if !out.ends_with('-') {
+            out.push('-');
+        }
+    }
+    out.trim_matches(['-', '.']).to_string()
+}
+
+/// Options for [`plan_renames`].
+#[derive(Debug, Clone)]
+pub struct RenameOptions {
+    pub start: usize,
+    pub sanitize: bool,
+}
+
+impl Default for RenameOptions {
+    fn default() -> Self {
+        Self {
+            start: 1,
+            sanitize: true,
+        }
+    }
+}
+
+/// New names for `files`, numbered in date order (then by name). Files whose
+/// name does not change are left out. Fails if two files would get the same
+/// name; names already taken on disk are caught when the renames are staged.
+pub fn plan_renames(
+    files: &[PathBuf],
+    template: &RenameTemplate,
+    options: &RenameOptions,
+) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let mut dated = Vec::new();
+    for path in files {
+        let meta = path
+            .symlink_metadata()
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        if !meta.is_file() {
+            return Err(format!("{} is not a file", path.display()));
+        }
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let date = date_in_name(&stem).or_else(|| {
+            let modified = meta.modified().ok()?;
+            Some(chrono::DateTime::<chrono::Local>::from(modified).date_naive())
+        });
+        dated.push((date.unwrap_or_default(), path, stem));
+    }
+    dated.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(b.1)));
+
+    let mut claimed = HashSet::new();
+    let mut renames = Vec::new();
+    for (index, (date, path, stem)) in dated.into_iter().enumerate() {
+        let mut new_stem = template.render(&stem, date, options.start + index);
+        if options.sanitize {
+            new_stem = sanitize(&new_stem);
+        }
+        if new_stem.is_empty() || new_stem.contains('/') {
+            return Err(format!(
+                "the template gives an unusable name for {}",
+                path.display()
+            ));
+        }
+        let new_name = match path.extension() {
+            Some(ext) => format!("{new_stem}.{}", ext.to_string_lossy()),
+            None => new_stem,
+        };
+        let target = path.with_file_name(new_name);
+        if !claimed.insert(target.clone()) {
+            return Err(format!(
+                "more than one file would be named {}",
+                target.display()
+            ));
+        }
+        if target != *path {
+            renames.push((path.clone(), target));
+        }
+    }
+    Ok(renames)
+}
+
+/// Regular files directly inside `dir`, optionally only with `extensions`
+/// (compared case-insensitively).
+fn files_in(dir: &Path, extensions: &[String]) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.symlink_metadata().is_ok_and(|meta| meta.is_file()) {
+            continue;
+        }
+        let ext = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+        if extensions.is_empty() || ext.is_some_and(|ext| extensions.contains(&ext)) {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+pub struct FsBatchRenameTool {
+    area: Arc<StagingArea>,
+}
+
+impl FsBatchRenameTool {
+    pub fn new(area: Arc<StagingArea>) -> Self {
+        Self { area }
+    }
+}
+
+#[async_trait]
+impl Tool for FsBatchRenameTool {
+    fn name(&self) -> &'static str {
+        "fs.batch_rename"
+    }
+    fn description(&self) -> &'static str {
+        "Rename many files from one template and stage the renames for a single fs.apply_staged review. Template gives the name without extension: {name}, {date} or {date:%Y%m%d} (from the file name, else modification date), {n} or {n:3} (counter in date order)"
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "template": {"type": "string"},
+                "paths": {"type": "array", "items": {"type": "string"}},
+                "dir": {"type": "string", "description": "Rename the files directly inside"},
+                "extensions": {"type": "array", "items": {"type": "string"}},
+                "start": {"type": "integer", "minimum": 0},
+                "sanitize": {"type": "boolean", "description": "Default true"}
+            },
+            "required": ["template"],
+            "additionalProperties": false
+        })
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    fn path_inputs(&self) -> &'static [&'static str] {
+        &["paths", "dir"]
+    }
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let template = input["template"]
+            .as_str()
+            .ok_or_else(|| ToolError::ValidationError("'template' is required".into()))?;
+        let template = RenameTemplate::parse(template).map_err(ToolError::ValidationError)?;
+        let options = RenameOptions {
+            start: input["start"].as_u64().map_or(1, |start| start as usize),
+            sanitize: input["sanitize"].as_bool().unwrap_or(true),
+        };
+        let mut files: Vec<PathBuf> = input["paths"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|path| path.as_str())
+            .map(|path| ctx.capabilities.confine(path))
+            .collect::<Result<_, _>>()?;
+        if let Some(dir) = input["dir"].as_str() {
+            let extensions: Vec<String> = input["extensions"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|ext| ext.as_str())
+                .map(|ext| ext.trim_start_matches('.').to_lowercase())
+                .collect();
+            files.extend(
+                files_in(&ctx.capabilities.confine(dir)?, &extensions)
+                    .map_err(|e| ToolError::ExecutionFailed(format!("{dir}: {e}")))?,
+            );
+        }
+        if files.is_empty() {
+            return Err(ToolError::ValidationError(
+                "give 'paths' or a 'dir' with matching files".into(),
+            ));
+        }
+
+        let renames =
+            plan_renames(&files, &template, &options).map_err(ToolError::ValidationError)?;
+        let ops = renames
+            .iter()
+            .map(|(from, to)| StagedOp::Move {
+                from: from.clone(),
+                to: to.clone(),
+            })
+            .collect();
+        let staged = self
+            .area
+            .stage(&ctx.session_key, ops)
+            .map_err(ToolError::ValidationError)?;
+        let preview: Vec<Value> = renames
+            .iter()
+            .take(PREVIEW_RENAMES)
+            .map(|(from, to)| json!({"from": from, "to": to}))
+            .collect();
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({
+                "renames": renames.len(),
+                "unchanged": files.len() - renames.len(),
+                "preview": preview,
+                "staged_total": staged,
+                "note": "Nothing is renamed yet. Call fs.apply_staged to review and apply."
+            })),
+            error: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates_fill_dates_counters_and_names() {
+        let template = RenameTemplate::parse("{date:%Y%m%d}-trip-{n:2}").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 12).unwrap();
+        assert_eq!(template.render("x", date, 7), "20240312-trip-07");
+        assert!(RenameTemplate::parse("photo").is_err());
+        assert!(RenameTemplate::parse("{size}").is_err());
+        assert!(RenameTemplate::parse("{name").is_err());
+
+        assert_eq!(date_in_name("IMG_20240312_101500"), Some(date));
+        assert_eq!(date_in_name("Screenshot 2024-03-12 at 10"), Some(date));
+        assert_eq!(date_in_name("invoice-20241399"), None);
+        assert_eq!(sanitize("My  Photo (1)!"), "My-Photo-1");
+    }
+
+    #[test]
+    fn renames_are_numbered_in_date_order_and_staged() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name);
+        std::fs::write(path("IMG_20240314_0900.jpg"), "b").unwrap();
+        std::fs::write(path("IMG_20240312_1200.jpg"), "a").unwrap();
+        std::fs::write(path("notes.txt"), "").unwrap();
+        let template = RenameTemplate::parse("{date}_{n:2}").unwrap();
+        let files = files_in(dir.path(), &["jpg".to_string()]).unwrap();
+        let renames = plan_renames(&files, &template, &RenameOptions::default()).unwrap();
+        assert_eq!(
+            renames,
+            vec![
+                (path("IMG_20240312_1200.jpg"), path("2024-03-12_01.jpg")),
+                (path("IMG_20240314_0900.jpg"), path("2024-03-14_02.jpg")),
+            ]
+        );
+
+        let clash = RenameTemplate::parse("{date:%Y}").unwrap();
+        assert!(plan_renames(&files, &clash, &RenameOptions::default()).is_err());
+
+        // Applied through the staging area and undone from its journal.
+        let area = StagingArea::new();
+        let ops: Vec<StagedOp> = renames
+            .iter()
+            .map(|(from, to)| StagedOp::Move {
+                from: from.clone(),
+                to: to.clone(),
+            })
+            .collect();
+        area.stage("s", ops).unwrap();
+        let staged = area.take("s");
+        crate::staging::apply(&staged).unwrap();
+        area.record_applied("s", &staged);
+        assert!(path("2024-03-12_01.jpg").exists());
+        crate::staging::apply(&area.take_undo("s")).unwrap();
+        assert!(path("IMG_20240312_1200.jpg").exists());
+        assert!(path("IMG_20240314_0900.jpg").exists());
+        assert!(area.take_undo("s").is_empty());
+    }
+}
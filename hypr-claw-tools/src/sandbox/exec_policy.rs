@@ -0,0 +1,137 @@
+use super::command_analysis;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// User rules for the command lines `hypr.exec` and `proc.spawn` may run.
+/// Patterns are regexes searched anywhere in the command line (program and
+/// arguments joined by spaces). `deny` wins over `allow`; an empty `allow`
+/// permits every command not denied.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExecPolicy {
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+impl ExecPolicy {
+    /// Patterns that are not valid regexes, with the parse error.
+    pub fn invalid_patterns(&self) -> Vec<(String, String)> {
+        self.allow
+            .iter()
+            .chain(&self.deny)
+            .filter_map(|p| Regex::new(p).err().map(|e| (p.clone(), e.to_string())))
+            .collect()
+    }
+
+    pub fn compile(&self) -> Result<ExecRules, regex::Error> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|p| Regex::new(p))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(ExecRules {
+            allow: compile(&self.allow)?,
+            deny: compile(&self.deny)?,
+        })
+    }
+}
+
+/// Compiled [`ExecPolicy`], checked by the dispatcher before the permission
+/// engine sees a call.
+#[derive(Debug, Clone, Default)]
+pub struct ExecRules {
+    allow: Vec<Regex>,
+    deny: Vec<Regex>,
+}
+
+impl ExecRules {
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Why a command line is refused, if it is.
+    pub fn check(&self, command_line: &str) -> Option<String> {
+        if let Some(rule) = self.deny.iter().find(|r| r.is_match(command_line)) {
+            return Some(format!(
+                "command '{command_line}' matches deny rule '{}'",
+                rule.as_str()
+            ));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|r| r.is_match(command_line)) {
+            return Some(format!("command '{command_line}' matches no allow rule"));
+        }
+        None
+    }
+
+    /// Why a tool call is refused, if it runs a command line the rules refuse.
+    pub fn check_call(&self, tool_name: &str, input: &Value) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+        let line = command_analysis::command_line(tool_name, input)?;
+        self.check(&line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn policy(allow: &[&str], deny: &[&str]) -> ExecRules {
+        ExecPolicy {
+            allow: allow.iter().map(|p| p.to_string()).collect(),
+            deny: deny.iter().map(|p| p.to_string()).collect(),
+        }
+        .compile()
+        .unwrap()
+    }
+
+    #[test]
+    fn deny_wins_and_allow_restricts() {
+        let rules = policy(&[], &[r"\bsudo\b", r"^dd\b", r"pacman\s+-R"]);
+        assert!(rules.check("kitty --hold").is_none());
+        assert!(rules
+            .check("sh -c 'sudo reboot'")
+            .unwrap()
+            .contains(r"deny rule '\bsudo\b'"));
+        assert!(rules.check("dd if=a of=b").is_some());
+        assert!(rules.check("pacman -Rns firefox").is_some());
+        assert!(rules.check("pacman -S firefox").is_none());
+
+        let rules = policy(&["^(kitty|firefox)\\b"], &["--private"]);
+        assert!(rules.check("firefox https://example.com").is_none());
+        assert!(rules.check("firefox --private").is_some());
+        assert!(rules
+            .check("alacritty")
+            .unwrap()
+            .ends_with("matches no allow rule"));
+    }
+
+    #[test]
+    fn calls_are_checked_for_exec_tools_only() {
+        let rules = policy(&[], &[r"\bsudo\b"]);
+        assert!(rules
+            .check_call(
+                "proc.spawn",
+                &json!({"command": "sudo", "args": ["pacman", "-Syu"]})
+            )
+            .is_some());
+        assert!(rules
+            .check_call("hypr.exec", &json!({"command": "kitty"}))
+            .is_none());
+        assert!(rules
+            .check_call("fs.write", &json!({"command": "sudo"}))
+            .is_none());
+
+        let bad = ExecPolicy {
+            allow: vec!["(".to_string()],
+            deny: vec![r"\bsudo\b".to_string()],
+        };
+        assert_eq!(bad.invalid_patterns().len(), 1);
+        assert!(bad.compile().is_err());
+    }
+}
@@ -0,0 +1,198 @@
+use super::expand_home;
+use landlock::{
+    path_beneath_rules, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreatedAttr, RulesetStatus,
+    ABI,
+};
+use seccompiler::{BpfProgram, SeccompAction, SeccompFilter};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Read-only roots every hardened agent needs: binaries, libraries,
+/// configuration and kernel interfaces.
+const SYSTEM_READ_ROOTS: &[&str] = &[
+    "/usr", "/bin", "/sbin", "/lib", "/lib64", "/etc", "/opt", "/proc", "/sys", "/run",
+];
+
+/// Read-write roots besides the working directory.
+const SYSTEM_WRITE_ROOTS: &[&str] = &["/tmp", "/dev"];
+
+/// Syscalls the agent never needs: debugging other processes, changing the
+/// mount table, loading kernel code and rebooting.
+const DENIED_SYSCALLS: &[i64] = &[
+    libc::SYS_ptrace,
+    libc::SYS_process_vm_readv,
+    libc::SYS_process_vm_writev,
+    libc::SYS_mount,
+    libc::SYS_umount2,
+    libc::SYS_pivot_root,
+    libc::SYS_chroot,
+    libc::SYS_unshare,
+    libc::SYS_setns,
+    libc::SYS_kexec_load,
+    libc::SYS_init_module,
+    libc::SYS_finit_module,
+    libc::SYS_delete_module,
+    libc::SYS_reboot,
+    libc::SYS_swapon,
+    libc::SYS_swapoff,
+    libc::SYS_bpf,
+    libc::SYS_perf_event_open,
+    libc::SYS_userfaultfd,
+    libc::SYS_open_by_handle_at,
+    libc::SYS_keyctl,
+    libc::SYS_add_key,
+    libc::SYS_request_key,
+    libc::SYS_acct,
+];
+
+/// Self-hardening applied to the agent process at startup.
+///
+/// Landlock limits the filesystem to the system roots, the working directory
+/// and the configured `read` / `write` roots; the seccomp filter makes the
+/// syscalls in [`DENIED_SYSCALLS`] fail with `EPERM`. Both are inherited by
+/// every process the agent starts, so apps it launches only see the same
+/// roots, and setuid helpers such as `sudo` stop working.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HardeningPolicy {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub read: Vec<String>,
+    #[serde(default)]
+    pub write: Vec<String>,
+    #[serde(default = "default_true")]
+    pub seccomp: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for HardeningPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            read: Vec::new(),
+            write: Vec::new(),
+            seccomp: true,
+        }
+    }
+}
+
+/// What [`HardeningPolicy::apply`] managed to enforce.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HardeningReport {
+    /// `full`, `partial` (older kernel ABI) or `none` (Landlock unavailable).
+    pub landlock: &'static str,
+    pub seccomp: bool,
+}
+
+impl HardeningPolicy {
+    /// Existing read-only and read-write roots for a process working in
+    /// `workdir`. Missing paths are skipped; Landlock needs an open handle.
+    pub fn roots(&self, workdir: &Path) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let existing = |paths: Vec<PathBuf>| -> Vec<PathBuf> {
+            paths.into_iter().filter(|p| p.exists()).collect()
+        };
+        let read = SYSTEM_READ_ROOTS
+            .iter()
+            .map(PathBuf::from)
+            .chain(self.read.iter().map(|p| expand_home(p)))
+            .collect();
+        let write = std::iter::once(workdir.to_path_buf())
+            .chain(SYSTEM_WRITE_ROOTS.iter().map(PathBuf::from))
+            .chain(self.write.iter().map(|p| expand_home(p)))
+            .collect();
+        (existing(read), existing(write))
+    }
+
+    /// Configured roots that are not absolute after `~` expansion.
+    pub fn relative_paths(&self) -> Vec<String> {
+        self.read
+            .iter()
+            .chain(&self.write)
+            .filter(|p| !expand_home(p).is_absolute())
+            .cloned()
+            .collect()
+    }
+
+    /// Restricts the calling thread and every thread or process it creates
+    /// afterwards. Call it before the async runtime starts its workers:
+    /// Landlock cannot reach threads that already exist.
+    pub fn apply(&self, workdir: &Path) -> Result<HardeningReport, Box<dyn std::error::Error>> {
+        let abi = ABI::V5;
+        let (read, write) = self.roots(workdir);
+        let status = Ruleset::default()
+            .handle_access(AccessFs::from_all(abi))?
+            .create()?
+            .add_rules(path_beneath_rules(&read, AccessFs::from_read(abi)))?
+            .add_rules(path_beneath_rules(&write, AccessFs::from_all(abi)))?
+            .restrict_self()?;
+        let landlock = match status.ruleset {
+            RulesetStatus::FullyEnforced => "full",
+            RulesetStatus::PartiallyEnforced => "partial",
+            RulesetStatus::NotEnforced => "none",
+        };
+
+        if self.seccomp {
+            seccompiler::apply_filter_all_threads(&seccomp_program()?)?;
+        }
+        Ok(HardeningReport {
+            landlock,
+            seccomp: self.seccomp,
+        })
+    }
+}
+
+/// BPF program denying [`DENIED_SYSCALLS`] with `EPERM` and allowing the rest.
+pub fn seccomp_program() -> Result<BpfProgram, Box<dyn std::error::Error>> {
+    let rules: BTreeMap<i64, Vec<seccompiler::SeccompRule>> =
+        DENIED_SYSCALLS.iter().map(|&nr| (nr, Vec::new())).collect();
+    let filter = SeccompFilter::new(
+        rules,
+        SeccompAction::Allow,
+        SeccompAction::Errno(libc::EPERM as u32),
+        std::env::consts::ARCH.try_into()?,
+    )?;
+    Ok(filter.try_into()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roots_cover_system_dirs_and_workdir_only() {
+        let workdir = tempfile::tempdir().unwrap();
+        let policy = HardeningPolicy {
+            enabled: true,
+            read: vec!["/does/not/exist".to_string()],
+            write: vec![workdir.path().join("out").display().to_string()],
+            ..HardeningPolicy::default()
+        };
+        let (read, write) = policy.roots(workdir.path());
+        assert!(read.contains(&PathBuf::from("/usr")));
+        assert!(!read.iter().any(|p| p.starts_with("/does")));
+        assert_eq!(write[0], workdir.path());
+        assert!(write.contains(&PathBuf::from("/tmp")));
+        assert!(!write.iter().any(|p| p.ends_with("out")));
+        if let Some(home) = std::env::var_os("HOME") {
+            assert!(!read.contains(&PathBuf::from(&home)));
+            assert!(!write.contains(&PathBuf::from(home)));
+        }
+    }
+
+    #[test]
+    fn policy_defaults_and_seccomp_program_builds() {
+        let policy: HardeningPolicy = serde_json::from_str("{}").unwrap();
+        assert!(!policy.enabled);
+        assert!(policy.seccomp);
+
+        let policy: HardeningPolicy =
+            serde_json::from_str(r#"{"enabled": true, "read": ["~/Documents", "docs"]}"#).unwrap();
+        assert_eq!(policy.relative_paths(), vec!["docs".to_string()]);
+
+        assert!(!seccomp_program().unwrap().is_empty());
+    }
+}
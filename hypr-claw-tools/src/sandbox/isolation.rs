@@ -0,0 +1,148 @@
+use super::expand_home;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const BWRAP: &str = "bwrap";
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IsolationMode {
+    /// Spawned processes run with the agent's own view of the system.
+    #[default]
+    Off,
+    /// Spawned processes run under bubblewrap.
+    Bwrap,
+}
+
+/// How `proc.spawn` isolates the processes it starts.
+///
+/// Under `bwrap` the whole filesystem is mounted read-only, `hidden` paths
+/// are replaced by empty tmpfs mounts, `writable` paths are bound back
+/// read-write, and every namespace is unshared. The network namespace is
+/// shared only when `network` is set and the dispatch grants unrestricted
+/// network access.
+/// `~` at the start of a path is the user's home directory.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IsolationPolicy {
+    #[serde(default)]
+    pub mode: IsolationMode,
+    #[serde(default = "default_hidden")]
+    pub hidden: Vec<String>,
+    #[serde(default)]
+    pub writable: Vec<String>,
+    #[serde(default)]
+    pub network: bool,
+}
+
+fn default_hidden() -> Vec<String> {
+    vec!["~".to_string()]
+}
+
+impl Default for IsolationPolicy {
+    fn default() -> Self {
+        Self {
+            mode: IsolationMode::Off,
+            hidden: default_hidden(),
+            writable: Vec::new(),
+            network: false,
+        }
+    }
+}
+
+impl IsolationPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.mode != IsolationMode::Off
+    }
+
+    /// Configured paths that are not absolute after `~` expansion.
+    pub fn relative_paths(&self) -> Vec<String> {
+        self.hidden
+            .iter()
+            .chain(&self.writable)
+            .filter(|p| !expand_home(p).is_absolute())
+            .cloned()
+            .collect()
+    }
+
+    /// `bwrap` arguments that run `program args` under this policy.
+    /// The network is shared only if the policy enables it and
+    /// `network_granted` is set.
+    pub fn bwrap_args(&self, program: &str, args: &[String], network_granted: bool) -> Vec<String> {
+        let mut out: Vec<String> = ["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        for path in &self.hidden {
+            let path = expand_home(path).to_string_lossy().to_string();
+            out.extend(["--tmpfs".to_string(), path]);
+        }
+        for path in &self.writable {
+            let path = expand_home(path).to_string_lossy().to_string();
+            out.extend(["--bind-try".to_string(), path.clone(), path]);
+        }
+        out.push("--unshare-all".to_string());
+        if self.network && network_granted {
+            out.push("--share-net".to_string());
+        }
+        // Keeps the child from pushing input into the agent's terminal.
+        out.push("--new-session".to_string());
+        out.push("--".to_string());
+        out.push(program.to_string());
+        out.extend(args.iter().cloned());
+        out
+    }
+}
+
+/// Whether `bwrap` is on `PATH`.
+pub fn bwrap_available() -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| is_file(&dir.join(BWRAP))))
+}
+
+fn is_file(path: &Path) -> bool {
+    path.metadata().map(|m| m.is_file()).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(policy: &IsolationPolicy, network_granted: bool) -> Vec<String> {
+        policy.bwrap_args("ls", &["-la".to_string()], network_granted)
+    }
+
+    #[test]
+    fn bwrap_args_hide_home_and_drop_network_unless_granted() {
+        let home = std::env::var("HOME").unwrap_or_else(|_| "~".to_string());
+        let policy = IsolationPolicy {
+            mode: IsolationMode::Bwrap,
+            writable: vec!["/tmp/work".to_string()],
+            ..IsolationPolicy::default()
+        };
+        let out = args(&policy, true).join(" ");
+        assert!(out.starts_with("--ro-bind / / --dev /dev --proc /proc"));
+        assert!(out.contains(&format!("--tmpfs {home} --bind-try /tmp/work /tmp/work")));
+        assert!(out.ends_with("--unshare-all --new-session -- ls -la"));
+
+        let networked = IsolationPolicy {
+            network: true,
+            ..policy
+        };
+        assert!(args(&networked, true).contains(&"--share-net".to_string()));
+        assert!(!args(&networked, false).contains(&"--share-net".to_string()));
+    }
+
+    #[test]
+    fn policy_defaults_off_and_flags_relative_paths() {
+        let policy: IsolationPolicy = serde_json::from_str("{}").unwrap();
+        assert!(!policy.is_enabled());
+        assert_eq!(policy.hidden, vec!["~".to_string()]);
+        assert!(policy.relative_paths().is_empty());
+
+        let policy: IsolationPolicy =
+            serde_json::from_str(r#"{"mode": "bwrap", "writable": ["~/Downloads", "out"]}"#)
+                .unwrap();
+        assert!(policy.is_enabled());
+        assert_eq!(policy.relative_paths(), vec!["out".to_string()]);
+    }
+}
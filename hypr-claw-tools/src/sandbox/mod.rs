@@ -1,7 +1,30 @@
+pub mod command_analysis;
 pub mod command_guard;
+pub mod env_policy;
+pub mod exec_policy;
+pub mod hardening;
+pub mod isolation;
 pub mod limits;
 pub mod path_guard;
 
 pub use command_guard::CommandGuard;
+pub use env_policy::EnvPolicy;
+pub use exec_policy::{ExecPolicy, ExecRules};
+pub use hardening::HardeningPolicy;
+pub use isolation::{IsolationMode, IsolationPolicy};
 pub use limits::ResourceLimits;
 pub use path_guard::PathGuard;
+
+use std::path::PathBuf;
+
+/// `path` with a leading `~` replaced by the user's home directory.
+pub(crate) fn expand_home(path: &str) -> PathBuf {
+    let home = || std::env::var_os("HOME").map(PathBuf::from);
+    match path.strip_prefix('~') {
+        Some("") => home().unwrap_or_else(|| PathBuf::from(path)),
+        Some(rest) if rest.starts_with('/') => home()
+            .map(|h| h.join(rest.trim_start_matches('/')))
+            .unwrap_or_else(|| PathBuf::from(path)),
+        _ => PathBuf::from(path),
+    }
+}
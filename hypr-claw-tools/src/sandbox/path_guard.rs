@@ -1,6 +1,6 @@
 use crate::error::ToolError;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024; // 10MB
 
@@ -109,4 +109,44 @@ impl PathGuard {
 
         Ok(full_path)
     }
+
+    /// Like [`validate_new`](Self::validate_new), but also takes absolute
+    /// paths, for tools that address the whole filesystem and are only
+    /// narrowed by a granted root. Relative paths resolve against the root.
+    pub fn contain(&self, path: impl AsRef<Path>) -> Result<PathBuf, ToolError> {
+        let path = path.as_ref();
+        if path.components().any(|c| c == Component::ParentDir) {
+            return Err(ToolError::SandboxViolation(
+                "Path traversal detected".into(),
+            ));
+        }
+        let full_path = self.sandbox_root.join(path);
+
+        // Resolve symlinks through the deepest ancestor that exists; the
+        // missing tail cannot point anywhere yet.
+        let mut existing = full_path.as_path();
+        let mut missing = Vec::new();
+        while fs::symlink_metadata(existing).is_err() {
+            match (existing.parent(), existing.file_name()) {
+                (Some(parent), Some(name)) => {
+                    missing.push(name);
+                    existing = parent;
+                }
+                _ => break,
+            }
+        }
+        let mut resolved = fs::canonicalize(existing).map_err(|_| {
+            ToolError::SandboxViolation("Path does not exist or is inaccessible".into())
+        })?;
+        resolved.extend(missing.iter().rev());
+
+        if !resolved.starts_with(&self.sandbox_root) {
+            return Err(ToolError::SandboxViolation(format!(
+                "{} is outside {}",
+                path.display(),
+                self.sandbox_root.display()
+            )));
+        }
+        Ok(resolved)
+    }
 }
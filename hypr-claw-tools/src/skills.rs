@@ -0,0 +1,930 @@
+//! Skills: parameterized sequences of tool calls defined in YAML.
+//!
+//! Each `<name>.yaml` file in the skills directory becomes a `skill.<name>`
+//! tool. Steps are dispatched one by one through the tool dispatcher, so
+//! every step goes through the same permission checks and audit log as a
+//! call made by the model. Everything a skill refers to (tools, parameters,
+//! earlier steps) is checked when it is loaded, not when it runs.
+//!
+//! ```yaml
+//! description: Move screenshots out of Downloads
+//! params:
+//!   dir:
+//!     description: Folder to tidy
+//!     default: /home/me/Downloads
+//!   dry_run:
+//!     type: boolean
+//!     default: false
+//! steps:
+//!   - id: list
+//!     tool: fs.list
+//!     input: { path: "{{dir}}" }
+//!   - tool: fs.create_dir
+//!     input: { path: "{{dir}}/Screenshots" }
+//!     when: { param: dry_run, equals: false }
+//!     continue_on_error: true
+//! ```
+//!
+//! A string input that is exactly `{{param}}` takes the parameter's value
+//! with its type; otherwise placeholders are replaced by text.
+//!
+//! An optional `permissions:` manifest lists reviewed calls (tool, target
+//! and, for calls with a second path such as a move, `to`; a trailing `*`
+//! matches anything under a prefix) that may run without asking while the
+//! skill runs. Any other call, such as a step on a different path, goes
+//! through normal approval.
+
+use crate::approvals::{ApprovalBook, PermissionGrant};
+use crate::dispatcher::ToolDispatcherImpl;
+use crate::error::ToolError;
+use crate::execution_context::ExecutionContext;
+use crate::registry::ToolRegistryImpl;
+use crate::tools::base::{Tool, ToolResult};
+use crate::traits::PermissionTier;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, Weak};
+
+/// Registered tool names are `skill.<name>`.
+pub const SKILL_TOOL_PREFIX: &str = "skill.";
+
+/// Time allowed for a whole skill; each step keeps the dispatcher's own
+/// per-call timeout.
+const SKILL_TIMEOUT_MS: u64 = 5 * 60 * 1000;
+
+/// The dispatcher skill steps run through. Skills are registered before the
+/// dispatcher exists, so it is filled in once startup created it.
+pub type DispatcherSlot = Arc<OnceLock<Weak<ToolDispatcherImpl>>>;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamKind {
+    #[default]
+    String,
+    Number,
+    Boolean,
+}
+
+impl ParamKind {
+    fn matches(self, value: &Value) -> bool {
+        match self {
+            Self::String => value.is_string(),
+            Self::Number => value.is_number(),
+            Self::Boolean => value.is_boolean(),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Number => "number",
+            Self::Boolean => "boolean",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SkillParam {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default, rename = "type")]
+    pub kind: ParamKind,
+    /// Parameters without a default are required.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+}
+
+/// Runs a step only if a parameter has (or lacks) a value, or an earlier
+/// step succeeded (or failed).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Condition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub param: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub equals: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_equals: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub succeeded: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SkillStep {
+    /// Lets later conditions refer to this step.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub tool: String,
+    #[serde(default = "empty_object")]
+    pub input: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<Condition>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub continue_on_error: bool,
+}
+
+fn empty_object() -> Value {
+    json!({})
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Skill {
+    #[serde(skip)]
+    pub name: String,
+    pub description: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, SkillParam>,
+    pub steps: Vec<SkillStep>,
+    /// Reviewed calls that run without asking; see the module docs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub permissions: Vec<PermissionGrant>,
+}
+
+impl Skill {
+    pub fn parse(name: &str, yaml: &str) -> Result<Self, String> {
+        validate_skill_name(name)?;
+        let mut skill: Skill =
+            serde_yaml::from_str(yaml).map_err(|e| format!("invalid skill '{name}': {e}"))?;
+        skill.name = name.to_string();
+        Ok(skill)
+    }
+
+    pub fn to_yaml(&self) -> Result<String, String> {
+        serde_yaml::to_string(self).map_err(|e| e.to_string())
+    }
+
+    /// Checks every reference in the skill and returns the most sensitive
+    /// permission tier among its steps' tools.
+    pub fn verify(&self, registry: &ToolRegistryImpl) -> Result<PermissionTier, String> {
+        let fail = |step: usize, message: String| Err(format!("step {}: {message}", step + 1));
+        if self.steps.is_empty() {
+            return Err("a skill needs at least one step".to_string());
+        }
+        for (name, param) in &self.params {
+            if let Some(default) = &param.default {
+                if !param.kind.matches(default) {
+                    return Err(format!(
+                        "default of parameter '{name}' is not a {}",
+                        param.kind.label()
+                    ));
+                }
+            }
+        }
+
+        let mut tier = PermissionTier::Read;
+        let mut earlier_steps = HashSet::new();
+        for (i, step) in self.steps.iter().enumerate() {
+            if step.tool.starts_with(SKILL_TOOL_PREFIX) {
+                return fail(i, "skills cannot call other skills".to_string());
+            }
+            let Some(tool) = registry.get(&step.tool) else {
+                return fail(i, format!("unknown tool '{}'", step.tool));
+            };
+            tier = tier.max(tool.permission_tier());
+            if !step.input.is_object() {
+                return fail(i, "input must be a mapping".to_string());
+            }
+            for placeholder in
+                placeholders(&step.input).map_err(|e| format!("step {}: {e}", i + 1))?
+            {
+                if !self.params.contains_key(&placeholder) {
+                    return fail(i, format!("unknown parameter '{{{{{placeholder}}}}}'"));
+                }
+            }
+            if let Some(condition) = &step.when {
+                self.verify_condition(condition, &earlier_steps)
+                    .map_err(|e| format!("step {}: {e}", i + 1))?;
+            }
+            if let Some(id) = &step.id {
+                if !earlier_steps.insert(id.as_str()) {
+                    return fail(i, format!("duplicate step id '{id}'"));
+                }
+            }
+        }
+        for grant in &self.permissions {
+            if !self.steps.iter().any(|step| step.tool == grant.tool) {
+                return Err(format!(
+                    "permission for {}, which no step calls",
+                    grant.tool
+                ));
+            }
+            let templated = |pattern: &str| pattern.trim().is_empty() || pattern.contains("{{");
+            if templated(&grant.target) || grant.to.as_deref().is_some_and(templated) {
+                return Err(format!(
+                    "permission for {} needs a literal target",
+                    grant.tool
+                ));
+            }
+        }
+        Ok(tier)
+    }
+
+    fn verify_condition(
+        &self,
+        condition: &Condition,
+        earlier_steps: &HashSet<&str>,
+    ) -> Result<(), String> {
+        match condition {
+            Condition {
+                param: Some(param),
+                step: None,
+                succeeded: None,
+                equals,
+                not_equals,
+            } => {
+                let Some(declared) = self.params.get(param) else {
+                    return Err(format!("condition on unknown parameter '{param}'"));
+                };
+                let value = match (equals, not_equals) {
+                    (Some(value), None) | (None, Some(value)) => value,
+                    _ => {
+                        return Err(format!(
+                            "condition on '{param}' needs exactly one of equals or not_equals"
+                        ))
+                    }
+                };
+                if !declared.kind.matches(value) {
+                    return Err(format!(
+                        "condition compares '{param}' with a value that is not a {}",
+                        declared.kind.label()
+                    ));
+                }
+                Ok(())
+            }
+            Condition {
+                step: Some(step),
+                param: None,
+                equals: None,
+                not_equals: None,
+                succeeded: Some(_),
+            } => {
+                if earlier_steps.contains(step.as_str()) {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "condition on '{step}', which is not an earlier step"
+                    ))
+                }
+            }
+            _ => Err(
+                "condition needs either param with equals/not_equals or step with succeeded"
+                    .to_string(),
+            ),
+        }
+    }
+
+    /// Parameter values for one run: given arguments checked against their
+    /// declared types, then defaults.
+    fn bind(&self, input: &Value) -> Result<Map<String, Value>, String> {
+        let given = match input {
+            Value::Object(given) => given.clone(),
+            Value::Null => Map::new(),
+            _ => return Err("skill arguments must be an object".to_string()),
+        };
+        if let Some(unknown) = given.keys().find(|key| !self.params.contains_key(*key)) {
+            return Err(format!("unknown parameter '{unknown}'"));
+        }
+        let mut values = Map::new();
+        for (name, param) in &self.params {
+            let value = match given.get(name).or(param.default.as_ref()) {
+                Some(value) => value.clone(),
+                None => return Err(format!("missing parameter '{name}'")),
+            };
+            if !param.kind.matches(&value) {
+                return Err(format!(
+                    "parameter '{name}' must be a {}",
+                    param.kind.label()
+                ));
+            }
+            values.insert(name.clone(), value);
+        }
+        Ok(values)
+    }
+
+    fn schema(&self) -> Value {
+        let properties = self
+            .params
+            .iter()
+            .map(|(name, param)| {
+                let mut property = json!({ "type": param.kind.label() });
+                if !param.description.is_empty() {
+                    property["description"] = json!(param.description);
+                }
+                if let Some(default) = &param.default {
+                    property["default"] = default.clone();
+                }
+                (name.clone(), property)
+            })
+            .collect::<Map<_, _>>();
+        let required = self
+            .params
+            .iter()
+            .filter(|(_, param)| param.default.is_none())
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        json!({ "type": "object", "properties": properties, "required": required })
+    }
+}
+
+/// Drafts a skill that replays `calls`, the tool name and input of each
+/// successful call of a run in order. String arguments the user typed in
+/// `prompt`, or that recur across calls, become parameters named after the
+/// field they first appear in and defaulting to the value used. Calls that
+/// may ask for approval are pre-granted exactly as they were made, so the
+/// recorded run replays without prompts but changed arguments do not.
+pub fn draft_from_calls(
+    name: &str,
+    prompt: &str,
+    calls: &[(String, Value)],
+    registry: &ToolRegistryImpl,
+) -> Result<Skill, String> {
+    validate_skill_name(name)?;
+    if calls.is_empty() {
+        return Err("the run made no successful tool calls".to_string());
+    }
+    if let Some((tool, _)) = calls
+        .iter()
+        .find(|(tool, _)| tool.starts_with(SKILL_TOOL_PREFIX))
+    {
+        return Err(format!(
+            "the run called {tool}; skills cannot call other skills"
+        ));
+    }
+
+    let typed = prompt.to_lowercase();
+    // Field, value and the call it was first seen in; `usize::MAX` once the
+    // value shows up in a second call.
+    let mut seen: Vec<(String, String, usize)> = Vec::new();
+    for (i, (_, input)) in calls.iter().enumerate() {
+        for (field, value) in string_fields(input) {
+            match seen
+                .iter_mut()
+                .find(|(_, seen_value, _)| *seen_value == value)
+            {
+                Some((_, _, uses)) if *uses != i => *uses = usize::MAX,
+                Some(_) => {}
+                None => seen.push((field, value, i)),
+            }
+        }
+    }
+
+    let mut params = BTreeMap::new();
+    let mut names = BTreeMap::new();
+    for (field, value, uses) in seen {
+        let was_typed = value.trim().len() >= 2 && typed.contains(&value.to_lowercase());
+        if !was_typed && uses != usize::MAX {
+            continue;
+        }
+        let base = param_name(&field);
+        let mut param = base.clone();
+        let mut n = 2;
+        while params.contains_key(&param) {
+            param = format!("{base}_{n}");
+            n += 1;
+        }
+        params.insert(
+            param.clone(),
+            SkillParam {
+                description: format!("{field} of the recorded run"),
+                kind: ParamKind::String,
+                default: Some(Value::String(value.clone())),
+            },
+        );
+        names.insert(value, param);
+    }
+
+    let steps = calls
+        .iter()
+        .map(|(tool, input)| SkillStep {
+            id: None,
+            tool: tool.clone(),
+            input: parameterize(input, &names),
+            when: None,
+            continue_on_error: false,
+        })
+        .collect();
+    let mut permissions: Vec<PermissionGrant> = Vec::new();
+    for (tool, input) in calls {
+        let asks = registry
+            .get(tool)
+            .is_some_and(|tool| tool.permission_tier() > PermissionTier::Read);
+        let grant = PermissionGrant::for_call(tool, input);
+        if asks && !permissions.contains(&grant) {
+            permissions.push(grant);
+        }
+    }
+    let mut description: String = prompt.chars().take(80).collect();
+    if description.len() < prompt.len() {
+        description.push_str("...");
+    }
+    Ok(Skill {
+        name: name.to_string(),
+        description: format!("Learned from: {description}"),
+        params,
+        steps,
+        permissions,
+    })
+}
+
+/// `(field, value)` of every string value in `input`, depth first.
+fn string_fields(input: &Value) -> Vec<(String, String)> {
+    let mut found = Vec::new();
+    let mut stack = vec![(String::new(), input)];
+    while let Some((field, value)) = stack.pop() {
+        match value {
+            Value::String(text) if !field.is_empty() => found.push((field, text.clone())),
+            Value::Object(fields) => {
+                for (key, value) in fields.iter().rev() {
+                    stack.push((key.clone(), value));
+                }
+            }
+            Value::Array(items) => {
+                for item in items.iter().rev() {
+                    stack.push((field.clone(), item));
+                }
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+fn param_name(field: &str) -> String {
+    let name: String = field
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        "value".to_string()
+    } else {
+        name
+    }
+}
+
+fn parameterize(value: &Value, names: &BTreeMap<String, String>) -> Value {
+    match value {
+        Value::String(text) => match names.get(text) {
+            Some(param) => Value::String(format!("{{{{{param}}}}}")),
+            None => value.clone(),
+        },
+        Value::Array(items) => {
+            Value::Array(items.iter().map(|item| parameterize(item, names)).collect())
+        }
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, field)| (key.clone(), parameterize(field, names)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+pub fn validate_skill_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || name.len() > 40
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+    {
+        return Err(format!(
+            "invalid skill name '{name}': use lowercase letters, digits, '-' or '_'"
+        ));
+    }
+    Ok(())
+}
+
+/// Parses every `*.yaml`/`*.yml` file in `dir`, named after the file. A
+/// missing directory has no skills.
+pub fn load_dir(dir: &Path) -> io::Result<Vec<(PathBuf, Result<Skill, String>)>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("yaml" | "yml")
+            )
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    Ok(paths
+        .into_iter()
+        .map(|path| {
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let skill = std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|yaml| Skill::parse(&name, &yaml));
+            (path, skill)
+        })
+        .collect())
+}
+
+/// Names of the parameters referenced by `{{...}}` in string values.
+fn placeholders(value: &Value) -> Result<Vec<String>, String> {
+    let mut found = Vec::new();
+    match value {
+        Value::String(text) => {
+            let mut rest = text.as_str();
+            while let Some(start) = rest.find("{{") {
+                let Some(len) = rest[start + 2..].find("}}") else {
+                    return Err(format!("unterminated placeholder in '{text}'"));
+                };
+                found.push(rest[start + 2..start + 2 + len].trim().to_string());
+                rest = &rest[start + 2 + len + 2..];
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                found.extend(placeholders(item)?);
+            }
+        }
+        Value::Object(fields) => {
+            for field in fields.values() {
+                found.extend(placeholders(field)?);
+            }
+        }
+        _ => {}
+    }
+    Ok(found)
+}
+
+fn render(value: &Value, params: &Map<String, Value>) -> Value {
+    match value {
+        Value::String(text) => {
+            let trimmed = text.trim();
+            if let Some(name) = trimmed
+                .strip_prefix("{{")
+                .and_then(|rest| rest.strip_suffix("}}"))
+                .filter(|name| !name.contains("{{") && !name.contains("}}"))
+            {
+                if let Some(value) = params.get(name.trim()) {
+                    return value.clone();
+                }
+            }
+            let mut out = text.clone();
+            for (name, value) in params {
+                let text = match value {
+                    Value::String(text) => text.clone(),
+                    other => other.to_string(),
+                };
+                out = out.replace(&format!("{{{{{name}}}}}"), &text);
+            }
+            Value::String(out)
+        }
+        Value::Array(items) => {
+            Value::Array(items.iter().map(|item| render(item, params)).collect())
+        }
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, field)| (key.clone(), render(field, params)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn condition_holds(
+    condition: &Condition,
+    params: &Map<String, Value>,
+    outcomes: &BTreeMap<String, bool>,
+) -> bool {
+    if let Some(param) = &condition.param {
+        let value = params.get(param);
+        return match (&condition.equals, &condition.not_equals) {
+            (Some(expected), _) => value == Some(expected),
+            (_, Some(unexpected)) => value != Some(unexpected),
+            _ => false,
+        };
+    }
+    match (&condition.step, condition.succeeded) {
+        (Some(step), Some(succeeded)) => outcomes.get(step) == Some(&succeeded),
+        _ => false,
+    }
+}
+
+/// Exposes a verified skill as a tool.
+pub struct SkillTool {
+    name: &'static str,
+    description: &'static str,
+    skill: Skill,
+    tier: PermissionTier,
+    dispatcher: DispatcherSlot,
+    approvals: Option<Arc<ApprovalBook>>,
+}
+
+/// Revokes a running skill's grants however its run ends.
+struct GrantGuard<'a> {
+    book: &'a ApprovalBook,
+    session_key: &'a str,
+}
+
+impl Drop for GrantGuard<'_> {
+    fn drop(&mut self) {
+        self.book.revoke(self.session_key);
+    }
+}
+
+impl SkillTool {
+    pub fn new(
+        skill: Skill,
+        registry: &ToolRegistryImpl,
+        dispatcher: DispatcherSlot,
+    ) -> Result<Self, String> {
+        let tier = skill.verify(registry)?;
+        // Tool names and descriptions are 'static; skills are loaded once at startup.
+        let name: &'static str =
+            Box::leak(format!("{SKILL_TOOL_PREFIX}{}", skill.name).into_boxed_str());
+        let description: &'static str = Box::leak(
+            format!("{} (skill, {} steps)", skill.description, skill.steps.len()).into_boxed_str(),
+        );
+        Ok(Self {
+            name,
+            description,
+            skill,
+            tier,
+            dispatcher,
+            approvals: None,
+        })
+    }
+
+    /// Book the skill's `permissions` are granted in while it runs. Without
+    /// one every step asks as usual.
+    pub fn with_approvals(mut self, book: Arc<ApprovalBook>) -> Self {
+        self.approvals = Some(book);
+        self
+    }
+
+    pub fn skill(&self) -> &Skill {
+        &self.skill
+    }
+}
+
+#[async_trait]
+impl Tool for SkillTool {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+    fn description(&self) -> &'static str {
+        self.description
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        self.tier
+    }
+    fn schema(&self) -> Value {
+        self.skill.schema()
+    }
+    fn timeout_ms(&self) -> Option<u64> {
+        Some(SKILL_TIMEOUT_MS)
+    }
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let params = self
+            .skill
+            .bind(&input)
+            .map_err(ToolError::ValidationError)?;
+        let dispatcher = self
+            .dispatcher
+            .get()
+            .and_then(Weak::upgrade)
+            .ok_or_else(|| {
+                ToolError::ExecutionFailed("skill dispatcher is not ready".to_string())
+            })?;
+        let _grants = match &self.approvals {
+            Some(book) if !self.skill.permissions.is_empty() => {
+                book.grant(&ctx.session_key, &self.skill.permissions);
+                Some(GrantGuard {
+                    book,
+                    session_key: &ctx.session_key,
+                })
+            }
+            _ => None,
+        };
+
+        let mut outcomes = BTreeMap::new();
+        let mut log = Vec::new();
+        let mut failed = None;
+        for (i, step) in self.skill.steps.iter().enumerate() {
+            let label = step.id.clone().unwrap_or_else(|| format!("{}", i + 1));
+            if let Some(condition) = &step.when {
+                if !condition_holds(condition, &params, &outcomes) {
+                    log.push(json!({"step": label, "tool": step.tool, "status": "skipped"}));
+                    continue;
+                }
+            }
+            let result = dispatcher
+                .dispatch_with_capabilities(
+                    ctx.session_key.clone(),
+                    step.tool.clone(),
+                    render(&step.input, &params),
+                    ctx.capabilities.clone(),
+                )
+                .await;
+            let mut entry = json!({"step": label, "tool": step.tool});
+            let succeeded = match result {
+                Ok(result) if result.success => {
+                    entry["status"] = json!("ok");
+                    entry["output"] = result.output.unwrap_or(Value::Null);
+                    true
+                }
+                Ok(result) => {
+                    entry["status"] = json!("failed");
+                    entry["error"] = json!(result.error);
+                    false
+                }
+                Err(e) => {
+                    entry["status"] = json!("failed");
+                    entry["error"] = json!(e.to_string());
+                    false
+                }
+            };
+            if let Some(id) = &step.id {
+                outcomes.insert(id.clone(), succeeded);
+            }
+            log.push(entry);
+            if !succeeded && !step.continue_on_error {
+                failed = Some(format!("step {label} ({}) failed", step.tool));
+                break;
+            }
+        }
+
+        Ok(ToolResult {
+            success: failed.is_none(),
+            output: Some(json!({"skill": self.skill.name, "steps": log})),
+            error: failed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approvals::action_target;
+
+    fn registry() -> ToolRegistryImpl {
+        let mut registry = ToolRegistryImpl::new();
+        registry.register(Arc::new(crate::tools::EchoTool));
+        registry
+    }
+
+    const SKILL: &str = r#"
+description: Greet twice
+params:
+  who:
+    description: Name to greet
+  loud:
+    type: boolean
+    default: false
+steps:
+  - id: first
+    tool: echo
+    input: { message: "hello {{who}}", flag: "{{loud}}" }
+  - tool: echo
+    input: { message: "HELLO {{who}}" }
+    when: { param: loud, equals: true }
+  - tool: echo
+    input: { message: "done" }
+    when: { step: first, succeeded: true }
+"#;
+
+    #[test]
+    fn verifies_and_binds_parameters() {
+        let skill = Skill::parse("greet", SKILL).unwrap();
+        assert_eq!(skill.verify(&registry()).unwrap(), PermissionTier::Write);
+        let params = skill.bind(&json!({"who": "ana"})).unwrap();
+        assert_eq!(params["loud"], json!(false));
+        assert_eq!(
+            render(&skill.steps[0].input, &params),
+            json!({"message": "hello ana", "flag": false})
+        );
+        assert!(skill
+            .bind(&json!({}))
+            .unwrap_err()
+            .contains("missing parameter 'who'"));
+        assert!(skill
+            .bind(&json!({"who": "ana", "loud": "yes"}))
+            .unwrap_err()
+            .contains("must be a boolean"));
+        assert_eq!(skill.schema()["required"], json!(["who"]));
+    }
+
+    #[test]
+    fn load_time_checks_reject_bad_references() {
+        let check = |yaml: &str| {
+            Skill::parse("bad", yaml)
+                .and_then(|skill| skill.verify(&registry()).map(|_| ()))
+                .unwrap_err()
+        };
+        assert!(check("description: x\nsteps:\n  - tool: nope\n").contains("unknown tool 'nope'"));
+        assert!(check("description: x\nsteps:\n  - tool: skill.other\n").contains("other skills"));
+        assert!(check(
+            "description: x\nsteps:\n  - tool: echo\n    input: { message: \"{{who}}\" }\n"
+        )
+        .contains("unknown parameter"));
+        assert!(check(
+            "description: x\nsteps:\n  - tool: echo\n    when: { step: later, succeeded: true }\n  - id: later\n    tool: echo\n"
+        )
+        .contains("not an earlier step"));
+        assert!(check(
+            "description: x\nparams:\n  n:\n    type: number\nsteps:\n  - tool: echo\n    when: { param: n, equals: \"1\" }\n"
+        )
+        .contains("not a number"));
+        assert!(
+            check("description: x\nsteps:\n  - tool: echo\n    when: { param: n }\n")
+                .contains("condition")
+        );
+        assert!(check(
+            "description: x\nsteps:\n  - tool: echo\npermissions:\n  - { tool: fs.delete, target: /tmp }\n"
+        )
+        .contains("no step calls"));
+        assert!(check(
+            "description: x\nsteps:\n  - tool: echo\npermissions:\n  - { tool: echo, target: \"{{p}}\" }\n"
+        )
+        .contains("literal target"));
+        assert!(Skill::parse("Bad Name", "description: x\nsteps: []\n").is_err());
+    }
+
+    #[test]
+    fn drafts_parameterize_typed_and_shared_values() {
+        let calls = vec![
+            ("echo".to_string(), json!({"message": "/home/u/Downloads"})),
+            (
+                "echo".to_string(),
+                json!({"message": "report.pdf", "flag": "/home/u/Downloads"}),
+            ),
+            ("echo".to_string(), json!({"message": "fixed", "count": 3})),
+        ];
+        let draft =
+            draft_from_calls("tidy", "Move REPORT.pdf somewhere", &calls, &registry()).unwrap();
+        assert_eq!(draft.params.len(), 2);
+        assert_eq!(
+            draft.params["message"].default,
+            Some(json!("/home/u/Downloads"))
+        );
+        assert_eq!(draft.params["message_2"].default, Some(json!("report.pdf")));
+        assert_eq!(
+            draft.steps[1].input,
+            json!({"message": "{{message_2}}", "flag": "{{message}}"})
+        );
+        assert_eq!(
+            draft.steps[2].input,
+            json!({"message": "fixed", "count": 3})
+        );
+        // Grants are for the calls exactly as recorded.
+        assert_eq!(draft.permissions.len(), 3);
+        assert_eq!(draft.permissions[0].target, action_target(&calls[0].1));
+
+        // The draft is a valid skill that round-trips through YAML.
+        let reloaded = Skill::parse("tidy", &draft.to_yaml().unwrap()).unwrap();
+        assert_eq!(reloaded, draft);
+        assert!(reloaded.verify(&registry()).is_ok());
+
+        let nested = vec![("skill.other".to_string(), json!({}))];
+        assert!(draft_from_calls("tidy", "", &nested, &registry()).is_err());
+    }
+
+    #[test]
+    fn conditions_follow_parameters_and_step_outcomes() {
+        let params = Map::from_iter([("loud".to_string(), json!(true))]);
+        let outcomes = BTreeMap::from([("first".to_string(), false)]);
+        let on_param = |equals| Condition {
+            param: Some("loud".to_string()),
+            equals: Some(json!(equals)),
+            ..Condition::default()
+        };
+        assert!(condition_holds(&on_param(true), &params, &outcomes));
+        assert!(!condition_holds(&on_param(false), &params, &outcomes));
+        let on_step = Condition {
+            step: Some("first".to_string()),
+            succeeded: Some(false),
+            ..Condition::default()
+        };
+        assert!(condition_holds(&on_step, &params, &outcomes));
+    }
+}
@@ -0,0 +1,307 @@
+//! Sandboxed runs of short Python and Bash snippets for `code.run_snippet`.
+//!
+//! Each snippet is written to a fresh temporary directory and run under
+//! bubblewrap with only `/usr` (and the `/bin`, `/lib` links into it) mounted
+//! read-only, the temporary directory as its writable working directory
+//! `/work`, and every namespace unshared, so it has no network and cannot see
+//! the user's files. Resource limits cap its CPU time, memory, file sizes,
+//! open files and processes, a wall-clock limit stops it, and it is killed
+//! once its output goes past what is kept.
+
+use crate::sandbox::isolation::{self, BWRAP};
+use serde::Serialize;
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::{Child, Command};
+
+/// Working directory of a snippet inside the sandbox.
+pub const WORKDIR: &str = "/work";
+
+/// Seconds of CPU time a snippet may use.
+const CPU_SECS: u64 = 2;
+/// Address space limit.
+const MEMORY_BYTES: u64 = 256 * 1024 * 1024;
+/// Largest file a snippet may write.
+const FILE_BYTES: u64 = 16 * 1024 * 1024;
+const OPEN_FILES: u64 = 64;
+/// Processes a snippet may run at once; bwrap's user namespace counts them
+/// apart from the user's own.
+const PROCESSES: u64 = 64;
+/// Wall-clock limit, kept below the dispatcher's tool timeout.
+pub const WALL_LIMIT: Duration = Duration::from_secs(4);
+/// Longest snippet accepted.
+pub const MAX_CODE_BYTES: usize = 16 * 1024;
+/// Bytes of stdout and of stderr kept; a snippet writing more is killed.
+pub const MAX_OUTPUT_BYTES: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Python,
+    Bash,
+}
+
+impl Language {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "python" | "python3" | "py" => Some(Self::Python),
+            "bash" | "sh" | "shell" => Some(Self::Bash),
+            _ => None,
+        }
+    }
+
+    fn interpreter(self) -> &'static str {
+        match self {
+            Self::Python => "/usr/bin/python3",
+            Self::Bash => "/usr/bin/bash",
+        }
+    }
+
+    fn file_name(self) -> &'static str {
+        match self {
+            Self::Python => "snippet.py",
+            Self::Bash => "snippet.sh",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct SnippetOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// `None` when the snippet was killed by a signal, e.g. for running out
+    /// of CPU time.
+    pub exit_code: Option<i32>,
+    pub truncated: bool,
+}
+
+/// `bwrap` arguments that run the snippet file of `language` with `dir`
+/// mounted as [`WORKDIR`].
+pub fn bwrap_args(language: Language, dir: &Path) -> Vec<String> {
+    let dir = dir.to_string_lossy();
+    let script = format!("{WORKDIR}/{}", language.file_name());
+    let mut out: Vec<String> = ["--ro-bind", "/usr", "/usr"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    for link in ["/bin", "/sbin", "/lib", "/lib64"] {
+        out.extend(["--ro-bind-try", link, link].map(str::to_string));
+    }
+    out.extend(
+        [
+            "--proc",
+            "/proc",
+            "--dev",
+            "/dev",
+            "--tmpfs",
+            "/tmp",
+            "--bind",
+            &dir,
+            WORKDIR,
+            "--chdir",
+            WORKDIR,
+            "--unshare-all",
+            "--die-with-parent",
+            "--new-session",
+            "--",
+            language.interpreter(),
+            &script,
+        ]
+        .map(str::to_string),
+    );
+    out
+}
+
+/// Runs `code` in the sandbox and returns its output, or why it could not
+/// run. Stops it after `wall_limit`.
+pub async fn run(
+    language: Language,
+    code: &str,
+    wall_limit: Duration,
+) -> Result<SnippetOutput, String> {
+    if code.len() > MAX_CODE_BYTES {
+        return Err(format!(
+            "snippet is {} bytes, the limit is {MAX_CODE_BYTES}",
+            code.len()
+        ));
+    }
+    if !isolation::bwrap_available() {
+        return Err("running snippets needs bwrap, which is not installed".to_string());
+    }
+
+    let dir = std::env::temp_dir().join(format!("hypr-claw-snippet-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let result = run_in(language, code, &dir, wall_limit).await;
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+async fn run_in(
+    language: Language,
+    code: &str,
+    dir: &Path,
+    wall_limit: Duration,
+) -> Result<SnippetOutput, String> {
+    std::fs::write(dir.join(language.file_name()), code).map_err(|e| e.to_string())?;
+
+    let mut cmd = Command::new(BWRAP);
+    cmd.args(bwrap_args(language, dir))
+        .env_clear()
+        .env("PATH", "/usr/bin:/bin")
+        .env("HOME", WORKDIR)
+        .env("TMPDIR", "/tmp")
+        .env("LANG", "C.UTF-8")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    // SAFETY: only async-signal-safe calls between fork and exec.
+    unsafe {
+        cmd.pre_exec(|| {
+            for (resource, limit) in [
+                (libc::RLIMIT_CPU, CPU_SECS),
+                (libc::RLIMIT_AS, MEMORY_BYTES),
+                (libc::RLIMIT_FSIZE, FILE_BYTES),
+                (libc::RLIMIT_NOFILE, OPEN_FILES),
+                (libc::RLIMIT_NPROC, PROCESSES),
+            ] {
+                let limit = libc::rlimit {
+                    rlim_cur: limit,
+                    rlim_max: limit,
+                };
+                if libc::setrlimit(resource, &limit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("failed to start bwrap: {e}"))?;
+    let (stdout, stderr, status) = tokio::time::timeout(wall_limit, capture(&mut child))
+        .await
+        .map_err(|_| format!("snippet ran longer than {}s", wall_limit.as_secs_f32()))?
+        .map_err(|e| format!("failed to read the snippet's output: {e}"))?;
+    let (stdout, stdout_cut) = truncate(&stdout);
+    let (stderr, stderr_cut) = truncate(&stderr);
+    Ok(SnippetOutput {
+        stdout,
+        stderr,
+        exit_code: status.code(),
+        truncated: stdout_cut || stderr_cut,
+    })
+}
+
+/// Reads `child`'s stdout and stderr, at most one byte past
+/// [`MAX_OUTPUT_BYTES`] each, killing it as soon as either goes past, then
+/// waits for it.
+async fn capture(child: &mut Child) -> std::io::Result<(Vec<u8>, Vec<u8>, ExitStatus)> {
+    let mut stdout = Box::pin(read_capped(child.stdout.take()));
+    let mut stderr = Box::pin(read_capped(child.stderr.take()));
+    let (mut out, mut err) = (None, None);
+    while out.is_none() || err.is_none() {
+        let read = tokio::select! {
+            bytes = &mut stdout, if out.is_none() => out.insert(bytes?).len(),
+            bytes = &mut stderr, if err.is_none() => err.insert(bytes?).len(),
+        };
+        if read > MAX_OUTPUT_BYTES {
+            child.start_kill()?;
+        }
+    }
+    let status = child.wait().await?;
+    Ok((out.unwrap_or_default(), err.unwrap_or_default(), status))
+}
+
+async fn read_capped(pipe: Option<impl AsyncRead + Unpin>) -> std::io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    if let Some(pipe) = pipe {
+        pipe.take(MAX_OUTPUT_BYTES as u64 + 1)
+            .read_to_end(&mut bytes)
+            .await?;
+    }
+    Ok(bytes)
+}
+
+/// The first [`MAX_OUTPUT_BYTES`] of `bytes` as text, and whether any were
+/// cut.
+fn truncate(bytes: &[u8]) -> (String, bool) {
+    let cut = bytes.len() > MAX_OUTPUT_BYTES;
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(MAX_OUTPUT_BYTES)]);
+    (text.into_owned(), cut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bwrap_args_expose_only_usr_and_the_workdir() {
+        let args = bwrap_args(Language::Python, Path::new("/tmp/hypr-claw-snippet-x")).join(" ");
+        assert!(args.starts_with("--ro-bind /usr /usr --ro-bind-try /bin /bin"));
+        assert!(args.contains("--bind /tmp/hypr-claw-snippet-x /work --chdir /work"));
+        assert!(args.contains("--unshare-all"));
+        assert!(!args.contains("--share-net"));
+        assert!(!args.contains("--ro-bind / /"));
+        assert!(args.ends_with("-- /usr/bin/python3 /work/snippet.py"));
+    }
+
+    #[test]
+    fn languages_parse_and_output_is_truncated() {
+        assert_eq!(Language::parse("Python3"), Some(Language::Python));
+        assert_eq!(Language::parse("sh"), Some(Language::Bash));
+        assert_eq!(Language::parse("ruby"), None);
+
+        let (text, cut) = truncate(&vec![b'a'; MAX_OUTPUT_BYTES + 1]);
+        assert!(cut);
+        assert_eq!(text.len(), MAX_OUTPUT_BYTES);
+        assert_eq!(truncate(b"ok"), ("ok".to_string(), false));
+    }
+
+    #[tokio::test]
+    async fn endless_output_kills_the_process() {
+        let mut child = Command::new("yes")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+        let (stdout, stderr, status) =
+            tokio::time::timeout(Duration::from_secs(5), capture(&mut child))
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(stdout.len(), MAX_OUTPUT_BYTES + 1);
+        assert!(stderr.is_empty());
+        assert_eq!(status.code(), None);
+    }
+
+    #[tokio::test]
+    async fn snippets_compute_without_network_or_home() {
+        if !isolation::bwrap_available() {
+            return;
+        }
+        let out = run(Language::Bash, "echo $((6 * 7)); ls ~ | wc -l", WALL_LIMIT)
+            .await
+            .unwrap();
+        assert_eq!(out.stdout, "42\n1\n");
+        assert_eq!(out.exit_code, Some(0));
+
+        let out = run(
+            Language::Bash,
+            "exec 3<>/dev/tcp/1.1.1.1/80 && echo connected",
+            WALL_LIMIT,
+        )
+        .await
+        .unwrap();
+        assert_ne!(out.exit_code, Some(0));
+        assert!(!out.stdout.contains("connected"));
+
+        let err = run(Language::Bash, "sleep 10", Duration::from_millis(200))
+            .await
+            .unwrap_err();
+        assert!(err.contains("longer than"), "{err}");
+    }
+}
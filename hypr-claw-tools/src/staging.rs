@@ -0,0 +1,739 @@
+//! Two-phase bulk deletes and moves.
+//!
+//! For organize and cleanup tasks the model first collects what it intends
+//! to delete or move with `fs.stage`, which changes nothing. `fs.apply_staged`
+//! then shows the user one consolidated preview (counts, total size, the
+//! largest items and sample paths) and applies the whole manifest only if
+//! they accept. Deletes are first renamed to a hidden sibling so every step
+//! can be undone; if a step fails, the completed ones are rolled back in
+//! reverse order and nothing is removed. The moves of the last applied
+//! manifest are journaled so `fs.undo_apply` can put them back.
+
+use crate::error::ToolError;
+use crate::execution_context::ExecutionContext;
+use crate::os_capabilities::wipe;
+use crate::tools::{Tool, ToolResult};
+use crate::traits::PermissionTier;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::time::{timeout, Duration};
+
+/// How long the user has to answer the preview.
+const DECISION_TIMEOUT: Duration = Duration::from_secs(120);
+const PREVIEW_ITEMS: usize = 5;
+/// Entries visited when sizing one directory; larger trees are undercounted.
+const MAX_SIZE_ENTRIES: usize = 10_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum StagedOp {
+    Delete {
+        path: PathBuf,
+        /// Overwrite file contents before unlinking, see
+        /// [`crate::os_capabilities::wipe`].
+        #[serde(default)]
+        secure: bool,
+    },
+    Move {
+        from: PathBuf,
+        to: PathBuf,
+    },
+}
+
+impl StagedOp {
+    fn source(&self) -> &Path {
+        match self {
+            StagedOp::Delete { path, .. } => path,
+            StagedOp::Move { from, .. } => from,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            StagedOp::Delete {
+                path,
+                secure: false,
+            } => format!("delete {}", path.display()),
+            StagedOp::Delete { path, secure: true } => {
+                format!("securely delete {}", path.display())
+            }
+            StagedOp::Move { from, to } => {
+                format!("move {} -> {}", from.display(), to.display())
+            }
+        }
+    }
+}
+
+/// Staged operations per session, in the order they were staged.
+#[derive(Default)]
+pub struct StagingArea {
+    manifests: Mutex<HashMap<String, Vec<StagedOp>>>,
+    /// Moves of each session's last applied manifest, for undo.
+    applied: Mutex<HashMap<String, Vec<StagedOp>>>,
+}
+
+impl StagingArea {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `ops` to the session's manifest. Nothing is staged if any of them
+    /// is invalid: a missing source, a source staged twice, or a move onto an
+    /// existing or already claimed path.
+    pub fn stage(&self, session_key: &str, ops: Vec<StagedOp>) -> Result<usize, String> {
+        let mut manifests = self
+            .manifests
+            .lock()
+            .map_err(|_| "staging area is unavailable".to_string())?;
+        let manifest = manifests.entry(session_key.to_string()).or_default();
+        let mut combined = manifest.clone();
+        combined.extend(ops);
+        validate(&combined)?;
+        *manifest = combined;
+        Ok(manifest.len())
+    }
+
+    pub fn list(&self, session_key: &str) -> Vec<StagedOp> {
+        self.manifests
+            .lock()
+            .ok()
+            .and_then(|manifests| manifests.get(session_key).cloned())
+            .unwrap_or_default()
+    }
+
+    pub fn take(&self, session_key: &str) -> Vec<StagedOp> {
+        self.manifests
+            .lock()
+            .ok()
+            .and_then(|mut manifests| manifests.remove(session_key))
+            .unwrap_or_default()
+    }
+
+    /// Journal the moves of a manifest that was just applied, replacing the
+    /// previous entry. Deletes cannot be undone and are not kept.
+    pub fn record_applied(&self, session_key: &str, ops: &[StagedOp]) {
+        let moves: Vec<StagedOp> = ops
+            .iter()
+            .filter(|op| matches!(op, StagedOp::Move { .. }))
+            .cloned()
+            .collect();
+        if let Ok(mut applied) = self.applied.lock() {
+            applied.insert(session_key.to_string(), moves);
+        }
+    }
+
+    /// Moves that undo the session's last applied manifest, in the order to
+    /// apply them. The journal entry is removed.
+    pub fn take_undo(&self, session_key: &str) -> Vec<StagedOp> {
+        let moves = self
+            .applied
+            .lock()
+            .ok()
+            .and_then(|mut applied| applied.remove(session_key))
+            .unwrap_or_default();
+        moves
+            .into_iter()
+            .rev()
+            .filter_map(|op| match op {
+                StagedOp::Move { from, to } => Some(StagedOp::Move { from: to, to: from }),
+                StagedOp::Delete { .. } => None,
+            })
+            .collect()
+    }
+}
+
+fn validate(ops: &[StagedOp]) -> Result<(), String> {
+    let mut sources = HashSet::new();
+    let mut destinations = HashSet::new();
+    for op in ops {
+        let source = op.source();
+        if source.parent().is_none() || source.as_os_str().is_empty() {
+            return Err(format!("refusing to stage '{}'", source.display()));
+        }
+        if source.symlink_metadata().is_err() {
+            return Err(format!("{} does not exist", source.display()));
+        }
+        if !sources.insert(source.to_path_buf()) {
+            return Err(format!("{} is staged twice", source.display()));
+        }
+        if let StagedOp::Move { to, .. } = op {
+            if to.symlink_metadata().is_ok() || !destinations.insert(to.clone()) {
+                return Err(format!("{} already exists", to.display()));
+            }
+        }
+    }
+    if let Some(claimed) = destinations.iter().find(|to| sources.contains(*to)) {
+        return Err(format!(
+            "{} is both moved and a move destination",
+            claimed.display()
+        ));
+    }
+    Ok(())
+}
+
+/// What the user is shown before a manifest is applied.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StagingPreview {
+    pub deletes: usize,
+    /// Deletes that overwrite before unlinking.
+    pub secure_deletes: usize,
+    pub moves: usize,
+    pub total_bytes: u64,
+    /// Largest sources, biggest first.
+    pub largest: Vec<(PathBuf, u64)>,
+    /// The first few operations, described.
+    pub samples: Vec<String>,
+}
+
+impl StagingPreview {
+    pub fn build(ops: &[StagedOp]) -> Self {
+        let mut sizes: Vec<(PathBuf, u64)> = ops
+            .iter()
+            .map(|op| (op.source().to_path_buf(), path_size(op.source())))
+            .collect();
+        sizes.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+        Self {
+            deletes: ops
+                .iter()
+                .filter(|op| matches!(op, StagedOp::Delete { .. }))
+                .count(),
+            secure_deletes: ops
+                .iter()
+                .filter(|op| matches!(op, StagedOp::Delete { secure: true, .. }))
+                .count(),
+            moves: ops
+                .iter()
+                .filter(|op| matches!(op, StagedOp::Move { .. }))
+                .count(),
+            total_bytes: sizes.iter().map(|(_, size)| size).sum(),
+            largest: sizes.into_iter().take(PREVIEW_ITEMS).collect(),
+            samples: ops
+                .iter()
+                .take(PREVIEW_ITEMS)
+                .map(StagedOp::describe)
+                .collect(),
+        }
+    }
+
+    pub fn render(&self) -> Vec<String> {
+        let total = self.deletes + self.moves;
+        let secure = match self.secure_deletes {
+            0 => String::new(),
+            n => format!(" ({n} secure)"),
+        };
+        let mut lines = vec![format!(
+            "{} delete(s){secure}, {} move(s), {} in total",
+            self.deletes,
+            self.moves,
+            format_bytes(self.total_bytes)
+        )];
+        lines.push("Largest:".to_string());
+        for (path, size) in &self.largest {
+            lines.push(format!("  {:>9}  {}", format_bytes(*size), path.display()));
+        }
+        lines.push("Sample:".to_string());
+        for sample in &self.samples {
+            lines.push(format!("  {sample}"));
+        }
+        if total > self.samples.len() {
+            lines.push(format!("  ... and {} more", total - self.samples.len()));
+        }
+        lines
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+/// Size of a file, or of everything under a directory; symlinks are not
+/// followed.
+fn path_size(path: &Path) -> u64 {
+    let mut total = 0;
+    let mut visited = 0;
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(path) = pending.pop() {
+        visited += 1;
+        if visited > MAX_SIZE_ENTRIES {
+            break;
+        }
+        let Ok(meta) = path.symlink_metadata() else {
+            continue;
+        };
+        if meta.is_dir() {
+            if let Ok(entries) = std::fs::read_dir(&path) {
+                pending.extend(entries.filter_map(|e| e.ok()).map(|e| e.path()));
+            }
+        } else {
+            total += meta.len();
+        }
+    }
+    total
+}
+
+/// Outcome of applying a manifest.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApplyReport {
+    pub deleted: usize,
+    pub moved: usize,
+    /// Held deletes that could not be removed after everything else
+    /// succeeded; they are left as hidden siblings.
+    pub leftovers: Vec<PathBuf>,
+    /// Secure deletes that were only unlinked, e.g. on a copy-on-write
+    /// filesystem.
+    pub not_overwritten: Vec<PathBuf>,
+}
+
+/// One completed step: `current` is put back at `original` on rollback.
+struct Undo {
+    current: PathBuf,
+    original: PathBuf,
+}
+
+/// Apply `ops` in order, all or nothing. On failure every completed step is
+/// undone and the error names the step that failed.
+pub fn apply(ops: &[StagedOp]) -> Result<ApplyReport, String> {
+    validate(ops)?;
+    let mut undo: Vec<Undo> = Vec::new();
+    let mut created_dirs: Vec<PathBuf> = Vec::new();
+    let mut held = Vec::new();
+    for (index, op) in ops.iter().enumerate() {
+        let step = match op {
+            StagedOp::Delete { path, secure } => holding_path(path).and_then(|hold| {
+                std::fs::rename(path, &hold)?;
+                held.push((hold.clone(), path.clone(), *secure));
+                Ok(Undo {
+                    current: hold,
+                    original: path.clone(),
+                })
+            }),
+            StagedOp::Move { from, to } => create_parents(to, &mut created_dirs).and_then(|()| {
+                std::fs::rename(from, to)?;
+                Ok(Undo {
+                    current: to.clone(),
+                    original: from.clone(),
+                })
+            }),
+        };
+        match step {
+            Ok(done) => undo.push(done),
+            Err(e) => {
+                let failed = format!("step {} ({}) failed: {e}", index + 1, op.describe());
+                return Err(match rollback(undo, created_dirs) {
+                    Ok(()) => format!("{failed}; all earlier steps were rolled back"),
+                    Err(stuck) => {
+                        format!("{failed}; rollback could not restore: {}", stuck.join(", "))
+                    }
+                });
+            }
+        }
+    }
+
+    let mut report = ApplyReport {
+        deleted: held.len(),
+        moved: ops.len() - held.len(),
+        ..ApplyReport::default()
+    };
+    for (hold, original, secure) in held {
+        let removed = if secure {
+            wipe::secure_delete(&hold).map(|overwritten| {
+                if !overwritten {
+                    report.not_overwritten.push(original);
+                }
+            })
+        } else if hold.is_dir() && !hold.is_symlink() {
+            std::fs::remove_dir_all(&hold)
+        } else {
+            std::fs::remove_file(&hold)
+        };
+        if removed.is_err() {
+            report.leftovers.push(hold);
+        }
+    }
+    Ok(report)
+}
+
+/// Hidden sibling a deleted path waits in until the manifest is applied.
+fn holding_path(path: &Path) -> io::Result<PathBuf> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::other("path has no file name"))?
+        .to_string_lossy();
+    let parent = path.parent().unwrap_or(Path::new("."));
+    (0..100)
+        .map(|n| {
+            parent.join(format!(
+                ".{name}.hypr-claw-deleting-{}-{n}",
+                std::process::id()
+            ))
+        })
+        .find(|hold| hold.symlink_metadata().is_err())
+        .ok_or_else(|| io::Error::other("no free holding name"))
+}
+
+fn create_parents(to: &Path, created: &mut Vec<PathBuf>) -> io::Result<()> {
+    let Some(parent) = to.parent() else {
+        return Ok(());
+    };
+    let missing: Vec<PathBuf> = parent
+        .ancestors()
+        .take_while(|dir| !dir.as_os_str().is_empty() && dir.symlink_metadata().is_err())
+        .map(Path::to_path_buf)
+        .collect();
+    for dir in missing.into_iter().rev() {
+        std::fs::create_dir(&dir)?;
+        created.push(dir);
+    }
+    Ok(())
+}
+
+fn rollback(undo: Vec<Undo>, created_dirs: Vec<PathBuf>) -> Result<(), Vec<String>> {
+    let mut stuck = Vec::new();
+    for step in undo.into_iter().rev() {
+        if std::fs::rename(&step.current, &step.original).is_err() {
+            stuck.push(step.original.display().to_string());
+        }
+    }
+    for dir in created_dirs.into_iter().rev() {
+        let _ = std::fs::remove_dir(dir);
+    }
+    if stuck.is_empty() {
+        Ok(())
+    } else {
+        Err(stuck)
+    }
+}
+
+/// Asks the user whether to apply a previewed manifest.
+pub type StagingDecider = Arc<dyn Fn(&StagingPreview) -> bool + Send + Sync>;
+
+pub struct FsStageTool {
+    area: Arc<StagingArea>,
+}
+
+impl FsStageTool {
+    pub fn new(area: Arc<StagingArea>) -> Self {
+        Self { area }
+    }
+}
+
+#[async_trait]
+impl Tool for FsStageTool {
+    fn name(&self) -> &'static str {
+        "fs.stage"
+    }
+    fn description(&self) -> &'static str {
+        "Stage deletes and moves for a bulk organize or cleanup task without changing anything. Stage everything first, then call fs.apply_staged once so the user can review the whole plan"
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "deletes": {"type": "array", "items": {"type": "string"}},
+                "moves": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "from": {"type": "string"},
+                            "to": {"type": "string"}
+                        },
+                        "required": ["from", "to"]
+                    }
+                },
+                "secure": {
+                    "type": "boolean",
+                    "description": "Overwrite these deletes before unlinking, for sensitive files"
+                },
+                "clear": {"type": "boolean", "description": "Drop what is staged first"}
+            },
+            "additionalProperties": false
+        })
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    fn path_inputs(&self) -> &'static [&'static str] {
+        &["deletes", "moves.from", "moves.to"]
+    }
+    async fn execute(&self, ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let mut ops = Vec::new();
+        let secure = input["secure"].as_bool() == Some(true);
+        for path in input["deletes"].as_array().into_iter().flatten() {
+            let path = path
+                .as_str()
+                .ok_or_else(|| ToolError::ValidationError("'deletes' must be paths".into()))?;
+            ops.push(StagedOp::Delete {
+                path: ctx.capabilities.confine(path)?,
+                secure,
+            });
+        }
+        for item in input["moves"].as_array().into_iter().flatten() {
+            let (Some(from), Some(to)) = (item["from"].as_str(), item["to"].as_str()) else {
+                return Err(ToolError::ValidationError(
+                    "every move needs 'from' and 'to'".into(),
+                ));
+            };
+            ops.push(StagedOp::Move {
+                from: ctx.capabilities.confine(from)?,
+                to: ctx.capabilities.confine(to)?,
+            });
+        }
+        if input["clear"].as_bool() == Some(true) {
+            self.area.take(&ctx.session_key);
+        }
+        let staged = self
+            .area
+            .stage(&ctx.session_key, ops)
+            .map_err(ToolError::ValidationError)?;
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({
+                "staged": staged,
+                "note": "Nothing has changed yet. Call fs.apply_staged when everything is staged."
+            })),
+            error: None,
+        })
+    }
+}
+
+pub struct FsApplyStagedTool {
+    area: Arc<StagingArea>,
+    decide: StagingDecider,
+}
+
+impl FsApplyStagedTool {
+    pub fn new(area: Arc<StagingArea>, decide: StagingDecider) -> Self {
+        Self { area, decide }
+    }
+}
+
+#[async_trait]
+impl Tool for FsApplyStagedTool {
+    fn name(&self) -> &'static str {
+        "fs.apply_staged"
+    }
+    fn description(&self) -> &'static str {
+        "Show the user a preview of everything staged with fs.stage and, if they accept, apply it all at once; a failed step rolls back the others"
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {},
+            "additionalProperties": false
+        })
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Write
+    }
+    async fn execute(&self, ctx: ExecutionContext, _input: Value) -> Result<ToolResult, ToolError> {
+        let ops = self.area.take(&ctx.session_key);
+        if ops.is_empty() {
+            return Err(ToolError::ValidationError(
+                "nothing is staged; use fs.stage first".into(),
+            ));
+        }
+        let shown = ops.clone();
+        let decide = self.decide.clone();
+        let accepted = timeout(
+            DECISION_TIMEOUT,
+            tokio::task::spawn_blocking(move || decide(&StagingPreview::build(&shown))),
+        )
+        .await;
+        if !matches!(accepted, Ok(Ok(true))) {
+            return Ok(ToolResult {
+                success: false,
+                output: Some(json!({"applied": false})),
+                error: Some("the user declined the staged changes; nothing was changed".into()),
+            });
+        }
+
+        let applied = ops.clone();
+        let report = tokio::task::spawn_blocking(move || apply(&applied))
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?
+            .map_err(ToolError::ExecutionFailed)?;
+        self.area.record_applied(&ctx.session_key, &ops);
+        let mut deleted_paths = Vec::new();
+        let mut moved_paths = Vec::new();
+        for op in &ops {
+            match op {
+                StagedOp::Delete { path, .. } => deleted_paths.push(json!(path)),
+                StagedOp::Move { from, to } => moved_paths.push(json!({"from": from, "to": to})),
+            }
+        }
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({
+                "applied": true,
+                "deleted": report.deleted,
+                "moved": report.moved,
+                "deleted_paths": deleted_paths,
+                "moved_paths": moved_paths,
+                "leftovers": report.leftovers,
+                "not_overwritten": report.not_overwritten,
+            })),
+            error: None,
+        })
+    }
+}
+
+pub struct FsUndoApplyTool {
+    area: Arc<StagingArea>,
+}
+
+impl FsUndoApplyTool {
+    pub fn new(area: Arc<StagingArea>) -> Self {
+        Self { area }
+    }
+}
+
+#[async_trait]
+impl Tool for FsUndoApplyTool {
+    fn name(&self) -> &'static str {
+        "fs.undo_apply"
+    }
+    fn description(&self) -> &'static str {
+        "Put back the moves and renames of the last fs.apply_staged in this session; deleted files cannot be restored"
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {},
+            "additionalProperties": false
+        })
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Write
+    }
+    async fn execute(&self, ctx: ExecutionContext, _input: Value) -> Result<ToolResult, ToolError> {
+        let undo = self.area.take_undo(&ctx.session_key);
+        if undo.is_empty() {
+            return Err(ToolError::ValidationError(
+                "no applied moves to undo in this session".into(),
+            ));
+        }
+        let report = tokio::task::spawn_blocking(move || apply(&undo))
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?
+            .map_err(ToolError::ExecutionFailed)?;
+        Ok(ToolResult {
+            success: true,
+            output: Some(json!({"restored": report.moved})),
+            error: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview_counts_and_orders_by_size() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("small.txt"), "x").unwrap();
+        std::fs::create_dir(dir.path().join("logs")).unwrap();
+        std::fs::write(dir.path().join("logs/a.log"), vec![b'x'; 2048]).unwrap();
+        let ops = vec![
+            StagedOp::Delete {
+                path: dir.path().join("small.txt"),
+                secure: false,
+            },
+            StagedOp::Move {
+                from: dir.path().join("logs"),
+                to: dir.path().join("old/logs"),
+            },
+        ];
+        let preview = StagingPreview::build(&ops);
+        assert_eq!((preview.deletes, preview.moves), (1, 1));
+        assert_eq!(preview.total_bytes, 2049);
+        assert_eq!(preview.largest[0].0, dir.path().join("logs"));
+        assert_eq!(
+            preview.render()[0],
+            "1 delete(s), 1 move(s), 2.0 KiB in total"
+        );
+    }
+
+    #[test]
+    fn apply_is_all_or_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name);
+        std::fs::write(path("junk.tmp"), "x").unwrap();
+        std::fs::write(path("photo.jpg"), "x").unwrap();
+        std::fs::write(path("notes.txt"), "x").unwrap();
+
+        // The last move cannot happen (its parent is a file): the delete and
+        // the first move are undone.
+        let failing = vec![
+            StagedOp::Delete {
+                path: path("junk.tmp"),
+                secure: true,
+            },
+            StagedOp::Move {
+                from: path("photo.jpg"),
+                to: path("Pictures/2026/photo.jpg"),
+            },
+            StagedOp::Move {
+                from: path("notes.txt"),
+                to: path("junk.tmp.d/notes.txt"),
+            },
+        ];
+        std::fs::write(path("junk.tmp.d"), "not a directory").unwrap();
+        let err = apply(&failing).unwrap_err();
+        assert!(err.contains("step 3"), "{err}");
+        assert!(err.contains("rolled back"), "{err}");
+        assert!(path("junk.tmp").exists() && path("photo.jpg").exists());
+        assert!(!path("Pictures").exists());
+
+        let report = apply(&failing[..2]).unwrap();
+        assert_eq!((report.deleted, report.moved), (1, 1));
+        assert!(report.leftovers.is_empty());
+        assert!(!path("junk.tmp").exists());
+        assert!(path("Pictures/2026/photo.jpg").exists());
+        let names: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        assert!(!names.iter().any(|n| n.contains("hypr-claw-deleting")));
+    }
+
+    #[test]
+    fn staging_rejects_the_whole_batch_on_a_bad_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a"), "x").unwrap();
+        std::fs::write(dir.path().join("b"), "x").unwrap();
+        let area = StagingArea::new();
+        let delete = |name: &str| StagedOp::Delete {
+            path: dir.path().join(name),
+            secure: false,
+        };
+        assert_eq!(area.stage("s", vec![delete("a")]), Ok(1));
+        assert!(area.stage("s", vec![delete("b"), delete("a")]).is_err());
+        assert!(area.stage("s", vec![delete("missing")]).is_err());
+        let onto_existing = StagedOp::Move {
+            from: dir.path().join("b"),
+            to: dir.path().join("a"),
+        };
+        assert!(area.stage("s", vec![onto_existing]).is_err());
+        assert_eq!(area.list("s"), vec![delete("a")]);
+        assert_eq!(area.take("s").len(), 1);
+        assert!(area.list("s").is_empty());
+    }
+}
@@ -0,0 +1,699 @@
+//! CSV querying for `csv.query` / `csv.write`.
+//!
+//! Filters use a small expression language:
+//! `amount >= 100 and (status == "paid" or note contains 'refund')`.
+//! Comparisons are numeric when both sides parse as numbers, otherwise textual.
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Deepest filter the parser accepts, counting `not`s, parentheses and
+/// chained `and`/`or`s, so evaluation cannot run out of stack.
+const MAX_FILTER_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn read(path: &Path, delimiter: u8) -> Result<Self, String> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .flexible(true)
+            .from_path(path)
+            .map_err(|e| e.to_string())?;
+        let headers = reader
+            .headers()
+            .map_err(|e| e.to_string())?
+            .iter()
+            .map(|h| h.trim().to_string())
+            .collect::<Vec<_>>();
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let record = record.map_err(|e| e.to_string())?;
+            if record.len() > headers.len() {
+                return Err(format!(
+                    "line {} has {} cells but the header has {}",
+                    record.position().map_or(0, |p| p.line()),
+                    record.len(),
+                    headers.len()
+                ));
+            }
+            let mut row: Vec<String> = record.iter().map(str::to_string).collect();
+            row.resize(headers.len(), String::new());
+            rows.push(row);
+        }
+        Ok(Self { headers, rows })
+    }
+
+    pub fn write(&self, path: &Path, delimiter: u8, append: bool) -> Result<(), String> {
+        let exists = path.exists()
+            && std::fs::metadata(path)
+                .map(|m| m.len() > 0)
+                .unwrap_or(false);
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)
+            .map_err(|e| e.to_string())?;
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .from_writer(file);
+        if !(append && exists) {
+            writer
+                .write_record(&self.headers)
+                .map_err(|e| e.to_string())?;
+        }
+        for row in &self.rows {
+            writer.write_record(row).map_err(|e| e.to_string())?;
+        }
+        writer.flush().map_err(|e| e.to_string())
+    }
+
+    fn column(&self, name: &str) -> Result<usize, String> {
+        self.headers
+            .iter()
+            .position(|h| h == name)
+            .or_else(|| {
+                self.headers
+                    .iter()
+                    .position(|h| h.eq_ignore_ascii_case(name))
+            })
+            .ok_or_else(|| {
+                format!(
+                    "unknown column '{name}' (columns: {})",
+                    self.headers.join(", ")
+                )
+            })
+    }
+
+    /// Builds a table from JSON rows: arrays (with explicit `columns`) or objects.
+    pub fn from_json(columns: Option<Vec<String>>, rows: &[Value]) -> Result<Self, String> {
+        let headers = match columns {
+            Some(columns) => columns,
+            None => {
+                let mut headers: Vec<String> = Vec::new();
+                for row in rows {
+                    let obj = row
+                        .as_object()
+                        .ok_or("rows must be objects when 'columns' is omitted")?;
+                    for key in obj.keys() {
+                        if !headers.contains(key) {
+                            headers.push(key.clone());
+                        }
+                    }
+                }
+                headers
+            }
+        };
+        let cell = |v: &Value| match v {
+            Value::String(s) => s.clone(),
+            Value::Null => String::new(),
+            other => other.to_string(),
+        };
+        let mut out = Vec::with_capacity(rows.len());
+        for row in rows {
+            let values = match row {
+                Value::Array(items) => {
+                    let mut values: Vec<String> = items.iter().map(cell).collect();
+                    values.resize(headers.len(), String::new());
+                    values
+                }
+                Value::Object(obj) => headers
+                    .iter()
+                    .map(|h| obj.get(h).map(cell).unwrap_or_default())
+                    .collect(),
+                _ => return Err("each row must be an array or object".to_string()),
+            };
+            out.push(values);
+        }
+        Ok(Self { headers, rows: out })
+    }
+
+    pub fn to_json_rows(&self) -> Vec<Value> {
+        self.rows
+            .iter()
+            .map(|row| {
+                let mut obj = Map::new();
+                for (h, v) in self.headers.iter().zip(row) {
+                    obj.insert(h.clone(), json!(v));
+                }
+                Value::Object(obj)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    pub filter: Option<String>,
+    pub select: Vec<String>,
+    pub group_by: Vec<String>,
+    pub aggregates: Vec<String>,
+    pub order_by: Option<String>,
+    pub descending: bool,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryStats {
+    pub scanned_rows: usize,
+    pub matched_rows: usize,
+}
+
+/// Runs a query and returns the result table plus row statistics.
+pub fn run_query(table: &Table, query: &Query) -> Result<(Table, QueryStats), String> {
+    let filter = query.filter.as_deref().map(parse_expr).transpose()?;
+    let mut matched = Vec::new();
+    for row in &table.rows {
+        let keep = match &filter {
+            Some(expr) => truthy(&expr.eval(table, row)?),
+            None => true,
+        };
+        if keep {
+            matched.push(row.clone());
+        }
+    }
+    let stats = QueryStats {
+        scanned_rows: table.rows.len(),
+        matched_rows: matched.len(),
+    };
+
+    let mut result = if !query.aggregates.is_empty() || !query.group_by.is_empty() {
+        aggregate(table, &matched, &query.group_by, &query.aggregates)?
+    } else if !query.select.is_empty() {
+        let indices = query
+            .select
+            .iter()
+            .map(|c| table.column(c))
+            .collect::<Result<Vec<_>, _>>()?;
+        Table {
+            headers: indices.iter().map(|&i| table.headers[i].clone()).collect(),
+            rows: matched
+                .iter()
+                .map(|row| indices.iter().map(|&i| row[i].clone()).collect())
+                .collect(),
+        }
+    } else {
+        Table {
+            headers: table.headers.clone(),
+            rows: matched,
+        }
+    };
+
+    if let Some(order_by) = &query.order_by {
+        let idx = result.column(order_by)?;
+        result.rows.sort_by(|a, b| {
+            let ord = compare_cells(&a[idx], &b[idx]);
+            if query.descending {
+                ord.reverse()
+            } else {
+                ord
+            }
+        });
+    }
+    if let Some(limit) = query.limit {
+        result.rows.truncate(limit);
+    }
+    Ok((result, stats))
+}
+
+fn aggregate(
+    table: &Table,
+    rows: &[Vec<String>],
+    group_by: &[String],
+    aggregates: &[String],
+) -> Result<Table, String> {
+    let group_idx = group_by
+        .iter()
+        .map(|c| table.column(c))
+        .collect::<Result<Vec<_>, _>>()?;
+    let specs = aggregates
+        .iter()
+        .map(|spec| parse_aggregate(table, spec))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut groups: BTreeMap<Vec<String>, Vec<&Vec<String>>> = BTreeMap::new();
+    for row in rows {
+        let key = group_idx.iter().map(|&i| row[i].clone()).collect();
+        groups.entry(key).or_default().push(row);
+    }
+    if groups.is_empty() && group_idx.is_empty() {
+        groups.insert(Vec::new(), Vec::new());
+    }
+
+    let mut headers: Vec<String> = group_idx
+        .iter()
+        .map(|&i| table.headers[i].clone())
+        .collect();
+    headers.extend(specs.iter().map(|s| s.label.clone()));
+    let mut out = Vec::new();
+    for (key, members) in groups {
+        let mut row = key;
+        for spec in &specs {
+            row.push(spec.apply(&members));
+        }
+        out.push(row);
+    }
+    Ok(Table { headers, rows: out })
+}
+
+struct AggregateSpec {
+    label: String,
+    func: String,
+    column: Option<usize>,
+}
+
+impl AggregateSpec {
+    fn apply(&self, rows: &[&Vec<String>]) -> String {
+        let Some(col) = self.column else {
+            return rows.len().to_string();
+        };
+        if self.func == "count" {
+            return rows
+                .iter()
+                .filter(|r| !r[col].trim().is_empty())
+                .count()
+                .to_string();
+        }
+        let numbers: Vec<f64> = rows
+            .iter()
+            .filter_map(|r| r[col].trim().parse::<f64>().ok())
+            .collect();
+        let value = match self.func.as_str() {
+            "sum" => Some(numbers.iter().sum()),
+            "avg" => {
+                (!numbers.is_empty()).then(|| numbers.iter().sum::<f64>() / numbers.len() as f64)
+            }
+            "min" => numbers.iter().copied().reduce(f64::min),
+            "max" => numbers.iter().copied().reduce(f64::max),
+            _ => None,
+        };
+        value.map(format_number).unwrap_or_default()
+    }
+}
+
+fn parse_aggregate(table: &Table, spec: &str) -> Result<AggregateSpec, String> {
+    let spec = spec.trim();
+    let (func, arg) = match spec.split_once('(') {
+        Some((func, rest)) => (
+            func.trim().to_lowercase(),
+            rest.strip_suffix(')')
+                .ok_or_else(|| format!("invalid aggregate '{spec}'"))?
+                .trim()
+                .to_string(),
+        ),
+        None => (spec.to_lowercase(), String::new()),
+    };
+    if !matches!(func.as_str(), "count" | "sum" | "avg" | "min" | "max") {
+        return Err(format!(
+            "unknown aggregate '{func}' (use count, sum, avg, min, max)"
+        ));
+    }
+    let column = if arg.is_empty() || arg == "*" {
+        if func != "count" {
+            return Err(format!("{func}() needs a column"));
+        }
+        None
+    } else {
+        Some(table.column(&arg)?)
+    };
+    Ok(AggregateSpec {
+        label: spec.to_string(),
+        func,
+        column,
+    })
+}
+
+fn format_number(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        format!("{:.4}", n)
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    }
+}
+
+fn compare_cells(a: &str, b: &str) -> std::cmp::Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(x), Ok(y)) => x.partial_cmp(&y).unwrap_or(std::cmp::Ordering::Equal),
+        _ => a.cmp(b),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(String),
+    Op(String),
+    LParen,
+    RParen,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+        } else if c == '"' || c == '\'' || c == '`' {
+            let end = chars[i + 1..]
+                .iter()
+                .position(|&ch| ch == c)
+                .ok_or_else(|| format!("unterminated quote at position {i}"))?;
+            let text: String = chars[i + 1..i + 1 + end].iter().collect();
+            tokens.push(if c == '`' {
+                Token::Ident(text)
+            } else {
+                Token::Str(text)
+            });
+            i += end + 2;
+        } else if "=!<>".contains(c) {
+            let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+            if matches!(two.as_str(), "==" | "!=" | "<=" | ">=" | "<>") {
+                tokens.push(Token::Op(two));
+                i += 2;
+            } else if c == '!' {
+                return Err("unexpected '!'".to_string());
+            } else {
+                tokens.push(Token::Op(c.to_string()));
+                i += 1;
+            }
+        } else if c.is_ascii_digit()
+            || (c == '-' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit()))
+        {
+            let start = i;
+            i += 1;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            tokens.push(Token::Num(chars[start..i].iter().collect()));
+        } else if c.is_alphanumeric() || c == '_' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+            {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else {
+            return Err(format!("unexpected character '{c}'"));
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug)]
+enum Expr {
+    Column(String),
+    Literal(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Box<Expr>, String, Box<Expr>),
+}
+
+fn parse_expr(input: &str) -> Result<Expr, String> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        depth: 0,
+    };
+    let expr = parser.or()?;
+    if parser.pos != parser.tokens.len() {
+        return Err(format!(
+            "unexpected token {:?} in filter",
+            parser.tokens[parser.pos]
+        ));
+    }
+    Ok(expr)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn descend(&mut self) -> Result<(), String> {
+        self.depth += 1;
+        if self.depth > MAX_FILTER_DEPTH {
+            return Err(format!(
+                "filter is nested more than {MAX_FILTER_DEPTH} levels deep"
+            ));
+        }
+        Ok(())
+    }
+
+    fn keyword(&mut self, word: &str) -> bool {
+        if let Some(Token::Ident(id)) = self.tokens.get(self.pos) {
+            if id.eq_ignore_ascii_case(word) {
+                self.pos += 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let depth = self.depth;
+        let mut left = self.and()?;
+        while self.keyword("or") {
+            self.descend()?;
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let depth = self.depth;
+        let mut left = self.not()?;
+        while self.keyword("and") {
+            self.descend()?;
+            left = Expr::And(Box::new(left), Box::new(self.not()?));
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.keyword("not") {
+            self.descend()?;
+            let inner = self.not()?;
+            self.depth -= 1;
+            return Ok(Expr::Not(Box::new(inner)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.primary()?;
+        let op = match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => op.clone(),
+            Some(Token::Ident(id))
+                if matches!(
+                    id.to_lowercase().as_str(),
+                    "contains" | "startswith" | "endswith"
+                ) =>
+            {
+                id.to_lowercase()
+            }
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        let right = self.primary()?;
+        Ok(Expr::Compare(Box::new(left), op, Box::new(right)))
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or("unexpected end of filter")?;
+        self.pos += 1;
+        match token {
+            Token::LParen => {
+                self.descend()?;
+                let inner = self.or()?;
+                if self.tokens.get(self.pos) != Some(&Token::RParen) {
+                    return Err("missing ')' in filter".to_string());
+                }
+                self.pos += 1;
+                self.depth -= 1;
+                Ok(inner)
+            }
+            Token::Ident(name) => Ok(Expr::Column(name)),
+            Token::Str(s) | Token::Num(s) => Ok(Expr::Literal(s)),
+            other => Err(format!("unexpected token {other:?} in filter")),
+        }
+    }
+}
+
+fn truthy(value: &str) -> bool {
+    !(value.is_empty() || value == "false" || value == "0")
+}
+
+fn bool_str(b: bool) -> String {
+    if b { "true" } else { "false" }.to_string()
+}
+
+impl Expr {
+    fn eval(&self, table: &Table, row: &[String]) -> Result<String, String> {
+        Ok(match self {
+            Expr::Column(name) => row[table.column(name)?].clone(),
+            Expr::Literal(value) => value.clone(),
+            Expr::Not(inner) => bool_str(!truthy(&inner.eval(table, row)?)),
+            Expr::And(a, b) => {
+                bool_str(truthy(&a.eval(table, row)?) && truthy(&b.eval(table, row)?))
+            }
+            Expr::Or(a, b) => {
+                bool_str(truthy(&a.eval(table, row)?) || truthy(&b.eval(table, row)?))
+            }
+            Expr::Compare(a, op, b) => {
+                let left = a.eval(table, row)?;
+                let right = b.eval(table, row)?;
+                let ord = compare_cells(&left, &right);
+                let (l, r) = (left.to_lowercase(), right.to_lowercase());
+                bool_str(match op.as_str() {
+                    "=" | "==" => ord.is_eq(),
+                    "!=" | "<>" => !ord.is_eq(),
+                    "<" => ord.is_lt(),
+                    "<=" => ord.is_le(),
+                    ">" => ord.is_gt(),
+                    ">=" => ord.is_ge(),
+                    "contains" => l.contains(&r),
+                    "startswith" => l.starts_with(&r),
+                    "endswith" => l.ends_with(&r),
+                    other => return Err(format!("unknown operator '{other}'")),
+                })
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Table {
+        Table {
+            headers: vec!["name".into(), "status".into(), "amount".into()],
+            rows: vec![
+                vec!["a".into(), "paid".into(), "120".into()],
+                vec!["b".into(), "open".into(), "80".into()],
+                vec!["c".into(), "paid".into(), "30.5".into()],
+                vec!["d".into(), "refund".into(), "9".into()],
+            ],
+        }
+    }
+
+    #[test]
+    fn filters_with_numeric_and_text_comparisons() {
+        let query = Query {
+            filter: Some("amount > 50 and (status == 'paid' or status = \"open\")".into()),
+            select: vec!["name".into()],
+            ..Query::default()
+        };
+        let (result, stats) = run_query(&sample(), &query).unwrap();
+        assert_eq!(stats.matched_rows, 2);
+        assert_eq!(
+            result.rows,
+            vec![vec!["a".to_string()], vec!["b".to_string()]]
+        );
+
+        let query = Query {
+            filter: Some("not status contains 'PAI'".into()),
+            ..Query::default()
+        };
+        assert_eq!(run_query(&sample(), &query).unwrap().1.matched_rows, 2);
+    }
+
+    #[test]
+    fn groups_and_aggregates() {
+        let query = Query {
+            group_by: vec!["status".into()],
+            aggregates: vec!["count".into(), "sum(amount)".into()],
+            order_by: Some("sum(amount)".into()),
+            descending: true,
+            ..Query::default()
+        };
+        let (result, _) = run_query(&sample(), &query).unwrap();
+        assert_eq!(result.headers, vec!["status", "count", "sum(amount)"]);
+        assert_eq!(result.rows[0], vec!["paid", "2", "150.5"]);
+        assert_eq!(result.rows.len(), 3);
+    }
+
+    #[test]
+    fn rejects_unknown_columns_and_bad_syntax() {
+        let bad_column = Query {
+            filter: Some("total > 1".into()),
+            ..Query::default()
+        };
+        assert!(run_query(&sample(), &bad_column).is_err());
+        assert!(parse_expr("amount > (1").is_err());
+        assert!(parse_expr("name == 'x").is_err());
+    }
+
+    #[test]
+    fn rejects_filters_nested_too_deep() {
+        assert!(parse_expr(&format!("{}status == 'paid'", "not ".repeat(100_000))).is_err());
+        let parens = format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert!(parse_expr(&parens).is_err());
+        let chain = vec!["amount > 1"; 100_000].join(" or ");
+        assert!(parse_expr(&chain).is_err());
+        // Realistic filters still parse.
+        let nested = format!("{}status == 'paid'{}", "(".repeat(20), ")".repeat(20));
+        assert!(parse_expr(&format!("not not {nested} and amount > 1")).is_ok());
+    }
+
+    #[test]
+    fn reports_rows_wider_than_the_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ragged.csv");
+        std::fs::write(&path, "name,amount\na,1\nb,2,extra\nc\n").unwrap();
+        let err = Table::read(&path, b',').unwrap_err();
+        assert!(err.contains("line 3"), "{err}");
+        std::fs::write(&path, "name,amount\na,1\nc\n").unwrap();
+        assert_eq!(Table::read(&path, b',').unwrap().rows[1], vec!["c", ""]);
+    }
+
+    #[test]
+    fn round_trips_through_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.csv");
+        sample().write(&path, b',', false).unwrap();
+        let existing = Table::read(&path, b',').unwrap().headers;
+        Table::from_json(
+            Some(existing),
+            &[json!({"amount": 1, "name": "e", "status": "open"})],
+        )
+        .unwrap()
+        .write(&path, b',', true)
+        .unwrap();
+        let table = Table::read(&path, b',').unwrap();
+        assert_eq!(table.headers, vec!["name", "status", "amount"]);
+        assert_eq!(table.rows.len(), 5);
+        assert_eq!(table.rows[4], vec!["e", "open", "1"]);
+    }
+}
@@ -4,6 +4,9 @@ use crate::traits::PermissionTier;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
+/// Path fields most file tools take; the default for [`Tool::path_inputs`].
+pub const DEFAULT_PATH_INPUTS: &[&str] = &["path", "from", "to", "dir", "output"];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolResult {
     pub success: bool,
@@ -20,6 +23,25 @@ pub trait Tool: Send + Sync {
         PermissionTier::Write
     }
 
+    /// Input fields naming files or directories the call reads or writes,
+    /// so confined sessions can check every one. A field may hold a string or
+    /// a list of strings; `list.field` names a field of each object in a list.
+    fn path_inputs(&self) -> &'static [&'static str] {
+        DEFAULT_PATH_INPUTS
+    }
+
+    /// Rewrites input before the permission check, so approval prompts and
+    /// audit entries show resolved values (e.g. a contact's address).
+    fn resolve_input(&self, input: serde_json::Value) -> Result<serde_json::Value, ToolError> {
+        Ok(input)
+    }
+
+    /// Overrides the dispatcher's per-call timeout, for tools that run other
+    /// tools.
+    fn timeout_ms(&self) -> Option<u64> {
+        None
+    }
+
     async fn execute(
         &self,
         ctx: ExecutionContext,
@@ -49,13 +49,13 @@ impl Tool for FileReadTool {
 
     async fn execute(
         &self,
-        _ctx: ExecutionContext,
+        ctx: ExecutionContext,
         input: serde_json::Value,
     ) -> Result<ToolResult, ToolError> {
         let input: FileReadInput =
             serde_json::from_value(input).map_err(|e| ToolError::ValidationError(e.to_string()))?;
 
-        let path_guard = PathGuard::new(&self.sandbox_root)?;
+        let path_guard = ctx.capabilities.path_guard(&self.sandbox_root)?;
         let validated_path = path_guard.validate(&input.path)?;
 
         let content = fs::read_to_string(&validated_path)
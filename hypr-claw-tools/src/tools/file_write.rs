@@ -54,13 +54,13 @@ impl Tool for FileWriteTool {
 
     async fn execute(
         &self,
-        _ctx: ExecutionContext,
+        ctx: ExecutionContext,
         input: serde_json::Value,
     ) -> Result<ToolResult, ToolError> {
         let input: FileWriteInput =
             serde_json::from_value(input).map_err(|e| ToolError::ValidationError(e.to_string()))?;
 
-        let path_guard = PathGuard::new(&self.sandbox_root)?;
+        let path_guard = ctx.capabilities.path_guard(&self.sandbox_root)?;
         let validated_path = path_guard.validate_new(&input.path)?;
 
         if validated_path.exists() && !input.overwrite {
@@ -1,7 +1,9 @@
+use crate::sandbox::command_analysis::CommandAnalysis;
 use async_trait::async_trait;
 use serde_json::Value;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Ordered from least to most sensitive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum PermissionTier {
     Read,
     Write,
@@ -25,6 +27,11 @@ pub struct PermissionRequest {
     pub input: Value,
     pub permission_tier: PermissionTier,
     pub timestamp: String,
+    /// Hazards found in the command line of `hypr.exec`/`proc.spawn` calls.
+    pub command_risk: Option<CommandAnalysis>,
+    /// Files and directories the call names, from the tool's
+    /// [`path_inputs`](crate::tools::Tool::path_inputs).
+    pub paths: Vec<String>,
 }
 
 /// Permission engine trait
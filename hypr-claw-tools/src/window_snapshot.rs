@@ -0,0 +1,377 @@
+//! Window snapshots for verifying an action against one window.
+//!
+//! `desktop.window_snapshot` captures a single window's region, fingerprints
+//! the image and OCRs it into lines; `desktop.window_diff` captures the same
+//! window again and reports what changed. An unchanged image skips OCR
+//! entirely, and a window is far less to read than the whole screen, so this
+//! is the cheap check to run after typing, clicking or launching.
+
+use crate::error::ToolError;
+use crate::execution_context::ExecutionContext;
+use crate::os_capabilities::desktop::{self, OcrMatch};
+use crate::tools::{Tool, ToolResult};
+use crate::traits::PermissionTier;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// Snapshots kept per process; older ones are dropped.
+const MAX_SNAPSHOTS: usize = 20;
+/// Changed lines listed in a diff.
+const MAX_LISTED_LINES: usize = 40;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowSnapshot {
+    pub address: String,
+    pub class: String,
+    pub title: String,
+    /// grim geometry, `x,y wxh`.
+    pub region: String,
+    pub image_sha256: String,
+    /// OCR lines top to bottom; `None` when OCR is unavailable.
+    pub lines: Option<Vec<String>>,
+}
+
+impl WindowSnapshot {
+    fn to_json(&self, id: &str) -> Value {
+        json!({
+            "snapshot_id": id,
+            "address": self.address,
+            "class": self.class,
+            "title": self.title,
+            "region": self.region,
+            "image_sha256": self.image_sha256,
+            "ocr": self.lines.is_some(),
+            "text": self.lines.as_ref().map(|lines| lines.join("\n")),
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct WindowSnapshots {
+    inner: Mutex<(u64, VecDeque<(String, WindowSnapshot)>)>,
+}
+
+impl WindowSnapshots {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, snapshot: WindowSnapshot) -> String {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.0 += 1;
+        let id = format!("snap-{}", inner.0);
+        if inner.1.len() == MAX_SNAPSHOTS {
+            inner.1.pop_front();
+        }
+        inner.1.push_back((id.clone(), snapshot));
+        id
+    }
+
+    pub fn get(&self, id: &str) -> Option<WindowSnapshot> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner
+            .1
+            .iter()
+            .find(|(have, _)| have == id)
+            .map(|(_, snapshot)| snapshot.clone())
+    }
+}
+
+/// Group OCR words into lines: words whose vertical centres fall within half
+/// a word height of each other share a line, read left to right.
+pub fn ocr_lines(words: &[OcrMatch]) -> Vec<String> {
+    let mut sorted: Vec<&OcrMatch> = words.iter().collect();
+    sorted.sort_by_key(|word| (word.center_y, word.x));
+    let mut lines: Vec<(i32, i32, Vec<&OcrMatch>)> = Vec::new();
+    for word in sorted {
+        match lines.last_mut() {
+            Some((center, height, line)) if (word.center_y - *center).abs() <= *height / 2 => {
+                line.push(word);
+                *height = (*height).max(word.height);
+            }
+            _ => lines.push((word.center_y, word.height.max(1), vec![word])),
+        }
+    }
+    lines
+        .into_iter()
+        .map(|(_, _, mut line)| {
+            line.sort_by_key(|word| word.x);
+            line.iter()
+                .map(|word| word.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+/// Lines in `after` but not `before`, and the reverse, counting repeats.
+pub fn diff_lines(before: &[String], after: &[String]) -> (Vec<String>, Vec<String>) {
+    let count = |lines: &[String]| {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for line in lines {
+            *counts.entry(line.clone()).or_default() += 1;
+        }
+        counts
+    };
+    let mut unmatched_before = count(before);
+    let mut added = Vec::new();
+    for line in after {
+        match unmatched_before.get_mut(line) {
+            Some(n) if *n > 0 => *n -= 1,
+            _ => added.push(line.clone()),
+        }
+    }
+    let mut unmatched_after = count(after);
+    let mut removed = Vec::new();
+    for line in before {
+        match unmatched_after.get_mut(line) {
+            Some(n) if *n > 0 => *n -= 1,
+            _ => removed.push(line.clone()),
+        }
+    }
+    (added, removed)
+}
+
+/// Capture `window` and fingerprint it; OCR runs unless `skip_ocr_if` holds
+/// the same image hash.
+async fn capture(
+    window: &Value,
+    lang: Option<&str>,
+    skip_ocr_if: Option<&WindowSnapshot>,
+) -> Result<WindowSnapshot, ToolError> {
+    let field = |name: &str| {
+        window
+            .get(name)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    let region = desktop::window_region(window)
+        .ok_or_else(|| ToolError::ExecutionFailed("window has no visible region".into()))?;
+    let path = desktop::capture_region(&region)
+        .await
+        .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+    let result = async {
+        let bytes = tokio::fs::read(&path)
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        let image_sha256 = format!("{:x}", Sha256::digest(&bytes));
+        let lines = match skip_ocr_if {
+            Some(before) if before.image_sha256 == image_sha256 => before.lines.clone(),
+            _ => desktop::ocr_screen(Some(&path), lang)
+                .await
+                .ok()
+                .map(|(_, words)| ocr_lines(&words)),
+        };
+        Ok(WindowSnapshot {
+            address: field("address"),
+            class: field("class"),
+            title: field("title"),
+            region: region.clone(),
+            image_sha256,
+            lines,
+        })
+    }
+    .await;
+    let _ = tokio::fs::remove_file(&path).await;
+    result
+}
+
+pub struct DesktopWindowSnapshotTool {
+    snapshots: Arc<WindowSnapshots>,
+}
+
+impl DesktopWindowSnapshotTool {
+    pub fn new(snapshots: Arc<WindowSnapshots>) -> Self {
+        Self { snapshots }
+    }
+}
+
+#[async_trait]
+impl Tool for DesktopWindowSnapshotTool {
+    fn name(&self) -> &'static str {
+        "desktop.window_snapshot"
+    }
+    fn description(&self) -> &'static str {
+        "Capture one window's image fingerprint and OCR text before an action; pass the snapshot_id to desktop.window_diff afterwards to see what changed"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "window": {
+                    "type": "string",
+                    "description": "Window address (0x…), class or title; default the active window"
+                },
+                "lang": {"type": "string", "description": "Tesseract language, e.g. deu or jpn+eng; defaults to ocr.language"}
+            },
+            "additionalProperties": false
+        })
+    }
+    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let window = desktop::find_window(input["window"].as_str())
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        let snapshot = capture(&window, input["lang"].as_str(), None).await?;
+        let id = self.snapshots.insert(snapshot.clone());
+        Ok(ToolResult {
+            success: true,
+            output: Some(snapshot.to_json(&id)),
+            error: None,
+        })
+    }
+}
+
+pub struct DesktopWindowDiffTool {
+    snapshots: Arc<WindowSnapshots>,
+}
+
+impl DesktopWindowDiffTool {
+    pub fn new(snapshots: Arc<WindowSnapshots>) -> Self {
+        Self { snapshots }
+    }
+}
+
+#[async_trait]
+impl Tool for DesktopWindowDiffTool {
+    fn name(&self) -> &'static str {
+        "desktop.window_diff"
+    }
+    fn description(&self) -> &'static str {
+        "Capture the window of an earlier desktop.window_snapshot again and report what changed: image, title, geometry and added/removed OCR lines, optionally checking that expect_text is now visible"
+    }
+    fn permission_tier(&self) -> PermissionTier {
+        PermissionTier::Read
+    }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "snapshot_id": {"type": "string"},
+                "expect_text": {"type": "string", "description": "Text that should be visible after the action"},
+                "lang": {"type": "string", "description": "Tesseract language, e.g. deu or jpn+eng; defaults to ocr.language"}
+            },
+            "required": ["snapshot_id"],
+            "additionalProperties": false
+        })
+    }
+    async fn execute(&self, _ctx: ExecutionContext, input: Value) -> Result<ToolResult, ToolError> {
+        let id = input["snapshot_id"]
+            .as_str()
+            .ok_or_else(|| ToolError::ValidationError("'snapshot_id' is required".into()))?;
+        let before = self.snapshots.get(id).ok_or_else(|| {
+            ToolError::ValidationError(format!(
+                "unknown snapshot '{id}'; take one with desktop.window_snapshot"
+            ))
+        })?;
+        let window = match desktop::find_window(Some(&before.address)).await {
+            Ok(window) => window,
+            Err(_) => {
+                return Ok(ToolResult {
+                    success: true,
+                    output: Some(json!({"changed": true, "window_closed": true})),
+                    error: None,
+                });
+            }
+        };
+        let after = capture(&window, input["lang"].as_str(), Some(&before)).await?;
+        let after_id = self.snapshots.insert(after.clone());
+
+        let image_changed = after.image_sha256 != before.image_sha256;
+        let mut output = json!({
+            "changed": image_changed || after.title != before.title || after.region != before.region,
+            "image_changed": image_changed,
+            "title_changed": after.title != before.title,
+            "geometry_changed": after.region != before.region,
+            "after_snapshot_id": after_id,
+        });
+        if after.title != before.title {
+            output["title_before"] = json!(before.title);
+            output["title_after"] = json!(after.title);
+        }
+        if let (Some(old), Some(new)) = (&before.lines, &after.lines) {
+            let (added, removed) = diff_lines(old, new);
+            output["added_lines"] = json!(added.iter().take(MAX_LISTED_LINES).collect::<Vec<_>>());
+            output["removed_lines"] =
+                json!(removed.iter().take(MAX_LISTED_LINES).collect::<Vec<_>>());
+        }
+        if let Some(expect) = input["expect_text"].as_str() {
+            let visible = after.lines.as_ref().map(|lines| {
+                let text = lines.join(" ").to_lowercase();
+                text.contains(&expect.to_lowercase())
+            });
+            output["expect_text_visible"] = json!(visible);
+        }
+        Ok(ToolResult {
+            success: true,
+            output: Some(output),
+            error: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, x: i32, y: i32) -> OcrMatch {
+        OcrMatch {
+            text: text.to_string(),
+            confidence: 90.0,
+            x,
+            y,
+            width: 40,
+            height: 20,
+            center_x: x + 20,
+            center_y: y + 10,
+        }
+    }
+
+    #[test]
+    fn words_group_into_lines_and_lines_diff() {
+        let words = vec![
+            word("world", 60, 12),
+            word("hello", 10, 10),
+            word("Saved", 10, 50),
+        ];
+        let before = ocr_lines(&words);
+        assert_eq!(before, vec!["hello world", "Saved"]);
+
+        let after = vec![
+            "hello world".to_string(),
+            "Unsaved changes".to_string(),
+            "hello world".to_string(),
+        ];
+        let (added, removed) = diff_lines(&before, &after);
+        assert_eq!(added, vec!["Unsaved changes", "hello world"]);
+        assert_eq!(removed, vec!["Saved"]);
+    }
+
+    #[test]
+    fn snapshots_are_capped() {
+        let snapshots = WindowSnapshots::new();
+        let snapshot = WindowSnapshot {
+            address: "0x1".into(),
+            class: "kitty".into(),
+            title: "~".into(),
+            region: "0,0 10x10".into(),
+            image_sha256: "ab".into(),
+            lines: None,
+        };
+        let first = snapshots.insert(snapshot.clone());
+        for _ in 0..MAX_SNAPSHOTS {
+            snapshots.insert(snapshot.clone());
+        }
+        assert!(snapshots.get(&first).is_none());
+        assert_eq!(
+            snapshots.get(&format!("snap-{}", MAX_SNAPSHOTS + 1)),
+            Some(snapshot)
+        );
+    }
+}
@@ -0,0 +1,51 @@
+//! Tool contracts: every fixture under `contracts/` run against a mocked OS
+//! layer. `cargo test --features tool-contracts`.
+
+use hypr_claw_tools::contracts;
+use hypr_claw_tools::os_tools::*;
+use hypr_claw_tools::ToolRegistryImpl;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Tools with a contract. Adding one here without a fixture fails the test.
+fn contract_registry() -> ToolRegistryImpl {
+    let mut registry = ToolRegistryImpl::new();
+    registry
+        .register(Arc::new(FsReadTool))
+        .register(Arc::new(FsWriteTool))
+        .register(Arc::new(FsListTool))
+        .register(Arc::new(FsMoveTool))
+        .register(Arc::new(FsDeleteTool))
+        .register(Arc::new(HyprWorkspaceSwitchTool))
+        .register(Arc::new(DesktopActiveWindowTool))
+        .register(Arc::new(DesktopListWindowsTool))
+        .register(Arc::new(CalcEvaluateTool))
+        .register(Arc::new(TimeFormatTool));
+    registry
+}
+
+// One test on purpose: cases swap the process's PATH, so they run in turn.
+#[tokio::test]
+async fn tools_keep_their_contracts() {
+    let registry = contract_registry();
+    let fixtures = contracts::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("contracts"))
+        .expect("fixtures load");
+
+    let mut failures = Vec::new();
+    for (path, fixture) in &fixtures {
+        match registry.get(&fixture.tool) {
+            Some(tool) => failures.extend(contracts::run(fixture, tool.as_ref()).await),
+            None => failures.push(format!(
+                "{}: {} is not in the contract registry",
+                path.display(),
+                fixture.tool
+            )),
+        }
+    }
+    for tool in registry.list() {
+        if !fixtures.iter().any(|(_, fixture)| fixture.tool == tool) {
+            failures.push(format!("{tool} has no fixture"));
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
@@ -383,4 +383,301 @@ mod integration_tests {
 
         // Should not panic
     }
+
+    #[tokio::test]
+    async fn test_plan_approval_gates_later_calls() {
+        use hypr_claw_tools::approvals::{ApprovalBook, PlanApprovalGate, PlanApprovalTool};
+
+        let book = Arc::new(ApprovalBook::new());
+        let mut registry = ToolRegistryImpl::new();
+        registry.register(Arc::new(EchoTool));
+        registry.register(Arc::new(PlanApprovalTool::new(
+            book.clone(),
+            Arc::new(|actions| actions.iter().map(|a| a.target.contains("keep")).collect()),
+        )));
+        let dispatcher = ToolDispatcherImpl::new(
+            Arc::new(registry),
+            Arc::new(PlanApprovalGate::new(
+                Arc::new(MockPermissionEngine) as Arc<dyn PermissionEngine>,
+                book,
+            )) as Arc<dyn PermissionEngine>,
+            Arc::new(MockAuditLogger) as Arc<dyn AuditLogger>,
+            5000,
+        );
+
+        let plan = dispatcher
+            .dispatch(
+                "session".into(),
+                "plan.request_approval".into(),
+                json!({"actions": [
+                    {"tool": "echo", "input": {"message": "keep"}},
+                    {"tool": "echo", "input": {"message": "drop"}}
+                ]}),
+            )
+            .await
+            .unwrap();
+        let output = plan.output.unwrap();
+        assert_eq!(output["approved"].as_array().unwrap().len(), 1);
+        assert_eq!(output["skipped"][0]["item"], 2);
+
+        let accepted = dispatcher
+            .dispatch("session".into(), "echo".into(), json!({"message": "keep"}))
+            .await
+            .unwrap();
+        assert!(accepted.success);
+
+        let skipped = dispatcher
+            .dispatch("session".into(), "echo".into(), json!({"message": "drop"}))
+            .await;
+        assert!(matches!(skipped, Err(ToolError::PermissionDenied(_))));
+
+        // Calls outside the plan fall through to the inner engine.
+        let unplanned = dispatcher
+            .dispatch("session".into(), "echo".into(), json!({"message": "other"}))
+            .await
+            .unwrap();
+        assert!(unplanned.success);
+    }
+
+    #[tokio::test]
+    async fn test_approval_threshold_raises_tier() {
+        use hypr_claw_tools::approvals::{ApprovalBook, PlanApprovalGate};
+
+        struct PromptsCritical;
+
+        #[async_trait]
+        impl PermissionEngine for PromptsCritical {
+            async fn check(&self, request: PermissionRequest) -> PermissionDecision {
+                if request.permission_tier == PermissionTier::SystemCritical {
+                    PermissionDecision::RequireApproval("critical".into())
+                } else {
+                    PermissionDecision::Allow
+                }
+            }
+        }
+
+        let mut registry = ToolRegistryImpl::new();
+        registry.register(Arc::new(EchoTool));
+        let gate = Arc::new(PlanApprovalGate::new(
+            Arc::new(PromptsCritical) as Arc<dyn PermissionEngine>,
+            Arc::new(ApprovalBook::new()),
+        ));
+        let dispatcher = ToolDispatcherImpl::new(
+            Arc::new(registry),
+            gate.clone() as Arc<dyn PermissionEngine>,
+            Arc::new(MockAuditLogger) as Arc<dyn AuditLogger>,
+            5000,
+        );
+        let echo =
+            || dispatcher.dispatch("session".into(), "echo".into(), json!({"message": "hi"}));
+
+        assert!(echo().await.unwrap().success);
+        gate.set_prompt_from(PermissionTier::Write);
+        let gated = echo().await.unwrap();
+        assert!(!gated.success);
+        assert_eq!(gated.output.unwrap()["approval_required"], true);
+    }
+
+    #[tokio::test]
+    async fn test_skill_runs_steps_through_dispatcher() {
+        use hypr_claw_tools::skills::{DispatcherSlot, Skill, SkillTool};
+
+        let skill = Skill::parse(
+            "greet",
+            "description: Greet\n\
+             params:\n  who: {}\n  loud: { type: boolean, default: false }\n\
+             steps:\n\
+             - id: hello\n  tool: echo\n  input: { message: \"hi {{who}}\" }\n\
+             - tool: echo\n  input: { message: \"HI\" }\n  when: { param: loud, equals: true }\n\
+             - tool: missing.tool\n  input: {}\n",
+        )
+        .unwrap();
+        let slot = DispatcherSlot::default();
+        let mut registry = ToolRegistryImpl::new();
+        registry.register(Arc::new(EchoTool));
+        // Unknown step tools are refused at load time.
+        assert!(SkillTool::new(skill.clone(), &registry, slot.clone()).is_err());
+
+        let mut skill = skill;
+        skill.steps.pop();
+        let tool = SkillTool::new(skill, &registry, slot.clone()).unwrap();
+        registry.register(Arc::new(tool));
+        let dispatcher = Arc::new(ToolDispatcherImpl::new(
+            Arc::new(registry),
+            Arc::new(MockPermissionEngine) as Arc<dyn PermissionEngine>,
+            Arc::new(MockAuditLogger) as Arc<dyn AuditLogger>,
+            5000,
+        ));
+        slot.set(Arc::downgrade(&dispatcher)).unwrap();
+
+        let result = dispatcher
+            .dispatch(
+                "session".into(),
+                "skill.greet".into(),
+                json!({"who": "ana"}),
+            )
+            .await
+            .unwrap();
+        assert!(result.success);
+        let steps = result.output.unwrap()["steps"].clone();
+        assert_eq!(steps[0]["status"], "ok");
+        assert_eq!(steps[0]["output"]["message"], "hi ana");
+        assert_eq!(steps[1]["status"], "skipped");
+
+        let missing = dispatcher
+            .dispatch("session".into(), "skill.greet".into(), json!({}))
+            .await;
+        assert!(matches!(missing, Err(ToolError::ValidationError(_))));
+    }
+
+    /// Asks the "user" about every call raised to SystemCritical, approving
+    /// only the skill itself.
+    struct PromptRecorder(std::sync::Mutex<Vec<String>>);
+
+    #[async_trait]
+    impl PermissionEngine for PromptRecorder {
+        async fn check(&self, request: PermissionRequest) -> PermissionDecision {
+            if request.permission_tier < PermissionTier::SystemCritical {
+                return PermissionDecision::Allow;
+            }
+            self.0.lock().unwrap().push(request.tool_name.clone());
+            if request.tool_name.starts_with("skill.") {
+                PermissionDecision::Allow
+            } else {
+                PermissionDecision::RequireApproval(request.tool_name)
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_skill_permissions_skip_prompts_only_for_granted_calls() {
+        use hypr_claw_tools::approvals::{ApprovalBook, PlanApprovalGate};
+        use hypr_claw_tools::skills::{DispatcherSlot, Skill, SkillTool};
+
+        let skill = Skill::parse(
+            "greet",
+            "description: Greet\n\
+             permissions:\n- { tool: echo, target: '{\"message\":\"hi\"}' }\n\
+             steps:\n\
+             - tool: echo\n  input: { message: hi }\n\
+             - tool: echo\n  input: { message: bye }\n  continue_on_error: true\n",
+        )
+        .unwrap();
+        let slot = DispatcherSlot::default();
+        let book = Arc::new(ApprovalBook::new());
+        let mut registry = ToolRegistryImpl::new();
+        registry.register(Arc::new(EchoTool));
+        let tool = SkillTool::new(skill, &registry, slot.clone())
+            .unwrap()
+            .with_approvals(book.clone());
+        registry.register(Arc::new(tool));
+        let recorder = Arc::new(PromptRecorder(Default::default()));
+        let gate = PlanApprovalGate::new(recorder.clone(), book.clone());
+        gate.set_prompt_from(PermissionTier::Write);
+        let dispatcher = Arc::new(ToolDispatcherImpl::new(
+            Arc::new(registry),
+            Arc::new(gate) as Arc<dyn PermissionEngine>,
+            Arc::new(MockAuditLogger) as Arc<dyn AuditLogger>,
+            5000,
+        ));
+        slot.set(Arc::downgrade(&dispatcher)).unwrap();
+
+        let result = dispatcher
+            .dispatch("session".into(), "skill.greet".into(), json!({}))
+            .await
+            .unwrap();
+        let steps = result.output.unwrap()["steps"].clone();
+        assert_eq!(steps[0]["status"], "ok");
+        // The second call deviates from the manifest and is asked about.
+        assert_eq!(steps[1]["status"], "failed");
+        assert_eq!(*recorder.0.lock().unwrap(), vec!["skill.greet", "echo"]);
+        // Grants end with the run.
+        assert_eq!(
+            book.take("session", "echo", &json!({"message": "hi"})),
+            None
+        );
+    }
+
+    struct EnvEchoTool;
+
+    #[async_trait]
+    impl Tool for EnvEchoTool {
+        fn name(&self) -> &'static str {
+            "env.echo"
+        }
+        fn description(&self) -> &'static str {
+            "Echo the call's environment overlay"
+        }
+        fn schema(&self) -> serde_json::Value {
+            json!({"type": "object"})
+        }
+        fn permission_tier(&self) -> PermissionTier {
+            PermissionTier::Read
+        }
+        async fn execute(
+            &self,
+            ctx: ExecutionContext,
+            _input: serde_json::Value,
+        ) -> Result<ToolResult, ToolError> {
+            Ok(ToolResult {
+                success: true,
+                output: Some(json!(ctx.env)),
+                error: None,
+            })
+        }
+    }
+
+    struct RecordingAuditLogger(tokio::sync::mpsc::UnboundedSender<serde_json::Value>);
+
+    #[async_trait]
+    impl AuditLogger for RecordingAuditLogger {
+        async fn log(&self, entry: serde_json::Value) {
+            let _ = self.0.send(entry);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_env_overlay_reaches_calls_of_its_session_and_the_audit() {
+        let mut registry = ToolRegistryImpl::new();
+        registry.register(Arc::new(EnvEchoTool));
+        let (sender, mut entries) = tokio::sync::mpsc::unbounded_channel();
+        let dispatcher = ToolDispatcherImpl::new(
+            Arc::new(registry),
+            Arc::new(MockPermissionEngine) as Arc<dyn PermissionEngine>,
+            Arc::new(RecordingAuditLogger(sender)) as Arc<dyn AuditLogger>,
+            5000,
+        );
+        dispatcher.set_env_overlay(
+            "thread-a",
+            [
+                ("RUST_LOG".to_string(), "debug".to_string()),
+                ("API_TOKEN".to_string(), "hunter2".to_string()),
+            ]
+            .into(),
+        );
+
+        let result = dispatcher
+            .dispatch("thread-a".into(), "env.echo".into(), json!({}))
+            .await
+            .unwrap();
+        assert_eq!(
+            result.output.unwrap(),
+            json!({"API_TOKEN": "hunter2", "RUST_LOG": "debug"})
+        );
+        let entry = entries.recv().await.unwrap();
+        assert_eq!(
+            entry["env"],
+            json!({"API_TOKEN": "<redacted 7 chars>", "RUST_LOG": "debug"})
+        );
+
+        let result = dispatcher
+            .dispatch("thread-b".into(), "env.echo".into(), json!({}))
+            .await
+            .unwrap();
+        assert_eq!(result.output.unwrap(), json!({}));
+        assert!(entries.recv().await.unwrap().get("env").is_none());
+
+        dispatcher.set_env_overlay("thread-a", Default::default());
+        assert!(dispatcher.env_overlay("thread-a").is_empty());
+    }
 }
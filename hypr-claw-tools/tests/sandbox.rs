@@ -273,4 +273,45 @@ mod sandbox_tests {
             assert!(!err.to_string().is_empty());
         }
     }
+
+    #[test]
+    fn test_env_policy_allow_deny_patterns() {
+        let policy = EnvPolicy {
+            allow: vec!["XDG_*".into(), "HOME".into()],
+            deny: vec!["XDG_RUNTIME_DIR".into()],
+            redact_secrets: true,
+        };
+        assert!(policy.is_exposed("HOME"));
+        assert!(policy.is_exposed("XDG_CONFIG_HOME"));
+        assert!(!policy.is_exposed("XDG_RUNTIME_DIR"));
+        assert!(!policy.is_exposed("PATH"));
+    }
+
+    #[test]
+    fn test_env_policy_redacts_secret_looking_values() {
+        let policy = EnvPolicy::default();
+        assert!(policy.present("OPENAI_API_KEY", "abc").1);
+        assert!(policy.present("GITHUB_TOKEN", "ghp_x").1);
+        assert!(policy.present("DATABASE_URL", "postgres://u:pw@db/app").1);
+        assert!(policy.present("RANDOM", "sk-live-123").1);
+        assert!(!policy.present("HOME", "/home/user").1);
+        assert!(!policy.present("KEYBOARD_LAYOUT", "us").1);
+        assert!(!policy.present("PATH", "/usr/bin:/bin").1);
+    }
+
+    #[test]
+    fn test_parse_dotenv() {
+        let vars = env_policy::parse_dotenv(
+            "# comment\nexport A=1\nB=\"two words\"\nC='x' \nD=plain # trailing\nbad line\n",
+        );
+        assert_eq!(
+            vars,
+            vec![
+                ("A".to_string(), "1".to_string()),
+                ("B".to_string(), "two words".to_string()),
+                ("C".to_string(), "x".to_string()),
+                ("D".to_string(), "plain".to_string()),
+            ]
+        );
+    }
 }
@@ -286,4 +286,343 @@ mod tests {
         let schema = tool.schema();
         assert!(schema["properties"]["cmd"].is_object());
     }
+
+    struct CapturingPermissionEngine {
+        seen: std::sync::Mutex<Vec<serde_json::Value>>,
+    }
+
+    #[async_trait]
+    impl PermissionEngine for CapturingPermissionEngine {
+        async fn check(&self, request: PermissionRequest) -> PermissionDecision {
+            self.seen.lock().unwrap().push(request.input);
+            PermissionDecision::Deny("test".into())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mail_compose_approval_sees_resolved_contact() {
+        use hypr_claw_tools::contacts::{Contact, ContactStore};
+        use hypr_claw_tools::os_tools::MailComposeTool;
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ContactStore::open(dir.path().join("contacts.json")).unwrap());
+        store
+            .upsert(Contact {
+                name: "Alice Smith".into(),
+                email: Some("alice@example.com".into()),
+                ..Default::default()
+            })
+            .unwrap();
+
+        let mut registry = ToolRegistryImpl::new();
+        registry.register(Arc::new(MailComposeTool::new(store)));
+        let permission = Arc::new(CapturingPermissionEngine {
+            seen: std::sync::Mutex::new(Vec::new()),
+        });
+        let dispatcher = ToolDispatcherImpl::new(
+            Arc::new(registry),
+            permission.clone(),
+            Arc::new(MockAuditLogger),
+            5000,
+        );
+
+        let input = json!({"to": "alice", "subject": "Summary", "body": "hi"});
+        let result = dispatcher
+            .dispatch("s".into(), "mail.compose".into(), input)
+            .await;
+        assert!(matches!(result, Err(ToolError::PermissionDenied(_))));
+        let seen = permission.seen.lock().unwrap().clone();
+        assert_eq!(seen[0]["to"], "alice@example.com");
+        assert_eq!(seen[0]["to_contact"], "Alice Smith");
+
+        let unknown = json!({"to": "bob", "subject": "s", "body": "b"});
+        let result = dispatcher
+            .dispatch("s".into(), "mail.compose".into(), unknown)
+            .await;
+        assert!(matches!(result, Err(ToolError::ValidationError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_telegram_literal_handle_is_not_a_contact_name() {
+        use hypr_claw_tools::contacts::{Contact, ContactStore};
+        use hypr_claw_tools::os_tools::TelegramOpenChatTool;
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ContactStore::open(dir.path().join("contacts.json")).unwrap());
+        store
+            .upsert(Contact {
+                name: "Bob Jones".into(),
+                telegram: Some("bobjones".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        let tool = Arc::new(TelegramOpenChatTool::new(store));
+
+        // Execution sees the already resolved input; resolving it again
+        // must not turn the handle into a contact lookup.
+        let resolved = tool.resolve_input(json!({"to": "@bob"})).unwrap();
+        assert_eq!(tool.resolve_input(resolved).unwrap()["to"], "@bob");
+        let resolved = tool.resolve_input(json!({"to": "bob jones"})).unwrap();
+        assert_eq!(resolved["to"], "@bobjones");
+        assert_eq!(tool.resolve_input(resolved).unwrap()["to"], "@bobjones");
+
+        let mut registry = ToolRegistryImpl::new();
+        registry.register(tool);
+        let permission = Arc::new(CapturingPermissionEngine {
+            seen: std::sync::Mutex::new(Vec::new()),
+        });
+        let dispatcher = ToolDispatcherImpl::new(
+            Arc::new(registry),
+            permission.clone(),
+            Arc::new(MockAuditLogger),
+            5000,
+        );
+        let result = dispatcher
+            .dispatch(
+                "s".into(),
+                "telegram.open_chat".into(),
+                json!({"to": "@bob"}),
+            )
+            .await;
+        assert!(matches!(result, Err(ToolError::PermissionDenied(_))));
+        let seen = permission.seen.lock().unwrap().clone();
+        assert_eq!(seen[0]["to"], "@bob");
+        assert!(seen[0].get("to_contact").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_with_narrowed_sandbox_root() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("scratch")).unwrap();
+        std::fs::write(dir.path().join("scratch/note.txt"), "inside").unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "outside").unwrap();
+
+        let mut registry = ToolRegistryImpl::new();
+        registry.register(Arc::new(
+            FileReadTool::new(dir.path().to_str().unwrap()).unwrap(),
+        ));
+        let dispatcher = ToolDispatcherImpl::new(
+            Arc::new(registry),
+            Arc::new(MockPermissionEngine),
+            Arc::new(MockAuditLogger),
+            5000,
+        );
+        let caps = Capabilities {
+            sandbox_root: Some("scratch".into()),
+            ..Capabilities::default()
+        };
+
+        let result = dispatcher
+            .dispatch_with_capabilities(
+                "s".into(),
+                "file.read".into(),
+                json!({"path": "note.txt"}),
+                caps.clone(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.output, Some(json!({"content": "inside"})));
+
+        let escaped = dispatcher
+            .dispatch_with_capabilities(
+                "s".into(),
+                "file.read".into(),
+                json!({"path": "../secret.txt"}),
+                caps,
+            )
+            .await;
+        assert!(matches!(escaped, Err(ToolError::SandboxViolation(_))));
+
+        let wider = Capabilities {
+            sandbox_root: Some(std::env::temp_dir()),
+            ..Capabilities::default()
+        };
+        let widened = dispatcher
+            .dispatch_with_capabilities(
+                "s".into(),
+                "file.read".into(),
+                json!({"path": "secret.txt"}),
+                wider,
+            )
+            .await;
+        assert!(matches!(widened, Err(ToolError::SandboxViolation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_os_fs_read_confined_to_narrowed_sandbox_root() {
+        use hypr_claw_tools::os_tools::{FsReadTool, FsWriteTool};
+
+        let dir = tempfile::tempdir().unwrap();
+        let scratch = dir.path().join("scratch");
+        std::fs::create_dir(&scratch).unwrap();
+        std::fs::write(scratch.join("note.txt"), "inside").unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "outside").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("secret.txt"), scratch.join("link.txt"))
+            .unwrap();
+
+        let mut registry = ToolRegistryImpl::new();
+        registry.register(Arc::new(FsReadTool));
+        registry.register(Arc::new(FsWriteTool));
+        let dispatcher = ToolDispatcherImpl::new(
+            Arc::new(registry),
+            Arc::new(MockPermissionEngine),
+            Arc::new(MockAuditLogger),
+            5000,
+        );
+        let caps = Capabilities {
+            sandbox_root: Some(scratch.clone()),
+            ..Capabilities::default()
+        };
+        let read = |path: std::path::PathBuf| {
+            dispatcher.dispatch_with_capabilities(
+                "s".into(),
+                "fs.read".into(),
+                json!({"path": path}),
+                caps.clone(),
+            )
+        };
+
+        let result = read(scratch.join("note.txt")).await.unwrap();
+        assert_eq!(result.output.unwrap()["content"], "inside");
+        for escape in [
+            dir.path().join("secret.txt"),
+            scratch.join("../secret.txt"),
+            scratch.join("link.txt"),
+        ] {
+            let result = read(escape.clone()).await;
+            assert!(
+                matches!(result, Err(ToolError::SandboxViolation(_))),
+                "{} was not confined: {result:?}",
+                escape.display()
+            );
+        }
+
+        let written = dispatcher
+            .dispatch_with_capabilities(
+                "s".into(),
+                "fs.write".into(),
+                json!({"path": dir.path().join("new.txt"), "content": "x"}),
+                caps,
+            )
+            .await;
+        assert!(matches!(written, Err(ToolError::SandboxViolation(_))));
+        assert!(!dir.path().join("new.txt").exists());
+
+        // Without a granted root the tool still takes any path.
+        let result = dispatcher
+            .dispatch(
+                "s".into(),
+                "fs.read".into(),
+                json!({"path": dir.path().join("secret.txt")}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.output.unwrap()["content"], "outside");
+    }
+
+    #[tokio::test]
+    async fn test_doc_generate_confines_template_and_separates_runs() {
+        use hypr_claw_tools::os_tools::DocGenerateTool;
+
+        let dir = tempfile::tempdir().unwrap();
+        let templates = dir.path().join("templates");
+        std::fs::create_dir(&templates).unwrap();
+        std::fs::write(templates.join("note.md"), "Hi {{name}}").unwrap();
+        std::fs::write(dir.path().join("secret.md"), "secret").unwrap();
+        let tool = DocGenerateTool::new(dir.path().join("artifacts"));
+        let ctx = || {
+            ExecutionContext::new("s".into(), 5000).with_capabilities(Capabilities {
+                sandbox_root: Some(templates.clone()),
+                ..Capabilities::default()
+            })
+        };
+        let input = |template: std::path::PathBuf| {
+            json!({
+                "template_path": template,
+                "data": {"name": "Ann"},
+                "compile": false
+            })
+        };
+
+        let escaped = tool
+            .execute(ctx(), input(dir.path().join("secret.md")))
+            .await;
+        assert!(matches!(escaped, Err(ToolError::SandboxViolation(_))));
+
+        let first = tool
+            .execute(ctx(), input(templates.join("note.md")))
+            .await
+            .unwrap();
+        let second = tool
+            .execute(ctx(), input(templates.join("note.md")))
+            .await
+            .unwrap();
+        let source = |result: &ToolResult| {
+            result.output.as_ref().unwrap()["source_path"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+        assert_ne!(source(&first), source(&second));
+        assert_eq!(std::fs::read_to_string(source(&first)).unwrap(), "Hi Ann");
+    }
+
+    #[tokio::test]
+    async fn test_network_capability_blocks_open_url() {
+        use hypr_claw_tools::os_tools::DesktopOpenUrlTool;
+
+        let ctx = ExecutionContext::new("s".into(), 5000).with_capabilities(Capabilities::none());
+        let result = DesktopOpenUrlTool
+            .execute(ctx, json!({"url": "https://example.com"}))
+            .await;
+        assert!(matches!(result, Err(ToolError::PermissionDenied(_))));
+
+        let ctx = ExecutionContext::new("s".into(), 5000).with_capabilities(Capabilities {
+            network: NetworkAccess::Hosts(vec!["example.org".into()]),
+            ..Capabilities::default()
+        });
+        let result = DesktopOpenUrlTool
+            .execute(ctx, json!({"url": "https://example.com"}))
+            .await;
+        assert!(matches!(result, Err(ToolError::PermissionDenied(_))));
+    }
+
+    #[tokio::test]
+    async fn test_exec_rules_deny_before_permission_check() {
+        use hypr_claw_tools::os_tools::ProcSpawnTool;
+
+        let mut registry = ToolRegistryImpl::new();
+        registry.register(Arc::new(ProcSpawnTool::default()));
+        registry.register(Arc::new(EchoTool));
+        let rules = ExecPolicy {
+            allow: Vec::new(),
+            deny: vec![r"\bsudo\b".into()],
+        }
+        .compile()
+        .unwrap();
+        let dispatcher = ToolDispatcherImpl::new(
+            Arc::new(registry),
+            Arc::new(MockPermissionEngine) as Arc<dyn PermissionEngine>,
+            Arc::new(MockAuditLogger) as Arc<dyn AuditLogger>,
+            5000,
+        )
+        .with_exec_rules(rules);
+
+        let denied = dispatcher
+            .dispatch(
+                "s".into(),
+                "proc.spawn".into(),
+                json!({"command": "sudo", "args": ["true"]}),
+            )
+            .await;
+        assert!(
+            matches!(denied, Err(ToolError::PermissionDenied(reason)) if reason.contains("deny rule"))
+        );
+
+        let echoed = dispatcher
+            .dispatch("s".into(), "echo".into(), json!({"message": "sudo"}))
+            .await
+            .unwrap();
+        assert!(echoed.success);
+    }
 }